@@ -0,0 +1,106 @@
+/*
+ * Contractus C API
+ *
+ * Embed the Contractus compiler and bytecode VM in a host program: create a
+ * context, compile source, query diagnostics and call compiled functions.
+ * Link against the static library built by cargo (libcontractus.a, or
+ * contractus.lib on MSVC) together with the platform's system libraries.
+ *
+ * A context must not be used from several threads at the same time. Strings
+ * returned by the library belong to the context and stay valid until the next
+ * call that takes the context. Every call runs in a fresh VM; statics are
+ * initialized on first use.
+ */
+
+#ifndef CONTRACTUS_H
+#define CONTRACTUS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes */
+#define CONTRACTUS_OK 0
+#define CONTRACTUS_COMPILE_ERROR 1
+#define CONTRACTUS_RUNTIME_ERROR 2 /* includes contract violations */
+#define CONTRACTUS_INVALID_ARGUMENT 3
+#define CONTRACTUS_NOT_COMPILED 4
+#define CONTRACTUS_INTERNAL_ERROR 5
+
+/* Value kinds */
+#define CONTRACTUS_UNIT 0
+#define CONTRACTUS_BOOL 1   /* int_value is 0 or 1 */
+#define CONTRACTUS_INT 2    /* int_value */
+#define CONTRACTUS_FLOAT 3  /* float_value */
+#define CONTRACTUS_CHAR 4   /* int_value is the code point */
+#define CONTRACTUS_STRING 5 /* string_value, NUL-terminated UTF-8 */
+#define CONTRACTUS_OTHER 6  /* results only: string_value is the printed form */
+
+typedef struct ContractusContext ContractusContext;
+
+typedef struct ContractusValue {
+    int kind;
+    int64_t int_value;
+    double float_value;
+    const char *string_value;
+} ContractusValue;
+
+/* Receives program output: `len` bytes of UTF-8, not NUL-terminated. */
+typedef void (*ContractusOutput)(void *user_data, const char *text, size_t len);
+
+/* Library version, a static NUL-terminated string. */
+const char *contractus_version(void);
+
+ContractusContext *contractus_context_new(void);
+void contractus_context_free(ContractusContext *ctx);
+
+/* Options for the next compilation: "opt-level" ("0", "1", "2", "s") and
+ * "contracts" ("check", "off"). */
+int contractus_set_option(ContractusContext *ctx, const char *name, const char *value);
+
+/* Sends program output to `callback` instead of standard output; NULL restores it. */
+void contractus_set_output(ContractusContext *ctx, ContractusOutput callback, void *user_data);
+
+/* Allows (`enabled` non-zero) or forbids the io module (files, program arguments
+ * and io::exit) in later calls; disabled by default. `argv` holds `argc`
+ * NUL-terminated strings returned by io::args() and may be NULL when `argc` is 0. */
+int contractus_set_io(ContractusContext *ctx, int enabled, const char *const *argv, size_t argc);
+
+/* Allows (`enabled` non-zero) or forbids reading environment variables with the
+ * env module in later calls; disabled by default. */
+int contractus_set_env(ContractusContext *ctx, int enabled);
+
+/* Allows (`enabled` non-zero) or forbids reading the clock and sleeping with the
+ * time module in later calls; disabled by default. */
+int contractus_set_time(ContractusContext *ctx, int enabled);
+
+/* Compiles `len` bytes of UTF-8 source, replacing the previous program.
+ * Returns CONTRACTUS_OK or CONTRACTUS_COMPILE_ERROR. */
+int contractus_compile(ContractusContext *ctx, const char *source, size_t len);
+
+/* Diagnostics of the last compilation or call. */
+size_t contractus_diagnostic_count(const ContractusContext *ctx);
+/* Formatted like the command line, e.g. "error[E0425] at line 2, column 11: ..." */
+const char *contractus_diagnostic_message(const ContractusContext *ctx, size_t index);
+/* Error code such as "E0425", or NULL. */
+const char *contractus_diagnostic_code(const ContractusContext *ctx, size_t index);
+uint32_t contractus_diagnostic_line(const ContractusContext *ctx, size_t index);
+uint32_t contractus_diagnostic_column(const ContractusContext *ctx, size_t index);
+int contractus_diagnostic_is_error(const ContractusContext *ctx, size_t index);
+
+/* Calls the compiled function `name`; `result` may be NULL.
+ * Returns CONTRACTUS_RUNTIME_ERROR on runtime errors and contract violations. */
+int contractus_call(ContractusContext *ctx, const char *name, const ContractusValue *args,
+                    size_t argc, ContractusValue *result);
+
+/* Runs `main`. */
+int contractus_run_main(ContractusContext *ctx);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CONTRACTUS_H */
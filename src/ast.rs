@@ -1,11 +1,43 @@
+mod json;
+mod source;
+
+pub use source::string_literal;
+
+pub(crate) use json::write_string as write_json_string;
+
+use crate::features::{Edition, Feature};
 use crate::span::Span;
+use crate::token::TokenTree;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
 
 #[derive(Debug, Clone)]
 pub struct Program {
+    pub attributes: Vec<Attribute>, // 文件开头的 `#![...]`，如开启特性的 `#![feature(...)]`
     pub items: Vec<Item>,
     pub span: Span,
 }
 
+impl Program {
+    /// 文件开头是否用 `#![feature(...)]` 开启了 `feature`
+    pub fn has_feature(&self, feature: Feature) -> bool {
+        self.attributes
+            .iter()
+            .filter(|attribute| attribute.name == "feature")
+            .any(|attribute| attribute.args.iter().any(|arg| *arg == feature.name()))
+    }
+
+    /// 文件开头的 `#![edition(...)]` 选择的版本
+    pub fn edition(&self) -> Edition {
+        self.attributes
+            .iter()
+            .filter(|attribute| attribute.name == "edition")
+            .find_map(|attribute| attribute.args.first()?.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Item {
     Function(Function),
@@ -15,30 +47,137 @@ pub enum Item {
     Static(StaticDef),
     Import(ImportStmt),
     Export(ExportStmt),
+    MacroRules(MacroDef),
+    MacroCall(MacroCall), // 条目位置的宏调用，展开为零个或多个条目
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
+    pub attributes: Vec<Attribute>,
     pub visibility: Visibility,
     pub name: String,
     pub generics: Option<Generics>,
     pub params: Vec<Parameter>,
     pub return_type: Option<Type>,
+    pub contracts: Vec<Contract>, // requires 和 ensures，按书写顺序
     pub body: Block,
+    pub operator: Option<OperatorDecl>, // 函数实现的自定义运算符
+    pub asynchronous: bool,             // `async fn`
+    pub pure: bool,                     // `pure fn`
+    pub span: Span,
+}
+
+impl Function {
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| attribute.name == name)
+    }
+
+    /// `#[intrinsic("name")]` 声明的编译器认识的函数，见 intrinsics.rs
+    pub fn intrinsic(&self) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == "intrinsic")?
+            .string_arg(0)
+    }
+}
+
+/// 写在条目之前的属性 `#[name]` 或 `#[name(arg, ...)]`，
+/// 如标记基准测试的 `#[bench]` 和结构体、枚举上的 `#[derive(Eq, Clone)]`
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<String>, // 字符串参数保留引号和转义，如 `"sqrt"`
+    pub span: Span,
+}
+
+impl Attribute {
+    /// 第 `index` 个参数是字符串字面量时它的值
+    pub fn string_arg(&self, index: usize) -> Option<String> {
+        let arg = self.args.get(index)?;
+        let text = arg.strip_prefix('"')?.strip_suffix('"')?;
+        Some(crate::literal::unescape(text).ok()?.into_owned())
+    }
+}
+
+/// 写在函数之前的自定义运算符声明 `operator <*> (precedence 70, left)`，
+/// `a <*> b` 解析为对这个函数的调用 `f(a, b)`，见 parser.rs
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorDecl {
+    pub symbol: String,
+    pub precedence: u8,
+    pub associativity: Associativity,
     pub span: Span,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+impl Associativity {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Associativity::Left => "left",
+            Associativity::Right => "right",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StructDef {
+    pub attributes: Vec<Attribute>,
     pub visibility: Visibility,
     pub name: String,
     pub generics: Option<Generics>,
     pub fields: Vec<Field>,
+    pub invariants: Vec<Contract>,
     pub span: Span,
 }
 
+/// 契约子句：函数的 `requires`/`ensures`、结构体的 `invariant` 和循环的 `invariant`
+/// `ensures` 中用 `result` 引用返回值，结构体的 `invariant` 中用 `self` 引用结构体的值
+#[derive(Debug, Clone)]
+pub struct Contract {
+    pub kind: ContractKind,
+    pub condition: Expr,
+    pub text: String, // 条件的源码，违反时报告
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractKind {
+    Requires,
+    Ensures,
+    Invariant,
+    LoopInvariant,
+}
+
+impl ContractKind {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            ContractKind::Requires => "requires",
+            ContractKind::Ensures => "ensures",
+            ContractKind::Invariant | ContractKind::LoopInvariant => "invariant",
+        }
+    }
+
+    /// 报告违反时的名称
+    pub fn description(self) -> &'static str {
+        match self {
+            ContractKind::Requires => "precondition",
+            ContractKind::Ensures => "postcondition",
+            ContractKind::Invariant => "invariant",
+            ContractKind::LoopInvariant => "loop invariant",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EnumDef {
+    pub attributes: Vec<Attribute>,
     pub visibility: Visibility,
     pub name: String,
     pub generics: Option<Generics>,
@@ -79,9 +218,116 @@ pub struct ImportStmt {
     pub span: Span,
 }
 
+/// `macro_rules! name { (模式) => { 模板 }; ... }`
+/// 规则保存为记号树，展开时才检查，见 macros.rs
+#[derive(Debug, Clone)]
+pub struct MacroDef {
+    pub name: String,
+    pub rules: Vec<MacroRule>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroRule {
+    pub pattern: TokenTree, // 带定界符
+    pub body: TokenTree,    // 带定界符，定界符不属于展开结果
+    pub span: Span,
+}
+
+/// 宏调用 `name!(...)`、`name![...]` 或 `name!{...}`，参数是还没有解析的记号树
+#[derive(Debug, Clone)]
+pub struct MacroCall {
+    pub name: String,
+    pub args: TokenTree, // 带定界符
+    pub span: Span,
+}
+
+/// 内联汇编 `asm!("add {0}, {1}", inout(reg) x, in(reg) y, options(nostack))`。
+/// 模板中的 `{}` 和 `{n}` 按顺序或序号引用操作数，`{{` 和 `}}` 是花括号本身
+#[derive(Debug, Clone)]
+pub struct InlineAsm {
+    pub template: Vec<String>, // 每个字符串是一行汇编
+    pub operands: Vec<AsmOperand>,
+    pub options: Vec<String>,
+}
+
+impl InlineAsm {
+    /// 操作数中的表达式，按书写的顺序
+    pub fn exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.operands
+            .iter()
+            .filter_map(|operand| operand.expr.as_ref())
+    }
+
+    /// `options(noreturn)`：汇编不会执行到结尾
+    pub fn noreturn(&self) -> bool {
+        self.options.iter().any(|option| option == "noreturn")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmOperand {
+    pub dir: AsmDir,
+    pub reg: AsmReg,
+    pub expr: Option<Expr>, // 输出操作数写成 `_` 时为 None，表示丢弃寄存器的值
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsmDir {
+    In,
+    Out,
+    LateOut,
+    InOut,
+}
+
+impl AsmDir {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            AsmDir::In => "in",
+            AsmDir::Out => "out",
+            AsmDir::LateOut => "lateout",
+            AsmDir::InOut => "inout",
+        }
+    }
+
+    /// 汇编是否读取操作数的值
+    pub fn reads(self) -> bool {
+        matches!(self, AsmDir::In | AsmDir::InOut)
+    }
+
+    /// 汇编是否写入操作数
+    pub fn writes(self) -> bool {
+        !matches!(self, AsmDir::In)
+    }
+}
+
+/// 操作数的寄存器：`reg` 这样的寄存器类由后端分配，`"eax"` 指定具体的寄存器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmReg {
+    Class(String),
+    Explicit(String),
+}
+
+impl fmt::Display for AsmReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmReg::Class(class) => f.write_str(class),
+            AsmReg::Explicit(name) => write!(f, "\"{}\"", name),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExportStmt {
-    pub items: Vec<String>,
+    pub items: Vec<ExportItem>,
+    pub span: Span,
+}
+
+// 导出项：本模块的条目名，或 `module::item` 形式的重新导出
+#[derive(Debug, Clone)]
+pub struct ExportItem {
+    pub path: Vec<String>,
     pub span: Span,
 }
 
@@ -97,6 +343,12 @@ pub struct Generics {
     pub span: Span,
 }
 
+impl Generics {
+    pub fn param_names(&self) -> Vec<String> {
+        self.params.iter().map(|param| param.name.clone()).collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GenericParam {
     pub name: String,
@@ -144,6 +396,7 @@ pub struct LetStmt {
     pub pattern: Pattern,
     pub ty: Option<Type>,
     pub init: Option<Expr>,
+    pub else_block: Option<Block>, // `let Some(x) = y else { return; };`，模式不匹配时执行，必须发散
     pub mutable: bool,
     pub span: Span,
 }
@@ -165,21 +418,32 @@ pub struct ReturnStmt {
 pub struct IfStmt {
     pub cond: Expr,
     pub then_block: Block,
-    pub else_block: Option<Block>,
+    pub else_block: Option<ElseBranch>,
     pub span: Span,
 }
 
+/// `else` 之后的部分：代码块，或者 `else if` 的 if 语句
+#[derive(Debug, Clone)]
+pub enum ElseBranch {
+    Block(Block),
+    If(Box<IfStmt>),
+}
+
 #[derive(Debug, Clone)]
 pub struct WhileStmt {
+    pub label: Option<String>, // `'outer: while ...`
     pub cond: Expr,
+    pub invariants: Vec<Contract>, // `while i < n invariant sum >= 0 { ... }`
     pub body: Block,
     pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct ForStmt {
+    pub label: Option<String>,
     pub pattern: Pattern,
     pub iterable: Expr,
+    pub invariants: Vec<Contract>,
     pub body: Block,
     pub span: Span,
 }
@@ -217,6 +481,7 @@ pub enum Pattern {
     Ident(String),
     Literal(Literal),
     Struct(String, Vec<(String, Pattern)>),
+    TupleStruct(String, Vec<Pattern>), // 元组变体 `Some(x)`，名字是路径的最后一段
     Tuple(Vec<Pattern>),
     Or(Vec<Pattern>),
     Wildcard,
@@ -226,23 +491,28 @@ pub enum Pattern {
 pub enum Expr {
     Literal(Literal, Span),
     Ident(String, Span),
-    Binary(BinOp, Box<Expr>, Box<Expr>, Span),
-    Unary(UnOp, Box<Expr>, Span),
-    Call(Box<Expr>, Vec<Expr>, Span),
-    MethodCall(Box<Expr>, String, Vec<Expr>, Span),
+    Path(Vec<String>, Span),               // a::b::c，模块成员或枚举变体
+    Turbofish(Box<Expr>, Vec<Type>, Span), // `path::<T>`，显式给出类型实参
+    Binary(BinOp, Box<Expr>, Box<Expr>, Span, OperandType),
+    Unary(UnOp, Box<Expr>, Span, OperandType),
+    Call(Box<Expr>, Vec<Expr>, Span, OperandType), // 记录的是 `abs` 的参数类型
+    MethodCall(Box<Expr>, String, Vec<Expr>, Span, OperandType),
     FieldAccess(Box<Expr>, String, Span),
+    TupleIndex(Box<Expr>, usize, Span), // `pair.0`
     IndexAccess(Box<Expr>, Box<Expr>, Span),
     StructLit(String, Vec<(String, Expr)>, Span),
     ArrayLit(Vec<Expr>, Span),
     TupleLit(Vec<Expr>, Span),
-    Range(Box<Expr>, Box<Expr>, bool, Span), // inclusive flag
+    Range(Option<Box<Expr>>, Option<Box<Expr>>, bool, Span), // inclusive flag；只有切片可以省略起点或终点
     Assign(Box<Expr>, Box<Expr>, Span),
-    CompoundAssign(BinOp, Box<Expr>, Box<Expr>, Span),
+    CompoundAssign(BinOp, Box<Expr>, Box<Expr>, Span, OperandType),
     Block(Block, Span),
-    If(Box<Expr>, Block, Option<Block>, Span),
+    Unsafe(Block, Span),     // `unsafe { ... }`，其中可以进行指针转换
+    AsyncBlock(Block, Span), // `async { ... }`，见 features.rs
+    If(Box<Expr>, Block, Option<ElseBranch>, Span),
     Match(Box<Expr>, Vec<MatchArm>, Span),
-    While(Box<Expr>, Block, Span),
-    For(Pattern, Box<Expr>, Block, Span),
+    While(Option<String>, Box<Expr>, Block, Span), // 标签、条件和循环体
+    For(Option<String>, Pattern, Box<Expr>, Block, Span),
     Break(Option<String>, Option<Box<Expr>>, Span),
     Continue(Option<String>, Span),
     Return(Option<Box<Expr>>, Span),
@@ -250,6 +520,10 @@ pub enum Expr {
     Cast(Box<Expr>, Type, Span),
     Ref(Box<Expr>, bool, Span), // mutable flag
     Deref(Box<Expr>, Span),
+    Try(Box<Expr>, Span),            // `expr?`，见 prelude.rs
+    Await(Box<Expr>, Span),          // `expr.await`
+    MacroCall(MacroCall),            // 语义分析之前展开，见 macros.rs
+    InlineAsm(Box<InlineAsm>, Span), // `asm!(...)`，见 features.rs
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -284,16 +558,70 @@ pub enum Type {
     Generic(String, Vec<Type>),
 
     // 函数类型
-    Function(Vec<Type>, Box<Type>),
+    Function(Vec<Type>, Box<Type>, bool), // pure flag：`pure fn(T) -> U` 只接受没有副作用的函数
 
     // 特殊类型
     Never,
     Infer,
+    TypeOf(Arc<TypeOf>), // `typeof(expr)`，只能出现在函数体中
+}
+
+/// `typeof(expr)` 类型运算符：表达式不求值，它的类型由语义分析推断后记录在节点中，
+/// 之后的阶段通过 `Type::expand_typeof` 使用推断出的类型。语法树的副本共享同一个节点
+#[derive(Debug)]
+pub struct TypeOf {
+    pub expr: Expr,
+    resolved: OnceLock<Type>,
+}
+
+impl TypeOf {
+    pub fn new(expr: Expr) -> Self {
+        Self {
+            expr,
+            resolved: OnceLock::new(),
+        }
+    }
+
+    /// 语义分析推断出的类型
+    pub fn resolved(&self) -> Option<&Type> {
+        self.resolved.get()
+    }
+
+    /// 记录推断出的类型；重复分析同一棵语法树时保留第一次的结果
+    pub fn resolve(&self, ty: Type) {
+        let _ = self.resolved.set(ty);
+    }
+}
+
+// 同一个节点，或者推断出的类型相同
+impl PartialEq for TypeOf {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+            || matches!((self.resolved(), other.resolved()), (Some(a), Some(b)) if a == b)
+    }
+}
+
+/// 二元运算、一元运算和复合赋值的操作数类型，以及 `abs` 的参数类型：由语义分析推断后记录在节点中，
+/// 两个后端按整数类型的宽度检查运算是否溢出，`f32` 的运算结果舍入到单精度。操作数都是没有后缀的
+/// 字面量（类型由上下文决定）或者类型含有类型参数时不记录，运算按 i64 检查。语法树的副本共享同一个记录
+#[derive(Debug, Clone, Default)]
+pub struct OperandType(Arc<OnceLock<Type>>);
+
+impl OperandType {
+    /// 语义分析记录的类型
+    pub fn get(&self) -> Option<&Type> {
+        self.0.get()
+    }
+
+    /// 记录类型；重复分析同一棵语法树时保留第一次的结果
+    pub fn record(&self, ty: Type) {
+        let _ = self.0.set(ty);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    Int(i64),
+    Int(i64, Option<Type>), // 带后缀的整数字面量（`300u8`）记录后缀的类型
     Float(f64),
     Bool(bool),
     Char(char),
@@ -338,3 +666,240 @@ pub enum UnOp {
     Ref,        // &
     RefMut,     // &mut
 }
+
+impl Type {
+    /// 把类型中的泛型参数（以 `Named` 出现）替换为 `args` 中的实际类型
+    pub fn substitute(&self, args: &BTreeMap<String, Type>) -> Type {
+        let subst = |ty: &Type| Box::new(ty.substitute(args));
+        match self {
+            Type::Named(name) => args.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Array(elem, len) => Type::Array(subst(elem), *len),
+            Type::Slice(elem) => Type::Slice(subst(elem)),
+            Type::Tuple(types) => Type::Tuple(types.iter().map(|t| t.substitute(args)).collect()),
+            Type::Pointer(inner, mutable) => Type::Pointer(subst(inner), *mutable),
+            Type::Reference(inner, mutable) => Type::Reference(subst(inner), *mutable),
+            Type::Generic(name, types) => Type::Generic(
+                name.clone(),
+                types.iter().map(|t| t.substitute(args)).collect(),
+            ),
+            Type::Function(params, ret, pure) => Type::Function(
+                params.iter().map(|t| t.substitute(args)).collect(),
+                subst(ret),
+                *pure,
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// 把类型中的 `typeof(expr)` 替换为推断出的类型，还没有推断时为 `_`
+    pub fn expand_typeof(&self) -> Type {
+        let expand = |ty: &Type| Box::new(ty.expand_typeof());
+        let expand_all = |types: &[Type]| types.iter().map(Type::expand_typeof).collect();
+        match self {
+            Type::TypeOf(node) => node.resolved().map_or(Type::Infer, Type::expand_typeof),
+            Type::Array(elem, len) => Type::Array(expand(elem), *len),
+            Type::Slice(elem) => Type::Slice(expand(elem)),
+            Type::Tuple(types) => Type::Tuple(expand_all(types)),
+            Type::Pointer(inner, mutable) => Type::Pointer(expand(inner), *mutable),
+            Type::Reference(inner, mutable) => Type::Reference(expand(inner), *mutable),
+            Type::Generic(name, types) => Type::Generic(name.clone(), expand_all(types)),
+            Type::Function(params, ret, pure) => {
+                Type::Function(expand_all(params), expand(ret), *pure)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// 类型中出现的 `typeof(expr)`
+    pub fn typeofs(&self) -> Vec<&Arc<TypeOf>> {
+        match self {
+            Type::TypeOf(node) => vec![node],
+            Type::Array(inner, _)
+            | Type::Slice(inner)
+            | Type::Pointer(inner, _)
+            | Type::Reference(inner, _) => inner.typeofs(),
+            Type::Tuple(types) | Type::Generic(_, types) => {
+                types.iter().flat_map(Type::typeofs).collect()
+            }
+            Type::Function(types, ret, _) => types
+                .iter()
+                .chain([&**ret])
+                .flat_map(Type::typeofs)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 类型中是否出现 `params` 中的泛型参数
+    pub fn mentions(&self, params: &[String]) -> bool {
+        match self {
+            Type::Named(name) => params.contains(name),
+            Type::Array(inner, _)
+            | Type::Slice(inner)
+            | Type::Pointer(inner, _)
+            | Type::Reference(inner, _) => inner.mentions(params),
+            Type::Tuple(types) | Type::Generic(_, types) => {
+                types.iter().any(|ty| ty.mentions(params))
+            }
+            Type::Function(types, ret, _) => {
+                types.iter().any(|ty| ty.mentions(params)) || ret.mentions(params)
+            }
+            _ => false,
+        }
+    }
+
+    /// 类型中是否有推断不出的部分 `_`
+    pub fn has_infer(&self) -> bool {
+        match self {
+            Type::Infer => true,
+            Type::Array(inner, _)
+            | Type::Slice(inner)
+            | Type::Pointer(inner, _)
+            | Type::Reference(inner, _) => inner.has_infer(),
+            Type::Tuple(types) | Type::Generic(_, types) => types.iter().any(Type::has_infer),
+            Type::Function(params, ret, _) => params.iter().any(Type::has_infer) || ret.has_infer(),
+            _ => false,
+        }
+    }
+
+    /// 用实际类型匹配含泛型参数 `params` 的类型，推断出的参数写入 `args`
+    ///
+    /// 实际类型未知（`_`）的部分跳过；结构不一致或与已推断的参数冲突时返回 false。
+    pub fn infer_params(
+        &self,
+        actual: &Type,
+        params: &[String],
+        args: &mut BTreeMap<String, Type>,
+    ) -> bool {
+        match (self, actual) {
+            (_, Type::Infer) => true,
+            (Type::Named(name), _) if params.contains(name) => match args.get(name) {
+                Some(Type::Infer) | None => {
+                    args.insert(name.clone(), actual.clone());
+                    true
+                }
+                Some(known) => known == actual,
+            },
+            (Type::Array(a, n), Type::Array(b, m)) => n == m && a.infer_params(b, params, args),
+            // 数组的引用可以转换为切片的引用
+            (Type::Slice(a), Type::Slice(b) | Type::Array(b, _)) => a.infer_params(b, params, args),
+            (Type::Pointer(a, x), Type::Pointer(b, y))
+            | (Type::Reference(a, x), Type::Reference(b, y)) => {
+                x == y && a.infer_params(b, params, args)
+            }
+            (Type::Tuple(a), Type::Tuple(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(a, b)| a.infer_params(b, params, args))
+            }
+            (Type::Generic(x, a), Type::Generic(y, b)) => {
+                x == y
+                    && a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(a, b)| a.infer_params(b, params, args))
+            }
+            // 推断不区分 `pure fn` 和 `fn`，实参的纯度在调用处单独检查
+            (Type::Function(a, r, _), Type::Function(b, q, _)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(a, b)| a.infer_params(b, params, args))
+                    && r.infer_params(q, params, args)
+            }
+            _ => self == actual,
+        }
+    }
+}
+
+// 按源代码语法显示类型，用于诊断信息和 MIR 输出
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::Usize => write!(f, "usize"),
+            Type::Isize => write!(f, "isize"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::Bool => write!(f, "bool"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::Unit => write!(f, "()"),
+            Type::Array(elem, len) => write!(f, "[{}; {}]", elem, len),
+            Type::Slice(elem) => write!(f, "[{}]", elem),
+            Type::Tuple(types) => {
+                write!(f, "(")?;
+                write_list(f, types)?;
+                if types.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Type::Pointer(inner, true) => write!(f, "*mut {}", inner),
+            Type::Pointer(inner, false) => write!(f, "*const {}", inner),
+            Type::Reference(inner, true) => write!(f, "&mut {}", inner),
+            Type::Reference(inner, false) => write!(f, "&{}", inner),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Generic(name, args) => {
+                write!(f, "{}<", name)?;
+                write_list(f, args)?;
+                write!(f, ">")
+            }
+            Type::Function(params, ret, pure) => {
+                if *pure {
+                    write!(f, "pure ")?;
+                }
+                write!(f, "fn(")?;
+                write_list(f, params)?;
+                write!(f, ") -> {}", ret)
+            }
+            Type::Never => write!(f, "!"),
+            Type::Infer => write!(f, "_"),
+            Type::TypeOf(node) => write!(f, "typeof({})", source::expr_source(&node.expr)),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, types: &[Type]) -> fmt::Result {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", ty)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Equal => "==",
+            BinOp::NotEqual => "!=",
+            BinOp::Less => "<",
+            BinOp::Greater => ">",
+            BinOp::LessEqual => "<=",
+            BinOp::GreaterEqual => ">=",
+            BinOp::LogicalAnd => "&&",
+            BinOp::LogicalOr => "||",
+            BinOp::BitwiseAnd => "&",
+            BinOp::BitwiseOr => "|",
+            BinOp::BitwiseXor => "^",
+            BinOp::LeftShift => "<<",
+            BinOp::RightShift => ">>",
+        };
+        write!(f, "{}", symbol)
+    }
+}
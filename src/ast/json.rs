@@ -0,0 +1,792 @@
+// 语法树的 JSON 形式（`--emit=ast-json`），给编辑器插件和外部工具使用
+// 每个节点是一个对象：枚举节点用 "kind" 区分变体，带位置的节点有 "span"
+// 字段名和变体名与 ast.rs 中的定义一一对应（变体名转为 snake_case）
+
+use super::*;
+use std::fmt::Write as _;
+
+enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Program {
+    /// 带缩进的 JSON 文本
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        write_json(&mut out, &program(self), 0);
+        out.push('\n');
+        out
+    }
+
+    /// 去掉位置的 JSON 文本，只有位置和排版不同的两棵语法树得到同样的结果
+    pub fn to_json_without_spans(&self) -> String {
+        let mut out = String::new();
+        write_json(&mut out, &without_spans(program(self)), 0);
+        out.push('\n');
+        out
+    }
+}
+
+// 去掉所有的 "span" 字段，以及契约中随排版变化的源码 "text"
+fn without_spans(value: Json) -> Json {
+    match value {
+        Json::Array(values) => Json::Array(values.into_iter().map(without_spans).collect()),
+        Json::Object(fields) => Json::Object(
+            fields
+                .into_iter()
+                .filter(|(key, _)| *key != "span" && *key != "text")
+                .map(|(key, value)| (key, without_spans(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn write_json(out: &mut String, value: &Json, indent: usize) {
+    match value {
+        Json::Null => out.push_str("null"),
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::Int(n) => {
+            let _ = write!(out, "{}", n);
+        }
+        // JSON 没有 NaN 和无穷大
+        Json::Float(x) if x.is_finite() => {
+            let _ = write!(out, "{:?}", x);
+        }
+        Json::Float(_) => out.push_str("null"),
+        Json::Str(s) => write_string(out, s),
+        Json::Array(values) if values.is_empty() => out.push_str("[]"),
+        Json::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                out.push_str(if i == 0 { "\n" } else { ",\n" });
+                push_indent(out, indent + 1);
+                write_json(out, value, indent + 1);
+            }
+            out.push('\n');
+            push_indent(out, indent);
+            out.push(']');
+        }
+        Json::Object(fields) => {
+            out.push('{');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                out.push_str(if i == 0 { "\n" } else { ",\n" });
+                push_indent(out, indent + 1);
+                let _ = write!(out, "\"{}\": ", key);
+                write_json(out, value, indent + 1);
+            }
+            out.push('\n');
+            push_indent(out, indent);
+            out.push('}');
+        }
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+pub(crate) fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// ---- 构造 JSON 值的辅助函数 ----
+
+fn string(s: &str) -> Json {
+    Json::Str(s.to_string())
+}
+
+fn array<T>(items: &[T], f: impl Fn(&T) -> Json) -> Json {
+    Json::Array(items.iter().map(f).collect())
+}
+
+fn optional<T>(item: Option<&T>, f: impl Fn(&T) -> Json) -> Json {
+    item.map_or(Json::Null, f)
+}
+
+// 带 "kind" 的对象
+fn node(kind: &str, mut fields: Vec<(&'static str, Json)>) -> Json {
+    fields.insert(0, ("kind", string(kind)));
+    Json::Object(fields)
+}
+
+fn span(sp: &Span) -> Json {
+    Json::Object(vec![
+        ("start", Json::Int(sp.start as i64)),
+        ("end", Json::Int(sp.end as i64)),
+        ("line", Json::Int(sp.line as i64)),
+        ("column", Json::Int(sp.column as i64)),
+    ])
+}
+
+fn visibility(vis: &Visibility) -> Json {
+    string(match vis {
+        Visibility::Public => "public",
+        Visibility::Private => "private",
+    })
+}
+
+// ---- 条目 ----
+
+fn program(p: &Program) -> Json {
+    Json::Object(vec![
+        ("attributes", array(&p.attributes, attribute)),
+        ("items", array(&p.items, item)),
+        ("span", span(&p.span)),
+    ])
+}
+
+fn item(it: &Item) -> Json {
+    match it {
+        Item::Function(func) => function(func),
+        Item::Struct(def) => node(
+            "struct",
+            vec![
+                ("attributes", array(&def.attributes, attribute)),
+                ("visibility", visibility(&def.visibility)),
+                ("name", string(&def.name)),
+                ("generics", optional(def.generics.as_ref(), generics)),
+                ("fields", array(&def.fields, field)),
+                ("invariants", array(&def.invariants, contract)),
+                ("span", span(&def.span)),
+            ],
+        ),
+        Item::Enum(def) => node(
+            "enum",
+            vec![
+                ("attributes", array(&def.attributes, attribute)),
+                ("visibility", visibility(&def.visibility)),
+                ("name", string(&def.name)),
+                ("generics", optional(def.generics.as_ref(), generics)),
+                ("variants", array(&def.variants, variant)),
+                ("span", span(&def.span)),
+            ],
+        ),
+        Item::Const(def) => node(
+            "const",
+            vec![
+                ("visibility", visibility(&def.visibility)),
+                ("name", string(&def.name)),
+                ("ty", ty(&def.ty)),
+                ("value", expr(&def.value)),
+                ("span", span(&def.span)),
+            ],
+        ),
+        Item::Static(def) => node(
+            "static",
+            vec![
+                ("visibility", visibility(&def.visibility)),
+                ("mutable", Json::Bool(def.mutable)),
+                ("name", string(&def.name)),
+                ("ty", ty(&def.ty)),
+                ("value", expr(&def.value)),
+                ("span", span(&def.span)),
+            ],
+        ),
+        Item::Import(import) => node(
+            "import",
+            vec![
+                ("path", array(&import.path, |s| string(s))),
+                ("alias", optional(import.alias.as_ref(), |s| string(s))),
+                ("span", span(&import.span)),
+            ],
+        ),
+        Item::MacroRules(def) => node(
+            "macro_rules",
+            vec![
+                ("name", string(&def.name)),
+                (
+                    "rules",
+                    array(&def.rules, |rule| {
+                        Json::Object(vec![
+                            ("pattern", tokens(&rule.pattern)),
+                            ("body", tokens(&rule.body)),
+                            ("span", span(&rule.span)),
+                        ])
+                    }),
+                ),
+                ("span", span(&def.span)),
+            ],
+        ),
+        Item::MacroCall(call) => macro_call(call),
+        Item::Export(export) => node(
+            "export",
+            vec![
+                (
+                    "items",
+                    array(&export.items, |export_item| {
+                        Json::Object(vec![
+                            ("path", array(&export_item.path, |s| string(s))),
+                            ("span", span(&export_item.span)),
+                        ])
+                    }),
+                ),
+                ("span", span(&export.span)),
+            ],
+        ),
+    }
+}
+
+fn function(func: &Function) -> Json {
+    node(
+        "function",
+        vec![
+            ("attributes", array(&func.attributes, attribute)),
+            ("operator", optional(func.operator.as_ref(), operator)),
+            ("visibility", visibility(&func.visibility)),
+            ("async", Json::Bool(func.asynchronous)),
+            ("pure", Json::Bool(func.pure)),
+            ("name", string(&func.name)),
+            ("generics", optional(func.generics.as_ref(), generics)),
+            ("params", array(&func.params, parameter)),
+            ("return_type", optional(func.return_type.as_ref(), ty)),
+            ("contracts", array(&func.contracts, contract)),
+            ("body", block(&func.body)),
+            ("span", span(&func.span)),
+        ],
+    )
+}
+
+fn attribute(a: &Attribute) -> Json {
+    Json::Object(vec![
+        ("name", string(&a.name)),
+        ("args", array(&a.args, |arg| string(arg))),
+        ("span", span(&a.span)),
+    ])
+}
+
+fn operator(op: &OperatorDecl) -> Json {
+    Json::Object(vec![
+        ("symbol", string(&op.symbol)),
+        ("precedence", Json::Int(op.precedence as i64)),
+        ("associativity", string(op.associativity.keyword())),
+        ("span", span(&op.span)),
+    ])
+}
+
+fn contract(c: &Contract) -> Json {
+    node(
+        c.kind.keyword(),
+        vec![
+            ("condition", expr(&c.condition)),
+            ("text", string(&c.text)),
+            ("span", span(&c.span)),
+        ],
+    )
+}
+
+fn variant(v: &EnumVariant) -> Json {
+    Json::Object(vec![
+        ("name", string(&v.name)),
+        (
+            "fields",
+            optional(v.fields.as_ref(), |fields| array(fields, ty)),
+        ),
+        ("span", span(&v.span)),
+    ])
+}
+
+fn generics(g: &Generics) -> Json {
+    Json::Object(vec![
+        (
+            "params",
+            array(&g.params, |param| {
+                Json::Object(vec![
+                    ("name", string(&param.name)),
+                    ("bounds", array(&param.bounds, |s| string(s))),
+                    ("span", span(&param.span)),
+                ])
+            }),
+        ),
+        ("span", span(&g.span)),
+    ])
+}
+
+fn field(f: &Field) -> Json {
+    Json::Object(vec![
+        ("visibility", visibility(&f.visibility)),
+        ("name", string(&f.name)),
+        ("ty", ty(&f.ty)),
+        ("span", span(&f.span)),
+    ])
+}
+
+fn parameter(param: &Parameter) -> Json {
+    Json::Object(vec![
+        ("pattern", pattern(&param.pattern)),
+        ("ty", ty(&param.ty)),
+        ("span", span(&param.span)),
+    ])
+}
+
+// 类型按源代码语法写成字符串
+fn ty(t: &Type) -> Json {
+    Json::Str(t.to_string())
+}
+
+// ---- 语句 ----
+
+fn block(b: &Block) -> Json {
+    Json::Object(vec![
+        ("statements", array(&b.statements, statement)),
+        ("span", span(&b.span)),
+    ])
+}
+
+fn if_stmt(stmt: &IfStmt) -> Json {
+    node(
+        "if",
+        vec![
+            ("cond", expr(&stmt.cond)),
+            ("then_block", block(&stmt.then_block)),
+            (
+                "else_block",
+                optional(stmt.else_block.as_ref(), else_branch),
+            ),
+            ("span", span(&stmt.span)),
+        ],
+    )
+}
+
+// `else if` 是嵌套的 if 语句节点，`else` 代码块和其他代码块相同
+fn else_branch(branch: &ElseBranch) -> Json {
+    match branch {
+        ElseBranch::Block(b) => block(b),
+        ElseBranch::If(stmt) => if_stmt(stmt),
+    }
+}
+
+fn statement(stmt: &Statement) -> Json {
+    match stmt {
+        Statement::Let(stmt) => node(
+            "let",
+            vec![
+                ("pattern", pattern(&stmt.pattern)),
+                ("ty", optional(stmt.ty.as_ref(), ty)),
+                ("init", optional(stmt.init.as_ref(), expr)),
+                ("else", optional(stmt.else_block.as_ref(), block)),
+                ("mutable", Json::Bool(stmt.mutable)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::Expr(stmt) => node(
+            "expr",
+            vec![
+                ("expr", expr(&stmt.expr)),
+                ("semicolon", Json::Bool(stmt.semicolon)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::Return(stmt) => node(
+            "return",
+            vec![
+                ("expr", optional(stmt.expr.as_ref(), expr)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::If(stmt) => if_stmt(stmt),
+        Statement::While(stmt) => node(
+            "while",
+            vec![
+                ("label", optional(stmt.label.as_ref(), |s| string(s))),
+                ("cond", expr(&stmt.cond)),
+                ("invariants", array(&stmt.invariants, contract)),
+                ("body", block(&stmt.body)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::For(stmt) => node(
+            "for",
+            vec![
+                ("label", optional(stmt.label.as_ref(), |s| string(s))),
+                ("pattern", pattern(&stmt.pattern)),
+                ("iterable", expr(&stmt.iterable)),
+                ("invariants", array(&stmt.invariants, contract)),
+                ("body", block(&stmt.body)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::Match(stmt) => node(
+            "match",
+            vec![
+                ("expr", expr(&stmt.expr)),
+                ("arms", array(&stmt.arms, match_arm)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::Break(stmt) => node(
+            "break",
+            vec![
+                ("label", optional(stmt.label.as_ref(), |s| string(s))),
+                ("expr", optional(stmt.expr.as_ref(), expr)),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::Continue(stmt) => node(
+            "continue",
+            vec![
+                ("label", optional(stmt.label.as_ref(), |s| string(s))),
+                ("span", span(&stmt.span)),
+            ],
+        ),
+        Statement::Block(inner) => node(
+            "block",
+            vec![
+                ("statements", array(&inner.statements, statement)),
+                ("span", span(&inner.span)),
+            ],
+        ),
+    }
+}
+
+fn match_arm(arm: &MatchArm) -> Json {
+    Json::Object(vec![
+        ("pattern", pattern(&arm.pattern)),
+        ("guard", optional(arm.guard.as_ref(), expr)),
+        ("body", expr(&arm.body)),
+        ("span", span(&arm.span)),
+    ])
+}
+
+fn pattern(pat: &Pattern) -> Json {
+    match pat {
+        Pattern::Ident(name) => node("ident", vec![("name", string(name))]),
+        Pattern::Literal(lit) => node("literal", vec![("value", literal(lit))]),
+        Pattern::Struct(name, fields) => node(
+            "struct",
+            vec![
+                ("name", string(name)),
+                (
+                    "fields",
+                    array(fields, |(field, sub)| {
+                        Json::Object(vec![("name", string(field)), ("pattern", pattern(sub))])
+                    }),
+                ),
+            ],
+        ),
+        Pattern::TupleStruct(name, elems) => node(
+            "tuple_struct",
+            vec![("name", string(name)), ("elems", array(elems, pattern))],
+        ),
+        Pattern::Tuple(elems) => node("tuple", vec![("elems", array(elems, pattern))]),
+        Pattern::Or(alternatives) => {
+            node("or", vec![("alternatives", array(alternatives, pattern))])
+        }
+        Pattern::Wildcard => node("wildcard", Vec::new()),
+    }
+}
+
+fn literal(lit: &Literal) -> Json {
+    match lit {
+        Literal::Int(n, _) => Json::Int(*n),
+        Literal::Float(x) => Json::Float(*x),
+        Literal::Bool(b) => Json::Bool(*b),
+        Literal::Char(c) => Json::Str(c.to_string()),
+        Literal::String(s) => string(s),
+    }
+}
+
+// ---- 表达式 ----
+
+fn expr(e: &Expr) -> Json {
+    match e {
+        Expr::Literal(lit, s) => {
+            let kind = match lit {
+                Literal::Int(..) => "int",
+                Literal::Float(_) => "float",
+                Literal::Bool(_) => "bool",
+                Literal::Char(_) => "char",
+                Literal::String(_) => "string",
+            };
+            node(
+                "literal",
+                vec![
+                    ("type", string(kind)),
+                    ("value", literal(lit)),
+                    ("span", span(s)),
+                ],
+            )
+        }
+        Expr::Ident(name, s) => node("ident", vec![("name", string(name)), ("span", span(s))]),
+        Expr::Path(path, s) => node(
+            "path",
+            vec![("segments", array(path, |s| string(s))), ("span", span(s))],
+        ),
+        Expr::Turbofish(path, args, s) => node(
+            "turbofish",
+            vec![
+                ("path", expr(path)),
+                ("args", array(args, ty)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Binary(op, lhs, rhs, s, _) => node(
+            "binary",
+            vec![
+                ("op", Json::Str(op.to_string())),
+                ("lhs", expr(lhs)),
+                ("rhs", expr(rhs)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Unary(op, operand, s, _) => node(
+            "unary",
+            vec![
+                ("op", string(unary_op(op))),
+                ("operand", expr(operand)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Call(callee, args, s, _) => node(
+            "call",
+            vec![
+                ("callee", expr(callee)),
+                ("args", array(args, expr)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::MethodCall(receiver, method, args, s, _) => node(
+            "method_call",
+            vec![
+                ("receiver", expr(receiver)),
+                ("method", string(method)),
+                ("args", array(args, expr)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::FieldAccess(object, name, s) => node(
+            "field_access",
+            vec![
+                ("object", expr(object)),
+                ("field", string(name)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::TupleIndex(object, index, s) => node(
+            "tuple_index",
+            vec![
+                ("object", expr(object)),
+                ("index", Json::Int(*index as i64)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::IndexAccess(object, index, s) => node(
+            "index_access",
+            vec![
+                ("object", expr(object)),
+                ("index", expr(index)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::StructLit(name, fields, s) => node(
+            "struct_lit",
+            vec![
+                ("name", string(name)),
+                (
+                    "fields",
+                    array(fields, |(field, value)| {
+                        Json::Object(vec![("name", string(field)), ("value", expr(value))])
+                    }),
+                ),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::ArrayLit(elems, s) => node(
+            "array_lit",
+            vec![("elems", array(elems, expr)), ("span", span(s))],
+        ),
+        Expr::TupleLit(elems, s) => node(
+            "tuple_lit",
+            vec![("elems", array(elems, expr)), ("span", span(s))],
+        ),
+        Expr::Range(start, end, inclusive, s) => node(
+            "range",
+            vec![
+                ("start", optional(start.as_deref(), expr)),
+                ("end", optional(end.as_deref(), expr)),
+                ("inclusive", Json::Bool(*inclusive)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Assign(target, value, s) => node(
+            "assign",
+            vec![
+                ("target", expr(target)),
+                ("value", expr(value)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::CompoundAssign(op, target, value, s, _) => node(
+            "compound_assign",
+            vec![
+                ("op", Json::Str(op.to_string())),
+                ("target", expr(target)),
+                ("value", expr(value)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Block(inner, s) => node("block", vec![("block", block(inner)), ("span", span(s))]),
+        Expr::Unsafe(inner, s) => node("unsafe", vec![("block", block(inner)), ("span", span(s))]),
+        Expr::AsyncBlock(inner, s) => {
+            node("async", vec![("block", block(inner)), ("span", span(s))])
+        }
+        Expr::If(cond, then_block, else_block, s) => node(
+            "if",
+            vec![
+                ("cond", expr(cond)),
+                ("then_block", block(then_block)),
+                ("else_block", optional(else_block.as_ref(), else_branch)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Match(scrutinee, arms, s) => node(
+            "match",
+            vec![
+                ("expr", expr(scrutinee)),
+                ("arms", array(arms, match_arm)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::While(label, cond, body, s) => node(
+            "while",
+            vec![
+                ("label", optional(label.as_ref(), |s| string(s))),
+                ("cond", expr(cond)),
+                ("body", block(body)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::For(label, pat, iterable, body, s) => node(
+            "for",
+            vec![
+                ("label", optional(label.as_ref(), |s| string(s))),
+                ("pattern", pattern(pat)),
+                ("iterable", expr(iterable)),
+                ("body", block(body)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Break(label, value, s) => node(
+            "break",
+            vec![
+                ("label", optional(label.as_ref(), |s| string(s))),
+                ("expr", optional(value.as_deref(), expr)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Continue(label, s) => node(
+            "continue",
+            vec![
+                ("label", optional(label.as_ref(), |s| string(s))),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Return(value, s) => node(
+            "return",
+            vec![
+                ("expr", optional(value.as_deref(), expr)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Closure(params, ret, body, s) => node(
+            "closure",
+            vec![
+                ("params", array(params, parameter)),
+                ("return_type", optional(ret.as_ref(), ty)),
+                ("body", expr(body)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Cast(value, target, s) => node(
+            "cast",
+            vec![("expr", expr(value)), ("ty", ty(target)), ("span", span(s))],
+        ),
+        Expr::Ref(value, mutable, s) => node(
+            "ref",
+            vec![
+                ("expr", expr(value)),
+                ("mutable", Json::Bool(*mutable)),
+                ("span", span(s)),
+            ],
+        ),
+        Expr::Deref(value, s) => node("deref", vec![("expr", expr(value)), ("span", span(s))]),
+        Expr::Try(value, s) => node("try", vec![("expr", expr(value)), ("span", span(s))]),
+        Expr::Await(value, s) => node("await", vec![("expr", expr(value)), ("span", span(s))]),
+        Expr::MacroCall(call) => macro_call(call),
+        Expr::InlineAsm(asm, s) => node(
+            "inline_asm",
+            vec![
+                ("template", array(&asm.template, |line| string(line))),
+                ("operands", array(&asm.operands, asm_operand)),
+                ("options", array(&asm.options, |option| string(option))),
+                ("span", span(s)),
+            ],
+        ),
+    }
+}
+
+fn asm_operand(operand: &AsmOperand) -> Json {
+    let (reg, explicit) = match &operand.reg {
+        AsmReg::Class(class) => (class, false),
+        AsmReg::Explicit(name) => (name, true),
+    };
+    node(
+        "asm_operand",
+        vec![
+            ("dir", string(operand.dir.keyword())),
+            ("reg", string(reg)),
+            ("explicit", Json::Bool(explicit)),
+            ("expr", optional(operand.expr.as_ref(), expr)),
+            ("span", span(&operand.span)),
+        ],
+    )
+}
+
+// 宏的参数和规则是没有解析的记号，写成源码字符串
+fn tokens(tree: &TokenTree) -> Json {
+    Json::Str(crate::macros::tokens_text(std::slice::from_ref(tree)))
+}
+
+fn macro_call(call: &MacroCall) -> Json {
+    node(
+        "macro_call",
+        vec![
+            ("name", string(&call.name)),
+            ("args", tokens(&call.args)),
+            ("span", span(&call.span)),
+        ],
+    )
+}
+
+fn unary_op(op: &UnOp) -> &'static str {
+    match op {
+        UnOp::Neg => "-",
+        UnOp::LogicalNot => "!",
+        UnOp::BitwiseNot => "~",
+        UnOp::Deref => "*",
+        UnOp::Ref => "&",
+        UnOp::RefMut => "&mut",
+    }
+}
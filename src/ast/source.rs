@@ -0,0 +1,898 @@
+// 语法树还原为源码（`--emit=expanded`），用于查看宏展开的结果
+// 只保证重新解析得到同样的语法树：注释、空行和原来的括号不保留，
+// 运算的优先级需要时加上括号；命令行再交给格式化器统一排版
+
+use super::*;
+use crate::literal;
+use crate::macros::tokens_text;
+use crate::token::TokenKind;
+use std::fmt::Write as _;
+
+impl Program {
+    /// 程序的源码
+    pub fn to_source(&self) -> String {
+        let mut printer = Printer::default();
+        for attribute in &self.attributes {
+            match attribute.args.is_empty() {
+                true => {
+                    let _ = writeln!(printer.out, "#![{}]", attribute.name);
+                }
+                false => {
+                    let args = attribute.args.join(", ");
+                    let _ = writeln!(printer.out, "#![{}({})]", attribute.name, args);
+                }
+            }
+        }
+        if !self.attributes.is_empty() && !self.items.is_empty() {
+            printer.out.push('\n');
+        }
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                printer.out.push('\n');
+            }
+            printer.item(item);
+        }
+        printer.out
+    }
+}
+
+/// 单个表达式的源码，用于显示 `typeof(expr)` 类型
+pub(super) fn expr_source(expr: &Expr) -> String {
+    let mut printer = Printer::default();
+    printer.expr(expr, 0);
+    printer.out
+}
+
+// 运算符的优先级，数值越大结合越紧，与 parser.rs 中 `binding_power` 的各层一一对应
+const ASSIGN: u8 = 1;
+const LOGICAL_OR: u8 = 2;
+const RANGE: u8 = 10;
+const CAST: u8 = 13;
+const UNARY: u8 = 14;
+const POSTFIX: u8 = 15;
+const PRIMARY: u8 = 16;
+
+fn binary_precedence(op: &BinOp) -> u8 {
+    match op {
+        BinOp::LogicalOr => LOGICAL_OR,
+        BinOp::LogicalAnd => 3,
+        BinOp::BitwiseOr => 4,
+        BinOp::BitwiseXor => 5,
+        BinOp::BitwiseAnd => 6,
+        BinOp::Equal | BinOp::NotEqual => 7,
+        BinOp::Less | BinOp::Greater | BinOp::LessEqual | BinOp::GreaterEqual => 8,
+        BinOp::LeftShift | BinOp::RightShift => 9,
+        BinOp::Add | BinOp::Sub => 11,
+        BinOp::Mul | BinOp::Div | BinOp::Mod => 12,
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Assign(..) | Expr::CompoundAssign(..) => ASSIGN,
+        // 闭包、return 和 break 向右吞掉整个表达式
+        Expr::Closure(..) | Expr::Return(..) | Expr::Break(..) => 0,
+        Expr::Binary(op, ..) => binary_precedence(op),
+        Expr::Range(..) => RANGE,
+        Expr::Cast(..) => CAST,
+        Expr::Unary(..) | Expr::Ref(..) | Expr::Deref(..) => UNARY,
+        Expr::Literal(Literal::Int(n, _), _) if *n < 0 => UNARY,
+        Expr::Literal(Literal::Float(x), _) if x.is_sign_negative() => UNARY,
+        Expr::Call(..)
+        | Expr::MethodCall(..)
+        | Expr::FieldAccess(..)
+        | Expr::TupleIndex(..)
+        | Expr::IndexAccess(..)
+        | Expr::Try(..)
+        | Expr::Await(..) => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn visibility(&mut self, visibility: &Visibility) {
+        if *visibility == Visibility::Public {
+            self.out.push_str("pub ");
+        }
+    }
+
+    // ---- 条目 ----
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Function(function) => self.function(function),
+            Item::Struct(def) => {
+                self.attributes(&def.attributes);
+                self.visibility(&def.visibility);
+                let _ = write!(self.out, "struct {}", def.name);
+                self.generics(def.generics.as_ref());
+                self.out.push_str(" {");
+                self.indent += 1;
+                for field in &def.fields {
+                    self.newline();
+                    self.visibility(&field.visibility);
+                    let _ = write!(self.out, "{}: {},", field.name, field.ty);
+                }
+                for contract in &def.invariants {
+                    self.newline();
+                    self.out.push_str("invariant ");
+                    self.condition(&contract.condition);
+                    self.out.push(',');
+                }
+                self.indent -= 1;
+                self.newline();
+                self.out.push_str("}\n");
+            }
+            Item::Enum(def) => {
+                self.attributes(&def.attributes);
+                self.visibility(&def.visibility);
+                let _ = write!(self.out, "enum {}", def.name);
+                self.generics(def.generics.as_ref());
+                self.out.push_str(" {");
+                self.indent += 1;
+                for variant in &def.variants {
+                    self.newline();
+                    self.out.push_str(&variant.name);
+                    if let Some(fields) = &variant.fields {
+                        let fields: Vec<String> = fields.iter().map(Type::to_string).collect();
+                        let _ = write!(self.out, "({})", fields.join(", "));
+                    }
+                    self.out.push(',');
+                }
+                self.indent -= 1;
+                self.newline();
+                self.out.push_str("}\n");
+            }
+            Item::Const(def) => {
+                self.visibility(&def.visibility);
+                let _ = write!(self.out, "const {}: {} = ", def.name, def.ty);
+                self.expr(&def.value, 0);
+                self.out.push_str(";\n");
+            }
+            Item::Static(def) => {
+                self.visibility(&def.visibility);
+                self.out.push_str("static ");
+                if def.mutable {
+                    self.out.push_str("mut ");
+                }
+                let _ = write!(self.out, "{}: {} = ", def.name, def.ty);
+                self.expr(&def.value, 0);
+                self.out.push_str(";\n");
+            }
+            Item::Import(import) => {
+                let _ = write!(self.out, "import {}", import.path.join("::"));
+                if let Some(alias) = &import.alias {
+                    let _ = write!(self.out, " as {}", alias);
+                }
+                self.out.push_str(";\n");
+            }
+            Item::Export(export) => {
+                let paths: Vec<String> = export
+                    .items
+                    .iter()
+                    .map(|item| item.path.join("::"))
+                    .collect();
+                let _ = writeln!(self.out, "export {{ {} }};", paths.join(", "));
+            }
+            Item::MacroRules(def) => {
+                let _ = write!(self.out, "macro_rules! {} {{", def.name);
+                self.indent += 1;
+                for rule in &def.rules {
+                    self.newline();
+                    self.out
+                        .push_str(&tokens_text(std::slice::from_ref(&rule.pattern)));
+                    self.out.push_str(" => ");
+                    self.out
+                        .push_str(&tokens_text(std::slice::from_ref(&rule.body)));
+                    self.out.push(';');
+                }
+                self.indent -= 1;
+                self.newline();
+                self.out.push_str("}\n");
+            }
+            // `name! { ... }` 之后没有分号
+            Item::MacroCall(call) => {
+                self.macro_call(call);
+                match &call.args {
+                    TokenTree::Delimited(open, ..) if open.kind == TokenKind::LeftBrace => {
+                        self.out.push('\n')
+                    }
+                    _ => self.out.push_str(";\n"),
+                }
+            }
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            match attribute.args.is_empty() {
+                true => {
+                    let _ = writeln!(self.out, "#[{}]", attribute.name);
+                }
+                false => {
+                    let args = attribute.args.join(", ");
+                    let _ = writeln!(self.out, "#[{}({})]", attribute.name, args);
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.attributes(&function.attributes);
+        if let Some(operator) = &function.operator {
+            let _ = writeln!(
+                self.out,
+                "operator {} (precedence {}, {})",
+                operator.symbol,
+                operator.precedence,
+                operator.associativity.keyword()
+            );
+        }
+        self.visibility(&function.visibility);
+        if function.pure {
+            self.out.push_str("pure ");
+        }
+        if function.asynchronous {
+            self.out.push_str("async ");
+        }
+        let _ = write!(self.out, "fn {}", function.name);
+        self.generics(function.generics.as_ref());
+        self.out.push('(');
+        for (i, param) in function.params.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.pattern(&param.pattern);
+            let _ = write!(self.out, ": {}", param.ty);
+        }
+        self.out.push(')');
+        if let Some(ty) = &function.return_type {
+            let _ = write!(self.out, " -> {}", ty);
+        }
+        self.indent += 1;
+        for contract in &function.contracts {
+            self.newline();
+            let _ = write!(self.out, "{} ", contract.kind.keyword());
+            self.condition(&contract.condition);
+        }
+        self.indent -= 1;
+        match function.contracts.is_empty() {
+            true => self.out.push(' '),
+            false => self.newline(),
+        }
+        self.block(&function.body);
+        self.out.push('\n');
+    }
+
+    fn generics(&mut self, generics: Option<&Generics>) {
+        let Some(generics) = generics else {
+            return;
+        };
+        let params: Vec<String> = generics
+            .params
+            .iter()
+            .map(|param| match param.bounds.is_empty() {
+                true => param.name.clone(),
+                false => format!("{}: {}", param.name, param.bounds.join(" + ")),
+            })
+            .collect();
+        let _ = write!(self.out, "<{}>", params.join(", "));
+    }
+
+    fn macro_call(&mut self, call: &MacroCall) {
+        let _ = write!(
+            self.out,
+            "{}!{}",
+            call.name,
+            tokens_text(std::slice::from_ref(&call.args))
+        );
+    }
+
+    // ---- 语句 ----
+
+    fn block(&mut self, block: &Block) {
+        if block.statements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.indent += 1;
+        for statement in &block.statements {
+            self.newline();
+            self.statement(statement);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let(stmt) => {
+                self.out.push_str("let ");
+                if stmt.mutable {
+                    self.out.push_str("mut ");
+                }
+                self.pattern(&stmt.pattern);
+                if let Some(ty) = &stmt.ty {
+                    let _ = write!(self.out, ": {}", ty);
+                }
+                if let Some(init) = &stmt.init {
+                    self.out.push_str(" = ");
+                    // 末尾是没有 else 的 `if` 时，`let ... else` 的 else 会被解析为它的分支
+                    match stmt.else_block.is_some() && ends_with_open_if(init) {
+                        true => self.grouped(init),
+                        false => self.expr(init, 0),
+                    }
+                }
+                if let Some(block) = &stmt.else_block {
+                    self.out.push_str(" else ");
+                    self.block(block);
+                }
+                self.out.push(';');
+            }
+            // 以 `if`、`{`、`return` 等开头的会解析为对应的语句，整个表达式加上括号
+            Statement::Expr(stmt) => {
+                match starts_like_statement(&stmt.expr) {
+                    true => self.grouped(&stmt.expr),
+                    false => self.expr(&stmt.expr, 0),
+                }
+                if stmt.semicolon {
+                    self.out.push(';');
+                }
+            }
+            Statement::Return(stmt) => {
+                self.out.push_str("return");
+                if let Some(expr) = &stmt.expr {
+                    self.out.push(' ');
+                    self.expr(expr, 0);
+                }
+                self.out.push(';');
+            }
+            Statement::If(stmt) => {
+                self.if_chain(&stmt.cond, &stmt.then_block, stmt.else_block.as_ref())
+            }
+            Statement::While(stmt) => self.while_loop(
+                stmt.label.as_ref(),
+                &stmt.cond,
+                &stmt.invariants,
+                &stmt.body,
+            ),
+            Statement::For(stmt) => self.for_loop(
+                stmt.label.as_ref(),
+                &stmt.pattern,
+                &stmt.iterable,
+                &stmt.invariants,
+                &stmt.body,
+            ),
+            Statement::Match(stmt) => self.match_expr(&stmt.expr, &stmt.arms),
+            Statement::Break(stmt) => {
+                self.jump("break", stmt.label.as_ref(), stmt.expr.as_ref());
+                self.out.push(';');
+            }
+            Statement::Continue(stmt) => {
+                self.jump("continue", stmt.label.as_ref(), None);
+                self.out.push(';');
+            }
+            Statement::Block(block) => self.block(block),
+        }
+    }
+
+    fn if_chain(&mut self, cond: &Expr, then_block: &Block, else_block: Option<&ElseBranch>) {
+        self.out.push_str("if ");
+        self.condition(cond);
+        self.out.push(' ');
+        self.block(then_block);
+        let Some(else_block) = else_block else {
+            return;
+        };
+        self.out.push_str(" else ");
+        match else_block {
+            ElseBranch::If(nested) => {
+                self.if_chain(&nested.cond, &nested.then_block, nested.else_block.as_ref())
+            }
+            ElseBranch::Block(block) => self.block(block),
+        }
+    }
+
+    fn loop_label(&mut self, label: Option<&String>) {
+        if let Some(label) = label {
+            let _ = write!(self.out, "'{}: ", label);
+        }
+    }
+
+    fn while_loop(
+        &mut self,
+        label: Option<&String>,
+        cond: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+    ) {
+        self.loop_label(label);
+        self.out.push_str("while ");
+        self.condition(cond);
+        self.loop_invariants(invariants);
+        self.out.push(' ');
+        self.block(body);
+    }
+
+    fn for_loop(
+        &mut self,
+        label: Option<&String>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+    ) {
+        self.loop_label(label);
+        self.out.push_str("for ");
+        self.pattern(pattern);
+        self.out.push_str(" in ");
+        self.condition(iterable);
+        self.loop_invariants(invariants);
+        self.out.push(' ');
+        self.block(body);
+    }
+
+    fn loop_invariants(&mut self, invariants: &[Contract]) {
+        for contract in invariants {
+            self.out.push_str(" invariant ");
+            self.condition(&contract.condition);
+        }
+    }
+
+    fn match_expr(&mut self, scrutinee: &Expr, arms: &[MatchArm]) {
+        self.out.push_str("match ");
+        self.condition(scrutinee);
+        self.out.push_str(" {");
+        self.indent += 1;
+        for arm in arms {
+            self.newline();
+            self.pattern(&arm.pattern);
+            if let Some(guard) = &arm.guard {
+                self.out.push_str(" if ");
+                self.expr(guard, 0);
+            }
+            self.out.push_str(" => ");
+            // 语句形式的 match 中 `=> {` 开始的代码块就是整个分支体，`{ x } + 1` 要加上括号
+            match arm.body {
+                Expr::Block(..) => self.expr(&arm.body, 0),
+                _ if matches!(leftmost(&arm.body), Expr::Block(..)) => self.grouped(&arm.body),
+                _ => self.expr(&arm.body, 0),
+            }
+            self.out.push(',');
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn jump(&mut self, keyword: &str, label: Option<&String>, value: Option<&Expr>) {
+        self.out.push_str(keyword);
+        if let Some(label) = label {
+            let _ = write!(self.out, " '{}", label);
+        }
+        if let Some(value) = value {
+            self.out.push(' ');
+            // 没有标签时 `break 'a: while ...` 的 `'a` 是 break 的标签
+            let labeled_loop = matches!(
+                leftmost(value),
+                Expr::While(Some(_), ..) | Expr::For(Some(_), ..)
+            );
+            match label.is_none() && labeled_loop {
+                true => self.grouped(value),
+                false => self.expr(value, 0),
+            }
+        }
+    }
+
+    // ---- 表达式 ----
+
+    // `if`、`while`、`for` 和 `match` 之后的 `{` 开始代码块，其中的结构体字面量加上括号
+    fn condition(&mut self, expr: &Expr) {
+        match has_struct_literal(expr) {
+            true => self.grouped(expr),
+            false => self.expr(expr, 0),
+        }
+    }
+
+    fn grouped(&mut self, expr: &Expr) {
+        self.out.push('(');
+        self.expr(expr, 0);
+        self.out.push(')');
+    }
+
+    // 优先级低于 `min` 的表达式加上括号
+    fn expr(&mut self, expr: &Expr, min: u8) {
+        if precedence(expr) < min {
+            self.grouped(expr);
+            return;
+        }
+        match expr {
+            Expr::Literal(literal, _) => self.literal(literal),
+            Expr::Ident(name, _) => self.out.push_str(name),
+            Expr::Path(segments, _) => self.out.push_str(&segments.join("::")),
+            Expr::Turbofish(path, args, _) => {
+                self.expr(path, PRIMARY);
+                let args: Vec<String> = args.iter().map(Type::to_string).collect();
+                let _ = write!(self.out, "::<{}>", args.join(", "));
+            }
+            Expr::Binary(op, left, right, _, _) => {
+                let precedence = binary_precedence(op);
+                // 比较不能连用，`(a < b) < c` 的括号要保留
+                let chained = matches!(precedence, 7 | 8);
+                self.expr(left, precedence + chained as u8);
+                let _ = write!(self.out, " {} ", op);
+                self.expr(right, precedence + 1);
+            }
+            Expr::Unary(op, inner, _, _) => {
+                self.out.push_str(match op {
+                    UnOp::Neg => "-",
+                    UnOp::LogicalNot => "!",
+                    UnOp::BitwiseNot => "~",
+                    UnOp::Deref => "*",
+                    UnOp::Ref => "&",
+                    UnOp::RefMut => "&mut ",
+                });
+                // `-x.abs()` 是语法错误，要写成 `-(x.abs())`
+                match (op, &**inner) {
+                    (UnOp::Neg, Expr::MethodCall(..)) => self.grouped(inner),
+                    _ => self.expr(inner, UNARY),
+                }
+            }
+            Expr::Ref(inner, mutable, _) => {
+                self.out.push_str(if *mutable { "&mut " } else { "&" });
+                self.expr(inner, UNARY);
+            }
+            Expr::Deref(inner, _) => {
+                self.out.push('*');
+                self.expr(inner, UNARY);
+            }
+            Expr::Call(callee, args, _, _) => {
+                // 调用字段中的函数要写成 `(x.f)(...)`，`x.f(...)` 是方法调用
+                match **callee {
+                    Expr::FieldAccess(..) => self.expr(callee, PRIMARY),
+                    _ => self.expr(callee, POSTFIX),
+                }
+                self.list("(", args, ")");
+            }
+            Expr::MethodCall(receiver, method, args, _, _) => {
+                self.expr(receiver, POSTFIX);
+                let _ = write!(self.out, ".{}", method);
+                self.list("(", args, ")");
+            }
+            Expr::FieldAccess(inner, field, _) => {
+                self.expr(inner, POSTFIX);
+                let _ = write!(self.out, ".{}", field);
+            }
+            Expr::TupleIndex(inner, index, _) => {
+                // `1.0` 是浮点数的写法，整数字面量要加上括号
+                match **inner {
+                    Expr::Literal(Literal::Int(..), _) => self.grouped(inner),
+                    _ => self.expr(inner, POSTFIX),
+                }
+                let _ = write!(self.out, ".{}", index);
+            }
+            Expr::IndexAccess(inner, index, _) => {
+                self.expr(inner, POSTFIX);
+                self.out.push('[');
+                self.expr(index, 0);
+                self.out.push(']');
+            }
+            Expr::Try(inner, _) => {
+                self.expr(inner, POSTFIX);
+                self.out.push('?');
+            }
+            Expr::Await(inner, _) => {
+                self.expr(inner, POSTFIX);
+                self.out.push_str(".await");
+            }
+            Expr::StructLit(name, fields, _) => {
+                let _ = write!(self.out, "{} {{", name);
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    self.out.push_str(if i == 0 { " " } else { ", " });
+                    match value {
+                        Expr::Ident(name, _) if name == field => self.out.push_str(field),
+                        _ => {
+                            let _ = write!(self.out, "{}: ", field);
+                            self.expr(value, 0);
+                        }
+                    }
+                }
+                self.out
+                    .push_str(if fields.is_empty() { "}" } else { " }" });
+            }
+            Expr::ArrayLit(elements, _) => self.list("[", elements, "]"),
+            Expr::TupleLit(elements, _) => {
+                self.list("(", elements, "");
+                self.out
+                    .push_str(if elements.len() == 1 { ",)" } else { ")" });
+            }
+            Expr::Range(start, end, inclusive, _) => {
+                if let Some(start) = start {
+                    self.expr(start, RANGE + 1);
+                }
+                self.out.push_str(if *inclusive { "..=" } else { ".." });
+                if let Some(end) = end {
+                    self.expr(end, RANGE + 1);
+                }
+            }
+            Expr::Assign(target, value, _) => {
+                self.expr(target, LOGICAL_OR);
+                self.out.push_str(" = ");
+                self.expr(value, ASSIGN);
+            }
+            Expr::CompoundAssign(op, target, value, _, _) => {
+                self.expr(target, LOGICAL_OR);
+                let _ = write!(self.out, " {}= ", op);
+                self.expr(value, ASSIGN);
+            }
+            Expr::Block(block, _) => self.block(block),
+            Expr::Unsafe(block, _) => {
+                self.out.push_str("unsafe ");
+                self.block(block);
+            }
+            Expr::AsyncBlock(block, _) => {
+                self.out.push_str("async ");
+                self.block(block);
+            }
+            Expr::If(cond, then_block, else_block, _) => {
+                self.if_chain(cond, then_block, else_block.as_ref())
+            }
+            Expr::Match(scrutinee, arms, _) => self.match_expr(scrutinee, arms),
+            Expr::While(label, cond, body, _) => self.while_loop(label.as_ref(), cond, &[], body),
+            Expr::For(label, pattern, iterable, body, _) => {
+                self.for_loop(label.as_ref(), pattern, iterable, &[], body)
+            }
+            Expr::Break(label, value, _) => self.jump("break", label.as_ref(), value.as_deref()),
+            Expr::Continue(label, _) => self.jump("continue", label.as_ref(), None),
+            Expr::Return(value, _) => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.expr(value, 0);
+                }
+            }
+            Expr::Closure(params, return_type, body, _) => {
+                self.out.push('|');
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.pattern(&param.pattern);
+                    if param.ty != Type::Infer {
+                        let _ = write!(self.out, ": {}", param.ty);
+                    }
+                }
+                self.out.push_str("| ");
+                if let Some(ty) = return_type {
+                    let _ = write!(self.out, "-> {} ", ty);
+                }
+                self.expr(body, 0);
+            }
+            Expr::Cast(inner, ty, _) => {
+                self.expr(inner, UNARY);
+                let _ = write!(self.out, " as {}", ty);
+            }
+            Expr::MacroCall(call) => self.macro_call(call),
+            Expr::InlineAsm(asm, _) => self.inline_asm(asm),
+        }
+    }
+
+    fn inline_asm(&mut self, asm: &InlineAsm) {
+        self.out.push_str("asm!(");
+        for (i, line) in asm.template.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.out.push_str(&string_literal(line));
+        }
+        for operand in &asm.operands {
+            let _ = write!(self.out, ", {}({}) ", operand.dir.keyword(), operand.reg);
+            match &operand.expr {
+                Some(expr) => self.expr(expr, 0),
+                None => self.out.push('_'),
+            }
+        }
+        if !asm.options.is_empty() {
+            let _ = write!(self.out, ", options({})", asm.options.join(", "));
+        }
+        self.out.push(')');
+    }
+
+    fn list(&mut self, open: &str, exprs: &[Expr], close: &str) {
+        self.out.push_str(open);
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr, 0);
+        }
+        self.out.push_str(close);
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Int(n, suffix) => {
+                let _ = write!(self.out, "{}", n);
+                if let Some(ty) = suffix {
+                    let _ = write!(self.out, "{}", ty);
+                }
+            }
+            Literal::Float(x) => {
+                let _ = write!(self.out, "{:?}", x);
+            }
+            Literal::Bool(b) => {
+                let _ = write!(self.out, "{}", b);
+            }
+            Literal::Char(c) => {
+                self.out.push('\'');
+                literal::escape(&mut self.out, *c, '\'');
+                self.out.push('\'');
+            }
+            Literal::String(s) => self.out.push_str(&string_literal(s)),
+        }
+    }
+
+    // ---- 模式 ----
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Ident(name) => self.out.push_str(name),
+            Pattern::Literal(literal) => self.literal(literal),
+            Pattern::Struct(name, fields) => {
+                let _ = write!(self.out, "{} {{", name);
+                for (i, (field, pattern)) in fields.iter().enumerate() {
+                    self.out.push_str(if i == 0 { " " } else { ", " });
+                    match pattern {
+                        Pattern::Ident(name) if name == field => self.out.push_str(field),
+                        _ => {
+                            let _ = write!(self.out, "{}: ", field);
+                            self.pattern(pattern);
+                        }
+                    }
+                }
+                self.out
+                    .push_str(if fields.is_empty() { "}" } else { " }" });
+            }
+            Pattern::TupleStruct(name, patterns) => {
+                self.out.push_str(name);
+                self.patterns(patterns);
+            }
+            Pattern::Tuple(patterns) => {
+                self.patterns(patterns);
+                // `(x)` 也是单元素的元组模式，加上逗号更清楚
+                if patterns.len() == 1 {
+                    self.out.insert(self.out.len() - 1, ',');
+                }
+            }
+            Pattern::Or(patterns) => {
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(" | ");
+                    }
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Wildcard => self.out.push('_'),
+        }
+    }
+
+    fn patterns(&mut self, patterns: &[Pattern]) {
+        self.out.push('(');
+        for (i, pattern) in patterns.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.pattern(pattern);
+        }
+        self.out.push(')');
+    }
+}
+
+/// 字符串的字面量写法，转义见 literal.rs
+pub fn string_literal(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        literal::escape(&mut out, c, '"');
+    }
+    out.push('"');
+    out
+}
+
+// 在语句开头按 `if`、`while`、代码块等语句解析的表达式
+fn starts_like_statement(expr: &Expr) -> bool {
+    matches!(
+        leftmost(expr),
+        Expr::Block(..)
+            | Expr::If(..)
+            | Expr::Match(..)
+            | Expr::While(..)
+            | Expr::For(..)
+            | Expr::Break(..)
+            | Expr::Continue(..)
+            | Expr::Return(..)
+    )
+}
+
+// 没有括号时表达式的第一个记号所属的子表达式
+fn leftmost(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Binary(_, left, _, _, _)
+        | Expr::Range(Some(left), _, _, _)
+        | Expr::Assign(left, _, _)
+        | Expr::CompoundAssign(_, left, _, _, _)
+        | Expr::Cast(left, _, _)
+        | Expr::FieldAccess(left, _, _)
+        | Expr::TupleIndex(left, _, _)
+        | Expr::Try(left, _)
+        | Expr::Await(left, _)
+        | Expr::Call(left, _, _, _)
+        | Expr::MethodCall(left, _, _, _, _)
+        | Expr::IndexAccess(left, _, _)
+        | Expr::Turbofish(left, _, _) => leftmost(left),
+        _ => expr,
+    }
+}
+
+// 没有括号时表达式的最后一个记号所属的子表达式是否是没有 else 的 `if`
+fn ends_with_open_if(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary(_, _, right, _, _)
+        | Expr::Range(_, Some(right), _, _)
+        | Expr::Assign(_, right, _)
+        | Expr::CompoundAssign(_, _, right, _, _)
+        | Expr::Unary(_, right, _, _)
+        | Expr::Ref(right, _, _)
+        | Expr::Deref(right, _)
+        | Expr::Closure(_, _, right, _)
+        | Expr::Return(Some(right), _)
+        | Expr::Break(_, Some(right), _) => ends_with_open_if(right),
+        Expr::If(_, _, else_branch, _) => {
+            let mut else_branch = else_branch.as_ref();
+            while let Some(ElseBranch::If(nested)) = else_branch {
+                else_branch = nested.else_block.as_ref();
+            }
+            else_branch.is_none()
+        }
+        _ => false,
+    }
+}
+
+fn has_struct_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::StructLit(..) => true,
+        Expr::Binary(_, left, right, _, _)
+        | Expr::Assign(left, right, _)
+        | Expr::CompoundAssign(_, left, right, _, _) => {
+            has_struct_literal(left) || has_struct_literal(right)
+        }
+        Expr::Range(start, end, _, _) => start.iter().chain(end).any(|e| has_struct_literal(e)),
+        Expr::Unary(_, inner, _, _)
+        | Expr::Ref(inner, _, _)
+        | Expr::Deref(inner, _)
+        | Expr::Cast(inner, _, _)
+        | Expr::FieldAccess(inner, _, _)
+        | Expr::TupleIndex(inner, _, _)
+        | Expr::Try(inner, _)
+        | Expr::Await(inner, _)
+        | Expr::Call(inner, _, _, _)
+        | Expr::MethodCall(inner, _, _, _, _)
+        | Expr::IndexAccess(inner, _, _)
+        | Expr::Closure(_, _, inner, _) => has_struct_literal(inner),
+        _ => false,
+    }
+}
@@ -0,0 +1,117 @@
+// 基准测试（`contractus bench`）
+// 在字节码虚拟机中反复调用 `#[bench]` 函数：先预热一段时间，用预热的平均耗时确定
+// 每次采样调用的次数，使每次采样的耗时接近 `measure / samples`；报告每次调用的
+// 平均耗时和各次采样之间的标准差。基准测试的输出不显示
+
+use crate::bytecode::{self, Vm};
+use crate::diagnostic::Diagnostic;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    pub warmup: Duration,
+    pub measure: Duration, // 所有采样的总时间
+    pub samples: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            warmup: Duration::from_millis(300),
+            measure: Duration::from_secs(1),
+            samples: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: u64, // 每次采样调用的次数
+    pub samples: usize,
+    pub ns_per_iter: f64,
+    pub std_dev: f64,
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ns/iter (+/- {})",
+            group_digits(self.ns_per_iter),
+            group_digits(self.std_dev)
+        )
+    }
+}
+
+/// 运行一个基准测试，基准测试运行出错（包括违反契约）时返回错误
+pub fn run(
+    module: &bytecode::Module,
+    name: &str,
+    options: &BenchOptions,
+) -> Result<BenchResult, Vec<Diagnostic>> {
+    let mut vm = Vm::new(module, io::sink());
+    let mut call = || vm.call_function(name, Vec::new()).map(drop);
+
+    // 至少调用一次
+    let start = Instant::now();
+    let mut warmup = 0u64;
+    loop {
+        call()?;
+        warmup += 1;
+        if start.elapsed() >= options.warmup {
+            break;
+        }
+    }
+    let per_iter = start.elapsed().as_nanos() as f64 / warmup as f64;
+
+    let samples = options.samples.max(1);
+    let sample_time = options.measure.as_nanos() as f64 / samples as f64;
+    let iterations = ((sample_time / per_iter.max(1.0)) as u64).max(1);
+    let mut times = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            call()?;
+        }
+        times.push(start.elapsed().as_nanos() as f64 / iterations as f64);
+    }
+
+    let (ns_per_iter, std_dev) = mean_and_std_dev(&times);
+    Ok(BenchResult {
+        name: name.to_string(),
+        iterations,
+        samples,
+        ns_per_iter,
+        std_dev,
+    })
+}
+
+/// 平均值和样本标准差，少于两个样本时标准差为 0
+pub fn mean_and_std_dev(samples: &[f64]) -> (f64, f64) {
+    if samples.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    if samples.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+// 四舍五入为整数并每三位加逗号：1234567.8 → 1,234,568
+fn group_digits(value: f64) -> String {
+    let digits = (value.round() as u64).to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
@@ -0,0 +1,802 @@
+// Contractus 内建函数
+// 内建函数不需要声明就可以调用，位于每个模块的根作用域中；程序中定义或导入的同名函数优先。
+// 这里登记它们的签名：语义分析据此检查参数个数和能推断出的参数类型，
+// 解释器（interp.rs）和字节码虚拟机（bytecode/vm.rs 的 `Builtin`）分别实现它们。
+// - print(value, ...)：每个值输出一行，接受基本类型（bool、整数、浮点数、char、string）
+//   以及由它们组成的元组、数组、结构体和枚举
+// - len(value) -> usize：数组、切片、字符串或 Map 的长度（也可以通过引用），字符串按字节计
+// - assert(condition[, message])：条件为 false 时以运行时错误结束程序
+// - panic([message]) -> !：以运行时错误结束程序，调用之后的代码不可达
+// - catch_panic(f) -> Result<T, string>：调用没有参数的函数或闭包 f，返回 `Ok(f())`；
+//   f 中发生运行时错误（panic）时返回 `Err(错误信息)`。`--panic=abort` 时不捕获
+// - Box::new(value) -> Box<T>：把值移到堆上，`*b` 是盒子中的值，访问字段时自动解引用；
+//   盒子离开作用域时释放
+// - Rc::new(value) -> Rc<T>、Rc::clone(&rc) -> Rc<T>、Rc::strong_count(&rc) -> usize：
+//   引用计数的共享值，不能修改；clone 使计数加一，Rc 离开作用域时计数减一，减到零时析构其中的值。
+//   不检测循环引用，循环中的值不会释放
+// - Vec::new() -> Vec<T>、push(&mut vec, value)、pop(&mut vec) -> Option<T>、
+//   remove(&mut vec, index) -> T：可以增长的数组，pop 在 MIR 中展开为长度检查和 remove
+// - StringBuilder::new()、append(&mut builder, value)：逐段拼接字符串，追加的值按 print 的格式输出
+// - Map::new() -> Map<K, V>、insert(&mut map, key, value)、get(&map, key) -> Option<V>、
+//   remove(&mut map, key) -> Option<V>、contains_key(&map, key)、entries(&map) -> Vec<(K, V)>：
+//   哈希表，键是 bool、整数、char 或字符串
+// - to_string(value) -> string：按 print 的格式转换为字符串
+// - format!("x = {}, y = {:>8.2}", x, y) -> string：按格式字符串拼接（fmt.rs），
+//   每个占位符在宏展开时变为一次 `fmt::arg(&value, spec) -> string`，语义分析按格式说明检查参数类型；
+//   `println!(...)` 输出拼接的结果和换行，`print(x)` 仍然是输出一个值最简单的写法
+// - abs(n)：整数的绝对值，类型与参数相同，溢出时是运行时错误；通常按方法调用：`(a - b).abs()`
+// - 字符串是不可变的 UTF-8 文本，位置和长度都按字节计，`+` 拼接出新的字符串：
+//   substring(s, start, end)、split(s, separator) -> Vec<string>、contains(s, pattern)、
+//   find(s, pattern) -> Option<usize>、to_int(s) -> Option<i64>、is_int(s)、chars(s) -> Vec<char>；
+//   substring 的边界必须落在字符之间，`for c in s` 按字符遍历
+// - io::read_file(path) -> Result<string, string>、io::write_file(path, contents) -> Result<(), string>、
+//   io::args() -> Vec<string>、io::exit(code)：文件和进程，需要宿主打开 io 能力（interp/host.rs）；
+//   std::process::args() 和 std::process::exit(code) 是 io::args 和 io::exit 的别名。
+//   程序调用 exit 时以它给出的退出码结束，否则 `fn main() -> i32` 的返回值是退出码
+// - env::get(name) -> Option<string>、env::has(name) -> bool：环境变量，需要宿主打开 env 能力
+// - time::now_millis() -> i64、time::sleep(ms)：自 Unix 纪元以来的毫秒数和等待 ms 毫秒，
+//   需要宿主打开 time 能力；沙箱和嵌入的宿主默认不打开这些能力
+// 返回 Option 的函数（pop 除外）在 MIR 中展开为检查函数（contains_key、contains、is_int、env::has）和取值；
+// 返回 Result 的 io 函数在虚拟机中返回 `(是否成功, 值, 错误信息)`，在 MIR 中展开为 Ok 或 Err
+// 以集合为第一个参数的内建函数也可以按方法调用，接收者自动取引用：`v.push(1)`、`m.get(k)`。
+// 同名的内建函数（Vec 和 Map 的 remove）按第一个参数的类型区分
+
+use crate::ast::Type;
+
+/// 内建类型：`Vec<T>` 是可以增长的数组，`StringBuilder` 是可以追加内容的字符串。
+/// 运行时分别表示为数组和字符串，下标、迭代、`len` 和输出与它们相同；
+/// `Map<K, V>` 是哈希表，迭代得到 `(K, V)` 元组；`Box<T>` 独占堆上的一个 T，
+/// `Rc<T>` 共享堆上的一个 T，它们的大小是一个指针，可以打破递归类型的循环
+pub const TYPES: &[&str] = &["Vec", "StringBuilder", "Map", "Box", "Rc"];
+
+pub fn is_type(name: &str) -> bool {
+    TYPES.contains(&name)
+}
+
+/// 数组、切片和 `Vec` 的元素类型（也可以通过引用）
+pub fn element_type(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::Array(elem, _) | Type::Slice(elem) => Some((**elem).clone()),
+        Type::Generic(name, args) if name == "Vec" => args.first().cloned(),
+        Type::Reference(inner, _) => element_type(inner),
+        _ => None,
+    }
+}
+
+/// `Box<T>` 和 `Rc<T>` 中的值的类型
+pub fn pointee_type(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::Generic(name, args) if (name == "Box" || name == "Rc") && args.len() == 1 => {
+            Some(args[0].clone())
+        }
+        _ => None,
+    }
+}
+
+/// `Rc<T>`，其中的值不能修改
+pub fn is_shared(ty: &Type) -> bool {
+    matches!(ty, Type::Generic(name, _) if name == "Rc")
+}
+
+/// `Map<K, V>` 的键和值的类型（也可以通过引用）
+pub fn map_types(ty: &Type) -> Option<(Type, Type)> {
+    match ty {
+        Type::Generic(name, args) if name == "Map" && args.len() == 2 => {
+            Some((args[0].clone(), args[1].clone()))
+        }
+        Type::Reference(inner, _) => map_types(inner),
+        _ => None,
+    }
+}
+
+/// 字符串（也可以通过引用）
+pub fn is_string(ty: &Type) -> bool {
+    match ty {
+        Type::String => true,
+        Type::Reference(inner, _) => is_string(inner),
+        _ => false,
+    }
+}
+
+/// for 循环每次迭代得到的值：数组、切片和 `Vec` 的元素，`Map` 的 `(K, V)` 元组，字符串的字符
+pub fn item_type(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::String => Some(Type::Char),
+        Type::Reference(inner, _) => item_type(inner),
+        _ => element_type(ty).or_else(|| {
+            let (key, value) = map_types(ty)?;
+            Some(Type::Tuple(vec![key, value]))
+        }),
+    }
+}
+
+/// 内建函数参数接受的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Value,    // 任意可以输出的值
+    Sequence, // 数组、切片或字符串
+    Bool,
+    String,
+    Integer,
+    Key,           // Map 的键：bool、整数、char 或字符串
+    Vec,           // `&mut Vec<T>`，内建函数修改它指向的 Vec
+    StringBuilder, // `&mut StringBuilder`
+    Map,           // `Map<K, V>`，也可以通过引用
+    MapMut,        // `&mut Map<K, V>`
+    Shared,        // `&Rc<T>`
+    Callback,      // 没有参数的函数或闭包
+}
+
+impl Param {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Param::Value => "a value",
+            Param::Sequence => "an array, slice or string",
+            Param::Bool => "`bool`",
+            Param::String => "`string`",
+            Param::Integer => "an integer",
+            Param::Key => "a `bool`, integer, `char` or `string` key",
+            Param::Vec => "`&mut Vec<T>`",
+            Param::StringBuilder => "`&mut StringBuilder`",
+            Param::Map => "a map",
+            Param::MapMut => "`&mut Map<K, V>`",
+            Param::Shared => "`&Rc<T>`",
+            Param::Callback => "a function without parameters",
+        }
+    }
+
+    // 通过 `&mut` 修改的内建类型
+    fn target(self) -> Option<&'static str> {
+        match self {
+            Param::Vec => Some("Vec"),
+            Param::StringBuilder => Some("StringBuilder"),
+            Param::MapMut => Some("Map"),
+            _ => None,
+        }
+    }
+
+    /// 类型不符合时返回 false；类型变量、用户定义的类型等无法判断的情况都接受
+    pub fn accepts(self, ty: &Type) -> bool {
+        match (self, ty) {
+            (_, Type::Never) => true, // `!` 可以转换为任何类型
+            (Param::Vec | Param::StringBuilder | Param::MapMut, Type::Reference(inner, true)) => {
+                self.accepts_target(inner)
+            }
+            (
+                Param::Vec | Param::StringBuilder | Param::MapMut,
+                Type::Named(name) | Type::Generic(name, _),
+            ) => !is_type(name),
+            (Param::Vec | Param::StringBuilder | Param::MapMut, _) => false,
+            (Param::Value, Type::Reference(_, _)) => true,
+            (Param::Shared, Type::Reference(inner, _)) => match &**inner {
+                Type::Named(name) | Type::Generic(name, _) => name == "Rc" || !is_type(name),
+                _ => false,
+            },
+            (Param::Shared, Type::Named(name) | Type::Generic(name, _)) => !is_type(name),
+            (Param::Shared, _) => false,
+            (_, Type::Reference(inner, _)) => self.accepts(inner),
+            (Param::Value, _) => !matches!(ty, Type::Function(..) | Type::Pointer(_, _)),
+            (Param::Sequence, _) => {
+                *ty == Type::String || (!is_scalar(ty) && !matches!(ty, Type::Tuple(_)))
+            }
+            (Param::Bool, _) => *ty == Type::Bool || !is_scalar(ty),
+            (Param::String, _) => *ty == Type::String || !is_scalar(ty),
+            (Param::Integer, _) => is_integer(ty) || !is_scalar(ty),
+            (Param::Key, _) => is_key(ty) || !is_composite(ty),
+            (Param::Map, Type::Named(name) | Type::Generic(name, _)) => {
+                name == "Map" || !is_type(name)
+            }
+            (Param::Map, _) => false,
+            (Param::Callback, Type::Function(params, _, _)) => params.is_empty(),
+            (Param::Callback, _) => !is_scalar(ty) && !is_composite(ty),
+        }
+    }
+
+    // `&mut` 指向的值；`&mut &mut Vec<T>` 自动解引用
+    fn accepts_target(self, ty: &Type) -> bool {
+        match ty {
+            Type::Reference(inner, _) => self.accepts_target(inner),
+            Type::Named(name) | Type::Generic(name, _) => {
+                self.target() == Some(name.as_str()) || !is_type(name)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 整数类型
+pub fn is_integer(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+            | Type::Isize
+    )
+}
+
+/// 可以作为 Map 键的类型
+pub fn is_key(ty: &Type) -> bool {
+    is_integer(ty) || matches!(ty, Type::Bool | Type::Char | Type::String)
+}
+
+// 确定不能作为键的类型；类型变量和用户定义的类型无法判断
+fn is_composite(ty: &Type) -> bool {
+    match ty {
+        Type::Named(name) | Type::Generic(name, _) => is_type(name),
+        Type::Infer => false,
+        _ => !is_key(ty),
+    }
+}
+
+fn is_scalar(ty: &Type) -> bool {
+    is_integer(ty)
+        || matches!(
+            ty,
+            Type::F32 | Type::F64 | Type::Bool | Type::Char | Type::String | Type::Unit
+        )
+}
+
+/// 内建函数的返回类型
+#[derive(Debug)]
+pub enum Returns {
+    Type(Type),
+    Vec(Type), // `Vec<T>`，`Type::Infer` 表示元素类型由使用处推断
+    StringBuilder,
+    Argument,        // 第一个参数的类型
+    Element,         // 第一个参数的元素类型
+    OptionalElement, // `Option<元素类型>`
+    Map,             // 键和值的类型由使用处推断的 `Map<_, _>`
+    Value,           // 第一个参数是 `Map<K, V>`，返回 `V`
+    Entries,         // `Vec<(K, V)>`
+    Callback,        // 第一个参数是函数，返回它的返回类型
+    Boxed,           // `Box<第一个参数的类型>`
+    Shared,          // `Rc<第一个参数的类型>`
+    Referent,        // 第一个参数是引用，返回它指向的值的类型
+    // `Option<T>`：在 MIR 中展开为检查函数和取值，虚拟机中的同名函数在检查为 true 时直接返回 `T`
+    Checked(&'static str, &'static Returns),
+    // `Result<T, string>`：虚拟机中的同名函数返回 `(bool, T, string)`，在 MIR 中展开为 Ok 或 Err
+    Fallible(&'static Returns),
+}
+
+impl Returns {
+    /// `first` 是第一个实参的类型，元素类型推断不出时为 `_`
+    pub fn resolve(&self, first: Option<&Type>) -> Type {
+        let element = || first.and_then(element_type).unwrap_or(Type::Infer);
+        let (key, value) = first
+            .and_then(map_types)
+            .unwrap_or((Type::Infer, Type::Infer));
+        match self {
+            Returns::Type(ty) => ty.clone(),
+            Returns::Vec(ty) => Type::Generic("Vec".to_string(), vec![ty.clone()]),
+            Returns::StringBuilder => Type::Named("StringBuilder".to_string()),
+            Returns::Argument => first.cloned().unwrap_or(Type::Infer),
+            Returns::Element => element(),
+            Returns::OptionalElement => Type::Generic("Option".to_string(), vec![element()]),
+            Returns::Map => Type::Generic("Map".to_string(), vec![Type::Infer, Type::Infer]),
+            Returns::Value => value,
+            Returns::Entries => {
+                Type::Generic("Vec".to_string(), vec![Type::Tuple(vec![key, value])])
+            }
+            Returns::Boxed => Type::Generic(
+                "Box".to_string(),
+                vec![first.cloned().unwrap_or(Type::Infer)],
+            ),
+            Returns::Shared => Type::Generic(
+                "Rc".to_string(),
+                vec![first.cloned().unwrap_or(Type::Infer)],
+            ),
+            Returns::Referent => match first {
+                Some(Type::Reference(inner, _)) => (**inner).clone(),
+                _ => Type::Infer,
+            },
+            Returns::Callback => match first {
+                Some(Type::Function(_, ret, _)) => (**ret).clone(),
+                _ => Type::Infer,
+            },
+            Returns::Checked(_, inner) => {
+                Type::Generic("Option".to_string(), vec![inner.resolve(first)])
+            }
+            Returns::Fallible(inner) => Type::Generic(
+                "Result".to_string(),
+                vec![inner.resolve(first), Type::String],
+            ),
+        }
+    }
+}
+
+/// 内建函数的签名
+#[derive(Debug)]
+pub struct Signature {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub required: usize, // 必须提供的参数个数，其余参数可以省略
+    pub variadic: bool,  // 最后一个参数可以重复任意次
+    pub ret: Returns,
+    pub pure: bool, // 没有副作用，可以在契约条件中调用；修改参数的内建函数按写入参数处理
+    pub allocates: bool, // 分配堆内存（创建或增长 Vec、Map 和字符串），`#[no_alloc]` 函数中不能调用
+    pub usage: &'static str,
+}
+
+impl Signature {
+    /// 修改第一个参数指向的值（`push`、`append`、`insert` 等）
+    pub fn mutates(&self) -> bool {
+        self.params
+            .first()
+            .is_some_and(|param| param.target().is_some())
+    }
+
+    /// 按方法调用时接收者自动取的引用：`Some(true)` 为 `&mut`，`Some(false)` 为 `&`
+    pub fn receiver(&self) -> Option<bool> {
+        match self.params.first()? {
+            Param::Map => Some(false),
+            param => param.target().map(|_| true),
+        }
+    }
+
+    /// 返回类型；`first` 是第一个实参的类型，元素类型推断不出时为 `_`
+    pub fn return_type(&self, first: Option<&Type>) -> Type {
+        self.ret.resolve(first)
+    }
+
+    pub fn accepts_count(&self, count: usize) -> bool {
+        count >= self.required && (self.variadic || count <= self.params.len())
+    }
+
+    /// 第 `index` 个实参对应的参数
+    pub fn param(&self, index: usize) -> Option<Param> {
+        match self.params.get(index) {
+            Some(param) => Some(*param),
+            None if self.variadic => self.params.last().copied(),
+            None => None,
+        }
+    }
+
+    /// 期望的参数个数，用于诊断信息，如 "1 argument"、"1 to 2 arguments"
+    pub fn expected(&self) -> String {
+        let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+        let max = self.params.len();
+        if self.variadic {
+            format!("at least {} {}", self.required, plural(self.required))
+        } else if self.required == max {
+            format!("{} {}", max, plural(max))
+        } else {
+            format!("{} to {} {}", self.required, max, plural(max))
+        }
+    }
+}
+
+pub static BUILTINS: &[Signature] = &[
+    Signature {
+        name: "print",
+        params: &[Param::Value],
+        required: 0,
+        variadic: true,
+        ret: Returns::Type(Type::Unit),
+        pure: false,
+        allocates: false,
+        usage: "print(value, ...)",
+    },
+    Signature {
+        name: "len",
+        params: &[Param::Sequence],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Usize),
+        pure: true,
+        allocates: false,
+        usage: "len(value) -> usize",
+    },
+    Signature {
+        name: "assert",
+        params: &[Param::Bool, Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Unit),
+        pure: true,
+        allocates: false,
+        usage: "assert(condition[, message])",
+    },
+    Signature {
+        name: "panic",
+        params: &[Param::String],
+        required: 0,
+        variadic: false,
+        ret: Returns::Type(Type::Never),
+        pure: true,
+        allocates: false,
+        usage: "panic([message]) -> !",
+    },
+    Signature {
+        name: "catch_panic",
+        params: &[Param::Callback],
+        required: 1,
+        variadic: false,
+        ret: Returns::Fallible(&Returns::Callback),
+        pure: false,
+        allocates: true,
+        usage: "catch_panic(f) -> Result<T, string>",
+    },
+    Signature {
+        name: "Box::new",
+        params: &[Param::Value],
+        required: 1,
+        variadic: false,
+        ret: Returns::Boxed,
+        pure: true,
+        allocates: true,
+        usage: "Box::new(value) -> Box<T>",
+    },
+    Signature {
+        name: "Rc::new",
+        params: &[Param::Value],
+        required: 1,
+        variadic: false,
+        ret: Returns::Shared,
+        pure: true,
+        allocates: true,
+        usage: "Rc::new(value) -> Rc<T>",
+    },
+    Signature {
+        name: "Rc::clone",
+        params: &[Param::Shared],
+        required: 1,
+        variadic: false,
+        ret: Returns::Referent,
+        pure: true,
+        allocates: false,
+        usage: "Rc::clone(&rc) -> Rc<T>",
+    },
+    Signature {
+        name: "Rc::strong_count",
+        params: &[Param::Shared],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Usize),
+        pure: true,
+        allocates: false,
+        usage: "Rc::strong_count(&rc) -> usize",
+    },
+    Signature {
+        name: "Vec::new",
+        params: &[],
+        required: 0,
+        variadic: false,
+        ret: Returns::Vec(Type::Infer),
+        pure: true,
+        allocates: true,
+        usage: "Vec::new() -> Vec<T>",
+    },
+    Signature {
+        name: "push",
+        params: &[Param::Vec, Param::Value],
+        required: 2,
+        variadic: false,
+        ret: Returns::Type(Type::Unit),
+        pure: true,
+        allocates: true,
+        usage: "push(&mut vec, value)",
+    },
+    Signature {
+        name: "pop",
+        params: &[Param::Vec],
+        required: 1,
+        variadic: false,
+        ret: Returns::OptionalElement,
+        pure: true,
+        allocates: false,
+        usage: "pop(&mut vec) -> Option<T>",
+    },
+    Signature {
+        name: "remove",
+        params: &[Param::Vec, Param::Integer],
+        required: 2,
+        variadic: false,
+        ret: Returns::Element,
+        pure: true,
+        allocates: false,
+        usage: "remove(&mut vec, index) -> T",
+    },
+    Signature {
+        name: "StringBuilder::new",
+        params: &[],
+        required: 0,
+        variadic: false,
+        ret: Returns::StringBuilder,
+        pure: true,
+        allocates: true,
+        usage: "StringBuilder::new() -> StringBuilder",
+    },
+    Signature {
+        name: "append",
+        params: &[Param::StringBuilder, Param::Value],
+        required: 2,
+        variadic: false,
+        ret: Returns::Type(Type::Unit),
+        pure: true,
+        allocates: true,
+        usage: "append(&mut builder, value)",
+    },
+    Signature {
+        name: "Map::new",
+        params: &[],
+        required: 0,
+        variadic: false,
+        ret: Returns::Map,
+        pure: true,
+        allocates: true,
+        usage: "Map::new() -> Map<K, V>",
+    },
+    Signature {
+        name: "insert",
+        params: &[Param::MapMut, Param::Key, Param::Value],
+        required: 3,
+        variadic: false,
+        ret: Returns::Type(Type::Unit),
+        pure: true,
+        allocates: true,
+        usage: "insert(&mut map, key, value)",
+    },
+    Signature {
+        name: "get",
+        params: &[Param::Map, Param::Key],
+        required: 2,
+        variadic: false,
+        ret: Returns::Checked("contains_key", &Returns::Value),
+        pure: true,
+        allocates: false,
+        usage: "get(&map, key) -> Option<V>",
+    },
+    Signature {
+        name: "remove",
+        params: &[Param::MapMut, Param::Key],
+        required: 2,
+        variadic: false,
+        ret: Returns::Checked("contains_key", &Returns::Value),
+        pure: true,
+        allocates: false,
+        usage: "remove(&mut map, key) -> Option<V>",
+    },
+    Signature {
+        name: "contains_key",
+        params: &[Param::Map, Param::Key],
+        required: 2,
+        variadic: false,
+        ret: Returns::Type(Type::Bool),
+        pure: true,
+        allocates: false,
+        usage: "contains_key(&map, key) -> bool",
+    },
+    Signature {
+        name: "entries",
+        params: &[Param::Map],
+        required: 1,
+        variadic: false,
+        ret: Returns::Entries,
+        pure: true,
+        allocates: true,
+        usage: "entries(&map) -> Vec<(K, V)>",
+    },
+    Signature {
+        name: "to_string",
+        params: &[Param::Value],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::String),
+        pure: true,
+        allocates: true,
+        usage: "to_string(value) -> string",
+    },
+    Signature {
+        name: "fmt::arg",
+        params: &[Param::Value, Param::String],
+        required: 2,
+        variadic: false,
+        ret: Returns::Type(Type::String),
+        pure: true,
+        allocates: true,
+        usage: "fmt::arg(&value, spec) -> string",
+    },
+    Signature {
+        name: "abs",
+        params: &[Param::Integer],
+        required: 1,
+        variadic: false,
+        ret: Returns::Argument,
+        pure: true,
+        allocates: false,
+        usage: "abs(n) -> n",
+    },
+    Signature {
+        name: "substring",
+        params: &[Param::String, Param::Integer, Param::Integer],
+        required: 3,
+        variadic: false,
+        ret: Returns::Type(Type::String),
+        pure: true,
+        allocates: true,
+        usage: "substring(s, start, end) -> string",
+    },
+    Signature {
+        name: "split",
+        params: &[Param::String, Param::String],
+        required: 2,
+        variadic: false,
+        ret: Returns::Vec(Type::String),
+        pure: true,
+        allocates: true,
+        usage: "split(s, separator) -> Vec<string>",
+    },
+    Signature {
+        name: "contains",
+        params: &[Param::String, Param::String],
+        required: 2,
+        variadic: false,
+        ret: Returns::Type(Type::Bool),
+        pure: true,
+        allocates: false,
+        usage: "contains(s, pattern) -> bool",
+    },
+    Signature {
+        name: "find",
+        params: &[Param::String, Param::String],
+        required: 2,
+        variadic: false,
+        ret: Returns::Checked("contains", &Returns::Type(Type::Usize)),
+        pure: true,
+        allocates: false,
+        usage: "find(s, pattern) -> Option<usize>",
+    },
+    Signature {
+        name: "is_int",
+        params: &[Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Bool),
+        pure: true,
+        allocates: false,
+        usage: "is_int(s) -> bool",
+    },
+    Signature {
+        name: "to_int",
+        params: &[Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Checked("is_int", &Returns::Type(Type::I64)),
+        pure: true,
+        allocates: false,
+        usage: "to_int(s) -> Option<i64>",
+    },
+    Signature {
+        name: "chars",
+        params: &[Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Vec(Type::Char),
+        pure: true,
+        allocates: true,
+        usage: "chars(s) -> Vec<char>",
+    },
+    Signature {
+        name: "io::read_file",
+        params: &[Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Fallible(&Returns::Type(Type::String)),
+        pure: false,
+        allocates: true,
+        usage: "io::read_file(path) -> Result<string, string>",
+    },
+    Signature {
+        name: "io::write_file",
+        params: &[Param::String, Param::String],
+        required: 2,
+        variadic: false,
+        ret: Returns::Fallible(&Returns::Type(Type::Unit)),
+        pure: false,
+        allocates: false,
+        usage: "io::write_file(path, contents) -> Result<(), string>",
+    },
+    Signature {
+        name: "io::args",
+        params: &[],
+        required: 0,
+        variadic: false,
+        ret: Returns::Vec(Type::String),
+        pure: false,
+        allocates: true,
+        usage: "io::args() -> Vec<string>",
+    },
+    Signature {
+        name: "io::exit",
+        params: &[Param::Integer],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Unit),
+        pure: false,
+        allocates: false,
+        usage: "io::exit(code)",
+    },
+    Signature {
+        name: "env::get",
+        params: &[Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Checked("env::has", &Returns::Type(Type::String)),
+        pure: false,
+        allocates: true,
+        usage: "env::get(name) -> Option<string>",
+    },
+    Signature {
+        name: "env::has",
+        params: &[Param::String],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Bool),
+        pure: false,
+        allocates: false,
+        usage: "env::has(name) -> bool",
+    },
+    Signature {
+        name: "time::now_millis",
+        params: &[],
+        required: 0,
+        variadic: false,
+        ret: Returns::Type(Type::I64),
+        pure: false,
+        allocates: false,
+        usage: "time::now_millis() -> i64",
+    },
+    Signature {
+        name: "time::sleep",
+        params: &[Param::Integer],
+        required: 1,
+        variadic: false,
+        ret: Returns::Type(Type::Unit),
+        pure: false,
+        allocates: false,
+        usage: "time::sleep(ms)",
+    },
+];
+
+/// 内建函数的别名和它代表的内建函数
+pub const ALIASES: [(&str, &str); 2] = [
+    ("std::process::exit", "io::exit"),
+    ("std::process::args", "io::args"),
+];
+
+/// 别名代表的内建函数的名字；不是别名时原样返回
+pub fn canonical(name: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, target)| target)
+}
+
+pub fn lookup(name: &str) -> Option<&'static Signature> {
+    let name = canonical(name);
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// 同名的内建函数中第一个参数接受 `first` 的一个；类型未知或都不接受时取第一个
+pub fn resolve(name: &str, first: Option<&Type>) -> Option<&'static Signature> {
+    let name = canonical(name);
+    let mut overloads = BUILTINS.iter().filter(|builtin| builtin.name == name);
+    let default = overloads.next()?;
+    let Some(first) = first else {
+        return Some(default);
+    };
+    let accepts = |builtin: &&Signature| builtin.param(0).is_some_and(|param| param.accepts(first));
+    Some(
+        std::iter::once(default)
+            .chain(overloads)
+            .find(accepts)
+            .unwrap_or(default),
+    )
+}
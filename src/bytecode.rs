@@ -0,0 +1,544 @@
+// Contractus 字节码
+// 由单态化后的 MIR 编译而来，供栈式虚拟机执行，也可以保存为 `.ctxb` 文件缓存：
+// 1. 常量池：整数、浮点数、字符、字符串和函数引用，指令按下标引用
+// 2. 函数表：参数个数、局部变量个数、指令流和行号表
+// 3. 类型表：结构体的字段名和枚举的变体名，用于输出值和按名字访问字段
+// 指令是一个字节的操作码加上定长的小端操作数（只有 Switch 是变长的），见 Op
+// 局部变量与 MIR 一一对应：`_0` 是返回值，`_1.._n` 是参数
+// 文件格式见 encode.rs，执行见 vm.rs
+
+mod compile;
+mod encode;
+mod vm;
+
+pub use compile::compile;
+pub use encode::{decode, encode, MAGIC, VERSION};
+pub use vm::{run, run_with_output, Exceeded, RunLimits, Value, Vm, FRAME_LIMIT};
+
+use crate::ast::{BinOp, Type};
+use crate::span::Span;
+use std::fmt;
+
+/// `.ctxb` 文件的扩展名
+pub const EXTENSION: &str = "ctxb";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub constants: Vec<Constant>,
+    pub functions: Vec<Function>,
+    pub structs: Vec<TypeInfo>, // 成员是字段名，按声明顺序
+    pub enums: Vec<TypeInfo>,   // 成员是变体名，按声明顺序
+    pub statics: Vec<u32>,      // 每个静态变量的初始化函数，下标即静态变量编号
+    pub entry: Option<u32>,     // `main`
+    pub file: Option<String>,   // 源文件名，运行时错误按它报告位置
+    pub hash: u64,              // 输入和编译选项的内容哈希（`Compiler::content_hash`），0 表示未知
+}
+
+impl Module {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|func| func.name == name)
+    }
+
+    pub fn function_by_symbol(&self, symbol: &str) -> Option<&Function> {
+        self.functions.iter().find(|func| func.symbol == symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    Function(u32),
+    Builtin(Builtin),
+}
+
+/// 由虚拟机直接实现的函数，签名见 builtins.rs；模块文件中按名字保存。
+/// `pop` 在 MIR 中展开为 `remove`，虚拟机不需要实现；其他返回 `Option` 的函数在 MIR 中展开为
+/// 检查和取值（如 `contains_key` 和 `get`），虚拟机中的 `get`、Map 的 `remove`、`find`、`to_int` 和 `env::get`
+/// 直接返回值，要求它存在；返回 `Result` 的 io 函数返回 `(是否成功, 值, 错误信息)` 元组。
+/// `Memcpy` 之后的几个实现 intrinsics.rs 中的同名 intrinsic，对 `#[intrinsic]` 函数的调用编译为调用它们
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    Print,
+    Len,
+    Assert,
+    Panic,
+    VecNew,
+    Push,
+    Remove,
+    StringBuilderNew,
+    Append,
+    MapNew,
+    Insert,
+    Get,
+    ContainsKey,
+    Entries,
+    ToString,
+    Substring,
+    Split,
+    Contains,
+    Find,
+    IsInt,
+    ToInt,
+    Chars,
+    Abs,
+    ReadFile,
+    WriteFile,
+    Args,
+    Exit,
+    Memcpy,
+    Ctpop,
+    Ctlz,
+    Cttz,
+    Sqrt,
+    CatchPanic,
+    BoxNew,
+    RcNew,
+    RcClone,
+    RcStrongCount,
+    FmtArg,
+    EnvGet,
+    EnvHas,
+    NowMillis,
+    Sleep,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 42] = [
+        Builtin::Print,
+        Builtin::Len,
+        Builtin::Assert,
+        Builtin::Panic,
+        Builtin::VecNew,
+        Builtin::Push,
+        Builtin::Remove,
+        Builtin::StringBuilderNew,
+        Builtin::Append,
+        Builtin::MapNew,
+        Builtin::Insert,
+        Builtin::Get,
+        Builtin::ContainsKey,
+        Builtin::Entries,
+        Builtin::ToString,
+        Builtin::Substring,
+        Builtin::Split,
+        Builtin::Contains,
+        Builtin::Find,
+        Builtin::IsInt,
+        Builtin::ToInt,
+        Builtin::Chars,
+        Builtin::Abs,
+        Builtin::ReadFile,
+        Builtin::WriteFile,
+        Builtin::Args,
+        Builtin::Exit,
+        Builtin::Memcpy,
+        Builtin::Ctpop,
+        Builtin::Ctlz,
+        Builtin::Cttz,
+        Builtin::Sqrt,
+        Builtin::CatchPanic,
+        Builtin::BoxNew,
+        Builtin::RcNew,
+        Builtin::RcClone,
+        Builtin::RcStrongCount,
+        Builtin::FmtArg,
+        Builtin::EnvGet,
+        Builtin::EnvHas,
+        Builtin::NowMillis,
+        Builtin::Sleep,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Len => "len",
+            Builtin::Assert => "assert",
+            Builtin::Panic => "panic",
+            Builtin::VecNew => "Vec::new",
+            Builtin::Push => "push",
+            Builtin::Remove => "remove",
+            Builtin::StringBuilderNew => "StringBuilder::new",
+            Builtin::Append => "append",
+            Builtin::MapNew => "Map::new",
+            Builtin::Insert => "insert",
+            Builtin::Get => "get",
+            Builtin::ContainsKey => "contains_key",
+            Builtin::Entries => "entries",
+            Builtin::ToString => "to_string",
+            Builtin::Substring => "substring",
+            Builtin::Split => "split",
+            Builtin::Contains => "contains",
+            Builtin::Find => "find",
+            Builtin::IsInt => "is_int",
+            Builtin::ToInt => "to_int",
+            Builtin::Chars => "chars",
+            Builtin::Abs => "abs",
+            Builtin::ReadFile => "io::read_file",
+            Builtin::WriteFile => "io::write_file",
+            Builtin::Args => "io::args",
+            Builtin::Exit => "io::exit",
+            Builtin::Memcpy => "memcpy",
+            Builtin::Ctpop => "ctpop",
+            Builtin::Ctlz => "ctlz",
+            Builtin::Cttz => "cttz",
+            Builtin::Sqrt => "sqrt",
+            Builtin::CatchPanic => "catch_panic",
+            Builtin::BoxNew => "Box::new",
+            Builtin::RcNew => "Rc::new",
+            Builtin::RcClone => "Rc::clone",
+            Builtin::RcStrongCount => "Rc::strong_count",
+            Builtin::FmtArg => "fmt::arg",
+            Builtin::EnvGet => "env::get",
+            Builtin::EnvHas => "env::has",
+            Builtin::NowMillis => "time::now_millis",
+            Builtin::Sleep => "time::sleep",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeInfo {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub symbol: String, // 修饰后的符号名，见 mangle.rs
+    pub arity: u16,
+    pub locals: u16, // 包括返回值和参数
+    pub code: Vec<u8>,
+    pub lines: Vec<(u32, Span)>, // (指令偏移, 源码位置)，按偏移递增
+}
+
+impl Function {
+    /// 偏移 pc 处的指令对应的源码位置
+    pub fn span_at(&self, pc: usize) -> Span {
+        let index = self
+            .lines
+            .partition_point(|(offset, _)| *offset as usize <= pc);
+        match index.checked_sub(1) {
+            Some(index) => self.lines[index].1,
+            None => Span::new(0, 0, 0, 0),
+        }
+    }
+}
+
+/// `Cast` 指令的目标类型，操作数是这张表的下标
+pub const CAST_TYPES: [Type; 14] = [
+    Type::I8,
+    Type::I16,
+    Type::I32,
+    Type::I64,
+    Type::Isize,
+    Type::U8,
+    Type::U16,
+    Type::U32,
+    Type::U64,
+    Type::Usize,
+    Type::F32,
+    Type::F64,
+    Type::Bool,
+    Type::Char,
+];
+
+/// `Checked` 指令的运算，操作数是这张表的下标
+pub const CHECKED_OPS: [BinOp; 7] = [
+    BinOp::Add,
+    BinOp::Sub,
+    BinOp::Mul,
+    BinOp::Div,
+    BinOp::Mod,
+    BinOp::LeftShift,
+    BinOp::RightShift,
+];
+
+macro_rules! opcodes {
+    ($($(#[$doc:meta])* $name:ident,)*) => {
+        /// 操作码，注释中是操作数和对栈的作用
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u8)]
+        pub enum Op {
+            $($(#[$doc])* $name,)*
+        }
+
+        impl Op {
+            pub const ALL: &'static [Op] = &[$(Op::$name,)*];
+            pub const COUNT: usize = Self::ALL.len();
+
+            pub fn from_byte(byte: u8) -> Option<Op> {
+                Self::ALL.get(byte as usize).copied()
+            }
+        }
+    };
+}
+
+opcodes! {
+    /// 压入 `()`
+    Unit,
+    /// u32 常量下标：压入常量
+    Const,
+    /// u16 局部变量：压入局部变量的副本
+    Load,
+    /// u16 局部变量：弹出值写入局部变量
+    Store,
+    /// 弹出并丢弃栈顶
+    Pop,
+    /// u16 静态变量：压入静态变量的副本
+    LoadStatic,
+    /// u16 静态变量：压入指向静态变量的引用，静态变量的地址在整个运行期间不变
+    AddrStatic,
+    /// u16 局部变量：压入指向局部变量的引用
+    AddrLocal,
+    /// u16 字段序号：把栈顶的引用换成指向其字段的引用
+    Field,
+    /// u32 字段名常量：同 Field，字段序号在运行时按名字查找
+    FieldNamed,
+    /// 弹出下标和引用，压入指向元素的引用，越界时报错
+    Index,
+    /// 弹出终点、起点和引用，压入指向其中一段的切片引用（不含终点），越界时报错
+    Subslice,
+    /// 弹出引用，压入它指向的引用
+    Deref,
+    /// 弹出引用，压入它指向的值的副本
+    LoadRef,
+    /// 弹出值和引用，把值写入引用指向的位置
+    StoreRef,
+    /// 弹出引用，析构它指向的值：其中每个 `Rc` 的计数减一，减到零时析构 `Rc` 中的值
+    Drop,
+    /// 二元运算：弹出右、左操作数，压入结果
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    And,
+    Or,
+    /// 一元运算
+    Neg,
+    Not,
+    /// u8 CHECKED_OPS 下标, u8 CAST_TYPES 下标：弹出右、左操作数，按整数类型的宽度运算，
+    /// 结果超出类型的范围或者移位的位数过大时报错；`f32` 的结果舍入到单精度
+    Checked,
+    /// u8 CAST_TYPES 下标：按整数类型取负，结果超出类型的范围时报错
+    NegAs,
+    /// u8 CAST_TYPES 下标：在整数类型的宽度内按位取反
+    NotAs,
+    /// u8 CAST_TYPES 下标
+    Cast,
+    /// u16 元素个数：弹出元素组成元组
+    Tuple,
+    /// u16 元素个数：弹出元素组成数组
+    Array,
+    /// u16 结构体, u16 字段个数：按声明顺序弹出字段
+    Struct,
+    /// u16 枚举, u16 变体, u16 字段个数
+    Variant,
+    /// u8 是否包含终点：弹出终点和起点组成区间
+    Range,
+    /// u32 函数, u16 捕获个数：弹出捕获的值组成闭包
+    Closure,
+    /// 弹出指向数组或切片的引用，压入长度
+    Len,
+    /// 弹出指向枚举值的引用，压入变体序号
+    Discriminant,
+    /// u32 目标偏移
+    Jump,
+    /// u32 目标偏移：弹出条件，为假时跳转
+    JumpIfFalse,
+    /// u16 分支数 n, n 个 (i64 值, u32 目标), u32 其他情况的目标：弹出整数、bool 或字符。
+    /// 分支按值从小到大排列
+    Switch,
+    /// u8 实参个数：栈上依次是被调函数和实参
+    Call,
+    /// 返回 `_0`
+    Return,
+    /// 弹出下标、长度和条件，条件为假时报告下标越界
+    AssertBounds,
+    /// u8 是否在调用处报告, u32 消息常量, u32 注释常量：弹出条件，为假时报告违反契约
+    AssertContract,
+    /// 到达不可达代码，报错
+    Unreachable,
+}
+
+pub(crate) fn read_u8(code: &[u8], at: usize) -> u8 {
+    code[at]
+}
+
+pub(crate) fn read_u16(code: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([code[at], code[at + 1]])
+}
+
+pub(crate) fn read_u32(code: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(code[at..at + 4].try_into().unwrap())
+}
+
+pub(crate) fn read_i64(code: &[u8], at: usize) -> i64 {
+    i64::from_le_bytes(code[at..at + 8].try_into().unwrap())
+}
+
+/// 偏移 pc 处指令的总长度（包括操作码），越界或操作码无效时返回 None
+pub fn instruction_len(code: &[u8], pc: usize) -> Option<usize> {
+    let op = Op::from_byte(*code.get(pc)?)?;
+    let operands = match op {
+        Op::Const | Op::FieldNamed | Op::Jump | Op::JumpIfFalse => 4,
+        Op::Load | Op::Store | Op::LoadStatic | Op::AddrStatic | Op::AddrLocal | Op::Field => 2,
+        Op::Tuple | Op::Array | Op::Checked => 2,
+        Op::Struct => 4,
+        Op::Variant => 6,
+        Op::Closure => 6,
+        Op::AssertContract => 9,
+        Op::Cast | Op::NegAs | Op::NotAs | Op::Range | Op::Call => 1,
+        Op::Switch => {
+            let count = u16::from_le_bytes([*code.get(pc + 1)?, *code.get(pc + 2)?]) as usize;
+            2 + count * 12 + 4
+        }
+        _ => 0,
+    };
+    (pc + 1 + operands <= code.len()).then_some(1 + operands)
+}
+
+/// 偏移 pc 处的指令从栈上弹出和压入的值的个数，指令需要是 `instruction_len` 认可的
+pub fn stack_effect(code: &[u8], pc: usize) -> (usize, usize) {
+    let count = |at: usize| read_u16(code, pc + at) as usize;
+    match Op::from_byte(code[pc]).unwrap() {
+        Op::Unit | Op::Const | Op::Load | Op::LoadStatic | Op::AddrStatic | Op::AddrLocal => (0, 1),
+        Op::Store | Op::Pop | Op::Drop | Op::JumpIfFalse | Op::Switch | Op::AssertContract => {
+            (1, 0)
+        }
+        Op::Field | Op::FieldNamed | Op::Deref | Op::LoadRef | Op::Len | Op::Discriminant => (1, 1),
+        Op::Neg | Op::Not | Op::NegAs | Op::NotAs | Op::Cast => (1, 1),
+        Op::Index | Op::Range => (2, 1),
+        Op::StoreRef => (2, 0),
+        Op::Subslice => (3, 1),
+        Op::AssertBounds => (3, 0),
+        Op::Tuple | Op::Array => (count(1), 1),
+        Op::Struct => (count(3), 1),
+        Op::Variant | Op::Closure => (count(5), 1),
+        Op::Call => (read_u8(code, pc + 1) as usize + 1, 1),
+        Op::Jump | Op::Return | Op::Unreachable => (0, 0),
+        // 其余的是二元运算，包括 Checked
+        _ => (2, 1),
+    }
+}
+
+// 反汇编，格式不保证稳定：
+//
+//     fn main (arity 0, locals 2):  ; _CXN4mainEh...
+//         0000  Const 0              ; 42
+//         0005  Store _1
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, func) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "fn {} (arity {}, locals {}):  ; {}",
+                func.name, func.arity, func.locals, func.symbol
+            )?;
+            let mut pc = 0;
+            while let Some(len) = instruction_len(&func.code, pc) {
+                writeln!(f, "    {:04}  {}", pc, self.instruction(&func.code, pc))?;
+                pc += len;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Module {
+    fn instruction(&self, code: &[u8], pc: usize) -> String {
+        let op = Op::from_byte(code[pc]).expect("invalid opcode");
+        let at = pc + 1;
+        let operands = match op {
+            Op::Const => {
+                let index = read_u32(code, at);
+                let comment = match self.constants.get(index as usize) {
+                    Some(Constant::Function(func)) => self
+                        .functions
+                        .get(*func as usize)
+                        .map_or("?".to_string(), |func| format!("fn {}", func.name)),
+                    Some(Constant::Builtin(builtin)) => format!("builtin {}", builtin.name()),
+                    Some(Constant::Str(text)) => format!("{:?}", text),
+                    Some(Constant::Int(n)) => n.to_string(),
+                    Some(Constant::Float(x)) => x.to_string(),
+                    Some(Constant::Bool(b)) => b.to_string(),
+                    Some(Constant::Char(c)) => format!("{:?}", c),
+                    None => "?".to_string(),
+                };
+                format!("{:<12} ; {}", index, comment)
+            }
+            Op::Load | Op::Store | Op::AddrLocal => format!("_{}", read_u16(code, at)),
+            Op::LoadStatic | Op::AddrStatic | Op::Field | Op::Tuple | Op::Array => {
+                read_u16(code, at).to_string()
+            }
+            Op::FieldNamed | Op::Jump | Op::JumpIfFalse => read_u32(code, at).to_string(),
+            Op::Cast | Op::NegAs | Op::NotAs => CAST_TYPES
+                .get(read_u8(code, at) as usize)
+                .map_or("?".to_string(), Type::to_string),
+            Op::Checked => format!(
+                "{} {}",
+                CHECKED_OPS
+                    .get(read_u8(code, at) as usize)
+                    .map_or("?".to_string(), |op| format!("{:?}", op)),
+                CAST_TYPES
+                    .get(read_u8(code, at + 1) as usize)
+                    .map_or("?".to_string(), Type::to_string)
+            ),
+            Op::Range | Op::Call => read_u8(code, at).to_string(),
+            Op::Struct => format!("{} {}", read_u16(code, at), read_u16(code, at + 2)),
+            Op::Variant => format!(
+                "{} {} {}",
+                read_u16(code, at),
+                read_u16(code, at + 2),
+                read_u16(code, at + 4)
+            ),
+            Op::Closure => format!("{} {}", read_u32(code, at), read_u16(code, at + 4)),
+            Op::AssertContract => format!(
+                "{} {} {}",
+                read_u8(code, at),
+                read_u32(code, at + 1),
+                read_u32(code, at + 5)
+            ),
+            Op::Switch => {
+                let count = read_u16(code, at) as usize;
+                let mut arms = Vec::new();
+                for i in 0..count {
+                    let arm = at + 2 + i * 12;
+                    arms.push(format!(
+                        "{}: {}",
+                        read_i64(code, arm),
+                        read_u32(code, arm + 8)
+                    ));
+                }
+                let otherwise = read_u32(code, at + 2 + count * 12);
+                arms.push(format!("otherwise: {}", otherwise));
+                format!("[{}]", arms.join(", "))
+            }
+            _ => String::new(),
+        };
+        format!("{:?} {}", op, operands).trim_end().to_string()
+    }
+}
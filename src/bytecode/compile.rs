@@ -0,0 +1,732 @@
+// MIR 到字节码的编译
+// 每个基本块按编号顺序依次生成，跳转目标先记录为基本块编号，全部生成后回填偏移：
+// - 不带投影的 place 直接用 Load/Store 读写局部变量
+// - 带投影的 place 先算出引用（AddrLocal + Field/Index/Deref），再用 LoadRef/StoreRef
+// - 调用的结果留在栈顶，写入带投影的目标时引用要先于被调函数入栈
+// - 存储标记在虚拟机中没有作用；Drop 只对可能包含 `Rc` 的值生成 Drop 指令维护引用计数，
+//   其他值的内存随值栈回收，只保留控制流。panic 直接结束虚拟机的执行，
+//   不经过调用和断言的清理边，清理块结尾的 Resume 不会执行
+// 程序应当已经单态化，泛型函数体在这里报错
+
+use super::{Builtin, Constant, Function, Module, Op, TypeInfo, CAST_TYPES, CHECKED_OPS};
+use crate::ast::{BinOp, ContractKind, Type, UnOp};
+use crate::builtins;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::interp::ops;
+use crate::mangle::Symbol;
+use crate::mir::{
+    AggregateKind, AssertMessage, BasicBlock, Body, ConstValue, Local, MirProgram, Operand, Place,
+    PlaceElem, Rvalue, StatementKind, TerminatorKind,
+};
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+
+/// 把单态化后的 MIR 编译为字节码模块
+pub fn compile(mir: &MirProgram) -> Result<Module, Vec<Diagnostic>> {
+    let mut cx = Context::new(mir);
+    let mut errors = Vec::new();
+
+    let bodies: Vec<&Body> = mir.bodies.iter().chain(&mir.statics).collect();
+    for body in &bodies {
+        let mut builder = FunctionBuilder::new(&mut cx, body);
+        builder.compile_body();
+        let (func, body_errors) = builder.finish();
+        cx.module.functions.push(func);
+        errors.extend(body_errors);
+    }
+    cx.module.statics = (mir.bodies.len()..bodies.len()).map(|i| i as u32).collect();
+    cx.module.entry = cx.functions.get("main").copied();
+
+    if errors.is_empty() {
+        Ok(cx.module)
+    } else {
+        Err(errors)
+    }
+}
+
+// 整个模块共享的表
+struct Context<'m> {
+    mir: &'m MirProgram,
+    module: Module,
+    functions: HashMap<&'m str, u32>,
+    statics: HashMap<&'m str, u16>,
+    structs: HashMap<&'m str, u16>,
+    enums: HashMap<&'m str, u16>,
+    constants: HashMap<ConstantKey, u32>,
+}
+
+// 常量池去重的键，浮点数按位比较
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Int(i64),
+    Float(u64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    Function(u32),
+    Builtin(&'static str),
+}
+
+impl<'m> Context<'m> {
+    fn new(mir: &'m MirProgram) -> Self {
+        let module = Module {
+            structs: mir
+                .structs
+                .iter()
+                .map(|def| TypeInfo {
+                    name: def.name.clone(),
+                    members: def.fields.iter().map(|field| field.name.clone()).collect(),
+                })
+                .collect(),
+            enums: mir
+                .enums
+                .iter()
+                .map(|def| TypeInfo {
+                    name: def.name.clone(),
+                    members: def.variants.iter().map(|v| v.name.clone()).collect(),
+                })
+                .collect(),
+            ..Module::default()
+        };
+        Self {
+            mir,
+            module,
+            functions: mir
+                .bodies
+                .iter()
+                .chain(&mir.statics)
+                .enumerate()
+                .map(|(i, body)| (body.name.as_str(), i as u32))
+                .collect(),
+            statics: mir
+                .statics
+                .iter()
+                .enumerate()
+                .map(|(i, body)| (body.name.as_str(), i as u16))
+                .collect(),
+            structs: mir
+                .structs
+                .iter()
+                .enumerate()
+                .map(|(i, def)| (def.name.as_str(), i as u16))
+                .collect(),
+            enums: mir
+                .enums
+                .iter()
+                .enumerate()
+                .map(|(i, def)| (def.name.as_str(), i as u16))
+                .collect(),
+            constants: HashMap::new(),
+        }
+    }
+
+    // 析构时可能要减少引用计数的类型：包含 `Rc`，或者无法判断（类型未知）
+    fn may_share(&self, ty: &Type) -> bool {
+        self.may_share_in(ty, &mut HashSet::new())
+    }
+
+    fn may_share_in<'t>(&self, ty: &'t Type, visiting: &mut HashSet<&'t str>) -> bool
+    where
+        'm: 't,
+    {
+        match ty {
+            Type::Infer => true,
+            Type::Array(elem, _) | Type::Slice(elem) => self.may_share_in(elem, visiting),
+            Type::Tuple(types) => types.iter().any(|ty| self.may_share_in(ty, visiting)),
+            Type::Named(name) | Type::Generic(name, _) if builtins::is_type(name) => {
+                let args = match ty {
+                    Type::Generic(_, args) => args.as_slice(),
+                    _ => &[],
+                };
+                name == "Rc" || args.iter().any(|ty| self.may_share_in(ty, visiting))
+            }
+            Type::Named(name) | Type::Generic(name, _) => {
+                // 递归类型在展开过程中再次遇到时不重复计算
+                if !visiting.insert(name) {
+                    return false;
+                }
+                let mir = self.mir;
+                if let Some(def) = mir.structs.iter().find(|def| def.name == *name) {
+                    def.fields
+                        .iter()
+                        .any(|field| self.may_share_in(&field.ty, visiting))
+                } else if let Some(def) = mir.enums.iter().find(|def| def.name == *name) {
+                    def.variants
+                        .iter()
+                        .filter_map(|variant| variant.fields.as_ref())
+                        .flatten()
+                        .any(|ty| self.may_share_in(ty, visiting))
+                } else {
+                    true
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn constant(&mut self, constant: Constant) -> u32 {
+        let key = match &constant {
+            Constant::Int(n) => ConstantKey::Int(*n),
+            Constant::Float(x) => ConstantKey::Float(x.to_bits()),
+            Constant::Bool(b) => ConstantKey::Bool(*b),
+            Constant::Char(c) => ConstantKey::Char(*c),
+            Constant::Str(text) => ConstantKey::Str(text.clone()),
+            Constant::Function(func) => ConstantKey::Function(*func),
+            Constant::Builtin(builtin) => ConstantKey::Builtin(builtin.name()),
+        };
+        let constants = &mut self.module.constants;
+        *self.constants.entry(key).or_insert_with(|| {
+            constants.push(constant);
+            constants.len() as u32 - 1
+        })
+    }
+}
+
+struct FunctionBuilder<'a, 'm> {
+    cx: &'a mut Context<'m>,
+    body: &'m Body,
+    code: Vec<u8>,
+    lines: Vec<(u32, Span)>,
+    block_offsets: Vec<u32>,
+    patches: Vec<(usize, BasicBlock)>, // 需要回填的跳转操作数位置
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a, 'm> FunctionBuilder<'a, 'm> {
+    fn new(cx: &'a mut Context<'m>, body: &'m Body) -> Self {
+        Self {
+            cx,
+            body,
+            code: Vec::new(),
+            lines: Vec::new(),
+            block_offsets: vec![0; body.blocks.len()],
+            patches: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn compile_body(&mut self) {
+        if self.body.is_generic() {
+            self.error(
+                ErrorCode::E0810,
+                format!(
+                    "cannot compile generic function `{}` before monomorphization",
+                    self.body.name
+                ),
+                self.body.span,
+            );
+            return;
+        }
+        for (block, data) in self.body.basic_blocks() {
+            self.block_offsets[block.index()] = self.code.len() as u32;
+            for statement in &data.statements {
+                match &statement.kind {
+                    StatementKind::Assign(place, rvalue) => {
+                        self.mark(statement.span);
+                        self.assign(place, rvalue, statement.span);
+                    }
+                    StatementKind::InlineAsm(_) => self.error(
+                        ErrorCode::E0810,
+                        "inline assembly is not supported by the bytecode backend".to_string(),
+                        statement.span,
+                    ),
+                    _ => {}
+                }
+            }
+            self.mark(data.terminator.span);
+            self.terminator(block, &data.terminator.kind, data.terminator.span);
+        }
+    }
+
+    fn finish(mut self) -> (Function, Vec<Diagnostic>) {
+        for (at, block) in std::mem::take(&mut self.patches) {
+            let offset = self.block_offsets[block.index()];
+            self.code[at..at + 4].copy_from_slice(&offset.to_le_bytes());
+        }
+        let func = Function {
+            name: self.body.name.clone(),
+            symbol: Symbol::for_body(&[], self.body).mangle(),
+            arity: self.body.arg_count as u16,
+            locals: self.body.locals.len() as u16,
+            code: self.code,
+            lines: self.lines,
+        };
+        (func, self.errors)
+    }
+
+    // ---------------- 语句和终结符 ----------------
+
+    fn assign(&mut self, place: &Place, rvalue: &Rvalue, span: Span) {
+        match place.as_local() {
+            Some(local) => {
+                self.rvalue(rvalue, span);
+                self.op_u16(Op::Store, local.index() as u16);
+            }
+            None => {
+                self.address(place);
+                self.rvalue(rvalue, span);
+                self.op(Op::StoreRef);
+            }
+        }
+    }
+
+    fn terminator(&mut self, block: BasicBlock, kind: &TerminatorKind, span: Span) {
+        match kind {
+            TerminatorKind::Goto { target } => self.jump(block, *target),
+            TerminatorKind::Drop { place, target, .. } => {
+                if self.cx.may_share(&self.cx.mir.place_ty(self.body, place)) {
+                    self.address(place);
+                    self.op(Op::Drop);
+                }
+                self.jump(block, *target)
+            }
+            TerminatorKind::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                self.operand(discr, span);
+                // bool 分支是最常见的形式：`[0: 假] otherwise: 真`
+                if let ([(0, else_bb)], Type::Bool) = (targets.as_slice(), self.operand_ty(discr)) {
+                    self.op(Op::JumpIfFalse);
+                    self.target(*else_bb);
+                    self.jump(block, *otherwise);
+                    return;
+                }
+                // 虚拟机按取值二分查找分支
+                let mut targets = targets.clone();
+                targets.sort_by_key(|(value, _)| *value);
+                self.op_u16(Op::Switch, targets.len() as u16);
+                for (value, target) in &targets {
+                    self.code.extend_from_slice(&value.to_le_bytes());
+                    self.target(*target);
+                }
+                self.target(*otherwise);
+            }
+            TerminatorKind::Return => self.op(Op::Return),
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                ..
+            } => {
+                let local = destination.as_local();
+                if local.is_none() {
+                    self.address(destination);
+                }
+                self.operand(func, span);
+                for arg in args {
+                    self.operand(arg, span);
+                }
+                self.op(Op::Call);
+                self.code.push(args.len() as u8);
+                match local {
+                    Some(local) => self.op_u16(Op::Store, local.index() as u16),
+                    None => self.op(Op::StoreRef),
+                }
+                match target {
+                    Some(target) => self.jump(block, *target),
+                    None => self.op(Op::Unreachable),
+                }
+            }
+            TerminatorKind::Assert {
+                cond,
+                msg: AssertMessage::BoundsCheck { len, index },
+                target,
+                ..
+            } => {
+                self.operand(cond, span);
+                self.operand(len, span);
+                self.operand(index, span);
+                self.op(Op::AssertBounds);
+                self.jump(block, *target);
+            }
+            TerminatorKind::Assert {
+                cond,
+                msg:
+                    AssertMessage::Contract {
+                        kind,
+                        owner,
+                        clause,
+                        declared,
+                    },
+                target,
+                ..
+            } => {
+                let message = ops::contract_violation(*kind, clause);
+                let message = self.cx.constant(Constant::Str(message));
+                let note = ops::contract_note(*kind, owner, *declared);
+                let note = self.cx.constant(Constant::Str(note));
+                self.operand(cond, span);
+                self.op(Op::AssertContract);
+                // 前置和后置条件由调用者负责，在调用处报告
+                let at_caller = matches!(kind, ContractKind::Requires | ContractKind::Ensures);
+                self.code.push(at_caller as u8);
+                self.code.extend_from_slice(&message.to_le_bytes());
+                self.code.extend_from_slice(&note.to_le_bytes());
+                self.jump(block, *target);
+            }
+            TerminatorKind::Resume | TerminatorKind::Unreachable => self.op(Op::Unreachable),
+        }
+    }
+
+    // 跳到紧接着生成的下一个块时不需要跳转指令
+    fn jump(&mut self, from: BasicBlock, to: BasicBlock) {
+        if to.index() != from.index() + 1 {
+            self.op(Op::Jump);
+            self.target(to);
+        }
+    }
+
+    // ---------------- 右值和操作数 ----------------
+
+    fn rvalue(&mut self, rvalue: &Rvalue, span: Span) {
+        match rvalue {
+            Rvalue::Use(operand) => self.operand(operand, span),
+            Rvalue::BinaryOp(op, lhs, rhs, ty) => {
+                self.operand(lhs, span);
+                self.operand(rhs, span);
+                // i64 的运算本来就检查溢出，更窄的整数类型和无符号类型按宽度检查，f32 的结果要舍入
+                let checked = CHECKED_OPS.iter().position(|checked| checked == op);
+                let width = ty
+                    .as_deref()
+                    .filter(|ty| !matches!(ty, Type::I64 | Type::Isize))
+                    .and_then(|ty| CAST_TYPES.iter().position(|cast| cast == ty));
+                match (checked, width) {
+                    (Some(checked), Some(width)) => {
+                        self.op(Op::Checked);
+                        self.code.push(checked as u8);
+                        self.code.push(width as u8);
+                    }
+                    _ => self.op(binary_op(op)),
+                }
+            }
+            Rvalue::UnaryOp(op, operand, ty) => {
+                self.operand(operand, span);
+                // 和二元运算一样，i64 之外的整数类型按宽度取负和取反
+                let width = ty
+                    .as_deref()
+                    .filter(|ty| !matches!(ty, Type::I64 | Type::Isize))
+                    .and_then(|ty| CAST_TYPES.iter().position(|cast| cast == ty));
+                let op = match (op, width) {
+                    (UnOp::Neg, None) => Op::Neg,
+                    (UnOp::Neg, Some(_)) => Op::NegAs,
+                    (_, None) => Op::Not,
+                    (_, Some(_)) => Op::NotAs,
+                };
+                self.op(op);
+                if let Some(width) = width {
+                    self.code.push(width as u8);
+                }
+            }
+            Rvalue::Ref(place, _) => self.address(place),
+            Rvalue::Aggregate(kind, operands) => self.aggregate(kind, operands, span),
+            Rvalue::Cast(operand, ty) => {
+                self.operand(operand, span);
+                match CAST_TYPES.iter().position(|cast| cast == ty) {
+                    // 指针转换不改变指向的位置，不需要指令
+                    None if matches!(ty, Type::Pointer(_, _) | Type::Reference(_, _)) => {}
+                    Some(index) => {
+                        self.op(Op::Cast);
+                        self.code.push(index as u8);
+                    }
+                    None => self.error(
+                        ErrorCode::E0605,
+                        format!("unsupported cast to `{}`", ty),
+                        span,
+                    ),
+                }
+            }
+            Rvalue::Len(place) => {
+                self.address(place);
+                self.op(Op::Len);
+            }
+            Rvalue::Discriminant(place) => {
+                self.address(place);
+                self.op(Op::Discriminant);
+            }
+        }
+    }
+
+    fn aggregate(&mut self, kind: &AggregateKind, operands: &[Operand], span: Span) {
+        match kind {
+            AggregateKind::Struct(name, fields) => {
+                let Some(&id) = self.cx.structs.get(name.as_str()) else {
+                    self.error(
+                        ErrorCode::E0810,
+                        format!("cannot find struct `{}`", name),
+                        span,
+                    );
+                    return;
+                };
+                // 字段按声明顺序入栈
+                let members = self.cx.module.structs[id as usize].members.clone();
+                for member in &members {
+                    match fields.iter().position(|field| field == member) {
+                        Some(i) => self.operand(&operands[i], span),
+                        None => self.error(
+                            ErrorCode::E0810,
+                            format!("missing field `{}` in initializer of `{}`", member, name),
+                            span,
+                        ),
+                    }
+                }
+                self.op_u16(Op::Struct, id);
+                self.code
+                    .extend_from_slice(&(members.len() as u16).to_le_bytes());
+            }
+            AggregateKind::Variant(enum_name, variant) => {
+                let ids = self.cx.enums.get(enum_name.as_str()).copied().zip(
+                    self.cx
+                        .mir
+                        .variant_index(enum_name, variant)
+                        .map(|i| i as u16),
+                );
+                let Some((id, index)) = ids else {
+                    self.error(
+                        ErrorCode::E0810,
+                        format!("cannot find variant `{}::{}`", enum_name, variant),
+                        span,
+                    );
+                    return;
+                };
+                self.operands(operands, span);
+                self.op_u16(Op::Variant, id);
+                self.code.extend_from_slice(&index.to_le_bytes());
+                self.code
+                    .extend_from_slice(&(operands.len() as u16).to_le_bytes());
+            }
+            AggregateKind::Tuple => {
+                self.operands(operands, span);
+                self.op_u16(Op::Tuple, operands.len() as u16);
+            }
+            AggregateKind::Array(_) => {
+                self.operands(operands, span);
+                self.op_u16(Op::Array, operands.len() as u16);
+            }
+            AggregateKind::Range(inclusive) => {
+                self.operands(operands, span);
+                self.op(Op::Range);
+                self.code.push(*inclusive as u8);
+            }
+            AggregateKind::Closure(name) => {
+                let Some(&func) = self.cx.functions.get(name.as_str()) else {
+                    self.error(
+                        ErrorCode::E0810,
+                        format!("cannot find closure body `{}`", name),
+                        span,
+                    );
+                    return;
+                };
+                self.operands(operands, span);
+                self.op(Op::Closure);
+                self.code.extend_from_slice(&func.to_le_bytes());
+                self.code
+                    .extend_from_slice(&(operands.len() as u16).to_le_bytes());
+            }
+        }
+    }
+
+    fn operands(&mut self, operands: &[Operand], span: Span) {
+        for operand in operands {
+            self.operand(operand, span);
+        }
+    }
+
+    fn operand(&mut self, operand: &Operand, span: Span) {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => match place.as_local() {
+                Some(local) => self.op_u16(Op::Load, local.index() as u16),
+                None => {
+                    self.address(place);
+                    self.op(Op::LoadRef);
+                }
+            },
+            Operand::Constant(constant) => {
+                let constant = match &constant.value {
+                    ConstValue::Unit => return self.op(Op::Unit),
+                    ConstValue::Int(n) => Constant::Int(*n),
+                    ConstValue::Float(x) => Constant::Float(*x),
+                    ConstValue::Bool(b) => Constant::Bool(*b),
+                    ConstValue::Char(c) => Constant::Char(*c),
+                    ConstValue::Str(text) => Constant::Str(text.clone()),
+                    ConstValue::Function(name) => match self.function_constant(name) {
+                        Some(constant) => constant,
+                        None => {
+                            return self.error(
+                                ErrorCode::E0810,
+                                format!("cannot find function `{}`", name),
+                                span,
+                            )
+                        }
+                    },
+                    ConstValue::Static(name) | ConstValue::StaticRef(name) => {
+                        let op = match &constant.value {
+                            ConstValue::Static(_) => Op::LoadStatic,
+                            _ => Op::AddrStatic,
+                        };
+                        match self.cx.statics.get(name.as_str()).copied() {
+                            Some(index) => self.op_u16(op, index),
+                            None => self.error(
+                                ErrorCode::E0810,
+                                format!("cannot find static `{}`", name),
+                                span,
+                            ),
+                        }
+                        return;
+                    }
+                };
+                let index = self.cx.constant(constant);
+                self.op(Op::Const);
+                self.code.extend_from_slice(&index.to_le_bytes());
+            }
+        }
+    }
+
+    // 程序中定义的函数优先于同名的内建函数；`#[intrinsic]` 函数换成虚拟机实现的同名内建函数，
+    // 虚拟机不认识的 intrinsic 仍然调用函数本身
+    fn function_constant(&self, name: &str) -> Option<Constant> {
+        let intrinsic = self.cx.mir.intrinsics.get(name);
+        if let Some(builtin) = intrinsic.and_then(|intrinsic| Builtin::from_name(intrinsic)) {
+            return Some(Constant::Builtin(builtin));
+        }
+        match self.cx.functions.get(name) {
+            Some(&func) => Some(Constant::Function(func)),
+            None => Builtin::from_name(name).map(Constant::Builtin),
+        }
+    }
+
+    fn operand_ty(&self, operand: &Operand) -> Type {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.cx.mir.place_ty(self.body, place),
+            Operand::Constant(constant) => constant.ty.clone(),
+        }
+    }
+
+    // 计算 place 的引用并压入栈
+    fn address(&mut self, place: &Place) {
+        let mut projection = place.projection.as_slice();
+        // 对局部变量中的引用解引用时，局部变量的值本身就是引用；盒子和 Rc 要取地址之后向内走
+        let pointee = builtins::pointee_type(&self.body.local_decl(place.local).ty);
+        if let ([PlaceElem::Deref, rest @ ..], None) = (projection, pointee) {
+            self.op_u16(Op::Load, place.local.index() as u16);
+            projection = rest;
+        } else {
+            self.op_u16(Op::AddrLocal, place.local.index() as u16);
+        }
+
+        let mut prefix = Place::local(place.local);
+        prefix.projection = place.projection[..place.projection.len() - projection.len()].to_vec();
+        for elem in projection {
+            match elem {
+                PlaceElem::Deref => self.op(Op::Deref),
+                PlaceElem::Field(name) => self.field(&prefix, name),
+                PlaceElem::Index(local) => {
+                    self.op_u16(Op::Load, local.index() as u16);
+                    self.indexed(*local, Op::Index);
+                }
+                PlaceElem::Subslice(from, to) => {
+                    self.op_u16(Op::Load, from.index() as u16);
+                    self.op_u16(Op::Load, to.index() as u16);
+                    self.indexed(*from, Op::Subslice);
+                }
+                // 变体的字段按位置访问，不需要额外的指令
+                PlaceElem::Downcast(_) => {}
+            }
+            prefix = prefix.project(elem.clone());
+        }
+    }
+
+    // 越界的下标和切片在下标表达式处报告，与解释器相同：下标的临时变量记录了这个位置
+    fn indexed(&mut self, index: Local, op: Op) {
+        let outer = self.lines.last().map(|(_, span)| *span);
+        self.mark(self.body.local_decl(index).span);
+        self.op(op);
+        if let Some(outer) = outer {
+            self.mark(outer);
+        }
+    }
+
+    // 元组和变体的字段名就是位置；结构体字段在类型已知时按声明顺序换成序号
+    fn field(&mut self, base: &Place, name: &str) {
+        if let Ok(index) = name.parse::<u16>() {
+            return self.op_u16(Op::Field, index);
+        }
+        let ty = self.cx.mir.place_ty(self.body, base);
+        let index = self.struct_of(&ty).and_then(|id| {
+            self.cx.module.structs[id as usize]
+                .members
+                .iter()
+                .position(|member| member == name)
+        });
+        match index {
+            Some(index) => self.op_u16(Op::Field, index as u16),
+            None => {
+                let constant = self.cx.constant(Constant::Str(name.to_string()));
+                self.op(Op::FieldNamed);
+                self.code.extend_from_slice(&constant.to_le_bytes());
+            }
+        }
+    }
+
+    fn struct_of(&self, ty: &Type) -> Option<u16> {
+        match ty {
+            Type::Named(name) | Type::Generic(name, _) => {
+                self.cx.structs.get(name.as_str()).copied()
+            }
+            _ => None,
+        }
+    }
+
+    // ---------------- 输出 ----------------
+
+    fn op(&mut self, op: Op) {
+        self.code.push(op as u8);
+    }
+
+    fn op_u16(&mut self, op: Op, operand: u16) {
+        self.op(op);
+        self.code.extend_from_slice(&operand.to_le_bytes());
+    }
+
+    fn target(&mut self, block: BasicBlock) {
+        self.patches.push((self.code.len(), block));
+        self.code.extend_from_slice(&[0; 4]);
+    }
+
+    // 记录接下来的指令对应的源码位置，与上一条相同时不重复记录
+    fn mark(&mut self, span: Span) {
+        if self.lines.last().map(|(_, last)| *last) != Some(span) {
+            self.lines.push((self.code.len() as u32, span));
+        }
+    }
+
+    fn error(&mut self, code: ErrorCode, message: String, span: Span) {
+        self.errors
+            .push(Diagnostic::error(message, span).with_code(code));
+    }
+}
+
+fn binary_op(op: &BinOp) -> Op {
+    match op {
+        BinOp::Add => Op::Add,
+        BinOp::Sub => Op::Sub,
+        BinOp::Mul => Op::Mul,
+        BinOp::Div => Op::Div,
+        BinOp::Mod => Op::Rem,
+        BinOp::BitwiseAnd => Op::BitAnd,
+        BinOp::BitwiseOr => Op::BitOr,
+        BinOp::BitwiseXor => Op::BitXor,
+        BinOp::LeftShift => Op::Shl,
+        BinOp::RightShift => Op::Shr,
+        BinOp::Equal => Op::Eq,
+        BinOp::NotEqual => Op::Ne,
+        BinOp::Less => Op::Lt,
+        BinOp::Greater => Op::Gt,
+        BinOp::LessEqual => Op::Le,
+        BinOp::GreaterEqual => Op::Ge,
+        BinOp::LogicalAnd => Op::And,
+        BinOp::LogicalOr => Op::Or,
+    }
+}
@@ -0,0 +1,480 @@
+// `.ctxb` 文件格式
+// 文件头是 4 字节的 MAGIC 和 2 字节小端的 VERSION，之后依次是源文件名（空串表示未知）、
+// 8 字节小端的内容哈希、
+// 常量池、函数表、结构体表、枚举表、静态变量表和入口函数：
+// - 整数用 LEB128 变长编码，有符号整数先做 zigzag 变换
+// - 浮点数按 IEEE 754 位模式存为 8 字节小端整数
+// - 字符串和指令流是长度加原始字节，列表是个数加元素
+// - 入口函数存为编号加一，0 表示没有 main
+// 解码时检查指令边界、所有下标和操作数栈的高度，保证虚拟机执行时不会越界访问表和值栈；
+// 函数的符号必须是合法的修饰名且互不相同
+
+use super::{
+    instruction_len, read_i64, read_u16, read_u32, read_u8, stack_effect, Builtin, Constant,
+    Function, Module, Op, TypeInfo, CAST_TYPES, CHECKED_OPS,
+};
+use crate::mangle::Symbol;
+use crate::span::Span;
+use std::collections::HashSet;
+
+/// 文件头
+pub const MAGIC: [u8; 4] = *b"CTXB";
+
+/// 格式版本，不兼容的修改时递增
+pub const VERSION: u16 = 10;
+
+// 常量的类型标记
+const TAG_INT: u8 = 0;
+const TAG_FLOAT: u8 = 1;
+const TAG_BOOL: u8 = 2;
+const TAG_CHAR: u8 = 3;
+const TAG_STR: u8 = 4;
+const TAG_FUNCTION: u8 = 5;
+const TAG_BUILTIN: u8 = 6;
+
+pub fn encode(module: &Module) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.bytes.extend_from_slice(&MAGIC);
+    writer.bytes.extend_from_slice(&VERSION.to_le_bytes());
+    writer.str(module.file.as_deref().unwrap_or(""));
+    writer.bytes.extend_from_slice(&module.hash.to_le_bytes());
+
+    writer.uint(module.constants.len() as u64);
+    for constant in &module.constants {
+        match constant {
+            Constant::Int(n) => {
+                writer.bytes.push(TAG_INT);
+                writer.int(*n);
+            }
+            Constant::Float(x) => {
+                writer.bytes.push(TAG_FLOAT);
+                writer.bytes.extend_from_slice(&x.to_bits().to_le_bytes());
+            }
+            Constant::Bool(b) => {
+                writer.bytes.push(TAG_BOOL);
+                writer.bytes.push(*b as u8);
+            }
+            Constant::Char(c) => {
+                writer.bytes.push(TAG_CHAR);
+                writer.uint(*c as u64);
+            }
+            Constant::Str(text) => {
+                writer.bytes.push(TAG_STR);
+                writer.str(text);
+            }
+            Constant::Function(func) => {
+                writer.bytes.push(TAG_FUNCTION);
+                writer.uint(*func as u64);
+            }
+            Constant::Builtin(builtin) => {
+                writer.bytes.push(TAG_BUILTIN);
+                writer.str(builtin.name());
+            }
+        }
+    }
+
+    writer.uint(module.functions.len() as u64);
+    for func in &module.functions {
+        writer.str(&func.name);
+        writer.str(&func.symbol);
+        writer.uint(func.arity as u64);
+        writer.uint(func.locals as u64);
+        writer.uint(func.code.len() as u64);
+        writer.bytes.extend_from_slice(&func.code);
+        writer.uint(func.lines.len() as u64);
+        for (offset, span) in &func.lines {
+            writer.uint(*offset as u64);
+            writer.uint(span.start as u64);
+            writer.uint(span.end as u64);
+            writer.uint(span.line as u64);
+            writer.uint(span.column as u64);
+        }
+    }
+
+    for types in [&module.structs, &module.enums] {
+        writer.uint(types.len() as u64);
+        for info in types {
+            writer.str(&info.name);
+            writer.uint(info.members.len() as u64);
+            for member in &info.members {
+                writer.str(member);
+            }
+        }
+    }
+
+    writer.uint(module.statics.len() as u64);
+    for func in &module.statics {
+        writer.uint(*func as u64);
+    }
+    writer.uint(module.entry.map_or(0, |entry| entry as u64 + 1));
+    writer.bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Module, String> {
+    decode_module(bytes).map_err(|message| format!("invalid bytecode file: {}", message))
+}
+
+fn decode_module(bytes: &[u8]) -> Result<Module, String> {
+    if bytes.len() < 6 || bytes[..4] != MAGIC {
+        return Err("missing `CTXB` header".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        return Err(format!(
+            "unsupported version {} (expected {})",
+            version, VERSION
+        ));
+    }
+    let mut reader = Reader { bytes, pos: 6 };
+    let mut module = Module::default();
+    let file = reader.str()?;
+    module.file = (!file.is_empty()).then_some(file);
+    module.hash = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+
+    for _ in 0..reader.len()? {
+        let constant = match reader.byte()? {
+            TAG_INT => Constant::Int(reader.int()?),
+            TAG_FLOAT => Constant::Float(f64::from_bits(u64::from_le_bytes(
+                reader.take(8)?.try_into().unwrap(),
+            ))),
+            TAG_BOOL => Constant::Bool(reader.byte()? != 0),
+            TAG_CHAR => {
+                let code = reader.uint()?;
+                let c = u32::try_from(code).ok().and_then(char::from_u32);
+                Constant::Char(c.ok_or_else(|| format!("invalid character {:#x}", code))?)
+            }
+            TAG_STR => Constant::Str(reader.str()?),
+            TAG_FUNCTION => Constant::Function(reader.u32()?),
+            TAG_BUILTIN => {
+                let name = reader.str()?;
+                Constant::Builtin(
+                    Builtin::from_name(&name)
+                        .ok_or_else(|| format!("unknown builtin `{}`", name))?,
+                )
+            }
+            tag => return Err(format!("unknown constant tag {}", tag)),
+        };
+        module.constants.push(constant);
+    }
+
+    for _ in 0..reader.len()? {
+        let name = reader.str()?;
+        let symbol = reader.str()?;
+        let arity = reader.u16()?;
+        let locals = reader.u16()?;
+        let len = reader.len()?;
+        let code = reader.take(len)?.to_vec();
+        let mut lines = Vec::new();
+        for _ in 0..reader.len()? {
+            let offset = reader.u32()?;
+            let start = reader.uint()? as usize;
+            let end = reader.uint()? as usize;
+            let line = reader.u32()?;
+            let column = reader.u32()?;
+            lines.push((offset, Span::new(start, end, line, column)));
+        }
+        module.functions.push(Function {
+            name,
+            symbol,
+            arity,
+            locals,
+            code,
+            lines,
+        });
+    }
+
+    for types in [&mut module.structs, &mut module.enums] {
+        for _ in 0..reader.len()? {
+            let name = reader.str()?;
+            let mut members = Vec::new();
+            for _ in 0..reader.len()? {
+                members.push(reader.str()?);
+            }
+            types.push(TypeInfo { name, members });
+        }
+    }
+
+    for _ in 0..reader.len()? {
+        module.statics.push(reader.u32()?);
+    }
+    module.entry = match reader.uint()? {
+        0 => None,
+        entry => Some(u32::try_from(entry - 1).map_err(|_| "entry out of range")?),
+    };
+    if reader.pos != bytes.len() {
+        return Err("trailing data after the module".to_string());
+    }
+
+    validate(&module)?;
+    Ok(module)
+}
+
+// 检查所有下标都在对应的表中，跳转目标都是指令的起始位置，指令不会弹出空的操作数栈
+fn validate(module: &Module) -> Result<(), String> {
+    let functions = module.functions.len();
+    let function_index = |func: u32| {
+        if (func as usize) < functions {
+            Ok(())
+        } else {
+            Err(format!("function {} out of range", func))
+        }
+    };
+    for constant in &module.constants {
+        if let Constant::Function(func) = constant {
+            function_index(*func)?;
+        }
+    }
+    let mut symbols = HashSet::new();
+    for func in &module.functions {
+        Symbol::parse(&func.symbol)?;
+        if !symbols.insert(func.symbol.as_str()) {
+            return Err(format!("duplicate symbol `{}`", func.symbol));
+        }
+    }
+    for func in module.statics.iter().chain(&module.entry) {
+        function_index(*func)?;
+    }
+
+    for func in &module.functions {
+        let error =
+            |pc: usize, message: String| format!("in `{}` at {}: {}", func.name, pc, message);
+        if (func.locals as usize) < func.arity as usize + 1 {
+            return Err(error(0, "fewer locals than arguments".to_string()));
+        }
+
+        let code = &func.code;
+        let mut starts = vec![false; code.len() + 1];
+        let mut targets = Vec::new();
+        let mut pc = 0;
+        while pc < code.len() {
+            let len = instruction_len(code, pc)
+                .ok_or_else(|| error(pc, "invalid or truncated instruction".to_string()))?;
+            starts[pc] = true;
+            let at = pc + 1;
+            let check = |ok: bool, what: &str| {
+                if ok {
+                    Ok(())
+                } else {
+                    Err(error(pc, format!("{} out of range", what)))
+                }
+            };
+            match Op::from_byte(code[pc]).unwrap() {
+                Op::Const => check(
+                    (read_u32(code, at) as usize) < module.constants.len(),
+                    "constant",
+                )?,
+                Op::FieldNamed => check(
+                    matches!(
+                        module.constants.get(read_u32(code, at) as usize),
+                        Some(Constant::Str(_))
+                    ),
+                    "field name",
+                )?,
+                Op::AssertContract => check(
+                    [read_u32(code, at + 1), read_u32(code, at + 5)]
+                        .iter()
+                        .all(|index| {
+                            matches!(
+                                module.constants.get(*index as usize),
+                                Some(Constant::Str(_))
+                            )
+                        }),
+                    "contract message",
+                )?,
+                Op::Load | Op::Store | Op::AddrLocal => {
+                    check(read_u16(code, at) < func.locals, "local")?
+                }
+                Op::LoadStatic | Op::AddrStatic => check(
+                    (read_u16(code, at) as usize) < module.statics.len(),
+                    "static",
+                )?,
+                Op::Struct => check(
+                    (read_u16(code, at) as usize) < module.structs.len(),
+                    "struct",
+                )?,
+                Op::Variant => check((read_u16(code, at) as usize) < module.enums.len(), "enum")?,
+                Op::Closure => function_index(read_u32(code, at)).map_err(|e| error(pc, e))?,
+                Op::Cast | Op::NegAs | Op::NotAs => {
+                    check((read_u8(code, at) as usize) < CAST_TYPES.len(), "cast")?
+                }
+                Op::Checked => {
+                    check(
+                        (read_u8(code, at) as usize) < CHECKED_OPS.len(),
+                        "operation",
+                    )?;
+                    check((read_u8(code, at + 1) as usize) < CAST_TYPES.len(), "cast")?
+                }
+                Op::Jump | Op::JumpIfFalse => targets.push((pc, read_u32(code, at))),
+                Op::Switch => {
+                    let count = read_u16(code, at) as usize;
+                    let values: Vec<i64> = (0..count)
+                        .map(|i| read_i64(code, at + 2 + i * 12))
+                        .collect();
+                    if !values.windows(2).all(|pair| pair[0] < pair[1]) {
+                        let message = "switch values are not in ascending order".to_string();
+                        return Err(error(pc, message));
+                    }
+                    for i in 0..count {
+                        targets.push((pc, read_u32(code, at + 2 + i * 12 + 8)));
+                    }
+                    targets.push((pc, read_u32(code, at + 2 + count * 12)));
+                }
+                _ => {}
+            }
+            pc += len;
+        }
+        // 最后一条指令必须结束控制流，否则会执行到指令流之外
+        let last = (0..code.len()).rev().find(|&pc| starts[pc]);
+        let terminated = last.is_some_and(|pc| {
+            matches!(
+                Op::from_byte(code[pc]),
+                Some(Op::Return | Op::Jump | Op::Switch | Op::Unreachable)
+            )
+        });
+        if !terminated {
+            return Err(error(
+                code.len(),
+                "code does not end with a jump or return".to_string(),
+            ));
+        }
+        for (pc, target) in targets {
+            if !starts.get(target as usize).copied().unwrap_or(false) {
+                return Err(error(pc, format!("invalid jump target {}", target)));
+            }
+        }
+
+        // 沿控制流计算每条指令之前操作数栈（局部变量之上）的高度：不能弹出比栈上更多的值，
+        // 从不同路径到达同一条指令时高度相同
+        let mut heights = vec![None; code.len()];
+        let mut pending = vec![(0, 0usize)];
+        while let Some((pc, height)) = pending.pop() {
+            match heights[pc] {
+                Some(known) if known == height => continue,
+                Some(known) => {
+                    let message = format!(
+                        "operand stack height {} differs from {} on another path",
+                        height, known
+                    );
+                    return Err(error(pc, message));
+                }
+                None => heights[pc] = Some(height),
+            }
+            let (pops, pushes) = stack_effect(code, pc);
+            let height = height
+                .checked_sub(pops)
+                .ok_or_else(|| error(pc, "operand stack underflow".to_string()))?
+                + pushes;
+            let next = pc + instruction_len(code, pc).unwrap();
+            let at = pc + 1;
+            match Op::from_byte(code[pc]).unwrap() {
+                Op::Return | Op::Unreachable => {}
+                Op::Jump => pending.push((read_u32(code, at) as usize, height)),
+                Op::JumpIfFalse => {
+                    pending.push((next, height));
+                    pending.push((read_u32(code, at) as usize, height));
+                }
+                Op::Switch => {
+                    let count = read_u16(code, at) as usize;
+                    for i in 0..count {
+                        pending.push((read_u32(code, at + 2 + i * 12 + 8) as usize, height));
+                    }
+                    pending.push((read_u32(code, at + 2 + count * 12) as usize, height));
+                }
+                _ => pending.push((next, height)),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn uint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn int(&mut self, value: i64) {
+        self.uint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn str(&mut self, text: &str) {
+        self.uint(text.len() as u64);
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.pos).ok_or("unexpected end of file")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or("unexpected end of file")?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("integer is too long".to_string())
+    }
+
+    fn int(&mut self) -> Result<i64, String> {
+        let value = self.uint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        u16::try_from(self.uint()?).map_err(|_| "integer out of range".to_string())
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        u32::try_from(self.uint()?).map_err(|_| "integer out of range".to_string())
+    }
+
+    // 列表长度和字节数，不能超过剩余的数据
+    fn len(&mut self) -> Result<usize, String> {
+        let len = self.uint()?;
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err("length exceeds the file size".to_string());
+        }
+        Ok(len as usize)
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "invalid UTF-8 in string".to_string())
+    }
+}
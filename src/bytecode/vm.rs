@@ -0,0 +1,1957 @@
+// 字节码虚拟机
+// 所有栈帧共用一个值栈：栈帧从被调函数在栈上的位置开始，依次是 `_0`、参数和其余局部变量，
+// 再往上是操作数栈。调用时被调函数所在的位置直接成为 `_0`，返回时栈截断到这里并压入返回值
+// - 当前栈帧的函数、指令位置和基址放在虚拟机的字段中，调用时才保存到 frames
+// - 引用是值栈上的绝对位置加上字段/下标路径，取字段和下标时自动穿过引用、盒子和 Rc
+// - 分派用按操作码索引的处理函数表（相当于 computed goto），每条指令一次间接调用
+// - 静态变量在 main 之前按声明顺序初始化，初始化代码引用后面的静态变量时先初始化它
+// - 设置了资源限制（沙箱）时每执行 FUEL 条指令检查一次时间和估算的内存用量
+// - `io` 模块经过解释器的宿主接口，默认不允许使用；`io::exit` 沿嵌套的执行一直退出到最外层
+// 运行时错误的消息与解释器一致，位置取自函数的行号表；
+// 解码时只检查了指令边界和下标范围，操作数栈的平衡由编译器保证
+
+use super::{
+    read_i64, read_u16, read_u32, read_u8, Builtin, Constant, Module, Op, CAST_TYPES, CHECKED_OPS,
+};
+use crate::ast::{BinOp, Type, UnOp};
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::fmt;
+use crate::interp::{self, ops, Host, Key, Table};
+use crate::span::Span;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// 最多同时存在的栈帧数，与解释器的调用深度限制相同
+pub const FRAME_LIMIT: usize = interp::CALL_DEPTH_LIMIT;
+
+// 有资源限制时两次检查之间执行的指令数
+const FUEL: u32 = 4096;
+
+/// 执行的资源限制，超出时以运行时错误停止
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunLimits {
+    pub max_time: Option<Duration>,
+    pub max_memory: Option<usize>, // 值栈和静态变量占用的字节数（估算）
+}
+
+/// 超出的资源限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exceeded {
+    Time,
+    Memory,
+}
+
+/// 虚拟机的运行时值，结构体和枚举按类型表中的编号保存
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Char(char),
+    Str(Rc<str>),
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+    Map(Table<Value>),
+    Struct(u32, Vec<Value>),       // 字段按声明顺序
+    Variant(u32, u32, Vec<Value>), // (枚举, 变体序号, 字段)
+    Range(Box<[Value; 2]>, bool),  // 起点、终点和是否包含终点
+    Function(u32),
+    Builtin(Builtin),
+    Closure(u32, Vec<Value>), // 函数和按值捕获的变量
+    Boxed(Box<Value>),        // `Box<T>`，盒子中的值是它唯一的子值
+    Shared(Rc<Shared>),       // `Rc<T>`，共享的值是它唯一的子值
+    Ref(Pointer),
+}
+
+/// `Rc<T>` 共享的值和引用计数。虚拟机中的值可以随意复制，计数只由 `Rc::clone` 和
+/// MIR 的析构（`Op::Drop`）维护，与 `Rc` 在程序中的所有者个数一致
+#[derive(Debug)]
+pub struct Shared {
+    pub strong: Cell<usize>,
+    pub value: Value,
+}
+
+// 共享的值按其中的值比较，与计数无关
+impl PartialEq for Shared {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+/// 指向值栈上的变量（包括静态变量）或其一部分。
+/// 切片的引用是胖指针：路径指向数组，`window` 是切片在数组中的起点和长度
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pointer {
+    pub slot: usize,
+    pub path: Vec<u32>, // 字段序号或数组下标
+    pub window: Option<(u32, u32)>,
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unit => "()",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Str(_) => "string",
+            Value::Tuple(_) => "tuple",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Struct(_, _) => "struct",
+            Value::Variant(_, _, _) => "enum",
+            Value::Range(_, _) => "range",
+            Value::Function(_) | Value::Builtin(_) => "function",
+            Value::Closure(_, _) => "closure",
+            Value::Boxed(_) => "box",
+            Value::Shared(_) => "rc",
+            Value::Ref(_) => "reference",
+        }
+    }
+
+    fn child(&self, index: u32) -> Option<&Value> {
+        match self {
+            Value::Tuple(values)
+            | Value::Array(values)
+            | Value::Struct(_, values)
+            | Value::Variant(_, _, values)
+            | Value::Closure(_, values) => values.get(index as usize),
+            Value::Range(bounds, _) => bounds.get(index as usize),
+            Value::Boxed(inner) if index == 0 => Some(inner),
+            Value::Shared(shared) if index == 0 => Some(&shared.value),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, index: u32) -> Option<&mut Value> {
+        match self {
+            Value::Tuple(values)
+            | Value::Array(values)
+            | Value::Struct(_, values)
+            | Value::Variant(_, _, values)
+            | Value::Closure(_, values) => values.get_mut(index as usize),
+            Value::Range(bounds, _) => bounds.get_mut(index as usize),
+            Value::Boxed(inner) if index == 0 => Some(inner),
+            _ => None,
+        }
+    }
+
+    fn to_key(&self) -> Result<Key, Exit> {
+        Ok(match self {
+            Value::Bool(b) => Key::Bool(*b),
+            Value::Int(n) => Key::Int(*n),
+            Value::Char(c) => Key::Char(*c),
+            Value::Str(text) => Key::Str(text.to_string()),
+            other => {
+                return Err(format!("cannot use {} value as a map key", other.type_name()).into())
+            }
+        })
+    }
+
+    fn from_key(key: &Key) -> Value {
+        match key {
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Int(n) => Value::Int(*n),
+            Key::Char(c) => Value::Char(*c),
+            Key::Str(text) => Value::Str(text.as_str().into()),
+        }
+    }
+
+    // 与解释器交换标量，复用解释器的运算符实现
+    fn to_interp(&self) -> Option<interp::Value> {
+        Some(match self {
+            Value::Unit => interp::Value::Unit,
+            Value::Bool(b) => interp::Value::Bool(*b),
+            Value::Int(n) => interp::Value::Int(*n),
+            Value::Float(x) => interp::Value::Float(*x),
+            Value::Char(c) => interp::Value::Char(*c),
+            Value::Str(text) => interp::Value::Str(text.to_string()),
+            _ => return None,
+        })
+    }
+
+    fn from_interp(value: interp::Value) -> Value {
+        match value {
+            interp::Value::Bool(b) => Value::Bool(b),
+            interp::Value::Int(n) => Value::Int(n),
+            interp::Value::Float(x) => Value::Float(x),
+            interp::Value::Char(c) => Value::Char(c),
+            interp::Value::Str(text) => Value::Str(text.into()),
+            _ => Value::Unit,
+        }
+    }
+}
+
+// 停止执行的原因，沿处理函数的返回值传递到分派循环
+enum Exit {
+    Halt(Value),            // 最外层的栈帧返回
+    Error(String),          // 当前指令出错，位置由分派循环补上
+    Fault(Box<Diagnostic>), // 已经带有位置的错误（来自嵌套执行）
+    Exited,                 // `io::exit`，退出码记录在虚拟机中
+}
+
+impl From<String> for Exit {
+    fn from(message: String) -> Self {
+        Exit::Error(message)
+    }
+}
+
+impl From<&str> for Exit {
+    fn from(message: &str) -> Self {
+        Exit::Error(message.to_string())
+    }
+}
+
+type Step = Result<(), Exit>;
+type Handler<V> = fn(&mut V) -> Step;
+
+// 调用者保存的栈帧
+struct Frame<'m> {
+    func: u32,
+    code: &'m [u8],
+    pc: usize,
+    base: usize,
+}
+
+// 静态变量的值保存在值栈底部预留的槽位中（第 i 个静态变量在槽位 i），
+// 地址在整个运行期间不变，可以取引用和通过引用修改
+enum Static {
+    Uninit,
+    Initializing,
+    Ready,
+}
+
+pub struct Vm<'m, W: Write> {
+    module: &'m Module,
+    constants: Vec<Value>,
+    statics: Vec<Static>,
+    stack: Vec<Value>,
+    frames: Vec<Frame<'m>>,
+    depth: usize,      // 正在执行的栈帧数
+    stop_depth: usize, // frames 回到这个长度时停止执行
+    // 当前栈帧
+    func: u32,
+    code: &'m [u8],
+    pc: usize,
+    op_pc: usize, // 当前指令的起始位置，用于报告错误
+    base: usize,
+    output: W,
+    // 资源限制；fuel 用完时检查
+    deadline: Option<Instant>,
+    limits: RunLimits,
+    fuel: u32,
+    exceeded: Option<Exceeded>,
+    host: Host,
+    exit_code: Option<i32>,
+}
+
+impl<'m, W: Write> Vm<'m, W> {
+    pub fn new(module: &'m Module, output: W) -> Self {
+        let constants = module
+            .constants
+            .iter()
+            .map(|constant| match constant {
+                Constant::Int(n) => Value::Int(*n),
+                Constant::Float(x) => Value::Float(*x),
+                Constant::Bool(b) => Value::Bool(*b),
+                Constant::Char(c) => Value::Char(*c),
+                Constant::Str(text) => Value::Str(text.as_str().into()),
+                Constant::Function(func) => Value::Function(*func),
+                Constant::Builtin(builtin) => Value::Builtin(*builtin),
+            })
+            .collect();
+        Self {
+            module,
+            constants,
+            statics: module.statics.iter().map(|_| Static::Uninit).collect(),
+            stack: vec![Value::Unit; module.statics.len()],
+            frames: Vec::new(),
+            depth: 0,
+            stop_depth: 0,
+            func: 0,
+            code: &[],
+            pc: 0,
+            op_pc: 0,
+            base: 0,
+            output,
+            deadline: None,
+            limits: RunLimits::default(),
+            fuel: u32::MAX,
+            exceeded: None,
+            host: Host::default(),
+            exit_code: None,
+        }
+    }
+
+    /// 设置资源限制，时间从现在开始计算
+    pub fn set_limits(&mut self, limits: RunLimits) {
+        self.set_limits_since(limits, Instant::now());
+    }
+
+    /// 设置资源限制，时间从 `start` 开始计算，之前用掉的时间（例如编译）也算在内
+    pub fn set_limits_since(&mut self, limits: RunLimits, start: Instant) {
+        self.deadline = limits.max_time.map(|time| start + time);
+        self.limits = limits;
+        self.fuel = 0;
+    }
+
+    /// 程序可以使用的宿主环境，默认不允许使用 `io` 模块
+    pub fn set_host(&mut self, host: Host) {
+        self.host = host;
+    }
+
+    /// 进程的退出码：程序调用 `io::exit` 时是它给出的值，否则是 `main() -> i32` 的返回值
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// 因为超出资源限制而停止时，超出的是哪一项
+    pub fn exceeded(&self) -> Option<Exceeded> {
+        self.exceeded
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// 初始化静态变量后执行 `main`，返回 `main` 的返回值
+    pub fn run_main(&mut self) -> Result<Value, Vec<Diagnostic>> {
+        for index in 0..self.statics.len() {
+            if let Err(exit) = self.init_static(index) {
+                return self.finish(Err(exit));
+            }
+        }
+        match self.module.entry {
+            Some(main) => {
+                let result = self.invoke(main, Vec::new());
+                if let (None, Ok(Value::Int(code))) = (self.exit_code, &result) {
+                    self.exit_code = Some(*code as i32);
+                }
+                self.finish(result)
+            }
+            None => Err(vec![Diagnostic::error(
+                "`main` function not found".to_string(),
+                Span::new(0, 0, 1, 1),
+            )
+            .with_code(ErrorCode::E0601)]),
+        }
+    }
+
+    /// 按名字调用函数
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, Vec<Diagnostic>> {
+        let Some(func) = self.module.functions.iter().position(|f| f.name == name) else {
+            return Err(vec![Diagnostic::error(
+                format!("cannot find function `{}`", name),
+                Span::new(0, 0, 1, 1),
+            )
+            .with_code(ErrorCode::E0425)]);
+        };
+        let result = self.invoke(func as u32, args);
+        self.finish(result)
+    }
+
+    /// 按解释器的格式输出值
+    pub fn display(&self, value: &Value) -> String {
+        let mut text = String::new();
+        self.write_value(&mut text, value, false);
+        text
+    }
+
+    // 调用 `io::exit` 结束的程序正常返回
+    fn finish(&self, result: Result<Value, Exit>) -> Result<Value, Vec<Diagnostic>> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(Exit::Exited) => Ok(Value::Unit),
+            Err(exit) => Err(self.diagnostics(exit)),
+        }
+    }
+
+    // 运行时错误（panic）的位置来自行号表，带上模块记录的源文件名
+    fn diagnostics(&self, exit: Exit) -> Vec<Diagnostic> {
+        let diagnostic = match exit {
+            Exit::Fault(diagnostic) => *diagnostic,
+            Exit::Error(message) => {
+                Diagnostic::error(message, self.current_span()).with_code(ErrorCode::E0900)
+            }
+            Exit::Halt(_) | Exit::Exited => return Vec::new(),
+        };
+        match &self.module.file {
+            Some(file) if diagnostic.file.is_none() => vec![diagnostic.with_file(file.clone())],
+            _ => vec![diagnostic],
+        }
+    }
+
+    fn current_span(&self) -> Span {
+        match self.module.functions.get(self.func as usize) {
+            Some(func) => func.span_at(self.op_pc),
+            None => Span::new(0, 0, 1, 1),
+        }
+    }
+
+    // ---------------- 执行 ----------------
+
+    // 在当前状态之上执行一个函数直到它返回，之后恢复原来的栈帧；可以嵌套
+    fn invoke(&mut self, func: u32, args: Vec<Value>) -> Result<Value, Exit> {
+        let position = self.stack.len();
+        self.stack.push(Value::Function(func));
+        let argc = args.len();
+        self.stack.extend(args);
+
+        let saved_stop = self.stop_depth;
+        self.save_frame();
+        self.stop_depth = self.frames.len();
+        let result = self
+            .enter(func, position, argc)
+            .and_then(|()| self.execute());
+        self.stop_depth = saved_stop;
+        let value = result?;
+        self.restore_frame();
+        self.stack.truncate(position);
+        Ok(value)
+    }
+
+    fn execute(&mut self) -> Result<Value, Exit> {
+        // 取引用使处理函数表提升为静态数据，而不是每条指令复制一次常量
+        let handlers = &Self::HANDLERS;
+        loop {
+            self.op_pc = self.pc;
+            let op = self.code[self.pc];
+            self.pc += 1;
+            let step = if self.fuel == 0 {
+                self.check_limits()
+                    .and_then(|()| handlers[op as usize](self))
+            } else {
+                self.fuel -= 1;
+                handlers[op as usize](self)
+            };
+            match step {
+                Ok(()) => {}
+                Err(Exit::Halt(value)) => return Ok(value),
+                Err(Exit::Error(message)) => {
+                    return Err(Exit::Fault(Box::new(
+                        Diagnostic::error(message, self.current_span()).with_code(ErrorCode::E0900),
+                    )))
+                }
+                Err(fault) => return Err(fault),
+            }
+        }
+    }
+
+    fn check_limits(&mut self) -> Step {
+        if self.deadline.is_none() && self.limits.max_memory.is_none() {
+            self.fuel = u32::MAX;
+            return Ok(());
+        }
+        self.fuel = FUEL;
+        if let (Some(deadline), Some(time)) = (self.deadline, self.limits.max_time) {
+            if Instant::now() >= deadline {
+                self.exceeded = Some(Exceeded::Time);
+                return Err(format!("time limit of {:?} exceeded", time).into());
+            }
+        }
+        if let Some(max) = self.limits.max_memory {
+            self.check_memory(self.memory_usage(), max)?;
+        }
+        Ok(())
+    }
+
+    fn check_memory(&mut self, used: usize, max: usize) -> Step {
+        if used > max {
+            self.exceeded = Some(Exceeded::Memory);
+            return Err(format!("memory limit of {} bytes exceeded", max).into());
+        }
+        Ok(())
+    }
+
+    /// 值栈（包括静态变量）和栈帧占用的字节数（估算，共享的字符串重复计算）
+    pub fn memory_usage(&self) -> usize {
+        self.stack.iter().map(heap_size).sum::<usize>()
+            + self.frames.len() * mem::size_of::<Frame>()
+    }
+
+    fn save_frame(&mut self) {
+        self.frames.push(Frame {
+            func: self.func,
+            code: self.code,
+            pc: self.pc,
+            base: self.base,
+        });
+    }
+
+    fn restore_frame(&mut self) {
+        let frame = self.frames.pop().expect("no frame to return to");
+        self.func = frame.func;
+        self.code = frame.code;
+        self.pc = frame.pc;
+        self.base = frame.base;
+    }
+
+    // 进入 position 处的函数，实参已经在它上面；调用者的栈帧已经保存
+    fn enter(&mut self, func: u32, position: usize, argc: usize) -> Step {
+        let function = &self.module.functions[func as usize];
+        if argc != function.arity as usize {
+            return Err(format!(
+                "this function takes {} arguments but {} were supplied",
+                function.arity, argc
+            )
+            .into());
+        }
+        if self.depth >= FRAME_LIMIT {
+            return Err(format!(
+                "stack overflow: call depth exceeded the limit of {}",
+                FRAME_LIMIT
+            )
+            .into());
+        }
+        self.depth += 1;
+        self.stack[position] = Value::Unit;
+        self.stack
+            .resize(position + function.locals as usize, Value::Unit);
+        self.func = func;
+        self.code = &function.code;
+        self.pc = 0;
+        self.base = position;
+        Ok(())
+    }
+
+    // 第一次使用时执行静态变量的初始化函数
+    fn init_static(&mut self, index: usize) -> Result<(), Exit> {
+        let func = self.module.statics[index];
+        match &self.statics[index] {
+            Static::Ready => return Ok(()),
+            Static::Initializing => {
+                return Err(format!(
+                    "cycle detected when initializing static `{}`",
+                    self.module.functions[func as usize].name
+                )
+                .into())
+            }
+            Static::Uninit => {}
+        }
+        self.statics[index] = Static::Initializing;
+        self.stack[index] = self.invoke(func, Vec::new())?;
+        self.statics[index] = Static::Ready;
+        Ok(())
+    }
+
+    // ---------------- 栈和操作数 ----------------
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("operand stack underflow")
+    }
+
+    fn pop_n(&mut self, n: usize) -> Vec<Value> {
+        let at = self.stack.len() - n;
+        self.stack.split_off(at)
+    }
+
+    fn next_u8(&mut self) -> u8 {
+        let value = read_u8(self.code, self.pc);
+        self.pc += 1;
+        value
+    }
+
+    fn next_u16(&mut self) -> u16 {
+        let value = read_u16(self.code, self.pc);
+        self.pc += 2;
+        value
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let value = read_u32(self.code, self.pc);
+        self.pc += 4;
+        value
+    }
+
+    // 局部变量操作数在值栈上的位置
+    fn next_local(&mut self) -> usize {
+        self.base + self.next_u16() as usize
+    }
+
+    // ---------------- 引用 ----------------
+
+    fn pop_pointer(&mut self) -> Result<Pointer, Exit> {
+        match self.pop() {
+            Value::Ref(pointer) => Ok(pointer),
+            other => Err(format!("cannot dereference {} value", other.type_name()).into()),
+        }
+    }
+
+    fn resolve(&self, pointer: &Pointer) -> Result<&Value, Exit> {
+        let mut value = self.stack.get(pointer.slot);
+        for &step in &pointer.path {
+            value = value.and_then(|value| value.child(step));
+        }
+        value.ok_or_else(|| "invalid memory access".into())
+    }
+
+    fn resolve_mut(&mut self, pointer: &Pointer) -> Result<&mut Value, Exit> {
+        let mut value = self.stack.get_mut(pointer.slot);
+        for &step in &pointer.path {
+            value = value.and_then(|value| value.child_mut(step));
+        }
+        value.ok_or_else(|| "invalid memory access".into())
+    }
+
+    // 指向引用的指针换成引用本身指向的位置，使路径中不含引用；切片指向的总是数组
+    fn follow(&self, mut pointer: Pointer) -> Result<Pointer, Exit> {
+        while pointer.window.is_none() {
+            let Value::Ref(target) = self.resolve(&pointer)? else {
+                break;
+            };
+            pointer = target.clone();
+        }
+        Ok(pointer)
+    }
+
+    // 取字段、下标、长度和变体时自动解引用：穿过引用，再进入盒子和 Rc 中的值
+    fn auto_deref(&self, pointer: Pointer) -> Result<Pointer, Exit> {
+        let mut pointer = self.follow(pointer)?;
+        while pointer.window.is_none()
+            && matches!(self.resolve(&pointer)?, Value::Boxed(_) | Value::Shared(_))
+        {
+            pointer.path.push(0);
+            pointer = self.follow(pointer)?;
+        }
+        Ok(pointer)
+    }
+
+    // 指向的值的副本，切片读取为它的元素组成的数组
+    fn load(&self, pointer: &Pointer) -> Result<Value, Exit> {
+        let value = self.resolve(pointer)?;
+        let Some((start, len)) = pointer.window else {
+            return Ok(value.clone());
+        };
+        match value {
+            Value::Array(elements) => {
+                let range = start as usize..(start + len) as usize;
+                let elements = elements.get(range).ok_or("invalid memory access")?;
+                Ok(Value::Array(elements.to_vec()))
+            }
+            _ => Err("invalid memory access".into()),
+        }
+    }
+
+    // 指向的数组或切片的长度
+    fn len_of(&self, pointer: &Pointer) -> Result<usize, Exit> {
+        match (pointer.window, self.resolve(pointer)?) {
+            (Some((_, len)), _) => Ok(len as usize),
+            (None, Value::Array(elements)) => Ok(elements.len()),
+            (None, other) => {
+                Err(format!("cannot take the length of {} value", other.type_name()).into())
+            }
+        }
+    }
+
+    // 算术和比较作用于引用的目标值
+    fn deref_value(&self, value: Value) -> Result<Value, Exit> {
+        match value {
+            Value::Ref(pointer) => self.load(&self.follow(pointer)?),
+            value => Ok(value),
+        }
+    }
+
+    // ---------------- 指令 ----------------
+
+    const HANDLERS: [Handler<Self>; Op::COUNT] = {
+        let mut table = [Self::op_unreachable as Handler<Self>; Op::COUNT];
+        let mut i = 0;
+        while i < Op::COUNT {
+            table[i] = Self::handler(Op::ALL[i]);
+            i += 1;
+        }
+        table
+    };
+
+    const fn handler(op: Op) -> Handler<Self> {
+        match op {
+            Op::Unit => Self::op_unit,
+            Op::Const => Self::op_const,
+            Op::Load => Self::op_load,
+            Op::Store => Self::op_store,
+            Op::Pop => Self::op_pop,
+            Op::LoadStatic => Self::op_load_static,
+            Op::AddrStatic => Self::op_addr_static,
+            Op::AddrLocal => Self::op_addr_local,
+            Op::Field => Self::op_field,
+            Op::FieldNamed => Self::op_field_named,
+            Op::Index => Self::op_index,
+            Op::Subslice => Self::op_subslice,
+            Op::Deref => Self::op_deref,
+            Op::LoadRef => Self::op_load_ref,
+            Op::StoreRef => Self::op_store_ref,
+            Op::Add => Self::op_add,
+            Op::Sub => Self::op_sub,
+            Op::Mul => Self::op_mul,
+            Op::Div => Self::op_div,
+            Op::Rem => Self::op_rem,
+            Op::BitAnd => Self::op_bit_and,
+            Op::BitOr => Self::op_bit_or,
+            Op::BitXor => Self::op_bit_xor,
+            Op::Shl => Self::op_shl,
+            Op::Shr => Self::op_shr,
+            Op::Eq => Self::op_eq,
+            Op::Ne => Self::op_ne,
+            Op::Lt => Self::op_lt,
+            Op::Gt => Self::op_gt,
+            Op::Le => Self::op_le,
+            Op::Ge => Self::op_ge,
+            Op::And => Self::op_and,
+            Op::Or => Self::op_or,
+            Op::Neg => Self::op_neg,
+            Op::Not => Self::op_not,
+            Op::Checked => Self::op_checked,
+            Op::NegAs => Self::op_neg_as,
+            Op::NotAs => Self::op_not_as,
+            Op::Cast => Self::op_cast,
+            Op::Tuple => Self::op_tuple,
+            Op::Array => Self::op_array,
+            Op::Struct => Self::op_struct,
+            Op::Variant => Self::op_variant,
+            Op::Range => Self::op_range,
+            Op::Closure => Self::op_closure,
+            Op::Len => Self::op_len,
+            Op::Discriminant => Self::op_discriminant,
+            Op::Jump => Self::op_jump,
+            Op::JumpIfFalse => Self::op_jump_if_false,
+            Op::Switch => Self::op_switch,
+            Op::Call => Self::op_call,
+            Op::Return => Self::op_return,
+            Op::AssertBounds => Self::op_assert_bounds,
+            Op::AssertContract => Self::op_assert_contract,
+            Op::Unreachable => Self::op_unreachable,
+            Op::Drop => Self::op_drop,
+        }
+    }
+
+    fn op_unit(&mut self) -> Step {
+        self.push(Value::Unit);
+        Ok(())
+    }
+
+    fn op_const(&mut self) -> Step {
+        let index = self.next_u32();
+        let value = self.constants[index as usize].clone();
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_load(&mut self) -> Step {
+        let slot = self.next_local();
+        let value = self.stack[slot].clone();
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_store(&mut self) -> Step {
+        let slot = self.next_local();
+        self.stack[slot] = self.pop();
+        Ok(())
+    }
+
+    fn op_pop(&mut self) -> Step {
+        self.pop();
+        Ok(())
+    }
+
+    fn op_load_static(&mut self) -> Step {
+        let index = self.next_u16();
+        self.init_static(index as usize)?;
+        self.push(self.stack[index as usize].clone());
+        Ok(())
+    }
+
+    fn op_addr_static(&mut self) -> Step {
+        let index = self.next_u16() as usize;
+        self.init_static(index)?;
+        self.push(Value::Ref(Pointer {
+            slot: index,
+            path: Vec::new(),
+            window: None,
+        }));
+        Ok(())
+    }
+
+    fn op_addr_local(&mut self) -> Step {
+        let slot = self.next_local();
+        self.push(Value::Ref(Pointer {
+            slot,
+            path: Vec::new(),
+            window: None,
+        }));
+        Ok(())
+    }
+
+    fn op_field(&mut self) -> Step {
+        let index = self.next_u16();
+        let pointer = self.pop_pointer()?;
+        let mut pointer = self.auto_deref(pointer)?;
+        pointer.path.push(index as u32);
+        self.push(Value::Ref(pointer));
+        Ok(())
+    }
+
+    fn op_field_named(&mut self) -> Step {
+        let index = self.next_u32();
+        let Value::Str(name) = &self.constants[index as usize] else {
+            return Err("invalid field name".into());
+        };
+        let name = Rc::clone(name);
+        let pointer = self.pop_pointer()?;
+        let mut pointer = self.auto_deref(pointer)?;
+        let index = match self.resolve(&pointer)? {
+            Value::Struct(id, _) => self.module.structs[*id as usize]
+                .members
+                .iter()
+                .position(|member| **member == *name),
+            Value::Range(_, _) => match &*name {
+                "start" => Some(0),
+                "end" => Some(1),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(index) = index else {
+            let target = self.resolve(&pointer)?.type_name();
+            return Err(format!("no field `{}` on {} value", name, target).into());
+        };
+        pointer.path.push(index as u32);
+        self.push(Value::Ref(pointer));
+        Ok(())
+    }
+
+    fn op_index(&mut self) -> Step {
+        let index = match self.pop() {
+            Value::Int(n) => n,
+            other => return Err(format!("cannot index with {} value", other.type_name()).into()),
+        };
+        let pointer = self.pop_pointer()?;
+        let mut pointer = self.auto_deref(pointer)?;
+        let Value::Array(elements) = self.resolve(&pointer)? else {
+            return Err("cannot index into a value that is not an array".into());
+        };
+        // 切片的下标换算为数组的下标
+        let (start, len) = match pointer.window.take() {
+            Some((start, len)) => (start, len as usize),
+            None => (0, elements.len()),
+        };
+        if index < 0 || index as usize >= len {
+            return Err(ops::index_out_of_bounds(len, index).into());
+        }
+        pointer.path.push(start + index as u32);
+        self.push(Value::Ref(pointer));
+        Ok(())
+    }
+
+    fn op_subslice(&mut self) -> Step {
+        let (end, start) = match (self.pop(), self.pop()) {
+            (Value::Int(end), Value::Int(start)) => (end, start),
+            _ => return Err("slice bounds must be integers".into()),
+        };
+        let pointer = self.pop_pointer()?;
+        let mut pointer = self.auto_deref(pointer)?;
+        if !matches!(self.resolve(&pointer)?, Value::Array(_)) {
+            return Err("cannot slice a value that is not an array".into());
+        }
+        let len = self.len_of(&pointer)?;
+        if let Some(message) = ops::slice_out_of_bounds(len, start, end) {
+            return Err(message.into());
+        }
+        let offset = pointer.window.map_or(0, |(offset, _)| offset);
+        pointer.window = Some((offset + start as u32, (end - start) as u32));
+        self.push(Value::Ref(pointer));
+        Ok(())
+    }
+
+    fn op_drop(&mut self) -> Step {
+        let pointer = self.pop_pointer()?;
+        release(self.resolve(&pointer)?);
+        Ok(())
+    }
+
+    fn op_deref(&mut self) -> Step {
+        let mut pointer = self.pop_pointer()?;
+        match self.resolve(&pointer)? {
+            Value::Ref(target) => {
+                let target = target.clone();
+                self.push(Value::Ref(target));
+                Ok(())
+            }
+            // 盒子和 Rc 中的值是它们的子值，指针向内走一步
+            Value::Boxed(_) | Value::Shared(_) => {
+                pointer.path.push(0);
+                self.push(Value::Ref(pointer));
+                Ok(())
+            }
+            other => Err(format!("cannot dereference {} value", other.type_name()).into()),
+        }
+    }
+
+    fn op_load_ref(&mut self) -> Step {
+        let pointer = self.pop_pointer()?;
+        let value = self.load(&pointer)?;
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_store_ref(&mut self) -> Step {
+        let value = self.pop();
+        let pointer = self.pop_pointer()?;
+        let target = self.resolve_mut(&pointer)?;
+        // 写入切片时替换数组中的同一段
+        match (pointer.window, target, value) {
+            (None, target, value) => *target = value,
+            (Some((start, len)), Value::Array(elements), Value::Array(values))
+                if values.len() == len as usize =>
+            {
+                let start = start as usize;
+                elements.splice(start..start + values.len(), values);
+            }
+            _ => return Err("invalid memory access".into()),
+        }
+        Ok(())
+    }
+
+    fn op_add(&mut self) -> Step {
+        self.binary(BinOp::Add)
+    }
+
+    fn op_sub(&mut self) -> Step {
+        self.binary(BinOp::Sub)
+    }
+
+    fn op_mul(&mut self) -> Step {
+        self.binary(BinOp::Mul)
+    }
+
+    fn op_div(&mut self) -> Step {
+        self.binary(BinOp::Div)
+    }
+
+    fn op_rem(&mut self) -> Step {
+        self.binary(BinOp::Mod)
+    }
+
+    fn op_bit_and(&mut self) -> Step {
+        self.binary(BinOp::BitwiseAnd)
+    }
+
+    fn op_bit_or(&mut self) -> Step {
+        self.binary(BinOp::BitwiseOr)
+    }
+
+    fn op_bit_xor(&mut self) -> Step {
+        self.binary(BinOp::BitwiseXor)
+    }
+
+    fn op_shl(&mut self) -> Step {
+        self.binary(BinOp::LeftShift)
+    }
+
+    fn op_shr(&mut self) -> Step {
+        self.binary(BinOp::RightShift)
+    }
+
+    fn op_eq(&mut self) -> Step {
+        self.binary(BinOp::Equal)
+    }
+
+    fn op_ne(&mut self) -> Step {
+        self.binary(BinOp::NotEqual)
+    }
+
+    fn op_lt(&mut self) -> Step {
+        self.binary(BinOp::Less)
+    }
+
+    fn op_gt(&mut self) -> Step {
+        self.binary(BinOp::Greater)
+    }
+
+    fn op_le(&mut self) -> Step {
+        self.binary(BinOp::LessEqual)
+    }
+
+    fn op_ge(&mut self) -> Step {
+        self.binary(BinOp::GreaterEqual)
+    }
+
+    fn op_and(&mut self) -> Step {
+        self.binary(BinOp::LogicalAnd)
+    }
+
+    fn op_or(&mut self) -> Step {
+        self.binary(BinOp::LogicalOr)
+    }
+
+    // 整数运算直接计算，其他标量交给解释器的实现；相等比较适用于所有值
+    fn binary(&mut self, op: BinOp) -> Step {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        let value = match (lhs, rhs) {
+            (Value::Int(a), Value::Int(b)) => match op {
+                BinOp::Equal => Value::Bool(a == b),
+                BinOp::NotEqual => Value::Bool(a != b),
+                BinOp::Less => Value::Bool(a < b),
+                BinOp::Greater => Value::Bool(a > b),
+                BinOp::LessEqual => Value::Bool(a <= b),
+                BinOp::GreaterEqual => Value::Bool(a >= b),
+                _ => Value::Int(ops::int_binary(&op, a, b)?),
+            },
+            (lhs, rhs) => {
+                let lhs = self.deref_value(lhs)?;
+                let rhs = self.deref_value(rhs)?;
+                match op {
+                    BinOp::Equal => Value::Bool(lhs == rhs),
+                    BinOp::NotEqual => Value::Bool(lhs != rhs),
+                    _ => match (lhs.to_interp(), rhs.to_interp()) {
+                        (Some(a), Some(b)) => Value::from_interp(ops::binary(&op, a, b)?),
+                        _ => {
+                            return Err(format!(
+                                "unsupported operation `{:?}` between {} and {}",
+                                op,
+                                lhs.type_name(),
+                                rhs.type_name()
+                            )
+                            .into())
+                        }
+                    },
+                }
+            }
+        };
+        // 字符串拼接可以让内存成倍增长，不等到下次检查
+        if let (Value::Str(text), Some(max)) = (&value, self.limits.max_memory) {
+            self.check_memory(text.len(), max)?;
+        }
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_neg(&mut self) -> Step {
+        self.unary(UnOp::Neg)
+    }
+
+    // 整数按位取反，bool 逻辑取反
+    fn op_not(&mut self) -> Step {
+        self.unary(UnOp::LogicalNot)
+    }
+
+    fn op_neg_as(&mut self) -> Step {
+        let ty = &CAST_TYPES[self.next_u8() as usize];
+        self.unary_as(UnOp::Neg, Some(ty))
+    }
+
+    fn op_not_as(&mut self) -> Step {
+        let ty = &CAST_TYPES[self.next_u8() as usize];
+        self.unary_as(UnOp::BitwiseNot, Some(ty))
+    }
+
+    fn unary(&mut self, op: UnOp) -> Step {
+        self.unary_as(op, None)
+    }
+
+    // 按整数类型 ty 的宽度运算，见 ops::unary_as
+    fn unary_as(&mut self, op: UnOp, ty: Option<&Type>) -> Step {
+        let operand = self.pop();
+        let operand = self.deref_value(operand)?;
+        let value = match operand.to_interp() {
+            Some(value) => Value::from_interp(ops::unary_as(&op, value, ty)?),
+            None => {
+                return Err(format!(
+                    "unsupported operation `{:?}` on {}",
+                    op,
+                    operand.type_name()
+                )
+                .into())
+            }
+        };
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_checked(&mut self) -> Step {
+        let op = &CHECKED_OPS[self.next_u8() as usize];
+        let ty = &CAST_TYPES[self.next_u8() as usize];
+        let rhs = self.pop();
+        let lhs = self.pop();
+        match (self.deref_value(lhs)?, self.deref_value(rhs)?) {
+            (Value::Int(a), Value::Int(b)) => {
+                let value = ops::int_binary_as(op, a, b, ty)?;
+                self.push(Value::Int(value));
+                Ok(())
+            }
+            (Value::Float(a), Value::Float(b)) => {
+                let value = ops::binary_as(
+                    op,
+                    interp::Value::Float(a),
+                    interp::Value::Float(b),
+                    Some(ty),
+                )?;
+                self.push(Value::from_interp(value));
+                Ok(())
+            }
+            (lhs, rhs) => Err(format!(
+                "unsupported operation `{:?}` between {} and {}",
+                op,
+                lhs.type_name(),
+                rhs.type_name()
+            )
+            .into()),
+        }
+    }
+
+    fn op_cast(&mut self) -> Step {
+        let ty = &CAST_TYPES[self.next_u8() as usize];
+        let operand = self.pop();
+        let operand = self.deref_value(operand)?;
+        let value = match operand.to_interp() {
+            Some(value) => Value::from_interp(ops::cast(value, ty)?),
+            None => {
+                return Err(format!("cannot cast {} value to `{}`", operand.type_name(), ty).into())
+            }
+        };
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_tuple(&mut self) -> Step {
+        let count = self.next_u16();
+        let elements = self.pop_n(count as usize);
+        self.push(Value::Tuple(elements));
+        Ok(())
+    }
+
+    fn op_array(&mut self) -> Step {
+        let count = self.next_u16();
+        let elements = self.pop_n(count as usize);
+        self.push(Value::Array(elements));
+        Ok(())
+    }
+
+    fn op_struct(&mut self) -> Step {
+        let id = self.next_u16();
+        let count = self.next_u16();
+        let fields = self.pop_n(count as usize);
+        self.push(Value::Struct(id as u32, fields));
+        Ok(())
+    }
+
+    fn op_variant(&mut self) -> Step {
+        let id = self.next_u16();
+        let variant = self.next_u16();
+        let count = self.next_u16();
+        let fields = self.pop_n(count as usize);
+        self.push(Value::Variant(id as u32, variant as u32, fields));
+        Ok(())
+    }
+
+    fn op_range(&mut self) -> Step {
+        let inclusive = self.next_u8() != 0;
+        let end = self.pop();
+        let start = self.pop();
+        self.push(Value::Range(Box::new([start, end]), inclusive));
+        Ok(())
+    }
+
+    fn op_closure(&mut self) -> Step {
+        let func = self.next_u32();
+        let count = self.next_u16();
+        let captures = self.pop_n(count as usize);
+        self.push(Value::Closure(func, captures));
+        Ok(())
+    }
+
+    fn op_len(&mut self) -> Step {
+        let pointer = self.pop_pointer()?;
+        let pointer = self.auto_deref(pointer)?;
+        let len = self.len_of(&pointer)?;
+        self.push(Value::Int(len as i64));
+        Ok(())
+    }
+
+    fn op_discriminant(&mut self) -> Step {
+        let pointer = self.pop_pointer()?;
+        let pointer = self.auto_deref(pointer)?;
+        let variant = match self.resolve(&pointer)? {
+            Value::Variant(_, variant, _) => *variant,
+            other => {
+                return Err(
+                    format!("cannot match {} value against variants", other.type_name()).into(),
+                )
+            }
+        };
+        self.push(Value::Int(variant as i64));
+        Ok(())
+    }
+
+    fn op_jump(&mut self) -> Step {
+        self.pc = self.next_u32() as usize;
+        Ok(())
+    }
+
+    fn op_jump_if_false(&mut self) -> Step {
+        let target = self.next_u32();
+        match self.pop() {
+            Value::Bool(true) => {}
+            Value::Bool(false) => self.pc = target as usize,
+            other => {
+                return Err(
+                    format!("expected a bool condition, found {}", other.type_name()).into(),
+                )
+            }
+        }
+        Ok(())
+    }
+
+    fn op_switch(&mut self) -> Step {
+        let count = self.next_u16() as usize;
+        let discr = match self.pop() {
+            Value::Int(n) => n,
+            Value::Bool(b) => b as i64,
+            Value::Char(c) => c as i64,
+            other => return Err(format!("cannot switch on {} value", other.type_name()).into()),
+        };
+        // 分支按取值从小到大排列（解码时检查），二分查找
+        let arms = self.pc;
+        let (mut low, mut high) = (0, count);
+        let mut target = read_u32(self.code, arms + count * 12);
+        while low < high {
+            let mid = (low + high) / 2;
+            let arm = arms + mid * 12;
+            match read_i64(self.code, arm).cmp(&discr) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    target = read_u32(self.code, arm + 8);
+                    break;
+                }
+            }
+        }
+        self.pc = target as usize;
+        Ok(())
+    }
+
+    fn op_call(&mut self) -> Step {
+        let mut argc = self.next_u8() as usize;
+        let position = self.stack.len() - argc - 1;
+        let callee = self.deref_value(self.stack[position].clone())?;
+        let func = match callee {
+            Value::Function(func) => func,
+            Value::Closure(func, captures) => {
+                // 捕获的变量作为最前面的参数
+                argc += captures.len();
+                self.stack.splice(position + 1..position + 1, captures);
+                func
+            }
+            Value::Builtin(builtin) => {
+                let args = self.pop_n(argc);
+                self.pop();
+                let value = self.builtin(builtin, args)?;
+                self.push(value);
+                return Ok(());
+            }
+            other => return Err(format!("cannot call {} value", other.type_name()).into()),
+        };
+        self.save_frame();
+        self.enter(func, position, argc).inspect_err(|_| {
+            self.restore_frame();
+        })
+    }
+
+    fn op_return(&mut self) -> Step {
+        let value = std::mem::replace(&mut self.stack[self.base], Value::Unit);
+        self.stack.truncate(self.base);
+        self.depth -= 1;
+        if self.frames.len() == self.stop_depth {
+            return Err(Exit::Halt(value));
+        }
+        self.restore_frame();
+        self.push(value);
+        Ok(())
+    }
+
+    fn op_assert_bounds(&mut self) -> Step {
+        let index = self.pop();
+        let len = self.pop();
+        if self.pop() == Value::Bool(true) {
+            return Ok(());
+        }
+        match (len, index) {
+            (Value::Int(len), Value::Int(index)) => {
+                Err(ops::index_out_of_bounds(len as usize, index).into())
+            }
+            _ => Err("bounds check on a non-integer index".into()),
+        }
+    }
+
+    fn op_assert_contract(&mut self) -> Step {
+        let at_caller = self.next_u8() != 0;
+        let message = self.next_u32();
+        let note = self.next_u32();
+        if self.pop() == Value::Bool(true) {
+            return Ok(());
+        }
+        let text = |index: u32| match &self.module.constants[index as usize] {
+            Constant::Str(text) => text.clone(),
+            _ => String::new(),
+        };
+        // 调用者的栈帧保存的 pc 在 Call 指令之后；由外部调用时没有调用处
+        let span = match self.frames.last() {
+            Some(caller) if at_caller && self.frames.len() > self.stop_depth => {
+                self.module.functions[caller.func as usize].span_at(caller.pc - 1)
+            }
+            _ => self.current_span(),
+        };
+        let diagnostic = Diagnostic::error(text(message), span)
+            .with_code(ErrorCode::E0901)
+            .with_note(text(note));
+        Err(Exit::Fault(Box::new(diagnostic)))
+    }
+
+    fn op_unreachable(&mut self) -> Step {
+        Err("entered unreachable code".into())
+    }
+
+    // ---------------- 内建函数 ----------------
+
+    fn builtin(&mut self, builtin: Builtin, args: Vec<Value>) -> Result<Value, Exit> {
+        match builtin {
+            Builtin::Print => {
+                for value in &args {
+                    let text = self.display(value);
+                    if writeln!(self.output, "{}", text).is_err() {
+                        return Err("failed to write program output".into());
+                    }
+                }
+                Ok(Value::Unit)
+            }
+            Builtin::Len => {
+                let [value] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `len`")?;
+                match self.deref_value(value)? {
+                    Value::Array(elements) => Ok(Value::Int(elements.len() as i64)),
+                    Value::Str(text) => Ok(Value::Int(text.len() as i64)),
+                    Value::Map(table) => Ok(Value::Int(table.len() as i64)),
+                    other => Err(format!("cannot take the length of {}", other.type_name()).into()),
+                }
+            }
+            Builtin::Assert => {
+                let mut args = args.into_iter();
+                let (Some(condition), message, None) = (args.next(), args.next(), args.next())
+                else {
+                    return Err("wrong number of arguments to builtin `assert`".into());
+                };
+                match (self.deref_value(condition)?, message) {
+                    (Value::Bool(true), _) => Ok(Value::Unit),
+                    (Value::Bool(false), None) => Err("assertion failed".into()),
+                    (Value::Bool(false), Some(message)) => {
+                        let message = self.deref_value(message)?;
+                        Err(format!("assertion failed: {}", self.display(&message)).into())
+                    }
+                    (other, _) => Err(format!(
+                        "assertion condition must be bool, found {}",
+                        other.type_name()
+                    )
+                    .into()),
+                }
+            }
+            Builtin::Panic => match <[Value; 1]>::try_from(args) {
+                Ok([message]) => {
+                    let message = self.deref_value(message)?;
+                    Err(format!("panicked: {}", self.display(&message)).into())
+                }
+                Err(args) if args.is_empty() => Err("explicit panic".into()),
+                Err(_) => Err("wrong number of arguments to builtin `panic`".into()),
+            },
+            Builtin::BoxNew => match <[Value; 1]>::try_from(args) {
+                Ok([value]) => Ok(Value::Boxed(Box::new(value))),
+                Err(_) => Err("wrong number of arguments to builtin `Box::new`".into()),
+            },
+            Builtin::RcNew => match <[Value; 1]>::try_from(args) {
+                Ok([value]) => Ok(Value::Shared(Rc::new(Shared {
+                    strong: Cell::new(1),
+                    value,
+                }))),
+                Err(_) => Err("wrong number of arguments to builtin `Rc::new`".into()),
+            },
+            Builtin::RcClone | Builtin::RcStrongCount => {
+                let [value] = <[Value; 1]>::try_from(args).map_err(|_| {
+                    format!("wrong number of arguments to builtin `{}`", builtin.name())
+                })?;
+                let Value::Shared(shared) = self.source(&value)? else {
+                    return Err(format!("builtin `{}` expects an `Rc`", builtin.name()).into());
+                };
+                if builtin == Builtin::RcStrongCount {
+                    return Ok(Value::Int(shared.strong.get() as i64));
+                }
+                shared.strong.set(shared.strong.get() + 1);
+                Ok(Value::Shared(Rc::clone(shared)))
+            }
+            Builtin::FmtArg => {
+                let [value, spec] = <[Value; 2]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `fmt::arg`")?;
+                let spec: fmt::Spec = match self.deref_value(spec)? {
+                    Value::Str(spec) => spec.parse()?,
+                    other => {
+                        return Err(
+                            format!("expected a format spec, found {}", other.type_name()).into(),
+                        )
+                    }
+                };
+                // 引用、盒子和 Rc 按其中的值格式化
+                let mut value = value;
+                let value = loop {
+                    value = match value {
+                        Value::Ref(_) => self.deref_value(value)?,
+                        Value::Boxed(inner) => *inner,
+                        Value::Shared(shared) => shared.value.clone(),
+                        value => break value,
+                    };
+                };
+                let arg = match value {
+                    Value::Int(n) => fmt::Arg::Int(n),
+                    Value::Float(x) => fmt::Arg::Float(x),
+                    value => {
+                        let mut text = String::new();
+                        self.write_value(&mut text, &value, spec.kind == fmt::Kind::Debug);
+                        fmt::Arg::Text(text)
+                    }
+                };
+                Ok(Value::Str(fmt::format(&spec, arg)?.into()))
+            }
+            Builtin::VecNew => Ok(Value::Array(Vec::new())),
+            Builtin::Push => {
+                let [target, value] = <[Value; 2]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `push`")?;
+                let value = self.deref_value(value)?;
+                match self.target(target)? {
+                    Value::Array(elements) => {
+                        elements.push(value);
+                        Ok(Value::Unit)
+                    }
+                    other => Err(format!("cannot push to {} value", other.type_name()).into()),
+                }
+            }
+            Builtin::Remove => {
+                let [target, index] = <[Value; 2]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `remove`")?;
+                let index = self.deref_value(index)?;
+                match (self.target(target)?, index) {
+                    (Value::Array(elements), Value::Int(index)) => {
+                        if index < 0 || index as usize >= elements.len() {
+                            return Err(ops::index_out_of_bounds(elements.len(), index).into());
+                        }
+                        Ok(elements.remove(index as usize))
+                    }
+                    (Value::Array(_), other) => Err(format!(
+                        "array index must be an integer, found {}",
+                        other.type_name()
+                    )
+                    .into()),
+                    (Value::Map(table), key) => table
+                        .remove(&key.to_key()?)
+                        .ok_or_else(|| "key not found in map".into()),
+                    (other, _) => {
+                        Err(format!("cannot remove from {} value", other.type_name()).into())
+                    }
+                }
+            }
+            Builtin::StringBuilderNew => Ok(Value::Str("".into())),
+            Builtin::Append => {
+                let [target, value] = <[Value; 2]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `append`")?;
+                let text = self.display(&value);
+                let max = self.limits.max_memory;
+                let buffer = match self.target(target)? {
+                    Value::Str(buffer) => buffer,
+                    other => {
+                        return Err(format!("cannot append to {} value", other.type_name()).into())
+                    }
+                };
+                // 和字符串拼接一样，反复追加自身可以让内存成倍增长，追加之前检查
+                let len = buffer.len() + text.len();
+                if max.is_none_or(|max| len <= max) {
+                    // 字符串不可变，追加时复制一份
+                    *buffer = format!("{}{}", buffer, text).into();
+                }
+                if let Some(max) = max {
+                    self.check_memory(len, max)?;
+                }
+                Ok(Value::Unit)
+            }
+            Builtin::MapNew => Ok(Value::Map(Table::new())),
+            Builtin::Insert => {
+                let [target, key, value] = <[Value; 3]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `insert`")?;
+                let key = self.deref_value(key)?.to_key()?;
+                let value = self.deref_value(value)?;
+                match self.target(target)? {
+                    Value::Map(table) => {
+                        table.insert(key, value);
+                        Ok(Value::Unit)
+                    }
+                    other => Err(format!("cannot insert into {} value", other.type_name()).into()),
+                }
+            }
+            Builtin::Get | Builtin::ContainsKey => {
+                let [map, key] = <[Value; 2]>::try_from(args).map_err(|_| {
+                    format!("wrong number of arguments to builtin `{}`", builtin.name())
+                })?;
+                let key = self.deref_value(key)?.to_key()?;
+                match (self.source(&map)?, builtin) {
+                    (Value::Map(table), Builtin::Get) => table
+                        .get(&key)
+                        .cloned()
+                        .ok_or_else(|| "key not found in map".into()),
+                    (Value::Map(table), _) => Ok(Value::Bool(table.contains_key(&key))),
+                    (other, _) => {
+                        Err(format!("expected a map, found {}", other.type_name()).into())
+                    }
+                }
+            }
+            Builtin::Entries => {
+                let [map] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `entries`")?;
+                match self.source(&map)? {
+                    Value::Map(table) => Ok(Value::Array(
+                        table
+                            .iter()
+                            .map(|(key, value)| {
+                                Value::Tuple(vec![Value::from_key(key), value.clone()])
+                            })
+                            .collect(),
+                    )),
+                    other => Err(format!("expected a map, found {}", other.type_name()).into()),
+                }
+            }
+            Builtin::ToString => {
+                let [value] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `to_string`")?;
+                let text = self.display(&value);
+                if let Some(max) = self.limits.max_memory {
+                    self.check_memory(text.len(), max)?;
+                }
+                Ok(Value::Str(text.into()))
+            }
+            Builtin::Abs => {
+                let [value] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `abs`")?;
+                let value = self.deref_value(value)?;
+                match value.to_interp() {
+                    Some(value) => Ok(Value::from_interp(ops::abs(value, None)?)),
+                    None => Err(format!("expected an integer, found {}", value.type_name()).into()),
+                }
+            }
+            Builtin::Substring
+            | Builtin::Split
+            | Builtin::Contains
+            | Builtin::Find
+            | Builtin::IsInt
+            | Builtin::ToInt
+            | Builtin::Chars => self.string_builtin(builtin, args),
+            Builtin::ReadFile
+            | Builtin::WriteFile
+            | Builtin::Args
+            | Builtin::Exit
+            | Builtin::EnvGet
+            | Builtin::EnvHas
+            | Builtin::NowMillis
+            | Builtin::Sleep => self.host_builtin(builtin, args),
+            Builtin::Memcpy => {
+                let [target, source] = <[Value; 2]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `memcpy`")?;
+                let Value::Array(elements) = self.deref_value(source)? else {
+                    return Err("expected a slice to copy from".into());
+                };
+                let Value::Ref(pointer) = target else {
+                    return Err("expected a mutable reference".into());
+                };
+                let pointer = self.follow(pointer)?;
+                let (start, len) = match pointer.window {
+                    Some((start, len)) => (start as usize, len as usize),
+                    None => (0, self.len_of(&pointer)?),
+                };
+                if len != elements.len() {
+                    return Err(format!(
+                        "`memcpy` between slices of different lengths ({} and {})",
+                        len,
+                        elements.len()
+                    )
+                    .into());
+                }
+                match self.resolve_mut(&pointer)? {
+                    Value::Array(target) => {
+                        target[start..start + len].clone_from_slice(&elements);
+                        Ok(Value::Unit)
+                    }
+                    other => Err(format!("cannot copy into {} value", other.type_name()).into()),
+                }
+            }
+            Builtin::Ctpop | Builtin::Ctlz | Builtin::Cttz => {
+                let [value] = <[Value; 1]>::try_from(args).map_err(|_| {
+                    format!("wrong number of arguments to builtin `{}`", builtin.name())
+                })?;
+                let Value::Int(n) = self.deref_value(value)? else {
+                    return Err("expected an integer".into());
+                };
+                // u64 按位保存在 i64 中
+                let bits = n as u64;
+                let count = match builtin {
+                    Builtin::Ctpop => bits.count_ones(),
+                    Builtin::Ctlz => bits.leading_zeros(),
+                    _ => bits.trailing_zeros(),
+                };
+                Ok(Value::Int(count as i64))
+            }
+            Builtin::Sqrt => {
+                let [value] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `sqrt`")?;
+                match self.deref_value(value)? {
+                    Value::Float(x) => Ok(Value::Float(x.sqrt())),
+                    other => Err(format!("expected a float, found {}", other.type_name()).into()),
+                }
+            }
+            Builtin::CatchPanic => {
+                let [callee] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| "wrong number of arguments to builtin `catch_panic`")?;
+                let (func, captures) = match self.deref_value(callee)? {
+                    Value::Function(func) => (func, Vec::new()),
+                    Value::Closure(func, captures) => (func, captures),
+                    other => return Err(format!("cannot call {} value", other.type_name()).into()),
+                };
+                let (frames, depth, stack, op_pc) =
+                    (self.frames.len(), self.depth, self.stack.len(), self.op_pc);
+                let message = match self.invoke(func, captures) {
+                    Ok(value) => {
+                        return Ok(Value::Tuple(vec![
+                            Value::Bool(true),
+                            value,
+                            Value::Str("".into()),
+                        ]))
+                    }
+                    // 超出资源限制和 `io::exit` 不是 panic，不能被捕获
+                    Err(Exit::Fault(diagnostic)) if self.exceeded.is_none() => diagnostic.message,
+                    Err(Exit::Error(message)) if self.exceeded.is_none() => message.to_string(),
+                    Err(exit) => return Err(exit),
+                };
+                // panic 时 invoke 没有恢复栈帧，回到调用 catch_panic 时的状态
+                self.frames.truncate(frames + 1);
+                self.restore_frame();
+                self.depth = depth;
+                self.stack.truncate(stack);
+                self.op_pc = op_pc;
+                Ok(Value::Tuple(vec![
+                    Value::Bool(false),
+                    Value::Unit,
+                    Value::Str(message.into()),
+                ]))
+            }
+        }
+    }
+
+    // `io`、`env` 和 `time` 模块；文件操作返回 `(是否成功, 值, 错误信息)`，在 MIR 中展开为 Result
+    fn host_builtin(&mut self, builtin: Builtin, args: Vec<Value>) -> Result<Value, Exit> {
+        self.host.require(builtin.name())?;
+        let values = args
+            .into_iter()
+            .map(|value| self.deref_value(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outcome = |result: Result<Value, String>, default: Value| match result {
+            Ok(value) => Value::Tuple(vec![Value::Bool(true), value, Value::Str("".into())]),
+            Err(message) => Value::Tuple(vec![
+                Value::Bool(false),
+                default,
+                Value::Str(message.into()),
+            ]),
+        };
+        match (builtin, values.as_slice()) {
+            (Builtin::ReadFile, [Value::Str(path)]) => Ok(outcome(
+                interp::read_file(path).map(|text| Value::Str(text.into())),
+                Value::Str("".into()),
+            )),
+            (Builtin::WriteFile, [Value::Str(path), Value::Str(contents)]) => Ok(outcome(
+                interp::write_file(path, contents).map(|()| Value::Unit),
+                Value::Unit,
+            )),
+            (Builtin::Args, []) => Ok(Value::Array(
+                self.host
+                    .args
+                    .iter()
+                    .map(|arg| Value::Str(arg.as_str().into()))
+                    .collect(),
+            )),
+            (Builtin::Exit, [Value::Int(code)]) => {
+                self.exit_code = Some(*code as i32);
+                Err(Exit::Exited)
+            }
+            (Builtin::EnvGet, [Value::Str(name)]) => match interp::env_var(name) {
+                Some(value) => Ok(Value::Str(value.into())),
+                None => Err(format!("environment variable `{}` is not set", name).into()),
+            },
+            (Builtin::EnvHas, [Value::Str(name)]) => {
+                Ok(Value::Bool(interp::env_var(name).is_some()))
+            }
+            (Builtin::NowMillis, []) => Ok(Value::Int(interp::now_millis())),
+            (Builtin::Sleep, [Value::Int(millis)]) => {
+                interp::sleep(*millis)?;
+                Ok(Value::Unit)
+            }
+            _ => Err(format!("invalid arguments to builtin `{}`", builtin.name()).into()),
+        }
+    }
+
+    // 字符串的内建函数，第一个参数是字符串
+    fn string_builtin(&self, builtin: Builtin, args: Vec<Value>) -> Result<Value, Exit> {
+        let values = args
+            .into_iter()
+            .map(|value| self.deref_value(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let text = match values.first() {
+            Some(Value::Str(text)) => text,
+            Some(other) => {
+                return Err(format!("expected a string, found {}", other.type_name()).into())
+            }
+            None => {
+                return Err(
+                    format!("wrong number of arguments to builtin `{}`", builtin.name()).into(),
+                )
+            }
+        };
+        let value = match (builtin, &values[1..]) {
+            (Builtin::Substring, [Value::Int(start), Value::Int(end)]) => {
+                Value::Str(ops::substring(text, *start, *end)?.into())
+            }
+            (Builtin::Split, [Value::Str(separator)]) => Value::Array(
+                ops::split(text, separator)?
+                    .into_iter()
+                    .map(|part| Value::Str(part.into()))
+                    .collect(),
+            ),
+            (Builtin::Contains, [Value::Str(pattern)]) => {
+                Value::Bool(text.contains(pattern.as_ref()))
+            }
+            (Builtin::Find, [Value::Str(pattern)]) => match text.find(pattern.as_ref()) {
+                Some(index) => Value::Int(index as i64),
+                None => return Err("pattern not found in string".into()),
+            },
+            (Builtin::IsInt, []) => Value::Bool(ops::parse_int(text).is_some()),
+            (Builtin::ToInt, []) => match ops::parse_int(text) {
+                Some(n) => Value::Int(n),
+                None => return Err("string is not a valid integer".into()),
+            },
+            (Builtin::Chars, []) => Value::Array(text.chars().map(Value::Char).collect()),
+            _ => return Err(format!("invalid arguments to builtin `{}`", builtin.name()).into()),
+        };
+        Ok(value)
+    }
+
+    // 读取参数的值；参数是引用时直接读取它指向的值，不复制整个集合
+    fn source<'v>(&'v self, value: &'v Value) -> Result<&'v Value, Exit> {
+        match value {
+            Value::Ref(pointer) => self.resolve(&self.follow(pointer.clone())?),
+            value => Ok(value),
+        }
+    }
+
+    // 修改第一个参数（引用）指向的值的内建函数
+    fn target(&mut self, target: Value) -> Result<&mut Value, Exit> {
+        match target {
+            Value::Ref(pointer) => {
+                let pointer = self.follow(pointer)?;
+                if pointer.window.is_some() {
+                    return Err("cannot change the length of a slice".into());
+                }
+                self.resolve_mut(&pointer)
+            }
+            other => {
+                Err(format!("expected a mutable reference, found {}", other.type_name()).into())
+            }
+        }
+    }
+
+    // 复合值内部的字符串和字符加引号，与顶层输出区分
+    fn write_value(&self, out: &mut String, value: &Value, nested: bool) {
+        match value {
+            Value::Unit => out.push_str("()"),
+            Value::Bool(b) => out.push_str(&b.to_string()),
+            Value::Int(n) => out.push_str(&n.to_string()),
+            Value::Float(x) => out.push_str(&x.to_string()),
+            Value::Char(c) if nested => out.push_str(&format!("{:?}", c)),
+            Value::Char(c) => out.push(*c),
+            Value::Str(text) if nested => out.push_str(&format!("{:?}", text)),
+            Value::Str(text) => out.push_str(text),
+            // 盒子按其中的值输出
+            Value::Boxed(inner) => self.write_value(out, inner, nested),
+            Value::Shared(shared) => self.write_value(out, &shared.value, nested),
+            Value::Tuple(elements) => {
+                out.push('(');
+                self.write_list(out, elements);
+                if elements.len() == 1 {
+                    out.push(',');
+                }
+                out.push(')');
+            }
+            Value::Array(elements) => {
+                out.push('[');
+                self.write_list(out, elements);
+                out.push(']');
+            }
+            Value::Map(table) => {
+                out.push('{');
+                for (i, (key, value)) in table.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.write_value(out, &Value::from_key(key), true);
+                    out.push_str(": ");
+                    self.write_value(out, value, true);
+                }
+                out.push('}');
+            }
+            Value::Struct(id, fields) => {
+                let def = &self.module.structs[*id as usize];
+                out.push_str(&def.name);
+                out.push_str(" { ");
+                for (i, (name, value)) in def.members.iter().zip(fields).enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(name);
+                    out.push_str(": ");
+                    self.write_value(out, value, true);
+                }
+                out.push_str(" }");
+            }
+            Value::Variant(id, variant, fields) => {
+                let def = &self.module.enums[*id as usize];
+                out.push_str(
+                    def.members
+                        .get(*variant as usize)
+                        .map_or("?", String::as_str),
+                );
+                if !fields.is_empty() {
+                    out.push('(');
+                    self.write_list(out, fields);
+                    out.push(')');
+                }
+            }
+            // 与解释器一样按左闭右开输出
+            Value::Range(bounds, inclusive) => match (&bounds[0], &bounds[1]) {
+                (Value::Int(start), Value::Int(end)) => {
+                    let end = if *inclusive { end + 1 } else { *end };
+                    out.push_str(&format!("{}..{}", start, end));
+                }
+                (start, end) => {
+                    self.write_value(out, start, true);
+                    out.push_str(if *inclusive { "..=" } else { ".." });
+                    self.write_value(out, end, true);
+                }
+            },
+            Value::Function(func) => {
+                out.push_str("fn ");
+                out.push_str(&self.module.functions[*func as usize].name);
+            }
+            Value::Builtin(builtin) => {
+                out.push_str("fn ");
+                out.push_str(builtin.name());
+            }
+            Value::Closure(_, _) => out.push_str("<closure>"),
+            Value::Ref(pointer) => match self.follow(pointer.clone()) {
+                Ok(pointer) => match self.load(&pointer) {
+                    Ok(target) => self.write_value(out, &target, nested),
+                    Err(_) => out.push_str("<dangling>"),
+                },
+                Err(_) => out.push_str("<dangling>"),
+            },
+        }
+    }
+
+    fn write_list(&self, out: &mut String, values: &[Value]) {
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.write_value(out, value, true);
+        }
+    }
+}
+
+// 析构值：其中每个 `Rc` 的计数减一，减到零时继续析构 `Rc` 中的值；引用指向的值不属于它
+fn release(value: &Value) {
+    match value {
+        Value::Shared(shared) => {
+            let strong = shared.strong.get().saturating_sub(1);
+            shared.strong.set(strong);
+            if strong == 0 {
+                release(&shared.value);
+            }
+        }
+        Value::Tuple(values)
+        | Value::Array(values)
+        | Value::Struct(_, values)
+        | Value::Variant(_, _, values)
+        | Value::Closure(_, values) => values.iter().for_each(release),
+        Value::Boxed(inner) => release(inner),
+        Value::Map(table) => table.iter().for_each(|(_, value)| release(value)),
+        _ => {}
+    }
+}
+
+// 值本身和它拥有的堆内存
+fn heap_size(value: &Value) -> usize {
+    let owned = match value {
+        // `StringBuilder` 也是字符串，按已经追加的长度计算
+        Value::Str(text) => text.len(),
+        Value::Tuple(values)
+        | Value::Array(values)
+        | Value::Struct(_, values)
+        | Value::Variant(_, _, values)
+        | Value::Closure(_, values) => values.iter().map(heap_size).sum(),
+        Value::Range(bounds, _) => bounds.iter().map(heap_size).sum(),
+        Value::Boxed(inner) => heap_size(inner),
+        Value::Shared(shared) => heap_size(&shared.value),
+        Value::Map(table) => table
+            .iter()
+            .map(|(key, value)| {
+                let text = match key {
+                    Key::Str(text) => text.len(),
+                    _ => 0,
+                };
+                mem::size_of::<Key>() + text + heap_size(value)
+            })
+            .sum(),
+        Value::Ref(pointer) => pointer.path.len() * mem::size_of::<u32>(),
+        _ => 0,
+    };
+    mem::size_of::<Value>() + owned
+}
+
+/// 在标准输出上运行模块的 `main` 函数
+pub fn run(module: &Module) -> Result<(), Vec<Diagnostic>> {
+    run_with_output(module, io::stdout()).0
+}
+
+/// 运行 `main`，返回结果和输出。虚拟机不在宿主栈上递归，不需要专门的大栈线程
+pub fn run_with_output<W: Write>(module: &Module, output: W) -> (Result<(), Vec<Diagnostic>>, W) {
+    let mut vm = Vm::new(module, output);
+    let result = vm.run_main().map(drop);
+    (result, vm.into_output())
+}
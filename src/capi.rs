@@ -0,0 +1,550 @@
+// C API（嵌入用）
+// 游戏引擎等宿主通过这些 `extern "C"` 函数把 Contractus 作为脚本或契约语言嵌入：
+// 创建编译上下文，编译源码，查询诊断信息，在字节码虚拟机中调用编译好的函数。
+// 声明在 include/contractus.h 中，宿主链接本库编译成的静态库。
+// - 上下文不能在多个线程中同时使用；返回的字符串属于上下文，在下一次调用上下文的函数前有效
+// - 每次调用在新的虚拟机中执行，静态变量在第一次使用时初始化
+// - 程序的输出默认写到标准输出，可以用回调交给宿主
+// - 程序默认不能使用 `io` 模块，宿主用 `contractus_set_io` 打开并给出 `io::args()` 的参数；
+//   `env` 和 `time` 模块同样默认关闭，分别用 `contractus_set_env`、`contractus_set_time` 打开
+// - panic 不会越过 FFI 边界，而是返回 CONTRACTUS_INTERNAL_ERROR
+
+use crate::bytecode::{self, Value, Vm};
+use crate::diagnostic::Diagnostic;
+use crate::driver::{Compiler, ContractMode, Emit, OptLevel};
+use crate::interp::{self, Host};
+use crate::span::Span;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+// 返回的状态
+pub const CONTRACTUS_OK: c_int = 0;
+pub const CONTRACTUS_COMPILE_ERROR: c_int = 1;
+pub const CONTRACTUS_RUNTIME_ERROR: c_int = 2;
+pub const CONTRACTUS_INVALID_ARGUMENT: c_int = 3;
+pub const CONTRACTUS_NOT_COMPILED: c_int = 4;
+pub const CONTRACTUS_INTERNAL_ERROR: c_int = 5;
+
+// 值的种类
+pub const CONTRACTUS_UNIT: c_int = 0;
+pub const CONTRACTUS_BOOL: c_int = 1;
+pub const CONTRACTUS_INT: c_int = 2;
+pub const CONTRACTUS_FLOAT: c_int = 3;
+pub const CONTRACTUS_CHAR: c_int = 4;
+pub const CONTRACTUS_STRING: c_int = 5;
+pub const CONTRACTUS_OTHER: c_int = 6; // 复合值，只能作为返回值，string_value 是它的输出形式
+
+/// 传给函数的实参和函数的返回值
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ContractusValue {
+    pub kind: c_int,
+    pub int_value: i64, // bool 为 0 或 1，char 为码点
+    pub float_value: f64,
+    pub string_value: *const c_char, // NUL 结尾的 UTF-8
+}
+
+impl ContractusValue {
+    const UNIT: ContractusValue = ContractusValue {
+        kind: CONTRACTUS_UNIT,
+        int_value: 0,
+        float_value: 0.0,
+        string_value: ptr::null(),
+    };
+}
+
+/// 程序输出的回调：`text` 是 `len` 个字节的 UTF-8，不以 NUL 结尾
+pub type ContractusOutput =
+    Option<extern "C" fn(user_data: *mut c_void, text: *const c_char, len: usize)>;
+
+pub struct ContractusContext {
+    opt_level: OptLevel,
+    contracts: ContractMode,
+    module: Option<bytecode::Module>,
+    diagnostics: Vec<CDiagnostic>,
+    strings: Vec<CString>, // 返回给宿主的字符串
+    output: ContractusOutput,
+    user_data: *mut c_void,
+    host: Host,
+}
+
+struct CDiagnostic {
+    diagnostic: Diagnostic,
+    message: CString, // 与命令行相同的格式
+    code: Option<CString>,
+}
+
+impl ContractusContext {
+    fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| CDiagnostic {
+                message: c_string(diagnostic.to_string()),
+                code: diagnostic
+                    .code
+                    .map(|code| c_string(code.as_str().to_string())),
+                diagnostic,
+            })
+            .collect();
+    }
+
+    fn error(&mut self, message: String) -> c_int {
+        self.set_diagnostics(vec![Diagnostic::error(message, Span::new(0, 0, 1, 1))]);
+        CONTRACTUS_INVALID_ARGUMENT
+    }
+
+    fn output(&self) -> Box<dyn Write> {
+        match self.output {
+            Some(callback) => Box::new(HostOutput {
+                callback,
+                user_data: self.user_data,
+            }),
+            None => Box::new(io::stdout()),
+        }
+    }
+}
+
+// 字符串中的 NUL 截断
+fn c_string(text: String) -> CString {
+    CString::new(text).unwrap_or_else(|error| {
+        let end = error.nul_position();
+        let mut bytes = error.into_vec();
+        bytes.truncate(end);
+        CString::new(bytes).unwrap_or_default()
+    })
+}
+
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(CONTRACTUS_INTERNAL_ERROR)
+}
+
+struct HostOutput {
+    callback: extern "C" fn(*mut c_void, *const c_char, usize),
+    user_data: *mut c_void,
+}
+
+impl Write for HostOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.callback)(self.user_data, buf.as_ptr().cast(), buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 库的版本，NUL 结尾的静态字符串
+#[no_mangle]
+pub extern "C" fn contractus_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// 创建上下文，用 `contractus_context_free` 释放
+#[no_mangle]
+pub extern "C" fn contractus_context_new() -> *mut ContractusContext {
+    Box::into_raw(Box::new(ContractusContext {
+        opt_level: OptLevel::default(),
+        contracts: ContractMode::default(),
+        module: None,
+        diagnostics: Vec::new(),
+        strings: Vec::new(),
+        output: None,
+        user_data: ptr::null_mut(),
+        host: Host::default(),
+    }))
+}
+
+/// 释放上下文，`ctx` 可以为空
+///
+/// # Safety
+///
+/// `ctx` 必须来自 `contractus_context_new`，且没有被释放过
+#[no_mangle]
+pub unsafe extern "C" fn contractus_context_free(ctx: *mut ContractusContext) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+/// 设置编译选项：`opt-level`（`0`、`1`、`2`、`s`）或 `contracts`（`check`、`off`），
+/// 在下一次编译时生效
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文，`name` 和 `value` 必须是 NUL 结尾的字符串
+#[no_mangle]
+pub unsafe extern "C" fn contractus_set_option(
+    ctx: *mut ContractusContext,
+    name: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    if name.is_null() || value.is_null() {
+        return ctx.error("option name and value must not be null".to_string());
+    }
+    let name = CStr::from_ptr(name).to_string_lossy();
+    let value = CStr::from_ptr(value).to_string_lossy();
+    let result = match name.as_ref() {
+        "opt-level" => value.parse().map(|level| ctx.opt_level = level),
+        "contracts" => value.parse().map(|mode| ctx.contracts = mode),
+        _ => Err(format!("unknown option `{}`", name)),
+    };
+    match result {
+        Ok(()) => CONTRACTUS_OK,
+        Err(message) => ctx.error(message),
+    }
+}
+
+/// 设置程序输出的回调，`callback` 为空时输出到标准输出
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文；调用函数时 `callback` 会收到 `user_data`
+#[no_mangle]
+pub unsafe extern "C" fn contractus_set_output(
+    ctx: *mut ContractusContext,
+    callback: ContractusOutput,
+    user_data: *mut c_void,
+) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.output = callback;
+        ctx.user_data = user_data;
+    }
+}
+
+/// 允许（`enabled` 非零）或禁止之后的调用使用 `io` 模块；`argv` 是 `argc` 个 NUL 结尾的字符串，
+/// 作为 `io::args()` 的结果，`argc` 为 0 时可以为空
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文，`argv` 必须指向 `argc` 个有效的字符串指针
+#[no_mangle]
+pub unsafe extern "C" fn contractus_set_io(
+    ctx: *mut ContractusContext,
+    enabled: c_int,
+    argv: *const *const c_char,
+    argc: usize,
+) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    let pointers = match (argv.is_null(), argc) {
+        (_, 0) => &[][..],
+        (true, _) => return ctx.error("`argv` must not be null".to_string()),
+        (false, _) => slice::from_raw_parts(argv, argc),
+    };
+    let mut args = Vec::new();
+    for &pointer in pointers {
+        if pointer.is_null() {
+            return ctx.error("program arguments must not be null".to_string());
+        }
+        args.push(CStr::from_ptr(pointer).to_string_lossy().into_owned());
+    }
+    ctx.host.io = enabled != 0;
+    ctx.host.args = args;
+    CONTRACTUS_OK
+}
+
+/// 允许（`enabled` 非零）或禁止之后的调用用 `env` 模块读取环境变量
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_set_env(ctx: *mut ContractusContext, enabled: c_int) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    ctx.host.env = enabled != 0;
+    CONTRACTUS_OK
+}
+
+/// 允许（`enabled` 非零）或禁止之后的调用用 `time` 模块读取时钟和休眠
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_set_time(ctx: *mut ContractusContext, enabled: c_int) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    ctx.host.time = enabled != 0;
+    CONTRACTUS_OK
+}
+
+/// 编译 `len` 个字节的 UTF-8 源码，替换之前编译的程序。
+/// 返回 CONTRACTUS_OK 或 CONTRACTUS_COMPILE_ERROR，诊断信息（包括警告）用
+/// `contractus_diagnostic_*` 查询
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文，`source` 必须指向 `len` 个可读的字节
+#[no_mangle]
+pub unsafe extern "C" fn contractus_compile(
+    ctx: *mut ContractusContext,
+    source: *const c_char,
+    len: usize,
+) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    ctx.module = None;
+    if source.is_null() && len > 0 {
+        return ctx.error("source must not be null".to_string());
+    }
+    let bytes = match len {
+        0 => &[][..],
+        _ => slice::from_raw_parts(source.cast::<u8>(), len),
+    };
+    let Ok(source) = std::str::from_utf8(bytes) else {
+        return ctx.error("source is not valid UTF-8".to_string());
+    };
+    let compiler = Compiler::new()
+        .source(source)
+        .opt_level(ctx.opt_level)
+        .contracts(ctx.contracts)
+        .emit(Emit::Object);
+    guard(|| {
+        // 语法分析是递归的，在大栈线程中编译
+        let output = interp::with_large_stack(|| compiler.run());
+        let failed = output.has_errors();
+        let module = output.artifact.and_then(|artifact| artifact.into_object());
+        ctx.set_diagnostics(output.diagnostics);
+        match module {
+            Some(module) if !failed => {
+                ctx.module = Some(module);
+                CONTRACTUS_OK
+            }
+            _ => CONTRACTUS_COMPILE_ERROR,
+        }
+    })
+}
+
+/// 上一次编译或调用的诊断信息条数
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_diagnostic_count(ctx: *const ContractusContext) -> usize {
+    ctx.as_ref().map_or(0, |ctx| ctx.diagnostics.len())
+}
+
+/// 第 `index` 条诊断信息，格式与命令行相同，如 `error[E0425] at line 2, column 11: ...`；
+/// 下标越界时返回空指针
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_diagnostic_message(
+    ctx: *const ContractusContext,
+    index: usize,
+) -> *const c_char {
+    diagnostic(ctx, index).map_or(ptr::null(), |d| d.message.as_ptr())
+}
+
+/// 第 `index` 条诊断信息的错误码（如 `E0425`），没有错误码时返回空指针
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_diagnostic_code(
+    ctx: *const ContractusContext,
+    index: usize,
+) -> *const c_char {
+    diagnostic(ctx, index)
+        .and_then(|d| d.code.as_ref())
+        .map_or(ptr::null(), |code| code.as_ptr())
+}
+
+/// 第 `index` 条诊断信息所在的行，从 1 开始；下标越界时返回 0
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_diagnostic_line(
+    ctx: *const ContractusContext,
+    index: usize,
+) -> u32 {
+    diagnostic(ctx, index).map_or(0, |d| d.diagnostic.span.line)
+}
+
+/// 第 `index` 条诊断信息所在的列，从 1 开始；下标越界时返回 0
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_diagnostic_column(
+    ctx: *const ContractusContext,
+    index: usize,
+) -> u32 {
+    diagnostic(ctx, index).map_or(0, |d| d.diagnostic.span.column)
+}
+
+/// 第 `index` 条诊断信息是错误时返回 1，警告等返回 0
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_diagnostic_is_error(
+    ctx: *const ContractusContext,
+    index: usize,
+) -> c_int {
+    diagnostic(ctx, index).is_some_and(|d| d.diagnostic.is_error()) as c_int
+}
+
+unsafe fn diagnostic<'a>(ctx: *const ContractusContext, index: usize) -> Option<&'a CDiagnostic> {
+    ctx.as_ref()?.diagnostics.get(index)
+}
+
+/// 调用编译好的函数 `name`，返回值写到 `result`（可以为空）。
+/// 运行时错误和违反契约返回 CONTRACTUS_RUNTIME_ERROR，错误用 `contractus_diagnostic_*` 查询
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文，`name` 必须是 NUL 结尾的字符串，`args` 必须指向 `argc` 个值，
+/// 其中的字符串必须以 NUL 结尾；`result` 为空或指向可写的值
+#[no_mangle]
+pub unsafe extern "C" fn contractus_call(
+    ctx: *mut ContractusContext,
+    name: *const c_char,
+    args: *const ContractusValue,
+    argc: usize,
+    result: *mut ContractusValue,
+) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    if name.is_null() || (args.is_null() && argc > 0) {
+        return ctx.error("function name and arguments must not be null".to_string());
+    }
+    let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+    let args = match argc {
+        0 => &[][..],
+        _ => slice::from_raw_parts(args, argc),
+    };
+    let mut values = Vec::with_capacity(argc);
+    for (i, arg) in args.iter().enumerate() {
+        match from_c(arg) {
+            Some(value) => values.push(value),
+            None => return ctx.error(format!("argument {} is not a valid value", i + 1)),
+        }
+    }
+    guard(|| {
+        ctx.strings.clear();
+        let Some(module) = &ctx.module else {
+            ctx.diagnostics.clear();
+            return CONTRACTUS_NOT_COMPILED;
+        };
+        let mut vm = Vm::new(module, ctx.output());
+        vm.set_host(ctx.host.clone());
+        match vm.call_function(&name, values) {
+            Ok(value) => {
+                let display = vm.display(&value);
+                drop(vm);
+                ctx.diagnostics.clear();
+                if let Some(result) = result.as_mut() {
+                    *result = to_c(ctx, &value, display);
+                }
+                CONTRACTUS_OK
+            }
+            Err(errors) => {
+                drop(vm);
+                ctx.set_diagnostics(errors);
+                CONTRACTUS_RUNTIME_ERROR
+            }
+        }
+    })
+}
+
+/// 调用 `main`
+///
+/// # Safety
+///
+/// `ctx` 必须是有效的上下文
+#[no_mangle]
+pub unsafe extern "C" fn contractus_run_main(ctx: *mut ContractusContext) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return CONTRACTUS_INVALID_ARGUMENT;
+    };
+    guard(|| {
+        let Some(module) = &ctx.module else {
+            ctx.diagnostics.clear();
+            return CONTRACTUS_NOT_COMPILED;
+        };
+        let mut vm = Vm::new(module, ctx.output());
+        vm.set_host(ctx.host.clone());
+        let result = vm.run_main();
+        drop(vm);
+        match result {
+            Ok(_) => {
+                ctx.diagnostics.clear();
+                CONTRACTUS_OK
+            }
+            Err(errors) => {
+                ctx.set_diagnostics(errors);
+                CONTRACTUS_RUNTIME_ERROR
+            }
+        }
+    })
+}
+
+unsafe fn from_c(value: &ContractusValue) -> Option<Value> {
+    Some(match value.kind {
+        CONTRACTUS_UNIT => Value::Unit,
+        CONTRACTUS_BOOL => Value::Bool(value.int_value != 0),
+        CONTRACTUS_INT => Value::Int(value.int_value),
+        CONTRACTUS_FLOAT => Value::Float(value.float_value),
+        CONTRACTUS_CHAR => Value::Char(char::from_u32(u32::try_from(value.int_value).ok()?)?),
+        CONTRACTUS_STRING if !value.string_value.is_null() => {
+            Value::Str(CStr::from_ptr(value.string_value).to_str().ok()?.into())
+        }
+        _ => return None,
+    })
+}
+
+// 字符串保存在上下文中
+fn to_c(ctx: &mut ContractusContext, value: &Value, display: String) -> ContractusValue {
+    let mut result = ContractusValue::UNIT;
+    match value {
+        Value::Unit => {}
+        Value::Bool(b) => {
+            result.kind = CONTRACTUS_BOOL;
+            result.int_value = *b as i64;
+        }
+        Value::Int(n) => {
+            result.kind = CONTRACTUS_INT;
+            result.int_value = *n;
+        }
+        Value::Float(x) => {
+            result.kind = CONTRACTUS_FLOAT;
+            result.float_value = *x;
+        }
+        Value::Char(c) => {
+            result.kind = CONTRACTUS_CHAR;
+            result.int_value = *c as i64;
+        }
+        _ => {
+            result.kind = match value {
+                Value::Str(_) => CONTRACTUS_STRING,
+                _ => CONTRACTUS_OTHER,
+            };
+            let text = c_string(display);
+            result.string_value = text.as_ptr();
+            ctx.strings.push(text);
+        }
+    }
+    result
+}
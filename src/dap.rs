@@ -0,0 +1,589 @@
+// 调试适配器 - 在标准输入输出上实现 Debug Adapter Protocol（`contractus dap`）
+//
+// 编辑器（VS Code 等）启动适配器，发送 `launch` 编译要调试的程序，然后设置断点、单步执行、
+// 查看调用栈和变量，不需要专门的插件。断点和单步的逻辑与命令行调试器相同（debugger.rs）。
+// - 每条消息是 `Content-Length: <n>\r\n\r\n` 加上 n 字节的 JSON
+// - 读取请求在单独的线程中进行：程序运行时每条语句之前处理已经到达的请求（`pause`、
+//   `setBreakpoints` 等），停下时阻塞等待请求，直到继续执行
+// - 程序的输出作为 `output` 事件发送，标准输出留给协议
+// - 只有一个线程，id 为 1；栈帧 id 从最内层开始编号；变量的值显示为文本，不能展开
+// 请求的顺序：initialize、launch（之后发送 `initialized` 事件）、setBreakpoints、
+// configurationDone，程序开始运行；结束时发送 `exited` 和 `terminated` 事件
+
+mod json;
+
+pub use json::{parse, Json};
+
+use crate::debugger::{display_value, Resume, Session, StopReason};
+use crate::diagnostic::Diagnostic;
+use crate::driver::{self, Compiler};
+use crate::interp::{DebugAction, DebugContext, Debugger, Host, Interpreter};
+use crate::module::Crate;
+use json::object;
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// 唯一的线程的 id
+pub const THREAD_ID: i64 = 1;
+
+/// 读取一条消息，输入结束时为 None
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(invalid_data("missing `Content-Length` header".to_string()));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let text = String::from_utf8(body).map_err(|error| invalid_data(error.to_string()))?;
+    json::parse(&text).map(Some).map_err(invalid_data)
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 在 `input` 和 `output` 上与一个客户端会话，直到客户端断开
+pub fn serve<R: Read + Send + 'static, W: Write>(input: R, output: W) -> io::Result<()> {
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = BufReader::new(input);
+        // 格式错误的消息和输入结束一样结束会话
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let client = Client {
+        output: Rc::new(RefCell::new(output)),
+        seq: Rc::new(Cell::new(1)),
+    };
+
+    let Some(launch) = wait_for_launch(&client, &requests) else {
+        return Ok(());
+    };
+    let root = launch.krate.root_module();
+    let mut adapter = Adapter {
+        client: client.clone(),
+        requests,
+        session: Session::new(&root.program, launch.stop_on_entry),
+        file: root.file.clone(),
+        disconnected: false,
+    };
+    client.event("initialized", object([]));
+
+    // 配置阶段：设置断点，直到 configurationDone
+    loop {
+        let Ok(request) = adapter.requests.recv() else {
+            return Ok(());
+        };
+        match adapter.handle(&request, None) {
+            Next::Wait => {}
+            Next::Run => break,
+            Next::Terminate => return Ok(()),
+        }
+    }
+
+    let program = launch.krate.merged_program();
+    let mut interpreter = Interpreter::with_output(
+        &program,
+        ProgramOutput {
+            client: client.clone(),
+            line: Vec::new(),
+        },
+    );
+    interpreter.set_host(Host::unrestricted(launch.args));
+    if !launch.no_debug {
+        interpreter.set_debugger(&mut adapter);
+    }
+    let result = interpreter.run_main();
+    let mut exit_code = interpreter.exit_code().unwrap_or(0);
+    drop(interpreter);
+    if let Err(errors) = result {
+        let file = root.file.display().to_string();
+        for error in errors {
+            let output = format!("{}\n", error.with_file(file.clone()));
+            client.event(
+                "output",
+                object([("category", "stderr".into()), ("output", output.into())]),
+            );
+        }
+        exit_code = 1;
+    }
+    client.event(
+        "exited",
+        object([("exitCode", i64::from(exit_code).into())]),
+    );
+    client.event("terminated", object([]));
+
+    // 等待客户端断开
+    while !adapter.disconnected {
+        let Ok(request) = adapter.requests.recv() else {
+            break;
+        };
+        adapter.handle(&request, None);
+    }
+    Ok(())
+}
+
+struct Launch {
+    krate: Crate,
+    args: Vec<String>,
+    stop_on_entry: bool,
+    no_debug: bool,
+}
+
+// 初始化，然后编译 `launch` 请求中的程序；客户端在此之前断开时为 None
+fn wait_for_launch<W: Write>(client: &Client<W>, requests: &Receiver<Json>) -> Option<Launch> {
+    loop {
+        let request = requests.recv().ok()?;
+        match command(&request) {
+            "initialize" => client.respond(
+                &request,
+                object([
+                    ("supportsConfigurationDoneRequest", true.into()),
+                    ("supportsTerminateRequest", true.into()),
+                ]),
+            ),
+            "launch" => match launch(request.get("arguments")) {
+                Ok(launch) => {
+                    client.respond(&request, object([]));
+                    return Some(launch);
+                }
+                Err(message) => client.fail(&request, &message),
+            },
+            "disconnect" => {
+                client.respond(&request, object([]));
+                return None;
+            }
+            command => client.fail(
+                &request,
+                &format!("`{}` is not supported before `launch`", command),
+            ),
+        }
+    }
+}
+
+// `launch` 的参数：`program`（源文件）、`args`、`stopOnEntry` 和 `noDebug`
+fn launch(arguments: Option<&Json>) -> Result<Launch, String> {
+    let argument = |name| arguments.and_then(|arguments| arguments.get(name));
+    let Some(program) = argument("program").and_then(Json::as_str) else {
+        return Err("`launch` needs the path of the `program` to debug".to_string());
+    };
+    let args = argument("args")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|arg| arg.as_str().map(str::to_string))
+        .collect();
+    let output = Compiler::new()
+        .file(program)
+        .emit(driver::Emit::Hir)
+        .run()
+        .into_result();
+    let krate = match output {
+        Ok(artifact) => artifact.into_crate().unwrap(),
+        Err(errors) => return Err(compile_errors(program, &errors)),
+    };
+    Ok(Launch {
+        krate,
+        args,
+        stop_on_entry: argument("stopOnEntry").and_then(Json::as_bool) == Some(true),
+        no_debug: argument("noDebug").and_then(Json::as_bool) == Some(true),
+    })
+}
+
+fn compile_errors(program: &str, errors: &[Diagnostic]) -> String {
+    let mut message = format!("cannot compile `{}`", program);
+    for error in errors {
+        message.push_str(&format!("\n{}", error));
+    }
+    message
+}
+
+fn command(request: &Json) -> &str {
+    request
+        .get("command")
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+}
+
+// 发送响应和事件，调试器和程序输出共用
+struct Client<W: Write> {
+    output: Rc<RefCell<W>>,
+    seq: Rc<Cell<i64>>,
+}
+
+impl<W: Write> Clone for Client<W> {
+    fn clone(&self) -> Self {
+        Self {
+            output: Rc::clone(&self.output),
+            seq: Rc::clone(&self.seq),
+        }
+    }
+}
+
+impl<W: Write> Client<W> {
+    // 写入失败说明客户端已经断开，之后的请求也读不到了，忽略错误
+    fn send(&self, kind: &str, mut fields: Vec<(String, Json)>) {
+        let seq = self.seq.get();
+        self.seq.set(seq + 1);
+        fields.insert(0, ("seq".to_string(), seq.into()));
+        fields.insert(1, ("type".to_string(), kind.into()));
+        let _ = write_message(&mut *self.output.borrow_mut(), &Json::Object(fields));
+    }
+
+    fn reply(&self, request: &Json, result: Result<Json, String>) {
+        let request_seq = request.get("seq").cloned().unwrap_or(Json::Null);
+        let mut fields = vec![
+            ("request_seq".to_string(), request_seq),
+            ("success".to_string(), result.is_ok().into()),
+            ("command".to_string(), command(request).into()),
+        ];
+        match result {
+            Ok(body) => fields.push(("body".to_string(), body)),
+            Err(message) => fields.push(("message".to_string(), message.into())),
+        }
+        self.send("response", fields);
+    }
+
+    fn respond(&self, request: &Json, body: Json) {
+        self.reply(request, Ok(body));
+    }
+
+    fn fail(&self, request: &Json, message: &str) {
+        self.reply(request, Err(message.to_string()));
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(
+            "event",
+            vec![
+                ("event".to_string(), event.into()),
+                ("body".to_string(), body),
+            ],
+        );
+    }
+}
+
+// 程序的输出，每一行是一个 `output` 事件；没有换行的结尾在解释器结束时发送
+struct ProgramOutput<W: Write> {
+    client: Client<W>,
+    line: Vec<u8>,
+}
+
+impl<W: Write> ProgramOutput<W> {
+    fn send(&mut self, end: usize) {
+        let line: Vec<u8> = self.line.drain(..end).collect();
+        let text = String::from_utf8_lossy(&line).into_owned();
+        self.client.event(
+            "output",
+            object([("category", "stdout".into()), ("output", text.into())]),
+        );
+    }
+}
+
+impl<W: Write> Write for ProgramOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(newline) = self.line.iter().position(|&b| b == b'\n') {
+            self.send(newline + 1);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.send(self.line.len());
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for ProgramOutput<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// 处理一个请求之后做什么
+enum Next {
+    Wait,
+    Run,
+    Terminate,
+}
+
+struct Adapter<W: Write> {
+    client: Client<W>,
+    requests: Receiver<Json>,
+    session: Session,
+    file: PathBuf,
+    disconnected: bool,
+}
+
+impl<W: Write> Adapter<W> {
+    // `context` 是程序停下时的位置，运行中和尚未开始时为 None
+    fn handle(&mut self, request: &Json, context: Option<&DebugContext<'_, '_>>) -> Next {
+        let arguments = request.get("arguments");
+        let argument = |name| arguments.and_then(|arguments| arguments.get(name));
+        let resume = match command(request) {
+            "continue" => Resume::Continue,
+            "next" => Resume::StepOver,
+            "stepIn" => Resume::StepInto,
+            "stepOut" => Resume::StepOut,
+            "configurationDone" => {
+                self.client.respond(request, object([]));
+                return Next::Run;
+            }
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(argument("source"), argument("breakpoints"));
+                self.client.respond(request, body);
+                return Next::Wait;
+            }
+            "threads" => {
+                let thread = object([("id", THREAD_ID.into()), ("name", "main".into())]);
+                self.client
+                    .respond(request, object([("threads", vec![thread].into())]));
+                return Next::Wait;
+            }
+            "pause" => {
+                self.session.pause();
+                self.client.respond(request, object([]));
+                return Next::Wait;
+            }
+            "terminate" => {
+                self.client.respond(request, object([]));
+                return Next::Terminate;
+            }
+            "disconnect" => {
+                self.disconnected = true;
+                self.client.respond(request, object([]));
+                return Next::Terminate;
+            }
+            command @ ("stackTrace" | "scopes" | "variables" | "evaluate") => {
+                let result = match context {
+                    Some(context) => self.inspect(command, argument, context),
+                    None => Err("the program is not stopped".to_string()),
+                };
+                self.client.reply(request, result);
+                return Next::Wait;
+            }
+            command => {
+                let message = format!("`{}` is not supported", command);
+                self.client.fail(request, &message);
+                return Next::Wait;
+            }
+        };
+        // 运行中的继续和单步请求没有作用
+        if context.is_none() {
+            self.client.respond(request, object([]));
+            return Next::Wait;
+        }
+        self.session.resume(resume);
+        let body = match resume {
+            Resume::Continue => object([("allThreadsContinued", true.into())]),
+            _ => object([]),
+        };
+        self.client.respond(request, body);
+        Next::Run
+    }
+
+    // 替换入口文件的所有断点；其他文件中的断点不能验证
+    fn set_breakpoints(&mut self, source: Option<&Json>, breakpoints: Option<&Json>) -> Json {
+        let path = source
+            .and_then(|source| source.get("path"))
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let lines = breakpoints
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .map(|breakpoint| breakpoint.get("line").and_then(Json::as_i64).unwrap_or(0));
+        let entry = same_file(Path::new(path), &self.file);
+        if entry {
+            self.session.clear_breakpoints();
+        }
+        let mut results = Vec::new();
+        for line in lines {
+            let result = match u32::try_from(line) {
+                _ if !entry => Err("only the entry file is debugged".to_string()),
+                Ok(line) if line > 0 => self.session.add_breakpoint(line),
+                _ => Err(format!("invalid line number {}", line)),
+            };
+            results.push(match result {
+                Ok(breakpoint) => object([
+                    ("id", breakpoint.id.into()),
+                    ("verified", true.into()),
+                    ("line", breakpoint.line.into()),
+                ]),
+                Err(message) => object([("verified", false.into()), ("message", message.into())]),
+            });
+        }
+        object([("breakpoints", results.into())])
+    }
+
+    // 程序停下时才能回答的请求
+    fn inspect<'a>(
+        &self,
+        command: &str,
+        argument: impl Fn(&'a str) -> Option<&'a Json>,
+        context: &DebugContext<'_, '_>,
+    ) -> Result<Json, String> {
+        let number = |name| argument(name).and_then(Json::as_i64).unwrap_or(0);
+        match command {
+            "stackTrace" => {
+                let name = self.file.file_name().unwrap_or_default().to_string_lossy();
+                let source = object([
+                    ("name", name.as_ref().into()),
+                    ("path", self.file.display().to_string().into()),
+                ]);
+                let frames: Vec<Json> = context
+                    .backtrace()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, (function, span))| {
+                        object([
+                            ("id", id.into()),
+                            ("name", function.into()),
+                            ("line", span.line.into()),
+                            ("column", span.column.into()),
+                            ("source", source.clone()),
+                        ])
+                    })
+                    .collect();
+                let total = frames.len();
+                Ok(object([
+                    ("stackFrames", frames.into()),
+                    ("totalFrames", total.into()),
+                ]))
+            }
+            // 栈帧 n 的局部变量的引用是 2n + 1，全局变量是 2n + 2
+            "scopes" => {
+                let frame = number("frameId").max(0);
+                let scope = |name: &str, reference: i64| {
+                    object([
+                        ("name", name.into()),
+                        ("variablesReference", reference.into()),
+                        ("expensive", false.into()),
+                    ])
+                };
+                let scopes = vec![
+                    scope("Locals", 2 * frame + 1),
+                    scope("Globals", 2 * frame + 2),
+                ];
+                Ok(object([("scopes", scopes.into())]))
+            }
+            "variables" => {
+                let reference = number("variablesReference");
+                let variables = match reference {
+                    r if r > 0 && r % 2 == 1 => context.locals(((r - 1) / 2) as usize),
+                    r if r > 0 => context.globals(),
+                    _ => return Err(format!("invalid variables reference {}", reference)),
+                };
+                let variables: Vec<Json> = variables
+                    .into_iter()
+                    .map(|(name, value)| {
+                        object([
+                            ("name", name.into()),
+                            ("value", display_value(&value).into()),
+                            ("type", value.type_name().into()),
+                            ("variablesReference", 0i64.into()),
+                        ])
+                    })
+                    .collect();
+                Ok(object([("variables", variables.into())]))
+            }
+            // 只能求值变量名
+            _ => {
+                let expression = argument("expression")
+                    .and_then(Json::as_str)
+                    .unwrap_or_default()
+                    .trim();
+                let frame = number("frameId").max(0) as usize;
+                match context.lookup(frame, expression) {
+                    Some(value) => Ok(object([
+                        ("result", display_value(&value).into()),
+                        ("type", value.type_name().into()),
+                        ("variablesReference", 0i64.into()),
+                    ])),
+                    None => Err(format!("no variable `{}` in this frame", expression)),
+                }
+            }
+        }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn stop_reason(reason: StopReason) -> &'static str {
+    match reason {
+        StopReason::Entry => "entry",
+        StopReason::Breakpoint(_) => "breakpoint",
+        StopReason::Step => "step",
+        StopReason::Pause => "pause",
+    }
+}
+
+impl<W: Write> Debugger for Adapter<W> {
+    fn before_statement(&mut self, context: &DebugContext<'_, '_>) -> DebugAction {
+        // 运行中到达的请求
+        while let Ok(request) = self.requests.try_recv() {
+            if let Next::Terminate = self.handle(&request, None) {
+                return DebugAction::Terminate;
+            }
+        }
+        let Some(reason) = self.session.check(context) else {
+            return DebugAction::Continue;
+        };
+        let mut body = vec![
+            ("reason".to_string(), stop_reason(reason).into()),
+            ("threadId".to_string(), THREAD_ID.into()),
+            ("allThreadsStopped".to_string(), true.into()),
+        ];
+        if let StopReason::Breakpoint(id) = reason {
+            body.push(("hitBreakpointIds".to_string(), vec![id.into()].into()));
+        }
+        self.client.event("stopped", Json::Object(body));
+        loop {
+            let Ok(request) = self.requests.recv() else {
+                return DebugAction::Terminate;
+            };
+            match self.handle(&request, Some(context)) {
+                Next::Wait => {}
+                Next::Run => return DebugAction::Continue,
+                Next::Terminate => return DebugAction::Terminate,
+            }
+        }
+    }
+}
@@ -0,0 +1,491 @@
+// 调试器 - 在解释器中设置断点、单步执行和查看变量（`contractus debug`）
+//
+// Session 记录断点和单步的状态，决定在哪条语句停下，与界面无关；
+// CommandLine 是基于它的命令行界面，从输入读取命令，停下时显示所在的函数和源码行。
+// - 断点按行设置，通过语句的 span 映射：没有语句开始的行（空行、注释、函数签名）
+//   移到其后第一条语句所在的行
+// - 单步以语句为单位：step 进入被调用的函数，next 不进入，finish 执行到当前函数返回
+// - 只在程序自身的函数和闭包中停下，预导入的函数整体作为一步
+// 解释器只执行根模块，所以断点都在入口文件中
+
+use crate::ast::{Block, ElseBranch, Expr, IfStmt, Item, Program, Statement};
+use crate::interp::{DebugAction, DebugContext, Debugger, Value};
+use std::collections::{BTreeSet, HashSet};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const HELP: &str = "\
+Commands:
+  break <line>|<file>:<line>   set a breakpoint (b)
+  delete <n>                   remove breakpoint <n> (d)
+  breakpoints                  list the breakpoints
+  continue                     run to the next breakpoint (c)
+  step                         run to the next statement, entering calls (s)
+  next                         run to the next statement, stepping over calls (n)
+  finish                       run until the current function returns (f)
+  print <name>                 show the value of a variable (p)
+  locals                       show the variables of the current function
+  backtrace                    show the call stack (bt)
+  help                         show this message (h)
+  quit                         stop the program (q)
+An empty line repeats the last command.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub id: usize,
+    pub line: u32, // 映射后的行，有语句从这一行开始
+}
+
+/// 程序停下的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Entry,
+    Breakpoint(usize),
+    Step,
+    Pause,
+}
+
+/// 停下之后如何继续
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    Continue,
+    StepInto,
+    StepOver,
+    StepOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Entry,
+    Run,
+    Into,
+    Over(usize), // 停下时的调用深度
+    Out(usize),
+    Pause,
+}
+
+pub struct Session {
+    lines: BTreeSet<u32>,       // 有语句开始的行
+    functions: HashSet<String>, // 程序自身的函数
+    breakpoints: Vec<Breakpoint>,
+    next_id: usize,
+    mode: Mode,
+    last: Option<(usize, u32)>, // 上一条语句的调用深度和行
+    depth: usize,               // 上次停下时的调用深度
+}
+
+impl Session {
+    /// `stop_on_entry` 时在 `main` 的第一条语句处停下
+    pub fn new(program: &Program, stop_on_entry: bool) -> Self {
+        let mut lines = BTreeSet::new();
+        let mut functions = HashSet::new();
+        for item in &program.items {
+            if let Item::Function(func) = item {
+                functions.insert(func.name.clone());
+                visit_block(&func.body, &mut lines);
+            }
+        }
+        Self {
+            lines,
+            functions,
+            breakpoints: Vec::new(),
+            next_id: 1,
+            mode: if stop_on_entry {
+                Mode::Entry
+            } else {
+                Mode::Run
+            },
+            last: None,
+            depth: 0,
+        }
+    }
+
+    /// 在这一行或其后的第一条语句处设置断点，同一行已有断点时返回它
+    pub fn add_breakpoint(&mut self, line: u32) -> Result<Breakpoint, String> {
+        let Some(&line) = self.lines.range(line..).next() else {
+            return Err(format!("no statement at or after line {}", line));
+        };
+        if let Some(existing) = self.breakpoints.iter().find(|bp| bp.line == line) {
+            return Ok(*existing);
+        }
+        let breakpoint = Breakpoint {
+            id: self.next_id,
+            line,
+        };
+        self.next_id += 1;
+        self.breakpoints.push(breakpoint);
+        Ok(breakpoint)
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|bp| bp.id != id);
+        self.breakpoints.len() != len
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn resume(&mut self, resume: Resume) {
+        self.mode = match resume {
+            Resume::Continue => Mode::Run,
+            Resume::StepInto => Mode::Into,
+            Resume::StepOver => Mode::Over(self.depth),
+            Resume::StepOut => Mode::Out(self.depth),
+        };
+    }
+
+    /// 在下一条语句处停下
+    pub fn pause(&mut self) {
+        self.mode = Mode::Pause;
+    }
+
+    /// 在即将执行的语句处是否停下。停下之后调用 `resume` 继续
+    pub fn check(&mut self, context: &DebugContext<'_, '_>) -> Option<StopReason> {
+        let function = context.function();
+        if function != "<closure>" && !self.functions.contains(function) {
+            return None;
+        }
+        let depth = context.depth();
+        let line = context.span().line;
+        // 同一行上的后续语句（`if c { x += 1; }`）不再次触发断点
+        let previous = self.last.replace((depth, line));
+        let breakpoint = self
+            .breakpoints
+            .iter()
+            .find(|bp| bp.line == line)
+            .filter(|_| previous != Some((depth, line)));
+        let reason = match (self.mode, breakpoint) {
+            (Mode::Entry, _) => StopReason::Entry,
+            (Mode::Pause, _) => StopReason::Pause,
+            (_, Some(breakpoint)) => StopReason::Breakpoint(breakpoint.id),
+            (Mode::Into, None) => StopReason::Step,
+            (Mode::Over(stopped), None) if depth <= stopped => StopReason::Step,
+            (Mode::Out(stopped), None) if depth < stopped => StopReason::Step,
+            _ => return None,
+        };
+        self.mode = Mode::Run;
+        self.depth = depth;
+        Some(reason)
+    }
+}
+
+fn visit_block(block: &Block, lines: &mut BTreeSet<u32>) {
+    for stmt in &block.statements {
+        lines.insert(stmt.span().line);
+        match stmt {
+            Statement::Let(stmt) => {
+                visit_exprs(stmt.init.iter(), lines);
+                if let Some(block) = &stmt.else_block {
+                    visit_block(block, lines);
+                }
+            }
+            Statement::Expr(stmt) => visit_expr(&stmt.expr, lines),
+            Statement::Return(stmt) => visit_exprs(stmt.expr.iter(), lines),
+            Statement::If(stmt) => visit_if(stmt, lines),
+            Statement::While(stmt) => {
+                visit_expr(&stmt.cond, lines);
+                visit_block(&stmt.body, lines);
+            }
+            Statement::For(stmt) => {
+                visit_expr(&stmt.iterable, lines);
+                visit_block(&stmt.body, lines);
+            }
+            Statement::Match(stmt) => {
+                visit_expr(&stmt.expr, lines);
+                for arm in &stmt.arms {
+                    visit_exprs(arm.guard.iter().chain([&arm.body]), lines);
+                }
+            }
+            Statement::Break(stmt) => visit_exprs(stmt.expr.iter(), lines),
+            Statement::Continue(_) => {}
+            Statement::Block(block) => visit_block(block, lines),
+        }
+    }
+}
+
+fn visit_if(stmt: &IfStmt, lines: &mut BTreeSet<u32>) {
+    visit_expr(&stmt.cond, lines);
+    visit_block(&stmt.then_block, lines);
+    match &stmt.else_block {
+        Some(ElseBranch::Block(block)) => visit_block(block, lines),
+        Some(ElseBranch::If(nested)) => visit_if(nested, lines),
+        None => {}
+    }
+}
+
+fn visit_exprs<'a>(exprs: impl IntoIterator<Item = &'a Expr>, lines: &mut BTreeSet<u32>) {
+    for expr in exprs {
+        visit_expr(expr, lines);
+    }
+}
+
+// 表达式中的代码块（闭包、if、match 等）也有语句
+fn visit_expr(expr: &Expr, lines: &mut BTreeSet<u32>) {
+    match expr {
+        Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+            visit_block(block, lines)
+        }
+        Expr::If(cond, then_block, else_block, _) => {
+            visit_expr(cond, lines);
+            visit_block(then_block, lines);
+            match else_block {
+                Some(ElseBranch::Block(block)) => visit_block(block, lines),
+                Some(ElseBranch::If(nested)) => visit_if(nested, lines),
+                None => {}
+            }
+        }
+        Expr::While(_, cond, body, _) => {
+            visit_expr(cond, lines);
+            visit_block(body, lines);
+        }
+        Expr::For(_, _, iterable, body, _) => {
+            visit_expr(iterable, lines);
+            visit_block(body, lines);
+        }
+        Expr::Match(scrutinee, arms, _) => {
+            visit_expr(scrutinee, lines);
+            for arm in arms {
+                visit_exprs(arm.guard.iter().chain([&arm.body]), lines);
+            }
+        }
+        Expr::Binary(_, lhs, rhs, _, _)
+        | Expr::IndexAccess(lhs, rhs, _)
+        | Expr::Assign(lhs, rhs, _)
+        | Expr::CompoundAssign(_, lhs, rhs, _, _) => visit_exprs([&**lhs, &**rhs], lines),
+        Expr::Range(start, end, _, _) => {
+            visit_exprs(start.iter().chain(end).map(|bound| &**bound), lines)
+        }
+        Expr::Unary(_, inner, _, _)
+        | Expr::Turbofish(inner, _, _)
+        | Expr::FieldAccess(inner, _, _)
+        | Expr::TupleIndex(inner, _, _)
+        | Expr::Closure(_, _, inner, _)
+        | Expr::Cast(inner, _, _)
+        | Expr::Ref(inner, _, _)
+        | Expr::Deref(inner, _)
+        | Expr::Try(inner, _)
+        | Expr::Await(inner, _) => visit_expr(inner, lines),
+        Expr::Call(callee, args, _, _) | Expr::MethodCall(callee, _, args, _, _) => {
+            visit_exprs(std::iter::once(&**callee).chain(args), lines)
+        }
+        Expr::StructLit(_, fields, _) => visit_exprs(fields.iter().map(|(_, expr)| expr), lines),
+        Expr::ArrayLit(items, _) | Expr::TupleLit(items, _) => visit_exprs(items, lines),
+        Expr::InlineAsm(asm, _) => visit_exprs(asm.exprs(), lines),
+        Expr::Break(_, value, _) | Expr::Return(value, _) => {
+            visit_exprs(value.iter().map(|value| &**value), lines)
+        }
+        Expr::Literal(..)
+        | Expr::Ident(..)
+        | Expr::Path(..)
+        | Expr::Continue(..)
+        | Expr::MacroCall(_) => {}
+    }
+}
+
+/// 调试器中显示的值，字符串和字符带引号
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::Str(text) => format!("{:?}", text),
+        Value::Char(c) => format!("{:?}", c),
+        value => value.to_string(),
+    }
+}
+
+/// 命令行界面：停下时显示位置，然后逐行读取命令，直到继续执行的命令。
+/// 输入结束时删除所有断点，程序执行到结束
+pub struct CommandLine<R: BufRead, W: Write> {
+    session: Session,
+    file: String,
+    source: Vec<String>,
+    input: R,
+    output: W,
+    last_command: Option<String>,
+}
+
+impl<R: BufRead, W: Write> CommandLine<R, W> {
+    pub fn new(program: &Program, file: &str, source: &str, input: R, output: W) -> Self {
+        Self {
+            session: Session::new(program, true),
+            file: file.to_string(),
+            source: source.lines().map(str::to_string).collect(),
+            input,
+            output,
+            last_command: None,
+        }
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    fn show_stop(&mut self, reason: StopReason, context: &DebugContext<'_, '_>) {
+        let line = context.span().line;
+        let location = format!("{} at {}:{}", context.function(), self.file, line);
+        let _ = match reason {
+            StopReason::Breakpoint(id) => writeln!(self.output, "Breakpoint {}, {}", id, location),
+            _ => writeln!(self.output, "{}", location),
+        };
+        if let Some(text) = (line as usize)
+            .checked_sub(1)
+            .and_then(|i| self.source.get(i))
+        {
+            let _ = writeln!(self.output, "{:>4} | {}", line, text);
+        }
+    }
+
+    // 执行一条命令，返回继续执行的方式；`None` 表示继续读取命令
+    fn execute(&mut self, command: &str, context: &DebugContext<'_, '_>) -> Option<DebugAction> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let arg = words.next();
+        let resume = match name {
+            "c" | "continue" => Resume::Continue,
+            "s" | "step" => Resume::StepInto,
+            "n" | "next" => Resume::StepOver,
+            "f" | "finish" => Resume::StepOut,
+            "q" | "quit" => return Some(DebugAction::Terminate),
+            _ => {
+                let result = self.inspect(name, arg, context);
+                if let Err(message) = result {
+                    let _ = writeln!(self.output, "error: {}", message);
+                }
+                return None;
+            }
+        };
+        self.session.resume(resume);
+        Some(DebugAction::Continue)
+    }
+
+    // 不继续执行的命令
+    fn inspect(
+        &mut self,
+        name: &str,
+        arg: Option<&str>,
+        context: &DebugContext<'_, '_>,
+    ) -> Result<(), String> {
+        let out = &mut self.output;
+        match (name, arg) {
+            ("b" | "break", Some(location)) => {
+                let line = self.breakpoint_line(location)?;
+                let breakpoint = self.session.add_breakpoint(line)?;
+                let _ = writeln!(
+                    self.output,
+                    "Breakpoint {} at {}:{}",
+                    breakpoint.id, self.file, breakpoint.line
+                );
+            }
+            ("d" | "delete", Some(id)) => {
+                let removed = id
+                    .parse()
+                    .is_ok_and(|id| self.session.remove_breakpoint(id));
+                if !removed {
+                    return Err(format!("no breakpoint `{}`", id));
+                }
+            }
+            ("breakpoints", None) => {
+                if self.session.breakpoints().is_empty() {
+                    let _ = writeln!(out, "No breakpoints.");
+                }
+                for breakpoint in self.session.breakpoints() {
+                    let _ = writeln!(
+                        out,
+                        "Breakpoint {} at {}:{}",
+                        breakpoint.id, self.file, breakpoint.line
+                    );
+                }
+            }
+            ("p" | "print", Some(variable)) => match context.lookup(0, variable) {
+                Some(value) => {
+                    let _ = writeln!(out, "{} = {}", variable, display_value(&value));
+                }
+                None => return Err(format!("no variable `{}` in the current scope", variable)),
+            },
+            ("locals", None) => {
+                let locals = context.locals(0);
+                if locals.is_empty() {
+                    let _ = writeln!(out, "No locals.");
+                }
+                for (name, value) in locals {
+                    let _ = writeln!(out, "{} = {}", name, display_value(&value));
+                }
+            }
+            ("bt" | "backtrace", None) => {
+                for (i, (function, span)) in context.backtrace().into_iter().enumerate() {
+                    let _ = writeln!(out, "#{:<2} {} at {}:{}", i, function, self.file, span.line);
+                }
+            }
+            ("h" | "help", None) => {
+                let _ = writeln!(out, "{}", HELP);
+            }
+            ("b" | "break" | "d" | "delete" | "p" | "print", None) => {
+                return Err(format!("`{}` needs an argument", name))
+            }
+            _ => {
+                return Err(format!(
+                    "unknown command `{}`; type `help` for a list of commands",
+                    name
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    // `12` 或 `main.ctx:12`，文件名可以省略目录
+    fn breakpoint_line(&self, location: &str) -> Result<u32, String> {
+        let (file, line) = match location.rsplit_once(':') {
+            Some((file, line)) => (Some(file), line),
+            None => (None, location),
+        };
+        if let Some(file) = file {
+            let entry = Path::new(&self.file);
+            if Path::new(file) != entry && entry.file_name() != Some(file.as_ref()) {
+                return Err(format!(
+                    "`{}` is not the entry file `{}`; only the entry file is debugged",
+                    file, self.file
+                ));
+            }
+        }
+        line.parse()
+            .ok()
+            .filter(|&line| line > 0)
+            .ok_or_else(|| format!("invalid line number `{}`", line))
+    }
+}
+
+impl<R: BufRead, W: Write> Debugger for CommandLine<R, W> {
+    fn before_statement(&mut self, context: &DebugContext<'_, '_>) -> DebugAction {
+        let Some(reason) = self.session.check(context) else {
+            return DebugAction::Continue;
+        };
+        self.show_stop(reason, context);
+        loop {
+            let _ = write!(self.output, "(debug) ");
+            let _ = self.output.flush();
+            let mut line = String::new();
+            if !matches!(self.input.read_line(&mut line), Ok(n) if n > 0) {
+                let _ = writeln!(self.output);
+                self.session.clear_breakpoints();
+                self.session.resume(Resume::Continue);
+                return DebugAction::Continue;
+            }
+            let command = match line.trim() {
+                "" => match self.last_command.clone() {
+                    Some(command) => command,
+                    None => continue,
+                },
+                command => command.to_string(),
+            };
+            let action = self.execute(&command, context);
+            self.last_command = Some(command);
+            if let Some(action) = action {
+                return action;
+            }
+        }
+    }
+}
@@ -0,0 +1,360 @@
+// `#[derive(...)]` 的展开
+// 结构体和枚举之前的 `#[derive(Eq, Clone, Debug, Hash)]` 为类型生成对应的函数。
+// 语言还没有 impl 块，生成的是以类型名的 snake_case 形式为前缀的普通函数，
+// 与类型的可见性相同，和其他函数一样可以按方法调用：
+//
+//     #[derive(Eq, Debug)]
+//     struct Point { x: i32, y: i32 }
+//
+// 生成 `fn point_eq(a: Point, b: Point) -> bool` 和 `fn point_debug(value: Point) -> string`，
+// `p.point_eq(q)` 或 `p.eq(q)` 逐个字段比较。泛型类型生成同样泛型的函数。
+//   Eq     `<ty>_eq(a, b) -> bool`，结构体逐个字段用 `==` 比较，枚举先比较变体再比较字段
+//   Clone  `<ty>_clone(value) -> T`，逐个字段复制出新的值
+//   Debug  `<ty>_debug(value) -> string`，与 `print` 显示的文本相同
+//   Hash   `<ty>_hash(value) -> i64`，由字段的文本计算，相等的值哈希值相同
+// 在宏展开之后、名称解析之前进行，生成的函数的位置都指向 derive 属性，
+// 展开之后类型上只保留 repr 属性，`--emit=expanded` 的输出可以再次编译
+
+use crate::ast::*;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::lexer::Lexer;
+use crate::module::item_name;
+use crate::parser::Parser;
+use crate::span::Span;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+/// 可以派生的 trait
+pub const TRAITS: [&str; 4] = ["Eq", "Clone", "Debug", "Hash"];
+
+/// 展开程序中所有结构体和枚举上的 derive 属性，生成的函数紧跟在类型之后
+pub fn expand(program: Program) -> Result<Program, Vec<Diagnostic>> {
+    let defined: HashSet<String> = program
+        .items
+        .iter()
+        .filter_map(item_name)
+        .map(str::to_string)
+        .collect();
+    let mut errors = Vec::new();
+    let mut items = Vec::new();
+    for mut item in program.items {
+        // `#[repr(...)]` 留在类型上，由语义分析检查、布局计算使用
+        let (attributes, shape) = match &mut item {
+            Item::Struct(def) => (
+                take_derives(&mut def.attributes),
+                Shape::Struct(def.clone()),
+            ),
+            Item::Enum(def) => (take_derives(&mut def.attributes), Shape::Enum(def.clone())),
+            _ => {
+                items.push(item);
+                continue;
+            }
+        };
+        items.push(item);
+        for attribute in &attributes {
+            match derives(attribute) {
+                Ok(traits) => {
+                    let mut source = String::new();
+                    for name in traits {
+                        // 生成的函数不能和程序中的条目同名，否则其中一个会被悄悄遮蔽
+                        let function = shape.function_name(name);
+                        match defined.contains(&function) {
+                            true => errors.push(
+                                Diagnostic::error(
+                                    format!("cannot derive `{}` for `{}`", name, shape.name()),
+                                    attribute.span,
+                                )
+                                .with_code(ErrorCode::E0108)
+                                .with_help(format!(
+                                    "the derived function `{}` is already defined",
+                                    function
+                                )),
+                            ),
+                            false => source.push_str(&shape.generate(name)),
+                        }
+                    }
+                    items.extend(parse(&source, attribute.span));
+                }
+                Err(error) => errors.push(*error),
+            }
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(Program {
+            attributes: program.attributes,
+            items,
+            span: program.span,
+        }),
+        false => Err(errors),
+    }
+}
+
+/// 为类型 `ty` 的 trait 生成的函数的名字，如 `Point` 的 `Eq` 为 `point_eq`
+pub fn function_name(ty: &str, name: &str) -> String {
+    method_function(ty, &name.to_lowercase())
+}
+
+/// 类型 `ty` 的值按方法调用 `value.method()` 时，没有名为 `method` 的函数时调用的函数，
+/// 如 `Point` 的 `clone` 为 `point_clone`；其他模块中的类型 `geometry::Point` 的函数在
+/// 同一个模块中，为 `geometry::point_clone`
+pub fn method_function(ty: &str, method: &str) -> String {
+    match ty.rsplit_once("::") {
+        Some((module, ty)) => format!("{}::{}_{}", module, snake_case(ty), method),
+        None => format!("{}_{}", snake_case(ty), method),
+    }
+}
+
+/// 类型名的 snake_case 形式，生成的函数以它为前缀：`HttpRequest` 为 `http_request`
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            // `HTTPServer` 为 `http_server`：缩写在下一个单词之前断开
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+// 取出除 repr 之外的属性
+fn take_derives(attributes: &mut Vec<Attribute>) -> Vec<Attribute> {
+    let (repr, rest) = std::mem::take(attributes)
+        .into_iter()
+        .partition(|attribute| attribute.name == "repr");
+    *attributes = repr;
+    rest
+}
+
+// derive 属性列出的 trait，重复的只生成一次
+fn derives(attribute: &Attribute) -> Result<Vec<&str>, Box<Diagnostic>> {
+    if attribute.name != "derive" {
+        return Err(Diagnostic::error(
+            format!(
+                "`#[{}]` is not allowed on structs and enums",
+                attribute.name
+            ),
+            attribute.span,
+        )
+        .with_code(ErrorCode::E0103)
+        .with_help(
+            "only `#[derive(...)]` and `#[repr(...)]` can be applied to structs and enums"
+                .to_string(),
+        )
+        .into());
+    }
+    let known = || {
+        let names: Vec<String> = TRAITS.iter().map(|name| format!("`{}`", name)).collect();
+        format!("the derivable traits are {}", names.join(", "))
+    };
+    if attribute.args.is_empty() {
+        return Err(Diagnostic::error(
+            "`derive` needs the traits to derive, like `#[derive(Eq)]`".to_string(),
+            attribute.span,
+        )
+        .with_code(ErrorCode::E0108)
+        .with_help(known())
+        .into());
+    }
+    let mut traits = Vec::new();
+    for name in &attribute.args {
+        if !TRAITS.contains(&name.as_str()) {
+            return Err(
+                Diagnostic::error(format!("cannot derive `{}`", name), attribute.span)
+                    .with_code(ErrorCode::E0108)
+                    .with_help(known())
+                    .into(),
+            );
+        }
+        if !traits.contains(&name.as_str()) {
+            traits.push(name.as_str());
+        }
+    }
+    Ok(traits)
+}
+
+// 生成的源码解析为条目，所有记号的位置改为 derive 属性的位置
+fn parse(source: &str, span: Span) -> Vec<Item> {
+    let mut tokens = Lexer::new(source)
+        .tokenize()
+        .expect("derive generated invalid tokens");
+    for token in &mut tokens {
+        token.span = span;
+    }
+    Parser::new(tokens)
+        .parse()
+        .expect("derive generated an invalid item")
+        .items
+}
+
+enum Shape {
+    Struct(StructDef),
+    Enum(EnumDef),
+}
+
+impl Shape {
+    fn name(&self) -> &str {
+        match self {
+            Shape::Struct(def) => &def.name,
+            Shape::Enum(def) => &def.name,
+        }
+    }
+
+    fn function_name(&self, name: &str) -> String {
+        function_name(self.name(), name)
+    }
+
+    // 生成的函数的可见性、名字和泛型参数，如 `pub fn point_eq<T: Bound>`
+    fn header(&self, name: &str) -> String {
+        let (visibility, generics) = match self {
+            Shape::Struct(def) => (&def.visibility, &def.generics),
+            Shape::Enum(def) => (&def.visibility, &def.generics),
+        };
+        let mut header = String::new();
+        if *visibility == Visibility::Public {
+            header.push_str("pub ");
+        }
+        let _ = write!(header, "fn {}", self.function_name(name));
+        if let Some(generics) = generics {
+            let params: Vec<String> = generics
+                .params
+                .iter()
+                .map(|param| match param.bounds.is_empty() {
+                    true => param.name.clone(),
+                    false => format!("{}: {}", param.name, param.bounds.join(" + ")),
+                })
+                .collect();
+            let _ = write!(header, "<{}>", params.join(", "));
+        }
+        header
+    }
+
+    // 类型本身，泛型类型带上它的类型参数，如 `Pair<T, U>`
+    fn ty(&self) -> String {
+        let generics = match self {
+            Shape::Struct(def) => &def.generics,
+            Shape::Enum(def) => &def.generics,
+        };
+        match generics {
+            Some(generics) => format!("{}<{}>", self.name(), generics.param_names().join(", ")),
+            None => self.name().to_string(),
+        }
+    }
+
+    fn generate(&self, name: &str) -> String {
+        let header = self.header(name);
+        let ty = self.ty();
+        match name {
+            "Eq" => format!(
+                "{}(a: {ty}, b: {ty}) -> bool {{ {} }}\n",
+                header,
+                self.eq_body()
+            ),
+            "Clone" => format!(
+                "{}(value: {ty}) -> {ty} {{ {} }}\n",
+                header,
+                self.clone_body()
+            ),
+            "Debug" => format!("{}(value: {}) -> string {{ to_string(value) }}\n", header, ty),
+            _ => format!(
+                "{}(value: {}) -> i64 {{ let mut hash: i64 = 17; for c in chars({}) {{ hash = (hash * 31 + c as i64) % 1000000007; }} hash }}\n",
+                header,
+                ty,
+                self.hash_key()
+            ),
+        }
+    }
+
+    // 结构体：`a.x == b.x && a.y == b.y`；枚举：对 `(a, b)` 按变体 match
+    fn eq_body(&self) -> String {
+        match self {
+            Shape::Struct(def) if def.fields.is_empty() => "true".to_string(),
+            Shape::Struct(def) => def
+                .fields
+                .iter()
+                .map(|field| format!("a.{0} == b.{0}", field.name))
+                .collect::<Vec<_>>()
+                .join(" && "),
+            Shape::Enum(def) => {
+                let mut arms = String::new();
+                for variant in &def.variants {
+                    let count = variant.fields.as_ref().map_or(0, Vec::len);
+                    let compare: Vec<String> =
+                        (0..count).map(|i| format!("a{0} == b{0}", i)).collect();
+                    let _ = write!(
+                        arms,
+                        "({}, {}) => {}, ",
+                        variant_pattern(&def.name, variant, "a"),
+                        variant_pattern(&def.name, variant, "b"),
+                        match compare.is_empty() {
+                            true => "true".to_string(),
+                            false => compare.join(" && "),
+                        }
+                    );
+                }
+                // 只有一个变体时没有变体不同的情况，通配分支不可达
+                if def.variants.len() != 1 {
+                    arms.push_str("_ => false, ");
+                }
+                format!("match (a, b) {{ {}}}", arms)
+            }
+        }
+    }
+
+    fn clone_body(&self) -> String {
+        match self {
+            Shape::Struct(def) => {
+                let fields: Vec<String> = def
+                    .fields
+                    .iter()
+                    .map(|field| format!("{0}: value.{0}", field.name))
+                    .collect();
+                format!("{} {{ {} }}", def.name, fields.join(", "))
+            }
+            Shape::Enum(def) => {
+                let mut arms = String::new();
+                for variant in &def.variants {
+                    let pattern = variant_pattern(&def.name, variant, "field");
+                    let _ = write!(arms, "{} => {}, ", pattern, pattern);
+                }
+                format!("match value {{ {}}}", arms)
+            }
+        }
+    }
+
+    // 计算哈希的文本：结构体由各字段的文本拼接（不含类型名），枚举用 `to_string` 的文本
+    fn hash_key(&self) -> String {
+        match self {
+            Shape::Struct(def) if def.fields.is_empty() => "\"\"".to_string(),
+            Shape::Struct(def) => def
+                .fields
+                .iter()
+                .map(|field| format!("to_string(value.{})", field.name))
+                .collect::<Vec<_>>()
+                .join(" + \", \" + "),
+            Shape::Enum(_) => "to_string(value)".to_string(),
+        }
+    }
+}
+
+// 变体的模式，字段依次绑定到 `<prefix>0`、`<prefix>1` ...：`Shape::Rect(a0, a1)`
+fn variant_pattern(enum_name: &str, variant: &EnumVariant, prefix: &str) -> String {
+    match &variant.fields {
+        Some(fields) => {
+            let names: Vec<String> = (0..fields.len())
+                .map(|i| format!("{}{}", prefix, i))
+                .collect();
+            format!("{}::{}({})", enum_name, variant.name, names.join(", "))
+        }
+        None => format!("{}::{}", enum_name, variant.name),
+    }
+}
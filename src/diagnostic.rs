@@ -0,0 +1,167 @@
+// Contractus 诊断信息
+// 语法分析之后的各个阶段（语义分析、MIR 等）共用的错误报告结构。
+// 每条诊断信息带有错误码（见 `codes`），`contractus --explain` 给出详细说明
+
+mod codes;
+
+pub use codes::ErrorCode;
+
+use crate::lexer::LexError;
+use crate::parser::ParseError;
+use crate::span::Span;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    pub span: Span,
+    pub file: Option<String>, // 多文件编译时所属的源文件
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Self {
+        Self {
+            level: Level::Error,
+            code: None,
+            message,
+            span,
+            file: None,
+            help: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: String, span: Span) -> Self {
+        Self {
+            level: Level::Warning,
+            ..Self::error(message, span)
+        }
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_file(mut self, file: String) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+}
+
+/// 按所属文件分组，组按文件第一次出现的顺序排列，组内保持原来的顺序
+pub fn group_by_file(diagnostics: Vec<Diagnostic>) -> Vec<(Option<String>, Vec<Diagnostic>)> {
+    let mut groups: Vec<(Option<String>, Vec<Diagnostic>)> = Vec::new();
+    for diagnostic in diagnostics {
+        match groups.iter_mut().find(|(file, _)| *file == diagnostic.file) {
+            Some((_, group)) => group.push(diagnostic),
+            None => groups.push((diagnostic.file.clone(), vec![diagnostic])),
+        }
+    }
+    groups
+}
+
+/// 去掉重复的诊断信息：错误码、位置和所属文件都相同的只保留第一条。
+/// 同一个出错的节点被多个阶段或多次检查报告时只输出一次
+pub fn dedup(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    let mut seen = HashSet::new();
+    diagnostics
+        .into_iter()
+        .filter(|d| seen.insert((d.file.clone(), d.code, d.span)))
+        .collect()
+}
+
+/// 最多保留 `limit` 条错误（0 表示不限制），警告都保留。
+/// 返回保留的诊断信息和省略的错误数
+pub fn limit(diagnostics: Vec<Diagnostic>, limit: usize) -> (Vec<Diagnostic>, usize) {
+    let mut errors = 0;
+    let mut omitted = 0;
+    let kept = diagnostics
+        .into_iter()
+        .filter(|d| {
+            if !d.is_error() {
+                return true;
+            }
+            errors += 1;
+            let keep = limit == 0 || errors <= limit;
+            if !keep {
+                omitted += 1;
+            }
+            keep
+        })
+        .collect();
+    (kept, omitted)
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        let level = match self.code {
+            Some(code) => format!("{}[{}]", level, code),
+            None => level.to_string(),
+        };
+        match &self.file {
+            Some(file) => write!(
+                f,
+                "{} at {}:{}:{}: {}",
+                level, file, self.span.line, self.span.column, self.message
+            )?,
+            None => write!(
+                f,
+                "{} at line {}, column {}: {}",
+                level, self.span.line, self.span.column, self.message
+            )?,
+        }
+        for note in &self.notes {
+            write!(f, "\nnote: {}", note)?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        Diagnostic::error(err.message, err.span).with_code(err.code)
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        let diagnostic = Diagnostic::error(err.message, err.span).with_code(err.code);
+        match err.help {
+            Some(help) => diagnostic.with_help(help),
+            None => diagnostic,
+        }
+    }
+}
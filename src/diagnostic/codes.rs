@@ -0,0 +1,160 @@
+// 错误码注册表
+// 每条诊断信息带有一个稳定的错误码，输出为 `error[E0308] at ...`。
+// `contractus --explain E0308` 输出错误码的详细说明，说明写在
+// `explanations/<错误码>.md` 中，包括出错的示例和改正的方法。
+//
+// 编号规则：E00xx 词法错误，E01xx 语法错误，E02xx–E07xx 语义错误（和 rustc 含义相同的
+// 错误沿用 rustc 的编号），E08xx 项目和构建错误，E09xx 运行时错误。
+// 错误码一旦发布就不再改变含义，不再使用的错误码也不会分配给其他错误
+
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! error_codes {
+    ($($code:ident: $title:literal,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum ErrorCode {
+            $($code,)*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$code,)*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$code => stringify!($code),)*
+                }
+            }
+
+            /// 一行的简短描述
+            pub fn title(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$code => $title,)*
+                }
+            }
+
+            /// 详细说明（Markdown）
+            pub fn explanation(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$code => include_str!(concat!(
+                        "explanations/",
+                        stringify!($code),
+                        ".md"
+                    )),)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    E0001: "unterminated string literal",
+    E0002: "unterminated character literal",
+    E0003: "invalid escape sequence",
+    E0004: "invalid character literal",
+    E0005: "invalid number literal",
+    E0006: "unexpected character",
+    E0100: "unexpected token",
+    E0101: "struct field after an invariant",
+    E0102: "unclosed delimiter",
+    E0103: "misplaced attribute",
+    E0104: "invalid macro definition",
+    E0105: "cannot find macro",
+    E0106: "no macro rule matches the invocation",
+    E0107: "macro recursion limit reached",
+    E0108: "cannot derive trait",
+    E0109: "cannot include file",
+    E0110: "chained comparison operators",
+    E0111: "assignment used as a condition",
+    E0112: "generic arguments without `::`",
+    E0113: "invalid operator declaration",
+    E0114: "unary minus before a method call",
+    E0115: "invalid format string",
+    E0013: "constant refers to a static",
+    E0015: "non-constant initializer",
+    E0054: "cast to bool",
+    E0061: "wrong number of arguments",
+    E0070: "invalid left-hand side of assignment",
+    E0072: "recursive type has infinite size",
+    E0080: "evaluation of constant value failed",
+    E0119: "conflicting implementations",
+    E0133: "unsafe operation outside of an unsafe block",
+    E0161: "slice used by value",
+    E0267: "`break` or `continue` inside of a closure",
+    E0268: "`break` or `continue` outside of a loop",
+    E0275: "instantiation limit reached",
+    E0277: "invalid use of the `?` operator",
+    E0282: "type annotations needed",
+    E0297: "non-exhaustive patterns",
+    E0308: "mismatched types",
+    E0369: "binary operation on unsupported types",
+    E0364: "invalid export",
+    E0391: "cycle in const or static initializers",
+    E0405: "cannot find trait",
+    E0412: "cannot find type",
+    E0425: "cannot find value",
+    E0426: "use of undeclared label",
+    E0428: "name defined multiple times",
+    E0432: "unresolved import",
+    E0433: "failed to resolve a path",
+    E0515: "cannot return a reference to a local value",
+    E0516: "misplaced `typeof`",
+    E0517: "misplaced representation hint",
+    E0552: "unrecognized representation hint",
+    E0580: "`main` function has wrong type",
+    E0594: "assignment through a shared reference",
+    E0599: "no such variant or method",
+    E0600: "cannot negate an unsigned value",
+    E0601: "`main` function not found",
+    E0603: "private item",
+    E0604: "only `u8` can be cast as `char`",
+    E0605: "unsupported cast",
+    E0606: "invalid cast",
+    E0608: "cannot slice a value of this type",
+    E0609: "no such field",
+    E0614: "dereference of a non-pointer type",
+    E0635: "unknown feature",
+    E0658: "use of an experimental feature",
+    E0659: "ambiguous operator",
+    E0701: "impure contract condition",
+    E0702: "unknown attribute",
+    E0703: "invalid benchmark function",
+    E0704: "invalid layout intrinsic",
+    E0705: "invalid edition",
+    E0706: "literal out of range",
+    E0707: "invalid inline assembly",
+    E0708: "invalid intrinsic declaration",
+    E0709: "unsatisfied trait bound",
+    E0710: "side effect in a `pure` function",
+    E0711: "allocation in a `#[no_alloc]` function",
+    E0728: "`await` outside of an async context",
+    E0800: "cannot read input",
+    E0801: "import cycle",
+    E0802: "file is not a module of the project",
+    E0803: "program too large",
+    E0810: "cannot generate code",
+    E0850: "invalid manifest",
+    E0851: "unresolvable dependency",
+    E0900: "runtime error",
+    E0901: "contract violation",
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    /// 接受 `E0308`、`e0308` 和 `0308`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix(['E', 'e']).unwrap_or(s);
+        ErrorCode::ALL
+            .iter()
+            .find(|code| code.as_str()[1..] == *digits)
+            .copied()
+            .ok_or_else(|| format!("`{}` is not a valid error code", s))
+    }
+}
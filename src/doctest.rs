@@ -0,0 +1,325 @@
+// 文档测试（`contractus test --doc`）
+// 从文档注释（`///` 和模块开头的 `//!`）中提取用 ``` 围起来的代码块，逐个编译为字节码
+// 并在虚拟机中运行，运行时的输出不显示。代码块和它所在文件的条目一起编译（文件自己的
+// `main` 被去掉），所以可以直接使用被注释的函数和类型；没有定义 `main` 的代码块作为
+// `main` 的函数体。代码块的信息字符串：
+// - 空、`ctx` 或 `contractus`：编译并运行，出错（包括违反契约）时测试失败
+// - `should_fail`：编译或运行必须出错
+// - `ignore`：不运行
+// - 其他（如 `text`）：不是测试
+// 诊断信息的位置换算回文档注释中的行和列
+
+use crate::bytecode;
+use crate::diagnostic::Diagnostic;
+use crate::driver::{Compiler, Emit};
+use crate::lexer::Lexer;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expect {
+    Pass,
+    Fail, // `should_fail`
+    Ignore,
+}
+
+#[derive(Debug, Clone)]
+pub struct DocTest {
+    pub item: Option<String>, // 被注释的条目，模块文档为 None
+    pub line: u32,            // 开头的 ``` 所在的行
+    pub expect: Expect,
+    pub code: String,
+    lines: Vec<CodeLine>, // 代码块每一行在源文件中的位置
+}
+
+// 代码块的一行在源文件中开始的位置
+#[derive(Debug, Clone, Copy)]
+struct CodeLine {
+    line: u32,
+    column: u32,
+    offset: usize,
+}
+
+impl DocTest {
+    /// 测试的名字，形如 `add (line 3)`
+    pub fn name(&self) -> String {
+        match &self.item {
+            Some(item) => format!("{} (line {})", item, self.line),
+            None => format!("(line {})", self.line),
+        }
+    }
+}
+
+// 文档注释中的一行
+struct DocLine {
+    text: String,
+    line: u32,
+    column: u32, // 去掉 `///` 之后的文本在源文件中的列
+    offset: usize,
+    module: bool, // `//!`
+    end: usize,   // 注释在源文件中的结束位置
+}
+
+/// 提取源码中的所有文档测试，源码有词法错误时返回这些错误
+pub fn extract(source: &str) -> Result<Vec<DocTest>, Vec<Diagnostic>> {
+    let (tokens, comments) = Lexer::new(source)
+        .tokenize_with_comments()
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+
+    // 连续行上的同一种文档注释组成一段文档
+    let mut docs: Vec<Vec<DocLine>> = Vec::new();
+    for span in comments {
+        let Some(line) = doc_line(source, span) else {
+            continue;
+        };
+        match docs.last_mut() {
+            Some(doc)
+                if doc.last().is_some_and(|last| {
+                    last.line + 1 == line.line && last.module == line.module
+                }) =>
+            {
+                doc.push(line)
+            }
+            _ => docs.push(vec![line]),
+        }
+    }
+
+    let mut tests = Vec::new();
+    for doc in docs {
+        let item = if doc[0].module {
+            None
+        } else {
+            let end = doc.last().map_or(0, |line| line.end);
+            documented_item(&tokens, end)
+        };
+        code_blocks(&doc, item, &mut tests);
+    }
+    Ok(tests)
+}
+
+fn doc_line(source: &str, span: Span) -> Option<DocLine> {
+    let text = &source[span.start..span.end];
+    let (rest, module) = if let Some(rest) = text.strip_prefix("//!") {
+        (rest, true)
+    } else {
+        // `////...` 是普通注释
+        (
+            text.strip_prefix("///")
+                .filter(|rest| !rest.starts_with('/'))?,
+            false,
+        )
+    };
+    let rest = rest.trim_end_matches(['\r', '\n']);
+    let (rest, prefix) = match rest.strip_prefix(' ') {
+        Some(rest) => (rest, 4),
+        None => (rest, 3),
+    };
+    Some(DocLine {
+        text: rest.to_string(),
+        line: span.line,
+        column: span.column + prefix,
+        offset: span.start + prefix as usize,
+        module,
+        end: span.end,
+    })
+}
+
+// 文档之后的条目：第一个标识符，带上之前的关键字，如 `fn add`
+fn documented_item(tokens: &[Token], end: usize) -> Option<String> {
+    let start = tokens.iter().position(|token| token.span.start >= end)?;
+    let mut keyword = None;
+    for token in &tokens[start..] {
+        match &token.kind {
+            TokenKind::Ident(name) => {
+                return Some(match keyword {
+                    Some(keyword) => format!("{} {}", keyword, name),
+                    None => name.to_string(),
+                })
+            }
+            TokenKind::Fn => keyword = Some("fn"),
+            TokenKind::Struct => keyword = Some("struct"),
+            TokenKind::Enum => keyword = Some("enum"),
+            TokenKind::Pub => {}
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn code_blocks(doc: &[DocLine], item: Option<String>, tests: &mut Vec<DocTest>) {
+    let mut lines = doc.iter();
+    while let Some(open) = lines.next() {
+        let Some(info) = open.text.trim_start().strip_prefix("```") else {
+            continue;
+        };
+        let mut expect = Some(Expect::Pass);
+        for attribute in info.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            expect = match (attribute, expect) {
+                ("ctx" | "contractus", _) => expect,
+                ("should_fail", Some(_)) => Some(Expect::Fail),
+                ("ignore", Some(_)) => Some(Expect::Ignore),
+                _ => None,
+            };
+        }
+        // 没有结尾的 ``` 时代码块延续到文档结束
+        let mut code = Vec::new();
+        for line in lines.by_ref() {
+            if line.text.trim_start().starts_with("```") {
+                break;
+            }
+            code.push(line);
+        }
+        if let Some(expect) = expect {
+            tests.push(DocTest {
+                item: item.clone(),
+                line: open.line,
+                expect,
+                code: code
+                    .iter()
+                    .map(|line| line.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                lines: code
+                    .iter()
+                    .map(|line| CodeLine {
+                        line: line.line,
+                        column: line.column,
+                        offset: line.offset,
+                    })
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// 编译并运行一个文档测试，`source` 是它所在的文件。失败时返回的诊断信息的位置
+/// 是源文件中的位置
+pub fn run(source: &str, test: &DocTest) -> Result<(), Vec<Diagnostic>> {
+    if test.expect == Expect::Ignore {
+        return Ok(());
+    }
+    let (program, first_line) = program(source, test);
+    let result = Compiler::new()
+        .source(program)
+        .emit(Emit::Object)
+        .run()
+        .into_result()
+        .and_then(|artifact| {
+            let module = artifact.into_object().expect("object artifact");
+            execute(&module)
+        });
+    match (result, test.expect) {
+        (Ok(()), Expect::Fail) => Err(vec![Diagnostic::error(
+            "doc test was expected to fail but passed".to_string(),
+            Span::new(0, 0, test.line, 1),
+        )
+        .with_note("the code block is marked `should_fail`".to_string())]),
+        (Err(_), Expect::Fail) => Ok(()),
+        (result, _) => result.map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| test.remap(error, first_line))
+                .collect()
+        }),
+    }
+}
+
+fn execute(module: &bytecode::Module) -> Result<(), Vec<Diagnostic>> {
+    bytecode::run_with_output(module, std::io::sink()).0
+}
+
+// 测试程序：源文件去掉 `main` 之后接上代码块，返回代码块第一行在程序中的行号。
+// 源文件部分的行号不变
+fn program(source: &str, test: &DocTest) -> (String, u32) {
+    let mut program = without_main(source);
+    if !program.ends_with('\n') {
+        program.push('\n');
+    }
+    let wrap = !defines_main(&test.code);
+    if wrap {
+        program.push_str("fn main() {\n");
+    }
+    let first_line = program.matches('\n').count() as u32 + 1;
+    program.push_str(&test.code);
+    if wrap {
+        program.push_str("\n}");
+    }
+    program.push('\n');
+    (program, first_line)
+}
+
+fn defines_main(code: &str) -> bool {
+    Lexer::new(code).tokenize().is_ok_and(|tokens| {
+        tokens.windows(2).any(|pair| {
+            pair[0].kind == TokenKind::Fn && pair[1].kind == TokenKind::Ident("main".into())
+        })
+    })
+}
+
+// 把顶层的 `main` 函数替换为空白，保留换行
+fn without_main(source: &str) -> String {
+    let Ok(tokens) = Lexer::new(source).tokenize() else {
+        return source.to_string();
+    };
+    let mut depth = 0;
+    for (i, pair) in tokens.windows(2).enumerate() {
+        match pair[0].kind {
+            TokenKind::LeftBrace => depth += 1,
+            TokenKind::RightBrace => depth -= 1,
+            TokenKind::Fn if depth == 0 && pair[1].kind == TokenKind::Ident("main".into()) => {
+                let start = match i.checked_sub(1).map(|p| &tokens[p]) {
+                    Some(token) if token.kind == TokenKind::Pub => token.span.start,
+                    _ => pair[0].span.start,
+                };
+                let Some(end) = body_end(&tokens[i..]) else {
+                    return source.to_string();
+                };
+                let blank: String = source[start..end]
+                    .chars()
+                    .map(|c| if c == '\n' { '\n' } else { ' ' })
+                    .collect();
+                return format!("{}{}{}", &source[..start], blank, &source[end..]);
+            }
+            _ => {}
+        }
+    }
+    source.to_string()
+}
+
+// 函数体结尾的 `}` 之后的位置
+fn body_end(tokens: &[Token]) -> Option<usize> {
+    let open = tokens
+        .iter()
+        .position(|token| token.kind == TokenKind::LeftBrace)?;
+    let mut depth = 0;
+    for token in &tokens[open..] {
+        match token.kind {
+            TokenKind::LeftBrace => depth += 1,
+            TokenKind::RightBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(token.span.end);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+impl DocTest {
+    // 代码块中的位置换算为源文件中的位置
+    fn remap(&self, mut error: Diagnostic, first_line: u32) -> Diagnostic {
+        let Some(index) = error.span.line.checked_sub(first_line) else {
+            return error;
+        };
+        let Some(line) = self.lines.get(index as usize) else {
+            return error;
+        };
+        let column = error.span.column.max(1) - 1;
+        let len = error.span.end.saturating_sub(error.span.start);
+        let start = line.offset + column as usize;
+        error.span = Span::new(start, start + len, line.line, line.column + column);
+        error
+    }
+}
@@ -0,0 +1,573 @@
+// 编译驱动
+// 把各个阶段串成一条流水线，CLI、测试和嵌入方共用：
+//
+//     词法分析 → 加载（语法分析）→ 语义分析 → MIR 降级 → 单态化 → 优化 → 字节码
+//
+// 输入是一段源码（单个模块）、入口文件（连同它导入的模块和一起编译的其他文件）
+// 或者项目目录（目录下的所有源文件，入口是 `main.ctx`）。`emit` 决定流水线停在
+// 哪个阶段、返回什么产物；任何阶段出错都会停下，并返回到此为止的所有诊断信息。
+// 入口是文件时，诊断信息和字节码模块都带上文件名。
+// 项目的路径依赖挂载为命名空间，由 `with_dependencies` 先逐个检查；之后的阶段
+// 处理所有模块（包括依赖包）合并成的一个程序，见 `Crate::merged_program`。
+// 读入的文件记录在 crate 的源文件表（`SourceMap`）中，`include!` 系列宏相对于写着调用的文件读取文件，
+// 拼接的代码中的诊断信息由它找回所在的文件。
+// 各阶段由 `timing::time` 包起来，`-Ztime-passes` 时统计它们的时间和内存，debug 日志中是各阶段的 span
+//
+// 编译是确定性的：同样的输入和选项总是得到逐字节相同的产物。各阶段只用 HashMap 查找，
+// 需要遍历的表用 BTreeMap 或按声明顺序的 Vec，目录按路径排序。字节码模块带有输入和
+// 选项的内容哈希（`content_hash`），构建缓存和包管理按它判断产物是否需要重新生成；
+// `verify_determinism` 编译两次并比较结果（`--verify-determinism`）。
+// 设置了 `incremental` 时，各阶段先在查询缓存中查找结果（incremental.rs）：
+// 没有变化的 crate 跳过语义分析，直接取出上次的字节码；只降级有变化的函数。
+// 嵌入方用 `limits` 限制每个源文件的大小、记号数和语法树节点数（limits.rs）
+
+use crate::bytecode;
+use crate::diagnostic::{self, Diagnostic, ErrorCode};
+use crate::incremental::QueryCache;
+use crate::log;
+use crate::manifest::{self, ResolvedDependency};
+use crate::mir::{self, transform, LowerOptions, MirProgram};
+use crate::module::{self, Crate, ModuleLoader};
+use crate::sema::SemanticAnalyzer;
+use crate::span::Span;
+use crate::timing;
+use crate::token::Token;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub use crate::limits::CompileLimits;
+pub use crate::mir::transform::OptLevel;
+pub use crate::mir::{ContractMode, PanicStrategy};
+pub use crate::source_map::SourceMap;
+
+/// 流水线停下的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emit {
+    /// 根模块的词法单元
+    Tokens,
+    /// 加载的所有模块的语法树，不做语义分析
+    Ast,
+    /// 通过名称解析和类型检查的语法树，解释器执行它。
+    /// 与 `check` 一样也做 MIR 降级和单态化，解释器不执行虚拟机拒绝的程序
+    Hir,
+    /// 优化后的 MIR
+    Mir,
+    /// 字节码模块，可以直接执行、写成 `.ctxb` 或链接为可执行文件
+    #[default]
+    Object,
+}
+
+#[derive(Debug, Clone)]
+pub enum Artifact {
+    Tokens(Vec<Token>),
+    Ast(Crate),
+    Hir(Crate),
+    Mir(MirProgram),
+    Object(bytecode::Module),
+}
+
+impl Artifact {
+    pub fn into_tokens(self) -> Option<Vec<Token>> {
+        match self {
+            Artifact::Tokens(tokens) => Some(tokens),
+            _ => None,
+        }
+    }
+
+    /// `Ast` 和 `Hir` 阶段的语法树
+    pub fn into_crate(self) -> Option<Crate> {
+        match self {
+            Artifact::Ast(krate) | Artifact::Hir(krate) => Some(krate),
+            _ => None,
+        }
+    }
+
+    pub fn into_mir(self) -> Option<MirProgram> {
+        match self {
+            Artifact::Mir(program) => Some(program),
+            _ => None,
+        }
+    }
+
+    pub fn into_object(self) -> Option<bytecode::Module> {
+        match self {
+            Artifact::Object(module) => Some(module),
+            _ => None,
+        }
+    }
+}
+
+/// 一次编译的结果：出错时没有产物
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub artifact: Option<Artifact>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Output {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn into_result(self) -> Result<Artifact, Vec<Diagnostic>> {
+        match self.artifact {
+            Some(artifact) if !self.has_errors() => Ok(artifact),
+            _ => Err(self.diagnostics),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Input {
+    Source(String),
+    Files(Vec<PathBuf>), // 第一个是入口文件
+    Dir(PathBuf),
+}
+
+/// 编译器配置，按构建器的方式设置后调用 `run`
+#[derive(Debug, Clone)]
+pub struct Compiler {
+    input: Option<Input>,
+    opt_level: OptLevel,
+    emit: Emit,
+    bounds_checks: bool,
+    contracts: ContractMode,
+    panic: PanicStrategy,
+    packages: Vec<(String, PathBuf)>, // 依赖包的名字和入口文件
+    checked: BTreeSet<String>,        // 已经检查过、语义分析时跳过的依赖包
+    incremental: Option<Arc<QueryCache>>,
+    limits: CompileLimits,
+}
+
+// 增量编译时的查询缓存和 crate 的内容哈希
+type Query<'a> = Option<(&'a QueryCache, u64)>;
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            input: None,
+            opt_level: OptLevel::default(),
+            emit: Emit::default(),
+            bounds_checks: true,
+            contracts: ContractMode::default(),
+            panic: PanicStrategy::default(),
+            packages: Vec::new(),
+            checked: BTreeSet::new(),
+            incremental: None,
+            limits: CompileLimits::default(),
+        }
+    }
+
+    /// 编译一段源码，不能导入其他模块
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.input = Some(Input::Source(source.into()));
+        self
+    }
+
+    /// 编译入口文件，导入的模块相对于它所在的目录查找
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input = Some(Input::Files(vec![path.into()]));
+        self
+    }
+
+    /// 一起编译多个文件：第一个是入口，其他文件按相对于入口所在目录的路径成为模块
+    pub fn files<P: Into<PathBuf>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.input = Some(Input::Files(paths.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// 编译目录下的所有源文件（`--root`）
+    pub fn root(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.input = Some(Input::Dir(dir.into()));
+        self
+    }
+
+    pub fn opt_level(mut self, level: OptLevel) -> Self {
+        self.opt_level = level;
+        self
+    }
+
+    pub fn emit(mut self, emit: Emit) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    /// false 时删除 MIR 中的下标越界检查（`--no-bounds-check`）
+    pub fn bounds_checks(mut self, enabled: bool) -> Self {
+        self.bounds_checks = enabled;
+        self
+    }
+
+    pub fn contracts(mut self, mode: ContractMode) -> Self {
+        self.contracts = mode;
+        self
+    }
+
+    /// panic 时展开（默认）还是立即结束程序（`--panic=abort`）
+    pub fn panic(mut self, strategy: PanicStrategy) -> Self {
+        self.panic = strategy;
+        self
+    }
+
+    /// 增量编译：各阶段的结果记忆在 `cache` 中，多次编译可以共用一个缓存
+    pub fn incremental(mut self, cache: Arc<QueryCache>) -> Self {
+        self.incremental = Some(cache);
+        self
+    }
+
+    /// 资源限制：超出时报告 "program too large"，错误数超出时只返回前面的错误
+    pub fn limits(mut self, limits: CompileLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 挂载依赖包：它的入口模块可以用 `import name;` 导入，其他模块是 `name::...`
+    pub fn dependency(mut self, name: impl Into<String>, entry: impl Into<PathBuf>) -> Self {
+        self.packages.push((name.into(), entry.into()));
+        self
+    }
+
+    /// 挂载项目的所有依赖包（`Manifest::resolve_deps` 的结果），并按依赖顺序检查它们。
+    ///
+    /// 每个包连同它的依赖作为一个项目检查，通过后把源码指纹写到 `cache_dir`；
+    /// 包和它的依赖都没有变化时不再检查。之后编译项目时跳过依赖包的语义分析。
+    /// 返回重新检查过的包名；某个包有错误时停下，返回它的诊断信息
+    pub fn with_dependencies(
+        mut self,
+        deps: &[ResolvedDependency],
+        cache_dir: &Path,
+    ) -> Result<(Self, Vec<String>), Vec<Diagnostic>> {
+        let mut fingerprints: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut rebuilt = Vec::new();
+        for dep in deps {
+            // 依赖包的指纹包含它的依赖的指纹，被依赖的包变化时依赖方也要重新检查
+            let fingerprint =
+                dep.manifest
+                    .deps
+                    .keys()
+                    .fold(dep.manifest.fingerprint(), |hash, name| {
+                        let inner = fingerprints.get(name.as_str()).copied().unwrap_or_default();
+                        hash.rotate_left(5) ^ inner
+                    });
+            fingerprints.insert(&dep.name, fingerprint);
+
+            let stamp = cache_dir.join(format!("{}.fingerprint", dep.name));
+            let recorded = fs::read_to_string(&stamp).ok();
+            if recorded.as_deref().map(str::trim) != Some(format!("{:016x}", fingerprint).as_str())
+            {
+                log::info!("checking package `{}`", dep.name);
+                let mut compiler = Compiler::new()
+                    .file(dep.manifest.entry_path())
+                    .contracts(self.contracts)
+                    .limits(self.limits);
+                compiler.packages = self.packages.clone();
+                compiler.checked = self.checked.clone();
+                let errors = compiler.check();
+                if !errors.is_empty() {
+                    return Err(errors);
+                }
+                // 记录失败只会导致下次重新检查
+                let _ = fs::create_dir_all(cache_dir)
+                    .and_then(|_| fs::write(&stamp, format!("{:016x}\n", fingerprint)));
+                rebuilt.push(dep.name.clone());
+            } else {
+                log::debug!("package `{}` is up to date", dep.name);
+            }
+
+            self.packages
+                .push((dep.name.clone(), dep.manifest.entry_path()));
+            self.checked.insert(dep.name.clone());
+        }
+        Ok((self, rebuilt))
+    }
+
+    pub fn run(&self) -> Output {
+        let (artifact, diagnostics) = match self.pipeline() {
+            Ok(artifact) => (Some(artifact), Vec::new()),
+            Err(diagnostics) => (None, self.limits.truncate(diagnostic::dedup(diagnostics))),
+        };
+        Output {
+            artifact,
+            diagnostics,
+        }
+    }
+
+    fn pipeline(&self) -> Result<Artifact, Vec<Diagnostic>> {
+        match self.emit {
+            Emit::Tokens => return self.tokenize().map(Artifact::Tokens),
+            Emit::Ast => return self.load().map(Artifact::Ast),
+            Emit::Hir | Emit::Mir | Emit::Object => {}
+        }
+        let krate = self.load()?;
+        let query = self.query(&krate);
+        if let (Emit::Object, Some((cache, hash))) = (self.emit, query) {
+            if let Some(mut module) = cache.object(hash) {
+                module.file = self.file_name();
+                return Ok(Artifact::Object(module));
+            }
+        }
+        let checked = self.analyze(&krate, query)?;
+        if self.emit == Emit::Hir {
+            if !checked {
+                self.lower(&krate, query)
+                    .map_err(|errors| self.attach_file(&krate, errors))?;
+            }
+            return Ok(Artifact::Hir(krate));
+        }
+        self.codegen(&krate, query)
+            .map_err(|errors| self.attach_file(&krate, errors))
+    }
+
+    /// 编译两次并比较产物和诊断信息，不同时返回第一处差异的位置
+    ///
+    /// 字节码比较编码后的字节，MIR 比较文本，其他产物比较调试输出。
+    /// 同一进程中每个 HashMap 的遍历顺序也不同，遍历顺序泄漏到产物中时两次的结果会不一样
+    pub fn verify_determinism(&self) -> Result<Output, String> {
+        // 两次都要真正编译，不从缓存中取结果
+        let compiler = Compiler {
+            incremental: None,
+            ..self.clone()
+        };
+        let first = compiler.run();
+        let second = compiler.run();
+        let (a, b) = (render(&first), render(&second));
+        if a == b {
+            return Ok(first);
+        }
+        let offset = a
+            .iter()
+            .zip(&b)
+            .position(|(x, y)| x != y)
+            .unwrap_or(a.len().min(b.len()));
+        Err(format!(
+            "compilation is not deterministic: the outputs of two runs differ at byte {}",
+            offset
+        ))
+    }
+
+    /// 输入和编译选项的内容哈希（64 位 FNV-1a）：编译器版本、选项、依赖包，以及读入的
+    /// 每个文件相对于项目根目录的路径和内容。与编译在哪个目录、哪台机器上进行无关
+    pub fn content_hash(&self, krate: &Crate) -> u64 {
+        let options = format!(
+            "contractus {}\0{:?}\0{}\0{:?}\0{:?}\0",
+            env!("CARGO_PKG_VERSION"),
+            self.opt_level,
+            self.bounds_checks,
+            self.contracts,
+            self.panic
+        );
+        let mut hash = manifest::fnv1a(manifest::FNV_OFFSET, options.as_bytes());
+        for (name, _) in &self.packages {
+            hash = manifest::fnv1a(hash, name.as_bytes());
+            hash = manifest::fnv1a(hash, &[0]);
+        }
+        if let Some(Input::Source(source)) = &self.input {
+            hash = manifest::fnv1a(hash, source.as_bytes());
+        }
+        for file in krate.sources.files() {
+            let relative = file.path.strip_prefix(&krate.root).unwrap_or(&file.path);
+            for bytes in [
+                relative.to_string_lossy().as_bytes(),
+                &[0],
+                &file.contents,
+                &[0],
+            ] {
+                hash = manifest::fnv1a(hash, bytes);
+            }
+        }
+        hash
+    }
+
+    /// 只检查，不优化也不生成代码（`contractus check`），返回所有诊断信息
+    ///
+    /// 除了语义分析，还做 MIR 降级和单态化，它们会报告 `break` 在循环外、
+    /// 泛型参数无法推断等错误。忽略 `emit` 的设置。
+    pub fn check(&self) -> Vec<Diagnostic> {
+        let result = self.load().and_then(|krate| {
+            let query = self.query(&krate);
+            if self.analyze(&krate, query)? {
+                return Ok(());
+            }
+            self.lower(&krate, query)
+                .map(drop)
+                .map_err(|errors| self.attach_file(&krate, errors))
+        });
+        self.limits
+            .truncate(diagnostic::dedup(result.err().unwrap_or_default()))
+    }
+
+    fn query<'a>(&'a self, krate: &Crate) -> Query<'a> {
+        let cache = self.incremental.as_deref()?;
+        Some((cache, self.content_hash(krate)))
+    }
+
+    // 语义分析；crate 上次已经通过检查时跳过，返回 true
+    fn analyze(&self, krate: &Crate, query: Query) -> Result<bool, Vec<Diagnostic>> {
+        if query.is_some_and(|(cache, hash)| cache.is_checked(hash)) {
+            log::debug!("skipping semantic analysis of an unchanged crate");
+            return Ok(true);
+        }
+        let mut analyzer = SemanticAnalyzer::new();
+        for package in &self.checked {
+            analyzer.skip_package(package.clone());
+        }
+        timing::time("semantic analysis", || analyzer.analyze_crate(krate))
+            .map_err(|errors| krate.sources.locate(errors))?;
+        Ok(false)
+    }
+
+    // 之后的阶段的诊断信息补上根模块的文件名；`include!` 拼接的代码中的错误指向被拼接的文件
+    fn attach_file(&self, krate: &Crate, errors: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let file = self.file_name();
+        let errors = errors
+            .into_iter()
+            .map(|error| match &file {
+                Some(file) if error.file.is_none() => error.with_file(file.clone()),
+                _ => error,
+            })
+            .collect();
+        krate.sources.locate(errors)
+    }
+
+    // 降级和单态化通过后，crate 就通过了检查
+    fn lower(&self, krate: &Crate, query: Query) -> Result<MirProgram, Vec<Diagnostic>> {
+        let options = LowerOptions {
+            contracts: self.contracts,
+            panic: self.panic,
+        };
+        let program = &*krate.merged_program();
+        let lowered = timing::time("MIR lowering", || match query {
+            Some((cache, _)) => mir::lower_program_incremental(program, options, cache),
+            None => mir::lower_program_with(program, options),
+        })?;
+        let program = timing::time("monomorphization", || mir::monomorphize(&lowered))?;
+        if let Some((cache, hash)) = query {
+            cache.set_checked(hash);
+        }
+        Ok(program)
+    }
+
+    fn codegen(&self, krate: &Crate, query: Query) -> Result<Artifact, Vec<Diagnostic>> {
+        let mut program = self.lower(krate, query)?;
+        if !self.bounds_checks {
+            transform::remove_all_bounds_checks(&mut program);
+        }
+        timing::time("optimization", || {
+            transform::optimize(&mut program, self.opt_level)
+        });
+        if self.emit == Emit::Mir {
+            return Ok(Artifact::Mir(program));
+        }
+
+        let mut module = timing::time("bytecode generation", || bytecode::compile(&program))?;
+        module.file = self.file_name();
+        match query {
+            Some((cache, hash)) => {
+                module.hash = hash;
+                cache.set_object(&module);
+            }
+            None => module.hash = self.content_hash(krate),
+        }
+        Ok(Artifact::Object(module))
+    }
+
+    // 只处理入口文件
+    fn tokenize(&self) -> Result<Vec<Token>, Vec<Diagnostic>> {
+        if let Some(Input::Source(source)) = &self.input {
+            return module::tokenize_with(source, &self.limits);
+        }
+        let path = self.entry().ok_or_else(no_input)?;
+        let display = path.display().to_string();
+        let size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        self.limits
+            .check_file_size(size)
+            .map_err(|error| vec![error.with_file(display.clone())])?;
+        let source = fs::read_to_string(&path).map_err(|err| {
+            vec![Diagnostic::error(
+                format!("cannot read `{}`: {}", display, err),
+                Span::new(0, 0, 1, 1),
+            )
+            .with_code(ErrorCode::E0800)
+            .with_file(display.clone())]
+        })?;
+        module::tokenize_with(&source, &self.limits).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| error.with_file(display.clone()))
+                .collect()
+        })
+    }
+
+    fn load(&self) -> Result<Crate, Vec<Diagnostic>> {
+        let krate = timing::time("loading", || self.load_modules())?;
+        log::debug!("loaded {} module(s)", krate.modules.len());
+        Ok(krate)
+    }
+
+    fn load_modules(&self) -> Result<Crate, Vec<Diagnostic>> {
+        match &self.input {
+            Some(Input::Source(source)) => {
+                module::parse_source_with(source, &self.limits).map(Crate::from_program)
+            }
+            Some(Input::Files(files)) => match files.split_first() {
+                Some((entry, rest)) => {
+                    let root = entry.parent().map(Path::to_path_buf).unwrap_or_default();
+                    self.loader(root).load_files(entry, rest)
+                }
+                None => Err(no_input()),
+            },
+            Some(Input::Dir(dir)) => self.loader(dir.clone()).load_all(),
+            None => Err(no_input()),
+        }
+    }
+
+    fn loader(&self, root: PathBuf) -> ModuleLoader {
+        self.packages.iter().fold(
+            ModuleLoader::new(root).with_limits(self.limits),
+            |loader, (name, entry)| loader.with_package(name.clone(), entry.clone()),
+        )
+    }
+
+    /// 入口文件的路径，输入是源码时为 None
+    pub fn entry(&self) -> Option<PathBuf> {
+        match &self.input {
+            Some(Input::Files(files)) => files.first().cloned(),
+            Some(Input::Dir(dir)) => Some(dir.join(module::ENTRY_FILE)),
+            _ => None,
+        }
+    }
+
+    fn file_name(&self) -> Option<String> {
+        self.entry().map(|path| path.display().to_string())
+    }
+}
+
+// `verify_determinism` 比较的内容
+fn render(output: &Output) -> Vec<u8> {
+    let mut bytes = match &output.artifact {
+        Some(Artifact::Object(module)) => bytecode::encode(module),
+        Some(Artifact::Mir(program)) => program.to_string().into_bytes(),
+        Some(artifact) => format!("{:#?}", artifact).into_bytes(),
+        None => Vec::new(),
+    };
+    for diagnostic in &output.diagnostics {
+        bytes.extend_from_slice(format!("{}\n", diagnostic).as_bytes());
+    }
+    bytes
+}
+
+fn no_input() -> Vec<Diagnostic> {
+    vec![Diagnostic::error(
+        "no input: call `source` or `file` first".to_string(),
+        Span::new(0, 0, 1, 1),
+    )
+    .with_code(ErrorCode::E0800)]
+}
@@ -0,0 +1,131 @@
+// 语言特性开关和版本
+// 实验性的语法要在文件开头用 `#![feature(name, ...)]` 开启才能使用，开启只对所在的文件有效；
+// 没有开启时使用这些语法报告 E0658，说明需要开启的特性：
+//
+//     custom_operators   自定义中缀运算符 `operator <*> (precedence 70, left)`
+//     async_await        `async fn`、`async { ... }` 和 `expr.await`，开启后 `async` 和 `await`
+//                        不能用作名字。在协程的实现之前按同步的方式执行：调用 async 函数
+//                        直接运行到结束，`async` 块立即求值，`.await` 得到操作数的值
+//     asm                内联汇编 `asm!("...", in(reg) x, out(reg) y)`，只能写在 unsafe 块中。
+//                        解释器和字节码后端都不能执行汇编：解释器执行到它时报错，
+//                        编译为字节码时报告 E0810；MIR 中保留操作数，留给生成机器码的后端
+//
+// 版本（edition）用 `#![edition(2025)]` 选择，同样只对所在的文件有效，没有写时是 2024。
+// 新版本可以做不兼容的改动：
+//
+//     2025   `async` 和 `await` 成为保留的关键字，不能用作名字
+
+use crate::token::{Token, TokenKind};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    CustomOperators,
+    AsyncAwait,
+    InlineAsm,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::CustomOperators,
+        Feature::AsyncAwait,
+        Feature::InlineAsm,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::CustomOperators => "custom_operators",
+            Feature::AsyncAwait => "async_await",
+            Feature::InlineAsm => "asm",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Feature::ALL
+            .iter()
+            .copied()
+            .find(|feature| feature.name() == name)
+            .ok_or_else(|| format!("unknown feature `{}`", name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Edition {
+    #[default]
+    E2024,
+    E2025,
+}
+
+impl Edition {
+    pub const ALL: &'static [Edition] = &[Edition::E2024, Edition::E2025];
+    pub const LATEST: Edition = Edition::E2025;
+
+    pub fn year(self) -> u32 {
+        match self {
+            Edition::E2024 => 2024,
+            Edition::E2025 => 2025,
+        }
+    }
+
+    /// 这个版本中保留的、不能用作名字的词
+    pub fn reserved_words(self) -> &'static [&'static str] {
+        match self {
+            Edition::E2024 => &[],
+            Edition::E2025 => &["async", "await"],
+        }
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.year())
+    }
+}
+
+impl FromStr for Edition {
+    type Err = String;
+
+    fn from_str(year: &str) -> Result<Self, Self::Err> {
+        Edition::ALL
+            .iter()
+            .copied()
+            .find(|edition| edition.year().to_string() == year)
+            .ok_or_else(|| format!("unknown edition `{}`", year))
+    }
+}
+
+/// 记号序列开头的 `#![feature(...)]` 开启的特性，忽略不认识的名字。
+/// 给不经过语法分析的地方使用（格式化器、模块加载器），语法分析器自己解析并报告错误
+pub fn scan(tokens: &[Token]) -> Vec<Feature> {
+    let mut features = Vec::new();
+    let mut i = 0;
+    while let [hash, bang, open, name, ..] = tokens.get(i..).unwrap_or_default() {
+        let inner = hash.kind == TokenKind::Hash
+            && bang.kind == TokenKind::LogicalNot
+            && open.kind == TokenKind::LeftBracket;
+        if !inner {
+            break;
+        }
+        let feature = matches!(&name.kind, TokenKind::Ident(name) if name == "feature");
+        i += 3;
+        while i < tokens.len() && tokens[i].kind != TokenKind::RightBracket {
+            if let (true, TokenKind::Ident(name)) = (feature, &tokens[i].kind) {
+                features.extend(name.parse::<Feature>());
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    features
+}
@@ -0,0 +1,335 @@
+// 格式字符串
+// `format!("x = {}, y = {:>8.2}", x, y)` 和 `println!(...)` 的第一个参数是格式字符串：
+// 文本原样输出，`{{` 和 `}}` 输出花括号，`{...}` 是占位符。占位符按顺序（`{}`）、
+// 按位置（`{0}`）或按名字（`{x}`，调用处可见的变量）引用参数，冒号之后是格式说明：
+//
+//     [[fill]align][+][#][0][width][.precision][type]
+//
+// - align 是 `<`（左对齐）、`>`（右对齐）或 `^`（居中），fill 是填充字符，默认是空格；
+//   不指定时数字右对齐，其他值左对齐。宽度按字符计
+// - `+` 给非负的数字加上正号，`0` 在符号之后用零补足宽度，`#` 给 x、b、o 加上 `0x`、`0b`、`0o`
+// - precision 是浮点数的小数位数，或者字符串最多保留的字符数
+// - type 为空时按 print 的格式输出，`?` 按嵌套的格式输出（字符串和字符带引号），
+//   `x`、`X`、`b`、`o` 把整数输出为十六进制、二进制和八进制，负数带负号
+// 格式字符串在宏展开时解析（macros.rs），每个占位符变为一次运行时的格式化调用；
+// 语义分析按格式说明检查参数的类型，两个后端都用 `format` 输出
+
+use crate::ast::Type;
+use crate::builtins;
+use std::str::FromStr;
+
+/// 格式字符串的一段
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Placeholder(Argument, String), // 格式说明的原文，已经检查过
+}
+
+/// 占位符引用的参数
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Next,         // `{}`：下一个按顺序的参数
+    Index(usize), // `{0}`
+    Name(String), // `{x}`：调用处的变量
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Auto, // 数字右对齐，其他值左对齐
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kind {
+    #[default]
+    Display,
+    Debug,
+    LowerHex,
+    UpperHex,
+    Binary,
+    Octal,
+}
+
+/// 占位符冒号之后的格式说明
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub fill: char,
+    pub align: Align,
+    pub plus: bool,
+    pub alternate: bool,
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+    pub kind: Kind,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            fill: ' ',
+            align: Align::Auto,
+            plus: false,
+            alternate: false,
+            zero: false,
+            width: 0,
+            precision: None,
+            kind: Kind::Display,
+        }
+    }
+}
+
+/// 格式说明对参数类型的要求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    Any,
+    Number,        // `+` 和 `0`
+    Integer,       // x、X、b、o
+    FloatOrString, // 精度
+}
+
+impl Requirement {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Requirement::Any => "a value",
+            Requirement::Number => "a number",
+            Requirement::Integer => "an integer",
+            Requirement::FloatOrString => "a float or a string",
+        }
+    }
+
+    /// 类型不符合时返回 false；类型变量和用户定义的类型无法判断，都接受
+    pub fn accepts(self, ty: &Type) -> bool {
+        let float = matches!(ty, Type::F32 | Type::F64);
+        let unknown = match ty {
+            Type::Infer | Type::Never => true,
+            Type::Named(name) | Type::Generic(name, _) => !builtins::is_type(name),
+            _ => false,
+        };
+        unknown
+            || match self {
+                Requirement::Any => true,
+                Requirement::Number => builtins::is_integer(ty) || float,
+                Requirement::Integer => builtins::is_integer(ty),
+                Requirement::FloatOrString => float || *ty == Type::String,
+            }
+    }
+}
+
+/// 解析格式字符串；出错时返回错误信息
+pub fn parse(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err("unmatched `}` in format string".to_string()),
+            '{' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => return Err("unclosed `{` in format string".to_string()),
+                        Some(c) => inner.push(c),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                let (argument, spec) = inner.split_once(':').unwrap_or((&inner, ""));
+                spec.parse::<Spec>()?;
+                pieces.push(Piece::Placeholder(argument.parse()?, spec.to_string()));
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+impl FromStr for Argument {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            Ok(Argument::Next)
+        } else if let Ok(index) = text.parse() {
+            Ok(Argument::Index(index))
+        } else if text.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && text.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            Ok(Argument::Name(text.to_string()))
+        } else {
+            Err(format!("invalid argument `{}` in format string", text))
+        }
+    }
+}
+
+impl FromStr for Spec {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut spec = Spec::default();
+        let align = |c: char| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+        let mut i = 0;
+        if let Some(a) = chars.get(1).and_then(|c| align(*c)) {
+            spec.fill = chars[0];
+            spec.align = a;
+            i = 2;
+        } else if let Some(a) = chars.first().and_then(|c| align(*c)) {
+            spec.align = a;
+            i = 1;
+        }
+        if chars.get(i) == Some(&'+') {
+            spec.plus = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'#') {
+            spec.alternate = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            spec.zero = true;
+            i += 1;
+        }
+        let digits = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+            let digits: String = chars[start..*i].iter().collect();
+            digits.parse::<usize>().ok()
+        };
+        spec.width = digits(&mut i).unwrap_or(0);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            match digits(&mut i) {
+                Some(precision) => spec.precision = Some(precision),
+                None => return Err(format!("expected a precision after `.` in `{{:{}}}`", text)),
+            }
+        }
+        let kind: String = chars[i..].iter().collect();
+        spec.kind = match kind.as_str() {
+            "" => Kind::Display,
+            "?" => Kind::Debug,
+            "x" => Kind::LowerHex,
+            "X" => Kind::UpperHex,
+            "b" => Kind::Binary,
+            "o" => Kind::Octal,
+            _ => {
+                return Err(format!(
+                    "unknown format `{}` in `{{:{}}}`; expected `?`, `x`, `X`, `b` or `o`",
+                    kind, text
+                ))
+            }
+        };
+        Ok(spec)
+    }
+}
+
+impl Spec {
+    /// 参数必须满足的要求，检查时按这个顺序取第一条
+    pub fn requirement(&self) -> Requirement {
+        if !matches!(self.kind, Kind::Display | Kind::Debug) {
+            Requirement::Integer
+        } else if self.precision.is_some() {
+            Requirement::FloatOrString
+        } else if self.plus || self.zero {
+            Requirement::Number
+        } else {
+            Requirement::Any
+        }
+    }
+}
+
+/// 要格式化的值：数字按值，其他值已经按 print 的格式（`{:?}` 时按嵌套的格式）转换为文本
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// 按格式说明输出一个值；参数不符合格式说明时返回错误信息
+pub fn format(spec: &Spec, arg: Arg) -> Result<String, String> {
+    let radix = match spec.kind {
+        Kind::LowerHex | Kind::UpperHex => Some((16, "0x")),
+        Kind::Binary => Some((2, "0b")),
+        Kind::Octal => Some((8, "0o")),
+        Kind::Display | Kind::Debug => None,
+    };
+    // 符号和前缀在补零的位置之前
+    let (numeric, sign, marker, body) = match (&arg, radix) {
+        (Arg::Int(n), Some((radix, marker))) => {
+            let magnitude = n.unsigned_abs();
+            let mut digits = match radix {
+                16 => format!("{:x}", magnitude),
+                2 => format!("{:b}", magnitude),
+                _ => format!("{:o}", magnitude),
+            };
+            if spec.kind == Kind::UpperHex {
+                digits = digits.to_uppercase();
+            }
+            let marker = if spec.alternate { marker } else { "" };
+            (true, *n < 0, marker, digits)
+        }
+        (_, Some(_)) => {
+            return Err("only integers can be formatted with `x`, `X`, `b` or `o`".to_string())
+        }
+        (Arg::Int(n), None) => (true, *n < 0, "", n.unsigned_abs().to_string()),
+        (Arg::Float(x), None) => {
+            let magnitude = match spec.precision {
+                Some(precision) => format!("{:.*}", precision, x.abs()),
+                None => x.abs().to_string(),
+            };
+            (true, x.is_sign_negative(), "", magnitude)
+        }
+        (Arg::Text(text), None) => {
+            let text = match spec.precision {
+                Some(precision) => text.chars().take(precision).collect(),
+                None => text.clone(),
+            };
+            (false, false, "", text)
+        }
+    };
+    let sign = match (sign, spec.plus && numeric) {
+        (true, _) => "-",
+        (false, true) => "+",
+        (false, false) => "",
+    };
+    let prefix = format!("{}{}", sign, marker);
+
+    let len = prefix.chars().count() + body.chars().count();
+    let padding = spec.width.saturating_sub(len);
+    if numeric && spec.zero {
+        return Ok(format!("{}{}{}", prefix, "0".repeat(padding), body));
+    }
+    let fill = |n: usize| spec.fill.to_string().repeat(n);
+    let (before, after) = match spec.align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+        Align::Auto if numeric => (padding, 0),
+        Align::Auto => (0, padding),
+    };
+    Ok(format!("{}{}{}{}", fill(before), prefix, body, fill(after)))
+}
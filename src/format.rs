@@ -0,0 +1,104 @@
+// Contractus 代码格式化（`contractus fmt`）
+// 在保留注释的记号流上工作，不经过语法树：注释、空行（连续的空行合并为一行）
+// 和字面量的写法（进制、转义）都原样保留。规则：
+// 1. 缩进 4 个空格；代码块中每条语句一行，结构体和枚举的定义、match 的分支每项一行
+// 2. 二元运算符两边有空格；一元运算符之后、调用和下标的括号之前、逗号和分号之前没有空格
+// 3. 圆括号、方括号和结构体字面量放得下时写在一行并去掉末尾的逗号，
+//    超过行宽时每项一行并补上末尾的逗号
+// 4. 契约子句每条一行，以逗号结尾，函数体的 `{` 另起一行
+// 5. 属性 `#[...]` 单独一行
+// 格式化之后的记号序列必须和原来相同（末尾的逗号除外），否则报告错误而不是输出
+
+mod layout;
+mod printer;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Lexer;
+use crate::module;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+/// 默认的行宽
+pub const DEFAULT_WIDTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_WIDTH,
+        }
+    }
+}
+
+/// 格式化一段源码。有词法或语法错误的源码不格式化，返回这些错误
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String, Vec<Diagnostic>> {
+    module::parse_syntax(source)?;
+    let (mut tokens, comments) = Lexer::new(source)
+        .tokenize_with_comments()
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    tokens.pop(); // EOF
+    layout::split_generic_ends(&mut tokens);
+
+    let layout = layout::analyze(&tokens, &comments);
+    let output = printer::Printer::new(source, &tokens, &comments, &layout, options.width).print();
+
+    if !same_tokens(source, &output) {
+        let span = Span::new(0, 0, 1, 1);
+        return Err(vec![Diagnostic::error(
+            "formatting would change the meaning of this file; it was left unchanged".to_string(),
+            span,
+        )
+        .with_note("this is a bug in the formatter".to_string())]);
+    }
+    Ok(output)
+}
+
+/// 源码是否已经格式化
+pub fn is_formatted(source: &str, options: &FormatOptions) -> Result<bool, Vec<Diagnostic>> {
+    format_source(source, options).map(|output| output == source)
+}
+
+// 比较格式化前后的记号和注释。右括号之前的逗号和契约子句之后的逗号可以增减，
+// `>>` 和 `> >` 在泛型中相同，`>>=` 和 `>> =` 在泛型结尾相同
+fn same_tokens(before: &str, after: &str) -> bool {
+    let tokens = |source: &str| -> Option<(Vec<TokenKind>, Vec<String>)> {
+        let (tokens, comments) = Lexer::new(source).tokenize_with_comments().ok()?;
+        let comments = comments
+            .iter()
+            .map(|span| source[span.start..span.end].trim_end().to_string())
+            .collect();
+        Some((normalize(&tokens), comments))
+    };
+    match (tokens(before), tokens(after)) {
+        (Some(before), Some(after)) => before == after,
+        _ => false,
+    }
+}
+
+fn normalize(tokens: &[Token]) -> Vec<TokenKind> {
+    let mut kinds: Vec<TokenKind> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        match &token.kind {
+            TokenKind::RightShift => kinds.extend([TokenKind::Greater, TokenKind::Greater]),
+            TokenKind::RightShiftAssign => {
+                kinds.extend([TokenKind::Greater, TokenKind::Greater, TokenKind::Assign])
+            }
+            TokenKind::GreaterEqual => kinds.extend([TokenKind::Greater, TokenKind::Assign]),
+            TokenKind::RightParen
+            | TokenKind::RightBracket
+            | TokenKind::RightBrace
+            | TokenKind::LeftBrace
+                if kinds.last() == Some(&TokenKind::Comma) =>
+            {
+                kinds.pop();
+                kinds.push(token.kind.clone());
+            }
+            kind => kinds.push(kind.clone()),
+        }
+    }
+    kinds
+}
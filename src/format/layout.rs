@@ -0,0 +1,566 @@
+// 记号的排版信息
+// 先确定每个记号的角色：左括号属于哪种分组、运算符是一元还是二元、`<` 是泛型的尖括号
+// 还是比较运算符、`|` 是否是闭包参数列表的边界，由此决定记号之前是否有空格；
+// 开启了 `custom_operators` 的文件中，二元运算符位置上紧挨着的几个运算符记号
+// 是一个自定义运算符，保持写在一起。
+// 再把记号和注释组织成以括号为界的树，打印时按分组的种类换行
+
+use crate::features::{self, Feature};
+use crate::parser::is_operator_token;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum GroupKind {
+    Block,  // 代码块，每条语句一行
+    List,   // 结构体和枚举的定义、match 的分支，每项一行
+    Inline, // 圆括号、方括号、结构体字面量和模式，放得下时写在一行
+}
+
+#[derive(Debug)]
+pub(super) enum Node {
+    Token(usize),
+    Comment(usize),
+    Group(Group),
+}
+
+#[derive(Debug)]
+pub(super) struct Group {
+    pub kind: GroupKind,
+    pub open: usize,
+    pub close: Option<usize>,
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct Info {
+    pub space_before: bool,
+    pub clause: bool,       // 开始一条契约子句的 `requires` 或 `ensures`
+    pub clause_comma: bool, // 契约子句之间的逗号
+    pub body: bool,         // 契约子句之后的函数体的 `{`
+    pub attribute: bool,    // 属性 `#[...]` 的 `[` 和运算符声明的 `(`，之后换行
+}
+
+pub(super) struct Layout {
+    pub info: Vec<Info>,
+    pub root: Vec<Node>,
+}
+
+// 同一层括号内的状态
+#[derive(Default)]
+struct Level {
+    pending: Option<GroupKind>, // 之前的关键字决定了下一个 `{` 的种类
+    function: bool,             // 在函数签名中
+    contracts: bool,            // 签名之后出现了契约子句
+}
+
+/// `tokens` 不包括结尾的 EOF，`comments` 按位置排列
+pub(super) fn analyze(tokens: &[Token], comments: &[Span]) -> Layout {
+    let mut analyzer = Analyzer::new(tokens);
+    analyzer.operators();
+    if features::scan(tokens).contains(&Feature::CustomOperators) {
+        analyzer.custom_operators();
+    }
+    analyzer.groups();
+    for i in 0..tokens.len() {
+        analyzer.info[i].space_before = analyzer.space_before(i);
+    }
+    let root = analyzer.tree(comments);
+    Layout {
+        info: analyzer.info,
+        root,
+    }
+}
+
+/// 泛型结尾和之后的 `=` 被词法分析为一个记号，如 `Vec<Vec<i32>>= v` 中的 `>>=`，
+/// 拆成 `>>` 和 `=`，使等号两边按赋值加上空格
+pub(super) fn split_generic_ends(tokens: &mut Vec<Token>) {
+    let mut analyzer = Analyzer::new(tokens);
+    analyzer.operators();
+    let ends: Vec<usize> = (0..tokens.len())
+        .filter(|&i| {
+            analyzer.generic[i]
+                && matches!(
+                    tokens[i].kind,
+                    TokenKind::GreaterEqual | TokenKind::RightShiftAssign
+                )
+        })
+        .collect();
+    for i in ends.into_iter().rev() {
+        let token = &mut tokens[i];
+        token.kind = if token.kind == TokenKind::GreaterEqual {
+            TokenKind::Greater
+        } else {
+            TokenKind::RightShift
+        };
+        token.span.end -= 1;
+        let mut assign = token.clone();
+        assign.kind = TokenKind::Assign;
+        assign.span.column += (token.span.end - token.span.start) as u32;
+        assign.span.start = token.span.end;
+        assign.span.end = assign.span.start + 1;
+        tokens.insert(i + 1, assign);
+    }
+}
+
+struct Analyzer<'a> {
+    tokens: &'a [Token],
+    unary: Vec<bool>,
+    generic: Vec<bool>, // 泛型的 `<` 和与它配对的 `>`（或 `>>`）
+    closure_open: Vec<bool>,
+    closure_close: Vec<bool>,
+    repetition: Vec<bool>,          // 宏的 `$(...)` 之后的分隔符和重复运算符
+    glued: Vec<bool>,               // 自定义运算符中紧跟着前一个记号的记号
+    groups: Vec<Option<GroupKind>>, // 左括号开始的分组
+    info: Vec<Info>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Analyzer {
+            tokens,
+            unary: vec![false; tokens.len()],
+            generic: vec![false; tokens.len()],
+            closure_open: vec![false; tokens.len()],
+            closure_close: vec![false; tokens.len()],
+            repetition: vec![false; tokens.len()],
+            glued: vec![false; tokens.len()],
+            groups: vec![None; tokens.len()],
+            info: vec![Info::default(); tokens.len()],
+        }
+    }
+
+    fn kind(&self, i: usize) -> &TokenKind {
+        &self.tokens[i].kind
+    }
+
+    // 位置 `open` 的 `(` 配对的 `)`
+    fn closing_paren(&self, open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for i in open..self.tokens.len() {
+            match self.kind(i) {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // 一元运算符、闭包的竖线和泛型的尖括号
+    fn operators(&mut self) {
+        for i in 0..self.tokens.len() {
+            let operand = i > 0 && self.ends_operand(i - 1);
+            let next = self.tokens.get(i + 1).map(|token| &token.kind);
+            let macro_call = *self.kind(i) == TokenKind::LogicalNot
+                && operand
+                && matches!(
+                    next,
+                    Some(TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace)
+                );
+            // `-> !` 中的 `!` 是类型
+            let never = i > 0 && *self.kind(i - 1) == TokenKind::Arrow;
+            match self.kind(i) {
+                TokenKind::LogicalNot if never => {}
+                TokenKind::Minus
+                | TokenKind::Star
+                | TokenKind::BitwiseAnd
+                | TokenKind::LogicalNot
+                | TokenKind::BitwiseNot
+                    if !operand || macro_call =>
+                {
+                    self.unary[i] = true
+                }
+                TokenKind::BitwiseOr if !operand && !self.closure_close[i] => {
+                    self.closure_open[i] = true;
+                    if let Some(end) = self.closure_end(i) {
+                        self.closure_close[end] = true;
+                    }
+                }
+                TokenKind::Dollar if next == Some(&TokenKind::LeftParen) => {
+                    self.repetition_end(i + 1)
+                }
+                TokenKind::Less
+                    if i > 0
+                        && matches!(
+                            self.kind(i - 1),
+                            TokenKind::Ident(_) | TokenKind::DoubleColon
+                        ) =>
+                {
+                    if let Some(end) = self.generic_end(i) {
+                        self.generic[i] = true;
+                        self.generic[end] = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // 操作数之后紧挨着的几个运算符记号（不是泛型的尖括号）组成一个自定义运算符，
+    // 运算符声明 `operator <*> (precedence 70, left)` 的 `(` 之后换行
+    fn custom_operators(&mut self) {
+        let mut i = 1;
+        while i < self.tokens.len() {
+            let start = i;
+            let keyword =
+                matches!(self.kind(start - 1), TokenKind::Ident(name) if name == "operator");
+            while i < self.tokens.len()
+                && is_operator_token(self.kind(i))
+                && (!self.generic[i] || keyword)
+                && (i == start || self.tokens[i - 1].span.end == self.tokens[i].span.start)
+            {
+                i += 1;
+            }
+            if i == start {
+                i += 1;
+                continue;
+            }
+            let declaration = keyword
+                && self.tokens.get(i).map(|token| &token.kind) == Some(&TokenKind::LeftParen)
+                && matches!(
+                    self.tokens.get(i + 1).map(|token| &token.kind),
+                    Some(TokenKind::Ident(name)) if name == "precedence"
+                );
+            if declaration {
+                self.info[i].attribute = true;
+            } else if i - start < 2 || !self.ends_operand(start - 1) {
+                continue;
+            }
+            for j in start..i {
+                self.unary[j] = false;
+                self.generic[j] = false;
+                self.glued[j] = j > start;
+            }
+        }
+    }
+
+    // 记号能否是一个操作数的结尾，决定之后的运算符是二元的
+    fn ends_operand(&self, i: usize) -> bool {
+        match self.kind(i) {
+            TokenKind::Ident(name) => {
+                !matches!(name.as_str(), "requires" | "ensures" | "invariant")
+            }
+            TokenKind::Greater | TokenKind::RightShift => self.generic[i],
+            kind => {
+                kind.is_type_keyword()
+                    || matches!(
+                        kind,
+                        TokenKind::IntLiteral(_)
+                            | TokenKind::SuffixedIntLiteral(..)
+                            | TokenKind::BoolLiteral(_)
+                            | TokenKind::StringLiteral(_)
+                            | TokenKind::CharLiteral(_)
+                            | TokenKind::RightParen
+                            | TokenKind::RightBracket
+                            | TokenKind::Question
+                            | TokenKind::Underscore
+                    )
+            }
+        }
+    }
+
+    // `$(...)` 之后紧跟的可选分隔符和 `*`、`+` 或 `?`
+    fn repetition_end(&mut self, open: usize) {
+        let mut depth = 0;
+        for i in open..self.tokens.len() {
+            match self.kind(i) {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let is_operator = |kind: Option<&TokenKind>| {
+                            matches!(
+                                kind,
+                                Some(TokenKind::Star | TokenKind::Plus | TokenKind::Question)
+                            )
+                        };
+                        let kinds = [i + 1, i + 2].map(|j| self.tokens.get(j).map(|t| &t.kind));
+                        let used = match (is_operator(kinds[0]), is_operator(kinds[1])) {
+                            (true, _) => 1,
+                            (false, true) => 2,
+                            _ => 0,
+                        };
+                        for j in i + 1..i + 1 + used {
+                            self.repetition[j] = true;
+                        }
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // 闭包参数列表结尾的 `|`
+    fn closure_end(&self, open: usize) -> Option<usize> {
+        let mut depth = 0i32;
+        for i in open + 1..self.tokens.len() {
+            match self.kind(i) {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    depth -= 1;
+                    if depth < 0 {
+                        return None;
+                    }
+                }
+                TokenKind::BitwiseOr if depth == 0 => return Some(i),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // 标识符或 `::` 之后的 `<` 到配对的 `>` 之间只有类型中的记号时是泛型的尖括号，
+    // 否则是比较运算符。嵌套泛型的结尾是一个 `>>`；`typeof(...)` 的括号中是表达式，整个跳过。
+    // 契约的关键字不会出现在类型中，`i < n invariant sum > 0` 的 `<` 是比较运算符
+    fn generic_end(&self, open: usize) -> Option<usize> {
+        let mut depth = 1;
+        let mut i = open;
+        while i + 1 < self.tokens.len() {
+            i += 1;
+            let kind = self.kind(i);
+            if matches!(kind, TokenKind::Ident(name) if name == "typeof")
+                && self.tokens.get(i + 1).map(|token| &token.kind) == Some(&TokenKind::LeftParen)
+            {
+                i = self.closing_paren(i + 1)?;
+                continue;
+            }
+            match kind {
+                TokenKind::Ident(name)
+                    if matches!(name.as_str(), "requires" | "ensures" | "invariant") =>
+                {
+                    return None
+                }
+                TokenKind::Less => depth += 1,
+                TokenKind::Greater
+                | TokenKind::RightShift
+                | TokenKind::GreaterEqual
+                | TokenKind::RightShiftAssign => {
+                    let closes = match kind {
+                        TokenKind::Greater | TokenKind::GreaterEqual => 1,
+                        _ => 2,
+                    };
+                    if depth <= closes {
+                        return Some(i);
+                    }
+                    depth -= closes;
+                }
+                TokenKind::Ident(_)
+                | TokenKind::IntLiteral(_)
+                | TokenKind::Comma
+                | TokenKind::Colon
+                | TokenKind::DoubleColon
+                | TokenKind::Plus
+                | TokenKind::Semicolon
+                | TokenKind::LeftBracket
+                | TokenKind::RightBracket
+                | TokenKind::LeftParen
+                | TokenKind::RightParen
+                | TokenKind::BitwiseAnd
+                | TokenKind::Star
+                | TokenKind::Mut
+                | TokenKind::Const
+                | TokenKind::Fn
+                | TokenKind::Arrow => {}
+                kind if kind.is_type_keyword() => {}
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    // 左括号的种类和契约子句。`{` 的种类由之前的关键字决定：函数、if、while、for、async、
+    // else 之后是代码块，match、struct、enum 之后是每项一行的列表；没有关键字时
+    // 紧跟在标识符之后的是结构体字面量或模式，其他是代码块
+    fn groups(&mut self) {
+        let mut levels = vec![Level::default()];
+        for i in 0..self.tokens.len() {
+            let next_is_paren =
+                self.tokens.get(i + 1).map(|t| &t.kind) == Some(&TokenKind::LeftParen);
+            let after_ident = i > 0 && matches!(self.kind(i - 1), TokenKind::Ident(_));
+            let level = levels.last_mut().expect("levels are never empty");
+            match &self.tokens[i].kind {
+                // `fn(i32) -> i32` 是函数类型
+                TokenKind::Fn if !next_is_paren => {
+                    level.pending = Some(GroupKind::Block);
+                    level.function = true;
+                }
+                TokenKind::If | TokenKind::While | TokenKind::For | TokenKind::Else => {
+                    level.pending = Some(GroupKind::Block)
+                }
+                TokenKind::Match | TokenKind::Struct | TokenKind::Enum => {
+                    level.pending = Some(GroupKind::List)
+                }
+                TokenKind::Export => level.pending = Some(GroupKind::Inline),
+                // `macro_rules! name { ... }` 的规则每条一行；`async { ... }` 是代码块
+                TokenKind::Ident(name) if name == "macro_rules" || name == "async" => {
+                    level.pending = Some(GroupKind::Block)
+                }
+                TokenKind::Semicolon => *level = Level::default(),
+                TokenKind::Ident(name)
+                    if level.function && matches!(name.as_str(), "requires" | "ensures") =>
+                {
+                    self.info[i].clause = true;
+                    level.contracts = true;
+                }
+                TokenKind::Comma if level.contracts => self.info[i].clause_comma = true,
+                TokenKind::LeftBrace => {
+                    let kind = level.pending.take().unwrap_or(if after_ident {
+                        GroupKind::Inline
+                    } else {
+                        GroupKind::Block
+                    });
+                    self.info[i].body = level.contracts;
+                    *level = Level::default();
+                    self.groups[i] = Some(kind);
+                    levels.push(Level::default());
+                }
+                TokenKind::LeftParen | TokenKind::LeftBracket => {
+                    // `#[...]` 和文件开头的 `#![...]`
+                    let hash = |j: usize| j > 0 && *self.kind(j - 1) == TokenKind::Hash;
+                    let inner = i > 0 && *self.kind(i - 1) == TokenKind::LogicalNot && hash(i - 1);
+                    self.info[i].attribute |= hash(i) || inner;
+                    self.groups[i] = Some(GroupKind::Inline);
+                    levels.push(Level::default());
+                }
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace
+                    if levels.len() > 1 =>
+                {
+                    levels.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // 记号和前一个记号在同一行时，中间是否有空格
+    fn space_before(&self, i: usize) -> bool {
+        let Some(prev) = i.checked_sub(1) else {
+            return false;
+        };
+        let (before, kind) = (self.kind(prev), self.kind(i));
+        if self.glued[i] {
+            return false;
+        }
+        match kind {
+            TokenKind::RightParen
+            | TokenKind::RightBracket
+            | TokenKind::Comma
+            | TokenKind::Semicolon
+            | TokenKind::Colon
+            | TokenKind::Dot
+            | TokenKind::DoubleColon
+            | TokenKind::Question => return false,
+            _ => {}
+        }
+        match before {
+            TokenKind::LeftParen
+            | TokenKind::LeftBracket
+            | TokenKind::Dot
+            | TokenKind::DoubleColon
+            | TokenKind::Hash => return false,
+            // `{}` 之间没有空格，结构体字面量的 `{ x }` 两边各有一个空格
+            TokenKind::LeftBrace => return *kind != TokenKind::RightBrace,
+            // `..` 两边没有空格，除非后面是模式结尾的 `}`
+            TokenKind::DotDot | TokenKind::DotDotEqual => return *kind == TokenKind::RightBrace,
+            _ => {}
+        }
+        // 宏调用 `name!(...)` 和宏定义 `macro_rules! name`
+        let macro_rules =
+            |j: usize| matches!(self.kind(j), TokenKind::Ident(name) if name == "macro_rules");
+        if *kind == TokenKind::LogicalNot
+            && (self.unary[i] && matches!(before, TokenKind::Ident(_)) || macro_rules(prev))
+        {
+            return false;
+        }
+        if *before == TokenKind::LogicalNot && prev > 0 && macro_rules(prev - 1) {
+            return true;
+        }
+        // 宏规则中的 `$x:expr`、`$(...)` 和 `$(...),*`
+        let fragment =
+            prev >= 2 && *before == TokenKind::Colon && *self.kind(prev - 2) == TokenKind::Dollar;
+        if *before == TokenKind::Dollar || fragment || self.repetition[i] {
+            return false;
+        }
+        // `& &x` 和 `| |` 写在一起会变成 `&&` 和 `||`
+        if self.unary[prev] {
+            return *kind == TokenKind::BitwiseAnd && *before == TokenKind::BitwiseAnd;
+        }
+        if self.closure_open[prev] {
+            return self.closure_close[i];
+        }
+        if self.closure_close[i] || self.generic[i] {
+            return false;
+        }
+        if self.generic[prev] && *before == TokenKind::Less {
+            return false;
+        }
+        match kind {
+            TokenKind::DotDot | TokenKind::DotDotEqual => !self.ends_operand(prev),
+            // 调用和下标的括号紧贴在前面；函数类型 `fn(i32)` 和汇编操作数 `in(reg)` 也是
+            TokenKind::LeftParen | TokenKind::LeftBracket => {
+                let asm_operand = *before == TokenKind::In
+                    && prev > 0
+                    && matches!(self.kind(prev - 1), TokenKind::Comma | TokenKind::LeftParen);
+                !(self.ends_operand(prev)
+                    || *before == TokenKind::Fn && *kind == TokenKind::LeftParen
+                    || asm_operand)
+            }
+            _ => true,
+        }
+    }
+
+    // 把记号和注释组织成树，注释放在它之后的第一个记号所在的分组中
+    fn tree(&self, comments: &[Span]) -> Vec<Node> {
+        let mut root = Vec::new();
+        let mut stack: Vec<Group> = Vec::new();
+        let mut comments = comments.iter().enumerate().peekable();
+        for (i, token) in self.tokens.iter().enumerate() {
+            while let Some((c, _)) = comments.next_if(|(_, span)| span.start < token.span.start) {
+                push(&mut stack, &mut root, Node::Comment(c));
+            }
+            if let Some(kind) = self.groups[i] {
+                stack.push(Group {
+                    kind,
+                    open: i,
+                    close: None,
+                    children: Vec::new(),
+                });
+            } else if is_closing(&token.kind) && !stack.is_empty() {
+                let mut group = stack.pop().unwrap();
+                group.close = Some(i);
+                push(&mut stack, &mut root, Node::Group(group));
+            } else {
+                push(&mut stack, &mut root, Node::Token(i));
+            }
+        }
+        // 没有闭合的分组（源码能通过语法分析时不会出现）
+        while let Some(group) = stack.pop() {
+            push(&mut stack, &mut root, Node::Group(group));
+        }
+        for (c, _) in comments {
+            root.push(Node::Comment(c));
+        }
+        root
+    }
+}
+
+fn push(stack: &mut [Group], root: &mut Vec<Node>, node: Node) {
+    match stack.last_mut() {
+        Some(group) => group.children.push(node),
+        None => root.push(node),
+    }
+}
+
+fn is_closing(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace
+    )
+}
@@ -0,0 +1,401 @@
+// 按排版信息输出源码
+// 代码块和列表总是每项一行；圆括号、方括号和结构体字面量先尝试写在一行，
+// 超过行宽或者其中有不能写在一行的内容（代码块、单行注释）时，如果只有最后一项
+// 以代码块结尾，代码块照常展开，其他部分写在一行；否则每项一行并补上末尾的逗号
+
+use super::layout::{Group, GroupKind, Layout, Node};
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+const INDENT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Break {
+    None,
+    Line,
+    Blank, // 保留源码中的一个空行
+}
+
+// 分组内容的排列方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Statements, // 分号和代码块之后换行
+    List,       // 每项一行，总是有末尾的逗号
+    Broken,     // 每项一行，多于一项时有末尾的逗号
+    Flat,       // 写在一行，去掉末尾的逗号
+    Hug,        // 写在一行，但其中的代码块照常展开
+}
+
+#[derive(Clone)]
+pub(super) struct Printer<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    comments: &'a [Span],
+    layout: &'a Layout,
+    width: usize,
+    out: String,
+    indent: usize,
+    column: usize,
+    pending: Break,
+    space: bool,            // 行内的块注释之后要有空格
+    line_start: bool,       // 当前行还没有内容
+    no_blank: bool,         // 左括号之后和右括号之前不留空行
+    last_line: Option<u32>, // 最后写出的记号或注释在源码中的行号
+    flat: bool,             // 正在尝试写在一行
+    failed: bool,           // 写在一行时遇到了必须换行的内容
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(
+        source: &'a str,
+        tokens: &'a [Token],
+        comments: &'a [Span],
+        layout: &'a Layout,
+        width: usize,
+    ) -> Self {
+        Self {
+            source,
+            tokens,
+            comments,
+            layout,
+            width,
+            out: String::new(),
+            indent: 0,
+            column: 0,
+            pending: Break::None,
+            space: false,
+            line_start: true,
+            no_blank: true,
+            last_line: None,
+            flat: false,
+            failed: false,
+        }
+    }
+
+    pub fn print(mut self) -> String {
+        let layout = self.layout;
+        self.sequence(&layout.root, Mode::Statements);
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out
+    }
+
+    fn sequence(&mut self, nodes: &[Node], mode: Mode) {
+        let last = nodes
+            .iter()
+            .rposition(|node| !matches!(node, Node::Comment(_)));
+        let commas = nodes
+            .iter()
+            .filter(|node| self.is_kind(node, &TokenKind::Comma))
+            .count();
+        let trailing = last.is_some_and(|last| self.is_kind(&nodes[last], &TokenKind::Comma));
+        let elements = commas + usize::from(!trailing && last.is_some());
+        // 一项的元组 `(x,)` 的逗号不能去掉，也不能给括号表达式 `(x)` 补上逗号
+        let add_comma = match mode {
+            Mode::List => true,
+            Mode::Broken => elements > 1,
+            _ => false,
+        };
+        let drop_comma = matches!(mode, Mode::Flat | Mode::Hug) && elements > 1;
+        let mut clauses = false;
+
+        for (n, node) in nodes.iter().enumerate() {
+            match node {
+                Node::Comment(c) => self.comment(*c),
+                Node::Token(i) => {
+                    let i = *i;
+                    let info = &self.layout.info[i];
+                    if Some(n) == last && trailing && drop_comma {
+                        continue;
+                    }
+                    if info.clause && mode == Mode::Statements {
+                        // 契约子句每条一行，比函数签名多缩进一层
+                        if !clauses {
+                            clauses = true;
+                            self.indent += 1;
+                        }
+                        self.line();
+                    }
+                    self.token(i);
+                    let kind = &self.tokens[i].kind;
+                    let separator = match mode {
+                        Mode::Statements => {
+                            *kind == TokenKind::Semicolon
+                                || (info.clause_comma && *kind == TokenKind::Comma)
+                        }
+                        Mode::List | Mode::Broken => *kind == TokenKind::Comma,
+                        Mode::Flat | Mode::Hug => false,
+                    };
+                    if separator {
+                        self.line();
+                    }
+                }
+                Node::Group(group) => {
+                    if clauses && self.layout.info[group.open].body {
+                        // 函数体的 `{` 另起一行
+                        clauses = false;
+                        self.indent -= 1;
+                        self.line();
+                    }
+                    self.group(group);
+                    // 属性之后换行
+                    let statement_end = mode == Mode::Statements
+                        && group.kind != GroupKind::Inline
+                        && !self.continues(nodes.get(n + 1..).unwrap_or_default());
+                    if statement_end || self.layout.info[group.open].attribute {
+                        self.line();
+                    }
+                }
+            }
+            if Some(n) == last && add_comma && !trailing {
+                self.punct(",");
+            }
+            // 最后一条契约子句也以逗号结尾
+            if clauses
+                && !self.is_kind(node, &TokenKind::Comma)
+                && self.before_body(&nodes[n + 1..])
+            {
+                self.punct(",");
+            }
+        }
+    }
+
+    // 之后第一个不是注释的节点是否是契约子句之后的函数体
+    fn before_body(&self, rest: &[Node]) -> bool {
+        match rest.iter().find(|node| !matches!(node, Node::Comment(_))) {
+            Some(Node::Group(group)) => self.layout.info[group.open].body,
+            _ => false,
+        }
+    }
+
+    fn group(&mut self, group: &Group) {
+        match group.kind {
+            GroupKind::Block | GroupKind::List => {
+                self.token(group.open);
+                if group.children.is_empty() {
+                    self.close(group, false);
+                    return;
+                }
+                if self.flat {
+                    self.failed = true;
+                    return;
+                }
+                let mode = if group.kind == GroupKind::Block {
+                    Mode::Statements
+                } else {
+                    Mode::List
+                };
+                self.indent += 1;
+                self.line();
+                self.no_blank = true;
+                self.sequence(&group.children, mode);
+                self.indent -= 1;
+                self.close(group, true);
+            }
+            GroupKind::Inline => {
+                if self.flat {
+                    self.token(group.open);
+                    self.sequence(&group.children, Mode::Flat);
+                    self.close(group, false);
+                    return;
+                }
+                let mode = if self.fits(group) {
+                    Mode::Flat
+                } else if self.can_hug(group) {
+                    Mode::Hug
+                } else {
+                    Mode::Broken
+                };
+                self.token(group.open);
+                if mode == Mode::Broken {
+                    self.indent += 1;
+                    self.line();
+                    self.no_blank = true;
+                    self.sequence(&group.children, mode);
+                    self.indent -= 1;
+                    self.close(group, true);
+                } else {
+                    let flat = self.flat;
+                    self.flat = mode == Mode::Flat;
+                    self.sequence(&group.children, mode);
+                    self.flat = flat;
+                    self.close(group, false);
+                }
+            }
+        }
+    }
+
+    fn close(&mut self, group: &Group, line: bool) {
+        if line {
+            self.line();
+            self.no_blank = true;
+        }
+        if let Some(close) = group.close {
+            self.token(close);
+        }
+    }
+
+    // 分组能否写在当前行：试着写一遍，看有没有遇到必须换行的内容和是否超过行宽
+    fn fits(&mut self, group: &Group) -> bool {
+        let mut trial = self.trial();
+        if trial.pending >= Break::Line {
+            trial.pending = Break::None;
+            trial.column = self.indent * INDENT;
+            trial.line_start = true;
+        }
+        trial.group(group);
+        !trial.failed && trial.column <= self.width
+    }
+
+    // 用于尝试的副本，不复制已经写出的内容
+    fn trial(&mut self) -> Self {
+        let out = std::mem::take(&mut self.out);
+        let mut trial = self.clone();
+        self.out = out;
+        trial.flat = true;
+        trial
+    }
+
+    // 只有最后一项以代码块结尾，其他部分都能写在一行时，代码块照常展开
+    fn can_hug(&mut self, group: &Group) -> bool {
+        let Some(last) = group
+            .children
+            .iter()
+            .rposition(|node| !matches!(node, Node::Comment(_)))
+        else {
+            return false;
+        };
+        let ends_with_block = matches!(
+            &group.children[last],
+            Node::Group(inner) if inner.kind != GroupKind::Inline && !inner.children.is_empty()
+        );
+        ends_with_block
+            && group
+                .children
+                .iter()
+                .enumerate()
+                .all(|(n, node)| match node {
+                    Node::Comment(c) => !self.is_line_comment(*c),
+                    Node::Group(inner) if n != last => {
+                        let mut trial = self.trial();
+                        trial.pending = Break::None;
+                        trial.group(inner);
+                        !trial.failed
+                    }
+                    _ => true,
+                })
+    }
+
+    // 代码块之后的记号是否还属于同一条语句
+    fn continues(&self, rest: &[Node]) -> bool {
+        let next = rest.iter().find_map(|node| match node {
+            Node::Token(i) => Some(&self.tokens[*i].kind),
+            Node::Group(group) => Some(&self.tokens[group.open].kind),
+            Node::Comment(_) => None,
+        });
+        matches!(
+            next,
+            Some(
+                TokenKind::Else
+                    | TokenKind::Semicolon
+                    | TokenKind::Comma
+                    | TokenKind::Dot
+                    | TokenKind::Question
+                    | TokenKind::As
+            )
+        )
+    }
+
+    fn token(&mut self, i: usize) {
+        let token = &self.tokens[i];
+        self.separate(self.layout.info[i].space_before, token.span.line);
+        let text = &self.source[token.span.start..token.span.end];
+        self.write(text);
+        self.last_line = Some(token.span.line + text.matches('\n').count() as u32);
+    }
+
+    // 补上的逗号
+    fn punct(&mut self, text: &str) {
+        let pending = std::mem::replace(&mut self.pending, Break::None);
+        self.write(text);
+        self.pending = pending;
+    }
+
+    fn comment(&mut self, c: usize) {
+        let span = self.comments[c];
+        let text = self.source[span.start..span.end].trim_end();
+        let line_comment = self.is_line_comment(c);
+        if self.last_line == Some(span.line) {
+            // 行尾注释留在原来的行上
+            if self.flat && line_comment {
+                self.failed = true;
+            }
+            self.write(" ");
+            self.write(text);
+        } else {
+            if self.flat {
+                self.failed = true;
+            }
+            self.line();
+            self.separate(false, span.line);
+            self.write(text);
+        }
+        self.last_line = Some(span.line + text.matches('\n').count() as u32);
+        if line_comment {
+            self.line();
+        } else {
+            self.space = true;
+        }
+    }
+
+    // 写出下一个记号或注释之前的空格或换行
+    fn separate(&mut self, space: bool, line: u32) {
+        let mut pending = std::mem::replace(&mut self.pending, Break::None);
+        if pending == Break::None {
+            if (space || self.space) && !self.line_start {
+                self.write(" ");
+            }
+        } else {
+            if self.flat {
+                self.failed = true;
+            }
+            let blank = self.last_line.is_some_and(|last| line > last + 1);
+            if pending == Break::Line && blank && !self.no_blank {
+                pending = Break::Blank;
+            }
+            if !self.out.is_empty() {
+                self.out.push('\n');
+                if pending == Break::Blank {
+                    self.out.push('\n');
+                }
+            }
+            self.column = 0;
+            let indent = " ".repeat(self.indent * INDENT);
+            self.write(&indent);
+        }
+        self.space = false;
+        self.no_blank = false;
+    }
+
+    fn line(&mut self) {
+        self.pending = self.pending.max(Break::Line);
+    }
+
+    fn write(&mut self, text: &str) {
+        self.line_start = self.line_start && text.trim().is_empty();
+        self.out.push_str(text);
+        match text.rfind('\n') {
+            Some(pos) => self.column = text[pos + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+    }
+
+    fn is_kind(&self, node: &Node, kind: &TokenKind) -> bool {
+        matches!(node, Node::Token(i) if self.tokens[*i].kind == *kind)
+    }
+
+    fn is_line_comment(&self, c: usize) -> bool {
+        self.source[self.comments[c].start..].starts_with("//")
+    }
+}
@@ -0,0 +1,633 @@
+// 语法描述 - 语言的 EBNF 产生式和实现它们的解析函数
+//
+// 解析器的每个 `parse_*` 方法对应这里的一条或几条规则，规则按解析器的层次排列：
+// 条目、泛型和参数、模式、类型、语句，最后是从低到高各个优先级的表达式。
+// `contractus --emit=grammar` 输出的 EBNF、文档和编辑器的语法文件都由这张表生成；
+// 修改解析器接受的语法时同时修改对应的规则，tests/grammar_test.rs 检查两者一致：
+// 规则引用的解析函数存在，引号中的终结符能被词法分析为一个记号，
+// token.rs 中的每个关键字都出现在某条规则中
+//
+// 记法是 W3C 的 EBNF：`"x"` 是终结符，大写的名字是一类记号，`?`、`*` 和 `+` 是可选、
+// 零次以上和一次以上，`|` 分隔候选
+
+/// 一条产生式。`alternatives` 是顶层的候选，各自是一个 EBNF 表达式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    pub name: &'static str,
+    pub alternatives: &'static [&'static str],
+    /// 实现这条规则的 `Parser` 方法；规则在方法内部展开时是所在的方法
+    pub parser: &'static str,
+    /// 产生式之外的约束，输出为规则之前的注释
+    pub note: Option<&'static str>,
+}
+
+const fn rule(
+    name: &'static str,
+    parser: &'static str,
+    alternatives: &'static [&'static str],
+) -> Rule {
+    Rule {
+        name,
+        alternatives,
+        parser,
+        note: None,
+    }
+}
+
+impl Rule {
+    const fn note(mut self, note: &'static str) -> Rule {
+        self.note = Some(note);
+        self
+    }
+}
+
+/// 规则的分组，对应解析器中的一部分
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Section {
+    pub title: &'static str,
+    pub rules: &'static [Rule],
+}
+
+/// 大写名字表示的记号类别
+pub const TOKEN_CLASSES: &[(&str, &str)] = &[
+    ("IDENT", "identifier that is not a keyword"),
+    (
+        "INT",
+        "integer literal: decimal, 0x or 0b digits with `_` separators",
+    ),
+    ("CHAR", "character literal in single quotes"),
+    ("STRING", "string literal in double quotes"),
+    (
+        "LABEL",
+        "loop label, an identifier after a single quote: 'outer",
+    ),
+    ("TOKEN", "any token other than a delimiter"),
+    (
+        "OPERATOR",
+        "operator characters written together, such as `<*>`",
+    ),
+];
+
+/// 词法分析为 IDENT、只在特定位置有特殊含义的词
+pub const CONTEXTUAL_KEYWORDS: &[&str] = &[
+    "requires",
+    "ensures",
+    "invariant",
+    "pure",
+    "macro_rules",
+    "async",
+    "await",
+    "typeof",
+    "asm",
+];
+
+/// 起始规则
+pub const START: &str = "program";
+
+pub const GRAMMAR: &[Section] = &[
+    Section {
+        title: "Items",
+        rules: &[
+            rule("program", "parse", &["inner_attribute* item*"]),
+            rule(
+                "inner_attribute",
+                "parse_inner_attributes",
+                &["\"#\" \"!\" \"[\" IDENT ( \"(\" ( ( IDENT | INT ) ( \",\" ( IDENT | INT ) )* \",\"? )? \")\" )? \"]\""],
+            )
+            .note("`feature` names the experimental features the file uses, `edition` selects its edition by year"),
+            rule(
+                "item",
+                "parse_item",
+                &[
+                    "attribute* operator_decl? \"pub\"? ( \"pure\"? \"async\"? function | struct | enum | const | static | import | export )",
+                    "macro_rules",
+                    "item_macro_call",
+                ],
+            )
+            .note("attributes are only allowed on functions, structs and enums"),
+            rule(
+                "attribute",
+                "parse_attributes",
+                &["\"#\" \"[\" IDENT ( \"(\" ( attribute_arg ( \",\" attribute_arg )* \",\"? )? \")\" )? \"]\""],
+            ),
+            rule("attribute_arg", "parse_attributes", &["IDENT", "integer_type", "INT", "STRING"])
+                .note("a string names an intrinsic, as in `#[intrinsic(\"sqrt\")]`"),
+            rule(
+                "operator_decl",
+                "parse_operator_declaration",
+                &["IDENT OPERATOR \"(\" IDENT INT \",\" IDENT \")\""],
+            )
+            .note("written `operator <*> (precedence 70, left)`, where `left` may be `right`; requires `#![feature(custom_operators)]`, a following function with two parameters and a precedence between 20 (`||`) and 120 (`*`)"),
+            rule(
+                "function",
+                "parse_function",
+                &["\"fn\" IDENT generics? \"(\" ( parameter ( \",\" parameter )* \",\"? )? \")\" ( \"->\" type )? contract_clause* block"],
+            ),
+            rule(
+                "contract_clause",
+                "parse_function",
+                &["( \"requires\" | \"ensures\" ) condition \",\"?"],
+            ),
+            rule(
+                "struct",
+                "parse_struct",
+                &["\"struct\" IDENT generics? \"{\" ( field ( \",\" field )* ( \",\" invariant )* \",\"? | invariant ( \",\" invariant )* \",\"? )? \"}\""],
+            )
+            .note("fields come before invariants"),
+            rule("field", "parse_struct", &["\"pub\"? IDENT \":\" type"]),
+            rule("invariant", "parse_contract", &["\"invariant\" condition"]),
+            rule(
+                "enum",
+                "parse_enum",
+                &["\"enum\" IDENT generics? \"{\" ( variant ( \",\" variant )* \",\"? )? \"}\""],
+            ),
+            rule(
+                "variant",
+                "parse_enum",
+                &["IDENT ( \"(\" ( type ( \",\" type )* \",\"? )? \")\" )?"],
+            ),
+            rule(
+                "const",
+                "parse_const",
+                &["\"const\" IDENT \":\" type \"=\" expression \";\""],
+            ),
+            rule(
+                "static",
+                "parse_static",
+                &["\"static\" \"mut\"? IDENT \":\" type \"=\" expression \";\""],
+            ),
+            rule(
+                "import",
+                "parse_import",
+                &["\"import\" module_path ( \"as\" IDENT )? \";\""],
+            ),
+            rule(
+                "export",
+                "parse_export",
+                &["\"export\" \"{\" ( module_path ( \",\" module_path )* \",\"? )? \"}\" \";\""],
+            ),
+            rule("module_path", "parse_import", &["IDENT ( \"::\" IDENT )*"]),
+        ],
+    },
+    Section {
+        title: "Macros",
+        rules: &[
+            rule(
+                "macro_rules",
+                "parse_macro_rules",
+                &["\"macro_rules\" \"!\" IDENT \"{\" ( macro_rule ( \";\" macro_rule )* \";\"? )? \"}\""],
+            )
+            .note("macros can only be defined at the top level of a module"),
+            rule(
+                "macro_rule",
+                "parse_macro_rules",
+                &["delimited_tree \"=>\" delimited_tree"],
+            ),
+            rule(
+                "item_macro_call",
+                "parse_item_macro_call",
+                &[
+                    "IDENT \"!\" \"(\" token_tree* \")\" \";\"",
+                    "IDENT \"!\" \"[\" token_tree* \"]\" \";\"",
+                    "IDENT \"!\" \"{\" token_tree* \"}\"",
+                ],
+            ),
+            rule("macro_call", "parse_macro_call", &["IDENT \"!\" delimited_tree"]),
+            rule(
+                "delimited_tree",
+                "parse_delimited_tree",
+                &[
+                    "\"(\" token_tree* \")\"",
+                    "\"[\" token_tree* \"]\"",
+                    "\"{\" token_tree* \"}\"",
+                ],
+            ),
+            rule("token_tree", "parse_token_tree", &["delimited_tree", "TOKEN"]),
+        ],
+    },
+    Section {
+        title: "Generics and parameters",
+        rules: &[
+            rule(
+                "generics",
+                "parse_generics",
+                &["\"<\" ( generic_param ( \",\" generic_param )* \",\"? )? \">\""],
+            ),
+            rule(
+                "generic_param",
+                "parse_generics",
+                &["IDENT ( \":\" IDENT ( \"+\" IDENT )* )?"],
+            ),
+            rule("parameter", "parse_parameter", &["pattern \":\" type"]),
+        ],
+    },
+    Section {
+        title: "Patterns",
+        rules: &[
+            rule(
+                "pattern",
+                "parse_pattern",
+                &["single_pattern ( \"|\" single_pattern )*"],
+            ),
+            rule(
+                "single_pattern",
+                "parse_single_pattern",
+                &[
+                    "\"_\"",
+                    "module_path ( \"(\" pattern_list? \")\" | \"{\" ( field_pattern ( \",\" field_pattern )* \",\"? )? \"}\" )?",
+                    "\"(\" pattern_list? \")\"",
+                    "\"-\"? INT",
+                    "\"true\"",
+                    "\"false\"",
+                    "CHAR",
+                    "STRING",
+                ],
+            ),
+            rule(
+                "pattern_list",
+                "parse_single_pattern",
+                &["pattern ( \",\" pattern )* \",\"?"],
+            ),
+            rule(
+                "field_pattern",
+                "parse_single_pattern",
+                &["IDENT ( \":\" pattern )?"],
+            ),
+        ],
+    },
+    Section {
+        title: "Types",
+        rules: &[
+            rule(
+                "type",
+                "parse_type",
+                &[
+                    "integer_type",
+                    "\"f32\" | \"f64\" | \"bool\" | \"char\" | \"string\"",
+                    "IDENT ( \"<\" type_args )?",
+                    "\"(\" \")\"",
+                    "\"(\" type_list \")\" ( \"->\" type )?",
+                    "\"pure\"? \"fn\" \"(\" type_list? \")\" \"->\" type",
+                    "\"[\" type ( \";\" INT )? \"]\"",
+                    "\"*\" ( \"mut\" | \"const\" )? type",
+                    "( \"&\" | \"&&\" ) \"mut\"? type",
+                    "\"!\"",
+                    "\"_\"",
+                    "\"typeof\" \"(\" expression \")\"",
+                ],
+            )
+            .note("`typeof(expr)` is only allowed in `let` annotations, `as` casts and type arguments inside function bodies"),
+            rule(
+                "integer_type",
+                "parse_type",
+                &["\"i8\" | \"i16\" | \"i32\" | \"i64\" | \"isize\" | \"u8\" | \"u16\" | \"u32\" | \"u64\" | \"usize\""],
+            ),
+            rule("type_list", "parse_type", &["type ( \",\" type )* \",\"?"]),
+            rule("type_args", "parse_type_args", &["type ( \",\" type )* \",\"? \">\""])
+                .note("a closing `>>` is split into two `>`"),
+        ],
+    },
+    Section {
+        title: "Statements",
+        rules: &[
+            rule("block", "parse_block", &["\"{\" statement* \"}\""]),
+            rule(
+                "statement",
+                "parse_statement",
+                &[
+                    "let_statement",
+                    "return_statement",
+                    "if_statement",
+                    "while_statement",
+                    "for_statement",
+                    "match_statement",
+                    "break_statement",
+                    "continue_statement",
+                    "block",
+                    "expression \";\"?",
+                ],
+            )
+            .note("only the last expression of a block may omit `;`"),
+            rule(
+                "let_statement",
+                "parse_let_statement",
+                &["\"let\" \"mut\"? pattern ( \":\" type )? ( \"=\" expression ( \"else\" block )? )? \";\""],
+            )
+            .note("the `else` block runs when the pattern does not match and must diverge"),
+            rule(
+                "return_statement",
+                "parse_return_statement",
+                &["\"return\" expression? \";\"?"],
+            )
+            .note("the `;` after `return`, `break` and `continue` may be left out before the `}` that ends the block"),
+            rule(
+                "if_statement",
+                "parse_if_statement",
+                &["\"if\" condition block else_branch?"],
+            ),
+            rule(
+                "else_branch",
+                "parse_else",
+                &["\"else\" ( if_statement | block )"],
+            ),
+            rule("loop_label", "parse_loop_label", &["LABEL \":\""]),
+            rule(
+                "while_statement",
+                "parse_while_statement",
+                &["loop_label? \"while\" condition loop_invariant* block"],
+            ),
+            rule(
+                "for_statement",
+                "parse_for_statement",
+                &["loop_label? \"for\" pattern \"in\" condition loop_invariant* block"],
+            ),
+            rule(
+                "loop_invariant",
+                "parse_loop_invariants",
+                &["\"invariant\" condition \",\"?"],
+            )
+            .note("only loop statements have invariants; loops used as expressions do not"),
+            rule(
+                "match_statement",
+                "parse_match_statement",
+                &["\"match\" condition \"{\" ( match_arm ( \",\" match_arm )* \",\"? )? \"}\""],
+            ),
+            rule(
+                "match_arm",
+                "parse_match_arms",
+                &["pattern ( \"if\" expression )? \"=>\" expression"],
+            ),
+            rule(
+                "break_statement",
+                "parse_break_statement",
+                &["\"break\" LABEL? expression? \";\"?"],
+            ),
+            rule(
+                "continue_statement",
+                "parse_continue_statement",
+                &["\"continue\" LABEL? \";\"?"],
+            ),
+        ],
+    },
+    Section {
+        title: "Expressions, from the lowest precedence to the highest",
+        rules: &[
+            rule("expression", "parse_expression", &["assignment"]),
+            rule("condition", "parse_condition", &["expression"]).note(
+                "a struct literal outside parentheses is not allowed: in `if x {` the `{` starts the block",
+            ),
+            rule(
+                "assignment",
+                "parse_binary",
+                &["logical_or ( ( \"=\" | \"+=\" | \"-=\" | \"*=\" | \"/=\" | \"%=\" | \"&=\" | \"|=\" | \"^=\" | \"<<=\" | \">>=\" ) assignment )?"],
+            ),
+            rule(
+                "logical_or",
+                "parse_binary",
+                &["logical_and ( \"||\" logical_and )*"],
+            ),
+            rule(
+                "logical_and",
+                "parse_binary",
+                &["bitwise_or ( \"&&\" bitwise_or )*"],
+            ),
+            rule(
+                "bitwise_or",
+                "parse_binary",
+                &["bitwise_xor ( \"|\" bitwise_xor )*"],
+            ),
+            rule(
+                "bitwise_xor",
+                "parse_binary",
+                &["bitwise_and ( \"^\" bitwise_and )*"],
+            ),
+            rule(
+                "bitwise_and",
+                "parse_binary",
+                &["equality ( \"&\" equality )*"],
+            ),
+            rule(
+                "equality",
+                "parse_binary",
+                &["comparison ( ( \"==\" | \"!=\" ) comparison )?"],
+            )
+            .note("equality and comparison operators do not chain: `a < b < c` is an error"),
+            rule(
+                "comparison",
+                "parse_binary",
+                &["shift ( ( \"<\" | \">\" | \"<=\" | \">=\" ) shift )?"],
+            ),
+            rule(
+                "shift",
+                "parse_binary",
+                &["range ( ( \"<<\" | \">>\" ) range )*"],
+            ),
+            rule(
+                "range",
+                "parse_binary",
+                &["additive ( ( \"..\" | \"..=\" ) additive )?"],
+            ),
+            rule(
+                "additive",
+                "parse_binary",
+                &["multiplicative ( ( \"+\" | \"-\" ) multiplicative )*"],
+            ),
+            rule(
+                "multiplicative",
+                "parse_binary",
+                &["cast ( ( \"*\" | \"/\" | \"%\" ) cast )*"],
+            ),
+            rule("cast", "parse_binary", &["unary ( \"as\" type )*"]),
+            rule(
+                "unary",
+                "parse_unary",
+                &[
+                    "( \"-\" | \"!\" | \"~\" | \"*\" ) unary",
+                    "( \"&\" | \"&&\" ) \"mut\"? unary",
+                    "postfix",
+                ],
+            )
+            .note("`-` directly before a method call, like `-x.abs()`, must be parenthesized: `-(x.abs())` or `(-x).abs()`"),
+            rule(
+                "postfix",
+                "parse_postfix",
+                &["primary ( \"(\" arguments? \")\" | \".\" IDENT ( \"(\" arguments? \")\" )? | \".\" INT | \".\" \"await\" | \"[\" expression \"]\" | \"?\" )*"],
+            )
+            .note("`async` blocks and `.await` require `#![feature(async_await)]` or edition 2025; otherwise `async` and `await` are ordinary names"),
+            rule(
+                "arguments",
+                "parse_args",
+                &["expression ( \",\" expression )* \",\"?"],
+            ),
+            rule(
+                "primary",
+                "parse_primary",
+                &[
+                    "inline_asm",
+                    "macro_call",
+                    "literal",
+                    "path_expression",
+                    "struct_literal",
+                    "\"(\" ( expression ( \",\" ( expression ( \",\" expression )* \",\"? )? )? )? \")\"",
+                    "\"[\" ( expression ( \",\" expression )* \",\"? | expression \";\" INT )? \"]\"",
+                    "closure",
+                    "block",
+                    "\"unsafe\" block",
+                    "\"async\" block",
+                    "\"if\" condition block else_branch?",
+                    "while_statement",
+                    "for_statement",
+                    "\"match\" condition \"{\" ( match_arm ( \",\" match_arm )* \",\"? )? \"}\"",
+                    "\"break\" LABEL? expression?",
+                    "\"continue\" LABEL?",
+                    "\"return\" expression?",
+                ],
+            )
+            .note("`break` and `return` have no value before `;`, `,` or a closing delimiter"),
+            rule(
+                "inline_asm",
+                "parse_inline_asm",
+                &["\"asm\" \"!\" \"(\" STRING ( \",\" STRING )* ( \",\" asm_operand )* ( \",\" IDENT \"(\" ( IDENT ( \",\" IDENT )* \",\"? )? \")\" )? \",\"? \")\""],
+            )
+            .note("inline assembly requires `#![feature(asm)]` and an `unsafe` block; the last IDENT is `options`"),
+            rule(
+                "asm_operand",
+                "parse_asm_operand",
+                &["( \"in\" | IDENT ) \"(\" ( IDENT | STRING ) \")\" ( expression | \"_\" )"],
+            )
+            .note("the IDENT before the register is `out`, `lateout` or `inout`; only outputs can be `_`"),
+            rule(
+                "literal",
+                "parse_primary",
+                &["INT", "\"true\"", "\"false\"", "CHAR", "STRING"],
+            ),
+            rule(
+                "path_expression",
+                "parse_turbofish",
+                &["IDENT ( \"::\" IDENT )* ( \"::\" \"<\" type_args )?"],
+            ),
+            rule(
+                "struct_literal",
+                "parse_struct_fields",
+                &["IDENT \"{\" ( struct_field ( \",\" struct_field )* \",\"? )? \"}\""],
+            ),
+            rule(
+                "struct_field",
+                "parse_struct_fields",
+                &["IDENT ( \":\" expression )?"],
+            ),
+            rule(
+                "closure",
+                "parse_closure",
+                &[
+                    "\"|\" ( closure_param ( \",\" closure_param )* \",\"? )? \"|\" ( \"->\" type )? expression",
+                    "\"||\" ( \"->\" type )? expression",
+                    "\"(\" parameter ( \",\" parameter )* \",\"? \")\" ( \"->\" type )? expression",
+                ],
+            ),
+            rule(
+                "closure_param",
+                "parse_closure_param",
+                &["single_pattern ( \":\" type )?"],
+            ),
+        ],
+    },
+];
+
+/// 所有规则，按输出的顺序
+pub fn rules() -> impl Iterator<Item = &'static Rule> {
+    GRAMMAR.iter().flat_map(|section| section.rules)
+}
+
+/// 按名字查找规则
+pub fn rule_named(name: &str) -> Option<&'static Rule> {
+    rules().find(|rule| rule.name == name)
+}
+
+/// EBNF 表达式中的一个符号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbol<'a> {
+    /// 引号中的终结符，不含引号
+    Terminal(&'a str),
+    /// 大写的记号类别，如 `IDENT`
+    TokenClass(&'a str),
+    /// 引用的其他规则
+    Rule(&'a str),
+}
+
+/// 表达式中按出现顺序的符号，跳过括号和 `?`、`*`、`+`、`|`
+pub fn symbols(expression: &str) -> Vec<Symbol<'_>> {
+    let mut symbols = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '"' {
+            // 终结符中没有引号：`"` 本身不是记号
+            let end = rest[1..].find('"').map_or(rest.len(), |end| end + 1);
+            symbols.push(Symbol::Terminal(&rest[1..end]));
+            rest = rest.get(end + 1..).unwrap_or("");
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            symbols.push(match word.chars().all(|c| c.is_ascii_uppercase()) {
+                true => Symbol::TokenClass(word),
+                false => Symbol::Rule(word),
+            });
+            rest = &rest[end..];
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+        rest = rest.trim_start();
+    }
+    symbols
+}
+
+/// 所有规则中出现的终结符，去重并保持第一次出现的顺序
+pub fn terminals() -> Vec<&'static str> {
+    let mut terminals = Vec::new();
+    for rule in rules() {
+        for alternative in rule.alternatives {
+            for symbol in symbols(alternative) {
+                if let Symbol::Terminal(text) = symbol {
+                    if !terminals.contains(&text) {
+                        terminals.push(text);
+                    }
+                }
+            }
+        }
+    }
+    terminals
+}
+
+/// 整个语法的 EBNF 文本，`contractus --emit=grammar` 的输出
+pub fn ebnf() -> String {
+    let width = rules().map(|rule| rule.name.len()).max().unwrap_or(0);
+    let mut out = String::from(
+        "/* Contractus grammar in W3C EBNF notation, generated from contractus::grammar */\n",
+    );
+    out.push_str("\n/* Tokens\n");
+    for (class, description) in TOKEN_CLASSES {
+        out.push_str(&format!("   {:<8} {}\n", class, description));
+    }
+    out.push_str(&format!(
+        "   Contextual keywords, lexed as IDENT: {}\n   Start symbol: {} */\n",
+        CONTEXTUAL_KEYWORDS.join(" "),
+        START
+    ));
+    for section in GRAMMAR {
+        out.push_str(&format!("\n/* {} */\n\n", section.title));
+        for rule in section.rules {
+            if let Some(note) = rule.note {
+                out.push_str(&format!("/* {} */\n", note));
+            }
+            for (i, alternative) in rule.alternatives.iter().enumerate() {
+                match i {
+                    0 => out.push_str(&format!("{:<width$} ::= {}\n", rule.name, alternative)),
+                    _ => out.push_str(&format!("{:<width$}   | {}\n", "", alternative)),
+                }
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,218 @@
+// 语法高亮 - 每个记号的种类、位置和高亮类别（`--emit=tokens-json`、`--emit=tokens-csv`）
+//
+// 编辑器插件和 playground 不嵌入编译器也能做基本的高亮。只看记号本身，不做语法分析，
+// 所以正在编辑、有词法错误的文件也能分类：出错的字符是 `error` 类别，注释也是记号。
+// 名字按后面的记号和大小写细分：紧跟 `!` 的是宏，紧跟 `(` 的是函数，全部大写的是常量，
+// 大写开头的是类型；上下文关键字（`requires` 等）后面不是冒号时是关键字
+
+use crate::ast::write_json_string;
+use crate::grammar::CONTEXTUAL_KEYWORDS;
+use crate::lexer::Lexer;
+use crate::span::Span;
+use crate::token::{TokenKind, KEYWORDS, OPERATORS, PUNCTUATION};
+use std::fmt;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightClass {
+    Keyword,
+    Type,
+    Function,
+    Macro,
+    Constant,
+    Variable,
+    Label,
+    Number,
+    String,
+    Char,
+    Boolean,
+    Operator,
+    Punctuation,
+    Comment,
+    Error,
+}
+
+impl HighlightClass {
+    pub const ALL: [HighlightClass; 15] = [
+        HighlightClass::Keyword,
+        HighlightClass::Type,
+        HighlightClass::Function,
+        HighlightClass::Macro,
+        HighlightClass::Constant,
+        HighlightClass::Variable,
+        HighlightClass::Label,
+        HighlightClass::Number,
+        HighlightClass::String,
+        HighlightClass::Char,
+        HighlightClass::Boolean,
+        HighlightClass::Operator,
+        HighlightClass::Punctuation,
+        HighlightClass::Comment,
+        HighlightClass::Error,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HighlightClass::Keyword => "keyword",
+            HighlightClass::Type => "type",
+            HighlightClass::Function => "function",
+            HighlightClass::Macro => "macro",
+            HighlightClass::Constant => "constant",
+            HighlightClass::Variable => "variable",
+            HighlightClass::Label => "label",
+            HighlightClass::Number => "number",
+            HighlightClass::String => "string",
+            HighlightClass::Char => "char",
+            HighlightClass::Boolean => "boolean",
+            HighlightClass::Operator => "operator",
+            HighlightClass::Punctuation => "punctuation",
+            HighlightClass::Comment => "comment",
+            HighlightClass::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for HighlightClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 一个分好类的记号。`kind` 是 `TokenKind` 的变体名，注释是 `Comment`，出错的字符是 `Error`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighlightToken {
+    pub kind: String,
+    pub text: String,
+    pub span: Span,
+    pub class: HighlightClass,
+}
+
+/// 源码中所有的记号和注释，按位置排序，不包括结尾的 `Eof`
+pub fn classify(source: &str) -> Vec<HighlightToken> {
+    let (tokens, comments, errors) = Lexer::new(source).tokenize_lossy();
+    let text = |span: Span| source.get(span.start..span.end).unwrap_or("").to_string();
+
+    let mut out = Vec::with_capacity(tokens.len() + comments.len());
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::Eof {
+            continue;
+        }
+        let next = tokens.get(i + 1).map(|next| &next.kind);
+        // 变体名，去掉 `Ident("x")` 等的内容
+        let kind = format!("{:?}", token.kind);
+        let kind = kind.split('(').next().unwrap_or_default().to_string();
+        out.push(HighlightToken {
+            kind,
+            text: text(token.span),
+            span: token.span,
+            class: class_of(&token.kind, next),
+        });
+    }
+    for span in comments {
+        out.push(HighlightToken {
+            kind: "Comment".to_string(),
+            text: text(span),
+            span,
+            class: HighlightClass::Comment,
+        });
+    }
+    for error in errors {
+        out.push(HighlightToken {
+            kind: "Error".to_string(),
+            text: text(error.span),
+            span: error.span,
+            class: HighlightClass::Error,
+        });
+    }
+    out.sort_by_key(|token| token.span.start);
+    out
+}
+
+// 记号的类别，`next` 是紧跟着的记号
+fn class_of(kind: &TokenKind, next: Option<&TokenKind>) -> HighlightClass {
+    match kind {
+        TokenKind::IntLiteral(_) | TokenKind::SuffixedIntLiteral(..) => HighlightClass::Number,
+        TokenKind::BoolLiteral(_) => HighlightClass::Boolean,
+        TokenKind::StringLiteral(_) => HighlightClass::String,
+        TokenKind::CharLiteral(_) => HighlightClass::Char,
+        TokenKind::Label(_) => HighlightClass::Label,
+        TokenKind::Ident(name) => match next {
+            Some(TokenKind::LogicalNot) => HighlightClass::Macro,
+            // 名为 `invariant` 的字段后面是冒号
+            Some(next)
+                if CONTEXTUAL_KEYWORDS.contains(&name.as_str()) && *next != TokenKind::Colon =>
+            {
+                HighlightClass::Keyword
+            }
+            Some(TokenKind::LeftParen) => HighlightClass::Function,
+            _ if is_constant_name(name) => HighlightClass::Constant,
+            _ if name.starts_with(|c: char| c.is_ascii_uppercase()) => HighlightClass::Type,
+            _ => HighlightClass::Variable,
+        },
+        kind if kind.is_type_keyword() => HighlightClass::Type,
+        kind if in_table(KEYWORDS, kind) => HighlightClass::Keyword,
+        kind if in_table(OPERATORS, kind) => HighlightClass::Operator,
+        kind if in_table(PUNCTUATION, kind) => HighlightClass::Punctuation,
+        _ => HighlightClass::Error,
+    }
+}
+
+fn in_table(table: &[(&str, TokenKind)], kind: &TokenKind) -> bool {
+    table.iter().any(|(_, entry)| entry == kind)
+}
+
+// `LIMIT`、`MAX_LEN`：至少两个字符，没有小写字母
+fn is_constant_name(name: &str) -> bool {
+    name.len() > 1
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && !name.contains(|c: char| c.is_ascii_lowercase())
+}
+
+/// 每个记号一行的 JSON：`{"tokens": [{"kind", "text", "class", "start", "end", "line", "column"}, ...]}`，
+/// `start` 和 `end` 是字节偏移，行号和列号从 1 开始
+pub fn to_json(tokens: &[HighlightToken]) -> String {
+    let mut out = String::from("{\n  \"tokens\": [");
+    for (i, token) in tokens.iter().enumerate() {
+        out.push_str(if i == 0 {
+            "\n    {\"kind\": "
+        } else {
+            ",\n    {\"kind\": "
+        });
+        write_json_string(&mut out, &token.kind);
+        out.push_str(", \"text\": ");
+        write_json_string(&mut out, &token.text);
+        let _ = write!(
+            out,
+            ", \"class\": \"{}\", \"start\": {}, \"end\": {}, \"line\": {}, \"column\": {}}}",
+            token.class, token.span.start, token.span.end, token.span.line, token.span.column
+        );
+    }
+    if !tokens.is_empty() {
+        out.push_str("\n  ");
+    }
+    out.push_str("]\n}\n");
+    out
+}
+
+/// CSV，第一行是列名；含有逗号、引号或换行的文本加上引号，其中的引号写两次
+pub fn to_csv(tokens: &[HighlightToken]) -> String {
+    let mut out = String::from("line,column,start,end,kind,class,text\n");
+    for token in tokens {
+        let _ = write!(
+            out,
+            "{},{},{},{},{},{},",
+            token.span.line,
+            token.span.column,
+            token.span.start,
+            token.span.end,
+            token.kind,
+            token.class
+        );
+        if token.text.contains([',', '"', '\n', '\r']) {
+            let _ = writeln!(out, "\"{}\"", token.text.replace('"', "\"\""));
+        } else {
+            let _ = writeln!(out, "{}", token.text);
+        }
+    }
+    out
+}
@@ -0,0 +1,201 @@
+// 增量编译：流水线的各阶段是按内容哈希记忆结果的查询
+//
+//     查询              结果                            键                            保存在
+//     check(crate)      语义分析和 MIR 检查通过          crate 的内容哈希              缓存目录
+//     lower(item)       一个函数或静态变量的 MIR         条目、程序接口和契约模式      内存
+//     object(crate)     字节码模块                      crate 的内容哈希              缓存目录
+//
+// crate 的内容哈希就是 `Compiler::content_hash`，包括所有读入的文件和编译选项。
+// 程序接口是降级一个条目时能看到的其他条目：类型、常量、静态变量的定义，以及函数的名字和签名；
+// 只改动一个函数体时，其他条目的键不变，不需要重新降级。条目的键包含位置信息，
+// 改动使之后的条目的位置变化时，它们也要重新降级（MIR 中的位置要和源码一致）。
+// 缓存目录（项目的 `target/incremental`）中 `<hash>.check` 记录通过检查的 crate，
+// `<hash>.ctxb` 是字节码模块。内存中的结果在共用一个 `QueryCache` 的多次编译之间复用。
+// 缓存只用来加速：读不出或损坏的记录按未命中处理，写入失败只会导致下次重新计算
+
+use crate::ast::{Item, Program};
+use crate::bytecode;
+use crate::log;
+use crate::manifest::{fnv1a, FNV_OFFSET};
+use crate::mir::{Body, LowerOptions};
+use crate::prelude;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 缓存目录在项目目录下的位置
+pub const INCREMENTAL_DIR: &str = "incremental";
+
+/// 各查询的命中和未命中次数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+#[derive(Debug, Default)]
+struct Memo {
+    lowered: HashMap<u64, (Body, Vec<Body>)>, // 条目的 MIR 和其中的闭包
+    checked: HashSet<u64>,
+    objects: HashMap<u64, bytecode::Module>,
+    stats: BTreeMap<&'static str, QueryStats>,
+}
+
+/// 查询结果的缓存，用 `Compiler::incremental` 交给编译器。可以在线程之间共享
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    dir: Option<PathBuf>,
+    memo: Mutex<Memo>,
+}
+
+impl QueryCache {
+    /// 只在内存中记忆结果
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// crate 级的结果还保存到 `dir` 中，之后的进程可以复用
+    pub fn persistent(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+            memo: Mutex::default(),
+        }
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// 查询的命中情况，按查询名排序
+    pub fn stats(&self) -> Vec<(&'static str, QueryStats)> {
+        let memo = self.memo.lock().unwrap();
+        memo.stats
+            .iter()
+            .map(|(name, stats)| (*name, *stats))
+            .collect()
+    }
+
+    /// crate 是否已经通过检查
+    pub(crate) fn is_checked(&self, hash: u64) -> bool {
+        let mut memo = self.memo.lock().unwrap();
+        let found = memo.checked.contains(&hash)
+            || self.file(hash, "check").is_some_and(|file| file.is_file());
+        if found {
+            memo.checked.insert(hash);
+        }
+        record(&mut memo, "check", found, hash);
+        found
+    }
+
+    pub(crate) fn set_checked(&self, hash: u64) {
+        self.memo.lock().unwrap().checked.insert(hash);
+        self.store(hash, "check", b"");
+    }
+
+    pub(crate) fn object(&self, hash: u64) -> Option<bytecode::Module> {
+        let mut memo = self.memo.lock().unwrap();
+        let mut module = memo.objects.get(&hash).cloned();
+        if module.is_none() {
+            module = self
+                .file(hash, bytecode::EXTENSION)
+                .and_then(|file| fs::read(file).ok())
+                .and_then(|bytes| bytecode::decode(&bytes).ok())
+                .filter(|module| module.hash == hash);
+            if let Some(module) = &module {
+                memo.objects.insert(hash, module.clone());
+            }
+        }
+        record(&mut memo, "object", module.is_some(), hash);
+        module
+    }
+
+    pub(crate) fn set_object(&self, module: &bytecode::Module) {
+        let mut memo = self.memo.lock().unwrap();
+        memo.objects.insert(module.hash, module.clone());
+        drop(memo);
+        self.store(module.hash, bytecode::EXTENSION, &bytecode::encode(module));
+    }
+
+    pub(crate) fn lowered(&self, key: u64) -> Option<(Body, Vec<Body>)> {
+        let mut memo = self.memo.lock().unwrap();
+        let lowered = memo.lowered.get(&key).cloned();
+        record(&mut memo, "lower", lowered.is_some(), key);
+        lowered
+    }
+
+    pub(crate) fn set_lowered(&self, key: u64, body: &Body, closures: &[Body]) {
+        let mut memo = self.memo.lock().unwrap();
+        memo.lowered.insert(key, (body.clone(), closures.to_vec()));
+    }
+
+    fn file(&self, hash: u64, extension: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(format!("{:016x}.{}", hash, extension)))
+    }
+
+    // 先写到临时文件再改名，并发的编译不会读到写了一半的文件
+    fn store(&self, hash: u64, extension: &str, bytes: &[u8]) {
+        let (Some(dir), Some(file)) = (&self.dir, self.file(hash, extension)) else {
+            return;
+        };
+        let temp = file.with_extension(format!("{}.{}.tmp", extension, std::process::id()));
+        let result = fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&temp, bytes))
+            .and_then(|_| fs::rename(&temp, &file));
+        if let Err(error) = result {
+            log::debug!("cannot write `{}`: {}", file.display(), error);
+            let _ = fs::remove_file(&temp);
+        }
+    }
+}
+
+fn record(memo: &mut Memo, query: &'static str, hit: bool, key: u64) {
+    let stats = memo.stats.entry(query).or_default();
+    if hit {
+        stats.hits += 1;
+    } else {
+        stats.misses += 1;
+    }
+    log::trace!(
+        "query `{}` {:016x}: {}",
+        query,
+        key,
+        if hit { "hit" } else { "miss" }
+    );
+}
+
+/// 降级条目时能看到的程序接口的哈希：函数只计入名字和签名
+pub fn interface_hash(program: &Program) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for item in prelude::items_for(program).chain(&program.items) {
+        let text = match item {
+            Item::Function(func) => {
+                let generics: Vec<(&str, &[String])> = func
+                    .generics
+                    .iter()
+                    .flat_map(|generics| &generics.params)
+                    .map(|param| (param.name.as_str(), param.bounds.as_slice()))
+                    .collect();
+                let params: Vec<_> = func.params.iter().map(|param| &param.ty).collect();
+                format!(
+                    "fn {} {:?} {:?} {:?}",
+                    func.name, generics, params, func.return_type
+                )
+            }
+            Item::Import(_) | Item::Export(_) | Item::MacroRules(_) | Item::MacroCall(_) => {
+                continue;
+            }
+            item => format!("{:?}", item),
+        };
+        hash = fnv1a(hash, text.as_bytes());
+        hash = fnv1a(hash, &[0]);
+    }
+    hash
+}
+
+/// `lower` 查询的键
+pub fn item_key(interface: u64, options: LowerOptions, item: &Item) -> u64 {
+    let text = format!("{:016x} {:?} {:?}", interface, options, item);
+    fnv1a(FNV_OFFSET, text.as_bytes())
+}
@@ -0,0 +1,1711 @@
+// Contractus 树遍历解释器
+// 直接对语法树求值，在本地代码生成完成之前让程序可以运行：
+// - 每次函数调用一个栈帧，栈帧内按代码块分作用域，变量保存在共享的槽中
+// - return/break/continue 作为 Flow 沿调用链向上传递，运行时错误也走同一条路径
+// - 方法调用按普通函数调用处理，接收者作为第一个参数（与 MIR 构建一致）
+// - 闭包和 MIR 一样按值捕获函数体中用到的局部变量，每次调用从捕获的值开始
+// - 内建函数（`print`、`len`、`push` 等，见 builtins.rs）在没有被同名的变量或函数遮蔽时调用；
+//   `Vec<T>` 就是数组，`StringBuilder` 就是字符串，`Map<K, V>` 是哈希表（table.rs），
+//   修改它们的内建函数通过引用写入；`io` 模块的函数经过宿主接口（host.rs），需要宿主打开 io 能力
+// - 契约与 MIR 的检查一致：requires/ensures 在调用处报告，invariant 在结构体字面量处报告
+// - 设置了调试器（debug.rs）时，每条语句执行之前调用它，调试器可以停下查看变量或结束程序
+// 假定程序已经通过语义分析，名字解析失败等错误在这里只作为运行时错误报告
+
+mod debug;
+mod host;
+pub(crate) mod ops;
+mod table;
+mod value;
+
+pub use debug::{DebugAction, DebugContext, Debugger};
+pub use host::Host;
+pub(crate) use host::{env_var, now_millis, read_file, sleep, write_file};
+pub use table::{Key, Table};
+pub use value::{Closure, Pointer, Slot, Step, Value};
+
+use crate::ast::{
+    BinOp, Block, Contract, ContractKind, ElseBranch, Expr, Function, Item, Literal, MatchArm,
+    OperandType, Parameter, Pattern, Program, Statement, StructDef, Type, UnOp,
+};
+use crate::builtins;
+use crate::derive;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::fmt;
+use crate::layout::{self, Layouts};
+use crate::mir::{self, ContractMode, PanicStrategy};
+use crate::prelude;
+use crate::sema;
+use crate::span::Span;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::thread;
+
+/// 函数调用的最大嵌套深度，超过时报告栈溢出而不是让解释器自身崩溃
+pub const CALL_DEPTH_LIMIT: usize = 10_000;
+
+/// 解释器线程的栈大小。树遍历求值每层调用占用数 KB 到数十 KB 的宿主栈，
+/// 需要足够的栈才能在达到 CALL_DEPTH_LIMIT 之前不溢出
+pub const STACK_SIZE: usize = 512 << 20;
+
+// 非正常的控制流：沿求值过程向上传递，直到被循环或函数调用接住
+enum Flow {
+    Break(Option<String>), // 目标循环的标签，没有标签时是最内层的循环
+    Continue(Option<String>),
+    Return(Value),
+    Error(Box<Diagnostic>),
+    Exit, // `io::exit`，退出码记录在解释器中
+}
+
+impl From<Diagnostic> for Flow {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Flow::Error(Box::new(diagnostic))
+    }
+}
+
+type Eval<T> = Result<T, Flow>;
+
+fn runtime_error<T>(message: impl Into<String>, span: Span) -> Eval<T> {
+    Err(Diagnostic::error(message.into(), span)
+        .with_code(ErrorCode::E0900)
+        .into())
+}
+
+struct Frame<'p> {
+    scopes: Vec<HashMap<String, Slot>>,
+    function: &'p str, // 调试器显示的函数名
+    span: Span,        // 正在执行的语句，只在调试时记录
+}
+
+impl<'p> Frame<'p> {
+    fn new(function: &'p str, scopes: Vec<HashMap<String, Slot>>) -> Self {
+        Self {
+            scopes,
+            function,
+            span: Span::new(0, 0, 1, 1),
+        }
+    }
+}
+
+/// 跨多次求值保留的顶层状态，REPL 的每次输入都在同一个环境中执行
+#[derive(Default)]
+pub struct Env {
+    globals: HashMap<String, Slot>,
+    locals: HashMap<String, Slot>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 顶层变量的当前值
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.locals.get(name).map(|slot| slot.borrow().clone())
+    }
+
+    /// 丢弃 const/static 的值，下次求值时按新的定义重新初始化
+    pub fn forget_global(&mut self, name: &str) {
+        self.globals.remove(name);
+    }
+}
+
+pub struct Interpreter<'p, W: Write = io::Stdout> {
+    functions: HashMap<&'p str, &'p Function>,
+    structs: HashMap<&'p str, &'p StructDef>,
+    variants: HashMap<&'p str, &'p str>, // 变体名 -> 所属枚举
+    globals: HashMap<String, Slot>,      // const 和 static
+    program: &'p Program,
+    frames: Vec<Frame<'p>>,
+    contracts: ContractMode,
+    panic: PanicStrategy,
+    host: Host,
+    debugger: Option<Box<dyn Debugger + 'p>>,
+    exit_code: Option<i32>,
+    output: W,
+}
+
+impl<'p> Interpreter<'p> {
+    /// 输出到标准输出的解释器
+    pub fn new(program: &'p Program) -> Self {
+        Self::with_output(program, io::stdout())
+    }
+}
+
+impl<'p, W: Write> Interpreter<'p, W> {
+    pub fn with_output(program: &'p Program, output: W) -> Self {
+        let mut functions = HashMap::new();
+        let mut structs = HashMap::new();
+        let mut variants = HashMap::new();
+        for item in prelude::items_for(program).chain(&program.items) {
+            match item {
+                Item::Function(func) => {
+                    functions.insert(func.name.as_str(), func);
+                }
+                Item::Struct(def) => {
+                    structs.insert(def.name.as_str(), def);
+                }
+                Item::Enum(def) => {
+                    for variant in &def.variants {
+                        variants.insert(variant.name.as_str(), def.name.as_str());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            functions,
+            structs,
+            variants,
+            globals: HashMap::new(),
+            program,
+            frames: Vec::new(),
+            contracts: ContractMode::default(),
+            panic: PanicStrategy::default(),
+            host: Host::default(),
+            debugger: None,
+            exit_code: None,
+            output,
+        }
+    }
+
+    /// `ContractMode::Off` 时不检查契约，条件也不求值
+    pub fn set_contract_mode(&mut self, mode: ContractMode) {
+        self.contracts = mode;
+    }
+
+    /// `PanicStrategy::Abort` 时 `catch_panic` 不捕获 panic，程序立即结束
+    pub fn set_panic_strategy(&mut self, strategy: PanicStrategy) {
+        self.panic = strategy;
+    }
+
+    /// 程序可以使用的宿主环境，默认不允许使用 `io` 模块
+    pub fn set_host(&mut self, host: Host) {
+        self.host = host;
+    }
+
+    /// 在每条语句执行之前调用的调试器
+    pub fn set_debugger(&mut self, debugger: impl Debugger + 'p) {
+        self.debugger = Some(Box::new(debugger));
+    }
+
+    /// 进程的退出码：程序调用 `io::exit` 时是它给出的值，否则是 `main() -> i32` 的返回值
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// 初始化 const/static 后执行 `main`，返回 `main` 的返回值
+    pub fn run_main(&mut self) -> Result<Value, Vec<Diagnostic>> {
+        self.init_globals()?;
+        if !self.functions.contains_key("main") {
+            return Err(vec![Diagnostic::error(
+                "`main` function not found".to_string(),
+                self.program.span,
+            )
+            .with_code(ErrorCode::E0601)]);
+        }
+        let value = self.call_function("main", Vec::new(), self.program.span)?;
+        if let (None, Value::Int(code)) = (self.exit_code, &value) {
+            self.exit_code = Some(*code as i32);
+        }
+        Ok(value)
+    }
+
+    /// 求值尚未初始化的 const 和 static，每个条目在它用到的条目之后求值，
+    /// 互不依赖时按声明顺序（见 sema/globals.rs）
+    pub fn init_globals(&mut self) -> Result<(), Vec<Diagnostic>> {
+        for item in sema::initialization_order(&self.program.items).items {
+            let (name, value, span) = match item {
+                Item::Const(def) => (&def.name, &def.value, def.span),
+                Item::Static(def) => (&def.name, &def.value, def.span),
+                _ => continue,
+            };
+            if self.globals.contains_key(name) {
+                continue;
+            }
+            self.frames.push(Frame::new(name, vec![HashMap::new()]));
+            let value = self.eval_expr(value);
+            self.frames.pop();
+            let value = finish(value, span)?;
+            self.globals.insert(name.clone(), value.new_slot());
+        }
+        Ok(())
+    }
+
+    /// 在环境的顶层作用域中执行语句，返回最后一个表达式的值（规则与代码块相同）。
+    /// 新的 let 绑定和 static 的修改写回环境
+    pub fn eval_statements(
+        &mut self,
+        env: &mut Env,
+        statements: &[Statement],
+    ) -> Result<Value, Vec<Diagnostic>> {
+        self.globals = std::mem::take(&mut env.globals);
+        let result = self.init_globals().and_then(|()| {
+            self.frames.push(Frame::new(
+                "<top level>",
+                vec![std::mem::take(&mut env.locals)],
+            ));
+            let result = self.exec_statements(statements);
+            let mut frame = self.frames.pop().expect("no active frame");
+            env.locals = frame.scopes.swap_remove(0);
+            finish(result, self.program.span)
+        });
+        env.globals = std::mem::take(&mut self.globals);
+        result
+    }
+
+    /// 以给定实参调用程序中的函数
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, Vec<Diagnostic>> {
+        finish(
+            self.call(&Value::Function(name.to_string()), args, span),
+            span,
+        )
+    }
+
+    // ---------------- 调用 ----------------
+
+    fn call(&mut self, callee: &Value, args: Vec<Value>, span: Span) -> Eval<Value> {
+        if self.frames.len() >= CALL_DEPTH_LIMIT {
+            return runtime_error(
+                format!(
+                    "stack overflow: call depth exceeded the limit of {}",
+                    CALL_DEPTH_LIMIT
+                ),
+                span,
+            );
+        }
+
+        let result = match callee {
+            Value::Function(name) => {
+                let Some(func) = self.functions.get(name.as_str()).copied() else {
+                    return runtime_error(format!("cannot find function `{}`", name), span);
+                };
+                self.enter(
+                    &func.name,
+                    HashMap::new(),
+                    &func.params,
+                    args,
+                    span,
+                    |this| {
+                        this.check_function_contracts(func, ContractKind::Requires, None, span)?;
+                        let value = match this.eval_block(&func.body) {
+                            Ok(value) | Err(Flow::Return(value)) => value,
+                            Err(flow) => return Err(flow),
+                        };
+                        this.check_function_contracts(
+                            func,
+                            ContractKind::Ensures,
+                            Some(&value),
+                            span,
+                        )?;
+                        Ok(value)
+                    },
+                )
+            }
+            Value::Closure(closure) => {
+                let closure = Rc::clone(closure);
+                let captures = closure
+                    .captures
+                    .iter()
+                    .map(|(name, value)| (name.clone(), Rc::new(RefCell::new(value.clone()))))
+                    .collect();
+                self.enter("<closure>", captures, &closure.params, args, span, |this| {
+                    this.eval_expr(&closure.body)
+                })
+            }
+            other => {
+                return runtime_error(format!("{} value is not callable", other.type_name()), span)
+            }
+        };
+        match result {
+            Err(Flow::Return(value)) => Ok(value),
+            Err(Flow::Break(_) | Flow::Continue(_)) => {
+                runtime_error("`break` or `continue` outside of a loop", span)
+            }
+            other => other,
+        }
+    }
+
+    // 在新栈帧中绑定参数并执行函数体；闭包的栈帧以捕获的变量为最外层作用域
+    fn enter(
+        &mut self,
+        function: &'p str,
+        captures: HashMap<String, Slot>,
+        params: &[Parameter],
+        args: Vec<Value>,
+        span: Span,
+        body: impl FnOnce(&mut Self) -> Eval<Value>,
+    ) -> Eval<Value> {
+        if params.len() != args.len() {
+            return runtime_error(
+                format!(
+                    "this function takes {} arguments but {} were supplied",
+                    params.len(),
+                    args.len()
+                ),
+                span,
+            );
+        }
+        self.frames
+            .push(Frame::new(function, vec![captures, HashMap::new()]));
+        let result = params
+            .iter()
+            .zip(args)
+            .try_for_each(|(param, arg)| self.bind_irrefutable(&param.pattern, arg, span))
+            .and_then(|()| body(self));
+        self.frames.pop();
+        result
+    }
+
+    // 检查函数的 requires 或 ensures，`result` 绑定到返回值，违反时在调用处报告
+    fn check_function_contracts(
+        &mut self,
+        func: &Function,
+        kind: ContractKind,
+        result: Option<&Value>,
+        span: Span,
+    ) -> Eval<()> {
+        if self.contracts == ContractMode::Off {
+            return Ok(());
+        }
+        for contract in func.contracts.iter().filter(|c| c.kind == kind) {
+            let bindings = result
+                .map(|value| ("result".to_string(), value.clone()))
+                .into_iter()
+                .collect();
+            self.check_contract(contract, &func.name, bindings, span)?;
+        }
+        Ok(())
+    }
+
+    fn check_contract(
+        &mut self,
+        contract: &Contract,
+        owner: &str,
+        bindings: Vec<(String, Value)>,
+        span: Span,
+    ) -> Eval<()> {
+        match self.scoped(bindings, |this| this.eval_expr(&contract.condition))? {
+            Value::Bool(true) => Ok(()),
+            _ => {
+                let message = ops::contract_violation(contract.kind, &contract.text);
+                let note = ops::contract_note(contract.kind, owner, contract.span);
+                Err(Diagnostic::error(message, span)
+                    .with_code(ErrorCode::E0901)
+                    .with_note(note)
+                    .into())
+            }
+        }
+    }
+
+    // ---------------- 作用域 ----------------
+
+    fn frame(&mut self) -> &mut Frame<'p> {
+        self.frames.last_mut().expect("no active frame")
+    }
+
+    fn declare(&mut self, name: String, value: Value) {
+        let scope = self.frame().scopes.last_mut().expect("no active scope");
+        scope.insert(name, value.new_slot());
+    }
+
+    fn lookup(&self, name: &str) -> Option<Slot> {
+        let frame = self.frames.last()?;
+        frame
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+            .cloned()
+    }
+
+    // 在新作用域中执行，无论结果如何都恢复作用域
+    fn scoped<T>(
+        &mut self,
+        bindings: Vec<(String, Value)>,
+        eval: impl FnOnce(&mut Self) -> Eval<T>,
+    ) -> Eval<T> {
+        self.frame().scopes.push(HashMap::new());
+        for (name, value) in bindings {
+            self.declare(name, value);
+        }
+        let result = eval(self);
+        self.frame().scopes.pop();
+        result
+    }
+
+    // ---------------- 语句 ----------------
+
+    // 代码块的值与 MIR 构建一致：最后一个不带分号的表达式，或者处于末尾的 if/match/代码块
+    fn eval_block(&mut self, block: &Block) -> Eval<Value> {
+        self.scoped(Vec::new(), |this| this.exec_statements(&block.statements))
+    }
+
+    fn exec_statements(&mut self, statements: &[Statement]) -> Eval<Value> {
+        let mut value = Value::Unit;
+        for (i, stmt) in statements.iter().enumerate() {
+            let is_tail = i + 1 == statements.len();
+            let result = self.exec_statement(stmt)?;
+            let has_value = match stmt {
+                Statement::Expr(expr_stmt) => !expr_stmt.semicolon,
+                Statement::If(_) | Statement::Match(_) | Statement::Block(_) => true,
+                _ => false,
+            };
+            if is_tail && has_value {
+                value = result;
+            }
+        }
+        Ok(value)
+    }
+
+    fn exec_statement(&mut self, stmt: &Statement) -> Eval<Value> {
+        if self.debugger.is_some() {
+            self.pause(stmt.span())?;
+        }
+        match stmt {
+            Statement::Let(let_stmt) => {
+                let value = self.eval_optional(let_stmt.init.as_ref())?;
+                match &let_stmt.else_block {
+                    Some(else_block) => self.bind_or_else(&let_stmt.pattern, value, else_block)?,
+                    None => self.bind_irrefutable(&let_stmt.pattern, value, let_stmt.span)?,
+                }
+                Ok(Value::Unit)
+            }
+            Statement::Expr(expr_stmt) => self.eval_expr(&expr_stmt.expr),
+            Statement::Return(ret) => Err(Flow::Return(self.eval_optional(ret.expr.as_ref())?)),
+            Statement::If(if_stmt) => self.eval_if(
+                &if_stmt.cond,
+                &if_stmt.then_block,
+                if_stmt.else_block.as_ref(),
+            ),
+            Statement::While(while_stmt) => self.eval_while(
+                while_stmt.label.as_ref(),
+                &while_stmt.cond,
+                &while_stmt.invariants,
+                &while_stmt.body,
+            ),
+            Statement::For(for_stmt) => self.eval_for(
+                for_stmt.label.as_ref(),
+                &for_stmt.pattern,
+                &for_stmt.iterable,
+                &for_stmt.invariants,
+                &for_stmt.body,
+                for_stmt.span,
+            ),
+            Statement::Match(match_stmt) => {
+                self.eval_match(&match_stmt.expr, &match_stmt.arms, match_stmt.span)
+            }
+            Statement::Break(break_stmt) => {
+                self.eval_optional(break_stmt.expr.as_ref())?;
+                Err(Flow::Break(break_stmt.label.clone()))
+            }
+            Statement::Continue(stmt) => Err(Flow::Continue(stmt.label.clone())),
+            Statement::Block(block) => self.eval_block(block),
+        }
+    }
+
+    // 记录栈帧中正在执行的语句，交给调试器决定是否继续
+    fn pause(&mut self, span: Span) -> Eval<()> {
+        self.frame().span = span;
+        let Some(mut debugger) = self.debugger.take() else {
+            return Ok(());
+        };
+        let context = DebugContext {
+            frames: &self.frames,
+            globals: &self.globals,
+        };
+        let action = debugger.before_statement(&context);
+        self.debugger = Some(debugger);
+        match action {
+            DebugAction::Continue => Ok(()),
+            DebugAction::Terminate => Err(Flow::Exit),
+        }
+    }
+
+    fn eval_if(
+        &mut self,
+        cond: &Expr,
+        then_block: &Block,
+        else_block: Option<&ElseBranch>,
+    ) -> Eval<Value> {
+        if self.eval_bool(cond)? {
+            return self.eval_block(then_block);
+        }
+        match else_block {
+            Some(ElseBranch::Block(block)) => self.eval_block(block),
+            Some(ElseBranch::If(nested)) => {
+                self.eval_if(&nested.cond, &nested.then_block, nested.else_block.as_ref())
+            }
+            None => Ok(Value::Unit),
+        }
+    }
+
+    // 循环不变式在每次求值条件之前检查：进入循环时、每次迭代之后和条件为假离开时
+    fn eval_while(
+        &mut self,
+        label: Option<&String>,
+        cond: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+    ) -> Eval<Value> {
+        loop {
+            self.check_loop_invariants(invariants)?;
+            if !self.eval_bool(cond)? || !next_iteration(label, self.eval_block(body))? {
+                break;
+            }
+        }
+        Ok(Value::Unit)
+    }
+
+    fn eval_for(
+        &mut self,
+        label: Option<&String>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+        span: Span,
+    ) -> Eval<Value> {
+        let items: Vec<Value> = match self.eval_expr(iterable)? {
+            Value::Range(start, end) => (start..end).map(Value::Int).collect(),
+            Value::Array(elements) => elements,
+            Value::Map(table) => entries(&table),
+            Value::Str(text) => text.chars().map(Value::Char).collect(),
+            Value::Ref(pointer) => match pointer.with(Value::clone) {
+                Some(Value::Array(elements)) => elements,
+                Some(Value::Map(table)) => entries(&table),
+                Some(Value::Str(text)) => text.chars().map(Value::Char).collect(),
+                _ => return runtime_error("cannot iterate over this reference", span),
+            },
+            other => {
+                return runtime_error(
+                    format!("cannot iterate over {} value", other.type_name()),
+                    span,
+                )
+            }
+        };
+        let mut items = items.into_iter();
+        loop {
+            // 循环变量还没有绑定，不变式中不能使用
+            self.check_loop_invariants(invariants)?;
+            let Some(item) = items.next() else {
+                break;
+            };
+            let mut bindings = Vec::new();
+            if !self.match_pattern(pattern, &item, &mut bindings) {
+                return runtime_error("loop pattern did not match the element", span);
+            }
+            if !next_iteration(label, self.scoped(bindings, |this| this.eval_block(body)))? {
+                break;
+            }
+        }
+        Ok(Value::Unit)
+    }
+
+    fn check_loop_invariants(&mut self, invariants: &[Contract]) -> Eval<()> {
+        if self.contracts != ContractMode::Check {
+            return Ok(());
+        }
+        let owner = self.frames.last().expect("no active frame").function;
+        for invariant in invariants {
+            self.check_contract(invariant, owner, Vec::new(), invariant.span)?;
+        }
+        Ok(())
+    }
+
+    fn eval_match(&mut self, scrutinee: &Expr, arms: &[MatchArm], span: Span) -> Eval<Value> {
+        let value = self.eval_expr(scrutinee)?;
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if !self.match_pattern(&arm.pattern, &value, &mut bindings) {
+                continue;
+            }
+            let result = self.scoped(bindings, |this| {
+                if let Some(guard) = &arm.guard {
+                    if !this.eval_bool(guard)? {
+                        return Ok(None);
+                    }
+                }
+                this.eval_expr(&arm.body).map(Some)
+            })?;
+            if let Some(value) = result {
+                return Ok(value);
+            }
+        }
+        runtime_error(format!("no match arm matched the value `{}`", value), span)
+    }
+
+    // ---------------- 模式 ----------------
+
+    // 匹配成功时把绑定追加到 bindings；失败时 bindings 可能残留部分绑定，由调用者丢弃
+    fn match_pattern(
+        &self,
+        pattern: &Pattern,
+        value: &Value,
+        bindings: &mut Vec<(String, Value)>,
+    ) -> bool {
+        // 对引用做模式匹配时自动解引用；单元变体的名字也要匹配引用指向的值
+        if let (Value::Ref(pointer), pattern) = (value, pattern) {
+            let binding = match pattern {
+                Pattern::Ident(name) => !self.variants.contains_key(name.as_str()),
+                Pattern::Wildcard => true,
+                _ => false,
+            };
+            if !binding {
+                return match pointer.with(Value::clone) {
+                    Some(target) => self.match_pattern(pattern, &target, bindings),
+                    None => false,
+                };
+            }
+        }
+
+        match pattern {
+            Pattern::Wildcard => true,
+            Pattern::Ident(name) => {
+                if self.variants.contains_key(name.as_str()) {
+                    matches!(value, Value::Variant(_, variant, _) if variant == name)
+                } else {
+                    bindings.push((name.clone(), value.clone()));
+                    true
+                }
+            }
+            Pattern::Literal(literal) => *value == literal_value(literal),
+            Pattern::Struct(name, fields) => match value {
+                Value::Struct(struct_name, values) if struct_name == name => {
+                    fields.iter().all(|(field, sub_pattern)| {
+                        values
+                            .iter()
+                            .find(|(value_field, _)| value_field == field)
+                            .is_some_and(|(_, field_value)| {
+                                self.match_pattern(sub_pattern, field_value, bindings)
+                            })
+                    })
+                }
+                _ => false,
+            },
+            Pattern::TupleStruct(name, patterns) => match value {
+                Value::Variant(_, variant, fields) if variant == name => {
+                    self.match_all(patterns, fields, bindings)
+                }
+                _ => false,
+            },
+            Pattern::Tuple(patterns) => match value {
+                Value::Tuple(elements) => self.match_all(patterns, elements, bindings),
+                Value::Unit => patterns.is_empty(),
+                _ => false,
+            },
+            Pattern::Or(alternatives) => alternatives.iter().any(|alternative| {
+                let len = bindings.len();
+                let matched = self.match_pattern(alternative, value, bindings);
+                if !matched {
+                    bindings.truncate(len);
+                }
+                matched
+            }),
+        }
+    }
+
+    fn match_all(
+        &self,
+        patterns: &[Pattern],
+        values: &[Value],
+        bindings: &mut Vec<(String, Value)>,
+    ) -> bool {
+        patterns.len() == values.len()
+            && patterns
+                .iter()
+                .zip(values)
+                .all(|(pattern, value)| self.match_pattern(pattern, value, bindings))
+    }
+
+    // let 和参数中的模式必须匹配
+    fn bind_irrefutable(&mut self, pattern: &Pattern, value: Value, span: Span) -> Eval<()> {
+        let mut bindings = Vec::new();
+        if !self.match_pattern(pattern, &value, &mut bindings) {
+            return runtime_error(
+                format!("refutable pattern did not match the value `{}`", value),
+                span,
+            );
+        }
+        for (name, value) in bindings {
+            self.declare(name, value);
+        }
+        Ok(())
+    }
+
+    // `let ... else`：不匹配时执行 else 块，语义分析保证它不会正常结束
+    fn bind_or_else(&mut self, pattern: &Pattern, value: Value, else_block: &Block) -> Eval<()> {
+        let mut bindings = Vec::new();
+        if !self.match_pattern(pattern, &value, &mut bindings) {
+            self.eval_block(else_block)?;
+            return runtime_error(
+                "the `else` block of `let ... else` did not diverge".to_string(),
+                else_block.span,
+            );
+        }
+        for (name, value) in bindings {
+            self.declare(name, value);
+        }
+        Ok(())
+    }
+
+    // ---------------- 表达式 ----------------
+
+    fn eval_bool(&mut self, expr: &Expr) -> Eval<bool> {
+        match self.eval_expr(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => runtime_error(
+                format!("expected a boolean condition, found {}", other.type_name()),
+                expr.span(),
+            ),
+        }
+    }
+
+    // 只做分派，各种表达式的求值放在单独的方法里，让递归求值的每一层栈帧保持较小
+    fn eval_expr(&mut self, expr: &Expr) -> Eval<Value> {
+        match expr {
+            Expr::Literal(literal, _) => Ok(literal_value(literal)),
+            Expr::Ident(name, span) => self.eval_name(name, *span),
+            Expr::Path(segments, span) => self.eval_path(segments, *span),
+            Expr::Binary(op, lhs, rhs, span, ty) => self.eval_binary(op, lhs, rhs, *span, ty),
+            Expr::Unary(UnOp::Ref | UnOp::RefMut, inner, _, _) | Expr::Ref(inner, _, _) => {
+                Ok(Value::Ref(self.eval_place(inner)?))
+            }
+            Expr::Unary(UnOp::Deref, _, span, _)
+            | Expr::Deref(_, span)
+            | Expr::FieldAccess(_, _, span)
+            | Expr::TupleIndex(_, _, span)
+            | Expr::IndexAccess(_, _, span) => {
+                let pointer = self.eval_place(expr)?;
+                self.read(&pointer, *span)
+            }
+            Expr::Unary(op, inner, span, ty) => self.eval_unary(op, inner, *span, ty),
+            Expr::Call(callee, args, span, ty) => self.eval_call(callee, args, *span, ty),
+            Expr::Turbofish(_, _, span) => runtime_error(
+                "explicit type arguments are only allowed when calling a layout intrinsic"
+                    .to_string(),
+                *span,
+            ),
+            Expr::MethodCall(receiver, method, args, span, ty) => {
+                self.eval_method_call(receiver, method, args, *span, ty)
+            }
+            Expr::StructLit(name, fields, span) => self.eval_struct(name, fields, *span),
+            Expr::ArrayLit(elements, _) => Ok(Value::Array(self.eval_list(elements)?)),
+            Expr::TupleLit(elements, _) if elements.is_empty() => Ok(Value::Unit),
+            Expr::TupleLit(elements, _) => Ok(Value::Tuple(self.eval_list(elements)?)),
+            Expr::Range(start, end, inclusive, span) => {
+                self.eval_range(start, end, *inclusive, *span)
+            }
+            Expr::Assign(lhs, rhs, span) => self.eval_assign(None, lhs, rhs, *span),
+            Expr::CompoundAssign(op, lhs, rhs, span, ty) => {
+                self.eval_assign(Some((op, ty)), lhs, rhs, *span)
+            }
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.eval_block(block)
+            }
+            Expr::If(cond, then_block, else_block, _) => {
+                self.eval_if(cond, then_block, else_block.as_ref())
+            }
+            Expr::Match(scrutinee, arms, span) => self.eval_match(scrutinee, arms, *span),
+            Expr::While(label, cond, body, _) => self.eval_while(label.as_ref(), cond, &[], body),
+            Expr::For(label, pattern, iterable, body, span) => {
+                self.eval_for(label.as_ref(), pattern, iterable, &[], body, *span)
+            }
+            Expr::Break(label, value, _) => {
+                // while/for 不产生值，break 的值只求值其副作用
+                self.eval_optional(value.as_deref())?;
+                Err(Flow::Break(label.clone()))
+            }
+            Expr::Continue(label, _) => Err(Flow::Continue(label.clone())),
+            Expr::Return(value, _) => Err(Flow::Return(self.eval_optional(value.as_deref())?)),
+            Expr::Try(inner, span) => self.eval_try(inner, *span),
+            // 在协程的实现之前 `.await` 直接得到操作数的值，见 features.rs
+            Expr::Await(inner, _) => self.eval_expr(inner),
+            Expr::MacroCall(call) => runtime_error(
+                format!("macro `{}!` was not expanded", call.name),
+                call.span,
+            ),
+            Expr::InlineAsm(_, span) => runtime_error(
+                "inline assembly cannot be run by the interpreter".to_string(),
+                *span,
+            ),
+            Expr::Closure(params, _, body, _) => Ok(self.make_closure(params, body)),
+            Expr::Cast(inner, ty, span) => {
+                let value = self.eval_expr(inner)?;
+                let ty = &ty.expand_typeof();
+                // 指针转换作用于引用本身，其他转换作用于引用的值
+                let value = match ty {
+                    Type::Pointer(_, _) | Type::Reference(_, _) => value,
+                    _ => deref_value(value),
+                };
+                ops::cast(value, ty).or_else(|message| runtime_error(message, *span))
+            }
+        }
+    }
+
+    // `?`：取出 Some/Ok 中的值，None/Err 原样从当前函数返回（见 prelude.rs）
+    fn eval_try(&mut self, inner: &Expr, span: Span) -> Eval<Value> {
+        match deref_value(self.eval_expr(inner)?) {
+            Value::Variant(enum_name, variant, mut fields) => match variant.as_str() {
+                "Some" | "Ok" if fields.len() == 1 => Ok(fields.remove(0)),
+                "None" | "Err" => Err(Flow::Return(Value::Variant(enum_name, variant, fields))),
+                _ => runtime_error(
+                    format!("the `?` operator cannot be applied to `{}`", enum_name),
+                    span,
+                ),
+            },
+            other => runtime_error(
+                format!(
+                    "the `?` operator cannot be applied to {}",
+                    other.type_name()
+                ),
+                span,
+            ),
+        }
+    }
+
+    fn eval_optional(&mut self, expr: Option<&Expr>) -> Eval<Value> {
+        match expr {
+            Some(expr) => self.eval_expr(expr),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn eval_path(&self, segments: &[String], span: Span) -> Eval<Value> {
+        match segments {
+            [enum_name, variant]
+                if self.variants.get(variant.as_str()) == Some(&enum_name.as_str()) =>
+            {
+                Ok(Value::Variant(
+                    enum_name.clone(),
+                    variant.clone(),
+                    Vec::new(),
+                ))
+            }
+            _ => runtime_error(format!("cannot find `{}`", segments.join("::")), span),
+        }
+    }
+
+    fn eval_binary(
+        &mut self,
+        op: &BinOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        span: Span,
+        ty: &OperandType,
+    ) -> Eval<Value> {
+        if matches!(op, BinOp::LogicalAnd | BinOp::LogicalOr) {
+            // 短路求值
+            let lhs = self.eval_bool(lhs)?;
+            if lhs == (*op == BinOp::LogicalOr) {
+                return Ok(Value::Bool(lhs));
+            }
+            return Ok(Value::Bool(self.eval_bool(rhs)?));
+        }
+        let lhs = self.eval_expr(lhs)?;
+        let rhs = self.eval_expr(rhs)?;
+        ops::binary_as(op, deref_value(lhs), deref_value(rhs), ty.get())
+            .or_else(|message| runtime_error(message, span))
+    }
+
+    fn eval_unary(&mut self, op: &UnOp, inner: &Expr, span: Span, ty: &OperandType) -> Eval<Value> {
+        let value = self.eval_expr(inner)?;
+        ops::unary_as(op, deref_value(value), ty.get())
+            .or_else(|message| runtime_error(message, span))
+    }
+
+    fn eval_method_call(
+        &mut self,
+        receiver: &Expr,
+        method: &str,
+        args: &[Expr],
+        span: Span,
+        ty: &OperandType,
+    ) -> Eval<Value> {
+        let builtin = builtins::lookup(method).filter(|_| !self.functions.contains_key(method));
+        // 以集合为第一个参数的内建函数：`v.push(x)` 即 `push(&mut v, x)`
+        let receiver = match builtin {
+            Some(builtin) if builtin.receiver().is_some() => Value::Ref(self.eval_place(receiver)?),
+            _ => self.eval_expr(receiver)?,
+        };
+        // 没有同名的函数时调用接收者类型的 `<type>_<method>`
+        let function = match nominal_type(&receiver) {
+            Some(ty) if builtin.is_none() && !self.functions.contains_key(method) => {
+                Some(derive::method_function(&ty, method))
+                    .filter(|function| self.functions.contains_key(function.as_str()))
+            }
+            _ => None,
+        };
+        let mut values = vec![receiver];
+        values.extend(self.eval_list(args)?);
+        match builtin {
+            Some(_) => self.builtin(method, values, ty.get(), span),
+            None => {
+                let function = function.unwrap_or_else(|| method.to_string());
+                self.call(&Value::Function(function), values, span)
+            }
+        }
+    }
+
+    fn eval_range(
+        &mut self,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+        inclusive: bool,
+        span: Span,
+    ) -> Eval<Value> {
+        match self.range_bounds(start, end, inclusive, span)? {
+            (Some(start), Some(end)) => Ok(Value::Range(start, end)),
+            _ => runtime_error("only a slice range can omit its start or end", span),
+        }
+    }
+
+    // 区间的起点和终点，`..=` 的终点换成不包含的终点；省略的一端是 None
+    fn range_bounds(
+        &mut self,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+        inclusive: bool,
+        span: Span,
+    ) -> Eval<(Option<i64>, Option<i64>)> {
+        let mut bound = |expr: &Option<Box<Expr>>| match expr {
+            Some(expr) => match self.eval_expr(expr)? {
+                Value::Int(value) => Ok(Some(value)),
+                _ => runtime_error("range bounds must be integers", span),
+            },
+            None => Ok(None),
+        };
+        let start = bound(start)?;
+        let end = bound(end)?.map(|end| match inclusive {
+            true => end.saturating_add(1),
+            false => end,
+        });
+        Ok((start, end))
+    }
+
+    // 赋值和复合赋值：先求右侧的值，再求左侧的位置
+    fn eval_assign(
+        &mut self,
+        op: Option<(&BinOp, &OperandType)>,
+        lhs: &Expr,
+        rhs: &Expr,
+        span: Span,
+    ) -> Eval<Value> {
+        let mut value = self.eval_expr(rhs)?;
+        let pointer = self.eval_place(lhs)?;
+        if let Some((op, ty)) = op {
+            let current = self.read(&pointer, span)?;
+            value = ops::binary_as(op, current, deref_value(value), ty.get())
+                .or_else(|message| runtime_error(message, span))?;
+        }
+        self.write(&pointer, value, span)?;
+        Ok(Value::Unit)
+    }
+
+    // 复制闭包体中用到的局部变量的值，之后外层对变量的修改闭包看不到
+    fn make_closure(&self, params: &[Parameter], body: &Expr) -> Value {
+        let mut names = BTreeSet::new();
+        mir::collect_expr_names(body, &mut names);
+        let scopes = &self.frames.last().expect("no active frame").scopes;
+        let mut captures = HashMap::new();
+        for name in names {
+            if let Some(slot) = scopes.iter().rev().find_map(|scope| scope.get(&name)) {
+                let value = slot.borrow().clone();
+                captures.insert(name, value);
+            }
+        }
+        Value::Closure(Rc::new(Closure {
+            params: params.to_vec(),
+            body: body.clone(),
+            captures,
+        }))
+    }
+
+    fn eval_list(&mut self, exprs: &[Expr]) -> Eval<Vec<Value>> {
+        exprs.iter().map(|expr| self.eval_expr(expr)).collect()
+    }
+
+    fn eval_name(&self, name: &str, span: Span) -> Eval<Value> {
+        if let Some(slot) = self.lookup(name) {
+            return Ok(slot.borrow().clone());
+        }
+        if self.functions.contains_key(name) {
+            return Ok(Value::Function(name.to_string()));
+        }
+        if let Some(enum_name) = self.variants.get(name) {
+            return Ok(Value::Variant(
+                enum_name.to_string(),
+                name.to_string(),
+                Vec::new(),
+            ));
+        }
+        runtime_error(format!("cannot find value `{}` in this scope", name), span)
+    }
+
+    // `std::mem::size_of::<T>()` 和 `std::mem::align_of::<T>()`，按与编译器相同的布局计算
+    fn eval_intrinsic(&mut self, path: &Expr, types: &[Type], span: Span) -> Eval<Value> {
+        let name = match path {
+            Expr::Path(segments, _) => layout::intrinsic(&segments.join("::")),
+            Expr::Ident(name, _) => layout::intrinsic(name),
+            _ => None,
+        };
+        let (Some(name), [ty]) = (name, types) else {
+            return runtime_error("invalid call of a layout intrinsic".to_string(), span);
+        };
+        let items = || prelude::items_for(self.program).chain(&self.program.items);
+        let layouts = Layouts::new(
+            items().filter_map(|item| match item {
+                Item::Struct(def) => Some(def),
+                _ => None,
+            }),
+            items().filter_map(|item| match item {
+                Item::Enum(def) => Some(def),
+                _ => None,
+            }),
+        );
+        match layouts.intrinsic(name, &ty.expand_typeof()) {
+            Ok(value) => Ok(Value::Int(value as i64)),
+            Err(error) => runtime_error(error.to_string(), span),
+        }
+    }
+
+    fn eval_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        span: Span,
+        ty: &OperandType,
+    ) -> Eval<Value> {
+        if let Expr::Turbofish(path, types, _) = callee {
+            return self.eval_intrinsic(path, types, span);
+        }
+        // 带字段的枚举变体：`Some(x)`、`Shape::Circle(r)`
+        let variant = match callee {
+            Expr::Ident(name, _) if self.lookup(name).is_none() => self
+                .variants
+                .get(name.as_str())
+                .map(|enum_name| (enum_name.to_string(), name.clone())),
+            Expr::Path(segments, _) => match segments.as_slice() {
+                [enum_name, variant]
+                    if self.variants.get(variant.as_str()) == Some(&enum_name.as_str()) =>
+                {
+                    Some((enum_name.clone(), variant.clone()))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some((enum_name, variant)) = variant {
+            let fields = self.eval_list(args)?;
+            return Ok(Value::Variant(enum_name, variant, fields));
+        }
+
+        let builtin = match callee {
+            Expr::Ident(name, _) => {
+                let shadowed =
+                    self.lookup(name).is_some() || self.functions.contains_key(name.as_str());
+                builtins::lookup(name).filter(|_| !shadowed)
+            }
+            Expr::Path(segments, _) => builtins::lookup(&segments.join("::")),
+            _ => None,
+        };
+        if let Some(builtin) = builtin {
+            let values = self.eval_list(args)?;
+            return self.builtin(builtin.name, values, ty.get(), span);
+        }
+
+        let callee = self.eval_expr(callee)?;
+        let args = self.eval_list(args)?;
+        self.call(&callee, args, span)
+    }
+
+    // 内建函数，签名见 builtins.rs
+    // `ty` 是语义分析记录的 `abs` 的参数类型
+    fn builtin(
+        &mut self,
+        name: &str,
+        values: Vec<Value>,
+        ty: Option<&Type>,
+        span: Span,
+    ) -> Eval<Value> {
+        match (name, values.as_slice()) {
+            ("print", values) => {
+                for value in values {
+                    if writeln!(self.output, "{}", value).is_err() {
+                        return runtime_error("failed to write program output", span);
+                    }
+                }
+                Ok(Value::Unit)
+            }
+            ("len", [value]) => match deref_value(value.clone()) {
+                Value::Array(elements) => Ok(Value::Int(elements.len() as i64)),
+                Value::Str(text) => Ok(Value::Int(text.len() as i64)),
+                Value::Map(table) => Ok(Value::Int(table.len() as i64)),
+                other => runtime_error(
+                    format!("cannot take the length of {}", other.type_name()),
+                    span,
+                ),
+            },
+            ("assert", [condition, message @ ..]) if message.len() <= 1 => {
+                match (deref_value(condition.clone()), message) {
+                    (Value::Bool(true), _) => Ok(Value::Unit),
+                    (Value::Bool(false), []) => runtime_error("assertion failed", span),
+                    (Value::Bool(false), [message]) => {
+                        runtime_error(format!("assertion failed: {}", message), span)
+                    }
+                    (other, _) => runtime_error(
+                        format!(
+                            "assertion condition must be bool, found {}",
+                            other.type_name()
+                        ),
+                        span,
+                    ),
+                }
+            }
+            ("panic", []) => runtime_error("explicit panic", span),
+            ("panic", [message]) => runtime_error(format!("panicked: {}", message), span),
+            ("catch_panic", [callee]) => {
+                match self.call(&deref_value(callee.clone()), Vec::new(), span) {
+                    Ok(value) => Ok(result(Ok(value))),
+                    Err(Flow::Error(diagnostic)) if self.panic == PanicStrategy::Unwind => {
+                        Ok(result(Err(diagnostic.message)))
+                    }
+                    Err(flow) => Err(flow),
+                }
+            }
+            ("Box::new", [value]) => Ok(Value::Boxed(Box::new(value.clone()))),
+            ("Rc::new", [value]) => Ok(Value::Shared(Rc::new(value.clone()))),
+            // 解释器不跟踪所有权转移，被移出的变量在离开作用域之前仍然持有计数
+            ("Rc::clone" | "Rc::strong_count", [value]) => match deref_value(value.clone()) {
+                Value::Shared(inner) if name == "Rc::clone" => Ok(Value::Shared(inner)),
+                // 不计入这里读出的副本
+                Value::Shared(inner) => Ok(Value::Int(Rc::strong_count(&inner) as i64 - 1)),
+                other => runtime_error(
+                    format!(
+                        "builtin `{}` expects an `Rc`, found {}",
+                        name,
+                        other.type_name()
+                    ),
+                    span,
+                ),
+            },
+            ("Vec::new", []) => Ok(Value::Array(Vec::new())),
+            ("push", [target, value]) => {
+                let value = deref_value(value.clone());
+                self.modify(target, span, |target| match target {
+                    Value::Array(elements) => {
+                        elements.push(value);
+                        Ok(Value::Unit)
+                    }
+                    other => Err(format!("cannot push to {} value", other.type_name())),
+                })
+            }
+            ("pop", [target]) => self.modify(target, span, |target| match target {
+                Value::Array(elements) => Ok(option(elements.pop())),
+                other => Err(format!("cannot pop from {} value", other.type_name())),
+            }),
+            ("remove", [target, index]) => {
+                let index = deref_value(index.clone());
+                self.modify(target, span, |target| match (target, index) {
+                    (Value::Array(elements), Value::Int(index)) => {
+                        if index < 0 || index as usize >= elements.len() {
+                            return Err(ops::index_out_of_bounds(elements.len(), index));
+                        }
+                        Ok(elements.remove(index as usize))
+                    }
+                    (Value::Array(_), other) => Err(format!(
+                        "array index must be an integer, found {}",
+                        other.type_name()
+                    )),
+                    (Value::Map(table), key) => Ok(option(table.remove(&map_key(&key)?))),
+                    (other, _) => Err(format!("cannot remove from {} value", other.type_name())),
+                })
+            }
+            ("StringBuilder::new", []) => Ok(Value::Str(String::new())),
+            ("append", [target, value]) => {
+                let text = deref_value(value.clone()).to_string();
+                self.modify(target, span, |target| match target {
+                    Value::Str(buffer) => {
+                        buffer.push_str(&text);
+                        Ok(Value::Unit)
+                    }
+                    other => Err(format!("cannot append to {} value", other.type_name())),
+                })
+            }
+            ("Map::new", []) => Ok(Value::Map(Table::new())),
+            ("insert", [target, key, value]) => {
+                let key = match map_key(&deref_value(key.clone())) {
+                    Ok(key) => key,
+                    Err(message) => return runtime_error(message, span),
+                };
+                let value = deref_value(value.clone());
+                self.modify(target, span, |target| match target {
+                    Value::Map(table) => {
+                        table.insert(key, value);
+                        Ok(Value::Unit)
+                    }
+                    other => Err(format!("cannot insert into {} value", other.type_name())),
+                })
+            }
+            ("get", [map, key]) => {
+                let key = deref_value(key.clone());
+                self.inspect(map, span, |map| match map {
+                    Value::Map(table) => Ok(option(table.get(&map_key(&key)?).cloned())),
+                    other => Err(format!("expected a map, found {}", other.type_name())),
+                })
+            }
+            ("contains_key", [map, key]) => {
+                let key = deref_value(key.clone());
+                self.inspect(map, span, |map| match map {
+                    Value::Map(table) => Ok(Value::Bool(table.contains_key(&map_key(&key)?))),
+                    other => Err(format!("expected a map, found {}", other.type_name())),
+                })
+            }
+            ("entries", [map]) => self.inspect(map, span, |map| match map {
+                Value::Map(table) => Ok(Value::Array(entries(table))),
+                other => Err(format!("expected a map, found {}", other.type_name())),
+            }),
+            ("to_string", [value]) => Ok(Value::Str(deref_value(value.clone()).to_string())),
+            ("fmt::arg", [value, spec]) => {
+                format_arg(value.clone(), spec).or_else(|message| runtime_error(message, span))
+            }
+            ("abs", [value]) => ops::abs(deref_value(value.clone()), ty)
+                .or_else(|message| runtime_error(message, span)),
+            (
+                "substring" | "split" | "contains" | "find" | "is_int" | "to_int" | "chars",
+                values,
+            ) => string_builtin(name, values).or_else(|message| runtime_error(message, span)),
+            (
+                "io::read_file" | "io::write_file" | "io::args" | "io::exit" | "env::get"
+                | "env::has" | "time::now_millis" | "time::sleep",
+                values,
+            ) => self.host_builtin(name, values, span),
+            _ => runtime_error(
+                format!("wrong number of arguments to builtin `{}`", name),
+                span,
+            ),
+        }
+    }
+
+    // `io`、`env` 和 `time` 模块；文件操作的结果是 `Result<T, string>`
+    fn host_builtin(&mut self, name: &str, values: &[Value], span: Span) -> Eval<Value> {
+        if let Err(message) = self.host.require(name) {
+            return runtime_error(message, span);
+        }
+        let values: Vec<Value> = values.iter().cloned().map(deref_value).collect();
+        match (name, values.as_slice()) {
+            ("io::read_file", [Value::Str(path)]) => Ok(result(read_file(path).map(Value::Str))),
+            ("io::write_file", [Value::Str(path), Value::Str(contents)]) => {
+                Ok(result(write_file(path, contents).map(|()| Value::Unit)))
+            }
+            ("io::args", []) => Ok(Value::Array(
+                self.host.args.iter().cloned().map(Value::Str).collect(),
+            )),
+            ("io::exit", [Value::Int(code)]) => {
+                self.exit_code = Some(*code as i32);
+                Err(Flow::Exit)
+            }
+            ("env::get", [Value::Str(name)]) => Ok(option(env_var(name).map(Value::Str))),
+            ("env::has", [Value::Str(name)]) => Ok(Value::Bool(env_var(name).is_some())),
+            ("time::now_millis", []) => Ok(Value::Int(now_millis())),
+            ("time::sleep", [Value::Int(millis)]) => match sleep(*millis) {
+                Ok(()) => Ok(Value::Unit),
+                Err(message) => runtime_error(message, span),
+            },
+            _ => runtime_error(format!("invalid arguments to builtin `{}`", name), span),
+        }
+    }
+
+    // 读取参数的值；参数是引用时直接读取它指向的值，不复制整个集合
+    fn inspect(
+        &self,
+        value: &Value,
+        span: Span,
+        f: impl FnOnce(&Value) -> Result<Value, String>,
+    ) -> Eval<Value> {
+        let result = match value {
+            Value::Ref(pointer) => {
+                let pointer = self.auto_deref(pointer.clone(), span)?;
+                match pointer.with(f) {
+                    Some(result) => result,
+                    None => return runtime_error("invalid memory access", span),
+                }
+            }
+            value => f(value),
+        };
+        result.or_else(|message| runtime_error(message, span))
+    }
+
+    // 修改第一个参数（引用）指向的值
+    fn modify(
+        &self,
+        target: &Value,
+        span: Span,
+        f: impl FnOnce(&mut Value) -> Result<Value, String>,
+    ) -> Eval<Value> {
+        let Value::Ref(pointer) = target else {
+            return runtime_error(
+                format!("expected a mutable reference, found {}", target.type_name()),
+                span,
+            );
+        };
+        let pointer = self.auto_deref(pointer.clone(), span)?;
+        match pointer.with_mut(f) {
+            Some(result) => result.or_else(|message| runtime_error(message, span)),
+            None => runtime_error("invalid memory access", span),
+        }
+    }
+
+    fn eval_struct(&mut self, name: &str, fields: &[(String, Expr)], span: Span) -> Eval<Value> {
+        let mut values = Vec::new();
+        for (field, expr) in fields {
+            values.push((field.clone(), self.eval_expr(expr)?));
+        }
+        // 字段按声明顺序保存，输出时与定义一致
+        if let Some(def) = self.structs.get(name) {
+            let mut ordered = Vec::new();
+            for field in &def.fields {
+                match values.iter().position(|(name, _)| *name == field.name) {
+                    Some(index) => ordered.push(values.remove(index)),
+                    None => {
+                        return runtime_error(
+                            format!(
+                                "missing field `{}` in initializer of `{}`",
+                                field.name, name
+                            ),
+                            span,
+                        )
+                    }
+                }
+            }
+            ordered.append(&mut values);
+            values = ordered;
+        }
+        let value = Value::Struct(name.to_string(), values);
+        if let Some(def) = self.structs.get(name).copied() {
+            if self.contracts == ContractMode::Check {
+                for invariant in &def.invariants {
+                    let bindings = vec![("self".to_string(), value.clone())];
+                    self.check_contract(invariant, name, bindings, span)?;
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    // ---------------- 位置 ----------------
+
+    // 求值可以读写的位置；不是位置的表达式先求值到临时槽中
+    fn eval_place(&mut self, expr: &Expr) -> Eval<Pointer> {
+        match expr {
+            Expr::Ident(name, span) => match self.lookup(name) {
+                Some(slot) => Ok(Pointer::to_slot(slot)),
+                None => Ok(Pointer::to_slot(self.eval_name(name, *span)?.new_slot())),
+            },
+            Expr::FieldAccess(base, field, span) => {
+                let base = self.eval_place(base)?;
+                let base = self.auto_deref(base, *span)?;
+                Ok(base.project(Step::Field(field.clone())))
+            }
+            Expr::TupleIndex(base, index, span) => {
+                let base = self.eval_place(base)?;
+                let base = self.auto_deref(base, *span)?;
+                Ok(base.project(Step::Field(index.to_string())))
+            }
+            Expr::IndexAccess(base, index, span) => {
+                let base = self.eval_place(base)?;
+                let base = self.auto_deref(base, *span)?;
+                // `a[2..]`、`a[..2]`、`a[..]` 省略的起点是 0，省略的终点是长度
+                if let Expr::Range(start, end, inclusive, range_span) = &**index {
+                    let (start, end) = self.range_bounds(start, end, *inclusive, *range_span)?;
+                    return self.slice(base, start.unwrap_or(0), end, *span);
+                }
+                let index = match self.eval_expr(index)? {
+                    Value::Int(index) => index,
+                    Value::Range(start, end) => return self.slice(base, start, Some(end), *span),
+                    other => {
+                        return runtime_error(
+                            format!(
+                                "array index must be an integer, found {}",
+                                other.type_name()
+                            ),
+                            *span,
+                        )
+                    }
+                };
+                let len = match base.with(|value| match value {
+                    Value::Array(elements) => Some(elements.len()),
+                    _ => None,
+                }) {
+                    Some(Some(len)) => len,
+                    _ => {
+                        return runtime_error(
+                            "cannot index into a value that is not an array",
+                            *span,
+                        )
+                    }
+                };
+                if index < 0 || index as usize >= len {
+                    return runtime_error(ops::index_out_of_bounds(len, index), *span);
+                }
+                Ok(base.project(Step::Index(index as usize)))
+            }
+            Expr::Unary(UnOp::Deref, inner, span, _) | Expr::Deref(inner, span) => {
+                let base = self.eval_place(inner)?;
+                match base.with(|value| match value {
+                    Value::Ref(target) => Ok(target.clone()),
+                    Value::Boxed(_) | Value::Shared(_) => Ok(base.clone().project(Step::Unbox)),
+                    other => Err(other.type_name()),
+                }) {
+                    Some(Ok(pointer)) => Ok(pointer),
+                    Some(Err(kind)) => {
+                        runtime_error(format!("cannot dereference {} value", kind), *span)
+                    }
+                    None => runtime_error("invalid memory access", *span),
+                }
+            }
+            _ => Ok(Pointer::to_slot(self.eval_expr(expr)?.new_slot())),
+        }
+    }
+
+    // `&a[start..end]`：指向数组或切片的一段的胖指针
+    fn slice(&self, base: Pointer, start: i64, end: Option<i64>, span: Span) -> Eval<Pointer> {
+        let len = match base.with(|value| match value {
+            Value::Array(elements) => Some(elements.len()),
+            _ => None,
+        }) {
+            Some(Some(len)) => len,
+            _ => return runtime_error("cannot slice a value that is not an array", span),
+        };
+        let end = end.unwrap_or(len as i64);
+        if let Some(message) = ops::slice_out_of_bounds(len, start, end) {
+            return runtime_error(message, span);
+        }
+        Ok(base.slice(start as usize, (end - start) as usize))
+    }
+
+    // 通过引用、盒子和 Rc 访问字段和元素时自动解引用
+    fn auto_deref(&self, mut pointer: Pointer, span: Span) -> Eval<Pointer> {
+        loop {
+            let target = pointer.with(|value| match value {
+                Value::Ref(target) => Some(target.clone()),
+                Value::Boxed(_) | Value::Shared(_) => Some(pointer.clone().project(Step::Unbox)),
+                _ => None,
+            });
+            match target {
+                Some(Some(target)) => pointer = target,
+                Some(None) => return Ok(pointer),
+                None => return runtime_error("invalid memory access", span),
+            }
+        }
+    }
+
+    fn read(&self, pointer: &Pointer, span: Span) -> Eval<Value> {
+        match pointer.with(Value::clone) {
+            Some(value) => Ok(value),
+            None => runtime_error("invalid memory access", span),
+        }
+    }
+
+    fn write(&self, pointer: &Pointer, value: Value, span: Span) -> Eval<()> {
+        match pointer.with_mut(|target| *target = value) {
+            Some(()) => Ok(()),
+            None => runtime_error("invalid memory access", span),
+        }
+    }
+}
+
+// 循环体执行一轮之后是否继续：`continue` 继续，`break` 结束循环；
+// 标签指向外层循环的跳转继续向外传递
+fn next_iteration(label: Option<&String>, result: Eval<Value>) -> Eval<bool> {
+    let targets = |target: &Option<String>| target.is_none() || target.as_ref() == label;
+    match result {
+        Ok(_) => Ok(true),
+        Err(Flow::Continue(target)) if targets(&target) => Ok(true),
+        Err(Flow::Break(target)) if targets(&target) => Ok(false),
+        Err(flow) => Err(flow),
+    }
+}
+
+// 把求值结果中的控制流转换为诊断
+fn finish(result: Eval<Value>, span: Span) -> Result<Value, Vec<Diagnostic>> {
+    match result {
+        Ok(value) | Err(Flow::Return(value)) => Ok(value),
+        Err(Flow::Exit) => Ok(Value::Unit),
+        Err(Flow::Error(diagnostic)) => Err(vec![*diagnostic]),
+        Err(Flow::Break(_) | Flow::Continue(_)) => Err(vec![Diagnostic::error(
+            "`break` or `continue` outside of a loop".to_string(),
+            span,
+        )
+        .with_code(ErrorCode::E0268)]),
+    }
+}
+
+// 算术和比较作用于引用的目标值
+// 预导入的 `Option<T>` 的值
+fn option(value: Option<Value>) -> Value {
+    match value {
+        Some(value) => Value::Variant("Option".to_string(), "Some".to_string(), vec![value]),
+        None => Value::Variant("Option".to_string(), "None".to_string(), Vec::new()),
+    }
+}
+
+fn map_key(value: &Value) -> Result<Key, String> {
+    value
+        .to_key()
+        .ok_or_else(|| format!("cannot use {} value as a map key", value.type_name()))
+}
+
+// 预导入的 `Result<T, string>` 的值
+fn result(value: Result<Value, String>) -> Value {
+    match value {
+        Ok(value) => Value::Variant("Result".to_string(), "Ok".to_string(), vec![value]),
+        Err(message) => {
+            let message = Value::Str(message);
+            Value::Variant("Result".to_string(), "Err".to_string(), vec![message])
+        }
+    }
+}
+
+// 字符串的内建函数，第一个参数是字符串
+fn string_builtin(name: &str, values: &[Value]) -> Result<Value, String> {
+    let values: Vec<Value> = values.iter().cloned().map(deref_value).collect();
+    let text = match values.first() {
+        Some(Value::Str(text)) => text,
+        Some(other) => return Err(format!("expected a string, found {}", other.type_name())),
+        None => return Err(format!("wrong number of arguments to builtin `{}`", name)),
+    };
+    let value = match (name, &values[1..]) {
+        ("substring", [Value::Int(start), Value::Int(end)]) => {
+            Value::Str(ops::substring(text, *start, *end)?.to_string())
+        }
+        ("split", [Value::Str(separator)]) => Value::Array(
+            ops::split(text, separator)?
+                .into_iter()
+                .map(|part| Value::Str(part.to_string()))
+                .collect(),
+        ),
+        ("contains", [Value::Str(pattern)]) => Value::Bool(text.contains(pattern.as_str())),
+        ("find", [Value::Str(pattern)]) => option(
+            text.find(pattern.as_str())
+                .map(|index| Value::Int(index as i64)),
+        ),
+        ("is_int", []) => Value::Bool(ops::parse_int(text).is_some()),
+        ("to_int", []) => option(ops::parse_int(text).map(Value::Int)),
+        ("chars", []) => Value::Array(text.chars().map(Value::Char).collect()),
+        _ => return Err(format!("invalid arguments to builtin `{}`", name)),
+    };
+    Ok(value)
+}
+
+// Map 的条目，按迭代顺序表示为 `(键, 值)` 元组
+fn entries(table: &Table<Value>) -> Vec<Value> {
+    table
+        .iter()
+        .map(|(key, value)| Value::Tuple(vec![Value::from_key(key), value.clone()]))
+        .collect()
+}
+
+// 结构体或枚举的值的类型名，经过引用
+fn nominal_type(value: &Value) -> Option<String> {
+    match value {
+        Value::Struct(name, _) | Value::Variant(name, _, _) => Some(name.clone()),
+        Value::Ref(pointer) => pointer.with(nominal_type).flatten(),
+        _ => None,
+    }
+}
+
+fn deref_value(value: Value) -> Value {
+    match value {
+        Value::Ref(pointer) => match pointer.with(Value::clone) {
+            Some(target) => deref_value(target),
+            None => Value::Unit,
+        },
+        value => value,
+    }
+}
+
+// `format!` 的一个占位符：引用、盒子和 Rc 按其中的值格式化
+fn format_arg(value: Value, spec: &Value) -> Result<Value, String> {
+    let spec: fmt::Spec = match deref_value(spec.clone()) {
+        Value::Str(spec) => spec.parse()?,
+        other => {
+            return Err(format!(
+                "expected a format spec, found {}",
+                other.type_name()
+            ))
+        }
+    };
+    let mut value = deref_value(value);
+    loop {
+        value = match value {
+            Value::Boxed(inner) => deref_value(*inner),
+            Value::Shared(inner) => deref_value((*inner).clone()),
+            _ => break,
+        };
+    }
+    let arg = match value {
+        Value::Int(n) => fmt::Arg::Int(n),
+        Value::Float(x) => fmt::Arg::Float(x),
+        value if spec.kind == fmt::Kind::Debug => fmt::Arg::Text(value.to_nested_string()),
+        value => fmt::Arg::Text(value.to_string()),
+    };
+    fmt::format(&spec, arg).map(Value::Str)
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Int(n, _) => Value::Int(*n),
+        Literal::Float(x) => Value::Float(*x),
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::Char(c) => Value::Char(*c),
+        Literal::String(text) => Value::Str(text.clone()),
+    }
+}
+
+/// 在标准输出上运行程序的 `main` 函数
+pub fn run(program: &Program) -> Result<(), Vec<Diagnostic>> {
+    run_with_output(program, io::stdout()).0
+}
+
+/// 在栈大小为 STACK_SIZE 的线程上运行 `main`，返回结果和输出
+pub fn run_with_output<W: Write + Send>(
+    program: &Program,
+    output: W,
+) -> (Result<(), Vec<Diagnostic>>, W) {
+    with_large_stack(move || {
+        let mut interpreter = Interpreter::with_output(program, output);
+        // 运行时值持有 Rc，不能离开解释器线程
+        let result = interpreter.run_main().map(drop);
+        (result, interpreter.into_output())
+    })
+}
+
+/// 在栈大小为 STACK_SIZE 的线程上执行 `f`，所有解释执行都应该经过这里
+pub fn with_large_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .name("interpreter".to_string())
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("failed to spawn the interpreter thread");
+        handle
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+}
@@ -0,0 +1,90 @@
+// 调试钩子：解释器在执行每条语句之前调用，调试器借此停下程序、查看调用栈和变量。
+// 断点、单步等策略在 debugger.rs 中，这里只提供执行位置和变量的只读视图
+
+use super::{Frame, Slot, Value};
+use crate::span::Span;
+use std::collections::{BTreeMap, HashMap};
+
+/// 语句执行之前调试器的决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    Continue,
+    Terminate, // 结束程序，与 `io::exit` 相同地不再执行后面的代码，但没有退出码
+}
+
+pub trait Debugger {
+    fn before_statement(&mut self, context: &DebugContext<'_, '_>) -> DebugAction;
+}
+
+impl<D: Debugger + ?Sized> Debugger for &mut D {
+    fn before_statement(&mut self, context: &DebugContext<'_, '_>) -> DebugAction {
+        (**self).before_statement(context)
+    }
+}
+
+/// 即将执行的语句和此时的调用栈。栈帧从 0 开始编号，0 是最内层的
+pub struct DebugContext<'a, 'p> {
+    pub(super) frames: &'a [Frame<'p>],
+    pub(super) globals: &'a HashMap<String, Slot>,
+}
+
+impl DebugContext<'_, '_> {
+    /// 即将执行的语句
+    pub fn span(&self) -> Span {
+        self.frame(0)
+            .map_or(Span::new(0, 0, 1, 1), |frame| frame.span)
+    }
+
+    /// 调用栈的深度，`main` 中为 1
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 当前函数的名字，闭包是 `<closure>`
+    pub fn function(&self) -> &str {
+        self.frame(0).map_or("", |frame| frame.function)
+    }
+
+    /// 每个栈帧的函数名和其中正在执行的语句，从最内层开始
+    pub fn backtrace(&self) -> Vec<(&str, Span)> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| (frame.function, frame.span))
+            .collect()
+    }
+
+    /// 栈帧中可见的变量，内层作用域遮蔽外层的同名变量，按名字排序
+    pub fn locals(&self, frame: usize) -> Vec<(String, Value)> {
+        let mut locals = BTreeMap::new();
+        for scope in self.frame(frame).map_or(&[][..], |frame| &frame.scopes) {
+            for (name, slot) in scope {
+                locals.insert(name.clone(), slot.borrow().clone());
+            }
+        }
+        locals.into_iter().collect()
+    }
+
+    /// 已经初始化的 const 和 static，按名字排序
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<(String, Value)> = self
+            .globals
+            .iter()
+            .map(|(name, slot)| (name.clone(), slot.borrow().clone()))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    /// 在栈帧中按名字查找变量，找不到时查找全局变量
+    pub fn lookup(&self, frame: usize, name: &str) -> Option<Value> {
+        self.frame(frame)
+            .and_then(|frame| frame.scopes.iter().rev().find_map(|scope| scope.get(name)))
+            .or_else(|| self.globals.get(name))
+            .map(|slot| slot.borrow().clone())
+    }
+
+    fn frame(&self, index: usize) -> Option<&Frame<'_>> {
+        self.frames.iter().rev().nth(index)
+    }
+}
@@ -0,0 +1,84 @@
+// `io`、`env` 和 `time` 模块的宿主接口，解释器和虚拟机共用
+// 程序通过 `io::read_file`、`io::write_file`、`io::args` 和 `io::exit` 访问文件系统和进程
+// （`std::process::args` 和 `std::process::exit` 是后两者的别名），通过 `env::get` 读取环境变量，
+// 通过 `time::now_millis` 和 `time::sleep` 读取时钟和等待。
+// 每个模块是一项能力：默认关闭（沙箱、测试和嵌入的宿主），此时调用其中的函数是运行时错误；
+// 命令行的 `run` 和 `build` 生成的可执行文件打开全部能力，并传入命令行上的程序参数
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 程序可以使用的宿主环境
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Host {
+    pub io: bool,          // 是否允许使用 `io` 模块
+    pub env: bool,         // 是否允许读取环境变量（`env` 模块）
+    pub time: bool,        // 是否允许读取时钟和等待（`time` 模块）
+    pub args: Vec<String>, // `io::args()` 返回的程序参数
+}
+
+impl Host {
+    /// 允许使用 `io` 模块，程序参数为 `args`
+    pub fn with_io(args: Vec<String>) -> Self {
+        Self {
+            io: true,
+            args,
+            ..Self::default()
+        }
+    }
+
+    /// 打开全部能力：命令行运行的程序和生成的可执行文件
+    pub fn unrestricted(args: Vec<String>) -> Self {
+        Self {
+            io: true,
+            env: true,
+            time: true,
+            args,
+        }
+    }
+
+    /// 调用宿主函数 `name` 前按它所在的模块检查能力。
+    /// `env::get` 在 MIR 中展开为 `env::has` 和取值，所以 env 和 time 的错误信息指出模块而不是函数，
+    /// 解释器和虚拟机给出的信息一致
+    pub fn require(&self, name: &str) -> Result<(), String> {
+        let (allowed, name, what) = match name.split("::").next() {
+            Some(module @ "env") => (self.env, module, "environment variables are"),
+            Some(module @ "time") => (self.time, module, "the clock is"),
+            _ => (self.io, name, "I/O is"),
+        };
+        match allowed {
+            true => Ok(()),
+            false => Err(format!(
+                "`{}` is not available: {} disabled in this environment",
+                name, what
+            )),
+        }
+    }
+}
+
+// 文件操作失败时的错误信息，作为 `Err` 交给程序
+
+pub fn read_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|error| format!("cannot read `{}`: {}", path, error))
+}
+
+pub fn write_file(path: &str, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|error| format!("cannot write `{}`: {}", path, error))
+}
+
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// 自 Unix 纪元以来的毫秒数
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+pub fn sleep(millis: i64) -> Result<(), String> {
+    let millis =
+        u64::try_from(millis).map_err(|_| format!("cannot sleep for {} milliseconds", millis))?;
+    std::thread::sleep(Duration::from_millis(millis));
+    Ok(())
+}
@@ -0,0 +1,386 @@
+// 运算符、类型转换和字符串操作的求值
+// 整数统一用 i64 计算，溢出、除零和过大的移位是运行时错误；语义分析记录了操作数的整数类型时
+// 结果还必须在这个类型的范围内，移位的位数必须小于它的位数；
+// `as` 转换按目标类型截断，浮点数转整数时饱和。
+// 字符串是不可变的 UTF-8 字节序列，下标按字节计，不在字符边界上的下标是运行时错误
+
+use super::value::Value;
+use crate::ast::{BinOp, ContractKind, Type, UnOp};
+use crate::span::Span;
+
+pub fn binary(op: &BinOp, lhs: Value, rhs: Value) -> Result<Value, String> {
+    use Value::*;
+    let value = match (op, &lhs, &rhs) {
+        (BinOp::Equal, _, _) => Bool(lhs == rhs),
+        (BinOp::NotEqual, _, _) => Bool(lhs != rhs),
+        (BinOp::Less | BinOp::Greater | BinOp::LessEqual | BinOp::GreaterEqual, _, _) => {
+            let ordering = compare(&lhs, &rhs).ok_or_else(|| {
+                format!(
+                    "cannot compare {} with {}",
+                    lhs.type_name(),
+                    rhs.type_name()
+                )
+            })?;
+            Bool(match op {
+                BinOp::Less => ordering.is_lt(),
+                BinOp::Greater => ordering.is_gt(),
+                BinOp::LessEqual => ordering.is_le(),
+                _ => ordering.is_ge(),
+            })
+        }
+        (_, Int(a), Int(b)) => Int(int_binary(op, *a, *b)?),
+        (_, Float(a), Float(b)) => match op {
+            BinOp::Add => Float(a + b),
+            BinOp::Sub => Float(a - b),
+            BinOp::Mul => Float(a * b),
+            BinOp::Div => Float(a / b),
+            BinOp::Mod => Float(a % b),
+            _ => return Err(unsupported(op, &lhs, &rhs)),
+        },
+        (_, Bool(a), Bool(b)) => match op {
+            BinOp::LogicalAnd | BinOp::BitwiseAnd => Bool(*a && *b),
+            BinOp::LogicalOr | BinOp::BitwiseOr => Bool(*a || *b),
+            BinOp::BitwiseXor => Bool(a != b),
+            _ => return Err(unsupported(op, &lhs, &rhs)),
+        },
+        (BinOp::Add, Str(a), Str(b)) => Str(format!("{}{}", a, b)),
+        _ => return Err(unsupported(op, &lhs, &rhs)),
+    };
+    Ok(value)
+}
+
+fn unsupported(op: &BinOp, lhs: &Value, rhs: &Value) -> String {
+    format!(
+        "unsupported operation `{:?}` between {} and {}",
+        op,
+        lhs.type_name(),
+        rhs.type_name()
+    )
+}
+
+/// 按语义分析记录的类型 `ty` 做二元运算，见 `ast::OperandType`：整数按类型的宽度检查溢出，
+/// `f32` 的结果舍入到单精度；没有记录时同 `binary`
+pub fn binary_as(op: &BinOp, lhs: Value, rhs: Value, ty: Option<&Type>) -> Result<Value, String> {
+    match (ty, &lhs, &rhs) {
+        (Some(ty), Value::Int(a), Value::Int(b)) if is_int(ty) && is_arithmetic(op) => {
+            Ok(Value::Int(int_binary_as(op, *a, *b, ty)?))
+        }
+        (Some(Type::F32), Value::Float(_), Value::Float(_)) => match binary(op, lhs, rhs)? {
+            Value::Float(x) => Ok(Value::Float(x as f32 as f64)),
+            value => Ok(value),
+        },
+        _ => binary(op, lhs, rhs),
+    }
+}
+
+/// 结果可能超出操作数类型的范围的整数运算
+pub fn is_arithmetic(op: &BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::Add
+            | BinOp::Sub
+            | BinOp::Mul
+            | BinOp::Div
+            | BinOp::Mod
+            | BinOp::LeftShift
+            | BinOp::RightShift
+    )
+}
+
+/// 整数类型 `ty` 的运算：移位的位数不能达到类型的位数，左移丢弃超出宽度的高位；
+/// 其他运算的结果超出类型的范围时报告溢出
+pub fn int_binary_as(op: &BinOp, a: i64, b: i64, ty: &Type) -> Result<i64, String> {
+    if matches!(op, BinOp::LeftShift | BinOp::RightShift) {
+        if !(0..int_bits(ty) as i64).contains(&b) {
+            return Err(overflow(op).to_string());
+        }
+        return Ok(wrap_int(int_binary(op, a, b)?, ty));
+    }
+    let result = int_binary(op, a, b)?;
+    let (min, max) = int_bounds(ty);
+    // 和 i64 一样，最小值除以 -1 的商和余数都算溢出
+    let min_by_minus_one = matches!(op, BinOp::Div | BinOp::Mod) && a == min && b == -1;
+    if (min..=max).contains(&result) && !min_by_minus_one {
+        Ok(result)
+    } else {
+        Err(overflow(op).to_string())
+    }
+}
+
+// 运算溢出时的 panic 消息
+fn overflow(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "attempt to add with overflow",
+        BinOp::Sub => "attempt to subtract with overflow",
+        BinOp::Mul => "attempt to multiply with overflow",
+        BinOp::Div => "attempt to divide with overflow",
+        BinOp::Mod => "attempt to calculate the remainder with overflow",
+        _ => "attempt to shift with overflow",
+    }
+}
+
+pub fn int_binary(op: &BinOp, a: i64, b: i64) -> Result<i64, String> {
+    let result = match op {
+        BinOp::Add => a.checked_add(b).ok_or(overflow(op))?,
+        BinOp::Sub => a.checked_sub(b).ok_or(overflow(op))?,
+        BinOp::Mul => a.checked_mul(b).ok_or(overflow(op))?,
+        BinOp::Div if b == 0 => return Err("attempt to divide by zero".to_string()),
+        BinOp::Div => a.checked_div(b).ok_or(overflow(op))?,
+        BinOp::Mod if b == 0 => {
+            return Err("attempt to calculate the remainder with a divisor of zero".to_string())
+        }
+        BinOp::Mod => a.checked_rem(b).ok_or(overflow(op))?,
+        BinOp::BitwiseAnd => a & b,
+        BinOp::BitwiseOr => a | b,
+        BinOp::BitwiseXor => a ^ b,
+        BinOp::LeftShift | BinOp::RightShift => {
+            let shift = u32::try_from(b)
+                .ok()
+                .filter(|shift| *shift < i64::BITS)
+                .ok_or(overflow(op))?;
+            if *op == BinOp::LeftShift {
+                a << shift
+            } else {
+                a >> shift
+            }
+        }
+        _ => return Err(format!("unsupported operation `{:?}` on integers", op)),
+    };
+    Ok(result)
+}
+
+/// 下标越界的 panic 消息，解释器和虚拟机共用
+pub fn index_out_of_bounds(len: usize, index: i64) -> String {
+    format!(
+        "index out of bounds: the len is {} but the index is {}",
+        len, index
+    )
+}
+
+/// 切片的区间不在数组之内时的 panic 消息，没有越界时为 None
+pub fn slice_out_of_bounds(len: usize, start: i64, end: i64) -> Option<String> {
+    if start < 0 {
+        Some(format!("slice index starts at {} which is negative", start))
+    } else if start > end {
+        Some(format!(
+            "slice index starts at {} but ends at {}",
+            start, end
+        ))
+    } else if end as u64 > len as u64 {
+        Some(format!(
+            "range end index {} out of range for slice of length {}",
+            end, len
+        ))
+    } else {
+        None
+    }
+}
+
+/// 违反契约的 panic 消息
+pub fn contract_violation(kind: ContractKind, clause: &str) -> String {
+    format!("{} violated: `{}`", kind.description(), clause)
+}
+
+/// 违反契约时指向契约声明处的注释
+pub fn contract_note(kind: ContractKind, owner: &str, declared: Span) -> String {
+    format!(
+        "the {} of `{}` is declared at line {}, column {}",
+        kind.description(),
+        owner,
+        declared.line,
+        declared.column
+    )
+}
+
+pub fn compare(lhs: &Value, rhs: &Value) -> Option<std::cmp::Ordering> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+pub fn unary(op: &UnOp, value: Value) -> Result<Value, String> {
+    match (op, value) {
+        (UnOp::Neg, Value::Int(n)) => n
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| "attempt to negate with overflow".to_string()),
+        (UnOp::Neg, Value::Float(x)) => Ok(Value::Float(-x)),
+        (UnOp::LogicalNot, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (UnOp::LogicalNot | UnOp::BitwiseNot, Value::Int(n)) => Ok(Value::Int(!n)),
+        (op, value) => Err(format!(
+            "unsupported operation `{:?}` on {}",
+            op,
+            value.type_name()
+        )),
+    }
+}
+
+/// 按语义分析记录的整数类型 `ty` 做一元运算：取负的结果超出类型的范围时报告溢出，
+/// 按位取反只在类型的宽度内进行；没有记录时同 `unary`
+pub fn unary_as(op: &UnOp, value: Value, ty: Option<&Type>) -> Result<Value, String> {
+    match (op, ty, value) {
+        (UnOp::Neg, Some(ty), Value::Int(n)) if is_int(ty) => {
+            let (min, max) = int_bounds(ty);
+            match n.checked_neg().filter(|n| (min..=max).contains(n)) {
+                Some(n) => Ok(Value::Int(n)),
+                None => Err("attempt to negate with overflow".to_string()),
+            }
+        }
+        (UnOp::LogicalNot | UnOp::BitwiseNot, Some(ty), Value::Int(n)) if is_int(ty) => {
+            Ok(Value::Int(int_not(n, ty)))
+        }
+        (_, _, value) => unary(op, value),
+    }
+}
+
+/// 整数类型 `ty` 的按位取反。执行时 `u64` 和 `usize` 只有低 63 位（见 `int_bounds`），
+/// 取反这 63 位，结果仍在类型的范围内
+pub fn int_not(n: i64, ty: &Type) -> i64 {
+    match ty {
+        Type::U64 | Type::Usize => n ^ i64::MAX,
+        ty => wrap_int(!n, ty),
+    }
+}
+
+/// 内建函数 `abs`：结果按语义分析记录的参数的整数类型 `ty` 检查溢出，没有记录时按 i64 检查
+pub fn abs(value: Value, ty: Option<&Type>) -> Result<Value, String> {
+    let max = ty
+        .filter(|ty| is_int(ty))
+        .map_or(i64::MAX, |ty| int_bounds(ty).1);
+    match value {
+        Value::Int(n) => n
+            .checked_abs()
+            .filter(|n| *n <= max)
+            .map(Value::Int)
+            .ok_or_else(|| "attempt to negate with overflow".to_string()),
+        Value::Float(x) => Ok(Value::Float(x.abs())),
+        value => Err(format!("expected an integer, found {}", value.type_name())),
+    }
+}
+
+/// `value as ty`，允许的转换见 sema/cast.rs；类型不确定的转换在这里报告错误
+pub fn cast(value: Value, ty: &Type) -> Result<Value, String> {
+    let value = match (value, ty) {
+        // 截断后的负数超出 `u64` 和 `usize` 能表示的范围
+        (Value::Int(n), Type::U64 | Type::Usize) if n < 0 => {
+            return Err(format!(
+                "cannot cast {} to `{}`: the value must be in 0..={}",
+                n,
+                ty,
+                i64::MAX
+            ))
+        }
+        (Value::Int(n), ty) if is_int(ty) => Value::Int(wrap_int(n, ty)),
+        (Value::Float(x), ty) if is_int(ty) => {
+            let (min, max) = int_bounds(ty);
+            Value::Int((x as i64).clamp(min, max))
+        }
+        (Value::Bool(b), ty) if is_int(ty) => Value::Int(b as i64),
+        (Value::Char(c), ty) if is_int(ty) => Value::Int(wrap_int(c as i64, ty)),
+        (Value::Int(n), Type::F32) => Value::Float(n as f32 as f64),
+        (Value::Int(n), Type::F64) => Value::Float(n as f64),
+        (Value::Float(x), Type::F32) => Value::Float(x as f32 as f64),
+        (Value::Float(x), Type::F64) => Value::Float(x),
+        (Value::Int(n), Type::Char) if (0..=255).contains(&n) => Value::Char(n as u8 as char),
+        (Value::Bool(b), Type::Bool) => Value::Bool(b),
+        (Value::Char(c), Type::Char) => Value::Char(c),
+        // 指针转换不改变指向的位置
+        (value @ Value::Ref(_), Type::Pointer(_, _) | Type::Reference(_, _)) => value,
+        (value, ty) => {
+            return Err(format!(
+                "cannot cast {} value to `{}`",
+                value.type_name(),
+                ty
+            ))
+        }
+    };
+    Ok(value)
+}
+
+fn is_int(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::Isize
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+    )
+}
+
+// 整数类型的取值范围，超出 i64 的部分按 i64 截断
+fn int_bounds(ty: &Type) -> (i64, i64) {
+    match ty {
+        Type::I8 => (i8::MIN as i64, i8::MAX as i64),
+        Type::I16 => (i16::MIN as i64, i16::MAX as i64),
+        Type::I32 => (i32::MIN as i64, i32::MAX as i64),
+        Type::U8 => (0, u8::MAX as i64),
+        Type::U16 => (0, u16::MAX as i64),
+        Type::U32 => (0, u32::MAX as i64),
+        Type::U64 | Type::Usize => (0, i64::MAX),
+        _ => (i64::MIN, i64::MAX),
+    }
+}
+
+// 整数类型的位数
+fn int_bits(ty: &Type) -> u32 {
+    match ty {
+        Type::I8 | Type::U8 => 8,
+        Type::I16 | Type::U16 => 16,
+        Type::I32 | Type::U32 => 32,
+        _ => 64,
+    }
+}
+
+// 按目标整数类型截断
+fn wrap_int(n: i64, ty: &Type) -> i64 {
+    match ty {
+        Type::I8 => n as i8 as i64,
+        Type::I16 => n as i16 as i64,
+        Type::I32 => n as i32 as i64,
+        Type::U8 => n as u8 as i64,
+        Type::U16 => n as u16 as i64,
+        Type::U32 => n as u32 as i64,
+        _ => n,
+    }
+}
+
+/// `substring(s, start, end)`：字节下标 `start..end` 之间的部分
+pub fn substring(text: &str, start: i64, end: i64) -> Result<&str, String> {
+    if start < 0 || end < start || end as usize > text.len() {
+        return Err(format!(
+            "byte range {}..{} is out of bounds of a string of length {}",
+            start,
+            end,
+            text.len()
+        ));
+    }
+    for index in [start, end] {
+        if !text.is_char_boundary(index as usize) {
+            return Err(format!("byte index {} is not a char boundary", index));
+        }
+    }
+    Ok(&text[start as usize..end as usize])
+}
+
+pub fn split<'a>(text: &'a str, separator: &str) -> Result<Vec<&'a str>, String> {
+    if separator.is_empty() {
+        return Err("separator must not be empty".to_string());
+    }
+    Ok(text.split(separator).collect())
+}
+
+/// `to_int` 和 `is_int` 接受的整数：可选的符号后跟十进制数字
+pub fn parse_int(text: &str) -> Option<i64> {
+    text.parse().ok()
+}
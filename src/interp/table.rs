@@ -0,0 +1,137 @@
+// `Map<K, V>` 的运行时哈希表，解释器和虚拟机共用
+// 键是 bool、整数、char 或字符串，按 FNV-1a 哈希：不做随机化，同一个程序每次运行的结果相同。
+// 条目按插入顺序放在一个数组中，桶里保存条目的下标；删除时把最后一个条目移到空出的位置，
+// 所以迭代顺序只取决于插入和删除的顺序，在解释器和虚拟机中一致
+
+/// 可以作为 Map 键的值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Key {
+    Bool(bool),
+    Int(i64),
+    Char(char),
+    Str(String),
+}
+
+impl Key {
+    fn hash(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let (tag, bytes): (u8, &[u8]) = match self {
+            Key::Bool(b) => (0, &[*b as u8]),
+            Key::Int(n) => (1, &n.to_le_bytes()),
+            Key::Char(c) => (2, &(*c as u32).to_le_bytes()),
+            Key::Str(text) => (3, text.as_bytes()),
+        };
+        let mut hash = OFFSET;
+        for byte in std::iter::once(&tag).chain(bytes) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+        hash
+    }
+}
+
+// 桶数的初始值；条目数超过桶数时桶数翻倍
+const INITIAL_BUCKETS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct Table<V> {
+    entries: Vec<(Key, V)>,
+    buckets: Vec<Vec<usize>>,
+}
+
+impl<V> Default for Table<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Table<V> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            buckets: vec![Vec::new(); INITIAL_BUCKETS],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 按插入顺序（删除会把最后一个条目移到空位）遍历条目
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &V)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&V> {
+        self.position(key).map(|index| &self.entries[index].1)
+    }
+
+    pub fn contains_key(&self, key: &Key) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// 插入或替换，返回原来的值
+    pub fn insert(&mut self, key: Key, value: V) -> Option<V> {
+        if let Some(index) = self.position(&key) {
+            return Some(std::mem::replace(&mut self.entries[index].1, value));
+        }
+        if self.entries.len() >= self.buckets.len() {
+            self.grow();
+        }
+        let bucket = self.bucket(&key);
+        self.buckets[bucket].push(self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    pub fn remove(&mut self, key: &Key) -> Option<V> {
+        let index = self.position(key)?;
+        let bucket = self.bucket(key);
+        self.buckets[bucket].retain(|&entry| entry != index);
+        // 最后一个条目移到 index，更新它在桶中的下标
+        let last = self.entries.len() - 1;
+        if index != last {
+            let moved = self.bucket(&self.entries[last].0);
+            for entry in &mut self.buckets[moved] {
+                if *entry == last {
+                    *entry = index;
+                }
+            }
+        }
+        Some(self.entries.swap_remove(index).1)
+    }
+
+    fn bucket(&self, key: &Key) -> usize {
+        (key.hash() % self.buckets.len() as u64) as usize
+    }
+
+    fn position(&self, key: &Key) -> Option<usize> {
+        self.buckets[self.bucket(key)]
+            .iter()
+            .copied()
+            .find(|&index| self.entries[index].0 == *key)
+    }
+
+    fn grow(&mut self) {
+        self.buckets = vec![Vec::new(); self.buckets.len() * 2];
+        for index in 0..self.entries.len() {
+            let bucket = self.bucket(&self.entries[index].0);
+            self.buckets[bucket].push(index);
+        }
+    }
+}
+
+// 条目相同即相等，与插入顺序无关
+impl<V: PartialEq> PartialEq for Table<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
@@ -0,0 +1,329 @@
+// 解释器的运行时值
+// 复合值按值保存，赋值和传参时整体复制；只有引用共享变量的存储
+
+use super::table::{Key, Table};
+use crate::ast::{Expr, Parameter};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// 变量的存储位置，引用共享同一个槽
+pub type Slot = Rc<RefCell<Value>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Char(char),
+    Str(String),
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+    Map(Table<Value>),
+    Struct(String, Vec<(String, Value)>),
+    Variant(String, String, Vec<Value>), // (枚举名, 变体名, 字段)
+    Range(i64, i64),                     // 左闭右开
+    Function(String),
+    Closure(Rc<Closure>),
+    Boxed(Box<Value>), // `Box<T>`
+    Shared(Rc<Value>), // `Rc<T>`，引用计数就是它的 Rust 引用计数
+    Ref(Pointer),
+}
+
+/// 闭包：参数、函数体和创建时捕获的变量的值
+#[derive(Debug)]
+pub struct Closure {
+    pub params: Vec<Parameter>,
+    pub body: Expr,
+    pub captures: HashMap<String, Value>,
+}
+
+// 闭包没有结构相等，只有同一个闭包值才相等
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// 指向变量或其一部分的指针，`&x.a[1]` 表示为 x 的槽加上路径 `.a[1]`。
+/// 切片的引用是胖指针：路径指向数组，`window` 是切片在数组中的起点和长度
+#[derive(Debug, Clone)]
+pub struct Pointer {
+    pub slot: Slot,
+    pub path: Vec<Step>,
+    pub window: Option<Window>,
+}
+
+/// 切片在数组中的范围，解释器和虚拟机的胖指针相同
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub start: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Field(String), // 结构体字段，或元组下标 "0"、"1"
+    Index(usize),
+    Unbox, // 盒子或 Rc 中的值
+}
+
+impl PartialEq for Pointer {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.slot, &other.slot)
+            && self.path == other.path
+            && self.window == other.window
+    }
+}
+
+impl Pointer {
+    pub fn to_slot(slot: Slot) -> Self {
+        Self {
+            slot,
+            path: Vec::new(),
+            window: None,
+        }
+    }
+
+    /// 切片的下标换算为数组的下标
+    pub fn project(mut self, step: Step) -> Self {
+        let step = match (self.window.take(), step) {
+            (Some(window), Step::Index(index)) => Step::Index(window.start + index),
+            (_, step) => step,
+        };
+        self.path.push(step);
+        self
+    }
+
+    /// 指向的数组或切片中从 `start` 开始的 `len` 个元素
+    pub fn slice(mut self, start: usize, len: usize) -> Self {
+        let offset = self.window.map_or(0, |window| window.start);
+        self.window = Some(Window {
+            start: offset + start,
+            len,
+        });
+        self
+    }
+
+    /// 读取指向的值，切片读取为它的元素组成的数组；路径无效时返回 None
+    pub fn with<R>(&self, f: impl FnOnce(&Value) -> R) -> Option<R> {
+        let value = self.slot.borrow();
+        let mut current = &*value;
+        for step in &self.path {
+            current = current.project(step)?;
+        }
+        match (self.window, current) {
+            (None, _) => Some(f(current)),
+            (Some(window), Value::Array(elements)) => {
+                let elements = elements.get(window.start..window.start + window.len)?;
+                Some(f(&Value::Array(elements.to_vec())))
+            }
+            (Some(_), _) => None,
+        }
+    }
+
+    /// 修改指向的值，切片修改后写回数组中的同一范围；路径无效时返回 None
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut Value) -> R) -> Option<R> {
+        let mut value = self.slot.borrow_mut();
+        let mut current = &mut *value;
+        for step in &self.path {
+            current = current.project_mut(step)?;
+        }
+        let Some(window) = self.window else {
+            return Some(f(current));
+        };
+        let Value::Array(elements) = current else {
+            return None;
+        };
+        let range = window.start..window.start + window.len;
+        let mut slice = Value::Array(elements.get(range.clone())?.to_vec());
+        let result = f(&mut slice);
+        if let Value::Array(slice) = slice {
+            elements.splice(range, slice);
+        }
+        Some(result)
+    }
+}
+
+impl Value {
+    pub fn new_slot(self) -> Slot {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unit => "()",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Str(_) => "string",
+            Value::Tuple(_) => "tuple",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Struct(_, _) => "struct",
+            Value::Variant(_, _, _) => "enum",
+            Value::Range(_, _) => "range",
+            Value::Function(_) => "function",
+            Value::Closure(_) => "closure",
+            Value::Boxed(_) => "box",
+            Value::Shared(_) => "rc",
+            Value::Ref(_) => "reference",
+        }
+    }
+
+    fn project(&self, step: &Step) -> Option<&Value> {
+        match (self, step) {
+            (Value::Struct(_, fields), Step::Field(name)) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            (Value::Tuple(elements), Step::Field(index)) => {
+                elements.get(index.parse::<usize>().ok()?)
+            }
+            (Value::Array(elements), Step::Index(index)) => elements.get(*index),
+            (Value::Boxed(inner), Step::Unbox) => Some(inner),
+            (Value::Shared(inner), Step::Unbox) => Some(inner),
+            _ => None,
+        }
+    }
+
+    fn project_mut(&mut self, step: &Step) -> Option<&mut Value> {
+        match (self, step) {
+            (Value::Struct(_, fields), Step::Field(name)) => fields
+                .iter_mut()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            (Value::Tuple(elements), Step::Field(index)) => {
+                elements.get_mut(index.parse::<usize>().ok()?)
+            }
+            (Value::Array(elements), Step::Index(index)) => elements.get_mut(*index),
+            (Value::Boxed(inner), Step::Unbox) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// 作为 Map 键的值；不能作为键时返回 None
+    pub fn to_key(&self) -> Option<Key> {
+        Some(match self {
+            Value::Bool(b) => Key::Bool(*b),
+            Value::Int(n) => Key::Int(*n),
+            Value::Char(c) => Key::Char(*c),
+            Value::Str(text) => Key::Str(text.clone()),
+            _ => return None,
+        })
+    }
+
+    pub fn from_key(key: &Key) -> Value {
+        match key {
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Int(n) => Value::Int(*n),
+            Key::Char(c) => Value::Char(*c),
+            Key::Str(text) => Value::Str(text.clone()),
+        }
+    }
+
+    /// 按复合值内部的格式输出（`{:?}`）：字符串和字符加引号
+    pub fn to_nested_string(&self) -> String {
+        struct Nested<'a>(&'a Value);
+
+        impl fmt::Display for Nested<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_nested(f)
+            }
+        }
+
+        Nested(self).to_string()
+    }
+
+    // 复合值内部的字符串和字符加引号，与顶层输出区分
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(text) => write!(f, "{:?}", text),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Boxed(inner) => inner.fmt_nested(f),
+            Value::Shared(inner) => inner.fmt_nested(f),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+fn fmt_list(f: &mut fmt::Formatter<'_>, values: &[Value]) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        value.fmt_nested(f)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Str(text) => write!(f, "{}", text),
+            Value::Tuple(elements) => {
+                write!(f, "(")?;
+                fmt_list(f, elements)?;
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Array(elements) => {
+                write!(f, "[")?;
+                fmt_list(f, elements)?;
+                write!(f, "]")
+            }
+            Value::Map(table) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in table.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    Value::from_key(key).fmt_nested(f)?;
+                    write!(f, ": ")?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Struct(name, fields) => {
+                write!(f, "{} {{ ", name)?;
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", field)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Variant(_, variant, fields) => {
+                write!(f, "{}", variant)?;
+                if !fields.is_empty() {
+                    write!(f, "(")?;
+                    fmt_list(f, fields)?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+            Value::Function(name) => write!(f, "fn {}", name),
+            Value::Closure(_) => write!(f, "<closure>"),
+            // 盒子和 Rc 按其中的值输出
+            Value::Boxed(inner) => write!(f, "{}", inner),
+            Value::Shared(inner) => write!(f, "{}", inner),
+            Value::Ref(pointer) => match pointer.with(|value| value.to_string()) {
+                Some(text) => write!(f, "{}", text),
+                None => write!(f, "<dangling>"),
+            },
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Contractus 编译器认识的函数（intrinsic）
+// 标准库用 `#[intrinsic("sqrt")]` 标记一个普通的 Contractus 函数，声明它实现了这里登记的某个操作：
+//
+//     #[intrinsic("ctpop")]
+//     fn count_ones(x: u64) -> u32 { ... }
+//
+// 函数的名字不限，签名必须和登记的一致（语义分析检查，E0708），函数体是用 Contractus 写的参考实现。
+// 知道这个操作的后端把对函数的调用换成自己的实现：字节码编译器调用虚拟机的同名内建函数
+// （bytecode.rs 的 `Builtin`）；解释器不认识任何 intrinsic，直接执行函数体。
+// 所以标准库仍然完全用 Contractus 编写，后端只是更快地实现其中的一部分
+// - memcpy(dst: &mut [T], src: &[T])：把 src 的元素复制到 dst，两者长度必须相同
+// - ctpop(x: u64) -> u32：二进制中 1 的个数
+// - ctlz(x: u64) -> u32：最高位开始连续的 0 的个数，x 为 0 时是 64
+// - cttz(x: u64) -> u32：最低位开始连续的 0 的个数，x 为 0 时是 64
+// - sqrt(x: f64) -> f64：平方根，负数的平方根是 NaN
+
+use crate::ast::Type;
+
+/// intrinsic 参数的类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    Exact(Type),
+    Slice,    // `&[T]`，元素类型和其他切片参数相同
+    SliceMut, // `&mut [T]`
+}
+
+/// 登记的 intrinsic 和它的签名
+#[derive(Debug)]
+pub struct Intrinsic {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub ret: Type,
+}
+
+pub const INTRINSICS: &[Intrinsic] = &[
+    Intrinsic {
+        name: "memcpy",
+        params: &[Param::SliceMut, Param::Slice],
+        ret: Type::Unit,
+    },
+    Intrinsic {
+        name: "ctpop",
+        params: &[Param::Exact(Type::U64)],
+        ret: Type::U32,
+    },
+    Intrinsic {
+        name: "ctlz",
+        params: &[Param::Exact(Type::U64)],
+        ret: Type::U32,
+    },
+    Intrinsic {
+        name: "cttz",
+        params: &[Param::Exact(Type::U64)],
+        ret: Type::U32,
+    },
+    Intrinsic {
+        name: "sqrt",
+        params: &[Param::Exact(Type::F64)],
+        ret: Type::F64,
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Intrinsic> {
+    INTRINSICS.iter().find(|intrinsic| intrinsic.name == name)
+}
+
+impl Intrinsic {
+    /// 参数和返回值的类型是否符合签名；没有返回类型的函数返回 `()`
+    pub fn accepts(&self, params: &[Type], ret: &Type) -> bool {
+        if params.len() != self.params.len() || *ret != self.ret {
+            return false;
+        }
+        let mut element = None;
+        for (param, ty) in self.params.iter().zip(params) {
+            let mutable = match param {
+                Param::Exact(expected) if expected == ty => continue,
+                Param::Exact(_) => return false,
+                Param::Slice => false,
+                Param::SliceMut => true,
+            };
+            let Type::Reference(inner, m) = ty else {
+                return false;
+            };
+            let Type::Slice(elem) = &**inner else {
+                return false;
+            };
+            if *m != mutable || *element.get_or_insert(elem) != elem {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// 签名的写法，如 `fn(u64) -> u32`
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::Exact(ty) => ty.to_string(),
+                Param::Slice => "&[T]".to_string(),
+                Param::SliceMut => "&mut [T]".to_string(),
+            })
+            .collect();
+        format!("fn({}) -> {}", params.join(", "), self.ret)
+    }
+}
@@ -0,0 +1,415 @@
+// 数据布局
+// 各后端共用的类型大小、对齐和字段偏移，按 64 位目标计算：
+// - 基础类型按自身大小对齐，`()` 和 `!` 大小为 0
+// - 字符串和切片的引用是指针加长度（16 字节），其他引用、指针、函数和运行时管理的值
+//   （Vec、Map 等）是一个指针（8 字节）。切片本身的长度只在运行时知道，没有布局
+// - 结构体和元组默认按对齐从大到小排列字段（对齐相同的保持声明顺序），减少填充；
+//   `#[repr(C)]` 的结构体按声明顺序排列，每个字段按自己的对齐放置，与 C 编译器一致；
+//   `#[repr(packed)]` 的结构体按声明顺序紧密排列，没有填充，对齐为 1。
+//   总大小都按结构体的对齐取整，字段的偏移总是按声明顺序给出
+// - 枚举由标签和载荷组成：标签默认是能容纳所有变体序号的最小无符号整数，`#[repr(C)]` 时
+//   与 C 的枚举相同是 i32，`#[repr(u8)]` 等指定标签的整数类型；标签位于偏移 0，
+//   各变体的字段像结构体一样从同一个载荷偏移开始排列（载荷是各变体的 union），
+//   载荷偏移按所有变体中最大的字段对齐取整。指定了 repr 的枚举的字段按声明顺序排列
+// 变体的标签值就是它的声明序号：MIR 的 `Discriminant`、虚拟机的 `Variant`/`Discriminant`
+// 指令都以它为准，match 降级出的 `switchInt` 按标签的类型比较。解释器按变体名比较，不需要标签
+// `std::mem::size_of::<T>()` 和 `std::mem::align_of::<T>()`（也可以写成 `size_of::<T>()`）
+// 在编译时（解释器中在求值时）由这里计算，`T` 可以是 `typeof(expr)`。
+// 布局只对具体类型有定义，类型参数要在单态化之后才能计算
+
+use crate::ast::{Attribute, EnumDef, StructDef, Type};
+use crate::builtins;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 指针的大小和对齐
+pub const POINTER_SIZE: u64 = 8;
+
+// 结构体和枚举按值嵌套的最大层数
+const MAX_NESTING: usize = 64;
+
+/// 按布局求值的内建函数，类型实参用 `::<T>` 给出
+pub const INTRINSICS: [&str; 2] = ["std::mem::size_of", "std::mem::align_of"];
+
+/// 路径表示的布局内建函数：完整的路径，或者只写最后一段的 `size_of`、`align_of`
+pub fn intrinsic(path: &str) -> Option<&'static str> {
+    INTRINSICS
+        .into_iter()
+        .find(|name| *name == path || name.rsplit("::").next() == Some(path))
+}
+
+/// `#[repr(...)]` 可以使用的表示方式：`C`、`packed`，以及枚举标签的整数类型
+pub const REPR_HINTS: &[&str] = &[
+    "C", "packed", "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+}
+
+impl Layout {
+    pub const fn new(size: u64, align: u64) -> Self {
+        Self { size, align }
+    }
+
+    fn scalar(size: u64) -> Self {
+        Self::new(size, size)
+    }
+}
+
+/// 结构体的布局和各字段的偏移，偏移按声明顺序
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout {
+    pub offsets: Vec<u64>,
+    pub layout: Layout,
+}
+
+/// 枚举变体的标签和字段在整个枚举值中的偏移
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantLayout {
+    pub name: String,
+    pub tag: u64,
+    pub offsets: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumLayout {
+    pub tag: Type,    // 标签的类型
+    pub payload: u64, // 载荷的偏移
+    pub variants: Vec<VariantLayout>,
+    pub layout: Layout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    Unknown(String),   // 没有定义的类型或类型参数
+    Recursive(String), // 不经过指针包含自身的类型，大小无限
+    Unsized(String),   // 切片，大小只在运行时知道
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Unknown(ty) => write!(f, "cannot compute the layout of `{}`", ty),
+            LayoutError::Recursive(name) => {
+                write!(f, "recursive type `{}` has infinite size", name)
+            }
+            LayoutError::Unsized(ty) => {
+                write!(f, "the size of `{}` is not known at compile time", ty)
+            }
+        }
+    }
+}
+
+/// 结构体或枚举的表示方式，由 `#[repr(...)]` 决定
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Repr {
+    pub c: bool,
+    pub packed: bool,
+    pub tag: Option<Type>, // 枚举标签的整数类型
+}
+
+impl Repr {
+    /// 类型上所有 repr 属性合起来的表示方式；不认识的表示方式由语义分析报告，这里忽略
+    pub fn of(attributes: &[Attribute]) -> Self {
+        let mut repr = Repr::default();
+        let hints = attributes
+            .iter()
+            .filter(|attribute| attribute.name == "repr")
+            .flat_map(|attribute| &attribute.args);
+        for hint in hints {
+            match hint.as_str() {
+                "C" => repr.c = true,
+                "packed" => repr.packed = true,
+                name => repr.tag = int_type(name).or(repr.tag),
+            }
+        }
+        repr
+    }
+
+    // 字段按声明顺序排列
+    fn ordered(&self) -> bool {
+        self.c || self.packed || self.tag.is_some()
+    }
+}
+
+/// 整数类型名对应的类型，用于 `#[repr(u8)]`
+pub fn int_type(name: &str) -> Option<Type> {
+    Some(match name {
+        "u8" => Type::U8,
+        "u16" => Type::U16,
+        "u32" => Type::U32,
+        "u64" => Type::U64,
+        "i8" => Type::I8,
+        "i16" => Type::I16,
+        "i32" => Type::I32,
+        "i64" => Type::I64,
+        _ => return None,
+    })
+}
+
+/// 默认的标签类型：能容纳 `variants` 个序号的最小无符号整数
+pub fn tag_type(variants: usize) -> Type {
+    match variants {
+        0..=0x100 => Type::U8,
+        0x101..=0x1_0000 => Type::U16,
+        _ => Type::U32,
+    }
+}
+
+/// 枚举标签的类型，考虑 `#[repr(...)]`
+pub fn enum_tag(def: &EnumDef) -> Type {
+    let repr = Repr::of(&def.attributes);
+    match (repr.tag, repr.c) {
+        (Some(tag), _) => tag,
+        (None, true) => Type::I32,
+        (None, false) => tag_type(def.variants.len()),
+    }
+}
+
+// 结构体或枚举定义
+#[derive(Clone, Copy)]
+enum Adt<'a> {
+    Struct(&'a StructDef),
+    Enum(&'a EnumDef),
+}
+
+/// 按程序中的结构体和枚举定义计算布局
+pub struct Layouts<'a> {
+    structs: Vec<&'a StructDef>,
+    enums: Vec<&'a EnumDef>,
+}
+
+impl<'a> Layouts<'a> {
+    pub fn new(
+        structs: impl IntoIterator<Item = &'a StructDef>,
+        enums: impl IntoIterator<Item = &'a EnumDef>,
+    ) -> Self {
+        Self {
+            structs: structs.into_iter().collect(),
+            enums: enums.into_iter().collect(),
+        }
+    }
+
+    pub fn layout_of(&self, ty: &Type) -> Result<Layout, LayoutError> {
+        self.layout(ty, &mut Vec::new())
+    }
+
+    /// 内建函数 `name`（见 INTRINSICS）对类型 `ty` 的值
+    pub fn intrinsic(&self, name: &str, ty: &Type) -> Result<u64, LayoutError> {
+        let layout = self.layout_of(ty)?;
+        Ok(match name {
+            "std::mem::align_of" => layout.align,
+            _ => layout.size,
+        })
+    }
+
+    /// 非泛型结构体的布局
+    pub fn struct_layout(&self, name: &str) -> Result<StructLayout, LayoutError> {
+        let def = self
+            .structs
+            .iter()
+            .find(|def| def.name == name && def.generics.is_none())
+            .ok_or_else(|| LayoutError::Unknown(name.to_string()))?;
+        self.struct_layout_in(def, &BTreeMap::new(), &mut vec![name.to_string()])
+    }
+
+    /// 非泛型枚举的布局
+    pub fn enum_layout(&self, name: &str) -> Result<EnumLayout, LayoutError> {
+        let def = self
+            .enums
+            .iter()
+            .find(|def| def.name == name && def.generics.is_none())
+            .ok_or_else(|| LayoutError::Unknown(name.to_string()))?;
+        self.enum_layout_in(def, &BTreeMap::new(), &mut vec![name.to_string()])
+    }
+
+    // `visiting` 是正在计算布局的结构体和枚举（带类型实参），再次遇到其中之一说明类型递归；
+    // 每层都换一组类型实参的递归（`struct W<T> { next: W<(T, T)> }`）由嵌套层数限制发现
+    fn layout(&self, ty: &Type, visiting: &mut Vec<String>) -> Result<Layout, LayoutError> {
+        Ok(match ty {
+            Type::I8 | Type::U8 | Type::Bool => Layout::scalar(1),
+            Type::I16 | Type::U16 => Layout::scalar(2),
+            Type::I32 | Type::U32 | Type::F32 | Type::Char => Layout::scalar(4),
+            Type::I64 | Type::U64 | Type::F64 | Type::Usize | Type::Isize => Layout::scalar(8),
+            Type::Unit | Type::Never => Layout::new(0, 1),
+            Type::String => Layout::new(2 * POINTER_SIZE, POINTER_SIZE),
+            Type::Slice(_) => return Err(LayoutError::Unsized(ty.to_string())),
+            Type::Pointer(inner, _) | Type::Reference(inner, _)
+                if matches!(**inner, Type::Slice(_)) =>
+            {
+                Layout::new(2 * POINTER_SIZE, POINTER_SIZE)
+            }
+            Type::Pointer(_, _) | Type::Reference(_, _) | Type::Function(..) => {
+                Layout::scalar(POINTER_SIZE)
+            }
+            Type::Array(element, count) => {
+                let element = self.layout(element, visiting)?;
+                Layout::new(element.size * *count as u64, element.align)
+            }
+            Type::Tuple(types) => {
+                let types: Vec<&Type> = types.iter().collect();
+                self.sequence(&types, 0, &Repr::default(), visiting)?.1
+            }
+            Type::Named(name) if builtins::TYPES.contains(&name.as_str()) => {
+                Layout::scalar(POINTER_SIZE)
+            }
+            Type::Generic(name, _) if !self.is_adt(name) => Layout::scalar(POINTER_SIZE),
+            Type::Named(name) | Type::Generic(name, _) => {
+                let (adt, args) = self
+                    .adt(ty)
+                    .ok_or_else(|| LayoutError::Unknown(ty.to_string()))?;
+                // 按类型实参区分：`Option<Option<i32>>` 不是递归类型
+                let instance = ty.to_string();
+                if visiting.contains(&instance) || visiting.len() > MAX_NESTING {
+                    return Err(LayoutError::Recursive(name.clone()));
+                }
+                visiting.push(instance);
+                let layout = match adt {
+                    Adt::Struct(def) => self
+                        .struct_layout_in(def, &args, visiting)
+                        .map(|s| s.layout),
+                    Adt::Enum(def) => self.enum_layout_in(def, &args, visiting).map(|e| e.layout),
+                };
+                visiting.pop();
+                layout?
+            }
+            Type::TypeOf(node) => match node.resolved() {
+                Some(resolved) => self.layout(resolved, visiting)?,
+                None => return Err(LayoutError::Unknown(ty.to_string())),
+            },
+            Type::Infer => return Err(LayoutError::Unknown(ty.to_string())),
+        })
+    }
+
+    fn is_adt(&self, name: &str) -> bool {
+        self.structs.iter().any(|def| def.name == name)
+            || self.enums.iter().any(|def| def.name == name)
+    }
+
+    // 类型对应的定义和类型参数到实参的映射：`Pair<i32>` 对应泛型定义 `Pair<T>`，
+    // 单态化之后的 `Pair<i32>` 是一个非泛型定义的名字。类型实参的个数不对时为 None
+    fn adt(&self, ty: &Type) -> Option<(Adt<'a>, BTreeMap<String, Type>)> {
+        let (name, args) = match ty {
+            Type::Named(name) => (name, &[][..]),
+            Type::Generic(name, args) => (name, args.as_slice()),
+            _ => return None,
+        };
+        let (adt, generics) = match self.structs.iter().find(|def| &def.name == name) {
+            Some(def) => (Adt::Struct(def), &def.generics),
+            None => {
+                let def = self.enums.iter().find(|def| &def.name == name)?;
+                (Adt::Enum(def), &def.generics)
+            }
+        };
+        let params = generics
+            .as_ref()
+            .map_or_else(Vec::new, |generics| generics.param_names());
+        (params.len() == args.len()).then(|| {
+            let args = params.into_iter().zip(args.iter().cloned()).collect();
+            (adt, args)
+        })
+    }
+
+    fn struct_layout_in(
+        &self,
+        def: &StructDef,
+        args: &BTreeMap<String, Type>,
+        visiting: &mut Vec<String>,
+    ) -> Result<StructLayout, LayoutError> {
+        let types: Vec<Type> = def
+            .fields
+            .iter()
+            .map(|field| field.ty.substitute(args))
+            .collect();
+        let types: Vec<&Type> = types.iter().collect();
+        let repr = Repr::of(&def.attributes);
+        let (offsets, layout) = self.sequence(&types, 0, &repr, visiting)?;
+        Ok(StructLayout { offsets, layout })
+    }
+
+    fn enum_layout_in(
+        &self,
+        def: &EnumDef,
+        args: &BTreeMap<String, Type>,
+        visiting: &mut Vec<String>,
+    ) -> Result<EnumLayout, LayoutError> {
+        let repr = Repr::of(&def.attributes);
+        let tag = enum_tag(def);
+        let tag_size = self.layout(&tag, visiting)?.size;
+
+        // 先求出所有字段中最大的对齐，确定载荷的偏移
+        let mut fields = Vec::new();
+        let mut align = tag_size;
+        for variant in &def.variants {
+            let types: Vec<Type> = variant
+                .fields
+                .iter()
+                .flatten()
+                .map(|ty| ty.substitute(args))
+                .collect();
+            for ty in &types {
+                align = align.max(self.layout(ty, visiting)?.align);
+            }
+            fields.push(types);
+        }
+        let payload = round_up(tag_size, align);
+
+        let mut size = payload;
+        let mut variants = Vec::new();
+        for (tag, (variant, types)) in def.variants.iter().zip(fields).enumerate() {
+            let types: Vec<&Type> = types.iter().collect();
+            let (offsets, layout) = self.sequence(&types, payload, &repr, visiting)?;
+            size = size.max(payload + layout.size);
+            variants.push(VariantLayout {
+                name: variant.name.clone(),
+                tag: tag as u64,
+                offsets,
+            });
+        }
+        Ok(EnumLayout {
+            tag,
+            payload,
+            variants,
+            layout: Layout::new(round_up(size, align), align),
+        })
+    }
+
+    // 从偏移 `start` 开始按表示方式排列字段，返回按声明顺序的各字段偏移和这段字段自身的布局
+    fn sequence(
+        &self,
+        types: &[&Type],
+        start: u64,
+        repr: &Repr,
+        visiting: &mut Vec<String>,
+    ) -> Result<(Vec<u64>, Layout), LayoutError> {
+        let layouts = types
+            .iter()
+            .map(|ty| self.layout(ty, visiting))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut order: Vec<usize> = (0..types.len()).collect();
+        if !repr.ordered() {
+            order.sort_by_key(|&i| Reverse(layouts[i].align));
+        }
+
+        let mut offsets = vec![0; types.len()];
+        let mut offset = start;
+        let mut align = 1;
+        for i in order {
+            let field_align = if repr.packed { 1 } else { layouts[i].align };
+            offset = round_up(offset, field_align);
+            offsets[i] = offset;
+            offset += layouts[i].size;
+            align = align.max(field_align);
+        }
+        let size = round_up(offset - start, align);
+        Ok((offsets, Layout::new(size, align)))
+    }
+}
+
+fn round_up(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align) * align
+}
@@ -4,20 +4,49 @@
 // 2. 最小化内存分配
 // 3. 内联关键路径
 // 4. 预分配 token 向量
+// 5. 标识符和字符串驻留为共享的符号，重复出现的名字不再分配；记号不保存源码文本
 
+use crate::ast::Type;
+use crate::diagnostic::ErrorCode;
+use crate::literal::{self, EscapeError};
 use crate::span::Span;
+use crate::symbol::{Interner, Symbol};
 use crate::token::{Token, TokenKind};
+use std::borrow::Cow;
 use std::fmt;
+use std::ops::Range;
+
+/// 词法错误，位置是出错的记号的起点
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// 扫描一个记号的结果，出错时带上错误码
+type Scan = Result<TokenKind, (ErrorCode, String)>;
 
 // 高效的词法分析器 - 为自举优化
 pub struct Lexer<'a> {
-    input: &'a [u8],    // 直接操作字节，最高效
-    pos: usize,         // 当前位置
-    current: u8,        // 当前字符（避免重复索引）
-    line: u32,          // 行号
-    column: u32,        // 列号
-    line_start: usize,  // 当前行的起始位置
-    tokens: Vec<Token>, // 预分配的 token 向量
+    source: &'a str,               // 源码，截取字面量的内容
+    input: &'a [u8],               // 直接操作字节，最高效
+    pos: usize,                    // 当前位置
+    current: u8,                   // 当前字符（避免重复索引）
+    line: u32,                     // 行号
+    column: u32,                   // 列号
+    line_start: usize,             // 当前行的起始位置
+    tokens: Vec<Token>,            // 预分配的 token 向量
+    comments: Option<Vec<Span>>,   // 需要保留注释时记录注释的位置
+    symbols: Interner,             // 标识符和字符串：相同的文本只分配一次
+    literal_errors: Vec<LexError>, // 字面量中错误的转义，字面量本身仍然产生记号
+    max_tokens: usize,             // 记号数的上限，超出时报告 E0803 并停下
 }
 
 impl<'a> Lexer<'a> {
@@ -27,6 +56,7 @@ impl<'a> Lexer<'a> {
         let estimated_tokens = input.len() / 6; // 经验值：平均6字符一个token
 
         Self {
+            source: input,
             input: bytes,
             pos: 0,
             current: if bytes.is_empty() { 0 } else { bytes[0] },
@@ -34,11 +64,50 @@ impl<'a> Lexer<'a> {
             column: 1,
             line_start: 0,
             tokens: Vec::with_capacity(estimated_tokens),
+            comments: None,
+            symbols: Interner::new(),
+            literal_errors: Vec::new(),
+            max_tokens: usize::MAX,
         }
     }
 
+    /// 最多产生 `max` 个记号（不含末尾的 Eof），更多时报告 "program too large" 并停下
+    pub fn with_max_tokens(mut self, max: usize) -> Self {
+        self.max_tokens = max;
+        self
+    }
+
     // 主要的 tokenize 方法
-    pub fn tokenize(mut self) -> Result<Vec<Token>, Vec<String>> {
+    pub fn tokenize(mut self) -> Result<Vec<Token>, Vec<LexError>> {
+        self.scan()
+    }
+
+    /// 同时返回所有注释的位置（按出现顺序），格式化时用来保留注释
+    pub fn tokenize_with_comments(mut self) -> Result<(Vec<Token>, Vec<Span>), Vec<LexError>> {
+        self.comments = Some(Vec::new());
+        let tokens = self.scan()?;
+        Ok((tokens, self.comments.unwrap_or_default()))
+    }
+
+    /// 出错时也返回所有记号和注释的位置，以及所有的词法错误；出错的字符没有记号。
+    /// 编辑器高亮正在编辑的文件时使用
+    pub fn tokenize_lossy(mut self) -> (Vec<Token>, Vec<Span>, Vec<LexError>) {
+        self.comments = Some(Vec::new());
+        let errors = self.scan_all();
+        (self.tokens, self.comments.unwrap_or_default(), errors)
+    }
+
+    fn scan(&mut self) -> Result<Vec<Token>, Vec<LexError>> {
+        let errors = self.scan_all();
+        if errors.is_empty() {
+            Ok(std::mem::take(&mut self.tokens))
+        } else {
+            Err(errors)
+        }
+    }
+
+    // 扫描到结尾，记号留在 self.tokens 中，返回遇到的错误
+    fn scan_all(&mut self) -> Vec<LexError> {
         let mut errors = Vec::new();
 
         while !self.is_eof() {
@@ -52,35 +121,59 @@ impl<'a> Lexer<'a> {
             let start_line = self.line;
             let start_column = self.column;
 
+            if self.tokens.len() >= self.max_tokens {
+                errors.push(LexError {
+                    code: ErrorCode::E0803,
+                    message: format!("program too large: more than {} tokens", self.max_tokens),
+                    span: Span::new(start_pos, start_pos + 1, start_line, start_column),
+                });
+                break;
+            }
+
             match self.next_token_kind() {
                 Ok(kind) => {
                     let span = Span::new(start_pos, self.pos, start_line, start_column);
-                    let raw =
-                        String::from_utf8_lossy(&self.input[span.start..span.end]).to_string();
-                    self.tokens.push(Token::new(kind, span, raw));
+                    self.tokens.push(Token::new(kind, span));
+                    errors.append(&mut self.literal_errors);
                 }
-                Err(err) => {
-                    errors.push(err);
-                    self.advance(); // jump error char
+                Err((code, message)) => {
+                    let span = Span::new(
+                        start_pos,
+                        self.pos.max(start_pos + 1),
+                        start_line,
+                        start_column,
+                    );
+                    errors.push(LexError {
+                        code,
+                        message,
+                        span,
+                    });
+                    // 跳过出错的字符；换行留给 skip_whitespace_and_comments 计算行号
+                    if self.current != b'\n' {
+                        self.advance();
+                    }
                 }
             }
         }
 
         // 添加 EOF token
         let span = Span::new(self.pos, self.pos, self.line, self.column);
-        self.tokens
-            .push(Token::new(TokenKind::Eof, span, String::new()));
-
-        if errors.is_empty() {
-            Ok(self.tokens)
-        } else {
-            Err(errors)
-        }
+        self.tokens.push(Token::new(TokenKind::Eof, span));
+        errors
     }
 
     // 核心 token 识别 - 内联优化
+    // 运算符和分隔符按最长匹配：总是取 token.rs 的表中能匹配的最长拼写，
+    // 所以 `>>=` 是一个记号，`a<<=b` 是 `a <<= b`，`..=` 优先于 `..`。例外和约定：
+    // - `//` 和 `/*` 开始注释，优先于 `/` 和 `/=`
+    // - 表中没有的拼写分成最长的几段：`...` 是 `..` `.`，`&&=` 是 `&&` `=`，`||=` 是 `||` `=`；
+    //   它们不是运算符，解析器认出这几种写法并给出改正的提示
+    // - 解析器需要时把记号拆开：关闭泛型参数时 `>>`、`>=`、`>>=` 拆出一个 `>`，
+    //   `&&x` 是两层引用，`||` 是没有参数的闭包
+    // - 自定义运算符由紧挨着的几个记号组成（parser.rs 的 `operator_run`），不改变这里的切分
+    // 新增运算符时加在 token.rs 的表中并在这里识别，`test_maximal_munch` 检查两者一致
     #[inline]
-    fn next_token_kind(&mut self) -> Result<TokenKind, String> {
+    fn next_token_kind(&mut self) -> Scan {
         match self.current {
             b'0'..=b'9' => self.scan_number(),
             b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.scan_identifier_or_keyword(),
@@ -128,6 +221,14 @@ impl<'a> Lexer<'a> {
                 self.advance();
                 Ok(TokenKind::Question)
             }
+            b'#' => {
+                self.advance();
+                Ok(TokenKind::Hash)
+            }
+            b'$' => {
+                self.advance();
+                Ok(TokenKind::Dollar)
+            }
             b'~' => {
                 self.advance();
                 Ok(TokenKind::BitwiseNot)
@@ -179,7 +280,12 @@ impl<'a> Lexer<'a> {
 
             b'%' => {
                 self.advance();
-                Ok(TokenKind::Percent)
+                if self.current == b'=' {
+                    self.advance();
+                    Ok(TokenKind::PercentAssign)
+                } else {
+                    Ok(TokenKind::Percent)
+                }
             }
 
             b'=' => {
@@ -212,7 +318,12 @@ impl<'a> Lexer<'a> {
                     Ok(TokenKind::LessEqual)
                 } else if self.current == b'<' {
                     self.advance();
-                    Ok(TokenKind::LeftShift)
+                    if self.current == b'=' {
+                        self.advance();
+                        Ok(TokenKind::LeftShiftAssign)
+                    } else {
+                        Ok(TokenKind::LeftShift)
+                    }
                 } else {
                     Ok(TokenKind::Less)
                 }
@@ -225,7 +336,12 @@ impl<'a> Lexer<'a> {
                     Ok(TokenKind::GreaterEqual)
                 } else if self.current == b'>' {
                     self.advance();
-                    Ok(TokenKind::RightShift)
+                    if self.current == b'=' {
+                        self.advance();
+                        Ok(TokenKind::RightShiftAssign)
+                    } else {
+                        Ok(TokenKind::RightShift)
+                    }
                 } else {
                     Ok(TokenKind::Greater)
                 }
@@ -236,6 +352,9 @@ impl<'a> Lexer<'a> {
                 if self.current == b'&' {
                     self.advance();
                     Ok(TokenKind::LogicalAnd)
+                } else if self.current == b'=' {
+                    self.advance();
+                    Ok(TokenKind::BitAndAssign)
                 } else {
                     Ok(TokenKind::BitwiseAnd)
                 }
@@ -246,6 +365,9 @@ impl<'a> Lexer<'a> {
                 if self.current == b'|' {
                     self.advance();
                     Ok(TokenKind::LogicalOr)
+                } else if self.current == b'=' {
+                    self.advance();
+                    Ok(TokenKind::BitOrAssign)
                 } else {
                     Ok(TokenKind::BitwiseOr)
                 }
@@ -253,7 +375,12 @@ impl<'a> Lexer<'a> {
 
             b'^' => {
                 self.advance();
-                Ok(TokenKind::BitwiseXor)
+                if self.current == b'=' {
+                    self.advance();
+                    Ok(TokenKind::BitXorAssign)
+                } else {
+                    Ok(TokenKind::BitwiseXor)
+                }
             }
 
             b':' => {
@@ -281,83 +408,128 @@ impl<'a> Lexer<'a> {
                 }
             }
 
-            c => Err(format!(
-                "Unexpected character '{}' at line {}, column {}",
-                c as char, self.line, self.column
+            c => Err((
+                ErrorCode::E0006,
+                format!(
+                    "Unexpected character '{}' at line {}, column {}",
+                    c as char, self.line, self.column
+                ),
             )),
         }
     }
 
     // 数字扫描 - 优化的整数解析
     #[inline]
-    fn scan_number(&mut self) -> Result<TokenKind, String> {
+    fn scan_number(&mut self) -> Scan {
         let start = self.pos;
 
-        // 处理十六进制
-        if self.current == b'0' && self.peek() == b'x' {
+        // 十六进制和二进制
+        let (radix, digits_start, name) = if self.current == b'0' && self.peek() == b'x' {
             self.advance();
             self.advance();
-
             while self.current.is_ascii_hexdigit() || self.current == b'_' {
                 self.advance();
             }
-
-            let num_str =
-                String::from_utf8_lossy(&self.input[start + 2..self.pos]).replace('_', "");
-
-            match i32::from_str_radix(&num_str, 16) {
-                Ok(value) => return Ok(TokenKind::IntLiteral(value)),
-                Err(_) => return Err(format!("Invalid hexadecimal number at line {}", self.line)),
-            }
-        }
-
-        // 处理二进制
-        if self.current == b'0' && self.peek() == b'b' {
+            (16, start + 2, "hexadecimal number")
+        } else if self.current == b'0' && self.peek() == b'b' {
             self.advance();
             self.advance();
-
             while self.current == b'0' || self.current == b'1' || self.current == b'_' {
                 self.advance();
             }
-
-            let num_str =
-                String::from_utf8_lossy(&self.input[start + 2..self.pos]).replace('_', "");
-
-            match i32::from_str_radix(&num_str, 2) {
-                Ok(value) => return Ok(TokenKind::IntLiteral(value)),
-                Err(_) => return Err(format!("Invalid binary number at line {}", self.line)),
+            (2, start + 2, "binary number")
+        } else {
+            // 扫描整数部分
+            while self.current.is_ascii_digit() || self.current == b'_' {
+                self.advance();
             }
-        }
 
-        // 扫描整数部分
-        while self.current.is_ascii_digit() || self.current == b'_' {
-            self.advance();
-        }
+            // 检查是否是浮点数；`.` 之后的数字是元组下标，`pair.0.1` 中的 `0.1` 不是浮点数
+            let tuple_index =
+                matches!(self.tokens.last(), Some(token) if token.kind == TokenKind::Dot);
+            if self.current == b'.' && self.peek().is_ascii_digit() && !tuple_index {
+                // 暂时跳过浮点数支持，可以后续添加
+                return Err((
+                    ErrorCode::E0005,
+                    format!("Float literals not yet supported at line {}", self.line),
+                ));
+            }
+            (10, start, "number")
+        };
+        let digits = self.digits(digits_start);
+        let suffix = self.int_suffix()?;
+
+        // 值最大是 i64::MAX，也是 u64 和 usize 的上限（见 sema::literals::int_range）；
+        // 是否在类型的范围内由语义分析按上下文检查
+        let value = match i64::from_str_radix(&digits, radix) {
+            Ok(value) => value,
+            Err(error) if *error.kind() == std::num::IntErrorKind::PosOverflow => {
+                return Err((
+                    ErrorCode::E0005,
+                    format!(
+                        "integer literal is too large at line {}: the largest is {}",
+                        self.line,
+                        i64::MAX
+                    ),
+                ))
+            }
+            Err(_) if radix == 10 => {
+                return Err((
+                    ErrorCode::E0005,
+                    format!("Invalid number '{}' at line {}", digits, self.line),
+                ))
+            }
+            Err(_) => {
+                return Err((
+                    ErrorCode::E0005,
+                    format!("Invalid {} at line {}", name, self.line),
+                ))
+            }
+        };
+        Ok(match suffix {
+            Some(ty) => TokenKind::SuffixedIntLiteral(value, ty),
+            None => TokenKind::IntLiteral(value),
+        })
+    }
 
-        // 检查是否是浮点数
-        if self.current == b'.' && self.peek().is_ascii_digit() {
-            // 暂时跳过浮点数支持，可以后续添加
-            return Err(format!(
-                "Float literals not yet supported at line {}",
-                self.line
-            ));
+    // 紧跟在数字之后的类型后缀，可以用 `_` 与数字隔开
+    fn int_suffix(&mut self) -> Result<Option<Type>, (ErrorCode, String)> {
+        if !(self.current.is_ascii_alphabetic() || self.current == b'_') {
+            return Ok(None);
         }
-
-        // 解析整数
-        let num_str = String::from_utf8_lossy(&self.input[start..self.pos]).replace('_', "");
-
-        match num_str.parse::<i32>() {
-            Ok(value) => Ok(TokenKind::IntLiteral(value)),
-            Err(_) => Err(format!(
-                "Invalid number '{}' at line {}",
-                num_str, self.line
-            )),
+        let start = self.pos;
+        while self.current.is_ascii_alphanumeric() || self.current == b'_' {
+            self.advance();
         }
+        let source = self.source;
+        let suffix = source[start..self.pos].trim_start_matches('_');
+        let ty = match suffix {
+            "i8" => Type::I8,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "i64" => Type::I64,
+            "isize" => Type::Isize,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "usize" => Type::Usize,
+            _ => {
+                return Err((
+                    ErrorCode::E0005,
+                    format!(
+                        "invalid suffix `{}` for number literal at line {}",
+                        suffix, self.line
+                    ),
+                ))
+            }
+        };
+        Ok(Some(ty))
     }
 
     // 标识符和关键字扫描 - 使用完美哈希或跳转表优化
     #[inline]
-    fn scan_identifier_or_keyword(&mut self) -> Result<TokenKind, String> {
+    fn scan_identifier_or_keyword(&mut self) -> Scan {
         let start = self.pos;
 
         // 扫描标识符字符
@@ -371,7 +543,7 @@ impl<'a> Lexer<'a> {
             std::str::from_utf8_unchecked(ident_bytes)
         };
 
-        // 关键字识别 - 按长度和频率优化
+        // 关键字识别 - 按长度和频率优化；与 token.rs 中的 KEYWORDS 等表一致
         Ok(match ident {
             // 关键字
             "fn" => TokenKind::Fn,
@@ -394,6 +566,7 @@ impl<'a> Lexer<'a> {
             "const" => TokenKind::Const,
             "static" => TokenKind::Static,
             "as" => TokenKind::As,
+            "unsafe" => TokenKind::Unsafe,
 
             // 类型
             "i8" => TokenKind::I8,
@@ -420,100 +593,165 @@ impl<'a> Lexer<'a> {
             "_" => TokenKind::Underscore,
 
             // 普通标识符
-            _ => TokenKind::Ident(ident.to_string()),
+            _ => TokenKind::Ident(self.symbols.intern(ident)),
         })
     }
 
-    // 字符串扫描
-    fn scan_string(&mut self) -> Result<TokenKind, String> {
+    // 字符串扫描；先找到结束的引号，再按 literal.rs 的规则求出内容的值
+    fn scan_string(&mut self) -> Scan {
+        let token = (self.pos, self.line, self.column);
         self.advance(); // 跳过开始的 "
-        let mut string = String::new();
+        let start = self.pos;
 
         while !self.is_eof() && self.current != b'"' {
+            // 转义的字符不会结束字符串
             if self.current == b'\\' {
                 self.advance();
-                if !self.is_eof() {
-                    let escaped = match self.current {
-                        b'n' => '\n',
-                        b'r' => '\r',
-                        b't' => '\t',
-                        b'\\' => '\\',
-                        b'"' => '"',
-                        b'0' => '\0',
-                        c => {
-                            return Err(format!(
-                                "Invalid escape sequence '\\{}' at line {}",
-                                c as char, self.line
-                            ));
-                        }
-                    };
-                    string.push(escaped);
-                    self.advance();
+                if self.is_eof() {
+                    break;
                 }
-            } else {
-                string.push(self.current as char);
-                self.advance();
             }
+            // 跨行的字符串之后的记号从新的一行计算行列号
+            if self.current == b'\n' {
+                self.line += 1;
+                self.column = 0;
+                self.line_start = self.pos + 1;
+            }
+            self.advance();
         }
 
         if self.is_eof() {
-            return Err(format!("Unterminated string at line {}", self.line));
+            return Err((
+                ErrorCode::E0001,
+                format!("Unterminated string at line {}", self.line),
+            ));
         }
 
+        let end = self.pos;
         self.advance(); // 跳过结束的 "
-        Ok(TokenKind::StringLiteral(string))
+        let source = self.source;
+        let text = &source[start..end];
+        let value = match literal::unescape(text) {
+            Ok(value) => value,
+            Err(errors) => {
+                for error in errors {
+                    self.escape_error(token, start, error, "");
+                }
+                text.into()
+            }
+        };
+        Ok(TokenKind::StringLiteral(self.symbols.intern(&value)))
+    }
+
+    // 字面量内容中错误的转义，位置是转义序列本身；`token` 是字面量的起点
+    fn escape_error(
+        &mut self,
+        token: (usize, u32, u32),
+        content: usize,
+        error: EscapeError,
+        context: &str,
+    ) {
+        let range: Range<usize> = content + error.range.start..content + error.range.end;
+        let before = &self.input[token.0..range.start];
+        let (line, column) = match before.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => {
+                let lines = before.iter().filter(|&&b| b == b'\n').count() as u32;
+                (token.1 + lines, (before.len() - newline) as u32)
+            }
+            None => (token.1, token.2 + before.len() as u32),
+        };
+        self.literal_errors.push(LexError {
+            code: ErrorCode::E0003,
+            message: format!("{}{} at line {}", error.message, context, line),
+            span: Span::new(range.start, range.end, line, column),
+        });
     }
 
     #[inline]
-    fn scan_char(&mut self) -> Result<TokenKind, String> {
+    fn scan_char(&mut self) -> Scan {
+        let token = (self.pos, self.line, self.column);
         self.advance(); // 跳过开始的 '
 
-        if self.is_eof() {
-            return Err(format!(
-                "Unterminated character literal at line {}",
-                self.line
+        // `'a'` 是字符，`'a` 后面不是 ' 时是循环标签
+        if (self.current.is_ascii_alphabetic() || self.current == b'_') && self.peek() != b'\'' {
+            let start = self.pos;
+            while self.current.is_ascii_alphanumeric() || self.current == b'_' {
+                self.advance();
+            }
+            // `'ab'` 和 `'é'`（e 加上组合重音）是多个字符的字面量，不是标签
+            if self.current == b'\'' || !self.current.is_ascii() {
+                return self.multi_char_literal();
+            }
+            return Ok(TokenKind::Label(self.text(start, self.pos)));
+        }
+
+        // 字符字面量不能跨行
+        if self.is_eof() || self.current == b'\n' {
+            return Err((
+                ErrorCode::E0002,
+                format!("Unterminated character literal at line {}", self.line),
             ));
         }
 
         let ch = if self.current == b'\\' {
             self.advance();
-            if self.is_eof() {
-                return Err(format!(
-                    "Unterminated character literal at line {}",
-                    self.line
+            if self.is_eof() || self.current == b'\n' {
+                return Err((
+                    ErrorCode::E0002,
+                    format!("Unterminated character literal at line {}", self.line),
                 ));
             }
-            match self.current {
-                b'n' => '\n',
-                b'r' => '\r',
-                b't' => '\t',
-                b'\\' => '\\',
-                b'\'' => '\'',
-                b'0' => '\0',
-                c => {
-                    return Err(format!(
-                        "Invalid escape sequence '\\{}' in character literal at line {}",
-                        c as char, self.line
-                    ));
+            let start = self.pos - 1;
+            let source = self.source;
+            let (ch, len) = match literal::escape_sequence(&source[start..]) {
+                Ok(escaped) => escaped,
+                Err(error) => {
+                    let len = error.range.end;
+                    self.escape_error(token, start, error, " in character literal");
+                    ('\\', len)
                 }
+            };
+            for _ in 1..len {
+                self.advance();
             }
+            ch
         } else {
-            self.current as char
+            // 源码中的多字节字符按 UTF-8 解码为一个 Unicode 标量值
+            let source = self.source;
+            let ch = source[self.pos..].chars().next().unwrap_or_default();
+            for _ in 0..ch.len_utf8() {
+                self.advance();
+            }
+            ch
         };
 
-        self.advance();
-
         if self.current != b'\'' {
-            return Err(format!(
-                "Character literal must be exactly one character at line {}",
-                self.line
-            ));
+            return self.multi_char_literal();
         }
 
         self.advance(); // 跳过结束的 '
         Ok(TokenKind::CharLiteral(ch))
     }
 
+    // 字符字面量中有多于一个字符；同一行中有结束的 ' 时停在它上面，出错后跳过它，
+    // 之后的记号照常扫描
+    fn multi_char_literal(&mut self) -> Scan {
+        let rest = &self.input[self.pos..];
+        let line_end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        if let Some(quote) = rest[..line_end].iter().position(|&b| b == b'\'') {
+            for _ in 0..quote {
+                self.advance();
+            }
+        }
+        Err((
+            ErrorCode::E0004,
+            format!(
+                "Character literal must be exactly one character at line {}",
+                self.line
+            ),
+        ))
+    }
+
     // 跳过空白字符和注释 - 优化的版本
     #[inline]
     fn skip_whitespace_and_comments(&mut self) {
@@ -535,12 +773,15 @@ impl<'a> Lexer<'a> {
                 }
                 b'/' if self.peek() == b'/' => {
                     // 单行注释
+                    let start = (self.pos, self.line, self.column);
                     while !self.is_eof() && self.current != b'\n' {
                         self.advance();
                     }
+                    self.record_comment(start);
                 }
                 b'/' if self.peek() == b'*' => {
                     // 多行注释
+                    let start = (self.pos, self.line, self.column);
                     self.advance(); // /
                     self.advance(); // *
 
@@ -557,12 +798,19 @@ impl<'a> Lexer<'a> {
                         }
                         self.advance();
                     }
+                    self.record_comment(start);
                 }
                 _ => break,
             }
         }
     }
 
+    fn record_comment(&mut self, (start, line, column): (usize, u32, u32)) {
+        if let Some(comments) = &mut self.comments {
+            comments.push(Span::new(start, self.pos, line, column));
+        }
+    }
+
     // 内联的辅助方法
     #[inline]
     fn advance(&mut self) {
@@ -590,6 +838,22 @@ impl<'a> Lexer<'a> {
     fn is_eof(&self) -> bool {
         self.pos >= self.input.len()
     }
+
+    // 从 start 到当前位置的数字，去掉分隔符 `_`；没有分隔符时不分配
+    fn digits(&self, start: usize) -> Cow<'a, str> {
+        let digits = String::from_utf8_lossy(&self.input[start..self.pos]);
+        if digits.contains('_') {
+            Cow::Owned(digits.replace('_', ""))
+        } else {
+            digits
+        }
+    }
+
+    // 驻留一段源码；记号的边界都在字符边界上
+    fn text(&mut self, start: usize, end: usize) -> Symbol {
+        let text = String::from_utf8_lossy(&self.input[start..end]);
+        self.symbols.intern(&text)
+    }
 }
 
 // 实现 Display trait 用于调试
@@ -598,12 +862,14 @@ impl fmt::Display for TokenKind {
         match self {
             // --- 字面量 ---
             TokenKind::IntLiteral(n) => write!(f, "{}", n),
+            TokenKind::SuffixedIntLiteral(n, ty) => write!(f, "{}{}", n, ty),
             TokenKind::BoolLiteral(b) => write!(f, "{}", if *b { "true" } else { "false" }),
             TokenKind::StringLiteral(s) => write!(f, "\"{}\"", s.escape_debug()), // 安全转义
             TokenKind::CharLiteral(c) => write!(f, "'{}'", c.escape_debug()),     // 安全转义
 
             // --- 标识符 ---
             TokenKind::Ident(name) => write!(f, "{}", name),
+            TokenKind::Label(name) => write!(f, "'{}", name),
 
             // --- 关键字 ---
             TokenKind::Fn => write!(f, "fn"),
@@ -626,6 +892,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Const => write!(f, "const"),
             TokenKind::Static => write!(f, "static"),
             TokenKind::As => write!(f, "as"),
+            TokenKind::Unsafe => write!(f, "unsafe"),
 
             // --- 类型关键字 ---
             TokenKind::I8 => write!(f, "i8"),
@@ -656,6 +923,12 @@ impl fmt::Display for TokenKind {
             TokenKind::MinusAssign => write!(f, "-="),
             TokenKind::StarAssign => write!(f, "*="),
             TokenKind::SlashAssign => write!(f, "/="),
+            TokenKind::PercentAssign => write!(f, "%="),
+            TokenKind::BitAndAssign => write!(f, "&="),
+            TokenKind::BitOrAssign => write!(f, "|="),
+            TokenKind::BitXorAssign => write!(f, "^="),
+            TokenKind::LeftShiftAssign => write!(f, "<<="),
+            TokenKind::RightShiftAssign => write!(f, ">>="),
 
             TokenKind::Equal => write!(f, "=="),
             TokenKind::NotEqual => write!(f, "!="),
@@ -694,6 +967,8 @@ impl fmt::Display for TokenKind {
             TokenKind::FatArrow => write!(f, "=>"),
             TokenKind::Question => write!(f, "?"),
             TokenKind::At => write!(f, "@"),
+            TokenKind::Hash => write!(f, "#"),
+            TokenKind::Dollar => write!(f, "$"),
             TokenKind::Underscore => write!(f, "_"),
 
             // --- 特殊 ---
@@ -718,7 +993,6 @@ mod tests {
                 line: 1,
                 column: 1,
             },
-            raw: "fn".to_string(),
         };
         assert_eq!(token.kind, TokenKind::Fn);
     }
@@ -747,7 +1021,7 @@ mod tests {
     fn test_token_display() {
         assert_eq!(format!("{}", TokenKind::Fn), "fn");
         assert_eq!(format!("{}", TokenKind::IntLiteral(42)), "42");
-        assert_eq!(format!("{}", TokenKind::Ident("test".to_string())), "test");
+        assert_eq!(format!("{}", TokenKind::Ident("test".into())), "test");
     }
 
     #[test]
@@ -767,6 +1041,83 @@ mod tests {
         assert_eq!(tokens[8].kind, TokenKind::In);
     }
 
+    // token.rs 中的每个拼写都词法分析为对应的一个记号，`Display` 还原为同样的拼写；
+    // 这里识别的每个关键字都在表中
+    #[test]
+    fn test_token_tables() {
+        use crate::token::{BOOL_LITERALS, KEYWORDS, OPERATORS, PUNCTUATION, TYPE_KEYWORDS};
+        let tables = [
+            KEYWORDS,
+            TYPE_KEYWORDS,
+            BOOL_LITERALS,
+            OPERATORS,
+            PUNCTUATION,
+        ];
+        for (text, kind) in tables.iter().flat_map(|table| table.iter()) {
+            let tokens = Lexer::new(text).tokenize().unwrap();
+            assert_eq!(tokens.len(), 2, "`{}` is not a single token", text);
+            assert_eq!(&tokens[0].kind, kind, "`{}`", text);
+            assert_eq!(kind.to_string(), *text);
+        }
+
+        let recognized = include_str!("lexer.rs")
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix('"'))
+            .filter_map(|line| line.split_once("\" => TokenKind::"))
+            .map(|(text, _)| text);
+        for text in recognized {
+            assert!(
+                tables
+                    .iter()
+                    .any(|table| table.iter().any(|(t, _)| *t == text)),
+                "`{}` is missing from the tables in token.rs",
+                text
+            );
+        }
+    }
+
+    // 表中任意两个或三个运算符、分隔符连写时，词法分析的结果和按表每次取最长前缀的结果相同；
+    // 连写出注释开头（`//`、`/*`）的组合除外
+    #[test]
+    fn test_maximal_munch() {
+        use crate::token::{OPERATORS, PUNCTUATION};
+        let table: Vec<&(&str, TokenKind)> = OPERATORS.iter().chain(PUNCTUATION).collect();
+        let munch = |mut text: &str| {
+            let mut kinds = Vec::new();
+            while !text.is_empty() {
+                let (spelling, kind) = table
+                    .iter()
+                    .filter(|(spelling, _)| text.starts_with(spelling))
+                    .max_by_key(|(spelling, _)| spelling.len())
+                    .expect("every text is made of spellings in the table");
+                kinds.push(kind.clone());
+                text = &text[spelling.len()..];
+            }
+            kinds.push(TokenKind::Eof);
+            kinds
+        };
+        let check = |text: &str| {
+            if text.contains("//") || text.contains("/*") {
+                return;
+            }
+            let kinds: Vec<TokenKind> = Lexer::new(text)
+                .tokenize()
+                .unwrap_or_else(|_| panic!("`{}` does not lex", text))
+                .into_iter()
+                .map(|token| token.kind)
+                .collect();
+            assert_eq!(kinds, munch(text), "`{}`", text);
+        };
+        for (a, _) in &table {
+            for (b, _) in &table {
+                check(&format!("{}{}", a, b));
+                for (c, _) in &table {
+                    check(&format!("{}{}{}", a, b, c));
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_number_parsing() {
         let input = "0 42 123 999";
@@ -2,21 +2,96 @@
 //
 // 这个库包含了 Contractus 编程语言的所有核心组件：
 // - 词法分析器 (Lexer)
-// - 语法分析器 (Parser) - 待实现
-// - 语义分析器 (Semantic Analyzer) - 待实现
-// - 中间表示 (MIR) - 待实现
+// - 字面量 (Literal) - 字符串和字符字面量的转义，词法分析和还原源码共用
+// - 语法分析器 (Parser)
+// - 特性开关 (Features) - 文件开头的 `#![feature(...)]` 开启的实验性语法和 `#![edition(...)]` 选择的版本
+// - 语法描述 (Grammar) - 解析器实现的 EBNF 产生式（`--emit=grammar`）
+// - 语义分析器 (Semantic Analyzer) - 名称解析和字段查找
+// - 内建函数 (Builtins) - 根作用域中的 `print`、`len`、`assert` 及其签名
+// - 编译器认识的函数 (Intrinsics) - 标准库用 `#[intrinsic(...)]` 声明、由后端特殊实现的函数
+// - 宏展开 (Macros) - `macro_rules!` 声明宏在名称解析之前的展开
+// - 格式字符串 (Fmt) - `format!` 和 `println!` 的占位符和格式说明，宏展开、语义分析和两个后端共用
+// - 派生 (Derive) - 为带 `#[derive(...)]` 的结构体和枚举生成函数
+// - 预导入 (Prelude) - 内置的 `Option`、`Result` 和 `?` 的展开
+// - 中间表示 (MIR) - 基本块组成的显式控制流图
+// - 数据布局 (Layout) - 各后端共用的类型大小、对齐、字段偏移和枚举的标签
+// - 解释器 (Interpreter) - 直接对语法树求值
+// - 调试器 (Debugger) - 解释器上的断点、单步执行和变量查看（`contractus debug`）
+// - 调试适配器 (DAP) - 供编辑器使用的 Debug Adapter Protocol 服务（`contractus dap`）
+// - 字节码 (Bytecode) - 由 MIR 编译的紧凑指令流和栈式虚拟机
+// - 链接驱动 (Linker) - 把字节码和运行时静态库链接为可执行文件
+// - 符号修饰 (Mangling) - 各后端共用的函数符号名
+// - 编译驱动 (Driver) - 各阶段组成的流水线，CLI 和嵌入方共用
+// - 资源限制 (Limits) - 嵌入方限制源文件的大小、记号数、语法树节点数和错误数
+// - 增量编译 (Incremental) - 按内容哈希记忆各阶段的查询结果（`target/incremental`）
+// - 符号 (Symbol) - 词法分析器驻留的共享字符串，相同的文本只分配一次
+// - 源文件表 (Source Map) - 读入的文件和 `include!` 拼接的代码的位置
+// - 项目清单 (Manifest) - Contractus.toml 中的包信息、依赖和构建配置
+// - 交互式环境 (REPL) - 基于解释器的读取-求值-输出循环
+// - 代码格式化 (Formatter) - 保留注释的记号流上的格式化
+// - 语法高亮 (Highlight) - 记号的高亮类别，供编辑器插件使用（`--emit=tokens-json`）
+// - 编辑器语法 (Editor Syntax) - 由记号表生成 TextMate 和 Tree-sitter 语法（`contractus gen-syntax`）
+// - 文档测试 (Doc-tests) - 运行文档注释中的代码示例
+// - 基准测试 (Benchmarks) - 在字节码虚拟机中为 `#[bench]` 函数计时
+// - 编译计时 (Timing) - 各阶段的时间和内存（`-Ztime-passes`）
+// - 调试日志 (Logging) - 按模块过滤的日志和各阶段的 span（`CONTRACTUS_LOG`）
+// - 沙箱 (Sandbox) - 在时间、内存和输出的限制下编译运行不受信任的程序
+// - 测试工具 (Testing) - 随机生成语法树，检查还原的源码能解析回同样的结构
+// - C API - 供宿主程序嵌入的 `extern "C"` 接口（include/contractus.h）
 // - 代码生成器 (Code Generator) - 待实现
 
 // 声明模块
 pub mod ast;
+pub mod bench;
+pub mod builtins;
+pub mod bytecode;
+pub mod capi;
+pub mod dap;
+pub mod debugger;
+pub mod derive;
+pub mod diagnostic;
+pub mod doctest;
+pub mod driver;
+pub mod features;
+pub mod fmt;
+pub mod format;
+pub mod grammar;
+pub mod highlight;
+pub mod incremental;
+pub mod interp;
+pub mod intrinsics;
+pub mod layout;
 pub mod lexer;
+pub mod limits;
+pub mod link;
+pub mod literal;
+pub mod log;
+pub mod macros;
+pub mod mangle;
+pub mod manifest;
+pub mod mir;
+pub mod module;
 pub mod parser;
+pub mod prelude;
+pub mod repl;
+pub mod sandbox;
+pub mod sema;
+pub mod source_map;
 pub mod span;
+pub mod symbol;
+pub mod syntax;
+pub mod testing;
+pub mod timing;
 pub mod token;
 
 // 重新导出主要的公共接口
 pub use ast::*;
+pub use diagnostic::{Diagnostic, Level};
+pub use interp::Interpreter;
 pub use lexer::Lexer;
+pub use mir::MirProgram;
+pub use module::{Crate, ModuleLoader};
 pub use parser::{ParseError, Parser};
+pub use sema::SemanticAnalyzer;
 pub use span::Span;
 pub use token::{Token, TokenKind};
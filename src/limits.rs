@@ -0,0 +1,81 @@
+// 资源限制
+// 嵌入编译器的一方（编辑器、在线试用）用 `Compiler::limits` 限制一次编译使用的资源，
+// 超出时报告 E0803 "program too large" 并停下，而不是耗尽内存：
+//
+//     max_file_size   每个源文件的字节数，读入之前按文件大小检查，`include!` 读入的文件也受限制
+//     max_tokens      每个源文件的记号数，词法分析到上限时停下
+//     max_ast_nodes   每个源文件的语法树节点数（条目、语句、类型、模式和表达式的操作数），
+//                     语法分析到上限时停下
+//     max_errors      报告的错误数，多出的错误换成一条说明
+//     max_nesting_depth
+//                     表达式的嵌套层数（一元表达式和运算符、后缀的每一层都算），语法分析到上限时停下。
+//                     之后的阶段递归地处理语法树，一长串 `1 + 1 + ...` 这样很深的表达式会耗尽栈
+//
+// 嵌套层数默认限制为 DEFAULT_NESTING_DEPTH，其他的默认没有限制
+
+use crate::diagnostic::{self, Diagnostic, ErrorCode};
+use crate::span::Span;
+
+/// 默认的表达式嵌套层数的上限。调试构建的语法分析每层括号最多占用约 60 KB 的栈，
+/// 这个层数在主线程 8 MiB 的栈上也能处理
+pub const DEFAULT_NESTING_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileLimits {
+    pub max_file_size: Option<usize>,
+    pub max_tokens: Option<usize>,
+    pub max_ast_nodes: Option<usize>,
+    pub max_errors: Option<usize>,
+    pub max_nesting_depth: Option<usize>,
+}
+
+impl Default for CompileLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: None,
+            max_tokens: None,
+            max_ast_nodes: None,
+            max_errors: None,
+            max_nesting_depth: Some(DEFAULT_NESTING_DEPTH),
+        }
+    }
+}
+
+impl CompileLimits {
+    /// 源文件的大小超出限制时的诊断信息
+    pub fn check_file_size(&self, size: u64) -> Result<(), Box<Diagnostic>> {
+        match self.max_file_size {
+            Some(max) if size > max as u64 => Err(Box::new(too_large(
+                format!(
+                    "the file has {} bytes, more than the limit of {}",
+                    size, max
+                ),
+                Span::new(0, 0, 1, 1),
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// 只保留前 `max_errors` 个错误（至少一个），警告都保留，最后说明省略的错误数
+    pub fn truncate(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let Some(max) = self.max_errors else {
+            return diagnostics;
+        };
+        let max = max.max(1);
+        let (mut kept, omitted) = diagnostic::limit(diagnostics, max);
+        if omitted > 0 {
+            kept.push(too_large(
+                format!("aborting after {} errors, {} more not shown", max, omitted),
+                Span::new(0, 0, 1, 1),
+            ));
+        }
+        kept
+    }
+}
+
+/// "program too large" 错误
+pub fn too_large(message: String, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("program too large: {}", message), span)
+        .with_code(ErrorCode::E0803)
+        .with_help("the compiler was configured with resource limits for this input".to_string())
+}
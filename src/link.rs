@@ -0,0 +1,284 @@
+// Contractus 链接驱动
+// `contractus build` 把程序编译为字节码之后分两步生成可执行文件：
+// 1. 目标文件：字节码作为常量数组写进一个很小的 C 源文件，由系统 C 编译器编译成 .o/.obj；
+//    其中的 `main` 调用运行时入口 contractus_rt_main 执行内嵌的字节码（`--emit=obj` 到此为止）
+// 2. 链接：调用系统链接器，把目标文件和运行时静态库（本库编译成的 libcontractus.a）
+//    以及标准库依赖的系统库链接为可执行文件
+// 平台差别见 target.rs：Unix 和 MinGW 上通过 C 编译器驱动链接，`--linker=lld` 时改用 lld；
+// MSVC 上直接调用 link.exe（或 lld-link）
+// 运行时静态库默认在编译器可执行文件旁边查找；交叉编译时必须用 CONTRACTUS_RUNTIME
+// 指定为目标平台构建的运行时
+// 优化级别传给 C 编译器（O0 时附带调试信息），-Os 时链接器去掉符号表
+
+mod runtime;
+mod target;
+
+pub use runtime::contractus_rt_main;
+pub use target::{Os, Target};
+
+use crate::bytecode::{self, Module};
+use crate::log;
+use crate::mir::transform::OptLevel;
+use crate::timing;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 指定运行时静态库路径的环境变量
+pub const RUNTIME_ENV: &str = "CONTRACTUS_RUNTIME";
+
+#[derive(Debug, Clone)]
+pub struct LinkOptions {
+    pub target: Target,
+    pub linker: Option<String>, // `lld` 或链接器的路径，None 时使用平台默认的链接器
+    pub runtime: Option<PathBuf>, // None 时按 RUNTIME_ENV 或在编译器旁边查找
+    pub opt_level: OptLevel,
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        Self {
+            target: Target::host(),
+            linker: None,
+            runtime: None,
+            opt_level: OptLevel::default(),
+        }
+    }
+}
+
+/// 生成可执行文件，返回实际写入的路径（Windows 上补上 `.exe`）
+pub fn build_executable(
+    module: &Module,
+    output: &Path,
+    options: &LinkOptions,
+) -> Result<PathBuf, String> {
+    let runtime = match &options.runtime {
+        Some(runtime) => runtime.clone(),
+        None => find_runtime(&options.target)?,
+    };
+    if !runtime.is_file() {
+        return Err(format!(
+            "runtime library `{}` does not exist",
+            runtime.display()
+        ));
+    }
+    log::debug!("using runtime library `{}`", runtime.display());
+    let output = executable_path(output, &options.target);
+
+    let work = work_dir()?;
+    let result = (|| {
+        let source = work.join("main.c");
+        let object = work.join(format!("main.{}", options.target.object_suffix()));
+        fs::write(&source, object_source(&bytecode::encode(module)))
+            .map_err(|error| format!("cannot write `{}`: {}", source.display(), error))?;
+        timing::time("object generation", || {
+            execute(compile_command(options, &source, &object), "compiling")
+        })?;
+        timing::time("linking", || {
+            execute(link_command(options, &object, &runtime, &output), "linking")
+        })
+    })();
+    let _ = fs::remove_dir_all(&work);
+    result.map(|()| output)
+}
+
+/// 只做第一步，生成目标文件（`--emit=obj`），返回写入的路径
+pub fn build_object(
+    module: &Module,
+    output: &Path,
+    options: &LinkOptions,
+) -> Result<PathBuf, String> {
+    let work = work_dir()?;
+    let result = (|| {
+        let source = work.join("main.c");
+        fs::write(&source, object_source(&bytecode::encode(module)))
+            .map_err(|error| format!("cannot write `{}`: {}", source.display(), error))?;
+        timing::time("object generation", || {
+            execute(compile_command(options, &source, output), "compiling")
+        })
+    })();
+    let _ = fs::remove_dir_all(&work);
+    result.map(|()| output.to_path_buf())
+}
+
+/// 目标文件的 C 源码：内嵌的字节码和调用运行时的 `main`
+pub fn object_source(bytecode: &[u8]) -> String {
+    let mut source = String::from(
+        "/* generated by `contractus build` */\n\
+         #include <stddef.h>\n\n\
+         int contractus_rt_main(const unsigned char *bytecode, size_t len);\n\n\
+         static const unsigned char BYTECODE[] = {",
+    );
+    for (i, byte) in bytecode.iter().enumerate() {
+        if i % 16 == 0 {
+            source.push_str("\n   ");
+        }
+        let _ = write!(source, " 0x{:02x},", byte);
+    }
+    source.push_str(
+        "\n};\n\n\
+         int main(void) {\n    \
+         return contractus_rt_main(BYTECODE, sizeof BYTECODE);\n\
+         }\n",
+    );
+    source
+}
+
+/// 可执行文件的路径：没有扩展名时按目标平台补上
+pub fn executable_path(output: &Path, target: &Target) -> PathBuf {
+    let suffix = target.exe_suffix();
+    if suffix.is_empty() || output.extension().is_some() {
+        output.to_path_buf()
+    } else {
+        output.with_extension(suffix)
+    }
+}
+
+/// 把 C 源文件编译为目标文件的命令，编译器可以用 CC 环境变量覆盖
+pub fn compile_command(options: &LinkOptions, source: &Path, object: &Path) -> Command {
+    let target = &options.target;
+    if target.msvc {
+        let mut command = Command::new(env::var("CC").unwrap_or_else(|_| "cl".to_string()));
+        command
+            .arg("/nologo")
+            .arg("/c")
+            .args(target.opt_flags(options.opt_level))
+            .arg(source)
+            .arg(format!("/Fo{}", object.display()));
+        return command;
+    }
+    let mut command = Command::new(c_compiler());
+    if !target.is_host() {
+        command.arg(format!("--target={}", target.triple));
+    }
+    command
+        .args(target.opt_flags(options.opt_level))
+        .arg("-c")
+        .arg(source)
+        .arg("-o")
+        .arg(object);
+    command
+}
+
+/// 链接命令
+pub fn link_command(
+    options: &LinkOptions,
+    object: &Path,
+    runtime: &Path,
+    output: &Path,
+) -> Command {
+    let target = &options.target;
+    if target.msvc {
+        let linker = match options.linker.as_deref() {
+            Some("lld") => "lld-link",
+            Some(linker) => linker,
+            None => "link",
+        };
+        let mut command = Command::new(linker);
+        command
+            .arg("/nologo")
+            .arg(format!("/OUT:{}", output.display()))
+            .args(target.link_flags())
+            .args(strip_flags(options))
+            .arg(object)
+            .arg(runtime)
+            .args(target.system_libs());
+        return command;
+    }
+
+    // 通过 C 编译器驱动链接，它知道启动文件和 libc 的位置
+    let mut command = match options.linker.as_deref() {
+        Some("lld") => {
+            let mut command = Command::new(c_compiler());
+            command.arg("-fuse-ld=lld");
+            command
+        }
+        Some(linker) => Command::new(linker),
+        None => Command::new(c_compiler()),
+    };
+    if !target.is_host() {
+        command.arg(format!("--target={}", target.triple));
+    }
+    command
+        .args(target.link_flags())
+        .args(strip_flags(options))
+        .arg(object)
+        .arg(runtime)
+        .arg("-o")
+        .arg(output)
+        .args(target.system_libs());
+    command
+}
+
+fn strip_flags(options: &LinkOptions) -> &'static [&'static str] {
+    if options.opt_level == OptLevel::Os {
+        options.target.strip_flags()
+    } else {
+        &[]
+    }
+}
+
+fn c_compiler() -> String {
+    env::var("CC").unwrap_or_else(|_| "cc".to_string())
+}
+
+/// 查找运行时静态库：先看 RUNTIME_ENV，再看编译器所在目录及其上一级
+/// （`cargo test` 时编译器在 target/debug/deps 中，静态库在 target/debug 中）
+pub fn find_runtime(target: &Target) -> Result<PathBuf, String> {
+    if let Some(path) = env::var_os(RUNTIME_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    if !target.is_host() {
+        return Err(format!(
+            "no runtime library for target `{}`; build the runtime for that target and set {}",
+            target, RUNTIME_ENV
+        ));
+    }
+    let exe = env::current_exe()
+        .map_err(|error| format!("cannot locate the compiler executable: {}", error))?;
+    exe.ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join(target.runtime_name()))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            format!(
+                "cannot find the runtime library `{}` next to `{}`; set {} to its path",
+                target.runtime_name(),
+                exe.display(),
+                RUNTIME_ENV
+            )
+        })
+}
+
+// 中间文件放在临时目录下的独立子目录中，用完删除
+fn work_dir() -> Result<PathBuf, String> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    let dir = env::temp_dir().join(format!("contractus-build-{}-{}", std::process::id(), nanos));
+    fs::create_dir_all(&dir)
+        .map_err(|error| format!("cannot create `{}`: {}", dir.display(), error))?;
+    Ok(dir)
+}
+
+fn execute(mut command: Command, action: &str) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    log::info!("running {:?}", command);
+    let output = command
+        .output()
+        .map_err(|error| format!("{} failed: cannot run `{}`: {}", action, program, error))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let mut message = format!("{} with `{}` failed: {}", action, program, output.status);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        message.push('\n');
+        message.push_str(stderr.trim_end());
+    }
+    Err(message)
+}
@@ -0,0 +1,37 @@
+// 可执行文件的运行时入口
+// `contractus build` 生成的目标文件只包含内嵌的字节码和调用这里的 `main`，
+// 解码、虚拟机和内建函数都来自本库编译成的静态库。
+// 生成的程序可以使用 `io`、`env` 和 `time` 模块，`io::args()` 是进程的命令行参数（不含程序名）
+
+use crate::bytecode::{self, Vm};
+use crate::interp::Host;
+use std::io;
+
+/// 解码并执行内嵌的字节码，返回进程的退出码（程序调用 `io::exit` 时是它给出的值，
+/// 否则是 `main() -> i32` 的返回值）
+///
+/// # Safety
+///
+/// `code` 必须指向 `len` 个可读的字节
+#[no_mangle]
+pub unsafe extern "C" fn contractus_rt_main(code: *const u8, len: usize) -> i32 {
+    let bytes = std::slice::from_raw_parts(code, len);
+    let module = match bytecode::decode(bytes) {
+        Ok(module) => module,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return 1;
+        }
+    };
+    let mut vm = Vm::new(&module, io::stdout());
+    vm.set_host(Host::unrestricted(std::env::args().skip(1).collect()));
+    match vm.run_main() {
+        Ok(_) => vm.exit_code().unwrap_or(0),
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            1
+        }
+    }
+}
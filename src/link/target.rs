@@ -0,0 +1,178 @@
+// 目标平台
+// 只区分链接时有差别的部分：操作系统决定可执行文件后缀、系统库和链接器风格，
+// Windows 上再按 ABI 区分 MSVC 工具链和 MinGW（GNU）工具链
+// 目标三元组的格式为 `<arch>-<vendor>-<os>[-<env>]`，例如 `x86_64-unknown-linux-gnu`
+// WebAssembly 只支持 WASI（`wasm32-unknown-wasi`），生成的 .wasm 模块由 WASI 运行时执行
+
+use crate::mir::transform::OptLevel;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Os {
+    Linux,
+    MacOs,
+    Windows,
+    Wasi,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub triple: String,
+    pub arch: String,
+    pub os: Os,
+    pub msvc: bool, // Windows 上使用 MSVC 工具链
+}
+
+impl Target {
+    /// 编译器自身运行的平台
+    pub fn host() -> Target {
+        let (os, vendor, suffix) = match std::env::consts::OS {
+            "macos" => (Os::MacOs, "apple", "darwin"),
+            "windows" if cfg!(target_env = "msvc") => (Os::Windows, "pc", "windows-msvc"),
+            "windows" => (Os::Windows, "pc", "windows-gnu"),
+            _ => (Os::Linux, "unknown", "linux-gnu"),
+        };
+        let arch = std::env::consts::ARCH;
+        Target {
+            triple: format!("{}-{}-{}", arch, vendor, suffix),
+            arch: arch.to_string(),
+            os,
+            msvc: cfg!(target_env = "msvc"),
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        *self == Target::host()
+    }
+
+    /// 可执行文件的扩展名
+    pub fn exe_suffix(&self) -> &'static str {
+        match self.os {
+            Os::Windows => "exe",
+            Os::Wasi => "wasm",
+            Os::Linux | Os::MacOs => "",
+        }
+    }
+
+    pub fn object_suffix(&self) -> &'static str {
+        if self.msvc {
+            "obj"
+        } else {
+            "o"
+        }
+    }
+
+    /// 运行时静态库的文件名
+    pub fn runtime_name(&self) -> &'static str {
+        if self.msvc {
+            "contractus.lib"
+        } else {
+            "libcontractus.a"
+        }
+    }
+
+    /// 去掉运行时中没有用到的代码（主要是编译器前端）
+    pub fn link_flags(&self) -> &'static [&'static str] {
+        match (self.os, self.msvc) {
+            (_, true) => &["/OPT:REF"],
+            (Os::MacOs, _) => &["-Wl,-dead_strip"],
+            (Os::Linux | Os::Windows | Os::Wasi, false) => &["-Wl,--gc-sections"],
+        }
+    }
+
+    /// C 编译器的优化参数；MSVC 的 /O1 是为大小优化
+    pub fn opt_flags(&self, level: OptLevel) -> &'static [&'static str] {
+        match (level, self.msvc) {
+            (OptLevel::O0, false) => &["-O0", "-g"],
+            (OptLevel::O1, false) => &["-O1"],
+            (OptLevel::O2, false) => &["-O2"],
+            (OptLevel::Os, false) => &["-Os"],
+            (OptLevel::O0, true) => &["/Od", "/Zi"],
+            (OptLevel::O1 | OptLevel::O2, true) => &["/O2"],
+            (OptLevel::Os, true) => &["/O1"],
+        }
+    }
+
+    /// 去掉可执行文件中符号表的链接参数（-Os）
+    pub fn strip_flags(&self) -> &'static [&'static str] {
+        match (self.os, self.msvc) {
+            (_, true) => &["/DEBUG:NONE"],
+            (Os::MacOs, _) => &["-Wl,-S", "-Wl,-x"],
+            (Os::Linux | Os::Windows | Os::Wasi, false) => &["-s"],
+        }
+    }
+
+    /// 运行时（Rust 标准库）依赖的系统库
+    pub fn system_libs(&self) -> &'static [&'static str] {
+        match (self.os, self.msvc) {
+            (Os::Linux, _) => &[
+                "-lgcc_s",
+                "-lutil",
+                "-lrt",
+                "-lpthread",
+                "-lm",
+                "-ldl",
+                "-lc",
+            ],
+            (Os::MacOs, _) => &["-lSystem", "-lc", "-lm"],
+            // wasi-libc 由 C 编译器自动链接
+            (Os::Wasi, _) => &[],
+            (Os::Windows, false) => &[
+                "-lkernel32",
+                "-ladvapi32",
+                "-lntdll",
+                "-luserenv",
+                "-lws2_32",
+                "-lbcrypt",
+            ],
+            (Os::Windows, true) => &[
+                "kernel32.lib",
+                "advapi32.lib",
+                "ntdll.lib",
+                "userenv.lib",
+                "ws2_32.lib",
+                "bcrypt.lib",
+                "/defaultlib:msvcrt",
+            ],
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(triple: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = triple.split('-').collect();
+        if parts.len() < 3 || parts.iter().any(|part| part.is_empty()) {
+            return Err(format!(
+                "invalid target `{}`; expected `<arch>-<vendor>-<os>[-<env>]`",
+                triple
+            ));
+        }
+        let os = match parts[2] {
+            "linux" => Os::Linux,
+            "darwin" | "macos" => Os::MacOs,
+            "windows" => Os::Windows,
+            "wasi" | "wasip1" | "wasip2" => Os::Wasi,
+            other => {
+                return Err(format!(
+                    "unsupported target operating system `{}` in `{}`",
+                    other, triple
+                ))
+            }
+        };
+        Ok(Target {
+            triple: triple.to_string(),
+            arch: parts[0].to_string(),
+            os,
+            msvc: os == Os::Windows && parts.get(3) == Some(&"msvc"),
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.triple)
+    }
+}
@@ -0,0 +1,152 @@
+// 字符串和字符字面量的转义
+// 字面量中的 `\` 开始一个转义序列，其他字符（包括多字节字符和字符串中的换行）原样保留：
+//   `\n` 换行  `\r` 回车  `\t` 制表符  `\\` 反斜杠  `\0` 空字符  `\"` 双引号  `\'` 单引号
+//   `\u{...}` 1 到 6 位十六进制数字（可以用 `_` 分隔）给出的 Unicode 标量值，
+//   代理项（D800 到 DFFF）和超过 10FFFF 的值不是字符
+// 两种引号在字符串和字符字面量中都可以转义。其他转义都是错误（E0003），
+// 错误的位置是转义序列本身，一个字面量中的所有错误转义都会报告。
+// 词法分析器用 unescape 求出字面量的值，还原源码时用 escape 写出，两者互逆；
+// 以后的字节字符串使用同样的转义
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// 错误的转义序列，`range` 是它在字面量内容中的字节范围
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscapeError {
+    pub message: String,
+    pub range: Range<usize>,
+}
+
+/// 字面量内容（不含引号）的值；没有转义时不复制
+pub fn unescape(text: &str) -> Result<Cow<'_, str>, Vec<EscapeError>> {
+    if !text.contains('\\') {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut value = String::with_capacity(text.len());
+    let mut errors = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = text[pos..].find('\\') {
+        value.push_str(&text[pos..pos + offset]);
+        pos += offset;
+        match escape_sequence(&text[pos..]) {
+            Ok((c, len)) => {
+                value.push(c);
+                pos += len;
+            }
+            Err(error) => {
+                pos += error.range.end;
+                errors.push(EscapeError {
+                    message: error.message,
+                    range: error.range.start + pos - error.range.end..pos,
+                });
+            }
+        }
+    }
+    value.push_str(&text[pos..]);
+    if errors.is_empty() {
+        Ok(Cow::Owned(value))
+    } else {
+        Err(errors)
+    }
+}
+
+/// `text` 开头的转义序列（以 `\` 开始）表示的字符和它的字节数
+pub fn escape_sequence(text: &str) -> Result<(char, usize), EscapeError> {
+    debug_assert!(text.starts_with('\\'));
+    let Some(c) = text[1..].chars().next() else {
+        return Err(EscapeError {
+            message: "Unterminated escape sequence".to_string(),
+            range: 0..1,
+        });
+    };
+    let len = 1 + c.len_utf8();
+    let value = match c {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '\\' => '\\',
+        '0' => '\0',
+        '"' => '"',
+        '\'' => '\'',
+        'u' => return unicode_escape(text),
+        c => {
+            return Err(EscapeError {
+                message: format!("Invalid escape sequence '\\{}'", c),
+                range: 0..len,
+            })
+        }
+    };
+    Ok((value, len))
+}
+
+// `\u{...}`，`text` 以 `\u` 开始
+fn unicode_escape(text: &str) -> Result<(char, usize), EscapeError> {
+    let error = |message: String, len: usize| EscapeError {
+        message,
+        range: 0..len,
+    };
+    let bytes = text.as_bytes();
+    if bytes.get(2) != Some(&b'{') {
+        let message = "Invalid unicode escape: expected '{' after '\\u'".to_string();
+        return Err(error(message, 2));
+    }
+    let mut len = 3;
+    let mut value: u32 = 0;
+    let mut digits = 0;
+    loop {
+        match bytes.get(len) {
+            Some(b'}') => break,
+            Some(b'_') => {}
+            Some(&b) if b.is_ascii_hexdigit() => {
+                digits += 1;
+                if digits > 6 {
+                    let message = "Invalid unicode escape: at most 6 hex digits".to_string();
+                    return Err(error(message, len + 1));
+                }
+                value = value * 16 + (b as char).to_digit(16).unwrap_or(0);
+            }
+            None | Some(b'"' | b'\'' | b'\n') => {
+                let message = "Unterminated unicode escape: expected '}'".to_string();
+                return Err(error(message, len));
+            }
+            Some(_) => {
+                let c = text[len..].chars().next().unwrap_or_default();
+                let message = format!("Invalid character '{}' in unicode escape", c);
+                return Err(error(message, len + c.len_utf8()));
+            }
+        }
+        len += 1;
+    }
+    len += 1; // 跳过 }
+    if digits == 0 {
+        let message = "Empty unicode escape '\\u{}'".to_string();
+        return Err(error(message, len));
+    }
+    match char::from_u32(value) {
+        Some(c) => Ok((c, len)),
+        None => Err(error(
+            format!(
+                "Invalid unicode escape '{}': not a Unicode scalar value",
+                &text[..len]
+            ),
+            len,
+        )),
+    }
+}
+
+/// 把字符写成以 `quote` 为引号的字面量中的形式
+pub fn escape(out: &mut String, c: char, quote: char) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\\' => out.push_str("\\\\"),
+        '\0' => out.push_str("\\0"),
+        c if c == quote => {
+            out.push('\\');
+            out.push(c);
+        }
+        c => out.push(c),
+    }
+}
@@ -0,0 +1,304 @@
+// 编译器的调试日志
+// 日志写到标准错误，用于在用户的机器上排查编译器的问题。每条日志有级别和目标
+// （发出日志的模块路径，如 `contractus::sema`）。过滤规则的写法和 `CONTRACTUS_LOG` 相同：
+// 逗号分隔的若干条，每条是 `级别`、`目标` 或 `目标=级别`，目标匹配它自己和它的子模块，
+// 匹配的目标最长的一条生效，例如 `warn,contractus::mir=trace`。
+//
+// 编译器的各个阶段（`timing::time`）同时是日志的 span：进入时输出阶段名，结束时输出耗时，
+// 内层阶段的日志缩进。目标取调用 `timing::time` 的模块，默认的 `warn` 级别下不输出
+
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// 环境变量，值为过滤规则
+pub const ENV: &str = "CONTRACTUS_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    const ALL: [Level; 5] = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+// `off` 表示不输出，解析为 None
+fn parse_level(text: &str) -> Option<Option<Level>> {
+    if text.eq_ignore_ascii_case("off") {
+        return Some(None);
+    }
+    Level::ALL
+        .into_iter()
+        .find(|level| text.eq_ignore_ascii_case(level.as_str()))
+        .map(Some)
+}
+
+/// 按目标和级别过滤日志
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    default: Option<Level>,
+    directives: Vec<(String, Option<Level>)>, // 目标和它的最高级别
+}
+
+impl Filter {
+    /// 所有目标都输出到 `level` 为止的日志，None 表示都不输出
+    pub fn new(level: Option<Level>) -> Self {
+        Self {
+            default: level,
+            directives: Vec::new(),
+        }
+    }
+
+    /// 在当前的规则上加上 `CONTRACTUS_LOG` 写法的规则，后加的同名目标覆盖先加的
+    pub fn add_directives(&mut self, text: &str) -> Result<(), String> {
+        for directive in text.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (target, level) = match directive.split_once('=') {
+                Some((target, level)) => {
+                    let level = parse_level(level.trim()).ok_or_else(|| {
+                        format!("invalid log level `{}` in `{}`", level.trim(), directive)
+                    })?;
+                    (Some(target.trim()), level)
+                }
+                // 单独的级别设置默认值，否则是输出所有级别的目标
+                None => match parse_level(directive) {
+                    Some(level) => (None, level),
+                    None => (Some(directive), Some(Level::Trace)),
+                },
+            };
+            match target {
+                None => self.default = level,
+                Some(target) if !is_target(target) => {
+                    return Err(format!("invalid log target `{}`", target));
+                }
+                Some(target) => {
+                    self.directives.retain(|(t, _)| t != target);
+                    self.directives.push((target.to_string(), level));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        self.level_for(target).is_some_and(|max| level <= max)
+    }
+
+    fn level_for(&self, target: &str) -> Option<Level> {
+        self.directives
+            .iter()
+            .filter(|(prefix, _)| matches_target(prefix, target))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    // 所有目标中最高的级别，低于它的日志不用查找规则
+    fn max_level(&self) -> Option<Level> {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// 没有给出默认级别时为 `warn`
+    fn from_str(text: &str) -> Result<Self, String> {
+        let mut filter = Filter::new(Some(Level::Warn));
+        filter.add_directives(text)?;
+        Ok(filter)
+    }
+}
+
+fn is_target(target: &str) -> bool {
+    target
+        .split("::")
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_'))
+}
+
+// `contractus::mir` 匹配 `contractus::mir` 和 `contractus::mir::transform`，不匹配 `contractus::mirror`
+fn matches_target(prefix: &str, target: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+// 当前的规则；MAX_LEVEL 是它的最高级别（0 表示都不输出），用于快速跳过
+static FILTER: RwLock<Option<Filter>> = RwLock::new(None);
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(0);
+static START: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// 设置过滤规则，之后的日志输出到标准错误
+pub fn init(filter: Filter) {
+    START.get_or_init(Instant::now);
+    MAX_LEVEL.store(
+        filter.max_level().map_or(0, |level| level as usize),
+        Ordering::Relaxed,
+    );
+    *FILTER.write().unwrap_or_else(|e| e.into_inner()) = Some(filter);
+}
+
+pub fn enabled(target: &str, level: Level) -> bool {
+    if level as usize > MAX_LEVEL.load(Ordering::Relaxed) {
+        return false;
+    }
+    FILTER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|filter| filter.enabled(target, level))
+}
+
+/// 日志宏调用的入口
+pub fn log(level: Level, target: &str, message: fmt::Arguments<'_>) {
+    if enabled(target, level) {
+        let depth = DEPTH.with(Cell::get);
+        write_line(&format_line(elapsed(), level, target, depth, message));
+    }
+}
+
+fn elapsed() -> Duration {
+    START.get().map_or(Duration::ZERO, Instant::elapsed)
+}
+
+/// 一行日志：从 `init` 开始经过的时间、级别、目标，消息按所在 span 的层数缩进
+pub fn format_line(
+    elapsed: Duration,
+    level: Level,
+    target: &str,
+    depth: usize,
+    message: fmt::Arguments<'_>,
+) -> String {
+    format!(
+        "{:>10.3}ms {:<5} {}: {}{}",
+        elapsed.as_secs_f64() * 1000.0,
+        level,
+        target,
+        "  ".repeat(depth),
+        message
+    )
+}
+
+fn write_line(line: &str) {
+    let _ = writeln!(io::stderr().lock(), "{}", line);
+}
+
+/// 进入源文件 `file` 中的一个 span：在 debug 级别输出名字，之后的日志缩进一层；
+/// 返回的值被丢弃时退出 span，输出耗时
+pub fn enter(file: &str, name: &'static str) -> Entered {
+    if Level::Debug as usize > MAX_LEVEL.load(Ordering::Relaxed) {
+        return Entered { span: None };
+    }
+    let target = target_of(file);
+    if !enabled(&target, Level::Debug) {
+        return Entered { span: None };
+    }
+    log(Level::Debug, &target, format_args!("{}", name));
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+    Entered {
+        span: Some((target, name, Instant::now())),
+    }
+}
+
+pub struct Entered {
+    span: Option<(String, &'static str, Instant)>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        if let Some((target, name, start)) = self.span.take() {
+            DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+            let time = start.elapsed().as_secs_f64() * 1000.0;
+            log(
+                Level::Debug,
+                &target,
+                format_args!("{}: finished in {:.3}ms", name, time),
+            );
+        }
+    }
+}
+
+/// 源文件路径对应的目标：`src/mir/transform.rs` → `contractus::mir::transform`
+pub fn target_of(file: &str) -> String {
+    let path = file.replace('\\', "/");
+    let path = path.strip_prefix("src/").unwrap_or(&path);
+    let path = path.strip_suffix(".rs").unwrap_or(path);
+    match path {
+        "lib" | "main" => "contractus".to_string(),
+        _ => format!("contractus::{}", path.replace('/', "::")),
+    }
+}
+
+/// `error!("...")` 等宏按级别输出日志，目标是调用所在的模块
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Error, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Warn, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Info, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Debug, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Trace, module_path!(), format_args!($($arg)+))
+    };
+}
+
+pub use crate::{debug, error, info, trace, warn};
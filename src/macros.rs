@@ -0,0 +1,1630 @@
+// 声明宏 `macro_rules!` 的展开
+// 宏定义在模块顶层，整个模块中可见（与定义的位置无关），其他模块看不到：
+//
+//     macro_rules! max {
+//         ($a:expr) => { $a };
+//         ($a:expr, $($rest:expr),+) => { { let x = $a; let y = max!($($rest),+); if x > y { x } else { y } } };
+//     }
+//
+// 调用 `name!(...)`、`name![...]` 或 `name!{...}` 可以出现在表达式、语句和条目的位置。
+// 语法分析之后、名称解析之前展开：依次尝试每条规则，用第一条匹配整个输入的规则的
+// 模板替换调用，再按调用所在的位置把结果解析为表达式、一组语句或一组条目，
+// 其中的宏调用继续展开，嵌套的层数有上限。
+// 模式中 `$name:kind` 匹配一个语法片段，kind 是 expr、ident、ty、pat、literal、block 或 tt；
+// `$( ... ) sep? op` 匹配重复，op 是 `*`（零次以上）、`+`（一次以上）或 `?`（至多一次）。
+// 代入模板时多个记号组成的 expr 片段加上括号，`$a * 2` 不会因为优先级改变含义。
+// 卫生性：模板中由 `let`、`for`、match 分支和闭包参数绑定的变量改为新名字，
+// 既不会遮蔽调用处的变量，调用处的代码也看不到它们；通过 `$name` 传入的标识符保持原样。
+// 新名字的形式是 `x__1`，避开源码中出现过的所有标识符，展开的结果仍然是合法的源码。
+// 内建的 `include!("file")` 把文件的源码拼接到调用的位置，`include_str!` 和 `include_bytes!`
+// 把文件的内容嵌入为字符串字面量和 `u8` 数组字面量。路径相对于写着调用的文件，
+// 由源文件表（`SourceMap`）解析和读取。内建的 `format!("x = {:>6.2}", x)` 按格式字符串（fmt.rs）
+// 拼接出字符串，`println!` 再把它交给 `print` 输出一行；同名的 `macro_rules!` 宏优先
+
+use crate::ast::*;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::fmt::{self, Argument, Piece};
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+use crate::source_map::{FileId, SourceMap};
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::token::{Token, TokenKind, TokenTree};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+
+/// 宏调用嵌套展开的层数上限
+pub const RECURSION_LIMIT: usize = 64;
+
+/// 读取文件和格式化字符串的内建宏
+pub const BUILTIN_MACROS: [&str; 5] = [
+    "include",
+    "include_str",
+    "include_bytes",
+    "format",
+    "println",
+];
+
+/// 模式中的 `$name:kind` 匹配的语法片段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fragment {
+    Expr,
+    Ident,
+    Ty,
+    Pat,
+    Literal,
+    Block,
+    Tt, // 单个记号树
+}
+
+impl Fragment {
+    const NAMES: [(&'static str, Fragment); 7] = [
+        ("expr", Fragment::Expr),
+        ("ident", Fragment::Ident),
+        ("ty", Fragment::Ty),
+        ("pat", Fragment::Pat),
+        ("literal", Fragment::Literal),
+        ("block", Fragment::Block),
+        ("tt", Fragment::Tt),
+    ];
+
+    fn parse(name: &str) -> Option<Fragment> {
+        Self::NAMES
+            .iter()
+            .find(|(fragment, _)| *fragment == name)
+            .map(|(_, fragment)| *fragment)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repetition {
+    ZeroOrMore, // *
+    OneOrMore,  // +
+    ZeroOrOne,  // ?
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Token(TokenKind),
+    Group(TokenKind, Vec<Matcher>), // 左定界符和其中的模式
+    Var(String, Fragment),
+    Repeat(Vec<Matcher>, Option<TokenKind>, Repetition),
+}
+
+#[derive(Debug)]
+enum Template {
+    Token(Token, Rename),
+    Group(Token, Vec<Template>, Token),
+    Var(String, Span),
+    Repeat(Vec<Template>, Option<Token>, Span), // 模板中的重复次数由元变量决定
+}
+
+// 模板中的标识符是否是模板自己绑定的变量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rename {
+    No,
+    Yes,
+    Shorthand, // 结构体字面量或模式的简写字段 `Point { x }`，展开为 `x: x__1`
+}
+
+#[derive(Debug)]
+struct Rule {
+    matchers: Vec<Matcher>,
+    template: Vec<Template>,
+    pattern: Vec<TokenTree>, // 报告没有规则匹配时列出
+}
+
+#[derive(Debug)]
+struct Macro {
+    rules: Vec<Rule>,
+}
+
+// 元变量匹配到的记号树，在 n 层重复之内时是 n 层嵌套的 Many
+#[derive(Debug, Clone)]
+enum Binding {
+    One(Vec<TokenTree>, Fragment),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+/// 展开程序中的所有宏调用。`tokens` 是程序的记号，卫生性改名时避开其中的标识符。
+/// 程序不属于任何文件，不能使用 `include!` 系列宏
+pub fn expand(program: Program, tokens: &[Token]) -> Result<Program, Vec<Diagnostic>> {
+    let mut expander = Expander::new(&program, tokens);
+    let items = expander.items(program.items);
+    expander.finish(Program {
+        attributes: program.attributes,
+        items,
+        span: program.span,
+    })
+}
+
+/// 展开源文件表中的文件 `file` 的宏调用，`include!` 系列宏相对于它读取文件
+pub fn expand_file(
+    program: Program,
+    tokens: &[Token],
+    sources: &mut SourceMap,
+    file: FileId,
+) -> Result<Program, Vec<Diagnostic>> {
+    let mut expander = Expander::new(&program, tokens);
+    expander.sources = Some(sources);
+    expander.files.push(file);
+    let items = expander.items(program.items);
+    expander.finish(Program {
+        attributes: program.attributes,
+        items,
+        span: program.span,
+    })
+}
+
+/// 用 `program` 中定义的宏展开一组语句，用于 REPL 的输入
+pub fn expand_statements(
+    program: &Program,
+    statements: Vec<Statement>,
+) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+    let mut expander = Expander::new(program, &[]);
+    let mut expanded = Vec::new();
+    let count = statements.len();
+    for (i, statement) in statements.into_iter().enumerate() {
+        expander.statement(statement, i + 1 == count, &mut expanded);
+    }
+    expander.finish(expanded)
+}
+
+struct Expander<'a> {
+    macros: HashMap<String, Option<Rc<Macro>>>, // 定义有错的宏为 None，调用时不再报告
+    reserved: HashSet<Symbol>,                  // 源码中出现过的标识符
+    fresh: usize,
+    depth: usize, // 正在展开的嵌套层数
+    overflowed: bool,
+    sources: Option<&'a mut SourceMap>,
+    files: Vec<FileId>,       // 正在展开的文件链，最后一个是相对路径的起点
+    included: Option<FileId>, // 刚读入、还没有进入的 `include!` 文件
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Expander<'a> {
+    fn new(program: &Program, tokens: &[Token]) -> Self {
+        let reserved = tokens
+            .iter()
+            .filter_map(|token| match &token.kind {
+                TokenKind::Ident(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut expander = Self {
+            macros: HashMap::new(),
+            reserved,
+            fresh: 0,
+            depth: 0,
+            overflowed: false,
+            sources: None,
+            files: Vec::new(),
+            included: None,
+            errors: Vec::new(),
+        };
+        for item in &program.items {
+            if let Item::MacroRules(def) = item {
+                expander.define(def);
+            }
+        }
+        expander
+    }
+
+    fn finish<T>(self, result: T) -> Result<T, Vec<Diagnostic>> {
+        match self.errors.is_empty() {
+            true => Ok(result),
+            false => Err(self.errors),
+        }
+    }
+
+    fn define(&mut self, def: &MacroDef) {
+        if self.macros.contains_key(&def.name) {
+            let error = Diagnostic::error(
+                format!("the macro `{}` is defined multiple times", def.name),
+                def.span,
+            );
+            self.errors.push(error.with_code(ErrorCode::E0104));
+            return;
+        }
+        let mut rules = Vec::new();
+        for rule in &def.rules {
+            match compile_rule(rule) {
+                Ok(rule) => rules.push(rule),
+                Err(error) => self.errors.push(*error),
+            }
+        }
+        if def.rules.is_empty() {
+            let error =
+                Diagnostic::error(format!("the macro `{}` has no rules", def.name), def.span);
+            self.errors.push(error.with_code(ErrorCode::E0104));
+        }
+        let compiled =
+            (rules.len() == def.rules.len() && !rules.is_empty()).then(|| Rc::new(Macro { rules }));
+        self.macros.insert(def.name.clone(), compiled);
+    }
+
+    // ---- 遍历语法树 ----
+
+    fn items(&mut self, items: Vec<Item>) -> Vec<Item> {
+        let mut expanded = Vec::new();
+        for item in items {
+            self.item(item, &mut expanded);
+        }
+        expanded
+    }
+
+    fn item(&mut self, mut item: Item, out: &mut Vec<Item>) {
+        match &mut item {
+            Item::MacroCall(call) => {
+                let parse = |parser: &mut Parser| parser.parse().map(|program| program.items);
+                if let Some(items) = self.expand(call, parse) {
+                    let entered = self.enter();
+                    for item in items {
+                        self.item(item, out);
+                    }
+                    self.leave(entered);
+                }
+                return;
+            }
+            // 顶层的定义已经收集过，展开得到的定义从这里开始可用
+            Item::MacroRules(def) if self.depth > 0 => self.define(def),
+            Item::Function(function) => {
+                for contract in &mut function.contracts {
+                    self.expr(&mut contract.condition);
+                }
+                self.block(&mut function.body);
+            }
+            Item::Struct(def) => {
+                for contract in &mut def.invariants {
+                    self.expr(&mut contract.condition);
+                }
+            }
+            Item::Const(def) => self.expr(&mut def.value),
+            Item::Static(def) => self.expr(&mut def.value),
+            Item::MacroRules(_) | Item::Enum(_) | Item::Import(_) | Item::Export(_) => {}
+        }
+        out.push(item);
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        let statements = std::mem::take(&mut block.statements);
+        let count = statements.len();
+        for (i, statement) in statements.into_iter().enumerate() {
+            self.statement(statement, i + 1 == count, &mut block.statements);
+        }
+    }
+
+    fn if_else(
+        &mut self,
+        cond: &mut Expr,
+        then_block: &mut Block,
+        else_block: &mut Option<ElseBranch>,
+    ) {
+        self.expr(cond);
+        self.block(then_block);
+        match else_block {
+            Some(ElseBranch::Block(block)) => self.block(block),
+            Some(ElseBranch::If(nested)) => {
+                let nested = &mut **nested;
+                self.if_else(
+                    &mut nested.cond,
+                    &mut nested.then_block,
+                    &mut nested.else_block,
+                )
+            }
+            None => {}
+        }
+    }
+
+    // 语句位置的宏调用展开为一组语句；代码块结尾没有分号的调用是代码块的值，展开为表达式
+    fn statement(&mut self, mut statement: Statement, tail: bool, out: &mut Vec<Statement>) {
+        if let Statement::Expr(ExprStmt {
+            expr: Expr::MacroCall(call),
+            semicolon,
+            span,
+        }) = &statement
+        {
+            if tail && !semicolon {
+                if let Some(mut expr) = self.expand(call, Parser::parse_standalone_expression) {
+                    let entered = self.enter();
+                    self.expr(&mut expr);
+                    self.leave(entered);
+                    out.push(Statement::Expr(ExprStmt {
+                        expr,
+                        semicolon: false,
+                        span: *span,
+                    }));
+                }
+                return;
+            }
+            if let Some(mut statements) = self.expand(call, Parser::parse_statements) {
+                // `m!(...);` 展开为一个表达式时仍然带分号
+                if let (true, Some(Statement::Expr(last))) = (*semicolon, statements.last_mut()) {
+                    last.semicolon = true;
+                }
+                let entered = self.enter();
+                for statement in statements {
+                    self.statement(statement, false, out);
+                }
+                self.leave(entered);
+            }
+            return;
+        }
+
+        match &mut statement {
+            Statement::Let(stmt) => {
+                self.optional(stmt.init.as_mut());
+                if let Some(block) = &mut stmt.else_block {
+                    self.block(block);
+                }
+            }
+            Statement::Expr(stmt) => self.expr(&mut stmt.expr),
+            Statement::Return(stmt) => self.optional(stmt.expr.as_mut()),
+            Statement::If(stmt) => {
+                self.if_else(&mut stmt.cond, &mut stmt.then_block, &mut stmt.else_block)
+            }
+            Statement::While(stmt) => {
+                self.expr(&mut stmt.cond);
+                for contract in &mut stmt.invariants {
+                    self.expr(&mut contract.condition);
+                }
+                self.block(&mut stmt.body);
+            }
+            Statement::For(stmt) => {
+                self.expr(&mut stmt.iterable);
+                for contract in &mut stmt.invariants {
+                    self.expr(&mut contract.condition);
+                }
+                self.block(&mut stmt.body);
+            }
+            Statement::Match(stmt) => {
+                self.expr(&mut stmt.expr);
+                self.arms(&mut stmt.arms);
+            }
+            Statement::Break(stmt) => self.optional(stmt.expr.as_mut()),
+            Statement::Continue(_) => {}
+            Statement::Block(block) => self.block(block),
+        }
+        out.push(statement);
+    }
+
+    fn arms(&mut self, arms: &mut [MatchArm]) {
+        for arm in arms {
+            self.optional(arm.guard.as_mut());
+            self.expr(&mut arm.body);
+        }
+    }
+
+    fn optional(&mut self, expr: Option<&mut Expr>) {
+        if let Some(expr) = expr {
+            self.expr(expr);
+        }
+    }
+
+    fn exprs(&mut self, exprs: &mut [Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::MacroCall(call) => {
+                if let Some(mut expanded) = self.expand(call, Parser::parse_standalone_expression) {
+                    let entered = self.enter();
+                    self.expr(&mut expanded);
+                    self.leave(entered);
+                    *expr = expanded;
+                }
+            }
+            Expr::Literal(..) | Expr::Ident(..) | Expr::Path(..) | Expr::Continue(..) => {}
+            Expr::Binary(_, left, right, _, _)
+            | Expr::Assign(left, right, _)
+            | Expr::CompoundAssign(_, left, right, _, _)
+            | Expr::IndexAccess(left, right, _) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Range(start, end, _, _) => {
+                for bound in start.iter_mut().chain(end) {
+                    self.expr(bound);
+                }
+            }
+            Expr::Unary(_, inner, _, _)
+            | Expr::Turbofish(inner, _, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _)
+            | Expr::Closure(_, _, inner, _) => self.expr(inner),
+            Expr::Call(callee, args, _, _) | Expr::MethodCall(callee, _, args, _, _) => {
+                self.expr(callee);
+                self.exprs(args);
+            }
+            Expr::StructLit(_, fields, _) => {
+                for (_, value) in fields {
+                    self.expr(value);
+                }
+            }
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => self.exprs(elements),
+            Expr::InlineAsm(asm, _) => {
+                for value in asm
+                    .operands
+                    .iter_mut()
+                    .filter_map(|operand| operand.expr.as_mut())
+                {
+                    self.expr(value);
+                }
+            }
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.block(block)
+            }
+            Expr::If(cond, then_block, else_block, _) => self.if_else(cond, then_block, else_block),
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                self.arms(arms);
+            }
+            Expr::While(_, cond, body, _) => {
+                self.expr(cond);
+                self.block(body);
+            }
+            Expr::For(_, _, iterable, body, _) => {
+                self.expr(iterable);
+                self.block(body);
+            }
+            Expr::Break(_, value, _) | Expr::Return(value, _) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+        }
+    }
+
+    // ---- 展开一次调用 ----
+
+    // 进入一次展开的结果：嵌套层数加一，`include!` 拼接的代码中的路径相对于被拼接的文件。
+    // 返回是否进入了被拼接的文件
+    fn enter(&mut self) -> bool {
+        self.depth += 1;
+        match self.included.take() {
+            Some(file) => {
+                self.files.push(file);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn leave(&mut self, entered: bool) {
+        self.depth -= 1;
+        if entered {
+            self.files.pop();
+        }
+    }
+
+    // 匹配、代入并把结果解析为调用位置的语法；出错时报告并返回 None
+    fn expand<T>(
+        &mut self,
+        call: &MacroCall,
+        parse: impl FnOnce(&mut Parser) -> Result<T, Vec<ParseError>>,
+    ) -> Option<T> {
+        let tokens = self.transcribe_call(call)?;
+        let mut parser = Parser::new(tokens);
+        match parse(&mut parser) {
+            Ok(result) => Some(result),
+            Err(errors) => {
+                self.included = None;
+                let note = format!(
+                    "in this expansion of `{}!` on line {}",
+                    call.name, call.span.line
+                );
+                self.errors.extend(
+                    errors
+                        .into_iter()
+                        .map(|error| Diagnostic::from(error).with_note(note.clone())),
+                );
+                None
+            }
+        }
+    }
+
+    fn transcribe_call(&mut self, call: &MacroCall) -> Option<Vec<Token>> {
+        if self.overflowed {
+            return None;
+        }
+        let Some(definition) = self.macros.get(&call.name) else {
+            if BUILTIN_MACROS.contains(&call.name.as_str()) {
+                return match self.overflow(call) {
+                    true => None,
+                    false if call.name == "format" || call.name == "println" => self.format(call),
+                    false => self.include(call),
+                };
+            }
+            let error = Diagnostic::error(
+                format!("cannot find macro `{}` in this scope", call.name),
+                call.span,
+            );
+            self.errors
+                .push(
+                    error.with_code(ErrorCode::E0105).with_help(
+                        "macros are defined with `macro_rules!` at the top level of the module"
+                            .to_string(),
+                    ),
+                );
+            return None;
+        };
+        let definition = Rc::clone(definition.as_ref()?);
+        if self.overflow(call) {
+            return None;
+        }
+
+        let args = contents(&call.args);
+        let Some((rule, bindings)) = definition.rules.iter().find_map(|rule| {
+            self.match_seq(&rule.matchers, args, 0)
+                .into_iter()
+                .find(|(end, _)| *end == args.len())
+                .map(|(_, bindings)| (rule, bindings))
+        }) else {
+            let expected: Vec<String> = definition
+                .rules
+                .iter()
+                .map(|rule| format!("`{}!({})`", call.name, tokens_text(&rule.pattern)))
+                .collect();
+            let error = Diagnostic::error(
+                format!("no rule of macro `{}` matches this invocation", call.name),
+                call.span,
+            );
+            self.errors.push(
+                error
+                    .with_code(ErrorCode::E0106)
+                    .with_help(format!("expected {}", expected.join(" or "))),
+            );
+            return None;
+        };
+
+        let mut tokens = Vec::new();
+        let mut renames = HashMap::new();
+        if let Err(error) = self.transcribe(&rule.template, &bindings, &mut renames, &mut tokens) {
+            self.errors.push(*error);
+            return None;
+        }
+        let end = tokens.last().map_or(call.span, |token| token.span);
+        tokens.push(Token::new(TokenKind::Eof, end));
+        Some(tokens)
+    }
+
+    // 嵌套的层数达到上限时报告，之后的调用都不再展开，避免每个分支各自达到上限
+    fn overflow(&mut self, call: &MacroCall) -> bool {
+        if self.depth < RECURSION_LIMIT {
+            return false;
+        }
+        self.overflowed = true;
+        let error = Diagnostic::error(
+            format!("recursion limit reached while expanding `{}!`", call.name),
+            call.span,
+        );
+        self.errors
+            .push(error.with_code(ErrorCode::E0107).with_help(format!(
+                "macro invocations can be nested at most {} levels deep",
+                RECURSION_LIMIT
+            )));
+        true
+    }
+
+    // `include!`、`include_str!` 和 `include_bytes!`：参数是一个字符串字面量，
+    // 相对于当前文件的路径。`include!` 的记号移到被拼接文件的偏移范围，诊断信息指向那个文件
+    fn include(&mut self, call: &MacroCall) -> Option<Vec<Token>> {
+        let error =
+            |message: String| Diagnostic::error(message, call.span).with_code(ErrorCode::E0109);
+        let path = match contents(&call.args) {
+            [TokenTree::Token(Token {
+                kind: TokenKind::StringLiteral(path),
+                ..
+            })] => path.clone(),
+            _ => {
+                self.errors.push(
+                    error(format!(
+                        "`{}!` takes a string literal with the path of a file",
+                        call.name
+                    ))
+                    .with_help(format!("write `{}!(\"file\")`", call.name)),
+                );
+                return None;
+            }
+        };
+        let (Some(sources), Some(&from)) = (self.sources.as_deref_mut(), self.files.last()) else {
+            self.errors.push(
+                error(format!(
+                    "`{}!` can only be used in a source file",
+                    call.name
+                ))
+                .with_help(
+                    "the path is resolved relative to the file containing the macro call"
+                        .to_string(),
+                ),
+            );
+            return None;
+        };
+        let resolved = sources.resolve(from, &path);
+        let file = match sources.load(&resolved) {
+            Ok(file) => file,
+            Err(err) => {
+                let message = format!("cannot read `{}`: {}", resolved.display(), err);
+                self.errors.push(error(message));
+                return None;
+            }
+        };
+        let bytes = &sources.file(file).contents;
+
+        let mut tokens = Vec::new();
+        if call.name == "include_bytes" {
+            tokens.push(Token::new(TokenKind::LeftBracket, call.span));
+            for (i, byte) in bytes.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(Token::new(TokenKind::Comma, call.span));
+                }
+                let value = TokenKind::IntLiteral(i64::from(*byte));
+                tokens.push(Token::new(value, call.span));
+                tokens.push(Token::new(TokenKind::As, call.span));
+                tokens.push(Token::new(TokenKind::U8, call.span));
+            }
+            tokens.push(Token::new(TokenKind::RightBracket, call.span));
+            tokens.push(Token::new(TokenKind::Eof, call.span));
+            return Some(tokens);
+        }
+
+        let Ok(text) = std::str::from_utf8(bytes) else {
+            let message = format!("`{}` is not valid UTF-8", resolved.display());
+            self.errors.push(error(message).with_help(
+                "use `include_bytes!` to embed the file as an array of bytes".to_string(),
+            ));
+            return None;
+        };
+        if call.name == "include_str" {
+            let literal = TokenKind::StringLiteral(text.into());
+            tokens.push(Token::new(literal, call.span));
+            tokens.push(Token::new(TokenKind::Eof, call.span));
+            return Some(tokens);
+        }
+
+        if self.files.contains(&file) {
+            let message = format!("cyclic `include!` of `{}`", resolved.display());
+            self.errors.push(error(message));
+            return None;
+        }
+        let display = resolved.display().to_string();
+        let mut tokens = match Lexer::new(text).tokenize() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                self.errors.extend(
+                    errors
+                        .into_iter()
+                        .map(|error| Diagnostic::from(error).with_file(display.clone())),
+                );
+                return None;
+            }
+        };
+        sources.relocate(file, &mut tokens);
+        for token in &tokens {
+            if let TokenKind::Ident(name) = &token.kind {
+                self.reserved.insert(name.clone());
+            }
+        }
+        self.included = Some(file);
+        Some(tokens)
+    }
+
+    // `format!` 和 `println!`：第一个参数是格式字符串字面量，之后是逗号分隔的参数。
+    // 参数按书写的顺序求值一次并取引用，每个占位符变为一次 `fmt::arg(&参数, "格式说明")`，
+    // 各段用 `+` 拼接：`{ let arg__1 = &(x); "x = " + fmt::arg(arg__1, ">6.2") }`。
+    // `{name}` 直接引用调用处的变量；每个参数都必须被引用
+    fn format(&mut self, call: &MacroCall) -> Option<Vec<Token>> {
+        let error =
+            |message: String| Diagnostic::error(message, call.span).with_code(ErrorCode::E0115);
+        let usage = format!("write `{}!(\"x = {{}}\", x)`", call.name);
+        let mut parts: Vec<&[TokenTree]> = contents(&call.args)
+            .split(|tree| {
+                matches!(
+                    tree,
+                    TokenTree::Token(Token {
+                        kind: TokenKind::Comma,
+                        ..
+                    })
+                )
+            })
+            .collect();
+        if parts.len() > 1 && parts.last().is_some_and(|part| part.is_empty()) {
+            parts.pop();
+        }
+        let token = |kind: TokenKind| Token::new(kind, call.span);
+
+        let (template, args) = match parts.split_first() {
+            Some((&[], [])) if call.name == "println" => {
+                let empty = TokenKind::StringLiteral(Symbol::from(""));
+                let kinds = [
+                    TokenKind::Ident(Symbol::from("print")),
+                    TokenKind::LeftParen,
+                    empty,
+                    TokenKind::RightParen,
+                    TokenKind::Eof,
+                ];
+                return Some(kinds.into_iter().map(token).collect());
+            }
+            Some((
+                [TokenTree::Token(Token {
+                    kind: TokenKind::StringLiteral(template),
+                    ..
+                })],
+                args,
+            )) => (template.clone(), args),
+            _ => {
+                self.errors.push(
+                    error(format!(
+                        "`{}!` takes a string literal as its first argument",
+                        call.name
+                    ))
+                    .with_help(usage),
+                );
+                return None;
+            }
+        };
+        let pieces = match fmt::parse(&template) {
+            Ok(pieces) => pieces,
+            Err(message) => {
+                self.errors.push(error(message));
+                return None;
+            }
+        };
+        if let Some(index) = args.iter().position(|arg| arg.is_empty()) {
+            let message = format!("expected an expression for argument {}", index);
+            self.errors.push(error(message));
+            return None;
+        }
+
+        // 占位符引用的参数：序号或调用处的变量名
+        let mut next = 0;
+        let mut used = vec![false; args.len()];
+        let mut placeholders = Vec::new();
+        for piece in &pieces {
+            let Piece::Placeholder(argument, spec) = piece else {
+                continue;
+            };
+            let index = match argument {
+                Argument::Next => {
+                    next += 1;
+                    next - 1
+                }
+                Argument::Index(index) => *index,
+                Argument::Name(name) => {
+                    placeholders.push((Err(Symbol::from(name.as_str())), spec));
+                    continue;
+                }
+            };
+            if index >= args.len() {
+                let supplied = match args.len() {
+                    1 => "1 argument was".to_string(),
+                    n => format!("{} arguments were", n),
+                };
+                self.errors.push(
+                    error(format!(
+                        "the format string refers to argument {} but {} supplied",
+                        index, supplied
+                    ))
+                    .with_help("arguments are counted from 0".to_string()),
+                );
+                return None;
+            }
+            used[index] = true;
+            placeholders.push((Ok(index), spec));
+        }
+        if let Some(index) = used.iter().position(|used| !used) {
+            let span = first_span(args[index]).unwrap_or(call.span);
+            self.errors.push(
+                Diagnostic::error(
+                    format!("argument {} is never used in the format string", index),
+                    span,
+                )
+                .with_code(ErrorCode::E0115)
+                .with_help("add a `{}` placeholder for it or remove the argument".to_string()),
+            );
+            return None;
+        }
+
+        let mut tokens = Vec::new();
+        if call.name == "println" {
+            tokens.push(token(TokenKind::Ident(Symbol::from("print"))));
+            tokens.push(token(TokenKind::LeftParen));
+        }
+        let mut renames = HashMap::new();
+        let names: Vec<Symbol> = (0..args.len())
+            .map(|index| self.rename(&format!("arg{}", index), &mut renames))
+            .collect();
+        if !args.is_empty() {
+            tokens.push(token(TokenKind::LeftBrace));
+            for (name, arg) in names.iter().zip(args) {
+                tokens.push(token(TokenKind::Let));
+                tokens.push(token(TokenKind::Ident(name.clone())));
+                tokens.push(token(TokenKind::Assign));
+                tokens.push(token(TokenKind::BitwiseAnd));
+                tokens.push(token(TokenKind::LeftParen));
+                for tree in *arg {
+                    tree.flatten_into(&mut tokens);
+                }
+                tokens.push(token(TokenKind::RightParen));
+                tokens.push(token(TokenKind::Semicolon));
+            }
+        }
+        let mut placeholders = placeholders.into_iter();
+        for (i, piece) in pieces.iter().enumerate() {
+            if i > 0 {
+                tokens.push(token(TokenKind::Plus));
+            }
+            if let Piece::Text(text) = piece {
+                let literal = TokenKind::StringLiteral(Symbol::from(text.as_str()));
+                tokens.push(token(literal));
+                continue;
+            }
+            let (argument, spec) = placeholders.next()?;
+            tokens.push(token(TokenKind::Ident(Symbol::from("fmt"))));
+            tokens.push(token(TokenKind::DoubleColon));
+            tokens.push(token(TokenKind::Ident(Symbol::from("arg"))));
+            tokens.push(token(TokenKind::LeftParen));
+            match argument {
+                Ok(index) => {
+                    // 指向实参，语义分析对参数类型的诊断落在实参上
+                    let span = first_span(args[index]).unwrap_or(call.span);
+                    tokens.push(Token::new(TokenKind::Ident(names[index].clone()), span));
+                }
+                Err(name) => {
+                    tokens.push(token(TokenKind::BitwiseAnd));
+                    tokens.push(token(TokenKind::Ident(name)));
+                }
+            }
+            tokens.push(token(TokenKind::Comma));
+            tokens.push(token(TokenKind::StringLiteral(Symbol::from(spec.as_str()))));
+            tokens.push(token(TokenKind::RightParen));
+        }
+        if pieces.is_empty() {
+            tokens.push(token(TokenKind::StringLiteral(Symbol::from(""))));
+        }
+        if !args.is_empty() {
+            tokens.push(token(TokenKind::RightBrace));
+        }
+        if call.name == "println" {
+            tokens.push(token(TokenKind::RightParen));
+        }
+        tokens.push(token(TokenKind::Eof));
+        Some(tokens)
+    }
+
+    // ---- 匹配 ----
+
+    // 从 `pos` 开始匹配一串模式的所有方式：结束位置和绑定。重复多的在前
+    fn match_seq(
+        &self,
+        matchers: &[Matcher],
+        input: &[TokenTree],
+        pos: usize,
+    ) -> Vec<(usize, Bindings)> {
+        let Some((first, rest)) = matchers.split_first() else {
+            return vec![(pos, Bindings::new())];
+        };
+        let mut results = Vec::new();
+        for (end, bindings) in self.match_one(first, input, pos) {
+            for (end, more) in self.match_seq(rest, input, end) {
+                let mut all = bindings.clone();
+                all.extend(more);
+                results.push((end, all));
+            }
+        }
+        results
+    }
+
+    fn match_one(
+        &self,
+        matcher: &Matcher,
+        input: &[TokenTree],
+        pos: usize,
+    ) -> Vec<(usize, Bindings)> {
+        match matcher {
+            Matcher::Token(kind) => match input.get(pos) {
+                Some(TokenTree::Token(token)) if token.kind == *kind => {
+                    vec![(pos + 1, Bindings::new())]
+                }
+                _ => Vec::new(),
+            },
+            Matcher::Group(open, matchers) => match input.get(pos) {
+                Some(TokenTree::Delimited(token, trees, _)) if token.kind == *open => self
+                    .match_seq(matchers, trees, 0)
+                    .into_iter()
+                    .find(|(end, _)| *end == trees.len())
+                    .map(|(_, bindings)| (pos + 1, bindings))
+                    .into_iter()
+                    .collect(),
+                _ => Vec::new(),
+            },
+            Matcher::Var(name, fragment) => self
+                .match_fragment(*fragment, input, pos)
+                .map(|end| {
+                    let binding = Binding::One(input[pos..end].to_vec(), *fragment);
+                    (end, Bindings::from([(name.clone(), binding)]))
+                })
+                .into_iter()
+                .collect(),
+            Matcher::Repeat(matchers, separator, repetition) => {
+                self.match_repeat(matchers, separator.as_ref(), *repetition, input, pos)
+            }
+        }
+    }
+
+    fn match_repeat(
+        &self,
+        matchers: &[Matcher],
+        separator: Option<&TokenKind>,
+        repetition: Repetition,
+        input: &[TokenTree],
+        pos: usize,
+    ) -> Vec<(usize, Bindings)> {
+        let mut names = Vec::new();
+        matcher_vars(matchers, &mut names);
+        let min = usize::from(repetition == Repetition::OneOrMore);
+
+        let mut results = Vec::new();
+        let mut states: Vec<(usize, Vec<Bindings>)> = vec![(pos, Vec::new())];
+        while !states.is_empty() {
+            let mut next = Vec::new();
+            for (at, iterations) in states {
+                if iterations.len() >= min {
+                    results.push((at, collect_iterations(&names, &iterations)));
+                }
+                if repetition == Repetition::ZeroOrOne && iterations.len() == 1 {
+                    continue;
+                }
+                let start = match (iterations.is_empty(), separator) {
+                    (false, Some(separator)) => match input.get(at) {
+                        Some(TokenTree::Token(token)) if token.kind == *separator => at + 1,
+                        _ => continue,
+                    },
+                    _ => at,
+                };
+                for (end, bindings) in self.match_seq(matchers, input, start) {
+                    // 每次重复至少消耗一个记号树，否则不会结束
+                    if end > at {
+                        let mut iterations = iterations.clone();
+                        iterations.push(bindings);
+                        next.push((end, iterations));
+                    }
+                }
+            }
+            states = next;
+        }
+        results.reverse();
+        results
+    }
+
+    // 片段从 `pos` 开始，返回结束的位置
+    fn match_fragment(&self, fragment: Fragment, input: &[TokenTree], pos: usize) -> Option<usize> {
+        let tree = input.get(pos)?;
+        let token = match tree {
+            TokenTree::Token(token) => Some(&token.kind),
+            TokenTree::Delimited(..) => None,
+        };
+        match fragment {
+            Fragment::Tt => Some(pos + 1),
+            Fragment::Ident => matches!(token, Some(TokenKind::Ident(_))).then_some(pos + 1),
+            Fragment::Block => {
+                matches!(tree, TokenTree::Delimited(open, _, _) if open.kind == TokenKind::LeftBrace)
+                    .then_some(pos + 1)
+            }
+            Fragment::Literal => match token? {
+                TokenKind::IntLiteral(_)
+                | TokenKind::SuffixedIntLiteral(..)
+                | TokenKind::BoolLiteral(_)
+                | TokenKind::CharLiteral(_)
+                | TokenKind::StringLiteral(_) => Some(pos + 1),
+                TokenKind::Minus => matches!(
+                    input.get(pos + 1),
+                    Some(TokenTree::Token(Token {
+                        kind: TokenKind::IntLiteral(_) | TokenKind::SuffixedIntLiteral(..),
+                        ..
+                    }))
+                )
+                .then_some(pos + 2),
+                _ => None,
+            },
+            Fragment::Expr | Fragment::Ty | Fragment::Pat => {
+                // 交给语法分析器，再把用掉的记号数换算成记号树的个数
+                let mut tokens = Vec::new();
+                let mut ends = Vec::new();
+                for tree in &input[pos..] {
+                    tree.flatten_into(&mut tokens);
+                    ends.push(tokens.len());
+                }
+                let end = tokens[tokens.len() - 1].span;
+                tokens.push(Token::new(TokenKind::Eof, end));
+                let used = Parser::new(tokens).parse_fragment(fragment)?;
+                ends.iter().position(|&end| end == used).map(|i| pos + i + 1)
+            }
+        }
+    }
+
+    // ---- 代入模板 ----
+
+    fn transcribe(
+        &mut self,
+        templates: &[Template],
+        bindings: &Bindings,
+        renames: &mut HashMap<String, Symbol>,
+        out: &mut Vec<Token>,
+    ) -> Result<(), Box<Diagnostic>> {
+        for template in templates {
+            match template {
+                Template::Token(token, Rename::No) => out.push(token.clone()),
+                Template::Token(token, rename) => {
+                    let TokenKind::Ident(name) = &token.kind else {
+                        unreachable!("only identifiers are renamed");
+                    };
+                    let fresh = self.rename(name, renames);
+                    if *rename == Rename::Shorthand {
+                        out.push(token.clone());
+                        out.push(Token::new(TokenKind::Colon, token.span));
+                    }
+                    out.push(Token::new(TokenKind::Ident(fresh), token.span));
+                }
+                Template::Group(open, templates, close) => {
+                    out.push(open.clone());
+                    self.transcribe(templates, bindings, renames, out)?;
+                    out.push(close.clone());
+                }
+                Template::Var(name, span) => match &bindings[name] {
+                    Binding::One(trees, fragment) => {
+                        // 多个记号组成的表达式加上括号
+                        let parenthesize = *fragment == Fragment::Expr && trees.len() > 1;
+                        let (first, last) = (trees[0].span(), trees[trees.len() - 1].span());
+                        if parenthesize {
+                            out.push(Token::new(TokenKind::LeftParen, first));
+                        }
+                        for tree in trees {
+                            tree.flatten_into(out);
+                        }
+                        if parenthesize {
+                            out.push(Token::new(TokenKind::RightParen, last));
+                        }
+                    }
+                    Binding::Many(_) => return Err(still_repeating(name, *span).into()),
+                },
+                Template::Repeat(templates, separator, span) => {
+                    let mut names = Vec::new();
+                    template_vars(templates, &mut names);
+                    let mut count: Option<(&String, usize)> = None;
+                    for name in &names {
+                        let Some(Binding::Many(items)) = bindings.get(name) else {
+                            continue;
+                        };
+                        match count {
+                            Some((other, n)) if n != items.len() => {
+                                let error = Diagnostic::error(
+                                    format!(
+                                        "meta-variable `{}` repeats {} times, but `{}` repeats {} times",
+                                        other,
+                                        n,
+                                        name,
+                                        items.len()
+                                    ),
+                                    *span,
+                                );
+                                return Err(error.with_code(ErrorCode::E0106).into());
+                            }
+                            _ => count = Some((name, items.len())),
+                        }
+                    }
+                    let count = count.map_or(0, |(_, n)| n);
+                    for i in 0..count {
+                        if let (true, Some(separator)) = (i > 0, separator) {
+                            out.push(separator.clone());
+                        }
+                        let mut inner = bindings.clone();
+                        for name in &names {
+                            if let Some(Binding::Many(items)) = bindings.get(name) {
+                                inner.insert(name.clone(), items[i].clone());
+                            }
+                        }
+                        self.transcribe(templates, &inner, renames, out)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 同一次展开中同名的变量改为同一个新名字
+    fn rename(&mut self, name: &str, renames: &mut HashMap<String, Symbol>) -> Symbol {
+        if let Some(fresh) = renames.get(name) {
+            return fresh.clone();
+        }
+        let fresh = loop {
+            self.fresh += 1;
+            let fresh = format!("{}__{}", name, self.fresh);
+            if !self.reserved.contains(fresh.as_str()) {
+                break Symbol::from(fresh);
+            }
+        };
+        renames.insert(name.to_string(), fresh.clone());
+        fresh
+    }
+}
+
+fn collect_iterations(names: &[String], iterations: &[Bindings]) -> Bindings {
+    names
+        .iter()
+        .map(|name| {
+            let items = iterations
+                .iter()
+                .map(|bindings| bindings[name].clone())
+                .collect();
+            (name.clone(), Binding::Many(items))
+        })
+        .collect()
+}
+
+fn matcher_vars(matchers: &[Matcher], names: &mut Vec<String>) {
+    for matcher in matchers {
+        match matcher {
+            Matcher::Token(_) => {}
+            Matcher::Var(name, _) => names.push(name.clone()),
+            Matcher::Group(_, matchers) | Matcher::Repeat(matchers, _, _) => {
+                matcher_vars(matchers, names)
+            }
+        }
+    }
+}
+
+fn template_vars(templates: &[Template], names: &mut Vec<String>) {
+    for template in templates {
+        match template {
+            Template::Token(..) => {}
+            Template::Var(name, _) => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            Template::Group(_, templates, _) | Template::Repeat(templates, _, _) => {
+                template_vars(templates, names)
+            }
+        }
+    }
+}
+
+// ---- 编译宏定义 ----
+
+fn compile_rule(rule: &MacroRule) -> Result<Rule, Box<Diagnostic>> {
+    let pattern = contents(&rule.pattern);
+    let body = contents(&rule.body);
+    let mut vars = HashMap::new();
+    let matchers = compile_matchers(pattern, 0, &mut vars)?;
+    let mut bound = BTreeSet::new();
+    binders(body, &mut bound);
+    let template = compile_template(body, 0, &vars, &bound, false)?;
+    Ok(Rule {
+        matchers,
+        template,
+        pattern: pattern.to_vec(),
+    })
+}
+
+// `vars` 记录每个元变量所在的重复层数
+fn compile_matchers(
+    trees: &[TokenTree],
+    depth: usize,
+    vars: &mut HashMap<String, usize>,
+) -> Result<Vec<Matcher>, Box<Diagnostic>> {
+    let mut matchers = Vec::new();
+    let mut i = 0;
+    while i < trees.len() {
+        match &trees[i] {
+            TokenTree::Token(dollar) if dollar.kind == TokenKind::Dollar => {
+                match trees.get(i + 1) {
+                    Some(TokenTree::Token(Token {
+                        kind: TokenKind::Ident(name),
+                        span,
+                        ..
+                    })) => {
+                        let fragment = fragment_specifier(name, *span, trees.get(i + 2..i + 4))?;
+                        if vars.insert(name.to_string(), depth).is_some() {
+                            return Err(invalid_definition(
+                                format!("duplicate meta-variable `${}` in the pattern", name),
+                                *span,
+                            )
+                            .into());
+                        }
+                        matchers.push(Matcher::Var(name.to_string(), fragment));
+                        i += 4;
+                    }
+                    Some(TokenTree::Delimited(open, inner, _))
+                        if open.kind == TokenKind::LeftParen =>
+                    {
+                        let inner = compile_matchers(inner, depth + 1, vars)?;
+                        let (separator, repetition, used) =
+                            repetition(&trees[i + 2..], dollar.span)?;
+                        let separator = separator.map(|token| token.kind);
+                        matchers.push(Matcher::Repeat(inner, separator, repetition));
+                        i += 2 + used;
+                    }
+                    _ => return Err(expected_after_dollar(dollar.span).into()),
+                }
+            }
+            TokenTree::Token(token) => {
+                matchers.push(Matcher::Token(token.kind.clone()));
+                i += 1;
+            }
+            TokenTree::Delimited(open, inner, _) => {
+                let inner = compile_matchers(inner, depth, vars)?;
+                matchers.push(Matcher::Group(open.kind.clone(), inner));
+                i += 1;
+            }
+        }
+    }
+    Ok(matchers)
+}
+
+// `$name` 之后的 `:kind`
+fn fragment_specifier(
+    name: &str,
+    span: Span,
+    rest: Option<&[TokenTree]>,
+) -> Result<Fragment, Box<Diagnostic>> {
+    let kinds: Vec<String> = Fragment::NAMES
+        .iter()
+        .map(|(kind, _)| format!("`{}`", kind))
+        .collect();
+    let help = format!("fragment specifiers are {}", kinds.join(", "));
+    let Some(
+        [TokenTree::Token(Token {
+            kind: TokenKind::Colon,
+            ..
+        }), TokenTree::Token(kind)],
+    ) = rest
+    else {
+        return Err(invalid_definition(
+            format!("missing fragment specifier for `${}`", name),
+            span,
+        )
+        .with_help(format!("write `${}:expr`; {}", name, help))
+        .into());
+    };
+    let fragment = match &kind.kind {
+        TokenKind::Ident(kind) => Fragment::parse(kind),
+        _ => None,
+    };
+    fragment.ok_or_else(|| {
+        invalid_definition(
+            format!("invalid fragment specifier `{}`", kind.kind),
+            kind.span,
+        )
+        .with_help(help)
+        .into()
+    })
+}
+
+// `$(...)` 之后可选的分隔符和重复运算符，返回用掉的记号树个数
+fn repetition(
+    rest: &[TokenTree],
+    span: Span,
+) -> Result<(Option<Token>, Repetition, usize), Box<Diagnostic>> {
+    let operator = |tree: Option<&TokenTree>| match tree {
+        Some(TokenTree::Token(token)) => match token.kind {
+            TokenKind::Star => Some(Repetition::ZeroOrMore),
+            TokenKind::Plus => Some(Repetition::OneOrMore),
+            TokenKind::Question => Some(Repetition::ZeroOrOne),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(repetition) = operator(rest.first()) {
+        return Ok((None, repetition, 1));
+    }
+    match (rest.first(), operator(rest.get(1))) {
+        (Some(TokenTree::Token(separator)), Some(repetition))
+            if separator.kind != TokenKind::Dollar =>
+        {
+            Ok((Some(separator.clone()), repetition, 2))
+        }
+        _ => Err(invalid_definition(
+            "expected one of `*`, `+` or `?` after `$(...)`".to_string(),
+            span,
+        )
+        .into()),
+    }
+}
+
+fn compile_template(
+    trees: &[TokenTree],
+    depth: usize,
+    vars: &HashMap<String, usize>,
+    bound: &BTreeSet<String>,
+    struct_body: bool,
+) -> Result<Vec<Template>, Box<Diagnostic>> {
+    let mut templates = Vec::new();
+    let mut i = 0;
+    while i < trees.len() {
+        match &trees[i] {
+            TokenTree::Token(dollar) if dollar.kind == TokenKind::Dollar => {
+                match trees.get(i + 1) {
+                    Some(TokenTree::Token(Token {
+                        kind: TokenKind::Ident(name),
+                        span,
+                        ..
+                    })) => {
+                        match vars.get(name.as_str()) {
+                            None => {
+                                return Err(invalid_definition(
+                                    format!("unknown meta-variable `${}`", name),
+                                    *span,
+                                )
+                                .into())
+                            }
+                            Some(&var_depth) if var_depth > depth => {
+                                return Err(still_repeating(name, *span).into())
+                            }
+                            Some(_) => templates.push(Template::Var(name.to_string(), *span)),
+                        }
+                        i += 2;
+                    }
+                    Some(TokenTree::Delimited(open, inner, _))
+                        if open.kind == TokenKind::LeftParen =>
+                    {
+                        let inner = compile_template(inner, depth + 1, vars, bound, false)?;
+                        let mut names = Vec::new();
+                        template_vars(&inner, &mut names);
+                        if !names.iter().any(|name| vars[name] > depth) {
+                            return Err(invalid_definition(
+                                "this repetition does not contain a meta-variable that repeats at this depth"
+                                    .to_string(),
+                                dollar.span,
+                            ).into());
+                        }
+                        let (separator, _, used) = repetition(&trees[i + 2..], dollar.span)?;
+                        templates.push(Template::Repeat(inner, separator, dollar.span));
+                        i += 2 + used;
+                    }
+                    _ => return Err(expected_after_dollar(dollar.span).into()),
+                }
+            }
+            TokenTree::Token(token) => {
+                let rename = match &token.kind {
+                    TokenKind::Ident(name) if bound.contains(name.as_str()) => {
+                        rename_kind(trees, i, struct_body)
+                    }
+                    _ => Rename::No,
+                };
+                templates.push(Template::Token(token.clone(), rename));
+                i += 1;
+            }
+            TokenTree::Delimited(open, inner, close) => {
+                let is_struct = open.kind == TokenKind::LeftBrace && struct_name_before(trees, i);
+                let inner = compile_template(inner, depth, vars, bound, is_struct)?;
+                templates.push(Template::Group(open.clone(), inner, close.clone()));
+                i += 1;
+            }
+        }
+    }
+    Ok(templates)
+}
+
+// 模板绑定的变量名出现在 `trees[i]`：字段名、方法名和路径中的名字不改
+fn rename_kind(trees: &[TokenTree], i: usize, struct_body: bool) -> Rename {
+    let prev = i.checked_sub(1).and_then(|j| token_kind(&trees[j]));
+    let next = trees.get(i + 1).map(token_kind);
+    if matches!(
+        prev,
+        Some(TokenKind::Dot | TokenKind::DoubleColon | TokenKind::Dollar)
+    ) || next == Some(Some(&TokenKind::DoubleColon))
+    {
+        return Rename::No;
+    }
+    // 结构体字面量和模式中的字段
+    if struct_body && (i == 0 || prev == Some(&TokenKind::Comma)) {
+        match next {
+            None | Some(Some(TokenKind::Comma)) => return Rename::Shorthand,
+            Some(Some(TokenKind::Colon)) => return Rename::No,
+            _ => {}
+        }
+    }
+    Rename::Yes
+}
+
+// `Name {` 中的 `{` 开始结构体的字段；`if x {`、`while i < n {` 之类不是
+fn struct_name_before(trees: &[TokenTree], i: usize) -> bool {
+    let Some(Some(TokenKind::Ident(name))) = i.checked_sub(1).map(|j| token_kind(&trees[j])) else {
+        return false;
+    };
+    let before = i.checked_sub(2).and_then(|j| token_kind(&trees[j]));
+    name.starts_with(char::is_uppercase)
+        && !matches!(
+            before,
+            Some(
+                TokenKind::Dollar
+                    | TokenKind::In
+                    | TokenKind::If
+                    | TokenKind::While
+                    | TokenKind::Match
+            )
+        )
+}
+
+fn token_kind(tree: &TokenTree) -> Option<&TokenKind> {
+    match tree {
+        TokenTree::Token(token) => Some(&token.kind),
+        TokenTree::Delimited(..) => None,
+    }
+}
+
+// 收集模板中 `let`、`for`、match 分支和闭包参数绑定的变量名
+fn binders(trees: &[TokenTree], bound: &mut BTreeSet<String>) {
+    let position = |from: usize, stop: &dyn Fn(&TokenKind) -> bool| {
+        (from..trees.len())
+            .find(|&j| token_kind(&trees[j]).is_some_and(stop))
+            .unwrap_or(trees.len())
+    };
+    for (i, tree) in trees.iter().enumerate() {
+        let TokenTree::Token(token) = tree else {
+            if let TokenTree::Delimited(_, inner, _) = tree {
+                binders(inner, bound);
+            }
+            continue;
+        };
+        match token.kind {
+            TokenKind::Let => {
+                let start = match trees.get(i + 1).and_then(token_kind) {
+                    Some(TokenKind::Mut) => i + 2,
+                    _ => i + 1,
+                };
+                let end = position(start, &|kind| {
+                    matches!(
+                        kind,
+                        TokenKind::Assign | TokenKind::Colon | TokenKind::Semicolon
+                    )
+                });
+                pattern_binders(&trees[start.min(end)..end], bound);
+            }
+            TokenKind::For => {
+                let end = position(i + 1, &|kind| *kind == TokenKind::In);
+                pattern_binders(&trees[i + 1..end], bound);
+            }
+            // 分支的模式从上一个分支之后开始，到 `if` 守卫或 `=>` 为止
+            TokenKind::FatArrow => {
+                let mut start = i;
+                while start > 0 {
+                    let before = &trees[start - 1];
+                    let arm_end = match before {
+                        TokenTree::Token(token) => token.kind == TokenKind::Comma,
+                        TokenTree::Delimited(open, ..) => {
+                            open.kind == TokenKind::LeftBrace
+                                && start >= 2
+                                && token_kind(&trees[start - 2]) == Some(&TokenKind::FatArrow)
+                        }
+                    };
+                    if arm_end {
+                        break;
+                    }
+                    start -= 1;
+                }
+                let end = (start..i)
+                    .find(|&j| token_kind(&trees[j]) == Some(&TokenKind::If))
+                    .unwrap_or(i);
+                pattern_binders(&trees[start..end], bound);
+            }
+            // 闭包 `|a, b: T| ...` 出现在不能结束一个操作数的记号之后
+            TokenKind::BitwiseOr
+                if i == 0
+                    || matches!(
+                        token_kind(&trees[i - 1]),
+                        Some(
+                            TokenKind::Assign
+                                | TokenKind::Comma
+                                | TokenKind::Semicolon
+                                | TokenKind::Return
+                                | TokenKind::FatArrow
+                                | TokenKind::Colon
+                        )
+                    ) =>
+            {
+                let end = position(i + 1, &|kind| *kind == TokenKind::BitwiseOr);
+                for param in
+                    trees[i + 1..end].split(|tree| token_kind(tree) == Some(&TokenKind::Comma))
+                {
+                    let end = param
+                        .iter()
+                        .position(|tree| token_kind(tree) == Some(&TokenKind::Colon))
+                        .unwrap_or(param.len());
+                    pattern_binders(&param[..end], bound);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// 模式中的变量：小写开头的标识符，不是路径、变体或字段名，也不是元变量
+fn pattern_binders(trees: &[TokenTree], bound: &mut BTreeSet<String>) {
+    for (i, tree) in trees.iter().enumerate() {
+        match tree {
+            TokenTree::Token(Token {
+                kind: TokenKind::Ident(name),
+                ..
+            }) => {
+                let prev = i.checked_sub(1).and_then(|j| token_kind(&trees[j]));
+                let next = trees.get(i + 1);
+                let is_variable = name.starts_with(|c: char| c.is_lowercase() || c == '_')
+                    && !matches!(prev, Some(TokenKind::Dollar | TokenKind::DoubleColon))
+                    && !matches!(
+                        next.map(token_kind),
+                        Some(Some(TokenKind::DoubleColon | TokenKind::Colon) | None)
+                    );
+                if is_variable {
+                    bound.insert(name.to_string());
+                }
+            }
+            TokenTree::Token(_) => {}
+            TokenTree::Delimited(_, inner, _) => pattern_binders(inner, bound),
+        }
+    }
+}
+
+// ---- 辅助函数 ----
+
+// 记号树的第一个记号的位置
+fn first_span(trees: &[TokenTree]) -> Option<Span> {
+    match trees.first()? {
+        TokenTree::Token(token) => Some(token.span),
+        TokenTree::Delimited(open, _, _) => Some(open.span),
+    }
+}
+
+// 定界符之间的记号树
+fn contents(tree: &TokenTree) -> &[TokenTree] {
+    match tree {
+        TokenTree::Delimited(_, trees, _) => trees,
+        TokenTree::Token(_) => std::slice::from_ref(tree),
+    }
+}
+
+/// 记号树的源码，原来有空白的地方用一个空格分隔
+pub fn tokens_text(trees: &[TokenTree]) -> String {
+    let mut tokens = Vec::new();
+    for tree in trees {
+        tree.flatten_into(&mut tokens);
+    }
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && token.span.start > tokens[i - 1].span.end {
+            text.push(' ');
+        }
+        text.push_str(&token.kind.to_string());
+    }
+    text
+}
+
+fn invalid_definition(message: String, span: Span) -> Diagnostic {
+    Diagnostic::error(message, span).with_code(ErrorCode::E0104)
+}
+
+fn expected_after_dollar(span: Span) -> Diagnostic {
+    invalid_definition(
+        "expected a meta-variable name or `(` after `$`".to_string(),
+        span,
+    )
+}
+
+fn still_repeating(name: &str, span: Span) -> Diagnostic {
+    invalid_definition(
+        format!("meta-variable `${}` is still repeating at this depth", name),
+        span,
+    )
+    .with_help(format!("use `${}` inside a `$(...)` repetition", name))
+}
@@ -1,82 +1,1199 @@
 use std::env;
 use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use contractus::{Lexer, Parser};
+use contractus::bench::{self, BenchOptions};
+use contractus::bytecode::Vm;
+use contractus::diagnostic::ErrorCode;
+use contractus::dap;
+use contractus::debugger;
+use contractus::doctest;
+use contractus::driver::{self, Artifact, Compiler, ContractMode, PanicStrategy};
+use contractus::format::{self, FormatOptions};
+use contractus::grammar;
+use contractus::highlight;
+use contractus::incremental::QueryCache;
+use contractus::interp::Host;
+use contractus::link::{self, LinkOptions, Os, Target};
+use contractus::log::{self, Filter};
+use contractus::mangle;
+use contractus::source_map::SourceFile;
+use contractus::manifest::{Manifest, OutputKind, Profile};
+use contractus::syntax::{self, SyntaxFormat};
+use contractus::timing::{self, CountingAllocator};
+use contractus::{bytecode, diagnostic, interp, repl, Diagnostic, Interpreter, MirProgram};
+
+// 统计堆内存的用量，供 `-Ztime-passes` 报告每个阶段的峰值
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const USAGE: &str = "Usage: contractus [run [--vm]] [<options>] <inputs> [-- <args>...]
+       contractus --emit=<kind> [<options>] [--target=<triple>] <inputs> [-o <output>|-]
+       contractus run <file.ctxb>
+       contractus debug [<options>] <inputs> [-- <args>...]
+       contractus dap
+       contractus check [<options>] [<inputs>]
+       contractus bench [<options>] [--filter=<text>] [<inputs>]
+       contractus build [<options>] [--target=<triple>] [--linker=<cc|lld|path>] [<inputs>] [-o <output>]
+       contractus repl
+       contractus fmt [--check] [--width=<n>] [<file.ctx>...|-]
+       contractus test --doc [<file.ctx>...]
+       contractus demangle [<symbol>...]
+       contractus gen-syntax --format=tmlanguage|tree-sitter [-o <dir>]
+       contractus --explain <code>
+
+Options: -O0|-O1|-O2|-Os, --no-bounds-check, --contracts=check|off,
+         --panic=unwind|abort, --release, --profile=<name>,
+         --error-limit <n> (default 20, 0 for no limit),
+         --verify-determinism (compile twice and fail if the outputs differ),
+         -Ztime-passes (time and peak memory of each compiler pass), -Ztime-passes-format=text|json,
+         -Zincremental=<dir> (reuse unchanged results; projects use target/incremental),
+         -Ztoken-text (--emit=tokens also prints the source text of each token),
+         -v|--verbose (repeat for more compiler logs), -q|--quiet (only log errors)
+Inputs: <file.ctx>... (the first file is the entry) or --root <dir> (all .ctx files, entry main.ctx);
+        build, run, check and bench without inputs use the Contractus.toml of the current project
+Emit kinds: tokens, ast, ast-json, expanded, hir, mir, bytecode, asm, obj, wasm,
+            tokens-json, tokens-csv (tokens and comments with highlight classes),
+            grammar (the EBNF of the language, needs no inputs)
+Exit status: `run` exits with the code returned by `main() -> i32` or passed to `std::process::exit`,
+             and with 1 when compiling or running the program fails
+Environment: CONTRACTUS_LOG=<filter> selects compiler logs by module, e.g. `debug` or `warn,contractus::mir=trace`";
+
+// 可以输出的中间结果。文本格式默认写到标准输出；
+// 二进制格式（bytecode、obj、wasm）默认写到源文件旁边，`-o -` 时写到标准输出
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Emit {
+    Tokens,
+    TokensJson, // 带高亮类别的记号和注释，有词法错误时也输出
+    TokensCsv,
+    Ast,
+    AstJson,
+    Expanded, // 宏展开之后的源码
+    Hir,
+    Mir,
+    Bytecode,
+    Asm, // 字节码反汇编
+    Obj,
+    Wasm,
+    Grammar, // 语言的 EBNF，不需要输入文件
+}
+
+impl Emit {
+    const KINDS: [(&'static str, Emit); 13] = [
+        ("tokens", Emit::Tokens),
+        ("ast", Emit::Ast),
+        ("ast-json", Emit::AstJson),
+        ("expanded", Emit::Expanded),
+        ("hir", Emit::Hir),
+        ("mir", Emit::Mir),
+        ("bytecode", Emit::Bytecode),
+        ("asm", Emit::Asm),
+        ("obj", Emit::Obj),
+        ("wasm", Emit::Wasm),
+        ("tokens-json", Emit::TokensJson),
+        ("tokens-csv", Emit::TokensCsv),
+        ("grammar", Emit::Grammar),
+    ];
+
+    fn parse(kind: &str) -> Option<Emit> {
+        Self::KINDS
+            .iter()
+            .find(|(name, _)| *name == kind)
+            .map(|(_, emit)| *emit)
+    }
+}
+
+// `--emit=wasm` 的默认目标
+const WASM_TARGET: &str = "wasm32-unknown-wasi";
+
+// 最多输出的错误数（`--error-limit`），0 表示不限制
+static ERROR_LIMIT: AtomicUsize = AtomicUsize::new(20);
+
+// `--verify-determinism`：每次编译都做两遍并比较结果
+static VERIFY_DETERMINISM: AtomicBool = AtomicBool::new(false);
+
+// `-Ztoken-text`：`--emit=tokens` 同时输出每个记号的源码文本
+static TOKEN_TEXT: AtomicBool = AtomicBool::new(false);
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut emit = None;
+    // 编译选项，没有指定的按构建配置；`--no-bounds-check` 只影响 MIR，
+    // 解释器总是检查下标，契约模式两者都遵守
+    let mut opt_level = None;
+    let mut bounds_checks = None;
+    let mut contracts = None;
+    let mut panic = None;
+    let mut profile_name = None;
+    let mut use_vm = false;
+    let mut output = None;
+    let mut link_options = LinkOptions::default();
+    let mut target = None;
+    let mut root = None;
+    let mut files = Vec::new();
+    // `run` 时 `--` 之后的参数交给程序（`io::args()`）
+    let mut program_args = Vec::new();
+    let mut time_passes = false;
+    let mut time_passes_json = false;
+    let mut incremental = None;
 
-    if args.len() != 2 {
-        eprintln!("Usage: contractus <file.ctx>");
-        process::exit(1);
+    let mut args = init_logging(env::args().skip(1).collect())
+        .into_iter()
+        .peekable();
+
+    // `contractus repl` 启动交互式环境
+    if args.next_if(|arg| arg == "repl").is_some() {
+        if args.next().is_some() {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+        if let Err(error) = interp::with_large_stack(repl::run_interactive) {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+        return;
     }
 
-    let filename: &String = &args[1];
-    let source: String = match fs::read_to_string(filename) {
-        Ok(content) => content,
-        Err(err) => {
-            eprintln!("Error reading file '{}': {}", filename, err);
+    // `contractus dap` 在标准输入输出上提供调试适配器，由编辑器启动
+    if args.next_if(|arg| arg == "dap").is_some() {
+        if args.next().is_some() {
+            eprintln!("{}", USAGE);
             process::exit(1);
         }
-    };
+        if let Err(error) = interp::with_large_stack(|| dap::serve(io::stdin(), io::stdout())) {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // `contractus fmt` 格式化源文件，没有参数时格式化当前项目的所有源文件
+    if args.next_if(|arg| arg == "fmt").is_some() {
+        format_files(args.collect());
+        return;
+    }
+
+    // `contractus test --doc` 运行文档注释中的代码示例，没有参数时运行当前项目的
+    if args.next_if(|arg| arg == "test").is_some() {
+        doc_tests(args.collect());
+        return;
+    }
+
+    // `contractus demangle` 还原参数中的符号；没有参数时把标准输入中的符号替换后输出
+    if args.next_if(|arg| arg == "demangle").is_some() {
+        demangle_symbols(args.collect());
+        return;
+    }
 
-    println!("Contractus Compiler v0.1.0");
-    println!("Compiling: {}", filename);
+    // `contractus gen-syntax` 生成编辑器的语法文件
+    if args.next_if(|arg| arg == "gen-syntax").is_some() {
+        generate_syntax(args.collect());
+        return;
+    }
 
-    // 词法分析
-    let lexer = Lexer::new(&source);
-    let tokens = match lexer.tokenize() {
-        Ok(tokens) => {
-            println!("=== Lexical Analysis ===");
-            println!("Total tokens: {}", tokens.len());
-            tokens
+    // `contractus --explain E0308` 输出错误码的详细说明
+    if args.next_if(|arg| arg == "--explain").is_some() {
+        let (Some(code), None) = (args.next(), args.next()) else {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        };
+        match code.parse::<ErrorCode>() {
+            Ok(code) => print!("{}: {}\n\n{}", code, code.title(), code.explanation()),
+            Err(message) => {
+                eprintln!("error: {}", message);
+                process::exit(1);
+            }
         }
-        Err(errors) => {
-            for err in errors {
-                eprintln!("Lexical analysis failed: {}", err);
+        return;
+    }
+
+    // `contractus debug file.ctx` 在调试器中用解释器执行程序，命令从标准输入读取
+    let debug = args.next_if(|arg| arg == "debug").is_some();
+    // `contractus run file.ctx` 用解释器执行程序，加上 `--vm` 时编译为字节码后执行
+    let run = debug || args.next_if(|arg| arg == "run").is_some();
+    // `contractus build file.ctx -o main` 生成可执行文件
+    let build = !run && args.next_if(|arg| arg == "build").is_some();
+    // `contractus check file.ctx` 只报告错误，不生成代码
+    let check = !run && !build && args.next_if(|arg| arg == "check").is_some();
+    // `contractus bench file.ctx` 运行 `#[bench]` 函数，默认使用 release 配置
+    let bench = !run && !build && !check && args.next_if(|arg| arg == "bench").is_some();
+    let mut filter = None;
+
+    while let Some(arg) = args.next() {
+        if let Some(kind) = arg.strip_prefix("--emit=") {
+            emit = match Emit::parse(kind) {
+                Some(emit) => Some(emit),
+                None => {
+                    let kinds: Vec<_> = Emit::KINDS.iter().map(|(name, _)| *name).collect();
+                    eprintln!(
+                        "error: unknown emit kind `{}`; expected one of {}",
+                        kind,
+                        kinds.join(", ")
+                    );
+                    process::exit(1);
+                }
+            };
+        } else if let Some(level) = arg.strip_prefix("-O") {
+            opt_level = match level.parse() {
+                Ok(level) => Some(level),
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    process::exit(1);
+                }
+            };
+        } else if let Some(mode) = arg.strip_prefix("--contracts=") {
+            contracts = match mode.parse() {
+                Ok(mode) => Some(mode),
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    process::exit(1);
+                }
+            };
+        } else if let Some(strategy) = arg.strip_prefix("--panic=") {
+            panic = match strategy.parse() {
+                Ok(strategy) => Some(strategy),
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    process::exit(1);
+                }
+            };
+        } else if let Some(option) = arg.strip_prefix("-Z") {
+            match option {
+                "time-passes" => time_passes = true,
+                "time-passes-format=text" => time_passes_json = false,
+                "time-passes-format=json" => time_passes_json = true,
+                "token-text" => TOKEN_TEXT.store(true, Ordering::Relaxed),
+                _ if option.starts_with("incremental=") => {
+                    incremental = Some(PathBuf::from(&option["incremental=".len()..]));
+                }
+                _ => {
+                    eprintln!("error: unknown debugging option `-Z{}`", option);
+                    process::exit(1);
+                }
+            }
+        } else if arg == "--no-bounds-check" {
+            bounds_checks = Some(false);
+        } else if arg == "--release" {
+            profile_name = Some("release".to_string());
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            profile_name = Some(name.to_string());
+        } else if let Some(text) = arg.strip_prefix("--filter=").filter(|_| bench) {
+            filter = Some(text.to_string());
+        } else if arg == "--vm" && run && !debug {
+            use_vm = true;
+        } else if arg == "--" && run {
+            program_args.extend(args.by_ref());
+        } else if arg == "-o" && !run {
+            match args.next() {
+                Some(path) => output = Some(path),
+                None => {
+                    eprintln!("error: `-o` requires an output path");
+                    process::exit(1);
+                }
+            }
+        } else if let Some(triple) = arg.strip_prefix("--target=").filter(|_| !run) {
+            target = match triple.parse() {
+                Ok(target) => Some(target),
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    process::exit(1);
+                }
+            };
+        } else if let Some(linker) = arg.strip_prefix("--linker=").filter(|_| !run) {
+            link_options.linker = Some(linker.to_string());
+        } else if arg == "--error-limit" {
+            match args.next().map(|n| n.parse()) {
+                Some(Ok(limit)) => ERROR_LIMIT.store(limit, Ordering::Relaxed),
+                _ => {
+                    eprintln!("error: `--error-limit` requires a number");
+                    process::exit(1);
+                }
+            }
+        } else if arg == "--verify-determinism" {
+            VERIFY_DETERMINISM.store(true, Ordering::Relaxed);
+        } else if arg == "--root" {
+            match args.next() {
+                Some(dir) => root = Some(PathBuf::from(dir)),
+                None => {
+                    eprintln!("error: `--root` requires a directory");
+                    process::exit(1);
+                }
             }
+        } else if arg.starts_with("--") {
+            eprintln!("error: unknown option `{}`", arg);
+            eprintln!("{}", USAGE);
             process::exit(1);
+        } else {
+            files.push(arg);
         }
+    }
+
+    if emit == Some(Emit::Grammar) && !(run || build || check || bench) {
+        write_output(output.as_deref().map(Path::new), grammar::ebnf().as_bytes());
+        return;
+    }
+
+    // 没有输入时使用当前项目的清单
+    let project = if files.is_empty() && root.is_none() && (build || run || check || bench) {
+        find_project()
+    } else {
+        None
+    };
+
+    // `-o` 和 `--target` 只用于生成文件的命令
+    if (files.is_empty() == root.is_none() && project.is_none())
+        || (!build && emit.is_none() && (output.is_some() || target.is_some()))
+        || ((check || bench) && emit.is_some())
+    {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+    // `--emit=wasm` 默认生成 WASI 模块
+    link_options.target = match target {
+        Some(target) => target,
+        None if emit == Some(Emit::Wasm) => WASM_TARGET.parse().unwrap(),
+        None => Target::host(),
     };
+    if emit == Some(Emit::Wasm) && link_options.target.os != Os::Wasi {
+        eprintln!(
+            "error: `--emit=wasm` needs a WASI target such as `{}`, not `{}`",
+            WASM_TARGET, link_options.target
+        );
+        process::exit(1);
+    }
+
+    // `contractus run file.ctxb` 直接执行已编译的字节码
+    if let [file] = files.as_slice() {
+        let path = Path::new(file);
+        if run
+            && !debug
+            && path
+                .extension()
+                .is_some_and(|ext| ext == bytecode::EXTENSION)
+        {
+            run_bytecode_file(path, program_args);
+            return;
+        }
+    }
+
+    let _time_passes = time_passes.then(|| TimePasses::start(time_passes_json));
 
-    // 语法分析
-    let mut parser = Parser::new(tokens);
-    match parser.parse() {
-        Ok(program) => {
-            println!("=== Syntax Analysis ===");
-
-            // 分别统计函数和结构体
-            let mut functions = Vec::new();
-            let mut structs = Vec::new();
-
-            for item in program.items {
-                match item {
-                    contractus::Item::Function(func) => functions.push(func),
-                    contractus::Item::Struct(struct_) => structs.push(struct_),
-                    _ => (),
+    let default_profile = if bench { "release" } else { "debug" };
+    let profile_name = profile_name.unwrap_or_else(|| default_profile.to_string());
+    let profile = match &project {
+        Some(manifest) => manifest.profile(&profile_name).copied(),
+        None => Profile::builtin(&profile_name),
+    };
+    let Some(profile) = profile else {
+        eprintln!("error: unknown profile `{}`", profile_name);
+        process::exit(1);
+    };
+    let contracts = contracts.unwrap_or(profile.contracts);
+    let panic = panic.unwrap_or(profile.panic);
+    link_options.opt_level = opt_level.unwrap_or(profile.opt_level);
+    let compiler = Compiler::new()
+        .opt_level(link_options.opt_level)
+        .bounds_checks(bounds_checks.unwrap_or(profile.bounds_checks))
+        .contracts(contracts)
+        .panic(panic);
+
+    // 项目默认使用增量编译
+    let incremental = incremental.or_else(|| project.as_ref().map(Manifest::incremental_dir));
+    let compiler = match incremental {
+        Some(dir) => compiler.incremental(Arc::new(QueryCache::persistent(dir))),
+        None => compiler,
+    };
+
+    let compiler = match (&project, root) {
+        (Some(manifest), _) => {
+            // 依赖包先于项目检查，没有变化的包跳过
+            let deps = exit_on_errors(manifest.resolve_deps());
+            let deps_dir = manifest.deps_dir(&profile_name);
+            let (compiler, _) = exit_on_errors(compiler.with_dependencies(&deps, &deps_dir));
+            compiler.file(manifest.entry_path())
+        }
+        (None, Some(dir)) => compiler.root(dir),
+        (None, None) => compiler.files(&files),
+    };
+    let entry = compiler.entry().unwrap();
+    let path = entry.as_path();
+
+    if check {
+        let errors = compiler.check();
+        if !errors.is_empty() {
+            exit_on_errors::<()>(Err(errors));
+        }
+        return;
+    }
+
+    if bench {
+        run_benchmarks(&compiler, filter.as_deref());
+        return;
+    }
+
+    if let (true, Some(manifest)) = (build, &project) {
+        build_project(manifest, &profile_name, &compiler, output, &link_options);
+        return;
+    }
+
+    if build {
+        // 默认输出到当前目录下与源文件同名的可执行文件
+        let output = output
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(path.file_stem().unwrap_or(path.as_os_str())));
+        build_executable(&compiler, &output, &link_options);
+        return;
+    }
+
+    match emit {
+        Some(emit) => emit_output(emit, &compiler, path, output.as_deref(), &link_options),
+        None if run && use_vm => run_module(&compile_bytecode(&compiler), program_args),
+        None if run => run_program(&compiler, contracts, panic, program_args, debug),
+        None => print_summary(&compiler),
+    }
+}
+
+// 取出所有子命令共用的日志选项，返回其余的参数。`-v` 每次提高一级（info、debug、trace），
+// `-q` 只输出错误；CONTRACTUS_LOG 中的规则在此基础上生效
+fn init_logging(args: Vec<String>) -> Vec<String> {
+    let mut verbosity = 0;
+    let mut quiet = false;
+    let args = args
+        .into_iter()
+        .filter(|arg| {
+            match arg.as_str() {
+                "--verbose" => verbosity += 1,
+                "-q" | "--quiet" => quiet = true,
+                _ => match arg
+                    .strip_prefix('-')
+                    .filter(|v| !v.is_empty() && v.bytes().all(|b| b == b'v'))
+                {
+                    Some(v) => verbosity += v.len(),
+                    None => return true,
+                },
+            }
+            false
+        })
+        .collect();
+
+    let level = match (quiet, verbosity) {
+        (true, _) => log::Level::Error,
+        (false, 0) => log::Level::Warn,
+        (false, 1) => log::Level::Info,
+        (false, 2) => log::Level::Debug,
+        (false, _) => log::Level::Trace,
+    };
+    let mut filter = Filter::new(Some(level));
+    if let Ok(directives) = env::var(log::ENV) {
+        if let Err(message) = filter.add_directives(&directives) {
+            eprintln!("warning: ignoring {}: {}", log::ENV, message);
+            filter = Filter::new(Some(level));
+        }
+    }
+    log::init(filter);
+    args
+}
+
+// `-Ztime-passes`：从开始到 main 返回，记录编译器各阶段的时间和内存，
+// 返回时输出到标准错误。出错退出时不输出
+struct TimePasses {
+    json: bool,
+}
+
+impl TimePasses {
+    fn start(json: bool) -> Self {
+        timing::start();
+        Self { json }
+    }
+}
+
+impl Drop for TimePasses {
+    fn drop(&mut self) {
+        let passes = timing::finish();
+        if self.json {
+            eprint!("{}", timing::to_json(&passes));
+        } else {
+            eprint!("{}", timing::report(&passes));
+        }
+    }
+}
+
+fn exit_on_errors<T>(result: Result<T, Vec<Diagnostic>>) -> T {
+    result.unwrap_or_else(|errors| {
+        print_diagnostics(errors);
+        process::exit(1);
+    })
+}
+
+// 按文件分组输出；涉及多个文件时每组后面给出该文件的错误数。
+// 超过 `--error-limit` 的错误不输出，只给出省略的数目；最后提示可以用 `--explain` 查看错误码的说明
+fn print_diagnostics(diagnostics: Vec<Diagnostic>) {
+    let (diagnostics, omitted) =
+        diagnostic::limit(diagnostics, ERROR_LIMIT.load(Ordering::Relaxed));
+    let mut codes: Vec<ErrorCode> = diagnostics.iter().filter_map(|d| d.code).collect();
+    codes.sort();
+    codes.dedup();
+    let groups = diagnostic::group_by_file(diagnostics);
+    let summarize = groups.len() > 1;
+    for (i, (file, group)) in groups.into_iter().enumerate() {
+        if i > 0 && summarize {
+            eprintln!();
+        }
+        let count = group.iter().filter(|error| error.is_error()).count();
+        for error in group {
+            eprintln!("{}", error);
+        }
+        if let (true, Some(file)) = (summarize, file) {
+            let plural = if count == 1 { "" } else { "s" };
+            eprintln!("{}: {} error{}", file, count, plural);
+        }
+    }
+    if omitted > 0 {
+        let plural = if omitted == 1 { "" } else { "s" };
+        eprintln!(
+            "note: {} previous error{} omitted (use `--error-limit <n>` to show more, 0 for all)",
+            omitted, plural
+        );
+    }
+    match codes.as_slice() {
+        [] => {}
+        [code] => eprintln!(
+            "For more information about this error, try `contractus --explain {}`.",
+            code
+        ),
+        [first, ..] => {
+            let list: Vec<&str> = codes.iter().map(|code| code.as_str()).collect();
+            eprintln!(
+                "Some errors have detailed explanations: {}.",
+                list.join(", ")
+            );
+            eprintln!(
+                "For more information about an error, try `contractus --explain {}`.",
+                first
+            );
+        }
+    }
+}
+
+// `--check` 只报告没有格式化的文件，有这样的文件时退出码为 1；`-` 从标准输入读取，
+// 结果写到标准输出
+fn format_files(args: Vec<String>) {
+    let mut check = false;
+    let mut options = FormatOptions::default();
+    let mut files = Vec::new();
+    for arg in args {
+        if arg == "--check" {
+            check = true;
+        } else if let Some(width) = arg.strip_prefix("--width=") {
+            options.width = match width.parse() {
+                Ok(width) if width > 0 => width,
+                _ => {
+                    eprintln!("error: invalid width `{}`", width);
+                    process::exit(1);
                 }
+            };
+        } else if arg.starts_with("--") {
+            eprintln!("error: unknown option `{}`", arg);
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        } else {
+            files.push(PathBuf::from(arg));
+        }
+    }
+
+    if files.len() == 1 && files[0] == Path::new("-") {
+        let mut source = String::new();
+        if let Err(error) = io::stdin().read_to_string(&mut source) {
+            eprintln!("error: cannot read standard input: {}", error);
+            process::exit(1);
+        }
+        let output = exit_on_errors(format::format_source(&source, &options));
+        if check && output != source {
+            eprintln!("standard input is not formatted");
+            process::exit(1);
+        }
+        print!("{}", output);
+        return;
+    }
+
+    if files.is_empty() {
+        let Some(manifest) = find_project() else {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        };
+        files = manifest.sources().unwrap_or_else(|error| {
+            eprintln!("error: cannot read the project sources: {}", error);
+            process::exit(1);
+        });
+    }
+
+    let mut failed = false;
+    let mut errors = Vec::new();
+    for file in &files {
+        let name = file.display().to_string();
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("error: cannot read `{}`: {}", name, error);
+                failed = true;
+                continue;
+            }
+        };
+        let output = match format::format_source(&source, &options) {
+            Ok(output) => output,
+            Err(diagnostics) => {
+                errors.extend(diagnostics.into_iter().map(|d| d.with_file(name.clone())));
+                continue;
             }
+        };
+        if output == source {
+            continue;
+        }
+        if check {
+            eprintln!("{} is not formatted", name);
+            failed = true;
+        } else if let Err(error) = fs::write(file, output) {
+            eprintln!("error: cannot write `{}`: {}", name, error);
+            failed = true;
+        }
+    }
+    if !errors.is_empty() {
+        print_diagnostics(errors);
+        failed = true;
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+// 运行文档测试，输出每个测试的结果，最后列出失败测试的诊断信息
+fn doc_tests(args: Vec<String>) {
+    let mut doc = false;
+    let mut files = Vec::new();
+    for arg in args {
+        if arg == "--doc" {
+            doc = true;
+        } else if arg.starts_with("--") {
+            eprintln!("error: unknown option `{}`", arg);
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        } else {
+            files.push(PathBuf::from(arg));
+        }
+    }
+    if !doc {
+        eprintln!("error: only documentation tests are supported; pass `--doc`");
+        process::exit(1);
+    }
+    if files.is_empty() {
+        let Some(manifest) = find_project() else {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        };
+        files = manifest.sources().unwrap_or_else(|error| {
+            eprintln!("error: cannot read the project sources: {}", error);
+            process::exit(1);
+        });
+    }
+
+    // (文件名, 源码, 测试)
+    let mut tests = Vec::new();
+    let mut errors = Vec::new();
+    for file in &files {
+        let name = file.display().to_string();
+        let source = fs::read_to_string(file).unwrap_or_else(|error| {
+            eprintln!("error: cannot read `{}`: {}", name, error);
+            process::exit(1);
+        });
+        match doctest::extract(&source) {
+            Ok(found) if !found.is_empty() => tests.push((name, source, found)),
+            Ok(_) => {}
+            Err(diagnostics) => {
+                errors.extend(diagnostics.into_iter().map(|d| d.with_file(name.clone())))
+            }
+        }
+    }
+    if !errors.is_empty() {
+        exit_on_errors::<()>(Err(errors));
+    }
 
-            println!("Structs: {}", structs.len());
-            println!("Functions: {}", functions.len());
+    let count: usize = tests.iter().map(|(_, _, found)| found.len()).sum();
+    let plural = if count == 1 { "" } else { "s" };
+    println!("running {} doc test{}", count, plural);
+    let (mut passed, mut ignored) = (0, 0);
+    let mut failures = Vec::new();
+    for (name, source, test) in tests
+        .iter()
+        .flat_map(|(name, source, found)| found.iter().map(move |test| (name, source, test)))
+    {
+        let title = format!("{} - {}", name, test.name());
+        if test.expect == doctest::Expect::Ignore {
+            println!("test {} ... ignored", title);
+            ignored += 1;
+            continue;
+        }
+        match interp::with_large_stack(|| doctest::run(source, test)) {
+            Ok(()) => {
+                println!("test {} ... ok", title);
+                passed += 1;
+            }
+            Err(diagnostics) => {
+                println!("test {} ... FAILED", title);
+                failures.push((title, name, diagnostics));
+            }
+        }
+    }
 
-            for struct_ in &structs {
-                println!("  struct {}: {} fields", struct_.name, struct_.fields.len());
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (title, name, diagnostics) in &failures {
+            println!("\n---- {} ----", title);
+            for diagnostic in diagnostics {
+                println!("{}", diagnostic.clone().with_file(name.to_string()));
             }
+        }
+    }
+    let result = if failures.is_empty() { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} ignored",
+        result,
+        passed,
+        failures.len(),
+        ignored
+    );
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn demangle_symbols(symbols: Vec<String>) {
+    if symbols.is_empty() {
+        let mut input = String::new();
+        if let Err(error) = io::stdin().read_to_string(&mut input) {
+            eprintln!("error: cannot read standard input: {}", error);
+            process::exit(1);
+        }
+        print!("{}", mangle::demangle_text(&input));
+        return;
+    }
+    let mut failed = false;
+    for symbol in symbols {
+        match mangle::demangle(&symbol) {
+            Ok(name) => println!("{}", name),
+            Err(message) => {
+                eprintln!("error: {}", message);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+// 没有 `-o` 时把主文件写到标准输出；`-o <dir>` 把所有文件写到目录中
+fn generate_syntax(args: Vec<String>) {
+    let mut format = None;
+    let mut output = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--format=") {
+            format = Some(name.parse::<SyntaxFormat>().unwrap_or_else(|error| {
+                eprintln!("error: {}", error);
+                process::exit(1);
+            }));
+        } else if arg == "-o" {
+            output = Some(args.next().map(PathBuf::from).unwrap_or_else(|| {
+                eprintln!("error: `-o` needs a directory");
+                process::exit(1);
+            }));
+        } else {
+            eprintln!("error: unknown argument `{}`", arg);
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    }
+    let Some(format) = format else {
+        eprintln!("error: `gen-syntax` needs `--format=tmlanguage` or `--format=tree-sitter`");
+        process::exit(1);
+    };
+
+    let files = syntax::generate(format);
+    let Some(dir) = output else {
+        print!("{}", files[0].1);
+        return;
+    };
+    for (name, contents) in files {
+        let path = dir.join(name);
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, contents));
+        if let Err(error) = written {
+            eprintln!("error: cannot write `{}`: {}", path.display(), error);
+            process::exit(1);
+        }
+    }
+}
+
+// 语义分析通过后用解释器执行根模块的 main，其他模块的函数一起载入。命令行运行的程序可以使用 `io`、`env` 和 `time` 模块，
+// 以 `io::exit` 给出的退出码或 `main() -> i32` 的返回值结束；`debug` 时在命令行调试器中执行
+fn run_program(
+    compiler: &Compiler,
+    contracts: ContractMode,
+    panic: PanicStrategy,
+    args: Vec<String>,
+    debug: bool,
+) {
+    let krate = compile(compiler, driver::Emit::Hir).into_crate().unwrap();
+    let root = krate.root_module();
+    let file = root.file.display().to_string();
+    let source = if debug {
+        fs::read_to_string(&root.file).unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let program = krate.merged_program();
+    let (result, exit_code) = interp::with_large_stack(|| {
+        let mut interpreter = Interpreter::new(&program);
+        interpreter.set_contract_mode(contracts);
+        interpreter.set_panic_strategy(panic);
+        interpreter.set_host(Host::unrestricted(args));
+        if debug {
+            let stdin = io::stdin();
+            interpreter.set_debugger(debugger::CommandLine::new(
+                &root.program,
+                &file,
+                &source,
+                stdin.lock(),
+                io::stdout(),
+            ));
+        }
+        let result = interpreter.run_main().map(drop);
+        (result, interpreter.exit_code())
+    });
+    // 运行时错误带上源文件名，与字节码虚拟机一致
+    exit_on_errors(result.map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|error| error.with_file(file.clone()))
+            .collect()
+    }));
+    exit_with(exit_code);
+}
+
+// 在虚拟机中执行，与 `run_program` 相同地打开 io、env 和 time
+fn run_module(module: &bytecode::Module, args: Vec<String>) {
+    let mut vm = Vm::new(module, io::stdout());
+    vm.set_host(Host::unrestricted(args));
+    let result = vm.run_main();
+    let exit_code = vm.exit_code();
+    exit_on_errors(result);
+    exit_with(exit_code);
+}
+
+fn exit_with(exit_code: Option<i32>) {
+    if let Some(code) = exit_code {
+        let _ = io::stdout().flush();
+        process::exit(code);
+    }
+}
+
+// 编译后逐个运行名字包含 `filter` 的 `#[bench]` 函数
+fn run_benchmarks(compiler: &Compiler, filter: Option<&str>) {
+    let program = lower_to_mir(compiler);
+    let mut module = exit_on_errors(bytecode::compile(&program));
+    module.file = compiler.entry().map(|path| path.display().to_string());
 
-            for func in &functions {
-                println!("  fn {}: {} params", func.name, func.params.len());
+    let benches: Vec<&String> = program
+        .benches
+        .iter()
+        .filter(|name| filter.is_none_or(|filter| name.contains(filter)))
+        .collect();
+    let filtered = program.benches.len() - benches.len();
+    let plural = if benches.len() == 1 { "" } else { "s" };
+    println!("running {} benchmark{}", benches.len(), plural);
+
+    let width = benches.iter().map(|name| name.len()).max().unwrap_or(0);
+    let options = BenchOptions::default();
+    let (mut measured, mut failed) = (0, 0);
+    let mut errors = Vec::new();
+    for name in benches {
+        match bench::run(&module, name, &options) {
+            Ok(result) => {
+                println!("test {:width$} ... bench: {}", name, result);
+                measured += 1;
+            }
+            Err(diagnostics) => {
+                println!("test {:width$} ... FAILED", name);
+                errors.extend(diagnostics);
+                failed += 1;
             }
+        }
+    }
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} measured; {} failed; {} filtered out",
+        result, measured, failed, filtered
+    );
+    if !errors.is_empty() {
+        exit_on_errors::<()>(Err(errors));
+    }
+}
+
+fn compile(compiler: &Compiler, emit: driver::Emit) -> Artifact {
+    let compiler = compiler.clone().emit(emit);
+    if !VERIFY_DETERMINISM.load(Ordering::Relaxed) {
+        return exit_on_errors(compiler.run().into_result());
+    }
+    match compiler.verify_determinism() {
+        Ok(output) => exit_on_errors(output.into_result()),
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::exit(1);
+        }
+    }
+}
+
+fn lower_to_mir(compiler: &Compiler) -> MirProgram {
+    compile(compiler, driver::Emit::Mir).into_mir().unwrap()
+}
+
+fn compile_bytecode(compiler: &Compiler) -> bytecode::Module {
+    compile(compiler, driver::Emit::Object)
+        .into_object()
+        .unwrap()
+}
 
-            println!("✅ Parsing successful!");
+// 输出 `--emit` 指定的中间结果
+fn emit_output(
+    emit: Emit,
+    compiler: &Compiler,
+    path: &Path,
+    output: Option<&str>,
+    options: &LinkOptions,
+) {
+    let bytes = match emit {
+        Emit::Tokens => {
+            let tokens = compile(compiler, driver::Emit::Tokens)
+                .into_tokens()
+                .unwrap();
+            // 记号不保存源码文本，需要时重新读入入口文件
+            let source = TOKEN_TEXT
+                .load(Ordering::Relaxed)
+                .then(|| SourceFile::new(path, fs::read(path).unwrap_or_default()));
+            let mut text = String::new();
+            for token in tokens {
+                text.push_str(&format!(
+                    "{}:{}  {:?}",
+                    token.span.line, token.span.column, token.kind
+                ));
+                if let Some(source) = &source {
+                    text.push_str(&format!("  `{}`", token.text(source)));
+                }
+                text.push('\n');
+            }
+            text.into_bytes()
+        }
+        // 只看入口文件的源码，不经过编译器，有错误的文件也能高亮
+        Emit::TokensJson | Emit::TokensCsv => {
+            let source = fs::read_to_string(path).unwrap_or_else(|error| {
+                eprintln!("error: cannot read `{}`: {}", path.display(), error);
+                process::exit(1);
+            });
+            let tokens = highlight::classify(&source);
+            match emit {
+                Emit::TokensJson => highlight::to_json(&tokens).into_bytes(),
+                _ => highlight::to_csv(&tokens).into_bytes(),
+            }
+        }
+        Emit::Ast | Emit::AstJson => {
+            let krate = compile(compiler, driver::Emit::Ast).into_crate().unwrap();
+            let program = &krate.root_module().program;
+            if emit == Emit::AstJson {
+                program.to_json().into_bytes()
+            } else {
+                format!("{:#?}\n", program).into_bytes()
+            }
+        }
+        // 还原的源码交给格式化器排版
+        Emit::Expanded => {
+            let krate = compile(compiler, driver::Emit::Ast).into_crate().unwrap();
+            let text = krate.root_module().program.to_source();
+            format::format_source(&text, &FormatOptions::default())
+                .unwrap_or(text)
+                .into_bytes()
+        }
+        // 所有模块，按模块路径排序
+        Emit::Hir => {
+            let krate = compile(compiler, driver::Emit::Hir).into_crate().unwrap();
+            let mut text = String::new();
+            for module in krate.modules.values() {
+                text.push_str(&format!(
+                    "// module {} ({})\n{:#?}\n",
+                    module.display_name(),
+                    module.file.display(),
+                    module.program
+                ));
+            }
+            text.into_bytes()
         }
-        Err(errors) => {
-            eprintln!("=== Parse Errors ===");
-            for error in errors {
-                eprintln!("{}", error);
+        Emit::Mir => lower_to_mir(compiler).to_string().into_bytes(),
+        Emit::Asm => compile_bytecode(compiler).to_string().into_bytes(),
+        Emit::Grammar => grammar::ebnf().into_bytes(),
+        Emit::Bytecode => bytecode::encode(&compile_bytecode(compiler)),
+        Emit::Obj | Emit::Wasm => {
+            let default = if emit == Emit::Obj {
+                path.with_extension(options.target.object_suffix())
+            } else {
+                path.with_extension(options.target.exe_suffix())
+            };
+            let module = compile_bytecode(compiler);
+            // 写到标准输出时先生成到临时文件
+            let file = match output {
+                Some("-") => env::temp_dir().join(format!(
+                    "contractus-emit-{}.{}",
+                    process::id(),
+                    default.extension().unwrap().to_string_lossy()
+                )),
+                Some(output) => PathBuf::from(output),
+                None => default,
+            };
+            let result = if emit == Emit::Obj {
+                link::build_object(&module, &file, options)
+            } else {
+                link::build_executable(&module, &file, options)
+            };
+            if let Err(message) = result {
+                eprintln!("error: {}", message);
+                process::exit(1);
+            }
+            if output != Some("-") {
+                return;
             }
+            let bytes = fs::read(&file).unwrap_or_else(|error| {
+                eprintln!("error: cannot read `{}`: {}", file.display(), error);
+                process::exit(1);
+            });
+            let _ = fs::remove_file(&file);
+            bytes
+        }
+    };
+
+    let output = match output {
+        Some(output) => Some(PathBuf::from(output)),
+        None if emit == Emit::Bytecode => Some(path.with_extension(bytecode::EXTENSION)),
+        None => None,
+    };
+    write_output(output.as_deref(), &bytes);
+}
+
+// 写到 `-o` 指定的文件，没有指定或者是 `-` 时写到标准输出
+fn write_output(output: Option<&Path>, bytes: &[u8]) {
+    let result = match output {
+        Some(file) if file != Path::new("-") => fs::write(file, bytes),
+        _ => io::stdout().write_all(bytes),
+    };
+    if let Err(error) = result {
+        let target = output.map_or("standard output".to_string(), |file| {
+            format!("`{}`", file.display())
+        });
+        eprintln!("error: cannot write {}: {}", target, error);
+        process::exit(1);
+    }
+}
+
+// 从当前目录向上查找并读取项目清单
+fn find_project() -> Option<Manifest> {
+    let dir = env::current_dir().ok()?;
+    let path = Manifest::find(&dir)?;
+    log::info!("using project manifest `{}`", path.display());
+    Some(exit_on_errors(Manifest::load(&path)))
+}
+
+// 按清单的输出类型生成到 `target/<配置名>/` 下
+fn build_project(
+    manifest: &Manifest,
+    profile: &str,
+    compiler: &Compiler,
+    output: Option<String>,
+    options: &LinkOptions,
+) {
+    let output = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest.output_path(profile));
+    if let Some(dir) = output.parent() {
+        if let Err(error) = fs::create_dir_all(dir) {
+            eprintln!("error: cannot create `{}`: {}", dir.display(), error);
             process::exit(1);
         }
     }
+    match manifest.package.output {
+        OutputKind::Executable => build_executable(compiler, &output, options),
+        OutputKind::Bytecode => {
+            let module = compile_bytecode(compiler);
+            if let Err(error) = fs::write(&output, bytecode::encode(&module)) {
+                eprintln!("error: cannot write `{}`: {}", output.display(), error);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn build_executable(compiler: &Compiler, output: &Path, options: &LinkOptions) {
+    let module = compile_bytecode(compiler);
+    if let Err(message) = link::build_executable(&module, output, options) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
+
+fn run_bytecode_file(path: &Path, args: Vec<String>) {
+    let bytes = fs::read(path).unwrap_or_else(|error| {
+        eprintln!("error: cannot read `{}`: {}", path.display(), error);
+        process::exit(1);
+    });
+    let module = bytecode::decode(&bytes).unwrap_or_else(|message| {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    });
+    run_module(&module, args);
+}
+
+// 只做词法和语法分析，打印根模块的概要
+fn print_summary(compiler: &Compiler) {
+    let krate = compile(compiler, driver::Emit::Ast).into_crate().unwrap();
+
+    log::info!("Contractus compiler v{}", env!("CARGO_PKG_VERSION"));
+    log::info!("compiling {}", compiler.entry().unwrap().display());
+    println!("=== Syntax Analysis ===");
+    println!("Modules: {}", krate.modules.len());
+
+    // 分别统计函数和结构体
+    let mut functions = Vec::new();
+    let mut structs = Vec::new();
+
+    for item in &krate.root_module().program.items {
+        match item {
+            contractus::Item::Function(func) => functions.push(func),
+            contractus::Item::Struct(struct_) => structs.push(struct_),
+            _ => (),
+        }
+    }
+
+    println!("Structs: {}", structs.len());
+    println!("Functions: {}", functions.len());
+
+    for struct_ in &structs {
+        println!("  struct {}: {} fields", struct_.name, struct_.fields.len());
+    }
+
+    for func in &functions {
+        println!("  fn {}: {} params", func.name, func.params.len());
+    }
+
+    println!("✅ Parsing successful!");
 }
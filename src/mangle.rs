@@ -0,0 +1,416 @@
+// 符号名修饰（mangling）
+// 字节码的函数表、`.ctxb` 文件和链接出的可执行文件都用修饰后的名字标识函数，
+// 同名的函数（不同模块、不同的泛型实例、不同的签名）得到不同的符号：
+//
+//     symbol    = "_CX" path [generics] hash
+//     path      = "N" component* "E"             模块路径加函数名，闭包追加在所属函数之后
+//     component = <长度> <标识符> | "C" <序号> "_"   后者是 `{closure#序号}`
+//     generics  = "I" type* "E"                  单态化实例的类型实参
+//     hash      = "h" <16 位十六进制>             参数和返回类型的 FNV-1a 哈希，区分重载
+//
+// 类型的编码见 `encode_type`。标识符中字母、数字和下划线以外的字节写成 `$` 加两位十六进制，
+// 因此符号只包含链接器接受的字符。哈希只依赖签名的文本，不随编译器版本和运行环境变化
+
+use crate::ast::Type;
+use crate::mir::Body;
+use std::fmt::{self, Write as _};
+
+/// 修饰后的符号的前缀
+pub const PREFIX: &str = "_CX";
+
+/// 修饰前的符号
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub path: Vec<String>, // 模块路径、函数名，以及闭包的 `{closure#N}`
+    pub generics: Vec<Type>,
+    pub hash: u64,
+}
+
+impl Symbol {
+    pub fn new(path: Vec<String>, generics: Vec<Type>, params: &[Type], ret: &Type) -> Self {
+        Self {
+            path,
+            generics,
+            hash: signature_hash(params, ret),
+        }
+    }
+
+    /// MIR 函数体的符号。函数体名中的 `::<...>` 是泛型实参，由 `type_args` 给出
+    pub fn for_body(module: &[String], body: &Body) -> Self {
+        let mut path = module.to_vec();
+        path.extend(
+            split_path(&body.name)
+                .into_iter()
+                .filter(|segment| !segment.starts_with('<')),
+        );
+        let params: Vec<Type> = body
+            .args()
+            .map(|local| body.local_decl(local).ty.clone())
+            .collect();
+        Self::new(path, body.type_args.clone(), &params, body.return_ty())
+    }
+
+    pub fn mangle(&self) -> String {
+        let mut out = String::from(PREFIX);
+        out.push('N');
+        for component in &self.path {
+            match closure_index(component) {
+                Some(index) => {
+                    let _ = write!(out, "C{}_", index);
+                }
+                None => encode_ident(&mut out, component),
+            }
+        }
+        out.push('E');
+        if !self.generics.is_empty() {
+            out.push('I');
+            for ty in &self.generics {
+                encode_type(&mut out, ty);
+            }
+            out.push('E');
+        }
+        let _ = write!(out, "h{:016x}", self.hash);
+        out
+    }
+
+    /// 解析修饰后的符号，整个字符串必须是一个符号
+    pub fn parse(symbol: &str) -> Result<Symbol, String> {
+        let mut reader = Reader {
+            bytes: symbol.as_bytes(),
+            pos: 0,
+        };
+        let parsed = reader
+            .symbol()
+            .ok_or_else(|| format!("invalid symbol `{}`", symbol))?;
+        if reader.pos != symbol.len() {
+            return Err(format!("invalid symbol `{}`: trailing characters", symbol));
+        }
+        Ok(parsed)
+    }
+}
+
+// 修饰前的写法：`math::max::<i32>::{closure#0}`，泛型实参跟在函数名之后
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let function = self
+            .path
+            .iter()
+            .rposition(|component| closure_index(component).is_none());
+        for (i, component) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, "::")?;
+            }
+            write!(f, "{}", component)?;
+            if Some(i) == function && !self.generics.is_empty() {
+                let args: Vec<String> = self.generics.iter().map(Type::to_string).collect();
+                write!(f, "::<{}>", args.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 修饰函数名
+pub fn mangle(path: &[String], generics: &[Type], params: &[Type], ret: &Type) -> String {
+    Symbol::new(path.to_vec(), generics.to_vec(), params, ret).mangle()
+}
+
+/// 还原修饰前的名字，不含签名哈希
+pub fn demangle(symbol: &str) -> Result<String, String> {
+    Symbol::parse(symbol).map(|symbol| symbol.to_string())
+}
+
+/// 还原一段文本中出现的所有符号，其余内容原样保留（`contractus demangle` 的过滤模式）
+pub fn demangle_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PREFIX) {
+        out.push_str(&rest[..start]);
+        let mut reader = Reader {
+            bytes: &rest.as_bytes()[start..],
+            pos: 0,
+        };
+        match reader.symbol() {
+            Some(symbol) => {
+                out.push_str(&symbol.to_string());
+                rest = &rest[start + reader.pos..];
+            }
+            None => {
+                out.push_str(PREFIX);
+                rest = &rest[start + PREFIX.len()..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 签名哈希：参数和返回类型文本的 64 位 FNV-1a
+pub fn signature_hash(params: &[Type], ret: &Type) -> u64 {
+    let params: Vec<String> = params.iter().map(Type::to_string).collect();
+    let signature = format!("fn({}) -> {}", params.join(", "), ret);
+    signature.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// 按深度为 0 的 `::` 切分函数体名，泛型实参里的 `::` 不切分
+fn split_path(name: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'<' | b'(' | b'[' => depth += 1,
+            b'>' | b')' | b']' => depth = depth.saturating_sub(1),
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => {
+                segments.push(name[start..i].to_string());
+                i += 2;
+                start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    segments.push(name[start..].to_string());
+    segments
+}
+
+fn closure_index(component: &str) -> Option<u32> {
+    component
+        .strip_prefix("{closure#")?
+        .strip_suffix('}')?
+        .parse()
+        .ok()
+}
+
+fn encode_ident(out: &mut String, ident: &str) {
+    let mut escaped = String::new();
+    for byte in ident.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'_' {
+            escaped.push(byte as char);
+        } else {
+            let _ = write!(escaped, "${:02x}", byte);
+        }
+    }
+    let _ = write!(out, "{}{}", escaped.len(), escaped);
+}
+
+// 基础类型是一个小写字母；复合类型以大写字母开头：
+// `A<长度>_T` 数组、`ST` 切片、`T...E` 元组、`RT`/`QT` 引用/可变引用、
+// `PT`/`OT` 指针/可变指针、`G<名字>...E` 泛型类型、`F...ER` 函数类型，
+// 其他命名类型直接写标识符
+fn encode_type(out: &mut String, ty: &Type) {
+    let code = match ty {
+        Type::I8 => 'a',
+        Type::I16 => 's',
+        Type::I32 => 'i',
+        Type::I64 => 'x',
+        Type::Isize => 'l',
+        Type::U8 => 'h',
+        Type::U16 => 't',
+        Type::U32 => 'j',
+        Type::U64 => 'y',
+        Type::Usize => 'm',
+        Type::F32 => 'f',
+        Type::F64 => 'd',
+        Type::Bool => 'b',
+        Type::Char => 'c',
+        Type::String => 'r',
+        Type::Unit => 'v',
+        Type::Never => 'z',
+        Type::Infer => 'u',
+        Type::Array(elem, len) => {
+            let _ = write!(out, "A{}_", len);
+            return encode_type(out, elem);
+        }
+        Type::Slice(elem) => {
+            out.push('S');
+            return encode_type(out, elem);
+        }
+        Type::Tuple(types) => {
+            out.push('T');
+            types.iter().for_each(|ty| encode_type(out, ty));
+            out.push('E');
+            return;
+        }
+        Type::Reference(inner, mutable) | Type::Pointer(inner, mutable) => {
+            out.push(match (ty, mutable) {
+                (Type::Reference(..), false) => 'R',
+                (Type::Reference(..), true) => 'Q',
+                (_, false) => 'P',
+                (_, true) => 'O',
+            });
+            return encode_type(out, inner);
+        }
+        Type::Named(name) => return encode_ident(out, name),
+        // 符号中的类型都已经推断出来
+        Type::TypeOf(_) => return encode_type(out, &ty.expand_typeof()),
+        Type::Generic(name, args) => {
+            out.push('G');
+            encode_ident(out, name);
+            args.iter().for_each(|ty| encode_type(out, ty));
+            out.push('E');
+            return;
+        }
+        // `pure fn` 和 `fn` 的值表示相同，名字中不区分
+        Type::Function(params, ret, _) => {
+            out.push('F');
+            params.iter().for_each(|ty| encode_type(out, ty));
+            out.push('E');
+            return encode_type(out, ret);
+        }
+    };
+    out.push(code);
+}
+
+// 解析失败时返回 None，由调用者给出错误信息
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn symbol(&mut self) -> Option<Symbol> {
+        self.expect_str(PREFIX)?;
+        self.expect(b'N')?;
+        let mut path = Vec::new();
+        while !self.eat(b'E') {
+            if self.eat(b'C') {
+                let index = self.number()?;
+                self.expect(b'_')?;
+                path.push(format!("{{closure#{}}}", index));
+            } else {
+                path.push(self.ident()?);
+            }
+        }
+        if path.is_empty() {
+            return None;
+        }
+        let mut generics = Vec::new();
+        if self.eat(b'I') {
+            while !self.eat(b'E') {
+                generics.push(self.ty()?);
+            }
+        }
+        self.expect(b'h')?;
+        let digits = self.bytes.get(self.pos..self.pos + 16)?;
+        let hash = u64::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+        self.pos += 16;
+        Some(Symbol {
+            path,
+            generics,
+            hash,
+        })
+    }
+
+    fn ty(&mut self) -> Option<Type> {
+        let byte = *self.bytes.get(self.pos)?;
+        if byte.is_ascii_digit() {
+            return self.ident().map(Type::Named);
+        }
+        self.pos += 1;
+        let ty = match byte {
+            b'a' => Type::I8,
+            b's' => Type::I16,
+            b'i' => Type::I32,
+            b'x' => Type::I64,
+            b'l' => Type::Isize,
+            b'h' => Type::U8,
+            b't' => Type::U16,
+            b'j' => Type::U32,
+            b'y' => Type::U64,
+            b'm' => Type::Usize,
+            b'f' => Type::F32,
+            b'd' => Type::F64,
+            b'b' => Type::Bool,
+            b'c' => Type::Char,
+            b'r' => Type::String,
+            b'v' => Type::Unit,
+            b'z' => Type::Never,
+            b'u' => Type::Infer,
+            b'A' => {
+                let len = self.number()?;
+                self.expect(b'_')?;
+                Type::Array(Box::new(self.ty()?), len as usize)
+            }
+            b'S' => Type::Slice(Box::new(self.ty()?)),
+            b'T' => Type::Tuple(self.types()?),
+            b'R' => Type::Reference(Box::new(self.ty()?), false),
+            b'Q' => Type::Reference(Box::new(self.ty()?), true),
+            b'P' => Type::Pointer(Box::new(self.ty()?), false),
+            b'O' => Type::Pointer(Box::new(self.ty()?), true),
+            b'G' => {
+                let name = self.ident()?;
+                Type::Generic(name, self.types()?)
+            }
+            b'F' => {
+                let params = self.types()?;
+                Type::Function(params, Box::new(self.ty()?), false)
+            }
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    // 以 `E` 结尾的类型列表
+    fn types(&mut self) -> Option<Vec<Type>> {
+        let mut types = Vec::new();
+        while !self.eat(b'E') {
+            types.push(self.ty()?);
+        }
+        Some(types)
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        let len = self.number()? as usize;
+        let escaped = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        let mut bytes = Vec::with_capacity(len);
+        let mut i = 0;
+        while i < escaped.len() {
+            if escaped[i] == b'$' {
+                let hex = std::str::from_utf8(escaped.get(i + 1..i + 3)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            } else if escaped[i].is_ascii_alphanumeric() || escaped[i] == b'_' {
+                bytes.push(escaped[i]);
+                i += 1;
+            } else {
+                return None;
+            }
+        }
+        String::from_utf8(bytes).ok()
+    }
+
+    fn number(&mut self) -> Option<u64> {
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.bytes.get(self.pos) == Some(&byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+
+    fn expect_str(&mut self, text: &str) -> Option<()> {
+        let found = self.bytes[self.pos..].starts_with(text.as_bytes());
+        found.then(|| self.pos += text.len())
+    }
+}
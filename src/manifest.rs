@@ -0,0 +1,561 @@
+// Contractus 项目清单（Contractus.toml）
+// 在项目目录中不带输入文件执行 `contractus build/run/check` 时，从当前目录向上查找清单：
+//
+//     [package]
+//     name = "hello"
+//     entry = "src/main.ctx"    # 默认 main.ctx，相对于清单所在目录
+//     output = "exe"            # exe：可执行文件；bytecode：.ctxb 字节码
+//
+//     [deps]
+//     math = { path = "../math" }
+//
+//     [profile.release]
+//     opt-level = 2             # 0、1、2 或 "s"
+//     bounds-checks = true
+//     contracts = "check"       # check 或 off
+//     panic = "unwind"          # unwind 或 abort
+//
+// 内置 debug（默认）和 release 两个配置，清单中的同名配置只覆盖写出的设置；
+// 其他名字的配置以 debug 为基础。命令行上的 -O 等选项优先于配置。
+// 构建产物放在 `target/<配置名>/` 下
+//
+// 路径依赖递归解析，依赖包先于依赖方检查；依赖的名字就是它在依赖方中的命名空间
+// （`import math::vector;`）。检查通过的依赖包在 `target/<配置名>/deps/` 下记录源码指纹，
+// 指纹没有变化时不再检查
+
+mod toml;
+
+pub use toml::{Entry, Table, TomlError, Value};
+
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::mir::transform::OptLevel;
+use crate::mir::{ContractMode, PanicStrategy};
+use crate::span::Span;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 清单文件名
+pub const MANIFEST_FILE: &str = "Contractus.toml";
+
+/// 构建产物所在的目录
+pub const TARGET_DIR: &str = "target";
+
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub dir: PathBuf, // 清单所在的项目目录
+    pub package: Package,
+    pub deps: BTreeMap<String, Dependency>,
+    pub profiles: BTreeMap<String, Profile>, // 包括内置的 debug 和 release
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub entry: PathBuf,
+    pub output: OutputKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputKind {
+    #[default]
+    Executable,
+    Bytecode,
+}
+
+/// 本地路径依赖，路径相对于清单所在目录
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub path: PathBuf,
+    pub span: Span,
+}
+
+/// 解析出的依赖包：它在依赖方中的名字和它自己的清单
+#[derive(Debug, Clone)]
+pub struct ResolvedDependency {
+    pub name: String,
+    pub manifest: Manifest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    pub opt_level: OptLevel,
+    pub bounds_checks: bool,
+    pub contracts: ContractMode,
+    pub panic: PanicStrategy,
+}
+
+impl Profile {
+    pub fn debug() -> Self {
+        Self {
+            opt_level: OptLevel::O0,
+            bounds_checks: true,
+            contracts: ContractMode::Check,
+            panic: PanicStrategy::Unwind,
+        }
+    }
+
+    pub fn release() -> Self {
+        Self {
+            opt_level: OptLevel::O2,
+            ..Self::debug()
+        }
+    }
+
+    /// 内置配置，没有清单时也可以使用
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Self::debug()),
+            "release" => Some(Self::release()),
+            _ => None,
+        }
+    }
+}
+
+impl Manifest {
+    /// 从 `start` 开始向上查找清单文件
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// 读取并检查清单，出错时诊断信息带上清单的路径
+    pub fn load(path: &Path) -> Result<Manifest, Vec<Diagnostic>> {
+        let display = path.display().to_string();
+        let source = fs::read_to_string(path).map_err(|err| {
+            vec![Diagnostic::error(
+                format!("cannot read `{}`: {}", display, err),
+                Span::new(0, 0, 1, 1),
+            )
+            .with_code(ErrorCode::E0800)
+            .with_file(display.clone())]
+        })?;
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Self::parse(&source, &dir).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| error.with_file(display.clone()))
+                .collect()
+        })
+    }
+
+    pub fn parse(source: &str, dir: &Path) -> Result<Manifest, Vec<Diagnostic>> {
+        let table = toml::parse(source).map_err(|error| {
+            vec![Diagnostic::error(error.message, error.span).with_code(ErrorCode::E0850)]
+        })?;
+        let mut checker = Checker { errors: Vec::new() };
+        let manifest = checker.manifest(&table, dir);
+        match manifest {
+            Some(manifest) if checker.errors.is_empty() => Ok(manifest),
+            _ => Err(checker.errors),
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    pub fn entry_path(&self) -> PathBuf {
+        self.dir.join(&self.package.entry)
+    }
+
+    /// 指定配置的构建产物目录
+    pub fn output_dir(&self, profile: &str) -> PathBuf {
+        self.dir.join(TARGET_DIR).join(profile)
+    }
+
+    /// 增量编译的缓存目录，各配置共用：编译选项是缓存键的一部分
+    pub fn incremental_dir(&self) -> PathBuf {
+        self.dir
+            .join(TARGET_DIR)
+            .join(crate::incremental::INCREMENTAL_DIR)
+    }
+
+    /// 依赖包的检查记录所在的目录
+    pub fn deps_dir(&self, profile: &str) -> PathBuf {
+        self.output_dir(profile).join("deps")
+    }
+
+    /// 递归读取所有路径依赖的清单，按依赖顺序排列：被依赖的包在前。
+    /// 同一个名字只能指向一个目录，依赖不能成环
+    pub fn resolve_deps(&self) -> Result<Vec<ResolvedDependency>, Vec<Diagnostic>> {
+        let mut resolver = Resolver {
+            stack: vec![(canonical_dir(&self.dir), self.package.name.clone())],
+            resolved: Vec::new(),
+            errors: Vec::new(),
+        };
+        resolver.visit(self);
+        if resolver.errors.is_empty() {
+            Ok(resolver.resolved)
+        } else {
+            Err(resolver.errors)
+        }
+    }
+
+    /// 入口所在目录下所有的源文件，按路径排序
+    pub fn sources(&self) -> std::io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        if let Some(root) = self.entry_path().parent() {
+            crate::module::discover_sources(root, &mut files)?;
+        }
+        Ok(files)
+    }
+
+    /// 包的源码指纹：清单和入口所在目录下所有源文件的 64 位 FNV-1a，
+    /// 读不出的文件按空内容计算
+    pub fn fingerprint(&self) -> u64 {
+        let mut files = vec![self.dir.join(MANIFEST_FILE)];
+        // 目录读不出时只计算清单，之后加载模块时会报告错误
+        files.extend(self.sources().unwrap_or_default());
+        let mut hash = FNV_OFFSET;
+        for file in &files {
+            let relative = file.strip_prefix(&self.dir).unwrap_or(file);
+            let contents = fs::read(file).unwrap_or_default();
+            for bytes in [relative.to_string_lossy().as_bytes(), &[0], &contents, &[0]] {
+                hash = fnv1a(hash, bytes);
+            }
+        }
+        hash
+    }
+
+    fn manifest_file(&self) -> String {
+        self.dir.join(MANIFEST_FILE).display().to_string()
+    }
+
+    /// 构建产物的默认路径：可执行文件以包名命名，字节码再加上扩展名
+    pub fn output_path(&self, profile: &str) -> PathBuf {
+        let path = self.output_dir(profile).join(&self.package.name);
+        match self.package.output {
+            OutputKind::Executable => path,
+            OutputKind::Bytecode => path.with_extension(crate::bytecode::EXTENSION),
+        }
+    }
+}
+
+// 深度优先解析依赖，包在它的依赖都解析完之后加入结果
+struct Resolver {
+    stack: Vec<(PathBuf, String)>, // 正在解析的包的目录和包名，用于检测依赖环
+    resolved: Vec<ResolvedDependency>,
+    errors: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn visit(&mut self, manifest: &Manifest) {
+        for (name, dep) in &manifest.deps {
+            let dir = manifest.dir.join(&dep.path);
+            let key = canonical_dir(&dir);
+            let error = |message: String| {
+                Diagnostic::error(message, dep.span)
+                    .with_code(ErrorCode::E0851)
+                    .with_file(manifest.manifest_file())
+            };
+
+            if let Some(pos) = self.stack.iter().position(|(dir, _)| *dir == key) {
+                let cycle: Vec<String> = self.stack[pos..]
+                    .iter()
+                    .chain(std::iter::once(&self.stack[pos]))
+                    .map(|(_, package)| format!("`{}`", package))
+                    .collect();
+                self.errors.push(error(format!(
+                    "dependency cycle detected: {}",
+                    cycle.join(" → ")
+                )));
+                continue;
+            }
+            // 同名的依赖只解析一次
+            let conflict = |resolved: &[ResolvedDependency]| {
+                let existing = resolved.iter().find(|r| r.name == *name)?;
+                Some((canonical_dir(&existing.manifest.dir) != key).then(|| {
+                    error(format!(
+                        "dependency `{}` refers to both `{}` and `{}`",
+                        name,
+                        existing.manifest.dir.display(),
+                        dir.display()
+                    ))
+                }))
+            };
+            if let Some(conflict) = conflict(&self.resolved) {
+                self.errors.extend(conflict);
+                continue;
+            }
+
+            let path = dir.join(MANIFEST_FILE);
+            if !path.is_file() {
+                self.errors.push(error(format!(
+                    "cannot find `{}` for dependency `{}` in `{}`",
+                    MANIFEST_FILE,
+                    name,
+                    dir.display()
+                )));
+                continue;
+            }
+            let dep_manifest = match Manifest::load(&path) {
+                Ok(dep_manifest) => dep_manifest,
+                Err(errors) => {
+                    self.errors.extend(errors);
+                    continue;
+                }
+            };
+            self.stack
+                .push((key.clone(), dep_manifest.package.name.clone()));
+            self.visit(&dep_manifest);
+            self.stack.pop();
+            // 它的依赖中可能已经有同名的包
+            if let Some(conflict) = conflict(&self.resolved) {
+                self.errors.extend(conflict);
+                continue;
+            }
+            self.resolved.push(ResolvedDependency {
+                name: name.clone(),
+                manifest: dep_manifest,
+            });
+        }
+    }
+}
+
+fn canonical_dir(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+struct Checker {
+    errors: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn manifest(&mut self, table: &Table, dir: &Path) -> Option<Manifest> {
+        self.known_keys(table, "", &["package", "deps", "profile"]);
+
+        let package = match table.get("package") {
+            Some(entry) => self.table(entry, "package").and_then(|t| self.package(t)),
+            None => {
+                self.error(
+                    "missing `[package]` table".to_string(),
+                    Span::new(0, 0, 1, 1),
+                );
+                None
+            }
+        };
+
+        let mut deps = BTreeMap::new();
+        if let Some(table) = table
+            .get("deps")
+            .and_then(|entry| self.table(entry, "deps"))
+        {
+            for (name, entry) in table {
+                if let Some(dep) = self.dependency(name, entry) {
+                    deps.insert(name.clone(), dep);
+                }
+            }
+        }
+
+        let mut profiles: BTreeMap<String, Profile> = ["debug", "release"]
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), Profile::builtin(name)?)))
+            .collect();
+        if let Some(table) = table
+            .get("profile")
+            .and_then(|entry| self.table(entry, "profile"))
+        {
+            for (name, entry) in table {
+                let base = profiles.get(name).copied().unwrap_or_else(Profile::debug);
+                let key = format!("profile.{}", name);
+                if let Some(settings) = self.table(entry, &key) {
+                    let profile = self.profile(settings, base, &key);
+                    profiles.insert(name.clone(), profile);
+                }
+            }
+        }
+
+        Some(Manifest {
+            dir: dir.to_path_buf(),
+            package: package?,
+            deps,
+            profiles,
+        })
+    }
+
+    fn package(&mut self, table: &Table) -> Option<Package> {
+        self.known_keys(table, "package", &["name", "entry", "output"]);
+        let name = match table.get("name") {
+            Some(entry) => {
+                let name = self.string(entry, "package.name")?;
+                if !is_package_name(name) {
+                    self.error(format!("invalid package name `{}`", name), entry.span);
+                    return None;
+                }
+                name.to_string()
+            }
+            None => {
+                self.error("missing `package.name`".to_string(), Span::new(0, 0, 1, 1));
+                return None;
+            }
+        };
+        let entry = match table.get("entry") {
+            Some(entry) => PathBuf::from(self.string(entry, "package.entry")?),
+            None => PathBuf::from(crate::module::ENTRY_FILE),
+        };
+        let output = match table.get("output") {
+            Some(entry) => match self.string(entry, "package.output")? {
+                "exe" => OutputKind::Executable,
+                "bytecode" => OutputKind::Bytecode,
+                other => {
+                    self.error(
+                        format!(
+                            "invalid output kind `{}`; expected `exe` or `bytecode`",
+                            other
+                        ),
+                        entry.span,
+                    );
+                    return None;
+                }
+            },
+            None => OutputKind::default(),
+        };
+        Some(Package {
+            name,
+            entry,
+            output,
+        })
+    }
+
+    fn dependency(&mut self, name: &str, entry: &Entry) -> Option<Dependency> {
+        let key = format!("deps.{}", name);
+        let table = self.table(entry, &key)?;
+        self.known_keys(table, &key, &["path"]);
+        match table.get("path") {
+            Some(path) => Some(Dependency {
+                path: PathBuf::from(self.string(path, &format!("{}.path", key))?),
+                span: entry.span,
+            }),
+            None => {
+                self.error(format!("dependency `{}` needs a `path`", name), entry.span);
+                None
+            }
+        }
+    }
+
+    fn profile(&mut self, table: &Table, mut profile: Profile, key: &str) -> Profile {
+        self.known_keys(
+            table,
+            key,
+            &["opt-level", "bounds-checks", "contracts", "panic"],
+        );
+        if let Some(entry) = table.get("opt-level") {
+            let level = match &entry.value {
+                Value::Integer(n) => n.to_string(),
+                Value::String(s) => s.clone(),
+                _ => String::new(),
+            };
+            match level.parse() {
+                Ok(level) => profile.opt_level = level,
+                Err(message) => self.error(message, entry.span),
+            }
+        }
+        if let Some(entry) = table.get("bounds-checks") {
+            match &entry.value {
+                Value::Boolean(enabled) => profile.bounds_checks = *enabled,
+                other => self.type_error(
+                    &format!("{}.bounds-checks", key),
+                    "boolean",
+                    other,
+                    entry.span,
+                ),
+            }
+        }
+        if let Some(entry) = table.get("contracts") {
+            if let Some(mode) = self.string(entry, &format!("{}.contracts", key)) {
+                match mode.parse() {
+                    Ok(mode) => profile.contracts = mode,
+                    Err(message) => self.error(message, entry.span),
+                }
+            }
+        }
+        if let Some(entry) = table.get("panic") {
+            if let Some(strategy) = self.string(entry, &format!("{}.panic", key)) {
+                match strategy.parse() {
+                    Ok(strategy) => profile.panic = strategy,
+                    Err(message) => self.error(message, entry.span),
+                }
+            }
+        }
+        profile
+    }
+
+    fn table<'a>(&mut self, entry: &'a Entry, key: &str) -> Option<&'a Table> {
+        match &entry.value {
+            Value::Table(table) => Some(table),
+            other => {
+                self.type_error(key, "table", other, entry.span);
+                None
+            }
+        }
+    }
+
+    fn string<'a>(&mut self, entry: &'a Entry, key: &str) -> Option<&'a str> {
+        match &entry.value {
+            Value::String(s) => Some(s),
+            other => {
+                self.type_error(key, "string", other, entry.span);
+                None
+            }
+        }
+    }
+
+    fn known_keys(&mut self, table: &Table, prefix: &str, known: &[&str]) {
+        for (key, entry) in table {
+            if !known.contains(&key.as_str()) {
+                let full = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                self.errors.push(
+                    Diagnostic::error(format!("unknown key `{}`", full), entry.span)
+                        .with_code(ErrorCode::E0850)
+                        .with_help(format!("expected one of {}", quote_list(known))),
+                );
+            }
+        }
+    }
+
+    fn type_error(&mut self, key: &str, expected: &str, found: &Value, span: Span) {
+        self.error(
+            format!(
+                "`{}` must be a {}, found {}",
+                key,
+                expected,
+                found.type_name()
+            ),
+            span,
+        );
+    }
+
+    fn error(&mut self, message: String, span: Span) {
+        self.errors
+            .push(Diagnostic::error(message, span).with_code(ErrorCode::E0850));
+    }
+}
+
+// 包名用作可执行文件名和依赖的命名空间，必须是标识符
+fn is_package_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn quote_list(items: &[&str]) -> String {
+    let quoted: Vec<String> = items.iter().map(|item| format!("`{}`", item)).collect();
+    quoted.join(", ")
+}
@@ -0,0 +1,381 @@
+// 清单文件用到的 TOML 子集
+// 支持：注释、`[a.b]` 表头、裸键/带引号的键和点分键、基本字符串和字面字符串、
+// 整数（可带 `_` 分隔）、布尔值、数组（可以跨行）和内联表。
+// 不支持：多行字符串、浮点数、日期时间和 `[[数组表]]`
+// 每个值记录它在文件中的位置，清单检查出错时可以指向具体的行
+
+use crate::span::Span;
+use std::collections::BTreeMap;
+
+pub type Table = BTreeMap<String, Entry>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Entry>),
+    Table(Table),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub value: Value,
+    pub span: Span,
+}
+
+impl Value {
+    /// 出错信息中使用的类型名
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlError {
+    pub message: String,
+    pub span: Span,
+}
+
+pub fn parse(input: &str) -> Result<Table, TomlError> {
+    let mut parser = TomlParser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    parser.document()
+}
+
+struct TomlParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: u32,
+    column: u32,
+}
+
+impl TomlParser {
+    fn document(&mut self) -> Result<Table, TomlError> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+        // 用表头显式定义过的表，不能再定义一次
+        let mut headers: Vec<Vec<String>> = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            let Some(c) = self.peek() else {
+                return Ok(root);
+            };
+            if c == '[' {
+                let span = self.span();
+                self.bump();
+                if self.peek() == Some('[') {
+                    return Err(self.error_at("arrays of tables are not supported", span));
+                }
+                let path = self.key_path()?;
+                self.expect(']')?;
+                if headers.contains(&path) {
+                    return Err(self.error_at(
+                        &format!("table `{}` is defined more than once", path.join(".")),
+                        span,
+                    ));
+                }
+                table_at(&mut root, &path, span)?;
+                headers.push(path.clone());
+                current = path;
+            } else {
+                let span = self.span();
+                let path = self.key_path()?;
+                self.expect('=')?;
+                let value = self.value()?;
+                let table = table_at(&mut root, &current, span)?;
+                insert(table, &path, value, span)?;
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    // a.b."c d"
+    fn key_path(&mut self) -> Result<Vec<String>, TomlError> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_spaces();
+            path.push(self.key()?);
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(path);
+            }
+            self.bump();
+        }
+    }
+
+    fn key(&mut self) -> Result<String, TomlError> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.bump();
+                }
+                if self.pos == start {
+                    return Err(self.error("expected a key"));
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Entry, TomlError> {
+        self.skip_spaces();
+        let span = self.span();
+        let value = match self.peek() {
+            Some('"') => Value::String(self.basic_string()?),
+            Some('\'') => Value::String(self.literal_string()?),
+            Some('[') => Value::Array(self.array()?),
+            Some('{') => Value::Table(self.inline_table()?),
+            Some(c) if c == '-' || c == '+' || c.is_ascii_digit() => self.integer()?,
+            Some(c) if c.is_ascii_alphabetic() => {
+                let word = self.key()?;
+                match word.as_str() {
+                    "true" => Value::Boolean(true),
+                    "false" => Value::Boolean(false),
+                    _ => return Err(self.error_at(&format!("invalid value `{}`", word), span)),
+                }
+            }
+            _ => return Err(self.error("expected a value")),
+        };
+        Ok(Entry { value, span })
+    }
+
+    fn integer(&mut self) -> Result<Value, TomlError> {
+        let span = self.span();
+        let start = self.pos;
+        if matches!(self.peek(), Some('-' | '+')) {
+            self.bump();
+        }
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            self.bump();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| self.error_at(&format!("invalid integer `{}`", text), span))
+    }
+
+    fn array(&mut self) -> Result<Vec<Entry>, TomlError> {
+        self.bump();
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(values);
+            }
+            values.push(self.value()?);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(',') => self.bump(),
+                Some(']') => {}
+                _ => return Err(self.error("expected `,` or `]` in array")),
+            }
+        }
+    }
+
+    // 内联表必须写在一行内
+    fn inline_table(&mut self) -> Result<Table, TomlError> {
+        self.bump();
+        let mut table = Table::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(table);
+        }
+        loop {
+            let span = self.span();
+            let path = self.key_path()?;
+            self.expect('=')?;
+            let value = self.value()?;
+            insert(&mut table, &path, value, span)?;
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.bump(),
+                Some('}') => {
+                    self.bump();
+                    return Ok(table);
+                }
+                _ => return Err(self.error("expected `,` or `}` in inline table")),
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, TomlError> {
+        let span = self.span();
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error_at("unterminated string", span)),
+                Some('"') => {
+                    self.bump();
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.bump();
+                    let escaped = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    self.bump();
+                    text.push(escaped);
+                }
+                Some(c) => {
+                    self.bump();
+                    text.push(c);
+                }
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, TomlError> {
+        let span = self.span();
+        self.bump();
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error_at("unterminated string", span)),
+                Some('\'') => {
+                    let text = self.chars[start..self.pos].iter().collect();
+                    self.bump();
+                    return Ok(text);
+                }
+                Some(_) => self.bump(),
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), TomlError> {
+        self.skip_spaces();
+        match self.peek() {
+            None | Some('\n') | Some('#') => Ok(()),
+            Some(c) => Err(self.error(&format!("unexpected `{}` after value", c))),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), TomlError> {
+        self.skip_spaces();
+        if self.peek() == Some(expected) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", expected)))
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r')) {
+            self.bump();
+        }
+    }
+
+    // 空白、换行和注释
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some('\n') => self.bump(),
+                Some('#') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.pos, self.pos + 1, self.line, self.column)
+    }
+
+    fn error(&self, message: &str) -> TomlError {
+        self.error_at(message, self.span())
+    }
+
+    fn error_at(&self, message: &str, span: Span) -> TomlError {
+        TomlError {
+            message: message.to_string(),
+            span,
+        }
+    }
+}
+
+// 沿路径找到（必要时创建）子表
+fn table_at<'a>(
+    root: &'a mut Table,
+    path: &[String],
+    span: Span,
+) -> Result<&'a mut Table, TomlError> {
+    let mut table = root;
+    for (i, key) in path.iter().enumerate() {
+        let entry = table.entry(key.clone()).or_insert_with(|| Entry {
+            value: Value::Table(Table::new()),
+            span,
+        });
+        table = match &mut entry.value {
+            Value::Table(table) => table,
+            _ => {
+                return Err(TomlError {
+                    message: format!("`{}` is not a table", path[..=i].join(".")),
+                    span,
+                })
+            }
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Table, path: &[String], value: Entry, span: Span) -> Result<(), TomlError> {
+    let (key, parents) = path.split_last().expect("empty key path");
+    let table = table_at(table, parents, span)?;
+    if table.contains_key(key) {
+        return Err(TomlError {
+            message: format!("duplicate key `{}`", path.join(".")),
+            span,
+        });
+    }
+    table.insert(key.clone(), value);
+    Ok(())
+}
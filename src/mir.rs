@@ -0,0 +1,713 @@
+// Contractus 中间表示 (MIR)
+// 设计原则：
+// 1. 每个函数降级为一个 `Body`：局部变量表 + 基本块组成的显式控制流图
+// 2. 基本块由若干语句（赋值、存储标记）和一个终结符（跳转、分支、调用、返回）组成
+// 3. 所有表达式都被拆成对 place 的简单操作，控制流（if/while/for/match/&&/||）全部显式化
+// 4. 局部变量、临时变量、语句和终结符都保留 span，便于后续阶段报告错误
+// 所有后端（解释器、字节码、C 代码生成）都只消费这里定义的 API
+// 文本输出见 pretty.rs（`--emit=mir`）
+
+mod build;
+pub mod dataflow;
+mod monomorphize;
+mod pretty;
+pub mod transform;
+
+pub(crate) use build::collect_expr_names;
+pub use build::{lower_program, lower_program_incremental, lower_program_with};
+pub use monomorphize::{monomorphize, RECURSION_LIMIT, TYPE_LENGTH_LIMIT};
+
+use crate::ast::{AsmDir, AsmReg, BinOp, ContractKind, EnumDef, Generics, StructDef, Type, UnOp};
+use crate::builtins;
+use crate::layout::Layouts;
+use crate::span::Span;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// 契约（requires/ensures/invariant）的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContractMode {
+    /// 在函数入口、返回前和结构体构造之后插入运行时检查
+    #[default]
+    Check,
+    /// 不生成检查，条件也不求值
+    Off,
+}
+
+impl FromStr for ContractMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "check" => Ok(ContractMode::Check),
+            "off" => Ok(ContractMode::Off),
+            _ => Err(format!(
+                "invalid contract mode `{}`; expected `check` or `off`",
+                s
+            )),
+        }
+    }
+}
+
+/// panic 的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicStrategy {
+    /// 沿调用栈展开，经过的函数在清理块中析构它们的变量；`catch_panic` 可以捕获
+    #[default]
+    Unwind,
+    /// 立即结束程序，不生成清理块，`catch_panic` 不捕获
+    Abort,
+}
+
+impl FromStr for PanicStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unwind" => Ok(PanicStrategy::Unwind),
+            "abort" => Ok(PanicStrategy::Abort),
+            _ => Err(format!(
+                "invalid panic strategy `{}`; expected `unwind` or `abort`",
+                s
+            )),
+        }
+    }
+}
+
+/// MIR 构建选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowerOptions {
+    pub contracts: ContractMode,
+    pub panic: PanicStrategy,
+}
+
+/// 局部变量编号：`_0` 是返回值，`_1.._n` 是参数，之后是用户变量和临时变量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Local(pub usize);
+
+impl Local {
+    pub const RETURN: Local = Local(0);
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// 基本块编号，`bb0` 是函数入口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BasicBlock(pub usize);
+
+impl BasicBlock {
+    pub const START: BasicBlock = BasicBlock(0);
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// 函数体中的一个位置：`statement_index` 等于语句数时指向终结符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    pub block: BasicBlock,
+    pub statement_index: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalKind {
+    ReturnPointer,
+    Arg,
+    Var,  // 用户声明的变量
+    Temp, // 编译器生成的临时变量
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalDecl {
+    pub name: Option<String>,
+    pub ty: Type, // 推断不出时为 Type::Infer
+    pub mutable: bool,
+    pub kind: LocalKind,
+    pub span: Span,
+}
+
+/// 函数体
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub name: String,
+    pub type_params: Vec<String>, // 泛型函数的类型参数，单态化之后为空
+    pub type_args: Vec<Type>,     // 单态化实例的类型实参，与泛型函数的类型参数一一对应
+    pub arg_count: usize,
+    pub locals: Vec<LocalDecl>,
+    pub blocks: Vec<BasicBlockData>,
+    pub span: Span,
+}
+
+impl Body {
+    pub fn is_generic(&self) -> bool {
+        !self.type_params.is_empty()
+    }
+
+    pub fn return_ty(&self) -> &Type {
+        &self.locals[Local::RETURN.index()].ty
+    }
+
+    pub fn local_decl(&self, local: Local) -> &LocalDecl {
+        &self.locals[local.index()]
+    }
+
+    pub fn block(&self, block: BasicBlock) -> &BasicBlockData {
+        &self.blocks[block.index()]
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = Local> {
+        (1..=self.arg_count).map(Local)
+    }
+
+    pub fn basic_blocks(&self) -> impl Iterator<Item = (BasicBlock, &BasicBlockData)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(i, data)| (BasicBlock(i), data))
+    }
+
+    /// 基本块终结符所在的位置
+    pub fn terminator_loc(&self, block: BasicBlock) -> Location {
+        Location {
+            block,
+            statement_index: self.block(block).statements.len(),
+        }
+    }
+
+    /// 从入口可达的基本块的逆后序，前向数据流按这个顺序迭代收敛最快
+    pub fn reverse_postorder(&self) -> Vec<BasicBlock> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::new();
+        // 显式栈模拟递归：(块, 下一个要访问的后继序号)
+        let mut stack = vec![(BasicBlock::START, 0)];
+        visited[BasicBlock::START.index()] = true;
+        while let Some((block, next)) = stack.pop() {
+            let successors = self.block(block).terminator.successors();
+            if let Some(&succ) = successors.get(next) {
+                stack.push((block, next + 1));
+                if !visited[succ.index()] {
+                    visited[succ.index()] = true;
+                    stack.push((succ, 0));
+                }
+            } else {
+                postorder.push(block);
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    /// 每个基本块的前驱列表
+    pub fn predecessors(&self) -> Vec<Vec<BasicBlock>> {
+        let mut preds = vec![Vec::new(); self.blocks.len()];
+        for (block, data) in self.basic_blocks() {
+            for succ in data.terminator.successors() {
+                preds[succ.index()].push(block);
+            }
+        }
+        preds
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlockData {
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+    Assign(Place, Rvalue),
+    StorageLive(Local),
+    StorageDead(Local),
+    /// 内联汇编，读取输入操作数之后写入所有输出位置
+    InlineAsm(Box<InlineAsm>),
+    Nop,
+}
+
+/// 内联汇编：模板和选项原样保留给生成机器码的后端，操作数已经降级为操作数和 place。
+/// 解释执行的后端不能执行汇编
+#[derive(Debug, Clone)]
+pub struct InlineAsm {
+    pub template: Vec<String>,
+    pub operands: Vec<AsmOperand>,
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmOperand {
+    pub dir: AsmDir,
+    pub reg: AsmReg,
+    pub input: Option<Operand>, // `in` 和 `inout` 读取的值
+    pub output: Option<Place>,  // `out`、`lateout` 和 `inout` 写入的位置，写成 `_` 时为 None
+}
+
+impl InlineAsm {
+    pub fn inputs(&self) -> impl Iterator<Item = &Operand> {
+        self.operands
+            .iter()
+            .filter_map(|operand| operand.input.as_ref())
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &Place> {
+        self.operands
+            .iter()
+            .filter_map(|operand| operand.output.as_ref())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Terminator {
+    pub kind: TerminatorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum TerminatorKind {
+    Goto {
+        target: BasicBlock,
+    },
+    /// 按整数值分支；bool 按 0/1，字符按码点，枚举判别值按变体序号。各分支的取值互不相同
+    SwitchInt {
+        discr: Operand,
+        targets: Vec<(i64, BasicBlock)>,
+        otherwise: BasicBlock,
+    },
+    Return,
+    /// 函数调用：`target` 为 None 表示被调函数不返回，
+    /// `unwind` 是 panic 时析构变量的清理块，为 None 表示没有要析构的变量，直接向调用者展开
+    Call {
+        func: Operand,
+        args: Vec<Operand>,
+        destination: Place,
+        target: Option<BasicBlock>,
+        unwind: Option<BasicBlock>,
+    },
+    /// 检查 `cond`，为假时以 `msg` panic，为真时继续执行 `target`
+    Assert {
+        cond: Operand,
+        msg: AssertMessage,
+        target: BasicBlock,
+        unwind: Option<BasicBlock>,
+    },
+    /// 析构 place 中的值，之后 place 视为未初始化
+    Drop {
+        place: Place,
+        target: BasicBlock,
+        unwind: Option<BasicBlock>,
+    },
+    /// 清理块的结尾：继续向调用者展开 panic
+    Resume,
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BasicBlock> {
+        self.kind.successors()
+    }
+}
+
+impl TerminatorKind {
+    /// 终结符读取的所有 place，不包括 `Return` 隐含读取的返回值
+    pub fn read_places(&self) -> Vec<&Place> {
+        match self {
+            TerminatorKind::SwitchInt { discr, .. } => discr.place().into_iter().collect(),
+            TerminatorKind::Call { func, args, .. } => func
+                .place()
+                .into_iter()
+                .chain(args.iter().filter_map(Operand::place))
+                .collect(),
+            TerminatorKind::Assert { cond, msg, .. } => cond
+                .place()
+                .into_iter()
+                .chain(msg.operands().into_iter().filter_map(Operand::place))
+                .collect(),
+            TerminatorKind::Drop { place, .. } => vec![place],
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Resume
+            | TerminatorKind::Unreachable => Vec::new(),
+        }
+    }
+
+    pub fn successors(&self) -> Vec<BasicBlock> {
+        match self {
+            TerminatorKind::Goto { target } => vec![*target],
+            TerminatorKind::SwitchInt {
+                targets, otherwise, ..
+            } => targets
+                .iter()
+                .map(|(_, target)| *target)
+                .chain(std::iter::once(*otherwise))
+                .collect(),
+            TerminatorKind::Call { target, unwind, .. } => {
+                target.iter().chain(unwind.iter()).copied().collect()
+            }
+            TerminatorKind::Assert { target, unwind, .. }
+            | TerminatorKind::Drop { target, unwind, .. } => {
+                std::iter::once(*target).chain(*unwind).collect()
+            }
+            TerminatorKind::Return | TerminatorKind::Resume | TerminatorKind::Unreachable => {
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BasicBlock> {
+        match self {
+            TerminatorKind::Goto { target } => vec![target],
+            TerminatorKind::SwitchInt {
+                targets, otherwise, ..
+            } => targets
+                .iter_mut()
+                .map(|(_, target)| target)
+                .chain(std::iter::once(otherwise))
+                .collect(),
+            TerminatorKind::Call { target, unwind, .. } => {
+                target.iter_mut().chain(unwind.iter_mut()).collect()
+            }
+            TerminatorKind::Assert { target, unwind, .. }
+            | TerminatorKind::Drop { target, unwind, .. } => {
+                std::iter::once(target).chain(unwind.iter_mut()).collect()
+            }
+            TerminatorKind::Return | TerminatorKind::Resume | TerminatorKind::Unreachable => {
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// `Assert` 失败时的 panic 消息，操作数只在失败时求值
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssertMessage {
+    /// 下标越界：`index` 不在 `0..len` 中
+    BoundsCheck { len: Operand, index: Operand },
+    /// 违反契约：`owner` 是声明契约的函数或结构体，`declared` 是子句的位置；
+    /// 前置和后置条件在调用处报告，不变式在构造处（assert 本身的位置）报告
+    Contract {
+        kind: ContractKind,
+        owner: String,
+        clause: String,
+        declared: Span,
+    },
+}
+
+impl AssertMessage {
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            AssertMessage::BoundsCheck { len, index } => vec![len, index],
+            AssertMessage::Contract { .. } => Vec::new(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            AssertMessage::BoundsCheck { len, index } => vec![len, index],
+            AssertMessage::Contract { .. } => Vec::new(),
+        }
+    }
+}
+
+/// 内存位置：局部变量加上一串投影（解引用、字段、下标、子切片、枚举变体）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub local: Local,
+    pub projection: Vec<PlaceElem>,
+}
+
+impl Place {
+    pub fn local(local: Local) -> Self {
+        Self {
+            local,
+            projection: Vec::new(),
+        }
+    }
+
+    pub fn project(&self, elem: PlaceElem) -> Self {
+        let mut place = self.clone();
+        place.projection.push(elem);
+        place
+    }
+
+    /// 不带投影的 place 直接就是局部变量
+    pub fn as_local(&self) -> Option<Local> {
+        if self.projection.is_empty() {
+            Some(self.local)
+        } else {
+            None
+        }
+    }
+
+    /// 不经过解引用和下标的 place 属于局部变量本身，移出时可以跟踪初始化状态
+    pub fn is_owned_by_local(&self) -> bool {
+        !self.projection.iter().any(|elem| {
+            matches!(
+                elem,
+                PlaceElem::Deref | PlaceElem::Index(_) | PlaceElem::Subslice(_, _)
+            )
+        })
+    }
+
+    /// 投影中作为下标读取的局部变量
+    pub fn index_locals(&self) -> impl Iterator<Item = Local> + '_ {
+        self.projection.iter().flat_map(|elem| match elem {
+            PlaceElem::Index(local) => vec![*local],
+            PlaceElem::Subslice(from, to) => vec![*from, *to],
+            _ => Vec::new(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaceElem {
+    Deref,
+    Field(String), // 结构体字段名，元组和变体的位置字段用 "0"、"1"...
+    Index(Local),
+    Subslice(Local, Local), // `[from..to]`，不含 to，类型是切片
+    Downcast(String),       // 把枚举值视为某个变体，之后才能访问其字段
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Copy(Place),
+    /// 转移所有权：读取之后 place 视为未初始化，不再析构
+    Move(Place),
+    Constant(Constant),
+}
+
+impl Operand {
+    pub fn place(&self) -> Option<&Place> {
+        match self {
+            Operand::Copy(place) | Operand::Move(place) => Some(place),
+            Operand::Constant(_) => None,
+        }
+    }
+
+    pub fn constant(&self) -> Option<&Constant> {
+        match self {
+            Operand::Constant(constant) => Some(constant),
+            Operand::Copy(_) | Operand::Move(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constant {
+    pub value: ConstValue,
+    pub ty: Type,
+}
+
+impl Constant {
+    pub fn int(value: i64, ty: Type) -> Self {
+        Self {
+            value: ConstValue::Int(value),
+            ty,
+        }
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Self {
+            value: ConstValue::Bool(value),
+            ty: Type::Bool,
+        }
+    }
+
+    pub fn unit() -> Self {
+        Self {
+            value: ConstValue::Unit,
+            ty: Type::Unit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    Unit,
+    Function(String),  // 函数项，作为调用目标或函数指针
+    Static(String),    // 读取全局静态变量
+    StaticRef(String), // 静态变量的引用，赋值和取引用时通过它解引用，地址不变
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rvalue {
+    Use(Operand),
+    BinaryOp(BinOp, Operand, Operand, Option<Box<Type>>), // 按这个整数类型的宽度检查溢出，见 ast::OperandType
+    UnaryOp(UnOp, Operand, Option<Box<Type>>), // 只有 Neg / LogicalNot / BitwiseNot，类型同 BinaryOp
+    Ref(Place, bool),                          // mutable flag
+    Aggregate(AggregateKind, Vec<Operand>),
+    Cast(Operand, Type),
+    Len(Place),          // 数组或切片长度
+    Discriminant(Place), // 枚举值的变体序号
+}
+
+impl Rvalue {
+    /// 右值中的所有操作数
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Rvalue::Use(operand) | Rvalue::UnaryOp(_, operand, _) | Rvalue::Cast(operand, _) => {
+                vec![operand]
+            }
+            Rvalue::BinaryOp(_, lhs, rhs, _) => vec![lhs, rhs],
+            Rvalue::Aggregate(_, operands) => operands.iter().collect(),
+            Rvalue::Ref(_, _) | Rvalue::Len(_) | Rvalue::Discriminant(_) => Vec::new(),
+        }
+    }
+
+    /// 右值读取的所有 place（取引用也算读取）
+    pub fn read_places(&self) -> Vec<&Place> {
+        match self {
+            Rvalue::Use(operand) | Rvalue::UnaryOp(_, operand, _) | Rvalue::Cast(operand, _) => {
+                operand.place().into_iter().collect()
+            }
+            Rvalue::BinaryOp(_, lhs, rhs, _) => {
+                lhs.place().into_iter().chain(rhs.place()).collect()
+            }
+            Rvalue::Aggregate(_, operands) => operands.iter().filter_map(Operand::place).collect(),
+            Rvalue::Ref(place, _) | Rvalue::Len(place) | Rvalue::Discriminant(place) => {
+                vec![place]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateKind {
+    Tuple,
+    Array(Type),                 // 元素类型
+    Struct(String, Vec<String>), // 结构体名和各操作数对应的字段名
+    Variant(String, String),     // 枚举名和变体名
+    Range(bool),                 // inclusive flag
+    Closure(String),             // 闭包函数体名，操作数是按值捕获的变量
+}
+
+/// 整个程序的 MIR：函数体加上类型定义（供后端计算布局）
+#[derive(Debug, Clone, Default)]
+pub struct MirProgram {
+    pub structs: Vec<StructDef>,
+    pub enums: Vec<EnumDef>,
+    pub bodies: Vec<Body>,
+    pub statics: Vec<Body>,   // 静态变量的初始化代码，函数体名即静态变量名
+    pub benches: Vec<String>, // `#[bench]` 函数，和 main 一样是程序的入口
+    pub intrinsics: BTreeMap<String, String>, // `#[intrinsic]` 函数名 -> 它实现的 intrinsic
+}
+
+impl MirProgram {
+    pub fn body(&self, name: &str) -> Option<&Body> {
+        self.bodies.iter().find(|body| body.name == name)
+    }
+
+    pub fn static_init(&self, name: &str) -> Option<&Body> {
+        self.statics.iter().find(|body| body.name == name)
+    }
+
+    pub fn struct_def(&self, name: &str) -> Option<&StructDef> {
+        self.structs.iter().find(|s| s.name == name)
+    }
+
+    pub fn enum_def(&self, name: &str) -> Option<&EnumDef> {
+        self.enums.iter().find(|e| e.name == name)
+    }
+
+    /// 按程序中的类型定义计算布局，泛型类型在单态化之后才有布局
+    pub fn layouts(&self) -> Layouts<'_> {
+        Layouts::new(&self.structs, &self.enums)
+    }
+
+    /// place 的类型；泛型结构体和枚举的字段类型按类型实参替换
+    pub fn place_ty(&self, body: &Body, place: &Place) -> Type {
+        let mut ty = body.local_decl(place.local).ty.clone();
+        let mut variant: Option<String> = None;
+        for elem in &place.projection {
+            ty = match elem {
+                PlaceElem::Deref => match ty {
+                    Type::Reference(inner, _) | Type::Pointer(inner, _) => *inner,
+                    ty => builtins::pointee_type(&ty).unwrap_or(Type::Infer),
+                },
+                PlaceElem::Index(_) => builtins::element_type(&ty).unwrap_or(Type::Infer),
+                PlaceElem::Subslice(_, _) => match builtins::element_type(&ty) {
+                    Some(element) => Type::Slice(Box::new(element)),
+                    None => Type::Infer,
+                },
+                PlaceElem::Downcast(name) => {
+                    variant = Some(name.clone());
+                    continue;
+                }
+                PlaceElem::Field(field) => match variant.take() {
+                    Some(variant) => self.variant_field_ty(&ty, &variant, field),
+                    None => self.field_ty(&ty, field),
+                },
+            };
+        }
+        ty
+    }
+
+    pub fn field_ty(&self, ty: &Type, field: &str) -> Type {
+        match ty {
+            Type::Named(name) | Type::Generic(name, _) => {
+                let Some(def) = self.struct_def(name) else {
+                    return Type::Infer;
+                };
+                def.fields
+                    .iter()
+                    .find(|f| f.name == field)
+                    .map(|f| f.ty.substitute(&type_args(def.generics.as_ref(), ty)))
+                    .unwrap_or(Type::Infer)
+            }
+            Type::Tuple(types) => field
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| types.get(i).cloned())
+                .unwrap_or(Type::Infer),
+            _ => Type::Infer,
+        }
+    }
+
+    fn variant_field_ty(&self, ty: &Type, variant: &str, field: &str) -> Type {
+        let (Type::Named(name) | Type::Generic(name, _)) = ty else {
+            return Type::Infer;
+        };
+        let Some(def) = self.enum_def(name) else {
+            return Type::Infer;
+        };
+        def.variants
+            .iter()
+            .find(|v| v.name == variant)
+            .and_then(|v| v.fields.as_ref()?.get(field.parse::<usize>().ok()?))
+            .map(|f| f.substitute(&type_args(def.generics.as_ref(), ty)))
+            .unwrap_or(Type::Infer)
+    }
+
+    /// 枚举变体的序号，即 `Discriminant` 的取值和布局中的标签
+    pub fn variant_index(&self, enum_name: &str, variant: &str) -> Option<usize> {
+        self.enum_def(enum_name)?
+            .variants
+            .iter()
+            .position(|v| v.name == variant)
+    }
+}
+
+/// 泛型类型定义的参数到类型实参的映射；`ty` 不带实参时为空
+pub fn type_args(generics: Option<&Generics>, ty: &Type) -> BTreeMap<String, Type> {
+    match (generics, ty) {
+        (Some(generics), Type::Generic(_, args)) => generics
+            .param_names()
+            .into_iter()
+            .zip(args.iter().cloned())
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
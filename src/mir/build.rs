@@ -0,0 +1,3111 @@
+// MIR 构建：把 AST 函数降级为基本块
+// 目前没有独立的 HIR，直接从经过语义分析的 AST 降级：
+// 1. 表达式求值结果写入目标 place，需要时生成临时变量
+// 2. 局部变量的类型在第一次赋值时由右值推断（有标注时使用标注）
+// 3. for 循环降级为计数循环（区间）或按下标遍历（数组）；下标访问前插入越界检查，
+//    for 循环由循环条件保证下标有效，不再检查；遍历 Map 和字符串时先用 `entries`/`chars` 取出条目
+// 4. match 降级为判定树：在被匹配的值上测试变体或字面量的连续分支合成一个 switchInt，
+//    每个取值只测试可能匹配的分支；分支体只生成一次
+// 5. 闭包降级为独立的函数体，按值捕获外层变量并作为前几个参数传入
+//    以集合为第一个参数的内建函数按方法调用时（`v.push(x)`）接收者取引用；
+//    `pop` 展开为长度检查和 `remove`，其他返回 Option 的（`get`、`find` 等）展开为检查函数和取值
+// 6. 拥有值（需要析构）的变量在离开作用域、return、break/continue 时生成 drop，
+//    按值读取时转移所有权（move）；构建结束后由析构展开删除已移出变量的 drop
+// 7. 检查契约时，requires 在入口处、ensures 在每个 return 之前（析构之前）、
+//    结构体的 invariant 在结构体字面量构造之后降级为 assert
+// 8. panic 的处理方式为展开时，调用和断言的 unwind 边指向清理块，析构作用域中登记的变量后
+//    以 resume 继续展开；`catch_panic(f)` 在 panic 时立即结束程序时降级为 `Ok(f())`
+
+use super::transform::{elaborate_drops, remove_unreachable_blocks};
+use super::Statement as MirStatement;
+use super::*;
+use super::{AsmOperand as MirAsmOperand, InlineAsm as MirInlineAsm};
+use crate::ast::{
+    Block, ConstDef, Contract, ContractKind, Expr, Function, InlineAsm, Item, Literal,
+};
+use crate::ast::{ElseBranch, Generics, IfStmt, Program, Statement, StaticDef};
+use crate::ast::{MatchArm, Parameter, Pattern};
+use crate::builtins;
+use crate::derive;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::incremental::{self, QueryCache};
+use crate::layout::{self, Layouts};
+use crate::prelude::{self, TryKind};
+use crate::timing;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 按默认选项把整个程序降级为 MIR，程序应当已经通过语义分析
+pub fn lower_program(program: &Program) -> Result<MirProgram, Vec<Diagnostic>> {
+    lower_program_with(program, LowerOptions::default())
+}
+
+pub fn lower_program_with(
+    program: &Program,
+    options: LowerOptions,
+) -> Result<MirProgram, Vec<Diagnostic>> {
+    lower_items(program, options, None)
+}
+
+/// 与 `lower_program_with` 相同，但先在 `cache` 中查找每个函数和静态变量的 MIR（`lower` 查询），
+/// 只降级接口或自身有变化的条目
+pub fn lower_program_incremental(
+    program: &Program,
+    options: LowerOptions,
+    cache: &QueryCache,
+) -> Result<MirProgram, Vec<Diagnostic>> {
+    lower_items(program, options, Some(cache))
+}
+
+fn lower_items(
+    program: &Program,
+    options: LowerOptions,
+    cache: Option<&QueryCache>,
+) -> Result<MirProgram, Vec<Diagnostic>> {
+    let mut cx = Context::new(program);
+    cx.contracts = options.contracts;
+    cx.panic = options.panic;
+    let mut mir = MirProgram {
+        structs: cx.structs.values().map(|s| (*s).clone()).collect(),
+        enums: cx.enums.values().map(|e| (*e).clone()).collect(),
+        ..MirProgram::default()
+    };
+    let mut errors = Vec::new();
+    let interface = cache.map(|_| incremental::interface_hash(program));
+
+    for item in &program.items {
+        let (name, span) = match item {
+            Item::Function(func) => {
+                if func.has_attribute("bench") {
+                    mir.benches.push(func.name.clone());
+                }
+                if let Some(intrinsic) = func.intrinsic() {
+                    mir.intrinsics.insert(func.name.clone(), intrinsic);
+                }
+                (&func.name, func.span)
+            }
+            Item::Static(static_def) => (&static_def.name, static_def.span),
+            _ => continue,
+        };
+        let key = interface.map(|interface| incremental::item_key(interface, options, item));
+        let cached = cache.zip(key).and_then(|(cache, key)| cache.lowered(key));
+        let (body, closures) = cached.unwrap_or_else(|| {
+            let mut builder = Builder::new(&cx, name.clone(), span);
+            match item {
+                Item::Function(func) => builder.lower_function(func),
+                Item::Static(static_def) => builder.lower_static(static_def),
+                _ => unreachable!(),
+            }
+            let errors_before = errors.len();
+            let (body, closures) = builder.finish(&mut errors);
+            // 有错误的条目不记忆，下次仍然报告
+            if let (Some(cache), Some(key), true) = (cache, key, errors.len() == errors_before) {
+                cache.set_lowered(key, &body, &closures);
+            }
+            (body, closures)
+        });
+        match item {
+            Item::Static(_) => mir.statics.push(body),
+            _ => mir.bodies.push(body),
+        }
+        mir.bodies.extend(closures);
+    }
+
+    if errors.is_empty() {
+        Ok(mir)
+    } else {
+        Err(errors)
+    }
+}
+
+// 降级过程中需要的全局信息
+struct Context<'a> {
+    structs: BTreeMap<&'a str, &'a StructDef>,
+    enums: BTreeMap<&'a str, &'a EnumDef>,
+    variants: BTreeMap<&'a str, &'a str>, // 变体名 -> 枚举名，用于不带前缀的变体
+    functions: BTreeMap<&'a str, &'a Function>,
+    consts: BTreeMap<&'a str, &'a ConstDef>,
+    statics: BTreeMap<&'a str, &'a StaticDef>,
+    contracts: ContractMode,
+    panic: PanicStrategy,
+}
+
+impl<'a> Context<'a> {
+    fn new(program: &'a Program) -> Self {
+        let mut cx = Context {
+            structs: BTreeMap::new(),
+            enums: BTreeMap::new(),
+            variants: BTreeMap::new(),
+            functions: BTreeMap::new(),
+            consts: BTreeMap::new(),
+            statics: BTreeMap::new(),
+            contracts: ContractMode::default(),
+            panic: PanicStrategy::default(),
+        };
+        // 预导入的条目在前，程序中的同名条目覆盖它们
+        for item in prelude::items_for(program).chain(&program.items) {
+            match item {
+                Item::Struct(s) => {
+                    cx.structs.insert(&s.name, s);
+                }
+                Item::Enum(e) => {
+                    cx.enums.insert(&e.name, e);
+                    for variant in &e.variants {
+                        cx.variants.insert(&variant.name, &e.name);
+                    }
+                }
+                Item::Function(f) => {
+                    cx.functions.insert(&f.name, f);
+                }
+                Item::Const(c) => {
+                    cx.consts.insert(&c.name, c);
+                }
+                Item::Static(s) => {
+                    cx.statics.insert(&s.name, s);
+                }
+                Item::Import(_) | Item::Export(_) | Item::MacroRules(_) | Item::MacroCall(_) => {}
+            }
+        }
+        cx
+    }
+
+    fn function_type(&self, func: &Function) -> Type {
+        Type::Function(
+            func.params.iter().map(|p| p.ty.clone()).collect(),
+            Box::new(func.return_type.clone().unwrap_or(Type::Unit)),
+            func.pure,
+        )
+    }
+
+    // 值离开作用域时是否需要析构：字符串拥有堆内存，包含需要析构的值的复合类型也需要
+    fn needs_drop(&self, ty: &Type) -> bool {
+        self.needs_drop_in(ty, &mut BTreeSet::new())
+    }
+
+    fn needs_drop_in(&self, ty: &Type, visiting: &mut BTreeSet<String>) -> bool {
+        match ty {
+            Type::String => true,
+            // Vec 和 StringBuilder 拥有堆内存
+            Type::Named(name) | Type::Generic(name, _)
+                if builtins::is_type(name) && !self.structs.contains_key(name.as_str()) =>
+            {
+                true
+            }
+            Type::Array(elem, _) => self.needs_drop_in(elem, visiting),
+            Type::Tuple(types) => types.iter().any(|ty| self.needs_drop_in(ty, visiting)),
+            Type::Named(name) | Type::Generic(name, _) => {
+                // 递归类型在展开过程中再次遇到时不重复计算
+                if !visiting.insert(name.clone()) {
+                    return false;
+                }
+                let field_types: Vec<&Type> = if let Some(def) = self.structs.get(name.as_str()) {
+                    def.fields.iter().map(|field| &field.ty).collect()
+                } else if let Some(def) = self.enums.get(name.as_str()) {
+                    def.variants
+                        .iter()
+                        .filter_map(|variant| variant.fields.as_ref())
+                        .flatten()
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let result = field_types
+                    .into_iter()
+                    .any(|ty| self.needs_drop_in(ty, visiting));
+                visiting.remove(name);
+                result
+            }
+            _ => false,
+        }
+    }
+
+    // 按名字查找变体：`Enum::Variant` 或者不带前缀的变体名
+    fn variant(&self, enum_name: Option<&str>, variant: &str) -> Option<(String, usize)> {
+        let enum_name = match enum_name {
+            Some(name) => name,
+            None => *self.variants.get(variant)?,
+        };
+        let index = self
+            .enums
+            .get(enum_name)?
+            .variants
+            .iter()
+            .position(|v| v.name == variant)?;
+        Some((enum_name.to_string(), index))
+    }
+}
+
+// 构建中的基本块，终结符在块结束时才确定
+struct BlockBuilder {
+    statements: Vec<MirStatement>,
+    terminator: Option<Terminator>,
+}
+
+struct LoopScope {
+    label: Option<String>,
+    break_target: BasicBlock,
+    continue_target: BasicBlock,
+    scope_depth: usize, // 进入循环时的作用域层数，跳出时析构更深层的变量
+}
+
+// 词法作用域：声明的名字，以及离开作用域时要析构的变量（按声明顺序，析构时逆序）
+#[derive(Default)]
+struct Scope {
+    names: Vec<(String, Local)>,
+    drops: Vec<Local>,
+}
+
+// match 分支的模式在被匹配的值本身上测试的内容，见 `Builder::decide`
+#[derive(Debug, Clone, PartialEq)]
+enum Head {
+    Any,                        // 不测试值本身：通配、绑定、元组和结构体
+    Variants(String, Vec<i64>), // 枚举的变体序号
+    Values(Vec<i64>),           // 整数、bool 和字符字面量
+    Opaque,                     // 浮点数和字符串字面量，不能 switch
+}
+
+struct Builder<'a, 'cx> {
+    cx: &'cx Context<'a>,
+    name: String,
+    type_params: Vec<String>,
+    span: Span,
+    arg_count: usize,
+    locals: Vec<LocalDecl>,
+    blocks: Vec<BlockBuilder>,
+    current: BasicBlock,
+    scopes: Vec<Scope>,
+    loops: Vec<LoopScope>,
+    closures: Vec<Body>, // 本函数体中生成的闭包函数体
+    postconditions: Vec<&'a Contract>,
+    pending: Vec<PendingElement>,
+    cleanups: BTreeMap<Vec<Local>, BasicBlock>, // 按要析构的变量共用的清理块
+    errors: Vec<Diagnostic>,
+}
+
+// `pop`、`get` 等展开时集合的类型参数可能还不知道（由之后的 push/insert 推断），
+// 构建结束后按集合最终的类型补全元素临时变量和 `Option<_>` 的类型
+struct PendingElement {
+    collection: Place,
+    element: fn(&Type) -> Option<Type>,
+    locals: Vec<Local>,
+}
+
+impl<'a, 'cx> Builder<'a, 'cx> {
+    fn new(cx: &'cx Context<'a>, name: String, span: Span) -> Self {
+        let mut builder = Self {
+            cx,
+            name,
+            type_params: Vec::new(),
+            span,
+            arg_count: 0,
+            locals: Vec::new(),
+            blocks: Vec::new(),
+            current: BasicBlock::START,
+            scopes: vec![Scope::default()],
+            loops: Vec::new(),
+            closures: Vec::new(),
+            postconditions: Vec::new(),
+            pending: Vec::new(),
+            cleanups: BTreeMap::new(),
+            errors: Vec::new(),
+        };
+        builder.new_block();
+        builder
+    }
+
+    fn lower_function(&mut self, func: &'a Function) {
+        if let Some(generics) = &func.generics {
+            self.type_params = generics.param_names();
+        }
+        let ret = func.return_type.clone().unwrap_or(Type::Unit);
+        self.push_local(None, ret, false, LocalKind::ReturnPointer, func.span);
+        self.lower_params(&func.params);
+        if self.cx.contracts == ContractMode::Check {
+            for contract in &func.contracts {
+                match contract.kind {
+                    ContractKind::Requires => self.check_contract(contract, &func.name, &[]),
+                    _ => self.postconditions.push(contract),
+                }
+            }
+        }
+        self.lower_block(&func.body, Some(Place::local(Local::RETURN)));
+        self.check_postconditions();
+        self.drop_scopes(0, func.body.span);
+        self.terminate(TerminatorKind::Return, func.body.span);
+    }
+
+    fn lower_static(&mut self, static_def: &StaticDef) {
+        let span = static_def.span;
+        self.push_local(
+            None,
+            static_def.ty.clone(),
+            false,
+            LocalKind::ReturnPointer,
+            span,
+        );
+        self.lower_expr(&static_def.value, Some(Place::local(Local::RETURN)));
+        self.terminate(TerminatorKind::Return, span);
+    }
+
+    fn lower_params(&mut self, params: &[Parameter]) {
+        let args: Vec<Local> = params
+            .iter()
+            .map(|param| {
+                let name = match &param.pattern {
+                    Pattern::Ident(name) => Some(name.clone()),
+                    _ => None,
+                };
+                self.arg_count += 1;
+                self.push_local(name, param.ty.clone(), false, LocalKind::Arg, param.span)
+            })
+            .collect();
+
+        // 参数是解构模式时，在入口块中展开绑定
+        for (param, arg) in params.iter().zip(args) {
+            self.schedule_drop(arg);
+            match &param.pattern {
+                Pattern::Ident(name) => self.declare(name, arg),
+                pattern => self.bind_pattern(pattern, &Place::local(arg), false, param.span),
+            }
+        }
+    }
+
+    // 结束构建：补全终结符、删除不可达的基本块（return/break 之后的代码）
+    fn finish(mut self, errors: &mut Vec<Diagnostic>) -> (Body, Vec<Body>) {
+        errors.append(&mut self.errors);
+        for pending in std::mem::take(&mut self.pending) {
+            let collection = self.place_ty(&pending.collection);
+            let Some(element) = (pending.element)(&collection).filter(|ty| *ty != Type::Infer)
+            else {
+                continue;
+            };
+            for local in pending.locals {
+                match &mut self.locals[local.index()].ty {
+                    ty @ Type::Infer => *ty = element.clone(),
+                    Type::Generic(_, args) if args[..] == [Type::Infer] => {
+                        args[0] = element.clone()
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let span = self.span;
+        let blocks = self
+            .blocks
+            .into_iter()
+            .map(|block| BasicBlockData {
+                statements: block.statements,
+                terminator: block.terminator.unwrap_or(Terminator {
+                    kind: TerminatorKind::Unreachable,
+                    span,
+                }),
+            })
+            .collect();
+
+        let mut body = Body {
+            name: self.name,
+            type_params: self.type_params,
+            type_args: Vec::new(),
+            arg_count: self.arg_count,
+            locals: self.locals,
+            blocks,
+            span,
+        };
+        remove_unreachable_blocks(&mut body);
+        timing::time("drop elaboration", || elaborate_drops(&mut body));
+        (body, self.closures)
+    }
+
+    // ---------------- 语句 ----------------
+
+    // 降级语句块；`dest` 不为 None 时块的值写入 dest
+    fn lower_block(&mut self, block: &Block, dest: Option<Place>) {
+        self.scopes.push(Scope::default());
+        let count = block.statements.len();
+        let mut has_value = false;
+
+        for (i, stmt) in block.statements.iter().enumerate() {
+            let is_tail = i + 1 == count && dest.is_some();
+            match stmt {
+                Statement::Expr(expr_stmt) if is_tail && !expr_stmt.semicolon => {
+                    self.lower_expr(&expr_stmt.expr, dest.clone());
+                    has_value = true;
+                }
+                Statement::If(if_stmt) if is_tail => {
+                    self.lower_if_stmt(if_stmt, dest.clone());
+                    has_value = true;
+                }
+                Statement::Match(match_stmt) => {
+                    let target = if is_tail { dest.clone() } else { None };
+                    self.lower_match(&match_stmt.expr, &match_stmt.arms, target, match_stmt.span);
+                    has_value = is_tail;
+                }
+                Statement::Block(inner) if is_tail => {
+                    self.lower_block(inner, dest.clone());
+                    has_value = true;
+                }
+                _ => self.lower_statement(stmt),
+            }
+        }
+
+        // 没有尾表达式的块求值为 ()
+        if let Some(dest) = dest {
+            if !has_value && matches!(self.place_ty(&dest), Type::Unit | Type::Infer) {
+                self.assign(dest, Rvalue::Use(unit_operand()), block.span);
+            }
+        }
+
+        self.pop_scope(block.span);
+    }
+
+    fn lower_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let(let_stmt) => {
+                let span = let_stmt.span;
+                let ty = let_stmt
+                    .ty
+                    .as_ref()
+                    .map_or(Type::Infer, Type::expand_typeof);
+                match &let_stmt.pattern {
+                    Pattern::Ident(name)
+                        if !self.is_unit_variant(name) && let_stmt.else_block.is_none() =>
+                    {
+                        let local = self.push_local(
+                            Some(name.clone()),
+                            ty,
+                            let_stmt.mutable,
+                            LocalKind::Var,
+                            span,
+                        );
+                        self.push_statement(StatementKind::StorageLive(local), span);
+                        // 先求值初始化表达式再引入名字，`let x = x + 1;` 读取的是外层的 x
+                        if let Some(init) = &let_stmt.init {
+                            self.lower_expr(init, Some(Place::local(local)));
+                        }
+                        self.declare(name, local);
+                        if !self.is_copied_from_borrow(local) {
+                            self.schedule_drop(local);
+                        }
+                    }
+                    pattern => {
+                        let temp = self.new_temp(ty, span);
+                        if let Some(init) = &let_stmt.init {
+                            self.lower_expr(init, Some(Place::local(temp)));
+                        }
+                        self.schedule_drop(temp);
+                        // `let ... else`：不匹配时进入 else 块，它发散，之后的块不可达
+                        if let Some(else_block) = &let_stmt.else_block {
+                            let fail = self.new_block();
+                            self.test_pattern(pattern, &Place::local(temp), fail, span);
+                            let success = self.current;
+                            self.current = fail;
+                            self.lower_block(else_block, None);
+                            self.terminate(TerminatorKind::Unreachable, else_block.span);
+                            self.current = success;
+                        }
+                        self.bind_pattern(pattern, &Place::local(temp), let_stmt.mutable, span);
+                    }
+                }
+            }
+            Statement::Expr(expr_stmt) => self.lower_expr(&expr_stmt.expr, None),
+            Statement::Return(ret) => self.lower_return(ret.expr.as_ref(), ret.span),
+            Statement::If(if_stmt) => self.lower_if_stmt(if_stmt, None),
+            Statement::While(while_stmt) => self.lower_while(
+                while_stmt.label.as_ref(),
+                &while_stmt.cond,
+                &while_stmt.invariants,
+                &while_stmt.body,
+                while_stmt.span,
+            ),
+            Statement::For(for_stmt) => self.lower_for(
+                for_stmt.label.as_ref(),
+                &for_stmt.pattern,
+                &for_stmt.iterable,
+                &for_stmt.invariants,
+                &for_stmt.body,
+                for_stmt.span,
+            ),
+            Statement::Match(match_stmt) => {
+                self.lower_match(&match_stmt.expr, &match_stmt.arms, None, match_stmt.span)
+            }
+            Statement::Break(stmt) => {
+                self.lower_break(stmt.label.as_ref(), stmt.expr.as_ref(), stmt.span)
+            }
+            Statement::Continue(stmt) => self.lower_continue(stmt.label.as_ref(), stmt.span),
+            Statement::Block(block) => self.lower_block(block, None),
+        }
+    }
+
+    fn lower_return(&mut self, value: Option<&Expr>, span: Span) {
+        match value {
+            Some(expr) => self.lower_expr(expr, Some(Place::local(Local::RETURN))),
+            None => self.assign(
+                Place::local(Local::RETURN),
+                Rvalue::Use(unit_operand()),
+                span,
+            ),
+        }
+        self.check_postconditions();
+        self.drop_scopes(0, span);
+        self.terminate(TerminatorKind::Return, span);
+        self.start_unreachable_block();
+    }
+
+    // 带标签时跳出同名的循环，否则跳出最内层的循环
+    fn target_loop(&self, label: Option<&String>) -> Option<&LoopScope> {
+        self.loops
+            .iter()
+            .rev()
+            .find(|scope| label.is_none() || scope.label.as_ref() == label)
+    }
+
+    fn lower_break(&mut self, label: Option<&String>, value: Option<&Expr>, span: Span) {
+        // while/for 循环没有值，break 的值只为副作用求值
+        if let Some(expr) = value {
+            self.lower_expr(expr, None);
+        }
+        match self.target_loop(label) {
+            Some(scope) => {
+                let (target, depth) = (scope.break_target, scope.scope_depth);
+                self.drop_scopes(depth, span);
+                self.terminate(TerminatorKind::Goto { target }, span);
+            }
+            None => self.error(
+                ErrorCode::E0268,
+                "`break` outside of a loop".to_string(),
+                span,
+            ),
+        }
+        self.start_unreachable_block();
+    }
+
+    fn lower_continue(&mut self, label: Option<&String>, span: Span) {
+        match self.target_loop(label) {
+            Some(scope) => {
+                let (target, depth) = (scope.continue_target, scope.scope_depth);
+                self.drop_scopes(depth, span);
+                self.terminate(TerminatorKind::Goto { target }, span);
+            }
+            None => self.error(
+                ErrorCode::E0268,
+                "`continue` outside of a loop".to_string(),
+                span,
+            ),
+        }
+        self.start_unreachable_block();
+    }
+
+    // ---------------- 控制流 ----------------
+
+    fn lower_if(
+        &mut self,
+        cond: &Expr,
+        then_block: &Block,
+        else_block: Option<&ElseBranch>,
+        dest: Option<Place>,
+        span: Span,
+    ) {
+        let cond = self.lower_operand(cond);
+        let then_bb = self.new_block();
+        let else_bb = self.new_block();
+        let join = self.new_block();
+        self.switch_bool(cond, then_bb, else_bb, span);
+
+        self.current = then_bb;
+        self.lower_block(then_block, dest.clone());
+        self.goto(join, span);
+
+        self.current = else_bb;
+        match else_block {
+            Some(ElseBranch::Block(block)) => self.lower_block(block, dest),
+            Some(ElseBranch::If(nested)) => self.lower_if_stmt(nested, dest),
+            None => {
+                if let Some(dest) = dest {
+                    self.assign(dest, Rvalue::Use(unit_operand()), span);
+                }
+            }
+        }
+        self.goto(join, span);
+        self.current = join;
+    }
+
+    fn lower_if_stmt(&mut self, stmt: &IfStmt, dest: Option<Place>) {
+        let else_block = stmt.else_block.as_ref();
+        self.lower_if(&stmt.cond, &stmt.then_block, else_block, dest, stmt.span);
+    }
+
+    // 循环不变式在每次求值条件之前检查：进入循环时、每次迭代之后和条件为假离开时
+    fn lower_while(
+        &mut self,
+        label: Option<&String>,
+        cond: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+        span: Span,
+    ) {
+        let header = self.new_block();
+        let body_bb = self.new_block();
+        let exit = self.new_block();
+        self.goto(header, span);
+
+        self.current = header;
+        self.check_loop_invariants(invariants);
+        let cond = self.lower_operand(cond);
+        self.switch_bool(cond, body_bb, exit, span);
+
+        self.current = body_bb;
+        self.loops.push(LoopScope {
+            label: label.cloned(),
+            break_target: exit,
+            continue_target: header,
+            scope_depth: self.scopes.len(),
+        });
+        self.lower_block(body, None);
+        self.loops.pop();
+        self.goto(header, span);
+
+        self.current = exit;
+    }
+
+    // for 循环：区间按计数器遍历，其他可迭代对象按下标遍历
+    fn lower_for(
+        &mut self,
+        label: Option<&String>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+        span: Span,
+    ) {
+        let (counter, end, inclusive, sequence) = match iterable {
+            Expr::Range(start, end, inclusive, range_span) => {
+                let start = self.range_bound(start, *range_span);
+                let end = self.range_bound(end, *range_span);
+                (start, end, *inclusive, None)
+            }
+            _ => {
+                let mut place = self.as_place(iterable);
+                let ty = self.place_ty(&place);
+                if let Some((key, value)) = builtins::map_types(&ty) {
+                    let entry = Type::Tuple(vec![key, value]);
+                    place = self.collect_items("entries", place, entry, span);
+                } else if builtins::is_string(&ty) {
+                    place = self.collect_items("chars", place, Type::Char, span);
+                }
+                match self.place_ty(&place) {
+                    Type::Generic(name, _) if name == "Range" || name == "RangeInclusive" => {
+                        let start = Operand::Copy(place.project(PlaceElem::Field("start".into())));
+                        let end = Operand::Copy(place.project(PlaceElem::Field("end".into())));
+                        (start, end, name == "RangeInclusive", None)
+                    }
+                    _ => {
+                        let len = self.new_temp(Type::Usize, span);
+                        self.assign(Place::local(len), Rvalue::Len(place.clone()), span);
+                        let zero = Operand::Constant(Constant::int(0, Type::Usize));
+                        (zero, Operand::Copy(Place::local(len)), false, Some(place))
+                    }
+                }
+            }
+        };
+
+        let index = self.new_temp(Type::Infer, span);
+        self.assign(Place::local(index), Rvalue::Use(counter), span);
+        let index_ty = self.locals[index.index()].ty.clone();
+
+        let header = self.new_block();
+        let body_bb = self.new_block();
+        let step = self.new_block();
+        let exit = self.new_block();
+        self.goto(header, span);
+
+        self.current = header;
+        // 循环变量还没有绑定，不变式中不能使用
+        self.check_loop_invariants(invariants);
+        let op = if inclusive {
+            BinOp::LessEqual
+        } else {
+            BinOp::Less
+        };
+        let cond = self.new_temp(Type::Bool, span);
+        self.assign(
+            Place::local(cond),
+            Rvalue::BinaryOp(op, Operand::Copy(Place::local(index)), end, None),
+            span,
+        );
+        self.switch_bool(Operand::Copy(Place::local(cond)), body_bb, exit, span);
+
+        self.current = body_bb;
+        let scope_depth = self.scopes.len();
+        self.scopes.push(Scope::default());
+        let element = match sequence {
+            Some(place) => place.project(PlaceElem::Index(index)),
+            None => Place::local(index),
+        };
+        self.bind_pattern(pattern, &element, false, span);
+        self.loops.push(LoopScope {
+            label: label.cloned(),
+            break_target: exit,
+            continue_target: step,
+            scope_depth,
+        });
+        self.lower_block(body, None);
+        self.loops.pop();
+        self.pop_scope(span);
+        self.goto(step, span);
+
+        self.current = step;
+        let one = Operand::Constant(Constant::int(1, index_ty));
+        self.assign(
+            Place::local(index),
+            Rvalue::BinaryOp(BinOp::Add, Operand::Copy(Place::local(index)), one, None),
+            span,
+        );
+        self.goto(header, span);
+
+        self.current = exit;
+    }
+
+    fn check_loop_invariants(&mut self, invariants: &[Contract]) {
+        if self.cx.contracts != ContractMode::Check {
+            return;
+        }
+        let owner = self.name.clone();
+        for invariant in invariants {
+            self.check_contract(invariant, &owner, &[]);
+        }
+    }
+
+    // `entries(&map)`、`chars(&s)` 写入临时变量
+    fn collect_items(&mut self, builtin: &str, source: Place, item: Type, span: Span) -> Place {
+        let ty = Type::Reference(Box::new(self.place_ty(&source)), false);
+        let reference = self.new_temp(ty, span);
+        self.assign(Place::local(reference), Rvalue::Ref(source, false), span);
+        let items = self.new_temp(Type::Generic("Vec".to_string(), vec![item]), span);
+        let func = self.function_operand(builtin);
+        let args = vec![Operand::Copy(Place::local(reference))];
+        self.emit_call(func, args, Some(Place::local(items)), span);
+        Place::local(items)
+    }
+
+    // match：降级为判定树，见 `decide`
+    fn lower_match(
+        &mut self,
+        scrutinee: &Expr,
+        arms: &[MatchArm],
+        dest: Option<Place>,
+        span: Span,
+    ) {
+        let place = self.as_place(scrutinee);
+        self.lower_arms(&place, arms, dest, span);
+    }
+
+    // 每个分支一个入口块，在其中绑定变量、检查守卫并求值分支体，分支体只生成一次；
+    // 模式的测试由 `decide` 生成，匹配时跳到分支的入口块
+    fn lower_arms(&mut self, place: &Place, arms: &[MatchArm], dest: Option<Place>, span: Span) {
+        let join = self.new_block();
+        let entries: Vec<BasicBlock> = arms.iter().map(|_| self.new_block()).collect();
+        self.decide(place, arms, 0, &entries, span);
+
+        // 守卫不成立时从下一个分支开始重新判定。判定在所有分支之后生成，
+        // 这时分支绑定的名字已经离开作用域，不会遮蔽后面分支模式中的变体名
+        let mut retries = Vec::new();
+        for (i, arm) in arms.iter().enumerate() {
+            self.current = entries[i];
+            self.scopes.push(Scope::default());
+            self.bind_pattern(&arm.pattern, place, false, arm.span);
+            if let Some(guard) = &arm.guard {
+                let cond = self.lower_operand(guard);
+                let guarded = self.new_block();
+                let retry = self.new_block();
+                self.switch_bool(cond, guarded, retry, arm.span);
+                retries.push((retry, i + 1));
+                self.current = guarded;
+            }
+            self.lower_expr(&arm.body, dest.clone());
+            self.pop_scope(arm.span);
+            self.goto(join, arm.span);
+        }
+        for (retry, start) in retries {
+            self.current = retry;
+            self.decide(place, arms, start, &entries, span);
+        }
+        self.current = join;
+    }
+
+    // 从第 `start` 个分支开始判定被匹配的值：
+    // 1. 连续的、在值本身上测试变体或整数字面量的分支合成一个 `switchInt`，每个取值的目标块
+    //    只依次测试可能匹配这个取值的分支，不再重复测试变体；不测试值本身的分支（通配、绑定、
+    //    元组和结构体）出现在每个目标块和 otherwise 中
+    // 2. 浮点数和字符串字面量不能 switch，单独按顺序测试
+    // 3. 一段分支都不匹配时继续判定之后的分支；所有分支都不匹配不可达，穷尽性由语义分析保证
+    // 嵌套的模式（`Some(1)` 中的 `1`）在目标块中按顺序测试
+    fn decide(
+        &mut self,
+        place: &Place,
+        arms: &[MatchArm],
+        start: usize,
+        entries: &[BasicBlock],
+        span: Span,
+    ) {
+        let mut i = start;
+        while i < arms.len() {
+            let end = (i..arms.len())
+                .find(|&j| self.head(&arms[j].pattern) == Head::Opaque)
+                .unwrap_or(arms.len());
+            let segment = i..end.max(i + 1);
+            let rest = self.new_block();
+            match self.switch_values(&arms[segment.clone()]) {
+                Some((enum_name, values)) => {
+                    // 列出了所有变体时 otherwise 不可达
+                    let exhaustive = enum_name.as_ref().is_some_and(|name| {
+                        self.cx
+                            .enums
+                            .get(name.as_str())
+                            .map(|def| def.variants.len())
+                            == Some(values.len())
+                    });
+                    let discr = self.switch_operand(place, enum_name, span);
+                    let targets: Vec<(i64, BasicBlock)> = values
+                        .iter()
+                        .map(|&value| (value, self.new_block()))
+                        .collect();
+                    let otherwise = self.new_block();
+                    self.terminate(
+                        TerminatorKind::SwitchInt {
+                            discr,
+                            targets: targets.clone(),
+                            otherwise,
+                        },
+                        span,
+                    );
+                    for (value, target) in targets {
+                        self.current = target;
+                        let known = Some(value);
+                        let (arms, entries) = (&arms[segment.clone()], &entries[segment.clone()]);
+                        self.test_arms(place, arms, known, entries, rest, span);
+                    }
+                    self.current = otherwise;
+                    if exhaustive {
+                        self.terminate(TerminatorKind::Unreachable, span);
+                    } else {
+                        let (arms, entries) = (&arms[segment.clone()], &entries[segment.clone()]);
+                        self.test_arms(place, arms, None, entries, rest, span);
+                    }
+                }
+                None => {
+                    for j in segment.clone() {
+                        let next = if j + 1 == segment.end {
+                            rest
+                        } else {
+                            self.new_block()
+                        };
+                        self.test_pattern(&arms[j].pattern, place, next, arms[j].span);
+                        self.goto(entries[j], arms[j].span);
+                        self.current = next;
+                    }
+                }
+            }
+            self.current = rest;
+            i = segment.end;
+        }
+        self.terminate(TerminatorKind::Unreachable, span);
+    }
+
+    // 已知被匹配的值的取值时依次测试一段分支的其余部分，都不匹配时跳到 `fail`；
+    // `known` 为 None 表示值不是 switch 列出的任何一个取值。不可能匹配的分支不生成测试
+    fn test_arms(
+        &mut self,
+        place: &Place,
+        segment: &[MatchArm],
+        known: Option<i64>,
+        entries: &[BasicBlock],
+        fail: BasicBlock,
+        span: Span,
+    ) {
+        for (arm, &entry) in segment.iter().zip(entries) {
+            if !self.may_match(&arm.pattern, known) {
+                continue;
+            }
+            let next = self.new_block();
+            self.test_known(&arm.pattern, place, known, next, arm.span);
+            self.goto(entry, arm.span);
+            self.current = next;
+        }
+        self.goto(fail, span);
+    }
+
+    fn test_known(
+        &mut self,
+        pattern: &Pattern,
+        place: &Place,
+        known: Option<i64>,
+        fail: BasicBlock,
+        span: Span,
+    ) {
+        match pattern {
+            Pattern::Or(alternatives) => {
+                let alternatives: Vec<&Pattern> = alternatives
+                    .iter()
+                    .filter(|alternative| self.may_match(alternative, known))
+                    .collect();
+                let success = self.new_block();
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let next = if i + 1 == alternatives.len() {
+                        fail
+                    } else {
+                        self.new_block()
+                    };
+                    self.test_known(alternative, place, known, next, span);
+                    self.goto(success, span);
+                    self.current = next;
+                }
+                self.current = success;
+            }
+            Pattern::TupleStruct(name, patterns) if self.head(pattern) != Head::Any => {
+                let place = self.variant_place(name, place);
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let field_place = place.project(PlaceElem::Field(i.to_string()));
+                    self.test_pattern(sub_pattern, &field_place, fail, span);
+                }
+            }
+            // 单独的变体和字面量在 switch 中已经测试过
+            _ => match self.head(pattern) {
+                Head::Any | Head::Opaque => self.test_pattern(pattern, place, fail, span),
+                Head::Variants(_, _) | Head::Values(_) => {}
+            },
+        }
+    }
+
+    // 值的取值为 `known` 时模式是否可能匹配
+    fn may_match(&self, pattern: &Pattern, known: Option<i64>) -> bool {
+        match self.head(pattern) {
+            Head::Any | Head::Opaque => true,
+            Head::Variants(_, values) | Head::Values(values) => {
+                known.is_some_and(|value| values.contains(&value))
+            }
+        }
+    }
+
+    // 一段分支在值本身上测试的取值，从小到大排列；没有分支测试值本身时为 None。
+    // 返回的枚举名表示取值是变体序号
+    fn switch_values(&self, arms: &[MatchArm]) -> Option<(Option<String>, Vec<i64>)> {
+        let mut discr = None;
+        let mut values = BTreeSet::new();
+        for arm in arms {
+            match self.head(&arm.pattern) {
+                Head::Variants(enum_name, tested) => {
+                    discr = Some(Some(enum_name));
+                    values.extend(tested);
+                }
+                Head::Values(tested) => {
+                    discr = Some(None);
+                    values.extend(tested);
+                }
+                Head::Any | Head::Opaque => {}
+            }
+        }
+        Some((discr?, values.into_iter().collect()))
+    }
+
+    // switch 的操作数：枚举取标签，类型是布局中标签的类型；整数、bool 和字符直接比较
+    fn switch_operand(&mut self, place: &Place, enum_name: Option<String>, span: Span) -> Operand {
+        let Some(enum_name) = enum_name else {
+            return Operand::Copy(place.clone());
+        };
+        let discr = self.new_temp(self.tag_type(&enum_name), span);
+        self.assign(
+            Place::local(discr),
+            Rvalue::Discriminant(place.clone()),
+            span,
+        );
+        Operand::Copy(Place::local(discr))
+    }
+
+    fn tag_type(&self, enum_name: &str) -> Type {
+        self.cx
+            .enums
+            .get(enum_name)
+            .map_or(Type::U8, |def| layout::enum_tag(def))
+    }
+
+    // 模式在被匹配的值本身上测试的内容
+    fn head(&self, pattern: &Pattern) -> Head {
+        match pattern {
+            Pattern::Ident(name) => match self.unit_variant(name) {
+                Some((enum_name, index)) => Head::Variants(enum_name, vec![index as i64]),
+                None => Head::Any,
+            },
+            Pattern::TupleStruct(name, _) => match self.cx.variant(None, name) {
+                Some((enum_name, index)) => Head::Variants(enum_name, vec![index as i64]),
+                None => Head::Any,
+            },
+            Pattern::Literal(literal) => match literal {
+                Literal::Int(value, _) => Head::Values(vec![*value]),
+                Literal::Bool(value) => Head::Values(vec![*value as i64]),
+                Literal::Char(value) => Head::Values(vec![*value as i64]),
+                Literal::Float(_) | Literal::String(_) => Head::Opaque,
+            },
+            Pattern::Or(alternatives) => {
+                let mut head = None;
+                for alternative in alternatives {
+                    head = Some(match (head, self.head(alternative)) {
+                        (None, next) => next,
+                        (Some(Head::Opaque), _) | (_, Head::Opaque) => Head::Opaque,
+                        (Some(Head::Any), _) | (_, Head::Any) => Head::Any,
+                        (Some(Head::Variants(name, mut values)), Head::Variants(_, more)) => {
+                            values.extend(more);
+                            Head::Variants(name, values)
+                        }
+                        (Some(Head::Values(mut values)), Head::Values(more)) => {
+                            values.extend(more);
+                            Head::Values(values)
+                        }
+                        // 类型检查保证同一个值的模式不会既是变体又是字面量
+                        (Some(_), _) => Head::Opaque,
+                    });
+                }
+                head.unwrap_or(Head::Any)
+            }
+            Pattern::Wildcard | Pattern::Struct(_, _) | Pattern::Tuple(_) => Head::Any,
+        }
+    }
+
+    // `expr?`：按 prelude.rs 展开为 match，None/Err 的分支提前返回。
+    // 枚举由操作数的类型决定，推断不出时取所在函数的返回类型
+    fn lower_try(&mut self, inner: &Expr, dest: Option<Place>, span: Span) {
+        let place = self.as_place(inner);
+        let kind = TryKind::of(&self.place_ty(&place))
+            .or_else(|| TryKind::of(&self.locals[Local::RETURN.index()].ty));
+        match kind {
+            Some(kind) => self.lower_arms(&place, &kind.arms(span), dest, span),
+            None => self.error(
+                ErrorCode::E0282,
+                "cannot infer whether `?` is applied to an `Option` or a `Result`".to_string(),
+                span,
+            ),
+        }
+    }
+
+    // 生成模式测试：匹配时继续在当前块执行，不匹配时跳到 `fail`
+    fn test_pattern(&mut self, pattern: &Pattern, place: &Place, fail: BasicBlock, span: Span) {
+        match pattern {
+            Pattern::Wildcard => {}
+            Pattern::Ident(name) => {
+                if let Some((enum_name, index)) = self.unit_variant(name) {
+                    self.test_discriminant(place, enum_name, index, fail, span);
+                }
+            }
+            Pattern::Literal(literal) => {
+                let success = self.new_block();
+                let value = match literal {
+                    Literal::Int(value, _) => Some(*value),
+                    Literal::Bool(value) => Some(*value as i64),
+                    Literal::Char(value) => Some(*value as i64),
+                    Literal::Float(_) | Literal::String(_) => None,
+                };
+                match value {
+                    Some(value) => self.terminate(
+                        TerminatorKind::SwitchInt {
+                            discr: Operand::Copy(place.clone()),
+                            targets: vec![(value, success)],
+                            otherwise: fail,
+                        },
+                        span,
+                    ),
+                    None => {
+                        let constant = literal_constant(literal);
+                        let eq = self.new_temp(Type::Bool, span);
+                        self.assign(
+                            Place::local(eq),
+                            Rvalue::BinaryOp(
+                                BinOp::Equal,
+                                Operand::Copy(place.clone()),
+                                Operand::Constant(constant),
+                                None,
+                            ),
+                            span,
+                        );
+                        self.switch_bool(Operand::Copy(Place::local(eq)), success, fail, span);
+                    }
+                }
+                self.current = success;
+            }
+            Pattern::Struct(_, fields) => {
+                for (field, sub_pattern) in fields {
+                    let field_place = place.project(PlaceElem::Field(field.clone()));
+                    self.test_pattern(sub_pattern, &field_place, fail, span);
+                }
+            }
+            Pattern::TupleStruct(name, patterns) => {
+                if let Some((enum_name, index)) = self.cx.variant(None, name) {
+                    self.test_discriminant(place, enum_name, index, fail, span);
+                }
+                let place = self.variant_place(name, place);
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let field_place = place.project(PlaceElem::Field(i.to_string()));
+                    self.test_pattern(sub_pattern, &field_place, fail, span);
+                }
+            }
+            Pattern::Tuple(patterns) => {
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let field_place = place.project(PlaceElem::Field(i.to_string()));
+                    self.test_pattern(sub_pattern, &field_place, fail, span);
+                }
+            }
+            Pattern::Or(alternatives) => {
+                let success = self.new_block();
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let next = if i + 1 == alternatives.len() {
+                        fail
+                    } else {
+                        self.new_block()
+                    };
+                    self.test_pattern(alternative, place, next, span);
+                    self.goto(success, span);
+                    self.current = next;
+                }
+                self.current = success;
+            }
+        }
+    }
+
+    // 元组变体的字段要先把枚举值视为该变体；元组结构体直接访问字段
+    fn variant_place(&self, name: &str, place: &Place) -> Place {
+        if self.cx.variant(None, name).is_some() {
+            place.project(PlaceElem::Downcast(name.to_string()))
+        } else {
+            place.clone()
+        }
+    }
+
+    fn test_discriminant(
+        &mut self,
+        place: &Place,
+        enum_name: String,
+        index: usize,
+        fail: BasicBlock,
+        span: Span,
+    ) {
+        let discr = self.switch_operand(place, Some(enum_name), span);
+        let success = self.new_block();
+        self.terminate(
+            TerminatorKind::SwitchInt {
+                discr,
+                targets: vec![(index as i64, success)],
+                otherwise: fail,
+            },
+            span,
+        );
+        self.current = success;
+    }
+
+    // 把模式中的名字绑定为新的局部变量，值从 place 的对应部分复制
+    fn bind_pattern(&mut self, pattern: &Pattern, place: &Place, mutable: bool, span: Span) {
+        match pattern {
+            Pattern::Ident(name) => {
+                if self.is_unit_variant(name) {
+                    return;
+                }
+                let ty = self.place_ty(place);
+                // 通过引用匹配到的盒子和 Rc 按引用绑定，不从引用指向的值中复制出来
+                if let (Some(borrow), Some(_)) = (self.borrowed(place), builtins::pointee_type(&ty))
+                {
+                    let ty = Type::Reference(Box::new(ty), borrow);
+                    let local =
+                        self.push_local(Some(name.clone()), ty, mutable, LocalKind::Var, span);
+                    self.push_statement(StatementKind::StorageLive(local), span);
+                    self.assign(
+                        Place::local(local),
+                        Rvalue::Ref(place.clone(), borrow),
+                        span,
+                    );
+                    self.declare(name, local);
+                    return;
+                }
+                let local = self.push_local(Some(name.clone()), ty, mutable, LocalKind::Var, span);
+                self.push_statement(StatementKind::StorageLive(local), span);
+                let value = self.consume(place.clone());
+                let owned = matches!(value, Operand::Move(_));
+                self.assign(Place::local(local), Rvalue::Use(value), span);
+                self.declare(name, local);
+                if owned {
+                    self.schedule_drop(local);
+                }
+            }
+            Pattern::Struct(_, fields) => {
+                for (field, sub_pattern) in fields {
+                    let field_place = place.project(PlaceElem::Field(field.clone()));
+                    self.bind_pattern(sub_pattern, &field_place, mutable, span);
+                }
+            }
+            Pattern::TupleStruct(name, patterns) => {
+                let place = self.variant_place(name, place);
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let field_place = place.project(PlaceElem::Field(i.to_string()));
+                    self.bind_pattern(sub_pattern, &field_place, mutable, span);
+                }
+            }
+            Pattern::Tuple(patterns) => {
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let field_place = place.project(PlaceElem::Field(i.to_string()));
+                    self.bind_pattern(sub_pattern, &field_place, mutable, span);
+                }
+            }
+            Pattern::Or(alternatives) => {
+                // 每个分支绑定相同的名字，按第一个分支绑定
+                if let Some(first) = alternatives.first() {
+                    self.bind_pattern(first, place, mutable, span);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+        }
+    }
+
+    // ---------------- 表达式 ----------------
+
+    // 求值表达式，结果写入 dest；dest 为 None 时只保留副作用
+    fn lower_expr(&mut self, expr: &Expr, dest: Option<Place>) {
+        match expr {
+            Expr::Assign(lhs, rhs, span) => {
+                let place = self.as_place(lhs);
+                if self.cx.needs_drop(&self.place_ty(&place)) {
+                    // 先求出新值，再析构旧值，最后写入
+                    let ty = self.place_ty(&place);
+                    let value = self.lower_operand_as(rhs, Some(&ty));
+                    self.drop_place(place.clone(), *span);
+                    self.assign(place, Rvalue::Use(value), *span);
+                } else {
+                    let ty = self.place_ty(&place);
+                    let value = self.lower_rvalue_as(rhs, Some(&ty));
+                    self.assign(place, value, *span);
+                }
+                self.assign_unit(dest, *span);
+            }
+            Expr::CompoundAssign(op, lhs, rhs, span, ty) => {
+                // `a op= b` 即 `a = a op b`，和解释器一样先求右侧，再确定左侧的位置
+                let rhs = self.read_operand(rhs);
+                let place = self.as_place(lhs);
+                let lhs = Operand::Copy(place.clone());
+                let value = Rvalue::BinaryOp(op.clone(), lhs, rhs, ty.get().cloned().map(Box::new));
+                self.assign(place, value, *span);
+                self.assign_unit(dest, *span);
+            }
+            Expr::Binary(op @ (BinOp::LogicalAnd | BinOp::LogicalOr), lhs, rhs, span, _) => {
+                let dest = dest.unwrap_or_else(|| Place::local(self.new_temp(Type::Bool, *span)));
+                let lhs = self.lower_operand(lhs);
+                let eval_rhs = self.new_block();
+                let short_circuit = self.new_block();
+                let join = self.new_block();
+                // a && b：a 为假时结果为假；a || b：a 为真时结果为真
+                let is_and = *op == BinOp::LogicalAnd;
+                if is_and {
+                    self.switch_bool(lhs, eval_rhs, short_circuit, *span);
+                } else {
+                    self.switch_bool(lhs, short_circuit, eval_rhs, *span);
+                }
+
+                self.current = short_circuit;
+                let constant = Operand::Constant(Constant::bool(!is_and));
+                self.assign(dest.clone(), Rvalue::Use(constant), *span);
+                self.goto(join, *span);
+
+                self.current = eval_rhs;
+                self.lower_expr(rhs, Some(dest));
+                self.goto(join, *span);
+
+                self.current = join;
+            }
+            Expr::Call(callee, args, span, _) => self.lower_call(callee, args, dest, *span),
+            Expr::MethodCall(receiver, method, args, span, _) => {
+                // 暂时没有 impl 块，方法调用按普通函数调用处理，接收者作为第一个参数；
+                // 没有同名的函数时调用接收者类型的 `<type>_<method>`
+                if let Some(builtin) = self.builtin(method) {
+                    if let Some(mutable) = builtin.receiver() {
+                        let place = self.as_place(receiver);
+                        let receiver = Some((place, mutable));
+                        return self.lower_builtin_call(builtin, receiver, args, dest, *span);
+                    }
+                    let mut all = vec![(**receiver).clone()];
+                    all.extend(args.iter().cloned());
+                    return self.lower_builtin_call(builtin, None, &all, dest, *span);
+                }
+                let receiver = self.lower_operand(receiver);
+                let function = self.method_function(method, &receiver);
+                let params = self.param_types(&function);
+                let mut operands = vec![receiver];
+                operands.extend(self.lower_arguments(args, params.get(1..).unwrap_or_default()));
+                let func = self.function_operand(&function);
+                self.emit_call(func, operands, dest, *span);
+            }
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.lower_block(block, dest)
+            }
+            Expr::If(cond, then_block, else_block, span) => {
+                self.lower_if(cond, then_block, else_block.as_ref(), dest, *span)
+            }
+            Expr::Match(scrutinee, arms, span) => self.lower_match(scrutinee, arms, dest, *span),
+            Expr::While(label, cond, body, span) => {
+                self.lower_while(label.as_ref(), cond, &[], body, *span);
+                self.assign_unit(dest, *span);
+            }
+            Expr::For(label, pattern, iterable, body, span) => {
+                self.lower_for(label.as_ref(), pattern, iterable, &[], body, *span);
+                self.assign_unit(dest, *span);
+            }
+            Expr::Break(label, value, span) => {
+                self.lower_break(label.as_ref(), value.as_deref(), *span)
+            }
+            Expr::Continue(label, span) => self.lower_continue(label.as_ref(), *span),
+            Expr::Return(value, span) => self.lower_return(value.as_deref(), *span),
+            Expr::Try(inner, span) => self.lower_try(inner, dest, *span),
+            // 在协程的实现之前 `.await` 直接得到操作数的值，见 features.rs
+            Expr::Await(inner, _) => self.lower_expr(inner, dest),
+            Expr::InlineAsm(asm, span) => self.lower_inline_asm(asm, dest, *span),
+            _ => {
+                let expected = dest.as_ref().map(|dest| self.place_ty(dest));
+                let value = self.lower_rvalue_as(expr, expected.as_ref());
+                let span = expr.span();
+                match dest {
+                    Some(dest) => self.assign(dest, value, span),
+                    None => {
+                        // 丢弃的值立即析构
+                        let temp = self.new_temp(Type::Infer, span);
+                        self.assign(Place::local(temp), value, span);
+                        self.drop_temp(temp, span);
+                    }
+                }
+            }
+        }
+    }
+
+    fn lower_rvalue(&mut self, expr: &Expr) -> Rvalue {
+        self.lower_rvalue_as(expr, None)
+    }
+
+    // `expected` 同 lower_operand_as，数组和元组字面量把它的元素类型传给各个元素
+    fn lower_rvalue_as(&mut self, expr: &Expr, expected: Option<&Type>) -> Rvalue {
+        match expr {
+            Expr::Literal(literal, _) => Rvalue::Use(Operand::Constant(literal_constant(literal))),
+            Expr::Ident(name, span) => self.lower_name(name, *span),
+            Expr::Path(segments, _) => {
+                if let [enum_name, variant] = segments.as_slice() {
+                    if let Some((enum_name, _)) = self.cx.variant(Some(enum_name), variant) {
+                        return variant_aggregate(enum_name, variant, Vec::new());
+                    }
+                }
+                // 其他模块中的条目，按完整路径引用
+                Rvalue::Use(self.function_operand(&segments.join("::")))
+            }
+            Expr::Binary(op, lhs, rhs, _, ty)
+                if !matches!(op, BinOp::LogicalAnd | BinOp::LogicalOr) =>
+            {
+                let lhs = self.read_operand(lhs);
+                let rhs = self.read_operand(rhs);
+                Rvalue::BinaryOp(op.clone(), lhs, rhs, ty.get().cloned().map(Box::new))
+            }
+            Expr::Unary(UnOp::Ref, inner, _, _) | Expr::Ref(inner, false, _) => {
+                Rvalue::Ref(self.as_place(inner), false)
+            }
+            Expr::Unary(UnOp::RefMut, inner, _, _) | Expr::Ref(inner, true, _) => {
+                Rvalue::Ref(self.as_place(inner), true)
+            }
+            Expr::Unary(UnOp::Deref, _, _, _)
+            | Expr::Deref(_, _)
+            | Expr::FieldAccess(_, _, _)
+            | Expr::TupleIndex(_, _, _)
+            | Expr::IndexAccess(_, _, _) => {
+                let place = self.as_place(expr);
+                Rvalue::Use(self.consume(place))
+            }
+            Expr::Unary(op, inner, _, ty) => {
+                let operand = self.lower_operand(inner);
+                Rvalue::UnaryOp(op.clone(), operand, ty.get().cloned().map(Box::new))
+            }
+            Expr::StructLit(name, fields, span) => {
+                let names = fields.iter().map(|(field, _)| field.clone()).collect();
+                let operands = fields
+                    .iter()
+                    .map(|(_, value)| self.lower_operand(value))
+                    .collect::<Vec<_>>();
+                if let Some(def) = self.cx.structs.get(name.as_str()) {
+                    if def.generics.is_none() {
+                        for ((field, _), operand) in fields.iter().zip(&operands) {
+                            if let Some(decl) = def.fields.iter().find(|f| &f.name == field) {
+                                self.refine_operand_ty(operand, &decl.ty);
+                            }
+                        }
+                    }
+                }
+                let value = Rvalue::Aggregate(AggregateKind::Struct(name.clone(), names), operands);
+                let invariants = match self.cx.structs.get(name.as_str()) {
+                    Some(def) if self.cx.contracts == ContractMode::Check => &def.invariants,
+                    _ => return value,
+                };
+                if invariants.is_empty() {
+                    return value;
+                }
+                // 先构造到临时变量，检查不变式之后再作为结果
+                let ty = self.rvalue_ty(&value);
+                let temp = self.new_temp(ty, *span);
+                self.assign(Place::local(temp), value, *span);
+                for invariant in invariants {
+                    self.check_contract_at(invariant, name, &[("self", temp)], *span);
+                }
+                Rvalue::Use(self.consume(Place::local(temp)))
+            }
+            Expr::ArrayLit(elements, _) => {
+                let expected = match expected {
+                    Some(Type::Array(elem, _)) => Some(elem.as_ref()),
+                    _ => None,
+                };
+                let operands: Vec<Operand> = elements
+                    .iter()
+                    .map(|e| self.lower_operand_as(e, expected))
+                    .collect();
+                // 没有上下文时取第一个类型完整的元素的类型，`[Some(1), None]` 的 `None` 由它补全
+                let types: Vec<Type> = operands.iter().map(|op| self.operand_ty(op)).collect();
+                let elem_ty = expected
+                    .cloned()
+                    .or_else(|| types.iter().find(|ty| !ty.has_infer()).cloned())
+                    .or_else(|| types.first().cloned())
+                    .unwrap_or(Type::Infer);
+                for operand in &operands {
+                    self.refine_operand_ty(operand, &elem_ty);
+                }
+                Rvalue::Aggregate(AggregateKind::Array(elem_ty), operands)
+            }
+            Expr::TupleLit(elements, _) if elements.is_empty() => Rvalue::Use(unit_operand()),
+            Expr::TupleLit(elements, _) => {
+                let expected = match expected {
+                    Some(Type::Tuple(types)) => types.as_slice(),
+                    _ => &[],
+                };
+                let operands = self.lower_arguments(elements, expected);
+                Rvalue::Aggregate(AggregateKind::Tuple, operands)
+            }
+            Expr::Range(start, end, inclusive, span) => {
+                let operands = vec![self.range_bound(start, *span), self.range_bound(end, *span)];
+                Rvalue::Aggregate(AggregateKind::Range(*inclusive), operands)
+            }
+            Expr::Cast(inner, ty, _) => Rvalue::Cast(self.lower_operand(inner), ty.expand_typeof()),
+            Expr::Closure(params, ret, body, span) => {
+                self.lower_closure(params, ret.as_ref(), body, *span)
+            }
+            _ => {
+                // 控制流、调用、赋值等表达式先求值到临时变量
+                let span = expr.span();
+                let temp = self.new_temp(Type::Infer, span);
+                self.lower_expr(expr, Some(Place::local(temp)));
+                Rvalue::Use(self.consume(Place::local(temp)))
+            }
+        }
+    }
+
+    fn lower_operand(&mut self, expr: &Expr) -> Operand {
+        self.lower_operand_as(expr, None)
+    }
+
+    // `expected` 是上下文要求的类型：值的类型推断不完整时（如不知道类型实参的 `None`），
+    // 临时变量取这个类型，单态化由它确定泛型类型的实例
+    fn lower_operand_as(&mut self, expr: &Expr, expected: Option<&Type>) -> Operand {
+        match expr {
+            Expr::Literal(literal, _) => Operand::Constant(literal_constant(literal)),
+            Expr::Ident(name, _) if self.lookup(name).is_some() => {
+                let place = self.as_place(expr);
+                self.consume(place)
+            }
+            Expr::Unary(UnOp::Deref, _, _, _)
+            | Expr::Deref(_, _)
+            | Expr::FieldAccess(_, _, _)
+            | Expr::TupleIndex(_, _, _)
+            | Expr::IndexAccess(_, _, _) => {
+                let place = self.as_place(expr);
+                self.consume(place)
+            }
+            _ => match self.lower_rvalue_as(expr, expected) {
+                Rvalue::Use(operand) => operand,
+                value => {
+                    let span = expr.span();
+                    let ty = match (self.rvalue_ty(&value), expected) {
+                        (ty, Some(expected)) if ty.has_infer() => expected.clone(),
+                        (ty, _) => ty,
+                    };
+                    let temp = self.new_temp(ty, span);
+                    self.assign(Place::local(temp), value, span);
+                    self.consume(Place::local(temp))
+                }
+            },
+        }
+    }
+
+    // 运算符只读取操作数，不转移所有权
+    fn read_operand(&mut self, expr: &Expr) -> Operand {
+        match self.lower_operand(expr) {
+            Operand::Move(place) => Operand::Copy(place),
+            operand => operand,
+        }
+    }
+
+    // 按值读取 place：需要析构的值转移所有权；
+    // 经过解引用或下标的 place 不拥有其中的值，只能复制
+    fn consume(&self, place: Place) -> Operand {
+        // 通过引用做模式匹配时，绑定的是引用指向的值，不转移所有权
+        let borrowed = self.borrowed(&place).is_some();
+        if place.is_owned_by_local() && !borrowed && self.cx.needs_drop(&self.place_ty(&place)) {
+            Operand::Move(place)
+        } else {
+            Operand::Copy(place)
+        }
+    }
+
+    // place 的局部变量是引用或指针（对引用做模式匹配时隐式解引用）：返回它是否可变
+    fn borrowed(&self, place: &Place) -> Option<bool> {
+        match self.locals[place.local.index()].ty {
+            Type::Reference(_, mutable) | Type::Pointer(_, mutable) => Some(mutable),
+            _ => None,
+        }
+    }
+
+    // 求出表达式对应的 place；不是 place 表达式时先求值到临时变量
+    fn as_place(&mut self, expr: &Expr) -> Place {
+        match expr {
+            Expr::Ident(name, span) => {
+                if let Some(local) = self.lookup(name) {
+                    return Place::local(local);
+                }
+                // 静态变量通过它的引用访问，赋值和取引用作用于静态变量本身
+                if let Some(static_def) = self.cx.statics.get(name.as_str()) {
+                    let ty = Type::Reference(Box::new(static_def.ty.clone()), static_def.mutable);
+                    let constant = Constant {
+                        value: ConstValue::StaticRef(name.clone()),
+                        ty: ty.clone(),
+                    };
+                    let temp = self.new_temp(ty, *span);
+                    let operand = Operand::Constant(constant);
+                    self.assign(Place::local(temp), Rvalue::Use(operand), *span);
+                    return Place::local(temp).project(PlaceElem::Deref);
+                }
+            }
+            Expr::FieldAccess(base, field, _) => {
+                let base = self.as_place(base);
+                let base = self.auto_deref(base);
+                return base.project(PlaceElem::Field(field.clone()));
+            }
+            Expr::TupleIndex(base, index, _) => {
+                let base = self.as_place(base);
+                let base = self.auto_deref(base);
+                return base.project(PlaceElem::Field(index.to_string()));
+            }
+            Expr::IndexAccess(base, index, span) => {
+                let base = self.as_place(base);
+                let base = self.auto_deref(base);
+                // 子切片的边界由执行时检查，`..=` 的终点换成不含终点；
+                // 省略的起点是 0，省略的终点是长度
+                if let Expr::Range(start, end, inclusive, _) = &**index {
+                    let start = match start {
+                        Some(start) => self.index_local(start, *span),
+                        None => {
+                            let zero = self.new_temp(Type::Usize, *span);
+                            let constant = Operand::Constant(Constant::int(0, Type::Usize));
+                            self.assign(Place::local(zero), Rvalue::Use(constant), *span);
+                            zero
+                        }
+                    };
+                    let mut end = match end {
+                        Some(end) => self.index_local(end, *span),
+                        None => {
+                            let len = self.new_temp(Type::Usize, *span);
+                            self.assign(Place::local(len), Rvalue::Len(base.clone()), *span);
+                            len
+                        }
+                    };
+                    if *inclusive {
+                        let ty = match &self.locals[end.index()].ty {
+                            Type::Infer => Type::I32,
+                            ty => ty.clone(),
+                        };
+                        let exclusive = self.new_temp(ty.clone(), *span);
+                        let one = Operand::Constant(Constant::int(1, ty));
+                        let rvalue = Rvalue::BinaryOp(
+                            BinOp::Add,
+                            Operand::Copy(Place::local(end)),
+                            one,
+                            None,
+                        );
+                        self.assign(Place::local(exclusive), rvalue, *span);
+                        end = exclusive;
+                    }
+                    return base.project(PlaceElem::Subslice(start, end));
+                }
+                let index = self.index_local(index, *span);
+                self.bounds_check(&base, index, *span);
+                return base.project(PlaceElem::Index(index));
+            }
+            Expr::Unary(UnOp::Deref, inner, _, _) | Expr::Deref(inner, _) => {
+                return self.as_place(inner).project(PlaceElem::Deref);
+            }
+            _ => {}
+        }
+
+        let span = expr.span();
+        match self.lower_operand(expr) {
+            Operand::Copy(place) | Operand::Move(place) => place,
+            operand => {
+                let ty = self.operand_ty(&operand);
+                let temp = self.new_temp(ty, span);
+                self.assign(Place::local(temp), Rvalue::Use(operand), span);
+                Place::local(temp)
+            }
+        }
+    }
+
+    // 切片以外的区间必须有起点和终点
+    fn range_bound(&mut self, bound: &Option<Box<Expr>>, span: Span) -> Operand {
+        match bound {
+            Some(bound) => self.lower_operand(bound),
+            None => {
+                let message = "only a slice range can omit its start or end".to_string();
+                self.error(ErrorCode::E0810, message, span);
+                Operand::Constant(Constant::int(0, Type::I64))
+            }
+        }
+    }
+
+    // 下标求值到局部变量，投影中只能引用局部变量。总是使用新的临时变量：
+    // 它记录下标表达式的位置，虚拟机在这里报告越界
+    fn index_local(&mut self, index: &Expr, span: Span) -> Local {
+        let operand = self.lower_operand(index);
+        let ty = self.operand_ty(&operand);
+        let temp = self.new_temp(ty, span);
+        self.assign(Place::local(temp), Rvalue::Use(operand), span);
+        temp
+    }
+
+    // 通过引用、指针或盒子访问字段和下标时自动解引用
+    fn auto_deref(&self, mut place: Place) -> Place {
+        loop {
+            match self.place_ty(&place) {
+                Type::Reference(_, _) | Type::Pointer(_, _) => {}
+                ty if builtins::pointee_type(&ty).is_some() => {}
+                _ => return place,
+            }
+            place = place.project(PlaceElem::Deref);
+        }
+    }
+
+    fn lower_name(&mut self, name: &str, span: Span) -> Rvalue {
+        if let Some(local) = self.lookup(name) {
+            return Rvalue::Use(self.consume(Place::local(local)));
+        }
+        if let Some((enum_name, _)) = self.unit_variant(name) {
+            return variant_aggregate(enum_name, name, Vec::new());
+        }
+        if let Some(const_def) = self.cx.consts.get(name) {
+            // 常量在使用处内联
+            return self.lower_rvalue(&const_def.value);
+        }
+        if let Some(static_def) = self.cx.statics.get(name) {
+            return Rvalue::Use(Operand::Constant(Constant {
+                value: ConstValue::Static(name.to_string()),
+                ty: static_def.ty.clone(),
+            }));
+        }
+        if self.cx.functions.contains_key(name) {
+            return Rvalue::Use(self.function_operand(name));
+        }
+        self.error(
+            ErrorCode::E0425,
+            format!("cannot find value `{}` in this scope", name),
+            span,
+        );
+        Rvalue::Use(unit_operand())
+    }
+
+    fn lower_call(&mut self, callee: &Expr, args: &[Expr], dest: Option<Place>, span: Span) {
+        if let Expr::Turbofish(path, types, _) = callee {
+            return self.lower_intrinsic(path, types, dest, span);
+        }
+        // 带字段的枚举变体：`Some(x)`、`Shape::Circle(r)`
+        let variant = match callee {
+            Expr::Ident(name, _) if self.lookup(name).is_none() => self.cx.variant(None, name),
+            Expr::Path(segments, _) if segments.len() == 2 => {
+                self.cx.variant(Some(&segments[0]), &segments[1])
+            }
+            _ => None,
+        };
+        if let Some((enum_name, index)) = variant {
+            let def = self.cx.enums[enum_name.as_str()];
+            let variant = def.variants[index].name.clone();
+            let fields = match &def.generics {
+                Some(_) => Vec::new(),
+                None => def.variants[index].fields.clone().unwrap_or_default(),
+            };
+            let operands = self.lower_arguments(args, &fields);
+            let value = variant_aggregate(enum_name, &variant, operands);
+            let dest = dest.unwrap_or_else(|| Place::local(self.new_temp(Type::Infer, span)));
+            self.assign(dest, value, span);
+            return;
+        }
+
+        let func = match callee {
+            Expr::Ident(name, _) if self.lookup(name).is_none() => {
+                if let Some(builtin) = self.builtin(name) {
+                    return self.lower_builtin_call(builtin, None, args, dest, span);
+                }
+                self.function_operand(name)
+            }
+            Expr::Path(segments, _) => match self.builtin(&segments.join("::")) {
+                Some(builtin) => return self.lower_builtin_call(builtin, None, args, dest, span),
+                None => self.lower_operand(callee),
+            },
+            _ => self.lower_operand(callee),
+        };
+        let params = match callee {
+            Expr::Ident(name, _) if self.lookup(name).is_none() => self.param_types(name),
+            _ => Vec::new(),
+        };
+        let args = self.lower_arguments(args, &params);
+        self.emit_call(func, args, dest, span);
+    }
+
+    // 实参按对应的形参类型降级，`params` 中没有的按实参自己的类型
+    fn lower_arguments(&mut self, args: &[Expr], params: &[Type]) -> Vec<Operand> {
+        args.iter()
+            .enumerate()
+            .map(|(i, arg)| self.lower_operand_as(arg, params.get(i)))
+            .collect()
+    }
+
+    // 函数 `name` 的形参类型，含有类型参数的形参为 `_`
+    fn param_types(&self, name: &str) -> Vec<Type> {
+        let Some(func) = self.cx.functions.get(name) else {
+            return Vec::new();
+        };
+        let generics = func
+            .generics
+            .as_ref()
+            .map(Generics::param_names)
+            .unwrap_or_default();
+        func.params
+            .iter()
+            .map(|param| match param.ty.mentions(&generics) {
+                true => Type::Infer,
+                false => param.ty.clone(),
+            })
+            .collect()
+    }
+
+    // `std::mem::size_of::<T>()` 和 `std::mem::align_of::<T>()` 在编译时求值为 usize 常量
+    fn lower_intrinsic(&mut self, path: &Expr, types: &[Type], dest: Option<Place>, span: Span) {
+        let name = match path {
+            Expr::Path(segments, _) => layout::intrinsic(&segments.join("::")),
+            Expr::Ident(name, _) => layout::intrinsic(name),
+            _ => None,
+        };
+        let value = match (name, types) {
+            (Some(name), [ty]) => {
+                let layouts = Layouts::new(
+                    self.cx.structs.values().copied(),
+                    self.cx.enums.values().copied(),
+                );
+                layouts
+                    .intrinsic(name, &ty.expand_typeof())
+                    .map_err(|error| error.to_string())
+            }
+            _ => Err("invalid call of a layout intrinsic".to_string()),
+        };
+        let value = value.unwrap_or_else(|message| {
+            self.error(ErrorCode::E0810, message, span);
+            0
+        });
+        let dest = dest.unwrap_or_else(|| Place::local(self.new_temp(Type::Usize, span)));
+        let constant = Operand::Constant(Constant::int(value as i64, Type::Usize));
+        self.assign(dest, Rvalue::Use(constant), span);
+    }
+
+    // 没有被程序中的函数遮蔽的内建函数
+    fn builtin(&self, name: &str) -> Option<&'static builtins::Signature> {
+        match self.cx.functions.contains_key(name) {
+            true => None,
+            false => builtins::lookup(name),
+        }
+    }
+
+    // 内建函数调用；`receiver` 是按方法调用时接收者的 place 和引用是否可变，
+    // 以集合为第一个参数的内建函数对它取引用
+    fn lower_builtin_call(
+        &mut self,
+        builtin: &'static builtins::Signature,
+        receiver: Option<(Place, bool)>,
+        mut args: &[Expr],
+        dest: Option<Place>,
+        span: Span,
+    ) {
+        let borrowed = match args.first() {
+            Some(Expr::Ref(inner, mutable, _)) => Some((inner, *mutable)),
+            Some(Expr::Unary(UnOp::Ref, inner, _, _)) => Some((inner, false)),
+            Some(Expr::Unary(UnOp::RefMut, inner, _, _)) => Some((inner, true)),
+            _ => None,
+        };
+        let target = match (receiver, borrowed) {
+            (Some(receiver), _) => Some(receiver),
+            (None, Some((inner, mutable))) if builtin.receiver().is_some() => {
+                args = &args[1..];
+                Some((self.as_place(inner), mutable))
+            }
+            _ => None,
+        };
+        let mut operands = Vec::new();
+        if let Some((place, mutable)) = &target {
+            let ty = Type::Reference(Box::new(self.place_ty(place)), *mutable);
+            let reference = self.new_temp(ty, span);
+            self.assign(
+                Place::local(reference),
+                Rvalue::Ref(place.clone(), *mutable),
+                span,
+            );
+            operands.push(Operand::Copy(Place::local(reference)));
+        }
+        operands.extend(args.iter().map(|arg| self.lower_operand(arg)));
+        let first = operands.first().map(|operand| self.operand_ty(operand));
+        let builtin = builtins::resolve(builtin.name, first.as_ref()).unwrap_or(builtin);
+
+        let source = target.map(|(place, _)| place);
+        match (builtin.name, &source) {
+            ("push" | "insert", Some(target)) => self.infer_type_args(target, &operands[1..]),
+            ("pop", _) => return self.lower_pop(operands.remove(0), source, dest, span),
+            ("abs", _) if matches!(first, Some(Type::I8 | Type::I16 | Type::I32)) => {
+                return self.lower_abs(operands.remove(0), first.unwrap(), dest, span)
+            }
+            ("catch_panic", _) if self.cx.panic == PanicStrategy::Abort => {
+                return self.lower_uncaught(operands.remove(0), first, dest, span)
+            }
+            _ if matches!(builtin.ret, builtins::Returns::Checked(..)) => {
+                return self.lower_checked(builtin, operands, source, dest, span)
+            }
+            _ if matches!(builtin.ret, builtins::Returns::Fallible(..)) => {
+                return self.lower_fallible(builtin, operands, first, dest, span)
+            }
+            _ => {}
+        }
+        let func = self.function_operand(builtin.name);
+        self.emit_call(func, operands, dest, span);
+    }
+
+    // `Vec::new()`、`Map::new()` 的类型参数由第一次 push/insert 的值推断；
+    // 反过来，类型参数已知时补全值的类型，如 `v.push(None)` 的 `None`
+    fn infer_type_args(&mut self, collection: &Place, values: &[Operand]) {
+        let types: Vec<Type> = values.iter().map(|value| self.operand_ty(value)).collect();
+        if let Some(local) = collection.as_local() {
+            if let Type::Generic(name, args) = &mut self.locals[local.index()].ty {
+                if builtins::is_type(name) && args.len() == types.len() {
+                    for (arg, ty) in args.iter_mut().zip(types) {
+                        if *arg == Type::Infer {
+                            *arg = ty;
+                        }
+                    }
+                }
+            }
+        }
+        if let Type::Generic(name, args) = self.place_ty(collection) {
+            if builtins::is_type(&name) && args.len() == values.len() {
+                for (value, arg) in values.iter().zip(&args) {
+                    self.refine_operand_ty(value, arg);
+                }
+            }
+        }
+    }
+
+    // 预导入的 `Option`；程序定义的 Option 没有 `Some` 和 `None` 时为 None
+    fn option_enum(&self) -> Option<String> {
+        let (enum_name, _) = self.cx.variant(Some("Option"), "Some")?;
+        self.cx.variant(Some("Option"), "None")?;
+        Some(enum_name)
+    }
+
+    // 预导入的 `Result`，同样可能被程序自己的定义代替
+    fn result_enum(&self) -> Option<String> {
+        let (enum_name, _) = self.cx.variant(Some("Result"), "Ok")?;
+        self.cx.variant(Some("Result"), "Err")?;
+        Some(enum_name)
+    }
+
+    // 虚拟机没有构造 Option 的方式，`pop(v)` 展开为
+    // `if len(*v) == 0 { None } else { Some(remove(v, len(*v) - 1)) }`
+    fn lower_pop(&mut self, vec: Operand, source: Option<Place>, dest: Option<Place>, span: Span) {
+        let Some(option) = self.option_enum() else {
+            // 按普通的内建函数调用
+            let func = self.function_operand("pop");
+            return self.emit_call(func, vec![vec], dest, span);
+        };
+        let vec_ty = self.operand_ty(&vec);
+        let element = builtins::element_type(&vec_ty).unwrap_or(Type::Infer);
+        let reference = self.new_temp(vec_ty, span);
+        self.assign(Place::local(reference), Rvalue::Use(vec), span);
+        let len = self.new_temp(Type::Usize, span);
+        let target = Place::local(reference).project(PlaceElem::Deref);
+        self.assign(Place::local(len), Rvalue::Len(target), span);
+        let nonempty = self.new_temp(Type::Bool, span);
+        let zero = Operand::Constant(Constant::int(0, Type::Usize));
+        self.assign(
+            Place::local(nonempty),
+            Rvalue::BinaryOp(
+                BinOp::NotEqual,
+                Operand::Copy(Place::local(len)),
+                zero,
+                None,
+            ),
+            span,
+        );
+        let nonempty = Operand::Copy(Place::local(nonempty));
+        let unknown = element == Type::Infer;
+        let locals = self.lower_optional(option, element, nonempty, dest, span, |this, element| {
+            let index = this.new_temp(Type::Usize, span);
+            let one = Operand::Constant(Constant::int(1, Type::Usize));
+            this.assign(
+                Place::local(index),
+                Rvalue::BinaryOp(BinOp::Sub, Operand::Copy(Place::local(len)), one, None),
+                span,
+            );
+            let args = vec![
+                Operand::Copy(Place::local(reference)),
+                Operand::Copy(Place::local(index)),
+            ];
+            let func = this.function_operand("remove");
+            this.emit_call(func, args, Some(element), span);
+        });
+        if let (true, Some(collection)) = (unknown, source) {
+            self.pending.push(PendingElement {
+                collection,
+                element: builtins::element_type,
+                locals,
+            });
+        }
+    }
+
+    // 比 i64 窄的有符号整数的 `abs(x)` 展开为 `if x < 0 { -x } else { x }`，按参数的类型检查取负
+    // 是否溢出；虚拟机中的 `abs` 只按 i64 检查
+    fn lower_abs(&mut self, value: Operand, ty: Type, dest: Option<Place>, span: Span) {
+        let dest = match dest {
+            Some(place) => {
+                self.infer_local_ty(&place, ty.clone());
+                place
+            }
+            None => Place::local(self.new_temp(ty.clone(), span)),
+        };
+        let value = match value {
+            Operand::Constant(_) => value,
+            _ => {
+                let temp = self.new_temp(ty.clone(), span);
+                self.assign(Place::local(temp), Rvalue::Use(value), span);
+                Operand::Copy(Place::local(temp))
+            }
+        };
+        let negative = self.new_temp(Type::Bool, span);
+        let zero = Operand::Constant(Constant::int(0, ty.clone()));
+        self.assign(
+            Place::local(negative),
+            Rvalue::BinaryOp(BinOp::Less, value.clone(), zero, None),
+            span,
+        );
+        let negate_bb = self.new_block();
+        let keep_bb = self.new_block();
+        let join = self.new_block();
+        self.switch_bool(
+            Operand::Copy(Place::local(negative)),
+            negate_bb,
+            keep_bb,
+            span,
+        );
+
+        self.current = negate_bb;
+        let negated = Rvalue::UnaryOp(UnOp::Neg, value.clone(), Some(Box::new(ty)));
+        self.assign(dest.clone(), negated, span);
+        self.goto(join, span);
+
+        self.current = keep_bb;
+        self.assign(dest, Rvalue::Use(value), span);
+        self.goto(join, span);
+
+        self.current = join;
+    }
+
+    // 返回 Option 的内建函数展开为检查和取值，如 `get(m, k)` 展开为
+    // `if contains_key(m, k) { Some(get(m, k)) } else { None }`，虚拟机中的 `get` 直接取值；
+    // 检查和取值都不转移参数的所有权
+    fn lower_checked(
+        &mut self,
+        builtin: &builtins::Signature,
+        operands: Vec<Operand>,
+        source: Option<Place>,
+        dest: Option<Place>,
+        span: Span,
+    ) {
+        let (Some(option), builtins::Returns::Checked(check, value)) =
+            (self.option_enum(), &builtin.ret)
+        else {
+            let func = self.function_operand(builtin.name);
+            return self.emit_call(func, operands, dest, span);
+        };
+        let operands: Vec<Operand> = operands
+            .into_iter()
+            .map(|operand| match operand {
+                Operand::Move(place) => Operand::Copy(place),
+                operand => operand,
+            })
+            .collect();
+        let first = self.operand_ty(&operands[0]);
+        let value = value.resolve(Some(&first));
+        let found = self.new_temp(Type::Bool, span);
+        let func = self.function_operand(check);
+        self.emit_call(func, operands.clone(), Some(Place::local(found)), span);
+        let found = Operand::Copy(Place::local(found));
+        let unknown = value == Type::Infer;
+        let locals = self.lower_optional(option, value, found, dest, span, |this, element| {
+            let func = this.function_operand(builtin.name);
+            this.emit_call(func, operands, Some(element), span);
+        });
+        if let (true, Some(collection)) = (unknown, source) {
+            self.pending.push(PendingElement {
+                collection,
+                element: |ty| builtins::map_types(ty).map(|(_, value)| value),
+                locals,
+            });
+        }
+    }
+
+    // 返回 Result 的内建函数在虚拟机中返回 `(ok, 值, 错误信息)`，展开为
+    // `if ok { Ok(值) } else { Err(错误信息) }`
+    fn lower_fallible(
+        &mut self,
+        builtin: &builtins::Signature,
+        operands: Vec<Operand>,
+        first: Option<Type>,
+        dest: Option<Place>,
+        span: Span,
+    ) {
+        let func = self.function_operand(builtin.name);
+        let (Some(result), builtins::Returns::Fallible(value)) = (self.result_enum(), &builtin.ret)
+        else {
+            return self.emit_call(func, operands, dest, span);
+        };
+        let value = value.resolve(first.as_ref());
+        let outcome_ty = Type::Tuple(vec![Type::Bool, value.clone(), Type::String]);
+        let outcome = Place::local(self.new_temp(outcome_ty, span));
+        self.emit_call(func, operands, Some(outcome.clone()), span);
+
+        let ty = Type::Generic(result.clone(), vec![value, Type::String]);
+        let (dest, discarded) = match dest {
+            Some(place) => {
+                self.infer_local_ty(&place, ty);
+                (place, None)
+            }
+            None => {
+                let temp = self.new_temp(ty, span);
+                (Place::local(temp), Some(temp))
+            }
+        };
+        let ok_bb = self.new_block();
+        let err_bb = self.new_block();
+        let join = self.new_block();
+        let field = |index: usize| outcome.clone().project(PlaceElem::Field(index.to_string()));
+        self.switch_bool(Operand::Copy(field(0)), ok_bb, err_bb, span);
+
+        for (block, variant, index) in [(ok_bb, "Ok", 1), (err_bb, "Err", 2)] {
+            self.current = block;
+            let value = Operand::Move(field(index));
+            let aggregate = variant_aggregate(result.clone(), variant, vec![value]);
+            self.assign(dest.clone(), aggregate, span);
+            self.goto(join, span);
+        }
+
+        self.current = join;
+        if let Some(temp) = discarded {
+            self.drop_temp(temp, span);
+        }
+    }
+
+    // panic 时立即结束程序，没有要捕获的 panic：`catch_panic(f)` 就是 `Ok(f())`
+    fn lower_uncaught(
+        &mut self,
+        callee: Operand,
+        callee_ty: Option<Type>,
+        dest: Option<Place>,
+        span: Span,
+    ) {
+        let Some(result) = self.result_enum() else {
+            let func = self.function_operand("catch_panic");
+            return self.emit_call(func, vec![callee], dest, span);
+        };
+        let value_ty = builtins::Returns::Callback.resolve(callee_ty.as_ref());
+        let value = self.new_temp(value_ty.clone(), span);
+        self.emit_call(callee, Vec::new(), Some(Place::local(value)), span);
+
+        let ty = Type::Generic(result.clone(), vec![value_ty, Type::String]);
+        let (dest, discarded) = match dest {
+            Some(place) => {
+                self.infer_local_ty(&place, ty);
+                (place, None)
+            }
+            None => {
+                let temp = self.new_temp(ty, span);
+                (Place::local(temp), Some(temp))
+            }
+        };
+        let value = self.consume(Place::local(value));
+        self.assign(dest, variant_aggregate(result, "Ok", vec![value]), span);
+        if let Some(temp) = discarded {
+            self.drop_temp(temp, span);
+        }
+    }
+
+    // `if present { Some(值) } else { None }`，`some` 在 present 为 true 的分支中把值写入给定的 place；
+    // 返回元素临时变量和作为目标的局部变量，用于之后补全类型
+    fn lower_optional(
+        &mut self,
+        option: String,
+        element: Type,
+        present: Operand,
+        dest: Option<Place>,
+        span: Span,
+        some: impl FnOnce(&mut Self, Place),
+    ) -> Vec<Local> {
+        let ty = Type::Generic(option.clone(), vec![element.clone()]);
+        let (dest, discarded) = match dest {
+            Some(place) => {
+                self.infer_local_ty(&place, ty);
+                (place, None)
+            }
+            None => {
+                let temp = self.new_temp(ty, span);
+                (Place::local(temp), Some(temp))
+            }
+        };
+        let some_bb = self.new_block();
+        let none_bb = self.new_block();
+        let join = self.new_block();
+        self.switch_bool(present, some_bb, none_bb, span);
+
+        self.current = some_bb;
+        let element = self.new_temp(element, span);
+        let locals = std::iter::once(element).chain(dest.as_local()).collect();
+        some(self, Place::local(element));
+        let value = self.consume(Place::local(element));
+        self.assign(
+            dest.clone(),
+            variant_aggregate(option.clone(), "Some", vec![value]),
+            span,
+        );
+        self.goto(join, span);
+
+        self.current = none_bb;
+        let value = variant_aggregate(option, "None", Vec::new());
+        self.assign(dest, value, span);
+        self.goto(join, span);
+
+        self.current = join;
+        if let Some(temp) = discarded {
+            self.drop_temp(temp, span);
+        }
+        locals
+    }
+
+    fn emit_call(&mut self, func: Operand, args: Vec<Operand>, dest: Option<Place>, span: Span) {
+        let ret_ty = match self.operand_ty(&func) {
+            Type::Function(params, ret, _) => self.call_return_ty(&func, &params, *ret, &args),
+            _ => Type::Infer,
+        };
+        let diverges = ret_ty == Type::Never;
+        let (destination, discarded) = match dest {
+            Some(place) => {
+                self.infer_local_ty(&place, ret_ty);
+                (place, None)
+            }
+            None => {
+                let temp = self.new_temp(ret_ty, span);
+                (Place::local(temp), Some(temp))
+            }
+        };
+        // 返回 `!` 的函数不会返回，调用之后的代码不可达
+        if diverges {
+            let unwind = self.cleanup(span);
+            self.terminate(
+                TerminatorKind::Call {
+                    func,
+                    args,
+                    destination,
+                    target: None,
+                    unwind,
+                },
+                span,
+            );
+            return self.start_unreachable_block();
+        }
+        let target = self.new_block();
+        let unwind = self.cleanup(span);
+        self.terminate(
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target: Some(target),
+                unwind,
+            },
+            span,
+        );
+        self.current = target;
+        if let Some(temp) = discarded {
+            self.drop_temp(temp, span);
+        }
+    }
+
+    // 调用泛型函数时由实参类型推断返回类型，推断不出时为 `_`
+    fn call_return_ty(&self, func: &Operand, params: &[Type], ret: Type, args: &[Operand]) -> Type {
+        if let Some(ConstValue::Function(name)) = func.constant().map(|c| &c.value) {
+            if let Some(builtin) = self.builtin(name) {
+                let first = args.first().map(|arg| self.operand_ty(arg));
+                let builtin = builtins::resolve(name, first.as_ref()).unwrap_or(builtin);
+                return builtin.return_type(first.as_ref());
+            }
+        }
+        let generics = match func.constant().map(|c| &c.value) {
+            Some(ConstValue::Function(name)) => self
+                .cx
+                .functions
+                .get(name.as_str())
+                .and_then(|f| f.generics.as_ref()),
+            _ => None,
+        };
+        let Some(generics) = generics else {
+            return ret;
+        };
+        let type_params = generics.param_names();
+        let mut type_args = BTreeMap::new();
+        for (param, arg) in params.iter().zip(args) {
+            param.infer_params(&self.operand_ty(arg), &type_params, &mut type_args);
+        }
+        for param in &type_params {
+            type_args.entry(param.clone()).or_insert(Type::Infer);
+        }
+        ret.substitute(&type_args)
+    }
+
+    fn method_function(&self, method: &str, receiver: &Operand) -> String {
+        if self.cx.functions.contains_key(method) {
+            return method.to_string();
+        }
+        let mut ty = self.operand_ty(receiver);
+        while let Type::Reference(inner, _) = ty {
+            ty = *inner;
+        }
+        match ty {
+            Type::Named(name) | Type::Generic(name, _) => {
+                let function = derive::method_function(&name, method);
+                match self.cx.functions.contains_key(function.as_str()) {
+                    true => function,
+                    false => method.to_string(),
+                }
+            }
+            _ => method.to_string(),
+        }
+    }
+
+    fn function_operand(&self, name: &str) -> Operand {
+        let ty = self
+            .cx
+            .functions
+            .get(name)
+            .map(|func| self.cx.function_type(func))
+            .or_else(|| {
+                // 内建函数只知道返回类型，依赖参数的部分在调用处推断
+                let builtin = builtins::lookup(name)?;
+                Some(Type::Function(
+                    Vec::new(),
+                    Box::new(builtin.return_type(None)),
+                    builtin.pure,
+                ))
+            })
+            .unwrap_or(Type::Infer);
+        Operand::Constant(Constant {
+            value: ConstValue::Function(name.to_string()),
+            ty,
+        })
+    }
+
+    // 闭包降级为独立的函数体：捕获的变量在前，闭包参数在后
+    fn lower_closure(
+        &mut self,
+        params: &[Parameter],
+        ret: Option<&Type>,
+        body: &Expr,
+        span: Span,
+    ) -> Rvalue {
+        let mut names = BTreeSet::new();
+        collect_expr_names(body, &mut names);
+        for param in params {
+            if let Pattern::Ident(name) = &param.pattern {
+                names.remove(name);
+            }
+        }
+        let captures: Vec<(String, Local)> = names
+            .into_iter()
+            .filter_map(|name| self.lookup(&name).map(|local| (name, local)))
+            .collect();
+
+        let name = format!("{}::{{closure#{}}}", self.name, self.closures.len());
+        let mut builder = Builder::new(self.cx, name.clone(), span);
+        // 闭包使用外层函数的类型参数，随外层函数一起单态化
+        builder.type_params = self.type_params.clone();
+        builder.push_local(
+            None,
+            ret.cloned().unwrap_or(Type::Infer),
+            false,
+            LocalKind::ReturnPointer,
+            span,
+        );
+        for (capture, local) in &captures {
+            let ty = self.locals[local.index()].ty.clone();
+            builder.arg_count += 1;
+            let arg = builder.push_local(Some(capture.clone()), ty, false, LocalKind::Arg, span);
+            builder.declare(capture, arg);
+        }
+        builder.lower_params(params);
+        builder.lower_expr(body, Some(Place::local(Local::RETURN)));
+        builder.drop_scopes(0, span);
+        builder.terminate(TerminatorKind::Return, span);
+
+        let (closure, nested) = builder.finish(&mut self.errors);
+        self.closures.extend(nested);
+        self.closures.push(closure);
+
+        let operands = captures
+            .into_iter()
+            .map(|(_, local)| self.consume(Place::local(local)))
+            .collect();
+        Rvalue::Aggregate(AggregateKind::Closure(name), operands)
+    }
+
+    // ---------------- 类型 ----------------
+
+    fn place_ty(&self, place: &Place) -> Type {
+        let mut ty = self.locals[place.local.index()].ty.clone();
+        let mut variant: Option<(String, String)> = None;
+
+        for elem in &place.projection {
+            ty = match elem {
+                PlaceElem::Deref => match ty {
+                    Type::Reference(inner, _) | Type::Pointer(inner, _) => *inner,
+                    ty => builtins::pointee_type(&ty).unwrap_or(Type::Infer),
+                },
+                PlaceElem::Index(_) => builtins::element_type(&ty).unwrap_or(Type::Infer),
+                PlaceElem::Subslice(_, _) => match builtins::element_type(&ty) {
+                    Some(element) => Type::Slice(Box::new(element)),
+                    None => Type::Infer,
+                },
+                PlaceElem::Downcast(name) => {
+                    // 对引用做模式匹配时，变体属于引用指向的枚举
+                    while let Type::Reference(inner, _) | Type::Pointer(inner, _) = ty {
+                        ty = *inner;
+                    }
+                    if let Type::Named(enum_name) | Type::Generic(enum_name, _) = &ty {
+                        variant = Some((enum_name.clone(), name.clone()));
+                    }
+                    continue;
+                }
+                PlaceElem::Field(field) => match variant.take() {
+                    Some((enum_name, variant)) => self
+                        .cx
+                        .variant(Some(&enum_name), &variant)
+                        .and_then(|(_, variant_index)| {
+                            let def = self.cx.enums[enum_name.as_str()];
+                            let fields = def.variants[variant_index].fields.as_ref()?;
+                            let field_ty = fields.get(field.parse::<usize>().ok()?)?;
+                            Some(field_ty.substitute(&type_args(def.generics.as_ref(), &ty)))
+                        })
+                        .unwrap_or(Type::Infer),
+                    None => self.field_ty(&ty, field),
+                },
+            };
+        }
+        ty
+    }
+
+    fn field_ty(&self, ty: &Type, field: &str) -> Type {
+        match ty {
+            Type::Named(name) | Type::Generic(name, _) => {
+                if let Some(def) = self.cx.structs.get(name.as_str()) {
+                    if let Some(f) = def.fields.iter().find(|f| f.name == field) {
+                        return f.ty.substitute(&type_args(def.generics.as_ref(), ty));
+                    }
+                }
+                if name == "Range" || name == "RangeInclusive" {
+                    if let Type::Generic(_, args) = ty {
+                        return args.first().cloned().unwrap_or(Type::Infer);
+                    }
+                }
+                Type::Infer
+            }
+            Type::Tuple(types) => field
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| types.get(i).cloned())
+                .unwrap_or(Type::Infer),
+            _ => Type::Infer,
+        }
+    }
+
+    fn operand_ty(&self, operand: &Operand) -> Type {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.place_ty(place),
+            Operand::Constant(constant) => constant.ty.clone(),
+        }
+    }
+
+    fn rvalue_ty(&self, value: &Rvalue) -> Type {
+        match value {
+            Rvalue::Use(operand) => self.operand_ty(operand),
+            Rvalue::BinaryOp(op, lhs, rhs, _) => match op {
+                BinOp::Equal
+                | BinOp::NotEqual
+                | BinOp::Less
+                | BinOp::Greater
+                | BinOp::LessEqual
+                | BinOp::GreaterEqual
+                | BinOp::LogicalAnd
+                | BinOp::LogicalOr => Type::Bool,
+                _ => match self.operand_ty(lhs) {
+                    Type::Infer => self.operand_ty(rhs),
+                    ty => ty,
+                },
+            },
+            Rvalue::UnaryOp(_, operand, _) => self.operand_ty(operand),
+            Rvalue::Ref(place, mutable) => {
+                Type::Reference(Box::new(self.place_ty(place)), *mutable)
+            }
+            Rvalue::Aggregate(kind, operands) => match kind {
+                AggregateKind::Tuple => {
+                    Type::Tuple(operands.iter().map(|op| self.operand_ty(op)).collect())
+                }
+                AggregateKind::Array(elem) => Type::Array(Box::new(elem.clone()), operands.len()),
+                AggregateKind::Struct(name, fields) => {
+                    let Some(def) = self.cx.structs.get(name.as_str()) else {
+                        return Type::Named(name.clone());
+                    };
+                    let field_types = fields.iter().map(|field| {
+                        def.fields
+                            .iter()
+                            .find(|f| f.name == *field)
+                            .map(|f| f.ty.clone())
+                            .unwrap_or(Type::Infer)
+                    });
+                    self.instantiate(name, def.generics.as_ref(), field_types, operands)
+                }
+                AggregateKind::Variant(enum_name, variant) => {
+                    let def = self.cx.enums[enum_name.as_str()];
+                    let field_types = def
+                        .variants
+                        .iter()
+                        .find(|v| v.name == *variant)
+                        .and_then(|v| v.fields.clone())
+                        .unwrap_or_default();
+                    self.instantiate(
+                        enum_name,
+                        def.generics.as_ref(),
+                        field_types.into_iter(),
+                        operands,
+                    )
+                }
+                AggregateKind::Range(inclusive) => {
+                    let name = if *inclusive {
+                        "RangeInclusive"
+                    } else {
+                        "Range"
+                    };
+                    let elem = operands
+                        .first()
+                        .map(|op| self.operand_ty(op))
+                        .unwrap_or(Type::Infer);
+                    Type::Generic(name.to_string(), vec![elem])
+                }
+                AggregateKind::Closure(name) => self
+                    .closures
+                    .iter()
+                    .find(|body| body.name == *name)
+                    .map(|body| {
+                        let params = body
+                            .args()
+                            .skip(operands.len())
+                            .map(|arg| body.local_decl(arg).ty.clone())
+                            .collect();
+                        Type::Function(params, Box::new(body.return_ty().clone()), false)
+                    })
+                    .unwrap_or(Type::Infer),
+            },
+            Rvalue::Cast(_, ty) => ty.clone(),
+            Rvalue::Len(_) => Type::Usize,
+            Rvalue::Discriminant(_) => Type::Isize,
+        }
+    }
+
+    // 泛型结构体或枚举的值：由字段的实际类型推断类型实参，推断不出的为 `_`
+    fn instantiate(
+        &self,
+        name: &str,
+        generics: Option<&Generics>,
+        field_types: impl Iterator<Item = Type>,
+        operands: &[Operand],
+    ) -> Type {
+        let Some(generics) = generics else {
+            return Type::Named(name.to_string());
+        };
+        let params = generics.param_names();
+        let mut args = BTreeMap::new();
+        for (field_ty, operand) in field_types.zip(operands) {
+            field_ty.infer_params(&self.operand_ty(operand), &params, &mut args);
+        }
+        let args = params
+            .iter()
+            .map(|param| args.get(param).cloned().unwrap_or(Type::Infer))
+            .collect();
+        Type::Generic(name.to_string(), args)
+    }
+
+    // 目标是类型未知的局部变量时，用第一次赋值的类型作为它的类型；
+    // 类型不完整时（`let mut x = None;` 的 `Option<_>`）用之后赋值的完整类型补全
+    fn infer_local_ty(&mut self, place: &Place, ty: Type) {
+        if let Some(local) = place.as_local() {
+            if self.locals[local.index()].ty == Type::Infer {
+                self.locals[local.index()].ty = ty;
+            } else if self.locals[local.index()].ty.has_infer() && !ty.has_infer() {
+                self.refine_local_ty(local, &ty);
+            }
+        }
+    }
+
+    // 类型不完整的临时变量（如 `None` 的 `Option<_>`）用字段声明的类型补全
+    fn refine_operand_ty(&mut self, operand: &Operand, expected: &Type) {
+        if let Some(local) = operand.place().and_then(Place::as_local) {
+            self.refine_local_ty(local, expected);
+        }
+    }
+
+    fn refine_local_ty(&mut self, local: Local, expected: &Type) {
+        let decl = &mut self.locals[local.index()];
+        if decl.ty != *expected && expected.infer_params(&decl.ty, &[], &mut BTreeMap::new()) {
+            decl.ty = expected.clone();
+        }
+    }
+
+    // ---------------- 基础设施 ----------------
+
+    fn push_local(
+        &mut self,
+        name: Option<String>,
+        ty: Type,
+        mutable: bool,
+        kind: LocalKind,
+        span: Span,
+    ) -> Local {
+        self.locals.push(LocalDecl {
+            name,
+            ty,
+            mutable,
+            kind,
+            span,
+        });
+        Local(self.locals.len() - 1)
+    }
+
+    fn new_temp(&mut self, ty: Type, span: Span) -> Local {
+        self.push_local(None, ty, true, LocalKind::Temp, span)
+    }
+
+    fn declare(&mut self, name: &str, local: Local) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.push((name.to_string(), local));
+        }
+    }
+
+    // 变量拥有需要析构的值时，登记到当前作用域，离开作用域时析构
+    fn schedule_drop(&mut self, local: Local) {
+        if self.cx.needs_drop(&self.locals[local.index()].ty) {
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.drops.push(local);
+            }
+        }
+    }
+
+    // 变量的初始值是从引用或数组元素复制出来的，值仍归原处所有
+    fn is_copied_from_borrow(&self, local: Local) -> bool {
+        let statements = &self.blocks[self.current.index()].statements;
+        matches!(
+            statements.last(),
+            Some(MirStatement {
+                kind: StatementKind::Assign(dest, Rvalue::Use(Operand::Copy(source))),
+                ..
+            }) if dest.as_local() == Some(local) && !source.is_owned_by_local()
+        )
+    }
+
+    // 离开作用域时析构其中的变量，并结束用户变量的存储期
+    fn pop_scope(&mut self, span: Span) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        for local in scope.drops.into_iter().rev() {
+            self.drop_place(Place::local(local), span);
+        }
+        for (_, local) in scope.names.into_iter().rev() {
+            if self.locals[local.index()].kind == LocalKind::Var {
+                self.push_statement(StatementKind::StorageDead(local), span);
+            }
+        }
+    }
+
+    // 提前离开 `scopes[depth..]`（return/break/continue）：从内到外析构其中的变量，
+    // 作用域本身由正常的控制流结束
+    fn drop_scopes(&mut self, depth: usize, span: Span) {
+        let drops: Vec<Local> = self.scopes[depth..]
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.drops.iter().rev().copied())
+            .collect();
+        for local in drops {
+            self.drop_place(Place::local(local), span);
+        }
+    }
+
+    fn drop_place(&mut self, place: Place, span: Span) {
+        let target = self.new_block();
+        self.terminate(
+            TerminatorKind::Drop {
+                place,
+                target,
+                unwind: None,
+            },
+            span,
+        );
+        self.current = target;
+    }
+
+    // panic 展开时的清理块：从内到外析构所有作用域中登记的变量，之后继续展开；
+    // 没有要析构的变量或者 panic 时立即结束程序时为 None
+    fn cleanup(&mut self, span: Span) -> Option<BasicBlock> {
+        if self.cx.panic == PanicStrategy::Abort {
+            return None;
+        }
+        let drops: Vec<Local> = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.drops.iter().rev().copied())
+            .collect();
+        if drops.is_empty() {
+            return None;
+        }
+        if let Some(block) = self.cleanups.get(&drops) {
+            return Some(*block);
+        }
+        let current = self.current;
+        let entry = self.new_block();
+        self.current = entry;
+        for local in &drops {
+            self.drop_place(Place::local(*local), span);
+        }
+        self.terminate(TerminatorKind::Resume, span);
+        self.current = current;
+        self.cleanups.insert(drops, entry);
+        Some(entry)
+    }
+
+    fn drop_temp(&mut self, temp: Local, span: Span) {
+        if self.cx.needs_drop(&self.locals[temp.index()].ty) {
+            self.drop_place(Place::local(temp), span);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Local> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.iter().rev().find(|(n, _)| n == name))
+            .map(|(_, local)| *local)
+    }
+
+    // 不带字段的变体名（模式或表达式中单独出现的标识符）
+    fn unit_variant(&self, name: &str) -> Option<(String, usize)> {
+        if self.lookup(name).is_some() {
+            return None;
+        }
+        self.cx.variant(None, name)
+    }
+
+    fn is_unit_variant(&self, name: &str) -> bool {
+        self.cx.variant(None, name).is_some()
+    }
+
+    fn new_block(&mut self) -> BasicBlock {
+        self.blocks.push(BlockBuilder {
+            statements: Vec::new(),
+            terminator: None,
+        });
+        BasicBlock(self.blocks.len() - 1)
+    }
+
+    // 操作数按书写的顺序求值，`inout` 的位置既是输入也是输出
+    fn lower_inline_asm(&mut self, asm: &InlineAsm, dest: Option<Place>, span: Span) {
+        let mut operands = Vec::new();
+        for operand in &asm.operands {
+            let (input, output) = match (&operand.expr, operand.dir) {
+                (None, _) => (None, None),
+                (Some(expr), AsmDir::In) => (Some(self.lower_operand(expr)), None),
+                (Some(expr), AsmDir::InOut) => {
+                    let place = self.as_place(expr);
+                    (Some(Operand::Copy(place.clone())), Some(place))
+                }
+                (Some(expr), AsmDir::Out | AsmDir::LateOut) => (None, Some(self.as_place(expr))),
+            };
+            operands.push(MirAsmOperand {
+                dir: operand.dir,
+                reg: operand.reg.clone(),
+                input,
+                output,
+            });
+        }
+        let lowered = MirInlineAsm {
+            template: asm.template.clone(),
+            operands,
+            options: asm.options.clone(),
+        };
+        self.push_statement(StatementKind::InlineAsm(Box::new(lowered)), span);
+        if asm.noreturn() {
+            self.terminate(TerminatorKind::Unreachable, span);
+            self.start_unreachable_block();
+        } else {
+            self.assign_unit(dest, span);
+        }
+    }
+
+    // return/break/continue 之后的代码放进一个没有前驱的新块，构建结束时删除
+    fn start_unreachable_block(&mut self) {
+        self.current = self.new_block();
+    }
+
+    fn push_statement(&mut self, kind: StatementKind, span: Span) {
+        self.blocks[self.current.index()]
+            .statements
+            .push(MirStatement { kind, span });
+    }
+
+    fn assign(&mut self, place: Place, value: Rvalue, span: Span) {
+        let ty = self.rvalue_ty(&value);
+        self.infer_local_ty(&place, ty);
+        self.push_statement(StatementKind::Assign(place, value), span);
+    }
+
+    fn assign_unit(&mut self, dest: Option<Place>, span: Span) {
+        if let Some(dest) = dest {
+            self.assign(dest, Rvalue::Use(unit_operand()), span);
+        }
+    }
+
+    fn terminate(&mut self, kind: TerminatorKind, span: Span) {
+        let block = &mut self.blocks[self.current.index()];
+        if block.terminator.is_none() {
+            block.terminator = Some(Terminator { kind, span });
+        }
+    }
+
+    fn goto(&mut self, target: BasicBlock, span: Span) {
+        self.terminate(TerminatorKind::Goto { target }, span);
+    }
+
+    // 检查 `0 <= index < len(base)`，越界时 panic；常量下标的检查由常量传播删除
+    fn bounds_check(&mut self, base: &Place, index: Local, span: Span) {
+        let len = self.new_temp(Type::Usize, span);
+        self.assign(Place::local(len), Rvalue::Len(base.clone()), span);
+        let index_ty = match &self.locals[index.index()].ty {
+            Type::Infer => Type::I32,
+            ty => ty.clone(),
+        };
+        let index = Operand::Copy(Place::local(index));
+        let non_negative = self.new_temp(Type::Bool, span);
+        self.assign(
+            Place::local(non_negative),
+            Rvalue::BinaryOp(
+                BinOp::GreaterEqual,
+                index.clone(),
+                Operand::Constant(Constant::int(0, index_ty)),
+                None,
+            ),
+            span,
+        );
+        let below_len = self.new_temp(Type::Bool, span);
+        self.assign(
+            Place::local(below_len),
+            Rvalue::BinaryOp(
+                BinOp::Less,
+                index.clone(),
+                Operand::Copy(Place::local(len)),
+                None,
+            ),
+            span,
+        );
+        let in_bounds = self.new_temp(Type::Bool, span);
+        self.assign(
+            Place::local(in_bounds),
+            Rvalue::BinaryOp(
+                BinOp::BitwiseAnd,
+                Operand::Copy(Place::local(non_negative)),
+                Operand::Copy(Place::local(below_len)),
+                None,
+            ),
+            span,
+        );
+        let next = self.new_block();
+        let unwind = self.cleanup(span);
+        self.terminate(
+            TerminatorKind::Assert {
+                cond: Operand::Copy(Place::local(in_bounds)),
+                msg: AssertMessage::BoundsCheck {
+                    len: Operand::Copy(Place::local(len)),
+                    index,
+                },
+                target: next,
+                unwind,
+            },
+            span,
+        );
+        self.current = next;
+    }
+
+    // 在返回之前检查 ensures，`result` 绑定到返回值
+    fn check_postconditions(&mut self) {
+        let postconditions = self.postconditions.clone();
+        for contract in postconditions {
+            let owner = self.name.clone();
+            self.check_contract(contract, &owner, &[("result", Local::RETURN)]);
+        }
+    }
+
+    fn check_contract(&mut self, contract: &Contract, owner: &str, bindings: &[(&str, Local)]) {
+        self.check_contract_at(contract, owner, bindings, contract.span);
+    }
+
+    // 在新作用域中绑定 `result`/`self` 并求值条件，为假时 panic
+    fn check_contract_at(
+        &mut self,
+        contract: &Contract,
+        owner: &str,
+        bindings: &[(&str, Local)],
+        span: Span,
+    ) {
+        self.scopes.push(Scope::default());
+        for (name, local) in bindings {
+            self.declare(name, *local);
+        }
+        let cond = self.read_operand(&contract.condition);
+        self.pop_scope(span);
+        let next = self.new_block();
+        let unwind = self.cleanup(span);
+        self.terminate(
+            TerminatorKind::Assert {
+                cond,
+                msg: AssertMessage::Contract {
+                    kind: contract.kind,
+                    owner: owner.to_string(),
+                    clause: contract.text.clone(),
+                    declared: contract.span,
+                },
+                target: next,
+                unwind,
+            },
+            span,
+        );
+        self.current = next;
+    }
+
+    // bool 分支：0 为假，其余为真
+    fn switch_bool(&mut self, cond: Operand, then_bb: BasicBlock, else_bb: BasicBlock, span: Span) {
+        self.terminate(
+            TerminatorKind::SwitchInt {
+                discr: cond,
+                targets: vec![(0, else_bb)],
+                otherwise: then_bb,
+            },
+            span,
+        );
+    }
+
+    fn error(&mut self, code: ErrorCode, message: String, span: Span) {
+        self.errors
+            .push(Diagnostic::error(message, span).with_code(code));
+    }
+}
+
+fn literal_constant(literal: &Literal) -> Constant {
+    let (value, ty) = match literal {
+        Literal::Int(value, suffix) => {
+            (ConstValue::Int(*value), suffix.clone().unwrap_or(Type::I32))
+        }
+        Literal::Float(value) => (ConstValue::Float(*value), Type::F64),
+        Literal::Bool(value) => (ConstValue::Bool(*value), Type::Bool),
+        Literal::Char(value) => (ConstValue::Char(*value), Type::Char),
+        Literal::String(value) => (ConstValue::Str(value.clone()), Type::String),
+    };
+    Constant { value, ty }
+}
+
+fn unit_operand() -> Operand {
+    Operand::Constant(Constant::unit())
+}
+
+fn variant_aggregate(enum_name: String, variant: &str, operands: Vec<Operand>) -> Rvalue {
+    Rvalue::Aggregate(
+        AggregateKind::Variant(enum_name, variant.to_string()),
+        operands,
+    )
+}
+
+// 收集表达式中出现的所有标识符，用于计算闭包捕获的变量
+pub(crate) fn collect_expr_names(expr: &Expr, names: &mut BTreeSet<String>) {
+    match expr {
+        Expr::Ident(name, _) => {
+            names.insert(name.clone());
+        }
+        Expr::Literal(_, _)
+        | Expr::Path(_, _)
+        | Expr::Turbofish(_, _, _)
+        | Expr::Continue(_, _)
+        | Expr::MacroCall(_) => {}
+        Expr::Binary(_, lhs, rhs, _, _)
+        | Expr::Assign(lhs, rhs, _)
+        | Expr::CompoundAssign(_, lhs, rhs, _, _)
+        | Expr::IndexAccess(lhs, rhs, _) => {
+            collect_expr_names(lhs, names);
+            collect_expr_names(rhs, names);
+        }
+        Expr::Range(start, end, _, _) => {
+            for bound in start.iter().chain(end) {
+                collect_expr_names(bound, names);
+            }
+        }
+        Expr::Unary(_, inner, _, _)
+        | Expr::FieldAccess(inner, _, _)
+        | Expr::TupleIndex(inner, _, _)
+        | Expr::Cast(inner, _, _)
+        | Expr::Ref(inner, _, _)
+        | Expr::Deref(inner, _)
+        | Expr::Try(inner, _)
+        | Expr::Await(inner, _)
+        | Expr::Closure(_, _, inner, _) => collect_expr_names(inner, names),
+        Expr::Call(callee, args, _, _) => {
+            collect_expr_names(callee, names);
+            args.iter().for_each(|arg| collect_expr_names(arg, names));
+        }
+        Expr::MethodCall(receiver, _, args, _, _) => {
+            collect_expr_names(receiver, names);
+            args.iter().for_each(|arg| collect_expr_names(arg, names));
+        }
+        Expr::StructLit(_, fields, _) => {
+            for (_, value) in fields {
+                collect_expr_names(value, names);
+            }
+        }
+        Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => {
+            elements.iter().for_each(|e| collect_expr_names(e, names));
+        }
+        Expr::InlineAsm(asm, _) => asm.exprs().for_each(|e| collect_expr_names(e, names)),
+        Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+            collect_block_names(block, names)
+        }
+        Expr::If(cond, then_block, else_block, _) => {
+            collect_expr_names(cond, names);
+            collect_block_names(then_block, names);
+            collect_else_names(else_block.as_ref(), names);
+        }
+        Expr::Match(scrutinee, arms, _) => {
+            collect_expr_names(scrutinee, names);
+            collect_arm_names(arms, names);
+        }
+        Expr::While(_, cond, body, _) | Expr::For(_, _, cond, body, _) => {
+            collect_expr_names(cond, names);
+            collect_block_names(body, names);
+        }
+        Expr::Break(_, value, _) | Expr::Return(value, _) => {
+            if let Some(value) = value {
+                collect_expr_names(value, names);
+            }
+        }
+    }
+}
+
+fn collect_else_names(else_block: Option<&ElseBranch>, names: &mut BTreeSet<String>) {
+    match else_block {
+        Some(ElseBranch::Block(block)) => collect_block_names(block, names),
+        Some(ElseBranch::If(nested)) => {
+            collect_expr_names(&nested.cond, names);
+            collect_block_names(&nested.then_block, names);
+            collect_else_names(nested.else_block.as_ref(), names);
+        }
+        None => {}
+    }
+}
+
+fn collect_block_names(block: &Block, names: &mut BTreeSet<String>) {
+    for stmt in &block.statements {
+        match stmt {
+            Statement::Let(let_stmt) => {
+                if let Some(init) = &let_stmt.init {
+                    collect_expr_names(init, names);
+                }
+                if let Some(block) = &let_stmt.else_block {
+                    collect_block_names(block, names);
+                }
+            }
+            Statement::Expr(expr_stmt) => collect_expr_names(&expr_stmt.expr, names),
+            Statement::Return(ret) => {
+                if let Some(expr) = &ret.expr {
+                    collect_expr_names(expr, names);
+                }
+            }
+            Statement::If(if_stmt) => {
+                collect_expr_names(&if_stmt.cond, names);
+                collect_block_names(&if_stmt.then_block, names);
+                collect_else_names(if_stmt.else_block.as_ref(), names);
+            }
+            Statement::While(while_stmt) => {
+                collect_expr_names(&while_stmt.cond, names);
+                for contract in &while_stmt.invariants {
+                    collect_expr_names(&contract.condition, names);
+                }
+                collect_block_names(&while_stmt.body, names);
+            }
+            Statement::For(for_stmt) => {
+                collect_expr_names(&for_stmt.iterable, names);
+                for contract in &for_stmt.invariants {
+                    collect_expr_names(&contract.condition, names);
+                }
+                collect_block_names(&for_stmt.body, names);
+            }
+            Statement::Match(match_stmt) => {
+                collect_expr_names(&match_stmt.expr, names);
+                collect_arm_names(&match_stmt.arms, names);
+            }
+            Statement::Break(break_stmt) => {
+                if let Some(expr) = &break_stmt.expr {
+                    collect_expr_names(expr, names);
+                }
+            }
+            Statement::Continue(_) => {}
+            Statement::Block(block) => collect_block_names(block, names),
+        }
+    }
+}
+
+fn collect_arm_names(arms: &[MatchArm], names: &mut BTreeSet<String>) {
+    for arm in arms {
+        if let Some(guard) = &arm.guard {
+            collect_expr_names(guard, names);
+        }
+        collect_expr_names(&arm.body, names);
+    }
+}
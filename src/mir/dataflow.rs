@@ -0,0 +1,340 @@
+// MIR 数据流分析框架
+// 1. 分析实现 `Analysis`：给出格（Domain）、方向、边界条件和语句/终结符的传递函数
+// 2. `iterate_to_fixpoint` 用工作表算法迭代到不动点，结果按基本块保存
+// 3. `Results` 可以查询任意位置之前/之后的状态（按程序顺序）
+// gen/kill 形式的分析直接使用 `BitSet` 作为格，合并运算为并集
+// 内置分析：活跃变量（liveness）、到达定值（reaching definitions）、
+// 可能已初始化/可能未初始化的变量（析构展开使用），见 impls.rs
+
+mod impls;
+
+pub use impls::{
+    statement_init_effects, terminator_init_effects, Definition, Liveness, MaybeInitializedLocals,
+    MaybeUninitializedLocals, ReachingDefinitions,
+};
+
+use super::{BasicBlock, Body, Location, Statement, Terminator};
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// 数据流分析
+///
+/// 前向分析中基本块的入口状态是块开始处的状态；
+/// 后向分析中是块末尾（终结符之后）的状态。
+pub trait Analysis {
+    type Domain: Clone + PartialEq;
+
+    const DIRECTION: Direction;
+
+    /// 格的最小元，所有基本块的初始状态
+    fn bottom(&self, body: &Body) -> Self::Domain;
+
+    /// 边界条件：前向分析作用于入口块，后向分析作用于没有后继的块（return / unreachable）
+    fn initialize_boundary(&self, _body: &Body, _state: &mut Self::Domain) {}
+
+    /// 把 `other` 合并进 `state`，返回 state 是否改变
+    fn join(&self, state: &mut Self::Domain, other: &Self::Domain) -> bool;
+
+    fn apply_statement(&self, state: &mut Self::Domain, statement: &Statement, location: Location);
+
+    fn apply_terminator(
+        &self,
+        state: &mut Self::Domain,
+        terminator: &Terminator,
+        location: Location,
+    );
+}
+
+/// 不动点迭代的结果
+pub struct Results<'body, A: Analysis> {
+    pub analysis: A,
+    body: &'body Body,
+    entry_sets: Vec<A::Domain>,
+}
+
+pub fn iterate_to_fixpoint<A: Analysis>(analysis: A, body: &Body) -> Results<'_, A> {
+    let block_count = body.blocks.len();
+    let mut entry_sets = vec![analysis.bottom(body); block_count];
+    let predecessors = body.predecessors();
+
+    match A::DIRECTION {
+        Direction::Forward => {
+            if block_count > 0 {
+                analysis.initialize_boundary(body, &mut entry_sets[BasicBlock::START.index()]);
+            }
+        }
+        Direction::Backward => {
+            for (block, data) in body.basic_blocks() {
+                if data.terminator.successors().is_empty() {
+                    analysis.initialize_boundary(body, &mut entry_sets[block.index()]);
+                }
+            }
+        }
+    }
+
+    // 前向分析按逆后序处理，后向分析按后序处理；不可达的块排在最后
+    let mut order = body.reverse_postorder();
+    let mut in_order = vec![false; block_count];
+    for block in &order {
+        in_order[block.index()] = true;
+    }
+    order.extend(
+        (0..block_count)
+            .map(BasicBlock)
+            .filter(|b| !in_order[b.index()]),
+    );
+    if A::DIRECTION == Direction::Backward {
+        order.reverse();
+    }
+
+    let mut queued = vec![true; block_count];
+    let mut worklist: VecDeque<BasicBlock> = order.into();
+
+    while let Some(block) = worklist.pop_front() {
+        queued[block.index()] = false;
+        let mut state = entry_sets[block.index()].clone();
+        apply_block(&analysis, body, block, &mut state);
+
+        let targets = match A::DIRECTION {
+            Direction::Forward => body.block(block).terminator.successors(),
+            Direction::Backward => predecessors[block.index()].clone(),
+        };
+        for target in targets {
+            if analysis.join(&mut entry_sets[target.index()], &state) && !queued[target.index()] {
+                queued[target.index()] = true;
+                worklist.push_back(target);
+            }
+        }
+    }
+
+    Results {
+        analysis,
+        body,
+        entry_sets,
+    }
+}
+
+// 把整个基本块的传递函数作用到状态上（方向由分析决定）
+fn apply_block<A: Analysis>(analysis: &A, body: &Body, block: BasicBlock, state: &mut A::Domain) {
+    let data = body.block(block);
+    let terminator_loc = body.terminator_loc(block);
+    match A::DIRECTION {
+        Direction::Forward => {
+            for (i, statement) in data.statements.iter().enumerate() {
+                analysis.apply_statement(state, statement, location(block, i));
+            }
+            analysis.apply_terminator(state, &data.terminator, terminator_loc);
+        }
+        Direction::Backward => {
+            analysis.apply_terminator(state, &data.terminator, terminator_loc);
+            for (i, statement) in data.statements.iter().enumerate().rev() {
+                analysis.apply_statement(state, statement, location(block, i));
+            }
+        }
+    }
+}
+
+fn location(block: BasicBlock, statement_index: usize) -> Location {
+    Location {
+        block,
+        statement_index,
+    }
+}
+
+impl<'body, A: Analysis> Results<'body, A> {
+    /// 基本块的入口状态（含义见 `Analysis`）
+    pub fn entry_set(&self, block: BasicBlock) -> &A::Domain {
+        &self.entry_sets[block.index()]
+    }
+
+    /// 按程序顺序，执行 `location` 处的语句或终结符之前的状态
+    pub fn state_before(&self, location: Location) -> A::Domain {
+        self.state_at(location, false)
+    }
+
+    /// 按程序顺序，执行 `location` 处的语句或终结符之后的状态
+    pub fn state_after(&self, location: Location) -> A::Domain {
+        self.state_at(location, true)
+    }
+
+    fn state_at(&self, target: Location, after: bool) -> A::Domain {
+        let block = target.block;
+        let data = self.body.block(block);
+        let count = data.statements.len();
+        let mut state = self.entry_sets[block.index()].clone();
+
+        // 需要执行的位置（按分析方向的顺序）
+        let indices: Vec<usize> = match A::DIRECTION {
+            Direction::Forward => {
+                let end = if after {
+                    target.statement_index + 1
+                } else {
+                    target.statement_index
+                };
+                (0..end).collect()
+            }
+            Direction::Backward => {
+                let start = if after {
+                    target.statement_index + 1
+                } else {
+                    target.statement_index
+                };
+                (start..=count).rev().collect()
+            }
+        };
+
+        for i in indices {
+            let loc = location(block, i);
+            if i == count {
+                self.analysis
+                    .apply_terminator(&mut state, &data.terminator, loc);
+            } else {
+                self.analysis
+                    .apply_statement(&mut state, &data.statements[i], loc);
+            }
+        }
+        state
+    }
+}
+
+/// 定长位集合，gen/kill 分析的格
+#[derive(Clone, PartialEq, Eq)]
+pub struct BitSet {
+    size: usize,
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new_empty(size: usize) -> Self {
+        Self {
+            size,
+            words: vec![0; size.div_ceil(64)],
+        }
+    }
+
+    pub fn new_filled(size: usize) -> Self {
+        let mut set = Self::new_empty(size);
+        for i in 0..size {
+            set.insert(i);
+        }
+        set
+    }
+
+    pub fn domain_size(&self) -> usize {
+        self.size
+    }
+
+    /// 插入元素，返回集合是否改变
+    pub fn insert(&mut self, elem: usize) -> bool {
+        assert!(elem < self.size, "bit {} out of range {}", elem, self.size);
+        let (word, mask) = (elem / 64, 1u64 << (elem % 64));
+        let changed = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        changed
+    }
+
+    /// 删除元素，返回集合是否改变
+    pub fn remove(&mut self, elem: usize) -> bool {
+        assert!(elem < self.size, "bit {} out of range {}", elem, self.size);
+        let (word, mask) = (elem / 64, 1u64 << (elem % 64));
+        let changed = self.words[word] & mask != 0;
+        self.words[word] &= !mask;
+        changed
+    }
+
+    pub fn contains(&self, elem: usize) -> bool {
+        elem < self.size && self.words[elem / 64] & (1u64 << (elem % 64)) != 0
+    }
+
+    /// 并入另一个集合，返回集合是否改变
+    pub fn union(&mut self, other: &BitSet) -> bool {
+        let mut changed = false;
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            let new = *word | other;
+            changed |= new != *word;
+            *word = new;
+        }
+        changed
+    }
+
+    /// 与另一个集合求交，返回集合是否改变
+    pub fn intersect(&mut self, other: &BitSet) -> bool {
+        let mut changed = false;
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            let new = *word & other;
+            changed |= new != *word;
+            *word = new;
+        }
+        changed
+    }
+
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.size).filter(move |i| self.contains(*i))
+    }
+}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitset_insert_remove() {
+        let mut set = BitSet::new_empty(130);
+        assert!(set.is_empty());
+        assert!(set.insert(0));
+        assert!(set.insert(129));
+        assert!(!set.insert(129));
+        assert!(set.contains(129));
+        assert!(!set.contains(64));
+        assert_eq!(set.count(), 2);
+        assert!(set.remove(0));
+        assert!(!set.remove(0));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![129]);
+    }
+
+    #[test]
+    fn test_bitset_union_intersect() {
+        let mut a = BitSet::new_empty(70);
+        let mut b = BitSet::new_empty(70);
+        a.insert(1);
+        a.insert(65);
+        b.insert(65);
+        b.insert(3);
+
+        let mut union = a.clone();
+        assert!(union.union(&b));
+        assert!(!union.union(&b));
+        assert_eq!(union.iter().collect::<Vec<_>>(), vec![1, 3, 65]);
+
+        assert!(a.intersect(&b));
+        assert_eq!(a.iter().collect::<Vec<_>>(), vec![65]);
+        assert_eq!(BitSet::new_filled(70).count(), 70);
+    }
+}
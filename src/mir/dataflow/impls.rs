@@ -0,0 +1,351 @@
+// 内置的数据流分析
+
+use super::{Analysis, BitSet, Direction};
+use crate::mir::{Body, Local, Location, Place, Statement, StatementKind, Terminator};
+use crate::mir::{Operand, Rvalue, TerminatorKind};
+
+/// 活跃变量分析（后向）：某一点之后还可能被读取的局部变量
+///
+/// 整体赋值会杀死变量，只写入一部分（字段、下标）不会；
+/// `return` 读取返回值 `_0`。
+pub struct Liveness;
+
+impl Analysis for Liveness {
+    type Domain = BitSet;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn bottom(&self, body: &Body) -> BitSet {
+        BitSet::new_empty(body.locals.len())
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn apply_statement(&self, state: &mut BitSet, statement: &Statement, _location: Location) {
+        match &statement.kind {
+            StatementKind::Assign(place, rvalue) => {
+                kill_defined(state, place);
+                gen_rvalue_uses(state, rvalue);
+            }
+            StatementKind::InlineAsm(asm) => {
+                for place in asm.outputs() {
+                    kill_defined(state, place);
+                }
+                for place in asm.inputs().filter_map(Operand::place) {
+                    gen_place_uses(state, place);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_terminator(&self, state: &mut BitSet, terminator: &Terminator, _location: Location) {
+        if let TerminatorKind::Call { destination, .. } = &terminator.kind {
+            kill_defined(state, destination);
+        }
+        if let TerminatorKind::Return = terminator.kind {
+            state.insert(Local::RETURN.index());
+        }
+        for place in terminator.kind.read_places() {
+            gen_place_uses(state, place);
+        }
+    }
+}
+
+// 写入 place：整体赋值杀死变量，部分写入读取基变量和下标
+fn kill_defined(state: &mut BitSet, place: &Place) {
+    match place.as_local() {
+        Some(local) => {
+            state.remove(local.index());
+        }
+        None => gen_place_uses(state, place),
+    }
+}
+
+fn gen_rvalue_uses(state: &mut BitSet, rvalue: &Rvalue) {
+    for place in rvalue.read_places() {
+        gen_place_uses(state, place);
+    }
+}
+
+fn gen_place_uses(state: &mut BitSet, place: &Place) {
+    state.insert(place.local.index());
+    for index in place.index_locals() {
+        state.insert(index.index());
+    }
+}
+
+/// 一个定值点：参数在函数入口定值（`location` 为 None）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Definition {
+    pub local: Local,
+    pub location: Option<Location>,
+}
+
+/// 到达定值分析（前向）：某一点上可能仍然有效的赋值
+///
+/// 整体赋值杀死同一变量的其他定值，部分赋值只增加定值。
+pub struct ReachingDefinitions {
+    definitions: Vec<Definition>,
+}
+
+impl ReachingDefinitions {
+    pub fn new(body: &Body) -> Self {
+        let mut definitions: Vec<Definition> = body
+            .args()
+            .map(|local| Definition {
+                local,
+                location: None,
+            })
+            .collect();
+
+        for (block, data) in body.basic_blocks() {
+            for (i, statement) in data.statements.iter().enumerate() {
+                let location = Some(Location {
+                    block,
+                    statement_index: i,
+                });
+                match &statement.kind {
+                    StatementKind::Assign(place, _) => definitions.push(Definition {
+                        local: place.local,
+                        location,
+                    }),
+                    StatementKind::InlineAsm(asm) => {
+                        definitions.extend(asm.outputs().map(|place| Definition {
+                            local: place.local,
+                            location,
+                        }))
+                    }
+                    _ => {}
+                }
+            }
+            if let TerminatorKind::Call { destination, .. } = &data.terminator.kind {
+                definitions.push(Definition {
+                    local: destination.local,
+                    location: Some(body.terminator_loc(block)),
+                });
+            }
+        }
+
+        Self { definitions }
+    }
+
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    pub fn definition(&self, index: usize) -> Definition {
+        self.definitions[index]
+    }
+
+    /// 状态中指定变量的所有定值
+    pub fn definitions_of<'a>(
+        &'a self,
+        state: &'a BitSet,
+        local: Local,
+    ) -> impl Iterator<Item = Definition> + 'a {
+        state
+            .iter()
+            .map(|i| self.definitions[i])
+            .filter(move |def| def.local == local)
+    }
+
+    fn define(&self, state: &mut BitSet, place: &Place, location: Location) {
+        if place.as_local().is_some() {
+            for (i, def) in self.definitions.iter().enumerate() {
+                if def.local == place.local {
+                    state.remove(i);
+                }
+            }
+        }
+        if let Some(i) = self
+            .definitions
+            .iter()
+            .position(|def| def.location == Some(location) && def.local == place.local)
+        {
+            state.insert(i);
+        }
+    }
+}
+
+impl Analysis for ReachingDefinitions {
+    type Domain = BitSet;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self, _body: &Body) -> BitSet {
+        BitSet::new_empty(self.definitions.len())
+    }
+
+    fn initialize_boundary(&self, _body: &Body, state: &mut BitSet) {
+        for (i, def) in self.definitions.iter().enumerate() {
+            if def.location.is_none() {
+                state.insert(i);
+            }
+        }
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn apply_statement(&self, state: &mut BitSet, statement: &Statement, location: Location) {
+        match &statement.kind {
+            StatementKind::Assign(place, _) => self.define(state, place, location),
+            StatementKind::InlineAsm(asm) => {
+                for place in asm.outputs() {
+                    self.define(state, place, location);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_terminator(&self, state: &mut BitSet, terminator: &Terminator, location: Location) {
+        if let TerminatorKind::Call { destination, .. } = &terminator.kind {
+            self.define(state, destination, location);
+        }
+    }
+}
+
+/// 语句对局部变量初始化状态的影响：`true` 表示被初始化，`false` 表示被移出或析构
+///
+/// 先报告操作数的移出，再报告目标的写入。经过解引用或下标的移出和写入无法按变量跟踪，
+/// 不产生影响；移出一部分（字段）视为整个变量被移出，只写入一部分视为整个变量被初始化。
+pub fn statement_init_effects(statement: &Statement, mut effect: impl FnMut(Local, bool)) {
+    match &statement.kind {
+        StatementKind::Assign(place, rvalue) => {
+            for operand in rvalue.operands() {
+                move_effect(operand, &mut effect);
+            }
+            if place.is_owned_by_local() {
+                effect(place.local, true);
+            }
+        }
+        StatementKind::InlineAsm(asm) => {
+            for operand in asm.inputs() {
+                move_effect(operand, &mut effect);
+            }
+            for place in asm.outputs().filter(|place| place.is_owned_by_local()) {
+                effect(place.local, true);
+            }
+        }
+        StatementKind::StorageLive(local) | StatementKind::StorageDead(local) => {
+            effect(*local, false)
+        }
+        StatementKind::Nop => {}
+    }
+}
+
+/// 终结符对局部变量初始化状态的影响，含义同 `statement_init_effects`
+pub fn terminator_init_effects(terminator: &Terminator, mut effect: impl FnMut(Local, bool)) {
+    match &terminator.kind {
+        TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            ..
+        } => {
+            for operand in std::iter::once(func).chain(args) {
+                move_effect(operand, &mut effect);
+            }
+            if destination.is_owned_by_local() {
+                effect(destination.local, true);
+            }
+        }
+        TerminatorKind::Drop { place, .. } => {
+            if let Some(local) = place.as_local() {
+                effect(local, false);
+            }
+        }
+        TerminatorKind::Goto { .. }
+        | TerminatorKind::SwitchInt { .. }
+        | TerminatorKind::Assert { .. }
+        | TerminatorKind::Return
+        | TerminatorKind::Resume
+        | TerminatorKind::Unreachable => {}
+    }
+}
+
+fn move_effect(operand: &Operand, effect: &mut impl FnMut(Local, bool)) {
+    if let Operand::Move(place) = operand {
+        if place.is_owned_by_local() {
+            effect(place.local, false);
+        }
+    }
+}
+
+/// 可能已初始化的局部变量（前向）：存在一条路径使变量在此处持有值
+pub struct MaybeInitializedLocals;
+
+impl Analysis for MaybeInitializedLocals {
+    type Domain = BitSet;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self, body: &Body) -> BitSet {
+        BitSet::new_empty(body.locals.len())
+    }
+
+    fn initialize_boundary(&self, body: &Body, state: &mut BitSet) {
+        for arg in body.args() {
+            state.insert(arg.index());
+        }
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn apply_statement(&self, state: &mut BitSet, statement: &Statement, _location: Location) {
+        statement_init_effects(statement, |local, init| set_bit(state, local, init));
+    }
+
+    fn apply_terminator(&self, state: &mut BitSet, terminator: &Terminator, _location: Location) {
+        terminator_init_effects(terminator, |local, init| set_bit(state, local, init));
+    }
+}
+
+/// 可能未初始化的局部变量（前向）：存在一条路径使变量在此处没有值
+///
+/// 与 `MaybeInitializedLocals` 一起判断析构：两者都包含的变量需要运行时的析构标志。
+pub struct MaybeUninitializedLocals;
+
+impl Analysis for MaybeUninitializedLocals {
+    type Domain = BitSet;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self, body: &Body) -> BitSet {
+        BitSet::new_empty(body.locals.len())
+    }
+
+    fn initialize_boundary(&self, body: &Body, state: &mut BitSet) {
+        for local in body.arg_count + 1..body.locals.len() {
+            state.insert(local);
+        }
+        state.insert(Local::RETURN.index());
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn apply_statement(&self, state: &mut BitSet, statement: &Statement, _location: Location) {
+        statement_init_effects(statement, |local, init| set_bit(state, local, !init));
+    }
+
+    fn apply_terminator(&self, state: &mut BitSet, terminator: &Terminator, _location: Location) {
+        terminator_init_effects(terminator, |local, init| set_bit(state, local, !init));
+    }
+}
+
+fn set_bit(state: &mut BitSet, local: Local, value: bool) {
+    if value {
+        state.insert(local.index());
+    } else {
+        state.remove(local.index());
+    }
+}
@@ -0,0 +1,591 @@
+// 单态化：把泛型函数和泛型类型展开为具体实例
+// 1. 从所有非泛型函数体（包括 main）和静态变量初始化出发，收集对泛型函数的调用，
+//    类型实参由实参和返回值目标的类型推断
+// 2. 每组 (函数, 类型实参) 复制一份函数体并替换类型参数，命名为 `name::<A, B>`；
+//    新函数体中的调用继续收集，直到没有新的实例。闭包随外层函数一起实例化
+// 3. 用到的泛型结构体和枚举按类型实参生成具体定义，命名为 `Name<A, B>`
+// 4. 实例化链超过 RECURSION_LIMIT 层时报错，例如 `f::<T>` 调用 `f::<(T, i32)>`；
+//    类型实参每层成倍增长时（`f::<(T, T)>`）先触及 TYPE_LENGTH_LIMIT
+// 单态化之后的程序不再包含类型参数，后端只需要处理具体类型
+
+use super::{
+    AggregateKind, Body, ConstValue, MirProgram, Operand, Rvalue, StatementKind, TerminatorKind,
+};
+use crate::ast::{EnumDef, StructDef, Type};
+use crate::builtins;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::span::Span;
+use std::collections::{BTreeMap, VecDeque};
+
+/// 实例化链的最大深度
+pub const RECURSION_LIMIT: usize = 64;
+
+/// 单个实例的类型实参最多包含的类型节点数
+pub const TYPE_LENGTH_LIMIT: usize = 4096;
+
+/// 单态化整个程序，返回只包含具体类型的新程序
+pub fn monomorphize(program: &MirProgram) -> Result<MirProgram, Vec<Diagnostic>> {
+    let mut collector = Collector::new(program);
+    collector.collect_functions();
+    if !collector.errors.is_empty() {
+        return Err(collector.errors);
+    }
+
+    let mut output = MirProgram {
+        structs: Vec::new(),
+        enums: Vec::new(),
+        bodies: collector.bodies,
+        statics: collector.statics,
+        benches: program.benches.clone(),
+        intrinsics: program.intrinsics.clone(),
+    };
+    let mut types = TypeInstances::new(program);
+    types.rewrite(&mut output);
+    if !types.errors.is_empty() {
+        return Err(types.errors);
+    }
+    output.structs = types.structs;
+    output.enums = types.enums;
+    Ok(output)
+}
+
+// ---------------- 函数实例 ----------------
+
+struct Collector<'p> {
+    program: &'p MirProgram,
+    generic_bodies: BTreeMap<&'p str, &'p Body>,
+    instances: BTreeMap<String, usize>, // 已生成的实例名 -> 实例化深度
+    bodies: Vec<Body>,
+    statics: Vec<Body>,
+    queue: VecDeque<(usize, usize)>, // (bodies 中的下标, 实例化深度)
+    errors: Vec<Diagnostic>,
+}
+
+impl<'p> Collector<'p> {
+    fn new(program: &'p MirProgram) -> Self {
+        let generic_bodies = program
+            .bodies
+            .iter()
+            .filter(|body| body.is_generic())
+            .map(|body| (body.name.as_str(), body))
+            .collect();
+        Self {
+            program,
+            generic_bodies,
+            instances: BTreeMap::new(),
+            bodies: Vec::new(),
+            statics: Vec::new(),
+            queue: VecDeque::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn collect_functions(&mut self) {
+        for body in &self.program.bodies {
+            if !body.is_generic() {
+                self.queue.push_back((self.bodies.len(), 0));
+                self.bodies.push(body.clone());
+            }
+        }
+        let mut statics = self.program.statics.clone();
+        for body in &mut statics {
+            self.resolve_calls(body, 0);
+        }
+        self.statics = statics;
+
+        while let Some((index, depth)) = self.queue.pop_front() {
+            let mut body = self.bodies[index].clone();
+            self.resolve_calls(&mut body, depth);
+            self.bodies[index] = body;
+        }
+    }
+
+    // 把函数体中对泛型函数的调用改为调用具体实例
+    fn resolve_calls(&mut self, body: &mut Body, depth: usize) {
+        for index in 0..body.blocks.len() {
+            let terminator = &body.blocks[index].terminator;
+            let span = terminator.span;
+            let TerminatorKind::Call {
+                func: Operand::Constant(constant),
+                args,
+                destination,
+                ..
+            } = &terminator.kind
+            else {
+                continue;
+            };
+            let ConstValue::Function(name) = &constant.value else {
+                continue;
+            };
+            let Some(generic) = self.generic_bodies.get(name.as_str()).copied() else {
+                continue;
+            };
+
+            // 形参类型与实参类型、返回类型与目标类型逐一匹配
+            let mut type_args = BTreeMap::new();
+            for (param, arg) in generic.args().zip(args) {
+                let actual = self.operand_ty(body, arg);
+                generic.local_decl(param).ty.infer_params(
+                    &actual,
+                    &generic.type_params,
+                    &mut type_args,
+                );
+            }
+            let actual = self.program.place_ty(body, destination);
+            generic
+                .return_ty()
+                .infer_params(&actual, &generic.type_params, &mut type_args);
+
+            let Some(args) = self.type_args(generic, &type_args, span) else {
+                continue;
+            };
+            let instance = instance_name(name, &args);
+            let substituted = constant.ty.substitute(&type_args);
+            if self.request(generic, &type_args, &instance, depth + 1, span) {
+                if let TerminatorKind::Call {
+                    func: Operand::Constant(constant),
+                    ..
+                } = &mut body.blocks[index].terminator.kind
+                {
+                    constant.value = ConstValue::Function(instance);
+                    constant.ty = substituted;
+                }
+            }
+        }
+    }
+
+    // 按类型参数的声明顺序排列推断出的类型实参，缺少的报错
+    fn type_args(
+        &mut self,
+        generic: &Body,
+        inferred: &BTreeMap<String, Type>,
+        span: Span,
+    ) -> Option<Vec<Type>> {
+        let mut args = Vec::new();
+        for param in &generic.type_params {
+            match inferred.get(param) {
+                Some(ty) if is_concrete(self.program, ty) => args.push(ty.clone()),
+                _ => {
+                    self.errors.push(
+                        Diagnostic::error(
+                            format!(
+                                "cannot infer type parameter `{}` for call to `{}`",
+                                param, generic.name
+                            ),
+                            span,
+                        )
+                        .with_code(ErrorCode::E0282),
+                    );
+                    return None;
+                }
+            }
+        }
+        Some(args)
+    }
+
+    // 确保实例存在，新实例加入工作队列；超过深度上限时报错并返回 false
+    fn request(
+        &mut self,
+        generic: &Body,
+        type_args: &BTreeMap<String, Type>,
+        instance: &str,
+        depth: usize,
+        span: Span,
+    ) -> bool {
+        if self.instances.contains_key(instance) {
+            return true;
+        }
+        let length: usize = type_args.values().map(type_length).sum();
+        if length > TYPE_LENGTH_LIMIT {
+            self.errors.push(
+                Diagnostic::error(
+                    format!(
+                        "reached the type-length limit while instantiating `{}`",
+                        generic.name
+                    ),
+                    span,
+                )
+                .with_code(ErrorCode::E0275)
+                .with_note(format!(
+                    "the type arguments contain {} types; the limit is {}",
+                    length, TYPE_LENGTH_LIMIT
+                )),
+            );
+            return false;
+        }
+        if depth > RECURSION_LIMIT {
+            self.errors.push(
+                Diagnostic::error(
+                    format!(
+                        "reached the recursion limit while instantiating `{}`",
+                        instance
+                    ),
+                    span,
+                )
+                .with_code(ErrorCode::E0275)
+                .with_note(format!(
+                    "`{}` instantiates itself with ever larger type arguments",
+                    generic.name
+                )),
+            );
+            return false;
+        }
+        self.instances.insert(instance.to_string(), depth);
+
+        let mut body = generic.clone();
+        body.name = instance.to_string();
+        body.type_args = generic
+            .type_params
+            .iter()
+            .filter_map(|param| type_args.get(param).cloned())
+            .collect();
+        body.type_params.clear();
+        substitute_body(&mut body, type_args);
+
+        // 外层函数中的闭包使用相同的类型实参
+        let prefix = format!("{}::{{closure#", generic.name);
+        for data in &mut body.blocks {
+            for statement in &mut data.statements {
+                let StatementKind::Assign(_, Rvalue::Aggregate(AggregateKind::Closure(name), _)) =
+                    &mut statement.kind
+                else {
+                    continue;
+                };
+                if !name.starts_with(&prefix) {
+                    continue;
+                }
+                let closure_instance = format!("{}{}", instance, &name[generic.name.len()..]);
+                if let Some(closure) = self.generic_bodies.get(name.as_str()).copied() {
+                    self.request(closure, type_args, &closure_instance, depth, statement.span);
+                }
+                *name = closure_instance;
+            }
+        }
+
+        self.queue.push_back((self.bodies.len(), depth));
+        self.bodies.push(body);
+        true
+    }
+
+    fn operand_ty(&self, body: &Body, operand: &Operand) -> Type {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.program.place_ty(body, place),
+            Operand::Constant(constant) => constant.ty.clone(),
+        }
+    }
+}
+
+fn instance_name(name: &str, args: &[Type]) -> String {
+    let args: Vec<String> = args.iter().map(Type::to_string).collect();
+    format!("{}::<{}>", name, args.join(", "))
+}
+
+fn type_length(ty: &Type) -> usize {
+    let mut length = 0;
+    visit_type(ty, &mut |_| length += 1);
+    length
+}
+
+// 类型中不含未知部分和类型参数
+fn is_concrete(program: &MirProgram, ty: &Type) -> bool {
+    let mut concrete = true;
+    visit_type(ty, &mut |ty| match ty {
+        Type::Infer => concrete = false,
+        Type::Named(name) | Type::Generic(name, _) => {
+            concrete &= program.struct_def(name).is_some()
+                || program.enum_def(name).is_some()
+                || builtins::is_type(name)
+        }
+        _ => {}
+    });
+    concrete
+}
+
+// 把类型中推断不出的部分 `_` 换成 `()`
+fn infer_as_unit(ty: &Type) -> Type {
+    match ty {
+        Type::Infer => Type::Unit,
+        Type::Array(inner, len) => Type::Array(Box::new(infer_as_unit(inner)), *len),
+        Type::Slice(inner) => Type::Slice(Box::new(infer_as_unit(inner))),
+        Type::Pointer(inner, mutable) => Type::Pointer(Box::new(infer_as_unit(inner)), *mutable),
+        Type::Reference(inner, mutable) => {
+            Type::Reference(Box::new(infer_as_unit(inner)), *mutable)
+        }
+        Type::Tuple(types) => Type::Tuple(types.iter().map(infer_as_unit).collect()),
+        Type::Generic(name, args) => {
+            Type::Generic(name.clone(), args.iter().map(infer_as_unit).collect())
+        }
+        Type::Function(params, ret, pure) => Type::Function(
+            params.iter().map(infer_as_unit).collect(),
+            Box::new(infer_as_unit(ret)),
+            *pure,
+        ),
+        ty => ty.clone(),
+    }
+}
+
+fn visit_type(ty: &Type, f: &mut impl FnMut(&Type)) {
+    f(ty);
+    match ty {
+        Type::Array(inner, _)
+        | Type::Slice(inner)
+        | Type::Pointer(inner, _)
+        | Type::Reference(inner, _) => visit_type(inner, f),
+        Type::Tuple(types) | Type::Generic(_, types) => {
+            types.iter().for_each(|ty| visit_type(ty, f))
+        }
+        Type::Function(params, ret, _) => {
+            params.iter().for_each(|ty| visit_type(ty, f));
+            visit_type(ret, f);
+        }
+        _ => {}
+    }
+}
+
+fn substitute_body(body: &mut Body, type_args: &BTreeMap<String, Type>) {
+    map_body_types(body, &mut |ty| ty.substitute(type_args));
+}
+
+// 对函数体中出现的每个类型（局部变量、常量、数组元素、转换目标）应用 `f`
+fn map_body_types(body: &mut Body, f: &mut impl FnMut(&Type) -> Type) {
+    for decl in &mut body.locals {
+        decl.ty = f(&decl.ty);
+    }
+    let map_operand = |operand: &mut Operand, f: &mut dyn FnMut(&Type) -> Type| {
+        if let Operand::Constant(constant) = operand {
+            constant.ty = f(&constant.ty);
+        }
+    };
+    for data in &mut body.blocks {
+        for statement in &mut data.statements {
+            let StatementKind::Assign(_, rvalue) = &mut statement.kind else {
+                continue;
+            };
+            match rvalue {
+                Rvalue::Use(operand) | Rvalue::UnaryOp(_, operand, _) => map_operand(operand, f),
+                Rvalue::Cast(operand, ty) => {
+                    map_operand(operand, f);
+                    *ty = f(ty);
+                }
+                Rvalue::BinaryOp(_, lhs, rhs, _) => {
+                    map_operand(lhs, f);
+                    map_operand(rhs, f);
+                }
+                Rvalue::Aggregate(kind, operands) => {
+                    if let AggregateKind::Array(elem) = kind {
+                        *elem = f(elem);
+                    }
+                    for operand in operands {
+                        map_operand(operand, f);
+                    }
+                }
+                Rvalue::Ref(_, _) | Rvalue::Len(_) | Rvalue::Discriminant(_) => {}
+            }
+        }
+        match &mut data.terminator.kind {
+            TerminatorKind::SwitchInt { discr, .. } => map_operand(discr, f),
+            TerminatorKind::Call { func, args, .. } => {
+                map_operand(func, f);
+                for arg in args {
+                    map_operand(arg, f);
+                }
+            }
+            TerminatorKind::Assert { cond, msg, .. } => {
+                map_operand(cond, f);
+                for operand in msg.operands_mut() {
+                    map_operand(operand, f);
+                }
+            }
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Drop { .. }
+            | TerminatorKind::Resume
+            | TerminatorKind::Unreachable => {}
+        }
+    }
+}
+
+// ---------------- 类型实例 ----------------
+
+struct TypeInstances<'p> {
+    program: &'p MirProgram,
+    structs: Vec<StructDef>,
+    enums: Vec<EnumDef>,
+    instances: BTreeMap<String, usize>, // 已生成的类型实例名 -> 展开深度
+    errors: Vec<Diagnostic>,
+}
+
+impl<'p> TypeInstances<'p> {
+    fn new(program: &'p MirProgram) -> Self {
+        let structs = program
+            .structs
+            .iter()
+            .filter(|def| def.generics.is_none())
+            .cloned()
+            .collect();
+        let enums = program
+            .enums
+            .iter()
+            .filter(|def| def.generics.is_none())
+            .cloned()
+            .collect();
+        Self {
+            program,
+            structs,
+            enums,
+            instances: BTreeMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn is_generic_adt(&self, name: &str) -> bool {
+        let generic_struct = self
+            .program
+            .struct_def(name)
+            .is_some_and(|def| def.generics.is_some());
+        let generic_enum = self
+            .program
+            .enum_def(name)
+            .is_some_and(|def| def.generics.is_some());
+        generic_struct || generic_enum
+    }
+
+    fn rewrite(&mut self, output: &mut MirProgram) {
+        for body in output.bodies.iter_mut().chain(output.statics.iter_mut()) {
+            // 泛型类型的聚合值改用目标 place 的具体类型
+            for index in 0..body.blocks.len() {
+                for i in 0..body.blocks[index].statements.len() {
+                    let StatementKind::Assign(place, Rvalue::Aggregate(kind, _)) =
+                        &body.blocks[index].statements[i].kind
+                    else {
+                        continue;
+                    };
+                    let name = match kind {
+                        AggregateKind::Struct(name, _) | AggregateKind::Variant(name, _) => name,
+                        _ => continue,
+                    };
+                    if !self.is_generic_adt(name) {
+                        continue;
+                    }
+                    let ty = self.program.place_ty(body, place);
+                    let span = body.blocks[index].statements[i].span;
+                    let Some(instance) = self.instantiate(&ty, 0, span) else {
+                        continue;
+                    };
+                    if let StatementKind::Assign(
+                        _,
+                        Rvalue::Aggregate(
+                            AggregateKind::Struct(name, _) | AggregateKind::Variant(name, _),
+                            _,
+                        ),
+                    ) = &mut body.blocks[index].statements[i].kind
+                    {
+                        *name = instance;
+                    }
+                }
+            }
+
+            let span = body.span;
+            map_body_types(body, &mut |ty| self.concrete(ty, 0, span));
+        }
+    }
+
+    // 把类型中的泛型类型实例改写为具体定义的名字；`depth` 是类型定义的展开深度
+    fn concrete(&mut self, ty: &Type, depth: usize, span: Span) -> Type {
+        match ty {
+            Type::Generic(name, args) => match self.instantiate(ty, depth, span) {
+                Some(instance) => Type::Named(instance),
+                // 内建的泛型类型（`Vec<T>`）保留，只替换类型实参
+                None => Type::Generic(
+                    name.clone(),
+                    args.iter()
+                        .map(|ty| self.concrete(ty, depth, span))
+                        .collect(),
+                ),
+            },
+            Type::Array(inner, len) => {
+                Type::Array(Box::new(self.concrete(inner, depth, span)), *len)
+            }
+            Type::Slice(inner) => Type::Slice(Box::new(self.concrete(inner, depth, span))),
+            Type::Pointer(inner, mutable) => {
+                Type::Pointer(Box::new(self.concrete(inner, depth, span)), *mutable)
+            }
+            Type::Reference(inner, mutable) => {
+                Type::Reference(Box::new(self.concrete(inner, depth, span)), *mutable)
+            }
+            Type::Tuple(types) => Type::Tuple(
+                types
+                    .iter()
+                    .map(|ty| self.concrete(ty, depth, span))
+                    .collect(),
+            ),
+            // 纯度只在语义分析中检查，`pure fn` 和 `fn` 的实例相同
+            Type::Function(params, ret, _) => Type::Function(
+                params
+                    .iter()
+                    .map(|ty| self.concrete(ty, depth, span))
+                    .collect(),
+                Box::new(self.concrete(ret, depth, span)),
+                false,
+            ),
+            _ => ty.clone(),
+        }
+    }
+
+    // 生成泛型结构体或枚举的具体定义，返回实例名；不是泛型类型实例时返回 None
+    fn instantiate(&mut self, ty: &Type, depth: usize, span: Span) -> Option<String> {
+        let Type::Generic(name, args) = ty else {
+            return None;
+        };
+        // 没有任何上下文确定的类型实参（`let t = (None, 1);` 的 `None`）按 `()` 实例化，
+        // 不带字段的变体的值与类型实参无关
+        if self.program.enum_def(name).is_some() && args.iter().any(Type::has_infer) {
+            let args = args.iter().map(infer_as_unit).collect();
+            return self.instantiate(&Type::Generic(name.clone(), args), depth, span);
+        }
+        if !self.is_generic_adt(name) || !args.iter().all(|ty| is_concrete(self.program, ty)) {
+            return None;
+        }
+        let instance = ty.to_string();
+        if self.instances.contains_key(&instance) {
+            return Some(instance);
+        }
+        if depth > RECURSION_LIMIT {
+            self.errors.push(
+                Diagnostic::error(
+                    format!(
+                        "reached the recursion limit while instantiating `{}`",
+                        instance
+                    ),
+                    span,
+                )
+                .with_code(ErrorCode::E0275),
+            );
+            return None;
+        }
+        self.instances.insert(instance.clone(), depth);
+
+        if let Some(def) = self.program.struct_def(name) {
+            let type_args = super::type_args(def.generics.as_ref(), ty);
+            let mut def = def.clone();
+            def.name = instance.clone();
+            def.generics = None;
+            for field in &mut def.fields {
+                field.ty = self.concrete(&field.ty.substitute(&type_args), depth + 1, span);
+            }
+            self.structs.push(def);
+        } else if let Some(def) = self.program.enum_def(name) {
+            let type_args = super::type_args(def.generics.as_ref(), ty);
+            let mut def = def.clone();
+            def.name = instance.clone();
+            def.generics = None;
+            for variant in &mut def.variants {
+                for field in variant.fields.iter_mut().flatten() {
+                    *field = self.concrete(&field.substitute(&type_args), depth + 1, span);
+                }
+            }
+            self.enums.push(def);
+        }
+        Some(instance)
+    }
+}
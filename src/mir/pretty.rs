@@ -0,0 +1,405 @@
+// MIR 文本输出，格式参照 rustc 的 `-Zunpretty=mir`：
+//
+//     fn max(_1: i32, _2: i32) -> i32 {
+//         debug a => _1;
+//         let mut _0: i32;
+//
+//         bb0: {
+//             _3 = Gt(_1, _2);
+//             switchInt(_3) -> [0: bb2, otherwise: bb1];
+//         }
+//     }
+//
+// 只用于调试和 `--emit=mir`，输出格式不保证稳定
+
+use super::*;
+use crate::interp::ops;
+use std::fmt;
+
+const INDENT: &str = "    ";
+
+impl fmt::Display for Local {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "_{}", self.0)
+    }
+}
+
+impl fmt::Display for BasicBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = self.local.to_string();
+        for elem in &self.projection {
+            text = match elem {
+                PlaceElem::Deref => format!("(*{})", text),
+                PlaceElem::Field(field) => format!("{}.{}", text, field),
+                PlaceElem::Index(index) => format!("{}[{}]", text, index),
+                PlaceElem::Subslice(from, to) => format!("{}[{}..{}]", text, from, to),
+                PlaceElem::Downcast(variant) => format!("({} as {})", text, variant),
+            };
+        }
+        write!(f, "{}", text)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Copy(place) => write!(f, "{}", place),
+            Operand::Move(place) => write!(f, "move {}", place),
+            Operand::Constant(constant) => write!(f, "{}", constant),
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            ConstValue::Int(value) => write!(f, "const {}", value),
+            ConstValue::Float(value) => write!(f, "const {:?}", value),
+            ConstValue::Bool(value) => write!(f, "const {}", value),
+            ConstValue::Char(value) => write!(f, "const '{}'", value.escape_debug()),
+            ConstValue::Str(value) => write!(f, "const \"{}\"", value.escape_debug()),
+            ConstValue::Unit => write!(f, "const ()"),
+            ConstValue::Function(name) => write!(f, "{}", name),
+            ConstValue::Static(name) => write!(f, "static {}", name),
+            ConstValue::StaticRef(name) => write!(f, "&static {}", name),
+        }
+    }
+}
+
+impl fmt::Display for Rvalue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rvalue::Use(operand) => write!(f, "{}", operand),
+            Rvalue::BinaryOp(op, lhs, rhs, _) => write!(f, "{}({}, {})", bin_op_name(op), lhs, rhs),
+            Rvalue::UnaryOp(op, operand, _) => write!(f, "{}({})", un_op_name(op), operand),
+            Rvalue::Ref(place, true) => write!(f, "&mut {}", place),
+            Rvalue::Ref(place, false) => write!(f, "&{}", place),
+            Rvalue::Aggregate(kind, operands) => write_aggregate(f, kind, operands),
+            Rvalue::Cast(operand, ty) => write!(f, "{} as {}", operand, ty),
+            Rvalue::Len(place) => write!(f, "Len({})", place),
+            Rvalue::Discriminant(place) => write!(f, "discriminant({})", place),
+        }
+    }
+}
+
+fn write_aggregate(
+    f: &mut fmt::Formatter<'_>,
+    kind: &AggregateKind,
+    operands: &[Operand],
+) -> fmt::Result {
+    let list = operand_list(operands);
+    match kind {
+        AggregateKind::Tuple if operands.len() == 1 => write!(f, "({},)", list),
+        AggregateKind::Tuple => write!(f, "({})", list),
+        AggregateKind::Array(_) => write!(f, "[{}]", list),
+        AggregateKind::Struct(name, fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .zip(operands)
+                .map(|(field, operand)| format!("{}: {}", field, operand))
+                .collect();
+            write!(f, "{} {{ {} }}", name, fields.join(", "))
+        }
+        AggregateKind::Variant(enum_name, variant) if operands.is_empty() => {
+            write!(f, "{}::{}", enum_name, variant)
+        }
+        AggregateKind::Variant(enum_name, variant) => {
+            write!(f, "{}::{}({})", enum_name, variant, list)
+        }
+        AggregateKind::Range(inclusive) => {
+            let op = if *inclusive { "..=" } else { ".." };
+            write!(f, "{}{}{}", operands[0], op, operands[1])
+        }
+        AggregateKind::Closure(name) => write!(f, "closure {}({})", name, list),
+    }
+}
+
+fn operand_list(operands: &[Operand]) -> String {
+    operands
+        .iter()
+        .map(Operand::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn bin_op_name(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "Add",
+        BinOp::Sub => "Sub",
+        BinOp::Mul => "Mul",
+        BinOp::Div => "Div",
+        BinOp::Mod => "Rem",
+        BinOp::Equal => "Eq",
+        BinOp::NotEqual => "Ne",
+        BinOp::Less => "Lt",
+        BinOp::Greater => "Gt",
+        BinOp::LessEqual => "Le",
+        BinOp::GreaterEqual => "Ge",
+        BinOp::LogicalAnd => "And",
+        BinOp::LogicalOr => "Or",
+        BinOp::BitwiseAnd => "BitAnd",
+        BinOp::BitwiseOr => "BitOr",
+        BinOp::BitwiseXor => "BitXor",
+        BinOp::LeftShift => "Shl",
+        BinOp::RightShift => "Shr",
+    }
+}
+
+fn un_op_name(op: &UnOp) -> &'static str {
+    match op {
+        UnOp::Neg => "Neg",
+        UnOp::LogicalNot => "Not",
+        UnOp::BitwiseNot => "BitNot",
+        UnOp::Deref => "Deref",
+        UnOp::Ref => "Ref",
+        UnOp::RefMut => "RefMut",
+    }
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementKind::Assign(place, rvalue) => write!(f, "{} = {};", place, rvalue),
+            StatementKind::StorageLive(local) => write!(f, "StorageLive({});", local),
+            StatementKind::StorageDead(local) => write!(f, "StorageDead({});", local),
+            StatementKind::InlineAsm(asm) => write!(f, "{};", asm),
+            StatementKind::Nop => write!(f, "nop;"),
+        }
+    }
+}
+
+// `asm!("...", in(reg) _1, out(reg) _2, inout(reg) _3)`，`inout` 的输入就是输出的位置
+impl fmt::Display for InlineAsm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let template: Vec<String> = self
+            .template
+            .iter()
+            .map(|line| format!("{:?}", line))
+            .collect();
+        write!(f, "asm!({}", template.join(", "))?;
+        for operand in &self.operands {
+            write!(f, ", {}({}) ", operand.dir.keyword(), operand.reg)?;
+            match (&operand.output, &operand.input) {
+                (Some(place), _) => write!(f, "{}", place)?,
+                (None, Some(input)) => write!(f, "{}", input)?,
+                (None, None) => write!(f, "_")?,
+            }
+        }
+        if !self.options.is_empty() {
+            write!(f, ", options({})", self.options.join(", "))?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for TerminatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminatorKind::Goto { target } => write!(f, "goto -> {};", target),
+            TerminatorKind::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                write!(f, "switchInt({}) -> [", discr)?;
+                for (value, target) in targets {
+                    write!(f, "{}: {}, ", value, target)?;
+                }
+                write!(f, "otherwise: {}];", otherwise)
+            }
+            TerminatorKind::Return => write!(f, "return;"),
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                unwind,
+            } => {
+                write!(f, "{} = {}({}) -> ", destination, func, operand_list(args))?;
+                let unwind = unwind_text(*unwind);
+                match target {
+                    Some(target) => write!(f, "[return: {}, {}];", target, unwind),
+                    None => write!(f, "{};", unwind),
+                }
+            }
+            TerminatorKind::Assert {
+                cond,
+                msg,
+                target,
+                unwind,
+            } => write!(
+                f,
+                "assert({}, {}) -> [success: {}, {}];",
+                cond,
+                msg,
+                target,
+                unwind_text(*unwind)
+            ),
+            TerminatorKind::Drop {
+                place,
+                target,
+                unwind,
+            } => write!(
+                f,
+                "drop({}) -> [return: {}, {}];",
+                place,
+                target,
+                unwind_text(*unwind)
+            ),
+            TerminatorKind::Resume => write!(f, "resume;"),
+            TerminatorKind::Unreachable => write!(f, "unreachable;"),
+        }
+    }
+}
+
+impl fmt::Display for AssertMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssertMessage::BoundsCheck { len, index } => write!(
+                f,
+                "\"index out of bounds: the len is {{}} but the index is {{}}\", {}, {}",
+                len, index
+            ),
+            AssertMessage::Contract { kind, clause, .. } => {
+                write!(f, "\"{}\"", ops::contract_violation(*kind, clause))
+            }
+        }
+    }
+}
+
+fn unwind_text(unwind: Option<BasicBlock>) -> String {
+    match unwind {
+        Some(cleanup) => format!("unwind: {}", cleanup),
+        None => "unwind continue".to_string(),
+    }
+}
+
+impl fmt::Display for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self
+            .args()
+            .map(|arg| format!("{}: {}", arg, self.local_decl(arg).ty))
+            .collect();
+        let type_params = if self.is_generic() {
+            format!("<{}>", self.type_params.join(", "))
+        } else {
+            String::new()
+        };
+        writeln!(
+            f,
+            "fn {}{}({}) -> {} {{",
+            self.name,
+            type_params,
+            args.join(", "),
+            self.return_ty()
+        )?;
+        self.write_locals(f)?;
+
+        for (block, data) in self.basic_blocks() {
+            writeln!(f)?;
+            writeln!(f, "{}{}: {{", INDENT, block)?;
+            for statement in &data.statements {
+                writeln!(f, "{}{}{}", INDENT, INDENT, statement.kind)?;
+            }
+            writeln!(f, "{}{}{}", INDENT, INDENT, data.terminator.kind)?;
+            writeln!(f, "{}}}", INDENT)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl Body {
+    // 先输出源代码变量名到局部变量的映射，再声明参数以外的所有局部变量
+    fn write_locals(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, decl) in self.locals.iter().enumerate() {
+            if let Some(name) = &decl.name {
+                writeln!(f, "{}debug {} => {};", INDENT, name, Local(i))?;
+            }
+        }
+        for (i, decl) in self.locals.iter().enumerate() {
+            if decl.kind == LocalKind::Arg {
+                continue;
+            }
+            let mutability = if decl.mutable || decl.kind == LocalKind::ReturnPointer {
+                "mut "
+            } else {
+                ""
+            };
+            writeln!(f, "{}let {}{}: {};", INDENT, mutability, Local(i), decl.ty)?;
+        }
+        Ok(())
+    }
+
+    /// 输出静态变量的初始化代码：`static NAME: T = { ... }`
+    pub fn display_static(&self) -> String {
+        let text = self.to_string();
+        let header_end = text.find('\n').unwrap_or(text.len());
+        format!(
+            "static {}: {} = {{{}",
+            self.name,
+            self.return_ty(),
+            &text[header_end..]
+        )
+    }
+}
+
+impl fmt::Display for MirProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        // 具体结构体和枚举的布局以注释列在最前面，字段偏移按声明顺序：
+        //     // struct Pair: size 16, align 8, fields at [8, 0]
+        //     // enum Shape: size 12, align 4, tag u8, payload at 4
+        //     //     Rect: tag 1, fields at [4, 8]
+        let layouts = self.layouts();
+        for def in &self.structs {
+            let Ok(layout) = layouts.struct_layout(&def.name) else {
+                continue;
+            };
+            writeln!(
+                f,
+                "// struct {}: size {}, align {}, fields at {:?}",
+                def.name, layout.layout.size, layout.layout.align, layout.offsets
+            )?;
+            first = false;
+        }
+        for def in &self.enums {
+            let Ok(layout) = layouts.enum_layout(&def.name) else {
+                continue;
+            };
+            writeln!(
+                f,
+                "// enum {}: size {}, align {}, tag {}, payload at {}",
+                def.name, layout.layout.size, layout.layout.align, layout.tag, layout.payload
+            )?;
+            for variant in &layout.variants {
+                write!(f, "//     {}: tag {}", variant.name, variant.tag)?;
+                match variant.offsets.is_empty() {
+                    true => writeln!(f)?,
+                    false => writeln!(f, ", fields at {:?}", variant.offsets)?,
+                }
+            }
+            first = false;
+        }
+        for body in &self.statics {
+            if !first {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", body.display_static())?;
+            first = false;
+        }
+        for body in &self.bodies {
+            if !first {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", body)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
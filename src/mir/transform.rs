@@ -0,0 +1,105 @@
+// MIR 优化
+// 优化级别决定运行哪些 pass：
+// - O0：不做任何变换，MIR 与降级结果一致，每个源码语句都有对应的代码（便于调试）
+// - O1：常量折叠和传播、删除常量条件下的死分支、简化控制流图，各运行一遍
+// - O2：与 O1 相同，并重复运行直到不再变化
+// - Os：在 O2 的基础上删除从 main 不可达的函数，生成的代码最小；
+//   O2 及以下保留所有函数，嵌入方可以按名字调用任何函数
+// 链接可执行文件时，优化级别同样决定 C 编译器和链接器的参数（见 link.rs）
+// 每个 pass 只改变函数体的结构，不改变程序的可观察行为
+// 析构展开（elaborate_drops）决定程序语义，属于 MIR 构建的一部分，不受优化级别控制
+// 常量下标的越界检查在 O1 以上由常量传播删除，`--no-bounds-check` 时全部删除
+
+mod bounds_checks;
+mod const_prop;
+mod dead_functions;
+mod elaborate_drops;
+mod simplify_cfg;
+
+pub use bounds_checks::remove_bounds_checks;
+pub use const_prop::{fold_binary, fold_cast, fold_unary, ConstPropagation, Value};
+pub use dead_functions::remove_unused_functions;
+pub use elaborate_drops::elaborate_drops;
+pub use simplify_cfg::{merge_blocks, remove_unreachable_blocks};
+
+use super::{Body, MirProgram};
+use crate::timing;
+use std::fmt;
+use std::str::FromStr;
+
+// 顺序即包含关系：较高的级别运行较低级别的所有 pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+    Os,
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            "s" => Ok(OptLevel::Os),
+            _ => Err(format!(
+                "invalid optimization level `{}`; expected `0`, `1`, `2` or `s`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptLevel::O0 => write!(f, "O0"),
+            OptLevel::O1 => write!(f, "O1"),
+            OptLevel::O2 => write!(f, "O2"),
+            OptLevel::Os => write!(f, "Os"),
+        }
+    }
+}
+
+// O2 重复运行 pass 的上限，防止意外的不收敛
+const MAX_ROUNDS: usize = 8;
+
+/// 按优化级别优化整个程序
+pub fn optimize(mir: &mut MirProgram, level: OptLevel) {
+    for body in mir.bodies.iter_mut().chain(mir.statics.iter_mut()) {
+        optimize_body(body, level);
+    }
+    // 常量传播删除分支之后，一些函数才变得不可达
+    if level == OptLevel::Os {
+        timing::time("dead function removal", || remove_unused_functions(mir));
+    }
+}
+
+/// 删除整个程序的越界检查，在 `optimize` 之前调用
+pub fn remove_all_bounds_checks(mir: &mut MirProgram) {
+    for body in mir.bodies.iter_mut().chain(mir.statics.iter_mut()) {
+        timing::time("bounds check removal", || remove_bounds_checks(body));
+    }
+}
+
+pub fn optimize_body(body: &mut Body, level: OptLevel) {
+    let rounds = match level {
+        OptLevel::O0 => return,
+        OptLevel::O1 => 1,
+        OptLevel::O2 | OptLevel::Os => MAX_ROUNDS,
+    };
+    for _ in 0..rounds {
+        let mut changed = timing::time("constant propagation", || ConstPropagation::run(body));
+        changed |= timing::time("unreachable block removal", || {
+            remove_unreachable_blocks(body)
+        });
+        changed |= timing::time("block merging", || merge_blocks(body));
+        if !changed {
+            break;
+        }
+    }
+}
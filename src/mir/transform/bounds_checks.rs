@@ -0,0 +1,77 @@
+// 删除下标越界检查（`--no-bounds-check`，用于基准测试）
+// 越界检查的 assert 变成 goto，只为检查而计算的临时变量（长度和比较结果）
+// 随之成为死赋值，一并删除
+// 后端访问元素时仍然检查下标，越界不会读写无关的内存，只是不再有 MIR 层面的检查
+
+use crate::ast::BinOp;
+use crate::mir::dataflow::{iterate_to_fixpoint, Analysis, BitSet, Liveness};
+use crate::mir::{AssertMessage, Body, LocalKind, Location, Rvalue, StatementKind, TerminatorKind};
+
+/// 删除函数体中所有的越界检查，返回是否有变化
+pub fn remove_bounds_checks(body: &mut Body) -> bool {
+    let mut changed = false;
+    for data in &mut body.blocks {
+        if let TerminatorKind::Assert {
+            msg: AssertMessage::BoundsCheck { .. },
+            target,
+            ..
+        } = data.terminator.kind
+        {
+            data.terminator.kind = TerminatorKind::Goto { target };
+            changed = true;
+        }
+    }
+    if changed {
+        while remove_dead_checks(body) {}
+    }
+    changed
+}
+
+// 删除之后不再被读取的临时变量上的长度和比较赋值
+fn remove_dead_checks(body: &mut Body) -> bool {
+    let results = iterate_to_fixpoint(Liveness, body);
+    let mut dead: Vec<(usize, usize)> = Vec::new();
+
+    for (block, data) in body.basic_blocks() {
+        let mut live: BitSet = results.entry_set(block).clone();
+        let terminator_loc = body.terminator_loc(block);
+        results
+            .analysis
+            .apply_terminator(&mut live, &data.terminator, terminator_loc);
+        for (i, statement) in data.statements.iter().enumerate().rev() {
+            if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                let is_check = matches!(
+                    rvalue,
+                    Rvalue::Len(_)
+                        | Rvalue::BinaryOp(
+                            BinOp::GreaterEqual | BinOp::Less | BinOp::BitwiseAnd,
+                            _,
+                            _,
+                            _
+                        )
+                );
+                if let Some(local) = place.as_local() {
+                    if is_check
+                        && body.local_decl(local).kind == LocalKind::Temp
+                        && !live.contains(local.index())
+                    {
+                        dead.push((block.index(), i));
+                    }
+                }
+            }
+            let location = Location {
+                block,
+                statement_index: i,
+            };
+            results
+                .analysis
+                .apply_statement(&mut live, statement, location);
+        }
+    }
+
+    // 同一基本块内从后往前删除，下标保持有效
+    for (block, statement) in &dead {
+        body.blocks[*block].statements.remove(*statement);
+    }
+    !dead.is_empty()
+}
@@ -0,0 +1,576 @@
+// 常量折叠和传播
+// 1. 前向数据流分析：每个局部变量的格为 未定义 < 常量 < 不确定
+// 2. 取过引用或被部分写入（字段、下标）的变量不参与传播
+// 3. 用分析结果把已知常量的操作数替换为常量，能整体算出的右值折叠为常量
+// 4. 条件为常量的 switchInt 变成 goto，死分支由 CFG 简化删除；
+//    条件为真的 assert（例如常量下标的越界检查）变成 goto
+// 5. 折叠后不再被读取的临时变量赋值被删除
+// 折叠不改变运行时语义：溢出、除零、越界移位都保留到运行时处理
+
+use crate::ast::{BinOp, Type, UnOp};
+use crate::mir::dataflow::{iterate_to_fixpoint, Analysis, BitSet, Direction, Liveness};
+use crate::mir::{
+    BasicBlock, Body, ConstValue, Constant, Local, LocalKind, Location, Operand, Place, PlaceElem,
+    Rvalue, Statement, StatementKind, Terminator, TerminatorKind,
+};
+
+/// 常量传播的格：未定义 < 已知常量 < 不确定
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Undefined,
+    Known(Constant),
+    Overdefined,
+}
+
+impl Value {
+    fn join(&mut self, other: &Value) -> bool {
+        let joined = match (&*self, other) {
+            (_, Value::Undefined) => return false,
+            (Value::Undefined, other) => other.clone(),
+            (Value::Known(a), Value::Known(b)) if a == b => return false,
+            (Value::Overdefined, _) => return false,
+            _ => Value::Overdefined,
+        };
+        *self = joined;
+        true
+    }
+}
+
+pub struct ConstPropagation {
+    tracked: Vec<bool>, // 可以参与传播的局部变量
+    types: Vec<Type>,   // 局部变量的类型，整数常量按目标类型重新检查
+}
+
+impl ConstPropagation {
+    pub fn new(body: &Body) -> Self {
+        let mut tracked = vec![true; body.locals.len()];
+        for data in &body.blocks {
+            for statement in &data.statements {
+                if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                    if place.as_local().is_none() {
+                        tracked[place.local.index()] = false;
+                    }
+                    if let Rvalue::Ref(referent, _) = rvalue {
+                        tracked[referent.local.index()] = false;
+                    }
+                }
+            }
+            if let TerminatorKind::Call { destination, .. } = &data.terminator.kind {
+                if destination.as_local().is_none() {
+                    tracked[destination.local.index()] = false;
+                }
+            }
+        }
+        let types = body.locals.iter().map(|decl| decl.ty.clone()).collect();
+        Self { tracked, types }
+    }
+
+    /// 对函数体做常量传播，返回是否有变化
+    pub fn run(body: &mut Body) -> bool {
+        let analysis = ConstPropagation::new(body);
+        let results = iterate_to_fixpoint(analysis, body);
+        let entry_sets: Vec<Vec<Value>> = body
+            .basic_blocks()
+            .map(|(block, _)| results.entry_set(block).clone())
+            .collect();
+        let analysis = results.analysis;
+
+        let mut changed = false;
+        for (index, mut state) in entry_sets.into_iter().enumerate() {
+            let data = &mut body.blocks[index];
+            for (i, statement) in data.statements.iter_mut().enumerate() {
+                changed |= analysis.rewrite_statement(statement, &state);
+                let location = Location {
+                    block: BasicBlock(index),
+                    statement_index: i,
+                };
+                analysis.apply_statement(&mut state, statement, location);
+            }
+            changed |= analysis.rewrite_terminator(&mut data.terminator, &state);
+        }
+
+        changed |= remove_dead_constants(body);
+        changed
+    }
+
+    fn rewrite_statement(&self, statement: &mut Statement, state: &[Value]) -> bool {
+        let StatementKind::Assign(_, rvalue) = &mut statement.kind else {
+            return false;
+        };
+        let mut changed = false;
+        match rvalue {
+            Rvalue::Use(operand) | Rvalue::UnaryOp(_, operand, _) | Rvalue::Cast(operand, _) => {
+                changed |= propagate(operand, state);
+            }
+            Rvalue::BinaryOp(_, lhs, rhs, _) => {
+                changed |= propagate(lhs, state);
+                changed |= propagate(rhs, state);
+            }
+            Rvalue::Aggregate(_, operands) => {
+                for operand in operands {
+                    changed |= propagate(operand, state);
+                }
+            }
+            Rvalue::Ref(_, _) | Rvalue::Len(_) | Rvalue::Discriminant(_) => {}
+        }
+
+        if !matches!(rvalue, Rvalue::Use(Operand::Constant(_))) {
+            if let Value::Known(constant) = self.eval(rvalue, state) {
+                *rvalue = Rvalue::Use(Operand::Constant(constant));
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn rewrite_terminator(&self, terminator: &mut Terminator, state: &[Value]) -> bool {
+        let mut changed = false;
+        match &mut terminator.kind {
+            TerminatorKind::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                changed |= propagate(discr, state);
+                if let Some(value) = discr.constant().and_then(switch_value) {
+                    let target = targets
+                        .iter()
+                        .find(|(v, _)| *v == value)
+                        .map(|(_, target)| *target)
+                        .unwrap_or(*otherwise);
+                    terminator.kind = TerminatorKind::Goto { target };
+                    changed = true;
+                }
+            }
+            TerminatorKind::Call { args, .. } => {
+                for arg in args {
+                    changed |= propagate(arg, state);
+                }
+            }
+            TerminatorKind::Assert {
+                cond, msg, target, ..
+            } => {
+                changed |= propagate(cond, state);
+                for operand in msg.operands_mut() {
+                    changed |= propagate(operand, state);
+                }
+                if cond
+                    .constant()
+                    .is_some_and(|c| c.value == ConstValue::Bool(true))
+                {
+                    terminator.kind = TerminatorKind::Goto { target: *target };
+                    changed = true;
+                }
+            }
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Drop { .. }
+            | TerminatorKind::Resume
+            | TerminatorKind::Unreachable => {}
+        }
+        changed
+    }
+
+    fn eval(&self, rvalue: &Rvalue, state: &[Value]) -> Value {
+        match rvalue {
+            Rvalue::Use(operand) => operand_value(operand, state),
+            Rvalue::BinaryOp(op, lhs, rhs, ty) => {
+                match (operand_value(lhs, state), operand_value(rhs, state)) {
+                    (Value::Known(mut a), Value::Known(b)) => {
+                        // 按记录的整数类型的宽度折叠，溢出的运算留到运行时报告
+                        if let Some(ty) = ty {
+                            a.ty = (**ty).clone();
+                        }
+                        known(fold_binary(op, &a, &b))
+                    }
+                    (Value::Overdefined, _) | (_, Value::Overdefined) => Value::Overdefined,
+                    _ => Value::Undefined,
+                }
+            }
+            Rvalue::UnaryOp(op, operand, ty) => match operand_value(operand, state) {
+                Value::Known(mut c) => {
+                    if let Some(ty) = ty {
+                        c.ty = (**ty).clone();
+                    }
+                    known(fold_unary(op, &c))
+                }
+                other => other,
+            },
+            Rvalue::Cast(operand, ty) => match operand_value(operand, state) {
+                Value::Known(c) => known(fold_cast(&c, ty)),
+                other => other,
+            },
+            Rvalue::Len(place) => match self.array_len(place) {
+                Some(len) => Value::Known(Constant::int(len as i64, Type::Usize)),
+                None => Value::Overdefined,
+            },
+            _ => Value::Overdefined,
+        }
+    }
+
+    // 定长数组（或指向它的引用）的长度由类型决定
+    fn array_len(&self, place: &Place) -> Option<usize> {
+        let mut ty = &self.types[place.local.index()];
+        for elem in &place.projection {
+            ty = match (elem, ty) {
+                (PlaceElem::Deref, Type::Reference(inner, _) | Type::Pointer(inner, _)) => {
+                    inner.as_ref()
+                }
+                _ => return None,
+            };
+        }
+        match ty {
+            Type::Array(_, len) => Some(*len),
+            _ => None,
+        }
+    }
+}
+
+impl Analysis for ConstPropagation {
+    type Domain = Vec<Value>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self, body: &Body) -> Vec<Value> {
+        vec![Value::Undefined; body.locals.len()]
+    }
+
+    fn initialize_boundary(&self, body: &Body, state: &mut Vec<Value>) {
+        for arg in body.args() {
+            state[arg.index()] = Value::Overdefined;
+        }
+    }
+
+    fn join(&self, state: &mut Vec<Value>, other: &Vec<Value>) -> bool {
+        let mut changed = false;
+        for (value, other) in state.iter_mut().zip(other) {
+            changed |= value.join(other);
+        }
+        changed
+    }
+
+    fn apply_statement(&self, state: &mut Vec<Value>, statement: &Statement, _location: Location) {
+        // 汇编写入的值无法知道
+        if let StatementKind::InlineAsm(asm) = &statement.kind {
+            for place in asm.outputs() {
+                state[place.local.index()] = Value::Overdefined;
+            }
+        }
+        if let StatementKind::Assign(place, rvalue) = &statement.kind {
+            let local = place.local.index();
+            state[local] = match place.as_local() {
+                Some(_) if self.tracked[local] => match self.eval(rvalue, state) {
+                    // 整数字面量默认是 i32，赋给其他整数类型的变量时换成变量的类型
+                    Value::Known(Constant {
+                        value: ConstValue::Int(value),
+                        ..
+                    }) if int_layout(&self.types[local]).is_some() => {
+                        known(checked_int(value as i128, &self.types[local]))
+                    }
+                    value => value,
+                },
+                _ => Value::Overdefined,
+            };
+        }
+    }
+
+    fn apply_terminator(
+        &self,
+        state: &mut Vec<Value>,
+        terminator: &Terminator,
+        _location: Location,
+    ) {
+        if let TerminatorKind::Call { destination, .. } = &terminator.kind {
+            state[destination.local.index()] = Value::Overdefined;
+        }
+    }
+}
+
+fn operand_value(operand: &Operand, state: &[Value]) -> Value {
+    match operand {
+        Operand::Constant(constant) => Value::Known(constant.clone()),
+        Operand::Copy(place) | Operand::Move(place) => match place.as_local() {
+            Some(local) => state[local.index()].clone(),
+            None => Value::Overdefined,
+        },
+    }
+}
+
+// 把值已知的局部变量操作数替换为常量
+fn propagate(operand: &mut Operand, state: &[Value]) -> bool {
+    let Operand::Copy(place) = operand else {
+        return false;
+    };
+    let Some(local) = place.as_local() else {
+        return false;
+    };
+    match &state[local.index()] {
+        Value::Known(constant) if is_scalar(constant) => {
+            *operand = Operand::Constant(constant.clone());
+            true
+        }
+        _ => false,
+    }
+}
+
+// 只传播标量常量，字符串等值保留为变量引用
+fn is_scalar(constant: &Constant) -> bool {
+    matches!(
+        constant.value,
+        ConstValue::Int(_)
+            | ConstValue::Float(_)
+            | ConstValue::Bool(_)
+            | ConstValue::Char(_)
+            | ConstValue::Unit
+    )
+}
+
+fn known(constant: Option<Constant>) -> Value {
+    match constant {
+        Some(constant) => Value::Known(constant),
+        None => Value::Overdefined,
+    }
+}
+
+fn switch_value(constant: &Constant) -> Option<i64> {
+    match constant.value {
+        ConstValue::Int(value) => Some(value),
+        ConstValue::Bool(value) => Some(value as i64),
+        ConstValue::Char(value) => Some(value as i64),
+        _ => None,
+    }
+}
+
+// 删除值为常量、之后不再被读取的临时变量赋值
+fn remove_dead_constants(body: &mut Body) -> bool {
+    let results = iterate_to_fixpoint(Liveness, body);
+    let mut dead: Vec<(usize, usize)> = Vec::new();
+
+    for (block, data) in body.basic_blocks() {
+        let mut live: BitSet = results.entry_set(block).clone();
+        let terminator_loc = body.terminator_loc(block);
+        results
+            .analysis
+            .apply_terminator(&mut live, &data.terminator, terminator_loc);
+        for (i, statement) in data.statements.iter().enumerate().rev() {
+            if let StatementKind::Assign(place, Rvalue::Use(Operand::Constant(_))) = &statement.kind
+            {
+                if let Some(local) = place.as_local() {
+                    if is_temp(body, local) && !live.contains(local.index()) {
+                        dead.push((block.index(), i));
+                    }
+                }
+            }
+            let location = Location {
+                block,
+                statement_index: i,
+            };
+            results
+                .analysis
+                .apply_statement(&mut live, statement, location);
+        }
+    }
+
+    // 同一基本块内从后往前删除，下标保持有效
+    for (block, statement) in &dead {
+        body.blocks[*block].statements.remove(*statement);
+    }
+    !dead.is_empty()
+}
+
+fn is_temp(body: &Body, local: Local) -> bool {
+    body.local_decl(local).kind == LocalKind::Temp
+}
+
+// ---------------- 折叠 ----------------
+
+// 整数类型的位宽和符号，其他类型返回 None
+fn int_layout(ty: &Type) -> Option<(u32, bool)> {
+    match ty {
+        Type::I8 => Some((8, true)),
+        Type::I16 => Some((16, true)),
+        Type::I32 => Some((32, true)),
+        Type::I64 | Type::Isize => Some((64, true)),
+        Type::U8 => Some((8, false)),
+        Type::U16 => Some((16, false)),
+        Type::U32 => Some((32, false)),
+        Type::U64 | Type::Usize => Some((64, false)),
+        _ => None,
+    }
+}
+
+fn int_range(ty: &Type) -> Option<(i128, i128)> {
+    let (bits, signed) = int_layout(ty)?;
+    Some(if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    })
+}
+
+// 结果在类型范围内（并且能用 i64 表示）时才生成常量，否则留给运行时
+fn checked_int(value: i128, ty: &Type) -> Option<Constant> {
+    let (min, max) = int_range(ty)?;
+    if value < min || value > max {
+        return None;
+    }
+    let value = i64::try_from(value).ok()?;
+    Some(Constant::int(value, ty.clone()))
+}
+
+// `as` 转换的截断语义：保留低位，有符号类型做符号扩展
+fn wrapping_int(value: i128, ty: &Type) -> Option<Constant> {
+    let (bits, signed) = int_layout(ty)?;
+    let mask = (1i128 << bits) - 1;
+    let mut truncated = value & mask;
+    if signed && truncated >> (bits - 1) & 1 == 1 {
+        truncated -= 1i128 << bits;
+    }
+    let value = i64::try_from(truncated).ok()?;
+    Some(Constant::int(value, ty.clone()))
+}
+
+fn operand_type<'a>(lhs: &'a Constant, rhs: &'a Constant) -> &'a Type {
+    if lhs.ty == Type::Infer {
+        &rhs.ty
+    } else {
+        &lhs.ty
+    }
+}
+
+/// 折叠二元运算，无法在编译期确定结果（溢出、除零等）时返回 None
+pub fn fold_binary(op: &BinOp, lhs: &Constant, rhs: &Constant) -> Option<Constant> {
+    match (&lhs.value, &rhs.value) {
+        (ConstValue::Int(a), ConstValue::Int(b)) => {
+            let ty = operand_type(lhs, rhs);
+            let (bits, _) = int_layout(ty)?;
+            let (a, b) = (*a as i128, *b as i128);
+            // 最小值除以 -1 的商和余数都算溢出
+            if matches!(op, BinOp::Div | BinOp::Mod)
+                && b == -1
+                && Some(a) == int_range(ty).map(|r| r.0)
+            {
+                return None;
+            }
+            match op {
+                BinOp::Add => checked_int(a + b, ty),
+                BinOp::Sub => checked_int(a - b, ty),
+                BinOp::Mul => checked_int(a * b, ty),
+                BinOp::Div if b != 0 => checked_int(a / b, ty),
+                BinOp::Mod if b != 0 => checked_int(a % b, ty),
+                BinOp::BitwiseAnd => checked_int(a & b, ty),
+                BinOp::BitwiseOr => checked_int(a | b, ty),
+                BinOp::BitwiseXor => checked_int(a ^ b, ty),
+                BinOp::LeftShift if (0..bits as i128).contains(&b) => wrapping_int(a << b, ty),
+                BinOp::RightShift if (0..bits as i128).contains(&b) => checked_int(a >> b, ty),
+                _ => compare(op, a.cmp(&b)),
+            }
+        }
+        (ConstValue::Float(a), ConstValue::Float(b)) => {
+            let single = *operand_type(lhs, rhs) == Type::F32;
+            let value = match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Mod => a % b,
+                _ => return compare(op, a.partial_cmp(b)?),
+            };
+            let value = if single { value as f32 as f64 } else { value };
+            Some(Constant {
+                value: ConstValue::Float(value),
+                ty: lhs.ty.clone(),
+            })
+        }
+        (ConstValue::Bool(a), ConstValue::Bool(b)) => match op {
+            BinOp::BitwiseAnd | BinOp::LogicalAnd => Some(Constant::bool(*a && *b)),
+            BinOp::BitwiseOr | BinOp::LogicalOr => Some(Constant::bool(*a || *b)),
+            BinOp::BitwiseXor => Some(Constant::bool(a != b)),
+            _ => compare(op, a.cmp(b)),
+        },
+        (ConstValue::Char(a), ConstValue::Char(b)) => compare(op, a.cmp(b)),
+        (ConstValue::Str(a), ConstValue::Str(b)) => compare(op, a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn compare(op: &BinOp, ordering: std::cmp::Ordering) -> Option<Constant> {
+    use std::cmp::Ordering::*;
+    let result = match op {
+        BinOp::Equal => ordering == Equal,
+        BinOp::NotEqual => ordering != Equal,
+        BinOp::Less => ordering == Less,
+        BinOp::Greater => ordering == Greater,
+        BinOp::LessEqual => ordering != Greater,
+        BinOp::GreaterEqual => ordering != Less,
+        _ => return None,
+    };
+    Some(Constant::bool(result))
+}
+
+/// 折叠一元运算
+pub fn fold_unary(op: &UnOp, operand: &Constant) -> Option<Constant> {
+    match (op, &operand.value) {
+        (UnOp::Neg, ConstValue::Int(value)) => {
+            let (_, signed) = int_layout(&operand.ty)?;
+            if !signed {
+                return None;
+            }
+            checked_int(-(*value as i128), &operand.ty)
+        }
+        (UnOp::Neg, ConstValue::Float(value)) => Some(Constant {
+            value: ConstValue::Float(-value),
+            ty: operand.ty.clone(),
+        }),
+        (UnOp::LogicalNot, ConstValue::Bool(value)) => Some(Constant::bool(!value)),
+        // 整数的 `!` 和 `~` 都是按位取反
+        (UnOp::LogicalNot | UnOp::BitwiseNot, ConstValue::Int(value)) => {
+            wrapping_int(!(*value as i128), &operand.ty)
+        }
+        _ => None,
+    }
+}
+
+/// 折叠 `as` 转换，语义与运行时一致：整数截断，浮点转整数饱和
+pub fn fold_cast(operand: &Constant, ty: &Type) -> Option<Constant> {
+    if operand.ty == *ty {
+        return Some(operand.clone());
+    }
+    let float = |value: f64| {
+        let value = if *ty == Type::F32 {
+            value as f32 as f64
+        } else {
+            value
+        };
+        Some(Constant {
+            value: ConstValue::Float(value),
+            ty: ty.clone(),
+        })
+    };
+
+    match &operand.value {
+        ConstValue::Int(value) => match ty {
+            Type::F32 | Type::F64 => float(*value as f64),
+            Type::Char if operand.ty == Type::U8 => Some(Constant {
+                value: ConstValue::Char(char::from(*value as u8)),
+                ty: Type::Char,
+            }),
+            _ => wrapping_int(*value as i128, ty),
+        },
+        ConstValue::Float(value) => match ty {
+            Type::F32 | Type::F64 => float(*value),
+            _ => {
+                let (min, max) = int_range(ty)?;
+                let saturated = if value.is_nan() {
+                    0
+                } else {
+                    (value.trunc() as i128).clamp(min, max)
+                };
+                checked_int(saturated, ty)
+            }
+        },
+        ConstValue::Bool(value) => wrapping_int(*value as i128, ty),
+        ConstValue::Char(value) => wrapping_int(*value as i128, ty),
+        _ => None,
+    }
+}
@@ -0,0 +1,66 @@
+// 删除用不到的函数（-Os）
+// 从 main、基准测试和静态变量的初始化代码出发，沿调用、函数指针和闭包构造找到所有用到的函数，
+// 其余的函数体删除。没有 main 的程序（被嵌入方按名字调用）保留所有函数
+
+use crate::mir::{
+    AggregateKind, Body, ConstValue, MirProgram, Operand, Rvalue, StatementKind, TerminatorKind,
+};
+use std::collections::BTreeSet;
+
+/// 删除从入口不可达的函数，返回是否有变化
+pub fn remove_unused_functions(mir: &mut MirProgram) -> bool {
+    let Some(main) = mir.body("main") else {
+        return false;
+    };
+    let mut used = BTreeSet::from([main.name.clone()]);
+    used.extend(mir.benches.iter().cloned());
+    let mut stack: Vec<&Body> = mir.statics.iter().chain([main]).collect();
+    stack.extend(mir.benches.iter().filter_map(|name| mir.body(name)));
+    while let Some(body) = stack.pop() {
+        for name in referenced_functions(body) {
+            if used.insert(name.clone()) {
+                stack.extend(mir.body(&name));
+            }
+        }
+    }
+
+    let before = mir.bodies.len();
+    mir.bodies.retain(|body| used.contains(&body.name));
+    mir.bodies.len() != before
+}
+
+// 函数体中出现的函数常量和闭包
+fn referenced_functions(body: &Body) -> Vec<String> {
+    let mut operands: Vec<&Operand> = Vec::new();
+    let mut names = Vec::new();
+    for data in &body.blocks {
+        for statement in &data.statements {
+            let StatementKind::Assign(_, rvalue) = &statement.kind else {
+                continue;
+            };
+            operands.extend(rvalue.operands());
+            if let Rvalue::Aggregate(AggregateKind::Closure(name), _) = rvalue {
+                names.push(name.clone());
+            }
+        }
+        match &data.terminator.kind {
+            TerminatorKind::Call { func, args, .. } => {
+                operands.push(func);
+                operands.extend(args);
+            }
+            TerminatorKind::SwitchInt { discr: value, .. }
+            | TerminatorKind::Assert { cond: value, .. } => operands.push(value),
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Drop { .. }
+            | TerminatorKind::Resume
+            | TerminatorKind::Unreachable => {}
+        }
+    }
+    for operand in operands {
+        if let Some(ConstValue::Function(name)) = operand.constant().map(|c| &c.value) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
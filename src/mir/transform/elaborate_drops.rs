@@ -0,0 +1,222 @@
+// 析构展开
+// MIR 构建在每个作用域出口为所有拥有值的变量生成 `drop`，不管变量是否已经被移出。
+// 这里根据初始化状态决定每个 `drop` 的形式：
+// 1. 变量在此处一定未初始化（已被移出）：删除析构，直接跳转
+// 2. 变量在此处一定已初始化：保留无条件析构
+// 3. 取决于经过的路径：为变量引入 bool 析构标志，初始化时置真、移出或析构时置假，
+//    析构前按标志分支
+// 这一步决定程序语义，与优化级别无关，在 MIR 构建结束时执行
+
+use crate::ast::Type;
+use crate::mir::dataflow::{
+    iterate_to_fixpoint, statement_init_effects, terminator_init_effects, MaybeInitializedLocals,
+    MaybeUninitializedLocals,
+};
+use crate::mir::{
+    BasicBlock, BasicBlockData, Body, Constant, Local, LocalDecl, LocalKind, Operand, Place,
+    Rvalue, Statement, StatementKind, Terminator, TerminatorKind,
+};
+use crate::span::Span;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DropStyle {
+    Dead,
+    Static,
+    Conditional,
+}
+
+/// 展开函数体中的所有 `drop`，返回是否有变化
+pub fn elaborate_drops(body: &mut Body) -> bool {
+    let styles = drop_styles(body);
+    if styles.is_empty() {
+        return false;
+    }
+
+    // 需要析构标志的变量
+    let mut flags: BTreeMap<Local, Local> = BTreeMap::new();
+    for (block, style) in &styles {
+        if *style == DropStyle::Conditional {
+            let local = dropped_place(body, *block).local;
+            if let Entry::Vacant(entry) = flags.entry(local) {
+                entry.insert(new_flag(body, local));
+            }
+        }
+    }
+
+    insert_flag_updates(body, &flags);
+
+    for (block, style) in styles {
+        let terminator = &mut body.blocks[block.index()].terminator;
+        let TerminatorKind::Drop { place, target, .. } = terminator.kind.clone() else {
+            continue;
+        };
+        let span = terminator.span;
+        // 析构整个变量之后变量未初始化，标志随之置假；析构字段（赋值前）不改变标志
+        let flag = flags.get(&place.local).copied();
+        let clear_flag = flag
+            .filter(|_| place.as_local().is_some())
+            .map(|flag| set_flag(flag, false, span));
+        match style {
+            DropStyle::Dead => terminator.kind = TerminatorKind::Goto { target },
+            DropStyle::Static => {
+                body.blocks[block.index()].statements.extend(clear_flag);
+            }
+            DropStyle::Conditional => {
+                let flag = flag.expect("conditional drop has a flag");
+                let drop = body.blocks[block.index()].terminator.clone();
+                let drop_block = push_block(body, clear_flag.into_iter().collect(), drop);
+                body.blocks[block.index()].terminator.kind = TerminatorKind::SwitchInt {
+                    discr: Operand::Copy(Place::local(flag)),
+                    targets: vec![(0, target)],
+                    otherwise: drop_block,
+                };
+            }
+        }
+    }
+    true
+}
+
+// 按析构位置上变量的初始化状态给每个 drop 分类
+fn drop_styles(body: &Body) -> Vec<(BasicBlock, DropStyle)> {
+    let drops: Vec<BasicBlock> = body
+        .basic_blocks()
+        .filter(|(_, data)| matches!(data.terminator.kind, TerminatorKind::Drop { .. }))
+        .map(|(block, _)| block)
+        .collect();
+    if drops.is_empty() {
+        return Vec::new();
+    }
+
+    let maybe_init = iterate_to_fixpoint(MaybeInitializedLocals, body);
+    let maybe_uninit = iterate_to_fixpoint(MaybeUninitializedLocals, body);
+    drops
+        .into_iter()
+        .map(|block| {
+            let location = body.terminator_loc(block);
+            let local = dropped_place(body, block).local.index();
+            let init = maybe_init.state_before(location).contains(local);
+            let uninit = maybe_uninit.state_before(location).contains(local);
+            let style = match (init, uninit) {
+                (false, _) => DropStyle::Dead,
+                (true, false) => DropStyle::Static,
+                (true, true) => DropStyle::Conditional,
+            };
+            (block, style)
+        })
+        .collect()
+}
+
+fn dropped_place(body: &Body, block: BasicBlock) -> &Place {
+    match &body.block(block).terminator.kind {
+        TerminatorKind::Drop { place, .. } => place,
+        _ => unreachable!("block {} does not end in a drop", block),
+    }
+}
+
+fn new_flag(body: &mut Body, local: Local) -> Local {
+    let span = body.local_decl(local).span;
+    body.locals.push(LocalDecl {
+        name: None,
+        ty: Type::Bool,
+        mutable: true,
+        kind: LocalKind::Temp,
+        span,
+    });
+    Local(body.locals.len() - 1)
+}
+
+fn set_flag(flag: Local, value: bool, span: Span) -> Statement {
+    Statement {
+        kind: StatementKind::Assign(
+            Place::local(flag),
+            Rvalue::Use(Operand::Constant(Constant::bool(value))),
+        ),
+        span,
+    }
+}
+
+// 在每个改变初始化状态的位置同步更新析构标志，入口处所有标志为假
+fn insert_flag_updates(body: &mut Body, flags: &BTreeMap<Local, Local>) {
+    if flags.is_empty() {
+        return;
+    }
+    let block_count = body.blocks.len();
+    for index in 0..block_count {
+        let statements = std::mem::take(&mut body.blocks[index].statements);
+        let mut updated = Vec::with_capacity(statements.len());
+        for statement in statements {
+            let mut last_effect = BTreeMap::new();
+            statement_init_effects(&statement, |local, init| {
+                last_effect.insert(local, init);
+            });
+            let span = statement.span;
+            updated.push(statement);
+            for (local, init) in last_effect {
+                if let Some(flag) = flags.get(&local) {
+                    updated.push(set_flag(*flag, init, span));
+                }
+            }
+        }
+
+        // 终结符：移出在调用之前生效，目标的初始化在调用返回之后生效
+        let terminator = body.blocks[index].terminator.clone();
+        let span = terminator.span;
+        let mut moved_in_call = Vec::new();
+        let mut initialized_by_call = None;
+        if let TerminatorKind::Call { .. } = terminator.kind {
+            terminator_init_effects(&terminator, |local, init| {
+                if !flags.contains_key(&local) {
+                    return;
+                }
+                if init {
+                    initialized_by_call = Some(local);
+                } else {
+                    moved_in_call.push(local);
+                }
+            });
+        }
+        for local in moved_in_call {
+            updated.push(set_flag(flags[&local], false, span));
+        }
+        body.blocks[index].statements = updated;
+
+        if let Some(local) = initialized_by_call {
+            // 拆开调用的返回边，在新块中置标志
+            let TerminatorKind::Call {
+                target: Some(target),
+                ..
+            } = terminator.kind
+            else {
+                continue;
+            };
+            let flag = flags[&local];
+            let landing = push_block(
+                body,
+                vec![set_flag(flag, true, span)],
+                Terminator {
+                    kind: TerminatorKind::Goto { target },
+                    span,
+                },
+            );
+            if let TerminatorKind::Call { target, .. } = &mut body.blocks[index].terminator.kind {
+                *target = Some(landing);
+            }
+        }
+    }
+
+    let span = body.span;
+    let entry = &mut body.blocks[BasicBlock::START.index()].statements;
+    for (position, flag) in flags.values().enumerate() {
+        entry.insert(position, set_flag(*flag, false, span));
+    }
+}
+
+fn push_block(body: &mut Body, statements: Vec<Statement>, terminator: Terminator) -> BasicBlock {
+    body.blocks.push(BasicBlockData {
+        statements,
+        terminator,
+    });
+    BasicBlock(body.blocks.len() - 1)
+}
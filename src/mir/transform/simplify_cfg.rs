@@ -0,0 +1,81 @@
+// 控制流图简化：删除不可达的基本块、合并直线相连的基本块
+
+use crate::mir::{BasicBlock, Body, Terminator, TerminatorKind};
+
+/// 删除从入口不可达的基本块并重新编号，返回是否删除了基本块
+pub fn remove_unreachable_blocks(body: &mut Body) -> bool {
+    let mut reachable = vec![false; body.blocks.len()];
+    let mut stack = vec![BasicBlock::START];
+    while let Some(block) = stack.pop() {
+        if reachable[block.index()] {
+            continue;
+        }
+        reachable[block.index()] = true;
+        stack.extend(body.block(block).terminator.successors());
+    }
+    if reachable.iter().all(|r| *r) {
+        return false;
+    }
+
+    // 保持原有的相对顺序
+    let mut remap = vec![None; body.blocks.len()];
+    let mut next = 0;
+    for (old, is_reachable) in reachable.iter().enumerate() {
+        if *is_reachable {
+            remap[old] = Some(BasicBlock(next));
+            next += 1;
+        }
+    }
+
+    let blocks = std::mem::take(&mut body.blocks);
+    for (old, mut data) in blocks.into_iter().enumerate() {
+        if !reachable[old] {
+            continue;
+        }
+        for target in data.terminator.kind.successors_mut() {
+            *target = remap[target.index()].expect("successor of a reachable block is reachable");
+        }
+        body.blocks.push(data);
+    }
+    true
+}
+
+/// 合并 `goto` 相连且后继只有这一个前驱的基本块，返回是否合并了基本块
+pub fn merge_blocks(body: &mut Body) -> bool {
+    let mut changed = false;
+    loop {
+        let predecessors = body.predecessors();
+        let candidate = body.basic_blocks().find_map(|(block, data)| {
+            let TerminatorKind::Goto { target } = data.terminator.kind else {
+                return None;
+            };
+            let mergeable = target != block
+                && target != BasicBlock::START
+                && predecessors[target.index()].len() == 1;
+            mergeable.then_some((block, target))
+        });
+        let Some((block, target)) = candidate else {
+            break;
+        };
+
+        // 被合并的块变成没有前驱的空块，最后统一删除
+        let absorbed = std::mem::take(&mut body.blocks[target.index()].statements);
+        let span = body.blocks[target.index()].terminator.span;
+        let terminator = std::mem::replace(
+            &mut body.blocks[target.index()].terminator,
+            Terminator {
+                kind: TerminatorKind::Unreachable,
+                span,
+            },
+        );
+        let data = &mut body.blocks[block.index()];
+        data.statements.extend(absorbed);
+        data.terminator = terminator;
+        changed = true;
+    }
+
+    if changed {
+        remove_unreachable_blocks(body);
+    }
+    changed
+}
@@ -0,0 +1,758 @@
+// Contractus 模块加载器
+// 把 `import a::b;` 映射到项目根目录下的 .ctx 文件：
+// 1. 优先把整个路径当作模块：`a/b.ctx`，导入后通过 `b::name` 访问其成员
+// 2. 否则把最后一段当作条目名：从模块 `a`（`a.ctx`）中导入条目 `b`
+// 被导入的模块会递归加载，并检测循环导入。所有模块合并为一个 `Crate`，
+// 每个模块保留自己的命名空间，名称解析由 sema 基于 `ResolvedImport` 完成。
+// 其他模块只能导入 `pub` 条目或出现在 `export { ... }` 列表中的条目。
+// 除了从入口出发沿导入加载，还可以一起编译额外的文件（命令行上的多个文件或 `--root`
+// 目录下的所有文件）：它们按相对于根目录的路径成为模块，即使没有被导入也会加载和检查。
+// 依赖包挂载在自己的名字下：包的入口模块是 `math`，包里的 `vector.ctx` 是 `math::vector`；
+// 包内的导入相对于包自己的目录查找，依赖包和根目录下的同名模块冲突。
+// 开启了 `custom_operators` 的文件在解析之前先找出导入的模块，收集其中公开的自定义运算符，
+// 解析器需要它们的优先级。
+// 之后的阶段（MIR 降级和解释器）处理所有模块合并成的一个程序，见 merge.rs。
+
+mod merge;
+
+use crate::ast::{Item, Program, Visibility};
+use crate::derive;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::features::{self, Feature};
+use crate::lexer::Lexer;
+use crate::limits::CompileLimits;
+use crate::macros;
+use crate::parser::{self, CustomOperator, Parser};
+use crate::source_map::{FileId, SourceMap};
+use crate::span::Span;
+use crate::timing;
+use crate::token::{Token, TokenKind};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// 模块路径，根模块为空路径
+pub type ModulePath = Vec<String>;
+
+/// 源文件扩展名
+pub const SOURCE_EXTENSION: &str = "ctx";
+
+/// `--root` 目录中入口模块的文件名
+pub const ENTRY_FILE: &str = "main.ctx";
+
+#[derive(Debug, Clone)]
+pub struct ResolvedImport {
+    pub name: String,         // 在导入方模块中可见的名字（别名或最后一段）
+    pub module: ModulePath,   // 被导入的模块
+    pub item: Option<String>, // None 表示导入整个模块作为命名空间
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub path: ModulePath,
+    pub file: PathBuf,
+    pub package: Option<String>, // 所属的依赖包，项目自己的模块为 None
+    pub program: Program,
+    pub imports: Vec<ResolvedImport>,
+}
+
+impl Module {
+    /// 模块的显示名，根模块显示为 `crate`
+    pub fn display_name(&self) -> String {
+        display_path(&self.path)
+    }
+
+    /// 模块中是否定义了指定名字的顶层条目
+    pub fn defines(&self, name: &str) -> bool {
+        self.item_names().any(|item| item == name)
+    }
+
+    pub fn item_names(&self) -> impl Iterator<Item = &str> {
+        self.program.items.iter().filter_map(item_name)
+    }
+
+    /// 查找本模块中定义的顶层条目
+    pub fn find_item(&self, name: &str) -> Option<&Item> {
+        self.program
+            .items
+            .iter()
+            .find(|item| item_name(item) == Some(name))
+    }
+
+    /// 通过 `import` 引入的模块命名空间
+    pub fn imported_namespace(&self, name: &str) -> Option<&ModulePath> {
+        self.imports
+            .iter()
+            .find(|import| import.item.is_none() && import.name == name)
+            .map(|import| &import.module)
+    }
+}
+
+/// 公开条目的定义位置：所在模块和条目在该模块中的名字
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportTarget {
+    pub module: ModulePath,
+    pub item: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Crate {
+    pub root: PathBuf, // 项目根目录
+    pub modules: BTreeMap<ModulePath, Module>,
+    pub sources: SourceMap, // 读入的模块文件和 `include!` 系列宏读入的文件
+}
+
+impl Crate {
+    /// 由单个已解析的程序构造只含根模块的 crate
+    pub fn from_program(program: Program) -> Self {
+        let mut modules = BTreeMap::new();
+        modules.insert(
+            Vec::new(),
+            Module {
+                path: Vec::new(),
+                file: PathBuf::new(),
+                package: None,
+                program,
+                imports: Vec::new(),
+            },
+        );
+        Self {
+            root: PathBuf::new(),
+            modules,
+            sources: SourceMap::new(),
+        }
+    }
+
+    pub fn root_module(&self) -> &Module {
+        &self.modules[&Vec::new()]
+    }
+
+    pub fn module(&self, path: &[String]) -> Option<&Module> {
+        self.modules.get(path)
+    }
+
+    /// 交给 MIR 降级和解释器的程序：只有根模块时就是根模块的程序，
+    /// 否则是所有模块合并成的程序，其他模块的条目按模块路径命名
+    pub fn merged_program(&self) -> Cow<'_, Program> {
+        match self.modules.len() {
+            1 => Cow::Borrowed(&self.root_module().program),
+            _ => Cow::Owned(merge::merge(self)),
+        }
+    }
+
+    /// 模块的公开接口：`pub` 条目加上 `export` 列表（包括重新导出的条目）
+    ///
+    /// 无法解析的导出项会被忽略，由 sema 负责报告。
+    pub fn public_items(&self, path: &[String]) -> BTreeMap<String, ExportTarget> {
+        let mut visiting = BTreeSet::new();
+        self.collect_public_items(path, &mut visiting)
+    }
+
+    fn collect_public_items(
+        &self,
+        path: &[String],
+        visiting: &mut BTreeSet<ModulePath>,
+    ) -> BTreeMap<String, ExportTarget> {
+        let mut items = BTreeMap::new();
+        let Some(module) = self.module(path) else {
+            return items;
+        };
+        // 重新导出可能形成环，环上再次遇到的模块不再贡献条目
+        if !visiting.insert(path.to_vec()) {
+            return items;
+        }
+
+        for item in &module.program.items {
+            if let (Some(name), Some(Visibility::Public)) = (item_name(item), item_visibility(item))
+            {
+                items.insert(name.to_string(), local_target(path, name));
+            }
+        }
+
+        for item in &module.program.items {
+            let Item::Export(export) = item else {
+                continue;
+            };
+            for entry in &export.items {
+                match entry.path.as_slice() {
+                    [name] if module.defines(name) => {
+                        items.insert(name.clone(), local_target(path, name));
+                    }
+                    [namespace, name] => {
+                        let Some(target) = module.imported_namespace(namespace) else {
+                            continue;
+                        };
+                        if let Some(found) =
+                            self.collect_public_items(target, visiting).remove(name)
+                        {
+                            items.insert(name.clone(), found);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        visiting.remove(path);
+        items
+    }
+}
+
+fn local_target(path: &[String], name: &str) -> ExportTarget {
+    ExportTarget {
+        module: path.to_vec(),
+        item: name.to_string(),
+    }
+}
+
+// 挂载的依赖包
+struct Package {
+    entry: PathBuf,
+    root: PathBuf, // 入口所在的目录，包内的模块相对于它查找
+}
+
+pub struct ModuleLoader {
+    root: PathBuf,
+    packages: BTreeMap<String, Package>,
+    modules: BTreeMap<ModulePath, Module>,
+    visited: BTreeSet<ModulePath>, // 已经尝试加载过的模块（包括加载失败的）
+    stack: Vec<ModulePath>,        // 正在加载的模块链，用于检测循环导入
+    sources: SourceMap,
+    limits: CompileLimits,
+    errors: Vec<Diagnostic>,
+}
+
+impl ModuleLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            packages: BTreeMap::new(),
+            modules: BTreeMap::new(),
+            visited: BTreeSet::new(),
+            stack: Vec::new(),
+            sources: SourceMap::new(),
+            limits: CompileLimits::default(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// 限制每个源文件的大小、记号数和语法树节点数
+    pub fn with_limits(mut self, limits: CompileLimits) -> Self {
+        self.sources.set_max_file_size(limits.max_file_size);
+        self.limits = limits;
+        self
+    }
+
+    /// 以入口文件所在目录为项目根目录加载整个 crate
+    pub fn load_entry(entry: &Path) -> Result<Crate, Vec<Diagnostic>> {
+        let root = entry.parent().map(Path::to_path_buf).unwrap_or_default();
+        Self::new(root).load(entry)
+    }
+
+    /// 挂载依赖包，`import name::...` 在包入口所在的目录中查找
+    pub fn with_package(mut self, name: impl Into<String>, entry: impl Into<PathBuf>) -> Self {
+        let entry = entry.into();
+        let root = entry.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.packages.insert(name.into(), Package { entry, root });
+        self
+    }
+
+    /// 加载目录下的所有源文件，入口是目录中的 `main.ctx`
+    pub fn load_dir(root: &Path) -> Result<Crate, Vec<Diagnostic>> {
+        Self::new(root).load_all()
+    }
+
+    /// 加载根目录下的所有源文件，入口是根目录中的 `main.ctx`
+    pub fn load_all(self) -> Result<Crate, Vec<Diagnostic>> {
+        let root = self.root.clone();
+        let entry = root.join(ENTRY_FILE);
+        if !entry.is_file() {
+            return Err(vec![Diagnostic::error(
+                format!("cannot find `{}` in `{}`", ENTRY_FILE, root.display()),
+                Span::new(0, 0, 1, 1),
+            )
+            .with_code(ErrorCode::E0800)
+            .with_help(format!(
+                "the entry module of a project is its `{}`",
+                ENTRY_FILE
+            ))]);
+        }
+        let mut files = Vec::new();
+        discover_sources(&root, &mut files).map_err(|error| {
+            vec![Diagnostic::error(
+                format!("cannot read directory `{}`: {}", root.display(), error),
+                Span::new(0, 0, 1, 1),
+            )
+            .with_code(ErrorCode::E0800)]
+        })?;
+        self.load_files(&entry, &files)
+    }
+
+    pub fn load(self, entry: &Path) -> Result<Crate, Vec<Diagnostic>> {
+        self.load_files(entry, &[])
+    }
+
+    /// 加载入口文件和其他一起编译的文件，它们必须位于项目根目录之下
+    pub fn load_files(mut self, entry: &Path, files: &[PathBuf]) -> Result<Crate, Vec<Diagnostic>> {
+        self.load_module(Vec::new(), entry.to_path_buf());
+        for file in files {
+            if same_file(file, entry) {
+                continue;
+            }
+            match self.module_path(file) {
+                Some(path) if self.packages.contains_key(&path[0]) => self.errors.push(
+                    Diagnostic::error(
+                        format!(
+                            "module `{}` conflicts with dependency `{}`",
+                            path.join("::"),
+                            path[0]
+                        ),
+                        Span::new(0, 0, 1, 1),
+                    )
+                    .with_code(ErrorCode::E0802)
+                    .with_file(file.display().to_string()),
+                ),
+                Some(path) if !self.visited.contains(&path) => self.load_module(path, file.clone()),
+                Some(_) => {}
+                None => self.errors.push(
+                    Diagnostic::error(
+                        format!(
+                            "`{}` is not a module under the project root `{}`",
+                            file.display(),
+                            self.root.display()
+                        ),
+                        Span::new(0, 0, 1, 1),
+                    )
+                    .with_code(ErrorCode::E0802)
+                    .with_file(file.display().to_string()),
+                ),
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(Crate {
+                root: self.root,
+                modules: self.modules,
+                sources: self.sources,
+            })
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn load_module(&mut self, path: ModulePath, file: PathBuf) {
+        self.visited.insert(path.clone());
+        let package = path
+            .first()
+            .filter(|name| self.packages.contains_key(*name))
+            .cloned();
+        let Some(program) = self.parse_file(&file, package.as_deref()) else {
+            return;
+        };
+
+        self.stack.push(path.clone());
+        let mut imports = Vec::new();
+
+        for item in &program.items {
+            let Item::Import(import) = item else {
+                continue;
+            };
+
+            let Some((module, item_name)) = self.locate(package.as_deref(), &import.path) else {
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("cannot find module `{}`", import.path.join("::")),
+                        import.span,
+                    )
+                    .with_code(ErrorCode::E0432)
+                    .with_file(file.display().to_string())
+                    .with_help(format!(
+                        "expected a file at `{}`",
+                        self.module_file(&import.path).display()
+                    )),
+                );
+                continue;
+            };
+
+            if let Some(pos) = self.stack.iter().position(|p| *p == module) {
+                let cycle: Vec<String> = self.stack[pos..]
+                    .iter()
+                    .chain(std::iter::once(&module))
+                    .map(|p| format!("`{}`", display_path(p)))
+                    .collect();
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("import cycle detected: {}", cycle.join(" → ")),
+                        import.span,
+                    )
+                    .with_code(ErrorCode::E0801)
+                    .with_file(file.display().to_string()),
+                );
+                continue;
+            }
+
+            if !self.visited.contains(&module) {
+                let module_file = self.module_file(&module);
+                self.load_module(module.clone(), module_file);
+            }
+
+            let name = import
+                .alias
+                .clone()
+                .unwrap_or_else(|| import.path.last().cloned().unwrap_or_default());
+            imports.push(ResolvedImport {
+                name,
+                module,
+                item: item_name,
+                span: import.span,
+            });
+        }
+
+        self.stack.pop();
+        self.modules.insert(
+            path.clone(),
+            Module {
+                path,
+                file,
+                package,
+                program,
+                imports,
+            },
+        );
+    }
+
+    // 确定导入路径指向的模块和条目；依赖包中的导入先在包内查找，再查找其他依赖包
+    fn locate(
+        &self,
+        package: Option<&str>,
+        import_path: &[String],
+    ) -> Option<(ModulePath, Option<String>)> {
+        let Some(package) = package else {
+            return self.locate_in(import_path, 1);
+        };
+        let mut path = vec![package.to_string()];
+        path.extend_from_slice(import_path);
+        // 不能从包的入口模块导入条目，和项目的根模块一样
+        self.locate_in(&path, 2).or_else(|| {
+            let dependency = import_path.first()?;
+            self.packages
+                .contains_key(dependency)
+                .then(|| self.locate_in(import_path, 1))?
+        })
+    }
+
+    // 先把整个路径当作模块，否则最后一段是条目，前面的部分至少有 `min_len` 段
+    fn locate_in(&self, path: &[String], min_len: usize) -> Option<(ModulePath, Option<String>)> {
+        if !path.is_empty() && self.module_file(path).is_file() {
+            return Some((path.to_vec(), None));
+        }
+
+        let (item, module) = path.split_last()?;
+        if module.len() >= min_len && self.module_file(module).is_file() {
+            return Some((module.to_vec(), Some(item.clone())));
+        }
+
+        None
+    }
+
+    // 根目录下的文件对应的模块路径：`a/b.ctx` → `a::b`
+    fn module_path(&self, file: &Path) -> Option<ModulePath> {
+        let canonical = |path: &Path| path.canonicalize().ok();
+        let relative = match file.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => canonical(file)?
+                .strip_prefix(canonical(&self.root)?)
+                .ok()?
+                .to_path_buf(),
+        };
+        if relative.extension()? != SOURCE_EXTENSION {
+            return None;
+        }
+        let path: Option<ModulePath> = relative
+            .with_extension("")
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => name.to_str().map(str::to_string),
+                _ => None,
+            })
+            .collect();
+        path.filter(|path| !path.is_empty())
+    }
+
+    fn module_file(&self, path: &[String]) -> PathBuf {
+        if let Some((first, rest)) = path.split_first() {
+            if let Some(package) = self.packages.get(first) {
+                if rest.is_empty() {
+                    return package.entry.clone();
+                }
+                return source_file(&package.root, rest);
+            }
+        }
+        source_file(&self.root, path)
+    }
+
+    fn parse_file(&mut self, file: &Path, package: Option<&str>) -> Option<Program> {
+        let display = file.display().to_string();
+        // 先按文件大小检查，太大的文件不读入
+        let size = fs::metadata(file).map_or(0, |metadata| metadata.len());
+        if let Err(error) = self.limits.check_file_size(size) {
+            self.errors.push(error.with_file(display));
+            return None;
+        }
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(err) => {
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("cannot read `{}`: {}", display, err),
+                        Span::new(0, 0, 1, 1),
+                    )
+                    .with_code(ErrorCode::E0800)
+                    .with_file(display),
+                );
+                return None;
+            }
+        };
+
+        let id = self
+            .sources
+            .add(file.to_path_buf(), source.clone().into_bytes());
+        let parsed = tokenize_with(&source, &self.limits).and_then(|tokens| {
+            let operators = match features::scan(&tokens).contains(&Feature::CustomOperators) {
+                true => self.imported_operators(&tokens, package),
+                false => Vec::new(),
+            };
+            parse_source_file(tokens, &mut self.sources, id, &self.limits, operators)
+        });
+        match parsed {
+            Ok(program) => Some(program),
+            Err(errors) => {
+                // 被拼接的文件中的错误已经指向那个文件
+                self.errors
+                    .extend(self.sources.locate(errors).into_iter().map(
+                        |error| match error.file {
+                            Some(_) => error,
+                            None => error.with_file(display.clone()),
+                        },
+                    ));
+                None
+            }
+        }
+    }
+}
+
+impl ModuleLoader {
+    // `tokens` 中导入的模块公开的自定义运算符，实现它们的函数以导入的模块名开头。
+    // 找不到或读不了的模块跳过，加载模块时再报告
+    fn imported_operators(&self, tokens: &[Token], package: Option<&str>) -> Vec<CustomOperator> {
+        let mut operators = Vec::new();
+        for (path, alias) in imported_modules(tokens) {
+            let Some((module, None)) = self.locate(package, &path) else {
+                continue;
+            };
+            let file = self.module_file(&module);
+            let size = fs::metadata(&file).map_or(0, |metadata| metadata.len());
+            if self.limits.check_file_size(size).is_err() {
+                continue;
+            }
+            let Some(tokens) = fs::read_to_string(&file)
+                .ok()
+                .and_then(|source| tokenize_with(&source, &self.limits).ok())
+            else {
+                continue;
+            };
+            let name = alias.unwrap_or_else(|| path.last().cloned().unwrap_or_default());
+            for mut operator in parser::declared_operators(tokens) {
+                if operator.public {
+                    operator.function.insert(0, name.clone());
+                    operators.push(operator);
+                }
+            }
+        }
+        operators
+    }
+}
+
+// 记号中的 `import a::b as c;`：模块路径和别名
+fn imported_modules(tokens: &[Token]) -> Vec<(ModulePath, Option<String>)> {
+    let mut imports = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        i += 1;
+        if tokens[i - 1].kind != TokenKind::Import {
+            continue;
+        }
+        let mut path = Vec::new();
+        while let Some(TokenKind::Ident(name)) = tokens.get(i).map(|token| &token.kind) {
+            path.push(name.to_string());
+            match tokens.get(i + 1).map(|token| &token.kind) {
+                Some(TokenKind::DoubleColon) => i += 2,
+                _ => {
+                    i += 1;
+                    break;
+                }
+            }
+        }
+        let alias = match (tokens.get(i), tokens.get(i + 1)) {
+            (Some(as_), Some(alias)) if as_.kind == TokenKind::As => match &alias.kind {
+                TokenKind::Ident(alias) => Some(alias.to_string()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if !path.is_empty() {
+            imports.push((path, alias));
+        }
+    }
+    imports
+}
+
+fn source_file(root: &Path, path: &[String]) -> PathBuf {
+    let mut file = root.to_path_buf();
+    for segment in path {
+        file.push(segment);
+    }
+    file.set_extension(SOURCE_EXTENSION);
+    file
+}
+
+/// 递归收集目录下的源文件，按路径排序
+pub(crate) fn discover_sources(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            discover_sources(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// 对一段源码做词法分析
+pub fn tokenize_source(source: &str) -> Result<Vec<Token>, Vec<Diagnostic>> {
+    tokenize_with(source, &CompileLimits::default())
+}
+
+/// 对一段源码做词法分析，源码的大小和记号数不能超出限制
+pub fn tokenize_with(source: &str, limits: &CompileLimits) -> Result<Vec<Token>, Vec<Diagnostic>> {
+    limits
+        .check_file_size(source.len() as u64)
+        .map_err(|error| vec![*error])?;
+    let lexer = Lexer::new(source);
+    let lexer = match limits.max_tokens {
+        Some(max) => lexer.with_max_tokens(max),
+        None => lexer,
+    };
+    timing::time("lexing", || lexer.tokenize())
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect())
+}
+
+/// 对一段源码做词法分析和语法分析，并展开其中的宏；没有所在的文件，不能使用 `include!` 系列宏
+pub fn parse_source(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    parse_with(source, None, &CompileLimits::default())
+}
+
+/// 同 `parse_source`，源码不能超出资源限制
+pub fn parse_source_with(source: &str, limits: &CompileLimits) -> Result<Program, Vec<Diagnostic>> {
+    parse_with(source, None, limits)
+}
+
+/// 对源文件表中的文件 `file` 的记号做语法分析并展开宏，
+/// `include!` 系列宏相对于它读取文件，读入的文件登记到 `sources` 中；
+/// `operators` 是导入的模块中声明的自定义运算符
+pub fn parse_source_file(
+    tokens: Vec<Token>,
+    sources: &mut SourceMap,
+    file: FileId,
+    limits: &CompileLimits,
+    operators: Vec<CustomOperator>,
+) -> Result<Program, Vec<Diagnostic>> {
+    parse_tokens(tokens, Some((sources, file)), limits, operators)
+}
+
+fn parse_with(
+    source: &str,
+    file: Option<(&mut SourceMap, FileId)>,
+    limits: &CompileLimits,
+) -> Result<Program, Vec<Diagnostic>> {
+    let tokens = tokenize_with(source, limits)?;
+    parse_tokens(tokens, file, limits, Vec::new())
+}
+
+fn parse_tokens(
+    tokens: Vec<Token>,
+    file: Option<(&mut SourceMap, FileId)>,
+    limits: &CompileLimits,
+    operators: Vec<CustomOperator>,
+) -> Result<Program, Vec<Diagnostic>> {
+    let parser = Parser::new(tokens.clone()).with_operators(operators);
+    let parser = match limits.max_ast_nodes {
+        Some(max) => parser.with_max_nodes(max),
+        None => parser,
+    };
+    let mut parser = match limits.max_nesting_depth {
+        Some(max) => parser.with_max_depth(max),
+        None => parser,
+    };
+    let program = timing::time("parsing", || parser.parse())
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    timing::time("macro expansion", || {
+        let expanded = match file {
+            Some((sources, file)) => macros::expand_file(program, &tokens, sources, file),
+            None => macros::expand(program, &tokens),
+        };
+        expanded.and_then(derive::expand)
+    })
+}
+
+/// 只做词法分析和语法分析，宏调用保持原样（格式化器用来检查语法）。
+/// 看不到导入的模块，没有声明的自定义运算符按最低的优先级解析
+pub fn parse_syntax(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    let tokens = tokenize_source(source)?;
+    Parser::new(tokens)
+        .with_unknown_operators()
+        .parse()
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect())
+}
+
+pub fn display_path(path: &[String]) -> String {
+    if path.is_empty() {
+        "crate".to_string()
+    } else {
+        path.join("::")
+    }
+}
+
+/// 顶层条目的可见性，import/export 和宏没有可见性
+pub fn item_visibility(item: &Item) -> Option<Visibility> {
+    match item {
+        Item::Function(func) => Some(func.visibility.clone()),
+        Item::Struct(struct_def) => Some(struct_def.visibility.clone()),
+        Item::Enum(enum_def) => Some(enum_def.visibility.clone()),
+        Item::Const(const_def) => Some(const_def.visibility.clone()),
+        Item::Static(static_def) => Some(static_def.visibility.clone()),
+        Item::Import(_) | Item::Export(_) | Item::MacroRules(_) | Item::MacroCall(_) => None,
+    }
+}
+
+/// 顶层条目的名字，import/export 和宏没有名字
+pub fn item_name(item: &Item) -> Option<&str> {
+    match item {
+        Item::Function(func) => Some(&func.name),
+        Item::Struct(struct_def) => Some(&struct_def.name),
+        Item::Enum(enum_def) => Some(&enum_def.name),
+        Item::Const(const_def) => Some(&const_def.name),
+        Item::Static(static_def) => Some(&static_def.name),
+        Item::Import(_) | Item::Export(_) | Item::MacroRules(_) | Item::MacroCall(_) => None,
+    }
+}
@@ -0,0 +1,438 @@
+// 把 crate 的所有模块合并为一个程序，交给 MIR 降级和解释器
+// 根模块的条目保留原名，其他模块的条目加上模块路径：`math::vector` 中的 `add` 为
+// `math::vector::add`。每个模块中对条目的引用按它自己的命名空间改写为合并后的名字：
+// 本模块的条目、导入的条目（包括重新导出的条目）和经过导入的模块的路径 `vector::add`；
+// 局部变量和类型参数遮蔽同名的条目。名字解析的错误已经由 sema 报告，解析不了的名字保持原样
+
+use super::{item_name, Crate, Module, ModulePath};
+use crate::ast::*;
+use std::collections::HashMap;
+
+// 合并后条目的名字
+fn qualified_name(module: &[String], name: &str) -> String {
+    if module.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", module.join("::"), name)
+    }
+}
+
+/// 所有模块的条目合并为一个程序，文件属性取自根模块
+pub fn merge(krate: &Crate) -> Program {
+    let root = krate.root_module();
+    let mut items = Vec::new();
+    for module in krate.modules.values() {
+        let mut names = Names::new(krate, module);
+        items.extend(
+            module
+                .program
+                .items
+                .iter()
+                .filter(|item| item_name(item).is_some())
+                .map(|item| names.item(item.clone())),
+        );
+    }
+    Program {
+        attributes: root.program.attributes.clone(),
+        items,
+        span: root.program.span,
+    }
+}
+
+// 一个模块中的名字
+struct Names<'a> {
+    krate: &'a Crate,
+    module: &'a ModulePath,
+    items: HashMap<String, String>, // 可见的条目名 -> 合并后的名字
+    namespaces: HashMap<&'a str, &'a ModulePath>, // 导入的模块
+    locals: Vec<String>,            // 作用域中的局部变量和类型参数
+}
+
+impl<'a> Names<'a> {
+    fn new(krate: &'a Crate, module: &'a Module) -> Self {
+        let mut items: HashMap<String, String> = module
+            .item_names()
+            .map(|name| (name.to_string(), qualified_name(&module.path, name)))
+            .collect();
+        let mut namespaces = HashMap::new();
+        for import in &module.imports {
+            match &import.item {
+                None => {
+                    namespaces.insert(import.name.as_str(), &import.module);
+                }
+                Some(item) => {
+                    if let Some(target) = krate.public_items(&import.module).remove(item) {
+                        let name = qualified_name(&target.module, &target.item);
+                        items.insert(import.name.clone(), name);
+                    }
+                }
+            }
+        }
+        Self {
+            krate,
+            module: &module.path,
+            items,
+            namespaces,
+            locals: Vec::new(),
+        }
+    }
+
+    // 没有被局部变量遮蔽的条目的合并后的名字
+    fn resolve(&self, name: &str) -> Option<String> {
+        if self.locals.iter().any(|local| local == name) {
+            return None;
+        }
+        self.items.get(name).cloned()
+    }
+
+    fn rename(&self, name: &mut String) {
+        if let Some(resolved) = self.resolve(name) {
+            *name = resolved;
+        }
+    }
+
+    // 导入的模块 `namespace` 中的公开条目
+    fn resolve_in(&self, namespace: &str, name: &str) -> Option<String> {
+        let module = self.namespaces.get(namespace)?;
+        let target = self.krate.public_items(module).remove(name)?;
+        Some(qualified_name(&target.module, &target.item))
+    }
+
+    // 在新的作用域中执行 `f`，之后移除其中声明的局部变量
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let depth = self.locals.len();
+        let result = f(self);
+        self.locals.truncate(depth);
+        result
+    }
+
+    fn bind_generics(&mut self, generics: &Option<Generics>) {
+        if let Some(generics) = generics {
+            self.locals.extend(generics.param_names());
+        }
+    }
+
+    fn item(&mut self, item: Item) -> Item {
+        let module = self.module;
+        match item {
+            Item::Function(mut func) => self.scoped(|names| {
+                func.name = qualified_name(module, &func.name);
+                names.bind_generics(&func.generics);
+                for param in &mut func.params {
+                    names.ty(&mut param.ty);
+                }
+                if let Some(ty) = &mut func.return_type {
+                    names.ty(ty);
+                }
+                for param in &mut func.params {
+                    names.pattern(&mut param.pattern);
+                }
+                names.contracts(&mut func.contracts);
+                names.block(&mut func.body);
+                Item::Function(func)
+            }),
+            Item::Struct(mut struct_def) => self.scoped(|names| {
+                struct_def.name = qualified_name(module, &struct_def.name);
+                names.bind_generics(&struct_def.generics);
+                for field in &mut struct_def.fields {
+                    names.ty(&mut field.ty);
+                }
+                names.contracts(&mut struct_def.invariants);
+                Item::Struct(struct_def)
+            }),
+            Item::Enum(mut enum_def) => self.scoped(|names| {
+                enum_def.name = qualified_name(module, &enum_def.name);
+                names.bind_generics(&enum_def.generics);
+                for variant in &mut enum_def.variants {
+                    for ty in variant.fields.iter_mut().flatten() {
+                        names.ty(ty);
+                    }
+                }
+                Item::Enum(enum_def)
+            }),
+            Item::Const(mut const_def) => {
+                const_def.name = qualified_name(module, &const_def.name);
+                self.ty(&mut const_def.ty);
+                self.expr(&mut const_def.value);
+                Item::Const(const_def)
+            }
+            Item::Static(mut static_def) => {
+                static_def.name = qualified_name(module, &static_def.name);
+                self.ty(&mut static_def.ty);
+                self.expr(&mut static_def.value);
+                Item::Static(static_def)
+            }
+            item => item,
+        }
+    }
+
+    fn contracts(&mut self, contracts: &mut [Contract]) {
+        for contract in contracts {
+            self.expr(&mut contract.condition);
+        }
+    }
+
+    fn ty(&self, ty: &mut Type) {
+        match ty {
+            Type::Named(name) => self.rename(name),
+            Type::Generic(name, args) => {
+                self.rename(name);
+                for arg in args {
+                    self.ty(arg);
+                }
+            }
+            Type::Array(inner, _)
+            | Type::Slice(inner)
+            | Type::Pointer(inner, _)
+            | Type::Reference(inner, _) => self.ty(inner),
+            Type::Tuple(types) => {
+                for ty in types {
+                    self.ty(ty);
+                }
+            }
+            Type::Function(params, ret, _) => {
+                for param in params {
+                    self.ty(param);
+                }
+                self.ty(ret);
+            }
+            // 之后的阶段使用 sema 推断出的类型
+            Type::TypeOf(type_of) => {
+                if let Some(resolved) = type_of.resolved() {
+                    let mut resolved = resolved.clone();
+                    self.ty(&mut resolved);
+                    *ty = resolved;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 模式中的结构体名按条目改写，名字成为局部变量
+    fn pattern(&mut self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Ident(name) => self.locals.push(name.clone()),
+            Pattern::Struct(name, fields) => {
+                self.rename(name);
+                for (_, field) in fields {
+                    self.pattern(field);
+                }
+            }
+            Pattern::TupleStruct(name, patterns) => {
+                self.rename(name);
+                for pattern in patterns {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+                for pattern in patterns {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+        }
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        self.scoped(|names| {
+            for statement in &mut block.statements {
+                names.statement(statement);
+            }
+        })
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Let(let_stmt) => {
+                if let Some(ty) = &mut let_stmt.ty {
+                    self.ty(ty);
+                }
+                if let Some(init) = &mut let_stmt.init {
+                    self.expr(init);
+                }
+                if let Some(else_block) = &mut let_stmt.else_block {
+                    self.block(else_block);
+                }
+                self.pattern(&mut let_stmt.pattern);
+            }
+            Statement::Expr(expr_stmt) => self.expr(&mut expr_stmt.expr),
+            Statement::Return(return_stmt) => {
+                if let Some(expr) = &mut return_stmt.expr {
+                    self.expr(expr);
+                }
+            }
+            Statement::If(if_stmt) => self.if_stmt(if_stmt),
+            Statement::While(while_stmt) => {
+                self.expr(&mut while_stmt.cond);
+                self.contracts(&mut while_stmt.invariants);
+                self.block(&mut while_stmt.body);
+            }
+            Statement::For(for_stmt) => {
+                self.expr(&mut for_stmt.iterable);
+                self.scoped(|names| {
+                    names.pattern(&mut for_stmt.pattern);
+                    names.contracts(&mut for_stmt.invariants);
+                    names.block(&mut for_stmt.body);
+                });
+            }
+            Statement::Match(match_stmt) => {
+                self.expr(&mut match_stmt.expr);
+                self.arms(&mut match_stmt.arms);
+            }
+            Statement::Break(break_stmt) => {
+                if let Some(expr) = &mut break_stmt.expr {
+                    self.expr(expr);
+                }
+            }
+            Statement::Continue(_) => {}
+            Statement::Block(block) => self.block(block),
+        }
+    }
+
+    fn if_stmt(&mut self, if_stmt: &mut IfStmt) {
+        self.expr(&mut if_stmt.cond);
+        self.block(&mut if_stmt.then_block);
+        if let Some(else_branch) = &mut if_stmt.else_block {
+            self.else_branch(else_branch);
+        }
+    }
+
+    fn else_branch(&mut self, else_branch: &mut ElseBranch) {
+        match else_branch {
+            ElseBranch::Block(block) => self.block(block),
+            ElseBranch::If(if_stmt) => self.if_stmt(if_stmt),
+        }
+    }
+
+    fn arms(&mut self, arms: &mut [MatchArm]) {
+        for arm in arms {
+            self.scoped(|names| {
+                names.pattern(&mut arm.pattern);
+                if let Some(guard) = &mut arm.guard {
+                    names.expr(guard);
+                }
+                names.expr(&mut arm.body);
+            });
+        }
+    }
+
+    fn exprs(&mut self, exprs: &mut [Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Ident(name, _) => self.rename(name),
+            Expr::Path(segments, span) => match segments.as_mut_slice() {
+                [namespace, name] if self.namespaces.contains_key(namespace.as_str()) => {
+                    if let Some(resolved) = self.resolve_in(namespace, name) {
+                        *expr = Expr::Ident(resolved, *span);
+                    }
+                }
+                [namespace, enum_name, variant] => {
+                    if let Some(resolved) = self.resolve_in(namespace, enum_name) {
+                        *segments = vec![resolved, variant.clone()];
+                    }
+                }
+                [enum_name, _] => self.rename(enum_name),
+                _ => {}
+            },
+            Expr::Turbofish(inner, types, _) => {
+                self.expr(inner);
+                for ty in types {
+                    self.ty(ty);
+                }
+            }
+            Expr::Binary(_, lhs, rhs, _, _)
+            | Expr::IndexAccess(lhs, rhs, _)
+            | Expr::Assign(lhs, rhs, _)
+            | Expr::CompoundAssign(_, lhs, rhs, _, _) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Range(start, end, _, _) => {
+                for bound in start.iter_mut().chain(end) {
+                    self.expr(bound);
+                }
+            }
+            Expr::Unary(_, inner, _, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _) => self.expr(inner),
+            Expr::Call(callee, args, _, _) => {
+                self.expr(callee);
+                self.exprs(args);
+            }
+            // `value.method()` 调用名为 `method` 的函数
+            Expr::MethodCall(receiver, method, args, _, _) => {
+                self.expr(receiver);
+                self.rename(method);
+                self.exprs(args);
+            }
+            Expr::StructLit(name, fields, _) => {
+                self.rename(name);
+                for (_, value) in fields {
+                    self.expr(value);
+                }
+            }
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => self.exprs(elements),
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.block(block)
+            }
+            Expr::If(cond, then_block, else_branch, _) => {
+                self.expr(cond);
+                self.block(then_block);
+                if let Some(else_branch) = else_branch {
+                    self.else_branch(else_branch);
+                }
+            }
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                self.arms(arms);
+            }
+            Expr::While(_, cond, body, _) => {
+                self.expr(cond);
+                self.block(body);
+            }
+            Expr::For(_, pattern, iterable, body, _) => {
+                self.expr(iterable);
+                self.scoped(|names| {
+                    names.pattern(pattern);
+                    names.block(body);
+                });
+            }
+            Expr::Break(_, value, _) | Expr::Return(value, _) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Expr::Closure(params, ret, body, _) => self.scoped(|names| {
+                for param in params.iter_mut() {
+                    names.ty(&mut param.ty);
+                    names.pattern(&mut param.pattern);
+                }
+                if let Some(ret) = ret {
+                    names.ty(ret);
+                }
+                names.expr(body);
+            }),
+            Expr::Cast(inner, ty, _) => {
+                self.expr(inner);
+                self.ty(ty);
+            }
+            Expr::InlineAsm(asm, _) => {
+                for operand in &mut asm.operands {
+                    if let Some(expr) = &mut operand.expr {
+                        self.expr(expr);
+                    }
+                }
+            }
+            Expr::Literal(_, _) | Expr::Continue(_, _) | Expr::MacroCall(_) => {}
+        }
+    }
+}
@@ -2,29 +2,45 @@
 // 设计原则：
 // 1. 递归下降处理语句和声明
 // 2. Pratt parsing 处理表达式优先级
-// 3. 错误恢复：遇到错误时跳到下一个同步点
+// 3. 错误恢复：条目出错时丢弃整个条目，跳到下一个条目的开头继续，
+//    被丢弃的部分不再报告后续错误；缺少右定界符时指向没有闭合的左定界符，
+//    当作右定界符已经插入继续解析
 // 4. 所有节点都包含 span 信息
+//
+// 各个 parse_* 方法实现的产生式列在 grammar.rs 中，修改接受的语法时同时修改那里的规则
 
 use crate::ast::*;
+use crate::diagnostic::ErrorCode;
+use crate::features::{Edition, Feature};
+use crate::macros::Fragment;
 use crate::span::Span;
-use crate::token::{Token, TokenKind};
+use crate::token::{Token, TokenKind, TokenTree};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct ParseError {
+    pub code: ErrorCode,
     pub message: String,
     pub span: Span,
     pub help: Option<String>,
 }
 
 impl ParseError {
+    /// 默认是 E0100（意外的记号），其他错误用 `with_code` 指定
     pub fn new(message: String, span: Span) -> Self {
         Self {
+            code: ErrorCode::E0100,
             message,
             span,
             help: None,
         }
     }
 
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = code;
+        self
+    }
+
     pub fn with_help(mut self, help: String) -> Self {
         self.help = Some(help);
         self
@@ -45,12 +61,63 @@ impl std::fmt::Display for ParseError {
     }
 }
 
+// 条目的前缀：属性、运算符声明和可见性，以及条目的起点（有 `pub` 时是 `pub`）
+struct ItemHeader {
+    attributes: Vec<Attribute>,
+    operator: Option<OperatorDecl>,
+    visibility: Visibility,
+    asynchronous: Option<Span>, // `async fn` 的 `async`
+    pure: bool,                 // `pure fn`
+    start: Span,
+}
+
+/// `#![feature(async_await)]` 开启后成为关键字的词
+const ASYNC_KEYWORDS: [&str; 2] = ["async", "await"];
+
+/// 表达式中可以使用的自定义运算符：文件中声明的，以及导入的模块中公开的
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomOperator {
+    pub symbol: Vec<TokenKind>, // 组成运算符的记号，在源码中紧挨着
+    pub precedence: u8,
+    pub associativity: Associativity,
+    pub function: Vec<String>, // 实现运算符的函数，导入的运算符以模块名开头
+    pub public: bool,
+}
+
+impl CustomOperator {
+    /// 运算符的写法，如 `<*>`
+    pub fn spelling(&self) -> String {
+        self.symbol.iter().map(TokenKind::to_string).collect()
+    }
+
+    fn binding_power(&self) -> (u8, u8) {
+        let level = self.precedence;
+        match self.associativity {
+            Associativity::Left => (level, level + 1),
+            Associativity::Right => (level + 1, level),
+        }
+    }
+}
+
+/// 自定义运算符的优先级范围：从 `||` 到 `*`，见 `binding_power`
+pub const OPERATOR_PRECEDENCE: std::ops::RangeInclusive<u8> = 20..=120;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     errors: Vec<ParseError>,
-    panic_mode: bool,
-    loop_depth: usize, // 跟踪循环嵌套深度，用于break/continue验证
+    delimiters: Vec<(TokenKind, Span)>, // 还没有闭合的左定界符
+    recovered_at: Option<usize>,        // 上一次补上右定界符的位置
+    no_struct_literal: bool,            // if/while/for/match 的条件中 `x {` 的 `{` 属于代码块
+    nodes: usize,                       // 已经建立的语法树节点数
+    max_nodes: usize,                   // 节点数的上限，超出时报告 E0803 并停下
+    depth: usize,                       // 正在解析的表达式的嵌套层数
+    max_depth: usize,                   // 嵌套层数的上限，超出时报告 E0803 并停下
+    features: Vec<Feature>,             // 文件开头的 `#![feature(...)]` 开启的特性
+    edition: Option<Edition>,           // 文件开头的 `#![edition(...)]` 选择的版本
+    operators: Vec<CustomOperator>,     // 可以使用的自定义运算符，文件中声明的在前
+    unknown_operators: bool,            // 把没有声明的运算符也当作自定义运算符，只检查语法时使用
+    slice_depth: Option<usize>,         // 正在解析的索引的定界符层数，这一层的 `..]` 省略终点
 }
 
 impl Parser {
@@ -59,31 +126,77 @@ impl Parser {
             tokens,
             current: 0,
             errors: Vec::new(),
-            panic_mode: false,
-            loop_depth: 0,
-        }
+            delimiters: Vec::new(),
+            recovered_at: None,
+            no_struct_literal: false,
+            nodes: 0,
+            max_nodes: usize::MAX,
+            depth: 0,
+            max_depth: usize::MAX,
+            features: Vec::new(),
+            edition: None,
+            operators: Vec::new(),
+            unknown_operators: false,
+            slice_depth: None,
+        }
+    }
+
+    /// 可以使用导入的模块中声明的自定义运算符，`function` 以模块名开头
+    pub fn with_operators(mut self, operators: Vec<CustomOperator>) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    /// 开启了 `custom_operators` 的文件中，紧挨着的运算符记号不是声明过的运算符时
+    /// 也当作一个自定义运算符。格式化器只检查语法，看不到导入的模块中声明的运算符
+    pub fn with_unknown_operators(mut self) -> Self {
+        self.unknown_operators = true;
+        self
+    }
+
+    /// 最多建立 `max` 个语法树节点（条目、语句、类型、模式和表达式的操作数），
+    /// 更多时只报告 "program too large"
+    pub fn with_max_nodes(mut self, max: usize) -> Self {
+        self.max_nodes = max;
+        self
+    }
+
+    /// 表达式最多嵌套 `max` 层（一元表达式和运算符、后缀的每一层都算），更深时只报告
+    /// "program too large"
+    pub fn with_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
     }
 
     pub fn parse(&mut self) -> Result<Program, Vec<ParseError>> {
         let start_span = self.current_span();
+        let attributes = self.parse_inner_attributes();
+        // 文件中声明的运算符在声明之前也可以使用，同名时遮蔽导入的运算符
+        let mut operators = self.scan_operators();
+        let imported = std::mem::take(&mut self.operators);
+        let shadowed = |op: &CustomOperator| operators.iter().any(|own| own.symbol == op.symbol);
+        let imported: Vec<CustomOperator> =
+            imported.into_iter().filter(|op| !shadowed(op)).collect();
+        operators.extend(imported);
+        self.operators = operators;
         let mut items = Vec::new();
 
         while !self.is_at_end() {
+            let start = self.current;
             match self.parse_item() {
-                Ok(item) => {
-                    items.push(item);
-                    self.panic_mode = false;
-                }
+                Ok(item) => items.push(item),
                 Err(err) => {
                     self.errors.push(err);
-                    if !self.panic_mode {
-                        self.panic_mode = true;
-                        self.synchronize();
-                    }
+                    self.synchronize(start);
                 }
             }
         }
 
+        // 超出节点数或嵌套层数的上限之后的错误都是中途停下造成的，只报告上限
+        if self.nodes > self.max_nodes || self.depth > self.max_depth {
+            self.errors.retain(|error| error.code == ErrorCode::E0803);
+            self.errors.truncate(1);
+        }
         if self.errors.is_empty() {
             let end_span = if self.current > 0 {
                 self.tokens[self.current - 1].span
@@ -92,6 +205,7 @@ impl Parser {
             };
 
             Ok(Program {
+                attributes,
                 items,
                 span: start_span.merge(&end_span),
             })
@@ -100,21 +214,122 @@ impl Parser {
         }
     }
 
+    /// 解析一串语句直到输入结束，用于 REPL 输入
+    pub fn parse_statements(&mut self) -> Result<Vec<Statement>, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            match self.parse_statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    break;
+                }
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(self.errors.clone())
+        }
+    }
+
+    /// 解析单个表达式，表达式之后不能再有其他记号
+    pub fn parse_standalone_expression(&mut self) -> Result<Expr, Vec<ParseError>> {
+        let expr = self.parse_expression().map_err(|err| vec![err])?;
+        if !self.errors.is_empty() {
+            return Err(self.errors.clone());
+        }
+        if !self.is_at_end() {
+            return Err(vec![ParseError::new(
+                format!(
+                    "Expected end of expression, found {:?}",
+                    self.current_token_kind()
+                ),
+                self.current_span(),
+            )]);
+        }
+        Ok(expr)
+    }
+
     // 顶层项目解析
     fn parse_item(&mut self) -> Result<Item, ParseError> {
-        // 检查可见性
+        self.node()?;
+        let attributes = self.parse_attributes()?;
+        let operator = match self.at_operator_declaration() {
+            true => Some(self.parse_operator_declaration()?),
+            false => None,
+        };
+
+        // 条目的范围从 `pub` 开始，不包括属性
+        let start = self.current_span();
         let visibility = if self.match_token(&TokenKind::Pub) {
             Visibility::Public
         } else {
             Visibility::Private
         };
+        let pure = self.at_pure_fn();
+        if pure {
+            self.advance();
+        }
+        let asynchronous = match self.at_async_fn() {
+            true => {
+                self.advance();
+                Some(self.previous().span)
+            }
+            false => None,
+        };
+
+        if let (Some(operator), false) = (&operator, self.check(&TokenKind::Fn)) {
+            return Err(ParseError::new(
+                format!(
+                    "operator `{}` must be followed by the function that implements it",
+                    operator.symbol
+                ),
+                operator.span,
+            )
+            .with_code(ErrorCode::E0113));
+        }
+
+        let attributed = matches!(
+            self.current_token_kind(),
+            TokenKind::Fn | TokenKind::Struct | TokenKind::Enum
+        );
+        if let (Some(attribute), false) = (attributes.first(), attributed) {
+            return Err(ParseError::new(
+                "attributes are only allowed on functions, structs and enums".to_string(),
+                attribute.span,
+            )
+            .with_code(ErrorCode::E0103));
+        }
+
+        if self.at_macro_rules() || self.at_macro_call() {
+            if visibility == Visibility::Public {
+                return Err(ParseError::new(
+                    "macros cannot be `pub`; a macro is visible in the module that defines it"
+                        .to_string(),
+                    self.previous().span,
+                ));
+            }
+            return match self.at_macro_rules() {
+                true => self.parse_macro_rules().map(Item::MacroRules),
+                false => self.parse_item_macro_call().map(Item::MacroCall),
+            };
+        }
 
+        let header = ItemHeader {
+            attributes,
+            operator,
+            visibility,
+            asynchronous,
+            pure,
+            start,
+        };
         match self.current_token_kind() {
-            TokenKind::Fn => self.parse_function(visibility).map(Item::Function),
-            TokenKind::Struct => self.parse_struct(visibility).map(Item::Struct),
-            TokenKind::Enum => self.parse_enum(visibility).map(Item::Enum),
-            TokenKind::Const => self.parse_const(visibility).map(Item::Const),
-            TokenKind::Static => self.parse_static(visibility).map(Item::Static),
+            TokenKind::Fn => self.parse_function(header).map(Item::Function),
+            TokenKind::Struct => self.parse_struct(header).map(Item::Struct),
+            TokenKind::Enum => self.parse_enum(header).map(Item::Enum),
+            TokenKind::Const => self.parse_const(header).map(Item::Const),
+            TokenKind::Static => self.parse_static(header).map(Item::Static),
             TokenKind::Import => self.parse_import().map(Item::Import),
             TokenKind::Export => self.parse_export().map(Item::Export),
             _ => Err(ParseError::new(
@@ -127,33 +342,353 @@ impl Parser {
         }
     }
 
+    // 文件开头的属性 `#![feature(name, ...)]`，开启实验性的语法
+    fn parse_inner_attributes(&mut self) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        while self.check(&TokenKind::Hash) && self.peek_ahead(1) == Some(&TokenKind::LogicalNot) {
+            let (start, span) = (self.current, self.current_span());
+            self.advance();
+            self.advance();
+            match self.parse_attribute(span) {
+                Ok(attribute) => {
+                    self.inner_attribute(&attribute);
+                    attributes.push(attribute);
+                }
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize(start);
+                }
+            }
+        }
+        attributes
+    }
+
+    fn inner_attribute(&mut self, attribute: &Attribute) {
+        match attribute.name.as_str() {
+            "feature" => self.enable_features(attribute),
+            "edition" => self.select_edition(attribute),
+            name => {
+                let error = ParseError::new(format!("cannot find attribute `{}`", name), attribute.span)
+                    .with_code(ErrorCode::E0702)
+                    .with_help(
+                        "the attributes at the top of a file are `#![feature(...)]` and `#![edition(...)]`"
+                            .to_string(),
+                    );
+                self.errors.push(error);
+            }
+        }
+    }
+
+    fn enable_features(&mut self, attribute: &Attribute) {
+        for name in &attribute.args {
+            match name.parse::<Feature>() {
+                Ok(feature) => self.features.push(feature),
+                Err(message) => {
+                    let known: Vec<String> = Feature::ALL
+                        .iter()
+                        .map(|feature| format!("`{}`", feature))
+                        .collect();
+                    let error = ParseError::new(message, attribute.span)
+                        .with_code(ErrorCode::E0635)
+                        .with_help(format!("the available features are {}", known.join(", ")));
+                    self.errors.push(error);
+                }
+            }
+        }
+    }
+
+    fn select_edition(&mut self, attribute: &Attribute) {
+        let editions: Vec<String> = Edition::ALL
+            .iter()
+            .map(|edition| format!("`{}`", edition))
+            .collect();
+        let edition = match attribute.args.as_slice() {
+            _ if self.edition.is_some() => Err((
+                "the edition of this file is already set".to_string(),
+                "remove one of the `#![edition(...)]` attributes".to_string(),
+            )),
+            [year] => year.parse::<Edition>().map_err(|message| {
+                let help = format!("the available editions are {}", editions.join(", "));
+                (message, help)
+            }),
+            _ => Err((
+                "`#![edition(...)]` takes one edition".to_string(),
+                format!(
+                    "write the year of the edition, such as `#![edition({})]`",
+                    Edition::LATEST
+                ),
+            )),
+        };
+        match edition {
+            Ok(edition) => self.edition = Some(edition),
+            Err((message, help)) => {
+                let error = ParseError::new(message, attribute.span)
+                    .with_code(ErrorCode::E0705)
+                    .with_help(help);
+                self.errors.push(error);
+            }
+        }
+    }
+
+    // 这个版本中保留的词和开启的特性使用的词不能用作名字
+    fn check_reserved(&mut self, name: &str, span: Span) {
+        let edition = self.edition.unwrap_or_default();
+        let message = if edition.reserved_words().contains(&name) {
+            format!("`{}` is a reserved keyword in edition {}", name, edition)
+        } else if self.features.contains(&Feature::AsyncAwait) && ASYNC_KEYWORDS.contains(&name) {
+            format!(
+                "`{}` is a keyword with `#![feature({})]`",
+                name,
+                Feature::AsyncAwait
+            )
+        } else {
+            return;
+        };
+        let error = ParseError::new(message, span)
+            .with_help(format!("rename it, for example to `{}_`", name));
+        self.errors.push(error);
+    }
+
+    // `async` 块和 `.await` 是否是关键字：开启了特性或者版本保留了这两个词。
+    // 否则它们是普通的名字，如结构体字面量 `async {}` 和字段 `x.await`
+    fn async_keywords(&self) -> bool {
+        self.features.contains(&Feature::AsyncAwait)
+            || self.edition.unwrap_or_default() >= Edition::E2025
+    }
+
+    // 使用实验性的语法时检查文件开启了它的特性
+    fn require_feature(&mut self, feature: Feature, what: &str, span: Span) {
+        if self.features.contains(&feature) {
+            return;
+        }
+        let error = ParseError::new(format!("{} are experimental", what), span)
+            .with_code(ErrorCode::E0658)
+            .with_help(format!(
+                "add `#![feature({})]` at the top of the file",
+                feature
+            ));
+        self.errors.push(error);
+    }
+
+    // 条目之前的属性 `#[name]` 或 `#[name(arg, ...)]`，参数是标识符或整数类型名
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
+        let mut attributes = Vec::new();
+        while self.check(&TokenKind::Hash) {
+            let start = self.current_span();
+            self.advance();
+            if self.check(&TokenKind::LogicalNot) {
+                return Err(ParseError::new(
+                    "`#![...]` must come before all items of the file".to_string(),
+                    self.span_from(start),
+                )
+                .with_code(ErrorCode::E0103));
+            }
+            attributes.push(self.parse_attribute(start)?);
+        }
+        Ok(attributes)
+    }
+
+    // `#` 或 `#!` 之后的 `[name(arg, ...)]`，参数是标识符、基础类型名、整数或字符串；
+    // 字符串参数按源码中的写法（带引号）保存，取值见 `Attribute::string_arg`
+    fn parse_attribute(&mut self, start: Span) -> Result<Attribute, ParseError> {
+        self.open(TokenKind::LeftBracket, "Expected '[' after '#'")?;
+        let name = self.expect_ident("Expected attribute name")?;
+        let mut args = Vec::new();
+        if self.check(&TokenKind::LeftParen) {
+            self.open(TokenKind::LeftParen, "Expected '(' after attribute name")?;
+            while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+                // 基础类型名也可以作为参数，如 `#[repr(u8)]`
+                let arg = match self.current_token_kind() {
+                    kind @ (TokenKind::I8
+                    | TokenKind::I16
+                    | TokenKind::I32
+                    | TokenKind::I64
+                    | TokenKind::U8
+                    | TokenKind::U16
+                    | TokenKind::U32
+                    | TokenKind::U64
+                    | TokenKind::Usize
+                    | TokenKind::Isize) => {
+                        let arg = kind.to_string();
+                        self.advance();
+                        arg
+                    }
+                    // `#![edition(2025)]`
+                    TokenKind::IntLiteral(value) => {
+                        let arg = value.to_string();
+                        self.advance();
+                        arg
+                    }
+                    // `#[intrinsic("sqrt")]`
+                    TokenKind::StringLiteral(value) => {
+                        let arg = string_literal(value);
+                        self.advance();
+                        arg
+                    }
+                    _ => self.expect_ident("Expected attribute argument")?,
+                };
+                args.push(arg);
+                if !self.list_separator(&TokenKind::RightParen)? {
+                    break;
+                }
+            }
+            self.close(
+                TokenKind::RightParen,
+                "Expected ')' after attribute arguments",
+            )?;
+        }
+        self.close(TokenKind::RightBracket, "Expected ']' after attribute")?;
+        Ok(Attribute {
+            name,
+            args,
+            span: self.span_from(start),
+        })
+    }
+
+    // 运算符声明 `operator <*> (precedence 70, left)`，写在实现运算符的函数之前。
+    // 运算符是紧挨着的几个运算符记号，一个记号时不能是内建的二元运算符
+    fn parse_operator_declaration(&mut self) -> Result<OperatorDecl, ParseError> {
+        let start = self.current_span();
+        self.advance();
+        let length = self.operator_run(self.current);
+        let first = self.current_span();
+        let symbol: Vec<TokenKind> = (0..length).map(|_| self.advance().kind.clone()).collect();
+        let spelling: String = symbol.iter().map(TokenKind::to_string).collect();
+        if let [kind] = symbol.as_slice() {
+            if binding_power(kind).is_some() {
+                return Err(ParseError::new(
+                    format!("`{}` is a built-in operator", spelling),
+                    first,
+                )
+                .with_code(ErrorCode::E0113)
+                .with_help(
+                    "write a new operator with several operator characters, such as `<*>`"
+                        .to_string(),
+                ));
+            }
+        }
+
+        self.open(TokenKind::LeftParen, "Expected '(' after the operator")?;
+        if !self.match_contextual("precedence") {
+            return Err(ParseError::new(
+                format!(
+                    "Expected `precedence`, found {:?}",
+                    self.current_token_kind()
+                ),
+                self.current_span(),
+            ));
+        }
+        let number = self.current_span();
+        let precedence = match self.current_token_kind() {
+            TokenKind::IntLiteral(value) => u8::try_from(*value).ok(),
+            _ => {
+                return Err(ParseError::new(
+                    format!("Expected precedence, found {:?}", self.current_token_kind()),
+                    number,
+                ))
+            }
+        };
+        self.advance();
+        self.consume(TokenKind::Comma, "Expected ',' after the precedence")?;
+        let associativity = if self.match_contextual("left") {
+            Associativity::Left
+        } else if self.match_contextual("right") {
+            Associativity::Right
+        } else {
+            return Err(ParseError::new(
+                format!(
+                    "Expected `left` or `right`, found {:?}",
+                    self.current_token_kind()
+                ),
+                self.current_span(),
+            ));
+        };
+        self.close(
+            TokenKind::RightParen,
+            "Expected ')' after the associativity",
+        )?;
+
+        let Some(precedence) = precedence.filter(|value| OPERATOR_PRECEDENCE.contains(value))
+        else {
+            return Err(ParseError::new(
+                format!(
+                    "operator precedence must be between {} and {}",
+                    OPERATOR_PRECEDENCE.start(),
+                    OPERATOR_PRECEDENCE.end()
+                ),
+                number,
+            )
+            .with_code(ErrorCode::E0113)
+            .with_help("`||` has precedence 20, `==` 70, `+` 110 and `*` 120".to_string()));
+        };
+        Ok(OperatorDecl {
+            symbol: spelling,
+            precedence,
+            associativity,
+            span: self.span_from(start),
+        })
+    }
+
+    // 文件中声明的运算符和实现它们的函数，在解析条目之前收集，出错的声明留到解析到那里时报告
+    fn scan_operators(&mut self) -> Vec<CustomOperator> {
+        let (current, errors) = (self.current, self.errors.len());
+        let mut operators = Vec::new();
+        for i in current..self.tokens.len() {
+            self.current = i;
+            if !self.at_operator_declaration() {
+                continue;
+            }
+            let symbol: Vec<TokenKind> = (0..self.operator_run(i + 1))
+                .map(|n| self.tokens[i + 1 + n].kind.clone())
+                .collect();
+            let Ok(decl) = self.parse_operator_declaration() else {
+                continue;
+            };
+            let public = self.match_token(&TokenKind::Pub);
+            if let (TokenKind::Fn, Some(TokenKind::Ident(name))) =
+                (self.current_token_kind(), self.peek_ahead(1))
+            {
+                operators.push(CustomOperator {
+                    symbol,
+                    precedence: decl.precedence,
+                    associativity: decl.associativity,
+                    function: vec![name.to_string()],
+                    public,
+                });
+            }
+        }
+        self.current = current;
+        self.errors.truncate(errors);
+        self.delimiters.clear();
+        self.recovered_at = None;
+        operators
+    }
+
     // 函数解析
-    fn parse_function(&mut self, visibility: Visibility) -> Result<Function, ParseError> {
-        let start_span = self.current_span();
+    fn parse_function(&mut self, header: ItemHeader) -> Result<Function, ParseError> {
+        if let Some(span) = header.asynchronous {
+            self.require_feature(Feature::AsyncAwait, "async functions", span);
+        }
         self.consume(TokenKind::Fn, "Expected 'fn'")?;
 
         let name = self.expect_ident("Expected function name")?;
 
         // 泛型参数（可选）
-        let generics = if self.match_token(&TokenKind::Less) {
-            Some(self.parse_generics()?)
-        } else {
-            None
-        };
+        let generics = self.parse_generics()?;
 
         // 参数列表
-        self.consume(TokenKind::LeftParen, "Expected '(' after function name")?;
+        self.open(TokenKind::LeftParen, "Expected '(' after function name")?;
         let mut params = Vec::new();
 
         while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
             params.push(self.parse_parameter()?);
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightParen)? {
                 break;
             }
         }
 
-        self.consume(TokenKind::RightParen, "Expected ')' after parameters")?;
+        self.close(TokenKind::RightParen, "Expected ')' after parameters")?;
 
         // 返回类型（可选）
         let return_type = if self.match_token(&TokenKind::Arrow) {
@@ -162,37 +697,89 @@ impl Parser {
             None
         };
 
+        // 契约子句（可选）：`requires <条件>` / `ensures <条件>`
+        let mut contracts = Vec::new();
+        loop {
+            let kind = if self.match_contextual("requires") {
+                ContractKind::Requires
+            } else if self.match_contextual("ensures") {
+                ContractKind::Ensures
+            } else {
+                break;
+            };
+            contracts.push(self.parse_contract(kind)?);
+            self.match_token(&TokenKind::Comma);
+        }
+
         // 函数体
         let body = self.parse_block()?;
 
+        // `a <*> b` 调用 `f(a, b)`
+        if let Some(operator) = &header.operator {
+            self.require_feature(Feature::CustomOperators, "custom operators", operator.span);
+            let problem = match (params.len(), &return_type) {
+                (2, Some(_)) => None,
+                (2, None) => Some("must return a value"),
+                _ => Some("must have two parameters"),
+            };
+            if let Some(problem) = problem {
+                let error = ParseError::new(
+                    format!(
+                        "the function `{}` implementing operator `{}` {}",
+                        name, operator.symbol, problem
+                    ),
+                    operator.span,
+                )
+                .with_code(ErrorCode::E0113);
+                self.errors.push(error);
+            }
+        }
+
         Ok(Function {
-            visibility,
+            attributes: header.attributes,
+            visibility: header.visibility,
             name,
             generics,
             params,
             return_type,
+            contracts,
             body,
-            span: start_span.merge(&self.previous().span),
+            operator: header.operator,
+            asynchronous: header.asynchronous.is_some(),
+            pure: header.pure,
+            span: self.span_from(header.start),
         })
     }
 
     // 结构体解析
-    fn parse_struct(&mut self, visibility: Visibility) -> Result<StructDef, ParseError> {
-        let start_span = self.current_span();
+    fn parse_struct(&mut self, header: ItemHeader) -> Result<StructDef, ParseError> {
         self.consume(TokenKind::Struct, "Expected 'struct'")?;
 
         let name = self.expect_ident("Expected struct name")?;
+        let generics = self.parse_generics()?;
 
-        let generics = if self.match_token(&TokenKind::Less) {
-            Some(self.parse_generics()?)
-        } else {
-            None
-        };
-
-        self.consume(TokenKind::LeftBrace, "Expected '{' after struct name")?;
+        self.open(TokenKind::LeftBrace, "Expected '{' after struct name")?;
 
         let mut fields = Vec::new();
+        let mut invariants = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            // 字段之后的 `invariant <条件>`；名为 invariant 的字段后面跟着冒号
+            if self.peek_ahead(1) != Some(&TokenKind::Colon) && self.match_contextual("invariant") {
+                invariants.push(self.parse_contract(ContractKind::Invariant)?);
+                if !self.list_separator(&TokenKind::RightBrace)? {
+                    break;
+                }
+                continue;
+            }
+            if !invariants.is_empty() {
+                return Err(ParseError::new(
+                    "Struct fields must come before invariants".to_string(),
+                    self.current_span(),
+                )
+                .with_code(ErrorCode::E0101));
+            }
+
+            let field_start = self.current_span();
             let field_visibility = if self.match_token(&TokenKind::Pub) {
                 Visibility::Public
             } else {
@@ -207,39 +794,60 @@ impl Parser {
                 visibility: field_visibility,
                 name: field_name,
                 ty: field_type,
-                span: self.previous().span,
+                span: self.span_from(field_start),
             });
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightBrace)? {
                 break;
             }
         }
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after struct fields")?;
+        self.close(TokenKind::RightBrace, "Expected '}' after struct fields")?;
 
         Ok(StructDef {
-            visibility,
+            attributes: header.attributes,
+            visibility: header.visibility,
             name,
             generics,
             fields,
-            span: start_span.merge(&self.previous().span),
+            invariants,
+            span: self.span_from(header.start),
+        })
+    }
+
+    // 契约子句的条件，关键字已经读过；条件后面可能紧跟函数体，不允许结构体字面量
+    fn parse_contract(&mut self, kind: ContractKind) -> Result<Contract, ParseError> {
+        let start = self.current;
+        let condition = self.parse_bool_condition()?;
+        Ok(Contract {
+            kind,
+            text: self.source_text(start, self.current),
+            span: condition.span(),
+            condition,
         })
     }
 
+    // 由 tokens[start..end] 还原源码，token 之间有空白的地方用一个空格分隔；
+    // 记号按规范的写法输出，如整数写成十进制
+    fn source_text(&self, start: usize, end: usize) -> String {
+        let mut text = String::new();
+        for (i, token) in self.tokens[start..end].iter().enumerate() {
+            if i > 0 && token.span.start > self.tokens[start + i - 1].span.end {
+                text.push(' ');
+            }
+            text.push_str(&token.kind.to_string());
+        }
+        text
+    }
+
     // 枚举解析
-    fn parse_enum(&mut self, visibility: Visibility) -> Result<EnumDef, ParseError> {
-        let start_span = self.current_span();
+    fn parse_enum(&mut self, header: ItemHeader) -> Result<EnumDef, ParseError> {
         self.consume(TokenKind::Enum, "Expected 'enum'")?;
 
         let name = self.expect_ident("Expected enum name")?;
+        let generics = self.parse_generics()?;
 
-        let generics = if self.match_token(&TokenKind::Less) {
-            Some(self.parse_generics()?)
-        } else {
-            None
-        };
-
-        self.consume(TokenKind::LeftBrace, "Expected '{' after enum name")?;
+        self.open(TokenKind::LeftBrace, "Expected '{' after enum name")?;
 
         let mut variants = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
@@ -250,7 +858,7 @@ impl Parser {
                 let mut field_types = Vec::new();
                 while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
                     field_types.push(self.parse_type()?);
-                    if !self.match_token(&TokenKind::Comma) {
+                    if !self.list_separator(&TokenKind::RightParen)? {
                         break;
                     }
                 }
@@ -263,28 +871,28 @@ impl Parser {
             variants.push(EnumVariant {
                 name: variant_name,
                 fields,
-                span: variant_start.merge(&self.previous().span),
+                span: self.span_from(variant_start),
             });
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightBrace)? {
                 break;
             }
         }
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after enum variants")?;
+        self.close(TokenKind::RightBrace, "Expected '}' after enum variants")?;
 
         Ok(EnumDef {
-            visibility,
+            attributes: header.attributes,
+            visibility: header.visibility,
             name,
             generics,
             variants,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(header.start),
         })
     }
 
     // const 解析
-    fn parse_const(&mut self, visibility: Visibility) -> Result<ConstDef, ParseError> {
-        let start_span = self.current_span();
+    fn parse_const(&mut self, header: ItemHeader) -> Result<ConstDef, ParseError> {
         self.consume(TokenKind::Const, "Expected 'const'")?;
 
         let name = self.expect_ident("Expected const name")?;
@@ -295,17 +903,16 @@ impl Parser {
         self.consume(TokenKind::Semicolon, "Expected ';' after const value")?;
 
         Ok(ConstDef {
-            visibility,
+            visibility: header.visibility,
             name,
             ty,
             value,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(header.start),
         })
     }
 
     // static 解析
-    fn parse_static(&mut self, visibility: Visibility) -> Result<StaticDef, ParseError> {
-        let start_span = self.current_span();
+    fn parse_static(&mut self, header: ItemHeader) -> Result<StaticDef, ParseError> {
         self.consume(TokenKind::Static, "Expected 'static'")?;
 
         let mutable = self.match_token(&TokenKind::Mut);
@@ -317,12 +924,12 @@ impl Parser {
         self.consume(TokenKind::Semicolon, "Expected ';' after static value")?;
 
         Ok(StaticDef {
-            visibility,
+            visibility: header.visibility,
             mutable,
             name,
             ty,
             value,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(header.start),
         })
     }
 
@@ -348,7 +955,7 @@ impl Parser {
         Ok(ImportStmt {
             path,
             alias,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -357,28 +964,145 @@ impl Parser {
         let start_span = self.current_span();
         self.consume(TokenKind::Export, "Expected 'export'")?;
 
-        self.consume(TokenKind::LeftBrace, "Expected '{' after export")?;
+        self.open(TokenKind::LeftBrace, "Expected '{' after export")?;
 
         let mut items = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-            items.push(self.expect_ident("Expected export item name")?);
-            if !self.match_token(&TokenKind::Comma) {
+            let item_start = self.current_span();
+            let mut path = vec![self.expect_ident("Expected export item name")?];
+            while self.match_token(&TokenKind::DoubleColon) {
+                path.push(self.expect_ident("Expected item name after '::'")?);
+            }
+            items.push(ExportItem {
+                path,
+                span: self.span_from(item_start),
+            });
+            if !self.list_separator(&TokenKind::RightBrace)? {
                 break;
             }
         }
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after export items")?;
+        self.close(TokenKind::RightBrace, "Expected '}' after export items")?;
         self.consume(TokenKind::Semicolon, "Expected ';' after export")?;
 
         Ok(ExportStmt {
             items,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
-    // 泛型参数解析
-    fn parse_generics(&mut self) -> Result<Generics, ParseError> {
+    // 宏定义 `macro_rules! name { (模式) => { 模板 }; ... }`
+    fn parse_macro_rules(&mut self) -> Result<MacroDef, ParseError> {
+        let start_span = self.current_span();
+        self.advance(); // macro_rules
+        self.consume(TokenKind::LogicalNot, "Expected '!' after 'macro_rules'")?;
+        let name = self.expect_ident("Expected macro name")?;
+        self.open(TokenKind::LeftBrace, "Expected '{' after macro name")?;
+
+        let mut rules = Vec::new();
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let rule_start = self.current_span();
+            let pattern = self.parse_delimited_tree("Expected '(' to start a macro rule")?;
+            self.consume(TokenKind::FatArrow, "Expected '=>' after macro pattern")?;
+            let body = self.parse_delimited_tree("Expected '{' to start a macro body")?;
+            rules.push(MacroRule {
+                pattern,
+                body,
+                span: self.span_from(rule_start),
+            });
+            if !self.match_token(&TokenKind::Semicolon) {
+                break;
+            }
+        }
+
+        self.close(TokenKind::RightBrace, "Expected '}' after macro rules")?;
+        Ok(MacroDef {
+            name,
+            rules,
+            span: self.span_from(start_span),
+        })
+    }
+
+    // 条目位置的宏调用，圆括号和方括号之后要有分号
+    fn parse_item_macro_call(&mut self) -> Result<MacroCall, ParseError> {
+        let call = self.parse_macro_call()?;
+        if !matches!(&call.args, TokenTree::Delimited(open, _, _) if open.kind == TokenKind::LeftBrace)
+        {
+            self.consume(TokenKind::Semicolon, "Expected ';' after macro invocation")?;
+        }
+        Ok(call)
+    }
+
+    // `name!` 之后跟着一个带定界符的记号树
+    fn parse_macro_call(&mut self) -> Result<MacroCall, ParseError> {
+        let start_span = self.current_span();
+        let name = self.expect_ident("Expected macro name")?;
+        self.consume(TokenKind::LogicalNot, "Expected '!' after macro name")?;
+        let args = self.parse_delimited_tree("Expected '(', '[' or '{' after macro name")?;
+        Ok(MacroCall {
+            name,
+            args,
+            span: self.span_from(start_span),
+        })
+    }
+
+    fn parse_delimited_tree(&mut self, message: &str) -> Result<TokenTree, ParseError> {
+        if closing_delimiter(self.current_token_kind()).is_none() {
+            return Err(ParseError::new(
+                format!("{}, found {:?}", message, self.current_token_kind()),
+                self.current_span(),
+            ));
+        }
+        self.parse_token_tree()
+    }
+
+    // 一个记号，或者一对定界符和其中的所有记号树
+    fn parse_token_tree(&mut self) -> Result<TokenTree, ParseError> {
+        let kind = self.current_token_kind().clone();
+        if let Some(close) = closing_delimiter(&kind) {
+            let open = self.advance().clone();
+            self.opened();
+            let mut trees = Vec::new();
+            while !self.check(&close) && !self.is_at_end() {
+                trees.push(self.parse_token_tree()?);
+            }
+            let message = format!("Expected '{}'", close);
+            self.close(close, &message)?;
+            let close = self.previous().clone();
+            return Ok(TokenTree::Delimited(open, trees, close));
+        }
+        if matches!(
+            kind,
+            TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace
+        ) {
+            return Err(ParseError::new(
+                format!("unexpected closing delimiter `{}`", kind),
+                self.current_span(),
+            ));
+        }
+        Ok(TokenTree::Token(self.advance().clone()))
+    }
+
+    /// 宏展开时匹配片段 `$x:expr`、`$x:ty` 或 `$x:pat`：从开头解析一个片段，
+    /// 返回它占用的记号数，不能解析时返回 None
+    pub(crate) fn parse_fragment(&mut self, fragment: Fragment) -> Option<usize> {
+        let len = self.tokens.len();
+        let parsed = match fragment {
+            Fragment::Expr => self.parse_expression().is_ok(),
+            Fragment::Ty => self.parse_type().is_ok(),
+            Fragment::Pat => self.parse_pattern().is_ok(),
+            _ => false,
+        };
+        // 拆开的 `>>` 多出一个记号
+        (parsed && self.errors.is_empty()).then_some(self.current - (self.tokens.len() - len))
+    }
+
+    // 泛型参数解析（可选），范围包括两边的尖括号
+    fn parse_generics(&mut self) -> Result<Option<Generics>, ParseError> {
         let start_span = self.current_span();
+        if !self.match_token(&TokenKind::Less) {
+            return Ok(None);
+        }
         let mut params = Vec::new();
 
         while !self.check(&TokenKind::Greater) && !self.is_at_end() {
@@ -396,20 +1120,20 @@ impl Parser {
             params.push(GenericParam {
                 name,
                 bounds,
-                span: param_start.merge(&self.previous().span),
+                span: self.span_from(param_start),
             });
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::Greater)? {
                 break;
             }
         }
 
         self.consume(TokenKind::Greater, "Expected '>' after generic parameters")?;
 
-        Ok(Generics {
+        Ok(Some(Generics {
             params,
-            span: start_span.merge(&self.previous().span),
-        })
+            span: self.span_from(start_span),
+        }))
     }
 
     // 参数解析
@@ -422,7 +1146,7 @@ impl Parser {
         Ok(Parameter {
             pattern,
             ty,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -445,15 +1169,40 @@ impl Parser {
 
     // 单个模式解析
     fn parse_single_pattern(&mut self) -> Result<Pattern, ParseError> {
+        self.node()?;
         match self.current_token_kind() {
             TokenKind::Underscore => {
                 self.advance();
                 Ok(Pattern::Wildcard)
             }
             TokenKind::Ident(name) => {
-                let name = name.clone();
+                let (mut name, span) = (name.to_string(), self.current_span());
                 self.advance();
 
+                // 路径模式 `Enum::Variant`：变体按名字解析，只保留最后一段
+                while self.check(&TokenKind::DoubleColon)
+                    && matches!(self.peek_ahead(1), Some(TokenKind::Ident(_)))
+                {
+                    self.advance();
+                    name = self.expect_ident("Expected identifier after '::'")?;
+                }
+
+                // 元组结构体或元组变体模式 `Some(x)`
+                if self.match_token(&TokenKind::LeftParen) {
+                    let mut patterns = Vec::new();
+                    while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+                        patterns.push(self.parse_pattern()?);
+                        if !self.list_separator(&TokenKind::RightParen)? {
+                            break;
+                        }
+                    }
+                    self.consume(
+                        TokenKind::RightParen,
+                        "Expected ')' after tuple struct pattern",
+                    )?;
+                    return Ok(Pattern::TupleStruct(name, patterns));
+                }
+
                 // 检查是否是结构体模式
                 if self.check(&TokenKind::LeftBrace) {
                     self.advance();
@@ -470,7 +1219,7 @@ impl Parser {
 
                         fields.push((field_name, field_pattern));
 
-                        if !self.match_token(&TokenKind::Comma) {
+                        if !self.list_separator(&TokenKind::RightBrace)? {
                             break;
                         }
                     }
@@ -481,6 +1230,7 @@ impl Parser {
                     )?;
                     Ok(Pattern::Struct(name, fields))
                 } else {
+                    self.check_reserved(&name, span);
                     Ok(Pattern::Ident(name))
                 }
             }
@@ -490,7 +1240,7 @@ impl Parser {
 
                 while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
                     patterns.push(self.parse_pattern()?);
-                    if !self.match_token(&TokenKind::Comma) {
+                    if !self.list_separator(&TokenKind::RightParen)? {
                         break;
                     }
                 }
@@ -498,15 +1248,38 @@ impl Parser {
                 self.consume(TokenKind::RightParen, "Expected ')' after tuple pattern")?;
                 Ok(Pattern::Tuple(patterns))
             }
-            TokenKind::IntLiteral(n) => {
-                let n = *n as i64;
+            TokenKind::IntLiteral(_) | TokenKind::SuffixedIntLiteral(..) => {
+                let (n, suffix) = self.int_literal();
+                Ok(Pattern::Literal(Literal::Int(n, suffix)))
+            }
+            TokenKind::Minus => {
                 self.advance();
-                Ok(Pattern::Literal(Literal::Int(n)))
+                if !matches!(
+                    self.current_token_kind(),
+                    TokenKind::IntLiteral(_) | TokenKind::SuffixedIntLiteral(..)
+                ) {
+                    return Err(ParseError::new(
+                        "Expected integer literal after '-' in pattern".to_string(),
+                        self.current_span(),
+                    ));
+                }
+                let (n, suffix) = self.int_literal();
+                Ok(Pattern::Literal(Literal::Int(n.wrapping_neg(), suffix)))
             }
             TokenKind::BoolLiteral(b) => {
                 let b = *b;
                 self.advance();
-                Ok(Pattern::Literal(Literal::Bool(b)))
+                Ok(Pattern::Literal(Literal::Bool(b)))
+            }
+            TokenKind::CharLiteral(c) => {
+                let c = *c;
+                self.advance();
+                Ok(Pattern::Literal(Literal::Char(c)))
+            }
+            TokenKind::StringLiteral(text) => {
+                let text = text.to_string();
+                self.advance();
+                Ok(Pattern::Literal(Literal::String(text)))
             }
             _ => Err(ParseError::new(
                 format!("Expected pattern, found {:?}", self.current_token_kind()),
@@ -517,6 +1290,7 @@ impl Parser {
 
     // 类型解析
     fn parse_type(&mut self) -> Result<Type, ParseError> {
+        self.node()?;
         let ty = match self.current_token_kind() {
             TokenKind::I8 => {
                 self.advance();
@@ -578,14 +1352,15 @@ impl Parser {
                 self.advance();
                 Type::String
             }
-            TokenKind::Fn => {
+            TokenKind::Fn | TokenKind::Ident(_) if self.at_function_type() => {
+                let pure = self.match_contextual("pure");
                 self.advance();
                 self.consume(TokenKind::LeftParen, "Expected '(' after 'fn'")?;
 
                 let mut param_types = Vec::new();
                 while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
                     param_types.push(self.parse_type()?);
-                    if !self.match_token(&TokenKind::Comma) {
+                    if !self.list_separator(&TokenKind::RightParen)? {
                         break;
                     }
                 }
@@ -597,7 +1372,7 @@ impl Parser {
                 self.consume(TokenKind::Arrow, "Expected '->' after function parameters")?;
                 let return_type = Box::new(self.parse_type()?);
 
-                Type::Function(param_types, return_type)
+                Type::Function(param_types, return_type, pure)
             }
             TokenKind::LeftBracket => {
                 self.advance();
@@ -626,6 +1401,10 @@ impl Parser {
             TokenKind::Star => {
                 self.advance();
                 let mutable = self.match_token(&TokenKind::Mut);
+                if !mutable {
+                    // `*const T` 与 `*T` 相同
+                    self.match_token(&TokenKind::Const);
+                }
                 let inner = Box::new(self.parse_type()?);
                 Type::Pointer(inner, mutable)
             }
@@ -637,6 +1416,14 @@ impl Parser {
                 Type::Reference(inner, mutable)
             }
 
+            // `&&T` 被词法分析为 `&&`，是引用的引用
+            TokenKind::LogicalAnd => {
+                self.advance();
+                let mutable = self.match_token(&TokenKind::Mut);
+                let inner = Box::new(self.parse_type()?);
+                Type::Reference(Box::new(Type::Reference(inner, mutable)), false)
+            }
+
             TokenKind::LeftParen => {
                 self.advance();
 
@@ -660,27 +1447,34 @@ impl Parser {
                     // 检查是否是函数类型
                     if self.match_token(&TokenKind::Arrow) {
                         let return_type = Box::new(self.parse_type()?);
-                        Type::Function(types, return_type)
+                        Type::Function(types, return_type, false)
                     } else {
                         Type::Tuple(types)
                     }
                 }
             }
 
+            // `typeof(expr)`：`typeof` 只在类型中、紧跟 `(` 时是类型运算符
+            TokenKind::Ident(name)
+                if name == "typeof" && self.peek_ahead(1) == Some(&TokenKind::LeftParen) =>
+            {
+                self.advance();
+                self.advance();
+                let expr = self.with_struct_literals(true, |parser| parser.parse_expression())?;
+                self.consume(
+                    TokenKind::RightParen,
+                    "Expected ')' after typeof expression",
+                )?;
+                Type::TypeOf(Arc::new(TypeOf::new(expr)))
+            }
+
             TokenKind::Ident(name) => {
-                let name = name.clone();
+                let name = name.to_string();
                 self.advance();
 
                 // 检查泛型参数
                 if self.match_token(&TokenKind::Less) {
-                    let mut args = vec![self.parse_type()?];
-
-                    while self.match_token(&TokenKind::Comma) {
-                        args.push(self.parse_type()?);
-                    }
-
-                    self.consume(TokenKind::Greater, "Expected '>' after generic arguments")?;
-                    Type::Generic(name, args)
+                    Type::Generic(name, self.parse_type_args()?)
                 } else {
                     Type::Named(name)
                 }
@@ -710,30 +1504,41 @@ impl Parser {
     // 代码块解析
     fn parse_block(&mut self) -> Result<Block, ParseError> {
         let start_span = self.current_span();
-        self.consume(TokenKind::LeftBrace, "Expected '{'")?;
-
-        let mut statements = Vec::new();
-
-        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-            statements.push(self.parse_statement()?);
-        }
+        self.open(TokenKind::LeftBrace, "Expected '{'")?;
+
+        let statements = self.with_struct_literals(true, |parser| {
+            let mut statements = Vec::new();
+            // 语句不会以条目关键字开头，遇到时说明代码块缺少 `}`
+            while !parser.check(&TokenKind::RightBrace)
+                && !parser.is_at_end()
+                && !parser.at_item_start()
+            {
+                statements.push(parser.parse_statement()?);
+            }
+            Ok(statements)
+        })?;
 
-        self.consume(TokenKind::RightBrace, "Expected '}'")?;
+        self.close(TokenKind::RightBrace, "Expected '}'")?;
 
         Ok(Block {
             statements,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
     // 语句解析
     fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        self.node()?;
         match self.current_token_kind() {
             TokenKind::Let => Ok(Statement::Let(self.parse_let_statement()?)),
             TokenKind::Return => Ok(Statement::Return(self.parse_return_statement()?)),
             TokenKind::If => Ok(Statement::If(self.parse_if_statement()?)),
-            TokenKind::While => Ok(Statement::While(self.parse_while_statement()?)),
-            TokenKind::For => Ok(Statement::For(self.parse_for_statement()?)),
+            TokenKind::For | TokenKind::Label(_) if self.at_for_loop() => {
+                Ok(Statement::For(self.parse_for_statement()?))
+            }
+            TokenKind::While | TokenKind::Label(_) => {
+                Ok(Statement::While(self.parse_while_statement()?))
+            }
             TokenKind::Match => Ok(Statement::Match(self.parse_match_statement()?)),
             TokenKind::Break => Ok(Statement::Break(self.parse_break_statement()?)),
             TokenKind::Continue => Ok(Statement::Continue(self.parse_continue_statement()?)),
@@ -744,7 +1549,7 @@ impl Parser {
                 let semicolon = self.match_token(&TokenKind::Semicolon);
 
                 Ok(Statement::Expr(ExprStmt {
-                    span: expr.span(),
+                    span: self.span_from(expr.span()),
                     expr,
                     semicolon,
                 }))
@@ -774,14 +1579,22 @@ impl Parser {
             None
         };
 
+        // `let pattern = expr else { ... };`
+        let else_block = if init.is_some() && self.match_token(&TokenKind::Else) {
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+
         self.consume(TokenKind::Semicolon, "Expected ';' after let statement")?;
 
         Ok(LetStmt {
             pattern,
             ty,
             init,
+            else_block,
             mutable,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -790,17 +1603,16 @@ impl Parser {
         let start_span = self.current_span();
         self.consume(TokenKind::Return, "Expected 'return'")?;
 
-        let expr = if self.check(&TokenKind::Semicolon) {
-            None
-        } else {
-            Some(self.parse_expression()?)
+        let expr = match self.at_expression_end() {
+            true => None,
+            false => Some(self.parse_expression()?),
         };
 
-        self.consume(TokenKind::Semicolon, "Expected ';' after return statement")?;
+        self.end_jump("Expected ';' after return statement")?;
 
         Ok(ReturnStmt {
             expr,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -809,78 +1621,141 @@ impl Parser {
         let start_span = self.current_span();
         self.consume(TokenKind::If, "Expected 'if'")?;
 
-        let cond = self.parse_expression()?;
+        let cond = self.parse_bool_condition()?;
         let then_block = self.parse_block()?;
 
-        let else_block = if self.match_token(&TokenKind::Else) {
-            if self.check(&TokenKind::If) {
-                // else if - 递归解析为嵌套的if
-                let nested_if = self.parse_if_statement()?;
-                Some(Block {
-                    statements: vec![Statement::If(nested_if)],
-                    span: self.previous().span,
-                })
-            } else {
-                Some(self.parse_block()?)
-            }
-        } else {
-            None
-        };
+        let else_block = self.parse_else()?;
 
         Ok(IfStmt {
             cond,
             then_block,
             else_block,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
+    // `else` 分支（可选）；`else if` 递归解析为嵌套的 if 语句
+    fn parse_else(&mut self) -> Result<Option<ElseBranch>, ParseError> {
+        if !self.match_token(&TokenKind::Else) {
+            return Ok(None);
+        }
+        if self.check(&TokenKind::If) {
+            let nested = self.parse_if_statement()?;
+            return Ok(Some(ElseBranch::If(Box::new(nested))));
+        }
+        Ok(Some(ElseBranch::Block(self.parse_block()?)))
+    }
+
+    // 循环前的标签 `'outer:`
+    fn parse_loop_label(&mut self) -> Result<Option<String>, ParseError> {
+        let TokenKind::Label(name) = self.current_token_kind() else {
+            return Ok(None);
+        };
+        let name = name.to_string();
+        let span = self.current_span();
+        self.advance();
+        self.consume(TokenKind::Colon, "Expected ':' after loop label")?;
+        if !matches!(self.current_token_kind(), TokenKind::While | TokenKind::For) {
+            return Err(ParseError::new(
+                format!("expected `while` or `for` after label `'{}`", name),
+                span,
+            )
+            .with_help("labels can only be placed on loops".to_string()));
+        }
+        Ok(Some(name))
+    }
+
+    // `for` 或者 `'label: for`；其他带标签的语句按 while 解析，由 parse_loop_label 报错
+    fn at_for_loop(&self) -> bool {
+        match self.current_token_kind() {
+            TokenKind::For => true,
+            TokenKind::Label(_) => self.peek_ahead(2) == Some(&TokenKind::For),
+            _ => false,
+        }
+    }
+
+    // `break` 和 `continue` 后面的标签
+    fn parse_jump_label(&mut self) -> Option<String> {
+        let TokenKind::Label(name) = self.current_token_kind() else {
+            return None;
+        };
+        let name = name.to_string();
+        self.advance();
+        Some(name)
+    }
+
     // while 语句解析
     fn parse_while_statement(&mut self) -> Result<WhileStmt, ParseError> {
         let start_span = self.current_span();
+        let label = self.parse_loop_label()?;
         self.consume(TokenKind::While, "Expected 'while'")?;
 
-        let cond = self.parse_expression()?;
-
-        self.loop_depth += 1;
+        let cond = self.parse_bool_condition()?;
+        let invariants = self.parse_loop_invariants()?;
         let body = self.parse_block()?;
-        self.loop_depth -= 1;
 
         Ok(WhileStmt {
+            label,
             cond,
+            invariants,
             body,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
     // for 语句解析
     fn parse_for_statement(&mut self) -> Result<ForStmt, ParseError> {
         let start_span = self.current_span();
+        let label = self.parse_loop_label()?;
         self.consume(TokenKind::For, "Expected 'for'")?;
 
         let pattern = self.parse_pattern()?;
         self.consume(TokenKind::In, "Expected 'in' after for loop variable")?;
-        let iterable = self.parse_expression()?;
-
-        self.loop_depth += 1;
+        let iterable = self.parse_condition()?;
+        let invariants = self.parse_loop_invariants()?;
         let body = self.parse_block()?;
-        self.loop_depth -= 1;
 
         Ok(ForStmt {
+            label,
             pattern,
             iterable,
+            invariants,
             body,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
+    // 循环条件之后、循环体之前的 `invariant <条件>`，可以有多个，用逗号分隔
+    fn parse_loop_invariants(&mut self) -> Result<Vec<Contract>, ParseError> {
+        let mut invariants = Vec::new();
+        while self.match_contextual("invariant") {
+            invariants.push(self.parse_contract(ContractKind::LoopInvariant)?);
+            self.match_token(&TokenKind::Comma);
+        }
+        Ok(invariants)
+    }
+
+    // 表达式形式的循环没有保存不变式的位置
+    fn reject_loop_invariants(&mut self, invariants: &[Contract]) {
+        let Some(first) = invariants.first() else {
+            return;
+        };
+        let error = ParseError::new(
+            "loop invariants are only allowed on loop statements".to_string(),
+            first.span,
+        )
+        .with_code(ErrorCode::E0100)
+        .with_help("move the loop into a statement of its own".to_string());
+        self.errors.push(error);
+    }
+
     // match 语句解析
     fn parse_match_statement(&mut self) -> Result<MatchStmt, ParseError> {
         let start_span = self.current_span();
         self.consume(TokenKind::Match, "Expected 'match'")?;
 
-        let expr = self.parse_expression()?;
-        self.consume(TokenKind::LeftBrace, "Expected '{' after match expression")?;
+        let expr = self.parse_condition()?;
+        self.open(TokenKind::LeftBrace, "Expected '{' after match expression")?;
 
         let mut arms = Vec::new();
 
@@ -907,20 +1782,20 @@ impl Parser {
                 pattern,
                 guard,
                 body,
-                span: arm_start.merge(&self.previous().span),
+                span: self.span_from(arm_start),
             });
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightBrace)? {
                 break;
             }
         }
 
-        self.consume(TokenKind::RightBrace, "Expected '}' after match arms")?;
+        self.close(TokenKind::RightBrace, "Expected '}' after match arms")?;
 
         Ok(MatchStmt {
             expr,
             arms,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -928,34 +1803,19 @@ impl Parser {
     fn parse_break_statement(&mut self) -> Result<BreakStmt, ParseError> {
         let start_span = self.current_span();
         self.consume(TokenKind::Break, "Expected 'break'")?;
+        let label = self.parse_jump_label();
 
-        if self.loop_depth == 0 {
-            return Err(
-                ParseError::new("break statement outside of loop".to_string(), start_span)
-                    .with_help("break can only be used inside while or for loops".to_string()),
-            );
-        }
-
-        let label = if let TokenKind::Ident(name) = self.current_token_kind() {
-            let name = name.clone();
-            self.advance();
-            Some(name)
-        } else {
-            None
-        };
-
-        let expr = if !self.check(&TokenKind::Semicolon) {
-            Some(self.parse_expression()?)
-        } else {
-            None
+        let expr = match self.at_expression_end() {
+            true => None,
+            false => Some(self.parse_expression()?),
         };
 
-        self.consume(TokenKind::Semicolon, "Expected ';' after break statement")?;
+        self.end_jump("Expected ';' after break statement")?;
 
         Ok(BreakStmt {
             label,
             expr,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -963,230 +1823,260 @@ impl Parser {
     fn parse_continue_statement(&mut self) -> Result<ContinueStmt, ParseError> {
         let start_span = self.current_span();
         self.consume(TokenKind::Continue, "Expected 'continue'")?;
+        let label = self.parse_jump_label();
 
-        if self.loop_depth == 0 {
-            return Err(ParseError::new(
-                "continue statement outside of loop".to_string(),
-                start_span,
-            )
-            .with_help("continue can only be used inside while or for loops".to_string()));
-        }
-
-        let label = if let TokenKind::Ident(name) = self.current_token_kind() {
-            let name = name.clone();
-            self.advance();
-            Some(name)
-        } else {
-            None
-        };
-
-        self.consume(
-            TokenKind::Semicolon,
-            "Expected ';' after continue statement",
-        )?;
+        self.end_jump("Expected ';' after continue statement")?;
 
         Ok(ContinueStmt {
             label,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
-    // 表达式解析 - Pratt Parsing
-    fn parse_expression(&mut self) -> Result<Expr, ParseError> {
-        self.parse_assignment()
-    }
-
-    fn parse_assignment(&mut self) -> Result<Expr, ParseError> {
-        let expr = self.parse_logical_or()?;
-
-        if self.check(&TokenKind::Assign) {
-            self.advance();
-            let right = self.parse_assignment()?;
-
-            return Ok(Expr::Assign(
-                Box::new(expr.clone()),
-                Box::new(right),
-                expr.span().merge(&self.previous().span),
-            ));
-        }
-
-        // 复合赋值运算符
-        let compound_op = match self.current_token_kind() {
-            TokenKind::PlusAssign => Some(BinOp::Add),
-            TokenKind::MinusAssign => Some(BinOp::Sub),
-            TokenKind::StarAssign => Some(BinOp::Mul),
-            TokenKind::SlashAssign => Some(BinOp::Div),
-            _ => None,
-        };
-
-        if let Some(op) = compound_op {
-            self.advance();
-            let right = self.parse_assignment()?;
-
-            return Ok(Expr::CompoundAssign(
-                op,
-                Box::new(expr.clone()),
-                Box::new(right),
-                expr.span().merge(&self.previous().span),
-            ));
-        }
-
-        Ok(expr)
-    }
-
-    fn parse_logical_or(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_logical_and()?;
-
-        while self.match_token(&TokenKind::LogicalOr) {
-            let right = self.parse_logical_and()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(BinOp::LogicalOr, Box::new(expr), Box::new(right), span);
-        }
-
-        Ok(expr)
-    }
-
-    fn parse_logical_and(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_bitwise_or()?;
-
-        while self.match_token(&TokenKind::LogicalAnd) {
-            let right = self.parse_bitwise_or()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(BinOp::LogicalAnd, Box::new(expr), Box::new(right), span);
+    // return、break 和 continue 语句以 `;` 结束；在块的末尾可以省略，如 `if i > 2 { break }`
+    fn end_jump(&mut self, message: &str) -> Result<(), ParseError> {
+        if !self.check(&TokenKind::RightBrace) {
+            self.consume(TokenKind::Semicolon, message)?;
         }
-
-        Ok(expr)
+        Ok(())
     }
 
-    fn parse_bitwise_or(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_bitwise_xor()?;
-
-        while self.match_token(&TokenKind::BitwiseOr) {
-            let right = self.parse_bitwise_xor()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(BinOp::BitwiseOr, Box::new(expr), Box::new(right), span);
-        }
-
-        Ok(expr)
+    // 表达式解析 - Pratt Parsing
+    fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(0)
     }
 
-    fn parse_bitwise_xor(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_bitwise_and()?;
-
-        while self.match_token(&TokenKind::BitwiseXor) {
-            let right = self.parse_bitwise_and()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(BinOp::BitwiseXor, Box::new(expr), Box::new(right), span);
-        }
-
-        Ok(expr)
+    // 条件表达式中不允许结构体字面量，`if x {` 的 `{` 开始的是代码块
+    fn parse_condition(&mut self) -> Result<Expr, ParseError> {
+        self.with_struct_literals(false, Self::parse_expression)
     }
 
-    fn parse_bitwise_and(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_equality()?;
-
-        while self.match_token(&TokenKind::BitwiseAnd) {
-            let right = self.parse_equality()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(BinOp::BitwiseAnd, Box::new(expr), Box::new(right), span);
+    // `if`、`while` 和契约子句的条件。`if x = 5` 多半是想写 `==`，报告错误并继续解析
+    fn parse_bool_condition(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current;
+        let condition = self.parse_condition()?;
+        if let Expr::Assign(target, _, span) = &condition {
+            let end = target.span().end;
+            let assign = (start..self.current)
+                .find(|&i| {
+                    self.tokens[i].kind == TokenKind::Assign && self.tokens[i].span.start >= end
+                })
+                .unwrap_or(start);
+            let help = format!(
+                "use `==` to compare: `{} == {}`",
+                self.source_text(start, assign),
+                self.source_text(assign + 1, self.current)
+            );
+            let error =
+                ParseError::new("cannot use an assignment as a condition".to_string(), *span)
+                    .with_code(ErrorCode::E0111)
+                    .with_help(help);
+            self.errors.push(error);
         }
-
-        Ok(expr)
+        Ok(condition)
     }
 
-    fn parse_equality(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_comparison()?;
-
-        while let Some(op) = self.match_binary_op(&[TokenKind::Equal, TokenKind::NotEqual]) {
-            let right = self.parse_comparison()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(op, Box::new(expr), Box::new(right), span);
-        }
-
-        Ok(expr)
+    // 在括号、方括号和代码块内部重新允许结构体字面量
+    fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let saved = std::mem::replace(&mut self.no_struct_literal, !allowed);
+        let result = parse(self);
+        self.no_struct_literal = saved;
+        result
     }
 
-    fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_shift()?;
-
-        while let Some(op) = self.match_binary_op(&[
-            TokenKind::Less,
-            TokenKind::Greater,
-            TokenKind::LessEqual,
-            TokenKind::GreaterEqual,
-        ]) {
-            let right = self.parse_shift()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(op, Box::new(expr), Box::new(right), span);
-        }
-
-        Ok(expr)
+    // Pratt 循环：先读一个一元表达式，再读绑定力不低于 `min` 的运算符和它的右操作数。
+    // 右操作数只吸收绑定力更高的运算符，所以同一个循环里读到的运算符的优先级只降不升，
+    // 同一层的运算符总是连续出现：连用的比较在这一层结束时报告，连用的区间在第二个 `..` 处停下
+    fn parse_binary(&mut self, min: u8) -> Result<Expr, ParseError> {
+        self.nested(|this| this.parse_operators(min))
     }
 
-    fn parse_shift(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_range()?;
+    fn parse_operators(&mut self, min: u8) -> Result<Expr, ParseError> {
+        let start = self.current;
+        let mut expr = self.parse_unary()?;
+        let (mut level, mut right, mut chain) = (None, None, None);
 
-        while let Some(op) = self.match_binary_op(&[TokenKind::LeftShift, TokenKind::RightShift]) {
-            let right = self.parse_range()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(op, Box::new(expr), Box::new(right), span);
+        loop {
+            let custom = self.custom_operator();
+            let power = match &custom {
+                Some((operator, _)) => Some(operator.binding_power()),
+                None => binding_power(self.current_token_kind()),
+            };
+            let Some((left_bp, right_bp)) = power.filter(|(left_bp, _)| *left_bp >= min) else {
+                break;
+            };
+            self.deeper()?;
+            let kind = self.current_token_kind().clone();
+            if level == Some(left_bp) {
+                if custom.is_none() && matches!(kind, TokenKind::DotDot | TokenKind::DotDotEqual) {
+                    break;
+                }
+            } else {
+                self.report_chain(start, chain.take(), &expr);
+                right = None;
+            }
+            level = Some(left_bp);
+
+            // 自定义运算符调用实现它的函数
+            if let Some((operator, length)) = custom {
+                let span = self.current_span();
+                let span = span.merge(&self.tokens[self.current + length - 1].span);
+                self.current += length;
+                let operand = self.parse_binary(right_bp)?;
+                let callee = match operator.function.as_slice() {
+                    [name] => Expr::Ident(name.clone(), span),
+                    path => Expr::Path(path.to_vec(), span),
+                };
+                let span = expr.span().merge(&operand.span());
+                expr = Expr::Call(
+                    Box::new(callee),
+                    vec![expr, operand],
+                    span,
+                    OperandType::default(),
+                );
+                continue;
+            }
+            self.advance();
+            self.reject_split_operator(&kind)?;
+
+            expr = match kind {
+                // `x as u8 as char`
+                TokenKind::As => {
+                    let ty = self.parse_type()?;
+                    let span = self.span_from(expr.span());
+                    Expr::Cast(Box::new(expr), ty, span)
+                }
+                // 切片 `a[2..]` 省略终点
+                TokenKind::DotDot if self.at_slice_end() => {
+                    let span = self.span_from(expr.span());
+                    Expr::Range(Some(Box::new(expr)), None, false, span)
+                }
+                TokenKind::DotDot | TokenKind::DotDotEqual => {
+                    let end = self.parse_binary(right_bp)?;
+                    let span = expr.span().merge(&end.span());
+                    let inclusive = kind == TokenKind::DotDotEqual;
+                    Expr::Range(Some(Box::new(expr)), Some(Box::new(end)), inclusive, span)
+                }
+                TokenKind::Assign => {
+                    let value = self.parse_binary(right_bp)?;
+                    let span = self.span_from(expr.span());
+                    Expr::Assign(Box::new(expr), Box::new(value), span)
+                }
+                _ => {
+                    if let Some(op) = compound_assign_op(&kind) {
+                        let value = self.parse_binary(right_bp)?;
+                        let span = self.span_from(expr.span());
+                        Expr::CompoundAssign(
+                            op,
+                            Box::new(expr),
+                            Box::new(value),
+                            span,
+                            OperandType::default(),
+                        )
+                    } else {
+                        let op = binary_op(&kind).expect("every binary operator has a `BinOp`");
+                        if matches!(
+                            op,
+                            BinOp::Equal
+                                | BinOp::NotEqual
+                                | BinOp::Less
+                                | BinOp::Greater
+                                | BinOp::LessEqual
+                                | BinOp::GreaterEqual
+                        ) {
+                            self.note_chain(&mut right, &mut chain);
+                        }
+                        let operand = self.parse_binary(right_bp)?;
+                        let span = expr.span().merge(&operand.span());
+                        Expr::Binary(
+                            op,
+                            Box::new(expr),
+                            Box::new(operand),
+                            span,
+                            OperandType::default(),
+                        )
+                    }
+                }
+            };
         }
 
+        self.report_chain(start, chain, &expr);
         Ok(expr)
     }
 
-    fn parse_range(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_additive()?;
-
-        if self.match_token(&TokenKind::DotDot) {
-            let inclusive = self.match_token(&TokenKind::Assign); // ..=
-            let end = self.parse_additive()?;
-            let span = expr.span().merge(&end.span());
-            expr = Expr::Range(Box::new(expr), Box::new(end), inclusive, span);
+    // 按最长匹配，`&&=`、`||=` 和 `...` 分成 `&&` `=`、`||` `=` 和 `..` `.`（见 lexer.rs）。
+    // 它们不是运算符，刚读过前一个记号、后一个记号紧挨着它时报告，给出应有的写法
+    fn reject_split_operator(&self, kind: &TokenKind) -> Result<(), ParseError> {
+        let (spelling, help) = match (kind, self.current_token_kind()) {
+            (TokenKind::LogicalAnd, TokenKind::Assign) => (
+                "&&=",
+                "write the assignment out, e.g. `done = done && check()`",
+            ),
+            (TokenKind::LogicalOr, TokenKind::Assign) => (
+                "||=",
+                "write the assignment out, e.g. `found = found || check()`",
+            ),
+            (TokenKind::DotDot, TokenKind::Dot) => (
+                "...",
+                "use `..=` for an inclusive range or `..` for an exclusive one",
+            ),
+            _ => return Ok(()),
+        };
+        let previous = self.tokens[self.current - 1].span;
+        if previous.end != self.current_span().start {
+            return Ok(());
         }
-
-        Ok(expr)
+        Err(ParseError::new(
+            format!("`{}` is not an operator", spelling),
+            previous.merge(&self.current_span()),
+        )
+        .with_code(ErrorCode::E0100)
+        .with_help(help.to_string()))
     }
 
-    fn parse_additive(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_multiplicative()?;
-
-        while let Some(op) = self.match_binary_op(&[TokenKind::Plus, TokenKind::Minus]) {
-            let right = self.parse_multiplicative()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(op, Box::new(expr), Box::new(right), span);
+    // 同一层的比较运算符不能连用：`a < b < c` 会先比较 `a < b`，再拿得到的 bool 和 `c` 比较。
+    // 刚读过一个运算符时调用，`right` 是上一个右操作数开始的位置；
+    // 读到第二个运算符时记下中间操作数的开始和这个运算符的位置
+    fn note_chain(&self, right: &mut Option<usize>, chain: &mut Option<(usize, usize)>) {
+        let op = self.current - 1;
+        if let (Some(middle), None) = (*right, *chain) {
+            *chain = Some((middle, op));
         }
-
-        Ok(expr)
+        *right = Some(self.current);
     }
 
-    fn parse_multiplicative(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_cast()?;
-
-        while let Some(op) =
-            self.match_binary_op(&[TokenKind::Star, TokenKind::Slash, TokenKind::Percent])
-        {
-            let right = self.parse_cast()?;
-            let span = expr.span().merge(&right.span());
-            expr = Expr::Binary(op, Box::new(expr), Box::new(right), span);
-        }
-
-        Ok(expr)
+    // 语法上接受连用的比较并继续解析，只报告一个错误
+    fn report_chain(&mut self, start: usize, chain: Option<(usize, usize)>, expr: &Expr) {
+        let Some((middle, op)) = chain else {
+            return;
+        };
+        let help = format!(
+            "split the comparison in two: `{} && {}`",
+            self.source_text(start, op),
+            self.source_text(middle, self.current)
+        );
+        let error = ParseError::new(
+            "comparison operators cannot be chained".to_string(),
+            expr.span(),
+        )
+        .with_code(ErrorCode::E0110)
+        .with_help(help);
+        self.errors.push(error);
     }
 
-    fn parse_cast(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_unary()?;
-
-        if self.match_token(&TokenKind::As) {
-            let ty = self.parse_type()?;
-            let span = expr.span().merge(&self.previous().span);
-            expr = Expr::Cast(Box::new(expr), ty, span);
-        }
-
-        Ok(expr)
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        self.nested(|this| {
+            this.deeper()?;
+            this.parse_prefix()
+        })
     }
 
-    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+    fn parse_prefix(&mut self) -> Result<Expr, ParseError> {
+        self.node()?;
         let start_span = self.current_span();
 
         let op = match self.current_token_kind() {
@@ -1194,76 +2084,172 @@ impl Parser {
             TokenKind::LogicalNot => Some(UnOp::LogicalNot),
             TokenKind::BitwiseNot => Some(UnOp::BitwiseNot),
             TokenKind::Star => Some(UnOp::Deref),
-            TokenKind::BitwiseAnd => {
-                self.advance();
-                if self.match_token(&TokenKind::Mut) {
-                    let expr = self.parse_unary()?;
-                    return Ok(Expr::Unary(
-                        UnOp::RefMut,
-                        Box::new(expr),
-                        start_span.merge(&self.previous().span),
-                    ));
-                } else {
-                    let expr = self.parse_unary()?;
+            TokenKind::BitwiseAnd | TokenKind::LogicalAnd => {
+                let double = self.advance().kind == TokenKind::LogicalAnd;
+                let op = match self.match_token(&TokenKind::Mut) {
+                    true => UnOp::RefMut,
+                    false => UnOp::Ref,
+                };
+                let expr = self.parse_unary()?;
+                if !double {
                     return Ok(Expr::Unary(
-                        UnOp::Ref,
+                        op,
                         Box::new(expr),
-                        start_span.merge(&self.previous().span),
+                        self.span_from(start_span),
+                        OperandType::default(),
                     ));
                 }
+                // `&&x` 被词法分析为 `&&`，是引用的引用，里面的一层从第二个 `&` 开始
+                let inner_start = Span::new(
+                    start_span.start + 1,
+                    start_span.end,
+                    start_span.line,
+                    start_span.column + 1,
+                );
+                let inner = Expr::Unary(
+                    op,
+                    Box::new(expr),
+                    self.span_from(inner_start),
+                    OperandType::default(),
+                );
+                return Ok(Expr::Unary(
+                    UnOp::Ref,
+                    Box::new(inner),
+                    self.span_from(start_span),
+                    OperandType::default(),
+                ));
             }
             _ => None,
         };
 
         if let Some(op) = op {
             self.advance();
+            let operand = self.current;
             let expr = self.parse_unary()?;
+            let span = self.span_from(start_span);
+            if op == UnOp::Neg {
+                self.report_negated_method(operand, &expr, span);
+            }
             Ok(Expr::Unary(
                 op,
                 Box::new(expr),
-                start_span.merge(&self.previous().span),
+                span,
+                OperandType::default(),
             ))
         } else {
             self.parse_postfix()
         }
     }
 
+    // `-x.abs()` 是 `-(x.abs())`：方法调用比一元的 `-` 优先级高，很容易误读为 `(-x).abs()`。
+    // 方法调用没有用括号括起来时报告错误并继续解析，帮助信息给出两种加括号的写法；
+    // `operand` 是 `-` 之后第一个记号的位置
+    fn report_negated_method(&mut self, operand: usize, expr: &Expr, span: Span) {
+        // `-(x.abs())`：操作数在括号中
+        if !matches!(expr, Expr::MethodCall(..)) || self.previous().span.end != expr.span().end {
+            return;
+        }
+        // 第一个不在括号中的 `.` 之前是 `-` 看上去作用的值
+        let mut depth = 0;
+        let Some(dot) = (operand..self.current).find(|&i| {
+            match self.tokens[i].kind {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    depth -= 1
+                }
+                TokenKind::Dot => return depth == 0,
+                _ => {}
+            }
+            false
+        }) else {
+            return;
+        };
+        let help = format!(
+            "write `-({})` to negate the result, or `(-{}).{}` to call the method on the negated value",
+            self.source_text(operand, self.current),
+            self.source_text(operand, dot),
+            self.source_text(dot + 1, self.current)
+        );
+        let error = ParseError::new(
+            "unary `-` has lower precedence than a method call".to_string(),
+            span,
+        )
+        .with_code(ErrorCode::E0114)
+        .with_help(help);
+        self.errors.push(error);
+    }
+
     fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.parse_primary()?;
 
         loop {
+            if matches!(
+                self.current_token_kind(),
+                TokenKind::LeftParen
+                    | TokenKind::Dot
+                    | TokenKind::LeftBracket
+                    | TokenKind::Question
+            ) {
+                self.deeper()?;
+            }
             match self.current_token_kind() {
                 TokenKind::LeftParen => {
                     // 函数调用或方法调用
                     self.advance();
-                    let args = self.parse_args()?;
-                    self.consume(TokenKind::RightParen, "Expected ')' after arguments")?;
-                    let span = expr.span().merge(&self.previous().span);
-                    expr = Expr::Call(Box::new(expr), args, span);
+                    self.opened();
+                    let args = self.with_struct_literals(true, Self::parse_args)?;
+                    self.close(TokenKind::RightParen, "Expected ')' after arguments")?;
+                    let span = self.span_from(expr.span());
+                    expr = Expr::Call(Box::new(expr), args, span, OperandType::default());
                 }
 
                 TokenKind::Dot => {
                     self.advance();
 
                     if let TokenKind::Ident(name) = self.current_token_kind() {
-                        let name = name.clone();
+                        let name = name.to_string();
                         self.advance();
 
-                        if self.check(&TokenKind::LeftParen) {
+                        if name == "await" && self.async_keywords() {
+                            let span = self.span_from(expr.span());
+                            self.require_feature(Feature::AsyncAwait, "`.await` expressions", span);
+                            expr = Expr::Await(Box::new(expr), span);
+                        } else if self.check(&TokenKind::LeftParen) {
                             // 方法调用
                             self.advance();
-                            let args = self.parse_args()?;
-                            self.consume(
+                            self.opened();
+                            let args = self.with_struct_literals(true, Self::parse_args)?;
+                            self.close(
                                 TokenKind::RightParen,
                                 "Expected ')' after method arguments",
                             )?;
-                            let span = expr.span().merge(&self.previous().span);
-                            expr = Expr::MethodCall(Box::new(expr), name, args, span);
+                            let span = self.span_from(expr.span());
+                            expr = Expr::MethodCall(
+                                Box::new(expr),
+                                name,
+                                args,
+                                span,
+                                OperandType::default(),
+                            );
                         } else {
                             // 字段访问
-                            let span = expr.span().merge(&self.previous().span);
+                            let span = self.span_from(expr.span());
                             expr = Expr::FieldAccess(Box::new(expr), name, span);
                         }
+                    } else if let TokenKind::IntLiteral(index) = self.current_token_kind() {
+                        // 元组下标只能是不带前导零和分隔符的十进制数
+                        let index = *index;
+                        let index_span = self.current_span();
+                        if index.to_string().len() != index_span.end - index_span.start {
+                            return Err(ParseError::new(
+                                "invalid tuple index: expected a decimal integer like `0`"
+                                    .to_string(),
+                                index_span,
+                            ));
+                        }
+                        self.advance();
+                        let span = self.span_from(expr.span());
+                        expr = Expr::TupleIndex(Box::new(expr), index as usize, span);
                     } else {
                         return Err(ParseError::new(
                             "Expected identifier after '.'".to_string(),
@@ -1275,12 +2261,20 @@ impl Parser {
                 TokenKind::LeftBracket => {
                     // 索引访问
                     self.advance();
-                    let index = self.parse_expression()?;
-                    self.consume(TokenKind::RightBracket, "Expected ']' after index")?;
-                    let span = expr.span().merge(&self.previous().span);
+                    self.opened();
+                    let index = self.with_struct_literals(true, Self::parse_index)?;
+                    self.close(TokenKind::RightBracket, "Expected ']' after index")?;
+                    let span = self.span_from(expr.span());
                     expr = Expr::IndexAccess(Box::new(expr), Box::new(index), span);
                 }
 
+                TokenKind::Question => {
+                    // `?`：None/Err 时提前返回
+                    self.advance();
+                    let span = self.span_from(expr.span());
+                    expr = Expr::Try(Box::new(expr), span);
+                }
+
                 _ => break,
             }
         }
@@ -1291,11 +2285,23 @@ impl Parser {
     fn parse_primary(&mut self) -> Result<Expr, ParseError> {
         let start_span = self.current_span();
 
+        if self.at_inline_asm() {
+            return self.parse_inline_asm();
+        }
+        if self.at_macro_call() {
+            return self.parse_macro_call().map(Expr::MacroCall);
+        }
+        if self.at_macro_rules() {
+            return Err(ParseError::new(
+                "macros can only be defined at the top level of a module".to_string(),
+                start_span,
+            ));
+        }
+
         match self.current_token_kind() {
-            TokenKind::IntLiteral(n) => {
-                let n = *n as i64;
-                self.advance();
-                Ok(Expr::Literal(Literal::Int(n), start_span))
+            TokenKind::IntLiteral(_) | TokenKind::SuffixedIntLiteral(..) => {
+                let (n, suffix) = self.int_literal();
+                Ok(Expr::Literal(Literal::Int(n, suffix), start_span))
             }
 
             TokenKind::BoolLiteral(b) => {
@@ -1311,25 +2317,59 @@ impl Parser {
             }
 
             TokenKind::StringLiteral(s) => {
-                let s = s.clone();
+                let s = s.to_string();
                 self.advance();
                 Ok(Expr::Literal(Literal::String(s), start_span))
             }
 
+            TokenKind::Ident(name)
+                if name == "async"
+                    && self.peek_ahead(1) == Some(&TokenKind::LeftBrace)
+                    && self.async_keywords() =>
+            {
+                self.advance();
+                let block = self.parse_block()?;
+                let span = self.span_from(start_span);
+                self.require_feature(Feature::AsyncAwait, "async blocks", span);
+                Ok(Expr::AsyncBlock(block, span))
+            }
+
             TokenKind::Ident(name) => {
-                let name = name.clone();
+                let name = name.to_string();
+                let start = self.current;
                 self.advance();
 
+                // 路径表达式：module::item 或 Enum::Variant
+                if self.check(&TokenKind::DoubleColon)
+                    && matches!(self.peek_ahead(1), Some(TokenKind::Ident(_)))
+                {
+                    let mut segments = vec![name];
+                    while self.check(&TokenKind::DoubleColon)
+                        && matches!(self.peek_ahead(1), Some(TokenKind::Ident(_)))
+                    {
+                        self.advance();
+                        segments.push(self.expect_ident("Expected identifier after '::'")?);
+                    }
+                    let path = Expr::Path(segments, self.span_from(start_span));
+                    if self.at_missing_turbofish() {
+                        return self.recover_turbofish(start, path);
+                    }
+                    return self.parse_turbofish(path);
+                }
+                if self.at_turbofish() {
+                    return self.parse_turbofish(Expr::Ident(name, start_span));
+                }
+                if self.at_missing_turbofish() {
+                    return self.recover_turbofish(start, Expr::Ident(name, start_span));
+                }
+
                 // 检查是否是结构体字面量
-                if self.check(&TokenKind::LeftBrace) {
+                if self.check(&TokenKind::LeftBrace) && !self.no_struct_literal {
                     self.advance();
+                    self.opened();
                     let fields = self.parse_struct_fields()?;
-                    self.consume(TokenKind::RightBrace, "Expected '}' after struct fields")?;
-                    Ok(Expr::StructLit(
-                        name,
-                        fields,
-                        start_span.merge(&self.previous().span),
-                    ))
+                    self.close(TokenKind::RightBrace, "Expected '}' after struct fields")?;
+                    Ok(Expr::StructLit(name, fields, self.span_from(start_span)))
                 } else {
                     Ok(Expr::Ident(name, start_span))
                 }
@@ -1341,49 +2381,42 @@ impl Parser {
                 // 空元组
                 if self.check(&TokenKind::RightParen) {
                     self.advance();
-                    return Ok(Expr::TupleLit(
-                        vec![],
-                        start_span.merge(&self.previous().span),
-                    ));
+                    return Ok(Expr::TupleLit(vec![], self.span_from(start_span)));
                 }
 
-                // 尝试解析为闭包
-                if self.check(&TokenKind::BitwiseOr)
-                    || (matches!(self.current_token_kind(), TokenKind::Ident(_))
-                        && self.peek_ahead(1) == Some(&TokenKind::Colon))
+                // `(x: T) body` 形式的闭包；`(|x| x)` 是括号中的闭包，按表达式解析
+                if matches!(self.current_token_kind(), TokenKind::Ident(_))
+                    && self.peek_ahead(1) == Some(&TokenKind::Colon)
                 {
                     return self.parse_closure_from_paren(start_span);
                 }
 
-                let mut exprs = vec![self.parse_expression()?];
+                self.opened();
+                let (exprs, is_tuple) =
+                    self.with_struct_literals(true, Self::parse_paren_elements)?;
 
                 // 元组或括号表达式
-                if self.match_token(&TokenKind::Comma) {
-                    while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
-                        exprs.push(self.parse_expression()?);
-                        if !self.match_token(&TokenKind::Comma) {
-                            break;
-                        }
-                    }
-                    self.consume(TokenKind::RightParen, "Expected ')' after tuple")?;
-                    Ok(Expr::TupleLit(
-                        exprs,
-                        start_span.merge(&self.previous().span),
-                    ))
+                if is_tuple {
+                    self.close(TokenKind::RightParen, "Expected ')' after tuple")?;
+                    Ok(Expr::TupleLit(exprs, self.span_from(start_span)))
                 } else {
-                    self.consume(TokenKind::RightParen, "Expected ')' after expression")?;
+                    self.close(TokenKind::RightParen, "Expected ')' after expression")?;
                     Ok(exprs.into_iter().next().unwrap())
                 }
             }
 
             TokenKind::LeftBracket => {
                 self.advance();
-                let elements = self.parse_array_elements()?;
-                self.consume(TokenKind::RightBracket, "Expected ']' after array elements")?;
-                Ok(Expr::ArrayLit(
-                    elements,
-                    start_span.merge(&self.previous().span),
-                ))
+                self.opened();
+                let elements = self.with_struct_literals(true, Self::parse_array_elements)?;
+                self.close(TokenKind::RightBracket, "Expected ']' after array elements")?;
+                Ok(Expr::ArrayLit(elements, self.span_from(start_span)))
+            }
+
+            TokenKind::Unsafe => {
+                self.advance();
+                let block = self.parse_block()?;
+                Ok(Expr::Unsafe(block, self.span_from(start_span)))
             }
 
             TokenKind::LeftBrace => {
@@ -1393,131 +2426,77 @@ impl Parser {
 
             TokenKind::If => {
                 self.advance();
-                let cond = Box::new(self.parse_expression()?);
+                let cond = Box::new(self.parse_bool_condition()?);
                 let then_block = self.parse_block()?;
-                let else_block = if self.match_token(&TokenKind::Else) {
-                    Some(self.parse_block()?)
-                } else {
-                    None
-                };
+                let else_block = self.parse_else()?;
                 Ok(Expr::If(
                     cond,
                     then_block,
                     else_block,
-                    start_span.merge(&self.previous().span),
+                    self.span_from(start_span),
                 ))
             }
 
-            TokenKind::While => {
-                self.advance();
-                let cond = Box::new(self.parse_expression()?);
-                self.loop_depth += 1;
-                let block = self.parse_block()?;
-                self.loop_depth -= 1;
-                Ok(Expr::While(
-                    cond,
-                    block,
-                    start_span.merge(&self.previous().span),
+            // 和语句形式的循环相同，带上可选的标签
+            TokenKind::For | TokenKind::Label(_) if self.at_for_loop() => {
+                let stmt = self.parse_for_statement()?;
+                self.reject_loop_invariants(&stmt.invariants);
+                Ok(Expr::For(
+                    stmt.label,
+                    stmt.pattern,
+                    Box::new(stmt.iterable),
+                    stmt.body,
+                    stmt.span,
                 ))
             }
-
-            TokenKind::For => {
-                self.advance();
-                let pattern = self.parse_pattern()?;
-                self.consume(TokenKind::In, "Expected 'in' in for loop")?;
-                let iter = Box::new(self.parse_expression()?);
-                self.loop_depth += 1;
-                let block = self.parse_block()?;
-                self.loop_depth -= 1;
-                Ok(Expr::For(
-                    pattern,
-                    iter,
-                    block,
-                    start_span.merge(&self.previous().span),
+            TokenKind::While | TokenKind::Label(_) => {
+                let stmt = self.parse_while_statement()?;
+                self.reject_loop_invariants(&stmt.invariants);
+                Ok(Expr::While(
+                    stmt.label,
+                    Box::new(stmt.cond),
+                    stmt.body,
+                    stmt.span,
                 ))
             }
 
             TokenKind::Match => {
                 self.advance();
-                let expr = Box::new(self.parse_expression()?);
-                self.consume(TokenKind::LeftBrace, "Expected '{' after match expression")?;
-                let arms = self.parse_match_arms()?;
-                self.consume(TokenKind::RightBrace, "Expected '}' after match arms")?;
-                Ok(Expr::Match(
-                    expr,
-                    arms,
-                    start_span.merge(&self.previous().span),
-                ))
+                let expr = Box::new(self.parse_condition()?);
+                self.open(TokenKind::LeftBrace, "Expected '{' after match expression")?;
+                let arms = self.with_struct_literals(true, Self::parse_match_arms)?;
+                self.close(TokenKind::RightBrace, "Expected '}' after match arms")?;
+                Ok(Expr::Match(expr, arms, self.span_from(start_span)))
             }
 
             TokenKind::Break => {
                 self.advance();
-                if self.loop_depth == 0 {
-                    return Err(ParseError::new(
-                        "break outside of loop".to_string(),
-                        start_span,
-                    ));
-                }
-
-                let label = if let TokenKind::Ident(name) = self.current_token_kind() {
-                    let name = name.clone();
-                    self.advance();
-                    Some(name)
-                } else {
-                    None
-                };
+                let label = self.parse_jump_label();
 
-                let expr = if !self.check(&TokenKind::Semicolon)
-                    && !self.check(&TokenKind::RightBrace)
-                    && !self.check(&TokenKind::Comma)
-                {
-                    Some(Box::new(self.parse_expression()?))
-                } else {
-                    None
+                let expr = match self.at_expression_end() {
+                    true => None,
+                    false => Some(Box::new(self.parse_expression()?)),
                 };
 
-                Ok(Expr::Break(
-                    label,
-                    expr,
-                    start_span.merge(&self.previous().span),
-                ))
+                Ok(Expr::Break(label, expr, self.span_from(start_span)))
             }
 
             TokenKind::Continue => {
                 self.advance();
-                if self.loop_depth == 0 {
-                    return Err(ParseError::new(
-                        "continue outside of loop".to_string(),
-                        start_span,
-                    ));
-                }
-
-                let label = if let TokenKind::Ident(name) = self.current_token_kind() {
-                    let name = name.clone();
-                    self.advance();
-                    Some(name)
-                } else {
-                    None
-                };
-
-                Ok(Expr::Continue(
-                    label,
-                    start_span.merge(&self.previous().span),
-                ))
+                let label = self.parse_jump_label();
+                Ok(Expr::Continue(label, self.span_from(start_span)))
             }
 
             TokenKind::Return => {
                 self.advance();
-                let expr =
-                    if !self.check(&TokenKind::Semicolon) && !self.check(&TokenKind::RightBrace) {
-                        Some(Box::new(self.parse_expression()?))
-                    } else {
-                        None
-                    };
-                Ok(Expr::Return(expr, start_span.merge(&self.previous().span)))
+                let expr = match self.at_expression_end() {
+                    true => None,
+                    false => Some(Box::new(self.parse_expression()?)),
+                };
+                Ok(Expr::Return(expr, self.span_from(start_span)))
             }
 
-            TokenKind::BitwiseOr => {
+            TokenKind::BitwiseOr | TokenKind::LogicalOr => {
                 // 闭包
                 self.parse_closure(start_span)
             }
@@ -1532,13 +2511,134 @@ impl Parser {
         }
     }
 
+    // 表达式形式的 `break` 和 `return` 在这里结束时没有值，如 `_ => return,`、`(return)`
+    // 和 `let x = return else { ... };`
+    fn at_expression_end(&self) -> bool {
+        self.is_at_end()
+            || matches!(
+                self.current_token_kind(),
+                TokenKind::Semicolon
+                    | TokenKind::Comma
+                    | TokenKind::RightParen
+                    | TokenKind::RightBracket
+                    | TokenKind::RightBrace
+                    | TokenKind::Else
+            )
+    }
+
+    // 内联汇编 `asm!("模板", ..., 操作数, ..., options(...))`：先是一个或多个模板字符串，
+    // 然后是 `in(reg) x`、`out("eax") y` 这样的操作数，最后可以有 `options(...)`
+    fn parse_inline_asm(&mut self) -> Result<Expr, ParseError> {
+        let start_span = self.current_span();
+        self.advance(); // asm
+        self.advance(); // !
+        self.open(TokenKind::LeftParen, "Expected '(' after 'asm!'")?;
+        let mut asm = InlineAsm {
+            template: Vec::new(),
+            operands: Vec::new(),
+            options: Vec::new(),
+        };
+        while let TokenKind::StringLiteral(line) = self.current_token_kind() {
+            asm.template.push(line.to_string());
+            self.advance();
+            if !self.list_separator(&TokenKind::RightParen)? {
+                break;
+            }
+        }
+        if asm.template.is_empty() {
+            return Err(ParseError::new(
+                format!(
+                    "Expected assembly template string, found {:?}",
+                    self.current_token_kind()
+                ),
+                self.current_span(),
+            ));
+        }
+        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+            if matches!(self.current_token_kind(), TokenKind::Ident(name) if name == "options") {
+                self.advance();
+                self.open(TokenKind::LeftParen, "Expected '(' after 'options'")?;
+                while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+                    asm.options
+                        .push(self.expect_ident("Expected assembly option")?);
+                    if !self.list_separator(&TokenKind::RightParen)? {
+                        break;
+                    }
+                }
+                self.close(TokenKind::RightParen, "Expected ')' after assembly options")?;
+            } else {
+                let operand = self.with_struct_literals(true, Self::parse_asm_operand)?;
+                asm.operands.push(operand);
+            }
+            if !self.list_separator(&TokenKind::RightParen)? {
+                break;
+            }
+        }
+        self.close(
+            TokenKind::RightParen,
+            "Expected ')' after assembly operands",
+        )?;
+        let span = self.span_from(start_span);
+        self.require_feature(Feature::InlineAsm, "inline assembly blocks", span);
+        Ok(Expr::InlineAsm(Box::new(asm), span))
+    }
+
+    // 汇编操作数：方向、括号中的寄存器类或寄存器名，然后是表达式；输出可以写 `_`
+    fn parse_asm_operand(&mut self) -> Result<AsmOperand, ParseError> {
+        let start_span = self.current_span();
+        let dir = match self.current_token_kind() {
+            TokenKind::In => AsmDir::In,
+            TokenKind::Ident(name) if name == "out" => AsmDir::Out,
+            TokenKind::Ident(name) if name == "lateout" => AsmDir::LateOut,
+            TokenKind::Ident(name) if name == "inout" => AsmDir::InOut,
+            kind => {
+                return Err(ParseError::new(
+                    format!(
+                        "Expected assembly operand such as `in(reg) x`, found {:?}",
+                        kind
+                    ),
+                    start_span,
+                )
+                .with_help(
+                    "operands are `in`, `out`, `lateout` or `inout` followed by a register in parentheses"
+                        .to_string(),
+                ))
+            }
+        };
+        self.advance();
+        self.open(TokenKind::LeftParen, "Expected '(' after operand direction")?;
+        let reg = match self.current_token_kind() {
+            TokenKind::Ident(class) => AsmReg::Class(class.to_string()),
+            TokenKind::StringLiteral(name) => AsmReg::Explicit(name.to_string()),
+            kind => {
+                return Err(ParseError::new(
+                    format!("Expected register class or register name, found {:?}", kind),
+                    self.current_span(),
+                ))
+            }
+        };
+        self.advance();
+        self.close(TokenKind::RightParen, "Expected ')' after register")?;
+        let expr = if dir.writes() && self.match_token(&TokenKind::Underscore) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        Ok(AsmOperand {
+            dir,
+            reg,
+            expr,
+            span: self.span_from(start_span),
+        })
+    }
+
     // 辅助方法
     fn parse_args(&mut self) -> Result<Vec<Expr>, ParseError> {
         let mut args = Vec::new();
 
         while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
             args.push(self.parse_expression()?);
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightParen)? {
                 break;
             }
         }
@@ -1546,6 +2646,21 @@ impl Parser {
         Ok(args)
     }
 
+    // 括号内的表达式列表，返回是否是元组（出现了逗号）
+    fn parse_paren_elements(&mut self) -> Result<(Vec<Expr>, bool), ParseError> {
+        let mut exprs = vec![self.parse_expression()?];
+        if !self.list_separator(&TokenKind::RightParen)? {
+            return Ok((exprs, false));
+        }
+        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+            exprs.push(self.parse_expression()?);
+            if !self.list_separator(&TokenKind::RightParen)? {
+                break;
+            }
+        }
+        Ok((exprs, true))
+    }
+
     fn parse_struct_fields(&mut self) -> Result<Vec<(String, Expr)>, ParseError> {
         let mut fields = Vec::new();
 
@@ -1561,7 +2676,7 @@ impl Parser {
 
             fields.push((name, expr));
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightBrace)? {
                 break;
             }
         }
@@ -1596,7 +2711,7 @@ impl Parser {
                 }
             }
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightBracket)? {
                 break;
             }
         }
@@ -1625,10 +2740,10 @@ impl Parser {
                 pattern,
                 guard,
                 body,
-                span: start.merge(&self.previous().span),
+                span: self.span_from(start),
             });
 
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightBrace)? {
                 break;
             }
         }
@@ -1637,20 +2752,23 @@ impl Parser {
     }
 
     fn parse_closure(&mut self, start_span: Span) -> Result<Expr, ParseError> {
-        self.consume(TokenKind::BitwiseOr, "Expected '|' to start closure")?;
-
         let mut params = Vec::new();
-        while !self.check(&TokenKind::BitwiseOr) && !self.is_at_end() {
-            params.push(self.parse_closure_param()?);
-            if !self.match_token(&TokenKind::Comma) {
-                break;
+        // 无参闭包 `|| expr` 的 `||` 被词法分析为一个记号
+        if !self.match_token(&TokenKind::LogicalOr) {
+            self.consume(TokenKind::BitwiseOr, "Expected '|' to start closure")?;
+
+            while !self.check(&TokenKind::BitwiseOr) && !self.is_at_end() {
+                params.push(self.parse_closure_param()?);
+                if !self.list_separator(&TokenKind::BitwiseOr)? {
+                    break;
+                }
             }
-        }
 
-        self.consume(
-            TokenKind::BitwiseOr,
-            "Expected '|' after closure parameters",
-        )?;
+            self.consume(
+                TokenKind::BitwiseOr,
+                "Expected '|' after closure parameters",
+            )?;
+        }
 
         let return_type = if self.match_token(&TokenKind::Arrow) {
             Some(self.parse_type()?)
@@ -1664,14 +2782,15 @@ impl Parser {
             params,
             return_type,
             body,
-            start_span.merge(&self.previous().span),
+            self.span_from(start_span),
         ))
     }
 
     // In parse_parameter method, make type optional for closures
     fn parse_closure_param(&mut self) -> Result<Parameter, ParseError> {
         let start_span = self.current_span();
-        let pattern = self.parse_pattern()?;
+        // `|` 结束参数列表，参数不能是顶层的或模式
+        let pattern = self.parse_single_pattern()?;
 
         let ty = if self.match_token(&TokenKind::Colon) {
             self.parse_type()?
@@ -1683,7 +2802,7 @@ impl Parser {
         Ok(Parameter {
             pattern,
             ty,
-            span: start_span.merge(&self.previous().span),
+            span: self.span_from(start_span),
         })
     }
 
@@ -1693,7 +2812,7 @@ impl Parser {
 
         while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
             params.push(self.parse_parameter()?);
-            if !self.match_token(&TokenKind::Comma) {
+            if !self.list_separator(&TokenKind::RightParen)? {
                 break;
             }
         }
@@ -1715,10 +2834,146 @@ impl Parser {
             params,
             return_type,
             body,
-            start_span.merge(&self.previous().span),
+            self.span_from(start_span),
         ))
     }
 
+    // 嵌套泛型 `Option<Box<T>>` 的结尾被词法分析为 `>>`，拆成两个 `>`；
+    // 后面紧跟 `=` 时是 `>=` 或 `>>=`，拆出 `>` 之后剩下 `=` 或 `>=`
+    // `<` 之后的类型实参列表，包括结尾的 `>`
+    fn parse_type_args(&mut self) -> Result<Vec<Type>, ParseError> {
+        let mut args = vec![self.parse_type()?];
+        while self.list_separator(&TokenKind::Greater)? && !self.at_closing_angle() {
+            args.push(self.parse_type()?);
+        }
+        self.split_closing_angle();
+        self.consume(TokenKind::Greater, "Expected '>' after generic arguments")?;
+        Ok(args)
+    }
+
+    fn at_turbofish(&self) -> bool {
+        self.check(&TokenKind::DoubleColon) && self.peek_ahead(1) == Some(&TokenKind::Less)
+    }
+
+    // 路径之后显式给出的类型实参 `path::<T>`，如 `std::mem::size_of::<T>()`
+    fn parse_turbofish(&mut self, path: Expr) -> Result<Expr, ParseError> {
+        if !self.at_turbofish() {
+            return Ok(path);
+        }
+        self.advance();
+        self.advance();
+        let args = self.parse_type_args()?;
+        let span = self.span_from(path.span());
+        Ok(Expr::Turbofish(Box::new(path), args, span))
+    }
+
+    // 表达式中的 `<` 总是小于号，泛型实参要写成 `path::<T>`。`path<...>` 的 `>` 之后紧跟 `::`，
+    // 或者紧跟 `(` 而尖括号内没有顶层的逗号时，按比较解析不是语法错误就是连用的比较，
+    // 看作漏写了 `::`；有逗号时（`f(a < b, c > (d))`）仍然是两个比较
+    fn at_missing_turbofish(&self) -> bool {
+        self.check(&TokenKind::Less) && self.generic_args_end(self.current).is_some()
+    }
+
+    // 报告错误之后按 turbofish 继续解析，跳过之后的路径（`Vec<i32>::new` 的 `::new`）
+    fn recover_turbofish(&mut self, start: usize, path: Expr) -> Result<Expr, ParseError> {
+        let open = self.current;
+        self.advance();
+        let args = self.parse_type_args()?;
+        let help = format!(
+            "add `::` before the generic arguments: `{}::{}`",
+            self.source_text(start, open),
+            self.source_text(open, self.current)
+        );
+        let error = ParseError::new(
+            "generic arguments in an expression must be written as `::<...>`".to_string(),
+            self.span_from(self.tokens[open].span),
+        )
+        .with_code(ErrorCode::E0112)
+        .with_help(help);
+        self.errors.push(error);
+        while self.check(&TokenKind::DoubleColon)
+            && matches!(self.peek_ahead(1), Some(TokenKind::Ident(_)))
+        {
+            self.advance();
+            self.advance();
+        }
+        let span = self.span_from(path.span());
+        Ok(Expr::Turbofish(Box::new(path), args, span))
+    }
+
+    // 从 `<` 开始，之间只有类型中的记号时返回配对的 `>` 的下一个位置
+    fn generic_args_end(&self, open: usize) -> Option<usize> {
+        let (mut angles, mut brackets, mut comma) = (0, 0, false);
+        for i in open..self.tokens.len() {
+            match &self.tokens[i].kind {
+                TokenKind::Less => angles += 1,
+                kind @ (TokenKind::Greater | TokenKind::RightShift) => {
+                    let closes = if *kind == TokenKind::Greater { 1 } else { 2 };
+                    if brackets > 0 || closes > angles {
+                        return None;
+                    }
+                    angles -= closes;
+                    if angles > 0 {
+                        continue;
+                    }
+                    return match self.tokens.get(i + 1).map(|token| &token.kind) {
+                        Some(TokenKind::DoubleColon) => Some(i + 1),
+                        Some(TokenKind::LeftParen) if !comma => Some(i + 1),
+                        _ => None,
+                    };
+                }
+                TokenKind::LeftParen | TokenKind::LeftBracket => brackets += 1,
+                TokenKind::RightParen | TokenKind::RightBracket => {
+                    if brackets == 0 {
+                        return None;
+                    }
+                    brackets -= 1;
+                }
+                TokenKind::Comma => comma |= angles == 1 && brackets == 0,
+                TokenKind::Ident(_)
+                | TokenKind::DoubleColon
+                | TokenKind::Semicolon
+                | TokenKind::IntLiteral(_)
+                | TokenKind::BitwiseAnd
+                | TokenKind::Star
+                | TokenKind::Mut
+                | TokenKind::Const => {}
+                kind if kind.is_type_keyword() => {}
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn at_closing_angle(&self) -> bool {
+        matches!(
+            self.current_token_kind(),
+            TokenKind::Greater
+                | TokenKind::RightShift
+                | TokenKind::GreaterEqual
+                | TokenKind::RightShiftAssign
+        )
+    }
+
+    fn split_closing_angle(&mut self) {
+        let rest = match self.current_token_kind() {
+            TokenKind::RightShift => TokenKind::Greater,
+            TokenKind::RightShiftAssign => TokenKind::GreaterEqual,
+            TokenKind::GreaterEqual => TokenKind::Assign,
+            _ => return,
+        };
+        let token = &mut self.tokens[self.current];
+        token.kind = rest;
+        token.span.start += 1;
+        token.span.column += 1;
+        let mut first = token.clone();
+        first.kind = TokenKind::Greater;
+        first.span.start -= 1;
+        first.span.column -= 1;
+        first.span.end = first.span.start + 1;
+        self.tokens.insert(self.current, first);
+    }
+
     // Token 操作辅助方法
     fn current_token(&self) -> &Token {
         &self.tokens[self.current.min(self.tokens.len() - 1)]
@@ -1732,10 +2987,77 @@ impl Parser {
         self.current_token().span
     }
 
+    // 读入当前的整数字面量记号，返回它的值和后缀
+    fn int_literal(&mut self) -> (i64, Option<Type>) {
+        let literal = match self.current_token_kind() {
+            TokenKind::IntLiteral(n) => (*n, None),
+            TokenKind::SuffixedIntLiteral(n, ty) => (*n, Some(ty.clone())),
+            _ => unreachable!("not an integer literal"),
+        };
+        self.advance();
+        literal
+    }
+
+    // 从 `start` 到上一个读过的记号；`start` 在读入节点的第一个记号之前取得
+    fn span_from(&self, start: Span) -> Span {
+        start.merge(&self.previous().span)
+    }
+
     fn previous(&self) -> &Token {
         &self.tokens[(self.current - 1).min(self.tokens.len() - 1)]
     }
 
+    // 记录一个新的语法树节点；超出上限时跳到输入的末尾，语法分析就此停下
+    fn node(&mut self) -> Result<(), ParseError> {
+        self.nodes += 1;
+        if self.nodes <= self.max_nodes {
+            return Ok(());
+        }
+        let span = self.current_span();
+        self.current = self.tokens.len().saturating_sub(1);
+        Err(ParseError::new(
+            format!(
+                "program too large: more than {} syntax tree nodes",
+                self.max_nodes
+            ),
+            span,
+        )
+        .with_code(ErrorCode::E0803))
+    }
+
+    // 表达式深了一层：进入一个一元表达式，或者运算符、后缀包住了已经读到的表达式。
+    // 之后的阶段递归地处理语法树，太深的表达式会耗尽栈；超出上限时同 `node` 停下
+    fn deeper(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth <= self.max_depth {
+            return Ok(());
+        }
+        let span = self.current_span();
+        self.current = self.tokens.len().saturating_sub(1);
+        Err(ParseError::new(
+            format!(
+                "program too large: expressions nested more than {} levels deep",
+                self.max_depth
+            ),
+            span,
+        )
+        .with_code(ErrorCode::E0803))
+    }
+
+    // 解析嵌套的一部分，结束后（包括出错时）回到原来的层数；超出上限后保持不变，`parse` 据此
+    // 只报告上限
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let depth = self.depth;
+        let result = parse(self);
+        if self.depth <= self.max_depth {
+            self.depth = depth;
+        }
+        result
+    }
+
     fn is_at_end(&self) -> bool {
         matches!(self.current_token_kind(), TokenKind::Eof)
     }
@@ -1764,37 +3086,6 @@ impl Parser {
         }
     }
 
-    fn match_binary_op(&mut self, kinds: &[TokenKind]) -> Option<BinOp> {
-        for kind in kinds {
-            if self.check(kind) {
-                let op = match kind {
-                    TokenKind::Plus => BinOp::Add,
-                    TokenKind::Minus => BinOp::Sub,
-                    TokenKind::Star => BinOp::Mul,
-                    TokenKind::Slash => BinOp::Div,
-                    TokenKind::Percent => BinOp::Mod,
-                    TokenKind::Equal => BinOp::Equal,
-                    TokenKind::NotEqual => BinOp::NotEqual,
-                    TokenKind::Less => BinOp::Less,
-                    TokenKind::Greater => BinOp::Greater,
-                    TokenKind::LessEqual => BinOp::LessEqual,
-                    TokenKind::GreaterEqual => BinOp::GreaterEqual,
-                    TokenKind::LogicalAnd => BinOp::LogicalAnd,
-                    TokenKind::LogicalOr => BinOp::LogicalOr,
-                    TokenKind::BitwiseAnd => BinOp::BitwiseAnd,
-                    TokenKind::BitwiseOr => BinOp::BitwiseOr,
-                    TokenKind::BitwiseXor => BinOp::BitwiseXor,
-                    TokenKind::LeftShift => BinOp::LeftShift,
-                    TokenKind::RightShift => BinOp::RightShift,
-                    _ => continue,
-                };
-                self.advance();
-                return Some(op);
-            }
-        }
-        None
-    }
-
     fn peek_ahead(&self, n: usize) -> Option<&TokenKind> {
         if self.current + n < self.tokens.len() {
             Some(&self.tokens[self.current + n].kind)
@@ -1814,9 +3105,146 @@ impl Parser {
         }
     }
 
+    // 左定界符
+    // 索引中的表达式。切片的区间可以省略起点或终点：`a[..2]`、`a[2..]`、`a[..]`
+    fn parse_index(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.slice_depth.replace(self.delimiters.len());
+        let index = match self.check(&TokenKind::DotDot) || self.check(&TokenKind::DotDotEqual) {
+            true => self.parse_open_range(),
+            false => self.parse_expression(),
+        };
+        self.slice_depth = outer;
+        index
+    }
+
+    // 省略起点的区间 `..end`、`..=end` 和 `..`
+    fn parse_open_range(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        let inclusive = self.advance().kind == TokenKind::DotDotEqual;
+        let end = match !inclusive && self.at_slice_end() {
+            true => None,
+            false => Some(Box::new(self.parse_binary(101)?)),
+        };
+        let span = self.span_from(start);
+        Ok(Expr::Range(None, end, inclusive, span))
+    }
+
+    // 当前记号是正在解析的索引的 `]`
+    fn at_slice_end(&self) -> bool {
+        self.slice_depth == Some(self.delimiters.len()) && self.check(&TokenKind::RightBracket)
+    }
+
+    fn open(&mut self, kind: TokenKind, message: &str) -> Result<(), ParseError> {
+        self.consume(kind, message)?;
+        self.opened();
+        Ok(())
+    }
+
+    // 记录刚消耗的左定界符，缺少对应的右定界符时指向它
+    fn opened(&mut self) {
+        let token = self.previous();
+        let delimiter = (token.kind.clone(), token.span);
+        self.delimiters.push(delimiter);
+    }
+
+    // 右定界符。缺少时如果后面明显已经不属于这对定界符（文件结尾、下一个条目、
+    // 外层的右定界符或语句结尾的 `;`），报告没有闭合的左定界符，
+    // 当作右定界符已经插入继续解析
+    fn close(&mut self, kind: TokenKind, message: &str) -> Result<(), ParseError> {
+        let open = self.delimiters.pop();
+        if self.check(&kind) {
+            self.advance();
+            return Ok(());
+        }
+        match open {
+            Some((open, span)) if self.missing_close(&kind) => {
+                // 同一处补上多个右定界符时只报告最内层的一个
+                if self.recovered_at != Some(self.current) {
+                    self.recovered_at = Some(self.current);
+                    let error = self.unclosed(&open, &kind, span);
+                    self.errors.push(error);
+                }
+                Ok(())
+            }
+            _ => self.consume(kind, message).map(|_| ()),
+        }
+    }
+
+    fn missing_close(&self, kind: &TokenKind) -> bool {
+        let current = self.current_token_kind();
+        self.is_at_end()
+            || self.at_item_start()
+            || (*current == TokenKind::Semicolon && *kind != TokenKind::RightBrace)
+            || self
+                .delimiters
+                .iter()
+                .any(|(open, _)| closing_delimiter(open).as_ref() == Some(current))
+    }
+
+    // 逗号分隔的列表中一项之后：读到逗号时返回 true，继续读下一项（调用者在循环开头检查右定界符，
+    // 所以末尾的逗号总是允许的）；列表在这里结束时返回 false。
+    // 既不是逗号也不是右定界符的记号说明漏写了逗号；是其他右定界符或 `;` 时留给 `close` 报告
+    fn list_separator(&mut self, close: &TokenKind) -> Result<bool, ParseError> {
+        if self.match_token(&TokenKind::Comma) {
+            return Ok(true);
+        }
+        if self.check(close)
+            || self.is_at_end()
+            || self.at_closing_angle()
+            || matches!(
+                self.current_token_kind(),
+                TokenKind::RightParen
+                    | TokenKind::RightBracket
+                    | TokenKind::RightBrace
+                    | TokenKind::Semicolon
+            )
+        {
+            return Ok(false);
+        }
+        let found = self.current_token_kind();
+        Err(ParseError::new(
+            format!("expected `,` or `{}`, found `{}`", close, found),
+            self.current_span(),
+        )
+        .with_help(format!(
+            "insert `,` before `{}` on line {}",
+            found,
+            self.current_span().line
+        )))
+    }
+
+    fn unclosed(&self, open: &TokenKind, close: &TokenKind, span: Span) -> ParseError {
+        let place = if self.is_at_end() {
+            "at the end of the file".to_string()
+        } else {
+            format!(
+                "before `{}` on line {}",
+                self.current_token_kind(),
+                self.current_span().line
+            )
+        };
+        ParseError::new(
+            format!("unclosed `{}` opened here on line {}", open, span.line),
+            span,
+        )
+        .with_code(ErrorCode::E0102)
+        .with_help(format!("insert `{}` {}", close, place))
+    }
+
+    // 上下文关键字：只在特定位置作为关键字，其他地方仍然是普通标识符
+    fn match_contextual(&mut self, keyword: &str) -> bool {
+        if matches!(self.current_token_kind(), TokenKind::Ident(name) if name == keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
     fn expect_ident(&mut self, message: &str) -> Result<String, ParseError> {
         if let TokenKind::Ident(name) = self.current_token_kind() {
-            let name = name.clone();
+            let (name, span) = (name.to_string(), self.current_span());
+            self.check_reserved(&name, span);
             self.advance();
             Ok(name)
         } else {
@@ -1827,30 +3255,291 @@ impl Parser {
         }
     }
 
-    fn synchronize(&mut self) {
-        self.panic_mode = false;
-        self.advance();
+    // 跳到下一个条目的开头。出错的条目一个记号也没有消耗时先跳过一个，保证向前推进
+    fn synchronize(&mut self, start: usize) {
+        self.delimiters.clear();
+        if self.current == start {
+            self.advance();
+        }
+        while !self.is_at_end() && !self.at_item_start() && !self.at_macro_rules() {
+            self.advance();
+        }
+    }
 
-        while !self.is_at_end() {
-            if matches!(self.previous().kind, TokenKind::Semicolon) {
-                return;
-            }
+    fn at_macro_rules(&self) -> bool {
+        matches!(self.current_token_kind(), TokenKind::Ident(name) if name == "macro_rules")
+            && self.peek_ahead(1) == Some(&TokenKind::LogicalNot)
+    }
 
-            match self.current_token_kind() {
-                TokenKind::Fn
-                | TokenKind::Struct
-                | TokenKind::Enum
-                | TokenKind::Let
-                | TokenKind::If
-                | TokenKind::While
-                | TokenKind::For
-                | TokenKind::Return
-                | TokenKind::Match => return,
-                _ => {}
-            }
+    // `name!` 之后是左定界符
+    fn at_macro_call(&self) -> bool {
+        matches!(self.current_token_kind(), TokenKind::Ident(_))
+            && self.peek_ahead(1) == Some(&TokenKind::LogicalNot)
+            && self
+                .peek_ahead(2)
+                .is_some_and(|kind| closing_delimiter(kind).is_some())
+    }
 
-            self.advance();
+    // `asm!(`，内建的内联汇编，不是宏调用
+    fn at_inline_asm(&self) -> bool {
+        matches!(self.current_token_kind(), TokenKind::Ident(name) if name == "asm")
+            && self.peek_ahead(1) == Some(&TokenKind::LogicalNot)
+            && self.peek_ahead(2) == Some(&TokenKind::LeftParen)
+    }
+
+    // `pure fn`、`pure async fn`
+    fn at_pure_fn(&self) -> bool {
+        matches!(self.current_token_kind(), TokenKind::Ident(name) if name == "pure")
+            && (self.peek_ahead(1) == Some(&TokenKind::Fn)
+                || matches!(self.peek_ahead(1), Some(TokenKind::Ident(name)) if name == "async"))
+    }
+
+    // 函数类型 `fn(T) -> U` 或 `pure fn(T) -> U`
+    fn at_function_type(&self) -> bool {
+        match self.current_token_kind() {
+            TokenKind::Fn => true,
+            TokenKind::Ident(name) => name == "pure" && self.peek_ahead(1) == Some(&TokenKind::Fn),
+            _ => false,
+        }
+    }
+
+    // `async fn`
+    fn at_async_fn(&self) -> bool {
+        matches!(self.current_token_kind(), TokenKind::Ident(name) if name == "async")
+            && self.peek_ahead(1) == Some(&TokenKind::Fn)
+    }
+
+    // `operator` 之后是运算符记号；`operator!` 是宏调用
+    fn at_operator_declaration(&self) -> bool {
+        matches!(self.current_token_kind(), TokenKind::Ident(name) if name == "operator")
+            && self
+                .peek_ahead(1)
+                .is_some_and(|kind| is_operator_token(kind) && *kind != TokenKind::LogicalNot)
+    }
+
+    // 从 `start` 开始紧挨着的运算符记号的个数
+    fn operator_run(&self, start: usize) -> usize {
+        let mut end = start;
+        while end < self.tokens.len()
+            && is_operator_token(&self.tokens[end].kind)
+            && (end == start || self.tokens[end - 1].span.end == self.tokens[end].span.start)
+        {
+            end += 1;
+        }
+        end - start
+    }
+
+    // 当前位置的自定义运算符和它占的记号数，开头相同的几个运算符取最长的一个
+    fn custom_operator(&mut self) -> Option<(CustomOperator, usize)> {
+        if self.operators.is_empty() && !self.unknown_operators {
+            return None;
+        }
+        let run = &self.tokens[self.current..self.current + self.operator_run(self.current)];
+        let mut found: Option<&CustomOperator> = None;
+        for operator in &self.operators {
+            let matches = operator.symbol.len() <= run.len()
+                && operator
+                    .symbol
+                    .iter()
+                    .zip(run)
+                    .all(|(kind, token)| token.kind == *kind);
+            if matches && found.is_none_or(|longest| operator.symbol.len() > longest.symbol.len()) {
+                found = Some(operator);
+            }
+        }
+
+        let Some(operator) = found.cloned() else {
+            // 只检查语法时不知道导入的运算符，任何不是内建运算符的写法都可能是
+            let unknown = self.unknown_operators
+                && self.features.contains(&Feature::CustomOperators)
+                && (run.len() > 1
+                    || run
+                        .first()
+                        .is_some_and(|token| binding_power(&token.kind).is_none()));
+            if !unknown {
+                return None;
+            }
+            let symbol: Vec<TokenKind> = run.iter().map(|token| token.kind.clone()).collect();
+            let operator = CustomOperator {
+                function: vec![symbol.iter().map(TokenKind::to_string).collect()],
+                symbol,
+                precedence: *OPERATOR_PRECEDENCE.start(),
+                associativity: Associativity::Left,
+                public: false,
+            };
+            return Some((operator, run.len()));
+        };
+
+        // 两个导入的模块声明了同样的运算符
+        if let Some(other) = self
+            .operators
+            .iter()
+            .find(|other| other.symbol == operator.symbol && other.function != operator.function)
+        {
+            let error = ParseError::new(
+                format!("operator `{}` is ambiguous", operator.spelling()),
+                self.current_span(),
+            )
+            .with_code(ErrorCode::E0659)
+            .with_help(format!(
+                "it is implemented by both `{}` and `{}`",
+                operator.function.join("::"),
+                other.function.join("::")
+            ));
+            self.errors.push(error);
         }
+        let length = operator.symbol.len();
+        Some((operator, length))
+    }
+
+    fn at_item_start(&self) -> bool {
+        self.at_operator_declaration()
+            || self.at_async_fn()
+            || matches!(
+                self.current_token_kind(),
+                TokenKind::Fn
+                    | TokenKind::Struct
+                    | TokenKind::Enum
+                    | TokenKind::Const
+                    | TokenKind::Static
+                    | TokenKind::Import
+                    | TokenKind::Export
+                    | TokenKind::Pub
+                    | TokenKind::Hash
+            )
+    }
+}
+
+// 二元运算符的绑定力 `(左, 右)`，从低到高和 grammar.rs 中表达式规则的顺序一致。
+// 左结合的运算符右边的绑定力高一，右结合的赋值左边高一；`as` 的右边是类型，不用右绑定力。
+// 各层相隔 10，自定义运算符按声明的优先级排在它们之间（`CustomOperator::binding_power`）。
+// 连用的比较和区间在 `parse_binary` 中处理
+fn binding_power(kind: &TokenKind) -> Option<(u8, u8)> {
+    let power = match kind {
+        TokenKind::Assign
+        | TokenKind::PlusAssign
+        | TokenKind::MinusAssign
+        | TokenKind::StarAssign
+        | TokenKind::SlashAssign
+        | TokenKind::PercentAssign
+        | TokenKind::BitAndAssign
+        | TokenKind::BitOrAssign
+        | TokenKind::BitXorAssign
+        | TokenKind::LeftShiftAssign
+        | TokenKind::RightShiftAssign => (11, 10),
+        TokenKind::LogicalOr => (20, 21),
+        TokenKind::LogicalAnd => (30, 31),
+        TokenKind::BitwiseOr => (40, 41),
+        TokenKind::BitwiseXor => (50, 51),
+        TokenKind::BitwiseAnd => (60, 61),
+        TokenKind::Equal | TokenKind::NotEqual => (70, 71),
+        TokenKind::Less | TokenKind::Greater | TokenKind::LessEqual | TokenKind::GreaterEqual => {
+            (80, 81)
+        }
+        TokenKind::LeftShift | TokenKind::RightShift => (90, 91),
+        TokenKind::DotDot | TokenKind::DotDotEqual => (100, 101),
+        TokenKind::Plus | TokenKind::Minus => (110, 111),
+        TokenKind::Star | TokenKind::Slash | TokenKind::Percent => (120, 121),
+        TokenKind::As => (130, 131),
+        _ => return None,
+    };
+    Some(power)
+}
+
+// 可以组成自定义运算符的记号
+pub(crate) fn is_operator_token(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Star
+            | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Assign
+            | TokenKind::PlusAssign
+            | TokenKind::MinusAssign
+            | TokenKind::StarAssign
+            | TokenKind::SlashAssign
+            | TokenKind::PercentAssign
+            | TokenKind::BitAndAssign
+            | TokenKind::BitOrAssign
+            | TokenKind::BitXorAssign
+            | TokenKind::LeftShiftAssign
+            | TokenKind::RightShiftAssign
+            | TokenKind::Equal
+            | TokenKind::NotEqual
+            | TokenKind::Less
+            | TokenKind::Greater
+            | TokenKind::LessEqual
+            | TokenKind::GreaterEqual
+            | TokenKind::LogicalAnd
+            | TokenKind::LogicalOr
+            | TokenKind::LogicalNot
+            | TokenKind::BitwiseAnd
+            | TokenKind::BitwiseOr
+            | TokenKind::BitwiseXor
+            | TokenKind::BitwiseNot
+            | TokenKind::LeftShift
+            | TokenKind::RightShift
+            | TokenKind::Arrow
+    )
+}
+
+/// `tokens` 中声明的自定义运算符。模块加载器用它收集导入的模块中公开的运算符，
+/// 交给导入它们的文件的解析器（`Parser::with_operators`）
+pub fn declared_operators(tokens: Vec<Token>) -> Vec<CustomOperator> {
+    Parser::new(tokens).scan_operators()
+}
+
+fn binary_op(kind: &TokenKind) -> Option<BinOp> {
+    let op = match kind {
+        TokenKind::Plus => BinOp::Add,
+        TokenKind::Minus => BinOp::Sub,
+        TokenKind::Star => BinOp::Mul,
+        TokenKind::Slash => BinOp::Div,
+        TokenKind::Percent => BinOp::Mod,
+        TokenKind::Equal => BinOp::Equal,
+        TokenKind::NotEqual => BinOp::NotEqual,
+        TokenKind::Less => BinOp::Less,
+        TokenKind::Greater => BinOp::Greater,
+        TokenKind::LessEqual => BinOp::LessEqual,
+        TokenKind::GreaterEqual => BinOp::GreaterEqual,
+        TokenKind::LogicalAnd => BinOp::LogicalAnd,
+        TokenKind::LogicalOr => BinOp::LogicalOr,
+        TokenKind::BitwiseAnd => BinOp::BitwiseAnd,
+        TokenKind::BitwiseOr => BinOp::BitwiseOr,
+        TokenKind::BitwiseXor => BinOp::BitwiseXor,
+        TokenKind::LeftShift => BinOp::LeftShift,
+        TokenKind::RightShift => BinOp::RightShift,
+        _ => return None,
+    };
+    Some(op)
+}
+
+// 复合赋值运算符
+fn compound_assign_op(kind: &TokenKind) -> Option<BinOp> {
+    let op = match kind {
+        TokenKind::PlusAssign => BinOp::Add,
+        TokenKind::MinusAssign => BinOp::Sub,
+        TokenKind::StarAssign => BinOp::Mul,
+        TokenKind::SlashAssign => BinOp::Div,
+        TokenKind::PercentAssign => BinOp::Mod,
+        TokenKind::BitAndAssign => BinOp::BitwiseAnd,
+        TokenKind::BitOrAssign => BinOp::BitwiseOr,
+        TokenKind::BitXorAssign => BinOp::BitwiseXor,
+        TokenKind::LeftShiftAssign => BinOp::LeftShift,
+        TokenKind::RightShiftAssign => BinOp::RightShift,
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn closing_delimiter(open: &TokenKind) -> Option<TokenKind> {
+    match open {
+        TokenKind::LeftParen => Some(TokenKind::RightParen),
+        TokenKind::LeftBracket => Some(TokenKind::RightBracket),
+        TokenKind::LeftBrace => Some(TokenKind::RightBrace),
+        _ => None,
     }
 }
 
@@ -1860,23 +3549,26 @@ impl Expr {
         match self {
             Expr::Literal(_, span) => *span,
             Expr::Ident(_, span) => *span,
-            Expr::Binary(_, _, _, span) => *span,
-            Expr::Unary(_, _, span) => *span,
-            Expr::Call(_, _, span) => *span,
-            Expr::MethodCall(_, _, _, span) => *span,
+            Expr::Path(_, span) => *span,
+            Expr::Turbofish(_, _, span) => *span,
+            Expr::Binary(_, _, _, span, _) => *span,
+            Expr::Unary(_, _, span, _) => *span,
+            Expr::Call(_, _, span, _) => *span,
+            Expr::MethodCall(_, _, _, span, _) => *span,
             Expr::FieldAccess(_, _, span) => *span,
+            Expr::TupleIndex(_, _, span) => *span,
             Expr::IndexAccess(_, _, span) => *span,
             Expr::StructLit(_, _, span) => *span,
             Expr::ArrayLit(_, span) => *span,
             Expr::TupleLit(_, span) => *span,
             Expr::Range(_, _, _, span) => *span,
             Expr::Assign(_, _, span) => *span,
-            Expr::CompoundAssign(_, _, _, span) => *span,
-            Expr::Block(_, span) => *span,
+            Expr::CompoundAssign(_, _, _, span, _) => *span,
+            Expr::Block(_, span) | Expr::Unsafe(_, span) | Expr::AsyncBlock(_, span) => *span,
             Expr::If(_, _, _, span) => *span,
             Expr::Match(_, _, span) => *span,
-            Expr::While(_, _, span) => *span,
-            Expr::For(_, _, _, span) => *span,
+            Expr::While(_, _, _, span) => *span,
+            Expr::For(_, _, _, _, span) => *span,
             Expr::Break(_, _, span) => *span,
             Expr::Continue(_, span) => *span,
             Expr::Return(_, span) => *span,
@@ -1884,6 +3576,26 @@ impl Expr {
             Expr::Cast(_, _, span) => *span,
             Expr::Ref(_, _, span) => *span,
             Expr::Deref(_, span) => *span,
+            Expr::Try(_, span) | Expr::Await(_, span) => *span,
+            Expr::MacroCall(call) => call.span,
+            Expr::InlineAsm(_, span) => *span,
+        }
+    }
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let(stmt) => stmt.span,
+            Statement::Expr(stmt) => stmt.span,
+            Statement::Return(stmt) => stmt.span,
+            Statement::If(stmt) => stmt.span,
+            Statement::While(stmt) => stmt.span,
+            Statement::For(stmt) => stmt.span,
+            Statement::Match(stmt) => stmt.span,
+            Statement::Break(stmt) => stmt.span,
+            Statement::Continue(stmt) => stmt.span,
+            Statement::Block(block) => block.span,
         }
     }
 }
@@ -2171,12 +3883,31 @@ mod tests {
     "#;
         assert!(parse_program(valid).is_ok());
 
-        // Should fail - break outside loop
-        let invalid = r#"
+        // 循环之外的 break 由语义分析报告，语法上是合法的
+        let outside = r#"
         fn test() {
             break;
         }
     "#;
-        assert!(parse_program(invalid).is_err());
+        assert!(parse_program(outside).is_ok());
+
+        // 标签只能放在循环前面
+        let labeled = r#"
+        fn test() {
+            'outer: for i in 0..3 {
+                'inner: while true {
+                    break 'outer;
+                }
+                continue 'outer;
+            }
+        }
+    "#;
+        assert!(parse_program(labeled).is_ok());
+        let misplaced = r#"
+        fn test() {
+            'outer: let x = 1;
+        }
+    "#;
+        assert!(parse_program(misplaced).is_err());
     }
 }
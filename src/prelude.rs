@@ -0,0 +1,110 @@
+// 预导入（prelude）：编译器内置的 `Option<T>` 和 `Result<T, E>`
+// 定义写在 prelude.ctx 中，编译进编译器，第一次使用时解析。语义分析、解释器和 MIR 降级
+// 都把这些条目放在程序自己的条目之前，程序中定义的同名条目优先。
+// `expr?` 展开为对这两个枚举的 match：取出 `Some`/`Ok` 中的值，
+// 遇到 `None`/`Err(e)` 时从所在函数原样返回，所以函数必须返回同一种枚举
+
+use crate::ast::{Expr, Item, MatchArm, OperandType, Pattern, Program, Type};
+use crate::lexer::Lexer;
+use crate::module::item_name;
+use crate::parser::Parser;
+use crate::span::Span;
+use std::sync::OnceLock;
+
+pub const SOURCE: &str = include_str!("prelude.ctx");
+
+pub fn program() -> &'static Program {
+    static PROGRAM: OnceLock<Program> = OnceLock::new();
+    PROGRAM.get_or_init(|| {
+        let tokens = Lexer::new(SOURCE).tokenize().expect("invalid prelude");
+        Parser::new(tokens).parse().expect("invalid prelude")
+    })
+}
+
+/// `program` 没有定义同名条目的预导入条目
+pub fn items_for(program: &Program) -> impl Iterator<Item = &Item> {
+    let items: &[Item] = &self::program().items;
+    items.iter().filter(|item| {
+        let name = item_name(item);
+        !program.items.iter().any(|own| item_name(own) == name)
+    })
+}
+
+/// `?` 可以作用的枚举
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryKind {
+    Option,
+    Result,
+}
+
+impl TryKind {
+    pub fn of(ty: &Type) -> Option<TryKind> {
+        let (Type::Named(name) | Type::Generic(name, _)) = ty else {
+            return None;
+        };
+        match name.as_str() {
+            "Option" => Some(TryKind::Option),
+            "Result" => Some(TryKind::Result),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TryKind::Option => "Option",
+            TryKind::Result => "Result",
+        }
+    }
+
+    /// `expr?` 的值的类型：`Option<T>` 和 `Result<T, E>` 中的 `T`
+    pub fn output(ty: &Type) -> Option<Type> {
+        match ty {
+            Type::Generic(_, args) if TryKind::of(ty).is_some() => {
+                args.first().filter(|ty| **ty != Type::Infer).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// 展开后的分支：
+    /// `Some(v) => v, None => return None` 或 `Ok(v) => v, Err(e) => return Err(e)`
+    pub fn arms(self, span: Span) -> Vec<MatchArm> {
+        let ident = |name: &str| Expr::Ident(name.to_string(), span);
+        let arm = |pattern, body| MatchArm {
+            pattern,
+            guard: None,
+            body,
+            span,
+        };
+        let value = "__try_value";
+        let ret = |value| Expr::Return(Some(Box::new(value)), span);
+        match self {
+            TryKind::Option => vec![
+                arm(
+                    Pattern::TupleStruct("Some".to_string(), vec![Pattern::Ident(value.into())]),
+                    ident(value),
+                ),
+                arm(Pattern::Ident("None".to_string()), ret(ident("None"))),
+            ],
+            TryKind::Result => {
+                let error = "__try_error";
+                let err = Expr::Call(
+                    Box::new(ident("Err")),
+                    vec![ident(error)],
+                    span,
+                    OperandType::default(),
+                );
+                vec![
+                    arm(
+                        Pattern::TupleStruct("Ok".to_string(), vec![Pattern::Ident(value.into())]),
+                        ident(value),
+                    ),
+                    arm(
+                        Pattern::TupleStruct("Err".to_string(), vec![Pattern::Ident(error.into())]),
+                        ret(err),
+                    ),
+                ]
+            }
+        }
+    }
+}
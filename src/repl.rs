@@ -0,0 +1,364 @@
+// Contractus 交互式环境 (REPL)
+// 每次输入是一组条目（fn/struct/enum/const/static）或一组语句：
+// - 条目加入会话的程序，同名条目覆盖旧定义，整个程序重新做语义分析
+// - 语句在解释器的同一个顶层环境中执行，let 绑定在输入之间保留；
+//   最后一个表达式的值不是 `()` 时输出
+// 括号没有配对或字符串没有结束时继续读取下一行
+// 以 `:` 开头的是 REPL 命令，见 HELP
+
+mod editor;
+mod history;
+
+pub use editor::{LineEditor, ReadLine};
+pub use history::{History, HISTORY_LIMIT};
+
+use crate::ast::{Item, Program};
+use crate::derive;
+use crate::diagnostic::ErrorCode;
+use crate::interp::{Env, Interpreter, Value};
+use crate::lexer::Lexer;
+use crate::macros;
+use crate::module::item_name;
+use crate::parser::Parser;
+use crate::sema::SemanticAnalyzer;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+use std::env;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+const HELP: &str = "\
+Commands:
+  :type <expr>   show the type of an expression without evaluating it
+  :ast <expr>    show the syntax tree of an expression
+  :history       list previous inputs
+  :help          show this message
+  :quit          exit the REPL (also Ctrl-D)";
+
+/// 处理完一次输入后是否继续
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Continue,
+    Quit,
+}
+
+pub struct Repl<W: Write> {
+    program: Program,           // 已定义的条目
+    analyzer: SemanticAnalyzer, // 记住顶层 let 绑定的名字和类型
+    env: Env,
+    history: History,
+    output: W,
+}
+
+impl<W: Write> Repl<W> {
+    pub fn new(output: W) -> Self {
+        Self::with_history(output, History::new())
+    }
+
+    pub fn with_history(output: W, history: History) -> Self {
+        Self {
+            program: Program {
+                attributes: Vec::new(),
+                items: Vec::new(),
+                span: Span::new(0, 0, 1, 1),
+            },
+            analyzer: SemanticAnalyzer::new(),
+            env: Env::new(),
+            history,
+            output,
+        }
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// 顶层变量的当前值
+    pub fn binding(&self, name: &str) -> Option<Value> {
+        self.env.get(name)
+    }
+
+    /// 处理一次完整的输入，错误输出后会话继续
+    pub fn eval(&mut self, input: &str) -> io::Result<Action> {
+        let trimmed = input.trim();
+        if trimmed.is_empty() {
+            return Ok(Action::Continue);
+        }
+        self.history.push(input);
+
+        if let Some(command) = trimmed.strip_prefix(':') {
+            return self.command(command);
+        }
+
+        let tokens = match Lexer::new(input).tokenize() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                self.report(errors)?;
+                return Ok(Action::Continue);
+            }
+        };
+        if starts_item(&tokens) {
+            self.define_items(tokens)?;
+        } else {
+            self.eval_statements(tokens)?;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn command(&mut self, command: &str) -> io::Result<Action> {
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, arg)| (name, arg.trim()));
+        match name {
+            "q" | "quit" | "exit" => return Ok(Action::Quit),
+            "h" | "help" => writeln!(self.output, "{}", HELP)?,
+            "history" => {
+                for (i, entry) in self.history.iter().enumerate() {
+                    writeln!(
+                        self.output,
+                        "{:>4}  {}",
+                        i + 1,
+                        entry.replace('\n', "\n      ")
+                    )?;
+                }
+            }
+            "t" | "type" => {
+                if let Some(expr) = self.parse_expr(arg)? {
+                    match self.analyzer.analyze_expr_type(&self.program, &expr) {
+                        Ok(Some(ty)) => writeln!(self.output, "{}", ty)?,
+                        Ok(None) => {
+                            writeln!(self.output, "error: cannot infer the type of `{}`", arg)?
+                        }
+                        Err(errors) => self.report(errors)?,
+                    }
+                }
+            }
+            "ast" => {
+                if let Some(expr) = self.parse_expr(arg)? {
+                    writeln!(self.output, "{:#?}", expr)?;
+                }
+            }
+            _ => writeln!(
+                self.output,
+                "error: unknown command `:{}`; type `:help` for a list of commands",
+                name
+            )?,
+        }
+        Ok(Action::Continue)
+    }
+
+    fn parse_expr(&mut self, input: &str) -> io::Result<Option<crate::ast::Expr>> {
+        if input.is_empty() {
+            writeln!(
+                self.output,
+                "error: expected an expression after the command"
+            )?;
+            return Ok(None);
+        }
+        let tokens = match Lexer::new(input).tokenize() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                self.report(errors)?;
+                return Ok(None);
+            }
+        };
+        match Parser::new(tokens).parse_standalone_expression() {
+            Ok(expr) => Ok(Some(expr)),
+            Err(errors) => {
+                self.report(errors)?;
+                Ok(None)
+            }
+        }
+    }
+
+    // 新条目覆盖同名的旧条目；语义分析失败时会话的程序保持不变
+    fn define_items(&mut self, tokens: Vec<Token>) -> io::Result<()> {
+        let parsed = match Parser::new(tokens).parse() {
+            Ok(program) => program.items,
+            Err(errors) => return self.report(errors),
+        };
+        // 新定义的宏覆盖同名的旧宏，新条目用会话中的宏展开
+        let redefined: Vec<&str> = parsed.iter().filter_map(macro_name).collect();
+        let (macros, old_items): (Vec<Item>, Vec<Item>) = self
+            .program
+            .items
+            .iter()
+            .filter(|item| macro_name(item).is_none_or(|name| !redefined.contains(&name)))
+            .cloned()
+            .partition(|item| matches!(item, Item::MacroRules(_)));
+        let program = Program {
+            attributes: Vec::new(),
+            items: macros.into_iter().chain(parsed).collect(),
+            span: self.program.span,
+        };
+        let new_items = match macros::expand(program, &[]).and_then(derive::expand) {
+            Ok(program) => program.items,
+            Err(errors) => return self.report(errors),
+        };
+        if new_items
+            .iter()
+            .any(|item| matches!(item, Item::Import(_) | Item::Export(_)))
+        {
+            writeln!(
+                self.output,
+                "error: `import` and `export` are not supported in the REPL"
+            )?;
+            return Ok(());
+        }
+
+        let names: Vec<&str> = new_items.iter().filter_map(item_name).collect();
+        let mut items: Vec<Item> = old_items
+            .into_iter()
+            .filter(|item| item_name(item).is_none_or(|name| !names.contains(&name)))
+            .collect();
+        items.extend(new_items.iter().cloned());
+        let program = Program {
+            attributes: Vec::new(),
+            items,
+            span: self.program.span,
+        };
+        if let Err(errors) = SemanticAnalyzer::new().analyze(&program) {
+            return self.report(errors);
+        }
+
+        // 重新定义的 const/static 下次使用时按新定义初始化
+        for name in &names {
+            self.env.forget_global(name);
+        }
+        self.program = program;
+        Ok(())
+    }
+
+    fn eval_statements(&mut self, tokens: Vec<Token>) -> io::Result<()> {
+        let statements = match Parser::new(tokens).parse_statements() {
+            Ok(statements) => statements,
+            Err(errors) => return self.report(errors),
+        };
+        let statements = match macros::expand_statements(&self.program, statements) {
+            Ok(statements) => statements,
+            Err(errors) => return self.report(errors),
+        };
+        if let Err(errors) = self.analyzer.analyze_statements(&self.program, &statements) {
+            return self.report(errors);
+        }
+
+        let mut interpreter = Interpreter::with_output(&self.program, &mut self.output);
+        let result = interpreter.eval_statements(&mut self.env, &statements);
+        drop(interpreter);
+        match result {
+            Ok(Value::Unit) => Ok(()),
+            Ok(value) => writeln!(self.output, "{}", value),
+            Err(errors) => self.report(errors),
+        }
+    }
+
+    fn report<E: Display>(&mut self, errors: Vec<E>) -> io::Result<()> {
+        for error in errors {
+            let text = error.to_string();
+            // 词法错误只有消息，没有 `error` 前缀
+            if text.starts_with("error") {
+                writeln!(self.output, "{}", text)?;
+            } else {
+                writeln!(self.output, "error: {}", text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn macro_name(item: &Item) -> Option<&str> {
+    match item {
+        Item::MacroRules(def) => Some(&def.name),
+        _ => None,
+    }
+}
+
+// 以可见性或条目关键字开头的输入是条目定义，`macro_rules!` 也是
+fn starts_item(tokens: &[Token]) -> bool {
+    if let [first, second, ..] = tokens {
+        if first.kind == TokenKind::Ident("macro_rules".into())
+            && second.kind == TokenKind::LogicalNot
+        {
+            return true;
+        }
+    }
+    matches!(
+        tokens.first().map(|token| &token.kind),
+        Some(
+            TokenKind::Pub
+                | TokenKind::Hash
+                | TokenKind::Fn
+                | TokenKind::Struct
+                | TokenKind::Enum
+                | TokenKind::Const
+                | TokenKind::Static
+                | TokenKind::Import
+                | TokenKind::Export
+        )
+    )
+}
+
+/// 输入是否还需要后续的行：括号没有配对，或者字符串、字符字面量没有结束
+pub fn is_incomplete(input: &str) -> bool {
+    let tokens = match Lexer::new(input).tokenize() {
+        Ok(tokens) => tokens,
+        Err(errors) => return errors.iter().any(|error| error.code == ErrorCode::E0001),
+    };
+    let mut depth = 0i32;
+    for token in &tokens {
+        match token.kind {
+            TokenKind::LeftBrace | TokenKind::LeftParen | TokenKind::LeftBracket => depth += 1,
+            TokenKind::RightBrace | TokenKind::RightParen | TokenKind::RightBracket => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+// 历史保存在主目录下
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".contractus_history"))
+}
+
+/// 在终端上运行 REPL，直到 `:quit` 或输入结束
+pub fn run_interactive() -> io::Result<()> {
+    let history = history_path().map(History::load).unwrap_or_default();
+    let mut repl = Repl::with_history(io::stdout(), history);
+    let mut editor = LineEditor::new();
+
+    println!("Contractus REPL v0.1.0 (type `:help` for commands)");
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { ">> " } else { ".. " };
+        match editor.read_line(prompt, repl.history())? {
+            ReadLine::Line(line) => {
+                if !buffer.is_empty() {
+                    buffer.push('\n');
+                }
+                buffer.push_str(&line);
+                if is_incomplete(&buffer) {
+                    continue;
+                }
+                let input = std::mem::take(&mut buffer);
+                if repl.eval(&input)? == Action::Quit {
+                    break;
+                }
+                repl.output.flush()?;
+            }
+            ReadLine::Interrupted => buffer.clear(),
+            ReadLine::Eof => {
+                // 输入在多行输入的中途结束时，照常处理已读到的部分
+                if !buffer.is_empty() {
+                    repl.eval(&buffer)?;
+                }
+                break;
+            }
+        }
+    }
+    repl.history.save()
+}
@@ -0,0 +1,312 @@
+// REPL 的行编辑器
+// 标准输入输出都是终端时，用 `stty` 切换到原始模式逐键处理：
+// - 左右方向键、Home/End 和 Emacs 风格的 Ctrl-A/E/B/F 移动光标
+// - 退格、Delete、Ctrl-D/U/K/W 删除字符、行首、行尾和前一个单词
+// - 上下方向键和 Ctrl-P/N 翻阅历史，Ctrl-C 放弃当前输入
+// 不是终端（管道、重定向）或 `stty` 不可用时退化为逐行读取
+
+use super::history::History;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+/// 读取一行的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadLine {
+    Line(String),
+    Interrupted, // Ctrl-C
+    Eof,         // Ctrl-D 或输入结束
+}
+
+pub struct LineEditor {
+    terminal: bool,
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self {
+            terminal: io::stdin().is_terminal() && io::stdout().is_terminal(),
+        }
+    }
+
+    pub fn read_line(&mut self, prompt: &str, history: &History) -> io::Result<ReadLine> {
+        if !self.terminal {
+            return read_plain_line();
+        }
+        match RawMode::enable() {
+            Some(_raw) => Editing::new(prompt, history).run(),
+            None => {
+                print!("{}", prompt);
+                io::stdout().flush()?;
+                read_plain_line()
+            }
+        }
+    }
+}
+
+fn read_plain_line() -> io::Result<ReadLine> {
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(ReadLine::Eof);
+    }
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok(ReadLine::Line(line))
+}
+
+// 终端的原始模式，离开作用域时恢复原来的设置
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["raw", "-echo"])?;
+        Some(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[self.saved.as_str()]);
+    }
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// 一次编辑中的状态
+struct Editing<'a> {
+    prompt: &'a str,
+    history: &'a History,
+    buffer: Vec<char>,
+    cursor: usize,
+    history_index: usize, // 等于历史长度时表示正在编辑的新输入
+    draft: Vec<char>,     // 翻阅历史前正在编辑的内容
+}
+
+impl<'a> Editing<'a> {
+    fn new(prompt: &'a str, history: &'a History) -> Self {
+        Self {
+            prompt,
+            history,
+            buffer: Vec::new(),
+            cursor: 0,
+            history_index: history.len(),
+            draft: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> io::Result<ReadLine> {
+        let mut input = io::stdin().lock();
+        loop {
+            self.refresh()?;
+            let Some(byte) = read_byte(&mut input)? else {
+                return self.finish(ReadLine::Eof);
+            };
+            match byte {
+                b'\r' | b'\n' => {
+                    let line = self.buffer.iter().collect();
+                    return self.finish(ReadLine::Line(line));
+                }
+                3 => {
+                    print!("^C");
+                    return self.finish(ReadLine::Interrupted);
+                }
+                4 if self.buffer.is_empty() => return self.finish(ReadLine::Eof),
+                4 => self.delete(),
+                1 => self.cursor = 0,
+                5 => self.cursor = self.buffer.len(),
+                2 => self.cursor = self.cursor.saturating_sub(1),
+                6 => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+                16 => self.history_prev(),
+                14 => self.history_next(),
+                21 => {
+                    self.buffer.drain(..self.cursor);
+                    self.cursor = 0;
+                }
+                11 => self.buffer.truncate(self.cursor),
+                23 => self.delete_word(),
+                8 | 127 => self.backspace(),
+                b'\t' => {
+                    for _ in 0..4 {
+                        self.insert(' ');
+                    }
+                }
+                27 => self.escape_sequence(&mut input)?,
+                byte if byte >= 0x20 => {
+                    if let Some(c) = read_char(&mut input, byte)? {
+                        self.insert(c);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // 方向键等按键以 ESC [ 或 ESC O 开头
+    fn escape_sequence(&mut self, input: &mut impl Read) -> io::Result<()> {
+        if !matches!(read_byte(input)?, Some(b'[' | b'O')) {
+            return Ok(());
+        }
+        match read_byte(input)? {
+            Some(b'A') => self.history_prev(),
+            Some(b'B') => self.history_next(),
+            Some(b'C') => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            Some(b'D') => self.cursor = self.cursor.saturating_sub(1),
+            Some(b'H') => self.cursor = 0,
+            Some(b'F') => self.cursor = self.buffer.len(),
+            // ESC [ n ~ 形式：1/7 是 Home，4/8 是 End，3 是 Delete
+            Some(digit @ b'0'..=b'9') => {
+                let mut code = vec![digit];
+                while let Some(byte) = read_byte(input)? {
+                    if byte == b'~' {
+                        break;
+                    }
+                    code.push(byte);
+                }
+                match code.as_slice() {
+                    b"1" | b"7" => self.cursor = 0,
+                    b"4" | b"8" => self.cursor = self.buffer.len(),
+                    b"3" => self.delete(),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    // 删除光标前的空白和一个单词
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.buffer[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.buffer[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.buffer.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn history_prev(&mut self) {
+        if self.history_index == 0 {
+            return;
+        }
+        if self.history_index == self.history.len() {
+            self.draft = std::mem::take(&mut self.buffer);
+        }
+        self.history_index -= 1;
+        self.show(
+            self.history
+                .get(self.history_index)
+                .unwrap_or_default()
+                .chars()
+                .collect(),
+        );
+    }
+
+    fn history_next(&mut self) {
+        if self.history_index >= self.history.len() {
+            return;
+        }
+        self.history_index += 1;
+        let text = match self.history.get(self.history_index) {
+            Some(entry) => entry.chars().collect(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.show(text);
+    }
+
+    fn show(&mut self, text: Vec<char>) {
+        self.buffer = text;
+        self.cursor = self.buffer.len();
+    }
+
+    // 重画当前行并把光标移回原处；多行的历史条目中的换行显示为 `↵`
+    fn refresh(&self) -> io::Result<()> {
+        let line: String = self
+            .buffer
+            .iter()
+            .map(|&c| if c == '\n' { '↵' } else { c })
+            .collect();
+        let mut out = io::stdout().lock();
+        write!(out, "\r{}{}\x1b[K", self.prompt, line)?;
+        let back = self.buffer.len() - self.cursor;
+        if back > 0 {
+            write!(out, "\x1b[{}D", back)?;
+        }
+        out.flush()
+    }
+
+    fn finish(self, result: ReadLine) -> io::Result<ReadLine> {
+        // 原始模式下换行不会回到行首
+        print!("\r\n");
+        io::stdout().flush()?;
+        Ok(result)
+    }
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+// 读取以 first 开头的 UTF-8 字符的剩余字节
+fn read_char(input: &mut impl Read, first: u8) -> io::Result<Option<char>> {
+    let len = match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Ok(None),
+    };
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(byte) => bytes.push(byte),
+            None => return Ok(None),
+        }
+    }
+    Ok(std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|text| text.chars().next()))
+}
@@ -0,0 +1,103 @@
+// REPL 输入历史
+// 每条历史是一次完整的输入，可能跨多行；保存到文件时每条一行，
+// 换行和反斜杠转义为 `\n` 和 `\\`
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// 最多保留的历史条数
+pub const HISTORY_LIMIT: usize = 1000;
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>, // 没有路径时只保存在内存中
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从文件加载历史，文件不存在时从空历史开始
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|text| text.lines().map(unescape).collect())
+            .unwrap_or_default();
+        let mut history = Self {
+            entries,
+            path: Some(path),
+        };
+        history.truncate();
+        history
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push_str(&escape(entry));
+            text.push('\n');
+        }
+        fs::write(path, text)
+    }
+
+    /// 记录一次输入，忽略空输入和与上一条相同的输入
+    pub fn push(&mut self, entry: &str) {
+        let entry = entry.trim_end();
+        if entry.trim().is_empty() || self.entries.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        self.truncate();
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > HISTORY_LIMIT {
+            let excess = self.entries.len() - HISTORY_LIMIT;
+            self.entries.drain(..excess);
+        }
+    }
+}
+
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            _ => entry.push(c),
+        }
+    }
+    entry
+}
@@ -0,0 +1,195 @@
+// 沙箱中编译和运行（在线 playground 执行不受信任的程序）
+// 源码编译为字节码后在虚拟机中执行，运行时间、内存和输出都有硬性的上限：
+// - 时间从开始编译算起。编译在单独的线程中进行，超时后不再等待它的结果；
+//   虚拟机每执行一批指令检查一次，超时后停止
+// - 内存是虚拟机估算的值栈和静态变量的大小，字符串拼接的结果立即检查；编译器自身的内存不计
+// - 输出写到内存中，超过上限时截断并停止程序
+// - 源码的大小、记号数和语法树节点数有上限（`COMPILE_LIMITS`），太大的程序是编译错误
+// 程序不能使用 `io`（文件、参数和退出）、`env`（环境变量）和 `time`（时钟和等待）模块，
+// 调用它们是运行时错误。
+// 程序输出作为 stdout 返回，诊断信息按命令行的格式作为 stderr 返回。
+// 编译和执行在单独的大栈线程中进行，编译器的内部错误（panic）不会影响调用方
+
+use crate::bytecode::{Exceeded, Module, RunLimits, Vm};
+use crate::diagnostic::Diagnostic;
+use crate::driver::{CompileLimits, Compiler, Emit};
+use crate::interp;
+use crate::limits::{self, DEFAULT_NESTING_DEPTH};
+use crate::span::Span;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_time: Duration,
+    pub max_memory: usize, // 字节
+    pub max_output: usize, // 字节
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_time: Duration::from_secs(5),
+            max_memory: 64 * 1024 * 1024,
+            max_output: 64 * 1024,
+        }
+    }
+}
+
+/// 编译的资源限制
+pub const COMPILE_LIMITS: CompileLimits = CompileLimits {
+    max_file_size: Some(1024 * 1024),
+    max_tokens: Some(256 * 1024),
+    max_ast_nodes: Some(256 * 1024),
+    max_errors: Some(100),
+    max_nesting_depth: Some(DEFAULT_NESTING_DEPTH),
+};
+
+/// 程序结束的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    CompileError,
+    RuntimeError, // 包括违反契约
+    TimeLimitExceeded,
+    MemoryLimitExceeded,
+    OutputLimitExceeded,
+    InternalError, // 编译器或虚拟机 panic
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub status: Status,
+    pub stdout: String,
+    pub stderr: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub elapsed: Duration, // 编译和执行的总时间
+}
+
+/// 在资源限制下编译并运行 `source` 的 `main`
+pub fn compile_and_run(source: &str, limits: &Limits) -> Outcome {
+    let source = source.to_string();
+    let limits = *limits;
+    interp::with_large_stack(move || {
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| execute(&source, &limits)));
+        let (status, stdout, diagnostics) =
+            result.unwrap_or_else(|_| (Status::InternalError, Vec::new(), Vec::new()));
+        let mut stderr: String = diagnostics
+            .iter()
+            .map(|diagnostic| format!("{}\n", diagnostic))
+            .collect();
+        if status == Status::InternalError {
+            stderr.push_str("error: internal compiler error\n");
+        }
+        Outcome {
+            status,
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr,
+            diagnostics,
+            elapsed: start.elapsed(),
+        }
+    })
+}
+
+fn execute(source: &str, limits: &Limits) -> (Status, Vec<u8>, Vec<Diagnostic>) {
+    let start = Instant::now();
+    let module = match compile(source, limits.max_time) {
+        Ok(module) => module,
+        Err((status, errors)) => return (status, Vec::new(), errors),
+    };
+
+    let output = LimitedOutput {
+        bytes: Vec::new(),
+        limit: limits.max_output,
+        exceeded: false,
+    };
+    let mut vm = Vm::new(&module, output);
+    vm.set_limits_since(
+        RunLimits {
+            max_time: Some(limits.max_time),
+            max_memory: Some(limits.max_memory),
+        },
+        start,
+    );
+    let result = vm.run_main();
+    let exceeded = vm.exceeded();
+    let output = vm.into_output();
+    let (status, diagnostics) = match result {
+        Ok(_) => (Status::Success, Vec::new()),
+        Err(errors) if output.exceeded => {
+            let note = format!(
+                "the output limit of {} bytes was exceeded",
+                limits.max_output
+            );
+            let errors = errors
+                .into_iter()
+                .map(|e| e.with_note(note.clone()))
+                .collect();
+            (Status::OutputLimitExceeded, errors)
+        }
+        Err(errors) => match exceeded {
+            Some(Exceeded::Time) => (Status::TimeLimitExceeded, errors),
+            Some(Exceeded::Memory) => (Status::MemoryLimitExceeded, errors),
+            None => (Status::RuntimeError, errors),
+        },
+    };
+    (status, output.bytes, diagnostics)
+}
+
+// 在单独的大栈线程中编译，最多等待 `max_time`。编译器没有中途停下的办法，超时的线程
+// 继续运行到结束，结果被丢弃；编译器 panic 时线程的发送端被丢弃，作为内部错误
+fn compile(source: &str, max_time: Duration) -> Result<Module, (Status, Vec<Diagnostic>)> {
+    let (sender, receiver) = mpsc::channel();
+    let source = source.to_string();
+    thread::Builder::new()
+        .name("sandbox compiler".to_string())
+        .stack_size(interp::STACK_SIZE)
+        .spawn(move || {
+            let result = Compiler::new()
+                .source(&source)
+                .emit(Emit::Object)
+                .limits(COMPILE_LIMITS)
+                .run()
+                .into_result();
+            sender.send(result).ok();
+        })
+        .expect("failed to spawn the compiler thread");
+    match receiver.recv_timeout(max_time) {
+        Ok(Ok(artifact)) => Ok(artifact.into_object().unwrap()),
+        Ok(Err(errors)) => Err((Status::CompileError, errors)),
+        Err(RecvTimeoutError::Timeout) => {
+            let message = format!("time limit of {:?} exceeded while compiling", max_time);
+            let error = limits::too_large(message, Span::new(0, 0, 1, 1));
+            Err((Status::TimeLimitExceeded, vec![error]))
+        }
+        Err(RecvTimeoutError::Disconnected) => Err((Status::InternalError, Vec::new())),
+    }
+}
+
+// 写满上限后截断，之后的写入出错
+struct LimitedOutput {
+    bytes: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl Write for LimitedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.limit.saturating_sub(self.bytes.len());
+        if buf.len() > room {
+            self.bytes.extend_from_slice(&buf[..room]);
+            self.exceeded = true;
+            return Err(io::Error::other("output limit exceeded"));
+        }
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
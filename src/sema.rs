@@ -0,0 +1,4012 @@
+// Contractus 语义分析器
+// 当前实现：
+// 1. 收集顶层定义（结构体、枚举、函数、常量、静态变量）
+// 2. 名称解析：检查未定义的变量、函数和类型
+// 3. 字段查找：结构体字面量、结构体模式和字段访问
+// 4. 多模块：导入的条目和模块命名空间（`module::item`、`Enum::Variant` 路径）
+// 5. 导出列表校验，只有 `pub` 或被导出的条目可以被其他模块访问
+// 6. 契约条件（包括循环不变式）：类型必须是 bool，且不能有副作用（副作用分析见 effects.rs）
+// 7. 属性：函数上只能是已知的属性，`#[bench]` 函数没有参数；结构体和枚举上的
+//    `#[repr(...)]` 只能使用适用于它的表示方式
+// 8. 内建函数和内建类型（builtins.rs）：参数个数和能推断出的参数类型，`Vec<T>` 等类型名，
+//    `Map<K, V>` 的键类型
+// 9. 预导入的 Option/Result（prelude.rs）：`?` 的操作数和所在函数的返回类型，
+//    以及 match 的穷尽性（见 exhaustive.rs）
+// 10. 类型转换 `expr as T`：按 cast.rs 中的规则检查，指针转换只能写在 `unsafe` 块中
+// 11. 布局内建函数 `std::mem::size_of::<T>()`：恰好一个具体类型的类型实参，没有参数
+// 12. 赋值和复合赋值的左侧必须是变量、字段、下标或解引用，经过的引用和指针必须是可变的
+// 13. `break`/`continue` 必须在循环之内，标签必须是外层循环的标签；
+//     闭包体是新的上下文，不能跳出闭包外面的循环
+// 14. const 和 static 的初始值必须是常量表达式，常量不能引用静态变量；
+//     常量和不可变的静态变量不能赋值，`static mut` 只能在 `unsafe` 块中使用
+//     初始值之间的依赖不能成环，初始化顺序见 globals.rs
+// 15. 结构体和枚举不能不经过间接包含自身（见 representable.rs）
+// 16. 发散的表达式类型为 `!`，可以转换为任何类型；返回值不是 `()` 的函数在每条路径上
+//     都要给出值，返回 `!` 的函数体必须发散（见 divergence.rs）
+// 17. `a[start..end]` 取数组、切片或 `Vec` 的一段，类型是 `[T]`，只能通过 `&` 或 `&mut` 使用
+// 18. 整数字面量的类型来自上下文或后缀，值必须在类型的范围内；常量的整数初始值在编译时求值，
+//     结果必须在声明的类型的范围内（见 literals.rs）
+// 19. `typeof(expr)` 是表达式推断出的类型，只能用在函数体中的 let 标注、`as` 的目标和类型实参中；
+//     布局内建函数也可以不带路径写成 `size_of::<T>()` 和 `align_of::<T>()`
+// 20. 内联汇编 `asm!` 只能写在 unsafe 块中，模板、寄存器、操作数和选项的规则见 asm.rs
+// 21. 泛型函数调用处的类型实参由实参（包括闭包）推断，泛型结构体字面量由字段的值推断；
+//     推断出矛盾的类型或结果的类型不能确定时报告错误（见 infer.rs）
+// 22. 泛型参数的约束只能是内建的 trait，调用时推断出的类型实参必须实现约束中的 trait（见 traits.rs）
+// 23. 一个类型对一个 trait 只能有一个实现，不同类型的实现不能重叠（见 coherence.rs）
+// 24. 函数不能返回指向自己的局部变量或临时值的引用（见 escape.rs）
+// 25. `pure fn` 没有副作用，类型是 `pure fn(...)` 的参数只接受没有副作用的函数和闭包；
+//     `#[no_alloc]` 函数不在堆上分配，也不调用分配的函数（见 alloc.rs）
+// 26. 根模块的 `main` 没有参数，返回 `()` 或作为进程退出码的 `i32`
+// 名称或字段查找失败时，通过编辑距离在可见名字中寻找拼写建议
+
+mod alloc;
+mod asm;
+mod cast;
+mod coherence;
+mod compat;
+mod divergence;
+mod effects;
+mod escape;
+mod exhaustive;
+mod globals;
+mod infer;
+mod literals;
+mod representable;
+mod suggest;
+mod traits;
+
+pub use effects::{Effect, Effects};
+pub use globals::{initialization_order, Order, Use};
+pub use suggest::{edit_distance, find_similar};
+
+use crate::ast::*;
+use crate::builtins;
+use crate::derive;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::fmt;
+use crate::intrinsics;
+use crate::layout::{self, LayoutError, Layouts};
+use crate::module::{item_name, Crate, Module};
+use crate::prelude::{self, TryKind};
+use crate::span::Span;
+use crate::timing;
+use alloc::{Allocation, Allocations};
+use cast::CastKind;
+use divergence::Divergence;
+use exhaustive::Exhaustiveness;
+use infer::Inference;
+use literals::{int_range, ConstEval, FirstUse};
+use representable::{Containment, Representability};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use traits::Traits;
+
+/// 函数可以使用的属性
+pub const ATTRIBUTES: &[&str] = &["bench", "intrinsic", "no_alloc"];
+
+// 函数签名（只保留名称解析和字段查找需要的部分）
+#[derive(Debug, Clone)]
+struct FnSig {
+    params: Vec<Type>, // 内建函数没有记录参数类型
+    ret: Option<Type>,
+    generics: Vec<GenericParam>, // 泛型参数和它们的 trait 约束
+    builtin: bool,               // 没有被同名的函数遮蔽的内建函数
+    no_alloc: bool,              // 标注了 `#[no_alloc]`
+}
+
+impl FnSig {
+    fn type_params(&self) -> Vec<String> {
+        self.generics
+            .iter()
+            .map(|param| param.name.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Variable {
+    ty: Option<Type>, // 已知的类型（来自标注或简单推断）
+}
+
+// 常量和静态变量
+#[derive(Debug, Clone)]
+struct Global {
+    ty: Type,
+    kind: GlobalKind,
+    value: Option<Expr>, // 常量的初始值，用于常量求值
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GlobalKind {
+    Const,
+    Static,
+    StaticMut,
+}
+
+// 通过 `import a::b;` 导入的模块命名空间，只包含模块的公开接口
+#[derive(Debug, Clone)]
+struct Namespace {
+    name: String,
+    items: BTreeSet<String>,
+    private: BTreeSet<String>, // 模块中定义但未公开的条目，用于给出更准确的错误
+    enums: BTreeMap<String, Vec<String>>, // 公开的枚举名 -> 变体名
+}
+
+impl Namespace {
+    fn from_module(krate: &Crate, module: &Module) -> Self {
+        let public = krate.public_items(&module.path);
+
+        let mut enums = BTreeMap::new();
+        for (name, target) in &public {
+            let item = krate
+                .module(&target.module)
+                .and_then(|m| m.find_item(&target.item));
+            if let Some(Item::Enum(enum_def)) = item {
+                let variants = enum_def.variants.iter().map(|v| v.name.clone()).collect();
+                enums.insert(name.clone(), variants);
+            }
+        }
+
+        Self {
+            name: module.display_name(),
+            private: module
+                .item_names()
+                .filter(|name| !public.contains_key(*name))
+                .map(str::to_string)
+                .collect(),
+            items: public.into_keys().collect(),
+            enums,
+        }
+    }
+
+    // 检查条目能否从模块外访问，不能访问时返回错误码、错误信息和帮助
+    fn check_item(&self, item: &str) -> Option<(ErrorCode, String, Option<String>)> {
+        if self.items.contains(item) {
+            return None;
+        }
+        if self.private.contains(item) {
+            return Some((
+                ErrorCode::E0603,
+                format!("`{}` is private to module `{}`", item, self.name),
+                Some(format!(
+                    "mark it `pub` or add it to the `export {{ ... }}` list of `{}`",
+                    self.name
+                )),
+            ));
+        }
+        let help = find_similar(item, self.items.iter().map(String::as_str))
+            .map(|similar| similar_help("an item", item, similar));
+        Some((
+            ErrorCode::E0425,
+            format!("cannot find `{}` in module `{}`", item, self.name),
+            help,
+        ))
+    }
+}
+
+// 可以使用 `.await` 的地方：async 函数的函数体和 async 块，不包括其中的闭包
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AsyncContext {
+    Function,
+    Block,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    variables: HashMap<String, Variable>,
+}
+
+pub struct SemanticAnalyzer {
+    scopes: Vec<Scope>,
+    generics: Vec<Vec<GenericParam>>, // 当前可见的泛型参数和它们的约束
+    structs: BTreeMap<String, StructDef>,
+    enums: BTreeMap<String, EnumDef>,
+    variants: BTreeMap<String, String>, // 变体名 -> 所属枚举
+    functions: BTreeMap<String, FnSig>,
+    globals: BTreeMap<String, Global>,
+    namespaces: BTreeMap<String, Namespace>,
+    effects: Effects,
+    allocations: Allocations,
+    current_function: Option<String>, // 正在检查函数体的函数，用于记录分配
+    checked_packages: BTreeSet<String>, // 已经单独检查过的依赖包
+    poisoned: BTreeSet<String>,       // 导入失败的名字，使用它们时不再重复报错
+    returns: Vec<Option<Type>>,       // 外层函数和闭包的返回类型，闭包没有标注时为 None
+    unsafe_depth: usize,              // 所在的 unsafe 块的层数
+    loops: Vec<Option<String>>,       // 当前函数或闭包中外层循环的标签
+    closures: Vec<usize>,             // 外层闭包开始时的作用域层数，更外层的变量是捕获的
+    async_context: Option<AsyncContext>,
+    closure_types: HashMap<Span, Type>, // 检查过的闭包的类型，推断不出的部分为 `_`
+    expr_types: HashMap<usize, Option<Type>>, // 检查过的表达式的类型，按节点的地址，见 check_expr
+    errors: Vec<Diagnostic>,
+}
+
+impl Default for SemanticAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SemanticAnalyzer {
+    pub fn new() -> Self {
+        let mut analyzer = Self {
+            scopes: Vec::new(),
+            generics: Vec::new(),
+            structs: BTreeMap::new(),
+            enums: BTreeMap::new(),
+            variants: BTreeMap::new(),
+            functions: builtins::BUILTINS
+                .iter()
+                .map(|builtin| (builtin.name, builtin))
+                .chain(
+                    builtins::ALIASES
+                        .iter()
+                        .filter_map(|(alias, name)| Some((*alias, builtins::lookup(name)?))),
+                )
+                .map(|(name, builtin)| {
+                    let sig = FnSig {
+                        params: Vec::new(),
+                        ret: Some(builtin.return_type(None)),
+                        generics: Vec::new(),
+                        builtin: true,
+                        no_alloc: false,
+                    };
+                    (name.to_string(), sig)
+                })
+                .collect(),
+            globals: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
+            effects: Effects::default(),
+            allocations: Allocations::default(),
+            current_function: None,
+            checked_packages: BTreeSet::new(),
+            poisoned: BTreeSet::new(),
+            returns: Vec::new(),
+            closures: Vec::new(),
+            unsafe_depth: 0,
+            loops: Vec::new(),
+            async_context: None,
+            closure_types: HashMap::new(),
+            expr_types: HashMap::new(),
+            errors: Vec::new(),
+        };
+        for item in &prelude::program().items {
+            if let Some(name) = item_name(item) {
+                analyzer.register_item(name, item);
+            }
+        }
+        analyzer
+    }
+
+    /// 依赖包已经单独检查过，`analyze_crate` 跳过它的模块
+    pub fn skip_package(&mut self, name: impl Into<String>) {
+        self.checked_packages.insert(name.into());
+    }
+
+    pub fn analyze(&mut self, program: &Program) -> Result<(), Vec<Diagnostic>> {
+        self.expr_types.clear();
+        self.effects = Effects::analyze(program);
+        self.check_program(program);
+        self.check_main(program);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// 分析整个 crate：每个模块有独立的命名空间，导入的条目和模块在解析前注册
+    pub fn analyze_crate(&mut self, krate: &Crate) -> Result<(), Vec<Diagnostic>> {
+        let mut effects = timing::time("effect analysis", || Effects::analyze_crate(krate));
+        for module in krate.modules.values() {
+            if module
+                .package
+                .as_ref()
+                .is_some_and(|package| self.checked_packages.contains(package))
+            {
+                continue;
+            }
+            let mut analyzer = SemanticAnalyzer::new();
+            analyzer.effects = effects.remove(&module.path).unwrap_or_default();
+            timing::time("import resolution", || {
+                analyzer.register_imports(krate, module)
+            });
+            timing::time("name resolution and type checking", || {
+                analyzer.check_program(&module.program)
+            });
+            if module.path.is_empty() {
+                analyzer.check_main(&module.program);
+            }
+
+            // 由源码构造的 crate 中根模块没有文件名
+            let file =
+                (!module.file.as_os_str().is_empty()).then(|| module.file.display().to_string());
+            self.errors
+                .extend(analyzer.errors.into_iter().map(|err| match &file {
+                    Some(file) => err.with_file(file.clone()),
+                    None => err,
+                }));
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// 在程序顶层条目的基础上检查一段语句（REPL 的一次输入）。
+    /// let 绑定留在最外层作用域中供后续输入使用；出错时作用域恢复原状
+    pub fn analyze_statements(
+        &mut self,
+        program: &Program,
+        statements: &[Statement],
+    ) -> Result<(), Vec<Diagnostic>> {
+        self.expr_types.clear();
+        self.register_items(program);
+        if self.scopes.is_empty() {
+            self.push_scope();
+        }
+
+        let saved = self.scopes.clone();
+        for stmt in statements {
+            self.check_statement(stmt);
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            self.scopes = saved;
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// 检查表达式并返回推断出的类型，推断不出时为 None（REPL 的 `:type`）
+    pub fn analyze_expr_type(
+        &mut self,
+        program: &Program,
+        expr: &Expr,
+    ) -> Result<Option<Type>, Vec<Diagnostic>> {
+        self.expr_types.clear();
+        self.register_items(program);
+        self.check_expr(expr);
+
+        if self.errors.is_empty() {
+            Ok(self.type_of(expr))
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn register_items(&mut self, program: &Program) {
+        for item in &program.items {
+            if let Some(name) = item_name(item) {
+                self.register_item(name, item);
+            }
+        }
+    }
+
+    fn check_program(&mut self, program: &Program) {
+        self.register_items(program);
+        self.check_duplicates(program);
+        for cycle in initialization_order(&program.items).cycles {
+            self.errors.push(initialization_cycle(&cycle));
+        }
+        self.check_representable(program);
+        self.errors.extend(coherence::conflicts(&program.items));
+
+        for item in &program.items {
+            match item {
+                Item::Function(func) => self.check_function(func),
+                Item::Struct(struct_def) => self.check_struct(struct_def),
+                Item::Enum(enum_def) => self.check_enum(enum_def),
+                Item::Const(const_def) => {
+                    self.check_type(&const_def.ty, const_def.span);
+                    self.check_expr(&const_def.value);
+                    self.check_const_init(&const_def.value, GlobalKind::Const);
+                    self.check_const_value(&const_def.value, &const_def.ty);
+                }
+                Item::Static(static_def) => {
+                    self.check_type(&static_def.ty, static_def.span);
+                    self.check_expr(&static_def.value);
+                    self.check_const_init(&static_def.value, GlobalKind::Static);
+                    self.check_const_value(&static_def.value, &static_def.ty);
+                }
+                Item::Export(export) => self.check_export(program, export),
+                Item::MacroCall(call) => self.unexpanded_macro(call),
+                Item::Import(_) | Item::MacroRules(_) => {}
+            }
+        }
+        self.check_no_alloc(program);
+    }
+
+    // 函数、常量和静态变量的名字，以及结构体和枚举的名字，各自只能定义一次；
+    // 类型的 trait 实现函数重名由 coherence 报告
+    fn check_duplicates(&mut self, program: &Program) {
+        let impls: BTreeSet<String> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Struct(def) => Some(&def.name),
+                Item::Enum(def) => Some(&def.name),
+                _ => None,
+            })
+            .flat_map(|ty| {
+                derive::TRAITS
+                    .iter()
+                    .map(move |trait_name| derive::function_name(ty, trait_name))
+            })
+            .collect();
+        let mut defined: HashMap<(bool, &str), Span> = HashMap::new();
+        for item in &program.items {
+            let (name, span, is_type) = match item {
+                Item::Function(func) if impls.contains(&func.name) => continue,
+                Item::Function(func) => (&func.name, func.span, false),
+                Item::Const(def) => (&def.name, def.span, false),
+                Item::Static(def) => (&def.name, def.span, false),
+                Item::Struct(def) => (&def.name, def.span, true),
+                Item::Enum(def) => (&def.name, def.span, true),
+                _ => continue,
+            };
+            let Some(first) = defined.get(&(is_type, name.as_str())) else {
+                defined.insert((is_type, name), span);
+                continue;
+            };
+            let error = Diagnostic::error(
+                format!("the name `{}` is defined multiple times", name),
+                span,
+            )
+            .with_code(ErrorCode::E0428)
+            .with_note(format!(
+                "the first definition of `{}` is at line {}, column {}",
+                name, first.line, first.column
+            ))
+            .with_help(format!(
+                "rename or remove one of the definitions of `{}`",
+                name
+            ));
+            self.errors.push(error);
+        }
+    }
+
+    // 在当前命名空间中以 `name` 注册顶层条目，使其可以先使用后定义
+    fn register_item(&mut self, name: &str, item: &Item) {
+        match item {
+            Item::Function(func) => {
+                self.functions.insert(
+                    name.to_string(),
+                    FnSig {
+                        params: func.params.iter().map(|param| param.ty.clone()).collect(),
+                        ret: func.return_type.clone(),
+                        generics: func
+                            .generics
+                            .as_ref()
+                            .map(|generics| generics.params.clone())
+                            .unwrap_or_default(),
+                        builtin: false,
+                        no_alloc: func.has_attribute("no_alloc"),
+                    },
+                );
+            }
+            Item::Struct(struct_def) => {
+                self.structs.insert(name.to_string(), struct_def.clone());
+            }
+            Item::Enum(enum_def) => {
+                for variant in &enum_def.variants {
+                    self.variants
+                        .insert(variant.name.clone(), enum_def.name.clone());
+                }
+                self.enums.insert(name.to_string(), enum_def.clone());
+            }
+            Item::Const(const_def) => {
+                let global = Global {
+                    ty: const_def.ty.clone(),
+                    kind: GlobalKind::Const,
+                    value: Some(const_def.value.clone()),
+                };
+                self.globals.insert(name.to_string(), global);
+            }
+            Item::Static(static_def) => {
+                let kind = match static_def.mutable {
+                    true => GlobalKind::StaticMut,
+                    false => GlobalKind::Static,
+                };
+                let global = Global {
+                    ty: static_def.ty.clone(),
+                    kind,
+                    value: None,
+                };
+                self.globals.insert(name.to_string(), global);
+            }
+            Item::Import(_) | Item::Export(_) | Item::MacroRules(_) | Item::MacroCall(_) => {}
+        }
+    }
+
+    // 注册模块的导入：条目导入直接进入当前命名空间，模块导入成为可用路径访问的命名空间
+    fn register_imports(&mut self, krate: &Crate, module: &Module) {
+        for import in &module.imports {
+            // 找不到的模块由加载器报告
+            let Some(target) = krate.module(&import.module) else {
+                self.poisoned.insert(import.name.clone());
+                continue;
+            };
+            let namespace = Namespace::from_module(krate, target);
+
+            let Some(wanted) = &import.item else {
+                self.namespaces.insert(import.name.clone(), namespace);
+                continue;
+            };
+
+            // 条目可能是重新导出的，需要找到真正定义它的模块
+            let item = krate
+                .public_items(&import.module)
+                .remove(wanted)
+                .and_then(|found| krate.module(&found.module)?.find_item(&found.item));
+            match item {
+                Some(item) => self.register_item(&import.name, item),
+                None => {
+                    self.poisoned.insert(import.name.clone());
+                    if let Some((code, message, help)) = namespace.check_item(wanted) {
+                        self.report(code, message, import.span, help);
+                    }
+                }
+            }
+        }
+    }
+
+    // 导出列表校验：本模块的条目必须存在，重新导出的条目必须是被导入模块的公开条目
+    fn check_export(&mut self, program: &Program, export: &ExportStmt) {
+        for entry in &export.items {
+            let error = match entry.path.as_slice() {
+                [name]
+                    if program
+                        .items
+                        .iter()
+                        .any(|item| item_name(item) == Some(name)) =>
+                {
+                    None
+                }
+                [name] => {
+                    let help = find_similar(name, program.items.iter().filter_map(item_name))
+                        .map(|similar| similar_help("an item", name, similar));
+                    Some((
+                        ErrorCode::E0364,
+                        format!("cannot export `{}`: no such item in this module", name),
+                        help,
+                    ))
+                }
+                [namespace, name] => match self.namespaces.get(namespace) {
+                    Some(imported) => imported.check_item(name),
+                    None => Some((
+                        ErrorCode::E0364,
+                        format!(
+                            "cannot re-export `{}`: `{}` is not an imported module",
+                            entry.path.join("::"),
+                            namespace
+                        ),
+                        Some(format!(
+                            "import the module first, e.g. `import ...::{};`",
+                            namespace
+                        )),
+                    )),
+                },
+                _ => Some((
+                    ErrorCode::E0364,
+                    format!("unsupported export path `{}`", entry.path.join("::")),
+                    None,
+                )),
+            };
+            if let Some((code, message, help)) = error {
+                self.report(code, message, entry.span, help);
+            }
+        }
+    }
+
+    fn check_function(&mut self, func: &Function) {
+        self.check_attributes(func);
+        self.check_bounds(&func.generics);
+        self.push_generics(&func.generics);
+        self.push_scope();
+
+        for param in &func.params {
+            self.check_type(&param.ty, param.span);
+            self.bind_pattern(&param.pattern, Some(param.ty.clone()), param.span);
+            self.check_irrefutable(&param.pattern, Binding::Argument, param.span);
+        }
+        if let Some(ret) = &func.return_type {
+            self.check_type(ret, func.span);
+        }
+
+        // ensures 中可以用 `result` 引用返回值
+        for contract in &func.contracts {
+            self.push_scope();
+            if contract.kind == ContractKind::Ensures {
+                let ret = func.return_type.clone().unwrap_or(Type::Unit);
+                self.declare("result", Some(ret));
+            }
+            self.check_contract(contract);
+            self.pop_scope();
+        }
+
+        self.returns
+            .push(Some(func.return_type.clone().unwrap_or(Type::Unit)));
+        self.async_context = func.asynchronous.then_some(AsyncContext::Function);
+        self.allocations.enter(&func.name);
+        self.current_function = Some(func.name.clone());
+        // 函数体的值在函数体的作用域中检查
+        self.push_scope();
+        self.check_statements(&func.body);
+        if let Some(ret) = &func.return_type {
+            self.check_tail_value(&func.body, ret);
+        }
+        self.pop_scope();
+        self.current_function = None;
+        if let Some(ret) = &func.return_type {
+            self.check_block_literals(&func.body, ret);
+        }
+        self.async_context = None;
+        self.returns.pop();
+        self.check_returns_value(func);
+        self.check_pure(func);
+        self.errors.extend(escape::check(func));
+
+        self.pop_scope();
+        self.generics.pop();
+    }
+
+    // 程序的入口：没有参数，返回 `()`，或者返回作为进程退出码的 `i32`
+    fn check_main(&mut self, program: &Program) {
+        let Some(main) = program.items.iter().find_map(|item| match item {
+            Item::Function(func) if func.name == "main" => Some(func),
+            _ => None,
+        }) else {
+            return;
+        };
+        let ret = main.return_type.clone().unwrap_or(Type::Unit);
+        if main.params.is_empty()
+            && main.generics.is_none()
+            && !main.asynchronous
+            && matches!(ret, Type::Unit | Type::I32 | Type::Never)
+        {
+            return;
+        }
+        self.report(
+            ErrorCode::E0580,
+            "`main` function has wrong type".to_string(),
+            main.span,
+            Some(
+                "declare it as `fn main()`, or as `fn main() -> i32` to return the exit code"
+                    .to_string(),
+            ),
+        );
+    }
+
+    fn check_pure(&mut self, func: &Function) {
+        if !func.pure {
+            return;
+        }
+        let Some(effect) = self.effects.effect_of(&func.name) else {
+            return;
+        };
+        let message = format!(
+            "function `{}` is declared `pure` but it {}",
+            func.name,
+            effect.describe()
+        );
+        let diagnostic = Diagnostic::error(message, effect.span()).with_code(ErrorCode::E0710);
+        self.errors.push(effect_notes(diagnostic, effect).with_help(
+            "move the side effect to the caller, or remove `pure` from the declaration".to_string(),
+        ));
+    }
+
+    // `#[no_alloc]` 函数不能分配，包括经过它调用的函数
+    fn check_no_alloc(&mut self, program: &Program) {
+        let allocating = self.allocations.solve();
+        for item in &program.items {
+            let Item::Function(func) = item else {
+                continue;
+            };
+            if !func.has_attribute("no_alloc") {
+                continue;
+            }
+            let Some(allocation) = allocating.get(&func.name) else {
+                continue;
+            };
+            let message = format!(
+                "function `{}` is marked `#[no_alloc]` but it {}",
+                func.name,
+                allocation.describe()
+            );
+            let mut diagnostic =
+                Diagnostic::error(message, allocation.span()).with_code(ErrorCode::E0711);
+            let mut current = allocation;
+            while let Allocation::Call { callee, cause, .. } = current {
+                let Some(cause) = cause else {
+                    diagnostic = diagnostic.with_note(format!(
+                        "`{}` is defined in another module and is not marked `#[no_alloc]`",
+                        callee
+                    ));
+                    break;
+                };
+                let span = cause.span();
+                diagnostic = diagnostic.with_note(format!(
+                    "`{}` allocates because it {} at line {}, column {}",
+                    callee,
+                    cause.describe(),
+                    span.line,
+                    span.column
+                ));
+                current = cause;
+            }
+            self.errors.push(diagnostic.with_help(
+                "allocate before calling the function and pass the values in".to_string(),
+            ));
+        }
+    }
+
+    // 返回值不是 `()` 的函数体不能在没有值的路径上走到结尾；返回 `!` 的函数体必须发散
+    fn check_returns_value(&mut self, func: &Function) {
+        let ret = match &func.return_type {
+            None | Some(Type::Unit) => return,
+            Some(ret) => ret,
+        };
+        let divergence = Divergence::new(&self.functions);
+        let (message, help) = match ret {
+            Type::Never if !divergence.block(&func.body) => (
+                format!("function `{}` returns `!` but its body can finish", func.name),
+                "loop forever with `while true`, or end the body with a call to a function that returns `!` such as `panic`".to_string(),
+            ),
+            Type::Never => return,
+            _ if !divergence.has_value(&func.body) => (
+                format!(
+                    "function `{}` returns `{}` but its body can finish without a value",
+                    func.name, ret
+                ),
+                format!(
+                    "end the body with a value of type `{}`, or `return` one on every path",
+                    ret
+                ),
+            ),
+            _ => return,
+        };
+        self.report(ErrorCode::E0308, message, func.body.span, Some(help));
+    }
+
+    fn check_attributes(&mut self, func: &Function) {
+        for attribute in &func.attributes {
+            if attribute.name == "derive" {
+                self.report(
+                    ErrorCode::E0103,
+                    "`#[derive]` is only allowed on structs and enums".to_string(),
+                    attribute.span,
+                    None,
+                );
+            } else if attribute.name == "repr" {
+                self.report(
+                    ErrorCode::E0517,
+                    "`#[repr]` can only be applied to structs and enums".to_string(),
+                    attribute.span,
+                    None,
+                );
+            } else if !ATTRIBUTES.contains(&attribute.name.as_str()) {
+                let known: Vec<String> = ATTRIBUTES
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect();
+                self.report(
+                    ErrorCode::E0702,
+                    format!("cannot find attribute `{}`", attribute.name),
+                    attribute.span,
+                    Some(format!("the supported attributes are {}", known.join(", "))),
+                );
+            }
+        }
+        // 基准测试由 `contractus bench` 反复调用，不能有参数
+        if func.has_attribute("bench") {
+            let problem = if func.generics.is_some() {
+                Some("cannot be generic")
+            } else if !func.params.is_empty() {
+                Some("cannot take parameters")
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                self.report(
+                    ErrorCode::E0703,
+                    format!("benchmark function `{}` {}", func.name, problem),
+                    func.span,
+                    Some(
+                        "a benchmark is a function without parameters whose body is timed"
+                            .to_string(),
+                    ),
+                );
+            }
+        }
+        if let Some(attribute) = func
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name == "intrinsic")
+        {
+            self.check_intrinsic(func, attribute);
+        }
+    }
+
+    // `#[intrinsic("name")]` 的名字必须登记在 intrinsics.rs 中，函数的签名和登记的相同
+    fn check_intrinsic(&mut self, func: &Function, attribute: &Attribute) {
+        let name = match attribute.string_arg(0) {
+            Some(name) if attribute.args.len() == 1 => name,
+            _ => {
+                self.report(
+                    ErrorCode::E0708,
+                    "`#[intrinsic]` takes the name of the intrinsic as a string".to_string(),
+                    attribute.span,
+                    Some("write it like `#[intrinsic(\"sqrt\")]`".to_string()),
+                );
+                return;
+            }
+        };
+        let Some(intrinsic) = intrinsics::lookup(&name) else {
+            let known: Vec<String> = intrinsics::INTRINSICS
+                .iter()
+                .map(|intrinsic| format!("`{}`", intrinsic.name))
+                .collect();
+            self.report(
+                ErrorCode::E0708,
+                format!("unknown intrinsic `{}`", name),
+                attribute.span,
+                Some(format!("the known intrinsics are {}", known.join(", "))),
+            );
+            return;
+        };
+        let params: Vec<Type> = func.params.iter().map(|param| param.ty.clone()).collect();
+        let ret = func.return_type.clone().unwrap_or(Type::Unit);
+        if func.generics.is_some() {
+            self.report(
+                ErrorCode::E0708,
+                format!("intrinsic function `{}` cannot be generic", func.name),
+                func.span,
+                None,
+            );
+        } else if !intrinsic.accepts(&params, &ret) {
+            self.report(
+                ErrorCode::E0708,
+                format!(
+                    "intrinsic `{}` must have the signature `{}`",
+                    intrinsic.name,
+                    intrinsic.signature()
+                ),
+                func.span,
+                Some(format!(
+                    "`{}` is declared as `{}`",
+                    func.name,
+                    Type::Function(params, Box::new(ret), func.pure)
+                )),
+            );
+        }
+    }
+
+    fn check_struct(&mut self, struct_def: &StructDef) {
+        self.check_repr(&struct_def.attributes, false);
+        self.check_bounds(&struct_def.generics);
+        self.push_generics(&struct_def.generics);
+        for field in &struct_def.fields {
+            self.check_type(&field.ty, field.span);
+        }
+        // invariant 中用 `self` 引用结构体的值
+        for invariant in &struct_def.invariants {
+            self.push_scope();
+            let ty = match &struct_def.generics {
+                Some(generics) => Type::Generic(
+                    struct_def.name.clone(),
+                    generics
+                        .param_names()
+                        .into_iter()
+                        .map(Type::Named)
+                        .collect(),
+                ),
+                None => Type::Named(struct_def.name.clone()),
+            };
+            self.declare("self", Some(ty));
+            self.check_contract(invariant);
+            self.pop_scope();
+        }
+        self.generics.pop();
+    }
+
+    // 契约的条件必须是 bool，并且没有副作用
+    fn check_contract(&mut self, contract: &Contract) {
+        self.check_expr(&contract.condition);
+        if let Some(ty) = self.type_of(&contract.condition) {
+            if !matches!(ty, Type::Bool | Type::Never | Type::Infer) {
+                self.report(
+                    ErrorCode::E0308,
+                    format!(
+                        "`{}` condition must be `bool`, found `{}`",
+                        contract.kind.keyword(),
+                        ty
+                    ),
+                    contract.span,
+                    None,
+                );
+            }
+        }
+
+        let (bindings, pure_values) = self.visible_values();
+        if let Some(effect) =
+            self.effects
+                .condition_effect(&contract.condition, &bindings, &pure_values)
+        {
+            self.errors.push(impure_condition(contract, &effect));
+        }
+    }
+
+    // 可见的变量，以及其中类型是 `pure fn` 的变量
+    fn visible_values(&self) -> (BTreeSet<String>, BTreeSet<String>) {
+        let mut bindings = BTreeSet::new();
+        let mut pure_values = BTreeSet::new();
+        for (name, variable) in self.scopes.iter().flat_map(|scope| &scope.variables) {
+            bindings.insert(name.clone());
+            match variable.ty {
+                Some(Type::Function(_, _, true)) => pure_values.insert(name.clone()),
+                _ => pure_values.remove(name),
+            };
+        }
+        (bindings, pure_values)
+    }
+
+    fn check_enum(&mut self, enum_def: &EnumDef) {
+        self.check_repr(&enum_def.attributes, true);
+        self.check_bounds(&enum_def.generics);
+        self.push_generics(&enum_def.generics);
+        for variant in &enum_def.variants {
+            for ty in variant.fields.iter().flatten() {
+                self.check_type(ty, variant.span);
+            }
+        }
+        self.generics.pop();
+    }
+
+    // 不经过间接包含自身的结构体和枚举；互相包含的类型只在声明最早的一个上报告
+    fn check_representable(&mut self, program: &Program) {
+        let representability = Representability::new(&self.structs, &self.enums);
+        let mut reported = BTreeSet::new();
+        let mut errors = Vec::new();
+        for item in &program.items {
+            let (name, generics, span) = match item {
+                Item::Struct(def) => (&def.name, &def.generics, def.span),
+                Item::Enum(def) => (&def.name, &def.generics, def.span),
+                _ => continue,
+            };
+            let Some(path) = representability.recursion(name, generics) else {
+                continue;
+            };
+            let members: BTreeSet<String> = path
+                .iter()
+                .map(|step| step.owner.split('<').next().unwrap_or_default().to_string())
+                .collect();
+            if reported.insert(members) {
+                errors.push(recursive_type(name, span, &path, &program.items));
+            }
+        }
+        self.errors.extend(errors);
+    }
+
+    // 类型名称解析
+    fn check_type(&mut self, ty: &Type, span: Span) {
+        match ty {
+            Type::Named(name) => self.check_type_name(name, span),
+            Type::Generic(name, args) => {
+                self.check_type_name(name, span);
+                for arg in args {
+                    self.check_type(arg, span);
+                }
+                self.check_map_key(name, args, span);
+            }
+            Type::Array(inner, _)
+            | Type::Slice(inner)
+            | Type::Pointer(inner, _)
+            | Type::Reference(inner, _) => self.check_type(inner, span),
+            Type::Tuple(types) => {
+                for ty in types {
+                    self.check_type(ty, span);
+                }
+            }
+            Type::Function(params, ret, _) => {
+                for ty in params {
+                    self.check_type(ty, span);
+                }
+                self.check_type(ret, span);
+            }
+            // 允许使用 `typeof` 的地方由 check_local_type 先把它替换为推断出的类型
+            Type::TypeOf(_) => self.report(
+                ErrorCode::E0516,
+                "`typeof` is not allowed here".to_string(),
+                span,
+                Some(
+                    "`typeof(expr)` can only be used in `let` annotations, `as` casts and type arguments inside function bodies"
+                        .to_string(),
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    // 函数体中的类型：`typeof(expr)` 是表达式推断出的类型，表达式不求值
+    fn check_local_type(&mut self, ty: &Type, span: Span) {
+        for node in ty.typeofs() {
+            // 表达式本身有错误时不再报告推断失败
+            let reported = self.errors.len();
+            self.check_expr(&node.expr);
+            match self.type_of(&node.expr) {
+                Some(resolved) => node.resolve(resolved.expand_typeof()),
+                None if self.errors.len() > reported => {}
+                None => self.report(
+                    ErrorCode::E0282,
+                    format!("cannot infer the type of `{}`", Type::TypeOf(node.clone())),
+                    node.expr.span(),
+                    Some(
+                        "`typeof` needs an expression whose type is known, such as a variable with a type annotation"
+                            .to_string(),
+                    ),
+                ),
+            }
+        }
+        self.check_type(&ty.expand_typeof(), span);
+    }
+
+    // 内建的 `Map<K, V>` 只支持基本类型的键
+    fn check_map_key(&mut self, name: &str, args: &[Type], span: Span) {
+        if name != "Map" || self.structs.contains_key(name) || self.enums.contains_key(name) {
+            return;
+        }
+        if let Some(key) = args.first() {
+            if !builtins::Param::Key.accepts(key) {
+                self.report(
+                    ErrorCode::E0308,
+                    format!("the type `{}` cannot be used as a map key", key),
+                    span,
+                    Some("map keys must be `bool`, integers, `char` or `string`".to_string()),
+                );
+            }
+        }
+    }
+
+    fn check_type_name(&mut self, name: &str, span: Span) {
+        let is_generic = self.traits().is_generic(name);
+        if is_generic
+            || self.structs.contains_key(name)
+            || self.enums.contains_key(name)
+            || builtins::is_type(name)
+            || self.poisoned.contains(name)
+        {
+            return;
+        }
+
+        let candidates: Vec<&str> = self
+            .generics
+            .iter()
+            .flatten()
+            .map(|param| param.name.as_str())
+            .chain(self.structs.keys().map(String::as_str))
+            .chain(self.enums.keys().map(String::as_str))
+            .collect();
+        let help =
+            find_similar(name, candidates).map(|similar| similar_help("a type", name, similar));
+
+        self.report(
+            ErrorCode::E0412,
+            format!("cannot find type `{}` in this scope", name),
+            span,
+            help,
+        );
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.push_scope();
+        self.check_statements(block);
+        self.pop_scope();
+    }
+
+    fn check_statements(&mut self, block: &Block) {
+        for (i, stmt) in block.statements.iter().enumerate() {
+            match stmt {
+                Statement::Let(let_stmt) => self.check_let(let_stmt, &block.statements[i + 1..]),
+                stmt => self.check_statement(stmt),
+            }
+        }
+    }
+
+    // `rest` 是同一个块中之后的语句，没有标注类型的整数变量从中推断类型
+    fn check_let(&mut self, let_stmt: &LetStmt, rest: &[Statement]) {
+        if let Some(init) = &let_stmt.init {
+            self.check_expr(init);
+            if let_stmt.ty.is_none() {
+                let binding = match &let_stmt.pattern {
+                    Pattern::Ident(name) => format!("`{}`", name),
+                    _ => "the pattern".to_string(),
+                };
+                let what = format!("give {} a type annotation", binding);
+                self.check_annotations_needed(init, &what);
+            }
+        }
+        if let Some(ty) = &let_stmt.ty {
+            self.check_local_type(ty, let_stmt.span);
+            if let Some(init) = &let_stmt.init {
+                // 泛型函数的结果和标注的矛盾由 check_call_result 报告
+                let errors = self.errors.len();
+                self.check_call_result(init, &ty.expand_typeof());
+                if self.errors.len() == errors {
+                    self.check_value(init, &ty.expand_typeof());
+                }
+                if matches!(ty, Type::Function(_, _, true)) {
+                    let note = format!("the variable is declared with type `{}`", ty);
+                    self.check_pure_value(init, "expected a pure function".to_string(), note);
+                }
+            }
+        }
+        // else 块中还不能使用模式绑定的名字
+        match &let_stmt.else_block {
+            Some(else_block) => self.check_let_else(else_block),
+            None => self.check_irrefutable(&let_stmt.pattern, Binding::Let, let_stmt.span),
+        }
+        let ty = match (&let_stmt.ty, &let_stmt.init) {
+            (Some(ty), _) => Some(ty.expand_typeof()),
+            (None, Some(init)) if literals::is_unsuffixed(init) => {
+                let inferred = match &let_stmt.pattern {
+                    Pattern::Ident(name) => FirstUse {
+                        name,
+                        functions: &self.functions,
+                        ret: self.returns.last().and_then(Option::as_ref),
+                    }
+                    .find(rest),
+                    _ => None,
+                };
+                Some(inferred.unwrap_or(Type::I32))
+            }
+            (None, init) => init.as_ref().and_then(|e| self.type_of(e)),
+        };
+        if let (Some(ty), Some(init)) = (&ty, &let_stmt.init) {
+            self.check_literal_type(init, ty);
+        }
+        self.bind_pattern(&let_stmt.pattern, ty, let_stmt.span);
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let(let_stmt) => self.check_let(let_stmt, &[]),
+            Statement::Expr(expr_stmt) => {
+                self.check_expr(&expr_stmt.expr);
+                if expr_stmt.semicolon {
+                    let what = "bind the result with a type annotation";
+                    self.check_annotations_needed(&expr_stmt.expr, what);
+                }
+            }
+            Statement::Return(ret) => {
+                if let Some(expr) = &ret.expr {
+                    self.check_expr(expr);
+                    self.check_return_value(expr);
+                }
+                self.check_async_exit("`return`", ret.span);
+            }
+            Statement::If(if_stmt) => self.check_if(
+                &if_stmt.cond,
+                &if_stmt.then_block,
+                if_stmt.else_block.as_ref(),
+            ),
+            Statement::While(while_stmt) => {
+                self.check_expr(&while_stmt.cond);
+                self.check_condition(&while_stmt.cond);
+                for invariant in &while_stmt.invariants {
+                    self.check_contract(invariant);
+                }
+                self.check_loop_body(while_stmt.label.as_ref(), &while_stmt.body);
+            }
+            Statement::For(for_stmt) => self.check_for(
+                for_stmt.label.as_ref(),
+                &for_stmt.pattern,
+                &for_stmt.iterable,
+                &for_stmt.invariants,
+                &for_stmt.body,
+                for_stmt.span,
+            ),
+            Statement::Match(match_stmt) => {
+                self.check_match(&match_stmt.expr, &match_stmt.arms, match_stmt.span)
+            }
+            Statement::Break(break_stmt) => {
+                self.check_jump("break", break_stmt.label.as_ref(), break_stmt.span);
+                if let Some(expr) = &break_stmt.expr {
+                    self.check_expr(expr);
+                }
+            }
+            Statement::Continue(stmt) => {
+                self.check_jump("continue", stmt.label.as_ref(), stmt.span)
+            }
+            Statement::Block(block) => self.check_block(block),
+        }
+    }
+
+    fn check_for(
+        &mut self,
+        label: Option<&String>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        invariants: &[Contract],
+        body: &Block,
+        span: Span,
+    ) {
+        self.check_expr(iterable);
+        // 不变式在绑定循环变量之前检查，不能使用循环变量
+        for invariant in invariants {
+            self.check_contract(invariant);
+        }
+        let elem_ty = self
+            .type_of(iterable)
+            .and_then(|ty| builtins::item_type(&ty));
+
+        self.push_scope();
+        self.bind_pattern(pattern, elem_ty, span);
+        self.check_irrefutable(pattern, Binding::For, span);
+        self.check_loop_body(label, body);
+        self.pop_scope();
+    }
+
+    fn check_loop_body(&mut self, label: Option<&String>, body: &Block) {
+        self.loops.push(label.cloned());
+        self.check_block(body);
+        self.loops.pop();
+    }
+
+    // `break` 或 `continue` 的目标：带标签时是同名的外层循环，否则是最内层的循环
+    fn check_jump(&mut self, keyword: &str, label: Option<&String>, span: Span) {
+        if let Some(label) = label {
+            if self.loops.iter().flatten().any(|name| name == label) {
+                return;
+            }
+            let labels = self.loops.iter().flatten().map(String::as_str);
+            let help = find_similar(label, labels)
+                .map(|similar| {
+                    similar_help("a label", &format!("'{}", label), &format!("'{}", similar))
+                })
+                .or_else(|| {
+                    Some("labels are declared on loops: `'outer: while ... {`".to_string())
+                });
+            self.report(
+                ErrorCode::E0426,
+                format!("use of undeclared label `'{}`", label),
+                span,
+                help,
+            );
+        } else if self.loops.is_empty() {
+            // returns 中除了所在的函数还有闭包时，`break` 写在闭包体中
+            if self.returns.len() > 1 {
+                self.report(
+                    ErrorCode::E0267,
+                    format!("`{}` inside of a closure", keyword),
+                    span,
+                    Some("a closure cannot leave a loop around it; use `return` to leave the closure".to_string()),
+                );
+            } else {
+                self.report(
+                    ErrorCode::E0268,
+                    format!("`{}` outside of a loop", keyword),
+                    span,
+                    Some(format!(
+                        "`{}` can only be used inside `while` and `for` loops",
+                        keyword
+                    )),
+                );
+            }
+        }
+    }
+
+    fn check_match(&mut self, scrutinee: &Expr, arms: &[MatchArm], span: Span) {
+        self.check_expr(scrutinee);
+        let scrutinee_ty = self.type_of(scrutinee);
+
+        for arm in arms {
+            self.push_scope();
+            self.bind_pattern(&arm.pattern, scrutinee_ty.clone(), arm.span);
+            if let Some(guard) = &arm.guard {
+                self.check_expr(guard);
+            }
+            self.check_expr(&arm.body);
+            self.pop_scope();
+        }
+
+        let exhaustiveness = Exhaustiveness {
+            enums: &self.enums,
+            variants: &self.variants,
+        };
+        if let Some(missing) = exhaustiveness.missing(arms) {
+            let help = if missing == "_" {
+                "add a wildcard arm `_ => ...`".to_string()
+            } else {
+                format!("add an arm for `{}`, or a wildcard arm `_ => ...`", missing)
+            };
+            self.report(
+                ErrorCode::E0297,
+                format!("non-exhaustive patterns: `{}` not covered", missing),
+                span,
+                Some(help),
+            );
+        }
+    }
+
+    // let、for 和参数中的模式必须匹配所有的值，可能不匹配的模式要用 `let ... else` 或 match
+    fn check_irrefutable(&mut self, pattern: &Pattern, binding: Binding, span: Span) {
+        let exhaustiveness = Exhaustiveness {
+            enums: &self.enums,
+            variants: &self.variants,
+        };
+        let Some(value) = exhaustiveness.refuted(pattern) else {
+            return;
+        };
+        let help = match binding {
+            Binding::Let => {
+                "use `let ... else { ... }` to handle the values that do not match, or a `match`"
+            }
+            Binding::For => "bind the whole element and `match` on it in the loop body",
+            Binding::Argument => "bind the whole argument and `match` on it in the function body",
+        };
+        self.report(
+            ErrorCode::E0297,
+            format!(
+                "refutable pattern in {}: `{}` not covered",
+                binding.describe(),
+                value
+            ),
+            span,
+            Some(help.to_string()),
+        );
+    }
+
+    // `let ... else` 的 else 块在模式不匹配时执行，必须以 return、break 或 continue 离开
+    fn check_let_else(&mut self, else_block: &Block) {
+        self.check_block(else_block);
+        if !Divergence::new(&self.functions).block(else_block) {
+            self.report(
+                ErrorCode::E0308,
+                "the `else` block of `let ... else` must diverge".to_string(),
+                else_block.span,
+                Some(
+                    "end the block with `return`, `break`, `continue` or a call to `panic`"
+                        .to_string(),
+                ),
+            );
+        }
+    }
+
+    // `?` 只能作用于 Option 或 Result，并且所在函数返回同一种枚举
+    fn check_try(&mut self, inner: &Expr, span: Span) {
+        self.check_expr(inner);
+        let operand = self.type_of(inner);
+        let kind = operand.as_ref().and_then(TryKind::of);
+        if let Some(ty) = operand.filter(|ty| kind.is_none() && self.is_known_type(ty)) {
+            self.report(
+                ErrorCode::E0277,
+                format!(
+                    "the `?` operator can only be applied to values of type `Option` or `Result`, found `{}`",
+                    ty
+                ),
+                span,
+                None,
+            );
+            return;
+        }
+
+        let ret = match self.returns.last() {
+            Some(Some(ret)) => ret.clone(),
+            Some(None) => return, // 闭包的返回类型由 `?` 决定
+            None => {
+                self.report(
+                    ErrorCode::E0277,
+                    "the `?` operator can only be used inside a function".to_string(),
+                    span,
+                    None,
+                );
+                return;
+            }
+        };
+        match (TryKind::of(&ret), kind) {
+            (None, _) => self.report(
+                ErrorCode::E0277,
+                "the `?` operator can only be used in a function that returns `Option` or `Result`"
+                    .to_string(),
+                span,
+                Some(format!("this function returns `{}`", ret)),
+            ),
+            (Some(expected), Some(found)) if expected != found => self.report(
+                ErrorCode::E0277,
+                format!(
+                    "the `?` operator on `{}` cannot be used in a function that returns `{}`",
+                    found.name(),
+                    expected.name()
+                ),
+                span,
+                Some(format!(
+                    "use `match` to convert the `{}` into `{}`",
+                    found.name(),
+                    ret
+                )),
+            ),
+            _ => {}
+        }
+    }
+
+    // 类型完全确定：不是类型参数，也不是未知的名字
+    fn is_known_type(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Named(name) | Type::Generic(name, _) => {
+                self.structs.contains_key(name) || self.enums.contains_key(name)
+            }
+            Type::Infer | Type::Never => false,
+            _ => true,
+        }
+    }
+
+    // 检查完一个表达式后记录它的类型，之后的 type_of 直接读取，不再重新推断它的子表达式；
+    // 否则检查一长串运算的每一层都要重新推断下面所有层的类型。节点的地址只在一次分析中有效，
+    // 每次分析开始时清空。字面量和名字的类型不需要推断，而且变量的类型之后还可能补全，不记录
+    fn check_expr(&mut self, expr: &Expr) {
+        self.check_expr_kind(expr);
+        if !matches!(
+            expr,
+            Expr::Literal(_, _) | Expr::Ident(_, _) | Expr::Path(_, _)
+        ) {
+            let ty = self.type_of(expr);
+            self.expr_types.insert(expr as *const Expr as usize, ty);
+        }
+    }
+
+    fn check_expr_kind(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(Literal::Int(n, Some(ty)), span) => {
+                self.check_literal_range(i128::from(*n), ty, *span)
+            }
+            Expr::Unary(UnOp::Neg, inner, span, _)
+                if matches!(inner.as_ref(), Expr::Literal(Literal::Int(_, Some(_)), _)) =>
+            {
+                if let Some((value, Some(ty))) = literals::int_literal(expr) {
+                    self.check_literal_range(value, ty, *span);
+                }
+            }
+            Expr::Literal(_, _) => {}
+            Expr::Ident(name, span) => {
+                self.resolve_value(name, *span, "value");
+                self.check_static_mut(name, *span);
+            }
+            Expr::Path(segments, span) => self.resolve_path(segments, *span),
+            Expr::Turbofish(path, types, span) => {
+                if let Some(name) = self.check_turbofish(path, types, *span) {
+                    self.report(
+                        ErrorCode::E0704,
+                        format!("`{}` must be called", name),
+                        *span,
+                        Some(format!("write `{}::<T>()` to get the value", name)),
+                    );
+                }
+            }
+            Expr::Binary(op, left, right, span, operand_type) => {
+                self.check_expr(left);
+                self.check_expr(right);
+                if literals::same_operand_types(op) {
+                    self.check_operand_literals(left, right);
+                }
+                self.check_operands(op, left, right, *span);
+                self.record_operand_type(op, left, right, operand_type);
+                if *op == BinOp::Add && self.type_of(left) == Some(Type::String) {
+                    self.record_allocation(Allocation::Concat { span: *span });
+                }
+            }
+            Expr::Unary(UnOp::Deref, inner, span, _) | Expr::Deref(inner, span) => {
+                self.check_expr(inner);
+                self.check_deref(inner, *span);
+            }
+            Expr::Unary(UnOp::Ref | UnOp::RefMut, inner, _, _) | Expr::Ref(inner, _, _)
+                if is_slicing(inner) =>
+            {
+                if let Expr::IndexAccess(base, range, span) = inner.as_ref() {
+                    self.check_expr(base);
+                    self.check_expr(range);
+                    self.check_slicing(base, *span);
+                }
+            }
+            Expr::Unary(op, inner, _, operand_type) => {
+                self.check_expr(inner);
+                self.record_unary_type(op, inner, operand_type);
+            }
+            Expr::Ref(inner, _, _) => self.check_expr(inner),
+            Expr::Call(callee, args, span, operand_type) => {
+                match callee.as_ref() {
+                    Expr::Ident(name, span) => self.resolve_value(name, *span, "function"),
+                    Expr::Turbofish(path, types, turbofish_span) => {
+                        if let Some(name) = self.check_turbofish(path, types, *turbofish_span) {
+                            self.check_intrinsic_args(name, args, *span);
+                        }
+                    }
+                    other => self.check_expr(other),
+                }
+                let expected = self.expected_arg_types(callee, args);
+                for (arg, expected) in args.iter().zip(expected) {
+                    match arg {
+                        Expr::Closure(params, ret, body, span) => {
+                            self.check_closure(params, ret.as_ref(), body, *span, expected)
+                        }
+                        arg => self.check_expr(arg),
+                    }
+                }
+                self.check_arguments(callee, args, *span);
+                self.check_inference(callee, args);
+                self.check_pure_arguments(callee, args);
+                self.check_argument_literals(callee, args);
+                match callee.as_ref() {
+                    Expr::Ident(name, _) => {
+                        self.check_builtin_call(name, None, args, *span);
+                        match args.first() {
+                            Some(arg) if name == "abs" && self.builtin(name, false).is_some() => {
+                                self.record_int_type(arg, operand_type)
+                            }
+                            _ => {}
+                        }
+                    }
+                    Expr::Path(segments, _) => {
+                        self.check_builtin_call(&segments.join("::"), None, args, *span)
+                    }
+                    _ => {}
+                }
+                self.record_call(callee, *span);
+            }
+            Expr::MethodCall(receiver, method, args, span, operand_type) => {
+                // 内建函数按方法调用时检查签名，其他方法由 resolve_method 找到调用的函数
+                self.check_expr(receiver);
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                self.check_builtin_call(method, Some(receiver), args, *span);
+                if method == "abs" && self.builtin(method, true).is_some() {
+                    self.record_int_type(receiver, operand_type);
+                }
+                match self.builtin(method, true) {
+                    Some(builtin) if builtin.allocates => {
+                        self.record_allocation(Allocation::Builtin {
+                            name: method.clone(),
+                            span: *span,
+                        })
+                    }
+                    Some(_) => {}
+                    None => {
+                        let function = self.resolve_method(receiver, method, *span);
+                        self.record_function_call(function.as_deref().unwrap_or(method), *span)
+                    }
+                }
+            }
+            Expr::FieldAccess(base, field, span) => {
+                self.check_expr(base);
+                if let Some(struct_name) = self.type_of(base).and_then(|ty| self.struct_name(&ty)) {
+                    self.check_field(&struct_name, field, *span);
+                }
+            }
+            Expr::TupleIndex(base, index, span) => {
+                self.check_expr(base);
+                self.check_tuple_index(base, *index, *span);
+            }
+            Expr::IndexAccess(base, index, span) => {
+                self.check_expr(base);
+                self.check_expr(index);
+                if is_slicing(expr) {
+                    self.check_slicing(base, *span);
+                    self.report(
+                        ErrorCode::E0161,
+                        "a slice must be used through a reference".to_string(),
+                        *span,
+                        Some("the length of a slice is only known at run time; borrow it with `&` or `&mut`".to_string()),
+                    );
+                }
+            }
+            Expr::StructLit(name, fields, span) => {
+                if self.structs.contains_key(name) {
+                    for (field, _) in fields {
+                        self.check_field(name, field, *span);
+                    }
+                } else {
+                    self.check_type_name(name, *span);
+                }
+                let params = self.struct_params(name);
+                for (field, value) in fields {
+                    self.check_expr(value);
+                    if let Some(ty) = self.field_type(name, field) {
+                        self.check_literal_type(value, &ty);
+                        // 泛型结构体的类型实参由字段的值推断
+                        if !params.iter().any(|param| infer::mentions(&ty, param)) {
+                            self.check_value(value, &ty);
+                        }
+                    }
+                }
+            }
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => {
+                for element in elements {
+                    self.check_expr(element);
+                }
+            }
+            Expr::Range(start, end, _, _) => {
+                for bound in start.iter().chain(end) {
+                    self.check_expr(bound);
+                }
+            }
+            Expr::Assign(target, value, _) | Expr::CompoundAssign(_, target, value, _, _) => {
+                self.check_expr(target);
+                self.check_expr(value);
+                self.check_place(target);
+                let shift = matches!(expr, Expr::CompoundAssign(op, ..) if !literals::same_operand_types(op));
+                if let Some(ty) = self.type_of(target).filter(|_| !shift) {
+                    self.check_literal_type(value, &ty);
+                    if matches!(expr, Expr::Assign(..)) {
+                        self.check_value(value, &ty);
+                    }
+                }
+                if let Expr::CompoundAssign(op, _, _, _, operand_type) = expr {
+                    self.record_operand_type(op, target, value, operand_type);
+                }
+                if let (Expr::CompoundAssign(BinOp::Add, ..), Some(Type::String)) =
+                    (expr, self.type_of(target))
+                {
+                    self.record_allocation(Allocation::Concat { span: expr.span() });
+                }
+            }
+            Expr::InlineAsm(asm, span) => self.check_inline_asm(asm, *span),
+            Expr::Block(block, _) => self.check_block(block),
+            Expr::Unsafe(block, _) => {
+                self.unsafe_depth += 1;
+                self.check_block(block);
+                self.unsafe_depth -= 1;
+            }
+            // async 块不能离开外层的循环
+            Expr::AsyncBlock(block, _) => {
+                let context = self.async_context.replace(AsyncContext::Block);
+                let loops = std::mem::take(&mut self.loops);
+                self.check_block(block);
+                self.loops = loops;
+                self.async_context = context;
+            }
+            Expr::Await(inner, span) => {
+                self.check_expr(inner);
+                if self.async_context.is_none() {
+                    self.report(
+                        ErrorCode::E0728,
+                        "`await` is only allowed inside `async` functions and blocks".to_string(),
+                        *span,
+                        Some("declare the function with `async fn`".to_string()),
+                    );
+                }
+            }
+            Expr::If(cond, then_block, else_block, _) => {
+                self.check_if(cond, then_block, else_block.as_ref())
+            }
+            Expr::Match(scrutinee, arms, span) => self.check_match(scrutinee, arms, *span),
+            Expr::While(label, cond, body, _) => {
+                self.check_expr(cond);
+                self.check_condition(cond);
+                self.check_loop_body(label.as_ref(), body);
+            }
+            Expr::For(label, pattern, iterable, body, span) => {
+                self.check_for(label.as_ref(), pattern, iterable, &[], body, *span)
+            }
+            Expr::Break(label, value, span) => {
+                self.check_jump("break", label.as_ref(), *span);
+                if let Some(value) = value {
+                    self.check_expr(value);
+                }
+            }
+            Expr::Return(value, span) => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                    self.check_return_value(value);
+                }
+                self.check_async_exit("`return`", *span);
+            }
+            Expr::Continue(label, span) => self.check_jump("continue", label.as_ref(), *span),
+            Expr::Try(inner, span) => {
+                self.check_try(inner, *span);
+                self.check_async_exit("`?`", *span);
+            }
+            Expr::MacroCall(call) => self.unexpanded_macro(call),
+            Expr::Closure(params, ret, body, span) => {
+                self.check_closure(params, ret.as_ref(), body, *span, None)
+            }
+            Expr::Cast(inner, ty, span) => {
+                self.check_expr(inner);
+                self.check_local_type(ty, *span);
+                self.check_cast(inner, &ty.expand_typeof(), *span);
+            }
+        }
+    }
+
+    // 没有标注类型的参数取 `expected`（调用处的形参类型）中对应的类型；
+    // 检查后记录闭包的类型，参数和返回值推断不出的部分为 `_`
+    fn check_closure(
+        &mut self,
+        params: &[Parameter],
+        ret: Option<&Type>,
+        body: &Expr,
+        span: Span,
+        expected: Option<Type>,
+    ) {
+        self.record_allocation(Allocation::Closure { span });
+        let expected = match expected {
+            Some(Type::Function(params, _, _)) => params,
+            _ => Vec::new(),
+        };
+        self.closures.push(self.scopes.len());
+        self.push_scope();
+        let mut param_types = Vec::new();
+        for (i, param) in params.iter().enumerate() {
+            self.check_type(&param.ty, param.span);
+            let ty = match &param.ty {
+                Type::Infer => expected.get(i).cloned().unwrap_or(Type::Infer),
+                ty => ty.clone(),
+            };
+            let known = (!ty.has_infer()).then(|| ty.clone());
+            self.bind_pattern(&param.pattern, known, param.span);
+            self.check_irrefutable(&param.pattern, Binding::Argument, param.span);
+            param_types.push(ty);
+        }
+        if let Some(ret) = ret {
+            self.check_type(ret, span);
+        }
+        let ret = ret.filter(|ty| **ty != Type::Infer);
+        self.returns.push(ret.cloned());
+        let loops = std::mem::take(&mut self.loops);
+        let context = self.async_context.take();
+        self.check_expr(body);
+        self.async_context = context;
+        self.loops = loops;
+        self.returns.pop();
+        let ret = match ret {
+            Some(ty) => ty.clone(),
+            None => self.type_of(body).unwrap_or(Type::Infer),
+        };
+        self.pop_scope();
+        self.closures.pop();
+        self.closure_types
+            .insert(span, Type::Function(param_types, Box::new(ret), false));
+    }
+
+    fn record_allocation(&mut self, allocation: Allocation) {
+        if let Some(function) = &self.current_function {
+            self.allocations.allocate(function, allocation);
+        }
+    }
+
+    // 调用处的分配：分配的内建函数、函数值，以及到用户定义的函数的调用关系
+    fn record_call(&mut self, callee: &Expr, span: Span) {
+        let name = match callee {
+            Expr::Ident(name, _) if self.lookup_variable(name).is_some() => {
+                return self.record_allocation(Allocation::CallValue { span });
+            }
+            Expr::Ident(name, _) => name.clone(),
+            Expr::Path(segments, _) => segments.join("::"),
+            Expr::Turbofish(..) => return,
+            _ => return self.record_allocation(Allocation::CallValue { span }),
+        };
+        match self.builtin(&name, false) {
+            Some(builtin) if builtin.allocates => {
+                self.record_allocation(Allocation::Builtin { name, span })
+            }
+            Some(_) => {}
+            None => self.record_function_call(&name, span),
+        }
+    }
+
+    fn record_function_call(&mut self, name: &str, span: Span) {
+        let Some(function) = &self.current_function else {
+            return;
+        };
+        if let Some(sig) = self.functions.get(name).filter(|sig| !sig.builtin) {
+            self.allocations.call(function, name, span, sig.no_alloc);
+        } else if let Some((module, item)) = name.split_once("::") {
+            // 经过模块名调用的函数没有记录签名，视为没有标注 `#[no_alloc]`
+            let namespace = self.namespaces.get(module);
+            if namespace.is_some_and(|namespace| namespace.items.contains(item)) {
+                self.allocations.call(function, name, span, false);
+            }
+        }
+    }
+
+    // 类型是 `pure fn` 的参数只接受纯函数：没有副作用的函数和闭包，以及类型是 `pure fn` 的变量
+    fn check_pure_arguments(&mut self, callee: &Expr, args: &[Expr]) {
+        let Some((name, sig)) = self.user_function(callee) else {
+            return;
+        };
+        let name = name.to_string();
+        let params = sig.params.clone();
+        for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+            if matches!(param, Type::Function(_, _, true)) {
+                let note = format!("parameter {} of `{}` has type `{}`", index + 1, name, param);
+                self.check_pure_value(arg, format!("`{}` expects a pure function", name), note);
+            }
+        }
+    }
+
+    // `expected` 说明哪里要求纯函数，`note` 给出要求的类型
+    fn check_pure_value(&mut self, value: &Expr, expected: String, note: String) {
+        let Some((what, problem, help)) = self.impure_argument(value) else {
+            return;
+        };
+        let message = format!(
+            "{}, but {} {}",
+            expected,
+            what,
+            match &problem {
+                Some(effect) => effect.describe(),
+                None => "may have side effects".to_string(),
+            }
+        );
+        let mut diagnostic = Diagnostic::error(message, value.span())
+            .with_code(ErrorCode::E0710)
+            .with_note(note);
+        if let Some(effect) = &problem {
+            diagnostic = effect_notes(diagnostic, effect);
+        }
+        self.errors.push(diagnostic.with_help(help));
+    }
+
+    // 不纯的实参：(说明实参的主语, 第一个副作用, 帮助)；纯的实参为 None
+    fn impure_argument(&self, arg: &Expr) -> Option<(String, Option<Effect>, String)> {
+        let (bindings, pure_values) = self.visible_values();
+        let help = "only functions and closures without side effects can be passed here";
+        match arg {
+            Expr::Closure(params, _, body, _) => {
+                let effect = self
+                    .effects
+                    .closure_effect(params, body, &bindings, &pure_values)?;
+                Some(("the closure".to_string(), Some(effect), help.to_string()))
+            }
+            Expr::Ident(name, _) if self.lookup_variable(name).is_some() => {
+                if pure_values.contains(name) {
+                    return None;
+                }
+                let ty = self.lookup_variable(name)?.ty.clone();
+                let help = match ty {
+                    Some(Type::Function(params, ret, _)) => format!(
+                        "declare `{}` with the type `{}`",
+                        name,
+                        Type::Function(params, ret, true)
+                    ),
+                    _ => format!("declare `{}` with a `pure fn` type", name),
+                };
+                Some((format!("`{}`", name), None, help))
+            }
+            Expr::Ident(name, _) => {
+                let effect = self.function_effect(name, arg.span())?;
+                Some((format!("`{}`", name), Some(effect), help.to_string()))
+            }
+            Expr::Path(segments, _) => {
+                let name = segments.join("::");
+                let effect = self.function_effect(&name, arg.span())?;
+                Some((format!("`{}`", name), Some(effect), help.to_string()))
+            }
+            _ => Some((
+                "this argument".to_string(),
+                None,
+                "pass a function name, a closure or a variable of a `pure fn` type".to_string(),
+            )),
+        }
+    }
+
+    // 函数的第一个副作用；内建函数按它们的签名
+    fn function_effect(&self, name: &str, span: Span) -> Option<Effect> {
+        if let Some(effect) = self.effects.effect_of(name) {
+            return Some(effect.clone());
+        }
+        let builtin = self.builtin(name, false).filter(|builtin| !builtin.pure)?;
+        Some(Effect::Builtin {
+            name: builtin.name.to_string(),
+            span,
+        })
+    }
+
+    // 直接调用的用户定义的函数（不是变量、枚举变体或内建函数）
+    fn user_function(&self, callee: &Expr) -> Option<(&str, &FnSig)> {
+        let Expr::Ident(name, _) = callee else {
+            return None;
+        };
+        if self.lookup_variable(name).is_some() || self.variants.contains_key(name) {
+            return None;
+        }
+        let (name, sig) = self.functions.get_key_value(name)?;
+        (!sig.builtin).then_some((name, sig))
+    }
+
+    // 形参类型和实参合一，`result` 是返回类型和上下文要求的类型；没有后缀的整数字面量的类型
+    // 由其他实参和上下文决定，它们确定不了时才是 `i32`；发散的实参不提供信息
+    fn infer_args<'e>(
+        &self,
+        type_params: &[String],
+        params: &[Type],
+        args: impl Iterator<Item = &'e Expr>,
+        result: Option<(&Type, &Type)>,
+    ) -> Inference {
+        let args: Vec<&Expr> = args.collect();
+        let mut inference = Inference::default();
+        for (index, (param, arg)) in params.iter().zip(&args).enumerate() {
+            if literals::is_unsuffixed(arg) {
+                continue;
+            }
+            if let Some(ty) = self.type_of(arg).filter(|ty| *ty != Type::Never) {
+                inference.unify(type_params, param, &ty, index);
+            }
+        }
+        if let Some((ret, expected)) = result {
+            inference.unify(type_params, ret, expected, args.len());
+        }
+        for (index, (param, arg)) in params.iter().zip(&args).enumerate() {
+            if !literals::is_unsuffixed(arg) {
+                continue;
+            }
+            if !inference.missing(type_params, param).is_empty() {
+                inference.unify(type_params, param, &Type::I32, index);
+            } else if let Type::Named(name) = param {
+                inference.check_literal(name, index);
+            }
+        }
+        inference
+    }
+
+    fn infer_call<'e>(&self, sig: &FnSig, args: impl Iterator<Item = &'e Expr>) -> Inference {
+        self.infer_args(&sig.type_params(), &sig.params, args, None)
+    }
+
+    // 调用用户定义的函数的结果类型；泛型的返回类型中有推断不出的类型参数时为 None
+    fn call_type<'e>(&self, sig: &FnSig, args: impl Iterator<Item = &'e Expr>) -> Option<Type> {
+        let ret = sig.ret.as_ref()?;
+        if sig.generics.is_empty() {
+            return Some(ret.clone());
+        }
+        let inference = self.infer_call(sig, args);
+        inference
+            .missing(&sig.type_params(), ret)
+            .is_empty()
+            .then(|| inference.apply(ret))
+    }
+
+    // 闭包实参的期望类型：由其他实参推断出的形参类型，推断不出的类型参数为 `_`
+    fn expected_arg_types(&self, callee: &Expr, args: &[Expr]) -> Vec<Option<Type>> {
+        let Some((_, sig)) = self.user_function(callee) else {
+            return vec![None; args.len()];
+        };
+        // 还没有检查的闭包没有类型，不参与推断
+        let inference = self.infer_call(sig, args.iter());
+        (0..args.len())
+            .map(|i| {
+                let param = sig.params.get(i)?;
+                Some(inference.resolve(&sig.type_params(), param))
+            })
+            .collect()
+    }
+
+    // 同一个类型参数从不同的实参推断出不同的类型
+    fn check_inference(&mut self, callee: &Expr, args: &[Expr]) {
+        let Some((name, sig)) = self.user_function(callee) else {
+            return;
+        };
+        if sig.generics.is_empty() {
+            return;
+        }
+        let name = name.to_string();
+        let sig = sig.clone();
+        let inference = self.infer_call(&sig, args.iter());
+        for param in &sig.generics {
+            let Some(ty) = inference.types.get(&param.name) else {
+                continue;
+            };
+            for bound in &param.bounds {
+                if !self.traits().implements(ty, bound) {
+                    self.unsatisfied_bound(ty, bound, param, &name, callee.span());
+                }
+            }
+        }
+        for conflict in inference.conflicts {
+            let arg = &args[conflict.index];
+            // 字面量的类型由其他实参决定，不论它们在前还是在后
+            let because = match literals::is_unsuffixed(arg) {
+                true => "the other arguments",
+                false => "an earlier argument",
+            };
+            self.report(
+                ErrorCode::E0308,
+                format!(
+                    "expected `{}`, found {}",
+                    conflict.expected,
+                    self.describe(arg, &conflict.found)
+                ),
+                arg.span(),
+                Some(format!(
+                    "type parameter `{}` of `{}` is `{}` because of {}",
+                    conflict.param, name, conflict.expected, because
+                )),
+            );
+        }
+    }
+
+    // 指向调用，并说明是哪个函数的哪个约束
+    fn unsatisfied_bound(
+        &mut self,
+        ty: &Type,
+        bound: &str,
+        param: &GenericParam,
+        function: &str,
+        span: Span,
+    ) {
+        let message = format!("the trait bound `{}: {}` is not satisfied", ty, bound);
+        let help = match ty {
+            Type::Named(name) if self.traits().is_generic(name) => Some(format!(
+                "add `{}` to the bounds of type parameter `{}`",
+                bound, name
+            )),
+            Type::Named(name) | Type::Generic(name, _)
+                if derive::TRAITS.contains(&bound)
+                    && (self.structs.contains_key(name) || self.enums.contains_key(name)) =>
+            {
+                Some(format!("add `#[derive({})]` to `{}`", bound, name))
+            }
+            _ => None,
+        };
+        let mut diagnostic = Diagnostic::error(message, span)
+            .with_code(ErrorCode::E0709)
+            .with_note(format!(
+                "required by the bound `{}: {}` of `{}` at line {}, column {}",
+                param.name, bound, function, param.span.line, param.span.column
+            ));
+        if let Some(help) = help {
+            diagnostic = diagnostic.with_help(help);
+        }
+        self.errors.push(diagnostic);
+    }
+
+    // 有类型标注的 let 中调用泛型函数，标注的类型也参与推断
+    fn check_call_result(&mut self, expr: &Expr, expected: &Type) {
+        let Expr::Call(callee, args, _, _) = expr else {
+            return;
+        };
+        let Some((name, sig)) = self.user_function(callee) else {
+            return;
+        };
+        let Some(ret) = sig.ret.as_ref().filter(|_| !sig.generics.is_empty()) else {
+            return;
+        };
+        let inference = self.infer_args(
+            &sig.type_params(),
+            &sig.params,
+            args.iter(),
+            Some((ret, expected)),
+        );
+        let name = name.to_string();
+        let found = inference.apply(ret);
+        let between_args: Vec<usize> = self
+            .infer_call(sig, args.iter())
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.index)
+            .collect();
+        for conflict in inference.conflicts {
+            if between_args.contains(&conflict.index) {
+                continue; // 实参之间的矛盾由 check_inference 报告
+            }
+            // 类型参数由标注决定时，字面量实参可能和它矛盾
+            if let Some(arg) = args.get(conflict.index) {
+                self.report(
+                    ErrorCode::E0308,
+                    format!("expected `{}`, found integer", conflict.expected),
+                    arg.span(),
+                    Some(format!(
+                        "type parameter `{}` of `{}` is `{}` because of the type annotation",
+                        conflict.param, name, conflict.expected
+                    )),
+                );
+                continue;
+            }
+            self.report(
+                ErrorCode::E0308,
+                format!("expected `{}`, found `{}`", expected, found),
+                expr.span(),
+                Some(format!(
+                    "type parameter `{}` of `{}` is `{}` because of the arguments",
+                    conflict.param, name, conflict.expected
+                )),
+            );
+            break;
+        }
+    }
+
+    // 返回类型中的类型参数既不能由实参推断、也没有上下文约束时需要类型标注；
+    // `what` 说明在哪里加标注
+    fn check_annotations_needed(&mut self, expr: &Expr, what: &str) {
+        let Expr::Call(callee, args, span, _) = expr else {
+            return;
+        };
+        let Some((name, sig)) = self.user_function(callee) else {
+            return;
+        };
+        let Some(ret) = sig.ret.as_ref().filter(|_| !sig.generics.is_empty()) else {
+            return;
+        };
+        let missing = self
+            .infer_call(sig, args.iter())
+            .missing(&sig.type_params(), ret);
+        let Some(param) = missing.first() else {
+            return;
+        };
+        let help = format!(
+            "the arguments do not determine type parameter `{}` of `{}`; {}",
+            param, name, what
+        );
+        self.report(
+            ErrorCode::E0282,
+            "type annotations needed".to_string(),
+            *span,
+            Some(help),
+        );
+    }
+
+    // async 块立即求值，其中的 `return` 和 `?` 会离开外层的函数而不是 async 块
+    fn check_async_exit(&mut self, what: &str, span: Span) {
+        if self.async_context == Some(AsyncContext::Block) {
+            self.report(
+                ErrorCode::E0658,
+                format!("{} in an `async` block is not supported yet", what),
+                span,
+                Some(
+                    "the value of an `async` block is the value of its last expression".to_string(),
+                ),
+            );
+        }
+    }
+
+    fn check_if(&mut self, cond: &Expr, then_block: &Block, else_block: Option<&ElseBranch>) {
+        self.check_expr(cond);
+        self.check_condition(cond);
+        self.check_block(then_block);
+        match else_block {
+            Some(ElseBranch::Block(block)) => self.check_block(block),
+            Some(ElseBranch::If(nested)) => {
+                self.check_if(&nested.cond, &nested.then_block, nested.else_block.as_ref())
+            }
+            None => {}
+        }
+    }
+
+    // 赋值的左侧必须是位置表达式：变量、字段、下标或解引用
+    fn check_place(&mut self, target: &Expr) {
+        if matches!(
+            target,
+            Expr::Ident(_, _)
+                | Expr::FieldAccess(_, _, _)
+                | Expr::TupleIndex(_, _, _)
+                | Expr::IndexAccess(_, _, _)
+                | Expr::Unary(UnOp::Deref, _, _, _)
+                | Expr::Deref(_, _)
+        ) {
+            self.check_place_mutable(target, true);
+            return;
+        }
+        self.report(
+            ErrorCode::E0070,
+            "invalid left-hand side of assignment".to_string(),
+            target.span(),
+            Some(
+                "only variables, fields, indexing and dereferences can be assigned to".to_string(),
+            ),
+        );
+    }
+
+    // 沿着位置表达式向内，经过的引用和指针（包括字段和下标的自动解引用）都必须是可变的；
+    // Rc 中的值是共享的，不能修改。闭包捕获的是变量的副本，不能给它赋值；
+    // `owned` 表示修改的是变量自己的存储，而不是经过可变引用修改它指向的值
+    fn check_place_mutable(&mut self, place: &Expr, owned: bool) {
+        let base = match place {
+            Expr::Ident(name, span) if owned && self.is_captured(name) => {
+                return self.report(
+                    ErrorCode::E0594,
+                    format!("cannot assign to `{}`, as it is captured by a closure", name),
+                    *span,
+                    Some(format!(
+                        "the closure captures a copy of `{}`; return the new value from the closure and assign it outside",
+                        name
+                    )),
+                );
+            }
+            Expr::Ident(name, span) => return self.check_global_assign(name, *span),
+            Expr::FieldAccess(base, _, _)
+            | Expr::TupleIndex(base, _, _)
+            | Expr::IndexAccess(base, _, _)
+            | Expr::Unary(UnOp::Deref, base, _, _)
+            | Expr::Deref(base, _) => base,
+            _ => return,
+        };
+        let ty = self.type_of(base);
+        let shared = match &ty {
+            Some(Type::Reference(inner, _)) => builtins::is_shared(inner),
+            Some(ty) => builtins::is_shared(ty),
+            None => false,
+        };
+        if shared {
+            return self.report(
+                ErrorCode::E0594,
+                "cannot assign to data in an `Rc`".to_string(),
+                place.span(),
+                Some(
+                    "the value in an `Rc` is shared; build a new value with `Rc::new` instead"
+                        .to_string(),
+                ),
+            );
+        }
+        let (kind, help) = match ty {
+            Some(Type::Reference(_, false)) => (
+                "`&` reference",
+                "borrow the value with `&mut` to assign through the reference",
+            ),
+            Some(Type::Pointer(_, false)) => (
+                "`*const` pointer",
+                "cast the pointer to `*mut` to assign through it",
+            ),
+            Some(Type::Reference(_, true) | Type::Pointer(_, true)) => {
+                return self.check_place_mutable(base, false)
+            }
+            _ => return self.check_place_mutable(base, owned),
+        };
+        self.report(
+            ErrorCode::E0594,
+            format!("cannot assign to data behind a {}", kind),
+            place.span(),
+            Some(help.to_string()),
+        );
+    }
+
+    // 常量在使用处内联，不能赋值；静态变量只有 `static mut` 可以修改
+    fn check_global_assign(&mut self, name: &str, span: Span) {
+        match self.global(name).map(|global| global.kind) {
+            Some(GlobalKind::Const) => self.report(
+                ErrorCode::E0070,
+                format!("cannot assign to constant `{}`", name),
+                span,
+                Some("use a `static mut` for a global value that changes".to_string()),
+            ),
+            Some(GlobalKind::Static) => self.report(
+                ErrorCode::E0594,
+                format!("cannot assign to immutable static `{}`", name),
+                span,
+                Some(format!(
+                    "declare it as `static mut {}` to allow changes",
+                    name
+                )),
+            ),
+            Some(GlobalKind::StaticMut) | None => {}
+        }
+    }
+
+    // 任何地方都可以修改 `static mut`，读写它必须写在 unsafe 块中
+    fn check_static_mut(&mut self, name: &str, span: Span) {
+        let mutable = self.global(name).map(|global| global.kind) == Some(GlobalKind::StaticMut);
+        if mutable && self.unsafe_depth == 0 {
+            self.report(
+                ErrorCode::E0133,
+                format!("use of mutable static `{}` requires an unsafe block", name),
+                span,
+                Some("mutable statics can be changed from anywhere, so every use must be written inside `unsafe { ... }`".to_string()),
+            );
+        }
+    }
+
+    // const 和 static 的初始值在编译时求值：只能由字面量、运算、类型转换、引用、
+    // 结构体、元组、数组和变体的值以及其他常量组成；常量不能引用静态变量
+    fn check_const_init(&mut self, expr: &Expr, kind: GlobalKind) {
+        let context = match kind {
+            GlobalKind::Const => "constants",
+            GlobalKind::Static | GlobalKind::StaticMut => "statics",
+        };
+        let message = match expr {
+            Expr::Literal(_, _) | Expr::Path(_, _) => return,
+            Expr::Ident(name, span) => {
+                let refers_to_static = matches!(
+                    self.global(name).map(|global| global.kind),
+                    Some(GlobalKind::Static | GlobalKind::StaticMut)
+                );
+                if kind == GlobalKind::Const && refers_to_static {
+                    self.report(
+                        ErrorCode::E0013,
+                        format!("constants cannot refer to statics: `{}` is a static", name),
+                        *span,
+                        Some(format!("make `{}` a `const` instead", name)),
+                    );
+                }
+                return;
+            }
+            Expr::Binary(_, left, right, _, _) | Expr::IndexAccess(left, right, _) => {
+                self.check_const_init(left, kind);
+                return self.check_const_init(right, kind);
+            }
+            Expr::Range(start, end, _, _) => {
+                for bound in start.iter().chain(end) {
+                    self.check_const_init(bound, kind);
+                }
+                return;
+            }
+            Expr::Unary(_, inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _) => return self.check_const_init(inner, kind),
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => {
+                for element in elements {
+                    self.check_const_init(element, kind);
+                }
+                return;
+            }
+            Expr::StructLit(_, fields, _) => {
+                for (_, value) in fields {
+                    self.check_const_init(value, kind);
+                }
+                return;
+            }
+            // 变体构造和布局内建函数在编译时求值
+            Expr::Call(callee, args, _, _) => {
+                let name = match callee.as_ref() {
+                    Expr::Ident(name, _) => Some(name.clone()),
+                    Expr::Path(segments, _) => Some(segments.join("::")),
+                    _ => None,
+                };
+                let variant = name
+                    .as_deref()
+                    .and_then(|name| name.rsplit("::").next())
+                    .is_some_and(|last| self.variants.contains_key(last));
+                if variant || matches!(callee.as_ref(), Expr::Turbofish(_, _, _)) {
+                    for arg in args {
+                        self.check_const_init(arg, kind);
+                    }
+                    return;
+                }
+                match name {
+                    Some(name) => {
+                        format!("cannot call non-const function `{}` in {}", name, context)
+                    }
+                    None => format!("cannot call functions in {}", context),
+                }
+            }
+            Expr::MethodCall(_, method, _, _, _) => {
+                format!("cannot call method `{}` in {}", method, context)
+            }
+            _ => format!("this expression cannot be evaluated in {}", context),
+        };
+        self.report(
+            ErrorCode::E0015,
+            message,
+            expr.span(),
+            Some("initializers may only use literals, operators, casts, references, other constants, and struct, tuple, array or variant values".to_string()),
+        );
+    }
+
+    // 常量和静态变量的整数初始值在编译时求值，结果要在声明的类型的范围内；
+    // 初始值只是一个字面量时由字面量的检查报告
+    fn check_const_value(&mut self, value: &Expr, ty: &Type) {
+        self.check_literal_type(value, ty);
+        if literals::int_literal(value).is_some() || int_range(ty).is_none() {
+            return;
+        }
+        let eval = ConstEval {
+            globals: &self.globals,
+        };
+        let message = match eval.int(value) {
+            Ok(Some(result)) => match literals::out_of_range(result, ty) {
+                Some(_) => format!("the value {} does not fit into the type `{}`", result, ty),
+                None => return,
+            },
+            Ok(None) => return,
+            Err(message) => message,
+        };
+        self.report(
+            ErrorCode::E0080,
+            format!("evaluation of constant value failed: {}", message),
+            value.span(),
+            None,
+        );
+    }
+
+    // 表达式的值要是 `expected` 类型：检查其中决定值的整数字面量
+    fn check_literal_type(&mut self, expr: &Expr, expected: &Type) {
+        match (expr, expected) {
+            (Expr::ArrayLit(elements, _), Type::Array(element, _)) => {
+                for value in elements {
+                    self.check_literal_type(value, element);
+                }
+            }
+            (Expr::TupleLit(elements, _), Type::Tuple(types)) => {
+                for (value, ty) in elements.iter().zip(types) {
+                    self.check_literal_type(value, ty);
+                }
+            }
+            (Expr::Block(block, _) | Expr::Unsafe(block, _), _) => {
+                self.check_block_literals(block, expected)
+            }
+            (Expr::If(_, then_block, else_branch, _), _) => {
+                self.check_branch_literals(then_block, else_branch.as_ref(), expected)
+            }
+            (Expr::Match(_, arms, _), _) => {
+                for arm in arms {
+                    self.check_literal_type(&arm.body, expected);
+                }
+            }
+            // 两侧都是没有后缀的字面量时，check_expr 不知道它们的类型
+            (Expr::Binary(op, left, right, _, _), _)
+                if literals::same_operand_types(op)
+                    && literals::is_unsuffixed(left)
+                    && literals::is_unsuffixed(right) =>
+            {
+                self.check_int_literal(left, expected);
+                self.check_int_literal(right, expected);
+            }
+            _ => self.check_int_literal(expr, expected),
+        }
+    }
+
+    // 块的值（最后一个没有分号的表达式、if 或 match）
+    fn check_block_literals(&mut self, block: &Block, expected: &Type) {
+        match block.statements.last() {
+            Some(Statement::Expr(stmt)) if !stmt.semicolon => {
+                self.check_literal_type(&stmt.expr, expected)
+            }
+            Some(Statement::If(stmt)) => {
+                self.check_branch_literals(&stmt.then_block, stmt.else_block.as_ref(), expected)
+            }
+            Some(Statement::Match(stmt)) => {
+                for arm in &stmt.arms {
+                    self.check_literal_type(&arm.body, expected);
+                }
+            }
+            Some(Statement::Block(block)) => self.check_block_literals(block, expected),
+            _ => {}
+        }
+    }
+
+    fn check_branch_literals(
+        &mut self,
+        then_block: &Block,
+        else_branch: Option<&ElseBranch>,
+        expected: &Type,
+    ) {
+        self.check_block_literals(then_block, expected);
+        match else_branch {
+            Some(ElseBranch::Block(block)) => self.check_block_literals(block, expected),
+            Some(ElseBranch::If(nested)) => {
+                self.check_branch_literals(&nested.then_block, nested.else_block.as_ref(), expected)
+            }
+            None => {}
+        }
+    }
+
+    // 上下文要求整数类型时，没有后缀的字面量取这个类型，带后缀的字面量的类型要和它相同
+    fn check_int_literal(&mut self, expr: &Expr, expected: &Type) {
+        let Some((value, suffix)) = literals::int_literal(expr) else {
+            return;
+        };
+        if int_range(expected).is_none() {
+            return;
+        }
+        match suffix {
+            // 后缀本身的范围在 check_expr 中检查
+            Some(suffix) if suffix != expected => self.report(
+                ErrorCode::E0308,
+                format!("expected `{}`, found `{}`", expected, suffix),
+                expr.span(),
+                Some(format!(
+                    "change the type of the numeric literal from `{}` to `{}`",
+                    suffix, expected
+                )),
+            ),
+            Some(_) => {}
+            None => self.check_literal_range(value, expected, expr.span()),
+        }
+    }
+
+    fn check_literal_range(&mut self, value: i128, ty: &Type, span: Span) {
+        if value < 0 && int_range(ty).is_some_and(|(min, _)| min == 0) {
+            self.report(
+                ErrorCode::E0600,
+                format!("cannot apply unary operator `-` to type `{}`", ty),
+                span,
+                Some("unsigned values cannot be negated".to_string()),
+            );
+        } else if let Some((message, help)) = literals::out_of_range(value, ty) {
+            self.report(ErrorCode::E0706, message, span, Some(help));
+        }
+    }
+
+    // async 块中的 `return` 由 check_async_exit 报告
+    fn check_return_value(&mut self, value: &Expr) {
+        if self.async_context == Some(AsyncContext::Block) {
+            return;
+        }
+        if let Some(Some(ret)) = self.returns.last().cloned() {
+            self.check_literal_type(value, &ret);
+            self.check_value(value, &ret);
+        }
+    }
+
+    // 用户定义的函数的参数类型
+    fn check_argument_literals(&mut self, callee: &Expr, args: &[Expr]) {
+        let name = match callee {
+            Expr::Ident(name, _) if self.lookup_variable(name).is_none() => name.clone(),
+            Expr::Path(segments, _) => segments.join("::"),
+            _ => return,
+        };
+        let sig = match self.functions.get(&name) {
+            Some(sig) if !sig.builtin => sig.clone(),
+            _ => return,
+        };
+        // 泛型参数的字面量按推断出的类型检查
+        let inference = self.infer_call(&sig, args.iter());
+        for (arg, param) in args.iter().zip(&sig.params) {
+            self.check_literal_type(arg, &inference.apply(param));
+        }
+    }
+
+    // 二元运算的一侧是字面量时，它的类型是另一侧的类型；两侧都带后缀时只检查右侧
+    fn check_operand_literals(&mut self, left: &Expr, right: &Expr) {
+        let both = literals::int_literal(left).is_some() && literals::int_literal(right).is_some();
+        for (literal, other) in [(right, left), (left, right)] {
+            if literals::int_literal(literal).is_none() || literals::is_unsuffixed(other) {
+                continue;
+            }
+            if let Some(ty) = self.type_of(other) {
+                self.check_int_literal(literal, &ty);
+            }
+            if both {
+                break;
+            }
+        }
+    }
+
+    // 值的类型和上下文要求的类型相容；类型推断不出时不检查
+    fn check_value(&mut self, value: &Expr, expected: &Type) {
+        let Some(found) = self.type_of(value) else {
+            return;
+        };
+        if !self.compatible(expected, &found) {
+            self.report(
+                ErrorCode::E0308,
+                format!(
+                    "expected `{}`, found {}",
+                    expected,
+                    self.describe(value, &found)
+                ),
+                value.span(),
+                None,
+            );
+        }
+    }
+
+    // 块的值：最后一个没有分号的表达式
+    fn check_tail_value(&mut self, block: &Block, expected: &Type) {
+        if let Some(Statement::Expr(stmt)) = block.statements.last() {
+            if !stmt.semicolon {
+                self.check_value(&stmt.expr, expected);
+            }
+        }
+    }
+
+    // `if` 和 `while` 的条件
+    fn check_condition(&mut self, cond: &Expr) {
+        let Some(ty) = self.type_of(cond) else {
+            return;
+        };
+        if !self.compatible(&Type::Bool, &ty) {
+            self.report(
+                ErrorCode::E0308,
+                format!("expected `bool`, found {}", self.describe(cond, &ty)),
+                cond.span(),
+                Some("a condition must be a `bool`, compare the value instead".to_string()),
+            );
+        }
+    }
+
+    // 基础类型的操作数要支持运算，类型相同的运算两侧的类型要相容
+    fn check_operands(&mut self, op: &BinOp, left: &Expr, right: &Expr, span: Span) {
+        let (Some(left_ty), Some(right_ty)) = (self.type_of(left), self.type_of(right)) else {
+            return;
+        };
+        for (operand, ty) in [(left, &left_ty), (right, &right_ty)] {
+            if compat::is_scalar(ty) && !compat::supports(op, ty) {
+                self.report(
+                    ErrorCode::E0369,
+                    format!(
+                        "binary operation `{}` cannot be applied to {}",
+                        op,
+                        self.describe(operand, ty)
+                    ),
+                    span,
+                    None,
+                );
+                return;
+            }
+        }
+        if literals::same_operand_types(op)
+            && compat::is_scalar(&left_ty)
+            && compat::is_scalar(&right_ty)
+            && !self.compatible(&left_ty, &right_ty)
+        {
+            self.report(
+                ErrorCode::E0308,
+                format!(
+                    "expected {}, found {}",
+                    self.describe(left, &left_ty),
+                    self.describe(right, &right_ty)
+                ),
+                right.span(),
+                Some(format!("both operands of `{}` must have the same type", op)),
+            );
+        }
+    }
+
+    // 记录整数运算按哪个类型的宽度检查溢出、哪些浮点运算舍入到 f32，见 ast::OperandType。
+    // 类型由上下文决定的字面量不参与；整数类型之间可以混用，两个操作数的类型不同时取能容纳两者的
+    // 类型，移位只看左侧
+    fn record_operand_type(&self, op: &BinOp, left: &Expr, right: &Expr, record: &OperandType) {
+        let operands = match literals::same_operand_types(op) {
+            true => vec![left, right],
+            false => vec![left],
+        };
+        let mut widest: Option<Type> = None;
+        for operand in operands {
+            if compat::is_integer_literal(operand) {
+                continue;
+            }
+            let Some(ty) = self.type_of(operand).map(strip_references) else {
+                return;
+            };
+            if ty == Type::F32 {
+                return record.record(ty);
+            }
+            let Some((min, max)) = int_range(&ty) else {
+                return;
+            };
+            widest = match widest {
+                None => Some(ty),
+                Some(other) => {
+                    let (other_min, other_max) = int_range(&other).unwrap_or_default();
+                    if min >= other_min && max <= other_max {
+                        Some(other)
+                    } else if min <= other_min && max >= other_max {
+                        Some(ty)
+                    } else {
+                        Some(Type::I64)
+                    }
+                }
+            };
+        }
+        if let Some(ty) = widest {
+            record.record(ty);
+        }
+    }
+
+    // 取负和按位取反按操作数的整数类型检查溢出和截断
+    fn record_unary_type(&self, op: &UnOp, operand: &Expr, record: &OperandType) {
+        if matches!(op, UnOp::Neg | UnOp::BitwiseNot | UnOp::LogicalNot) {
+            self.record_int_type(operand, record);
+        }
+    }
+
+    // 记录操作数的整数类型；用于一元运算和 `abs` 的参数
+    fn record_int_type(&self, operand: &Expr, record: &OperandType) {
+        if compat::is_integer_literal(operand) {
+            return;
+        }
+        if let Some(ty) = self.type_of(operand).map(strip_references) {
+            if int_range(&ty).is_some() {
+                record.record(ty);
+            }
+        }
+    }
+
+    // 用户定义的函数的实参个数和类型；含有类型参数的形参由 check_inference 检查
+    fn check_arguments(&mut self, callee: &Expr, args: &[Expr], span: Span) {
+        let (name, sig) = match callee {
+            Expr::Ident(..) => match self.user_function(callee) {
+                Some((name, sig)) => (name.to_string(), sig.clone()),
+                None => return,
+            },
+            Expr::Path(segments, _) => {
+                let name = segments.join("::");
+                match self.functions.get(&name) {
+                    Some(sig) if !sig.builtin => (name, sig.clone()),
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+        if args.len() != sig.params.len() {
+            let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+            let params: Vec<String> = sig.params.iter().map(Type::to_string).collect();
+            self.report(
+                ErrorCode::E0061,
+                format!(
+                    "function `{}` takes {} {} but {} {} supplied",
+                    name,
+                    sig.params.len(),
+                    plural(sig.params.len()),
+                    args.len(),
+                    if args.len() == 1 { "was" } else { "were" }
+                ),
+                span,
+                Some(format!(
+                    "the function is declared as `fn {}({})`",
+                    name,
+                    params.join(", ")
+                )),
+            );
+            return;
+        }
+        let type_params = sig.type_params();
+        for (arg, param) in args.iter().zip(&sig.params) {
+            if !type_params.iter().any(|name| infer::mentions(param, name)) {
+                self.check_value(arg, param);
+            }
+        }
+    }
+
+    fn compatible(&self, expected: &Type, found: &Type) -> bool {
+        compat::compatible(expected, found, &|name| {
+            self.structs.contains_key(name) || self.enums.contains_key(name)
+        })
+    }
+
+    // 错误信息中的类型；没有后缀的整数字面量是 `integer`
+    fn describe(&self, expr: &Expr, ty: &Type) -> String {
+        match compat::is_integer_literal(expr) {
+            true => "integer".to_string(),
+            false => format!("`{}`", ty),
+        }
+    }
+
+    // 没有被局部变量遮蔽的常量或静态变量
+    fn global(&self, name: &str) -> Option<&Global> {
+        match self.lookup_variable(name) {
+            Some(_) => None,
+            None => self.globals.get(name),
+        }
+    }
+
+    // 数组、切片和 `Vec` 可以取切片；类型推断不出或是类型参数时不检查
+    fn check_slicing(&mut self, base: &Expr, span: Span) {
+        let Some(ty) = self.type_of(base).map(strip_references) else {
+            return;
+        };
+        if builtins::element_type(&ty).is_some() || !self.is_known_type(&ty) {
+            return;
+        }
+        let help = match ty {
+            Type::String => {
+                Some("use `substring(s, start, end)` to take part of a string".to_string())
+            }
+            _ => None,
+        };
+        self.report(
+            ErrorCode::E0608,
+            format!("cannot slice a value of type `{}`", ty),
+            span,
+            help,
+        );
+    }
+
+    // 只有引用和指针可以解引用；类型推断不出或是用户定义的类型时不检查
+    fn check_deref(&mut self, inner: &Expr, span: Span) {
+        let Some(ty) = self.type_of(inner) else {
+            return;
+        };
+        let derefable = matches!(
+            ty,
+            Type::Reference(..)
+                | Type::Pointer(..)
+                | Type::Named(_)
+                | Type::Generic(..)
+                | Type::Never
+                | Type::Infer
+        );
+        if !derefable {
+            self.report(
+                ErrorCode::E0614,
+                format!("type `{}` cannot be dereferenced", ty),
+                span,
+                None,
+            );
+        }
+    }
+
+    // 转换规则见 cast.rs；源类型推断不出或含有泛型参数时不检查
+    fn check_cast(&mut self, inner: &Expr, to: &Type, span: Span) {
+        let Some(from) = self.type_of(inner) else {
+            return;
+        };
+        let params = self.generic_names();
+        if from.mentions(&params) || to.mentions(&params) {
+            return;
+        }
+        // 整数字面量的类型由转换决定，`97 as char` 中的字面量是 u8
+        let from = match inner {
+            Expr::Literal(Literal::Int(n, None), _)
+                if *to == Type::Char && (0..=255).contains(n) =>
+            {
+                Type::U8
+            }
+            _ => from,
+        };
+        match cast::check(&from, to) {
+            Some(Ok(CastKind::Pointer)) if self.unsafe_depth == 0 => self.report(
+                ErrorCode::E0133,
+                format!("casting `{}` as `{}` requires an unsafe block", from, to),
+                span,
+                Some("pointer casts must be written inside `unsafe { ... }`".to_string()),
+            ),
+            Some(Err(error)) => {
+                let (code, message, help) = error.diagnostic(&from, to);
+                self.report(code, message, span, help);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_inline_asm(&mut self, asm: &InlineAsm, span: Span) {
+        if self.unsafe_depth == 0 {
+            self.report(
+                ErrorCode::E0133,
+                "use of inline assembly requires an unsafe block".to_string(),
+                span,
+                Some("inline assembly can do anything, so it must be written inside `unsafe { ... }`".to_string()),
+            );
+        }
+        let mut used = vec![false; asm.operands.len()];
+        let mut next = 0;
+        for line in &asm.template {
+            let refs = match asm::template_refs(line, &mut next) {
+                Ok(refs) => refs,
+                Err(message) => {
+                    self.report(ErrorCode::E0707, message, span, None);
+                    continue;
+                }
+            };
+            for index in refs {
+                match asm.operands.get(index) {
+                    Some(AsmOperand {
+                        reg: AsmReg::Explicit(name),
+                        span,
+                        ..
+                    }) => self.report(
+                        ErrorCode::E0707,
+                        format!("operand {} uses an explicit register and cannot be referred to in the template", index),
+                        *span,
+                        Some(format!("write the register `{}` in the template directly", name)),
+                    ),
+                    Some(_) => used[index] = true,
+                    None => self.report(
+                        ErrorCode::E0707,
+                        format!(
+                            "invalid reference to operand {}: there {} {} operand{}",
+                            index,
+                            if asm.operands.len() == 1 { "is" } else { "are" },
+                            asm.operands.len(),
+                            if asm.operands.len() == 1 { "" } else { "s" }
+                        ),
+                        span,
+                        None,
+                    ),
+                }
+            }
+        }
+        for (index, operand) in asm.operands.iter().enumerate() {
+            if let Some(expr) = &operand.expr {
+                self.check_expr(expr);
+                if operand.dir.writes() {
+                    self.check_place(expr);
+                }
+                if let Some(ty) = self.type_of(expr).filter(|ty| self.is_known_type(ty)) {
+                    if !asm::is_register_type(&ty) {
+                        self.report(
+                            ErrorCode::E0707,
+                            format!("cannot use a value of type `{}` as an assembly operand", ty),
+                            expr.span(),
+                            Some("operands must be integers, floats or raw pointers".to_string()),
+                        );
+                    }
+                }
+            }
+            match &operand.reg {
+                AsmReg::Class(class) if !asm::REGISTER_CLASSES.contains(&class.as_str()) => self
+                    .report(
+                        ErrorCode::E0707,
+                        format!("invalid register class `{}`", class),
+                        operand.span,
+                        Some(format!(
+                            "the register classes are {}",
+                            asm::REGISTER_CLASSES
+                                .map(|class| format!("`{}`", class))
+                                .join(", ")
+                        )),
+                    ),
+                AsmReg::Class(_) if !used[index] => self.report(
+                    ErrorCode::E0707,
+                    format!("operand {} is never used in the template", index),
+                    operand.span,
+                    Some(format!("refer to it as `{{{}}}` in the template", index)),
+                ),
+                _ => {}
+            }
+        }
+        for option in &asm.options {
+            if !asm::OPTIONS.contains(&option.as_str()) {
+                self.report(
+                    ErrorCode::E0707,
+                    format!("unknown inline assembly option `{}`", option),
+                    span,
+                    Some(format!(
+                        "the options are {}",
+                        asm::OPTIONS
+                            .map(|option| format!("`{}`", option))
+                            .join(", ")
+                    )),
+                );
+            }
+        }
+        let outputs = asm.operands.iter().any(|operand| operand.dir.writes());
+        if asm.noreturn() && outputs {
+            self.report(
+                ErrorCode::E0707,
+                "assembly with `options(noreturn)` cannot have outputs".to_string(),
+                span,
+                None,
+            );
+        }
+        if asm.options.iter().any(|option| option == "pure") && !outputs {
+            self.report(
+                ErrorCode::E0707,
+                "assembly with `options(pure)` must have at least one output".to_string(),
+                span,
+                Some("pure assembly without outputs does nothing and would be removed".to_string()),
+            );
+        }
+    }
+
+    // 值名称解析：局部变量 -> 函数 -> 常量/静态变量 -> 枚举变体
+    fn resolve_value(&mut self, name: &str, span: Span, kind: &str) {
+        if self.lookup_variable(name).is_some()
+            || self.functions.contains_key(name)
+            || self.globals.contains_key(name)
+            || self.variants.contains_key(name)
+            || self.poisoned.contains(name)
+        {
+            return;
+        }
+
+        let mut locals: Vec<&str> = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.variables.keys().map(String::as_str))
+            .collect();
+        locals.sort_unstable();
+
+        // 局部变量优先，其次是其他顶层名字
+        let help = if let Some(similar) = find_similar(name, locals.iter().copied()) {
+            Some(similar_help("a local variable", name, similar))
+        } else if let Some(similar) = find_similar(name, self.functions.keys().map(String::as_str))
+        {
+            Some(similar_help("a function", name, similar))
+        } else {
+            let others = self
+                .globals
+                .keys()
+                .chain(self.variants.keys())
+                .map(String::as_str);
+            find_similar(name, others).map(|similar| similar_help("an item", name, similar))
+        };
+
+        self.report(
+            ErrorCode::E0425,
+            format!("cannot find {} `{}` in this scope", kind, name),
+            span,
+            help,
+        );
+    }
+
+    // 内建函数的参数个数，以及能推断出类型的参数
+    // 没有被同名的变量或函数遮蔽的内建函数；方法调用只会被函数遮蔽
+    fn builtin(&self, name: &str, method: bool) -> Option<&'static builtins::Signature> {
+        if !method && self.lookup_variable(name).is_some() {
+            return None;
+        }
+        self.functions.get(name).filter(|sig| sig.builtin)?;
+        builtins::lookup(name)
+    }
+
+    // 方法调用 `a.m(b)` 调用以接收者为第一个实参的函数：名为 `m` 的函数，或者接收者的
+    // 结构体或枚举类型的 `<type>_m`（如 derive 生成的 `point_clone`）。
+    // 接收者的类型推断不出或是类型参数时不检查
+    fn resolve_method(&mut self, receiver: &Expr, method: &str, span: Span) -> Option<String> {
+        if self.functions.contains_key(method) {
+            return Some(method.to_string());
+        }
+        let ty = strip_references(self.type_of(receiver)?.expand_typeof());
+        let prefix = match &ty {
+            Type::Named(name) | Type::Generic(name, _)
+                if self.structs.contains_key(name) || self.enums.contains_key(name) =>
+            {
+                Some(format!("{}_", derive::snake_case(name)))
+            }
+            Type::Named(_) | Type::Infer | Type::Never => return None,
+            _ => None,
+        };
+        if let Some(prefix) = &prefix {
+            let function = format!("{}{}", prefix, method);
+            if self.functions.contains_key(&function) {
+                return Some(function);
+            }
+        }
+        let own = self.functions.keys().filter_map(|name| match &prefix {
+            Some(prefix) => name.strip_prefix(prefix.as_str()),
+            None => None,
+        });
+        // 内建函数只建议接受这个接收者的
+        let functions = self.functions.iter().filter(|(name, sig)| {
+            !sig.builtin
+                || builtins::lookup(name).is_some_and(|builtin| {
+                    let receiver = self.receiver_type(builtin, receiver);
+                    builtin
+                        .param(0)
+                        .zip(receiver)
+                        .is_some_and(|(param, ty)| param.accepts(&ty))
+                })
+        });
+        let candidates = own.chain(functions.map(|(name, _)| name.as_str()));
+        let help = find_similar(method, candidates)
+            .map(|similar| similar_help("a method", method, similar));
+        self.report(
+            ErrorCode::E0599,
+            format!("no method named `{}` found for type `{}`", method, ty),
+            span,
+            help,
+        );
+        None
+    }
+
+    // 按方法调用时接收者是第一个实参，以集合为第一个参数的内建函数自动取引用
+    fn receiver_type(&self, builtin: &builtins::Signature, receiver: &Expr) -> Option<Type> {
+        let ty = self.type_of(receiver)?;
+        Some(match builtin.receiver() {
+            Some(mutable) => Type::Reference(Box::new(ty), mutable),
+            None => ty,
+        })
+    }
+
+    // 显式的类型实参 `path::<T>` 只能用于布局内建函数，类型实参必须是具体类型。
+    // 可以计算布局时返回内建函数的名字
+    fn check_turbofish(&mut self, path: &Expr, types: &[Type], span: Span) -> Option<&'static str> {
+        let errors = self.errors.len();
+        for ty in types {
+            self.check_local_type(ty, span);
+        }
+        let name = match path {
+            Expr::Path(segments, _) => layout::intrinsic(&segments.join("::")),
+            // 没有被同名的变量或函数遮蔽
+            Expr::Ident(name, _)
+                if self.lookup_variable(name).is_none() && !self.functions.contains_key(name) =>
+            {
+                layout::intrinsic(name)
+            }
+            _ => None,
+        };
+        let Some(name) = name else {
+            let names: Vec<String> = layout::INTRINSICS
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect();
+            self.report(
+                ErrorCode::E0704,
+                "explicit type arguments are not supported here".to_string(),
+                span,
+                Some(format!(
+                    "type arguments can only be given to {}; the type arguments of other \
+                     generic functions are inferred from their arguments",
+                    names.join(" and ")
+                )),
+            );
+            return None;
+        };
+        if types.len() != 1 {
+            self.report(
+                ErrorCode::E0704,
+                format!(
+                    "`{}` takes 1 type argument but {} were supplied",
+                    name,
+                    types.len()
+                ),
+                span,
+                None,
+            );
+            return None;
+        }
+        // 类型不存在时已经报告过
+        if self.errors.len() > errors {
+            return None;
+        }
+        let ty = &types[0].expand_typeof();
+        let params = self.generic_names();
+        if ty.mentions(&params) {
+            self.report(
+                ErrorCode::E0704,
+                format!("cannot compute the layout of the generic type `{}`", ty),
+                span,
+                Some("layouts are only known for concrete types".to_string()),
+            );
+            return None;
+        }
+        let layouts = Layouts::new(self.structs.values(), self.enums.values());
+        match layouts.layout_of(ty) {
+            Ok(_) => {}
+            // 递归类型已经在定义处报告（E0072）
+            Err(LayoutError::Recursive(_)) => return None,
+            Err(error) => {
+                self.report(ErrorCode::E0704, error.to_string(), span, None);
+                return None;
+            }
+        }
+        Some(name)
+    }
+
+    fn check_intrinsic_args(&mut self, name: &str, args: &[Expr], span: Span) {
+        if !args.is_empty() {
+            self.report(
+                ErrorCode::E0061,
+                format!(
+                    "function `{}` takes 0 arguments but {} {} supplied",
+                    name,
+                    args.len(),
+                    if args.len() == 1 { "was" } else { "were" }
+                ),
+                span,
+                None,
+            );
+        }
+    }
+
+    // `#[repr(...)]`：`C` 和 `packed` 用于结构体，`C` 和整数类型用于枚举
+    fn check_repr(&mut self, attributes: &[Attribute], is_enum: bool) {
+        for attribute in attributes.iter().filter(|a| a.name == "repr") {
+            if attribute.args.is_empty() {
+                self.report(
+                    ErrorCode::E0552,
+                    "`#[repr]` needs a representation hint, like `#[repr(C)]`".to_string(),
+                    attribute.span,
+                    None,
+                );
+            }
+            for hint in &attribute.args {
+                let misplaced = match hint.as_str() {
+                    "C" => None,
+                    "packed" => is_enum.then_some("structs"),
+                    _ if layout::int_type(hint).is_some() => (!is_enum).then_some("enums"),
+                    _ => {
+                        let hints: Vec<String> = layout::REPR_HINTS
+                            .iter()
+                            .map(|hint| format!("`{}`", hint))
+                            .collect();
+                        self.report(
+                            ErrorCode::E0552,
+                            format!("unrecognized representation hint `{}`", hint),
+                            attribute.span,
+                            Some(format!("the representation hints are {}", hints.join(", "))),
+                        );
+                        continue;
+                    }
+                };
+                if let Some(kind) = misplaced {
+                    self.report(
+                        ErrorCode::E0517,
+                        format!("`#[repr({})]` can only be applied to {}", hint, kind),
+                        attribute.span,
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_builtin_call(
+        &mut self,
+        name: &str,
+        receiver: Option<&Expr>,
+        args: &[Expr],
+        span: Span,
+    ) {
+        let Some(builtin) = self.builtin(name, receiver.is_some()) else {
+            return;
+        };
+        let receiver = receiver.map(|receiver| (self.receiver_type(builtin, receiver), receiver));
+        let args = args.iter().map(|arg| (self.type_of(arg), arg));
+        let typed: Vec<(Option<Type>, &Expr)> = receiver.into_iter().chain(args).collect();
+        let first = typed.first().and_then(|(ty, _)| ty.as_ref());
+        let builtin = builtins::resolve(name, first).unwrap_or(builtin);
+
+        let count = typed.len();
+        if !builtin.accepts_count(count) {
+            self.report(
+                ErrorCode::E0061,
+                format!(
+                    "function `{}` takes {} but {} {} supplied",
+                    name,
+                    builtin.expected(),
+                    count,
+                    if count == 1 { "was" } else { "were" }
+                ),
+                span,
+                Some(format!("the builtin is called as `{}`", builtin.usage)),
+            );
+            return;
+        }
+        if builtin.name == "fmt::arg" {
+            self.check_format_spec(&typed);
+        }
+        for (index, (ty, arg)) in typed.into_iter().enumerate() {
+            let (Some(param), Some(ty)) = (builtin.param(index), ty) else {
+                continue;
+            };
+            if !param.accepts(&ty) {
+                self.report(
+                    ErrorCode::E0308,
+                    format!("`{}` expects {}, found `{}`", name, param.describe(), ty),
+                    arg.span(),
+                    None,
+                );
+            }
+        }
+    }
+
+    // `format!` 的占位符展开的 `fmt::arg(&value, "spec")`：检查格式说明和它要求的参数类型
+    fn check_format_spec(&mut self, typed: &[(Option<Type>, &Expr)]) {
+        let [(ty, value), (_, Expr::Literal(Literal::String(text), span))] = typed else {
+            return;
+        };
+        let spec = match text.parse::<fmt::Spec>() {
+            Ok(spec) => spec,
+            Err(message) => {
+                self.report(ErrorCode::E0115, message, *span, None);
+                return;
+            }
+        };
+        let Some(mut ty) = ty.clone() else {
+            return;
+        };
+        // 引用、盒子和 Rc 按其中的值格式化
+        ty = loop {
+            ty = match ty {
+                Type::Reference(inner, _) => *inner,
+                ty => match builtins::pointee_type(&ty) {
+                    Some(inner) => inner,
+                    None => break ty,
+                },
+            };
+        };
+        let requirement = spec.requirement();
+        if !requirement.accepts(&ty) {
+            self.report(
+                ErrorCode::E0308,
+                format!(
+                    "`{{:{}}}` expects {}, found `{}`",
+                    text,
+                    requirement.describe(),
+                    ty
+                ),
+                value.span(),
+                None,
+            );
+        }
+    }
+
+    // 路径解析：`module::item`、`module::Enum::Variant` 或 `Enum::Variant`
+    fn resolve_path(&mut self, segments: &[String], span: Span) {
+        let (first, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        if let Some(name) = layout::intrinsic(&segments.join("::")) {
+            self.report(
+                ErrorCode::E0704,
+                format!("`{}` needs a type argument", name),
+                span,
+                Some(format!("write `{}::<T>()`", name)),
+            );
+            return;
+        }
+
+        if let Some(namespace) = self.namespaces.get(first) {
+            let error = match rest {
+                [item] => namespace.check_item(item),
+                [enum_name, variant] => match namespace.enums.get(enum_name) {
+                    Some(variants) => {
+                        unknown_variant(enum_name, variant, variants.iter().map(String::as_str))
+                    }
+                    None => Some((
+                        ErrorCode::E0433,
+                        format!(
+                            "cannot find enum `{}` in module `{}`",
+                            enum_name, namespace.name
+                        ),
+                        None,
+                    )),
+                },
+                _ => Some((
+                    ErrorCode::E0433,
+                    format!("unsupported path `{}`", segments.join("::")),
+                    None,
+                )),
+            };
+            if let Some((code, message, help)) = error {
+                self.report(code, message, span, help);
+            }
+            return;
+        }
+
+        if let (Some(enum_def), [variant]) = (self.enums.get(first), rest) {
+            let variants: Vec<&str> = enum_def.variants.iter().map(|v| v.name.as_str()).collect();
+            if let Some((code, message, help)) = unknown_variant(first, variant, variants) {
+                self.report(code, message, span, help);
+            }
+            return;
+        }
+
+        if self.poisoned.contains(first) || self.builtin(&segments.join("::"), true).is_some() {
+            return;
+        }
+
+        let candidates: Vec<&str> = self
+            .namespaces
+            .keys()
+            .chain(self.enums.keys())
+            .map(String::as_str)
+            .collect();
+        let help = find_similar(first, candidates)
+            .map(|similar| similar_help("a module or enum", first, similar));
+        self.report(
+            ErrorCode::E0433,
+            format!(
+                "failed to resolve: use of undeclared module or enum `{}`",
+                first
+            ),
+            span,
+            help,
+        );
+    }
+
+    fn check_field(&mut self, struct_name: &str, field: &str, span: Span) {
+        let Some(struct_def) = self.structs.get(struct_name) else {
+            return;
+        };
+        if struct_def.fields.iter().any(|f| f.name == field) {
+            return;
+        }
+
+        let help = find_similar(field, struct_def.fields.iter().map(|f| f.name.as_str()))
+            .map(|similar| similar_help("a field", field, similar));
+
+        self.report(
+            ErrorCode::E0609,
+            format!("struct `{}` has no field named `{}`", struct_name, field),
+            span,
+            help,
+        );
+    }
+
+    // 元组下标不能超出元组的长度；结构体的字段有名字，不能用下标访问
+    fn check_tuple_index(&mut self, base: &Expr, index: usize, span: Span) {
+        let Some(ty) = self.type_of(base) else {
+            return;
+        };
+        if let Some(struct_name) = self.struct_name(&ty) {
+            self.check_field(&struct_name, &index.to_string(), span);
+            return;
+        }
+        let ty = strip_references(ty);
+        let help = match &ty {
+            Type::Tuple(types) if index >= types.len() => Some(match types.len() {
+                1 => "the tuple has 1 element".to_string(),
+                len => format!("the tuple has {} elements", len),
+            }),
+            Type::Tuple(_) => return,
+            ty if self.is_known_type(ty) => None,
+            _ => return,
+        };
+        self.report(
+            ErrorCode::E0609,
+            format!("no field `{}` on type `{}`", index, ty),
+            span,
+            help,
+        );
+    }
+
+    // 在当前作用域中引入模式绑定的名字
+    fn bind_pattern(&mut self, pattern: &Pattern, ty: Option<Type>, span: Span) {
+        match pattern {
+            Pattern::Ident(name) => {
+                // 与单元变体同名的模式是变体匹配，不是新绑定
+                if self.variants.contains_key(name) {
+                    return;
+                }
+                self.declare(name, ty);
+            }
+            Pattern::Struct(name, fields) => {
+                if self.structs.contains_key(name) {
+                    for (field, _) in fields {
+                        self.check_field(name, field, span);
+                    }
+                } else if !self.variants.contains_key(name) {
+                    self.check_type_name(name, span);
+                }
+                for (field, sub_pattern) in fields {
+                    let field_ty = self.field_type(name, field);
+                    self.bind_pattern(sub_pattern, field_ty, span);
+                }
+            }
+            Pattern::TupleStruct(name, patterns) => {
+                let fields = match self.variants.get(name) {
+                    Some(enum_name) => self.enums[enum_name]
+                        .variants
+                        .iter()
+                        .find(|variant| variant.name == *name)
+                        .and_then(|variant| variant.fields.clone()),
+                    None => {
+                        self.check_type_name(name, span);
+                        None
+                    }
+                };
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let field_ty = fields.as_ref().and_then(|fields| fields.get(i).cloned());
+                    self.bind_pattern(sub_pattern, field_ty, span);
+                }
+            }
+            Pattern::Tuple(patterns) => {
+                for (i, sub_pattern) in patterns.iter().enumerate() {
+                    let elem_ty = match &ty {
+                        Some(Type::Tuple(types)) => types.get(i).cloned(),
+                        _ => None,
+                    };
+                    self.bind_pattern(sub_pattern, elem_ty, span);
+                }
+            }
+            Pattern::Or(alternatives) => {
+                // 每个分支绑定相同的名字，取第一个分支即可
+                if let Some(first) = alternatives.first() {
+                    self.bind_pattern(first, ty, span);
+                }
+            }
+            Pattern::Literal(Literal::Int(n, suffix)) => {
+                let literal = Expr::Literal(Literal::Int(*n, suffix.clone()), span);
+                self.check_expr(&literal);
+                if let Some(ty) = &ty {
+                    self.check_int_literal(&literal, ty);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+        }
+    }
+
+    // 简单的类型推断，用于字段查找和 REPL 的 `:type`，推断不出时返回 None
+    // 枚举变体构造出的值的类型，类型参数由字段的值推断，推断不出时为 `_`
+    fn variant_type(&self, name: &str, args: &[Expr]) -> Option<Type> {
+        let enum_name = self.variants.get(name)?;
+        let def = self.enums.get(enum_name)?;
+        let Some(generics) = &def.generics else {
+            return Some(Type::Named(enum_name.clone()));
+        };
+        let variant = def.variants.iter().find(|variant| variant.name == name)?;
+        let fields = variant.fields.as_deref().unwrap_or_default();
+        let type_args = generics
+            .param_names()
+            .into_iter()
+            .map(|param| {
+                let param = Type::Named(param);
+                fields
+                    .iter()
+                    .zip(args)
+                    .find(|(field, _)| **field == param)
+                    .and_then(|(_, arg)| self.type_of(arg))
+                    .unwrap_or(Type::Infer)
+            })
+            .collect();
+        Some(Type::Generic(enum_name.clone(), type_args))
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<Type> {
+        if let Some(ty) = self.expr_types.get(&(expr as *const Expr as usize)) {
+            return ty.clone();
+        }
+        match expr {
+            Expr::Literal(Literal::Int(_, suffix), _) => Some(suffix.clone().unwrap_or(Type::I32)),
+            Expr::Literal(Literal::Float(_), _) => Some(Type::F64),
+            Expr::Literal(Literal::Bool(_), _) => Some(Type::Bool),
+            Expr::Literal(Literal::Char(_), _) => Some(Type::Char),
+            Expr::Literal(Literal::String(_), _) => Some(Type::String),
+            Expr::Ident(name, _) => match self.lookup_variable(name) {
+                Some(variable) => variable.ty.clone(),
+                None => match self.globals.get(name) {
+                    Some(global) => Some(global.ty.clone()),
+                    None => self.variant_type(name, &[]),
+                },
+            },
+            Expr::StructLit(name, fields, _) => self.struct_lit_type(name, fields),
+            Expr::FieldAccess(base, field, _) => {
+                let base = self.type_of(base)?;
+                let struct_name = self.struct_name(&base)?;
+                let ty = self.field_type(&struct_name, field)?;
+                let params = self.struct_params(&struct_name);
+                if params.is_empty() {
+                    return Some(ty);
+                }
+                // 泛型结构体的字段类型中代入结构体的类型实参
+                let args = match strip_references(base) {
+                    Type::Generic(_, args) => args,
+                    _ => Vec::new(),
+                };
+                let unknown = params[args.len().min(params.len())..]
+                    .iter()
+                    .any(|param| infer::mentions(&ty, param));
+                let types: BTreeMap<String, Type> = params.into_iter().zip(args).collect();
+                let ty = ty.substitute(&types);
+                (!unknown && !ty.has_infer()).then_some(ty)
+            }
+            Expr::TupleIndex(base, index, _) => match strip_references(self.type_of(base)?) {
+                Type::Tuple(types) => types.get(*index).cloned(),
+                _ => None,
+            },
+            Expr::IndexAccess(base, _, _) => {
+                let element = builtins::element_type(&self.type_of(base)?)?;
+                match is_slicing(expr) {
+                    true => Some(Type::Slice(Box::new(element))),
+                    false => Some(element),
+                }
+            }
+            Expr::Call(callee, args, _, _) => match callee.as_ref() {
+                Expr::Turbofish(..) => Some(Type::Usize),
+                Expr::Ident(name, _) if self.lookup_variable(name).is_none() => {
+                    match self.builtin(name, false) {
+                        Some(builtin) => {
+                            let first = args.first().and_then(|arg| self.type_of(arg));
+                            let builtin =
+                                builtins::resolve(name, first.as_ref()).unwrap_or(builtin);
+                            Some(builtin.return_type(first.as_ref()))
+                        }
+                        None => self
+                            .variant_type(name, args)
+                            .or_else(|| self.call_type(self.functions.get(name)?, args.iter())),
+                    }
+                }
+                // 闭包变量的调用
+                Expr::Ident(name, _) => match self.lookup_variable(name)?.ty.as_ref()? {
+                    Type::Function(_, ret, _) if !ret.has_infer() => Some(*ret.clone()),
+                    _ => None,
+                },
+                Expr::Path(segments, _) => {
+                    let builtin = self.builtin(&segments.join("::"), true)?;
+                    let first = args.first().and_then(|arg| self.type_of(arg));
+                    Some(builtin.return_type(first.as_ref()))
+                }
+                _ => None,
+            },
+            Expr::MethodCall(receiver, method, args, _, _) => match self.builtin(method, true) {
+                Some(builtin) => {
+                    let receiver = self.receiver_type(builtin, receiver);
+                    let builtin = builtins::resolve(method, receiver.as_ref()).unwrap_or(builtin);
+                    Some(builtin.return_type(receiver.as_ref()))
+                }
+                // 接收者是第一个实参
+                None => self.call_type(
+                    self.functions.get(method)?,
+                    std::iter::once(receiver.as_ref()).chain(args),
+                ),
+            },
+            Expr::Unary(UnOp::Deref, inner, _, _) | Expr::Deref(inner, _) => {
+                match self.type_of(inner)? {
+                    Type::Reference(inner, _) | Type::Pointer(inner, _) => Some(*inner),
+                    ty => builtins::pointee_type(&ty),
+                }
+            }
+            Expr::Unary(UnOp::Ref, inner, _, _) => {
+                Some(Type::Reference(Box::new(self.type_of(inner)?), false))
+            }
+            Expr::Unary(UnOp::RefMut, inner, _, _) => {
+                Some(Type::Reference(Box::new(self.type_of(inner)?), true))
+            }
+            Expr::Cast(_, ty, _) => Some(ty.expand_typeof()),
+            Expr::Try(inner, _) => TryKind::output(&self.type_of(inner)?),
+            Expr::Await(inner, _) => self.type_of(inner),
+            Expr::InlineAsm(asm, _) if asm.noreturn() => Some(Type::Never),
+            Expr::InlineAsm(..) => Some(Type::Unit),
+            Expr::Binary(op, left, right, _, _) => match op {
+                BinOp::Equal
+                | BinOp::NotEqual
+                | BinOp::Less
+                | BinOp::Greater
+                | BinOp::LessEqual
+                | BinOp::GreaterEqual
+                | BinOp::LogicalAnd
+                | BinOp::LogicalOr => Some(Type::Bool),
+                // 没有后缀的字面量取另一个操作数的类型；不支持运算的操作数已经报告过
+                _ if literals::is_unsuffixed(left) && literals::same_operand_types(op) => self
+                    .type_of(right)
+                    .filter(|ty| !compat::is_scalar(ty) || compat::supports(op, ty)),
+                _ => self
+                    .type_of(left)
+                    .filter(|ty| !compat::is_scalar(ty) || compat::supports(op, ty)),
+            },
+            Expr::Unary(UnOp::Neg | UnOp::LogicalNot | UnOp::BitwiseNot, inner, _, _) => {
+                self.type_of(inner)
+            }
+            Expr::ArrayLit(elements, _) => Some(Type::Array(
+                Box::new(self.type_of(elements.first()?)?),
+                elements.len(),
+            )),
+            Expr::TupleLit(elements, _) => elements
+                .iter()
+                .map(|element| self.type_of(element))
+                .collect::<Option<_>>()
+                .map(Type::Tuple),
+            Expr::Return(..) | Expr::Break(..) | Expr::Continue(..) => Some(Type::Never),
+            Expr::Block(block, _) | Expr::Unsafe(block, _) => self.block_type(block),
+            Expr::If(_, then_block, else_branch, _) => {
+                self.branches_type(then_block, else_branch.as_ref())
+            }
+            Expr::Match(_, arms, _) => self.arms_type(arms),
+            Expr::Closure(.., span) => self.closure_types.get(span).cloned(),
+            _ => None,
+        }
+    }
+
+    fn struct_params(&self, name: &str) -> Vec<String> {
+        self.structs
+            .get(name)
+            .and_then(|def| def.generics.as_ref())
+            .map(Generics::param_names)
+            .unwrap_or_default()
+    }
+
+    // 泛型结构体字面量的类型实参由字段的值推断，推断不出的为 `_`
+    fn struct_lit_type(&self, name: &str, fields: &[(String, Expr)]) -> Option<Type> {
+        let params = self.struct_params(name);
+        if params.is_empty() {
+            return Some(Type::Named(name.to_string()));
+        }
+        let (types, values): (Vec<Type>, Vec<&Expr>) = fields
+            .iter()
+            .filter_map(|(field, value)| Some((self.field_type(name, field)?, value)))
+            .unzip();
+        let inference = self.infer_args(&params, &types, values.into_iter(), None);
+        let args = params
+            .iter()
+            .map(|param| inference.resolve(&params, &Type::Named(param.clone())))
+            .collect();
+        Some(Type::Generic(name.to_string(), args))
+    }
+
+    // 块的值的类型：末尾表达式的类型，发散的块为 `!`
+    fn block_type(&self, block: &Block) -> Option<Type> {
+        match block.statements.last() {
+            Some(Statement::Expr(stmt)) if !stmt.semicolon => self.type_of(&stmt.expr),
+            _ if Divergence::new(&self.functions).block(block) => Some(Type::Never),
+            Some(Statement::If(stmt)) => {
+                self.branches_type(&stmt.then_block, stmt.else_block.as_ref())
+            }
+            Some(Statement::Match(stmt)) => self.arms_type(&stmt.arms),
+            Some(Statement::Block(block)) => self.block_type(block),
+            _ => Some(Type::Unit),
+        }
+    }
+
+    fn branches_type(&self, then_block: &Block, else_branch: Option<&ElseBranch>) -> Option<Type> {
+        let otherwise = match else_branch {
+            Some(ElseBranch::Block(block)) => self.block_type(block),
+            Some(ElseBranch::If(nested)) => {
+                self.branches_type(&nested.then_block, nested.else_block.as_ref())
+            }
+            None => Some(Type::Unit),
+        };
+        join([self.block_type(then_block), otherwise])
+    }
+
+    fn arms_type(&self, arms: &[MatchArm]) -> Option<Type> {
+        join(arms.iter().map(|arm| self.type_of(&arm.body)))
+    }
+
+    // 取出类型对应的结构体名，引用、指针和盒子自动解引用
+    fn struct_name(&self, ty: &Type) -> Option<String> {
+        match ty {
+            Type::Named(name) | Type::Generic(name, _) if self.structs.contains_key(name) => {
+                Some(name.clone())
+            }
+            Type::Reference(inner, _) | Type::Pointer(inner, _) => self.struct_name(inner),
+            ty => self.struct_name(&builtins::pointee_type(ty)?),
+        }
+    }
+
+    fn field_type(&self, struct_name: &str, field: &str) -> Option<Type> {
+        self.structs
+            .get(struct_name)?
+            .fields
+            .iter()
+            .find(|f| f.name == field)
+            .map(|f| f.ty.clone())
+    }
+
+    // 作用域管理
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // 泛型参数的约束必须是已知的 trait
+    fn check_bounds(&mut self, generics: &Option<Generics>) {
+        for param in generics.iter().flat_map(|g| &g.params) {
+            for bound in &param.bounds {
+                if traits::TRAITS.contains(&bound.as_str()) {
+                    continue;
+                }
+                let help = match find_similar(bound, traits::TRAITS.iter().copied()) {
+                    Some(similar) => similar_help("a trait", bound, similar),
+                    None => format!(
+                        "the bounds of a type parameter can be {}",
+                        traits::TRAITS
+                            .iter()
+                            .map(|name| format!("`{}`", name))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                };
+                self.report(
+                    ErrorCode::E0405,
+                    format!("cannot find trait `{}` in this scope", bound),
+                    param.span,
+                    Some(help),
+                );
+            }
+        }
+    }
+
+    fn generic_names(&self) -> Vec<String> {
+        self.generics
+            .iter()
+            .flatten()
+            .map(|param| param.name.clone())
+            .collect()
+    }
+
+    fn traits(&self) -> Traits<'_> {
+        Traits {
+            generics: &self.generics,
+            structs: &self.structs,
+            enums: &self.enums,
+            functions: &self.functions,
+        }
+    }
+
+    fn push_generics(&mut self, generics: &Option<Generics>) {
+        let params = generics
+            .iter()
+            .flat_map(|g| g.params.iter().cloned())
+            .collect();
+        self.generics.push(params);
+    }
+
+    fn declare(&mut self, name: &str, ty: Option<Type>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.variables.insert(name.to_string(), Variable { ty });
+        }
+    }
+
+    // 变量声明在最内层的闭包之外
+    fn is_captured(&self, name: &str) -> bool {
+        let Some(&depth) = self.closures.last() else {
+            return false;
+        };
+        self.scopes
+            .iter()
+            .rposition(|scope| scope.variables.contains_key(name))
+            .is_some_and(|index| index < depth)
+    }
+
+    fn lookup_variable(&self, name: &str) -> Option<&Variable> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name))
+    }
+
+    // 宏调用在解析模块时展开（见 macros.rs），直接交给语义分析的语法树里还可能留着
+    fn unexpanded_macro(&mut self, call: &MacroCall) {
+        self.report(
+            ErrorCode::E0105,
+            format!(
+                "macro `{}!` must be expanded before semantic analysis",
+                call.name
+            ),
+            call.span,
+            Some("parse the program with `module::parse_source`, which expands macros".to_string()),
+        );
+    }
+
+    fn report(&mut self, code: ErrorCode, message: String, span: Span, help: Option<String>) {
+        let mut diagnostic = Diagnostic::error(message, span).with_code(code);
+        if let Some(help) = help {
+            diagnostic = diagnostic.with_help(help);
+        }
+        self.errors.push(diagnostic);
+    }
+}
+
+// 分支的值的类型：`!` 可以转换为任何类型，所以取第一个不是 `!` 的分支的类型；
+// 有分支推断不出类型时为 None，每个分支都发散时为 `!`
+fn join(types: impl IntoIterator<Item = Option<Type>>) -> Option<Type> {
+    let mut joined = Type::Never;
+    for ty in types {
+        match ty? {
+            Type::Never => {}
+            ty if joined == Type::Never => joined = ty,
+            _ => {}
+        }
+    }
+    Some(joined)
+}
+
+// 以区间为下标取切片：`a[1..3]`
+fn is_slicing(expr: &Expr) -> bool {
+    matches!(expr, Expr::IndexAccess(_, index, _) if matches!(**index, Expr::Range(..)))
+}
+
+// 通过引用、指针和盒子访问时自动解引用
+fn strip_references(ty: Type) -> Type {
+    match ty {
+        Type::Reference(inner, _) | Type::Pointer(inner, _) => strip_references(*inner),
+        ty => match builtins::pointee_type(&ty) {
+            Some(inner) => strip_references(inner),
+            None => ty,
+        },
+    }
+}
+
+// 模式必须不可反驳的位置
+#[derive(Clone, Copy)]
+enum Binding {
+    Let,
+    For,
+    Argument,
+}
+
+impl Binding {
+    fn describe(self) -> &'static str {
+        match self {
+            Binding::Let => "local binding",
+            Binding::For => "`for` loop binding",
+            Binding::Argument => "function argument",
+        }
+    }
+}
+
+// 指向环上声明最早的条目对下一个条目的引用，并依次说明环上的每条边
+fn initialization_cycle(cycle: &[Use]) -> Diagnostic {
+    let message = format!("cycle detected when initializing `{}`", cycle[0].from);
+    let mut diagnostic = Diagnostic::error(message, cycle[0].span).with_code(ErrorCode::E0391);
+    for (i, edge) in cycle.iter().enumerate() {
+        let target = match edge.to == edge.from {
+            true => "itself".to_string(),
+            false => format!("`{}`", edge.to),
+        };
+        let end = match i + 1 == cycle.len() {
+            true => ", completing the cycle",
+            false => "",
+        };
+        diagnostic = diagnostic.with_note(format!(
+            "`{}` uses {} at line {}, column {}{}",
+            edge.from, target, edge.span.line, edge.span.column, end
+        ));
+    }
+    diagnostic.with_help(
+        "break the cycle by giving one of these items a value that does not depend on the others"
+            .to_string(),
+    )
+}
+
+// 依次说明递归路径上的每一步，建议在第一步加入间接。
+// 预导入或其他模块中的定义不在这个文件里，不给出位置
+fn recursive_type(name: &str, span: Span, path: &[Containment], items: &[Item]) -> Diagnostic {
+    let message = format!("recursive type `{}` has infinite size", name);
+    let mut diagnostic = Diagnostic::error(message, span).with_code(ErrorCode::E0072);
+    for step in path {
+        let owner = step.owner.split('<').next().unwrap_or_default();
+        let local = items.iter().any(|item| {
+            matches!(item, Item::Struct(_) | Item::Enum(_)) && item_name(item) == Some(owner)
+        });
+        let location = match local {
+            true => format!(" at line {}, column {}", step.span.line, step.span.column),
+            false => String::new(),
+        };
+        diagnostic = diagnostic.with_note(format!(
+            "`{}` contains `{}` through {}{}",
+            step.owner, step.ty, step.member, location
+        ));
+    }
+    let first = &path[0];
+    diagnostic.with_help(format!(
+        "insert some indirection to break the cycle, e.g. change `{ty}` in {} to `Box<{ty}>`, `&{ty}` or `Vec<{ty}>`",
+        first.member,
+        ty = first.ty
+    ))
+}
+
+// 指向产生副作用的表达式，并沿调用链说明每个被调函数为什么不纯
+fn impure_condition(contract: &Contract, effect: &Effect) -> Diagnostic {
+    let keyword = contract.kind.keyword();
+    let message = match effect {
+        Effect::Call { callee, .. } => {
+            format!("`{}` condition calls impure function `{}`", keyword, callee)
+        }
+        _ => format!(
+            "`{}` condition must be side-effect free, but it {}",
+            keyword,
+            effect.describe()
+        ),
+    };
+    let diagnostic = Diagnostic::error(message, effect.span()).with_code(ErrorCode::E0701);
+    effect_notes(diagnostic, effect).with_help(
+        "contract conditions are skipped under `--contracts=off`; move side effects into the function body"
+            .to_string(),
+    )
+}
+
+// 沿调用链说明被调函数为什么是非纯的
+fn effect_notes(mut diagnostic: Diagnostic, effect: &Effect) -> Diagnostic {
+    let mut current = effect;
+    while let Effect::Call { callee, cause, .. } = current {
+        let span = cause.span();
+        diagnostic = diagnostic.with_note(format!(
+            "`{}` is impure because it {} at line {}, column {}",
+            callee,
+            cause.describe(),
+            span.line,
+            span.column
+        ));
+        current = cause;
+    }
+    diagnostic
+}
+
+fn unknown_variant<'a, I>(
+    enum_name: &str,
+    variant: &str,
+    variants: I,
+) -> Option<(ErrorCode, String, Option<String>)>
+where
+    I: IntoIterator<Item = &'a str> + Clone,
+{
+    if variants.clone().into_iter().any(|v| v == variant) {
+        return None;
+    }
+    let help =
+        find_similar(variant, variants).map(|similar| similar_help("a variant", variant, similar));
+    Some((
+        ErrorCode::E0599,
+        format!("no variant named `{}` in enum `{}`", variant, enum_name),
+        help,
+    ))
+}
+
+fn similar_help(what: &str, name: &str, similar: &str) -> String {
+    format!(
+        "{} with a similar name exists: `{}` → `{}`",
+        what, name, similar
+    )
+}
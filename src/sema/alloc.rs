@@ -0,0 +1,129 @@
+// 分配分析：标注了 `#[no_alloc]` 的函数不能在堆上分配
+// 分配需要表达式的类型（字符串的 `+`），所以在类型检查函数体时记录，而不是像 effects.rs 那样
+// 单独遍历语法树。函数体中直接的分配有：
+// - 签名中标记为 `allocates` 的内建函数，如 `Vec::new`、`push`、`to_string`，按函数或方法调用
+// - 字符串的 `+` 和 `+=`，结果是新的字符串
+// - 创建闭包，闭包捕获的环境在堆上
+// - 调用函数值，不知道它会做什么
+// 调用用户定义的函数时，被调函数分配则调用者也分配，由 `solve` 沿调用关系求不动点；
+// 不在本模块中检查的函数（导入的函数）除非标注了 `#[no_alloc]`，都视为分配
+
+use crate::span::Span;
+use std::collections::BTreeMap;
+
+/// 函数中的一次分配
+#[derive(Debug, Clone, PartialEq)]
+pub enum Allocation {
+    Builtin {
+        name: String,
+        span: Span,
+    },
+    Concat {
+        span: Span,
+    },
+    Closure {
+        span: Span,
+    },
+    // 调用分配的函数，cause 是被调函数自己的分配，导入的函数没有 cause
+    Call {
+        callee: String,
+        span: Span,
+        cause: Option<Box<Allocation>>,
+    },
+    CallValue {
+        span: Span,
+    },
+}
+
+impl Allocation {
+    pub fn span(&self) -> Span {
+        match self {
+            Allocation::Builtin { span, .. }
+            | Allocation::Concat { span }
+            | Allocation::Closure { span }
+            | Allocation::Call { span, .. }
+            | Allocation::CallValue { span } => *span,
+        }
+    }
+
+    /// 用于诊断信息的描述，以动词开头
+    pub fn describe(&self) -> String {
+        match self {
+            Allocation::Builtin { name, .. } => format!("calls the builtin `{}`", name),
+            Allocation::Concat { .. } => "concatenates strings".to_string(),
+            Allocation::Closure { .. } => "creates a closure".to_string(),
+            Allocation::Call { callee, .. } => format!("calls `{}`", callee),
+            Allocation::CallValue { .. } => "calls a function value".to_string(),
+        }
+    }
+}
+
+// 一个函数中记录的分配和调用
+#[derive(Debug, Clone, Default)]
+struct Record {
+    direct: Option<Allocation>,       // 第一个直接的分配
+    calls: Vec<(String, Span, bool)>, // 被调函数、调用位置、被调函数是否标注了 `#[no_alloc]`
+}
+
+/// 检查过的函数中的分配
+#[derive(Debug, Clone, Default)]
+pub struct Allocations {
+    functions: BTreeMap<String, Record>,
+}
+
+impl Allocations {
+    /// 开始记录函数 `name` 的函数体
+    pub fn enter(&mut self, name: &str) {
+        self.functions.insert(name.to_string(), Record::default());
+    }
+
+    /// 函数中直接的分配，只保留第一个
+    pub fn allocate(&mut self, function: &str, allocation: Allocation) {
+        if let Some(record) = self.functions.get_mut(function) {
+            record.direct.get_or_insert(allocation);
+        }
+    }
+
+    /// 函数调用用户定义的函数
+    pub fn call(&mut self, function: &str, callee: &str, span: Span, no_alloc: bool) {
+        if let Some(record) = self.functions.get_mut(function) {
+            record.calls.push((callee.to_string(), span, no_alloc));
+        }
+    }
+
+    /// 每个分配的函数的第一个分配：直接的分配优先，其次是第一个分配的被调函数
+    pub fn solve(&self) -> BTreeMap<String, Allocation> {
+        let mut allocating: BTreeMap<String, Allocation> = self
+            .functions
+            .iter()
+            .filter_map(|(name, record)| Some((name.clone(), record.direct.clone()?)))
+            .collect();
+        loop {
+            let mut changed = false;
+            for (name, record) in &self.functions {
+                if allocating.contains_key(name) {
+                    continue;
+                }
+                let found = record.calls.iter().find_map(|(callee, span, no_alloc)| {
+                    let cause = match self.functions.contains_key(callee) {
+                        true => Some(Box::new(allocating.get(callee)?.clone())),
+                        false if *no_alloc => return None,
+                        false => None,
+                    };
+                    Some(Allocation::Call {
+                        callee: callee.clone(),
+                        span: *span,
+                        cause,
+                    })
+                });
+                if let Some(allocation) = found {
+                    allocating.insert(name.clone(), allocation);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return allocating;
+            }
+        }
+    }
+}
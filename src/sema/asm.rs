@@ -0,0 +1,71 @@
+// 内联汇编的检查
+// `asm!` 只能写在 unsafe 块中。模板中的 `{}` 按顺序引用下一个操作数，`{n}` 引用第 n 个操作数，
+// `{0:e}` 这样冒号之后的修饰符原样交给后端；`{{` 和 `}}` 是花括号本身。
+// 寄存器类由后端分配具体的寄存器，写成字符串的具体寄存器（`"eax"`）不能在模板中引用；
+// 其他每个操作数都必须在模板中用到。操作数只能是整数、浮点数和裸指针，
+// 输出操作数和赋值的左侧一样必须是可以写入的位置
+
+use crate::ast::Type;
+
+/// 后端认识的寄存器类
+pub const REGISTER_CLASSES: [&str; 6] = ["reg", "reg_byte", "reg_abcd", "freg", "vreg", "xmm_reg"];
+
+/// `options(...)` 中可以写的选项
+pub const OPTIONS: [&str; 8] = [
+    "pure",
+    "nomem",
+    "readonly",
+    "preserves_flags",
+    "noreturn",
+    "nostack",
+    "att_syntax",
+    "raw",
+];
+
+/// 类型的值能否放进寄存器
+pub fn is_register_type(ty: &Type) -> bool {
+    super::literals::int_range(ty).is_some()
+        || matches!(ty, Type::F32 | Type::F64 | Type::Pointer(_, _))
+}
+
+/// 一行模板引用的操作数序号，`next` 是下一个 `{}` 引用的序号
+pub fn template_refs(line: &str, next: &mut usize) -> Result<Vec<usize>, String> {
+    let mut refs = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '}' => return Err("unmatched `}` in the template".to_string()),
+            '{' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => return Err("unmatched `{` in the template".to_string()),
+                    }
+                }
+                let index = inner.split(':').next().unwrap_or_default().trim();
+                if index.is_empty() {
+                    refs.push(*next);
+                    *next += 1;
+                } else {
+                    let index = index.parse::<usize>().map_err(|_| {
+                        format!(
+                            "invalid reference `{{{}}}` in the template: operands are referred to by position",
+                            inner
+                        )
+                    })?;
+                    refs.push(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(refs)
+}
@@ -0,0 +1,141 @@
+// 类型转换 `expr as T` 的规则
+// - 数值（整数和浮点数）之间可以任意转换：整数之间按目标类型截断，变宽时有符号数做符号扩展、
+//   无符号数做零扩展；浮点数转整数向零取整并饱和到目标类型的范围，NaN 为 0；
+//   整数转浮点数和 f64 转 f32 取最接近的值
+// - `bool` 和 `char` 可以转为整数（`char` 取码点，再按目标类型截断）；只有 `u8` 可以转为 `char`
+// - 任何类型都不能转为 `bool`，`bool`/`char` 和浮点数之间也不能转换
+// - 指针：`&mut T` 可以转为 `*mut T` 和 `*const T`，`&T` 只能转为 `*const T`，
+//   裸指针之间可以任意转换。指针转换只能写在 `unsafe` 块中。
+//   指针没有数值地址，不能和整数互相转换
+// - 同一类型之间的转换总是允许的；结构体、枚举、字符串、元组等其他类型不能转换
+// 源类型不能完全确定时不检查，不支持的转换在运行时报告
+
+use crate::ast::Type;
+use crate::diagnostic::ErrorCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastKind {
+    Trivial, // 相同类型
+    Numeric,
+    Pointer, // 需要 unsafe
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastError {
+    ToBool,
+    ToChar,
+    Invalid,      // 基础类型之间不允许的转换
+    NonPrimitive, // 不是基础类型
+}
+
+impl CastError {
+    /// 错误码、信息和帮助
+    pub fn diagnostic(self, from: &Type, to: &Type) -> (ErrorCode, String, Option<String>) {
+        match self {
+            CastError::ToBool => (
+                ErrorCode::E0054,
+                format!("cannot cast `{}` as `bool`", from),
+                Some("compare with zero instead, like `x != 0`".to_string()),
+            ),
+            CastError::ToChar => (
+                ErrorCode::E0604,
+                format!("only `u8` can be cast as `char`, not `{}`", from),
+                Some("cast to `u8` first, like `x as u8 as char`".to_string())
+                    .filter(|_| is_integer(from)),
+            ),
+            CastError::Invalid => {
+                let help = match (from, to) {
+                    (Type::Reference(_, false), Type::Pointer(_, true)) => {
+                        "only a mutable reference can be cast to a `*mut` pointer"
+                    }
+                    (Type::Reference(_, _), Type::Pointer(_, _)) => {
+                        "a reference can only be cast to a pointer to the same type"
+                    }
+                    (Type::Pointer(..) | Type::Reference(..), _)
+                    | (_, Type::Pointer(..) | Type::Reference(..)) => {
+                        "pointers have no numeric address"
+                    }
+                    _ => "cast through an integer type first",
+                };
+                (
+                    ErrorCode::E0606,
+                    format!("casting `{}` as `{}` is invalid", from, to),
+                    Some(help.to_string()),
+                )
+            }
+            CastError::NonPrimitive => (
+                ErrorCode::E0605,
+                format!("non-primitive cast: `{}` as `{}`", from, to),
+                Some("only numbers, `bool`, `char` and pointers can be cast with `as`".to_string()),
+            ),
+        }
+    }
+}
+
+/// `from as to` 是否允许；类型中有未知部分时为 None
+pub fn check(from: &Type, to: &Type) -> Option<Result<CastKind, CastError>> {
+    if !is_known(from) {
+        return None;
+    }
+    let numeric = |ty: &Type| is_integer(ty) || is_float(ty);
+    let scalar = |ty: &Type| numeric(ty) || matches!(ty, Type::Bool | Type::Char);
+    Some(match (from, to) {
+        _ if from == to || *from == Type::Never => Ok(CastKind::Trivial),
+        (_, Type::Bool) if scalar(from) => Err(CastError::ToBool),
+        (Type::U8, Type::Char) => Ok(CastKind::Numeric),
+        (_, Type::Char) if scalar(from) => Err(CastError::ToChar),
+        (Type::Bool | Type::Char, _) if is_integer(to) => Ok(CastKind::Numeric),
+        _ if numeric(from) && numeric(to) => Ok(CastKind::Numeric),
+        (Type::Reference(a, from_mut), Type::Pointer(b, to_mut))
+            if a == b && (*from_mut || !*to_mut) =>
+        {
+            Ok(CastKind::Pointer)
+        }
+        (Type::Pointer(_, _), Type::Pointer(_, _)) => Ok(CastKind::Pointer),
+        _ if is_primitive(from) && is_primitive(to) => Err(CastError::Invalid),
+        _ => Err(CastError::NonPrimitive),
+    })
+}
+
+fn is_integer(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::Isize
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+    )
+}
+
+fn is_float(ty: &Type) -> bool {
+    matches!(ty, Type::F32 | Type::F64)
+}
+
+fn is_primitive(ty: &Type) -> bool {
+    is_integer(ty)
+        || is_float(ty)
+        || matches!(
+            ty,
+            Type::Bool | Type::Char | Type::Pointer(_, _) | Type::Reference(_, _)
+        )
+}
+
+// 类型中没有 `_`
+fn is_known(ty: &Type) -> bool {
+    match ty {
+        Type::Infer => false,
+        Type::Array(inner, _)
+        | Type::Slice(inner)
+        | Type::Pointer(inner, _)
+        | Type::Reference(inner, _) => is_known(inner),
+        Type::Tuple(types) | Type::Generic(_, types) => types.iter().all(is_known),
+        Type::Function(types, ret, _) => types.iter().all(is_known) && is_known(ret),
+        _ => true,
+    }
+}
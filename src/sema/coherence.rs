@@ -0,0 +1,108 @@
+// 一致性（coherence）检查
+// 语言还没有 impl 块，类型对 trait 的实现是名为 `<类型的 snake_case 形式>_<trait>` 的函数，
+// 由 derive 生成或者手写（见 derive.rs 和 traits.rs）。一个这样的函数名只能有一个定义，
+// 否则检查 trait 约束和按方法调用时不能确定用哪一个：
+// - 同一个类型的两个实现：两个 `fn point_eq`，或者两个 `#[derive(Eq)]` 属性
+// - 重叠的实现：不同类型的 snake_case 形式相同（`HTTPServer` 和 `HttpServer` 都是 `http_server`），
+//   它们的实现是同名的函数
+// 冲突报告在后出现的实现上，并给出先出现的实现的位置。
+// 实现函数只按名字对应 trait，这里不检查它们的签名和契约：trait 没有方法声明，
+// 实现的 `requires`/`ensures` 无从与 trait 的契约比较
+
+use crate::ast::*;
+use crate::derive;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::span::Span;
+use std::collections::BTreeMap;
+
+// 实现 trait 的函数
+struct Impl<'a> {
+    trait_name: &'a str,
+    ty: &'a str, // 实现 trait 的类型
+    span: Span,
+}
+
+/// 程序中互相冲突的 trait 实现
+pub fn conflicts(items: &[Item]) -> Vec<Diagnostic> {
+    let types: Vec<&str> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(def) => Some(def.name.as_str()),
+            Item::Enum(def) => Some(def.name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut impls: BTreeMap<&str, Vec<Impl>> = BTreeMap::new();
+    for item in items {
+        let Item::Function(func) = item else {
+            continue;
+        };
+        for trait_name in derive::TRAITS {
+            let mut candidates = types
+                .iter()
+                .filter(|ty| derive::function_name(ty, trait_name) == func.name);
+            let Some(&first) = candidates.next() else {
+                continue;
+            };
+            // 名字重叠时按第一个参数的类型区分
+            let ty = match func.params.first().map(|param| self_type(&param.ty)) {
+                Some(Some(ty)) if types.contains(&ty) => ty,
+                _ => first,
+            };
+            impls.entry(&func.name).or_default().push(Impl {
+                trait_name,
+                ty,
+                span: func.span,
+            });
+        }
+    }
+
+    let mut errors = Vec::new();
+    for (function, impls) in &impls {
+        let first = &impls[0];
+        for other in &impls[1..] {
+            errors.push(conflict(function, first, other));
+        }
+    }
+    errors.sort_by_key(|error| error.span.start);
+    errors
+}
+
+// 参数类型中的结构体或枚举名，经过引用
+fn self_type(ty: &Type) -> Option<&str> {
+    match ty {
+        Type::Named(name) | Type::Generic(name, _) => Some(name),
+        Type::Reference(inner, _) => self_type(inner),
+        _ => None,
+    }
+}
+
+fn conflict(function: &str, first: &Impl, other: &Impl) -> Diagnostic {
+    let (message, help) = match first.ty == other.ty {
+        true => (
+            format!(
+                "conflicting implementations of trait `{}` for type `{}`",
+                other.trait_name, other.ty
+            ),
+            "remove one of the implementations".to_string(),
+        ),
+        false => (
+            format!(
+                "conflicting implementations of trait `{}` for types `{}` and `{}`",
+                other.trait_name, first.ty, other.ty
+            ),
+            format!(
+                "both implementations are named `{}`; rename one of the types",
+                function
+            ),
+        ),
+    };
+    Diagnostic::error(message, other.span)
+        .with_code(ErrorCode::E0119)
+        .with_note(format!(
+            "the first implementation is at line {}, column {}",
+            first.span.line, first.span.column
+        ))
+        .with_help(help)
+}
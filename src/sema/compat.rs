@@ -0,0 +1,106 @@
+// 类型相容性
+// let 的类型标注、用户定义函数的实参、返回值、赋值、结构体字段、条件和二元运算的操作数
+// 要和上下文要求的类型相容（E0308），基础类型的操作数要支持二元运算（E0369）。
+// 类型推断是尽力而为的，只在两个类型都确定时比较：`_`、`!`、`typeof`、不认识的类型名
+// （包括类型参数）可以匹配任何类型。整数之间、浮点数之间不比较宽度，没有后缀的字面量
+// 和由它初始化的变量的宽度只是猜测，字面量的宽度和范围由 literals.rs 检查。
+// `&mut T` 可以当作 `&T`，数组和 `Vec` 的引用可以当作切片的引用
+
+use super::literals;
+use crate::ast::*;
+
+/// `found` 类型的值能否用在要求 `expected` 的位置；`known` 判断类型名是不是已知的结构体或枚举
+pub fn compatible(expected: &Type, found: &Type, known: &dyn Fn(&str) -> bool) -> bool {
+    let all = |expected: &[Type], found: &[Type]| {
+        expected.len() == found.len()
+            && expected
+                .iter()
+                .zip(found)
+                .all(|(expected, found)| compatible(expected, found, known))
+    };
+    match (expected, found) {
+        (Type::Infer | Type::Never | Type::TypeOf(_), _)
+        | (_, Type::Infer | Type::Never | Type::TypeOf(_)) => true,
+        (Type::Named(name), _) | (_, Type::Named(name)) if !known(name) => true,
+        _ if is_integer(expected) => is_integer(found),
+        _ if is_float(expected) => is_float(found),
+        (Type::Array(expected, n), Type::Array(found, m)) => {
+            n == m && compatible(expected, found, known)
+        }
+        (Type::Slice(expected), Type::Slice(found)) => compatible(expected, found, known),
+        (Type::Tuple(expected), Type::Tuple(found)) => all(expected, found),
+        (Type::Reference(expected, mutable), Type::Reference(found, found_mutable))
+        | (Type::Pointer(expected, mutable), Type::Pointer(found, found_mutable)) => {
+            (!mutable || *found_mutable) && pointee_compatible(expected, found, known)
+        }
+        (Type::Generic(expected, args), Type::Generic(found, found_args)) => {
+            expected == found && all(args, found_args)
+        }
+        (Type::Generic(expected, _), Type::Named(found))
+        | (Type::Named(expected), Type::Generic(found, _)) => expected == found,
+        (Type::Function(params, ret, _), Type::Function(found_params, found_ret, _)) => {
+            all(params, found_params) && compatible(ret, found_ret, known)
+        }
+        _ => expected == found,
+    }
+}
+
+// 引用和指针指向的类型；切片的引用可以指向数组或 `Vec`
+fn pointee_compatible(expected: &Type, found: &Type, known: &dyn Fn(&str) -> bool) -> bool {
+    match (expected, found) {
+        (Type::Slice(element), Type::Array(found, _)) => compatible(element, found, known),
+        (Type::Slice(element), Type::Generic(name, args)) if name == "Vec" && args.len() == 1 => {
+            compatible(element, &args[0], known)
+        }
+        _ => compatible(expected, found, known),
+    }
+}
+
+fn is_integer(ty: &Type) -> bool {
+    literals::int_range(ty).is_some()
+}
+
+fn is_float(ty: &Type) -> bool {
+    matches!(ty, Type::F32 | Type::F64)
+}
+
+/// 二元运算检查操作数的基础类型：整数、浮点数、`bool`、`char` 和 `string`
+pub fn is_scalar(ty: &Type) -> bool {
+    is_integer(ty) || is_float(ty) || matches!(ty, Type::Bool | Type::Char | Type::String)
+}
+
+/// 基础类型的操作数是否支持运算 `op`
+pub fn supports(op: &BinOp, ty: &Type) -> bool {
+    match op {
+        BinOp::Add => is_integer(ty) || is_float(ty) || *ty == Type::String,
+        BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => is_integer(ty) || is_float(ty),
+        BinOp::BitwiseAnd | BinOp::BitwiseOr | BinOp::BitwiseXor => {
+            is_integer(ty) || *ty == Type::Bool
+        }
+        BinOp::LeftShift | BinOp::RightShift => is_integer(ty),
+        BinOp::LogicalAnd | BinOp::LogicalOr => *ty == Type::Bool,
+        _ => true, // 比较
+    }
+}
+
+/// 只由没有后缀的整数字面量组成的算术表达式，它的类型是上下文要求的整数类型
+pub fn is_integer_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary(
+            BinOp::Add
+            | BinOp::Sub
+            | BinOp::Mul
+            | BinOp::Div
+            | BinOp::Mod
+            | BinOp::BitwiseAnd
+            | BinOp::BitwiseOr
+            | BinOp::BitwiseXor,
+            left,
+            right,
+            _,
+            _,
+        ) => is_integer_literal(left) && is_integer_literal(right),
+        Expr::Unary(UnOp::Neg | UnOp::BitwiseNot, inner, _, _) => is_integer_literal(inner),
+        expr => literals::is_unsuffixed(expr),
+    }
+}
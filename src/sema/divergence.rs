@@ -0,0 +1,282 @@
+// 发散分析
+// 发散的表达式（类型为 `!`）一定不会正常求值结束：`return`、`break`、`continue`，
+// 调用返回 `!` 的函数（如 `panic`），条件为 `true` 且循环体中没有 `break` 跳出它的 `while`，
+// 每个分支都发散的 if 和 match，以及求值时要先求值一个发散的操作数的表达式。
+// 块中的任何一条语句发散，块就发散。
+// 返回值不是 `()` 的函数体必须在每条路径上给出值：以值结束（if 和 match 的每个分支都有值），或者发散
+
+use super::FnSig;
+use crate::ast::*;
+use std::collections::BTreeMap;
+
+pub struct Divergence<'a> {
+    functions: &'a BTreeMap<String, FnSig>,
+}
+
+impl<'a> Divergence<'a> {
+    pub fn new(functions: &'a BTreeMap<String, FnSig>) -> Self {
+        Self { functions }
+    }
+
+    /// 块的执行一定不会走到结尾
+    pub fn block(&self, block: &Block) -> bool {
+        block.statements.iter().any(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&self, stmt: &Statement) -> bool {
+        match stmt {
+            Statement::Return(_) | Statement::Break(_) | Statement::Continue(_) => true,
+            Statement::Let(stmt) => stmt.init.as_ref().is_some_and(|init| self.expr(init)),
+            Statement::Expr(stmt) => self.expr(&stmt.expr),
+            Statement::If(stmt) => {
+                self.expr(&stmt.cond) || self.branches(&stmt.then_block, stmt.else_block.as_ref())
+            }
+            Statement::While(stmt) => {
+                self.expr(&stmt.cond) || infinite(stmt.label.as_ref(), &stmt.cond, &stmt.body)
+            }
+            Statement::For(stmt) => self.expr(&stmt.iterable),
+            Statement::Match(stmt) => self.expr(&stmt.expr) || self.arms(&stmt.arms),
+            Statement::Block(block) => self.block(block),
+        }
+    }
+
+    /// 表达式一定不会正常求值结束，类型为 `!`
+    pub fn expr(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Return(..) | Expr::Break(..) | Expr::Continue(..) => true,
+            Expr::Block(block, _) | Expr::Unsafe(block, _) => self.block(block),
+            Expr::If(cond, then_block, else_branch, _) => {
+                self.expr(cond) || self.branches(then_block, else_branch.as_ref())
+            }
+            Expr::Match(scrutinee, arms, _) => self.expr(scrutinee) || self.arms(arms),
+            Expr::While(label, cond, body, _) => {
+                self.expr(cond) || infinite(label.as_ref(), cond, body)
+            }
+            Expr::For(_, _, iterable, _, _) => self.expr(iterable),
+            Expr::Call(callee, args, _, _) => {
+                self.exprs(std::iter::once(&**callee).chain(args)) || self.never_returns(callee)
+            }
+            Expr::MethodCall(receiver, _, args, _, _) => {
+                self.exprs(std::iter::once(&**receiver).chain(args))
+            }
+            // `&&` 和 `||` 的右侧不一定求值
+            Expr::Binary(BinOp::LogicalAnd | BinOp::LogicalOr, left, _, _, _) => self.expr(left),
+            Expr::Binary(_, left, right, _, _)
+            | Expr::IndexAccess(left, right, _)
+            | Expr::Assign(left, right, _)
+            | Expr::CompoundAssign(_, left, right, _, _) => self.expr(left) || self.expr(right),
+            Expr::Range(start, end, _, _) => start.iter().chain(end).any(|e| self.expr(e)),
+            Expr::Unary(_, inner, _, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _) => self.expr(inner),
+            Expr::StructLit(_, fields, _) => self.exprs(fields.iter().map(|(_, value)| value)),
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => self.exprs(elements),
+            // `options(noreturn)` 的汇编不会回到后面的代码
+            Expr::InlineAsm(asm, _) => asm.noreturn() || self.exprs(asm.exprs()),
+            _ => false,
+        }
+    }
+
+    fn exprs<'e>(&self, exprs: impl IntoIterator<Item = &'e Expr>) -> bool {
+        exprs.into_iter().any(|expr| self.expr(expr))
+    }
+
+    // 调用的是返回 `!` 的函数
+    fn never_returns(&self, callee: &Expr) -> bool {
+        let name = match callee {
+            Expr::Ident(name, _) => name.clone(),
+            Expr::Path(segments, _) => segments.join("::"),
+            _ => return false,
+        };
+        self.functions
+            .get(&name)
+            .is_some_and(|sig| sig.ret == Some(Type::Never))
+    }
+
+    fn branches(&self, then_block: &Block, else_branch: Option<&ElseBranch>) -> bool {
+        self.block(then_block)
+            && match else_branch {
+                Some(ElseBranch::Block(block)) => self.block(block),
+                Some(ElseBranch::If(nested)) => {
+                    self.expr(&nested.cond)
+                        || self.branches(&nested.then_block, nested.else_block.as_ref())
+                }
+                None => false,
+            }
+    }
+
+    fn arms(&self, arms: &[MatchArm]) -> bool {
+        !arms.is_empty() && arms.iter().all(|arm| self.expr(&arm.body))
+    }
+
+    /// 块在每条路径上都以值结束或者发散
+    pub fn has_value(&self, block: &Block) -> bool {
+        if self.block(block) {
+            return true;
+        }
+        match block.statements.last() {
+            Some(Statement::Expr(stmt)) if !stmt.semicolon => self.expr_has_value(&stmt.expr),
+            Some(Statement::If(stmt)) => {
+                self.branches_have_value(&stmt.then_block, stmt.else_block.as_ref())
+            }
+            Some(Statement::Match(stmt)) => self.arms_have_value(&stmt.arms),
+            Some(Statement::Block(block)) => self.has_value(block),
+            _ => false,
+        }
+    }
+
+    fn expr_has_value(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Block(block, _) | Expr::Unsafe(block, _) => self.has_value(block),
+            Expr::If(_, then_block, else_branch, _) => {
+                self.branches_have_value(then_block, else_branch.as_ref())
+            }
+            Expr::Match(_, arms, _) => self.arms_have_value(arms),
+            // 循环和赋值的值是 `()`
+            Expr::While(..) | Expr::For(..) | Expr::Assign(..) | Expr::CompoundAssign(..) => {
+                self.expr(expr)
+            }
+            _ => true,
+        }
+    }
+
+    fn branches_have_value(&self, then_block: &Block, else_branch: Option<&ElseBranch>) -> bool {
+        self.has_value(then_block)
+            && match else_branch {
+                Some(ElseBranch::Block(block)) => self.has_value(block),
+                Some(ElseBranch::If(nested)) => {
+                    self.branches_have_value(&nested.then_block, nested.else_block.as_ref())
+                }
+                None => false,
+            }
+    }
+
+    fn arms_have_value(&self, arms: &[MatchArm]) -> bool {
+        arms.iter().all(|arm| self.expr_has_value(&arm.body))
+    }
+}
+
+// `while true` 的循环体中没有跳出它的 `break`
+fn infinite(label: Option<&String>, cond: &Expr, body: &Block) -> bool {
+    matches!(cond, Expr::Literal(Literal::Bool(true), _)) && !Breaks { label, depth: 0 }.block(body)
+}
+
+// 查找跳出某个循环的 `break`：不带标签的 `break` 只跳出最内层的循环，闭包中的 `break` 跳不出闭包
+struct Breaks<'l> {
+    label: Option<&'l String>,
+    depth: usize, // 循环体中嵌套的循环层数
+}
+
+impl Breaks<'_> {
+    fn block(&mut self, block: &Block) -> bool {
+        block.statements.iter().any(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> bool {
+        match stmt {
+            Statement::Let(stmt) => {
+                self.exprs(stmt.init.iter())
+                    || stmt.else_block.as_ref().is_some_and(|b| self.block(b))
+            }
+            Statement::Expr(stmt) => self.expr(&stmt.expr),
+            Statement::Return(stmt) => self.exprs(stmt.expr.iter()),
+            Statement::If(stmt) => self.if_stmt(stmt),
+            Statement::While(stmt) => self.expr(&stmt.cond) || self.nested(&stmt.body),
+            Statement::For(stmt) => self.expr(&stmt.iterable) || self.nested(&stmt.body),
+            Statement::Match(stmt) => self.expr(&stmt.expr) || self.arms(&stmt.arms),
+            Statement::Break(stmt) => {
+                self.targets(stmt.label.as_ref()) || self.exprs(stmt.expr.iter())
+            }
+            Statement::Continue(_) => false,
+            Statement::Block(block) => self.block(block),
+        }
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmt) -> bool {
+        self.expr(&stmt.cond)
+            || self.block(&stmt.then_block)
+            || self.else_branch(stmt.else_block.as_ref())
+    }
+
+    fn else_branch(&mut self, else_branch: Option<&ElseBranch>) -> bool {
+        match else_branch {
+            Some(ElseBranch::Block(block)) => self.block(block),
+            Some(ElseBranch::If(nested)) => self.if_stmt(nested),
+            None => false,
+        }
+    }
+
+    fn arms(&mut self, arms: &[MatchArm]) -> bool {
+        arms.iter()
+            .any(|arm| self.exprs(arm.guard.iter().chain([&arm.body])))
+    }
+
+    // 嵌套的循环
+    fn nested(&mut self, body: &Block) -> bool {
+        self.depth += 1;
+        let found = self.block(body);
+        self.depth -= 1;
+        found
+    }
+
+    fn targets(&self, label: Option<&String>) -> bool {
+        match label {
+            Some(label) => self.label == Some(label),
+            None => self.depth == 0,
+        }
+    }
+
+    fn exprs<'e>(&mut self, exprs: impl IntoIterator<Item = &'e Expr>) -> bool {
+        exprs.into_iter().any(|expr| self.expr(expr))
+    }
+
+    fn expr(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Break(label, value, _) => {
+                self.targets(label.as_ref()) || self.exprs(value.iter().map(|value| &**value))
+            }
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.block(block)
+            }
+            Expr::If(cond, then_block, else_branch, _) => {
+                self.expr(cond) || self.block(then_block) || self.else_branch(else_branch.as_ref())
+            }
+            Expr::While(_, cond, body, _) => self.expr(cond) || self.nested(body),
+            Expr::For(_, _, iterable, body, _) => self.expr(iterable) || self.nested(body),
+            Expr::Match(scrutinee, arms, _) => self.expr(scrutinee) || self.arms(arms),
+            Expr::Binary(_, left, right, _, _)
+            | Expr::IndexAccess(left, right, _)
+            | Expr::Assign(left, right, _)
+            | Expr::CompoundAssign(_, left, right, _, _) => self.expr(left) || self.expr(right),
+            Expr::Range(start, end, _, _) => start.iter().chain(end).any(|e| self.expr(e)),
+            Expr::Unary(_, inner, _, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _) => self.expr(inner),
+            Expr::Call(callee, args, _, _) | Expr::MethodCall(callee, _, args, _, _) => {
+                self.exprs(std::iter::once(&**callee).chain(args))
+            }
+            Expr::StructLit(_, fields, _) => self.exprs(fields.iter().map(|(_, value)| value)),
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => self.exprs(elements),
+            Expr::InlineAsm(asm, _) => self.exprs(asm.exprs()),
+            Expr::Return(value, _) => self.exprs(value.iter().map(|value| &**value)),
+            // 闭包体是新的上下文
+            Expr::Closure(..)
+            | Expr::Literal(..)
+            | Expr::Ident(..)
+            | Expr::Path(..)
+            | Expr::Turbofish(..)
+            | Expr::Continue(..)
+            | Expr::MacroCall(_) => false,
+        }
+    }
+}
@@ -0,0 +1,599 @@
+// 副作用分析
+// 契约条件在 `--contracts=off` 时不会求值，因此条件中不能有副作用。
+// 函数分为纯函数和非纯函数，以下情况是副作用：
+// 1. 调用有副作用的内建函数（`print`，见 builtins.rs）
+// 2. 写静态变量，或写条件/函数之外的变量
+// 3. 通过引用参数（或从它复制的引用）写入，包括 `push`、`append` 等修改参数的内建函数
+// 4. 调用非纯函数，或调用闭包、函数参数等函数值（无法确定其效果，保守地视为非纯）；
+//    类型是 `pure fn(T) -> U` 的参数和变量只能保存纯函数（调用处检查），调用它们不是副作用
+// 5. 没有 `options(pure)` 的内联汇编，以及写入汇编的输出操作数
+// 只修改自己的局部变量不算副作用。非纯性沿调用图传播，迭代到不动点；
+// 多模块时导入的函数解析到定义它的模块
+
+use crate::ast::*;
+use crate::builtins;
+use crate::module::{Crate, ExportTarget, ModulePath};
+use crate::span::Span;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 函数或表达式的第一个副作用
+#[derive(Debug, Clone)]
+pub enum Effect {
+    Builtin {
+        name: String,
+        span: Span,
+    },
+    WriteStatic {
+        name: String,
+        span: Span,
+    },
+    Write {
+        name: String,
+        span: Span,
+    },
+    WriteThroughReference {
+        span: Span,
+    },
+    // 调用非纯函数，cause 是被调函数自己的副作用
+    Call {
+        callee: String,
+        span: Span,
+        cause: Box<Effect>,
+    },
+    CallValue {
+        span: Span,
+    },
+    InlineAsm {
+        span: Span,
+    },
+}
+
+impl Effect {
+    pub fn span(&self) -> Span {
+        match self {
+            Effect::Builtin { span, .. }
+            | Effect::WriteStatic { span, .. }
+            | Effect::Write { span, .. }
+            | Effect::WriteThroughReference { span }
+            | Effect::Call { span, .. }
+            | Effect::CallValue { span }
+            | Effect::InlineAsm { span } => *span,
+        }
+    }
+
+    /// 用于诊断信息的描述，以动词开头
+    pub fn describe(&self) -> String {
+        match self {
+            Effect::Builtin { name, .. } => format!("calls the builtin `{}`", name),
+            Effect::WriteStatic { name, .. } => format!("writes to static `{}`", name),
+            Effect::Write { name, .. } => format!("assigns to `{}`", name),
+            Effect::WriteThroughReference { .. } => "writes through a reference".to_string(),
+            Effect::Call { callee, .. } => format!("calls `{}`", callee),
+            Effect::CallValue { .. } => {
+                "calls a function value whose effects are unknown".to_string()
+            }
+            Effect::InlineAsm { .. } => "executes inline assembly".to_string(),
+        }
+    }
+}
+
+// 函数的定义位置：所在模块和函数名
+type Target = (ModulePath, String);
+
+/// 一个模块中可见函数的纯/非纯分类
+#[derive(Debug, Clone, Default)]
+pub struct Effects {
+    functions: BTreeSet<String>, // 可见的函数名，包括导入的 `f` 和 `module::f`
+    statics: BTreeSet<String>,
+    impure: BTreeMap<String, Effect>,
+}
+
+impl Effects {
+    /// 分析单个程序中的函数
+    pub fn analyze(program: &Program) -> Self {
+        let root = ModulePath::new();
+        let aliases = own_functions(&root, program);
+        solve(vec![(root.clone(), program, aliases)])
+            .remove(&root)
+            .unwrap_or_default()
+    }
+
+    /// 分析整个 crate，返回每个模块的结果
+    pub fn analyze_crate(krate: &Crate) -> BTreeMap<ModulePath, Effects> {
+        let modules = krate
+            .modules
+            .values()
+            .map(|module| {
+                let mut aliases = own_functions(&module.path, &module.program);
+                for import in &module.imports {
+                    let functions = krate
+                        .public_items(&import.module)
+                        .into_iter()
+                        .filter(|(_, target)| is_function(krate, target));
+                    for (name, target) in functions {
+                        let alias = match &import.item {
+                            Some(item) if *item == name => import.name.clone(),
+                            Some(_) => continue,
+                            None => format!("{}::{}", import.name, name),
+                        };
+                        aliases.insert(alias, (target.module, target.item));
+                    }
+                }
+                (module.path.clone(), &module.program, aliases)
+            })
+            .collect();
+        solve(modules)
+    }
+
+    pub fn is_pure(&self, function: &str) -> bool {
+        !self.impure.contains_key(function)
+    }
+
+    /// 非纯函数的第一个副作用
+    pub fn effect_of(&self, function: &str) -> Option<&Effect> {
+        self.impure.get(function)
+    }
+
+    /// 条件表达式的第一个副作用；`bindings` 是条件外部可见的变量（参数、`result`、`self`），
+    /// 写它们是副作用，调用它们视为调用函数值，`pure_values` 中的除外
+    pub fn condition_effect(
+        &self,
+        condition: &Expr,
+        bindings: &BTreeSet<String>,
+        pure_values: &BTreeSet<String>,
+    ) -> Option<Effect> {
+        let mut walker = Walker::new(self, bindings);
+        walker.pure_values = pure_values.clone();
+        walker.expr(condition)
+    }
+
+    /// 闭包体的第一个副作用；闭包的参数是局部变量，其余同 `condition_effect`
+    pub fn closure_effect(
+        &self,
+        params: &[Parameter],
+        body: &Expr,
+        bindings: &BTreeSet<String>,
+        pure_values: &BTreeSet<String>,
+    ) -> Option<Effect> {
+        let mut walker = Walker::new(self, bindings);
+        walker.pure_values = pure_values.clone();
+        for param in params {
+            walker.bind_param(param);
+        }
+        walker.expr(body)
+    }
+}
+
+fn is_function(krate: &Crate, target: &ExportTarget) -> bool {
+    let item = krate
+        .module(&target.module)
+        .and_then(|module| module.find_item(&target.item));
+    matches!(item, Some(Item::Function(_)))
+}
+
+fn own_functions(path: &ModulePath, program: &Program) -> BTreeMap<String, Target> {
+    functions(program)
+        .map(|func| (func.name.clone(), (path.clone(), func.name.clone())))
+        .collect()
+}
+
+fn functions(program: &Program) -> impl Iterator<Item = &Function> {
+    program.items.iter().filter_map(|item| match item {
+        Item::Function(func) => Some(func),
+        _ => None,
+    })
+}
+
+// 反复分析尚未确定为非纯的函数，直到没有新的非纯函数
+fn solve(
+    modules: Vec<(ModulePath, &Program, BTreeMap<String, Target>)>,
+) -> BTreeMap<ModulePath, Effects> {
+    let mut impure: BTreeMap<Target, Effect> = BTreeMap::new();
+    let no_bindings = BTreeSet::new();
+    loop {
+        let mut changed = false;
+        for (path, program, aliases) in &modules {
+            let effects = visible(program, aliases, &impure);
+            for func in functions(program) {
+                let target = (path.clone(), func.name.clone());
+                if impure.contains_key(&target) {
+                    continue;
+                }
+                if let Some(effect) = Walker::new(&effects, &no_bindings).function(func) {
+                    impure.insert(target, effect);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    modules
+        .iter()
+        .map(|(path, program, aliases)| (path.clone(), visible(program, aliases, &impure)))
+        .collect()
+}
+
+fn visible(
+    program: &Program,
+    aliases: &BTreeMap<String, Target>,
+    impure: &BTreeMap<Target, Effect>,
+) -> Effects {
+    Effects {
+        functions: aliases.keys().cloned().collect(),
+        statics: program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Static(static_def) => Some(static_def.name.clone()),
+                _ => None,
+            })
+            .collect(),
+        impure: aliases
+            .iter()
+            .filter_map(|(alias, target)| Some((alias.clone(), impure.get(target)?.clone())))
+            .collect(),
+    }
+}
+
+// 按求值顺序查找第一个副作用
+struct Walker<'a> {
+    effects: &'a Effects,
+    outer: &'a BTreeSet<String>,
+    locals: BTreeSet<String>,
+    references: BTreeSet<String>, // 指向外部的引用，通过它们写入是副作用
+    pure_values: BTreeSet<String>, // 类型是 `pure fn` 的函数值
+}
+
+impl<'a> Walker<'a> {
+    fn new(effects: &'a Effects, outer: &'a BTreeSet<String>) -> Self {
+        Self {
+            effects,
+            outer,
+            locals: BTreeSet::new(),
+            references: BTreeSet::new(),
+            pure_values: BTreeSet::new(),
+        }
+    }
+
+    fn function(&mut self, func: &Function) -> Option<Effect> {
+        for param in &func.params {
+            self.bind_param(param);
+        }
+        self.block(&func.body)
+    }
+
+    fn bind_param(&mut self, param: &Parameter) {
+        let reference = matches!(param.ty, Type::Reference(..) | Type::Pointer(..));
+        self.bind(&param.pattern, reference);
+        self.bind_pure(&param.pattern, Some(&param.ty));
+    }
+
+    // 标注为 `pure fn` 的变量
+    fn bind_pure(&mut self, pattern: &Pattern, ty: Option<&Type>) {
+        if let (Pattern::Ident(name), Some(Type::Function(_, _, true))) = (pattern, ty) {
+            self.pure_values.insert(name.clone());
+        }
+    }
+
+    fn bind(&mut self, pattern: &Pattern, reference: bool) {
+        match pattern {
+            Pattern::Ident(name) => {
+                self.locals.insert(name.clone());
+                self.pure_values.remove(name);
+                if reference {
+                    self.references.insert(name.clone());
+                } else {
+                    self.references.remove(name);
+                }
+            }
+            Pattern::Struct(_, fields) => {
+                for (_, pattern) in fields {
+                    self.bind(pattern, reference);
+                }
+            }
+            Pattern::TupleStruct(_, patterns)
+            | Pattern::Tuple(patterns)
+            | Pattern::Or(patterns) => {
+                for pattern in patterns {
+                    self.bind(pattern, reference);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+        }
+    }
+
+    // 块中的绑定在块结束后失效
+    fn scoped(&mut self, f: impl FnOnce(&mut Self) -> Option<Effect>) -> Option<Effect> {
+        let locals = self.locals.clone();
+        let references = self.references.clone();
+        let pure_values = self.pure_values.clone();
+        let effect = f(self);
+        self.locals = locals;
+        self.references = references;
+        self.pure_values = pure_values;
+        effect
+    }
+
+    fn block(&mut self, block: &Block) -> Option<Effect> {
+        self.scoped(|walker| {
+            block
+                .statements
+                .iter()
+                .find_map(|stmt| walker.statement(stmt))
+        })
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Option<Effect> {
+        match stmt {
+            Statement::Let(let_stmt) => {
+                let effect = let_stmt
+                    .init
+                    .as_ref()
+                    .and_then(|init| self.expr(init))
+                    .or_else(|| let_stmt.else_block.as_ref().and_then(|b| self.block(b)));
+                let reference =
+                    matches!(let_stmt.ty, Some(Type::Reference(..) | Type::Pointer(..)))
+                        || let_stmt
+                            .init
+                            .as_ref()
+                            .is_some_and(|init| self.is_outer_reference(init));
+                self.bind(&let_stmt.pattern, reference);
+                self.bind_pure(&let_stmt.pattern, let_stmt.ty.as_ref());
+                effect
+            }
+            Statement::Expr(expr_stmt) => self.expr(&expr_stmt.expr),
+            Statement::Return(ret) => self.optional(ret.expr.as_ref()),
+            Statement::If(if_stmt) => self.if_else(
+                &if_stmt.cond,
+                &if_stmt.then_block,
+                if_stmt.else_block.as_ref(),
+            ),
+            Statement::While(while_stmt) => self
+                .expr(&while_stmt.cond)
+                .or_else(|| self.block(&while_stmt.body)),
+            Statement::For(for_stmt) => {
+                self.for_loop(&for_stmt.pattern, &for_stmt.iterable, &for_stmt.body)
+            }
+            Statement::Match(match_stmt) => self.match_arms(&match_stmt.expr, &match_stmt.arms),
+            Statement::Break(break_stmt) => self.optional(break_stmt.expr.as_ref()),
+            Statement::Continue(_) => None,
+            Statement::Block(block) => self.block(block),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Option<Effect> {
+        match expr {
+            Expr::Literal(..)
+            | Expr::Ident(..)
+            | Expr::Path(..)
+            | Expr::Continue(..)
+            | Expr::MacroCall(..) => None,
+            Expr::Binary(_, left, right, _, _) | Expr::IndexAccess(left, right, _) => {
+                self.expr(left).or_else(|| self.expr(right))
+            }
+            Expr::Range(start, end, _, _) => start.iter().chain(end).find_map(|e| self.expr(e)),
+            Expr::Unary(_, inner, _, _)
+            | Expr::Turbofish(inner, _, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _) => self.expr(inner),
+            Expr::Call(callee, args, span, _) => self
+                .list(args)
+                .or_else(|| self.call(callee, *span))
+                .or_else(|| match (callee.as_ref(), args.first()) {
+                    (Expr::Ident(name, _), Some(target))
+                        if !self.is_value(name) && self.mutates(name) =>
+                    {
+                        self.mutate(target, *span)
+                    }
+                    _ => None,
+                }),
+            Expr::MethodCall(receiver, method, args, span, _) => self
+                .expr(receiver)
+                .or_else(|| self.list(args))
+                .or_else(|| self.call_function(method, *span))
+                .or_else(|| {
+                    self.mutates(method)
+                        .then(|| self.mutate(receiver, *span))
+                        .flatten()
+                }),
+            Expr::StructLit(_, fields, _) => fields.iter().find_map(|(_, value)| self.expr(value)),
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => self.list(elements),
+            Expr::InlineAsm(asm, span) => asm
+                .exprs()
+                .find_map(|value| self.expr(value))
+                .or_else(|| {
+                    let pure = asm.options.iter().any(|option| option == "pure");
+                    (!pure).then_some(Effect::InlineAsm { span: *span })
+                })
+                .or_else(|| {
+                    asm.operands
+                        .iter()
+                        .filter(|operand| operand.dir.writes())
+                        .filter_map(|operand| operand.expr.as_ref())
+                        .find_map(|target| self.write(target, *span))
+                }),
+            Expr::Assign(lhs, rhs, span) | Expr::CompoundAssign(_, lhs, rhs, span, _) => self
+                .expr(rhs)
+                .or_else(|| self.expr(lhs))
+                .or_else(|| self.write(lhs, *span)),
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.block(block)
+            }
+            Expr::If(cond, then_block, else_block, _) => {
+                self.if_else(cond, then_block, else_block.as_ref())
+            }
+            Expr::Match(scrutinee, arms, _) => self.match_arms(scrutinee, arms),
+            Expr::While(_, cond, body, _) => self.expr(cond).or_else(|| self.block(body)),
+            Expr::For(_, pattern, iterable, body, _) => self.for_loop(pattern, iterable, body),
+            Expr::Break(_, value, _) | Expr::Return(value, _) => self.optional(value.as_deref()),
+            // 创建闭包没有副作用，调用时按函数值处理
+            Expr::Closure(..) => None,
+        }
+    }
+
+    fn optional(&mut self, expr: Option<&Expr>) -> Option<Effect> {
+        expr.and_then(|expr| self.expr(expr))
+    }
+
+    fn list(&mut self, exprs: &[Expr]) -> Option<Effect> {
+        exprs.iter().find_map(|expr| self.expr(expr))
+    }
+
+    fn if_else(
+        &mut self,
+        cond: &Expr,
+        then_block: &Block,
+        else_block: Option<&ElseBranch>,
+    ) -> Option<Effect> {
+        self.expr(cond)
+            .or_else(|| self.block(then_block))
+            .or_else(|| match else_block? {
+                ElseBranch::Block(block) => self.block(block),
+                ElseBranch::If(nested) => {
+                    self.if_else(&nested.cond, &nested.then_block, nested.else_block.as_ref())
+                }
+            })
+    }
+
+    fn for_loop(&mut self, pattern: &Pattern, iterable: &Expr, body: &Block) -> Option<Effect> {
+        self.expr(iterable).or_else(|| {
+            self.scoped(|walker| {
+                walker.bind(pattern, false);
+                walker.block(body)
+            })
+        })
+    }
+
+    fn match_arms(&mut self, scrutinee: &Expr, arms: &[MatchArm]) -> Option<Effect> {
+        self.expr(scrutinee).or_else(|| {
+            arms.iter().find_map(|arm| {
+                self.scoped(|walker| {
+                    walker.bind(&arm.pattern, false);
+                    walker
+                        .optional(arm.guard.as_ref())
+                        .or_else(|| walker.expr(&arm.body))
+                })
+            })
+        })
+    }
+
+    fn call(&mut self, callee: &Expr, span: Span) -> Option<Effect> {
+        match callee {
+            Expr::Ident(name, _) if self.pure_values.contains(name) => None,
+            Expr::Ident(name, _) if self.is_value(name) => Some(Effect::CallValue { span }),
+            Expr::Ident(name, _) => self.call_function(name, span),
+            Expr::Path(segments, _) => self.call_function(&segments.join("::"), span),
+            other => self.expr(other).or(Some(Effect::CallValue { span })),
+        }
+    }
+
+    // 未知的名字是枚举变体的构造或未定义的函数（由名称解析报告），不算副作用
+    fn call_function(&self, name: &str, span: Span) -> Option<Effect> {
+        if let Some(cause) = self.effects.impure.get(name) {
+            return Some(Effect::Call {
+                callee: name.to_string(),
+                span,
+                cause: Box::new(cause.clone()),
+            });
+        }
+        let builtin = builtins::lookup(name).filter(|builtin| !builtin.pure);
+        if builtin.is_some() && !self.effects.functions.contains(name) {
+            return Some(Effect::Builtin {
+                name: name.to_string(),
+                span,
+            });
+        }
+        None
+    }
+
+    // 修改第一个参数的内建函数（程序中定义的同名函数优先）
+    fn mutates(&self, name: &str) -> bool {
+        !self.effects.functions.contains(name)
+            && builtins::lookup(name).is_some_and(|builtin| builtin.mutates())
+    }
+
+    // 内建函数修改 `target` 指向的值：`&mut v` 写 `v`，引用参数写它指向的值
+    fn mutate(&self, target: &Expr, span: Span) -> Option<Effect> {
+        match target {
+            Expr::Ref(place, _, _) | Expr::Unary(UnOp::RefMut, place, _, _) => {
+                self.mutate(place, span)
+            }
+            Expr::Ident(name, _) if self.references.contains(name) => {
+                Some(Effect::WriteThroughReference { span })
+            }
+            place => self.write(place, span),
+        }
+    }
+
+    fn write(&self, place: &Expr, span: Span) -> Option<Effect> {
+        match place {
+            Expr::Ident(name, _) if self.locals.contains(name) => None,
+            Expr::Ident(name, _)
+                if self.effects.statics.contains(name) && !self.outer.contains(name) =>
+            {
+                Some(Effect::WriteStatic {
+                    name: name.clone(),
+                    span,
+                })
+            }
+            Expr::Ident(name, _) => Some(Effect::Write {
+                name: name.clone(),
+                span,
+            }),
+            Expr::Path(segments, _) => Some(Effect::WriteStatic {
+                name: segments.join("::"),
+                span,
+            }),
+            Expr::FieldAccess(base, _, _)
+            | Expr::TupleIndex(base, _, _)
+            | Expr::IndexAccess(base, _, _) => match base.as_ref() {
+                Expr::Ident(name, _) if self.references.contains(name) => {
+                    Some(Effect::WriteThroughReference { span })
+                }
+                base => self.write(base, span),
+            },
+            // 指向局部变量的引用可以写
+            Expr::Deref(inner, _) | Expr::Unary(UnOp::Deref, inner, _, _) => match inner.as_ref() {
+                Expr::Ident(name, _)
+                    if self.locals.contains(name) && !self.references.contains(name) =>
+                {
+                    None
+                }
+                _ => Some(Effect::WriteThroughReference { span }),
+            },
+            _ => Some(Effect::WriteThroughReference { span }),
+        }
+    }
+
+    fn is_value(&self, name: &str) -> bool {
+        self.locals.contains(name) || self.outer.contains(name)
+    }
+
+    // 初始值是否是指向外部的引用：复制引用参数，或借用非局部的位置
+    fn is_outer_reference(&self, init: &Expr) -> bool {
+        match init {
+            Expr::Ident(name, _) => self.references.contains(name),
+            Expr::Ref(place, _, _) | Expr::Unary(UnOp::Ref | UnOp::RefMut, place, _, _) => {
+                !self.is_local_place(place)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_local_place(&self, place: &Expr) -> bool {
+        match place {
+            Expr::Ident(name, _) => self.locals.contains(name) && !self.references.contains(name),
+            Expr::FieldAccess(base, _, _)
+            | Expr::TupleIndex(base, _, _)
+            | Expr::IndexAccess(base, _, _) => self.is_local_place(base),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// 逃逸分析：函数不能返回指向它自己的局部变量或临时值的引用
+// 函数返回之后它的局部变量（包括按值传递的参数）和表达式的临时值都不存在了，
+// 返回指向它们的引用会在使用时访问无效的内存。这是借用检查的第一步，还没有生命周期：
+// 返回类型中有引用的函数，每个返回的值（`return` 的值和函数体的值，经过 if、match
+// 和块的每个分支，以及元组、数组和结构体字面量的每个元素）不能是
+// - `&x`、`&x.field`、`&x[i]`：x 是 let 绑定的变量或按值的参数
+// - `&expr`：expr 不是位置表达式，如 `&(a + 1)`、`&f()` 和字面量 `&5`
+// - 保存了上面的引用的 let 变量，如 `let r = &x;` 之后的 `r`
+// 经过引用参数的位置（`&s[0]`、`&p.x`，s 和 p 是引用）、解引用和全局变量可以返回。
+// 模式绑定的变量和函数调用的结果不知道指向哪里，不报告
+
+use crate::ast::*;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::span::Span;
+use std::collections::HashMap;
+
+// 引用指向的地方
+#[derive(Debug, Clone, PartialEq)]
+enum Origin {
+    Local(String), // 局部变量
+    Temporary,     // 表达式的临时值
+    Outside,       // 函数外面的值，或者不知道指向哪里
+}
+
+// 变量中保存的值
+#[derive(Debug, Clone)]
+enum Binding {
+    Value,             // 局部的值
+    Reference(Origin), // 引用
+    Unknown,           // 模式绑定的变量
+}
+
+struct Escape {
+    scopes: Vec<HashMap<String, Binding>>,
+    errors: Vec<Diagnostic>,
+}
+
+/// 函数返回的指向局部变量或临时值的引用
+pub fn check(func: &Function) -> Vec<Diagnostic> {
+    match &func.return_type {
+        Some(ret) if contains_reference(ret) => {}
+        _ => return Vec::new(),
+    }
+    let mut escape = Escape {
+        scopes: vec![HashMap::new()],
+        errors: Vec::new(),
+    };
+    for param in &func.params {
+        let binding = match param.ty {
+            Type::Reference(..) | Type::Pointer(..) => Binding::Reference(Origin::Outside),
+            _ => Binding::Value,
+        };
+        escape.bind(&param.pattern, binding);
+    }
+    escape.block(&func.body, true);
+    escape.errors
+}
+
+fn contains_reference(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(..) => true,
+        Type::Array(inner, _) | Type::Slice(inner) => contains_reference(inner),
+        Type::Tuple(types) | Type::Generic(_, types) => types.iter().any(contains_reference),
+        _ => false,
+    }
+}
+
+impl Escape {
+    // `tail` 表示块的值是函数的返回值
+    fn block(&mut self, block: &Block, tail: bool) {
+        self.scopes.push(HashMap::new());
+        for (i, stmt) in block.statements.iter().enumerate() {
+            let tail = tail && i + 1 == block.statements.len();
+            self.statement(stmt, tail);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Statement, tail: bool) {
+        match stmt {
+            Statement::Let(stmt) => {
+                if let Some(init) = &stmt.init {
+                    self.expr(init);
+                }
+                if let Some(block) = &stmt.else_block {
+                    self.block(block, false);
+                }
+                let binding = match (&stmt.pattern, &stmt.init) {
+                    (Pattern::Ident(_), Some(init)) => match self.reference(init) {
+                        Some(origin) => Binding::Reference(origin),
+                        None => Binding::Value,
+                    },
+                    (Pattern::Ident(_), None) => Binding::Value,
+                    _ => Binding::Unknown,
+                };
+                self.bind(&stmt.pattern, binding);
+            }
+            Statement::Expr(stmt) if tail && !stmt.semicolon => self.returned(&stmt.expr),
+            Statement::Expr(stmt) => self.expr(&stmt.expr),
+            Statement::Return(ret) => {
+                if let Some(expr) = &ret.expr {
+                    self.returned(expr);
+                }
+            }
+            Statement::If(stmt) => self.if_stmt(stmt, tail),
+            Statement::While(stmt) => {
+                self.expr(&stmt.cond);
+                self.block(&stmt.body, false);
+            }
+            Statement::For(stmt) => {
+                self.expr(&stmt.iterable);
+                self.scopes.push(HashMap::new());
+                self.bind(&stmt.pattern, Binding::Unknown);
+                self.block(&stmt.body, false);
+                self.scopes.pop();
+            }
+            Statement::Match(stmt) => {
+                self.expr(&stmt.expr);
+                self.arms(&stmt.arms, tail);
+            }
+            Statement::Block(block) => self.block(block, tail),
+            Statement::Break(stmt) => {
+                if let Some(value) = &stmt.expr {
+                    self.expr(value);
+                }
+            }
+            Statement::Continue(_) => {}
+        }
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmt, tail: bool) {
+        self.expr(&stmt.cond);
+        self.block(&stmt.then_block, tail);
+        self.else_branch(stmt.else_block.as_ref(), tail);
+    }
+
+    fn else_branch(&mut self, branch: Option<&ElseBranch>, tail: bool) {
+        match branch {
+            Some(ElseBranch::Block(block)) => self.block(block, tail),
+            Some(ElseBranch::If(stmt)) => self.if_stmt(stmt, tail),
+            None => {}
+        }
+    }
+
+    fn arms(&mut self, arms: &[MatchArm], tail: bool) {
+        for arm in arms {
+            self.scopes.push(HashMap::new());
+            self.bind(&arm.pattern, Binding::Unknown);
+            if let Some(guard) = &arm.guard {
+                self.expr(guard);
+            }
+            match tail {
+                true => self.returned(&arm.body),
+                false => self.expr(&arm.body),
+            }
+            self.scopes.pop();
+        }
+    }
+
+    // 作为函数的返回值的表达式
+    fn returned(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Block(block, _) | Expr::Unsafe(block, _) => self.block(block, true),
+            Expr::If(cond, then_block, else_branch, _) => {
+                self.expr(cond);
+                self.block(then_block, true);
+                self.else_branch(else_branch.as_ref(), true);
+            }
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                self.arms(arms, true);
+            }
+            Expr::TupleLit(elements, _) | Expr::ArrayLit(elements, _) => {
+                for element in elements {
+                    self.returned(element);
+                }
+            }
+            Expr::StructLit(_, fields, _) => {
+                for (_, value) in fields {
+                    self.returned(value);
+                }
+            }
+            _ => {
+                self.expr(expr);
+                if let Some(origin) = self.reference(expr) {
+                    self.escaping(&origin, expr.span());
+                }
+            }
+        }
+    }
+
+    fn escaping(&mut self, origin: &Origin, span: Span) {
+        let (message, help) = match origin {
+            Origin::Outside => return,
+            Origin::Local(name) => (
+                format!("cannot return a reference to local variable `{}`", name),
+                format!(
+                    "`{}` is dropped when the function returns; return the value itself instead",
+                    name
+                ),
+            ),
+            Origin::Temporary => (
+                "cannot return a reference to a temporary value".to_string(),
+                "the temporary value is dropped when the function returns; return the value itself instead"
+                    .to_string(),
+            ),
+        };
+        self.errors.push(
+            Diagnostic::error(message, span)
+                .with_code(ErrorCode::E0515)
+                .with_help(help),
+        );
+    }
+
+    // 表达式的值是引用时它指向的地方，不是引用时为 None
+    fn reference(&self, expr: &Expr) -> Option<Origin> {
+        match expr {
+            Expr::Ref(inner, _, _) | Expr::Unary(UnOp::Ref | UnOp::RefMut, inner, _, _) => {
+                Some(self.place(inner))
+            }
+            Expr::Ident(name, _) => match self.lookup(name)? {
+                Binding::Reference(origin) => Some(origin.clone()),
+                Binding::Value | Binding::Unknown => None,
+            },
+            _ => None,
+        }
+    }
+
+    // 位置表达式所在的地方；不是位置表达式的值是临时值
+    fn place(&self, expr: &Expr) -> Origin {
+        match expr {
+            Expr::Ident(name, _) => match self.lookup(name) {
+                Some(Binding::Value | Binding::Reference(_)) => Origin::Local(name.clone()),
+                Some(Binding::Unknown) | None => Origin::Outside,
+            },
+            // 字段和下标经过引用时自动解引用，位置在引用指向的地方
+            Expr::FieldAccess(base, _, _)
+            | Expr::TupleIndex(base, _, _)
+            | Expr::IndexAccess(base, _, _) => match self.reference(base) {
+                Some(origin) => origin,
+                None => self.place(base),
+            },
+            Expr::Deref(inner, _) | Expr::Unary(UnOp::Deref, inner, _, _) => {
+                self.reference(inner).unwrap_or(Origin::Outside)
+            }
+            Expr::Path(..) => Origin::Outside,
+            _ => Origin::Temporary,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn bind(&mut self, pattern: &Pattern, binding: Binding) {
+        let mut names = Vec::new();
+        pattern_names(pattern, &mut names);
+        let scope = self.scopes.last_mut().expect("a scope is open");
+        for name in names {
+            scope.insert(name.to_string(), binding.clone());
+        }
+    }
+
+    // 其他表达式中的 `return` 和块
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Return(Some(value), _) => self.returned(value),
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.block(block, false)
+            }
+            Expr::If(cond, then_block, else_branch, _) => {
+                self.expr(cond);
+                self.block(then_block, false);
+                self.else_branch(else_branch.as_ref(), false);
+            }
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                self.arms(arms, false);
+            }
+            Expr::While(_, cond, body, _) => {
+                self.expr(cond);
+                self.block(body, false);
+            }
+            Expr::For(_, pattern, iterable, body, _) => {
+                self.expr(iterable);
+                self.scopes.push(HashMap::new());
+                self.bind(pattern, Binding::Unknown);
+                self.block(body, false);
+                self.scopes.pop();
+            }
+            Expr::Binary(_, left, right, _, _)
+            | Expr::IndexAccess(left, right, _)
+            | Expr::Assign(left, right, _)
+            | Expr::CompoundAssign(_, left, right, _, _) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Range(start, end, _, _) => {
+                for bound in start.iter().chain(end) {
+                    self.expr(bound);
+                }
+            }
+            Expr::Unary(_, inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _)
+            | Expr::Turbofish(inner, _, _) => self.expr(inner),
+            Expr::Break(_, Some(value), _) => self.expr(value),
+            Expr::Call(callee, args, _, _) => {
+                self.expr(callee);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::MethodCall(receiver, _, args, _, _) => {
+                self.expr(receiver);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => {
+                for element in elements {
+                    self.expr(element);
+                }
+            }
+            Expr::StructLit(_, fields, _) => {
+                for (_, value) in fields {
+                    self.expr(value);
+                }
+            }
+            // 闭包中的 `return` 离开的是闭包
+            Expr::Closure(..) => {}
+            _ => {}
+        }
+    }
+}
+
+fn pattern_names<'p>(pattern: &'p Pattern, names: &mut Vec<&'p str>) {
+    match pattern {
+        Pattern::Ident(name) => names.push(name),
+        Pattern::Struct(_, fields) => {
+            for (_, pattern) in fields {
+                pattern_names(pattern, names);
+            }
+        }
+        Pattern::TupleStruct(_, patterns) | Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+            for pattern in patterns {
+                pattern_names(pattern, names);
+            }
+        }
+        Pattern::Literal(_) | Pattern::Wildcard => {}
+    }
+}
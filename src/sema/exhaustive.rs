@@ -0,0 +1,223 @@
+// match 的穷尽性检查
+// 模式化简为构造器（枚举变体、bool、元组）和通配符后，按"有用性"逐列展开：
+// 如果在已有的分支之后再加一个通配符分支仍然能匹配到值，match 就没有穷尽，
+// 展开的过程同时构造出一个没有被覆盖的值，作为诊断信息中的示例。
+// 带守卫的分支不算覆盖；整数、字符和字符串字面量无法穷举，只有通配符能覆盖它们
+
+use crate::ast::{EnumDef, Literal, MatchArm, Pattern};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+    Or(Vec<Pat>),
+    Opaque, // 只能由通配符覆盖的值，如整数字面量
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Variant(String, usize), // 枚举名和变体序号
+    Bool(bool),
+    Tuple(usize),
+}
+
+pub(super) struct Exhaustiveness<'a> {
+    pub enums: &'a BTreeMap<String, EnumDef>,
+    pub variants: &'a BTreeMap<String, String>, // 变体名 -> 所属枚举
+}
+
+impl Exhaustiveness<'_> {
+    /// 没有被任何分支覆盖的值，如 `None`、`Some(false)`；穷尽时为 None
+    pub fn missing(&self, arms: &[MatchArm]) -> Option<String> {
+        let rows: Vec<Vec<Pat>> = arms
+            .iter()
+            .filter(|arm| arm.guard.is_none())
+            .map(|arm| vec![self.lower(&arm.pattern)])
+            .collect();
+        self.witness(&rows, 1).map(|mut values| values.remove(0))
+    }
+
+    /// let、for 和参数中的模式不匹配的值；模式不可反驳时为 None
+    pub fn refuted(&self, pattern: &Pattern) -> Option<String> {
+        self.witness(&[vec![self.lower(pattern)]], 1)
+            .map(|mut values| values.remove(0))
+    }
+
+    fn lower(&self, pattern: &Pattern) -> Pat {
+        match pattern {
+            Pattern::Wildcard => Pat::Wild,
+            Pattern::Ident(name) => match self.variant(name) {
+                Some(ctor) => Pat::Ctor(ctor, Vec::new()),
+                None => Pat::Wild,
+            },
+            Pattern::Literal(Literal::Bool(value)) => Pat::Ctor(Ctor::Bool(*value), Vec::new()),
+            Pattern::Literal(_) => Pat::Opaque,
+            Pattern::TupleStruct(name, patterns) => {
+                let ctor = self.variant(name).unwrap_or(Ctor::Tuple(patterns.len()));
+                Pat::Ctor(ctor, patterns.iter().map(|p| self.lower(p)).collect())
+            }
+            Pattern::Tuple(patterns) => Pat::Ctor(
+                Ctor::Tuple(patterns.len()),
+                patterns.iter().map(|p| self.lower(p)).collect(),
+            ),
+            // 结构体只有一个构造器，每个字段的模式都不可反驳时覆盖所有值
+            Pattern::Struct(_, fields) => {
+                let fields: Vec<Pat> = fields.iter().map(|(_, p)| self.lower(p)).collect();
+                if fields
+                    .iter()
+                    .all(|field| self.witness(&[vec![field.clone()]], 1).is_none())
+                {
+                    Pat::Wild
+                } else {
+                    Pat::Opaque
+                }
+            }
+            Pattern::Or(alternatives) => {
+                Pat::Or(alternatives.iter().map(|p| self.lower(p)).collect())
+            }
+        }
+    }
+
+    fn variant(&self, name: &str) -> Option<Ctor> {
+        let enum_name = self.variants.get(name)?;
+        let index = self
+            .enums
+            .get(enum_name)?
+            .variants
+            .iter()
+            .position(|variant| variant.name == name)?;
+        Some(Ctor::Variant(enum_name.clone(), index))
+    }
+
+    // 与 `ctor` 同一类型的所有构造器
+    fn siblings(&self, ctor: &Ctor) -> Vec<Ctor> {
+        match ctor {
+            Ctor::Variant(enum_name, _) => {
+                let count = self
+                    .enums
+                    .get(enum_name)
+                    .map_or(0, |def| def.variants.len());
+                (0..count)
+                    .map(|index| Ctor::Variant(enum_name.clone(), index))
+                    .collect()
+            }
+            Ctor::Bool(_) => vec![Ctor::Bool(true), Ctor::Bool(false)],
+            Ctor::Tuple(len) => vec![Ctor::Tuple(*len)],
+        }
+    }
+
+    fn arity(&self, ctor: &Ctor) -> usize {
+        match ctor {
+            Ctor::Variant(enum_name, index) => self.enums[enum_name].variants[*index]
+                .fields
+                .as_ref()
+                .map_or(0, Vec::len),
+            Ctor::Bool(_) => 0,
+            Ctor::Tuple(len) => *len,
+        }
+    }
+
+    fn render(&self, ctor: &Ctor, fields: &[String]) -> String {
+        let list = fields.join(", ");
+        match ctor {
+            Ctor::Variant(enum_name, index) => {
+                let name = &self.enums[enum_name].variants[*index].name;
+                if fields.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}({})", name, list)
+                }
+            }
+            Ctor::Bool(value) => value.to_string(),
+            Ctor::Tuple(1) => format!("({},)", list),
+            Ctor::Tuple(_) => format!("({})", list),
+        }
+    }
+
+    // 每行 `width` 列；返回一组不被任何行匹配的值（每列一个），所有值都被匹配时为 None
+    fn witness(&self, rows: &[Vec<Pat>], width: usize) -> Option<Vec<String>> {
+        if width == 0 {
+            return rows.is_empty().then(Vec::new);
+        }
+        let rows = expand_or(rows);
+        let heads: Vec<&Ctor> = rows
+            .iter()
+            .filter_map(|row| match &row[0] {
+                Pat::Ctor(ctor, _) => Some(ctor),
+                _ => None,
+            })
+            .collect();
+        let opaque = rows.iter().any(|row| matches!(row[0], Pat::Opaque));
+        let all = match heads.first() {
+            Some(first) if !opaque => self.siblings(first),
+            _ => Vec::new(),
+        };
+
+        if !all.is_empty() && all.iter().all(|ctor| heads.contains(&ctor)) {
+            // 每个构造器都出现了：分别检查每个构造器的字段
+            for ctor in &all {
+                let arity = self.arity(ctor);
+                let specialized: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter_map(|row| specialize(row, ctor, arity))
+                    .collect();
+                if let Some(mut values) = self.witness(&specialized, arity + width - 1) {
+                    let rest = values.split_off(arity);
+                    let mut result = vec![self.render(ctor, &values)];
+                    result.extend(rest);
+                    return Some(result);
+                }
+            }
+            return None;
+        }
+
+        // 有构造器没有出现：只有首列是通配符的行可能匹配它
+        let default: Vec<Vec<Pat>> = rows
+            .iter()
+            .filter(|row| matches!(row[0], Pat::Wild))
+            .map(|row| row[1..].to_vec())
+            .collect();
+        let mut values = self.witness(&default, width - 1)?;
+        let head = match all.iter().find(|ctor| !heads.contains(ctor)) {
+            Some(ctor) => self.render(ctor, &vec!["_".to_string(); self.arity(ctor)]),
+            None => "_".to_string(),
+        };
+        values.insert(0, head);
+        Some(values)
+    }
+}
+
+// 首列是或模式的行展开为多行
+fn expand_or(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    let mut expanded = Vec::new();
+    for row in rows {
+        match &row[0] {
+            Pat::Or(alternatives) => {
+                let alternatives: Vec<Vec<Pat>> = alternatives
+                    .iter()
+                    .map(|alternative| {
+                        let mut row = row.clone();
+                        row[0] = alternative.clone();
+                        row
+                    })
+                    .collect();
+                expanded.extend(expand_or(&alternatives));
+            }
+            _ => expanded.push(row.clone()),
+        }
+    }
+    expanded
+}
+
+// 首列匹配 `ctor` 的行：首列换成它的各个字段
+fn specialize(row: &[Pat], ctor: &Ctor, arity: usize) -> Option<Vec<Pat>> {
+    let mut fields = match &row[0] {
+        Pat::Ctor(head, fields) if head == ctor => fields.clone(),
+        Pat::Wild => Vec::new(),
+        _ => return None,
+    };
+    fields.resize(arity, Pat::Wild);
+    fields.extend_from_slice(&row[1..]);
+    Some(fields)
+}
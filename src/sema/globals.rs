@@ -0,0 +1,169 @@
+// const 和 static 的初始化顺序
+// 初始值用到的其他 const/static 必须先初始化。按声明顺序深度优先遍历依赖，
+// 每个条目排在它用到的条目之后，互不依赖的条目保持声明顺序；
+// 解释器按这个顺序初始化，虚拟机在第一次使用时初始化，得到的顺序相同。
+// 依赖成环时无法初始化，环上的每个条目和引用的位置都要报告
+
+use crate::ast::*;
+use crate::span::Span;
+use std::collections::{BTreeSet, HashMap};
+
+/// 环上的一条边：`from` 的初始值在 `span` 处用到了 `to`
+#[derive(Debug, Clone)]
+pub struct Use<'p> {
+    pub from: &'p str,
+    pub to: &'p str,
+    pub span: Span,
+}
+
+/// 初始化顺序和依赖环
+#[derive(Debug, Default)]
+pub struct Order<'p> {
+    pub items: Vec<&'p Item>,
+    pub cycles: Vec<Vec<Use<'p>>>, // 每个环从声明最早的条目开始
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Visiting,
+    Done,
+}
+
+/// 条目中 const 和 static 的初始化顺序
+pub fn initialization_order(items: &[Item]) -> Order<'_> {
+    let mut values: Vec<(&str, &Expr, &Item)> = Vec::new();
+    for item in items {
+        match item {
+            Item::Const(def) => values.push((&def.name, &def.value, item)),
+            Item::Static(def) => values.push((&def.name, &def.value, item)),
+            _ => {}
+        }
+    }
+    let index: HashMap<&str, usize> = values
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _, _))| (*name, i))
+        .collect();
+    let dependencies: Vec<Vec<(usize, Span)>> = values
+        .iter()
+        .map(|(_, value, _)| {
+            let mut uses = Vec::new();
+            collect_uses(value, &mut uses);
+            let mut seen = BTreeSet::new();
+            uses.into_iter()
+                .filter_map(|(name, span)| Some((*index.get(name)?, span)))
+                .filter(|(target, _)| seen.insert(*target))
+                .collect()
+        })
+        .collect();
+
+    let mut walk = Walk {
+        values: &values,
+        dependencies: &dependencies,
+        states: vec![None; values.len()],
+        path: Vec::new(),
+        order: Order::default(),
+        reported: BTreeSet::new(),
+    };
+    for i in 0..values.len() {
+        walk.visit(i);
+    }
+    walk.order
+}
+
+struct Walk<'a, 'p> {
+    values: &'a [(&'p str, &'p Expr, &'p Item)],
+    dependencies: &'a [Vec<(usize, Span)>],
+    states: Vec<Option<State>>,
+    path: Vec<(usize, Span)>, // 正在访问的条目和它用到下一个条目的位置
+    order: Order<'p>,
+    reported: BTreeSet<Vec<usize>>, // 已经报告过的环（成员排序后）
+}
+
+impl<'p> Walk<'_, 'p> {
+    fn visit(&mut self, i: usize) {
+        if self.states[i].is_some() {
+            return;
+        }
+        self.states[i] = Some(State::Visiting);
+        for &(target, span) in &self.dependencies[i] {
+            self.path.push((i, span));
+            match self.states[target] {
+                None => self.visit(target),
+                Some(State::Visiting) => self.cycle(target),
+                Some(State::Done) => {}
+            }
+            self.path.pop();
+        }
+        self.states[i] = Some(State::Done);
+        self.order.items.push(self.values[i].2);
+    }
+
+    // 路径上从 target 开始到当前条目的部分加上回到 target 的边组成一个环
+    fn cycle(&mut self, target: usize) {
+        let start = self
+            .path
+            .iter()
+            .position(|&(i, _)| i == target)
+            .expect("the target of a back edge is on the path");
+        let edges = &self.path[start..];
+        let mut members: Vec<usize> = edges.iter().map(|&(i, _)| i).collect();
+        members.sort_unstable();
+        if !self.reported.insert(members) {
+            return;
+        }
+        let first = (0..edges.len()).min_by_key(|&k| edges[k].0).unwrap_or(0);
+        let mut uses: Vec<Use<'p>> = edges
+            .iter()
+            .enumerate()
+            .map(|(k, &(from, span))| {
+                let to = edges.get(k + 1).map_or(target, |&(next, _)| next);
+                Use {
+                    from: self.values[from].0,
+                    to: self.values[to].0,
+                    span,
+                }
+            })
+            .collect();
+        uses.rotate_left(first);
+        self.order.cycles.push(uses);
+    }
+}
+
+// 初始值中按出现顺序用到的名字；初始值只能是常量表达式，不会有局部变量
+fn collect_uses<'p>(expr: &'p Expr, uses: &mut Vec<(&'p str, Span)>) {
+    match expr {
+        Expr::Ident(name, span) => uses.push((name, *span)),
+        Expr::Binary(_, left, right, _, _) | Expr::IndexAccess(left, right, _) => {
+            collect_uses(left, uses);
+            collect_uses(right, uses);
+        }
+        Expr::Range(start, end, _, _) => {
+            for bound in start.iter().chain(end) {
+                collect_uses(bound, uses);
+            }
+        }
+        Expr::Unary(_, inner, _, _)
+        | Expr::Cast(inner, _, _)
+        | Expr::Ref(inner, _, _)
+        | Expr::Deref(inner, _)
+        | Expr::FieldAccess(inner, _, _)
+        | Expr::TupleIndex(inner, _, _) => collect_uses(inner, uses),
+        Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => {
+            for element in elements {
+                collect_uses(element, uses);
+            }
+        }
+        Expr::StructLit(_, fields, _) => {
+            for (_, value) in fields {
+                collect_uses(value, uses);
+            }
+        }
+        Expr::Call(_, args, _, _) => {
+            for arg in args {
+                collect_uses(arg, uses);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,120 @@
+// 泛型函数调用处的类型实参推断
+// 每个形参类型和对应实参的类型合一（`Type::infer_params`），得到类型参数的替换；
+// 同一个类型参数从不同的实参推断出不同的类型时是类型错误。
+// 实参的类型未知（`_`）的部分不参与推断；没有后缀的整数字面量的类型先由其他实参和 let 的类型标注决定，
+// 都确定不了时才是 `i32`，确定的类型不是整数类型时是类型错误；数组的引用可以传给切片的引用。
+// 闭包实参先按其他实参推断出的形参类型检查，闭包的类型（参数和函数体的类型）再参与推断，所以 `map(5, |x| x > 2)` 得到 `U = bool`。
+// 泛型结构体字面量 `Wrapper { value: 5 }` 同样由字段的值推断结构体的类型实参。
+// 返回类型中的类型参数不能由实参确定、结果又没有用类型标注约束时，报告需要类型标注（E0282）
+
+use super::literals::int_range;
+use crate::ast::Type;
+use std::collections::BTreeMap;
+
+/// 同一个类型参数推断出的两个不同的类型
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub param: String,
+    pub expected: Type, // 先推断出的类型
+    pub found: Type,
+    pub index: usize, // 推断出 `found` 的实参
+}
+
+/// 一次调用中推断出的类型实参
+#[derive(Debug, Clone, Default)]
+pub struct Inference {
+    pub types: BTreeMap<String, Type>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Inference {
+    /// 形参类型 `param` 和实参类型 `arg` 合一，结果并入已推断的类型
+    pub fn unify(&mut self, type_params: &[String], param: &Type, arg: &Type, index: usize) {
+        let mut found = BTreeMap::new();
+        param.infer_params(arg, type_params, &mut found);
+        for (name, ty) in found {
+            let Some(known) = self.types.get(&name) else {
+                self.types.insert(name, ty);
+                continue;
+            };
+            match merge(known, &ty) {
+                Some(merged) => {
+                    self.types.insert(name, merged);
+                }
+                None => self.conflicts.push(Conflict {
+                    param: name,
+                    expected: known.clone(),
+                    found: ty,
+                    index,
+                }),
+            }
+        }
+    }
+
+    /// 没有后缀的整数字面量是类型参数 `param` 的实参：推断出的类型不是整数类型时矛盾
+    pub fn check_literal(&mut self, param: &str, index: usize) {
+        let Some(ty) = self.types.get(param) else {
+            return;
+        };
+        if int_range(ty).is_none() && !ty.has_infer() {
+            self.conflicts.push(Conflict {
+                param: param.to_string(),
+                expected: ty.clone(),
+                found: Type::I32,
+                index,
+            });
+        }
+    }
+
+    /// 用推断出的类型替换 `ty` 中的类型参数，没有推断出的保持原样
+    pub fn apply(&self, ty: &Type) -> Type {
+        ty.substitute(&self.types)
+    }
+
+    /// 同 `apply`，没有推断出的类型参数替换为 `_`
+    pub fn resolve(&self, type_params: &[String], ty: &Type) -> Type {
+        let mut types = self.types.clone();
+        for param in type_params {
+            types.entry(param.clone()).or_insert(Type::Infer);
+        }
+        ty.substitute(&types)
+    }
+
+    /// `ty` 中出现、但没有推断出完整类型的类型参数
+    pub fn missing(&self, type_params: &[String], ty: &Type) -> Vec<String> {
+        type_params
+            .iter()
+            .filter(|param| mentions(ty, param))
+            .filter(|param| self.types.get(*param).is_none_or(Type::has_infer))
+            .cloned()
+            .collect()
+    }
+}
+
+// 两次推断出的类型中更具体的一个；`_` 可以匹配任何类型，结构不同时为 None
+fn merge(known: &Type, found: &Type) -> Option<Type> {
+    let mut unused = BTreeMap::new();
+    if known.infer_params(found, &[], &mut unused) {
+        Some(known.clone())
+    } else if found.infer_params(known, &[], &mut unused) {
+        Some(found.clone())
+    } else {
+        None
+    }
+}
+
+/// 类型中是否出现类型参数 `param`
+pub fn mentions(ty: &Type, param: &str) -> bool {
+    match ty {
+        Type::Named(name) => name == param,
+        Type::Array(inner, _)
+        | Type::Slice(inner)
+        | Type::Pointer(inner, _)
+        | Type::Reference(inner, _) => mentions(inner, param),
+        Type::Tuple(types) | Type::Generic(_, types) => types.iter().any(|ty| mentions(ty, param)),
+        Type::Function(params, ret, _) => {
+            params.iter().any(|ty| mentions(ty, param)) || mentions(ret, param)
+        }
+        _ => false,
+    }
+}
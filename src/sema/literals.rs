@@ -0,0 +1,356 @@
+// 整数字面量的类型
+// 没有后缀的整数字面量的类型由上下文决定：let 的类型标注、常量和静态变量的类型、函数参数、
+// 返回值、赋值的左侧、二元运算的另一个操作数、结构体字段，以及数组和元组的元素类型。
+// 没有标注类型的 `let y = 5;` 取 y 之后第一次在函数参数、let 标注、返回值或者与带后缀的
+// 字面量运算中使用时的类型，都没有时是 i32。
+// 字面量的值必须在它的类型的范围内，无符号类型的字面量不能取负；带后缀的字面量（`300u8`）
+// 的类型就是后缀，和上下文要求的整数类型不同是类型错误。
+// 常量和静态变量的整数初始值在编译时按 i128 求值，结果必须在声明的类型的范围内
+
+use super::{FnSig, Global};
+use crate::ast::*;
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+/// 整数类型的取值范围，不是整数类型时为 None。
+/// 执行时整数都是 64 位有符号数，`u64` 和 `usize` 的最大值同样是 `i64::MAX`，
+/// 与词法分析器接受的最大字面量一致
+pub fn int_range(ty: &Type) -> Option<(i128, i128)> {
+    let (bits, signed) = match ty {
+        Type::I8 => (8, true),
+        Type::I16 => (16, true),
+        Type::I32 => (32, true),
+        Type::I64 | Type::Isize => (64, true),
+        Type::U8 => (8, false),
+        Type::U16 => (16, false),
+        Type::U32 => (32, false),
+        Type::U64 | Type::Usize => (64, false),
+        _ => return None,
+    };
+    Some(match signed {
+        true => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+        false => (0, ((1 << bits) - 1).min(i64::MAX as i128)),
+    })
+}
+
+/// 整数字面量或取负的整数字面量：值和后缀
+pub fn int_literal(expr: &Expr) -> Option<(i128, Option<&Type>)> {
+    match expr {
+        Expr::Literal(Literal::Int(n, suffix), _) => Some((i128::from(*n), suffix.as_ref())),
+        Expr::Unary(UnOp::Neg, inner, _, _) => match inner.as_ref() {
+            Expr::Literal(Literal::Int(n, suffix), _) => Some((-i128::from(*n), suffix.as_ref())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 没有后缀的整数字面量，它的类型完全由上下文决定
+pub fn is_unsuffixed(expr: &Expr) -> bool {
+    matches!(int_literal(expr), Some((_, None)))
+}
+
+/// 操作数的类型相同的二元运算；移位的右侧可以是任何整数类型
+pub fn same_operand_types(op: &BinOp) -> bool {
+    !matches!(
+        op,
+        BinOp::LogicalAnd | BinOp::LogicalOr | BinOp::LeftShift | BinOp::RightShift
+    )
+}
+
+/// 值超出类型的范围时的错误信息和帮助
+pub fn out_of_range(value: i128, ty: &Type) -> Option<(String, String)> {
+    let (min, max) = int_range(ty)?;
+    if (min..=max).contains(&value) {
+        return None;
+    }
+    Some((
+        format!("literal out of range for `{}`", ty),
+        format!(
+            "the literal `{}` does not fit into the type `{}` whose range is `{}..={}`",
+            value, ty, min, max
+        ),
+    ))
+}
+
+// 查找变量在之后的语句中第一次在能确定整数类型的位置上的使用；
+// 找到时以 `Break(Some(类型))` 结束，变量被重新绑定时以 `Break(None)` 结束
+pub struct FirstUse<'a> {
+    pub name: &'a str,
+    pub functions: &'a BTreeMap<String, FnSig>,
+    pub ret: Option<&'a Type>, // 所在函数的返回类型
+}
+
+impl FirstUse<'_> {
+    /// 变量第一次使用时要求的整数类型
+    pub fn find(&self, statements: &[Statement]) -> Option<Type> {
+        match self.statements(statements) {
+            ControlFlow::Break(ty) => ty,
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    fn statements(&self, statements: &[Statement]) -> ControlFlow<Option<Type>> {
+        statements.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&self, stmt: &Statement) -> ControlFlow<Option<Type>> {
+        match stmt {
+            Statement::Let(stmt) => {
+                if let Some(init) = &stmt.init {
+                    if let Some(ty) = stmt.ty.as_ref().filter(|_| self.is_name(init)) {
+                        self.found(ty)?;
+                    }
+                    self.expr(init)?;
+                }
+                if binds(&stmt.pattern, self.name) {
+                    return ControlFlow::Break(None);
+                }
+                ControlFlow::Continue(())
+            }
+            Statement::Expr(stmt) => self.expr(&stmt.expr),
+            Statement::Return(stmt) => self.returned(stmt.expr.as_ref()),
+            Statement::If(stmt) => self.if_stmt(stmt),
+            Statement::While(stmt) => {
+                self.expr(&stmt.cond)?;
+                self.statements(&stmt.body.statements)
+            }
+            Statement::For(stmt) => {
+                self.expr(&stmt.iterable)?;
+                self.statements(&stmt.body.statements)
+            }
+            Statement::Match(stmt) => {
+                self.expr(&stmt.expr)?;
+                self.arms(&stmt.arms)
+            }
+            Statement::Break(stmt) => self.exprs(stmt.expr.iter()),
+            Statement::Continue(_) => ControlFlow::Continue(()),
+            Statement::Block(block) => self.statements(&block.statements),
+        }
+    }
+
+    fn if_stmt(&self, stmt: &IfStmt) -> ControlFlow<Option<Type>> {
+        self.expr(&stmt.cond)?;
+        self.statements(&stmt.then_block.statements)?;
+        self.else_branch(stmt.else_block.as_ref())
+    }
+
+    fn else_branch(&self, else_branch: Option<&ElseBranch>) -> ControlFlow<Option<Type>> {
+        match else_branch {
+            Some(ElseBranch::Block(block)) => self.statements(&block.statements),
+            Some(ElseBranch::If(nested)) => self.if_stmt(nested),
+            None => ControlFlow::Continue(()),
+        }
+    }
+
+    fn arms(&self, arms: &[MatchArm]) -> ControlFlow<Option<Type>> {
+        arms.iter()
+            .try_for_each(|arm| self.exprs(arm.guard.iter().chain([&arm.body])))
+    }
+
+    fn exprs<'e>(&self, exprs: impl IntoIterator<Item = &'e Expr>) -> ControlFlow<Option<Type>> {
+        exprs.into_iter().try_for_each(|expr| self.expr(expr))
+    }
+
+    fn expr(&self, expr: &Expr) -> ControlFlow<Option<Type>> {
+        match expr {
+            Expr::Call(callee, args, _, _) => {
+                self.call(callee, args)?;
+                self.exprs(args)
+            }
+            Expr::MethodCall(receiver, _, args, _, _) => {
+                self.exprs(std::iter::once(&**receiver).chain(args))
+            }
+            Expr::Binary(op, left, right, _, _) => {
+                if same_operand_types(op) {
+                    for (operand, other) in [(left, right), (right, left)] {
+                        if let Some((_, Some(ty))) = int_literal(other) {
+                            if self.is_name(operand) {
+                                self.found(ty)?;
+                            }
+                        }
+                    }
+                }
+                self.expr(left)?;
+                self.expr(right)
+            }
+            Expr::Return(value, _) => self.returned(value.as_deref()),
+            Expr::Block(block, _) | Expr::Unsafe(block, _) | Expr::AsyncBlock(block, _) => {
+                self.statements(&block.statements)
+            }
+            Expr::If(cond, then_block, else_branch, _) => {
+                self.expr(cond)?;
+                self.statements(&then_block.statements)?;
+                self.else_branch(else_branch.as_ref())
+            }
+            Expr::While(_, cond, body, _) => {
+                self.expr(cond)?;
+                self.statements(&body.statements)
+            }
+            Expr::For(_, _, iterable, body, _) => {
+                self.expr(iterable)?;
+                self.statements(&body.statements)
+            }
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee)?;
+                self.arms(arms)
+            }
+            Expr::Range(start, end, _, _) => self.exprs(start.iter().chain(end).map(|e| &**e)),
+            Expr::IndexAccess(left, right, _)
+            | Expr::Assign(left, right, _)
+            | Expr::CompoundAssign(_, left, right, _, _) => {
+                self.expr(left)?;
+                self.expr(right)
+            }
+            Expr::Unary(_, inner, _, _)
+            | Expr::FieldAccess(inner, _, _)
+            | Expr::TupleIndex(inner, _, _)
+            | Expr::Cast(inner, _, _)
+            | Expr::Ref(inner, _, _)
+            | Expr::Deref(inner, _)
+            | Expr::Try(inner, _)
+            | Expr::Await(inner, _) => self.expr(inner),
+            Expr::StructLit(_, fields, _) => self.exprs(fields.iter().map(|(_, value)| value)),
+            Expr::ArrayLit(elements, _) | Expr::TupleLit(elements, _) => self.exprs(elements),
+            Expr::InlineAsm(asm, _) => self.exprs(asm.exprs()),
+            Expr::Break(_, value, _) => self.exprs(value.iter().map(|value| &**value)),
+            // 闭包的参数和返回值是另一个上下文
+            Expr::Closure(..)
+            | Expr::Literal(..)
+            | Expr::Ident(..)
+            | Expr::Path(..)
+            | Expr::Turbofish(..)
+            | Expr::Continue(..)
+            | Expr::MacroCall(_) => ControlFlow::Continue(()),
+        }
+    }
+
+    // 作为参数传给用户定义的函数
+    fn call(&self, callee: &Expr, args: &[Expr]) -> ControlFlow<Option<Type>> {
+        let name = match callee {
+            Expr::Ident(name, _) => name.clone(),
+            Expr::Path(segments, _) => segments.join("::"),
+            _ => return ControlFlow::Continue(()),
+        };
+        let Some(sig) = self.functions.get(&name).filter(|sig| !sig.builtin) else {
+            return ControlFlow::Continue(());
+        };
+        for (arg, param) in args.iter().zip(&sig.params) {
+            if self.is_name(arg) {
+                self.found(param)?;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn returned(&self, value: Option<&Expr>) -> ControlFlow<Option<Type>> {
+        let Some(value) = value else {
+            return ControlFlow::Continue(());
+        };
+        if let Some(ret) = self.ret.filter(|_| self.is_name(value)) {
+            self.found(ret)?;
+        }
+        self.expr(value)
+    }
+
+    // 只有整数类型能确定字面量的类型
+    fn found(&self, ty: &Type) -> ControlFlow<Option<Type>> {
+        match int_range(ty) {
+            Some(_) => ControlFlow::Break(Some(ty.clone())),
+            None => ControlFlow::Continue(()),
+        }
+    }
+
+    fn is_name(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::Ident(name, _) if name == self.name)
+    }
+}
+
+// 模式绑定了这个名字
+fn binds(pattern: &Pattern, name: &str) -> bool {
+    match pattern {
+        Pattern::Ident(ident) => ident == name,
+        Pattern::Struct(_, fields) => fields.iter().any(|(_, pattern)| binds(pattern, name)),
+        Pattern::TupleStruct(_, patterns) | Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+            patterns.iter().any(|pattern| binds(pattern, name))
+        }
+        Pattern::Literal(_) | Pattern::Wildcard => false,
+    }
+}
+
+// 常量初始值中的整数运算；常量之间的引用展开为它们的初始值（循环依赖另外报告）
+pub struct ConstEval<'a> {
+    pub globals: &'a BTreeMap<String, Global>,
+}
+
+impl ConstEval<'_> {
+    /// 初始值的整数值；不是能求值的整数表达式时为 `Ok(None)`，求值失败时是错误信息
+    pub fn int(&self, expr: &Expr) -> Result<Option<i128>, String> {
+        self.eval(expr, 0)
+    }
+
+    fn eval(&self, expr: &Expr, depth: usize) -> Result<Option<i128>, String> {
+        if depth > 64 {
+            return Ok(None);
+        }
+        let value = match expr {
+            Expr::Literal(Literal::Int(n, _), _) => i128::from(*n),
+            Expr::Ident(name, _) => match self.globals.get(name) {
+                Some(Global {
+                    value: Some(value), ..
+                }) => return self.eval(value, depth + 1),
+                _ => return Ok(None),
+            },
+            Expr::Unary(UnOp::Neg, inner, _, _) => match self.eval(inner, depth + 1)? {
+                Some(value) => -value,
+                None => return Ok(None),
+            },
+            // 整数之间的 `as` 截断为目标类型的宽度
+            Expr::Cast(inner, ty, _) => {
+                let (Some(value), Some((min, max))) = (self.eval(inner, depth + 1)?, int_range(ty))
+                else {
+                    return Ok(None);
+                };
+                (value - min).rem_euclid(max - min + 1) + min
+            }
+            Expr::Binary(op, left, right, _, _) => {
+                let (Some(left), Some(right)) =
+                    (self.eval(left, depth + 1)?, self.eval(right, depth + 1)?)
+                else {
+                    return Ok(None);
+                };
+                let value = match op {
+                    BinOp::Add => left.checked_add(right),
+                    BinOp::Sub => left.checked_sub(right),
+                    BinOp::Mul => left.checked_mul(right),
+                    BinOp::Div if right == 0 => return Err("attempt to divide by zero".to_string()),
+                    BinOp::Mod if right == 0 => {
+                        return Err(
+                            "attempt to calculate the remainder with a divisor of zero".to_string()
+                        )
+                    }
+                    BinOp::Div => left.checked_div(right),
+                    BinOp::Mod => left.checked_rem(right),
+                    BinOp::BitwiseAnd => Some(left & right),
+                    BinOp::BitwiseOr => Some(left | right),
+                    BinOp::BitwiseXor => Some(left ^ right),
+                    BinOp::LeftShift => u32::try_from(right)
+                        .ok()
+                        .filter(|shift| *shift < 64)
+                        .and_then(|shift| left.checked_shl(shift)),
+                    BinOp::RightShift => u32::try_from(right)
+                        .ok()
+                        .filter(|shift| *shift < 64)
+                        .map(|shift| left >> shift),
+                    _ => return Ok(None),
+                };
+                match value {
+                    Some(value) => value,
+                    None => return Err("attempt to compute a value that overflows".to_string()),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+}
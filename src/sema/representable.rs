@@ -0,0 +1,154 @@
+// 可表示性检查
+// 结构体和枚举不能不经过间接（引用、指针、切片、`Box`、`Vec` 等运行时管理的值）包含自身，
+// 否则大小无限。从定义出发展开它按值包含的结构体和枚举（代入类型实参），
+// 回到出发的定义说明类型递归；每层换一组类型实参的递归由嵌套层数限制发现
+
+use crate::ast::*;
+use crate::builtins;
+use crate::span::Span;
+use std::collections::{BTreeMap, BTreeSet};
+
+// 按值嵌套的最大层数，与 layout.rs 相同
+const MAX_NESTING: usize = 64;
+
+/// 递归路径上的一步：`owner` 通过 `member` 按值包含了结构体或枚举 `ty`
+#[derive(Debug, Clone)]
+pub struct Containment {
+    pub owner: String,
+    pub member: String, // "field `next`" 或 "variant `Cons`"
+    pub ty: Type,
+    pub span: Span,
+}
+
+pub struct Representability<'a> {
+    structs: &'a BTreeMap<String, StructDef>,
+    enums: &'a BTreeMap<String, EnumDef>,
+}
+
+impl<'a> Representability<'a> {
+    pub fn new(
+        structs: &'a BTreeMap<String, StructDef>,
+        enums: &'a BTreeMap<String, EnumDef>,
+    ) -> Self {
+        Self { structs, enums }
+    }
+
+    /// 从名为 `name` 的定义出发回到它自身的路径，没有递归时为 None
+    pub fn recursion(&self, name: &str, generics: &Option<Generics>) -> Option<Vec<Containment>> {
+        let start = match generics {
+            Some(generics) => Type::Generic(
+                name.to_string(),
+                generics
+                    .param_names()
+                    .into_iter()
+                    .map(Type::Named)
+                    .collect(),
+            ),
+            None => Type::Named(name.to_string()),
+        };
+        let mut path = Vec::new();
+        let mut visited = BTreeSet::new();
+        self.expand(name, &start, &mut path, &mut visited)
+            .then_some(path)
+    }
+
+    fn expand(
+        &self,
+        start: &str,
+        ty: &Type,
+        path: &mut Vec<Containment>,
+        visited: &mut BTreeSet<String>,
+    ) -> bool {
+        for (member, member_ty, span) in self.members(ty) {
+            let mut contained = Vec::new();
+            self.contained(&member_ty, &mut contained);
+            for inner in contained {
+                path.push(Containment {
+                    owner: ty.to_string(),
+                    member: member.clone(),
+                    ty: inner.clone(),
+                    span,
+                });
+                let name = match &inner {
+                    Type::Named(name) | Type::Generic(name, _) => name,
+                    _ => unreachable!("only structs and enums are contained"),
+                };
+                if name == start {
+                    return true;
+                }
+                // 只保留第一步，说明从哪里开始无限展开
+                if path.len() > MAX_NESTING {
+                    path.truncate(1);
+                    return true;
+                }
+                if visited.insert(inner.to_string()) && self.expand(start, &inner, path, visited) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+
+    // 结构体的字段或枚举各变体的字段，已经代入类型实参
+    fn members(&self, ty: &Type) -> Vec<(String, Type, Span)> {
+        let (name, args) = match ty {
+            Type::Named(name) => (name, &[][..]),
+            Type::Generic(name, args) => (name, args.as_slice()),
+            _ => return Vec::new(),
+        };
+        let substitute = |generics: &Option<Generics>| -> BTreeMap<String, Type> {
+            let params = generics
+                .as_ref()
+                .map_or_else(Vec::new, |generics| generics.param_names());
+            params.into_iter().zip(args.iter().cloned()).collect()
+        };
+        if let Some(def) = self.structs.get(name) {
+            let args = substitute(&def.generics);
+            return def
+                .fields
+                .iter()
+                .map(|field| {
+                    let member = format!("field `{}`", field.name);
+                    (member, field.ty.substitute(&args), field.span)
+                })
+                .collect();
+        }
+        if let Some(def) = self.enums.get(name) {
+            let args = substitute(&def.generics);
+            return def
+                .variants
+                .iter()
+                .flat_map(|variant| {
+                    let member = format!("variant `{}`", variant.name);
+                    let args = &args;
+                    variant
+                        .fields
+                        .iter()
+                        .flatten()
+                        .map(move |ty| (member.clone(), ty.substitute(args), variant.span))
+                })
+                .collect();
+        }
+        Vec::new()
+    }
+
+    // 类型按值包含的结构体和枚举；引用、指针、切片、函数和运行时管理的值是间接的
+    fn contained(&self, ty: &Type, out: &mut Vec<Type>) {
+        match ty {
+            Type::Array(element, _) => self.contained(element, out),
+            Type::Tuple(types) => {
+                for ty in types {
+                    self.contained(ty, out);
+                }
+            }
+            Type::Named(name) | Type::Generic(name, _)
+                if !builtins::TYPES.contains(&name.as_str())
+                    && (self.structs.contains_key(name) || self.enums.contains_key(name)) =>
+            {
+                out.push(ty.clone())
+            }
+            _ => {}
+        }
+    }
+}
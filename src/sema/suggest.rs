@@ -0,0 +1,91 @@
+// 拼写建议 - 基于编辑距离查找相近的名字
+// 使用 Damerau-Levenshtein（最优字符串对齐）距离，相邻字符交换算作一次编辑，
+// 这样 `lenght` → `length` 的距离是 1 而不是 2
+
+/// 允许给出建议的最大编辑距离
+pub const MAX_DISTANCE: usize = 2;
+
+/// 计算两个字符串之间的 Damerau-Levenshtein 距离（OSA 变体）
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    if a.is_empty() {
+        return b.len();
+    }
+    if b.is_empty() {
+        return a.len();
+    }
+
+    // 只保留三行，避免分配完整矩阵
+    let mut prev_prev = vec![0usize; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0usize; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut best = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(prev_prev[j - 2] + 1);
+            }
+            curr[j] = best;
+        }
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// 在候选名字中查找与 `name` 最接近的一个
+///
+/// 距离相同时取字典序最小的候选，保证输出稳定。
+/// 编辑距离必须小于名字本身的长度，避免给 `x` 推荐 `y` 这类无意义的建议。
+pub fn find_similar<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let limit = MAX_DISTANCE.min(name.chars().count().saturating_sub(1));
+    let mut best: Option<(usize, &'a str)> = None;
+
+    for candidate in candidates {
+        if candidate == name {
+            continue;
+        }
+        let distance = edit_distance(name, candidate);
+        if distance > limit {
+            continue;
+        }
+        best = match best {
+            Some((d, c)) if d < distance || (d == distance && c <= candidate) => Some((d, c)),
+            _ => Some((distance, candidate)),
+        };
+    }
+
+    best.map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("length", "length"), 0);
+        assert_eq!(edit_distance("lenght", "length"), 1);
+        assert_eq!(edit_distance("count", "cont"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let names = ["width", "height", "length"];
+        assert_eq!(find_similar("lenght", names), Some("length"));
+        assert_eq!(find_similar("heigth", names), Some("height"));
+        assert_eq!(find_similar("depth", names), None);
+        assert_eq!(find_similar("x", ["y"]), None);
+    }
+}
@@ -0,0 +1,101 @@
+// 泛型参数的 trait 约束
+// 语言还没有 trait 声明和 impl 块，`fn max<T: Ord>` 中的约束只能是下面的内建 trait：
+//   Copy   整数、浮点数、bool、char、()、共享引用、指针和函数，以及元素都是 Copy 的数组和元组
+//   Clone  Copy 的类型、string、StringBuilder，以及元素都是 Clone 的复合类型
+//   Eq     整数、bool、char、string、()、引用和指针，以及元素都是 Eq 的复合类型；浮点数不是 Eq
+//   Ord    整数、bool、char 和 string，即 `<` 等比较运算符可以比较的类型
+//   Hash   同 Eq
+//   Debug  函数以外的内建类型，以及元素都是 Debug 的复合类型
+// 复合类型指数组、切片、元组、`Vec`、`Map`、`Option` 和 `Result`。
+// 结构体和枚举通过 derive 生成的函数实现可以派生的 trait（见 derive.rs）：存在 `point_eq` 时
+// `Point: Eq`，手写的同名函数也算；泛型类型还要满足这个函数的约束，如 `Pair<T: Eq>` 的 `pair_eq`。
+// Copy 和 Ord 不能派生，结构体和枚举不实现它们。
+// 泛型参数实现它自己的约束中的 trait；推断不出的类型（`_`）和 `!` 视为实现了所有 trait。
+// 没有 trait 声明也就没有 trait 方法，方法上的 `requires`/`ensures` 以及实现对它们的
+// 继承（只能放宽前置条件、加强后置条件）都还不支持
+
+use super::literals::int_range;
+use super::FnSig;
+use crate::ast::*;
+use crate::derive;
+use std::collections::BTreeMap;
+
+/// 可以作为约束的 trait
+pub const TRAITS: &[&str] = &["Copy", "Clone", "Eq", "Ord", "Hash", "Debug"];
+
+pub struct Traits<'a> {
+    pub generics: &'a [Vec<GenericParam>], // 当前可见的泛型参数和它们的约束
+    pub structs: &'a BTreeMap<String, StructDef>,
+    pub enums: &'a BTreeMap<String, EnumDef>,
+    pub functions: &'a BTreeMap<String, FnSig>,
+}
+
+impl Traits<'_> {
+    /// 类型是否实现了 trait；未知的 trait 已经在声明处报告过，视为实现
+    pub fn implements(&self, ty: &Type, name: &str) -> bool {
+        if !TRAITS.contains(&name) {
+            return true;
+        }
+        let all = |types: &[Type]| types.iter().all(|ty| self.implements(ty, name));
+        match ty {
+            Type::Infer | Type::Never => true,
+            Type::TypeOf(_) => self.implements(&ty.expand_typeof(), name),
+            Type::Named(param) if self.is_generic(param) => self.bounded(param, name),
+            _ if name == "Ord" => {
+                int_range(ty).is_some() || matches!(ty, Type::Bool | Type::Char | Type::String)
+            }
+            Type::F32 | Type::F64 => matches!(name, "Copy" | "Clone" | "Debug"),
+            Type::String => name != "Copy",
+            Type::Reference(inner, mutable) => match name {
+                "Copy" | "Clone" => !mutable,
+                _ => self.implements(inner, name),
+            },
+            Type::Pointer(..) => true,
+            Type::Function(..) => matches!(name, "Copy" | "Clone"),
+            Type::Array(inner, _) => self.implements(inner, name),
+            Type::Slice(inner) => name != "Copy" && name != "Clone" && self.implements(inner, name),
+            Type::Tuple(types) => all(types),
+            Type::Named(ty) if ty == "StringBuilder" => matches!(name, "Clone" | "Debug"),
+            Type::Named(ty) => self.derived(ty, &[], name),
+            Type::Generic(ty, args) => match ty.as_str() {
+                "Vec" | "Map" | "Option" | "Result" => name != "Copy" && all(args),
+                _ => self.derived(ty, args, name),
+            },
+            _ => true,
+        }
+    }
+
+    pub fn is_generic(&self, name: &str) -> bool {
+        self.generics
+            .iter()
+            .flatten()
+            .any(|param| param.name == name)
+    }
+
+    // 泛型参数的约束中有这个 trait；内层的同名参数遮蔽外层的
+    fn bounded(&self, param: &str, name: &str) -> bool {
+        self.generics
+            .iter()
+            .rev()
+            .flatten()
+            .find(|p| p.name == param)
+            .is_some_and(|p| p.bounds.iter().any(|bound| bound == name))
+    }
+
+    // 结构体和枚举通过 derive 生成的函数实现 trait，类型实参要满足这个函数的约束
+    fn derived(&self, ty: &str, args: &[Type], name: &str) -> bool {
+        if !self.structs.contains_key(ty) && !self.enums.contains_key(ty) {
+            return true; // 找不到的类型已经报告过
+        }
+        if !derive::TRAITS.contains(&name) {
+            return false;
+        }
+        let Some(sig) = self.functions.get(&derive::function_name(ty, name)) else {
+            return false;
+        };
+        sig.generics
+            .iter()
+            .zip(args)
+            .all(|(param, arg)| param.bounds.iter().all(|bound| self.implements(arg, bound)))
+    }
+}
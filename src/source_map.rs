@@ -0,0 +1,151 @@
+// 源文件表
+// 记录一次编译读入的文件：模块文件，以及 `include!`、`include_str!`、`include_bytes!` 读入的文件。
+// 这些宏中的相对路径相对于写着宏调用的文件所在的目录解析；`include!` 拼接进来的代码中
+// 再出现的路径相对于被拼接的文件。
+// 拼接的代码的记号位置移到一段独立的偏移范围：每个被拼接的文件占据 `INCLUDE_BASE` 之后
+// 互不重叠的一段，行号和列号仍是它在自己文件中的位置。之后任何阶段的诊断信息
+// 都可以按偏移找回所在的文件（`locate`），不需要在语法树中记录文件
+
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::token::Token;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 拼接的代码的偏移起点，远大于任何单个源文件的长度
+pub const INCLUDE_BASE: usize = 1 << (usize::BITS - 2);
+
+/// 源文件在表中的编号
+pub type FileId = usize;
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+    base: Option<usize>, // 作为源码拼接时分配的偏移起点
+}
+
+impl SourceFile {
+    pub fn new(path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> Self {
+        Self {
+            path: path.into(),
+            contents: contents.into(),
+            base: None,
+        }
+    }
+
+    /// 位置对应的源码；拼接进来的文件按它的偏移起点换算，不在文件中的位置为空串
+    pub fn text(&self, span: Span) -> &str {
+        let base = match self.base {
+            Some(base) if span.start >= base => base,
+            _ => 0,
+        };
+        self.contents
+            .get(span.start - base..span.end.saturating_sub(base))
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .unwrap_or("")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    next_base: usize,
+    max_file_size: Option<usize>, // `load` 读入的文件的大小上限
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记已经读入的文件，同一路径只登记一次
+    pub fn add(&mut self, path: PathBuf, contents: Vec<u8>) -> FileId {
+        if let Some(id) = self.find(&path) {
+            return id;
+        }
+        self.files.push(SourceFile::new(path, contents));
+        self.files.len() - 1
+    }
+
+    /// 读入文件，已经读过的文件直接返回它的编号
+    pub fn load(&mut self, path: &Path) -> io::Result<FileId> {
+        if let Some(id) = self.find(path) {
+            return Ok(id);
+        }
+        if let Some(max) = self.max_file_size {
+            let size = fs::metadata(path)?.len();
+            if size > max as u64 {
+                return Err(io::Error::other(format!(
+                    "the file has {} bytes, more than the limit of {}",
+                    size, max
+                )));
+            }
+        }
+        Ok(self.add(path.to_path_buf(), fs::read(path)?))
+    }
+
+    /// `load` 不读入超过 `max` 字节的文件
+    pub fn set_max_file_size(&mut self, max: Option<usize>) {
+        self.max_file_size = max;
+    }
+
+    fn find(&self, path: &Path) -> Option<FileId> {
+        self.files.iter().position(|file| file.path == path)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    /// `relative` 相对于文件 `from` 所在目录的路径，绝对路径保持不变
+    pub fn resolve(&self, from: FileId, relative: &str) -> PathBuf {
+        let dir = self.files[from].path.parent().unwrap_or(Path::new(""));
+        dir.join(relative)
+    }
+
+    /// 把文件 `id` 的记号移到它的拼接偏移范围；第一次拼接时分配这段范围
+    pub fn relocate(&mut self, id: FileId, tokens: &mut [Token]) {
+        let base = match self.files[id].base {
+            Some(base) => base,
+            None => {
+                let base = INCLUDE_BASE + self.next_base;
+                // 末尾的 Eof 记号位于文件长度处，范围之间留出一个字节
+                self.next_base += self.files[id].contents.len() + 1;
+                self.files[id].base = Some(base);
+                base
+            }
+        };
+        for token in tokens {
+            token.span.start += base;
+            token.span.end += base;
+        }
+    }
+
+    /// 位置所在的拼接文件，不在拼接的代码中时为 None
+    pub fn lookup(&self, span: Span) -> Option<&SourceFile> {
+        self.files.iter().find(|file| {
+            file.base
+                .is_some_and(|base| (base..=base + file.contents.len()).contains(&span.start))
+        })
+    }
+
+    /// 位于拼接的代码中的诊断信息改为指向被拼接的文件
+    pub fn locate(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| match self.lookup(diagnostic.span) {
+                Some(file) => {
+                    let path = file.path.display().to_string();
+                    diagnostic.with_file(path)
+                }
+                None => diagnostic,
+            })
+            .collect()
+    }
+}
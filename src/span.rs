@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -16,16 +16,18 @@ impl Span {
         }
     }
 
+    /// 覆盖两个范围的最小范围，行号和列号取靠前的一个的起点
     pub fn merge(&self, other: &Self) -> Self {
+        let first = if other.start < self.start {
+            other
+        } else {
+            self
+        };
         Self {
-            start: self.start.min(other.start),
-            end: other.end.max(other.end),
-            line: self.line.min(other.line),
-            column: if self.line < other.line {
-                self.column
-            } else {
-                self.column.min(other.column)
-            },
+            start: first.start,
+            end: self.end.max(other.end),
+            line: first.line,
+            column: first.column,
         }
     }
 }
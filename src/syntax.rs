@@ -0,0 +1,327 @@
+// 编辑器语法文件 - 由 token.rs 的关键字和运算符表生成（`contractus gen-syntax`）
+//
+// - TextMate（VS Code 等）：contractus.tmLanguage.json
+// - Tree-sitter（nvim 等）：grammar.js 和 queries/highlights.scm。语法只到记号一层，
+//   源文件是记号的序列，足够做高亮，不做结构化的编辑
+//
+// 作用域和捕获名按 highlight.rs 的高亮类别选取，名字的细分规则与 `--emit=tokens-json` 相同：
+// 紧跟 `!` 的是宏，紧跟 `(` 的是函数，全部大写的是常量，大写开头的是类型。
+// 新增关键字或运算符之后重新生成即可
+
+use crate::ast::write_json_string;
+use crate::grammar::CONTEXTUAL_KEYWORDS;
+use crate::highlight::HighlightClass;
+use crate::token::{TokenKind, BOOL_LITERALS, KEYWORDS, OPERATORS, PUNCTUATION, TYPE_KEYWORDS};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// 源文件的扩展名
+pub const FILE_EXTENSION: &str = "ctx";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxFormat {
+    TextMate,
+    TreeSitter,
+}
+
+impl FromStr for SyntaxFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tmlanguage" => Ok(SyntaxFormat::TextMate),
+            "tree-sitter" => Ok(SyntaxFormat::TreeSitter),
+            _ => Err(format!(
+                "invalid syntax format `{}`; expected `tmlanguage` or `tree-sitter`",
+                s
+            )),
+        }
+    }
+}
+
+/// 生成的文件：相对路径和内容，第一个是主文件
+pub fn generate(format: SyntaxFormat) -> Vec<(&'static str, String)> {
+    match format {
+        SyntaxFormat::TextMate => vec![("contractus.tmLanguage.json", tmlanguage())],
+        SyntaxFormat::TreeSitter => vec![
+            ("grammar.js", tree_sitter_grammar()),
+            ("queries/highlights.scm", tree_sitter_highlights()),
+        ],
+    }
+}
+
+fn spellings(table: &[(&'static str, TokenKind)]) -> Vec<&'static str> {
+    table.iter().map(|(text, _)| *text).collect()
+}
+
+// 较长的在前，`<<=` 不会被匹配为 `<<` 和 `=`
+fn longest_first(table: &[(&'static str, TokenKind)]) -> Vec<&'static str> {
+    let mut spellings = spellings(table);
+    spellings.sort_by_key(|text| std::cmp::Reverse(text.len()));
+    spellings
+}
+
+// `<<=|>>=|...`
+fn alternation(table: &[(&'static str, TokenKind)]) -> String {
+    let items: Vec<String> = longest_first(table)
+        .iter()
+        .map(|text| regex_escape(text))
+        .collect();
+    items.join("|")
+}
+
+fn regex_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// `\b(?:fn|let|...)\b`
+fn word_regex(words: &[&str]) -> String {
+    let words: Vec<String> = words.iter().map(|word| regex_escape(word)).collect();
+    format!("\\b(?:{})\\b", words.join("|"))
+}
+
+// TextMate 的作用域名，不含语言后缀
+fn scope(class: HighlightClass) -> &'static str {
+    match class {
+        HighlightClass::Keyword => "keyword.control",
+        HighlightClass::Type => "entity.name.type",
+        HighlightClass::Function => "entity.name.function",
+        HighlightClass::Macro => "entity.name.function.macro",
+        HighlightClass::Constant => "constant.other",
+        HighlightClass::Variable => "variable.other",
+        HighlightClass::Label => "entity.name.label",
+        HighlightClass::Number => "constant.numeric",
+        HighlightClass::String => "string.quoted.double",
+        HighlightClass::Char => "constant.character",
+        HighlightClass::Boolean => "constant.language.boolean",
+        HighlightClass::Operator => "keyword.operator",
+        HighlightClass::Punctuation => "punctuation.separator",
+        HighlightClass::Comment => "comment",
+        HighlightClass::Error => "invalid.illegal",
+    }
+}
+
+/// TextMate 语法（JSON）
+pub fn tmlanguage() -> String {
+    let ident = "[A-Za-z_][A-Za-z0-9_]*";
+    // 先匹配的在前：注释和字面量中的内容不再细分，字符字面量先于标签
+    let patterns = [
+        (
+            HighlightClass::Char,
+            "'(?:[^'\\\\]|\\\\u\\{[0-9a-fA-F_]*\\}|\\\\.)'".to_string(),
+        ),
+        (HighlightClass::Label, format!("'{}", ident)),
+        (
+            HighlightClass::Number,
+            "\\b(?:0x[0-9a-fA-F_]+|0b[01_]+|[0-9][0-9_]*)(?:_?[iu](?:8|16|32|64|size))?\\b"
+                .to_string(),
+        ),
+        (
+            HighlightClass::Boolean,
+            word_regex(&spellings(BOOL_LITERALS)),
+        ),
+        // 名为 `invariant` 的字段后面是冒号
+        (
+            HighlightClass::Keyword,
+            format!("{}(?!\\s*:[^:])", word_regex(CONTEXTUAL_KEYWORDS)),
+        ),
+        (HighlightClass::Keyword, word_regex(&spellings(KEYWORDS))),
+        (HighlightClass::Type, word_regex(&spellings(TYPE_KEYWORDS))),
+        (HighlightClass::Macro, format!("\\b{}(?=!(?!=))", ident)),
+        (HighlightClass::Function, format!("\\b{}(?=\\s*\\()", ident)),
+        (
+            HighlightClass::Constant,
+            "\\b[A-Z][A-Z0-9_]+\\b".to_string(),
+        ),
+        (HighlightClass::Type, "\\b[A-Z][A-Za-z0-9_]*\\b".to_string()),
+        (HighlightClass::Operator, alternation(OPERATORS)),
+        (HighlightClass::Punctuation, alternation(PUNCTUATION)),
+    ];
+
+    let mut out =
+        String::from("{\n  \"name\": \"Contractus\",\n  \"scopeName\": \"source.contractus\",\n");
+    let _ = writeln!(out, "  \"fileTypes\": [\"{}\"],", FILE_EXTENSION);
+    out.push_str("  \"comment\": \"Generated by `contractus gen-syntax --format=tmlanguage`; do not edit.\",\n");
+    out.push_str("  \"patterns\": [\n");
+    out.push_str(
+        "    {\"name\": \"comment.line.double-slash.contractus\", \"match\": \"//.*$\"},\n",
+    );
+    out.push_str("    {\"name\": \"comment.block.contractus\", \"begin\": \"/\\\\*\", \"end\": \"\\\\*/\"},\n");
+    out.push_str("    {\"name\": \"string.quoted.double.contractus\", \"begin\": \"\\\"\", \"end\": \"\\\"\", ");
+    out.push_str("\"patterns\": [{\"name\": \"constant.character.escape.contractus\", \"match\": \"\\\\\\\\.\"}]}");
+    for (class, regex) in &patterns {
+        let _ = write!(
+            out,
+            ",\n    {{\"name\": \"{}.contractus\", \"match\": ",
+            scope(*class)
+        );
+        write_json_string(&mut out, regex);
+        out.push('}');
+    }
+    out.push_str("\n  ]\n}\n");
+    out
+}
+
+// JavaScript 的单引号字符串
+fn js_string(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// `choice('a', 'b', ...)`，每项一行
+fn js_choice(items: &[&str]) -> String {
+    let mut out = String::from("choice(\n");
+    for item in items {
+        let _ = writeln!(out, "      {},", js_string(item));
+    }
+    out.push_str("    )");
+    out
+}
+
+/// Tree-sitter 的 grammar.js
+pub fn tree_sitter_grammar() -> String {
+    let mut out = String::from(
+        "// Generated by `contractus gen-syntax --format=tree-sitter`; do not edit.
+// The grammar stops at tokens: a source file is a sequence of them, which is enough for highlighting.
+
+module.exports = grammar({
+  name: 'contractus',
+
+  extras: $ => [/\\s/],
+
+  word: $ => $.identifier,
+
+  rules: {
+    source_file: $ => repeat($._token),
+
+    _token: $ => choice(
+      $.line_comment,
+      $.block_comment,
+      $.string,
+      $.char,
+      $.label,
+      $.number,
+      $.boolean,
+      $.keyword,
+      $.primitive_type,
+      $.identifier,
+      $.operator,
+      $.punctuation,
+    ),
+
+    line_comment: $ => token(seq('//', /.*/)),
+
+    block_comment: $ => token(seq('/*', /[^*]*\\*+([^/*][^*]*\\*+)*/, '/')),
+
+    string: $ => token(seq('\"', repeat(choice(/[^\"\\\\]/, /\\\\./)), '\"')),
+
+    char: $ => token(seq('\\'', choice(/[^'\\\\]/, /\\\\u\\{[0-9a-fA-F_]*\\}/, /\\\\./), '\\'')),
+
+    label: $ => /'[A-Za-z_][A-Za-z0-9_]*/,
+
+    number: $ => /(0x[0-9a-fA-F_]+|0b[01_]+|[0-9][0-9_]*)(_?[iu](8|16|32|64|size))?/,
+
+    identifier: $ => /[A-Za-z_][A-Za-z0-9_]*/,
+",
+    );
+    let tables = [
+        ("boolean", spellings(BOOL_LITERALS)),
+        ("keyword", spellings(KEYWORDS)),
+        ("primitive_type", spellings(TYPE_KEYWORDS)),
+        ("operator", longest_first(OPERATORS)),
+        ("punctuation", spellings(PUNCTUATION)),
+    ];
+    for (name, items) in &tables {
+        let _ = write!(out, "\n    {}: $ => {},\n", name, js_choice(items));
+    }
+    out.push_str("  },\n});\n");
+    out
+}
+
+// Tree-sitter 的捕获名
+fn capture(class: HighlightClass) -> &'static str {
+    match class {
+        HighlightClass::Keyword => "keyword",
+        HighlightClass::Type => "type",
+        HighlightClass::Function => "function",
+        HighlightClass::Macro => "function.macro",
+        HighlightClass::Constant => "constant",
+        HighlightClass::Variable => "variable",
+        HighlightClass::Label => "label",
+        HighlightClass::Number => "number",
+        HighlightClass::String => "string",
+        HighlightClass::Char => "character",
+        HighlightClass::Boolean => "boolean",
+        HighlightClass::Operator => "operator",
+        HighlightClass::Punctuation => "punctuation.delimiter",
+        HighlightClass::Comment => "comment",
+        HighlightClass::Error => "error",
+    }
+}
+
+/// Tree-sitter 的 queries/highlights.scm
+pub fn tree_sitter_highlights() -> String {
+    let mut out = String::from(
+        "; Generated by `contractus gen-syntax --format=tree-sitter`; do not edit.\n\n",
+    );
+    let nodes = [
+        ("line_comment", HighlightClass::Comment),
+        ("block_comment", HighlightClass::Comment),
+        ("string", HighlightClass::String),
+        ("char", HighlightClass::Char),
+        ("label", HighlightClass::Label),
+        ("number", HighlightClass::Number),
+        ("boolean", HighlightClass::Boolean),
+        ("keyword", HighlightClass::Keyword),
+        ("operator", HighlightClass::Operator),
+        ("punctuation", HighlightClass::Punctuation),
+    ];
+    for (node, class) in nodes {
+        let _ = writeln!(out, "({}) @{}", node, capture(class));
+    }
+    out.push_str("(primitive_type) @type.builtin\n\n");
+
+    // 名字按后面的记号和大小写细分，各条规则互不重叠
+    let keywords: Vec<String> = CONTEXTUAL_KEYWORDS
+        .iter()
+        .map(|keyword| format!("\"{}\"", keyword))
+        .collect();
+    let keyword = capture(HighlightClass::Keyword);
+    let _ = writeln!(
+        out,
+        "((identifier) @{} (#any-of? @{} {}))",
+        keyword,
+        keyword,
+        keywords.join(" ")
+    );
+    let macro_ = capture(HighlightClass::Macro);
+    let _ = writeln!(
+        out,
+        "(source_file (identifier) @{} . (operator \"!\"))",
+        macro_
+    );
+    let function = capture(HighlightClass::Function);
+    let _ = writeln!(
+        out,
+        "(source_file (identifier) @{} . (punctuation \"(\"))",
+        function
+    );
+    for (class, regex) in [
+        (HighlightClass::Constant, "^[A-Z][A-Z0-9_]+$"),
+        (HighlightClass::Type, "^[A-Z].*[a-z]"),
+    ] {
+        let name = capture(class);
+        let _ = writeln!(
+            out,
+            "((identifier) @{} (#match? @{} \"{}\"))",
+            name, name, regex
+        );
+    }
+    out
+}
@@ -0,0 +1,742 @@
+// 测试工具 - 随机生成语法树，检查还原的源码重新解析后得到同样的结构
+//
+// `Arbitrary` 为语法树的节点生成随机的值，只生成解析器能够得到的形状：负数是取负运算而不是
+// 负的字面量，条件不是赋值，其中也没有 `return` 等跳转。名字和类型是随机组合的，
+// 不保证通过语义分析。生成是确定性的，同样的种子得到同样的语法树，`check` 在失败时
+// 报告种子以便重现。宏调用和 `macro_rules!` 不在生成的范围内
+//
+// 格式化器、编辑器插件等下游工具也可以用它生成输入
+
+use crate::ast::*;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::span::Span;
+
+// 生成的节点都没有位置
+const NOWHERE: Span = Span {
+    start: 0,
+    end: 0,
+    line: 1,
+    column: 1,
+};
+
+const VALUES: &[&str] = &["a", "b", "x", "y", "count", "total", "items"];
+const TYPES: &[&str] = &["Point", "Pair", "Shape", "Tree", "T", "U"];
+const VARIANTS: &[&str] = &["Some", "None", "Circle", "Square", "Leaf"];
+const FIELDS: &[&str] = &["x", "y", "left", "right", "len"];
+const FUNCTIONS: &[&str] = &["main", "add", "area", "get", "push", "parse"];
+const CONSTANTS: &[&str] = &["LIMIT", "ORIGIN", "COUNT"];
+const MODULES: &[&str] = &["math", "io", "shapes", "util"];
+const LABELS: &[&str] = &["outer", "inner", "search"];
+const PARAMS: &[&str] = &["T", "U", "V"];
+const BOUNDS: &[&str] = &["Copy", "Eq", "Ord", "Debug"];
+const ATTRIBUTES: &[&str] = &["inline", "test", "bench", "derive", "repr"];
+const ATTRIBUTE_ARGS: &[&str] = &["Debug", "Clone", "Eq", "u8", "i32"];
+const CHARS: &[char] = &[
+    'a', 'Z', '0', ' ', '_', '{', '}', '"', '\'', '\\', '\n', '\r', '\t', '\0',
+];
+
+/// 随机数的来源和当前的嵌套深度
+///
+/// 嵌套超过 `size` 层之后只生成字面量、名字等不再嵌套的节点，所以生成总会结束
+pub struct Gen {
+    state: u64,
+    size: usize,
+    depth: usize,
+    in_condition: bool, // 正在生成 `if`、`while` 等的条件
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Self {
+        Self::with_size(seed, 4)
+    }
+
+    pub fn with_size(seed: u64, size: usize) -> Self {
+        Gen {
+            state: seed,
+            size,
+            depth: 0,
+            in_condition: false,
+        }
+    }
+
+    /// 下一个随机数（SplitMix64）
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// `0..n` 中的一个数，`n` 不能是零
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// 零到 `max` 个元素
+    pub fn list<T>(&mut self, max: usize, mut item: impl FnMut(&mut Gen) -> T) -> Vec<T> {
+        let len = self.below(max + 1);
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    pub fn optional<T>(&mut self, item: impl FnOnce(&mut Gen) -> T) -> Option<T> {
+        match self.one_in(2) {
+            true => Some(item(self)),
+            false => None,
+        }
+    }
+
+    fn name(&mut self, names: &[&str]) -> String {
+        self.choose(names).to_string()
+    }
+
+    fn leaf(&self) -> bool {
+        self.depth >= self.size
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Gen) -> T) -> T {
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+        value
+    }
+}
+
+/// 可以随机生成的值
+pub trait Arbitrary: Sized {
+    fn arbitrary(gen: &mut Gen) -> Self;
+}
+
+/// 还原源码（`to_source`）之后重新解析，检查得到结构相同的语法树
+///
+/// 只做语法分析，不展开宏和 `#[derive]`；位置和契约的源码不参与比较。
+/// 不能解析或结构不同时返回说明，其中带有还原的源码
+pub fn round_trip(program: &Program) -> Result<(), String> {
+    let source = program.to_source();
+    let tokens = Lexer::new(&source)
+        .tokenize()
+        .map_err(|errors| format!("the printed source does not lex: {}\n{}", errors[0], source))?;
+    let reparsed = Parser::new(tokens).parse().map_err(|errors| {
+        format!(
+            "the printed source does not parse: {}\n{}",
+            errors[0], source
+        )
+    })?;
+    let expected = program.to_json_without_spans();
+    let actual = reparsed.to_json_without_spans();
+    if expected == actual {
+        return Ok(());
+    }
+    let (line, (old, new)) = expected
+        .lines()
+        .zip(actual.lines())
+        .enumerate()
+        .find(|(_, (old, new))| old != new)
+        .unwrap_or((0, ("", "")));
+    Err(format!(
+        "the printed source parses to a different tree; line {} of the JSON changed from `{}` to `{}`\n{}",
+        line + 1,
+        old.trim(),
+        new.trim(),
+        source
+    ))
+}
+
+/// 对 `cases` 个随机生成的值检查性质，失败时 panic 并报告种子
+///
+/// 第 i 个值用种子 `base + i` 生成，`base` 从环境变量 `CONTRACTUS_SEED` 读取，默认为零；
+/// 把报告的种子设为 `CONTRACTUS_SEED` 重新运行，第一个值就是失败的那个
+pub fn check<T: Arbitrary>(cases: u64, property: impl Fn(&T) -> Result<(), String>) {
+    let base = std::env::var("CONTRACTUS_SEED")
+        .ok()
+        .and_then(|seed| seed.parse::<u64>().ok())
+        .unwrap_or(0);
+    for seed in base..base.saturating_add(cases) {
+        let value = T::arbitrary(&mut Gen::new(seed));
+        if let Err(message) = property(&value) {
+            panic!(
+                "property failed for seed {} (rerun with CONTRACTUS_SEED={}):\n{}",
+                seed, seed, message
+            );
+        }
+    }
+}
+
+// ---- 条目 ----
+
+impl Arbitrary for Program {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        let count = 1 + gen.below(4);
+        Program {
+            attributes: Vec::new(),
+            items: (0..count).map(|_| Item::arbitrary(gen)).collect(),
+            span: NOWHERE,
+        }
+    }
+}
+
+impl Arbitrary for Item {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        match gen.below(10) {
+            0..=3 => Item::Function(function(gen)),
+            4 => Item::Struct(StructDef {
+                attributes: attributes(gen),
+                visibility: visibility(gen),
+                name: gen.name(TYPES),
+                generics: gen.optional(generics),
+                fields: gen.list(3, |gen| Field {
+                    visibility: visibility(gen),
+                    name: gen.name(FIELDS),
+                    ty: Type::arbitrary(gen),
+                    span: NOWHERE,
+                }),
+                invariants: gen.list(1, |gen| contract(gen, ContractKind::Invariant)),
+                span: NOWHERE,
+            }),
+            5 => Item::Enum(EnumDef {
+                attributes: attributes(gen),
+                visibility: visibility(gen),
+                name: gen.name(TYPES),
+                generics: gen.optional(generics),
+                variants: (0..1 + gen.below(3))
+                    .map(|_| EnumVariant {
+                        name: gen.name(VARIANTS),
+                        fields: gen.optional(|gen| gen.list(2, Type::arbitrary)),
+                        span: NOWHERE,
+                    })
+                    .collect(),
+                span: NOWHERE,
+            }),
+            6 => Item::Const(ConstDef {
+                visibility: visibility(gen),
+                name: gen.name(CONSTANTS),
+                ty: Type::arbitrary(gen),
+                value: Expr::arbitrary(gen),
+                span: NOWHERE,
+            }),
+            7 => Item::Static(StaticDef {
+                visibility: visibility(gen),
+                mutable: gen.one_in(2),
+                name: gen.name(CONSTANTS),
+                ty: Type::arbitrary(gen),
+                value: Expr::arbitrary(gen),
+                span: NOWHERE,
+            }),
+            8 => Item::Import(ImportStmt {
+                path: (0..1 + gen.below(3)).map(|_| gen.name(MODULES)).collect(),
+                alias: gen.optional(|gen| gen.name(MODULES)),
+                span: NOWHERE,
+            }),
+            _ => Item::Export(ExportStmt {
+                items: (0..1 + gen.below(3))
+                    .map(|_| {
+                        let mut path = gen.list(1, |gen| gen.name(MODULES));
+                        path.push(gen.name(FUNCTIONS));
+                        ExportItem {
+                            path,
+                            span: NOWHERE,
+                        }
+                    })
+                    .collect(),
+                span: NOWHERE,
+            }),
+        }
+    }
+}
+
+fn function(gen: &mut Gen) -> Function {
+    Function {
+        attributes: attributes(gen),
+        visibility: visibility(gen),
+        name: gen.name(FUNCTIONS),
+        generics: gen.optional(generics),
+        params: gen.list(3, |gen| Parameter {
+            pattern: Pattern::arbitrary(gen),
+            ty: Type::arbitrary(gen),
+            span: NOWHERE,
+        }),
+        return_type: gen.optional(Type::arbitrary),
+        contracts: gen.list(2, |gen| {
+            let kind = *gen.choose(&[ContractKind::Requires, ContractKind::Ensures]);
+            contract(gen, kind)
+        }),
+        body: Block::arbitrary(gen),
+        operator: None,
+        asynchronous: false,
+        pure: gen.one_in(4),
+        span: NOWHERE,
+    }
+}
+
+fn contract(gen: &mut Gen, kind: ContractKind) -> Contract {
+    Contract {
+        kind,
+        condition: condition(gen),
+        text: String::new(),
+        span: NOWHERE,
+    }
+}
+
+fn attributes(gen: &mut Gen) -> Vec<Attribute> {
+    gen.list(2, |gen| Attribute {
+        name: gen.name(ATTRIBUTES),
+        args: gen.list(2, |gen| gen.name(ATTRIBUTE_ARGS)),
+        span: NOWHERE,
+    })
+}
+
+fn visibility(gen: &mut Gen) -> Visibility {
+    match gen.one_in(3) {
+        true => Visibility::Public,
+        false => Visibility::Private,
+    }
+}
+
+fn generics(gen: &mut Gen) -> Generics {
+    Generics {
+        params: (0..1 + gen.below(2))
+            .map(|_| GenericParam {
+                name: gen.name(PARAMS),
+                bounds: gen.list(2, |gen| gen.name(BOUNDS)),
+                span: NOWHERE,
+            })
+            .collect(),
+        span: NOWHERE,
+    }
+}
+
+// ---- 语句 ----
+
+impl Arbitrary for Block {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        // 代码块中又可以出现跳转和结构体字面量
+        let in_condition = std::mem::replace(&mut gen.in_condition, false);
+        let mut statements = gen.list(3, Statement::arbitrary);
+        if gen.one_in(2) {
+            statements.push(Statement::Expr(ExprStmt {
+                expr: Expr::arbitrary(gen),
+                semicolon: false,
+                span: NOWHERE,
+            }));
+        }
+        gen.in_condition = in_condition;
+        Block {
+            statements,
+            span: NOWHERE,
+        }
+    }
+}
+
+impl Arbitrary for Statement {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        if gen.leaf() {
+            return Statement::Expr(ExprStmt {
+                expr: leaf_expr(gen),
+                semicolon: true,
+                span: NOWHERE,
+            });
+        }
+        gen.nested(|gen| match gen.below(12) {
+            0..=2 => {
+                let init = gen.optional(Expr::arbitrary);
+                let else_block = match init.is_some() && gen.one_in(4) {
+                    true => Some(Block::arbitrary(gen)),
+                    false => None,
+                };
+                Statement::Let(LetStmt {
+                    pattern: Pattern::arbitrary(gen),
+                    ty: gen.optional(Type::arbitrary),
+                    init,
+                    else_block,
+                    mutable: gen.one_in(2),
+                    span: NOWHERE,
+                })
+            }
+            3 | 4 => Statement::Expr(ExprStmt {
+                expr: Expr::arbitrary(gen),
+                semicolon: true,
+                span: NOWHERE,
+            }),
+            5 => Statement::Return(ReturnStmt {
+                expr: gen.optional(Expr::arbitrary),
+                span: NOWHERE,
+            }),
+            6 => Statement::If(if_stmt(gen)),
+            7 => Statement::While(WhileStmt {
+                label: label(gen),
+                cond: condition(gen),
+                invariants: gen.list(1, |gen| contract(gen, ContractKind::LoopInvariant)),
+                body: Block::arbitrary(gen),
+                span: NOWHERE,
+            }),
+            8 => Statement::For(ForStmt {
+                label: label(gen),
+                pattern: Pattern::arbitrary(gen),
+                iterable: condition(gen),
+                invariants: gen.list(1, |gen| contract(gen, ContractKind::LoopInvariant)),
+                body: Block::arbitrary(gen),
+                span: NOWHERE,
+            }),
+            9 => Statement::Match(MatchStmt {
+                expr: condition(gen),
+                arms: arms(gen),
+                span: NOWHERE,
+            }),
+            10 => match gen.one_in(2) {
+                true => Statement::Break(BreakStmt {
+                    label: label(gen),
+                    expr: gen.optional(Expr::arbitrary),
+                    span: NOWHERE,
+                }),
+                false => Statement::Continue(ContinueStmt {
+                    label: label(gen),
+                    span: NOWHERE,
+                }),
+            },
+            _ => Statement::Block(Block::arbitrary(gen)),
+        })
+    }
+}
+
+fn if_stmt(gen: &mut Gen) -> IfStmt {
+    IfStmt {
+        cond: condition(gen),
+        then_block: Block::arbitrary(gen),
+        else_block: else_branch(gen),
+        span: NOWHERE,
+    }
+}
+
+fn else_branch(gen: &mut Gen) -> Option<ElseBranch> {
+    match gen.below(3) {
+        0 => None,
+        1 if !gen.leaf() => Some(ElseBranch::If(Box::new(gen.nested(if_stmt)))),
+        _ => Some(ElseBranch::Block(Block::arbitrary(gen))),
+    }
+}
+
+fn label(gen: &mut Gen) -> Option<String> {
+    match gen.one_in(3) {
+        true => Some(gen.name(LABELS)),
+        false => None,
+    }
+}
+
+fn arms(gen: &mut Gen) -> Vec<MatchArm> {
+    (0..1 + gen.below(3))
+        .map(|_| MatchArm {
+            pattern: Pattern::arbitrary(gen),
+            guard: match gen.one_in(3) {
+                true => Some(condition(gen)),
+                false => None,
+            },
+            body: Expr::arbitrary(gen),
+            span: NOWHERE,
+        })
+        .collect()
+}
+
+// `if`、`while`、`for`、`match` 和契约的条件：不是赋值，其中没有跳转，
+// 否则 `return {` 会把后面的代码块当作返回值
+fn condition(gen: &mut Gen) -> Expr {
+    let in_condition = std::mem::replace(&mut gen.in_condition, true);
+    let expr = loop {
+        let expr = Expr::arbitrary(gen);
+        if !matches!(expr, Expr::Assign(..)) {
+            break expr;
+        }
+    };
+    gen.in_condition = in_condition;
+    expr
+}
+
+// ---- 表达式 ----
+
+impl Arbitrary for Expr {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        if gen.leaf() || gen.one_in(3) {
+            return leaf_expr(gen);
+        }
+        gen.nested(compound_expr)
+    }
+}
+
+fn leaf_expr(gen: &mut Gen) -> Expr {
+    match gen.below(5) {
+        0 | 1 => Expr::Literal(Literal::arbitrary(gen), NOWHERE),
+        2 | 3 => Expr::Ident(gen.name(VALUES), NOWHERE),
+        _ => Expr::Path(vec![gen.name(TYPES), gen.name(VARIANTS)], NOWHERE),
+    }
+}
+
+fn compound_expr(gen: &mut Gen) -> Expr {
+    let boxed = |gen: &mut Gen| Box::new(Expr::arbitrary(gen));
+    // 跳转排在最后，条件中不生成
+    let choices = if gen.in_condition { 24 } else { 27 };
+    match gen.below(choices) {
+        0..=2 => Expr::Binary(
+            BinOp::arbitrary(gen),
+            boxed(gen),
+            boxed(gen),
+            NOWHERE,
+            OperandType::default(),
+        ),
+        3 => {
+            let op = gen
+                .choose(&[
+                    UnOp::Neg,
+                    UnOp::LogicalNot,
+                    UnOp::BitwiseNot,
+                    UnOp::Deref,
+                    UnOp::Ref,
+                    UnOp::RefMut,
+                ])
+                .clone();
+            Expr::Unary(op, boxed(gen), NOWHERE, OperandType::default())
+        }
+        4 => Expr::Call(
+            boxed(gen),
+            gen.list(3, Expr::arbitrary),
+            NOWHERE,
+            OperandType::default(),
+        ),
+        5 => Expr::MethodCall(
+            boxed(gen),
+            gen.name(FUNCTIONS),
+            gen.list(2, Expr::arbitrary),
+            NOWHERE,
+            OperandType::default(),
+        ),
+        6 if gen.one_in(3) => Expr::TupleIndex(boxed(gen), gen.below(3), NOWHERE),
+        6 => Expr::FieldAccess(boxed(gen), gen.name(FIELDS), NOWHERE),
+        7 => Expr::IndexAccess(boxed(gen), boxed(gen), NOWHERE),
+        8 => Expr::StructLit(
+            gen.name(TYPES),
+            gen.list(3, |gen| (gen.name(FIELDS), Expr::arbitrary(gen))),
+            NOWHERE,
+        ),
+        9 => Expr::ArrayLit(gen.list(3, Expr::arbitrary), NOWHERE),
+        10 => Expr::TupleLit(gen.list(3, Expr::arbitrary), NOWHERE),
+        11 => Expr::Range(Some(boxed(gen)), Some(boxed(gen)), gen.one_in(2), NOWHERE),
+        12 => Expr::Assign(boxed(gen), boxed(gen), NOWHERE),
+        13 => {
+            let op = gen
+                .choose(&[
+                    BinOp::Add,
+                    BinOp::Sub,
+                    BinOp::Mul,
+                    BinOp::Div,
+                    BinOp::Mod,
+                    BinOp::BitwiseAnd,
+                    BinOp::BitwiseOr,
+                    BinOp::BitwiseXor,
+                    BinOp::LeftShift,
+                    BinOp::RightShift,
+                ])
+                .clone();
+            Expr::CompoundAssign(op, boxed(gen), boxed(gen), NOWHERE, OperandType::default())
+        }
+        14 => Expr::Block(Block::arbitrary(gen), NOWHERE),
+        15 => Expr::Unsafe(Block::arbitrary(gen), NOWHERE),
+        16 => Expr::If(
+            Box::new(condition(gen)),
+            Block::arbitrary(gen),
+            else_branch(gen),
+            NOWHERE,
+        ),
+        17 => Expr::Match(Box::new(condition(gen)), arms(gen), NOWHERE),
+        18 => Expr::While(
+            label(gen),
+            Box::new(condition(gen)),
+            Block::arbitrary(gen),
+            NOWHERE,
+        ),
+        19 => Expr::For(
+            label(gen),
+            Pattern::arbitrary(gen),
+            Box::new(condition(gen)),
+            Block::arbitrary(gen),
+            NOWHERE,
+        ),
+        20 => {
+            // 闭包的参数不能是顶层的或模式，`|a | b|` 的第二个 `|` 结束参数列表
+            let params = gen.list(2, |gen| Parameter {
+                pattern: single_pattern(gen),
+                ty: match gen.one_in(2) {
+                    true => Type::arbitrary(gen),
+                    false => Type::Infer,
+                },
+                span: NOWHERE,
+            });
+            let return_type = match gen.one_in(4) {
+                true => Some(Type::arbitrary(gen)),
+                false => None,
+            };
+            Expr::Closure(params, return_type, boxed(gen), NOWHERE)
+        }
+        // `x as T < y` 的 `<` 会被当作泛型实参，只转换为基础类型
+        21 => Expr::Cast(boxed(gen), primitive(gen), NOWHERE),
+        22 => Expr::Try(boxed(gen), NOWHERE),
+        23 => {
+            let path = match gen.one_in(2) {
+                true => Expr::Ident(gen.name(FUNCTIONS), NOWHERE),
+                false => Expr::Path(vec![gen.name(MODULES), gen.name(FUNCTIONS)], NOWHERE),
+            };
+            let args = (0..1 + gen.below(2))
+                .map(|_| Type::arbitrary(gen))
+                .collect();
+            Expr::Turbofish(Box::new(path), args, NOWHERE)
+        }
+        24 => Expr::Break(label(gen), gen.optional(boxed), NOWHERE),
+        25 => Expr::Continue(label(gen), NOWHERE),
+        _ => Expr::Return(gen.optional(boxed), NOWHERE),
+    }
+}
+
+impl Arbitrary for Literal {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        match gen.below(6) {
+            0 | 1 => Literal::Int(
+                match gen.one_in(8) {
+                    // 没有后缀的整数字面量默认是 i32
+                    true => (gen.next_u64() >> 33) as i64,
+                    false => gen.below(1000) as i64,
+                },
+                None,
+            ),
+            2 => Literal::Bool(gen.one_in(2)),
+            3 => Literal::Char(*gen.choose(CHARS)),
+            _ => Literal::String(gen.list(6, |gen| *gen.choose(CHARS)).into_iter().collect()),
+        }
+    }
+}
+
+impl Arbitrary for BinOp {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        gen.choose(&[
+            BinOp::Add,
+            BinOp::Sub,
+            BinOp::Mul,
+            BinOp::Div,
+            BinOp::Mod,
+            BinOp::Equal,
+            BinOp::NotEqual,
+            BinOp::Less,
+            BinOp::Greater,
+            BinOp::LessEqual,
+            BinOp::GreaterEqual,
+            BinOp::LogicalAnd,
+            BinOp::LogicalOr,
+            BinOp::BitwiseAnd,
+            BinOp::BitwiseOr,
+            BinOp::BitwiseXor,
+            BinOp::LeftShift,
+            BinOp::RightShift,
+        ])
+        .clone()
+    }
+}
+
+// ---- 类型 ----
+
+impl Arbitrary for Type {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        if gen.leaf() || gen.one_in(2) {
+            return match gen.below(4) {
+                0 | 1 => primitive(gen),
+                2 => Type::Named(gen.name(TYPES)),
+                _ => gen.choose(&[Type::Unit, Type::Never, Type::Infer]).clone(),
+            };
+        }
+        gen.nested(|gen| {
+            let boxed = |gen: &mut Gen| Box::new(Type::arbitrary(gen));
+            match gen.below(7) {
+                0 => Type::Array(boxed(gen), gen.below(16)),
+                1 => Type::Slice(boxed(gen)),
+                // 没有元素的元组写作 `()`，是单元类型
+                2 => Type::Tuple(
+                    (0..1 + gen.below(3))
+                        .map(|_| Type::arbitrary(gen))
+                        .collect(),
+                ),
+                3 => Type::Pointer(boxed(gen), gen.one_in(2)),
+                4 => Type::Reference(boxed(gen), gen.one_in(2)),
+                5 => Type::Generic(
+                    gen.name(TYPES),
+                    (0..1 + gen.below(2))
+                        .map(|_| Type::arbitrary(gen))
+                        .collect(),
+                ),
+                _ => Type::Function(gen.list(2, Type::arbitrary), boxed(gen), gen.one_in(2)),
+            }
+        })
+    }
+}
+
+fn primitive(gen: &mut Gen) -> Type {
+    gen.choose(&[
+        Type::I8,
+        Type::I16,
+        Type::I32,
+        Type::I64,
+        Type::U8,
+        Type::U16,
+        Type::U32,
+        Type::U64,
+        Type::Usize,
+        Type::Isize,
+        Type::F32,
+        Type::F64,
+        Type::Bool,
+        Type::Char,
+        Type::String,
+    ])
+    .clone()
+}
+
+// ---- 模式 ----
+
+impl Arbitrary for Pattern {
+    fn arbitrary(gen: &mut Gen) -> Self {
+        if !gen.leaf() && gen.one_in(6) {
+            // 或模式的分支不再是或模式，`a | b | c` 解析为一层
+            return gen.nested(|gen| {
+                Pattern::Or((0..2 + gen.below(2)).map(|_| single_pattern(gen)).collect())
+            });
+        }
+        single_pattern(gen)
+    }
+}
+
+fn single_pattern(gen: &mut Gen) -> Pattern {
+    if gen.leaf() || gen.one_in(2) {
+        return match gen.below(5) {
+            0 | 1 => Pattern::Ident(gen.name(VALUES)),
+            2 => Pattern::Wildcard,
+            // 模式中的负数是字面量
+            _ => match Literal::arbitrary(gen) {
+                Literal::Int(n, suffix) if gen.one_in(2) => {
+                    Pattern::Literal(Literal::Int(-n, suffix))
+                }
+                literal => Pattern::Literal(literal),
+            },
+        };
+    }
+    gen.nested(|gen| match gen.below(3) {
+        0 => Pattern::Tuple(gen.list(3, Pattern::arbitrary)),
+        1 => Pattern::TupleStruct(gen.name(VARIANTS), gen.list(3, Pattern::arbitrary)),
+        _ => Pattern::Struct(
+            gen.name(TYPES),
+            gen.list(3, |gen| (gen.name(FIELDS), Pattern::arbitrary(gen))),
+        ),
+    })
+}
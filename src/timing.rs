@@ -0,0 +1,245 @@
+// 编译器自身的计时和内存统计（`-Ztime-passes`）
+// 流水线的各个阶段用 `time` 包起来，只有在 `start` 和 `finish` 之间才记录，否则直接执行。
+// 记录保存在线程局部变量中，深处的阶段（每个模块的词法分析、每个函数体的 MIR pass）
+// 不需要层层传递状态。同一层中的同名阶段合并：时间相加，峰值内存取最大，并记录次数。
+// 内存统计来自 `CountingAllocator`，由可执行文件安装为全局分配器；没有安装时不报告内存
+
+use crate::log;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub depth: usize, // 嵌套的层数，顶层的阶段为 0
+    pub count: usize, // 执行的次数
+    pub duration: Duration,
+    pub peak_memory: Option<usize>, // 阶段执行期间堆内存的峰值（字节）
+}
+
+#[derive(Default)]
+struct Recorder {
+    passes: Vec<PassTiming>, // 按第一次开始的顺序
+    depth: usize,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// 开始在当前线程上记录
+pub fn start() {
+    RECORDER.with(|recorder| *recorder.borrow_mut() = Some(Recorder::default()));
+}
+
+/// 停止记录，返回记录到的阶段
+pub fn finish() -> Vec<PassTiming> {
+    RECORDER
+        .with(|recorder| recorder.borrow_mut().take())
+        .map(|recorder| recorder.passes)
+        .unwrap_or_default()
+}
+
+/// 记录 `f` 执行期间的各个阶段
+pub fn record<T>(f: impl FnOnce() -> T) -> (T, Vec<PassTiming>) {
+    start();
+    let value = f();
+    (value, finish())
+}
+
+/// 执行一个阶段，正在记录时统计它的时间和内存；阶段同时是一个日志 span
+#[track_caller]
+pub fn time<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let _span = log::enter(Location::caller().file(), name);
+    // 先占好位置，使外层的阶段排在内层之前
+    let index = RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        let recorder = recorder.as_mut()?;
+        let depth = recorder.depth;
+        recorder.depth += 1;
+        let index = match recorder
+            .passes
+            .iter()
+            .position(|pass| pass.name == name && pass.depth == depth)
+        {
+            Some(index) => index,
+            None => {
+                recorder.passes.push(PassTiming {
+                    name,
+                    depth,
+                    count: 0,
+                    duration: Duration::ZERO,
+                    peak_memory: None,
+                });
+                recorder.passes.len() - 1
+            }
+        };
+        Some(index)
+    });
+    let Some(index) = index else {
+        return f();
+    };
+
+    // 峰值从阶段开始时的用量重新计算，结束后和外层的峰值合并
+    let outer_peak = PEAK.swap(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+    let start = Instant::now();
+    let value = f();
+    let duration = start.elapsed();
+    let peak = PEAK.fetch_max(outer_peak, Ordering::Relaxed);
+
+    RECORDER.with(|recorder| {
+        if let Some(recorder) = recorder.borrow_mut().as_mut() {
+            recorder.depth -= 1;
+            if let Some(pass) = recorder.passes.get_mut(index) {
+                pass.count += 1;
+                pass.duration += duration;
+                if INSTALLED.load(Ordering::Relaxed) {
+                    pass.peak_memory = Some(pass.peak_memory.unwrap_or(0).max(peak));
+                }
+            }
+        }
+    });
+    value
+}
+
+/// 每行一个阶段，内层的阶段缩进
+pub fn report(passes: &[PassTiming]) -> String {
+    let mut out = String::new();
+    for pass in passes {
+        let memory = pass.peak_memory.map_or("-".to_string(), format_bytes);
+        let count = if pass.count > 1 {
+            format!(" (x{})", pass.count)
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            out,
+            "time: {:>9.3}ms  peak: {:>10}  {}{}{}",
+            pass.duration.as_secs_f64() * 1000.0,
+            memory,
+            "  ".repeat(pass.depth),
+            pass.name,
+            count
+        );
+    }
+    let total: Duration = passes
+        .iter()
+        .filter(|pass| pass.depth == 0)
+        .map(|pass| pass.duration)
+        .sum();
+    let _ = writeln!(out, "time: {:>9.3}ms  total", total.as_secs_f64() * 1000.0);
+    out
+}
+
+/// 给基准测试面板使用的 JSON：时间的单位是纳秒，内存的单位是字节
+pub fn to_json(passes: &[PassTiming]) -> String {
+    let mut out = String::from("{\n  \"passes\": [");
+    for (i, pass) in passes.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let memory = pass
+            .peak_memory
+            .map_or("null".to_string(), |bytes| bytes.to_string());
+        let _ = write!(
+            out,
+            "    {{\"name\": \"{}\", \"depth\": {}, \"count\": {}, \"time_ns\": {}, \"peak_memory\": {}}}",
+            pass.name,
+            pass.depth,
+            pass.count,
+            pass.duration.as_nanos(),
+            memory
+        );
+    }
+    if !passes.is_empty() {
+        out.push_str("\n  ");
+    }
+    out.push_str("]\n}\n");
+    out
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+// 当前分配的字节数和峰值，以及分配的总次数
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// 安装 `CountingAllocator` 以来所有线程分配（包括重新分配）的次数；没有安装时为 `None`。
+/// 两次读数之差就是其间的分配次数
+pub fn allocations() -> Option<usize> {
+    INSTALLED
+        .load(Ordering::Relaxed)
+        .then(|| ALLOCATIONS.load(Ordering::Relaxed))
+}
+
+/// 统计堆内存用量的全局分配器，实际的分配交给系统分配器：
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn allocated(size: usize) {
+        if !INSTALLED.load(Ordering::Relaxed) {
+            INSTALLED.store(true, Ordering::Relaxed);
+        }
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn freed(size: usize) {
+        CURRENT.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::freed(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            Self::freed(layout.size());
+            Self::allocated(new_size);
+        }
+        new
+    }
+}
@@ -1,15 +1,20 @@
+use crate::ast::Type;
+use crate::source_map::SourceFile;
 use crate::span::Span;
+use crate::symbol::Symbol;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind {
     // 字面量
-    IntLiteral(i32),
+    IntLiteral(i64),
+    SuffixedIntLiteral(i64, Type), // `300u8`、`5_i64`：后缀给出整数类型
     BoolLiteral(bool),
-    StringLiteral(String),
+    StringLiteral(Symbol),
     CharLiteral(char),
 
     // 标识符
-    Ident(String),
+    Ident(Symbol),
+    Label(Symbol), // 循环标签 `'outer`，不含引号
 
     // 关键字
     Fn,
@@ -32,6 +37,7 @@ pub enum TokenKind {
     Const,
     Static,
     As,
+    Unsafe,
 
     // 类型关键字
     I8,
@@ -57,11 +63,17 @@ pub enum TokenKind {
     Slash,   // /
     Percent, // %
 
-    Assign,      // =
-    PlusAssign,  // +=
-    MinusAssign, // -=
-    StarAssign,  // *=
-    SlashAssign, // /=
+    Assign,           // =
+    PlusAssign,       // +=
+    MinusAssign,      // -=
+    StarAssign,       // *=
+    SlashAssign,      // /=
+    PercentAssign,    // %=
+    BitAndAssign,     // &=
+    BitOrAssign,      // |=
+    BitXorAssign,     // ^=
+    LeftShiftAssign,  // <<=
+    RightShiftAssign, // >>=
 
     Equal,        // ==
     NotEqual,     // !=
@@ -100,6 +112,8 @@ pub enum TokenKind {
     FatArrow,    // =>
     Question,    // ?
     At,          // @
+    Hash,        // #
+    Dollar,      // $，只出现在宏定义中
     Underscore,  // _
 
     // 特殊
@@ -108,15 +122,188 @@ pub enum TokenKind {
     Error(String),
 }
 
+impl TokenKind {
+    /// 基本类型的关键字，如 `i32`、`bool`、`string`
+    pub fn is_type_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::I8
+                | TokenKind::I16
+                | TokenKind::I32
+                | TokenKind::I64
+                | TokenKind::U8
+                | TokenKind::U16
+                | TokenKind::U32
+                | TokenKind::U64
+                | TokenKind::Usize
+                | TokenKind::Isize
+                | TokenKind::F32
+                | TokenKind::F64
+                | TokenKind::Bool
+                | TokenKind::Char
+                | TokenKind::String
+        )
+    }
+}
+
+// 关键字和符号的拼写。词法分析器的关键字识别和 `Display` 与这些表一致（lexer.rs 的测试检查），
+// 编辑器的语法文件（`contractus gen-syntax`）和高亮类别由它们生成；新增关键字或运算符时加在这里
+
+/// 关键字，包括 `_`；`true` 和 `false` 是字面量，不在其中
+pub const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("fn", TokenKind::Fn),
+    ("let", TokenKind::Let),
+    ("mut", TokenKind::Mut),
+    ("return", TokenKind::Return),
+    ("if", TokenKind::If),
+    ("else", TokenKind::Else),
+    ("while", TokenKind::While),
+    ("for", TokenKind::For),
+    ("in", TokenKind::In),
+    ("break", TokenKind::Break),
+    ("continue", TokenKind::Continue),
+    ("struct", TokenKind::Struct),
+    ("enum", TokenKind::Enum),
+    ("match", TokenKind::Match),
+    ("import", TokenKind::Import),
+    ("export", TokenKind::Export),
+    ("pub", TokenKind::Pub),
+    ("const", TokenKind::Const),
+    ("static", TokenKind::Static),
+    ("as", TokenKind::As),
+    ("unsafe", TokenKind::Unsafe),
+    ("_", TokenKind::Underscore),
+];
+
+/// 基本类型的关键字
+pub const TYPE_KEYWORDS: &[(&str, TokenKind)] = &[
+    ("i8", TokenKind::I8),
+    ("i16", TokenKind::I16),
+    ("i32", TokenKind::I32),
+    ("i64", TokenKind::I64),
+    ("u8", TokenKind::U8),
+    ("u16", TokenKind::U16),
+    ("u32", TokenKind::U32),
+    ("u64", TokenKind::U64),
+    ("usize", TokenKind::Usize),
+    ("isize", TokenKind::Isize),
+    ("f32", TokenKind::F32),
+    ("f64", TokenKind::F64),
+    ("bool", TokenKind::Bool),
+    ("char", TokenKind::Char),
+    ("string", TokenKind::String),
+];
+
+/// 布尔字面量
+pub const BOOL_LITERALS: &[(&str, TokenKind)] = &[
+    ("true", TokenKind::BoolLiteral(true)),
+    ("false", TokenKind::BoolLiteral(false)),
+];
+
+/// 运算符，包括赋值、范围、`->`、`=>` 和 `?`
+pub const OPERATORS: &[(&str, TokenKind)] = &[
+    ("+", TokenKind::Plus),
+    ("-", TokenKind::Minus),
+    ("*", TokenKind::Star),
+    ("/", TokenKind::Slash),
+    ("%", TokenKind::Percent),
+    ("=", TokenKind::Assign),
+    ("+=", TokenKind::PlusAssign),
+    ("-=", TokenKind::MinusAssign),
+    ("*=", TokenKind::StarAssign),
+    ("/=", TokenKind::SlashAssign),
+    ("%=", TokenKind::PercentAssign),
+    ("&=", TokenKind::BitAndAssign),
+    ("|=", TokenKind::BitOrAssign),
+    ("^=", TokenKind::BitXorAssign),
+    ("<<=", TokenKind::LeftShiftAssign),
+    (">>=", TokenKind::RightShiftAssign),
+    ("==", TokenKind::Equal),
+    ("!=", TokenKind::NotEqual),
+    ("<", TokenKind::Less),
+    (">", TokenKind::Greater),
+    ("<=", TokenKind::LessEqual),
+    (">=", TokenKind::GreaterEqual),
+    ("&&", TokenKind::LogicalAnd),
+    ("||", TokenKind::LogicalOr),
+    ("!", TokenKind::LogicalNot),
+    ("&", TokenKind::BitwiseAnd),
+    ("|", TokenKind::BitwiseOr),
+    ("^", TokenKind::BitwiseXor),
+    ("~", TokenKind::BitwiseNot),
+    ("<<", TokenKind::LeftShift),
+    (">>", TokenKind::RightShift),
+    ("..", TokenKind::DotDot),
+    ("..=", TokenKind::DotDotEqual),
+    ("->", TokenKind::Arrow),
+    ("=>", TokenKind::FatArrow),
+    ("?", TokenKind::Question),
+];
+
+/// 定界符和分隔符
+pub const PUNCTUATION: &[(&str, TokenKind)] = &[
+    ("(", TokenKind::LeftParen),
+    (")", TokenKind::RightParen),
+    ("{", TokenKind::LeftBrace),
+    ("}", TokenKind::RightBrace),
+    ("[", TokenKind::LeftBracket),
+    ("]", TokenKind::RightBracket),
+    (";", TokenKind::Semicolon),
+    (":", TokenKind::Colon),
+    ("::", TokenKind::DoubleColon),
+    (",", TokenKind::Comma),
+    (".", TokenKind::Dot),
+    ("@", TokenKind::At),
+    ("#", TokenKind::Hash),
+    ("$", TokenKind::Dollar),
+];
+
+/// 记号不保存源码文本，需要时用 `text` 从源文件中按位置取出
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
-    pub raw: String, // 用于调试
 }
 
 impl Token {
-    pub fn new(kind: TokenKind, span: Span, raw: String) -> Self {
-        Self { kind, span, raw }
+    pub fn new(kind: TokenKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// 记号在源文件中的文本。宏生成的记号取它所来自的位置的文本，
+    /// 不在这个文件中的记号为空串
+    pub fn text<'s>(&self, source: &'s SourceFile) -> &'s str {
+        source.text(self.span)
+    }
+}
+
+/// 记号树：单个记号，或一对定界符（圆括号、方括号、花括号）括起来的记号树序列。
+/// 宏调用的输入和 `macro_rules!` 的规则保存为记号树，展开之后再解析
+#[derive(Debug, Clone)]
+pub enum TokenTree {
+    Token(Token),
+    Delimited(Token, Vec<TokenTree>, Token), // 左定界符、内容、右定界符
+}
+
+impl TokenTree {
+    pub fn span(&self) -> Span {
+        match self {
+            TokenTree::Token(token) => token.span,
+            TokenTree::Delimited(open, _, close) => open.span.merge(&close.span),
+        }
+    }
+
+    /// 展开为记号序列，包括定界符
+    pub fn flatten_into(&self, tokens: &mut Vec<Token>) {
+        match self {
+            TokenTree::Token(token) => tokens.push(token.clone()),
+            TokenTree::Delimited(open, trees, close) => {
+                tokens.push(open.clone());
+                for tree in trees {
+                    tree.flatten_into(tokens);
+                }
+                tokens.push(close.clone());
+            }
+        }
     }
 }
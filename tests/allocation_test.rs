@@ -0,0 +1,71 @@
+// Contractus 分配次数测试
+// 词法分析把标识符和字符串驻留为共享的符号，记号不保存源码文本，大型输入上的分配次数远少于记号数。
+// 分配次数是全局计数，这个文件只有一个测试，避免其他测试线程的分配混进来
+
+use contractus::symbol::{Interner, Symbol};
+use contractus::timing::{self, CountingAllocator};
+use contractus::token::TokenKind;
+use contractus::Lexer;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn large_input(functions: usize) -> String {
+    let mut input = String::new();
+    for i in 0..functions {
+        input.push_str(&format!(
+            "fn step{}(count: i32, total: i32) -> i32 {{\n    \
+             let next = count * 2 + total;\n    \
+             if next > 100 {{ print(\"overflow\"); }}\n    \
+             return next - count;\n}}\n",
+            i
+        ));
+    }
+    input
+}
+
+#[test]
+fn test_lexing_allocations() {
+    // 符号的比较和驻留
+    let mut interner = Interner::new();
+    let a = interner.intern("count");
+    let b = interner.intern("count");
+    assert_eq!(a, b);
+    assert_eq!(a, "count");
+    assert_eq!(interner.len(), 1);
+    assert_eq!(format!("{:?} {}", a, a), "\"count\" count");
+    assert_eq!(TokenKind::Ident(Symbol::from("count")), TokenKind::Ident(b));
+
+    let input = large_input(2000);
+    let before = timing::allocations().expect("the counting allocator is installed");
+    let tokens = Lexer::new(&input).tokenize().unwrap();
+    let allocations = timing::allocations().unwrap() - before;
+
+    // 重复的名字和字符串共用同一份文本
+    let names: Vec<&Symbol> = tokens
+        .iter()
+        .filter_map(|token| match &token.kind {
+            TokenKind::Ident(name) if name == "count" => Some(name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names.len(), 2000 * 3);
+    assert!(names
+        .iter()
+        .all(|name| std::ptr::eq(name.as_str(), names[0].as_str())));
+
+    println!(
+        "lexed {} bytes into {} tokens with {} allocations",
+        input.len(),
+        tokens.len(),
+        allocations
+    );
+    // 每个函数只有名字 `stepN` 是新的文本
+    assert!(tokens.len() > 2000 * 40);
+    assert!(
+        allocations * 10 < tokens.len(),
+        "{} allocations for {} tokens",
+        allocations,
+        tokens.len()
+    );
+}
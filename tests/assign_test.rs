@@ -0,0 +1,148 @@
+// Contractus 赋值位置测试
+// 赋值和复合赋值的左侧可以是解引用、字段、元组下标和索引的任意组合；
+// 经过 `&` 引用或 `*const` 指针的位置不能赋值，只有引用和指针可以解引用
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+
+const PROGRAM: &str = "struct Point {
+    x: i32,
+    y: i32,
+}
+
+struct Holder {
+    r: &mut i32,
+}
+
+fn bump(r: &mut i32) {
+    *r = *r + 1;
+    *r += 10;
+}
+
+fn shift(p: &mut Point) {
+    p.x -= 2;
+    (*p).y *= 3;
+}
+
+fn next(counter: &mut i32) -> usize {
+    *counter += 1;
+    0
+}
+
+fn main() {
+    let mut n = 1;
+    bump(&mut n);
+    print(n);
+    let mut arr = [1, 2, 3];
+    let i = 1;
+    arr[i] += 1;
+    arr[0] = 7;
+    print(arr[0], arr[1]);
+    let mut p = Point { x: 5, y: 2 };
+    shift(&mut p);
+    print(p.x, p.y);
+    let mut pair = (1, 2);
+    pair.0 *= 3;
+    pair.1 = 9;
+    print(pair.0, pair.1);
+    let mut points = [Point { x: 1, y: 1 }];
+    points[0].x += 4;
+    print(points[0].x);
+    let r = &mut arr;
+    r[2] += 5;
+    (*r)[2] -= 1;
+    print(arr[2]);
+    let q = &mut p;
+    q.y = 100;
+    print(p.y);
+    let mut a = 1;
+    let mut ra = &mut a;
+    let rra = &mut ra;
+    **rra = 3;
+    **rra *= 2;
+    print(a);
+    let h = Holder { r: &mut n };
+    *h.r += 1;
+    print(n);
+    unsafe {
+        let raw = &mut n as *mut i32;
+        *raw = 5;
+        *raw <<= 2;
+    }
+    print(n);
+    let mut calls = 0;
+    let mut grid = [[1, 2], [3, 4]];
+    grid[1][next(&mut calls)] *= 10;
+    arr[next(&mut calls)] += 5;
+    print(calls, grid[1][0], arr[0]);
+}
+";
+
+#[test]
+fn test_assign_through_places() {
+    // 下标中的函数调用只求值一次：`calls` 为 2
+    assert_eq!(
+        run(PROGRAM),
+        "12\n7\n3\n3\n6\n3\n9\n5\n7\n100\n6\n13\n20\n2\n30\n12\n"
+    );
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_assign_through_shared() {
+    let check = |body: &str| {
+        errors(&format!(
+            "struct Point {{\n    x: i32,\n    y: i32,\n}}\nfn main() {{\n    let mut n = 1;\n    let mut point = Point {{ x: 1, y: 2 }};\n    let mut arr = [1, 2];\n{}\n}}\n",
+            body
+        ))
+    };
+
+    for body in [
+        "    let r = &n;\n    *r = 5;",
+        "    let r = &n;\n    *r += 1;",
+        "    let p = &point;\n    p.x = 3;",
+        "    let a = &arr;\n    a[0] -= 1;",
+        "    let r = &mut n;\n    let rr = &r;\n    **rr = 2;",
+        "    unsafe {\n        let p = &n as *const i32;\n        *p = 2;\n    }",
+    ] {
+        let found = check(body);
+        assert_eq!(found.len(), 1, "{}: {:?}", body, found);
+        assert_eq!(found[0].0, Some(ErrorCode::E0594), "{}", body);
+    }
+
+    let found = check("    let p = &point;\n    p.y = 3;");
+    assert_eq!(found[0].1, "cannot assign to data behind a `&` reference");
+
+    // 经过 `&mut` 引用赋值，或只读取 `&` 引用后面的值
+    assert!(check(
+        "    let r = &mut n;\n    *r = 5;\n    let p = &mut point;\n    p.x += 1;\n    let a = &arr;\n    n = a[0] + *(&point.y);"
+    )
+    .is_empty());
+}
+
+#[test]
+fn test_assign_errors() {
+    let check = |body: &str| errors(&format!("fn main() {{\n    let mut n = 1;\n{}\n}}\n", body));
+
+    let found = check("    *n = 5;");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0614),
+            "type `i32` cannot be dereferenced".to_string()
+        )]
+    );
+    let found = check("    print(*true);");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0614));
+
+    for body in ["    5 = n;", "    (n + 1) += 2;", "    n.abs() = 3;"] {
+        let found = check(body);
+        assert_eq!(found.len(), 1, "{}: {:?}", body, found);
+        assert_eq!(found[0].0, Some(ErrorCode::E0070), "{}", body);
+    }
+}
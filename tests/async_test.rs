@@ -0,0 +1,116 @@
+// Contractus async/await 语法测试
+// `#![feature(async_await)]` 开启 `async fn`、`async { ... }` 和 `expr.await`。
+// 在协程的实现之前按同步的方式执行，解释器和虚拟机的结果应当一致
+
+mod common;
+
+use common::{errors, run};
+use contractus::ast::{Expr, Item, Statement};
+use contractus::diagnostic::ErrorCode;
+use contractus::module;
+
+const PROGRAM: &str = "#![feature(async_await)]
+
+async fn double(x: i32) -> i32 {
+    return x * 2;
+}
+
+async fn run() -> i32 {
+    let a = double(2).await;
+    let b = async {
+        double(a).await + 1
+    }.await;
+    return b;
+}
+
+fn main() {
+    let result = async {
+        run().await
+    };
+    print(result);
+}
+";
+
+#[test]
+fn test_async_syntax() {
+    assert_eq!(run(PROGRAM), "9\n");
+
+    let program = module::parse_source(PROGRAM).unwrap();
+    let Item::Function(double) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert!(double.asynchronous);
+    let Item::Function(main) = &program.items[2] else {
+        panic!("expected a function");
+    };
+    let Statement::Let(binding) = &main.body.statements[0] else {
+        panic!("expected a let statement");
+    };
+    assert!(matches!(binding.init, Some(Expr::AsyncBlock(..))));
+
+    // 还原的源码经过格式化和原来相同
+    let source = program.to_source();
+    assert!(source.contains("async fn run() -> i32 {"), "{}", source);
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_async_feature_gate() {
+    let found = errors("async fn f() {}\nfn main() {}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0658),
+            "async functions are experimental".to_string()
+        )]
+    );
+
+    // 2024 版中没有开启特性时 `async` 和 `await` 是普通的名字
+    let names = "struct Task {\n    await: i32,\n}\nfn main() {\n    let async = Task { await: 1 };\n    print(async.await);\n}\n";
+    assert_eq!(run(names), "1\n");
+
+    let found = errors(&format!("#![edition(2025)]\n{}", names));
+    assert!(found
+        .iter()
+        .any(|(code, message)| *code == Some(ErrorCode::E0658)
+            && message == "`.await` expressions are experimental"));
+
+    let found = errors("#![feature(async_await)]\nfn main() {\n    let async = 1;\n}\n");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(
+        found[0].1,
+        "`async` is a keyword with `#![feature(async_await)]`"
+    );
+}
+
+#[test]
+fn test_async_context() {
+    let source = "#![feature(async_await)]\nasync fn one() -> i32 {\n    return 1;\n}\n";
+    let check = |main: &str| errors(&format!("{}fn main() {{\n{}\n}}\n", source, main));
+
+    let found = check("    print(one().await);");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0728));
+
+    // async 块中的闭包不是异步的
+    let found = check("    let x = async {\n        let f = || one().await;\n        f()\n    };");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0728));
+
+    let found = check("    let x = async {\n        return 1;\n    };");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(
+        found[0],
+        (
+            Some(ErrorCode::E0658),
+            "`return` in an `async` block is not supported yet".to_string()
+        )
+    );
+
+    let found =
+        check("    while true {\n        let x = async {\n            break;\n        };\n    }");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0268));
+
+    assert!(check("    let x = async {\n        one().await\n    };\n    print(x);").is_empty());
+}
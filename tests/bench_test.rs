@@ -0,0 +1,169 @@
+// Contractus 基准测试
+// `#[bench]` 属性的解析和检查、基准测试函数在优化后保留、计时和统计，以及 `contractus bench`
+
+use contractus::bench::{self, BenchOptions};
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::{Compiler, Emit, OptLevel};
+use contractus::{bytecode, Item};
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+
+const PROGRAM: &str = "fn square(x: i32) -> i32 {
+    return x * x;
+}
+
+#[bench]
+fn bench_square() {
+    square(12);
+}
+
+#[bench]
+fn bench_overflow() {
+    let a = [1, 2, 3];
+    let i = 3;
+    print(a[i]);
+}
+
+fn main() {
+    print(square(3));
+}
+";
+
+fn codes(source: &str) -> Vec<ErrorCode> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .iter()
+        .filter_map(|d| d.code)
+        .collect()
+}
+
+fn quick() -> BenchOptions {
+    BenchOptions {
+        warmup: Duration::from_millis(5),
+        measure: Duration::from_millis(20),
+        samples: 5,
+    }
+}
+
+#[test]
+fn test_parse_attributes() {
+    let program = contractus::module::parse_source(PROGRAM).unwrap();
+    let benches: Vec<&str> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(func) if func.has_attribute("bench") => Some(func.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(benches, ["bench_square", "bench_overflow"]);
+    let json = program.to_json();
+    assert!(json.contains("\"attributes\": [\n"), "{}", json);
+    assert!(json.contains("\"name\": \"bench\""), "{}", json);
+
+    assert_eq!(
+        codes("#[bench] struct Point { x: i32 }"),
+        [ErrorCode::E0103]
+    );
+    assert_eq!(codes("#[benchmark] fn run() {}"), [ErrorCode::E0702]);
+    assert_eq!(codes("#[bench] fn run(n: i32) {}"), [ErrorCode::E0703]);
+    assert_eq!(codes("#[bench] fn run<T>() {}"), [ErrorCode::E0703]);
+    assert!(codes("#[bench] pub fn run() {}").is_empty());
+}
+
+#[test]
+fn test_benches_survive_optimization() {
+    let output = Compiler::new()
+        .source(PROGRAM)
+        .opt_level(OptLevel::Os)
+        .emit(Emit::Mir)
+        .run();
+    let program = output.into_result().unwrap().into_mir().unwrap();
+    assert_eq!(program.benches, ["bench_square", "bench_overflow"]);
+    assert!(program.body("bench_square").is_some());
+    assert!(program.body("square").is_some());
+}
+
+#[test]
+fn test_run_bench() {
+    let program = Compiler::new()
+        .source(PROGRAM)
+        .emit(Emit::Mir)
+        .run()
+        .into_result()
+        .unwrap()
+        .into_mir()
+        .unwrap();
+    let module = bytecode::compile(&program).unwrap();
+
+    let result = bench::run(&module, "bench_square", &quick()).unwrap();
+    assert_eq!(result.name, "bench_square");
+    assert_eq!(result.samples, 5);
+    assert!(result.iterations >= 1);
+    assert!(result.ns_per_iter > 0.0);
+
+    let errors = bench::run(&module, "bench_overflow", &quick()).unwrap_err();
+    assert_eq!(errors[0].code, Some(ErrorCode::E0900));
+}
+
+#[test]
+fn test_statistics() {
+    assert_eq!(bench::mean_and_std_dev(&[]), (0.0, 0.0));
+    assert_eq!(bench::mean_and_std_dev(&[5.0]), (5.0, 0.0));
+    let (mean, std_dev) = bench::mean_and_std_dev(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+    assert_eq!(mean, 5.0);
+    assert!((std_dev - 2.138).abs() < 0.001, "{}", std_dev);
+
+    let result = bench::BenchResult {
+        name: "b".to_string(),
+        iterations: 1,
+        samples: 1,
+        ns_per_iter: 1234567.6,
+        std_dev: 89.2,
+    };
+    assert_eq!(result.to_string(), "1,234,568 ns/iter (+/- 89)");
+}
+
+#[test]
+fn test_bench_command() {
+    let dir = env::temp_dir().join(format!("contractus_bench_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("bench.ctx");
+    fs::write(&file, PROGRAM).unwrap();
+    let bench = |filter: &str| {
+        Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .arg("bench")
+            .arg(format!("--filter={}", filter))
+            .arg(&file)
+            .output()
+            .expect("cannot run contractus")
+    };
+
+    let output = bench("square");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("running 1 benchmark\n"), "{}", stdout);
+    assert!(
+        stdout.contains("test bench_square ... bench: "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("ns/iter (+/- "), "{}", stdout);
+    assert!(stdout.contains("test result: ok. 1 measured; 0 failed; 1 filtered out"));
+
+    let output = bench("overflow");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("test bench_overflow ... FAILED"),
+        "{}",
+        stdout
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error[E0900]"), "{}", stderr);
+
+    fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,112 @@
+// Contractus Box 测试
+// `Box::new(value)` 把值移到堆上：`*b` 读写盒子中的值，字段访问和模式匹配自动解引用；
+// 盒子是间接的，可以用来定义递归的结构体和枚举，按所有权转移并在离开作用域时析构
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{mir, module};
+
+#[test]
+fn test_recursive_enum() {
+    let source = "enum List {
+    Cons(i32, Box<List>),
+    Nil,
+}
+
+fn sum(list: &List) -> i32 {
+    match list {
+        Cons(value, rest) => value + sum(&*rest),
+        Nil => 0,
+    }
+}
+
+fn main() {
+    let list = Cons(1, Box::new(Cons(2, Box::new(Cons(3, Box::new(Nil))))));
+    print(sum(&list));
+}
+";
+    assert_eq!(run(source), "6\n");
+}
+
+#[test]
+fn test_recursive_struct() {
+    let source = "struct Tree {
+    value: i32,
+    left: Option<Box<Tree>>,
+    right: Option<Box<Tree>>,
+}
+
+fn leaf(value: i32) -> Option<Box<Tree>> {
+    Some(Box::new(Tree { value: value, left: None, right: None }))
+}
+
+fn total(tree: &Option<Box<Tree>>) -> i32 {
+    match tree {
+        Some(node) => node.value + total(&node.left) + total(&node.right),
+        None => 0,
+    }
+}
+
+fn main() {
+    let inner = Tree { value: 2, left: leaf(4), right: None };
+    let root = Some(Box::new(Tree { value: 1, left: Some(Box::new(inner)), right: leaf(3) }));
+    print(total(&root), std::mem::size_of::<Box<Tree>>());
+}
+";
+    assert_eq!(run(source), "10\n8\n");
+}
+
+#[test]
+fn test_deref() {
+    let source = "struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {
+    let mut counter = Box::new(0);
+    for i in 1..=4 {
+        *counter += i;
+    }
+    print(*counter, counter);
+
+    let mut point = Box::new(Point { x: 1, y: 2 });
+    point.x = 10;
+    print(point.x + point.y);
+}
+";
+    assert_eq!(run(source), "10\n10\n12\n");
+}
+
+#[test]
+fn test_ownership() {
+    // 盒子按所有权转移，离开作用域时析构
+    let source = "fn main() {
+    let first = Box::new(1);
+    let second = first;
+    print(*second);
+}
+";
+    let program = module::parse_source(source).unwrap();
+    let lowered = mir::lower_program(&program).unwrap().to_string();
+    assert!(lowered.contains("let _2: Box<i32>;"), "{}", lowered);
+    assert!(lowered.contains("_2 = move _1;"), "{}", lowered);
+    assert!(lowered.contains("drop(_2)"), "{}", lowered);
+    assert!(!lowered.contains("drop(_1)"), "{}", lowered);
+}
+
+#[test]
+fn test_recursion_through_box() {
+    let found = Compiler::new()
+        .source("struct Node {\n    value: i32,\n    next: Option<Box<Node>>,\n}\nfn main() {}\n")
+        .check();
+    assert!(found.is_empty(), "{:?}", found);
+
+    let found = Compiler::new()
+        .source("struct Node {\n    value: i32,\n    next: Option<Node>,\n}\nfn main() {}\n")
+        .check();
+    assert_eq!(found[0].code, Some(ErrorCode::E0072));
+}
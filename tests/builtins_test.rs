@@ -0,0 +1,119 @@
+// Contractus 内建函数测试
+// 解释器和字节码虚拟机中的 print、len、assert；参数个数和参数类型的检查；
+// 程序中定义的同名函数优先于内建函数
+
+mod common;
+
+use common::try_run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{builtins, bytecode};
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_len_and_print() {
+    let output = try_run(
+        r#"
+        fn main() {
+            let a = [1, 2, 3];
+            let s = "hello";
+            let r = &a;
+            let n: usize = len(a);
+            print(n, len(s), len(r), len(""));
+            print(true, 'x', (1, "a"));
+        }
+    "#,
+    );
+    assert_eq!(output.unwrap(), "3\n5\n3\n0\ntrue\nx\n(1, \"a\")\n");
+}
+
+#[test]
+fn test_assert() {
+    let source = |condition: &str| {
+        format!(
+            "fn main() {{\n    let a = [1, 2];\n    assert(len(a) == 2);\n    {}\n    print(\"done\");\n}}",
+            condition
+        )
+    };
+    assert_eq!(try_run(&source("assert(true);")).unwrap(), "done\n");
+    assert_eq!(
+        try_run(&source("assert(len(a) > 2);")).unwrap_err(),
+        "assertion failed"
+    );
+    assert_eq!(
+        try_run(&source("assert(a[0] == 2, \"first element\");")).unwrap_err(),
+        "assertion failed: first element"
+    );
+}
+
+#[test]
+fn test_argument_checks() {
+    let errors = check("fn main() {\n    let a = [1, 2];\n    print(len(a, a));\n    assert();\n}");
+    assert_eq!(
+        errors,
+        vec![
+            (
+                Some(ErrorCode::E0061),
+                "function `len` takes 1 argument but 2 were supplied".to_string()
+            ),
+            (
+                Some(ErrorCode::E0061),
+                "function `assert` takes 1 to 2 arguments but 0 were supplied".to_string()
+            ),
+        ]
+    );
+
+    let errors = check("fn main() {\n    let n = 3;\n    print(len(n));\n    assert(n, \"n\");\n}");
+    assert_eq!(
+        errors,
+        vec![
+            (
+                Some(ErrorCode::E0308),
+                "`len` expects an array, slice or string, found `i32`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0308),
+                "`assert` expects `bool`, found `i32`".to_string()
+            ),
+        ]
+    );
+
+    // print 不能出现在契约条件中，len 可以
+    let errors = check("fn f(a: [i32; 2]) -> i32\n    requires len(a) == 2,\n    ensures print(1) == (),\n{\n    return a[0];\n}\nfn main() {}");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, Some(ErrorCode::E0701));
+}
+
+#[test]
+fn test_user_functions_shadow_builtins() {
+    let output = try_run(
+        r#"
+        fn len(x: i32) -> i32 {
+            return x * 2;
+        }
+        fn main() {
+            let assert = |x: i32| x + 1;
+            print(len(21), assert(1));
+        }
+    "#,
+    );
+    assert_eq!(output.unwrap(), "42\n2\n");
+    assert!(check("fn len(x: i32) -> i32 { return x; }\nfn main() { print(len(1)); }").is_empty());
+
+    for builtin in builtins::BUILTINS {
+        assert!(builtin.usage.starts_with(builtin.name));
+        // pop 在 MIR 中展开，虚拟机不实现它
+        assert_eq!(
+            bytecode::Builtin::from_name(builtin.name).is_some(),
+            builtin.name != "pop"
+        );
+    }
+}
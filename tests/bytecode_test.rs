@@ -0,0 +1,518 @@
+// Contractus 字节码测试
+// 经 MIR 编译为字节码后在虚拟机上执行，输出应与解释器一致；
+// 同时检查 `.ctxb` 编码的往返和损坏文件（包括操作数栈的高度不对）的报错
+
+use contractus::bytecode::{self, Module, Op, Value, Vm};
+use contractus::mir::transform::{self, OptLevel};
+use contractus::{interp, mir, Lexer, Parser, SemanticAnalyzer};
+
+fn parse(input: &str) -> contractus::ast::Program {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+    program
+}
+
+fn compile(input: &str, level: OptLevel) -> Module {
+    let lowered = mir::lower_program(&parse(input)).expect("MIR lowering failed");
+    let mut program = mir::monomorphize(&lowered).expect("monomorphization failed");
+    transform::optimize(&mut program, level);
+    bytecode::compile(&program).expect("bytecode compilation failed")
+}
+
+fn run_module(module: &Module) -> Result<String, String> {
+    let (result, output) = bytecode::run_with_output(module, Vec::new());
+    match result {
+        Ok(()) => Ok(String::from_utf8(output).unwrap()),
+        Err(errors) => Err(errors[0].to_string()),
+    }
+}
+
+// 在各个优化级别下运行，并与解释器的输出比较
+fn run(input: &str) -> String {
+    let (result, expected) = interp::run_with_output(&parse(input), Vec::new());
+    assert!(result.is_ok(), "interpreter failed: {:?}", result);
+    let expected = String::from_utf8(expected).unwrap();
+    for level in [OptLevel::O0, OptLevel::O2, OptLevel::Os] {
+        let output = run_module(&compile(input, level)).expect("runtime error");
+        assert_eq!(
+            output, expected,
+            "output differs from the interpreter at {:?}",
+            level
+        );
+    }
+    expected
+}
+
+fn run_err(input: &str) -> String {
+    match run_module(&compile(input, OptLevel::O0)) {
+        Ok(_) => panic!("expected a runtime error"),
+        Err(message) => message,
+    }
+}
+
+#[test]
+fn test_mvp_program() {
+    let output = run(r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        struct Rectangle {
+            top_left: Point,
+            bottom_right: Point,
+        }
+
+        enum Shape {
+            Circle(Point, i32),
+            Rectangle(Rectangle),
+        }
+
+        fn area(shape: Shape) -> i32 {
+            match shape {
+                Circle(center, radius) => 3 * radius * radius,
+                Rectangle(rect) => {
+                    let width = rect.bottom_right.x - rect.top_left.x;
+                    let height = rect.top_left.y - rect.bottom_right.y;
+                    return width * height;
+                }
+            }
+        }
+
+        fn main() -> i32 {
+            let circle = Shape::Circle(Point { x: 0, y: 0 }, 10);
+            let rect = Shape::Rectangle(Rectangle {
+                top_left: Point { x: 0, y: 10 },
+                bottom_right: Point { x: 10, y: 0 },
+            });
+
+            print(area(circle));
+            print(area(rect));
+
+            for i in 0..5 {
+                print(i * 2);
+            }
+
+            let values: [i32; 3] = [1, 2, 3];
+            for val in values {
+                print(val * val);
+            }
+
+            return 0;
+        }
+    "#);
+    assert_eq!(output, "300\n100\n0\n2\n4\n6\n8\n1\n4\n9\n");
+}
+
+#[test]
+fn test_values_match_interpreter() {
+    let output = run(r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        enum Op {
+            Move(i32, i32),
+            Reset,
+        }
+
+        static ORIGIN: i32 = 100;
+
+        fn apply(p: Point, op: Op) -> Point {
+            match op {
+                Op::Move(dx, dy) => Point { x: p.x + dx, y: p.y + dy },
+                Op::Reset => Point { x: 0, y: 0 },
+            }
+        }
+
+        fn bump(x: &mut i32) {
+            *x = *x + 1;
+        }
+
+        fn main() {
+            let mut p = Point { y: 2, x: 1 };
+            p = apply(p, Op::Move(3, 4));
+            print(p);
+            p.x = 10;
+            print(p.x + p.y);
+            print(apply(p, Op::Reset));
+
+            let mut values = [1, 2, 3];
+            bump(&mut values[1]);
+            print(values);
+
+            let pair = (1, "two", 'c');
+            print(pair);
+            print((5,));
+            print(ORIGIN + 1);
+            print(300 as u8);
+            print("con" + "tract");
+            print(!true);
+            print(0..3);
+        }
+    "#);
+    assert!(
+        output.starts_with("Point { x: 4, y: 6 }\n16\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_control_flow_and_closures() {
+    run(r#"
+        fn fib(n: i32) -> i32 {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+
+        fn twice(f: fn(i32) -> i32, x: i32) -> i32 {
+            return f(f(x));
+        }
+
+        fn inc(x: i32) -> i32 {
+            return x + 1;
+        }
+
+        fn main() {
+            let mut i = 0;
+            let mut sum = 0;
+            while true {
+                i += 1;
+                if i % 2 == 0 {
+                    continue;
+                }
+                if i > 9 {
+                    break;
+                }
+                sum += i;
+            }
+            print(sum);
+            print(fib(15));
+            print(twice(inc, 5));
+
+            let offset = 10;
+            let shift = |x: i32| x + offset;
+            print(shift(5));
+
+            let grade = match sum {
+                25 => 'A',
+                _ => 'F',
+            };
+            print(grade);
+        }
+    "#);
+}
+
+#[test]
+fn test_labeled_loops() {
+    let output = run(r#"
+        fn main() {
+            let mut found = 0;
+            'outer: for i in 0..10 {
+                let mut j = 0;
+                'inner: while j < 10 {
+                    j += 1;
+                    if j == 3 {
+                        continue 'inner;
+                    }
+                    if i * j == 12 {
+                        found = i * 100 + j;
+                        break 'outer;
+                    }
+                    if j > i {
+                        continue 'outer;
+                    }
+                }
+            }
+            print(found);
+
+            // 没有标签的 break 跳出最内层的循环
+            let mut count = 0;
+            'rows: for row in 0..3 {
+                for column in 0..3 {
+                    if column > row {
+                        break;
+                    }
+                    count += 1;
+                }
+            }
+            print(count);
+
+            let mut n = 0;
+            let unit = 'top: while true {
+                n += 1;
+                if n == 5 {
+                    break 'top;
+                }
+            };
+            print(n, unit == ());
+        }
+    "#);
+    assert_eq!(output, "304\n6\n5\ntrue\n");
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    let message = run_err(
+        r#"
+        fn main() {
+            let values = [1, 2, 3];
+            let i = 3;
+            print(values[i]);
+        }
+    "#,
+    );
+    assert!(
+        message.contains("index out of bounds: the len is 3 but the index is 3"),
+        "{}",
+        message
+    );
+    // 错误位置来自行号表
+    assert!(message.contains("line 5"), "{}", message);
+}
+
+#[test]
+fn test_negative_index_and_bounds_check_removal() {
+    let input = r#"
+        fn get(values: [i32; 3], i: i32) -> i32 {
+            return values[i];
+        }
+
+        fn main() {
+            print(get([1, 2, 3], 0 - 1));
+        }
+    "#;
+    let message = run_err(input);
+    assert!(
+        message.contains("index out of bounds: the len is 3 but the index is -1"),
+        "{}",
+        message
+    );
+    // 解释器报告相同的错误
+    let (result, _) = interp::run_with_output(&parse(input), Vec::new());
+    assert_eq!(result.unwrap_err()[0].to_string(), message);
+
+    // 删除 MIR 的检查之后虚拟机仍然拒绝越界访问
+    let mut program = mir::monomorphize(&mir::lower_program(&parse(input)).unwrap()).unwrap();
+    transform::remove_all_bounds_checks(&mut program);
+    let module = bytecode::compile(&program).unwrap();
+    assert!(!module.to_string().contains("AssertBounds"));
+    assert_eq!(run_module(&module).unwrap_err(), message);
+}
+
+#[test]
+fn test_division_by_zero() {
+    let message = run_err(
+        r#"
+        fn main() {
+            let zero = 0;
+            print(1 / zero);
+        }
+    "#,
+    );
+    assert!(message.contains("attempt to divide by zero"), "{}", message);
+}
+
+#[test]
+fn test_deep_recursion_is_reported() {
+    let message = run_err(
+        r#"
+        fn down(n: i32) -> i32 {
+            return down(n + 1);
+        }
+
+        fn main() {
+            down(0);
+        }
+    "#,
+    );
+    assert!(
+        message.contains(&format!(
+            "call depth exceeded the limit of {}",
+            bytecode::FRAME_LIMIT
+        )),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_call_function_directly() {
+    let module = compile(
+        r#"
+        fn square(x: i32) -> i32 {
+            return x * x;
+        }
+
+        fn main() {}
+    "#,
+        OptLevel::O1,
+    );
+    let mut vm = Vm::new(&module, Vec::new());
+    let value = vm.call_function("square", vec![Value::Int(12)]).unwrap();
+    assert_eq!(value, Value::Int(144));
+    // 虚拟机可以继续调用
+    let value = vm.call_function("square", vec![Value::Int(-3)]).unwrap();
+    assert_eq!(value, Value::Int(9));
+}
+
+#[test]
+fn test_encode_decode_round_trip() {
+    let module = compile(
+        r#"
+        enum Light {
+            Red,
+            Green,
+        }
+
+        fn next(light: Light) -> Light {
+            match light {
+                Light::Red => Light::Green,
+                Light::Green => Light::Red,
+            }
+        }
+
+        fn main() {
+            print(next(Light::Red));
+            print(-42);
+            print('x');
+            print("bytes");
+        }
+    "#,
+        OptLevel::O2,
+    );
+    let bytes = bytecode::encode(&module);
+    assert!(bytes.starts_with(&bytecode::MAGIC));
+    let decoded = bytecode::decode(&bytes).expect("decoding failed");
+    assert_eq!(decoded, module);
+    assert_eq!(run_module(&decoded).unwrap(), "Green\n-42\nx\nbytes\n");
+}
+
+#[test]
+fn test_decode_rejects_invalid_files() {
+    let module = compile("fn main() { print(1); }", OptLevel::O0);
+    let bytes = bytecode::encode(&module);
+
+    let error = bytecode::decode(b"ELF\x7f....").unwrap_err();
+    assert!(error.contains("missing `CTXB` header"), "{}", error);
+
+    let error = bytecode::decode(&bytes[..bytes.len() - 3]).unwrap_err();
+    assert!(error.starts_with("invalid bytecode file"), "{}", error);
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = 99;
+    let error = bytecode::decode(&wrong_version).unwrap_err();
+    assert!(error.contains("unsupported version 99"), "{}", error);
+
+    // 跳转到指令中间
+    let mut broken = module.clone();
+    let code = &mut broken.functions[0].code;
+    code.extend_from_slice(&[Op::Jump as u8, 1, 0, 0, 0]);
+    let error = bytecode::decode(&bytecode::encode(&broken)).unwrap_err();
+    assert!(error.contains("invalid jump target 1"), "{}", error);
+
+    // 弹出空的操作数栈：不检查时虚拟机执行到这里会 panic
+    let mut broken = module.clone();
+    broken.functions[0].code = vec![Op::Add as u8, Op::Return as u8];
+    let error = bytecode::decode(&bytecode::encode(&broken)).unwrap_err();
+    assert!(error.contains("at 0: operand stack underflow"), "{}", error);
+
+    // 跳转前后栈的高度不同
+    let mut broken = module.clone();
+    broken.functions[0].code = vec![
+        Op::Unit as u8,
+        Op::Unit as u8,
+        Op::JumpIfFalse as u8,
+        8,
+        0,
+        0,
+        0,
+        Op::Unit as u8,
+        Op::Return as u8,
+    ];
+    let error = bytecode::decode(&bytecode::encode(&broken)).unwrap_err();
+    assert!(
+        error.contains("at 8: operand stack height 2 differs from 1 on another path"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_disassembly() {
+    let module = compile(
+        r#"
+        fn add(a: i32, b: i32) -> i32 {
+            return a + b;
+        }
+
+        fn main() {
+            print(add(1, 2));
+        }
+    "#,
+        OptLevel::O0,
+    );
+    let text = module.to_string();
+    assert!(text.contains("fn add (arity 2, locals 3):"), "{}", text);
+    assert!(text.contains("Add"), "{}", text);
+    assert!(text.contains("; fn add"), "{}", text);
+    assert!(text.contains("; builtin print"), "{}", text);
+    assert!(text.contains("Call 2"), "{}", text);
+}
+
+#[test]
+fn test_contract_violations_match_interpreter() {
+    let source = r#"
+        struct Account {
+            balance: i32,
+            invariant self.balance >= 0
+        }
+
+        fn withdraw(balance: i32, amount: i32) -> i32
+            requires amount > 0,
+            ensures result >= 0
+        {
+            return balance - amount;
+        }
+
+        fn main() {
+            let account = Account { balance: withdraw(5, 2) };
+            print(account.balance);
+            print(withdraw(1, 3));
+        }
+    "#;
+    let (result, _) = interp::run_with_output(&parse(source), Vec::new());
+    let expected = result.unwrap_err()[0].to_string();
+    assert!(
+        expected.contains("line 17, column 19: postcondition violated: `result >= 0`"),
+        "{}",
+        expected
+    );
+    for level in [OptLevel::O0, OptLevel::O2] {
+        let (result, output) = bytecode::run_with_output(&compile(source, level), Vec::new());
+        assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+        assert_eq!(
+            result.unwrap_err()[0].to_string(),
+            expected,
+            "at {:?}",
+            level
+        );
+    }
+
+    // 关闭契约检查时不生成断言
+    let options = mir::LowerOptions {
+        contracts: mir::ContractMode::Off,
+        ..Default::default()
+    };
+    let lowered = mir::lower_program_with(&parse(source), options).unwrap();
+    let module = bytecode::compile(&mir::monomorphize(&lowered).unwrap()).unwrap();
+    assert!(!module.to_string().contains("AssertContract"));
+    assert_eq!(run_module(&module).unwrap(), "3\n-2\n");
+}
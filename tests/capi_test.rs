@@ -0,0 +1,283 @@
+// Contractus C API 测试
+// 编译、诊断信息、调用函数和输出回调；头文件与导出的函数一致；
+// 本机有 C 编译器时编译并运行一个嵌入 Contractus 的 C 程序
+
+use contractus::capi::*;
+use contractus::link::{self, Target};
+use std::env;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fs;
+use std::process::Command;
+use std::ptr;
+
+const SOURCE: &str = "fn add(a: i32, b: i32) -> i32 {
+    return a + b;
+}
+
+fn half(x: i32) -> i32
+    requires x % 2 == 0,
+{
+    return x / 2;
+}
+
+fn greet(name: string) -> string {
+    print(name);
+    return \"hello\";
+}
+
+fn main() {
+    print(add(1, 2));
+}
+";
+
+fn int(value: i64) -> ContractusValue {
+    ContractusValue {
+        kind: CONTRACTUS_INT,
+        int_value: value,
+        float_value: 0.0,
+        string_value: ptr::null(),
+    }
+}
+
+unsafe fn text<'a>(ptr: *const c_char) -> &'a str {
+    assert!(!ptr.is_null());
+    CStr::from_ptr(ptr).to_str().unwrap()
+}
+
+unsafe fn compile(ctx: *mut ContractusContext, source: &str) -> i32 {
+    contractus_compile(ctx, source.as_ptr().cast(), source.len())
+}
+
+extern "C" fn collect(user_data: *mut c_void, text: *const c_char, len: usize) {
+    let output = unsafe { &mut *user_data.cast::<Vec<u8>>() };
+    output.extend_from_slice(unsafe { std::slice::from_raw_parts(text.cast::<u8>(), len) });
+}
+
+#[test]
+fn test_compile_and_call() {
+    unsafe {
+        let ctx = contractus_context_new();
+        let mut output: Vec<u8> = Vec::new();
+        contractus_set_output(ctx, Some(collect), (&mut output as *mut Vec<u8>).cast());
+
+        let add = CString::new("add").unwrap();
+        assert_eq!(
+            contractus_call(ctx, add.as_ptr(), ptr::null(), 0, ptr::null_mut()),
+            CONTRACTUS_NOT_COMPILED
+        );
+        assert_eq!(compile(ctx, SOURCE), CONTRACTUS_OK);
+        assert_eq!(contractus_diagnostic_count(ctx), 0);
+
+        let mut result = int(0);
+        let args = [int(40), int(2)];
+        assert_eq!(
+            contractus_call(ctx, add.as_ptr(), args.as_ptr(), 2, &mut result),
+            CONTRACTUS_OK
+        );
+        assert_eq!((result.kind, result.int_value), (CONTRACTUS_INT, 42));
+
+        // 违反契约
+        let half = CString::new("half").unwrap();
+        assert_eq!(
+            contractus_call(ctx, half.as_ptr(), [int(3)].as_ptr(), 1, &mut result),
+            CONTRACTUS_RUNTIME_ERROR
+        );
+        assert_eq!(contractus_diagnostic_count(ctx), 1);
+        assert_eq!(text(contractus_diagnostic_code(ctx, 0)), "E0901");
+        assert_eq!(contractus_diagnostic_is_error(ctx, 0), 1);
+
+        // 字符串实参和返回值，输出交给回调
+        let greet = CString::new("greet").unwrap();
+        let name = CString::new("world").unwrap();
+        let arg = ContractusValue {
+            kind: CONTRACTUS_STRING,
+            string_value: name.as_ptr(),
+            ..int(0)
+        };
+        assert_eq!(
+            contractus_call(ctx, greet.as_ptr(), &arg, 1, &mut result),
+            CONTRACTUS_OK
+        );
+        assert_eq!(result.kind, CONTRACTUS_STRING);
+        assert_eq!(text(result.string_value), "hello");
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_OK);
+        assert_eq!(String::from_utf8_lossy(&output), "world\n3\n");
+
+        let missing = CString::new("missing").unwrap();
+        assert_eq!(
+            contractus_call(ctx, missing.as_ptr(), ptr::null(), 0, &mut result),
+            CONTRACTUS_RUNTIME_ERROR
+        );
+        assert_eq!(text(contractus_diagnostic_code(ctx, 0)), "E0425");
+        let bad = ContractusValue { kind: 99, ..int(0) };
+        assert_eq!(
+            contractus_call(ctx, add.as_ptr(), &bad, 1, &mut result),
+            CONTRACTUS_INVALID_ARGUMENT
+        );
+        contractus_context_free(ctx);
+    }
+}
+
+#[test]
+fn test_diagnostics_and_options() {
+    unsafe {
+        let ctx = contractus_context_new();
+        assert_eq!(
+            compile(ctx, "fn main() {\n    print(totl);\n}"),
+            CONTRACTUS_COMPILE_ERROR
+        );
+        assert_eq!(contractus_diagnostic_count(ctx), 1);
+        assert_eq!(text(contractus_diagnostic_code(ctx, 0)), "E0425");
+        assert_eq!(contractus_diagnostic_line(ctx, 0), 2);
+        assert_eq!(contractus_diagnostic_column(ctx, 0), 11);
+        assert!(text(contractus_diagnostic_message(ctx, 0))
+            .starts_with("error[E0425] at line 2, column 11: cannot find value `totl`"));
+        assert!(contractus_diagnostic_message(ctx, 1).is_null());
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_NOT_COMPILED);
+
+        // 关闭契约检查后不再报告违反契约
+        let option = |name: &str, value: &str| {
+            let name = CString::new(name).unwrap();
+            let value = CString::new(value).unwrap();
+            contractus_set_option(ctx, name.as_ptr(), value.as_ptr())
+        };
+        assert_eq!(option("contracts", "off"), CONTRACTUS_OK);
+        assert_eq!(option("opt-level", "2"), CONTRACTUS_OK);
+        assert_eq!(option("opt-level", "9"), CONTRACTUS_INVALID_ARGUMENT);
+        assert!(text(contractus_diagnostic_message(ctx, 0)).contains("invalid optimization level"));
+        assert_eq!(option("color", "on"), CONTRACTUS_INVALID_ARGUMENT);
+        assert_eq!(compile(ctx, SOURCE), CONTRACTUS_OK);
+        let half = CString::new("half").unwrap();
+        let mut result = int(0);
+        assert_eq!(
+            contractus_call(ctx, half.as_ptr(), [int(3)].as_ptr(), 1, &mut result),
+            CONTRACTUS_OK
+        );
+        assert_eq!(result.int_value, 1);
+
+        assert_eq!(
+            contractus_compile(ctx, [0xffu8].as_ptr().cast(), 1),
+            CONTRACTUS_INVALID_ARGUMENT
+        );
+        contractus_context_free(ctx);
+        contractus_context_free(ptr::null_mut());
+        assert_eq!(text(contractus_version()), env!("CARGO_PKG_VERSION"));
+    }
+}
+
+// io 默认关闭，宿主打开后程序可以读取参数
+#[test]
+fn test_io_capability() {
+    unsafe {
+        let ctx = contractus_context_new();
+        let mut output: Vec<u8> = Vec::new();
+        contractus_set_output(ctx, Some(collect), (&mut output as *mut Vec<u8>).cast());
+        let source = "fn main() {\n    print(io::args());\n}";
+        assert_eq!(compile(ctx, source), CONTRACTUS_OK);
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_RUNTIME_ERROR);
+        assert!(text(contractus_diagnostic_message(ctx, 0)).contains("I/O is disabled"));
+
+        let args = [CString::new("a").unwrap(), CString::new("b").unwrap()];
+        let argv: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+        assert_eq!(contractus_set_io(ctx, 1, argv.as_ptr(), 2), CONTRACTUS_OK);
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_OK);
+        assert_eq!(String::from_utf8_lossy(&output), "[\"a\", \"b\"]\n");
+        assert_eq!(
+            contractus_set_io(ctx, 1, ptr::null(), 1),
+            CONTRACTUS_INVALID_ARGUMENT
+        );
+        contractus_context_free(ctx);
+    }
+}
+
+// 头文件声明的函数就是导出的函数
+#[test]
+fn test_header_matches_exports() {
+    let names = |text: &str, pattern: &str| -> Vec<String> {
+        let mut names: Vec<String> = text
+            .match_indices(pattern)
+            .map(|(at, _)| {
+                text[at + pattern.len()..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '_')
+                    .collect()
+            })
+            .collect();
+        names.sort();
+        names
+    };
+    let header = include_str!("../include/contractus.h");
+    let source = include_str!("../src/capi.rs");
+    let exported = names(source, "extern \"C\" fn contractus_");
+    assert_eq!(exported.len(), 17);
+    let declared: Vec<String> = exported
+        .iter()
+        .filter(|name| header.contains(&format!("contractus_{}(", name)))
+        .cloned()
+        .collect();
+    assert_eq!(declared, exported);
+    for constant in names(source, "pub const CONTRACTUS_") {
+        assert!(
+            header.contains(&format!("#define CONTRACTUS_{} ", constant)),
+            "CONTRACTUS_{} is not in the header",
+            constant
+        );
+    }
+}
+
+// 需要本机的 C 编译器和 cargo 构建出的静态库，缺少时跳过
+#[test]
+fn test_embed_from_c() {
+    let target = Target::host();
+    let Ok(library) = link::find_runtime(&target) else {
+        return;
+    };
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if target.msvc || Command::new(&cc).arg("--version").output().is_err() {
+        return;
+    }
+
+    let dir = env::temp_dir().join(format!("contractus_capi_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let program = dir.join("embed.c");
+    fs::write(
+        &program,
+        r#"#include <stdio.h>
+#include <string.h>
+#include "contractus.h"
+
+int main(void) {
+    const char *source = "fn square(x: i64) -> i64 { return x * x; }\nfn main() { print(undefined); }";
+    ContractusContext *ctx = contractus_context_new();
+    if (contractus_compile(ctx, source, strlen(source)) != CONTRACTUS_COMPILE_ERROR) return 1;
+    printf("%s\n", contractus_diagnostic_code(ctx, 0));
+
+    source = "fn square(x: i64) -> i64 { return x * x; }\nfn main() {}";
+    if (contractus_compile(ctx, source, strlen(source)) != CONTRACTUS_OK) return 2;
+    ContractusValue arg = {CONTRACTUS_INT, 12, 0.0, NULL};
+    ContractusValue result;
+    if (contractus_call(ctx, "square", &arg, 1, &result) != CONTRACTUS_OK) return 3;
+    printf("%lld\n", (long long)result.int_value);
+    contractus_context_free(ctx);
+    return 0;
+}
+"#,
+    )
+    .unwrap();
+    let exe = dir.join("embed");
+    let include = concat!(env!("CARGO_MANIFEST_DIR"), "/include");
+    let status = Command::new(&cc)
+        .arg(format!("-I{}", include))
+        .arg(&program)
+        .arg(&library)
+        .arg("-o")
+        .arg(&exe)
+        .args(target.system_libs())
+        .status()
+        .expect("cannot run the C compiler");
+    assert!(status.success());
+    let output = Command::new(&exe).output().expect("cannot run the program");
+    fs::remove_dir_all(&dir).ok();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "E0425\n144\n");
+}
@@ -0,0 +1,136 @@
+// Contractus 类型转换测试
+// 数值之间的截断、扩展和饱和，`bool`/`char` 的转换，`unsafe` 块中的指针转换，
+// 以及不允许的转换的诊断。解释器、虚拟机和常量传播的结果应当一致
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_integer_casts() {
+    // 常量和变量分别经过常量传播和运行时转换
+    let output = run(r#"
+        fn main() {
+            let big: i64 = 300;
+            let minus: i32 = -1;
+            print(300 as u8, big as u8, minus as u8, minus as u16, minus as u32);
+            print(200 as i8, big as i8, -129 as i8, 40000 as i16, (big * 10000000) as i32);
+            print(-129 as i8 as i32, minus as u8 as i64, 255 as u8 as i8 as i64);
+            let small: u8 = 250;
+            print(small as i8, small as i64, small as u16 + small as u16);
+        }
+    "#);
+    assert_eq!(
+        output,
+        "44\n44\n255\n65535\n4294967295\n-56\n44\n127\n-25536\n-1294967296\n127\n255\n-1\n-6\n250\n500\n"
+    );
+}
+
+#[test]
+fn test_float_casts() {
+    let output = run(r#"
+        fn main() {
+            let hundred = 100 as f64;
+            let x = 399 as f64 / hundred;
+            let huge = (1000000 as f64) * (1000000 as f64) * (1000000 as f64);
+            // 向零取整，超出范围时饱和
+            print(x as i32, -x as i32, huge as i32, -huge as i16, huge as u8, -x as u32);
+            print((30050 as f64 / hundred) as u8, (0 as f64 / 0 as f64) as i32);
+            // f32 只保留 24 位有效数字
+            print(16777217 as f32, 16777217 as f64, 16777217 as f32 as i64);
+        }
+    "#);
+    assert_eq!(
+        output,
+        "3\n-3\n2147483647\n-32768\n255\n0\n255\n0\n16777216\n16777217\n16777216\n"
+    );
+}
+
+#[test]
+fn test_char_bool_and_pointer_casts() {
+    let output = run(r#"
+        fn main() {
+            print('A' as u8, 'A' as i64 as f64, true as i32, false as u8);
+            print(97 as char, 255 as u8 as char, 321 as u8 as char);
+            let code: u32 = 66;
+            print(code as u8 as char);
+
+            let mut value: i32 = 5;
+            let p = unsafe { &mut value as *mut i32 };
+            let q = unsafe { p as *const i32 };
+            print(*q);
+            unsafe {
+                *p = 7;
+            }
+            print(value);
+        }
+    "#);
+    assert_eq!(output, "65\n65\n1\n0\na\nÿ\nA\nB\n5\n7\n");
+}
+
+#[test]
+fn test_invalid_casts() {
+    let errors = check(
+        r#"
+        struct Point { x: i32 }
+        fn convert<T>(x: T) -> i64 { x as i64 }
+        fn main() {
+            let n: i32 = 3;
+            let a = n as bool;
+            let b = n as char;
+            let c = 300 as char;
+            let d = true as f64;
+            let e = Point { x: 1 } as i32;
+            let f = "s" as i32;
+            let r = &n;
+            let g = unsafe { r as *mut i32 };
+            let h = unsafe { r as *const i64 };
+            let i = unsafe { r as usize };
+            let j = r as *const i32;
+            let k = 'a' as f32;
+            let l = n as u8 as char;
+        }
+    "#,
+    );
+    let expected = [
+        (ErrorCode::E0054, "cannot cast `i32` as `bool`"),
+        (
+            ErrorCode::E0604,
+            "only `u8` can be cast as `char`, not `i32`",
+        ),
+        (
+            ErrorCode::E0604,
+            "only `u8` can be cast as `char`, not `i32`",
+        ),
+        (ErrorCode::E0606, "casting `bool` as `f64` is invalid"),
+        (ErrorCode::E0605, "non-primitive cast: `Point` as `i32`"),
+        (ErrorCode::E0605, "non-primitive cast: `string` as `i32`"),
+        (ErrorCode::E0606, "casting `&i32` as `*mut i32` is invalid"),
+        (
+            ErrorCode::E0606,
+            "casting `&i32` as `*const i64` is invalid",
+        ),
+        (ErrorCode::E0606, "casting `&i32` as `usize` is invalid"),
+        (
+            ErrorCode::E0133,
+            "casting `&i32` as `*const i32` requires an unsafe block",
+        ),
+        (ErrorCode::E0606, "casting `char` as `f32` is invalid"),
+    ];
+    let expected: Vec<(Option<ErrorCode>, String)> = expected
+        .iter()
+        .map(|(code, message)| (Some(*code), message.to_string()))
+        .collect();
+    assert_eq!(errors, expected);
+}
@@ -0,0 +1,127 @@
+// Contractus 字符字面量测试
+// `char` 是一个 Unicode 标量值：源码中的多字节字符按 UTF-8 解码，
+// `\u{...}` 转义可以写出任何标量值，多于一个标量值的字面量是错误
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::literal;
+use contractus::{Lexer, TokenKind};
+
+const PROGRAM: &str = "fn is_cjk(c: char) -> bool {
+    let code = c as u32;
+    code >= 19968 && code <= 40959
+}
+
+fn main() {
+    let letters = ['a', 'é', '中', '😀', '\\u{1F600}', '\\u{4e2d}'];
+    for c in letters {
+        print(c, c as u32, is_cjk(c));
+    }
+    print(letters[3] == letters[4], \"caf\\u{e9}\");
+    match letters[2] {
+        '中' => print(\"middle\"),
+        _ => print(\"other\"),
+    }
+}
+";
+
+fn lex_errors(source: &str) -> Vec<(ErrorCode, String, String)> {
+    Lexer::new(source)
+        .tokenize()
+        .unwrap_err()
+        .into_iter()
+        .map(|error| {
+            let text = source[error.span.start..error.span.end].to_string();
+            (error.code, error.message, text)
+        })
+        .collect()
+}
+
+#[test]
+fn test_unicode_chars() {
+    assert_eq!(
+        run(PROGRAM),
+        "a\n97\nfalse\né\n233\nfalse\n中\n20013\ntrue\n😀\n128512\nfalse\n😀\n128512\nfalse\n中\n20013\ntrue\ntrue\ncafé\nmiddle\n"
+    );
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(
+        common::roundtrip(PROGRAM),
+        PROGRAM
+            .replace("\\u{1F600}", "😀")
+            .replace("\\u{4e2d}", "中")
+            .replace("\\u{e9}", "é")
+    );
+}
+
+#[test]
+fn test_char_tokens() {
+    let tokens = Lexer::new("'é' '中' '\\u{0}' '\\u{10_FFFF}' 'x")
+        .tokenize()
+        .unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::CharLiteral('é'),
+            TokenKind::CharLiteral('中'),
+            TokenKind::CharLiteral('\0'),
+            TokenKind::CharLiteral('\u{10FFFF}'),
+            TokenKind::Label("x".into()),
+            TokenKind::Eof,
+        ]
+    );
+    // 记号的位置按字节计算，多字节字符之后的列号包括它的全部字节
+    let tokens = Lexer::new("'中' x").tokenize().unwrap();
+    assert_eq!((tokens[0].span.start, tokens[0].span.end), (0, 5));
+    assert_eq!(tokens[1].span.column, 7);
+}
+
+#[test]
+fn test_unicode_escapes() {
+    assert_eq!(literal::unescape("\\u{48}\\u{49}").unwrap(), "HI");
+    let messages: Vec<String> =
+        literal::unescape("\\u{D800} \\u{110000} \\u41 \\u{} \\u{1234567} \\u{12g}")
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.message)
+            .collect();
+    assert_eq!(
+        messages,
+        [
+            "Invalid unicode escape '\\u{D800}': not a Unicode scalar value",
+            "Invalid unicode escape '\\u{110000}': not a Unicode scalar value",
+            "Invalid unicode escape: expected '{' after '\\u'",
+            "Empty unicode escape '\\u{}'",
+            "Invalid unicode escape: at most 6 hex digits",
+            "Invalid character 'g' in unicode escape",
+        ]
+    );
+}
+
+#[test]
+fn test_more_than_one_char() {
+    let source = "let a = 'ab';\nlet b = 'e\u{301}';\nlet c = '\\u{D800}';\nlet d = 'x';";
+    assert_eq!(
+        lex_errors(source),
+        [
+            (
+                ErrorCode::E0004,
+                "Character literal must be exactly one character at line 1".to_string(),
+                "'ab".to_string()
+            ),
+            (
+                ErrorCode::E0004,
+                "Character literal must be exactly one character at line 2".to_string(),
+                "'e\u{301}".to_string()
+            ),
+            (
+                ErrorCode::E0003,
+                "Invalid unicode escape '\\u{D800}': not a Unicode scalar value in character literal at line 3".to_string(),
+                "\\u{D800}".to_string()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,117 @@
+// Contractus `check` 命令测试
+// 只报告诊断信息：成功时没有输出，出错时退出码为 1 且不生成任何文件
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_check_command() {
+    let dir = env::temp_dir().join(format!("contractus_check_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let run = |name: &str, source: &str| {
+        let file = dir.join(name);
+        fs::write(&file, source).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .arg("check")
+            .arg(&file)
+            .output()
+            .expect("cannot run contractus");
+        (output, file)
+    };
+
+    let (output, _) = run("ok.ctx", "fn main() { print(1 + 2); }");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+
+    let (output, file) = run(
+        "bad.ctx",
+        "fn make<T>() -> i32 { return 0; }\nfn main() { print(make()); }",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "error[E0282] at {}:2:19: cannot infer type parameter `T`",
+            file.display()
+        )),
+        "{}",
+        stderr
+    );
+    assert!(output.stdout.is_empty());
+    assert!(!file.with_extension("ctxb").exists());
+}
+
+#[test]
+fn test_check_project_groups_diagnostics() {
+    let dir = env::temp_dir().join(format!("contractus_check_root_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("geo")).unwrap();
+    fs::write(dir.join("main.ctx"), "fn main() { print(1); }").unwrap();
+    fs::write(
+        dir.join("a.ctx"),
+        "fn f() -> i32 { return x; }\nfn g() -> i32 { return y; }",
+    )
+    .unwrap();
+    fs::write(dir.join("geo/b.ctx"), "fn h() -> i32 { return z; }").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .arg("check")
+        .arg("--root")
+        .arg(&dir)
+        .output()
+        .expect("cannot run contractus");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let a = dir.join("a.ctx").display().to_string();
+    let b = dir.join("geo").join("b.ctx").display().to_string();
+    let expected = format!(
+        "error[E0425] at {a}:1:24: cannot find value `x` in this scope\n\
+         error[E0425] at {a}:2:24: cannot find value `y` in this scope\n\
+         {a}: 2 errors\n\
+         \n\
+         error[E0425] at {b}:1:24: cannot find value `z` in this scope\n\
+         {b}: 1 error\n\
+         For more information about this error, try `contractus --explain E0425`.\n"
+    );
+    assert_eq!(stderr, expected);
+
+    // 只传入口文件时不检查其他文件
+    let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .arg("check")
+        .arg(dir.join("main.ctx"))
+        .output()
+        .expect("cannot run contractus");
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_check_error_limit() {
+    let file = env::temp_dir().join(format!("contractus_check_limit_{}.ctx", std::process::id()));
+    let body: String = (0..25).map(|i| format!("    print(v{});\n", i)).collect();
+    fs::write(&file, format!("fn main() {{\n{}}}", body)).unwrap();
+    let check = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .arg("check")
+            .args(args)
+            .arg(&file)
+            .output()
+            .expect("cannot run contractus");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    // 默认最多输出 20 条错误
+    let stderr = check(&[]);
+    assert_eq!(stderr.matches("error[E0425]").count(), 20);
+    assert!(stderr.contains(
+        "note: 5 previous errors omitted (use `--error-limit <n>` to show more, 0 for all)"
+    ));
+
+    let stderr = check(&["--error-limit", "1"]);
+    assert_eq!(stderr.matches("error[E0425]").count(), 1);
+    assert!(stderr.contains("note: 24 previous errors omitted"));
+
+    let stderr = check(&["--error-limit", "0"]);
+    assert_eq!(stderr.matches("error[E0425]").count(), 25);
+    assert!(!stderr.contains("omitted"));
+}
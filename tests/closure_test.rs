@@ -1,6 +1,10 @@
 // Contractus 闭包测试
-// 测试闭包的各种用法和边界情况
+// 测试闭包的各种用法和边界情况，以及两个后端中捕获变量的语义
 
+mod common;
+
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
 use contractus::{Lexer, Parser};
 
 fn parse_program(input: &str) -> Result<contractus::ast::Program, Vec<contractus::parser::ParseError>> {
@@ -132,4 +136,37 @@ fn test_closure_capture() {
         }
     }
     assert!(result.is_ok());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_closure_captures_by_value() {
+    // 两个后端都按值捕获：之后对变量的修改闭包看不到，经过捕获的可变引用可以修改外面的值
+    let source = "fn main() {
+    let mut count = 1;
+    let show = |step: i32| count + step;
+    count = 10;
+    print(show(1), count);
+    let mut items = [1, 2];
+    let first = || items[0];
+    items[0] = 5;
+    print(first(), items[0]);
+    let mut total = 0;
+    let r = &mut total;
+    let set = |value: i32| *r = value;
+    set(3);
+    print(total);
+}
+";
+    assert_eq!(common::run(source), "2\n10\n1\n5\n3\n");
+
+    // 给捕获的变量赋值不会改变外面的变量，报告为错误
+    let errors = Compiler::new()
+        .source("fn main() {\n    let mut n = 0;\n    let inc = || n += 1;\n    inc();\n    print(n);\n}\n")
+        .check();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].code, Some(ErrorCode::E0594));
+    assert_eq!(
+        errors[0].message,
+        "cannot assign to `n`, as it is captured by a closure"
+    );
+}
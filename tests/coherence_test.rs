@@ -0,0 +1,120 @@
+// Contractus trait 实现的一致性测试
+// 类型对 trait 的实现是 derive 生成或手写的 `<类型>_<trait>` 函数，
+// 同一个实现只能定义一次，不同类型的实现不能同名
+
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+// (错误码, 信息, 注释, 帮助, 出错的位置)
+type Error = (
+    Option<ErrorCode>,
+    String,
+    Vec<String>,
+    Option<String>,
+    (u32, u32),
+);
+
+fn errors(source: &str) -> Vec<Error> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .into_iter()
+        .map(|error| {
+            let at = (error.span.line, error.span.column);
+            (error.code, error.message, error.notes, error.help, at)
+        })
+        .collect()
+}
+
+#[test]
+fn test_duplicate_impls() {
+    let source = "struct Point {
+    x: i32,
+}
+
+fn point_eq(a: Point, b: Point) -> bool {
+    a.x == b.x
+}
+
+fn point_eq(a: Point, b: Point) -> bool {
+    true
+}
+
+fn main() {}
+";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0119),
+            "conflicting implementations of trait `Eq` for type `Point`".to_string(),
+            vec!["the first implementation is at line 5, column 1".to_string()],
+            Some("remove one of the implementations".to_string()),
+            (9, 1)
+        )]
+    );
+
+    // 两个 derive 属性生成同一个实现
+    let source =
+        "#[derive(Debug)]\n#[derive(Clone, Debug)]\nenum Color {\n    Red,\n}\n\nfn main() {}\n";
+    let errors = errors(source);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].1,
+        "conflicting implementations of trait `Debug` for type `Color`"
+    );
+    assert_eq!(errors[0].4, (2, 1));
+}
+
+#[test]
+fn test_overlapping_impls() {
+    let source = "#[derive(Eq)]
+struct HTTPServer {
+    port: i32,
+}
+
+#[derive(Eq, Hash)]
+struct HttpServer {
+    name: string,
+}
+
+fn main() {}
+";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0119),
+            "conflicting implementations of trait `Eq` for types `HTTPServer` and `HttpServer`"
+                .to_string(),
+            vec!["the first implementation is at line 1, column 1".to_string()],
+            Some(
+                "both implementations are named `http_server_eq`; rename one of the types"
+                    .to_string()
+            ),
+            (6, 1)
+        )]
+    );
+}
+
+#[test]
+fn test_distinct_impls() {
+    // 不同的 trait、不同的类型，以及和 trait 无关的同名前缀都不冲突
+    let source = "#[derive(Eq, Debug)]
+struct Point {
+    x: i32,
+}
+
+fn point_hash(p: Point) -> i64 {
+    p.x as i64
+}
+
+fn point_norm(p: Point) -> i32 {
+    p.x
+}
+
+fn main() {
+    let p = Point { x: 2 };
+    print(p.point_eq(p), p.point_hash(), p.point_norm());
+}
+";
+    assert_eq!(errors(source), []);
+}
@@ -0,0 +1,203 @@
+// Contractus 集合类型测试
+// 内建的 Vec、StringBuilder 和 Map：解释器和字节码虚拟机中的 push/pop/remove/append、
+// insert/get/remove/contains_key 和遍历，方法调用时接收者自动取引用，
+// 参数类型和键类型的检查以及契约条件中的修改
+
+mod common;
+
+use common::try_run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_vec() {
+    let output = try_run(
+        r#"
+        fn main() {
+            let mut v = Vec::new();
+            v.push(1);
+            v.push(2);
+            push(&mut v, 3);
+            print(v, len(v), v[2]);
+            let mut total = 0;
+            for x in v {
+                total = total + x;
+            }
+            print(total);
+            print(v.pop(), v.remove(0), v);
+            print(v.pop(), v.pop());
+        }
+    "#,
+    );
+    assert_eq!(
+        output.unwrap(),
+        "[1, 2, 3]\n3\n3\n6\nSome(3)\n1\n[2]\nSome(2)\nNone\n"
+    );
+}
+
+#[test]
+fn test_vec_in_functions() {
+    let output = try_run(
+        r#"
+        fn fill<T>(v: &mut Vec<T>, value: T, count: i32) {
+            for i in 0..count {
+                v.push(value);
+            }
+        }
+
+        fn squares(n: i32) -> Vec<i32> {
+            let mut v: Vec<i32> = Vec::new();
+            for i in 0..n {
+                push(&mut v, i * i);
+            }
+            return v;
+        }
+
+        fn main() {
+            let mut names: Vec<string> = Vec::new();
+            fill(&mut names, "a", 2);
+            print(names, squares(4));
+        }
+    "#,
+    );
+    assert_eq!(output.unwrap(), "[\"a\", \"a\"]\n[0, 1, 4, 9]\n");
+
+    let error = try_run(
+        "fn main() {\n    let mut v = Vec::new();\n    v.push(1);\n    print(v.remove(3));\n}",
+    );
+    assert_eq!(
+        error.unwrap_err(),
+        "index out of bounds: the len is 1 but the index is 3"
+    );
+}
+
+#[test]
+fn test_string_builder() {
+    let output = try_run(
+        r#"
+        fn main() {
+            let mut b = StringBuilder::new();
+            for i in 0..3 {
+                b.append(i);
+                append(&mut b, ", ");
+            }
+            b.append('x');
+            b.append((true, 'y'));
+            print(b, len(b), to_string([1, 2]) + "!");
+        }
+    "#,
+    );
+    assert_eq!(output.unwrap(), "0, 1, 2, x(true, 'y')\n21\n[1, 2]!\n");
+}
+
+#[test]
+fn test_diagnostics() {
+    let errors = check(
+        "fn main() {\n    let v: Vec<i32> = Vec::new();\n    push(v, 1);\n    let mut b = StringBuilder::new();\n    push(&mut b, 1);\n    append(&mut b);\n}",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            (
+                Some(ErrorCode::E0308),
+                "`push` expects `&mut Vec<T>`, found `Vec<i32>`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0308),
+                "`push` expects `&mut Vec<T>`, found `&mut StringBuilder`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0061),
+                "function `append` takes 2 arguments but 1 was supplied".to_string()
+            ),
+        ]
+    );
+
+    // 契约条件不能修改参数
+    let errors = check("fn f(v: &mut Vec<i32>) -> i32\n    requires v.pop() == None,\n{\n    return 0;\n}\nfn main() {}");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, Some(ErrorCode::E0701));
+}
+
+#[test]
+fn test_map() {
+    let output = try_run(
+        r#"
+        fn count(words: [string; 5]) -> Map<string, i32> {
+            let mut counts = Map::new();
+            for word in words {
+                match counts.get(word) {
+                    Some(n) => counts.insert(word, n + 1),
+                    None => counts.insert(word, 1),
+                }
+            }
+            return counts;
+        }
+
+        fn main() {
+            let counts = count(["a", "b", "a", "c", "a"]);
+            print(counts, len(counts), counts.get("a"), get(&counts, "z"));
+            print(counts.contains_key("b"), contains_key(&counts, "q"));
+            for (word, n) in counts {
+                print(word, n);
+            }
+
+            let mut names: Map<i32, string> = Map::new();
+            for i in 0..20 {
+                names.insert(i, to_string(i));
+            }
+            names.insert(3, "three");
+            print(names.remove(0), remove(&mut names, 0), names.get(3), len(names));
+            let mut total = 0;
+            for (i, _) in entries(&names) {
+                total = total + i;
+            }
+            print(total);
+        }
+    "#,
+    );
+    assert_eq!(
+        output.unwrap(),
+        "{\"a\": 3, \"b\": 1, \"c\": 1}\n3\nSome(3)\nNone\ntrue\nfalse\n\
+         a\n3\nb\n1\nc\n1\nSome(\"0\")\nNone\nSome(\"three\")\n19\n190\n"
+    );
+}
+
+#[test]
+fn test_map_diagnostics() {
+    let errors = check(
+        "fn f(m: Map<(i32, i32), i32>) {}\nfn main() {\n    let mut m: Map<string, i32> = Map::new();\n    m.insert([1], 2);\n    insert(m, \"a\", 1);\n}",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            (
+                Some(ErrorCode::E0308),
+                "the type `(i32, i32)` cannot be used as a map key".to_string()
+            ),
+            (
+                Some(ErrorCode::E0308),
+                "`insert` expects a `bool`, integer, `char` or `string` key, found `[i32; 1]`"
+                    .to_string()
+            ),
+            (
+                Some(ErrorCode::E0308),
+                "`insert` expects `&mut Map<K, V>`, found `Map<string, i32>`".to_string()
+            ),
+        ]
+    );
+
+    // 查找可以出现在契约条件中，插入不可以
+    let errors = check("fn f(m: &mut Map<i32, i32>) -> i32\n    requires m.contains_key(1),\n    ensures m.insert(1, 2) == (),\n{\n    return 0;\n}\nfn main() {}");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, Some(ErrorCode::E0701));
+}
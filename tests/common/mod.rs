@@ -0,0 +1,129 @@
+// 集成测试共用的辅助函数，测试文件用 `mod common;` 引入
+// - `execute`：在解释器和字节码虚拟机（O0 和 O2，字节码经过编码和解码）中运行同一个程序，
+//   两者的输出、第一条错误信息和退出码应当一致；`run` 和 `try_run` 是默认宿主下的简写，
+//   `execute_crate` 运行多个模块组成的 crate
+// - `roundtrip`：还原的源码经过格式化的结果
+// - `errors`：`check` 报告的错误码和信息
+#![allow(dead_code)]
+
+use contractus::ast::Program;
+use contractus::diagnostic::{Diagnostic, ErrorCode};
+use contractus::driver::Compiler;
+use contractus::interp::{self, Host, Interpreter};
+use contractus::mir::transform::{self, OptLevel};
+use contractus::module::Crate;
+use contractus::{bytecode, format, mir, module, SemanticAnalyzer};
+
+/// 程序运行一次的结果
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub output: String,
+    pub error: Option<Diagnostic>, // 第一个运行时错误
+    pub exit_code: Option<i32>,
+}
+
+impl Execution {
+    // 运行时错误的信息和位置（行和列）都要相同
+    fn same_as(&self, other: &Execution) -> bool {
+        let error = |execution: &Execution| {
+            execution
+                .error
+                .as_ref()
+                .map(|error| (error.message.clone(), error.span.line, error.span.column))
+        };
+        self.output == other.output
+            && self.exit_code == other.exit_code
+            && error(self) == error(other)
+    }
+}
+
+/// 在解释器和虚拟机中运行 `source` 的 `main`，返回解释器的结果
+pub fn execute(source: &str, host: Host) -> Execution {
+    let program = module::parse_source(source).expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+    execute_program(&program, host)
+}
+
+/// 在两个后端中运行由多个模块组成的 crate，它们合并为一个程序
+pub fn execute_crate(krate: &Crate, host: Host) -> Execution {
+    SemanticAnalyzer::new()
+        .analyze_crate(krate)
+        .expect("semantic analysis failed");
+    execute_program(&krate.merged_program(), host)
+}
+
+// 运行已经通过语义分析的程序
+fn execute_program(program: &Program, host: Host) -> Execution {
+    let expected = interp::with_large_stack(|| {
+        let mut interpreter = Interpreter::with_output(program, Vec::new());
+        interpreter.set_host(host.clone());
+        let result = interpreter.run_main();
+        let exit_code = interpreter.exit_code();
+        Execution {
+            output: String::from_utf8(interpreter.into_output()).unwrap(),
+            error: result.err().map(|errors| errors[0].clone()),
+            exit_code,
+        }
+    });
+
+    for level in [OptLevel::O0, OptLevel::O2] {
+        let lowered = mir::lower_program(program).expect("MIR lowering failed");
+        let mut lowered = mir::monomorphize(&lowered).expect("monomorphization failed");
+        transform::optimize(&mut lowered, level);
+        let module = bytecode::compile(&lowered).expect("bytecode compilation failed");
+        let module = bytecode::decode(&bytecode::encode(&module)).expect("decoding failed");
+        let mut vm = bytecode::Vm::new(&module, Vec::new());
+        vm.set_host(host.clone());
+        let result = vm.run_main();
+        let exit_code = vm.exit_code();
+        let actual = Execution {
+            output: String::from_utf8(vm.into_output()).unwrap(),
+            error: result.err().map(|errors| errors[0].clone()),
+            exit_code,
+        };
+        assert!(
+            actual.same_as(&expected),
+            "the VM differs from the interpreter at {:?}:\n{:?}\n{:?}",
+            level,
+            actual,
+            expected
+        );
+    }
+    expected
+}
+
+/// 两个后端的输出，程序应当正常结束
+pub fn run(source: &str) -> String {
+    let execution = execute(source, Host::default());
+    if let Some(error) = execution.error {
+        panic!("the program failed: {}", error);
+    }
+    execution.output
+}
+
+/// 两个后端的输出，或者第一个运行时错误的信息
+pub fn try_run(source: &str) -> Result<String, String> {
+    let execution = execute(source, Host::default());
+    match execution.error {
+        Some(error) => Err(error.message),
+        None => Ok(execution.output),
+    }
+}
+
+/// 语法分析后还原的源码经过格式化的结果
+pub fn roundtrip(source: &str) -> String {
+    let program = module::parse_source(source).expect("parsing failed");
+    format::format_source(&program.to_source(), &Default::default()).expect("formatting failed")
+}
+
+/// `check` 报告的所有错误的错误码和信息
+pub fn errors(source: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
@@ -0,0 +1,191 @@
+// Contractus 复合赋值测试
+// `+= -= *= /= %= &= |= ^= <<= >>=` 的词法、语法和打印，
+// 以及它们在解释器、虚拟机和 MIR 中都按 `a = a op b` 求值
+
+mod common;
+
+use common::run;
+use contractus::ast::{BinOp, Expr, Item, Statement};
+use contractus::format::{format_source, FormatOptions};
+use contractus::mir::{Rvalue, StatementKind};
+use contractus::{mir, module, Lexer, TokenKind};
+
+#[test]
+fn test_tokens() {
+    let tokens = Lexer::new("+= -= *= /= %= &= |= ^= <<= >>= & && | || ^ << >> <= >=")
+        .tokenize()
+        .unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::PlusAssign,
+            TokenKind::MinusAssign,
+            TokenKind::StarAssign,
+            TokenKind::SlashAssign,
+            TokenKind::PercentAssign,
+            TokenKind::BitAndAssign,
+            TokenKind::BitOrAssign,
+            TokenKind::BitXorAssign,
+            TokenKind::LeftShiftAssign,
+            TokenKind::RightShiftAssign,
+            TokenKind::BitwiseAnd,
+            TokenKind::LogicalAnd,
+            TokenKind::BitwiseOr,
+            TokenKind::LogicalOr,
+            TokenKind::BitwiseXor,
+            TokenKind::LeftShift,
+            TokenKind::RightShift,
+            TokenKind::LessEqual,
+            TokenKind::GreaterEqual,
+            TokenKind::Eof,
+        ]
+    );
+    assert_eq!(TokenKind::RightShiftAssign.to_string(), ">>=");
+}
+
+#[test]
+fn test_parse_and_print() {
+    let source = "fn main() {\n    let mut x = 1;\n    x %= 7;\n    x &= 6;\n    x |= 1;\n    x ^= 3;\n    x <<= 2;\n    x >>= 1;\n}\n";
+    let program = module::parse_source(source).unwrap();
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    let ops: Vec<BinOp> = main
+        .body
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::Expr(stmt) => match &stmt.expr {
+                Expr::CompoundAssign(op, ..) => Some(op.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            BinOp::Mod,
+            BinOp::BitwiseAnd,
+            BinOp::BitwiseOr,
+            BinOp::BitwiseXor,
+            BinOp::LeftShift,
+            BinOp::RightShift,
+        ]
+    );
+
+    // 打印和格式化都保留原来的运算符
+    assert_eq!(
+        format_source(source, &FormatOptions::default()).unwrap(),
+        source
+    );
+    let printed = program.to_source();
+    assert!(printed.contains("x <<= 2;"), "{}", printed);
+    assert!(printed.contains("x >>= 1;"), "{}", printed);
+
+    // 泛型结尾紧跟 `=` 时不是复合赋值
+    let source = "fn main(){let v:Vec<Vec<i32>>=Vec::new();let w:Vec<i32>=Vec::new();}";
+    assert_eq!(
+        format_source(source, &FormatOptions::default()).unwrap(),
+        "fn main() {\n    let v: Vec<Vec<i32>> = Vec::new();\n    let w: Vec<i32> = Vec::new();\n}\n"
+    );
+}
+
+#[test]
+fn test_evaluation() {
+    let output = run(r#"
+        struct Counter {
+            value: i32,
+        }
+
+        fn main() {
+            let mut x = 100;
+            x %= 30;
+            print(x);
+            x &= 6;
+            print(x);
+            x |= 9;
+            print(x);
+            x ^= 5;
+            print(x);
+            x <<= 3;
+            print(x);
+            x >>= 2;
+            print(x);
+
+            let mut bits: i64 = 1;
+            bits <<= 40;
+            print(bits);
+            let mut signed = -64;
+            signed >>= 3;
+            print(signed);
+
+            let mut values = [1, 2, 3];
+            let mut i = 0;
+            while i < 3 {
+                values[i] <<= i;
+                values[i] ^= 1;
+                i += 1;
+            }
+            print(values[0], values[1], values[2]);
+
+            let mut counter = Counter { value: 7 };
+            counter.value %= 4;
+            let r = &mut counter.value;
+            *r |= 8;
+            print(counter.value);
+        }
+    "#);
+    assert_eq!(
+        output,
+        "10\n2\n11\n14\n112\n28\n1099511627776\n-8\n0\n5\n13\n11\n"
+    );
+}
+
+#[test]
+fn test_mir_desugaring() {
+    let program = module::parse_source(
+        r#"
+        fn update(a: i32, b: i32) -> i32 {
+            let mut x = a;
+            x += b;
+            x %= b;
+            x &= b;
+            x |= b;
+            x ^= b;
+            x <<= b;
+            x >>= b;
+            x
+        }
+    "#,
+    )
+    .unwrap();
+    let mir = mir::lower_program(&program).unwrap();
+    let body = mir.body("update").unwrap();
+    // 每个复合赋值都降级为同一个位置上的一次二元运算
+    let ops: Vec<BinOp> = body
+        .blocks
+        .iter()
+        .flat_map(|block| &block.statements)
+        .filter_map(|statement| match &statement.kind {
+            StatementKind::Assign(place, Rvalue::BinaryOp(op, lhs, _, _)) => {
+                assert_eq!(lhs.place(), Some(place));
+                Some(op.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            BinOp::Add,
+            BinOp::Mod,
+            BinOp::BitwiseAnd,
+            BinOp::BitwiseOr,
+            BinOp::BitwiseXor,
+            BinOp::LeftShift,
+            BinOp::RightShift,
+        ]
+    );
+}
@@ -0,0 +1,165 @@
+// Contractus 前端快照测试
+// tests/corpus/ 中的每个 `.ctx` 文件的记号、语法树 JSON 和诊断信息与
+// tests/corpus/snapshots/ 中提交的快照比较，不一致时输出逐行的差异。
+//
+// 修改了前端的输出之后，用 `CONTRACTUS_BLESS=1 cargo test --test corpus_test`
+// 重新生成快照，检查 git diff 后一起提交。不一致的快照旁边会写出 `.new` 文件
+
+use contractus::driver::Compiler;
+use contractus::{module, Lexer};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+
+// 和 `--emit=tokens` 相同：每行一个记号的位置和种类
+fn tokens(source: &str) -> String {
+    match Lexer::new(source).tokenize() {
+        Ok(tokens) => tokens
+            .iter()
+            .map(|token| {
+                format!(
+                    "{}:{}  {:?}\n",
+                    token.span.line, token.span.column, token.kind
+                )
+            })
+            .collect(),
+        Err(errors) => errors
+            .iter()
+            .map(|error| {
+                format!(
+                    "{}:{}  error: {}\n",
+                    error.span.line, error.span.column, error.message
+                )
+            })
+            .collect(),
+    }
+}
+
+// 和 `--emit=ast-json` 相同，宏已经展开；不能解析时为空
+fn ast(source: &str) -> String {
+    match module::parse_source(source) {
+        Ok(program) => program.to_json(),
+        Err(_) => String::new(),
+    }
+}
+
+// `contractus check` 报告的所有诊断信息
+fn diagnostics(source: &str) -> String {
+    Compiler::new()
+        .source(source)
+        .check()
+        .iter()
+        .map(|diagnostic| format!("{}\n", diagnostic))
+        .collect()
+}
+
+// 期望和实际内容的逐行差异，`-` 是快照中的行，`+` 是实际的行，只显示不同的部分和前后两行
+fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    // 最长公共子序列，lcs[i][j] 是 old[i..] 和 new[j..] 的长度
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    let mut out = String::new();
+    let mut last = None;
+    for (k, (tag, line)) in lines.iter().enumerate() {
+        if !changed.iter().any(|&c| c.abs_diff(k) <= 2) {
+            continue;
+        }
+        if last.is_some_and(|last| last + 1 != k) {
+            out.push_str("   ...\n");
+        }
+        out.push_str(&format!(" {} {}\n", tag, line));
+        last = Some(k);
+    }
+    out
+}
+
+// 比较一个快照；设置了 CONTRACTUS_BLESS 时直接更新，返回不一致的说明
+fn check_snapshot(path: &Path, actual: &str) -> Option<String> {
+    if std::env::var_os("CONTRACTUS_BLESS").is_some() {
+        fs::write(path, actual).unwrap();
+        return None;
+    }
+    let pending = PathBuf::from(format!("{}.new", path.display()));
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) if expected == actual => {
+            let _ = fs::remove_file(&pending);
+            return None;
+        }
+        Ok(expected) => expected,
+        Err(_) => String::new(),
+    };
+    fs::write(&pending, actual).unwrap();
+    Some(format!(
+        "snapshot {} differs:\n{}",
+        path.display(),
+        diff(&expected, actual)
+    ))
+}
+
+#[test]
+fn test_corpus_snapshots() {
+    let mut files: Vec<PathBuf> = fs::read_dir(CORPUS)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ctx"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no corpus files in {}", CORPUS);
+
+    let mut failures = Vec::new();
+    for file in &files {
+        let source = fs::read_to_string(file).unwrap();
+        let name = file.file_stem().unwrap().to_string_lossy();
+        let snapshots = Path::new(CORPUS).join("snapshots");
+        let outputs = [
+            ("tokens", tokens(&source)),
+            ("ast.json", ast(&source)),
+            ("diagnostics", diagnostics(&source)),
+        ];
+        for (kind, actual) in outputs {
+            let path = snapshots.join(format!("{}.{}", name, kind));
+            failures.extend(check_snapshot(&path, &actual));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nrun `CONTRACTUS_BLESS=1 cargo test --test corpus_test` to accept the changes",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_diff_shows_changed_lines() {
+    let expected = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let actual = "a\nb\nc\nd\nE\nf\ng\nh\ni\n";
+    assert_eq!(
+        diff(expected, actual),
+        "   c\n   d\n - e\n + E\n   f\n   g\n   h\n + i\n"
+    );
+}
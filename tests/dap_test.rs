@@ -0,0 +1,211 @@
+// Contractus 调试适配器测试
+// 消息的读写，以及通过 `contractus dap` 的一次完整调试会话
+
+use contractus::dap::{self, Json};
+use std::io::{BufReader, Write};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+const PROGRAM: &str = "fn square(n: i32) -> i32 {
+    let result = n * n;
+    return result;
+}
+
+fn main() {
+    let mut total = 0;
+    for i in 0..3 {
+        total += square(i);
+    }
+    print(total);
+}
+";
+
+#[test]
+fn test_messages() {
+    let message = dap::parse(
+        r#"{"seq": 1, "type": "request", "command": "launch",
+        "arguments": {"program": "a\\b \u00e9\ud83d\ude00", "args": [], "stopOnEntry": true}}"#,
+    )
+    .unwrap();
+    let arguments = message.get("arguments").unwrap();
+    assert_eq!(
+        arguments.get("program").and_then(Json::as_str),
+        Some("a\\b é😀")
+    );
+    assert_eq!(
+        arguments.get("stopOnEntry").and_then(Json::as_bool),
+        Some(true)
+    );
+    assert_eq!(message.get("seq").and_then(Json::as_i64), Some(1));
+    assert!(dap::parse("{\"a\": 1,}").is_err());
+    assert!(dap::parse("\"\\ud83d\"").is_err());
+
+    let mut framed = Vec::new();
+    dap::write_message(&mut framed, &message).unwrap();
+    let text = String::from_utf8(framed.clone()).unwrap();
+    assert!(text.starts_with("Content-Length: "));
+    assert!(text.contains(r#""seq":1,"type":"request""#), "{}", text);
+    let read = dap::read_message(&mut &framed[..]).unwrap();
+    assert_eq!(read, Some(message));
+    assert_eq!(dap::read_message(&mut &b""[..]).unwrap(), None);
+}
+
+struct Client {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    seq: i64,
+}
+
+impl Client {
+    fn request(&mut self, command: &str, arguments: &str) -> Json {
+        self.seq += 1;
+        let text = format!(
+            r#"{{"seq":{},"type":"request","command":"{}","arguments":{}}}"#,
+            self.seq, command, arguments
+        );
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", text.len(), text).unwrap();
+        self.stdin.flush().unwrap();
+        let response = self.next("response");
+        assert_eq!(
+            response.get("command").and_then(Json::as_str),
+            Some(command)
+        );
+        assert_eq!(
+            response.get("request_seq").and_then(Json::as_i64),
+            Some(self.seq)
+        );
+        response
+    }
+
+    // 下一条 `type` 为 `kind` 的消息，跳过程序输出之外的其他消息
+    fn next(&mut self, kind: &str) -> Json {
+        loop {
+            let message = dap::read_message(&mut self.stdout).unwrap().unwrap();
+            if message.get("type").and_then(Json::as_str) == Some(kind) {
+                return message;
+            }
+        }
+    }
+
+    fn event(&mut self, name: &str) -> Json {
+        let event = self.next("event");
+        assert_eq!(
+            event.get("event").and_then(Json::as_str),
+            Some(name),
+            "{}",
+            event
+        );
+        event.get("body").cloned().unwrap()
+    }
+}
+
+fn body(response: &Json) -> &Json {
+    assert_eq!(
+        response.get("success"),
+        Some(&Json::Bool(true)),
+        "{}",
+        response
+    );
+    response.get("body").unwrap()
+}
+
+#[test]
+fn test_dap_session() {
+    let dir = std::env::temp_dir().join(format!("contractus_dap_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("demo.ctx");
+    std::fs::write(&file, PROGRAM).unwrap();
+    let path = file.display().to_string().replace('\\', "\\\\");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .arg("dap")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut client = Client {
+        stdin: child.stdin.take().unwrap(),
+        stdout: BufReader::new(child.stdout.take().unwrap()),
+        seq: 0,
+    };
+
+    let capabilities = client.request("initialize", r#"{"adapterID":"contractus"}"#);
+    assert_eq!(
+        body(&capabilities).get("supportsConfigurationDoneRequest"),
+        Some(&Json::Bool(true))
+    );
+    let failed = client.request("launch", r#"{"program":"missing.ctx"}"#);
+    assert_eq!(failed.get("success"), Some(&Json::Bool(false)));
+    body(&client.request("launch", &format!(r#"{{"program":"{}"}}"#, path)));
+    client.event("initialized");
+
+    // 第 1 行移到第 2 行；其他文件的断点不能验证
+    let response = client.request(
+        "setBreakpoints",
+        &format!(
+            r#"{{"source":{{"path":"{}"}},"breakpoints":[{{"line":1}}]}}"#,
+            path
+        ),
+    );
+    assert_eq!(
+        body(&response).to_string(),
+        r#"{"breakpoints":[{"id":1,"verified":true,"line":2}]}"#
+    );
+    let response = client.request(
+        "setBreakpoints",
+        r#"{"source":{"path":"other.ctx"},"breakpoints":[{"line":3}]}"#,
+    );
+    let breakpoint = &body(&response)
+        .get("breakpoints")
+        .unwrap()
+        .as_array()
+        .unwrap()[0];
+    assert_eq!(breakpoint.get("verified"), Some(&Json::Bool(false)));
+    body(&client.request("configurationDone", "{}"));
+
+    let stopped = client.event("stopped");
+    assert_eq!(
+        stopped.get("reason").and_then(Json::as_str),
+        Some("breakpoint")
+    );
+    let trace = client.request("stackTrace", r#"{"threadId":1}"#);
+    let frames = body(&trace).get("stackFrames").unwrap().as_array().unwrap();
+    let names: Vec<_> = frames
+        .iter()
+        .map(|frame| {
+            let name = frame.get("name").and_then(Json::as_str).unwrap();
+            (name, frame.get("line").and_then(Json::as_i64).unwrap())
+        })
+        .collect();
+    assert_eq!(names, [("square", 2), ("main", 9)]);
+
+    let scopes = client.request("scopes", r#"{"frameId":1}"#);
+    let locals = &body(&scopes).get("scopes").unwrap().as_array().unwrap()[0];
+    let reference = locals.get("variablesReference").unwrap().to_string();
+    let variables = client.request(
+        "variables",
+        &format!(r#"{{"variablesReference":{}}}"#, reference),
+    );
+    assert_eq!(
+        body(&variables).to_string(),
+        r#"{"variables":[{"name":"i","value":"0","type":"integer","variablesReference":0},{"name":"total","value":"0","type":"integer","variablesReference":0}]}"#
+    );
+    let result = client.request("evaluate", r#"{"expression":"n","frameId":0}"#);
+    assert_eq!(
+        body(&result).get("result").and_then(Json::as_str),
+        Some("0")
+    );
+
+    // 删除断点后执行到结束
+    let response = client.request(
+        "setBreakpoints",
+        &format!(r#"{{"source":{{"path":"{}"}},"breakpoints":[]}}"#, path),
+    );
+    assert_eq!(body(&response).to_string(), r#"{"breakpoints":[]}"#);
+    body(&client.request("continue", r#"{"threadId":1}"#));
+    let output = client.event("output");
+    assert_eq!(output.get("output").and_then(Json::as_str), Some("5\n"));
+    assert_eq!(client.event("exited").to_string(), r#"{"exitCode":0}"#);
+    client.event("terminated");
+    body(&client.request("disconnect", "{}"));
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,205 @@
+// Contractus MIR 数据流分析测试
+// 测试不动点迭代、活跃变量分析和到达定值分析
+
+use contractus::mir::dataflow::{iterate_to_fixpoint, Liveness, ReachingDefinitions};
+use contractus::mir::{BasicBlock, Body, Local, Location, StatementKind, TerminatorKind};
+use contractus::{Lexer, MirProgram, Parser};
+
+fn lower(input: &str) -> MirProgram {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    contractus::mir::lower_program(&program).expect("lowering failed")
+}
+
+fn local_named(body: &Body, name: &str) -> Local {
+    Local(
+        body.locals
+            .iter()
+            .position(|decl| decl.name.as_deref() == Some(name))
+            .unwrap(),
+    )
+}
+
+// 对指定变量整体赋值的第一个位置
+fn assignment_of(body: &Body, local: Local) -> Location {
+    body.basic_blocks()
+        .find_map(|(block, data)| {
+            data.statements
+                .iter()
+                .position(|stmt| {
+                    matches!(&stmt.kind, StatementKind::Assign(place, _) if place.as_local() == Some(local))
+                })
+                .map(|statement_index| Location {
+                    block,
+                    statement_index,
+                })
+        })
+        .unwrap()
+}
+
+fn return_location(body: &Body) -> Location {
+    let (block, _) = body
+        .basic_blocks()
+        .find(|(_, data)| matches!(data.terminator.kind, TerminatorKind::Return))
+        .unwrap();
+    body.terminator_loc(block)
+}
+
+#[test]
+fn test_liveness_straight_line() {
+    let mir = lower(
+        r#"
+        fn pick(a: i32, b: i32) -> i32 {
+            let x = a + 1;
+            let unused = b * 2;
+            return x;
+        }
+    "#,
+    );
+    let body = mir.body("pick").unwrap();
+    let results = iterate_to_fixpoint(Liveness, body);
+
+    let x = local_named(body, "x");
+    let unused = local_named(body, "unused");
+
+    // 函数入口只有用到的参数是活跃的
+    let entry = results.state_before(Location {
+        block: BasicBlock::START,
+        statement_index: 0,
+    });
+    assert!(entry.contains(1));
+    assert!(entry.contains(2));
+    assert!(!entry.contains(x.index()));
+
+    // unused 赋值之后不再被读取
+    let after_unused = results.state_after(assignment_of(body, unused));
+    assert!(!after_unused.contains(unused.index()));
+    assert!(after_unused.contains(x.index()));
+
+    // return 读取返回值
+    let before_return = results.state_before(return_location(body));
+    assert!(before_return.contains(Local::RETURN.index()));
+    assert_eq!(before_return.count(), 1);
+}
+
+#[test]
+fn test_liveness_through_loop() {
+    let mir = lower(
+        r#"
+        fn sum(n: i32) -> i32 {
+            let mut i = 0;
+            let mut acc = 0;
+            while (i < n) {
+                acc = acc + i;
+                i = i + 1;
+            }
+            return acc;
+        }
+    "#,
+    );
+    let body = mir.body("sum").unwrap();
+    let results = iterate_to_fixpoint(Liveness, body);
+
+    let i = local_named(body, "i");
+    let acc = local_named(body, "acc");
+
+    // 循环头：循环体会再次读取 i、acc 和 n
+    let header = body.block(BasicBlock::START).terminator.successors()[0];
+    let live = results.state_before(Location {
+        block: header,
+        statement_index: 0,
+    });
+    for local in [Local(1), i, acc] {
+        assert!(live.contains(local.index()), "{:?} not live", local);
+    }
+
+    // i 在入口处还没有定值，不活跃
+    let entry = results.state_before(Location {
+        block: BasicBlock::START,
+        statement_index: 0,
+    });
+    assert!(!entry.contains(i.index()));
+    assert!(entry.contains(1));
+}
+
+#[test]
+fn test_reaching_definitions_merge() {
+    let mir = lower(
+        r#"
+        fn choose(flag: bool) -> i32 {
+            let mut x = 1;
+            if (flag) {
+                x = 2;
+            }
+            return x;
+        }
+    "#,
+    );
+    let body = mir.body("choose").unwrap();
+    let results = iterate_to_fixpoint(ReachingDefinitions::new(body), body);
+    let x = local_named(body, "x");
+
+    let state = results.state_before(return_location(body));
+    let defs: Vec<_> = results.analysis.definitions_of(&state, x).collect();
+    assert_eq!(defs.len(), 2);
+
+    // 参数的定值在函数入口
+    let entry = results.entry_set(BasicBlock::START);
+    let flag_defs: Vec<_> = results.analysis.definitions_of(entry, Local(1)).collect();
+    assert_eq!(flag_defs.len(), 1);
+    assert!(flag_defs[0].location.is_none());
+}
+
+#[test]
+fn test_reaching_definitions_kill() {
+    let mir = lower(
+        r#"
+        fn overwrite() -> i32 {
+            let mut x = 1;
+            x = 2;
+            x = 3;
+            return x;
+        }
+    "#,
+    );
+    let body = mir.body("overwrite").unwrap();
+    let results = iterate_to_fixpoint(ReachingDefinitions::new(body), body);
+    let x = local_named(body, "x");
+
+    let state = results.state_before(return_location(body));
+    let defs: Vec<_> = results.analysis.definitions_of(&state, x).collect();
+    assert_eq!(defs.len(), 1);
+
+    // 唯一到达的是最后一次赋值
+    let location = defs[0].location.unwrap();
+    match &body.block(location.block).statements[location.statement_index].kind {
+        StatementKind::Assign(_, rvalue) => assert_eq!(rvalue.to_string(), "const 3"),
+        other => panic!("unexpected statement {:?}", other),
+    }
+}
+
+#[test]
+fn test_reaching_definitions_around_loop() {
+    let mir = lower(
+        r#"
+        fn count() -> i32 {
+            let mut i = 0;
+            while i < 10 {
+                i = i + 1;
+            }
+            return i;
+        }
+    "#,
+    );
+    let body = mir.body("count").unwrap();
+    let results = iterate_to_fixpoint(ReachingDefinitions::new(body), body);
+    let i = local_named(body, "i");
+
+    // 循环头同时能看到初始化和循环体中的赋值
+    let header = body.block(BasicBlock::START).terminator.successors()[0];
+    let defs: Vec<_> = results
+        .analysis
+        .definitions_of(results.entry_set(header), i)
+        .collect();
+    assert_eq!(defs.len(), 2);
+}
@@ -0,0 +1,224 @@
+// Contractus 调试器测试
+// 断点的行映射、单步的停止位置，以及命令行界面的完整会话
+
+use contractus::ast::Program;
+use contractus::debugger::{CommandLine, Resume, Session, StopReason};
+use contractus::interp::{DebugAction, DebugContext, Debugger, Interpreter};
+use contractus::{Lexer, Parser, SemanticAnalyzer};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const PROGRAM: &str = "fn square(n: i32) -> i32 {
+    let result = n * n;
+    return result;
+}
+
+// 入口
+fn main() {
+    let name = \"demo\";
+    let mut total = 0;
+    for i in 0..3 {
+        total += square(i);
+    }
+    print(total);
+}
+";
+
+fn parse(input: &str) -> Program {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+    program
+}
+
+// 每次停下时记录函数、行和原因，然后按给定的方式继续
+struct Recorder {
+    session: Session,
+    resumes: Vec<Resume>,
+    stops: Vec<(String, u32, StopReason)>,
+}
+
+impl Debugger for Recorder {
+    fn before_statement(&mut self, context: &DebugContext<'_, '_>) -> DebugAction {
+        if let Some(reason) = self.session.check(context) {
+            self.stops
+                .push((context.function().to_string(), context.span().line, reason));
+            let resume = self.resumes.get(self.stops.len() - 1);
+            self.session
+                .resume(resume.copied().unwrap_or(Resume::Continue));
+        }
+        DebugAction::Continue
+    }
+}
+
+fn stops(breakpoints: &[u32], resumes: &[Resume]) -> Vec<(String, u32, StopReason)> {
+    let program = parse(PROGRAM);
+    let mut session = Session::new(&program, true);
+    for &line in breakpoints {
+        session.add_breakpoint(line).unwrap();
+    }
+    let mut recorder = Recorder {
+        session,
+        resumes: resumes.to_vec(),
+        stops: Vec::new(),
+    };
+    let mut interpreter = Interpreter::with_output(&program, Vec::new());
+    interpreter.set_debugger(&mut recorder);
+    interpreter.run_main().unwrap();
+    assert_eq!(interpreter.into_output(), b"5\n");
+    recorder.stops
+}
+
+fn stop(function: &str, line: u32, reason: StopReason) -> (String, u32, StopReason) {
+    (function.to_string(), line, reason)
+}
+
+#[test]
+fn test_breakpoint_lines() {
+    let program = parse(PROGRAM);
+    let mut session = Session::new(&program, false);
+    // 空行、注释和函数签名移到其后的第一条语句
+    assert_eq!(session.add_breakpoint(5).unwrap().line, 8);
+    assert_eq!(session.add_breakpoint(1).unwrap().line, 2);
+    assert_eq!(session.add_breakpoint(11).unwrap().line, 11);
+    // 同一行只有一个断点
+    assert_eq!(session.add_breakpoint(6).unwrap().id, 1);
+    assert_eq!(
+        session.add_breakpoint(14),
+        Err("no statement at or after line 14".to_string())
+    );
+    assert!(session.remove_breakpoint(2));
+    assert!(!session.remove_breakpoint(2));
+    let lines: Vec<u32> = session.breakpoints().iter().map(|bp| bp.line).collect();
+    assert_eq!(lines, [8, 11]);
+}
+
+#[test]
+fn test_stepping() {
+    use StopReason::{Breakpoint, Entry, Step};
+
+    // 每次循环都在断点处停下
+    assert_eq!(
+        stops(&[11], &[]),
+        [
+            stop("main", 8, Entry),
+            stop("main", 11, Breakpoint(1)),
+            stop("main", 11, Breakpoint(1)),
+            stop("main", 11, Breakpoint(1)),
+        ]
+    );
+
+    // step 进入 square，finish 回到调用者的下一条语句，next 不进入调用
+    assert_eq!(
+        stops(
+            &[],
+            &[
+                Resume::StepOver,
+                Resume::StepOver,
+                Resume::StepOver,
+                Resume::StepInto,
+                Resume::StepOut,
+                Resume::StepOver,
+            ]
+        ),
+        [
+            stop("main", 8, Entry),
+            stop("main", 9, Step),
+            stop("main", 10, Step),
+            stop("main", 11, Step),
+            stop("square", 2, Step),
+            stop("main", 11, Step),
+            stop("main", 11, Step),
+        ]
+    );
+}
+
+#[test]
+fn test_command_line_session() {
+    let program = parse(PROGRAM);
+    let input =
+        "b demo.ctx:11\nc\nlocals\nbt\ns\np n\nfinish\nd 1\nnext\n\np zz\nxyz\nb other.ctx:3\nc\n";
+    let mut debugger =
+        CommandLine::new(&program, "demo.ctx", PROGRAM, input.as_bytes(), Vec::new());
+    let mut interpreter = Interpreter::with_output(&program, Vec::new());
+    interpreter.set_debugger(&mut debugger);
+    interpreter.run_main().unwrap();
+    assert_eq!(interpreter.into_output(), b"5\n");
+    assert_eq!(
+        String::from_utf8_lossy(debugger.output()),
+        "main at demo.ctx:8
+   8 |     let name = \"demo\";
+(debug) Breakpoint 1 at demo.ctx:11
+(debug) Breakpoint 1, main at demo.ctx:11
+  11 |         total += square(i);
+(debug) i = 0
+name = \"demo\"
+total = 0
+(debug) #0  main at demo.ctx:11
+(debug) square at demo.ctx:2
+   2 |     let result = n * n;
+(debug) n = 0
+(debug) Breakpoint 1, main at demo.ctx:11
+  11 |         total += square(i);
+(debug) (debug) main at demo.ctx:11
+  11 |         total += square(i);
+(debug) main at demo.ctx:13
+  13 |     print(total);
+(debug) error: no variable `zz` in the current scope
+(debug) error: unknown command `xyz`; type `help` for a list of commands
+(debug) error: `other.ctx` is not the entry file `demo.ctx`; only the entry file is debugged
+(debug) "
+    );
+}
+
+#[test]
+fn test_quit_and_end_of_input() {
+    let program = parse(PROGRAM);
+
+    // quit 结束程序，后面的代码不再执行
+    let mut debugger = CommandLine::new(&program, "demo.ctx", PROGRAM, &b"q\n"[..], Vec::new());
+    let mut interpreter = Interpreter::with_output(&program, Vec::new());
+    interpreter.set_debugger(&mut debugger);
+    interpreter.run_main().unwrap();
+    assert!(interpreter.into_output().is_empty());
+
+    // 输入结束时删除断点，执行到结束
+    let input = &b"b 11\n"[..];
+    let mut debugger = CommandLine::new(&program, "demo.ctx", PROGRAM, input, Vec::new());
+    let mut interpreter = Interpreter::with_output(&program, Vec::new());
+    interpreter.set_debugger(&mut debugger);
+    interpreter.run_main().unwrap();
+    assert_eq!(interpreter.into_output(), b"5\n");
+}
+
+#[test]
+fn test_debug_command() {
+    let dir = std::env::temp_dir().join(format!("contractus_debug_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("demo.ctx");
+    std::fs::write(&file, PROGRAM).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .arg("debug")
+        .arg(&file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"b 13\nc\np total\nc\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("demo.ctx:13\n  13 |     print(total);\n(debug) total = 5\n(debug) 5\n"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,201 @@
+// Contractus 派生测试
+// 结构体、枚举和泛型类型上的 `#[derive(Eq, Clone, Debug, Hash)]` 生成的函数，
+// 宏生成的类型上的 derive，属性的诊断，以及展开结果和 JSON 中的属性
+
+mod common;
+
+use common::run;
+use contractus::derive;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{module, Item};
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_struct_derives() {
+    let output = run(r#"
+        #[derive(Eq, Clone, Debug, Hash)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        fn main() {
+            let p = Point { x: 1, y: 2 };
+            let q = p.point_clone();
+            print(point_eq(p, q), p.point_eq(Point { x: 2, y: 1 }));
+            print(point_debug(q));
+            print(point_hash(p) == point_hash(q), point_hash(p) == point_hash(Point { x: 2, y: 1 }));
+        }
+    "#);
+    assert_eq!(output, "true\nfalse\nPoint { x: 1, y: 2 }\ntrue\nfalse\n");
+}
+
+#[test]
+fn test_enum_and_generic_derives() {
+    let output = run(r#"
+        #[derive(Eq, Clone, Debug, Hash)]
+        enum Shape {
+            Circle(i32),
+            Rect(i32, i32),
+            Empty,
+        }
+
+        #[derive(Eq, Debug)]
+        struct Tagged<T> {
+            tag: string,
+            value: T,
+        }
+
+        #[derive(Eq, Clone)]
+        enum HTTPStatus {
+            Ok,
+        }
+
+        fn main() {
+            let s = Shape::Rect(1, 2);
+            print(shape_eq(s, shape_clone(s)), shape_eq(s, Shape::Rect(1, 3)), shape_eq(s, Shape::Empty));
+            print(shape_debug(s), shape_hash(Shape::Empty) == shape_hash(Shape::Empty));
+            let t = Tagged { tag: "id", value: 'x' };
+            print(tagged_eq(t, Tagged { tag: "id", value: 'x' }), tagged_eq(t, Tagged { tag: "id", value: 'y' }));
+            print(http_status_eq(http_status_clone(HTTPStatus::Ok), HTTPStatus::Ok));
+        }
+    "#);
+    assert_eq!(
+        output,
+        "true\nfalse\nfalse\nRect(1, 2)\ntrue\ntrue\nfalse\ntrue\n"
+    );
+    assert_eq!(derive::snake_case("HttpRequest"), "http_request");
+    assert_eq!(derive::snake_case("HTTPServer2"), "http_server2");
+}
+
+// `value.clone()` 调用接收者类型的 `<type>_clone`，接收者可以是引用，泛型类型也一样
+#[test]
+fn test_derived_methods() {
+    let output = run(r#"
+        #[derive(Clone, Eq)]
+        struct Point {
+            x: i32,
+            label: string,
+        }
+
+        #[derive(Clone, Eq)]
+        enum Shape {
+            Dot,
+            Line(i32),
+        }
+
+        #[derive(Eq)]
+        struct Tagged<T> {
+            value: T,
+        }
+
+        fn main() {
+            let a = Point { x: 1, label: "a" };
+            let b = a.clone();
+            print(a.eq(&b), a.eq(Point { x: 2, label: "a" }));
+            let s = Shape::Line(3);
+            let r = &s;
+            print(r.eq(Shape::Line(3)), s.clone().eq(Shape::Dot));
+            print(Tagged { value: 'x' }.eq(Tagged { value: 'x' }));
+        }
+    "#);
+    assert_eq!(output, "true\nfalse\ntrue\nfalse\ntrue\n");
+}
+
+// 宏展开出的类型上的 derive 同样展开
+#[test]
+fn test_derive_in_macro_expansion() {
+    let output = run(r#"
+        macro_rules! record {
+            ($name:ident { $($field:ident: $ty:ty),* }) => {
+                #[derive(Eq, Debug)]
+                struct $name { $($field: $ty),* }
+            };
+        }
+
+        record!(Size { width: i32, height: i32 });
+
+        fn main() {
+            let size = Size { width: 3, height: 4 };
+            print(size.size_eq(size), size_debug(size));
+        }
+    "#);
+    assert_eq!(output, "true\nSize { width: 3, height: 4 }\n");
+
+    // 展开后类型上不再有 derive 属性，生成的函数紧跟在类型之后
+    let program = module::parse_source("#[derive(Debug)]\nstruct Unit {}\nfn main() {}").unwrap();
+    match &program.items[..] {
+        [Item::Struct(def), Item::Function(debug), Item::Function(main)] => {
+            assert!(def.attributes.is_empty());
+            assert_eq!(debug.name, "unit_debug");
+            assert_eq!(main.name, "main");
+        }
+        items => panic!("unexpected items: {:?}", items),
+    }
+    let source = program.to_source();
+    assert!(
+        source.contains("fn unit_debug(value: Unit) -> string {"),
+        "{}",
+        source
+    );
+    assert!(module::parse_source(&source).is_ok());
+
+    let syntax = module::parse_syntax("#[derive(Eq, Hash)]\nenum E { A }").unwrap();
+    assert!(syntax
+        .to_source()
+        .starts_with("#[derive(Eq, Hash)]\nenum E {"));
+    let json = syntax.to_json();
+    assert!(json.contains("\"args\": [\n"), "{}", json);
+}
+
+#[test]
+fn test_diagnostics() {
+    let errors = check(
+        "#[derive(Eq, Ord)]\nstruct P { x: i32 }\n#[derive]\nenum E { A }\n#[derive(Debug)]\nstruct Q { x: i32 }\nfn q_debug(q: Q) -> string {\n    \"\"\n}\nfn main() {}",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            (Some(ErrorCode::E0108), "cannot derive `Ord`".to_string()),
+            (
+                Some(ErrorCode::E0108),
+                "`derive` needs the traits to derive, like `#[derive(Eq)]`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0108),
+                "cannot derive `Debug` for `Q`".to_string()
+            ),
+        ]
+    );
+
+    assert_eq!(
+        check("#[derive(Eq)]\nfn f() {}\nfn main() {}"),
+        vec![(
+            Some(ErrorCode::E0103),
+            "`#[derive]` is only allowed on structs and enums".to_string()
+        )]
+    );
+    assert_eq!(
+        check("#[bench]\nenum E { A }\nfn main() {}"),
+        vec![(
+            Some(ErrorCode::E0103),
+            "`#[bench]` is not allowed on structs and enums".to_string()
+        )]
+    );
+    assert_eq!(
+        check("#[derive(Eq)]\nconst X: i32 = 1;\nfn main() {}"),
+        vec![(
+            Some(ErrorCode::E0103),
+            "attributes are only allowed on functions, structs and enums".to_string()
+        )]
+    );
+}
@@ -0,0 +1,146 @@
+// Contractus 文档测试
+// 代码块的提取（信息字符串、被注释的条目、位置），运行结果，以及 `contractus test --doc`
+
+use contractus::doctest::{self, Expect};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+const SOURCE: &str = "//! Math helpers.
+//!
+//! ```
+//! print(add(1, 2));
+//! ```
+
+/// Adds two numbers.
+///
+/// ```
+/// let x = add(2, 3);
+/// print(missing);
+/// ```
+pub fn add(a: i32, b: i32) -> i32 {
+    return a + b;
+}
+
+/// ```should_fail
+/// divide(1, 0);
+/// ```
+///
+/// ```ctx,ignore
+/// this does not parse
+/// ```
+///
+/// ```text
+/// not a test
+/// ```
+fn divide(a: i32, b: i32) -> i32
+    requires b != 0,
+{
+    return a / b;
+}
+
+fn main() {
+    print(divide(add(1, 2), 1));
+}
+";
+
+#[test]
+fn test_extract_doc_tests() {
+    let tests = doctest::extract(SOURCE).unwrap();
+    let summary: Vec<(String, Expect)> = tests.iter().map(|t| (t.name(), t.expect)).collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("(line 3)".to_string(), Expect::Pass),
+            ("fn add (line 9)".to_string(), Expect::Pass),
+            ("fn divide (line 17)".to_string(), Expect::Fail),
+            ("fn divide (line 21)".to_string(), Expect::Ignore),
+        ]
+    );
+    assert_eq!(tests[1].code, "let x = add(2, 3);\nprint(missing);");
+
+    // 普通注释和 `////` 不是文档
+    assert!(
+        doctest::extract("// ```\n// print(1);\n// ```\n//// ```\nfn main() {}")
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_run_doc_tests() {
+    let tests = doctest::extract(SOURCE).unwrap();
+    // 代码块可以使用文件中的函数，文件自己的 main 被去掉
+    assert!(doctest::run(SOURCE, &tests[0]).is_ok());
+    assert!(doctest::run(SOURCE, &tests[2]).is_ok());
+    assert!(doctest::run(SOURCE, &tests[3]).is_ok());
+
+    // 错误的位置在文档注释中
+    let errors = doctest::run(SOURCE, &tests[1]).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].span.line, errors[0].span.column), (11, 11));
+    assert!(
+        errors[0].message.contains("`missing`"),
+        "{}",
+        errors[0].message
+    );
+
+    // 违反契约是运行时错误；标记为 should_fail 的代码块通过时测试失败
+    let source = "/// ```\n/// print(half(3));\n/// ```\n///\n/// ```should_fail\n/// print(half(4));\n/// ```\nfn half(x: i32) -> i32\n    requires x % 2 == 0,\n{\n    return x / 2;\n}\n";
+    let tests = doctest::extract(source).unwrap();
+    assert!(doctest::run(source, &tests[0]).is_err());
+    let errors = doctest::run(source, &tests[1]).unwrap_err();
+    assert!(
+        errors[0].message.contains("expected to fail"),
+        "{}",
+        errors[0].message
+    );
+
+    // 定义了 main 的代码块不再包装
+    let source =
+        "/// ```\n/// fn main() { print(one()); }\n/// ```\nfn one() -> i32 { return 1; }\n";
+    let tests = doctest::extract(source).unwrap();
+    assert!(doctest::run(source, &tests[0]).is_ok());
+}
+
+#[test]
+fn test_doc_command() {
+    let dir = env::temp_dir().join(format!("contractus_doctest_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("math.ctx");
+    fs::write(&file, SOURCE).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .args(["test", "--doc"])
+        .arg(&file)
+        .output()
+        .expect("cannot run contractus");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let name = file.display();
+    for expected in [
+        "running 4 doc tests".to_string(),
+        format!("test {} - (line 3) ... ok", name),
+        format!("test {} - fn add (line 9) ... FAILED", name),
+        format!("test {} - fn divide (line 21) ... ignored", name),
+        format!("error[E0425] at {}:11:11:", name),
+        "test result: FAILED. 2 passed; 1 failed; 1 ignored".to_string(),
+    ] {
+        assert!(
+            stdout.contains(&expected),
+            "missing `{}` in\n{}",
+            expected,
+            stdout
+        );
+    }
+
+    fs::write(&file, SOURCE.replace("print(missing);", "print(x);")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .args(["test", "--doc"])
+        .arg(&file)
+        .output()
+        .expect("cannot run contractus");
+    assert!(output.status.success());
+
+    fs::remove_dir_all(&dir).ok();
+}
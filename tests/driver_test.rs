@@ -0,0 +1,230 @@
+// Contractus 编译驱动测试
+// 检查各个 emit 阶段返回的产物、出错时返回的诊断信息，以及文件输入带上的文件名
+
+use contractus::bytecode;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::{Artifact, Compiler, ContractMode, Emit, OptLevel};
+use contractus::{Diagnostic, TokenKind};
+use std::fs;
+
+const PROGRAM: &str = r#"
+    fn square(x: i32) -> i32
+        requires x < 100,
+    {
+        return x * x;
+    }
+
+    fn main() {
+        let mut total = 0;
+        for i in 0..4 {
+            total = total + square(i);
+        }
+        print(total);
+    }
+"#;
+
+fn run_object(compiler: Compiler) -> String {
+    let module = match compiler.run().into_result() {
+        Ok(Artifact::Object(module)) => module,
+        other => panic!("expected an object, got {:?}", other),
+    };
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    result.unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_source_to_object() {
+    for level in [OptLevel::O0, OptLevel::O2, OptLevel::Os] {
+        let compiler = Compiler::new().source(PROGRAM).opt_level(level);
+        assert_eq!(run_object(compiler), "14\n", "at {:?}", level);
+    }
+
+    let compiler = Compiler::new()
+        .source(PROGRAM)
+        .bounds_checks(false)
+        .contracts(ContractMode::Off);
+    assert_eq!(run_object(compiler), "14\n");
+}
+
+#[test]
+fn test_emit_stages() {
+    let output = Compiler::new().source(PROGRAM).emit(Emit::Tokens).run();
+    let tokens = output.artifact.unwrap().into_tokens().unwrap();
+    assert_eq!(tokens[0].kind, TokenKind::Fn);
+    assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+
+    let output = Compiler::new().source(PROGRAM).emit(Emit::Hir).run();
+    assert!(output.diagnostics.is_empty());
+    let krate = output.artifact.unwrap().into_crate().unwrap();
+    assert_eq!(krate.root_module().program.items.len(), 2);
+
+    // Ast 阶段不做语义分析
+    let source = "fn main() { return missing; }";
+    let output = Compiler::new().source(source).emit(Emit::Ast).run();
+    assert!(output.artifact.unwrap().into_crate().is_some());
+    assert!(Compiler::new()
+        .source(source)
+        .emit(Emit::Hir)
+        .run()
+        .has_errors());
+
+    let output = Compiler::new().source(PROGRAM).emit(Emit::Mir).run();
+    let mir = output.artifact.unwrap().into_mir().unwrap();
+    assert!(mir.body("main").is_some());
+    assert!(mir.body("square").is_some());
+}
+
+#[test]
+fn test_hir_is_lowered_like_check() {
+    // 解释器执行 Hir 阶段的产物：单态化拒绝的多态递归同样报错，与 `check` 和虚拟机一致
+    let source = "fn nest<T>(x: T, n: i32) -> i32 {
+    if n == 0 {
+        return 0;
+    }
+    return 1 + nest((x, x), n - 1);
+}
+
+fn main() {
+    print(nest(1, 3));
+}
+";
+    let codes = |diagnostics: &[Diagnostic]| -> Vec<Option<ErrorCode>> {
+        diagnostics.iter().map(|error| error.code).collect()
+    };
+    let hir = Compiler::new().source(source).emit(Emit::Hir).run();
+    assert!(hir.artifact.is_none());
+    assert_eq!(codes(&hir.diagnostics), [Some(ErrorCode::E0275)]);
+    let check = Compiler::new().source(source).check();
+    assert_eq!(codes(&check), codes(&hir.diagnostics));
+    let object = Compiler::new().source(source).run();
+    assert_eq!(codes(&object.diagnostics), codes(&hir.diagnostics));
+}
+
+#[test]
+fn test_errors_return_diagnostics() {
+    let output = Compiler::new()
+        .source("fn main() { let x: i32 = missing; }")
+        .run();
+    assert!(output.has_errors());
+    assert!(output.artifact.is_none());
+    assert!(
+        output.diagnostics[0].message.contains("missing"),
+        "{:?}",
+        output.diagnostics
+    );
+
+    let errors = Compiler::new()
+        .source("fn main( {")
+        .run()
+        .into_result()
+        .unwrap_err();
+    assert!(!errors.is_empty());
+
+    let errors = Compiler::new().run().into_result().unwrap_err();
+    assert!(errors[0].message.contains("no input"));
+}
+
+#[test]
+fn test_file_input() {
+    let root = std::env::temp_dir().join(format!("contractus_driver_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let entry = root.join("main.ctx");
+    fs::write(
+        &entry,
+        "fn twice(x: i32) -> i32 { return x * 2; }\nfn main() { print(twice(21)); }",
+    )
+    .unwrap();
+
+    let module = Compiler::new()
+        .file(&entry)
+        .run()
+        .into_result()
+        .unwrap()
+        .into_object()
+        .unwrap();
+    assert_eq!(
+        module.file.as_deref(),
+        Some(entry.display().to_string().as_str())
+    );
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    result.unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "42\n");
+
+    fs::write(&entry, "fn main() { return missing; }").unwrap();
+    let errors = Compiler::new()
+        .file(&entry)
+        .run()
+        .into_result()
+        .unwrap_err();
+    assert!(
+        errors.iter().all(|error| error.file.is_some()),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn test_check_reports_errors_without_codegen() {
+    assert!(Compiler::new().source(PROGRAM).check().is_empty());
+
+    let errors = Compiler::new()
+        .source("fn main() { let x: i32 = missing; }")
+        .check();
+    assert!(errors[0].message.contains("missing"), "{:?}", errors);
+
+    // 语义分析之后的阶段发现的错误
+    let errors = Compiler::new()
+        .source("fn make<T>() -> i32 { return 0; }\nfn main() { print(make()); }")
+        .check();
+    assert_eq!(errors.len(), 1);
+    assert!(
+        errors[0]
+            .message
+            .contains("cannot infer type parameter `T` for call to `make`"),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn test_deterministic_output() {
+    let object = |compiler: Compiler| compiler.run().into_result().unwrap().into_object().unwrap();
+    let module = object(Compiler::new().source(PROGRAM));
+    assert_ne!(module.hash, 0);
+    assert_eq!(
+        bytecode::encode(&module),
+        bytecode::encode(&object(Compiler::new().source(PROGRAM)))
+    );
+    // 哈希随源码和选项变化
+    let other = object(Compiler::new().source(PROGRAM).opt_level(OptLevel::O2));
+    assert_ne!(other.hash, module.hash);
+    let other = object(Compiler::new().source(format!("{}\n", PROGRAM)));
+    assert_ne!(other.hash, module.hash);
+
+    for emit in [Emit::Tokens, Emit::Ast, Emit::Hir, Emit::Mir, Emit::Object] {
+        let compiler = Compiler::new().source(PROGRAM).emit(emit);
+        let output = compiler.verify_determinism().unwrap();
+        assert!(output.artifact.is_some(), "at {:?}", emit);
+    }
+    let output = Compiler::new()
+        .source("fn main() { return missing; }")
+        .verify_determinism()
+        .unwrap();
+    assert!(output.has_errors());
+
+    // 同样的项目在不同的目录中编译，哈希相同
+    let base = std::env::temp_dir().join(format!("contractus_hash_{}", std::process::id()));
+    let hashes: Vec<u64> = ["a", "b"]
+        .iter()
+        .map(|dir| {
+            let root = base.join(dir);
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join("util.ctx"), "pub fn one() -> i32 { return 1; }").unwrap();
+            fs::write(root.join("main.ctx"), "fn main() { print(1); }").unwrap();
+            object(Compiler::new().root(&root)).hash
+        })
+        .collect();
+    assert_eq!(hashes[0], hashes[1]);
+}
@@ -0,0 +1,210 @@
+// Contractus 析构插入测试
+// 测试作用域出口、提前返回、break/continue 上的 drop，以及移出后的析构展开
+
+use contractus::mir::dataflow::{iterate_to_fixpoint, MaybeInitializedLocals};
+use contractus::mir::{Body, Local, TerminatorKind};
+use contractus::{Lexer, MirProgram, Parser};
+
+fn lower(input: &str) -> MirProgram {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    contractus::mir::lower_program(&program).expect("lowering failed")
+}
+
+fn local_named(body: &Body, name: &str) -> Local {
+    Local(
+        body.locals
+            .iter()
+            .position(|decl| decl.name.as_deref() == Some(name))
+            .unwrap(),
+    )
+}
+
+// 析构指定变量的 drop 个数
+fn drop_count(body: &Body, local: Local) -> usize {
+    body.blocks
+        .iter()
+        .filter(|data| {
+            matches!(&data.terminator.kind, TerminatorKind::Drop { place, .. } if place.as_local() == Some(local))
+        })
+        .count()
+}
+
+fn total_drops(body: &Body) -> usize {
+    body.blocks
+        .iter()
+        .filter(|data| matches!(data.terminator.kind, TerminatorKind::Drop { .. }))
+        .count()
+}
+
+#[test]
+fn test_drop_at_scope_exit_in_reverse_order() {
+    let mir = lower(
+        r#"
+        fn f() {
+            let a = "first";
+            let n = 1;
+            let b = "second";
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let (a, b) = (local_named(body, "a"), local_named(body, "b"));
+    assert_eq!(drop_count(body, a), 1);
+    assert_eq!(drop_count(body, b), 1);
+    // 整数不需要析构
+    assert_eq!(total_drops(body), 2);
+
+    let text = body.to_string();
+    let drop_b = text.find(&format!("drop({})", b)).unwrap();
+    let drop_a = text.find(&format!("drop({})", a)).unwrap();
+    assert!(drop_b < drop_a, "{}", text);
+}
+
+#[test]
+fn test_moved_value_is_not_dropped() {
+    let mir = lower(
+        r#"
+        fn take(s: string) -> i32 {
+            return 0;
+        }
+
+        fn f() -> i32 {
+            let s = "owned";
+            let t = s;
+            return take(t);
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let s = local_named(body, "s");
+    let text = body.to_string();
+    assert!(text.contains(&format!("= move {};", s)), "{}", text);
+    assert_eq!(total_drops(body), 0, "{}", text);
+
+    // 被调函数负责析构按值传入的参数
+    let take = mir.body("take").unwrap();
+    assert_eq!(drop_count(take, Local(1)), 1);
+}
+
+#[test]
+fn test_early_return_drops_live_locals() {
+    let mir = lower(
+        r#"
+        fn f(c: bool) -> i32 {
+            let s = "outer";
+            if (c) {
+                let inner = "inner";
+                return 1;
+            }
+            return 2;
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let (s, inner) = (local_named(body, "s"), local_named(body, "inner"));
+    // 两条返回路径都析构 s，提前返回还要析构 inner
+    assert_eq!(drop_count(body, s), 2);
+    assert_eq!(drop_count(body, inner), 1);
+}
+
+#[test]
+fn test_break_and_continue_drop_loop_locals() {
+    let mir = lower(
+        r#"
+        fn f(c: bool) {
+            let outer = "kept";
+            while (c) {
+                let w = "loop";
+                if (c) {
+                    break;
+                }
+                if (c) {
+                    continue;
+                }
+            }
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let (outer, w) = (local_named(body, "outer"), local_named(body, "w"));
+    // break、continue 和正常的循环体结束各析构一次 w；outer 只在函数结束时析构
+    assert_eq!(drop_count(body, w), 3);
+    assert_eq!(drop_count(body, outer), 1);
+}
+
+#[test]
+fn test_conditional_move_uses_drop_flag() {
+    let mir = lower(
+        r#"
+        fn take(s: string) -> i32 {
+            return 0;
+        }
+
+        fn f(c: bool) {
+            let s = "maybe moved";
+            if (c) {
+                take(s);
+            }
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let s = local_named(body, "s");
+    assert_eq!(drop_count(body, s), 1);
+
+    // drop 之前按析构标志分支
+    let (drop_block, _) = body
+        .basic_blocks()
+        .find(|(_, data)| matches!(data.terminator.kind, TerminatorKind::Drop { .. }))
+        .unwrap();
+    let guarded = body.basic_blocks().any(|(_, data)| {
+        matches!(&data.terminator.kind, TerminatorKind::SwitchInt { otherwise, .. } if *otherwise == drop_block)
+    });
+    assert!(guarded, "{}", body);
+    let text = body.to_string();
+    assert!(text.contains("= const false;"), "{}", text);
+    assert!(text.contains("= const true;"), "{}", text);
+}
+
+#[test]
+fn test_assignment_drops_old_value() {
+    let mir = lower(
+        r#"
+        fn f() {
+            let mut s = "old";
+            s = "new";
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let s = local_named(body, "s");
+    // 赋值前析构旧值，作用域结束析构新值
+    assert_eq!(drop_count(body, s), 2);
+}
+
+#[test]
+fn test_maybe_initialized_after_move() {
+    let mir = lower(
+        r#"
+        fn take(s: string) -> i32 {
+            return 0;
+        }
+
+        fn f() -> i32 {
+            let s = "value";
+            return take(s);
+        }
+    "#,
+    );
+    let body = mir.body("f").unwrap();
+    let s = local_named(body, "s");
+    let results = iterate_to_fixpoint(MaybeInitializedLocals, body);
+    let (block, _) = body
+        .basic_blocks()
+        .find(|(_, data)| matches!(data.terminator.kind, TerminatorKind::Call { .. }))
+        .unwrap();
+    let location = body.terminator_loc(block);
+    assert!(results.state_before(location).contains(s.index()));
+    assert!(!results.state_after(location).contains(s.index()));
+}
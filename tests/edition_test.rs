@@ -0,0 +1,65 @@
+// Contractus 版本和特性开关测试
+// 文件开头的 `#![edition(...)]` 选择版本，`#![feature(...)]` 开启实验性的语法，都只对所在的文件有效
+
+mod common;
+
+use common::errors;
+use contractus::diagnostic::ErrorCode;
+use contractus::features::{Edition, Feature};
+use contractus::module;
+
+#[test]
+fn test_edition_attribute() {
+    let program = module::parse_source("#![edition(2025)]\nfn main() {}\n").unwrap();
+    assert_eq!(program.edition(), Edition::E2025);
+    let program = module::parse_source("fn main() {}\n").unwrap();
+    assert_eq!(program.edition(), Edition::E2024);
+
+    let program =
+        module::parse_source("#![edition(2025)]\n#![feature(custom_operators)]\nfn main() {}\n")
+            .unwrap();
+    assert!(program.has_feature(Feature::CustomOperators));
+    assert!(program
+        .to_source()
+        .starts_with("#![edition(2025)]\n#![feature(custom_operators)]\n\nfn main()"));
+
+    for (source, message) in [
+        (
+            "#![edition(2030)]\nfn main() {}\n",
+            "unknown edition `2030`",
+        ),
+        (
+            "#![edition]\nfn main() {}\n",
+            "`#![edition(...)]` takes one edition",
+        ),
+        (
+            "#![edition(2024)]\n#![edition(2025)]\nfn main() {}\n",
+            "the edition of this file is already set",
+        ),
+    ] {
+        let found = errors(source);
+        assert_eq!(found.len(), 1, "{}: {:?}", source, found);
+        assert_eq!(found[0], (Some(ErrorCode::E0705), message.to_string()));
+    }
+
+    let found = errors("#![no_std]\nfn main() {}\n");
+    assert_eq!(found[0].0, Some(ErrorCode::E0702));
+    assert_eq!(found[0].1, "cannot find attribute `no_std`");
+}
+
+#[test]
+fn test_reserved_words() {
+    let source = "fn await(async: i32) -> i32 {\n    let await = async;\n    return await;\n}\nfn main() {\n    print(await(1));\n}\n";
+    assert!(errors(source).is_empty());
+
+    let found = errors(&format!("#![edition(2025)]\n{}", source));
+    let messages: Vec<&str> = found.iter().map(|(_, message)| message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "`await` is a reserved keyword in edition 2025",
+            "`async` is a reserved keyword in edition 2025",
+            "`await` is a reserved keyword in edition 2025",
+        ]
+    );
+}
@@ -0,0 +1,171 @@
+// Contractus 效果标注测试
+// `pure fn` 的函数体没有副作用，类型是 `pure fn(...)` 的参数和变量只接受没有副作用的函数和闭包；
+// `#[no_alloc]` 函数不在堆上分配，也不调用分配的函数
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+#[test]
+fn test_pure_functions() {
+    let source = "pure fn square(x: i32) -> i32 {
+    let mut result = x;
+    result *= x;
+    return result;
+}
+
+fn apply(f: pure fn(i32) -> i32, x: i32) -> i32
+    requires f(x) >= 0,
+{
+    return f(x);
+}
+
+#[no_alloc]
+fn sum(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for value in values {
+        total += value;
+    }
+    return total;
+}
+
+fn main() {
+    let offset = 1;
+    let g: pure fn(i32) -> i32 = square;
+    print(apply(square, 3), apply(|x| x + offset, 3), apply(g, 4));
+    let values = [1, 2, 3];
+    print(sum(&values));
+}
+";
+    assert_eq!(run(source), "9\n4\n16\n6\n");
+}
+
+#[test]
+fn test_impure_pure_functions() {
+    let source = "static mut CALLS: i32 = 0;
+
+fn count() {
+    unsafe {
+        CALLS += 1;
+    }
+}
+
+pure fn f(x: i32) -> i32 {
+    count();
+    return x;
+}
+";
+    let diagnostics = Compiler::new().source(source).check();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, Some(ErrorCode::E0710));
+    assert_eq!(
+        diagnostics[0].message,
+        "function `f` is declared `pure` but it calls `count`"
+    );
+    assert_eq!(
+        diagnostics[0].notes,
+        ["`count` is impure because it writes to static `CALLS` at line 5, column 9"]
+    );
+}
+
+#[test]
+fn test_pure_arguments() {
+    let source = "fn bump(x: i32) -> i32 {
+    print(x);
+    return x + 1;
+}
+
+fn apply(f: pure fn(i32) -> i32, x: i32) -> i32 {
+    return f(x);
+}
+
+fn main() {
+    let f = |x: i32| x;
+    let mut total = 0;
+    print(apply(bump, 1));
+    print(apply(|x| { total += x; x }, 1));
+    print(apply(f, 1));
+    let g: pure fn(i32) -> i32 = |x| { print(x); x };
+}
+";
+    let code = Some(ErrorCode::E0710);
+    assert_eq!(
+        errors(source),
+        [
+            (
+                code,
+                "`apply` expects a pure function, but `bump` calls the builtin `print`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0594),
+                "cannot assign to `total`, as it is captured by a closure".to_string()
+            ),
+            (
+                code,
+                "`apply` expects a pure function, but the closure assigns to `total`".to_string()
+            ),
+            (
+                code,
+                "`apply` expects a pure function, but `f` may have side effects".to_string()
+            ),
+            (
+                code,
+                "expected a pure function, but the closure calls the builtin `print`".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_no_alloc() {
+    let source = "fn label(n: i32) -> string {
+    return \"item \" + to_string(n);
+}
+
+#[no_alloc]
+fn first(n: i32) -> i32 {
+    let s = label(n);
+    return n;
+}
+
+#[no_alloc]
+fn second() -> i32 {
+    let mut values = Vec::new();
+    values.push(1);
+    return 1;
+}
+
+#[no_alloc]
+fn third(a: string, b: string) -> string {
+    return a + b;
+}
+
+#[no_alloc]
+fn fourth(f: fn() -> i32) -> i32 {
+    return f();
+}
+";
+    let diagnostics = Compiler::new().source(source).check();
+    let messages: Vec<&str> = diagnostics
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "function `first` is marked `#[no_alloc]` but it calls `label`",
+            "function `second` is marked `#[no_alloc]` but it calls the builtin `Vec::new`",
+            "function `third` is marked `#[no_alloc]` but it concatenates strings",
+            "function `fourth` is marked `#[no_alloc]` but it calls a function value",
+        ]
+    );
+    assert!(diagnostics
+        .iter()
+        .all(|error| error.code == Some(ErrorCode::E0711)));
+    assert_eq!(
+        diagnostics[0].notes,
+        ["`label` allocates because it calls the builtin `to_string` at line 2, column 22"]
+    );
+}
@@ -0,0 +1,240 @@
+// Contractus `--emit` 测试
+// 通过命令行输出各个阶段的中间结果，检查输出的位置和格式
+
+use contractus::bytecode;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const PROGRAM: &str = r#"
+fn add(a: i32, b: i32) -> i32 {
+    return a + b;
+}
+
+fn main() {
+    print("tab\t");
+    print(add(1, 2));
+}
+"#;
+
+// 写到临时目录中，每个测试用自己的文件名
+fn source_file(name: &str, source: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("contractus_emit_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join(format!("{}.ctx", name));
+    fs::write(&file, source).unwrap();
+    file
+}
+
+fn contractus(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .args(args)
+        .output()
+        .expect("cannot run contractus")
+}
+
+fn emit(kind: &str, file: &Path) -> String {
+    let output = contractus(&[&format!("--emit={}", kind), file.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_emit_text_stages() {
+    let file = source_file("text", PROGRAM);
+
+    let tokens = emit("tokens", &file);
+    assert!(
+        tokens.starts_with("2:1  Fn\n2:4  Ident(\"add\")\n"),
+        "{}",
+        tokens
+    );
+    assert!(tokens.ends_with("  Eof\n"), "{}", tokens);
+
+    // `-Ztoken-text` 从源文件中取出记号的文本
+    let output = contractus(&["--emit=tokens", "-Ztoken-text", file.to_str().unwrap()]);
+    let tokens = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        tokens.contains("7:11  StringLiteral(\"tab\\t\")  `\"tab\\t\"`\n"),
+        "{}",
+        tokens
+    );
+    assert!(tokens.ends_with("  Eof  ``\n"), "{}", tokens);
+
+    let ast = emit("ast", &file);
+    assert!(ast.starts_with("Program {"), "{}", ast);
+    assert!(ast.contains("name: \"add\""), "{}", ast);
+
+    let hir = emit("hir", &file);
+    assert!(hir.starts_with("// module crate ("), "{}", hir);
+
+    let mir = emit("mir", &file);
+    assert!(mir.contains("fn add(_1: i32, _2: i32) -> i32 {"), "{}", mir);
+
+    let asm = emit("asm", &file);
+    assert!(asm.contains("fn main (arity 0"), "{}", asm);
+    assert!(asm.contains("    0000  Load _1"), "{}", asm);
+}
+
+#[test]
+fn test_emit_ast_json() {
+    let file = source_file("json", PROGRAM);
+    let json = emit("ast-json", &file);
+    assert!(json.starts_with("{\n  \"attributes\": [],\n  \"items\": [\n    {\n      \"kind\": \"function\",\n"));
+    assert!(json.contains("\"name\": \"add\""), "{}", json);
+    assert!(json.contains("\"value\": \"tab\\t\""), "{}", json);
+    assert!(json.contains("\"line\": 8,"), "{}", json);
+    // 括号配对
+    let depth = json.chars().fold(0i32, |depth, c| match c {
+        '{' | '[' => depth + 1,
+        '}' | ']' => depth - 1,
+        _ => depth,
+    });
+    assert_eq!(depth, 0);
+
+    // 语法树不经过语义分析，有错误的程序也能输出
+    let file = source_file("unresolved", "fn main() { return missing; }");
+    assert!(emit("ast-json", &file).contains("\"name\": \"missing\""));
+    let output = contractus(&["--emit=hir", file.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_emit_highlighted_tokens() {
+    // 有词法错误的文件也能输出
+    let file = source_file("highlight", "fn main() { # ` }");
+    let json = emit("tokens-json", &file);
+    assert!(
+        json.starts_with(
+            "{\n  \"tokens\": [\n    {\"kind\": \"Fn\", \"text\": \"fn\", \"class\": \"keyword\","
+        ),
+        "{}",
+        json
+    );
+    assert!(json
+        .contains("{\"kind\": \"Error\", \"text\": \"`\", \"class\": \"error\", \"start\": 14,"));
+
+    let csv = emit("tokens-csv", &file);
+    assert!(csv.starts_with("line,column,start,end,kind,class,text\n1,1,0,2,Fn,keyword,fn\n"));
+    assert!(
+        csv.ends_with("1,17,16,17,RightBrace,punctuation,}\n"),
+        "{}",
+        csv
+    );
+}
+
+#[test]
+fn test_emit_grammar() {
+    // 不需要输入文件，和库函数的输出相同
+    let output = contractus(&["--emit=grammar"]);
+    assert!(output.status.success());
+    let ebnf = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(ebnf, contractus::grammar::ebnf());
+    assert!(
+        ebnf.contains("\nprogram            ::= inner_attribute* item*\n"),
+        "{}",
+        ebnf
+    );
+
+    let file = source_file("grammar", "").with_extension("ebnf");
+    let output = contractus(&["--emit=grammar", "-o", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&file).unwrap(), ebnf);
+}
+
+#[test]
+fn test_emit_output_path() {
+    let file = source_file("output", PROGRAM);
+
+    // 字节码默认写到源文件旁边
+    let output = contractus(&["--emit=bytecode", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let default = file.with_extension(bytecode::EXTENSION);
+    let bytes = fs::read(&default).unwrap();
+    assert!(bytecode::decode(&bytes).is_ok());
+
+    // `-o -` 写到标准输出，内容相同
+    let output = contractus(&["--emit=bytecode", file.to_str().unwrap(), "-o", "-"]);
+    assert_eq!(output.stdout, bytes);
+    // 编译两次并比较，结果与一次编译相同
+    let output = contractus(&[
+        "--emit=bytecode",
+        "--verify-determinism",
+        file.to_str().unwrap(),
+        "-o",
+        "-",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, bytes);
+
+    let mir = file.with_extension("mir");
+    let output = contractus(&[
+        "--emit=mir",
+        file.to_str().unwrap(),
+        "-o",
+        mir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(fs::read_to_string(&mir)
+        .unwrap()
+        .contains("fn main() -> () {"));
+}
+
+#[test]
+fn test_emit_errors() {
+    let file = source_file("errors", "fn main() {}");
+    let output = contractus(&["--emit=llvm", file.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown emit kind `llvm`; expected one of tokens, ast,"),
+        "{}",
+        stderr
+    );
+
+    let output = contractus(&[
+        "--emit=wasm",
+        "--target=x86_64-unknown-linux-gnu",
+        file.to_str().unwrap(),
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("`--emit=wasm` needs a WASI target"),
+        "{}",
+        stderr
+    );
+
+    // 没有 `--emit` 时不能指定 `-o`
+    let output = contractus(&[file.to_str().unwrap(), "-o", "out"]);
+    assert!(!output.status.success());
+}
+
+// 需要本机的 C 编译器，缺少时跳过
+#[test]
+fn test_emit_object() {
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        return;
+    }
+    let file = source_file("object", PROGRAM);
+    let output = contractus(&["--emit=obj", file.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let object = fs::read(file.with_extension(if cfg!(target_env = "msvc") {
+        "obj"
+    } else {
+        "o"
+    }))
+    .unwrap();
+    assert!(!object.is_empty());
+}
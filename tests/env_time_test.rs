@@ -0,0 +1,138 @@
+// Contractus env 和 time 模块测试
+// 打开能力时解释器和字节码虚拟机中的 env::get/has、time::now_millis/sleep，
+// 默认（包括沙箱和只打开 io 的宿主）关闭时的运行时错误，以及 C API 的开关
+
+mod common;
+
+use contractus::capi::*;
+use contractus::interp::Host;
+use contractus::sandbox::{self, Limits, Status};
+use std::env;
+use std::ffi::CStr;
+
+// 两个后端的输出或第一条错误信息
+fn run(source: &str, host: Host) -> Result<String, String> {
+    let execution = common::execute(source, host);
+    match execution.error {
+        Some(error) => Err(error.message),
+        None => Ok(execution.output),
+    }
+}
+
+const ENV: &str = "fn show(name: string) {
+    match env::get(name) {
+        Some(value) => println!(\"{name} = {value}\"),
+        None => println!(\"{name} is not set\"),
+    }
+}
+
+fn main() {
+    show(\"CONTRACTUS_TEST_GREETING\");
+    show(\"CONTRACTUS_TEST_MISSING\");
+    print(env::has(\"CONTRACTUS_TEST_GREETING\"));
+}
+";
+
+#[test]
+fn test_env() {
+    env::set_var("CONTRACTUS_TEST_GREETING", "hello");
+    env::remove_var("CONTRACTUS_TEST_MISSING");
+    assert_eq!(
+        run(ENV, Host::unrestricted(Vec::new())),
+        Ok(
+            "CONTRACTUS_TEST_GREETING = hello\nCONTRACTUS_TEST_MISSING is not set\ntrue\n"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_time() {
+    let source = "fn main() {
+    let start = time::now_millis();
+    time::sleep(5);
+    let elapsed = time::now_millis() - start;
+    print(start > 0, elapsed >= 5);
+}
+";
+    assert_eq!(
+        run(source, Host::unrestricted(Vec::new())),
+        Ok("true\ntrue\n".to_string())
+    );
+
+    // 负的等待时间是运行时错误
+    assert_eq!(
+        run(
+            "fn main() {\n    time::sleep(-1);\n}\n",
+            Host::unrestricted(Vec::new())
+        ),
+        Err("cannot sleep for -1 milliseconds".to_string())
+    );
+}
+
+#[test]
+fn test_disabled_by_default() {
+    assert_eq!(
+        run(ENV, Host::default()),
+        Err(
+            "`env` is not available: environment variables are disabled in this environment"
+                .to_string()
+        )
+    );
+    // 只打开 io 不会打开其他能力
+    assert_eq!(
+        run(
+            "fn main() {\n    print(time::now_millis());\n}\n",
+            Host::with_io(Vec::new())
+        ),
+        Err("`time` is not available: the clock is disabled in this environment".to_string())
+    );
+
+    // 沙箱中的程序不能读取环境变量或等待
+    let outcome = sandbox::compile_and_run(
+        "fn main() {\n    time::sleep(60000);\n}\n",
+        &Limits::default(),
+    );
+    assert_eq!(outcome.status, Status::RuntimeError);
+    assert!(
+        outcome.stderr.contains("the clock is disabled"),
+        "{}",
+        outcome.stderr
+    );
+}
+
+#[test]
+fn test_capi_switches() {
+    unsafe {
+        let ctx = contractus_context_new();
+        let source = "fn main() {
+    let found = env::has(\"PATH\");
+    if time::now_millis() < 0 && found {
+        print(\"unreachable\");
+    }
+}";
+        assert_eq!(
+            contractus_compile(ctx, source.as_ptr().cast(), source.len()),
+            CONTRACTUS_OK
+        );
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_RUNTIME_ERROR);
+        let message = CStr::from_ptr(contractus_diagnostic_message(ctx, 0));
+        assert!(message
+            .to_str()
+            .unwrap()
+            .contains("environment variables are disabled"));
+
+        assert_eq!(contractus_set_env(ctx, 1), CONTRACTUS_OK);
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_RUNTIME_ERROR);
+        let message = CStr::from_ptr(contractus_diagnostic_message(ctx, 0));
+        assert!(message.to_str().unwrap().contains("the clock is disabled"));
+
+        assert_eq!(contractus_set_time(ctx, 1), CONTRACTUS_OK);
+        assert_eq!(contractus_run_main(ctx), CONTRACTUS_OK);
+        assert_eq!(
+            contractus_set_env(std::ptr::null_mut(), 1),
+            CONTRACTUS_INVALID_ARGUMENT
+        );
+        contractus_context_free(ctx);
+    }
+}
@@ -0,0 +1,95 @@
+// Contractus 错误码测试
+// 检查错误码的解析和输出格式，以及每个错误码说明中的示例：
+// 出错的示例必须报告这个错误码，改正后的示例不能再报告它
+
+use contractus::bytecode;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+// 说明中的 contractus 代码块，依次是出错的示例和改正后的示例
+fn examples(explanation: &str) -> Vec<String> {
+    explanation
+        .split("```")
+        .skip(1)
+        .step_by(2)
+        .filter_map(|block| block.strip_prefix("contractus\n"))
+        .map(str::to_string)
+        .collect()
+}
+
+// 检查、编译并执行一段源码，返回报告的错误码
+fn codes(source: &str) -> Vec<ErrorCode> {
+    let errors = Compiler::new().source(source).check();
+    let errors = if errors.is_empty() {
+        match Compiler::new().source(source).run().into_result() {
+            Ok(artifact) => {
+                let module = artifact.into_object().unwrap();
+                bytecode::run_with_output(&module, Vec::new())
+                    .0
+                    .err()
+                    .unwrap_or_default()
+            }
+            Err(errors) => errors,
+        }
+    } else {
+        errors
+    };
+    errors.iter().filter_map(|error| error.code).collect()
+}
+
+#[test]
+fn test_parse_error_codes() {
+    for code in ErrorCode::ALL {
+        assert_eq!(code.as_str().parse::<ErrorCode>(), Ok(*code));
+        assert!(!code.title().is_empty());
+        assert!(!code.explanation().is_empty());
+    }
+    assert_eq!("e0308".parse::<ErrorCode>(), Ok(ErrorCode::E0308));
+    assert_eq!("0308".parse::<ErrorCode>(), Ok(ErrorCode::E0308));
+    assert_eq!(
+        "E9999".parse::<ErrorCode>(),
+        Err("`E9999` is not a valid error code".to_string())
+    );
+}
+
+#[test]
+fn test_diagnostics_show_codes() {
+    let errors = Compiler::new()
+        .source("fn main() {\n    print(totl);\n}")
+        .check();
+    assert_eq!(
+        errors[0].to_string(),
+        "error[E0425] at line 2, column 11: cannot find value `totl` in this scope"
+    );
+
+    // 词法错误指向出错的记号
+    let errors = Compiler::new()
+        .source("fn main() {\n    let s = \"open;\n}")
+        .check();
+    assert_eq!(errors[0].code, Some(ErrorCode::E0001));
+    assert_eq!((errors[0].span.line, errors[0].span.column), (2, 13));
+}
+
+#[test]
+fn test_explanation_examples() {
+    for code in ErrorCode::ALL {
+        let examples = examples(code.explanation());
+        // 导入其他模块的示例无法作为单个源码编译
+        if examples.len() != 2 || examples.iter().any(|source| source.contains("import ")) {
+            continue;
+        }
+        let reported = codes(&examples[0]);
+        assert!(
+            reported.contains(code),
+            "the example of {} reports {:?}",
+            code,
+            reported
+        );
+        let reported = codes(&examples[1]);
+        assert!(
+            !reported.contains(code),
+            "the corrected example of {} still reports it",
+            code
+        );
+    }
+}
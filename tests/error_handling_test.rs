@@ -1,6 +1,8 @@
 // Contractus 错误处理测试
 // 测试词法分析器和解析器的错误处理能力
 
+use contractus::diagnostic::{self, Diagnostic, ErrorCode};
+use contractus::span::Span;
 use contractus::{Lexer, Parser};
 
 fn parse_program(input: &str) -> Result<contractus::ast::Program, Vec<contractus::parser::ParseError>> {
@@ -20,9 +22,11 @@ fn test_lexer_error_handling() {
     // 测试词法分析器对无效输入的处理
     let invalid_input = r#"let x = 123abc;"#;
     let lexer = Lexer::new(invalid_input);
-    // 词法分析器应该能够处理无效输入并产生错误标记
-    let tokens = lexer.tokenize();
-    assert!(tokens.is_ok()); // 词法分析器不应该panic
+    // 数字后面的字母是类型后缀，未知的后缀是词法错误，词法分析器不应该panic
+    let errors = lexer.tokenize().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, ErrorCode::E0005);
+    assert_eq!(errors[0].message, "invalid suffix `abc` for number literal at line 1");
 }
 
 #[test]
@@ -116,4 +120,184 @@ fn test_invalid_for_loop() {
     
     let result = parse_program(invalid_program);
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_missing_brace_does_not_cascade() {
+    // 缺少 `}` 时指向没有闭合的 `{`，补上 `}` 后之后的条目照常解析
+    let program = r#"
+fn main() {
+    let x = 1;
+    if x > 0 {
+        print(x);
+
+fn other(a: i32) -> i32 {
+    return a;
+}
+
+struct P { x: i32 }
+"#;
+    let errors = parse_program(program).unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].code, ErrorCode::E0102);
+    assert_eq!(errors[0].message, "unclosed `{` opened here on line 4");
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("insert `}` before `fn` on line 7")
+    );
+
+    // 缺少 `)` 和 `]`
+    let errors = parse_program("fn f() {\n    let a = (1 + 2;\n    g(a, [1, 2);\n}").unwrap_err();
+    let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "unclosed `(` opened here on line 2",
+            "unclosed `[` opened here on line 3"
+        ]
+    );
+    let errors = parse_program("fn f() {\n    g(1, 2\n}").unwrap_err();
+    assert_eq!(errors[0].message, "unclosed `(` opened here on line 2");
+    assert_eq!(errors[0].help.as_deref(), Some("insert `)` before `}` on line 3"));
+    let errors = parse_program("fn f() {\n    g(1);").unwrap_err();
+    assert_eq!(errors[0].help.as_deref(), Some("insert `}` at the end of the file"));
+
+    // 同一个条目中的后续错误不再报告
+    let errors = parse_program("fn f() {\n    let a = (1 + ;\n    let b = [1, 2;\n}").unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+}
+
+#[test]
+fn test_dedup_and_limit_diagnostics() {
+    let error = |line| {
+        Diagnostic::error("cannot find value `x`".to_string(), Span::new(0, 1, line, 1))
+            .with_code(ErrorCode::E0425)
+    };
+    let diagnostics = vec![error(1), error(1), error(2), error(1).with_file("a.ctx".to_string())];
+    let diagnostics = diagnostic::dedup(diagnostics);
+    assert_eq!(diagnostics.len(), 3);
+
+    let warning = Diagnostic::warning("unused".to_string(), Span::new(0, 1, 3, 1));
+    let diagnostics = vec![error(1), error(2), warning, error(4)];
+    let (kept, omitted) = diagnostic::limit(diagnostics.clone(), 1);
+    assert_eq!((kept.len(), omitted), (2, 2));
+    assert!(!kept[1].is_error());
+    assert_eq!(diagnostic::limit(diagnostics, 0).1, 0);
+}
+
+#[test]
+fn test_chained_comparison_and_assignment_in_condition() {
+    let errors = parse_program(
+        r#"
+        fn check(a: i32, b: i32, c: i32) -> bool
+            requires a = 0
+        {
+            let x = 5;
+            if x = 5 {
+                print(x);
+            }
+            while a + 1 < b <= c {}
+            let same = a == b == c;
+            let ok = a < b == (b < c);
+            a < b && b < c
+        }
+    "#,
+    )
+    .unwrap_err();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|e| (e.code, e.message.as_str(), e.help.as_deref().unwrap_or("")))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (ErrorCode::E0111, "cannot use an assignment as a condition", "use `==` to compare: `a == 0`"),
+            (ErrorCode::E0111, "cannot use an assignment as a condition", "use `==` to compare: `x == 5`"),
+            (ErrorCode::E0110, "comparison operators cannot be chained", "split the comparison in two: `a + 1 < b && b <= c`"),
+            (ErrorCode::E0110, "comparison operators cannot be chained", "split the comparison in two: `a == b && b == c`"),
+        ]
+    );
+    // 错误覆盖整个条件
+    assert_eq!((errors[1].span.line, errors[1].span.column), (6, 16));
+    assert_eq!(errors[2].span.end - errors[2].span.start, "a + 1 < b <= c".len());
+}
+
+#[test]
+fn test_generic_arguments_need_turbofish() {
+    let errors = parse_program(
+        r#"
+        fn a() -> usize { std::mem::size_of<i64>() }
+        fn b() { let v = Vec<Vec<i32>>::new(); }
+        fn c() { let m = Map<string, i32>::new(); }
+        fn d() { let p = id<*const i32>(x); }
+    "#,
+    )
+    .unwrap_err();
+    let found: Vec<_> = errors.iter().map(|e| (e.code, e.help.as_deref().unwrap_or(""))).collect();
+    assert_eq!(
+        found,
+        [
+            (ErrorCode::E0112, "add `::` before the generic arguments: `std::mem::size_of::<i64>`"),
+            (ErrorCode::E0112, "add `::` before the generic arguments: `Vec::<Vec<i32>>`"),
+            (ErrorCode::E0112, "add `::` before the generic arguments: `Map::<string, i32>`"),
+            (ErrorCode::E0112, "add `::` before the generic arguments: `id::<*const i32>`"),
+        ]
+    );
+    assert_eq!(errors[0].message, "generic arguments in an expression must be written as `::<...>`");
+    assert_eq!(errors[0].span.end - errors[0].span.start, "<i64>".len());
+
+    // `<` 和 `>` 是比较运算符的情况
+    assert!(parse_program(
+        r#"
+        fn f(a: i32, b: i32, c: i32, d: i32) -> bool { g(a < b, c > (d)) }
+        fn g(x: bool, y: bool) -> bool { x && y }
+        fn h(x: i32, y: i32) -> bool { x < y >> 2 }
+        fn k(n: i32, v: [i32; 3]) -> bool { n < v[0] && v[1] > (n) }
+    "#
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_list_separators() {
+    // 漏写的逗号在下一项的位置报告
+    let cases = [
+        ("struct P { x: i32 y: i32 }", "expected `,` or `}`, found `y`", 19),
+        ("enum E { A(i32 i64), B }", "expected `,` or `)`, found `i64`", 16),
+        ("fn f<T U>() {}", "expected `,` or `>`, found `U`", 8),
+        ("fn f() { g(1 2); }", "expected `,` or `)`, found `2`", 14),
+        ("fn f() { let a = [1, 2 3]; }", "expected `,` or `]`, found `3`", 24),
+        ("fn f() { let p = P { x: 1 y: 2 }; }", "expected `,` or `}`, found `y`", 27),
+        ("fn f() { match x { 1 => 2 _ => 3 } }", "expected `,` or `}`, found `_`", 27),
+        ("fn f() { let c = |a b| a; }", "expected `,` or `|`, found `b`", 21),
+    ];
+    for (source, message, column) in cases {
+        let errors = parse_program(source).unwrap_err();
+        assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+        assert_eq!(errors[0].message, message, "{}", source);
+        assert_eq!(errors[0].span.column, column, "{}", source);
+    }
+
+    // 多行字符串之后漏写逗号时，帮助信息给出下一项所在的行
+    let errors = parse_program("fn f() {\n    g(\"one\ntwo\" \"three\");\n}").unwrap_err();
+    assert_eq!(errors[0].help.as_deref(), Some("insert `,` before `\"three\"` on line 3"));
+
+    // 所有列表都允许末尾的逗号
+    assert!(parse_program(
+        r#"
+        #[repr(C,)]
+        struct P<T,> { x: T, }
+        enum E { A(i32, i64,), B, }
+        fn f<T,>(a: T, b: fn(i32,) -> i32,) -> (i32, i64,) {
+            let p = P { x: "multi
+                line", };
+            let v = g(1, [1, 2,], (3,),);
+            let c = |a, b,| a;
+            let n = std::mem::size_of::<i32,>();
+            match p { P { x, } => 1, }
+            (1, 2,)
+        }
+    "#
+    )
+    .is_ok());
+}
@@ -0,0 +1,111 @@
+// Contractus 返回引用的逃逸分析测试
+// 函数不能返回指向自己的局部变量、按值的参数或临时值的引用；
+// 经过引用参数的位置和全局变量可以返回
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+const PROGRAM: &str = "static LIMIT: i32 = 10;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn first(values: &[i32]) -> &i32 {
+    &values[0]
+}
+
+fn larger(a: &i32, b: &i32) -> &i32 {
+    if *a > *b {
+        return a;
+    }
+    b
+}
+
+fn y_of(p: &Point) -> &i32 {
+    let r = p;
+    &r.y
+}
+
+fn limit() -> &i32 {
+    &LIMIT
+}
+
+fn main() {
+    let values = [4, 8];
+    let p = Point { x: 1, y: 2 };
+    print(*first(&values), *larger(&values[0], &values[1]), *y_of(&p), *limit());
+}
+";
+
+// (错误码, 信息, 出错的源码)
+fn errors(source: &str) -> Vec<(Option<ErrorCode>, String, String)> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .into_iter()
+        .map(|error| {
+            let text = source[error.span.start..error.span.end].to_string();
+            (error.code, error.message, text)
+        })
+        .collect()
+}
+
+#[test]
+fn test_valid_references() {
+    assert_eq!(run(PROGRAM), "4\n8\n2\n10\n");
+}
+
+#[test]
+fn test_reference_to_local() {
+    let source = "fn dangle() -> &i32 {
+    let x = 5;
+    &x
+}
+
+fn field(p: (i32, i32)) -> &i32 {
+    let r = &p.1;
+    if p.0 > 0 {
+        return r;
+    }
+    &p.0
+}
+
+fn main() {}
+";
+    let local = |name: &str| format!("cannot return a reference to local variable `{}`", name);
+    assert_eq!(
+        errors(source),
+        [
+            (Some(ErrorCode::E0515), local("x"), "&x".to_string()),
+            (Some(ErrorCode::E0515), local("p"), "r".to_string()),
+            (Some(ErrorCode::E0515), local("p"), "&p.0".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_reference_to_temporary() {
+    let source = "fn five() -> &i32 {
+    &5
+}
+
+fn pair(a: &i32) -> (&i32, &i32) {
+    (a, &(*a + 1))
+}
+
+fn main() {}
+";
+    let temporary = "cannot return a reference to a temporary value".to_string();
+    assert_eq!(
+        errors(source),
+        [
+            (Some(ErrorCode::E0515), temporary.clone(), "&5".to_string()),
+            (Some(ErrorCode::E0515), temporary, "&(*a + 1)".to_string()),
+        ]
+    );
+}
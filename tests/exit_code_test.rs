@@ -0,0 +1,103 @@
+// Contractus 退出码测试
+// `fn main() -> i32` 的返回值是进程的退出码，`std::process::exit(code)` 提前以给出的退出码结束，
+// `std::process::args()` 是程序参数；解释器、虚拟机、可执行文件的运行时入口和命令行的结果一致
+
+mod common;
+
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::interp::Host;
+use contractus::{bytecode, link, mir, module};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+// 打开 io 后两个后端的输出和退出码
+fn run(source: &str, args: &[&str]) -> (String, Option<i32>) {
+    let host = Host::with_io(args.iter().map(|arg| arg.to_string()).collect());
+    let execution = common::execute(source, host);
+    assert!(execution.error.is_none(), "{:?}", execution.error);
+    (execution.output, execution.exit_code)
+}
+
+const EXIT: &str = "fn check(args: Vec<string>) -> i32 {
+    if len(&args) > 1 {
+        print(\"too many arguments\");
+        std::process::exit(2);
+    }
+    len(&args) as i32
+}
+
+fn main() -> i32 {
+    let code = check(std::process::args());
+    print(\"returning\");
+    code + 40
+}
+";
+
+#[test]
+fn test_main_return_value() {
+    assert_eq!(run(EXIT, &["a"]), ("returning\n".to_string(), Some(41)));
+    assert_eq!(
+        run(EXIT, &["a", "b"]),
+        ("too many arguments\n".to_string(), Some(2))
+    );
+
+    // 返回 `()` 的 main 没有给出退出码，进程以 0 结束
+    assert_eq!(
+        run("fn main() {\n    print(1);\n}\n", &[]),
+        ("1\n".to_string(), None)
+    );
+}
+
+#[test]
+fn test_runtime_entry() {
+    // 可执行文件的 `main` 调用的入口返回进程的退出码
+    let program = module::parse_source("fn main() -> i32 {\n    3 * 7\n}\n").unwrap();
+    let lowered = mir::monomorphize(&mir::lower_program(&program).unwrap()).unwrap();
+    let bytes = bytecode::encode(&bytecode::compile(&lowered).unwrap());
+    let code = unsafe { link::contractus_rt_main(bytes.as_ptr(), bytes.len()) };
+    assert_eq!(code, 21);
+}
+
+#[test]
+fn test_run_command() {
+    let dir = env::temp_dir().join(format!("contractus_exit_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.ctx");
+    fs::write(&file, EXIT).unwrap();
+    for vm in [false, true] {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_contractus"));
+        command.arg("run");
+        if vm {
+            command.arg("--vm");
+        }
+        let output = command
+            .arg(&file)
+            .args(["--", "a"])
+            .output()
+            .expect("cannot run contractus");
+        assert_eq!(output.status.code(), Some(41));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "returning\n");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_main_signature() {
+    for source in [
+        "fn main(n: i32) {}\n",
+        "fn main() -> bool {\n    true\n}\n",
+        "fn main<T>() {}\n",
+    ] {
+        let found = Compiler::new().source(source).check();
+        assert_eq!(found.len(), 1, "{}: {:?}", source, found);
+        assert_eq!(found[0].code, Some(ErrorCode::E0580));
+        assert_eq!(found[0].message, "`main` function has wrong type");
+    }
+
+    for source in ["fn main() {}\n", "fn main() -> i32 {\n    0\n}\n"] {
+        let found = Compiler::new().source(source).check();
+        assert!(found.is_empty(), "{}: {:?}", source, found);
+    }
+}
@@ -0,0 +1,184 @@
+// Contractus format! 和 println! 测试
+// 格式字符串的占位符按顺序、序号或变量名引用参数，格式说明控制宽度、对齐、精度和进制；
+// 格式说明在编译时按参数类型检查，宏展开为运行时的 `fmt::arg` 调用
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::fmt::{self, Arg, Argument, Piece, Spec};
+use contractus::{macros, module};
+
+#[test]
+fn test_placeholders() {
+    let source = "struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {
+    let name = \"Ada\";
+    let count = 3;
+    let message = format!(\"{name} has {} items\", count);
+    println!(\"{}\", message);
+    println!(\"{1}-{0}-{1} {{braces}}\", \"a\", \"b\");
+    println!(\"{} {:?} {:?}\", Point { x: 1, y: 2 }, name, [name]);
+    println!();
+    print(format!(\"plain\"), len(&format!(\"\")));
+}
+";
+    assert_eq!(
+        run(source),
+        "Ada has 3 items\nb-a-b {braces}\nPoint { x: 1, y: 2 } \"Ada\" [\"Ada\"]\n\nplain\n0\n"
+    );
+}
+
+#[test]
+fn test_specs() {
+    let source = "fn main() {
+    let n = 255;
+    let pi = 314159 as f64 / 100000 as f64;
+    println!(\"[{:>8.2}] [{:<6}] [{:^7}] [{:*^9}]\", pi, \"Ada\", \"mid\", n);
+    println!(\"{:x} {:X} {:#b} {:#o} {:x}\", n, n, 5, 8, -n);
+    println!(\"{:08.3} {:+} {:+05} {:.3}\", -pi, 7, -42, \"abcdef\");
+    let boxed = Box::new(16);
+    let r = &n;
+    println!(\"{:#x} {:5}|\", boxed, r);
+}
+";
+    assert_eq!(
+        run(source),
+        "[    3.14] [Ada   ] [  mid  ] [***255***]\n\
+         ff FF 0b101 0o10 -ff\n\
+         -003.142 +7 -0042 abc\n\
+         0x10   255|\n"
+    );
+}
+
+#[test]
+fn test_arguments_evaluated_once() {
+    // 参数按书写的顺序求值一次，不会被占位符的顺序和次数影响，也不会被移走
+    let source = "fn next(counter: &mut i32) -> i32 {
+    *counter += 1;
+    *counter
+}
+
+fn main() {
+    let mut counter = 0;
+    let words = \"kept\";
+    println!(\"{1} {0} {1}\", next(&mut counter), next(&mut counter));
+    println!(\"{} {}\", words, counter);
+    print(words);
+}
+";
+    assert_eq!(run(source), "2 1 2\nkept 2\nkept\n");
+}
+
+#[test]
+fn test_expansion() {
+    let program =
+        module::parse_source("fn main() {\n    println!(\"x = {:>4}\", 1 + 2);\n}\n").unwrap();
+    let text = program.to_source();
+    assert!(text.contains("let arg0__1 = &(1 + 2);"), "{}", text);
+    assert!(
+        text.contains("\"x = \" + fmt::arg(arg0__1, \">4\")"),
+        "{}",
+        text
+    );
+    assert!(macros::BUILTIN_MACROS.contains(&"format"));
+}
+
+#[test]
+fn test_parse_and_format() {
+    assert_eq!(
+        fmt::parse("a{{{}}}{x:>3}{0}"),
+        Ok(vec![
+            Piece::Text("a{".to_string()),
+            Piece::Placeholder(Argument::Next, String::new()),
+            Piece::Text("}".to_string()),
+            Piece::Placeholder(Argument::Name("x".to_string()), ">3".to_string()),
+            Piece::Placeholder(Argument::Index(0), String::new()),
+        ])
+    );
+    assert_eq!(
+        fmt::parse("}"),
+        Err("unmatched `}` in format string".to_string())
+    );
+    assert_eq!(
+        "8.".parse::<Spec>(),
+        Err("expected a precision after `.` in `{:8.}`".to_string())
+    );
+
+    let spec: Spec = "-^+#010x".parse().unwrap();
+    assert_eq!(
+        fmt::format(&spec, Arg::Int(255)),
+        Ok("+0x00000ff".to_string())
+    );
+    assert!(fmt::format(&spec, Arg::Text("a".to_string())).is_err());
+    let spec: Spec = "é>4".parse().unwrap();
+    assert_eq!(
+        fmt::format(&spec, Arg::Text("ü".to_string())),
+        Ok("éééü".to_string())
+    );
+}
+
+#[test]
+fn test_errors() {
+    let check = |body: &str| {
+        Compiler::new()
+            .source(format!("fn main() {{\n    {}\n}}\n", body))
+            .check()
+    };
+    for (body, code, message) in [
+        (
+            "println!(\"{} {}\", 1);",
+            ErrorCode::E0115,
+            "the format string refers to argument 1 but 1 argument was supplied",
+        ),
+        (
+            "println!(\"{}\", 1, 2);",
+            ErrorCode::E0115,
+            "argument 1 is never used in the format string",
+        ),
+        (
+            "let x = 1;\n    println!(x);",
+            ErrorCode::E0115,
+            "`println!` takes a string literal as its first argument",
+        ),
+        (
+            "println!(\"{:z}\", 1);",
+            ErrorCode::E0115,
+            "unknown format `z` in `{:z}`; expected `?`, `x`, `X`, `b` or `o`",
+        ),
+        (
+            "println!(\"{\", 1);",
+            ErrorCode::E0115,
+            "unclosed `{` in format string",
+        ),
+        (
+            "let s = \"a\";\n    println!(\"{:x}\", s);",
+            ErrorCode::E0308,
+            "`{:x}` expects an integer, found `string`",
+        ),
+        (
+            "println!(\"{:.2}\", true);",
+            ErrorCode::E0308,
+            "`{:.2}` expects a float or a string, found `bool`",
+        ),
+        (
+            "println!(\"{:+}\", 'c');",
+            ErrorCode::E0308,
+            "`{:+}` expects a number, found `char`",
+        ),
+    ] {
+        let found = check(body);
+        assert_eq!(found.len(), 1, "{}: {:?}", body, found);
+        assert_eq!(found[0].code, Some(code), "{}", body);
+        assert_eq!(found[0].message, message);
+    }
+
+    // 类型错误指向实参
+    let found = check("println!(\"{:x}\", \"text\");");
+    assert_eq!(found[0].span.column, 22);
+}
@@ -0,0 +1,174 @@
+// Contractus 代码格式化测试
+// 缩进和空格、末尾的逗号、按行宽换行、注释和空行的保留、契约子句的排版，
+// 以及格式化的幂等性：format(format(x)) == format(x)
+
+use contractus::format::{format_source, is_formatted, FormatOptions};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+fn format(source: &str) -> String {
+    format_with_width(source, contractus::format::DEFAULT_WIDTH)
+}
+
+fn format_with_width(source: &str, width: usize) -> String {
+    let output = format_source(source, &FormatOptions { width })
+        .unwrap_or_else(|errors| panic!("cannot format:\n{}\n{:?}", source, errors));
+    let again =
+        format_source(&output, &FormatOptions { width }).expect("formatted source must parse");
+    assert_eq!(output, again, "formatting is not idempotent");
+    output
+}
+
+#[test]
+fn test_format_spacing_and_indentation() {
+    let source =
+        "fn add(a:i32,b:i32)->i32{let c=a+b*-2;if c>0{return c;}else{return !true as i32;}}";
+    assert_eq!(
+        format(source),
+        "fn add(a: i32, b: i32) -> i32 {\n    let c = a + b * -2;\n    if c > 0 {\n        return c;\n    } else {\n        return !true as i32;\n    }\n}\n"
+    );
+
+    let source = "fn main(){let v:Vec<Vec<i32>>=Vec::new();let f=|x:i32|x+1;let r=&mut v;print(f(r.len())[0]);}";
+    assert_eq!(
+        format(source),
+        "fn main() {\n    let v: Vec<Vec<i32>> = Vec::new();\n    let f = |x: i32| x + 1;\n    let r = &mut v;\n    print(f(r.len())[0]);\n}\n"
+    );
+
+    // 比较运算符不是泛型的尖括号
+    assert_eq!(
+        format("fn main() { let a = x<y; let b = x < y && y > z; }"),
+        "fn main() {\n    let a = x < y;\n    let b = x < y && y > z;\n}\n"
+    );
+}
+
+#[test]
+fn test_format_lists() {
+    let source = "struct Point{x:i32,y:i32}\nenum Shape{Circle(i32),Square(i32)}\nfn f(s:Shape)->i32{match s{Shape::Circle(r)=>r,Shape::Square(w)=>{w*w}}}";
+    assert_eq!(
+        format(source),
+        "struct Point {\n    x: i32,\n    y: i32,\n}\nenum Shape {\n    Circle(i32),\n    Square(i32),\n}\nfn f(s: Shape) -> i32 {\n    match s {\n        Shape::Circle(r) => r,\n        Shape::Square(w) => {\n            w * w\n        },\n    }\n}\n"
+    );
+
+    // 写在一行时去掉末尾的逗号，一项的元组保留
+    assert_eq!(
+        format("fn main() { let p = Point { x: 1, y: 2, }; let t = (1,); print(max(1, 2,)); }"),
+        "fn main() {\n    let p = Point { x: 1, y: 2 };\n    let t = (1,);\n    print(max(1, 2));\n}\n"
+    );
+}
+
+#[test]
+fn test_format_wraps_at_width() {
+    let source = "fn main() { print(combine(first_argument, second_argument, third_argument)); }";
+    assert_eq!(
+        format_with_width(source, 40),
+        "fn main() {\n    print(\n        combine(\n            first_argument,\n            second_argument,\n            third_argument,\n        )\n    );\n}\n"
+    );
+    // 放得下时恢复成一行
+    assert_eq!(
+        format(&format_with_width(source, 40)),
+        "fn main() {\n    print(combine(first_argument, second_argument, third_argument));\n}\n"
+    );
+
+    // 只有最后一项以代码块结尾时代码块照常展开
+    assert_eq!(
+        format("fn main() { apply(|x: i32| { let y = x; y }); }"),
+        "fn main() {\n    apply(|x: i32| {\n        let y = x;\n        y\n    });\n}\n"
+    );
+}
+
+#[test]
+fn test_format_contracts() {
+    let source = "fn divide(a: i32, b: i32) -> i32 requires b != 0, ensures result * b <= a { return a / b; }\nstruct Account { balance: i32, invariant self.balance >= 0 }";
+    assert_eq!(
+        format(source),
+        "fn divide(a: i32, b: i32) -> i32\n    requires b != 0,\n    ensures result * b <= a,\n{\n    return a / b;\n}\nstruct Account {\n    balance: i32,\n    invariant self.balance >= 0,\n}\n"
+    );
+
+    // 循环不变式中的 `<` 和 `>` 是比较运算符
+    assert_eq!(
+        format("fn main() { while i < n invariant sum >= 0 { i += 1; } }"),
+        "fn main() {\n    while i < n invariant sum >= 0 {\n        i += 1;\n    }\n}\n"
+    );
+}
+
+#[test]
+fn test_format_attributes() {
+    assert_eq!(
+        format("#  [ bench ] fn run() { work(); }"),
+        "#[bench]\nfn run() {\n    work();\n}\n"
+    );
+}
+
+#[test]
+fn test_format_preserves_comments_and_blank_lines() {
+    let source = "// 模块注释\nfn main() {\n\n    let x = 1;   // 行尾注释\n\n\n\n    /* 块注释 */ print(x);\n    // 结尾的注释\n}\n";
+    assert_eq!(
+        format(source),
+        "// 模块注释\nfn main() {\n    let x = 1; // 行尾注释\n\n    /* 块注释 */ print(x);\n    // 结尾的注释\n}\n"
+    );
+
+    // 单行注释使参数列表每项一行，一项的参数列表不补逗号
+    assert_eq!(
+        format("fn main() { print(max(1, // 第一个\n2)); }"),
+        "fn main() {\n    print(\n        max(\n            1, // 第一个\n            2,\n        )\n    );\n}\n"
+    );
+}
+
+#[test]
+fn test_format_rejects_invalid_source() {
+    let errors = format_source("fn main() { let x = ; }", &FormatOptions::default()).unwrap_err();
+    assert!(!errors.is_empty());
+    assert!(is_formatted("fn main() {}\n", &FormatOptions::default()).unwrap());
+    assert!(!is_formatted("fn main(){}", &FormatOptions::default()).unwrap());
+}
+
+#[test]
+fn test_format_examples_idempotent() {
+    let mut count = 0;
+    for entry in fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/examples")).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "ctx") {
+            let source = fs::read_to_string(&path).unwrap();
+            if contractus::module::parse_source(&source).is_ok() {
+                format(&source);
+                count += 1;
+            }
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn test_fmt_command() {
+    let dir = env::temp_dir().join(format!("contractus_fmt_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.ctx");
+    fs::write(&file, "fn main(){print(1+2);}").unwrap();
+    let fmt = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .arg("fmt")
+            .args(args)
+            .arg(&file)
+            .output()
+            .expect("cannot run contractus")
+    };
+
+    let output = fmt(&["--check"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is not formatted"), "{}", stderr);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "fn main(){print(1+2);}");
+
+    let output = fmt(&[]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "fn main() {\n    print(1 + 2);\n}\n"
+    );
+
+    let output = fmt(&["--check"]);
+    assert!(output.status.success());
+
+    fs::remove_dir_all(&dir).ok();
+}
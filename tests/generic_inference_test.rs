@@ -0,0 +1,181 @@
+// Contractus 泛型函数调用处的类型实参推断测试
+// 类型实参由实参、闭包、结构体字面量的字段和 let 的类型标注推断，不需要写 turbofish；
+// 推断出矛盾的类型或确定不了结果的类型时报告错误
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{module, Lexer, Parser, SemanticAnalyzer};
+
+const PROGRAM: &str = "struct Wrapper<T> {
+    value: T,
+}
+
+fn identity<T>(x: T) -> T {
+    x
+}
+
+fn first<T>(xs: &[T]) -> T {
+    xs[0]
+}
+
+fn map<T, U>(x: T, f: fn(T) -> U) -> U {
+    f(x)
+}
+
+fn unwrap<T>(w: Wrapper<T>) -> T {
+    w.value
+}
+
+fn same<T>(a: T, b: T) -> T {
+    b
+}
+
+fn make<T>() -> Vec<T> {
+    Vec::new()
+}
+
+fn main() {
+    print(identity(5));
+    let a = [7, 8];
+    print(first(&a));
+    print(map(5, |x| x > 2));
+    print(map(20, |x| x + 1));
+    let w = Wrapper { value: 'c' };
+    print(unwrap(w));
+    let x = identity(Wrapper { value: 9 });
+    print(x.value);
+    print(same(1u8, 2));
+    let n: u8 = identity(7);
+    let items: Vec<u8> = make();
+    print(n, len(items));
+}
+";
+
+// (错误码, 信息, 帮助, 出错的源码)
+fn errors(source: &str) -> Vec<(Option<ErrorCode>, String, Option<String>, String)> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .into_iter()
+        .map(|error| {
+            let text = source[error.span.start..error.span.end].to_string();
+            (error.code, error.message, error.help, text)
+        })
+        .collect()
+}
+
+#[test]
+fn test_inferred_calls() {
+    assert_eq!(run(PROGRAM), "5\n7\ntrue\n21\nc\n9\n2\n7\n0\n");
+}
+
+#[test]
+fn test_inferred_types() {
+    let program = module::parse_source(PROGRAM).unwrap();
+    let mut analyzer = SemanticAnalyzer::new();
+    let mut type_of = |source: &str| {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let expr = Parser::new(tokens).parse_standalone_expression().unwrap();
+        analyzer
+            .analyze_expr_type(&program, &expr)
+            .unwrap()
+            .map(|ty| ty.to_string())
+    };
+    assert_eq!(type_of("identity(5)").as_deref(), Some("i32"));
+    assert_eq!(type_of("identity(5u8)").as_deref(), Some("u8"));
+    assert_eq!(type_of("map(1, |x| x > 0)").as_deref(), Some("bool"));
+    assert_eq!(
+        type_of("Wrapper { value: true }").as_deref(),
+        Some("Wrapper<bool>")
+    );
+    assert_eq!(
+        type_of("Wrapper { value: true }.value").as_deref(),
+        Some("bool")
+    );
+    assert_eq!(type_of("make()"), None);
+}
+
+#[test]
+fn test_inference_errors() {
+    let source =
+        "fn same<T>(a: T, b: T) -> T {\n    b\n}\n\nfn main() {\n    print(same(1u8, true));\n}\n";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0308),
+            "expected `u8`, found `bool`".to_string(),
+            Some("type parameter `T` of `same` is `u8` because of an earlier argument".to_string()),
+            "true".to_string()
+        )]
+    );
+
+    let source = "fn identity<T>(x: T) -> T {\n    x\n}\n\nfn main() {\n    let s: string = identity(true);\n}\n";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0308),
+            "expected `string`, found `bool`".to_string(),
+            Some("type parameter `T` of `identity` is `bool` because of the arguments".to_string()),
+            "identity(true)".to_string()
+        )]
+    );
+
+    let source = "fn make<T>() -> Vec<T> {\n    Vec::new()\n}\n\nfn main() {\n    let items = make();\n    make();\n}\n";
+    assert_eq!(
+        errors(source),
+        [
+            (
+                Some(ErrorCode::E0282),
+                "type annotations needed".to_string(),
+                Some("the arguments do not determine type parameter `T` of `make`; give `items` a type annotation".to_string()),
+                "make()".to_string()
+            ),
+            (
+                Some(ErrorCode::E0282),
+                "type annotations needed".to_string(),
+                Some("the arguments do not determine type parameter `T` of `make`; bind the result with a type annotation".to_string()),
+                "make()".to_string()
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_literal_conflicts() {
+    let items =
+        "fn same<T>(a: T, b: T) -> T {\n    b\n}\n\nfn identity<T>(x: T) -> T {\n    x\n}\n\n";
+    let check = |main: &str| errors(&format!("{}fn main() {{\n{}\n}}\n", items, main));
+
+    // 字面量在前或在后，类型参数都由另一个实参决定
+    for call in ["same(1, \"s\")", "same(\"s\", 1)"] {
+        assert_eq!(
+            check(&format!("    print({});", call)),
+            [(
+                Some(ErrorCode::E0308),
+                "expected `string`, found integer".to_string(),
+                Some(
+                    "type parameter `T` of `same` is `string` because of the other arguments"
+                        .to_string()
+                ),
+                "1".to_string()
+            )]
+        );
+    }
+    assert_eq!(
+        check("    let s: string = identity(5);"),
+        [(
+            Some(ErrorCode::E0308),
+            "expected `string`, found integer".to_string(),
+            Some(
+                "type parameter `T` of `identity` is `string` because of the type annotation"
+                    .to_string()
+            ),
+            "5".to_string()
+        )]
+    );
+    // 推断出整数类型时字面量取这个类型
+    assert!(check("    let n: u64 = identity(5);\n    print(same(1, 2i64), n);").is_empty());
+}
@@ -0,0 +1,135 @@
+// Contractus 语法描述测试
+// grammar.rs 中的规则和解析器、词法分析器保持一致
+
+use contractus::grammar::{self, Symbol};
+use contractus::token::{self, TokenKind};
+use contractus::Lexer;
+use std::collections::HashSet;
+
+const PARSER: &str = include_str!("../src/parser.rs");
+
+fn references(rule: &grammar::Rule) -> Vec<Symbol<'static>> {
+    rule.alternatives
+        .iter()
+        .flat_map(|alternative| grammar::symbols(alternative))
+        .collect()
+}
+
+#[test]
+fn test_rules_are_defined_and_reachable() {
+    let mut names = HashSet::new();
+    for rule in grammar::rules() {
+        assert!(
+            names.insert(rule.name),
+            "rule `{}` is defined twice",
+            rule.name
+        );
+    }
+    let classes: Vec<&str> = grammar::TOKEN_CLASSES
+        .iter()
+        .map(|(class, _)| *class)
+        .collect();
+    for rule in grammar::rules() {
+        for symbol in references(rule) {
+            match symbol {
+                Symbol::Rule(name) => assert!(
+                    names.contains(name),
+                    "rule `{}` refers to undefined rule `{}`",
+                    rule.name,
+                    name
+                ),
+                Symbol::TokenClass(class) => assert!(
+                    classes.contains(&class),
+                    "rule `{}` refers to unknown token class `{}`",
+                    rule.name,
+                    class
+                ),
+                Symbol::Terminal(_) => {}
+            }
+        }
+    }
+
+    // 每条规则都能从起始规则到达
+    let mut reached = HashSet::from([grammar::START]);
+    let mut pending = vec![grammar::START];
+    while let Some(name) = pending.pop() {
+        for symbol in references(grammar::rule_named(name).unwrap()) {
+            if let Symbol::Rule(next) = symbol {
+                if reached.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+    }
+    for rule in grammar::rules() {
+        assert!(
+            reached.contains(rule.name),
+            "rule `{}` is unreachable",
+            rule.name
+        );
+    }
+}
+
+#[test]
+fn test_rules_name_parser_methods() {
+    for rule in grammar::rules() {
+        assert!(
+            PARSER.contains(&format!("fn {}(", rule.parser)),
+            "rule `{}` names `{}`, which is not a parser method",
+            rule.name,
+            rule.parser
+        );
+    }
+}
+
+#[test]
+fn test_terminals_are_single_tokens() {
+    for terminal in grammar::terminals() {
+        let tokens = Lexer::new(terminal).tokenize().unwrap();
+        assert_eq!(tokens.len(), 2, "`{}` is not a single token", terminal);
+        // 上下文关键字是普通的标识符，其他的词都是关键字
+        let contextual = grammar::CONTEXTUAL_KEYWORDS.contains(&terminal);
+        assert_eq!(
+            matches!(tokens[0].kind, TokenKind::Ident(_)),
+            contextual,
+            "`{}` lexes as {:?}",
+            terminal,
+            tokens[0].kind
+        );
+    }
+}
+
+#[test]
+fn test_keywords_appear_in_the_grammar() {
+    let terminals = grammar::terminals();
+    let keywords = [token::KEYWORDS, token::TYPE_KEYWORDS, token::BOOL_LITERALS];
+    for (keyword, _) in keywords.iter().flat_map(|table| table.iter()) {
+        assert!(
+            terminals.contains(keyword),
+            "keyword `{}` does not appear in the grammar",
+            keyword
+        );
+    }
+    for keyword in grammar::CONTEXTUAL_KEYWORDS {
+        assert!(terminals.contains(keyword), "`{}` is not used", keyword);
+    }
+}
+
+#[test]
+fn test_ebnf_output() {
+    let ebnf = grammar::ebnf();
+    assert!(ebnf.starts_with("/* Contractus grammar in W3C EBNF notation"));
+    assert!(ebnf.contains("\n/* Expressions, from the lowest precedence to the highest */\n"));
+    // 候选对齐在 `::=` 之下，约束写在规则之前
+    assert!(ebnf.contains("\nattribute_arg      ::= IDENT\n                     | integer_type\n"));
+    assert!(ebnf.contains("/* fields come before invariants */\nstruct "));
+    assert_eq!(
+        grammar::symbols("\"(\" pattern_list? \")\" | IDENT"),
+        vec![
+            Symbol::Terminal("("),
+            Symbol::Rule("pattern_list"),
+            Symbol::Terminal(")"),
+            Symbol::TokenClass("IDENT"),
+        ]
+    );
+}
@@ -0,0 +1,126 @@
+// Contractus 语法高亮测试
+// 记号的高亮类别，以及 JSON 和 CSV 的输出格式
+
+use contractus::highlight::{self, HighlightClass};
+
+// 每个记号的文本和类别
+fn classes(source: &str) -> Vec<(String, &'static str)> {
+    highlight::classify(source)
+        .into_iter()
+        .map(|token| (token.text, token.class.name()))
+        .collect()
+}
+
+#[test]
+fn test_token_classes() {
+    let source = "// 入口
+fn main() requires LIMIT > 0 {
+    let p: Point = origin();
+    'outer: while true { break 'outer; }
+    print!(\"{}\", 'c' as u8);
+}
+";
+    let expected = [
+        ("// 入口", "comment"),
+        ("fn", "keyword"),
+        ("main", "function"),
+        ("(", "punctuation"),
+        (")", "punctuation"),
+        ("requires", "keyword"),
+        ("LIMIT", "constant"),
+        (">", "operator"),
+        ("0", "number"),
+        ("{", "punctuation"),
+        ("let", "keyword"),
+        ("p", "variable"),
+        (":", "punctuation"),
+        ("Point", "type"),
+        ("=", "operator"),
+        ("origin", "function"),
+        ("(", "punctuation"),
+        (")", "punctuation"),
+        (";", "punctuation"),
+        ("'outer", "label"),
+        (":", "punctuation"),
+        ("while", "keyword"),
+        ("true", "boolean"),
+        ("{", "punctuation"),
+        ("break", "keyword"),
+        ("'outer", "label"),
+        (";", "punctuation"),
+        ("}", "punctuation"),
+        ("print", "macro"),
+        ("!", "operator"),
+        ("(", "punctuation"),
+        ("\"{}\"", "string"),
+        (",", "punctuation"),
+        ("'c'", "char"),
+        ("as", "keyword"),
+        ("u8", "type"),
+        (")", "punctuation"),
+        (";", "punctuation"),
+        ("}", "punctuation"),
+    ];
+    let expected: Vec<(String, &str)> = expected
+        .iter()
+        .map(|(text, class)| (text.to_string(), *class))
+        .collect();
+    assert_eq!(classes(source), expected);
+
+    // 名为 `invariant` 的字段不是关键字
+    let fields = classes("struct S { invariant: i32, invariant x > 0 }");
+    assert_eq!(fields[3], ("invariant".to_string(), "variable"));
+    assert_eq!(fields[7], ("invariant".to_string(), "keyword"));
+}
+
+#[test]
+fn test_lex_errors_are_classified() {
+    // 出错的字符之后继续分类
+    let tokens = highlight::classify("let s = `x`; /* 未闭合");
+    let kinds: Vec<(&str, HighlightClass)> = tokens
+        .iter()
+        .map(|token| (token.kind.as_str(), token.class))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("Let", HighlightClass::Keyword),
+            ("Ident", HighlightClass::Variable),
+            ("Assign", HighlightClass::Operator),
+            ("Error", HighlightClass::Error),
+            ("Ident", HighlightClass::Variable),
+            ("Error", HighlightClass::Error),
+            ("Semicolon", HighlightClass::Punctuation),
+            ("Comment", HighlightClass::Comment),
+        ]
+    );
+    assert_eq!(tokens[3].span.column, 9);
+    assert_eq!(tokens[7].text, "/* 未闭合");
+}
+
+#[test]
+fn test_json_and_csv_output() {
+    let tokens = highlight::classify("x = \"a,\\\"b\";");
+    assert_eq!(
+        highlight::to_json(&tokens),
+        r#"{
+  "tokens": [
+    {"kind": "Ident", "text": "x", "class": "variable", "start": 0, "end": 1, "line": 1, "column": 1},
+    {"kind": "Assign", "text": "=", "class": "operator", "start": 2, "end": 3, "line": 1, "column": 3},
+    {"kind": "StringLiteral", "text": "\"a,\\\"b\"", "class": "string", "start": 4, "end": 11, "line": 1, "column": 5},
+    {"kind": "Semicolon", "text": ";", "class": "punctuation", "start": 11, "end": 12, "line": 1, "column": 12}
+  ]
+}
+"#
+    );
+    assert_eq!(highlight::to_json(&[]), "{\n  \"tokens\": []\n}\n");
+    assert_eq!(
+        highlight::to_csv(&tokens),
+        "line,column,start,end,kind,class,text
+1,1,0,1,Ident,variable,x
+1,3,2,3,Assign,operator,=
+1,5,4,11,StringLiteral,string,\"\"\"a,\\\"\"b\"\"\"
+1,12,11,12,Semicolon,punctuation,;
+"
+    );
+}
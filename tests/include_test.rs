@@ -0,0 +1,188 @@
+// Contractus 文件包含测试
+// `include!` 拼接源码、`include_str!`/`include_bytes!` 嵌入文件内容，
+// 路径相对于写着调用的文件，以及被拼接的代码中的诊断信息指向所在的文件
+
+use contractus::bytecode;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::source_map::{SourceMap, INCLUDE_BASE};
+use contractus::Span;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 在临时目录中创建一个项目，返回项目根目录
+fn create_project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!(
+        "contractus_include_{}_{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&root);
+    for (path, contents) in files {
+        let file = root.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, contents).unwrap();
+    }
+    root
+}
+
+fn run(root: &Path) -> String {
+    let module = Compiler::new()
+        .file(root.join("main.ctx"))
+        .run()
+        .into_result()
+        .expect("compilation failed")
+        .into_object()
+        .unwrap();
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    result.expect("the VM failed");
+    String::from_utf8(output).unwrap()
+}
+
+fn check(root: &Path) -> Vec<(Option<ErrorCode>, String, u32, String)> {
+    Compiler::new()
+        .file(root.join("main.ctx"))
+        .check()
+        .into_iter()
+        .map(|error| {
+            let file = PathBuf::from(error.file.unwrap_or_default());
+            let name = file
+                .strip_prefix(root)
+                .unwrap_or(&file)
+                .display()
+                .to_string();
+            (error.code, error.message, error.span.line, name)
+        })
+        .collect()
+}
+
+#[test]
+fn test_include_source() {
+    let root = create_project(
+        "source",
+        &[
+            (
+                "main.ctx",
+                "include!(\"parts/shapes.ctx\");\n\nfn main() {\n    print(area(Rect { w: 3, h: 4 }) + include!(\"parts/offset.ctx\"));\n}\n",
+            ),
+            // 被拼接的文件中的路径相对于它自己
+            (
+                "parts/shapes.ctx",
+                "struct Rect { w: i32, h: i32 }\ninclude!(\"area.ctx\");\n",
+            ),
+            ("parts/area.ctx", "fn area(r: Rect) -> i32 {\n    r.w * r.h\n}\n"),
+            ("parts/offset.ctx", "100 - 58\n"),
+        ],
+    );
+    assert_eq!(run(&root), "54\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_include_str_and_bytes() {
+    let root = create_project(
+        "embed",
+        &[
+            (
+                "main.ctx",
+                "fn main() {\n    let text = include_str!(\"data/greeting.txt\");\n    print(text, len(text));\n    let bytes = include_bytes!(\"data/blob.bin\");\n    print(len(bytes), bytes[0], bytes[2]);\n}\n",
+            ),
+            ("data/greeting.txt", "say \"hi\"\t\u{e9}"),
+        ],
+    );
+    fs::write(root.join("data/blob.bin"), [0, 255, 7]).unwrap();
+    assert_eq!(run(&root), "say \"hi\"\t\u{e9}\n11\n3\n0\n7\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+// 语法分析和语义分析的错误都指向被拼接的文件中的位置
+#[test]
+fn test_errors_in_included_files() {
+    let root = create_project(
+        "located",
+        &[
+            (
+                "main.ctx",
+                "include!(\"lib/util.ctx\");\n\nfn main() {\n    print(twice(1));\n}\n",
+            ),
+            (
+                "lib/util.ctx",
+                "fn twice(x: i32) -> i32 {\n    x * factor\n}\n",
+            ),
+        ],
+    );
+    let util = PathBuf::from("lib").join("util.ctx").display().to_string();
+    assert_eq!(
+        check(&root),
+        vec![(
+            Some(ErrorCode::E0425),
+            "cannot find value `factor` in this scope".to_string(),
+            2,
+            util.clone()
+        )]
+    );
+
+    fs::write(
+        root.join("lib/util.ctx"),
+        "fn twice(x: i32) -> i32 {\n    x *\n}\n",
+    )
+    .unwrap();
+    let errors = check(&root);
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].2, &errors[0].3), (3, &util));
+    fs::remove_dir_all(&root).unwrap();
+
+    let mut sources = SourceMap::new();
+    assert!(sources
+        .lookup(Span::new(INCLUDE_BASE, INCLUDE_BASE + 1, 1, 1))
+        .is_none());
+    let file = sources.add(PathBuf::from("dir/main.ctx"), Vec::new());
+    assert_eq!(
+        sources.resolve(file, "a/b.ctx"),
+        PathBuf::from("dir/a/b.ctx")
+    );
+}
+
+#[test]
+fn test_diagnostics() {
+    let root = create_project(
+        "errors",
+        &[
+            (
+                "main.ctx",
+                "include!(\"missing.ctx\");\ninclude!(path);\ninclude!(\"loop.ctx\");\nfn main() {\n    print(include_str!(\"latin1.txt\"));\n}\n",
+            ),
+            ("loop.ctx", "include!(\"main.ctx\");\n"),
+        ],
+    );
+    fs::write(root.join("latin1.txt"), [0xe9]).unwrap();
+    let messages: Vec<(Option<ErrorCode>, String)> = check(&root)
+        .into_iter()
+        .map(|(code, message, _, _)| (code, message.replace(root.to_str().unwrap(), "")))
+        .collect();
+    let code = Some(ErrorCode::E0109);
+    assert_eq!(messages.len(), 4, "{:?}", messages);
+    assert!(messages[0].1.starts_with("cannot read `/missing.ctx`: "));
+    assert_eq!(
+        messages[1..],
+        [
+            (
+                code,
+                "`include!` takes a string literal with the path of a file".to_string()
+            ),
+            (code, "cyclic `include!` of `/main.ctx`".to_string()),
+            (code, "`/latin1.txt` is not valid UTF-8".to_string()),
+        ]
+    );
+    fs::remove_dir_all(&root).unwrap();
+
+    // 不属于任何文件的源码不能包含文件
+    let errors = Compiler::new()
+        .source("fn main() {\n    print(include_str!(\"a.txt\"));\n}")
+        .check();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message,
+        "`include_str!` can only be used in a source file"
+    );
+}
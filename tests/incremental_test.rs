@@ -0,0 +1,176 @@
+// Contractus 增量编译测试
+// 共用查询缓存的多次编译：没有变化的 crate 直接取出字节码，只重新降级改动过的函数；
+// 改动的函数使没有改动的函数出现类型错误时照常报告；
+// 缓存目录中的结果在之后的编译器中复用，损坏的记录按未命中处理
+
+use contractus::bytecode;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::{Compiler, Emit};
+use contractus::incremental::{QueryCache, QueryStats};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+const PROGRAM: &str = "fn square(x: i32) -> i32 {
+    return x * x;
+}
+
+fn cube(x: i32) -> i32 {
+    return x * x * x;
+}
+
+fn main() {
+    print(square(3) + cube(2));
+}
+";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("contractus_incr_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(compiler: Compiler) -> String {
+    let module = compiler.run().into_result().unwrap().into_object().unwrap();
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    result.unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn stats(cache: &QueryCache, query: &str) -> QueryStats {
+    cache
+        .stats()
+        .into_iter()
+        .find(|(name, _)| *name == query)
+        .map_or(QueryStats::default(), |(_, stats)| stats)
+}
+
+#[test]
+fn test_reuse_in_memory() {
+    let cache = Arc::new(QueryCache::new());
+    let compiler = |source: &str| Compiler::new().source(source).incremental(cache.clone());
+
+    assert_eq!(run(compiler(PROGRAM)), "17\n");
+    assert_eq!(stats(&cache, "lower"), QueryStats { hits: 0, misses: 3 });
+    // 没有变化时不再分析和降级
+    assert_eq!(run(compiler(PROGRAM)), "17\n");
+    assert_eq!(stats(&cache, "object"), QueryStats { hits: 1, misses: 1 });
+    assert_eq!(stats(&cache, "lower"), QueryStats { hits: 0, misses: 3 });
+
+    // 只改动 cube 的函数体，其他条目的位置不变：它们的 MIR 直接复用
+    let changed = PROGRAM.replace("x * x * x", "x * x + x");
+    assert_eq!(run(compiler(&changed)), "15\n");
+    assert_eq!(stats(&cache, "lower"), QueryStats { hits: 2, misses: 4 });
+
+    // 改动签名时所有条目都要重新降级
+    let changed = PROGRAM.replace("fn cube(x: i32) -> i32", "fn cube(x: i64) -> i64");
+    let changed = changed.replace("cube(2))", "cube(2) as i32)");
+    assert_eq!(run(compiler(&changed)), "17\n");
+    assert_eq!(stats(&cache, "lower"), QueryStats { hits: 2, misses: 7 });
+
+    // 有错误的结果不记忆
+    let broken = "fn main() { return missing; }";
+    for _ in 0..2 {
+        assert!(!compiler(broken).check().is_empty());
+    }
+    assert!(compiler(PROGRAM).check().is_empty());
+}
+
+// 只改动 `a` 的签名和函数体，没有改动的 `b` 中对它的使用仍然重新检查
+#[test]
+fn test_type_error_in_dependent() {
+    let cache = Arc::new(QueryCache::new());
+    let compiler = |source: &str| Compiler::new().source(source).incremental(cache.clone());
+    let source = "fn a() -> i32 {\n    1\n}\n\nfn b() -> i32 {\n    a() + 1\n}\n\nfn main() {\n    print(b());\n}\n";
+    assert_eq!(run(compiler(source)), "2\n");
+
+    let changed = source.replace("fn a() -> i32 {\n    1", "fn a() -> bool {\n    true");
+    for _ in 0..2 {
+        let errors: Vec<(Option<ErrorCode>, String, u32)> = compiler(&changed)
+            .check()
+            .into_iter()
+            .map(|error| (error.code, error.message, error.span.line))
+            .collect();
+        assert_eq!(
+            errors,
+            [(
+                Some(ErrorCode::E0369),
+                "binary operation `+` cannot be applied to `bool`".to_string(),
+                6
+            )]
+        );
+    }
+    assert_eq!(run(compiler(source)), "2\n");
+}
+
+#[test]
+fn test_persistent_cache() {
+    let dir = temp_dir("cache");
+    let file = dir.join("main.ctx");
+    fs::write(&file, PROGRAM).unwrap();
+    let cache_dir = dir.join("target").join("incremental");
+    let compiler = |cache: &Arc<QueryCache>| Compiler::new().file(&file).incremental(cache.clone());
+
+    let first = Arc::new(QueryCache::persistent(&cache_dir));
+    assert_eq!(run(compiler(&first)), "17\n");
+    let module = compiler(&first)
+        .run()
+        .into_result()
+        .unwrap()
+        .into_object()
+        .unwrap();
+    let object = cache_dir.join(format!("{:016x}.ctxb", module.hash));
+    assert!(object.is_file());
+    assert!(object.with_extension("check").is_file());
+
+    // 新的缓存（之后的一次编译）读取目录中的结果
+    let second = Arc::new(QueryCache::persistent(&cache_dir));
+    assert!(compiler(&second).check().is_empty());
+    assert_eq!(stats(&second, "check"), QueryStats { hits: 1, misses: 0 });
+    assert_eq!(run(compiler(&second)), "17\n");
+    assert_eq!(stats(&second, "object"), QueryStats { hits: 1, misses: 0 });
+
+    // 损坏的记录按未命中处理，重新编译后覆盖
+    fs::write(&object, b"CTXB").unwrap();
+    let third = Arc::new(QueryCache::persistent(&cache_dir));
+    assert_eq!(run(compiler(&third)), "17\n");
+    assert_eq!(stats(&third, "object"), QueryStats { hits: 0, misses: 1 });
+    assert!(bytecode::decode(&fs::read(&object).unwrap()).is_ok());
+
+    // 其他阶段的产物照常生成
+    let output = compiler(&third).emit(Emit::Mir).run();
+    assert!(output.into_result().unwrap().into_mir().is_some());
+}
+
+#[test]
+fn test_incremental_option() {
+    let dir = temp_dir("cli");
+    let file = dir.join("main.ctx");
+    fs::write(&file, PROGRAM).unwrap();
+    let cache_dir = dir.join("cache");
+    let option = format!("-Zincremental={}", cache_dir.display());
+    let emit = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .args([
+                "--emit=bytecode",
+                &option,
+                file.to_str().unwrap(),
+                "-o",
+                "-",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        output.stdout
+    };
+    let bytes = emit();
+    assert_eq!(emit(), bytes);
+    let cached: Vec<_> = fs::read_dir(&cache_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".ctxb"))
+        .collect();
+    assert_eq!(cached.len(), 1);
+}
@@ -0,0 +1,114 @@
+// Contractus const 和 static 初始化顺序测试
+// 每个条目在它的初始值用到的条目之后初始化，互不依赖时按声明顺序；
+// 初始值之间的依赖成环时报告环上的每个条目
+
+mod common;
+
+use common::run;
+use contractus::ast::Item;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::module;
+use contractus::sema::initialization_order;
+
+const PROGRAM: &str = "struct Size {
+    width: i32,
+    height: i32,
+}
+
+static AREA: i32 = SIZE.width * SIZE.height;
+
+static SIZE: Size = Size { width: WIDTH, height: WIDTH / 2 };
+
+const WIDTH: i32 = BASE * 4;
+
+const BASE: i32 = 5;
+
+static mut TOTAL: i32 = AREA;
+
+fn main() {
+    print(AREA);
+    unsafe {
+        TOTAL += SIZE.height;
+        print(TOTAL);
+    }
+}
+";
+
+fn name(item: &Item) -> &str {
+    match item {
+        Item::Const(def) => &def.name,
+        Item::Static(def) => &def.name,
+        _ => panic!("expected a const or static"),
+    }
+}
+
+#[test]
+fn test_initialization_order() {
+    // 初始值可以用到后面声明的条目
+    assert_eq!(run(PROGRAM), "200\n210\n");
+
+    let program = module::parse_source(PROGRAM).unwrap();
+    let order = initialization_order(&program.items);
+    assert!(order.cycles.is_empty());
+    let names: Vec<&str> = order.items.into_iter().map(name).collect();
+    assert_eq!(names, ["BASE", "WIDTH", "SIZE", "AREA", "TOTAL"]);
+
+    // 互不依赖的条目保持声明顺序
+    let program = module::parse_source(
+        "static B: i32 = 1;\nconst A: i32 = 2;\nstatic C: i32 = A;\nfn main() {}\n",
+    )
+    .unwrap();
+    let order = initialization_order(&program.items);
+    let names: Vec<&str> = order.items.into_iter().map(name).collect();
+    assert_eq!(names, ["B", "A", "C"]);
+}
+
+#[test]
+fn test_initialization_cycles() {
+    let check = |items: &str| {
+        Compiler::new()
+            .source(format!("{}\nfn main() {{}}\n", items))
+            .check()
+    };
+
+    let found = check("static A: i32 = B + 1;\nstatic B: i32 = A;");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].code, Some(ErrorCode::E0391));
+    assert_eq!(found[0].message, "cycle detected when initializing `A`");
+    assert_eq!((found[0].span.line, found[0].span.column), (1, 17));
+    assert_eq!(
+        found[0].notes,
+        [
+            "`A` uses `B` at line 1, column 17",
+            "`B` uses `A` at line 2, column 17, completing the cycle",
+        ]
+    );
+
+    // 环从声明最早的条目开始，环外的条目不列出
+    let found = check(
+        "const START: i32 = THIRD;\nconst FIRST: i32 = SECOND * 2;\nconst THIRD: i32 = FIRST;\nconst SECOND: i32 = THIRD - 1;",
+    );
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].message, "cycle detected when initializing `FIRST`");
+    assert_eq!(
+        found[0].notes,
+        [
+            "`FIRST` uses `SECOND` at line 2, column 20",
+            "`SECOND` uses `THIRD` at line 4, column 21",
+            "`THIRD` uses `FIRST` at line 3, column 20, completing the cycle",
+        ]
+    );
+
+    let found = check("static SELF: [i32; 1] = [SELF[0]];");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(
+        found[0].notes,
+        ["`SELF` uses itself at line 1, column 26, completing the cycle"]
+    );
+
+    // 引用也是依赖
+    let found = check("static P: &i32 = &Q;\nstatic Q: i32 = *P;");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].code, Some(ErrorCode::E0391));
+}
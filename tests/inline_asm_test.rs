@@ -0,0 +1,173 @@
+// Contractus 内联汇编测试
+// `asm!` 要用 `#![feature(asm)]` 开启，语法树和 MIR 中保留模板、操作数和选项；
+// 解释器和字节码后端都不能执行汇编，分别在执行时和编译时报错
+
+mod common;
+
+use common::errors;
+use contractus::diagnostic::ErrorCode;
+use contractus::{bytecode, interp, mir, module, SemanticAnalyzer};
+
+const PROGRAM: &str = "#![feature(asm)]
+
+fn add(a: u64, b: u64) -> u64 {
+    let mut x = a;
+    unsafe {
+        asm!(\"add {0}, {1}\", inout(reg) x, in(reg) b, options(pure, nomem, nostack));
+    }
+    x
+}
+
+fn cpu_id(leaf: u32) -> u32 {
+    let mut ebx: u32 = 0;
+    unsafe {
+        asm!(
+            \"mov {0:e}, ebx\",
+            out(reg) ebx,
+            in(\"eax\") leaf,
+            lateout(\"eax\") _,
+            out(\"ecx\") _,
+            out(\"edx\") _,
+        );
+    }
+    ebx
+}
+
+fn halt() -> ! {
+    unsafe {
+        asm!(\"hlt\", options(noreturn))
+    }
+}
+
+fn main() {
+    print(add(1, 2), cpu_id(0));
+}
+";
+
+// 只有 main 的程序，函数体是 `body`
+fn asm_errors(body: &str) -> Vec<(Option<ErrorCode>, String)> {
+    errors(&format!(
+        "#![feature(asm)]\n\nfn main() {{\n    let mut x: u64 = 1;\n    let flag = true;\n    {}\n}}\n",
+        body
+    ))
+}
+
+#[test]
+fn test_inline_asm() {
+    let program = module::parse_source(PROGRAM).expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+
+    // MIR 中保留汇编，`inout` 的位置同时是输入和输出
+    let text = mir::lower_program(&program).unwrap().to_string();
+    assert!(
+        text.contains(
+            "asm!(\"add {0}, {1}\", inout(reg) _3, in(reg) _2, options(pure, nomem, nostack));"
+        ),
+        "{}",
+        text
+    );
+    assert!(text.contains("lateout(\"eax\") _"), "{}", text);
+}
+
+#[test]
+fn test_backends_reject_asm() {
+    let program = module::parse_source(PROGRAM).unwrap();
+    let (result, _) = interp::run_with_output(&program, Vec::new());
+    assert_eq!(
+        result.unwrap_err()[0].message,
+        "inline assembly cannot be run by the interpreter"
+    );
+
+    let lowered = mir::monomorphize(&mir::lower_program(&program).unwrap()).unwrap();
+    let errors = bytecode::compile(&lowered).unwrap_err();
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].code, Some(ErrorCode::E0810));
+    assert_eq!(
+        errors[0].message,
+        "inline assembly is not supported by the bytecode backend"
+    );
+}
+
+#[test]
+fn test_asm_feature_gate() {
+    let found = errors("fn main() {\n    unsafe {\n        asm!(\"nop\");\n    }\n}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0658),
+            "inline assembly blocks are experimental".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_asm_diagnostics() {
+    let check = |body: &str, code: ErrorCode, message: &str| {
+        assert_eq!(
+            asm_errors(body),
+            [(Some(code), message.to_string())],
+            "{}",
+            body
+        );
+    };
+    check(
+        "asm!(\"inc {0}\", inout(reg) x);",
+        ErrorCode::E0133,
+        "use of inline assembly requires an unsafe block",
+    );
+    check(
+        "unsafe { asm!(\"nop\", in(reg) x); }",
+        ErrorCode::E0707,
+        "operand 0 is never used in the template",
+    );
+    check(
+        "unsafe { asm!(\"mov {1}, {0}\", in(reg) x); }",
+        ErrorCode::E0707,
+        "invalid reference to operand 1: there is 1 operand",
+    );
+    check(
+        "unsafe { asm!(\"inc {0}\", inout(\"rax\") x); }",
+        ErrorCode::E0707,
+        "operand 0 uses an explicit register and cannot be referred to in the template",
+    );
+    check(
+        "unsafe { asm!(\"inc {0}\", inout(big) x); }",
+        ErrorCode::E0707,
+        "invalid register class `big`",
+    );
+    check(
+        "unsafe { asm!(\"test {0}\", in(reg) flag); }",
+        ErrorCode::E0707,
+        "cannot use a value of type `bool` as an assembly operand",
+    );
+    check(
+        "unsafe { asm!(\"nop {\"); }",
+        ErrorCode::E0707,
+        "unmatched `{` in the template",
+    );
+    check(
+        "unsafe { asm!(\"nop\", options(fast)); }",
+        ErrorCode::E0707,
+        "unknown inline assembly option `fast`",
+    );
+    check(
+        "unsafe { asm!(\"mov {0}, 1\", out(reg) 5); }",
+        ErrorCode::E0070,
+        "invalid left-hand side of assignment",
+    );
+    check(
+        "unsafe { asm!(\"nop\", options(pure)); }",
+        ErrorCode::E0707,
+        "assembly with `options(pure)` must have at least one output",
+    );
+    // `{{` 是花括号本身，不引用操作数
+    assert_eq!(
+        asm_errors("unsafe { asm!(\"nop {{}}\", \"inc {}\", inout(reg) x); }"),
+        []
+    );
+}
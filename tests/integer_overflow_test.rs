@@ -0,0 +1,199 @@
+// Contractus 整数溢出测试
+// 整数运算按操作数声明的类型的宽度检查溢出：结果超出类型的范围、移位的位数不小于类型的位数
+// 都是运行时错误，左移丢弃超出宽度的高位。操作数都是没有后缀的字面量时类型由上下文决定，
+// 按 i64 检查。取负和按位取反同样按操作数的类型，f32 运算的结果舍入到单精度。
+// 解释器、虚拟机和常量传播的结果应当一致
+
+mod common;
+
+use common::{run, try_run};
+
+fn overflow(body: &str) -> String {
+    let source = format!("fn main() {{\n{}\n}}\n", body);
+    try_run(&source).expect_err("the program should overflow")
+}
+
+#[test]
+fn test_u8_overflow() {
+    assert_eq!(
+        overflow("let a: u8 = 255; print(a + 1);"),
+        "attempt to add with overflow"
+    );
+    assert_eq!(
+        overflow("let a: u8 = 0; print(a - 1);"),
+        "attempt to subtract with overflow"
+    );
+    assert_eq!(
+        overflow("let a: u8 = 16; print(a * a);"),
+        "attempt to multiply with overflow"
+    );
+    assert_eq!(
+        overflow("let mut a: u8 = 200; a += 100; print(a);"),
+        "attempt to add with overflow"
+    );
+    assert_eq!(
+        overflow("let a: u8 = 1; print(a << 8);"),
+        "attempt to shift with overflow"
+    );
+}
+
+#[test]
+fn test_i8_overflow() {
+    assert_eq!(
+        overflow("let a: i8 = 127; print(a + 1);"),
+        "attempt to add with overflow"
+    );
+    assert_eq!(
+        overflow("let a: i8 = -128; print(a - 1);"),
+        "attempt to subtract with overflow"
+    );
+    assert_eq!(
+        overflow("let a: i8 = -128; print(a / -1);"),
+        "attempt to divide with overflow"
+    );
+    assert_eq!(
+        overflow("let a: i8 = -128; print(a % -1);"),
+        "attempt to calculate the remainder with overflow"
+    );
+}
+
+#[test]
+fn test_i32_overflow() {
+    assert_eq!(
+        overflow("let max: i32 = 2147483647; print(max + 1);"),
+        "attempt to add with overflow"
+    );
+    assert_eq!(
+        overflow("print((1 as i32) << 33);"),
+        "attempt to shift with overflow"
+    );
+    assert_eq!(
+        overflow("let mut x: i32 = 65536; x *= x; print(x);"),
+        "attempt to multiply with overflow"
+    );
+    // 参数的类型同样决定宽度
+    let source = "fn double(x: i32) -> i32 { x * 2 }\nfn main() { print(double(2000000000)); }\n";
+    assert_eq!(
+        try_run(source).unwrap_err(),
+        "attempt to multiply with overflow"
+    );
+}
+
+#[test]
+fn test_arithmetic_within_range() {
+    let output = run(r#"
+fn main() {
+    let a: u8 = 250;
+    print(a + 5);
+    let b: i8 = -128;
+    print(b + 127);
+    print((1 as i32) << 31);
+    let c: u8 = 255;
+    print(c << 1);
+    let d: i32 = -8;
+    print(d >> 1);
+}
+"#);
+    assert_eq!(output, "255\n-1\n-2147483648\n254\n-4\n");
+}
+
+#[test]
+fn test_width_from_context() {
+    // 字面量的类型由上下文决定，混用不同宽度的整数时按能容纳两者的类型检查
+    let output = run(r#"
+fn big() -> i64 {
+    3000000000
+}
+
+fn main() {
+    let z: i64 = 3000000 * 3000000;
+    print(z);
+    let x: i64 = big();
+    let mut sum = 0;
+    sum = sum + x;
+    print(sum);
+    let small: u8 = 200;
+    print(x + small);
+}
+"#);
+    assert_eq!(output, "9000000000000\n3000000000\n3000000200\n");
+}
+
+#[test]
+fn test_unary_width() {
+    // 取负和按位取反同样按操作数的类型：最小值取负溢出，取反只翻转类型宽度内的位
+    assert_eq!(
+        overflow("let x: i8 = -128; print(-x);"),
+        "attempt to negate with overflow"
+    );
+    assert_eq!(
+        overflow("let x: i32 = -2147483648; print(-x);"),
+        "attempt to negate with overflow"
+    );
+    let output = run(r#"
+fn main() {
+    let x: u8 = 0;
+    print(!x);
+    let y: i16 = 5;
+    print(-y);
+    print(!y);
+    let z: u64 = 0;
+    print(!z);
+}
+"#);
+    assert_eq!(output, "255\n-5\n-6\n9223372036854775807\n");
+}
+
+#[test]
+fn test_f32_rounding() {
+    let output = run(r#"
+fn main() {
+    let x = (1 as f32) / (3 as f32);
+    print(x);
+    let y = (2 as f32) / (7 as f32);
+    print(y * (3 as f32));
+}
+"#);
+    assert_eq!(output, "0.3333333432674408\n0.8571429252624512\n");
+}
+
+#[test]
+fn test_negative_to_u64() {
+    // 执行时 u64 只有 0..=i64::MAX，负数不能转换
+    assert_eq!(
+        overflow("let x: i64 = -1; print(x as u64);"),
+        "cannot cast -1 to `u64`: the value must be in 0..=9223372036854775807"
+    );
+    assert_eq!(
+        run("fn main() {\nlet x: i64 = 7; print(x as usize);\n}\n"),
+        "7\n"
+    );
+}
+
+#[test]
+fn test_abs_width() {
+    // `abs` 的结果按参数的类型检查：类型的最小值没有绝对值
+    assert_eq!(
+        overflow("let x: i32 = -2147483648; print(x.abs());"),
+        "attempt to negate with overflow"
+    );
+    assert_eq!(
+        overflow("let x: i16 = -32768; print(abs(x));"),
+        "attempt to negate with overflow"
+    );
+    assert_eq!(
+        overflow("let x: i8 = -128; x.abs();"),
+        "attempt to negate with overflow"
+    );
+    let output = run(r#"
+fn main() {
+    let x: i8 = -127;
+    print(x.abs());
+    let y: i32 = 42;
+    print(abs(y));
+    let z: i64 = -2147483648;
+    print(z.abs());
+}
+"#);
+    assert_eq!(output, "127\n42\n2147483648\n");
+}
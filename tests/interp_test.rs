@@ -0,0 +1,364 @@
+// Contractus 解释器测试
+// 直接对语法树求值，检查程序输出和运行时错误
+
+use contractus::interp::{self, Interpreter, Value};
+use contractus::{Lexer, Parser, SemanticAnalyzer};
+
+fn parse(input: &str) -> contractus::ast::Program {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+    program
+}
+
+// 运行 main，返回标准输出的内容
+fn run(input: &str) -> String {
+    let program = parse(input);
+    let (result, output) = interp::run_with_output(&program, Vec::new());
+    if let Err(errors) = result {
+        panic!("runtime error: {}", errors[0]);
+    }
+    String::from_utf8(output).unwrap()
+}
+
+fn run_err(input: &str) -> String {
+    let program = parse(input);
+    match interp::run_with_output(&program, Vec::new()).0 {
+        Ok(()) => panic!("expected a runtime error"),
+        Err(errors) => errors[0].to_string(),
+    }
+}
+
+#[test]
+fn test_mvp_program() {
+    let output = run(r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        struct Rectangle {
+            top_left: Point,
+            bottom_right: Point,
+        }
+
+        enum Shape {
+            Circle(Point, i32),
+            Rectangle(Rectangle),
+        }
+
+        fn area(shape: Shape) -> i32 {
+            match shape {
+                Circle(center, radius) => 3 * radius * radius,
+                Rectangle(rect) => {
+                    let width = rect.bottom_right.x - rect.top_left.x;
+                    let height = rect.top_left.y - rect.bottom_right.y;
+                    return width * height;
+                }
+            }
+        }
+
+        fn main() -> i32 {
+            let circle = Shape::Circle(Point { x: 0, y: 0 }, 10);
+            let rect = Shape::Rectangle(Rectangle {
+                top_left: Point { x: 0, y: 10 },
+                bottom_right: Point { x: 10, y: 0 },
+            });
+
+            print(area(circle));
+            print(area(rect));
+
+            for i in 0..5 {
+                print(i * 2);
+            }
+
+            let values: [i32; 3] = [1, 2, 3];
+            for val in values {
+                print(val * val);
+            }
+
+            return 0;
+        }
+    "#);
+    assert_eq!(output, "300\n100\n0\n2\n4\n6\n8\n1\n4\n9\n");
+}
+
+#[test]
+fn test_arithmetic_and_control_flow() {
+    let output = run(r#"
+        fn fib(n: i32) -> i32 {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+
+        fn main() {
+            let mut i = 0;
+            let mut sum = 0;
+            while true {
+                i += 1;
+                if i % 2 == 0 {
+                    continue;
+                }
+                if i > 9 {
+                    break;
+                }
+                sum += i;
+            }
+            print(sum);
+            print(fib(10));
+            print(7 / 2);
+            print(-7 % 3);
+            print(1 << 4);
+        }
+    "#);
+    assert_eq!(output, "25\n55\n3\n-1\n16\n");
+}
+
+#[test]
+fn test_else_if_expressions() {
+    let output = run(r#"
+        fn sign(a: i32) -> i32 {
+            let s = if a > 0 { 1 } else if a < 0 { -1 } else { 0 };
+            s
+        }
+
+        fn grade(a: i32) -> i32 {
+            if a > 90 {
+                3
+            } else if a > 60 {
+                2
+            } else if a > 30 {
+                1
+            } else {
+                0
+            }
+        }
+
+        fn main() {
+            print(sign(5), sign(-3), sign(0));
+            print(grade(95), grade(70), grade(40), grade(10));
+        }
+    "#);
+    assert_eq!(output, "1\n-1\n0\n3\n2\n1\n0\n");
+}
+
+#[test]
+fn test_structs_enums_and_match() {
+    let output = run(r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        enum Op {
+            Move(i32, i32),
+            Reset,
+        }
+
+        fn apply(p: Point, op: Op) -> Point {
+            match op {
+                Op::Move(dx, dy) => Point { x: p.x + dx, y: p.y + dy },
+                Op::Reset => Point { x: 0, y: 0 },
+            }
+        }
+
+        fn main() {
+            let mut p = Point { y: 2, x: 1 };
+            p = apply(p, Op::Move(3, 4));
+            print(p);
+            p.x = 10;
+            print(p.x + p.y);
+            print(apply(p, Op::Reset));
+        }
+    "#);
+    assert_eq!(output, "Point { x: 4, y: 6 }\n16\nPoint { x: 0, y: 0 }\n");
+}
+
+#[test]
+fn test_closures_capture_variables() {
+    let output = run(r#"
+        fn main() {
+            let mut count = 0;
+            let counter = &mut count;
+            let add = |n: i32| {
+                *counter += n;
+            };
+            add(2);
+            add(3);
+            print(count);
+
+            let offset = 10;
+            let shift = |x: i32| x + offset;
+            print(shift(5));
+        }
+    "#);
+    assert_eq!(output, "5\n15\n");
+}
+
+#[test]
+fn test_mutable_references() {
+    let output = run(r#"
+        fn bump(x: &mut i32) {
+            *x = *x + 1;
+        }
+
+        fn main() {
+            let mut values = [1, 2, 3];
+            bump(&mut values[1]);
+            print(values);
+        }
+    "#);
+    assert_eq!(output, "[1, 3, 3]\n");
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    let message = run_err(
+        r#"
+        fn main() {
+            let values = [1, 2, 3];
+            let i = 3;
+            print(values[i]);
+        }
+    "#,
+    );
+    assert!(
+        message.contains("index out of bounds: the len is 3 but the index is 3"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_division_by_zero() {
+    let message = run_err(
+        r#"
+        fn main() {
+            let zero = 0;
+            print(1 / zero);
+        }
+    "#,
+    );
+    assert!(message.contains("attempt to divide by zero"), "{}", message);
+}
+
+#[test]
+fn test_unbounded_recursion_is_reported() {
+    let message = run_err(
+        r#"
+        fn down(n: i32) -> i32 {
+            return down(n + 1);
+        }
+
+        fn main() {
+            down(0);
+        }
+    "#,
+    );
+    assert!(message.contains("call depth exceeded"), "{}", message);
+}
+
+#[test]
+fn test_call_function_directly() {
+    let program = parse(
+        r#"
+        fn square(x: i32) -> i32 {
+            return x * x;
+        }
+    "#,
+    );
+    let mut interpreter = Interpreter::with_output(&program, Vec::new());
+    let span = contractus::span::Span::new(0, 0, 1, 1);
+    let value = interpreter
+        .call_function("square", vec![Value::Int(12)], span)
+        .unwrap();
+    assert_eq!(value, Value::Int(144));
+}
+
+#[test]
+fn test_contract_violations() {
+    let message = run_err(
+        r#"
+        fn divide(a: i32, b: i32) -> i32
+            requires b != 0
+        {
+            return a / b;
+        }
+
+        fn main() {
+            print(divide(1, 0));
+        }
+    "#,
+    );
+    // 前置条件的违反指向调用处
+    assert!(
+        message.contains("line 9, column 19: precondition violated: `b != 0`"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("the precondition of `divide` is declared at line 3"),
+        "{}",
+        message
+    );
+
+    let message = run_err(
+        r#"
+        fn next(x: i32) -> i32 ensures result > x {
+            return x - 1;
+        }
+
+        fn main() {
+            next(1);
+        }
+    "#,
+    );
+    assert!(
+        message.contains("postcondition violated: `result > x`"),
+        "{}",
+        message
+    );
+
+    let message = run_err(
+        r#"
+        struct Account {
+            balance: i32,
+            invariant self.balance >= 0
+        }
+
+        fn main() {
+            let account = Account { balance: -1 };
+        }
+    "#,
+    );
+    assert!(
+        message.contains("invariant violated: `self.balance >= 0`"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_contracts_off() {
+    let program = parse(
+        r#"
+        fn positive(x: i32) -> i32 requires x > 0 {
+            return x;
+        }
+
+        fn main() {
+            print(positive(-1));
+        }
+    "#,
+    );
+    let result = interp::with_large_stack(|| {
+        let mut interpreter = Interpreter::with_output(&program, Vec::new());
+        interpreter.set_contract_mode(contractus::mir::ContractMode::Off);
+        interpreter.run_main().map(drop)
+    });
+    assert!(result.is_ok(), "{:?}", result);
+}
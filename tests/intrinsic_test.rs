@@ -0,0 +1,204 @@
+// Contractus intrinsic 测试
+// `#[intrinsic("name")]` 标记的函数在字节码虚拟机中换成同名的内建函数，
+// 解释器执行用 Contractus 写的函数体，两者的结果相同
+
+mod common;
+
+use common::errors;
+use contractus::ast::Item;
+use contractus::bytecode::{self, Builtin, Constant};
+use contractus::diagnostic::ErrorCode;
+use contractus::{interp, mir, module, SemanticAnalyzer};
+
+const PROGRAM: &str = "#[intrinsic(\"memcpy\")]
+fn copy_ints(dst: &mut [i32], src: &[i32]) {
+    assert(len(dst) == len(src), \"copy_ints: slices have different lengths\");
+    let mut i: usize = 0;
+    while i < len(src) {
+        dst[i] = src[i];
+        i += 1;
+    }
+}
+
+#[intrinsic(\"ctpop\")]
+fn count_ones(x: u64) -> u32 {
+    let mut n: u32 = 0;
+    let mut v = x;
+    while v != 0 {
+        n += (v & 1) as u32;
+        v = v >> 1;
+    }
+    n
+}
+
+#[intrinsic(\"ctlz\")]
+fn leading_zeros(x: u64) -> u32 {
+    let mut n: u32 = 64;
+    let mut v = x;
+    while v != 0 {
+        n -= 1;
+        v = v >> 1;
+    }
+    n
+}
+
+#[intrinsic(\"cttz\")]
+fn trailing_zeros(x: u64) -> u32 {
+    if x == 0 {
+        return 64;
+    }
+    let mut n: u32 = 0;
+    let mut v = x;
+    while (v & 1) == 0 {
+        n += 1;
+        v = v >> 1;
+    }
+    n
+}
+
+#[intrinsic(\"sqrt\")]
+fn square_root(x: f64) -> f64 {
+    let zero = 0 as f64;
+    if x == zero {
+        return zero;
+    }
+    let mut guess = x;
+    let mut i = 0;
+    while i < 64 {
+        guess = (guess + x / guess) / 2 as f64;
+        i += 1;
+    }
+    guess
+}
+
+fn main() {
+    let mut buffer = [0, 0, 0, 0, 0];
+    let source = [7, 8, 9];
+    copy_ints(&mut buffer[1..4], &source);
+    print(buffer);
+    print(count_ones(255), count_ones(4294967295), leading_zeros(1), leading_zeros(0));
+    print(trailing_zeros(8), trailing_zeros(0), trailing_zeros(1099511627776));
+    print(square_root(16 as f64), square_root(2 as f64) * square_root(2 as f64) > 1 as f64);
+}
+";
+
+fn compile(source: &str) -> bytecode::Module {
+    let program = module::parse_source(source).expect("parsing failed");
+    let lowered = mir::lower_program(&program).expect("MIR lowering failed");
+    let lowered = mir::monomorphize(&lowered).expect("monomorphization failed");
+    bytecode::compile(&lowered).expect("bytecode compilation failed")
+}
+
+fn interpret(source: &str) -> String {
+    let program = module::parse_source(source).expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+    let (result, output) = interp::run_with_output(&program, Vec::new());
+    result.expect("the interpreter failed");
+    String::from_utf8(output).unwrap()
+}
+
+fn execute(source: &str) -> String {
+    let (result, output) = bytecode::run_with_output(&compile(source), Vec::new());
+    result.expect("the VM failed");
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_intrinsics() {
+    let expected = "[0, 7, 8, 9, 0]\n8\n32\n63\n64\n3\n64\n40\n4\ntrue\n";
+    assert_eq!(interpret(PROGRAM), expected);
+    assert_eq!(execute(PROGRAM), expected);
+
+    // 字符串参数按原样还原
+    let program = module::parse_source(PROGRAM).unwrap();
+    let Item::Function(func) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(func.attributes[0].args, ["\"memcpy\""]);
+    assert_eq!(func.intrinsic().as_deref(), Some("memcpy"));
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_backends() {
+    // 虚拟机调用自己的实现，不执行函数体；解释器执行函数体
+    let source = "#[intrinsic(\"sqrt\")]
+fn root(x: f64) -> f64 {
+    print(\"fallback\");
+    x
+}
+
+fn main() {
+    print(root(9 as f64) as i32);
+}
+";
+    assert_eq!(interpret(source), "fallback\n9\n");
+    assert_eq!(execute(source), "3\n");
+    let module = compile(source);
+    assert!(module.constants.contains(&Constant::Builtin(Builtin::Sqrt)));
+
+    // 切片的长度不同时虚拟机报告运行时错误
+    let source = "#[intrinsic(\"memcpy\")]
+fn copy(dst: &mut [u8], src: &[u8]) {}
+
+fn main() {
+    let mut a: [u8; 3] = [0, 0, 0];
+    let b: [u8; 2] = [1, 2];
+    copy(&mut a, &b);
+}
+";
+    let (result, _) = bytecode::run_with_output(&compile(source), Vec::new());
+    let errors = result.unwrap_err();
+    assert!(errors[0]
+        .message
+        .contains("`memcpy` between slices of different lengths (3 and 2)"));
+}
+
+#[test]
+fn test_invalid_intrinsics() {
+    let source = "#[intrinsic(\"ctpop\")]
+fn count(x: u32) -> u32 {
+    x
+}
+
+#[intrinsic(\"fma\")]
+fn fused() {}
+
+#[intrinsic(sqrt)]
+fn root() {}
+
+#[intrinsic(\"memcpy\")]
+fn copy(dst: &mut [i32], src: &[u8]) {}
+
+#[intrinsic(\"memcpy\")]
+fn copy_mut(dst: &mut [i32], src: &mut [i32]) {}
+
+#[intrinsic(\"cttz\")]
+fn zeros<T>(x: u64) -> u32 {
+    0
+}
+
+#[intrinsic(\"memcpy\")]
+fn copy_ok(dst: &mut [bool], src: &[bool]) {}
+
+fn main() {}
+";
+    let found = errors(source);
+    let expected = [
+        "intrinsic `ctpop` must have the signature `fn(u64) -> u32`",
+        "unknown intrinsic `fma`",
+        "`#[intrinsic]` takes the name of the intrinsic as a string",
+        "intrinsic `memcpy` must have the signature `fn(&mut [T], &[T]) -> ()`",
+        "intrinsic `memcpy` must have the signature `fn(&mut [T], &[T]) -> ()`",
+        "intrinsic function `zeros` cannot be generic",
+    ];
+    assert_eq!(
+        found,
+        expected
+            .iter()
+            .map(|message| (Some(ErrorCode::E0708), message.to_string()))
+            .collect::<Vec<_>>()
+    );
+}
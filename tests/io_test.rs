@@ -0,0 +1,133 @@
+// Contractus io 模块测试
+// 打开 io 能力时解释器和字节码虚拟机中的 io::read_file/write_file/args/exit，
+// 默认（包括沙箱）关闭时的运行时错误，以及命令行 `run` 传入的参数和退出码
+
+mod common;
+
+use contractus::interp::{self, Host};
+use contractus::sandbox::{self, Limits, Status};
+use contractus::{Lexer, Parser};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+// 打开 io 后两个后端的输出（或错误信息）和退出码
+fn run(input: &str, args: &[&str]) -> (Result<String, String>, Option<i32>) {
+    let host = Host::with_io(args.iter().map(|arg| arg.to_string()).collect());
+    let execution = common::execute(input, host);
+    let outcome = match execution.error {
+        Some(error) => Err(error.message),
+        None => Ok(execution.output),
+    };
+    (outcome, execution.exit_code)
+}
+
+#[test]
+fn test_read_and_write_files() {
+    let dir = env::temp_dir().join(format!("contractus_io_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.txt");
+    let source = r#"
+        fn main() {
+            let path = io::args()[0];
+            match io::write_file(path, "one\ntwo") {
+                Ok(()) => print("written"),
+                Err(message) => print(message),
+            }
+            let text = io::read_file(path);
+            match text {
+                Ok(text) => print(len(text.split("\n"))),
+                Err(message) => print(message),
+            }
+            print(io::read_file(path + ".missing").is_err());
+        }
+
+        fn is_err(result: Result<string, string>) -> bool {
+            match result {
+                Ok(_) => return false,
+                Err(_) => return true,
+            }
+        }
+    "#;
+    let (output, exit_code) = run(source, &[path.to_str().unwrap()]);
+    assert_eq!(output.unwrap(), "written\n2\ntrue\n");
+    assert_eq!(exit_code, None);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo");
+
+    // 错误信息包含路径
+    let source = "fn main() {\n    match io::read_file(io::args()[0]) {\n        Ok(_) => print(0),\n        Err(message) => print(message),\n    }\n}";
+    let missing = dir.join("missing.txt");
+    let (output, _) = run(source, &[missing.to_str().unwrap()]);
+    let message = format!("cannot read `{}`: ", missing.display());
+    assert!(output.unwrap().starts_with(&message));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_exit() {
+    let source = r#"
+        fn check(args: Vec<string>) {
+            if len(args) < 2 {
+                print("usage: tool <a> <b>");
+                io::exit(2);
+            }
+        }
+
+        fn main() {
+            let args = io::args();
+            check(args);
+            print(args[0] + args[1]);
+        }
+    "#;
+    let (output, exit_code) = run(source, &["x"]);
+    assert_eq!(output.unwrap(), "usage: tool <a> <b>\n");
+    assert_eq!(exit_code, Some(2));
+    let (output, exit_code) = run(source, &["x", "y"]);
+    assert_eq!(output.unwrap(), "xy\n");
+    assert_eq!(exit_code, None);
+}
+
+// io 默认关闭，沙箱中也是如此
+#[test]
+fn test_disabled_by_default() {
+    let source = "fn main() {\n    print(1);\n    print(io::read_file(\"/etc/hostname\"));\n}";
+    let program = Parser::new(Lexer::new(source).tokenize().unwrap())
+        .parse()
+        .unwrap();
+    let (result, output) = interp::run_with_output(&program, Vec::new());
+    let message = "`io::read_file` is not available: I/O is disabled in this environment";
+    assert_eq!(result.unwrap_err()[0].message, message);
+    assert_eq!(output, b"1\n");
+
+    let outcome = sandbox::compile_and_run(source, &Limits::default());
+    assert_eq!(outcome.status, Status::RuntimeError);
+    assert_eq!(outcome.stdout, "1\n");
+    assert_eq!(outcome.diagnostics[0].message, message);
+}
+
+#[test]
+fn test_run_command() {
+    let dir = env::temp_dir().join(format!("contractus_io_run_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.ctx");
+    fs::write(
+        &file,
+        "fn main() {\n    for arg in io::args() {\n        print(arg);\n    }\n    io::exit(3);\n}",
+    )
+    .unwrap();
+    for vm in [false, true] {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_contractus"));
+        command.arg("run");
+        if vm {
+            command.arg("--vm");
+        }
+        let output = command
+            .arg(&file)
+            .args(["--", "a", "--vm"])
+            .output()
+            .expect("cannot run contractus");
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n--vm\n");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
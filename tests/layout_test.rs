@@ -0,0 +1,269 @@
+// Contractus 数据布局测试
+// 结构体的字段排列（默认、`#[repr(C)]`、`#[repr(packed)]`）、枚举的标签类型，
+// `std::mem::size_of::<T>()`/`align_of::<T>()` 在各后端中的值，以及 repr 和内建函数的诊断
+
+mod common;
+
+use common::run;
+use contractus::ast::Type;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::layout::Layout;
+use contractus::{mir, module};
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_struct_layouts() {
+    let program = module::parse_source(
+        r#"
+        struct Default {
+            kind: u8,
+            length: u32,
+            flag: bool,
+            value: i64,
+        }
+
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            length: u32,
+            flag: bool,
+            value: i64,
+        }
+
+        #[repr(packed)]
+        struct Packed {
+            kind: u8,
+            length: u32,
+            flag: bool,
+            value: i64,
+        }
+
+        #[repr(C, packed)]
+        struct Wire {
+            kind: u8,
+            inner: Header,
+        }
+
+        struct Pair<T> {
+            first: u8,
+            second: T,
+        }
+    "#,
+    )
+    .unwrap();
+    let mir = mir::lower_program(&program).unwrap();
+    let layouts = mir.layouts();
+    let layout = |name: &str| {
+        let layout = layouts.struct_layout(name).unwrap();
+        (layout.offsets, layout.layout)
+    };
+
+    // 默认按对齐从大到小排列，偏移按声明顺序给出
+    assert_eq!(layout("Default"), (vec![12, 8, 13, 0], Layout::new(16, 8)));
+    // C 的规则：按声明顺序，每个字段按自己的对齐放置
+    assert_eq!(layout("Header"), (vec![0, 4, 8, 16], Layout::new(24, 8)));
+    assert_eq!(layout("Packed"), (vec![0, 1, 5, 6], Layout::new(14, 1)));
+    assert_eq!(layout("Wire"), (vec![0, 1], Layout::new(25, 1)));
+
+    // 泛型结构体的实例按类型实参计算
+    let pair = |arg: Type| layouts.layout_of(&Type::Generic("Pair".to_string(), vec![arg]));
+    assert_eq!(pair(Type::I64), Ok(Layout::new(16, 8)));
+    assert_eq!(pair(Type::U16), Ok(Layout::new(4, 2)));
+    assert!(layouts.struct_layout("Pair").is_err());
+
+    let text = mir.to_string();
+    assert!(
+        text.contains("// struct Packed: size 14, align 1, fields at [0, 1, 5, 6]\n"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn test_enum_reprs() {
+    let mir = mir::lower_program(
+        &module::parse_source(
+            r#"
+            #[repr(u16)]
+            enum Opcode {
+                Halt,
+                Load(u8),
+            }
+
+            #[repr(C)]
+            enum Status {
+                Ok,
+                Failed(i64),
+            }
+
+            #[repr(i64)]
+            enum Wide {
+                A,
+                B,
+            }
+
+            fn opcode(op: Opcode) -> i32 {
+                match op {
+                    Halt => 0,
+                    Load(n) => n as i32,
+                }
+            }
+        "#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let layouts = mir.layouts();
+
+    let opcode = layouts.enum_layout("Opcode").unwrap();
+    assert_eq!((opcode.tag, opcode.payload), (Type::U16, 2));
+    assert_eq!(opcode.layout, Layout::new(4, 2));
+    let status = layouts.enum_layout("Status").unwrap();
+    assert_eq!((status.tag, status.payload), (Type::I32, 8));
+    assert_eq!(status.layout, Layout::new(16, 8));
+    let wide = layouts.enum_layout("Wide").unwrap();
+    assert_eq!((wide.tag, wide.layout), (Type::I64, Layout::new(8, 8)));
+
+    // match 按 repr 指定的标签类型比较
+    let text = mir.to_string();
+    assert!(text.contains("// enum Opcode: size 4, align 2, tag u16, payload at 2\n"));
+    let body = mir.body("opcode").unwrap();
+    assert!(body.locals.iter().any(|decl| decl.ty == Type::U16));
+}
+
+#[test]
+fn test_size_of_and_align_of() {
+    let output = run(r#"
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            length: u32,
+            flag: u8,
+        }
+
+        struct Reordered {
+            kind: u8,
+            length: u32,
+            flag: u8,
+        }
+
+        #[repr(packed)]
+        #[derive(Debug)]
+        struct Packed {
+            kind: u8,
+            length: u32,
+        }
+
+        enum Shape {
+            Circle(f64),
+            Rect(i32, i32),
+            Empty,
+        }
+
+        fn bytes(count: usize) -> usize {
+            count * std::mem::size_of::<Header>()
+        }
+
+        fn main() {
+            print(std::mem::size_of::<Header>(), std::mem::align_of::<Header>());
+            print(std::mem::size_of::<Reordered>(), std::mem::size_of::<Packed>());
+            print(std::mem::align_of::<Packed>(), bytes(3));
+            print(std::mem::size_of::<Shape>(), std::mem::size_of::<Option<i64>>());
+            print(std::mem::size_of::<(u8, string)>(), std::mem::size_of::<[u16; 5]>());
+            print(std::mem::size_of::<Vec<Header>>(), std::mem::align_of::<()>());
+            print(packed_debug(Packed { kind: 1, length: 2 }));
+        }
+    "#);
+    assert_eq!(
+        output,
+        "12\n4\n8\n5\n1\n36\n16\n16\n24\n10\n8\n1\nPacked { kind: 1, length: 2 }\n"
+    );
+}
+
+#[test]
+fn test_diagnostics() {
+    let errors = check(
+        r#"
+        #[repr(packed)]
+        enum E { A }
+        #[repr(u8, aligned)]
+        struct S { x: i32 }
+        #[repr]
+        struct T { x: i32 }
+        #[repr(C)]
+        fn f() {}
+        fn main() {}
+    "#,
+    );
+    let repr = |code, message: &str| (Some(code), message.to_string());
+    assert_eq!(
+        errors,
+        [
+            repr(
+                ErrorCode::E0517,
+                "`#[repr(packed)]` can only be applied to structs"
+            ),
+            repr(
+                ErrorCode::E0517,
+                "`#[repr(u8)]` can only be applied to enums"
+            ),
+            repr(
+                ErrorCode::E0552,
+                "unrecognized representation hint `aligned`"
+            ),
+            repr(
+                ErrorCode::E0552,
+                "`#[repr]` needs a representation hint, like `#[repr(C)]`"
+            ),
+            repr(
+                ErrorCode::E0517,
+                "`#[repr]` can only be applied to structs and enums"
+            ),
+        ]
+    );
+
+    let errors = check(
+        r#"
+        enum List { Cons(i32, List), Nil }
+        fn g<T>(x: T) -> usize { std::mem::size_of::<T>() }
+        fn id<T>(x: T) -> T { x }
+        fn main() {
+            let a = std::mem::size_of::<i32, i64>();
+            let b = std::mem::size_of;
+            let c = std::mem::align_of::<i32>;
+            let d = id::<i32>(1);
+            let e = std::mem::size_of::<i32>(5);
+            let f = std::mem::size_of::<List>();
+        }
+    "#,
+    );
+    let messages: Vec<&str> = errors.iter().map(|(_, message)| message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "recursive type `List` has infinite size",
+            "cannot compute the layout of the generic type `T`",
+            "`std::mem::size_of` takes 1 type argument but 2 were supplied",
+            "`std::mem::size_of` needs a type argument",
+            "`std::mem::align_of` must be called",
+            "explicit type arguments are not supported here",
+            "function `std::mem::size_of` takes 0 arguments but 1 was supplied",
+        ]
+    );
+    // 递归类型在定义处报告，`size_of::<List>()` 不再重复报告
+    assert_eq!(errors[0].0, Some(ErrorCode::E0072));
+    assert_eq!(errors[6].0, Some(ErrorCode::E0061));
+    assert!(errors
+        .iter()
+        .enumerate()
+        .all(|(i, (code, _))| i == 0 || i == 6 || *code == Some(ErrorCode::E0704)));
+}
@@ -0,0 +1,138 @@
+// Contractus let 模式和可反驳性测试
+// let、for 和参数中的模式必须匹配所有的值；可能不匹配的模式用 `let ... else { ... }`，
+// else 块在模式不匹配时执行，必须以 return、break 或 continue 离开
+
+mod common;
+
+use common::{errors, run};
+use contractus::ast::{Item, Statement};
+use contractus::diagnostic::ErrorCode;
+use contractus::module;
+
+const PROGRAM: &str = "enum Shape {
+    Circle(i32),
+    Square(i32, i32),
+}
+
+fn area(shape: Shape) -> i32 {
+    let Square(w, h) = shape else {
+        return 0;
+    };
+    w * h
+}
+
+fn half(x: i32) -> Option<i32> {
+    if x % 2 == 0 {
+        return Some(x / 2);
+    }
+    None
+}
+
+fn sum(values: [i32; 4]) -> i32 {
+    let mut total = 0;
+    for value in values {
+        let Some(x) = half(value) else {
+            break;
+        };
+        let (double, _) = (x * 2, x);
+        total = total + double;
+    }
+    total
+}
+
+fn main() {
+    print(area(Shape::Square(2, 3)));
+    print(area(Shape::Circle(1)));
+    print(sum([2, 4, 5, 8]));
+}
+";
+
+#[test]
+fn test_let_else() {
+    assert_eq!(run(PROGRAM), "6\n0\n6\n");
+
+    let program = module::parse_source(PROGRAM).unwrap();
+    let Item::Function(area) = &program.items[1] else {
+        panic!("expected a function");
+    };
+    let Statement::Let(binding) = &area.body.statements[0] else {
+        panic!("expected a let statement");
+    };
+    assert!(binding.else_block.is_some());
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_refutable_patterns() {
+    let check = |body: &str| {
+        errors(&format!(
+            "struct Point {{\n    x: i32,\n    y: i32,\n}}\nfn main() {{\n    let value = Some(1);\n{}\n}}\n",
+            body
+        ))
+    };
+
+    for (body, message) in [
+        (
+            "    let Some(x) = value;",
+            "refutable pattern in local binding: `None` not covered",
+        ),
+        (
+            "    let (true, x) = (false, 1);",
+            "refutable pattern in local binding: `(false, _)` not covered",
+        ),
+        (
+            "    let Point { x: 0, y } = Point { x: 1, y: 2 };",
+            "refutable pattern in local binding: `_` not covered",
+        ),
+        (
+            "    for Some(x) in [value] {}",
+            "refutable pattern in `for` loop binding: `None` not covered",
+        ),
+        (
+            "    let f = |Some(x): Option<i32>| x;",
+            "refutable pattern in function argument: `None` not covered",
+        ),
+    ] {
+        let found = check(body);
+        assert_eq!(found.len(), 1, "{}: {:?}", body, found);
+        assert_eq!(found[0], (Some(ErrorCode::E0297), message.to_string()));
+    }
+
+    // 不可反驳的模式
+    assert!(check("    let (a, (b, _)) = (1, (2, 3));\n    let Point { x, y: _ } = Point { x: a, y: b };\n    print(x);").is_empty());
+
+    let found = errors("fn first(Some(x): Option<i32>) -> i32 {\n    return x;\n}\nfn main() {}\n");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0297));
+}
+
+#[test]
+fn test_let_else_must_diverge() {
+    let check = |body: &str| {
+        errors(&format!(
+            "fn main() {{\n    let value = Some(1);\n{}\n}}\n",
+            body
+        ))
+    };
+
+    let found = check("    let Some(x) = value else {\n        print(0);\n    };");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0308),
+            "the `else` block of `let ... else` must diverge".to_string()
+        )]
+    );
+
+    // 模式绑定的名字在 else 块中不可用
+    let found = check("    let Some(x) = value else {\n        print(x);\n        return;\n    };");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0425));
+
+    assert!(check(
+        "    let Some(x) = value else {\n        if true {\n            return;\n        } else {\n            return;\n        }\n    };\n    print(x);"
+    )
+    .is_empty());
+}
@@ -1,6 +1,7 @@
 // Contractus 词法分析器测试
 // 测试词法分析器的所有功能
 
+use contractus::source_map::{SourceFile, SourceMap};
 use contractus::{Lexer, TokenKind};
 
 #[test]
@@ -10,12 +11,12 @@ fn test_basic_tokens() {
     let tokens = lexer.tokenize().unwrap();
 
     assert_eq!(tokens[0].kind, TokenKind::Fn);
-    assert_eq!(tokens[1].kind, TokenKind::Ident("main".to_string()));
+    assert_eq!(tokens[1].kind, TokenKind::Ident("main".into()));
     assert_eq!(tokens[2].kind, TokenKind::LeftParen);
     assert_eq!(tokens[3].kind, TokenKind::RightParen);
     assert_eq!(tokens[4].kind, TokenKind::LeftBrace);
     assert_eq!(tokens[5].kind, TokenKind::Let);
-    assert_eq!(tokens[6].kind, TokenKind::Ident("x".to_string()));
+    assert_eq!(tokens[6].kind, TokenKind::Ident("x".into()));
     assert_eq!(tokens[7].kind, TokenKind::Assign);
     assert_eq!(tokens[8].kind, TokenKind::IntLiteral(42));
     assert_eq!(tokens[9].kind, TokenKind::Semicolon);
@@ -78,13 +79,13 @@ fn test_struct_definition() {
     let tokens = lexer.tokenize().unwrap();
 
     assert_eq!(tokens[0].kind, TokenKind::Struct);
-    assert_eq!(tokens[1].kind, TokenKind::Ident("Point".to_string()));
+    assert_eq!(tokens[1].kind, TokenKind::Ident("Point".into()));
     assert_eq!(tokens[2].kind, TokenKind::LeftBrace);
-    assert_eq!(tokens[3].kind, TokenKind::Ident("x".to_string()));
+    assert_eq!(tokens[3].kind, TokenKind::Ident("x".into()));
     assert_eq!(tokens[4].kind, TokenKind::Colon);
     assert_eq!(tokens[5].kind, TokenKind::I32);
     assert_eq!(tokens[6].kind, TokenKind::Comma);
-    assert_eq!(tokens[7].kind, TokenKind::Ident("y".to_string()));
+    assert_eq!(tokens[7].kind, TokenKind::Ident("y".into()));
     assert_eq!(tokens[8].kind, TokenKind::Colon);
     assert_eq!(tokens[9].kind, TokenKind::I32);
 }
@@ -96,17 +97,63 @@ fn test_for_loop_syntax() {
     let tokens = lexer.tokenize().unwrap();
 
     assert_eq!(tokens[0].kind, TokenKind::For);
-    assert_eq!(tokens[1].kind, TokenKind::Ident("i".to_string()));
+    assert_eq!(tokens[1].kind, TokenKind::Ident("i".into()));
     assert_eq!(tokens[2].kind, TokenKind::In);
     assert_eq!(tokens[3].kind, TokenKind::IntLiteral(0));
     assert_eq!(tokens[4].kind, TokenKind::DotDot);
     assert_eq!(tokens[5].kind, TokenKind::IntLiteral(10));
     assert_eq!(tokens[6].kind, TokenKind::LeftBrace);
-    assert_eq!(tokens[7].kind, TokenKind::Ident("print".to_string()));
+    assert_eq!(tokens[7].kind, TokenKind::Ident("print".into()));
     assert_eq!(tokens[8].kind, TokenKind::LeftParen);
-    assert_eq!(tokens[9].kind, TokenKind::Ident("i".to_string()));
+    assert_eq!(tokens[9].kind, TokenKind::Ident("i".into()));
     assert_eq!(tokens[10].kind, TokenKind::RightParen);
     assert_eq!(tokens[11].kind, TokenKind::Semicolon);
     assert_eq!(tokens[12].kind, TokenKind::RightBrace);
     assert_eq!(tokens[13].kind, TokenKind::Eof);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_labels_and_chars() {
+    let tokens = Lexer::new("'outer: while 'a' != '_' { break 'outer; }")
+        .tokenize()
+        .unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Label("outer".into()),
+            TokenKind::Colon,
+            TokenKind::While,
+            TokenKind::CharLiteral('a'),
+            TokenKind::NotEqual,
+            TokenKind::CharLiteral('_'),
+            TokenKind::LeftBrace,
+            TokenKind::Break,
+            TokenKind::Label("outer".into()),
+            TokenKind::Semicolon,
+            TokenKind::RightBrace,
+            TokenKind::Eof,
+        ]
+    );
+    assert_eq!(TokenKind::Label("outer".into()).to_string(), "'outer");
+}
+
+#[test]
+fn test_token_text() {
+    let input = "let s = \"a\\tb\";  // 注释\nlet u = \"ü\" + 0x1F;";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let file = SourceFile::new("main.ctx", input);
+    let texts: Vec<&str> = tokens.iter().map(|token| token.text(&file)).collect();
+    assert_eq!(
+        texts,
+        ["let", "s", "=", "\"a\\tb\"", ";", "let", "u", "=", "\"ü\"", "+", "0x1F", ";", ""]
+    );
+
+    // 拼接进来的文件的记号按它的偏移起点换算
+    let mut sources = SourceMap::new();
+    let id = sources.add("lib.ctx".into(), b"fn one()".to_vec());
+    let mut tokens = Lexer::new("fn one()").tokenize().unwrap();
+    sources.relocate(id, &mut tokens);
+    assert_eq!(tokens[1].text(sources.file(id)), "one");
+    assert_eq!(tokens[1].text(&file), "");
+}
@@ -0,0 +1,226 @@
+// Contractus 资源限制测试
+// 源文件的大小、记号数、语法树节点数、表达式的嵌套层数和错误数超出限制时报告 E0803 并停下
+
+use contractus::diagnostic::{Diagnostic, ErrorCode};
+use contractus::driver::{CompileLimits, Compiler};
+use contractus::sandbox::{self, Status};
+use std::fs;
+
+const PROGRAM: &str =
+    "fn square(x: i32) -> i32 {\n    return x * x;\n}\nfn main() {\n    print(square(3));\n}\n";
+
+fn check(source: &str, limits: CompileLimits) -> Vec<Diagnostic> {
+    Compiler::new().source(source).limits(limits).check()
+}
+
+fn too_large(errors: &[Diagnostic]) -> bool {
+    errors.len() == 1
+        && errors[0].code == Some(ErrorCode::E0803)
+        && errors[0].message.starts_with("program too large: ")
+}
+
+#[test]
+fn test_size_limits() {
+    // 刚好在限制之内的程序正常编译
+    let exact = CompileLimits {
+        max_file_size: Some(PROGRAM.len()),
+        max_tokens: Some(30),
+        ..CompileLimits::default()
+    };
+    assert!(check(PROGRAM, exact).is_empty());
+    assert!(check(PROGRAM, CompileLimits::default()).is_empty());
+
+    let errors = check(
+        PROGRAM,
+        CompileLimits {
+            max_file_size: Some(PROGRAM.len() - 1),
+            ..CompileLimits::default()
+        },
+    );
+    assert!(too_large(&errors), "{:?}", errors);
+    assert!(errors[0].message.contains("more than the limit of"));
+
+    let errors = check(
+        PROGRAM,
+        CompileLimits {
+            max_tokens: Some(29),
+            ..CompileLimits::default()
+        },
+    );
+    assert!(too_large(&errors), "{:?}", errors);
+    assert_eq!(errors[0].message, "program too large: more than 29 tokens");
+    assert_eq!((errors[0].span.line, errors[0].span.column), (6, 1));
+}
+
+#[test]
+fn test_syntax_tree_limit() {
+    let nodes = |max| {
+        check(
+            PROGRAM,
+            CompileLimits {
+                max_ast_nodes: Some(max),
+                ..CompileLimits::default()
+            },
+        )
+    };
+    // 这个程序有 12 个节点
+    assert!(nodes(12).is_empty());
+    let errors = nodes(11);
+    assert!(too_large(&errors), "{:?}", errors);
+    assert_eq!(
+        errors[0].message,
+        "program too large: more than 11 syntax tree nodes"
+    );
+    // 中途停下不会引出其他的语法错误
+    assert!(too_large(&nodes(3)));
+
+    // 很长的函数在限制处停下，只报告一个错误
+    let long = format!("fn main() {{\n{}}}\n", "    print(1 + 2);\n".repeat(10_000));
+    let errors = check(
+        &long,
+        CompileLimits {
+            max_ast_nodes: Some(1000),
+            ..CompileLimits::default()
+        },
+    );
+    assert!(too_large(&errors), "{:?}", errors);
+}
+
+#[test]
+fn test_nesting_limit() {
+    let program = |expr: String| {
+        format!(
+            "fn main() {{\n    let x: i64 = {};\n    print(x);\n}}\n",
+            expr
+        )
+    };
+    let sum = |n| program(vec!["1"; n].join(" + "));
+
+    // 一长串加法在默认的上限处停下，不会耗尽栈
+    assert!(check(&sum(100), CompileLimits::default()).is_empty());
+    let errors = check(&sum(3000), CompileLimits::default());
+    assert!(too_large(&errors), "{:?}", errors);
+    assert_eq!(
+        errors[0].message,
+        "program too large: expressions nested more than 128 levels deep"
+    );
+
+    // 括号、一元运算符和后缀也都算一层
+    let depth = |source: &str, max| {
+        check(
+            source,
+            CompileLimits {
+                max_nesting_depth: Some(max),
+                ..CompileLimits::default()
+            },
+        )
+    };
+    let parens = program(format!("{}1{}", "(".repeat(10), ")".repeat(10)));
+    assert!(depth(&parens, 11).is_empty());
+    assert!(too_large(&depth(&parens, 10)));
+    let negations = program(format!("{}1", "-".repeat(10)));
+    assert!(depth(&negations, 11).is_empty());
+    assert!(too_large(&depth(&negations, 10)));
+    let calls = program(format!("1{}", ".abs()".repeat(10)));
+    assert!(too_large(&depth(&calls, 10)));
+
+    // 不限制时照常编译
+    let unlimited = CompileLimits {
+        max_nesting_depth: None,
+        ..CompileLimits::default()
+    };
+    assert!(check(&sum(200), unlimited).is_empty());
+}
+
+#[test]
+fn test_error_limit() {
+    let source = "fn main() {\n    print(a);\n    print(b);\n    print(c);\n    print(d);\n}";
+    assert_eq!(check(source, CompileLimits::default()).len(), 4);
+    let errors = check(
+        source,
+        CompileLimits {
+            max_errors: Some(2),
+            ..CompileLimits::default()
+        },
+    );
+    assert_eq!(errors.len(), 3);
+    assert!(errors[1].message.contains("`b`"));
+    assert_eq!(errors[2].code, Some(ErrorCode::E0803));
+    assert_eq!(
+        errors[2].message,
+        "program too large: aborting after 2 errors, 2 more not shown"
+    );
+
+    let output = Compiler::new()
+        .source(source)
+        .limits(CompileLimits {
+            max_errors: Some(1),
+            ..CompileLimits::default()
+        })
+        .run();
+    assert_eq!(output.diagnostics.len(), 2);
+}
+
+#[test]
+fn test_file_limits() {
+    let dir = std::env::temp_dir().join(format!("contractus_limits_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let limits = CompileLimits {
+        max_file_size: Some(200),
+        ..CompileLimits::default()
+    };
+
+    // 导入的模块太大时不读入
+    fs::write(
+        dir.join("main.ctx"),
+        "import big;\nfn main() { print(big::one()); }\n",
+    )
+    .unwrap();
+    let big = format!(
+        "pub fn one() -> i32 {{ return 1; }}\n{}",
+        "// padding\n".repeat(30)
+    );
+    fs::write(dir.join("big.ctx"), &big).unwrap();
+    let errors = Compiler::new()
+        .file(dir.join("main.ctx"))
+        .limits(limits)
+        .check();
+    assert!(too_large(&errors), "{:?}", errors);
+    assert!(errors[0].file.as_deref().unwrap().ends_with("big.ctx"));
+
+    // `include!` 读入的文件也受限制
+    fs::write(
+        dir.join("main.ctx"),
+        "include!(\"big.ctx\");\nfn main() { print(one()); }\n",
+    )
+    .unwrap();
+    let errors = Compiler::new()
+        .file(dir.join("main.ctx"))
+        .limits(limits)
+        .check();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(
+        errors[0].message.contains("more than the limit of 200"),
+        "{}",
+        errors[0].message
+    );
+    assert!(Compiler::new()
+        .file(dir.join("main.ctx"))
+        .check()
+        .is_empty());
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_sandbox_limits() {
+    let source = format!("fn main() {{\n{}}}\n", "    print(1);\n".repeat(100_000));
+    let outcome = sandbox::compile_and_run(&source, &sandbox::Limits::default());
+    assert_eq!(outcome.status, Status::CompileError);
+    assert!(too_large(&outcome.diagnostics), "{}", outcome.stderr);
+    assert!(
+        outcome.stderr.contains("error[E0803]"),
+        "{}",
+        outcome.stderr
+    );
+}
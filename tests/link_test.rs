@@ -0,0 +1,224 @@
+// Contractus 链接驱动测试
+// 检查目标平台解析和各平台的链接命令；本机有 C 编译器时实际生成并运行可执行文件
+
+use contractus::link::{self, LinkOptions, Os, Target};
+use contractus::mir::transform::{self, OptLevel};
+use contractus::{bytecode, mir, Lexer, Parser, SemanticAnalyzer};
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+fn target(triple: &str) -> Target {
+    triple.parse().expect("invalid target")
+}
+
+fn command_args(command: &Command) -> Vec<String> {
+    command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn test_parse_targets() {
+    let linux = target("x86_64-unknown-linux-gnu");
+    assert_eq!(linux.os, Os::Linux);
+    assert_eq!(linux.arch, "x86_64");
+    assert!(!linux.msvc);
+
+    assert_eq!(target("aarch64-apple-darwin").os, Os::MacOs);
+
+    let msvc = target("x86_64-pc-windows-msvc");
+    assert_eq!(msvc.os, Os::Windows);
+    assert!(msvc.msvc);
+    assert!(!target("x86_64-pc-windows-gnu").msvc);
+
+    let wasi = target("wasm32-unknown-wasi");
+    assert_eq!(wasi.os, Os::Wasi);
+    assert_eq!(
+        link::executable_path(Path::new("main"), &wasi),
+        Path::new("main.wasm")
+    );
+
+    let error = "wasm32-unknown-unknown".parse::<Target>().unwrap_err();
+    assert!(
+        error.contains("unsupported target operating system `unknown`"),
+        "{}",
+        error
+    );
+    let error = "x86_64".parse::<Target>().unwrap_err();
+    assert!(error.contains("invalid target `x86_64`"), "{}", error);
+
+    let host = Target::host();
+    assert!(host.is_host());
+    assert_eq!(host.triple.parse::<Target>().unwrap(), host);
+}
+
+#[test]
+fn test_executable_path() {
+    let windows = target("x86_64-pc-windows-msvc");
+    assert_eq!(
+        link::executable_path(Path::new("main"), &windows),
+        Path::new("main.exe")
+    );
+    assert_eq!(
+        link::executable_path(Path::new("main.exe"), &windows),
+        Path::new("main.exe")
+    );
+    let linux = target("x86_64-unknown-linux-gnu");
+    assert_eq!(
+        link::executable_path(Path::new("main"), &linux),
+        Path::new("main")
+    );
+}
+
+#[test]
+fn test_link_commands_per_platform() {
+    let object = Path::new("main.o");
+    let runtime = Path::new("libcontractus.a");
+    let output = Path::new("main");
+
+    let options = LinkOptions {
+        target: target("aarch64-apple-darwin"),
+        linker: None,
+        runtime: None,
+        opt_level: OptLevel::O0,
+    };
+    let command = link::link_command(&options, object, runtime, output);
+    let args = command_args(&command);
+    assert!(args.contains(&"-lSystem".to_string()), "{:?}", args);
+    assert!(args.contains(&"-Wl,-dead_strip".to_string()), "{:?}", args);
+
+    let options = LinkOptions {
+        target: target("x86_64-pc-windows-msvc"),
+        linker: Some("lld".to_string()),
+        runtime: None,
+        opt_level: OptLevel::O2,
+    };
+    let command = link::link_command(&options, object, runtime, output);
+    assert_eq!(command.get_program(), "lld-link");
+    let args = command_args(&command);
+    assert!(args.contains(&"/OUT:main".to_string()), "{:?}", args);
+    assert!(args.contains(&"ws2_32.lib".to_string()), "{:?}", args);
+
+    let options = LinkOptions {
+        target: target("x86_64-unknown-linux-gnu"),
+        linker: Some("lld".to_string()),
+        runtime: None,
+        opt_level: OptLevel::O2,
+    };
+    let command = link::link_command(&options, object, runtime, output);
+    let args = command_args(&command);
+    assert!(args.contains(&"-fuse-ld=lld".to_string()), "{:?}", args);
+    assert!(!args.contains(&"-s".to_string()), "{:?}", args);
+    assert!(args.contains(&"-lpthread".to_string()), "{:?}", args);
+    // 目标文件在运行时静态库之前，系统库在最后
+    let object_at = args.iter().position(|arg| arg == "main.o").unwrap();
+    let runtime_at = args
+        .iter()
+        .position(|arg| arg == "libcontractus.a")
+        .unwrap();
+    assert!(object_at < runtime_at);
+    assert_eq!(args.last().unwrap(), "-lc");
+}
+
+#[test]
+fn test_opt_level_flags() {
+    let source = Path::new("main.c");
+    let object = Path::new("main.o");
+    let mut options = LinkOptions {
+        target: target("x86_64-unknown-linux-gnu"),
+        linker: None,
+        runtime: None,
+        opt_level: OptLevel::O0,
+    };
+    let args = command_args(&link::compile_command(&options, source, object));
+    assert!(args.contains(&"-g".to_string()), "{:?}", args);
+
+    options.opt_level = OptLevel::Os;
+    let args = command_args(&link::compile_command(&options, source, object));
+    assert!(args.contains(&"-Os".to_string()), "{:?}", args);
+    let args = command_args(&link::link_command(
+        &options,
+        object,
+        Path::new("libcontractus.a"),
+        Path::new("main"),
+    ));
+    assert!(args.contains(&"-s".to_string()), "{:?}", args);
+
+    options.target = target("x86_64-pc-windows-msvc");
+    let args = command_args(&link::compile_command(&options, source, object));
+    assert!(args.contains(&"/O1".to_string()), "{:?}", args);
+}
+
+#[test]
+fn test_cross_target_needs_runtime() {
+    let foreign = if Target::host().os == Os::Windows {
+        target("x86_64-unknown-linux-gnu")
+    } else {
+        target("x86_64-pc-windows-msvc")
+    };
+    if env::var_os(link::RUNTIME_ENV).is_none() {
+        let error = link::find_runtime(&foreign).unwrap_err();
+        assert!(error.contains("no runtime library for target"), "{}", error);
+    }
+}
+
+#[test]
+fn test_object_source_embeds_bytecode() {
+    let source = link::object_source(b"CTXB\x01\x00");
+    assert!(
+        source.contains("0x43, 0x54, 0x58, 0x42, 0x01, 0x00,"),
+        "{}",
+        source
+    );
+    assert!(
+        source.contains("return contractus_rt_main(BYTECODE, sizeof BYTECODE);"),
+        "{}",
+        source
+    );
+}
+
+// 需要本机的 C 编译器和 cargo 构建出的运行时静态库，缺少时跳过
+#[test]
+fn test_build_and_run_executable() {
+    let options = LinkOptions::default();
+    let Ok(runtime) = link::find_runtime(&options.target) else {
+        return;
+    };
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        return;
+    }
+
+    let source = r#"
+        fn square(x: i32) -> i32 {
+            return x * x;
+        }
+
+        fn main() {
+            for i in 1..4 {
+                print(square(i));
+            }
+        }
+    "#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let program = Parser::new(tokens).parse().unwrap();
+    SemanticAnalyzer::new().analyze(&program).unwrap();
+    let mut program = mir::monomorphize(&mir::lower_program(&program).unwrap()).unwrap();
+    transform::optimize(&mut program, OptLevel::O1);
+    let module = bytecode::compile(&program).unwrap();
+
+    let output = env::temp_dir().join(format!("contractus-link-test-{}", std::process::id()));
+    let options = LinkOptions {
+        runtime: Some(runtime),
+        ..options
+    };
+    let exe = link::build_executable(&module, &output, &options).expect("build failed");
+    let result = Command::new(&exe)
+        .output()
+        .expect("cannot run the executable");
+    let _ = std::fs::remove_file(&exe);
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8_lossy(&result.stdout), "1\n4\n9\n");
+}
@@ -0,0 +1,132 @@
+// Contractus 字面量转义测试
+// 词法分析器和还原源码共用 literal.rs 中的转义规则；错误的转义按转义序列本身报告位置
+
+use contractus::diagnostic::ErrorCode;
+use contractus::literal::{self, EscapeError};
+use contractus::{module, Lexer, TokenKind};
+use std::borrow::Cow;
+
+#[test]
+fn test_unescape() {
+    assert!(matches!(
+        literal::unescape("plain ü"),
+        Ok(Cow::Borrowed("plain ü"))
+    ));
+    assert_eq!(
+        literal::unescape("a\\tb\\n\\\\\\0\\\"\\'").unwrap(),
+        "a\tb\n\\\0\"'"
+    );
+    // 换行和多字节字符原样保留
+    assert_eq!(literal::unescape("中\n文\\r").unwrap(), "中\n文\r");
+
+    // 所有错误的转义都报告，范围是转义序列在内容中的字节范围
+    assert_eq!(
+        literal::unescape("x\\qy\\é\\").unwrap_err(),
+        [
+            EscapeError {
+                message: "Invalid escape sequence '\\q'".to_string(),
+                range: 1..3,
+            },
+            EscapeError {
+                message: "Invalid escape sequence '\\é'".to_string(),
+                range: 4..7,
+            },
+            EscapeError {
+                message: "Unterminated escape sequence".to_string(),
+                range: 7..8,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_escape_roundtrip() {
+    for text in [
+        "tab\there",
+        "quote \" and ' and \\",
+        "nul \0 cr \r",
+        "多字节",
+    ] {
+        let mut literal = String::new();
+        for c in text.chars() {
+            literal::escape(&mut literal, c, '"');
+        }
+        assert_eq!(literal::unescape(&literal).unwrap(), text);
+    }
+    let mut literal = String::new();
+    literal::escape(&mut literal, '\'', '\'');
+    assert_eq!(literal, "\\'");
+}
+
+#[test]
+fn test_lexer_values() {
+    let tokens = Lexer::new("\"a\\tb\" \"it\\'s\" '\\\"' '\\n'")
+        .tokenize()
+        .unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::StringLiteral("a\tb".into()),
+            TokenKind::StringLiteral("it's".into()),
+            TokenKind::CharLiteral('"'),
+            TokenKind::CharLiteral('\n'),
+            TokenKind::Eof,
+        ]
+    );
+
+    // 字面量的值还原为源码后解析回同样的值
+    let source = "fn main() {\n    print(\"a\\\\b\\n\", '\\'');\n}\n";
+    let program = module::parse_source(source).unwrap();
+    assert_eq!(program.to_source(), source);
+}
+
+#[test]
+fn test_escape_error_spans() {
+    let source = "let s = \"a\\qb\n c\\zd\";\nlet c = '\\w';";
+    let errors = Lexer::new(source).tokenize().unwrap_err();
+    let found: Vec<(ErrorCode, &str, u32, u32, &str)> = errors
+        .iter()
+        .map(|error| {
+            let span = error.span;
+            let text = &source[span.start..span.end];
+            (
+                error.code,
+                error.message.as_str(),
+                span.line,
+                span.column,
+                text,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                ErrorCode::E0003,
+                "Invalid escape sequence '\\q' at line 1",
+                1,
+                11,
+                "\\q"
+            ),
+            (
+                ErrorCode::E0003,
+                "Invalid escape sequence '\\z' at line 2",
+                2,
+                3,
+                "\\z"
+            ),
+            (
+                ErrorCode::E0003,
+                "Invalid escape sequence '\\w' in character literal at line 3",
+                3,
+                10,
+                "\\w"
+            ),
+        ]
+    );
+
+    // 字面量之后的记号照常扫描，不会把字符串的剩余部分当作代码
+    let errors = Lexer::new("\"\\q + 1\" x").tokenize().unwrap_err();
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,250 @@
+// Contractus 整数字面量类型测试
+// 没有后缀的字面量的类型来自上下文（标注、参数、返回值、另一个操作数、变量之后的使用），
+// 带后缀的字面量的类型就是后缀；字面量和常量的整数值必须在类型的范围内
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{Lexer, TokenKind};
+
+const PROGRAM: &str = "const MASK: u8 = 0b1111 << 4;
+
+const LIMIT: u64 = 1 << 40;
+
+fn widen(n: u64) -> u64 {
+    n * 2
+}
+
+fn level() -> u8 {
+    if true {
+        255
+    } else {
+        0
+    }
+}
+
+fn main() {
+    let big = 3000000000;
+    print(widen(big));
+    let small: i8 = -128;
+    let typed = 200u8 + 55;
+    print(small, typed, 5i64 * -3, 65000u16);
+    print(MASK, LIMIT, level());
+    match typed {
+        255u8 => print(\"max\"),
+        _ => print(\"other\"),
+    }
+}
+";
+
+fn in_main(body: &str) -> Vec<(Option<ErrorCode>, String)> {
+    errors(&format!("fn main() {{\n{}\n}}\n", body))
+}
+
+fn out_of_range(ty: &str) -> (Option<ErrorCode>, String) {
+    (
+        Some(ErrorCode::E0706),
+        format!("literal out of range for `{}`", ty),
+    )
+}
+
+#[test]
+fn test_literal_types() {
+    assert_eq!(
+        run(PROGRAM),
+        "6000000000\n-128\n255\n-15\n65000\n240\n1099511627776\n255\nmax\n"
+    );
+
+    // 后缀在还原的源码中保留
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM.replace("0b1111", "15"));
+}
+
+#[test]
+fn test_suffix_tokens() {
+    let tokens = Lexer::new("300u8 5_i64 0xffusize 0b1_u16 7")
+        .tokenize()
+        .unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::SuffixedIntLiteral(300, contractus::ast::Type::U8),
+            TokenKind::SuffixedIntLiteral(5, contractus::ast::Type::I64),
+            TokenKind::SuffixedIntLiteral(255, contractus::ast::Type::Usize),
+            TokenKind::SuffixedIntLiteral(1, contractus::ast::Type::U16),
+            TokenKind::IntLiteral(7),
+            TokenKind::Eof,
+        ]
+    );
+
+    let errors = Lexer::new("5f32 99999999999999999999")
+        .tokenize()
+        .unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "invalid suffix `f32` for number literal at line 1",
+            "integer literal is too large at line 1: the largest is 9223372036854775807",
+        ]
+    );
+}
+
+#[test]
+fn test_out_of_range() {
+    assert_eq!(in_main("    let x: u8 = 300;"), [out_of_range("u8")]);
+    assert_eq!(in_main("    let x = 128i8;"), [out_of_range("i8")]);
+    assert_eq!(in_main("    let x = -129i8;"), [out_of_range("i8")]);
+    // 没有上下文时是 i32
+    assert_eq!(in_main("    let x = 3000000000;"), [out_of_range("i32")]);
+    assert_eq!(
+        in_main("    let x: u32 = -1;"),
+        [(
+            Some(ErrorCode::E0600),
+            "cannot apply unary operator `-` to type `u32`".to_string()
+        )]
+    );
+
+    // 上下文中的类型：参数、返回值、字段、数组元素、赋值和另一个操作数
+    let found = errors(
+        "struct Pixel {
+    level: u8,
+}
+
+fn gray(level: u8) -> u16 {
+    if level > 300 {
+        return 70000;
+    }
+    1
+}
+
+fn main() {
+    gray(256);
+    let p = Pixel { level: 999 };
+    let levels: [u8; 2] = [1, 256];
+    let mut total: i8 = 0;
+    total = 200;
+}
+",
+    );
+    assert_eq!(
+        found,
+        [
+            out_of_range("u8"),
+            out_of_range("u16"),
+            out_of_range("u8"),
+            out_of_range("u8"),
+            out_of_range("u8"),
+            out_of_range("i8"),
+        ]
+    );
+
+    let found = Compiler::new()
+        .source("fn main() {\n    let x: u8 = 256;\n}\n")
+        .check();
+    assert_eq!(
+        found[0].help.as_deref(),
+        Some("the literal `256` does not fit into the type `u8` whose range is `0..=255`")
+    );
+}
+
+#[test]
+fn test_u64_limit() {
+    // 执行时整数是 64 位有符号数，u64 的字面量最大也是 i64::MAX
+    let output = run("fn main() {\n    let x: u64 = 9223372036854775807;\n    print(x);\n}\n");
+    assert_eq!(output, "9223372036854775807\n");
+    assert_eq!(
+        in_main("    let x: u64 = 18446744073709551615;"),
+        [(
+            Some(ErrorCode::E0005),
+            "integer literal is too large at line 2: the largest is 9223372036854775807"
+                .to_string()
+        )]
+    );
+    // 超过上限的运算在两个后端和常量传播中都是溢出
+    let source = "fn main() {\n    let x: u64 = 9223372036854775807;\n    print(x + 1);\n}\n";
+    assert_eq!(
+        common::try_run(source).unwrap_err(),
+        "attempt to add with overflow"
+    );
+}
+
+#[test]
+fn test_inferred_from_use() {
+    // 变量的类型来自之后第一次使用它的地方
+    let source = "fn takes(n: u8) {
+    print(n);
+}
+
+fn main() {
+    let a = 300;
+    takes(a);
+    let b = 200;
+    takes(b);
+}
+";
+    assert_eq!(errors(source), [out_of_range("u8")]);
+    assert_eq!(
+        in_main("    let a = 300;\n    let b: u8 = a;"),
+        [out_of_range("u8")]
+    );
+    assert_eq!(
+        in_main("    let a = 3000000000;\n    let b = a + 1u64;"),
+        []
+    );
+    // 重新绑定之后的使用不影响原来的变量
+    assert_eq!(
+        in_main("    let a = 300;\n    let a = 1;\n    let b: u8 = a;"),
+        []
+    );
+}
+
+#[test]
+fn test_suffix_mismatch() {
+    assert_eq!(
+        in_main("    let x: u8 = 5u16;"),
+        [(
+            Some(ErrorCode::E0308),
+            "expected `u8`, found `u16`".to_string()
+        )]
+    );
+    assert_eq!(
+        in_main("    let x = 1u8 + 2u16;"),
+        [(
+            Some(ErrorCode::E0308),
+            "expected `u8`, found `u16`".to_string()
+        )]
+    );
+    assert_eq!(in_main("    let x = 200u8 + 256;"), [out_of_range("u8")]);
+}
+
+#[test]
+fn test_const_evaluation() {
+    let found = errors(
+        "const BASE: u8 = 200;
+const LIMIT: u8 = BASE + 100;
+const WRAPPED: u8 = 300 as u8;
+const RATIO: i32 = 10 / (BASE as i32 - 200);
+
+fn main() {
+    print(LIMIT, WRAPPED, RATIO);
+}
+",
+    );
+    assert_eq!(
+        found,
+        [
+            (
+                Some(ErrorCode::E0080),
+                "evaluation of constant value failed: the value 300 does not fit into the type `u8`"
+                    .to_string()
+            ),
+            (
+                Some(ErrorCode::E0080),
+                "evaluation of constant value failed: attempt to divide by zero".to_string()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,125 @@
+// Contractus 调试日志测试
+// 过滤规则的解析和匹配、日志行的格式，以及 `-v`/`-q` 和 `CONTRACTUS_LOG`
+
+use contractus::log::{self, Filter, Level};
+use std::env;
+use std::fs;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+#[test]
+fn test_filter() {
+    let filter: Filter = "info,contractus::mir=trace,contractus::mir::build=off"
+        .parse()
+        .unwrap();
+    assert!(filter.enabled("contractus::driver", Level::Info));
+    assert!(!filter.enabled("contractus::driver", Level::Debug));
+    assert!(filter.enabled("contractus::mir", Level::Trace));
+    assert!(filter.enabled("contractus::mir::transform", Level::Trace));
+    assert!(!filter.enabled("contractus::mir::build", Level::Error));
+    // 只匹配整段的模块名
+    assert!(!filter.enabled("contractus::mirror", Level::Debug));
+
+    // 没有默认级别时为 warn；只写目标时输出它的所有日志
+    let filter: Filter = "contractus::sema".parse().unwrap();
+    assert!(filter.enabled("contractus::sema::types", Level::Trace));
+    assert!(filter.enabled("contractus::link", Level::Warn));
+    assert!(!filter.enabled("contractus::link", Level::Info));
+
+    // 后加的规则覆盖先加的
+    let mut filter = Filter::new(Some(Level::Error));
+    filter.add_directives("contractus::link=debug").unwrap();
+    filter.add_directives("DEBUG,contractus::link=off").unwrap();
+    assert!(filter.enabled("contractus::driver", Level::Debug));
+    assert!(!filter.enabled("contractus::link", Level::Error));
+    assert!(!Filter::new(None).enabled("contractus", Level::Error));
+
+    assert_eq!(
+        "contractus::mir=loud".parse::<Filter>(),
+        Err("invalid log level `loud` in `contractus::mir=loud`".to_string())
+    );
+    assert_eq!(
+        "contractus::=debug".parse::<Filter>(),
+        Err("invalid log target `contractus::`".to_string())
+    );
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(
+        log::target_of("src/mir/transform.rs"),
+        "contractus::mir::transform"
+    );
+    assert_eq!(log::target_of("src/driver.rs"), "contractus::driver");
+    assert_eq!(log::target_of("src/lib.rs"), "contractus");
+    assert_eq!(
+        log::format_line(
+            Duration::from_micros(1250),
+            Level::Info,
+            "contractus::sema",
+            2,
+            format_args!("checking `{}`", "main")
+        ),
+        "     1.250ms INFO  contractus::sema:     checking `main`"
+    );
+}
+
+#[test]
+fn test_verbosity_options() {
+    let dir = env::temp_dir().join(format!("contractus_log_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.ctx");
+    fs::write(&file, "fn main() { print(1 + 2); }\n").unwrap();
+    let run = |args: &[&str], filter: Option<&str>| -> Output {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_contractus"));
+        command.args(args).arg(&file).env_remove(log::ENV);
+        if let Some(filter) = filter {
+            command.env(log::ENV, filter);
+        }
+        command.output().expect("cannot run contractus")
+    };
+    let stderr = |output: &Output| String::from_utf8_lossy(&output.stderr).into_owned();
+
+    // 默认不输出日志
+    let output = run(&["run", "--vm"], None);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert_eq!(stderr(&output), "");
+
+    // 各阶段是 span，内层的阶段缩进
+    let output = run(&["run", "--vm", "-vv"], None);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    let log = stderr(&output);
+    assert!(
+        log.contains(" DEBUG contractus::driver: loading\n"),
+        "{}",
+        log
+    );
+    assert!(
+        log.contains(" DEBUG contractus::module:   parsing\n"),
+        "{}",
+        log
+    );
+    assert!(log.contains("contractus::driver: semantic analysis: finished in "));
+    assert!(!log.contains("TRACE"), "{}", log);
+
+    let output = run(&["--verbose"], None);
+    assert!(stderr(&output).contains(" INFO  contractus: compiling "));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("=== Syntax Analysis ==="));
+    assert_eq!(stderr(&run(&["-v", "-q"], None)), "");
+
+    // CONTRACTUS_LOG 按模块选择
+    let output = run(&["check"], Some("contractus::sema=debug"));
+    let log = stderr(&output);
+    assert!(
+        log.contains("contractus::sema: effect analysis\n"),
+        "{}",
+        log
+    );
+    assert!(!log.contains("contractus::driver"), "{}", log);
+
+    let output = run(&["check"], Some("contractus::sema=noisy"));
+    assert!(output.status.success());
+    assert!(stderr(&output).starts_with("warning: ignoring CONTRACTUS_LOG: invalid log level"));
+
+    fs::remove_dir_all(&dir).ok();
+}
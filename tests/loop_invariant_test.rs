@@ -0,0 +1,151 @@
+// Contractus 循环不变式测试
+// `while` 和 `for` 语句的条件之后可以有 `invariant` 子句，在每次求值循环条件之前检查：
+// 进入循环时、每次迭代之后和离开循环时；`--contracts=off` 时不检查
+
+mod common;
+
+use common::errors;
+use contractus::diagnostic::ErrorCode;
+use contractus::interp::{Host, Interpreter};
+use contractus::mir::ContractMode;
+use contractus::{bytecode, interp, mir, module};
+
+// 两个后端的输出和第一个运行时错误
+fn run(source: &str) -> (String, Option<String>) {
+    let execution = common::execute(source, Host::default());
+    let error = execution.error.map(|error| error.to_string());
+    (execution.output, error)
+}
+
+#[test]
+fn test_invariants_hold() {
+    let source = "fn main() {
+    let n = 5;
+    let mut i = 0;
+    let mut sum = 0;
+    while i < n invariant sum >= 0, invariant i <= n {
+        sum += i;
+        i += 1;
+    }
+    let mut count = 0;
+    for x in 0..4 invariant count <= 4 {
+        count += 1;
+    }
+    print(sum, count);
+}
+";
+    assert_eq!(run(source), ("10\n4\n".to_string(), None));
+}
+
+#[test]
+fn test_invariant_violations() {
+    // 第三次求值条件之前 `i` 已经是 2
+    let source = "fn main() {
+    let mut i = 0;
+    while i < 10 invariant i < 2 {
+        print(i);
+        i += 1;
+    }
+}
+";
+    let (output, error) = run(source);
+    assert_eq!(output, "0\n1\n");
+    let error = error.expect("the invariant should fail");
+    assert!(
+        error.contains("line 3, column 28: loop invariant violated: `i < 2`"),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("the loop invariant of `main` is declared at line 3, column 28"),
+        "{}",
+        error
+    );
+
+    // 离开循环时也检查
+    let source = "fn main() {
+    let mut total = 0;
+    for x in 0..3 invariant total < 3 {
+        total += x;
+    }
+    print(total);
+}
+";
+    let (output, error) = run(source);
+    assert_eq!(output, "");
+    assert!(error
+        .unwrap()
+        .contains("loop invariant violated: `total < 3`"));
+}
+
+#[test]
+fn test_invariants_off() {
+    let source = "fn main() {
+    let mut i = 0;
+    while i < 3 invariant i == 0 {
+        i += 1;
+    }
+    print(i);
+}
+";
+    let program = module::parse_source(source).unwrap();
+    let result = interp::with_large_stack(|| {
+        let mut interpreter = Interpreter::with_output(&program, Vec::new());
+        interpreter.set_contract_mode(ContractMode::Off);
+        interpreter.run_main().map(drop)
+    });
+    assert!(result.is_ok(), "{:?}", result);
+
+    let options = mir::LowerOptions {
+        contracts: ContractMode::Off,
+        ..Default::default()
+    };
+    let lowered = mir::lower_program_with(&program, options).unwrap();
+    let module = bytecode::compile(&mir::monomorphize(&lowered).unwrap()).unwrap();
+    assert!(!module.to_string().contains("AssertContract"));
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    assert!(result.is_ok());
+    assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+}
+
+#[test]
+fn test_invalid_invariants() {
+    let source = "fn main() {
+    let mut i = 0;
+    while i < 3 invariant i {
+        i += 1;
+    }
+}
+";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0308),
+            "`invariant` condition must be `bool`, found `i32`".to_string()
+        )]
+    );
+
+    // 不变式在绑定循环变量之前检查
+    let source = "fn main() {
+    for x in 0..3 invariant x >= 0 {
+        print(x);
+    }
+}
+";
+    assert_eq!(errors(source)[0].1, "cannot find value `x` in this scope");
+
+    let source = "fn main() {
+    let mut i = 0;
+    let f = || while i < 3 invariant i >= 0 {
+        i += 1;
+    };
+}
+";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0100),
+            "loop invariants are only allowed on loop statements".to_string()
+        )]
+    );
+}
@@ -0,0 +1,181 @@
+// Contractus 宏测试
+// `macro_rules!` 在表达式、语句和条目位置的展开，重复和片段的匹配，
+// 模板中绑定的变量的卫生性，定义和调用的诊断，以及 `--emit=expanded` 和格式化
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::format::{self, FormatOptions};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_expression_and_statement_macros() {
+    let output = run(r#"
+        macro_rules! max {
+            ($a:expr) => { $a };
+            ($a:expr, $($rest:expr),+) => { { let x = $a; let y = max!($($rest),+); if x > y { x } else { y } } };
+        }
+
+        macro_rules! stop_at {
+            ($i:ident, $limit:literal) => { if $i == $limit { break; } };
+        }
+
+        fn main() {
+            let n = 7;
+            print(max!(3, n, n - 10, 2 + 2));
+            // 多个记号的参数代入时加上括号
+            print(max![n - 1] * 2);
+            let mut i = 0;
+            while true {
+                i = i + 1;
+                stop_at!(i, 4);
+            }
+            print(i);
+        }
+    "#);
+    assert_eq!(output, "7\n12\n4\n");
+}
+
+#[test]
+fn test_item_macros() {
+    let output = run(r#"
+        macro_rules! getters {
+            ($t:ident { $($field:ident: $ty:ty),* }) => {
+                struct $t { $($field: $ty),* }
+                $(fn $field(value: $t) -> $ty { value.$field })*
+            };
+        }
+
+        getters!(Size { width: i32, height: i32 });
+
+        macro_rules! sum {
+            () => { 0 };
+            ($x:expr $(, $rest:expr)*) => { $x + sum!($($rest),*) };
+        }
+
+        fn main() {
+            let size = Size { width: 3, height: 4 };
+            print(width(size) * height(size), sum!(1, 2, 3), sum!());
+        }
+    "#);
+    assert_eq!(output, "12\n6\n0\n");
+}
+
+// 模板中 `let`、`for`、match 分支和闭包绑定的变量不会和调用处的变量冲突
+#[test]
+fn test_hygiene() {
+    let output = run(r#"
+        macro_rules! swap {
+            ($a:ident, $b:ident) => { let tmp = $a; $a = $b; $b = tmp; };
+        }
+
+        macro_rules! total {
+            ($v:expr) => { { let mut sum = 0; for x in $v { sum = sum + x; } sum } };
+        }
+
+        macro_rules! apply {
+            ($f:expr, $x:expr) => { { let f = |value| $f(value); f($x) } };
+        }
+
+        fn double(value: i32) -> i32 {
+            value * 2
+        }
+
+        fn main() {
+            let mut tmp = 1;
+            let mut other = 2;
+            swap!(tmp, other);
+            print(tmp, other);
+            let sum = 100;
+            let x = [1, 2, 3];
+            print(total!(x) + sum);
+            print(apply!(double, sum));
+        }
+    "#);
+    assert_eq!(output, "2\n1\n106\n200\n");
+}
+
+#[test]
+fn test_diagnostics() {
+    let errors = check(
+        "macro_rules! bad { ($x) => { $x }; }\nmacro_rules! add { ($a:expr, $b:expr) => { $a + $b }; }\nfn main() {\n    print(bad!(1));\n    print(missing!(1));\n    print(add!(1; 2));\n}",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            (
+                Some(ErrorCode::E0104),
+                "missing fragment specifier for `$x`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0105),
+                "cannot find macro `missing` in this scope".to_string()
+            ),
+            (
+                Some(ErrorCode::E0106),
+                "no rule of macro `add` matches this invocation".to_string()
+            ),
+        ]
+    );
+
+    let errors = check(
+        "macro_rules! forever { ($x:expr) => { forever!($x) }; }\nfn main() {\n    print(forever!(1));\n}",
+    );
+    assert_eq!(
+        errors,
+        vec![(
+            Some(ErrorCode::E0107),
+            "recursion limit reached while expanding `forever!`".to_string()
+        )]
+    );
+
+    // 展开结果的语法错误指向宏调用
+    let errors = Compiler::new()
+        .source(
+            "macro_rules! half { ($x:expr) => { $x / }; }\nfn main() {\n    print(half!(4));\n}",
+        )
+        .check();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]
+        .notes
+        .contains(&"in this expansion of `half!` on line 3".to_string()));
+}
+
+#[test]
+fn test_emit_expanded_and_format() {
+    let source = "macro_rules! square {\n    ($x:expr) => {\n        $x * $x\n    };\n    ($($x:expr),+) => {\n        [$(square!($x)),+]\n    };\n}\n\nfn main() {\n    let tmp = 2;\n    print(square!(tmp + 1), square!(1, 2));\n}\n";
+    // 格式化保留宏的写法
+    assert_eq!(
+        format::format_source(source, &FormatOptions::default()).unwrap(),
+        source
+    );
+
+    let dir = env::temp_dir().join(format!("contractus_macro_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("square.ctx");
+    fs::write(&file, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_contractus"))
+        .arg("--emit=expanded")
+        .arg(&file)
+        .output()
+        .expect("cannot run contractus");
+    assert!(output.status.success());
+    let expanded = String::from_utf8(output.stdout).unwrap();
+    assert!(expanded.ends_with(
+        "fn main() {\n    let tmp = 2;\n    print((tmp + 1) * (tmp + 1), [1 * 1, 2 * 2]);\n}\n"
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,124 @@
+// Contractus 符号修饰测试
+// 检查修饰和还原的往返、签名哈希的稳定性，以及字节码中函数符号的唯一性
+
+use contractus::ast::Type;
+use contractus::bytecode;
+use contractus::mangle::{self, Symbol};
+use contractus::mir::transform::{self, OptLevel};
+use contractus::{mir, Lexer, Parser, SemanticAnalyzer};
+
+fn path(segments: &[&str]) -> Vec<String> {
+    segments.iter().map(|segment| segment.to_string()).collect()
+}
+
+#[test]
+fn test_mangle_round_trip() {
+    let symbol = mangle::mangle(
+        &path(&["math", "add"]),
+        &[],
+        &[Type::I32, Type::I32],
+        &Type::I32,
+    );
+    assert!(symbol.starts_with("_CXN4math3addEh"), "{}", symbol);
+    assert!(symbol
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$'));
+    assert_eq!(mangle::demangle(&symbol).unwrap(), "math::add");
+
+    let generics = vec![
+        Type::Generic("Pair".to_string(), vec![Type::I32, Type::String]),
+        Type::Array(Box::new(Type::Reference(Box::new(Type::U8), true)), 3),
+        Type::Tuple(vec![Type::Bool, Type::Char]),
+        Type::Function(vec![Type::I64], Box::new(Type::Unit), false),
+    ];
+    let symbol = Symbol::new(
+        path(&["max", "{closure#2}"]),
+        generics.clone(),
+        &[],
+        &Type::Unit,
+    );
+    let mangled = symbol.mangle();
+    assert_eq!(Symbol::parse(&mangled).unwrap(), symbol);
+    assert_eq!(
+        symbol.to_string(),
+        "max::<Pair<i32, string>, [&mut u8; 3], (bool, char), fn(i64) -> ()>::{closure#2}"
+    );
+
+    // 不是标识符字符的字节被转义
+    let symbol = mangle::mangle(&path(&["Point::<i32>"]), &[], &[], &Type::Unit);
+    assert!(symbol.contains("$3a$3a$3c"), "{}", symbol);
+    assert_eq!(mangle::demangle(&symbol).unwrap(), "Point::<i32>");
+}
+
+#[test]
+fn test_signature_hash_distinguishes_overloads() {
+    let name = path(&["area"]);
+    let int = mangle::mangle(&name, &[], &[Type::I32], &Type::I32);
+    let wide = mangle::mangle(&name, &[], &[Type::I64], &Type::I64);
+    assert_ne!(int, wide);
+    assert_eq!(mangle::demangle(&int), mangle::demangle(&wide));
+    // 哈希是 `fn() -> ()` 的 FNV-1a，不依赖运行环境
+    assert_eq!(
+        mangle::signature_hash(&[], &Type::Unit),
+        0xd642_8aff_4e41_2fe4
+    );
+}
+
+#[test]
+fn test_demangle_errors_and_text() {
+    for bad in [
+        "main",
+        "_CXNE",
+        "_CXN4mainEh12",
+        "_CXN4mainEhxyz0000000000000",
+        "_CXN4mainEh0000000000000000x",
+    ] {
+        assert!(mangle::demangle(bad).is_err(), "{}", bad);
+    }
+    let main = mangle::mangle(&path(&["main"]), &[], &[], &Type::Unit);
+    let text = format!("at {} (pc 4)\n_CX is not a symbol\n", main);
+    assert_eq!(
+        mangle::demangle_text(&text),
+        "at main (pc 4)\n_CX is not a symbol\n"
+    );
+}
+
+#[test]
+fn test_bytecode_function_symbols() {
+    let source = r#"
+        fn id<T>(x: T) -> T {
+            return x;
+        }
+
+        fn main() {
+            let f = |x: i32| x + 1;
+            print(id(f(1)));
+            print(id(true));
+        }
+    "#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let program = Parser::new(tokens).parse().unwrap();
+    SemanticAnalyzer::new().analyze(&program).unwrap();
+    let mut program = mir::monomorphize(&mir::lower_program(&program).unwrap()).unwrap();
+    transform::optimize(&mut program, OptLevel::O1);
+    let module = bytecode::compile(&program).unwrap();
+
+    let mut names: Vec<String> = module
+        .functions
+        .iter()
+        .map(|func| mangle::demangle(&func.symbol).unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        ["id::<bool>", "id::<i32>", "main", "main::{closure#0}"]
+    );
+    let main = module.function("main").unwrap();
+    assert_eq!(module.function_by_symbol(&main.symbol), Some(main));
+
+    // 符号重复的文件被拒绝
+    let mut broken = module.clone();
+    broken.functions[1].symbol = broken.functions[0].symbol.clone();
+    let error = bytecode::decode(&bytecode::encode(&broken)).unwrap_err();
+    assert!(error.contains("duplicate symbol"), "{}", error);
+}
@@ -0,0 +1,336 @@
+// Contractus 项目清单测试
+// 检查 TOML 子集的解析、清单字段和构建配置、路径依赖，以及在项目目录中执行的命令
+
+use contractus::driver::Compiler;
+use contractus::manifest::{Manifest, OutputKind, Profile};
+use contractus::mir::transform::OptLevel;
+use contractus::mir::ContractMode;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn parse(source: &str) -> Manifest {
+    Manifest::parse(source, Path::new("/project")).expect("invalid manifest")
+}
+
+fn errors(source: &str) -> Vec<String> {
+    Manifest::parse(source, Path::new("/project"))
+        .unwrap_err()
+        .iter()
+        .map(|error| error.to_string())
+        .collect()
+}
+
+#[test]
+fn test_parse_manifest() {
+    let manifest = parse(
+        r#"
+        # 项目信息
+        [package]
+        name = "hello_world"     # 包名
+        entry = 'src/main.ctx'
+        output = "bytecode"
+
+        [deps]
+        math = { path = "../math" }
+
+        [deps.strings]
+        path = "vendor/strings"
+
+        [profile.release]
+        opt-level = "s"
+        contracts = "off"
+
+        [profile.bench]
+        opt-level = 1
+        "#,
+    );
+
+    assert_eq!(manifest.package.name, "hello_world");
+    assert_eq!(manifest.entry_path(), Path::new("/project/src/main.ctx"));
+    assert_eq!(manifest.package.output, OutputKind::Bytecode);
+    assert_eq!(
+        manifest.output_path("release"),
+        Path::new("/project/target/release/hello_world.ctxb")
+    );
+
+    let deps: Vec<(&str, &Path)> = manifest
+        .deps
+        .iter()
+        .map(|(name, dep)| (name.as_str(), dep.path.as_path()))
+        .collect();
+    assert_eq!(
+        deps,
+        [
+            ("math", Path::new("../math")),
+            ("strings", Path::new("vendor/strings"))
+        ]
+    );
+
+    assert_eq!(manifest.profile("debug"), Some(&Profile::debug()));
+    let release = manifest.profile("release").unwrap();
+    assert_eq!(release.opt_level, OptLevel::Os);
+    assert_eq!(release.contracts, ContractMode::Off);
+    assert!(release.bounds_checks);
+    // 自定义配置以 debug 为基础
+    let bench = manifest.profile("bench").unwrap();
+    assert_eq!(bench.opt_level, OptLevel::O1);
+    assert_eq!(bench.contracts, ContractMode::Check);
+    assert!(manifest.profile("fast").is_none());
+}
+
+#[test]
+fn test_manifest_defaults() {
+    let manifest = parse("[package]\nname = \"app\"");
+    assert_eq!(manifest.package.entry, PathBuf::from("main.ctx"));
+    assert_eq!(manifest.package.output, OutputKind::Executable);
+    assert_eq!(
+        manifest.output_path("debug"),
+        Path::new("/project/target/debug/app")
+    );
+    assert_eq!(manifest.profile("release"), Some(&Profile::release()));
+    assert!(manifest.deps.is_empty());
+}
+
+#[test]
+fn test_manifest_errors() {
+    assert_eq!(
+        errors("[package]\nname = \"app\"\nversion = \"1.0\"\n"),
+        ["error[E0850] at line 3, column 11: unknown key `package.version`\nhelp: expected one of `name`, `entry`, `output`"]
+    );
+    assert_eq!(
+        errors("[package]\nname = 3"),
+        ["error[E0850] at line 2, column 8: `package.name` must be a string, found integer"]
+    );
+    assert_eq!(
+        errors("[package]\nname = \"my app\""),
+        ["error[E0850] at line 2, column 8: invalid package name `my app`"]
+    );
+    assert_eq!(
+        errors("[deps]\nmath = { }"),
+        [
+            "error[E0850] at line 1, column 1: missing `[package]` table",
+            "error[E0850] at line 2, column 8: dependency `math` needs a `path`"
+        ]
+    );
+    assert_eq!(
+        errors("[package]\nname = \"app\"\n[profile.debug]\nopt-level = 3\nbounds-checks = \"no\""),
+        [
+            "error[E0850] at line 4, column 13: invalid optimization level `3`; expected `0`, `1`, `2` or `s`",
+            "error[E0850] at line 5, column 17: `profile.debug.bounds-checks` must be a boolean, found string"
+        ]
+    );
+
+    // 语法错误
+    assert_eq!(
+        errors("[package]\nname = \"app"),
+        ["error[E0850] at line 2, column 8: unterminated string"]
+    );
+    assert_eq!(
+        errors("[package]\nname = \"a\"\nname = \"b\""),
+        ["error[E0850] at line 3, column 1: duplicate key `name`"]
+    );
+    assert_eq!(
+        errors("[package]\nname = \"a\"\n[package]"),
+        ["error[E0850] at line 3, column 1: table `package` is defined more than once"]
+    );
+    assert_eq!(
+        errors("[package]\nname = \"a\" entry = \"b\""),
+        ["error[E0850] at line 2, column 12: unexpected `e` after value"]
+    );
+}
+
+// 在临时目录中创建项目，从子目录中执行命令
+#[test]
+fn test_project_commands() {
+    let dir = env::temp_dir().join(format!("contractus_project_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("Contractus.toml"),
+        "[package]\nname = \"demo\"\nentry = \"src/main.ctx\"\noutput = \"bytecode\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/main.ctx"),
+        "fn half(x: i32) -> i32\n    requires x % 2 == 0,\n{\n    return x / 2;\n}\n\nfn main() { print(half(3)); }\n",
+    )
+    .unwrap();
+    let contractus = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .args(args)
+            .current_dir(dir.join("src"))
+            .output()
+            .expect("cannot run contractus")
+    };
+
+    assert!(contractus(&["check"]).status.success());
+
+    // debug 配置检查契约
+    let output = contractus(&["run"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("precondition"));
+
+    // 命令行选项优先于配置
+    let output = contractus(&["run", "--contracts=off"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+
+    let output = contractus(&["build", "--release"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let bytecode = dir.join("target/release/demo.ctxb");
+    assert!(bytecode.is_file());
+
+    let output = contractus(&["build", "--profile=fast"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown profile `fast`"));
+}
+
+// 在临时目录下创建几个包，每个包是 `(目录, [(文件, 内容)])`
+fn create_packages(name: &str, packages: &[(&str, &[(&str, &str)])]) -> PathBuf {
+    let dir = env::temp_dir().join(format!("contractus_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (package, files) in packages {
+        for (file, contents) in *files {
+            let path = dir.join(package).join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+    dir
+}
+
+#[test]
+fn test_path_dependencies() {
+    let dir = create_packages(
+        "deps",
+        &[
+            (
+                "math",
+                &[
+                    ("Contractus.toml", "[package]\nname = \"math\"\n"),
+                    (
+                        "main.ctx",
+                        "import vector;\npub fn square(x: i32) -> i32 { return x * x; }",
+                    ),
+                    (
+                        "vector.ctx",
+                        "pub fn dot(a: i32, b: i32) -> i32 { return a * b; }",
+                    ),
+                ],
+            ),
+            (
+                "geo",
+                &[
+                    (
+                        "Contractus.toml",
+                        "[package]\nname = \"geo\"\n[deps]\nmath = { path = \"../math\" }\n",
+                    ),
+                    (
+                        "main.ctx",
+                        "import math;\npub fn area(w: i32) -> i32 { return math::square(w); }",
+                    ),
+                ],
+            ),
+            (
+                "app",
+                &[
+                    (
+                        "Contractus.toml",
+                        "[package]\nname = \"app\"\n[deps]\ngeo = { path = \"../geo\" }\nmath = { path = \"../math\" }\n",
+                    ),
+                    (
+                        "main.ctx",
+                        "import geo;\nimport math::vector;\nfn main() { print(geo::area(3) + vector::dot(2, 3)); }",
+                    ),
+                ],
+            ),
+        ],
+    );
+    let manifest = Manifest::load(&dir.join("app/Contractus.toml")).unwrap();
+    let deps = manifest.resolve_deps().unwrap();
+    let names: Vec<&str> = deps.iter().map(|dep| dep.name.as_str()).collect();
+    assert_eq!(names, ["math", "geo"]);
+
+    // 依赖包先检查，指纹没有变化时跳过
+    let cache = manifest.deps_dir("debug");
+    let check = || {
+        let (compiler, rebuilt) = Compiler::new().with_dependencies(&deps, &cache)?;
+        let errors = compiler.file(manifest.entry_path()).check();
+        if errors.is_empty() {
+            Ok(rebuilt)
+        } else {
+            Err(errors)
+        }
+    };
+    assert_eq!(check().unwrap(), ["math", "geo"]);
+    assert!(check().unwrap().is_empty());
+
+    // 被依赖的包变化时，依赖它的包也重新检查
+    fs::write(
+        dir.join("math/vector.ctx"),
+        "pub fn dot(a: i32, b: i32) -> i32 { return a * b + 0; }",
+    )
+    .unwrap();
+    assert_eq!(check().unwrap(), ["math", "geo"]);
+
+    // 依赖包的错误指向它自己的文件
+    fs::write(
+        dir.join("geo/main.ctx"),
+        "import math;\npub fn area(w: i32) -> i32 { return math::cube(w); }",
+    )
+    .unwrap();
+    let errors = check().unwrap_err();
+    assert!(
+        errors[0].file.as_deref().unwrap().ends_with("main.ctx")
+            && errors[0].file.as_deref().unwrap().contains("geo"),
+        "{:?}",
+        errors
+    );
+
+    // 依赖环和同名依赖指向不同目录
+    fs::write(
+        dir.join("math/Contractus.toml"),
+        "[package]\nname = \"math\"\n[deps]\ngeo = { path = \"../geo\" }\n",
+    )
+    .unwrap();
+    let errors = manifest.resolve_deps().unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "dependency cycle detected: `geo` → `math` → `geo`"
+    );
+
+    fs::write(
+        dir.join("math/Contractus.toml"),
+        "[package]\nname = \"math\"\n[deps]\ngeo = { path = \"../app\" }\n",
+    )
+    .unwrap();
+    let errors = manifest.resolve_deps().unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "dependency cycle detected: `app` → `geo` → `math` → `app`"
+    );
+
+    fs::write(
+        dir.join("math/Contractus.toml"),
+        "[package]\nname = \"math\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("app/Contractus.toml"),
+        "[package]\nname = \"app\"\n[deps]\nmath = { path = \"../geo\" }\n",
+    )
+    .unwrap();
+    let manifest = Manifest::load(&dir.join("app/Contractus.toml")).unwrap();
+    let errors = manifest.resolve_deps().unwrap_err();
+    assert!(
+        errors[0]
+            .message
+            .starts_with("dependency `math` refers to both"),
+        "{:?}",
+        errors
+    );
+}
@@ -0,0 +1,277 @@
+// Contractus match 降级测试
+// match 降级为判定树：变体和字面量合成一个 switchInt、守卫不成立后继续判定、
+// 不能 switch 的字面量按顺序测试，枚举的布局，以及大型 match 的基准测试
+
+mod common;
+
+use common::run;
+use contractus::ast::Type;
+use contractus::bench::{self, BenchOptions};
+use contractus::bytecode;
+use contractus::layout::{self, Layout, LayoutError};
+use contractus::mir::{Body, MirProgram, Rvalue, StatementKind, TerminatorKind};
+use contractus::{mir, module};
+use std::fs;
+use std::time::Duration;
+
+fn lower(input: &str) -> MirProgram {
+    let program = module::parse_source(input).expect("parsing failed");
+    mir::lower_program(&program).expect("MIR lowering failed")
+}
+
+// 函数体中每个 switchInt 的取值
+fn switches(body: &Body) -> Vec<Vec<i64>> {
+    body.blocks
+        .iter()
+        .filter_map(|block| match &block.terminator.kind {
+            TerminatorKind::SwitchInt { targets, .. } => {
+                Some(targets.iter().map(|(value, _)| *value).collect())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn discriminants(body: &Body) -> Vec<Type> {
+    body.blocks
+        .iter()
+        .flat_map(|block| &block.statements)
+        .filter_map(|statement| match &statement.kind {
+            StatementKind::Assign(place, Rvalue::Discriminant(_)) => {
+                Some(body.local_decl(place.local).ty.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_single_switch_per_match() {
+    let mir = lower(
+        r#"
+        enum Token {
+            Number(i32),
+            Plus,
+            Minus,
+            Star,
+            Slash,
+            End,
+        }
+
+        fn weight(token: Token) -> i32 {
+            match token {
+                Number(0) => 0,
+                Slash => 4,
+                Number(n) => n,
+                _ => 1,
+                Plus | Minus => 2,
+            }
+        }
+
+        fn digit(n: i32) -> string {
+            match n {
+                3 => "three",
+                1 | 2 => "small",
+                x if x < 0 => "negative",
+                9 => "nine",
+                _ => "other",
+            }
+        }
+    "#,
+    );
+
+    // 变体只取一次标签，标签的类型是能容纳所有变体序号的最小整数
+    let weight = mir.body("weight").unwrap();
+    assert_eq!(discriminants(weight), [Type::U8]);
+    let switches_on_tag = switches(weight);
+    assert_eq!(switches_on_tag[0], [0, 1, 2, 4]);
+    // `Number(0)` 的字段在 `Number` 的目标块中测试
+    assert!(
+        switches_on_tag[1..].contains(&vec![0]),
+        "{:?}",
+        switches_on_tag
+    );
+
+    // 守卫之前和之后的字面量都在同一个 switchInt 中，守卫不成立时重新判定
+    let digit = mir.body("digit").unwrap();
+    assert_eq!(switches(digit)[0], [1, 2, 3, 9]);
+}
+
+#[test]
+fn test_decision_tree_semantics() {
+    let output = run(r#"
+        enum Shape {
+            Circle(i32),
+            Rect(i32, i32),
+            Empty,
+        }
+
+        fn describe(s: Shape, flag: bool) -> i32 {
+            match s {
+                Circle(r) if r > 10 => 1,
+                Rect(1, _) => 2,
+                _ if flag => 3,
+                Circle(_) | Empty => 4,
+                Rect(a, b) => a + b,
+            }
+        }
+
+        fn word(text: string, n: i32) -> i32 {
+            match (text, n) {
+                ("one", 1) => 1,
+                (_, 2) => 2,
+                ("two", _) => 22,
+                _ => 0,
+            }
+        }
+
+        fn size(n: i32) -> string {
+            match n {
+                0 => "zero",
+                x if x % 2 == 0 => "even",
+                1 | 3 | 5 => "small odd",
+                -1 => "minus one",
+                _ => "odd",
+            }
+        }
+
+        fn half(n: i32) -> Option<i32> {
+            if n % 2 == 0 { Some(n / 2) } else { None }
+        }
+
+        fn quarter(n: i32) -> Option<i32> {
+            let h = half(n)?;
+            half(h)
+        }
+
+        fn main() {
+            print(describe(Shape::Circle(20), false), describe(Shape::Circle(1), true));
+            print(describe(Shape::Rect(1, 5), false), describe(Shape::Rect(2, 5), false));
+            print(describe(Shape::Empty, false), describe(Shape::Circle(3), false));
+            print(word("one", 1), word("one", 2), word("two", 3), word("three", 3));
+            print(size(0), size(4), size(3), size(-1), size(7));
+            print(quarter(12), quarter(6), quarter(3));
+            let flags = [true, false];
+            for f in flags {
+                print(match f {
+                    true => 'y',
+                    false => 'n',
+                });
+            }
+        }
+    "#);
+    assert_eq!(
+        output,
+        "1\n3\n2\n7\n4\n4\n1\n2\n22\n0\nzero\neven\nsmall odd\nminus one\nodd\nSome(3)\nNone\nNone\ny\nn\n"
+    );
+}
+
+#[test]
+fn test_enum_layout() {
+    let mir = lower(
+        r#"
+        struct Pair {
+            flag: bool,
+            value: i64,
+        }
+
+        enum Value {
+            Small(u8),
+            Pair(Pair),
+            Both(i32, char),
+            Nothing,
+        }
+
+        enum Wrap<T> {
+            One(T),
+        }
+
+        enum List {
+            Cons(i32, List),
+            Nil,
+        }
+    "#,
+    );
+    let layouts = mir.layouts();
+    let value = layouts.enum_layout("Value").unwrap();
+    assert_eq!(value.tag, Type::U8);
+    assert_eq!(value.payload, 8);
+    assert_eq!(value.layout, Layout::new(24, 8));
+    let offsets: Vec<(&str, u64, &[u64])> = value
+        .variants
+        .iter()
+        .map(|v| (v.name.as_str(), v.tag, v.offsets.as_slice()))
+        .collect();
+    assert_eq!(
+        offsets,
+        [
+            ("Small", 0, &[8][..]),
+            ("Pair", 1, &[8][..]),
+            ("Both", 2, &[8, 12][..]),
+            ("Nothing", 3, &[][..]),
+        ]
+    );
+    let pair = layouts.struct_layout("Pair").unwrap();
+    assert_eq!(
+        (pair.offsets, pair.layout),
+        (vec![8, 0], Layout::new(16, 8))
+    );
+    assert_eq!(
+        layouts.layout_of(&Type::Tuple(vec![Type::Bool, Type::String])),
+        Ok(Layout::new(24, 8))
+    );
+
+    // 泛型类型在单态化之后才有布局，不经过指针包含自身的类型大小无限
+    assert_eq!(
+        layouts.enum_layout("Wrap"),
+        Err(LayoutError::Unknown("Wrap".to_string()))
+    );
+    let error = layouts.enum_layout("List").unwrap_err();
+    assert_eq!(error, LayoutError::Recursive("List".to_string()));
+    assert_eq!(error.to_string(), "recursive type `List` has infinite size");
+
+    assert_eq!(layout::tag_type(2), Type::U8);
+    assert_eq!(layout::tag_type(256), Type::U8);
+    assert_eq!(layout::tag_type(257), Type::U16);
+    assert_eq!(layout::tag_type(70000), Type::U32);
+
+    // `--emit=mir` 在最前面列出结构体和枚举的布局
+    let text = mir.to_string();
+    assert!(
+        text.starts_with(
+            "// struct Pair: size 16, align 8, fields at [8, 0]\n\
+             // enum Value: size 24, align 8, tag u8, payload at 8\n"
+        ),
+        "{}",
+        text
+    );
+    assert!(text.contains("//     Both: tag 2, fields at [8, 12]\n"));
+}
+
+#[test]
+fn test_large_match_benchmarks() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/match_bench.ctx");
+    let source = fs::read_to_string(path).unwrap();
+    assert_eq!(run(&source), "-2973\n954322\n");
+
+    // 16 个变体的 match 只取一次标签，两个守卫不成立时各重新判定一次
+    let mir = lower(&source);
+    let step = mir.body("step").unwrap();
+    assert_eq!(discriminants(step).len(), 3);
+    assert_eq!(switches(step)[0], (0..15).collect::<Vec<i64>>());
+    let classify = mir.body("classify").unwrap();
+    assert_eq!(switches(classify)[0].len(), 37);
+
+    let mir = mir::monomorphize(&mir).unwrap();
+    let module = bytecode::compile(&mir).unwrap();
+    let options = BenchOptions {
+        warmup: Duration::from_millis(1),
+        measure: Duration::from_millis(5),
+        samples: 2,
+    };
+    for name in &mir.benches {
+        let result = bench::run(&module, name, &options).unwrap();
+        assert!(result.ns_per_iter > 0.0);
+    }
+    assert_eq!(mir.benches, ["bench_enum_match", "bench_char_match"]);
+}
@@ -0,0 +1,146 @@
+// Contractus 运算符的最长匹配测试
+// 词法分析器总是取 token.rs 的表中最长的拼写（两个、三个拼写的所有组合由 lexer.rs 的
+// `test_maximal_munch` 检查）；解析器在需要时拆开 `>>`、`&&` 和 `||`，
+// 并把按最长匹配切开的 `&&=`、`||=` 和 `...` 报告为错误
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{Lexer, TokenKind};
+
+const PROGRAM: &str = "fn main() {
+    let mut nested: Vec<Vec<i32>> = Vec::new();
+    nested.push(Vec::new());
+    let x = 5;
+    let r = &&x;
+    let f = || 40;
+    let mut bits = 256;
+    bits >>= 2;
+    bits <<= 1;
+    let shifted = bits>>3;
+    print(len(nested), **r, f() + 2, bits, shifted, 1<=2, x>=5);
+    let mut total = 0;
+    for i in 0..=2 {
+        total += i;
+    }
+    print(total);
+}
+";
+
+fn kinds(source: &str) -> Vec<TokenKind> {
+    Lexer::new(source)
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .collect()
+}
+
+// (错误码, 信息, 帮助, 出错的源码)
+fn errors(source: &str) -> Vec<(Option<ErrorCode>, String, Option<String>, String)> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .into_iter()
+        .map(|error| {
+            let text = source[error.span.start..error.span.end].to_string();
+            (error.code, error.message, error.help, text)
+        })
+        .collect()
+}
+
+#[test]
+fn test_longest_match() {
+    let ident = |name: &str| TokenKind::Ident(name.into());
+    assert_eq!(
+        kinds("a<<=b>>=c"),
+        [
+            ident("a"),
+            TokenKind::LeftShiftAssign,
+            ident("b"),
+            TokenKind::RightShiftAssign,
+            ident("c"),
+            TokenKind::Eof,
+        ]
+    );
+    assert_eq!(
+        kinds("0..=1...2&&=||="),
+        [
+            TokenKind::IntLiteral(0),
+            TokenKind::DotDotEqual,
+            TokenKind::IntLiteral(1),
+            TokenKind::DotDot,
+            TokenKind::Dot,
+            TokenKind::IntLiteral(2),
+            TokenKind::LogicalAnd,
+            TokenKind::Assign,
+            TokenKind::LogicalOr,
+            TokenKind::Assign,
+            TokenKind::Eof,
+        ]
+    );
+    // 注释优先于 `/` 和 `/=`
+    assert_eq!(
+        kinds("a/=b//=c"),
+        [
+            ident("a"),
+            TokenKind::SlashAssign,
+            ident("b"),
+            TokenKind::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_parser_splits() {
+    // `>>` 关闭两层泛型参数，`&&x` 是两层引用，`||` 是没有参数的闭包
+    assert_eq!(run(PROGRAM), "1\n5\n42\n128\n16\ntrue\ntrue\n3\n");
+}
+
+#[test]
+fn test_not_operators() {
+    let source = "fn main() {\n    let mut done = true;\n    done &&= false;\n}\n";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0100),
+            "`&&=` is not an operator".to_string(),
+            Some("write the assignment out, e.g. `done = done && check()`".to_string()),
+            "&&=".to_string()
+        )]
+    );
+
+    let source = "fn main() {\n    let mut found = false;\n    found ||= true;\n}\n";
+    assert_eq!(errors(source)[0].1, "`||=` is not an operator");
+
+    let source = "fn main() {\n    for i in 0...3 {\n        print(i);\n    }\n}\n";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0100),
+            "`...` is not an operator".to_string(),
+            Some("use `..=` for an inclusive range or `..` for an exclusive one".to_string()),
+            "...".to_string()
+        )]
+    );
+
+    // 分开写的记号不是这几种写法，按一般的语法错误报告
+    let source = "fn main() {\n    let a = true && = false;\n}\n";
+    assert_ne!(errors(source)[0].1, "`&&=` is not an operator");
+
+    // 自定义运算符可以由这几个记号组成
+    let source = "#![feature(custom_operators)]
+
+operator &&= (precedence 30, left)
+fn both(a: bool, b: bool) -> bool {
+    a && b
+}
+
+fn main() {
+    print(true &&= false);
+}
+";
+    assert_eq!(run(source), "false\n");
+}
@@ -0,0 +1,375 @@
+// Contractus MIR 构建测试
+// 测试函数体降级为基本块、终结符和局部变量表
+
+use contractus::mir::{
+    AggregateKind, BasicBlock, Body, ConstValue, Local, LocalKind, Operand, Place, Rvalue,
+    StatementKind, TerminatorKind,
+};
+use contractus::{BinOp, Lexer, MirProgram, Parser, Type};
+
+fn lower(input: &str) -> MirProgram {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    contractus::mir::lower_program(&program).expect("lowering failed")
+}
+
+// 所有赋值语句的右值
+fn rvalues(body: &Body) -> Vec<&Rvalue> {
+    body.blocks
+        .iter()
+        .flat_map(|block| &block.statements)
+        .filter_map(|stmt| match &stmt.kind {
+            StatementKind::Assign(_, rvalue) => Some(rvalue),
+            _ => None,
+        })
+        .collect()
+}
+
+fn local_named(body: &Body, name: &str) -> Local {
+    let index = body
+        .locals
+        .iter()
+        .position(|decl| decl.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("no local named `{}`", name));
+    Local(index)
+}
+
+#[test]
+fn test_straight_line_function() {
+    let mir = lower("fn add(a: i32, b: i32) -> i32 { return a + b; }");
+    let body = mir.body("add").unwrap();
+
+    assert_eq!(body.arg_count, 2);
+    assert_eq!(body.return_ty(), &Type::I32);
+    assert_eq!(body.locals[0].kind, LocalKind::ReturnPointer);
+    assert_eq!(body.locals[1].name.as_deref(), Some("a"));
+    assert_eq!(body.locals[2].kind, LocalKind::Arg);
+
+    assert_eq!(body.blocks.len(), 1);
+    let block = body.block(BasicBlock::START);
+    assert!(matches!(block.terminator.kind, TerminatorKind::Return));
+    match &block.statements[0].kind {
+        StatementKind::Assign(place, Rvalue::BinaryOp(BinOp::Add, lhs, rhs, _)) => {
+            assert_eq!(place, &Place::local(Local::RETURN));
+            assert_eq!(lhs, &Operand::Copy(Place::local(Local(1))));
+            assert_eq!(rhs, &Operand::Copy(Place::local(Local(2))));
+        }
+        other => panic!("unexpected statement {:?}", other),
+    }
+}
+
+#[test]
+fn test_if_expression_cfg() {
+    let mir = lower("fn max(a: i32, b: i32) -> i32 { if (a > b) { a } else { b } }");
+    let body = mir.body("max").unwrap();
+
+    // 入口、then、else、汇合四个块
+    assert_eq!(body.blocks.len(), 4);
+    let (then_bb, else_bb) = match &body.block(BasicBlock::START).terminator.kind {
+        TerminatorKind::SwitchInt {
+            targets, otherwise, ..
+        } => (*otherwise, targets[0].1),
+        other => panic!("expected switchInt, got {:?}", other),
+    };
+    assert_eq!(targets_of(body, then_bb), targets_of(body, else_bb));
+
+    let preds = body.predecessors();
+    let join = targets_of(body, then_bb)[0];
+    assert_eq!(preds[join.index()].len(), 2);
+    assert!(matches!(
+        body.block(join).terminator.kind,
+        TerminatorKind::Return
+    ));
+}
+
+fn targets_of(body: &Body, block: BasicBlock) -> Vec<BasicBlock> {
+    body.block(block).terminator.successors()
+}
+
+#[test]
+fn test_while_loop_back_edge() {
+    let mir = lower(
+        r#"
+        fn count() -> i32 {
+            let mut i = 0;
+            while i < 10 {
+                if (i == 5) {
+                    break;
+                }
+                i = i + 1;
+            }
+            return i;
+        }
+    "#,
+    );
+    let body = mir.body("count").unwrap();
+
+    // 循环头有两个前驱：入口和循环体末尾
+    let preds = body.predecessors();
+    let header = targets_of(body, BasicBlock::START)[0];
+    assert_eq!(preds[header.index()].len(), 2);
+    assert!(preds[header.index()]
+        .iter()
+        .any(|pred| pred.index() > header.index()));
+
+    let i = local_named(body, "i");
+    assert_eq!(body.local_decl(i).ty, Type::I32);
+    assert!(body.local_decl(i).mutable);
+}
+
+#[test]
+fn test_call_terminator() {
+    let mir = lower(
+        r#"
+        fn square(x: i32) -> i32 { return x * x; }
+        fn main() -> i32 {
+            let y = square(3);
+            return y;
+        }
+    "#,
+    );
+    let body = mir.body("main").unwrap();
+
+    match &body.block(BasicBlock::START).terminator.kind {
+        TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            target,
+            unwind,
+        } => {
+            let callee = func.constant().unwrap();
+            assert_eq!(callee.value, ConstValue::Function("square".into()));
+            assert_eq!(args.len(), 1);
+            assert_eq!(destination.local, local_named(body, "y"));
+            assert!(target.is_some());
+            assert!(unwind.is_none());
+        }
+        other => panic!("expected call, got {:?}", other),
+    }
+    assert_eq!(body.local_decl(local_named(body, "y")).ty, Type::I32);
+}
+
+#[test]
+fn test_for_range_loop() {
+    let mir = lower(
+        r#"
+        fn sum() -> i32 {
+            let mut total = 0;
+            for i in 0..10 {
+                if (i == 3) {
+                    continue;
+                }
+                total = total + i;
+            }
+            return total;
+        }
+    "#,
+    );
+    let body = mir.body("sum").unwrap();
+
+    let i = local_named(body, "i");
+    assert_eq!(body.local_decl(i).ty, Type::I32);
+    assert!(rvalues(body)
+        .iter()
+        .any(|rv| matches!(rv, Rvalue::BinaryOp(BinOp::Less, _, _, _))));
+
+    // continue 跳到递增块，所有块都可达
+    let preds = body.predecessors();
+    for (block, _) in body.basic_blocks().skip(1) {
+        assert!(!preds[block.index()].is_empty(), "{:?} unreachable", block);
+    }
+}
+
+#[test]
+fn test_match_on_enum() {
+    let mir = lower(
+        r#"
+        enum Color {
+            Red,
+            Green,
+            Blue,
+        }
+
+        fn code(c: Color) -> i32 {
+            match (c) {
+                Red => 1,
+                Green | Blue => 2,
+            }
+        }
+    "#,
+    );
+    let body = mir.body("code").unwrap();
+
+    let discriminants = rvalues(body)
+        .into_iter()
+        .filter(|rv| matches!(rv, Rvalue::Discriminant(_)))
+        .count();
+    assert_eq!(discriminants, 1);
+    assert_eq!(mir.variant_index("Color", "Blue"), Some(2));
+
+    // 所有分支合成一个按变体序号的 switchInt
+    let switches: Vec<Vec<i64>> = body
+        .blocks
+        .iter()
+        .filter_map(|block| match &block.terminator.kind {
+            TerminatorKind::SwitchInt { targets, .. } => {
+                Some(targets.iter().map(|(value, _)| *value).collect())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(switches, vec![vec![0, 1, 2]]);
+}
+
+#[test]
+fn test_aggregates_and_field_places() {
+    let mir = lower(
+        r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        fn swap(p: Point) -> Point {
+            let q = Point { x: p.y, y: p.x };
+            return q;
+        }
+    "#,
+    );
+    let body = mir.body("swap").unwrap();
+
+    let q = local_named(body, "q");
+    assert_eq!(body.local_decl(q).ty, Type::Named("Point".into()));
+    let aggregate = rvalues(body)
+        .into_iter()
+        .find_map(|rv| match rv {
+            Rvalue::Aggregate(AggregateKind::Struct(name, fields), operands) => {
+                Some((name, fields, operands))
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(aggregate.0, "Point");
+    assert_eq!(aggregate.1, &vec!["x".to_string(), "y".to_string()]);
+    assert_eq!(format!("{:?}", aggregate.2[0]).matches("Field").count(), 1);
+}
+
+#[test]
+fn test_short_circuit_creates_blocks() {
+    let mir = lower("fn both(a: bool, b: bool) -> bool { return a && b; }");
+    let body = mir.body("both").unwrap();
+
+    assert!(matches!(
+        body.block(BasicBlock::START).terminator.kind,
+        TerminatorKind::SwitchInt { .. }
+    ));
+    assert!(!rvalues(body)
+        .iter()
+        .any(|rv| matches!(rv, Rvalue::BinaryOp(BinOp::LogicalAnd, _, _, _))));
+}
+
+#[test]
+fn test_closure_body_and_captures() {
+    let mir = lower(
+        r#"
+        fn main() -> i32 {
+            let base = 10;
+            let add = |x: i32| x + base;
+            return add(1);
+        }
+    "#,
+    );
+
+    let closure = mir.body("main::{closure#0}").unwrap();
+    assert_eq!(closure.arg_count, 2);
+    assert_eq!(closure.locals[1].name.as_deref(), Some("base"));
+    assert_eq!(closure.locals[2].name.as_deref(), Some("x"));
+
+    let main = mir.body("main").unwrap();
+    assert!(rvalues(main).iter().any(|rv| matches!(
+        rv,
+        Rvalue::Aggregate(AggregateKind::Closure(name), captures)
+            if name == "main::{closure#0}" && captures.len() == 1
+    )));
+}
+
+#[test]
+fn test_dead_code_after_return_removed() {
+    let mir = lower(
+        r#"
+        fn early(x: i32) -> i32 {
+            return x;
+            let y = x + 1;
+            return y;
+        }
+    "#,
+    );
+    let body = mir.body("early").unwrap();
+
+    assert_eq!(body.blocks.len(), 1);
+    // 局部变量仍然保留，span 指向源代码中的声明
+    let y = local_named(body, "y");
+    assert_eq!(body.local_decl(y).span.line, 4);
+    assert_eq!(body.block(BasicBlock::START).terminator.span.line, 3);
+}
+
+#[test]
+fn test_pretty_print_body() {
+    let mir = lower("fn max(a: i32, b: i32) -> i32 { if (a > b) { a } else { b } }");
+    let expected = "\
+fn max(_1: i32, _2: i32) -> i32 {
+    debug a => _1;
+    debug b => _2;
+    let mut _0: i32;
+    let mut _3: bool;
+
+    bb0: {
+        _3 = Gt(_1, _2);
+        switchInt(_3) -> [0: bb2, otherwise: bb1];
+    }
+
+    bb1: {
+        _0 = _1;
+        goto -> bb3;
+    }
+
+    bb2: {
+        _0 = _2;
+        goto -> bb3;
+    }
+
+    bb3: {
+        return;
+    }
+}";
+    assert_eq!(mir.body("max").unwrap().to_string(), expected);
+}
+
+#[test]
+fn test_pretty_print_calls_and_places() {
+    let mir = lower(
+        r#"
+        struct Pair {
+            left: i32,
+            right: i32,
+        }
+
+        fn first(items: [i32; 3], pair: &Pair) -> i32 {
+            return items[0] + pair.left;
+        }
+
+        fn main() {
+            let pair = Pair { left: 1, right: 2 };
+            let total = first([1, 2, 3], &pair);
+        }
+    "#,
+    );
+    let text = mir.to_string();
+
+    assert!(text.contains("_0 = Add(_1[_3], (*_2).left);"), "{}", text);
+    assert!(text.contains("_1 = Pair { left: const 1, right: const 2 };"));
+    assert!(text.contains("_3 = [const 1, const 2, const 3];"));
+    assert!(text.contains("_4 = &_1;"));
+    assert!(text.contains("_2 = first(_3, _4) -> [return: bb1, unwind continue];"));
+}
@@ -0,0 +1,444 @@
+// Contractus 模块加载测试
+// 测试跨文件导入、循环导入检测、导入名字的解析，以及跨模块调用在两个后端中的执行
+
+mod common;
+
+use contractus::interp::Host;
+use contractus::{Diagnostic, ModuleLoader, SemanticAnalyzer};
+use std::fs;
+use std::path::PathBuf;
+
+// 在临时目录中创建一个项目，返回项目根目录
+fn create_project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("contractus_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (path, source) in files {
+        let file = root.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, source).unwrap();
+    }
+    root
+}
+
+fn check_project(name: &str, files: &[(&str, &str)]) -> Result<(), Vec<Diagnostic>> {
+    let root = create_project(name, files);
+    let krate = ModuleLoader::load_entry(&root.join("main.ctx"))?;
+    SemanticAnalyzer::new().analyze_crate(&krate)
+}
+
+#[test]
+fn test_load_nested_modules() {
+    let root = create_project(
+        "nested",
+        &[
+            (
+                "main.ctx",
+                "import math::vector;\nfn main() -> i32 { return vector::dot(1, 2); }",
+            ),
+            (
+                "math/vector.ctx",
+                "import math::scalar;\npub fn dot(a: i32, b: i32) -> i32 { return scalar::mul(a, b); }",
+            ),
+            (
+                "math/scalar.ctx",
+                "pub fn mul(a: i32, b: i32) -> i32 { return a * b; }",
+            ),
+        ],
+    );
+
+    let krate = ModuleLoader::load_entry(&root.join("main.ctx")).unwrap();
+    assert_eq!(krate.modules.len(), 3);
+    assert!(krate.module(&["math".into(), "vector".into()]).is_some());
+    assert!(krate.module(&["math".into(), "scalar".into()]).is_some());
+    assert!(SemanticAnalyzer::new().analyze_crate(&krate).is_ok());
+}
+
+#[test]
+fn test_import_single_item() {
+    let result = check_project(
+        "item",
+        &[
+            (
+                "main.ctx",
+                "import geometry::Point;\nimport geometry::origin as zero;\nfn main() -> i32 { let p: Point = zero(); return p.x; }",
+            ),
+            (
+                "geometry.ctx",
+                "pub struct Point { x: i32, y: i32 }\npub fn origin() -> Point { return Point { x: 0, y: 0 }; }",
+            ),
+        ],
+    );
+
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_import_cycle_detected() {
+    let result = check_project(
+        "cycle",
+        &[
+            ("main.ctx", "import a;\nfn main() {}"),
+            ("a.ctx", "import b;\nfn fa() {}"),
+            ("b.ctx", "import a;\nfn fb() {}"),
+        ],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "import cycle detected: `a` → `b` → `a`");
+    assert!(errors[0].file.as_deref().unwrap().ends_with("b.ctx"));
+}
+
+#[test]
+fn test_missing_module() {
+    let result = check_project(
+        "missing",
+        &[("main.ctx", "import net::http;\nfn main() {}")],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors[0].message, "cannot find module `net::http`");
+    assert_eq!(errors[0].span.line, 1);
+}
+
+#[test]
+fn test_unknown_imported_name() {
+    let result = check_project(
+        "unknown",
+        &[
+            (
+                "main.ctx",
+                "import util;\nfn main() -> i32 { return util::clamb(5); }",
+            ),
+            ("util.ctx", "pub fn clamp(x: i32) -> i32 { return x; }"),
+        ],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "cannot find `clamb` in module `util`");
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("an item with a similar name exists: `clamb` → `clamp`")
+    );
+    assert!(errors[0].file.as_deref().unwrap().ends_with("main.ctx"));
+}
+
+#[test]
+fn test_failed_import_reported_once() {
+    // 导入失败的名字在使用处不再报告找不到
+    let result = check_project(
+        "poisoned",
+        &[
+            (
+                "main.ctx",
+                "import util::clamb;\nfn main() -> i32 { let c: clamb = clamb(5); return clamb(c); }",
+            ),
+            ("util.ctx", "pub fn clamp(x: i32) -> i32 { return x; }"),
+        ],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].message, "cannot find `clamb` in module `util`");
+}
+
+#[test]
+fn test_private_items_not_importable() {
+    let result = check_project(
+        "private",
+        &[
+            (
+                "main.ctx",
+                "import util;\nimport util::helper;\nfn main() -> i32 { return util::secret(); }",
+            ),
+            (
+                "util.ctx",
+                "fn helper() -> i32 { return 1; }\nfn secret() -> i32 { return 2; }",
+            ),
+        ],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].message, "`helper` is private to module `util`");
+    assert_eq!(errors[1].message, "`secret` is private to module `util`");
+    assert!(errors[1].help.as_deref().unwrap().contains("mark it `pub`"));
+}
+
+#[test]
+fn test_export_list_makes_items_public() {
+    let result = check_project(
+        "export_list",
+        &[
+            (
+                "main.ctx",
+                "import util::helper;\nfn main() -> i32 { return helper(); }",
+            ),
+            (
+                "util.ctx",
+                "export { helper };\nfn helper() -> i32 { return 1; }",
+            ),
+        ],
+    );
+
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_export_unknown_name() {
+    let result = check_project(
+        "export_unknown",
+        &[("main.ctx", "export { mian, Missing };\nfn main() {}")],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].message,
+        "cannot export `mian`: no such item in this module"
+    );
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("an item with a similar name exists: `mian` → `main`")
+    );
+    assert_eq!(
+        errors[1].message,
+        "cannot export `Missing`: no such item in this module"
+    );
+}
+
+#[test]
+fn test_reexport_through_layers() {
+    let result = check_project(
+        "reexport",
+        &[
+            (
+                "main.ctx",
+                "import std;\nimport std::Vec;\nfn main() -> i32 { let v: Vec = std::new_vec(); return v.len; }",
+            ),
+            (
+                "std.ctx",
+                "import std::vec;\nexport { vec::Vec, vec::new_vec };",
+            ),
+            (
+                "std/vec.ctx",
+                "pub struct Vec { len: i32 }\npub fn new_vec() -> Vec { return Vec { len: 0 }; }\nfn grow() {}",
+            ),
+        ],
+    );
+
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_reexport_private_item() {
+    let result = check_project(
+        "reexport_private",
+        &[
+            ("main.ctx", "import std;\nfn main() {}"),
+            (
+                "std.ctx",
+                "import std::vec;\nexport { vec::grow, io::print };",
+            ),
+            ("std/vec.ctx", "fn grow() {}"),
+        ],
+    );
+
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].message, "`grow` is private to module `std::vec`");
+    assert_eq!(
+        errors[1].message,
+        "cannot re-export `io::print`: `io` is not an imported module"
+    );
+}
+
+#[test]
+fn test_load_dir_includes_unimported_files() {
+    let root = create_project(
+        "dir",
+        &[
+            (
+                "main.ctx",
+                "import util;\nfn main() -> i32 { return util::one(); }",
+            ),
+            ("util.ctx", "pub fn one() -> i32 { return 1; }"),
+            ("geo/shape.ctx", "fn area() -> i32 { return side; }"),
+            ("notes.txt", "not a module"),
+        ],
+    );
+
+    let krate = ModuleLoader::load_dir(&root).unwrap();
+    let paths: Vec<String> = krate
+        .modules
+        .values()
+        .map(|module| module.display_name())
+        .collect();
+    assert_eq!(paths, ["crate", "geo::shape", "util"]);
+
+    // 没有被导入的文件同样经过语义分析
+    let errors = SemanticAnalyzer::new().analyze_crate(&krate).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].file.as_deref().unwrap().ends_with("shape.ctx"));
+
+    let error = ModuleLoader::load_dir(&root.join("geo")).unwrap_err();
+    assert!(error[0].message.starts_with("cannot find `main.ctx` in"));
+}
+
+#[test]
+fn test_load_extra_files() {
+    let root = create_project(
+        "files",
+        &[
+            ("main.ctx", "fn main() {}"),
+            ("tools/fmt.ctx", "pub fn width() -> i32 { return 80; }"),
+        ],
+    );
+    let krate = ModuleLoader::new(&root)
+        .load_files(&root.join("main.ctx"), &[root.join("tools/fmt.ctx")])
+        .unwrap();
+    assert!(krate
+        .module(&["tools".to_string(), "fmt".to_string()])
+        .is_some());
+
+    let outside = std::env::temp_dir().join("contractus_outside.ctx");
+    fs::write(&outside, "fn f() {}").unwrap();
+    let errors = ModuleLoader::new(&root)
+        .load_files(&root.join("main.ctx"), &[outside])
+        .unwrap_err();
+    assert!(errors[0]
+        .message
+        .contains("is not a module under the project root"));
+}
+
+#[test]
+fn test_load_dependency_packages() {
+    let root = create_project(
+        "packages",
+        &[
+            (
+                "app/main.ctx",
+                "import text::case::upper;\nimport text;\nfn main() -> i32 { return upper(text::len()); }",
+            ),
+            ("app/case.ctx", "fn local() {}"),
+            (
+                "text/main.ctx",
+                "import case;\npub fn len() -> i32 { return case::upper(1); }",
+            ),
+            ("text/case.ctx", "pub fn upper(x: i32) -> i32 { return x; }"),
+        ],
+    );
+    let entry = root.join("app/main.ctx");
+    let krate = ModuleLoader::new(root.join("app"))
+        .with_package("text", root.join("text/main.ctx"))
+        .load(&entry)
+        .unwrap();
+    let paths: Vec<String> = krate
+        .modules
+        .values()
+        .map(|module| module.display_name())
+        .collect();
+    // 包内的 `import case;` 指向包自己的 `text::case`，而不是项目的 `case`
+    assert_eq!(paths, ["crate", "text", "text::case"]);
+    assert_eq!(
+        krate
+            .module(&["text".to_string()])
+            .unwrap()
+            .package
+            .as_deref(),
+        Some("text")
+    );
+    SemanticAnalyzer::new().analyze_crate(&krate).unwrap();
+
+    fs::write(root.join("app/text.ctx"), "fn f() {}").unwrap();
+    let errors = ModuleLoader::new(root.join("app"))
+        .with_package("text", root.join("text/main.ctx"))
+        .load_all()
+        .unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "module `text` conflicts with dependency `text`"
+    );
+}
+
+#[test]
+fn test_run_cross_module_calls() {
+    let root = create_project(
+        "run",
+        &[
+            (
+                "main.ctx",
+                "import math::vector;
+import math::vector::Vec2;
+import math::scalar::twice as double;
+
+fn helper() -> i32 {
+    100
+}
+
+fn main() {
+    let v = vector::add(Vec2 { x: 1, y: 2 }, vector::unit());
+    print(v.x, v.y, vector::dot(v, v), double(helper()));
+    match vector::sign(-3) {
+        vector::Sign::Negative => print(\"negative\"),
+        _ => print(\"other\"),
+    }
+    let copy = v.clone();
+    print(copy.y);
+}
+",
+            ),
+            (
+                "math/vector.ctx",
+                "import math::scalar;
+
+#[derive(Clone)]
+pub struct Vec2 {
+    x: i32,
+    y: i32,
+}
+
+pub enum Sign {
+    Negative,
+    Positive,
+}
+
+// 和根模块的 `helper` 同名
+fn helper(a: i32, b: i32) -> i32 {
+    scalar::twice(a) + b
+}
+
+pub fn add(a: Vec2, b: Vec2) -> Vec2 {
+    Vec2 { x: a.x + b.x, y: a.y + b.y }
+}
+
+pub fn unit() -> Vec2 {
+    Vec2 { x: 1, y: 1 }
+}
+
+pub fn dot(a: Vec2, b: Vec2) -> i32 {
+    let helper = a.x * b.x;
+    helper + a.y * b.y
+}
+
+pub fn sign(n: i32) -> Sign {
+    if helper(n, 0) < 0 {
+        return Sign::Negative;
+    }
+    Sign::Positive
+}
+",
+            ),
+            (
+                "math/scalar.ctx",
+                "pub fn twice(x: i32) -> i32 {\n    x * 2\n}\n",
+            ),
+        ],
+    );
+
+    let krate = ModuleLoader::load_entry(&root.join("main.ctx")).unwrap();
+    let execution = common::execute_crate(&krate, Host::default());
+    assert!(execution.error.is_none(), "{:?}", execution.error);
+    assert_eq!(execution.output, "2\n3\n13\n200\nnegative\n3\n");
+}
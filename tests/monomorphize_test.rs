@@ -0,0 +1,182 @@
+// Contractus 单态化测试
+// 测试泛型函数和泛型结构体的实例收集、类型替换以及无限递归实例化的报错
+
+use contractus::ast::Type;
+use contractus::mir::Local;
+use contractus::{Lexer, MirProgram, Parser};
+
+fn lower(input: &str) -> MirProgram {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    contractus::mir::lower_program(&program).expect("lowering failed")
+}
+
+fn monomorphize(input: &str) -> MirProgram {
+    contractus::mir::monomorphize(&lower(input)).expect("monomorphization failed")
+}
+
+fn monomorphize_err(input: &str) -> String {
+    match contractus::mir::monomorphize(&lower(input)) {
+        Ok(mir) => panic!("expected an error, got:\n{}", mir),
+        Err(errors) => errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[test]
+fn test_generic_function_instances() {
+    let mir = monomorphize(
+        r#"
+        fn id<T>(x: T) -> T {
+            return x;
+        }
+
+        fn main() -> i32 {
+            let b = id(true);
+            let n = id(1);
+            return n;
+        }
+    "#,
+    );
+    let names: Vec<&str> = mir.bodies.iter().map(|body| body.name.as_str()).collect();
+    assert!(names.contains(&"id::<bool>"), "{:?}", names);
+    assert!(names.contains(&"id::<i32>"), "{:?}", names);
+    // 泛型原型不再保留
+    assert!(mir.body("id").is_none());
+    assert!(mir.bodies.iter().all(|body| !body.is_generic()));
+
+    let instance = mir.body("id::<bool>").unwrap();
+    assert_eq!(instance.local_decl(Local::RETURN).ty, Type::Bool);
+    let text = mir.body("main").unwrap().to_string();
+    assert!(text.contains("id::<bool>(const true)"), "{}", text);
+}
+
+#[test]
+fn test_unused_generic_function_is_dropped() {
+    let mir = monomorphize(
+        r#"
+        fn unused<T>(x: T) -> T {
+            return x;
+        }
+
+        fn main() -> i32 {
+            return 0;
+        }
+    "#,
+    );
+    assert_eq!(mir.bodies.len(), 1);
+}
+
+#[test]
+fn test_generic_struct_instance() {
+    let mir = monomorphize(
+        r#"
+        struct Pair<T> {
+            a: T,
+            b: T,
+        }
+
+        fn first<T>(p: Pair<T>) -> T {
+            return p.a;
+        }
+
+        fn main() -> i32 {
+            let p = Pair { a: 1, b: 2 };
+            return first(p);
+        }
+    "#,
+    );
+    let def = mir.struct_def("Pair<i32>").expect("struct instance");
+    assert!(def.fields.iter().all(|field| field.ty == Type::I32));
+    assert!(mir.struct_def("Pair").is_none());
+
+    let first = mir.body("first::<i32>").expect("function instance");
+    assert_eq!(
+        first.local_decl(first.args().next().unwrap()).ty,
+        Type::Named("Pair<i32>".to_string())
+    );
+}
+
+#[test]
+fn test_closure_inside_generic_function() {
+    let mir = monomorphize(
+        r#"
+        fn apply<T>(x: T) -> T {
+            let f = |y: T| y;
+            return f(x);
+        }
+
+        fn main() -> bool {
+            return apply(true);
+        }
+    "#,
+    );
+    let closure = mir
+        .body("apply::<bool>::{closure#0}")
+        .expect("closure instance");
+    assert!(!closure.is_generic());
+    assert!(mir.body("apply::{closure#0}").is_none());
+}
+
+#[test]
+fn test_infinite_instantiation_is_an_error() {
+    let message = monomorphize_err(
+        r#"
+        fn grow<T>(x: T) {
+            grow((x, 1));
+        }
+
+        fn main() {
+            grow(1);
+        }
+    "#,
+    );
+    assert!(
+        message.contains("reached the recursion limit"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_exponential_instantiation_hits_type_length_limit() {
+    let message = monomorphize_err(
+        r#"
+        fn grow<T>(x: T) {
+            grow((x, x));
+        }
+
+        fn main() {
+            grow(1);
+        }
+    "#,
+    );
+    assert!(
+        message.contains("reached the type-length limit while instantiating `grow`"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_uninferable_type_parameter() {
+    let message = monomorphize_err(
+        r#"
+        fn make<T>() -> i32 {
+            return 0;
+        }
+
+        fn main() -> i32 {
+            return make();
+        }
+    "#,
+    );
+    assert!(
+        message.contains("cannot infer type parameter `T` for call to `make`"),
+        "{}",
+        message
+    );
+}
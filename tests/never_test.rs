@@ -0,0 +1,197 @@
+// Contractus `!` 类型和发散测试
+// `return`、`break`、`continue` 和调用返回 `!` 的函数（如 `panic`）发散，类型为 `!`，
+// 可以出现在需要任何类型的地方；函数体在每条路径上都要给出返回值或者发散
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+use contractus::repl::Repl;
+use contractus::{bytecode, interp, mir, module};
+
+const PROGRAM: &str = "fn fail(message: string) -> ! {
+    panic(message)
+}
+
+fn spin() -> ! {
+    while true {}
+}
+
+fn pick(c: bool) -> i32 {
+    let x = if c {
+        1
+    } else {
+        return 0;
+    };
+    x + 1
+}
+
+fn halve(n: i32) -> i32 {
+    let half: i32 = match n % 2 {
+        0 => n / 2,
+        _ => return -1,
+    };
+    half
+}
+
+fn check(n: i32) -> i32 {
+    if n < 0 {
+        fail(\"negative\")
+    } else {
+        n
+    }
+}
+
+fn first_above(v: [i32; 4], limit: i32) -> i32 {
+    let mut i = 0;
+    while true {
+        if v[i] > limit {
+            return v[i];
+        }
+        i += 1;
+    }
+}
+
+fn main() {
+    print(pick(true), pick(false), halve(8), halve(7), check(4));
+    let mut i = 0;
+    while true {
+        let v = if i > 2 {
+            break;
+        } else {
+            i
+        };
+        i += v + 1;
+    }
+    print(i);
+    print(first_above([1, 3, 5, 7], 4));
+    let Some(n) = Some(9) else {
+        panic(\"no value\")
+    };
+    print(n);
+}
+";
+
+#[test]
+fn test_diverging_branches() {
+    assert_eq!(run(PROGRAM), "2\n0\n4\n-1\n4\n3\n5\n9\n");
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_panic() {
+    let source = "fn main() {\n    print(1);\n    panic(\"stop\");\n    print(2);\n}\n";
+    let program = module::parse_source(source).unwrap();
+    let (result, output) = interp::run_with_output(&program, Vec::new());
+    let error = result.unwrap_err();
+    assert_eq!(error[0].message, "panicked: stop");
+    assert_eq!(output, b"1\n");
+
+    let lowered = mir::lower_program(&program).unwrap();
+    // 调用之后没有返回的目标
+    assert!(lowered
+        .to_string()
+        .contains("panic(const \"stop\") -> unwind continue;"));
+    let module = bytecode::compile(&mir::monomorphize(&lowered).unwrap()).unwrap();
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    assert_eq!(result.unwrap_err()[0].message, "panicked: stop");
+    assert_eq!(output, b"1\n");
+
+    let source = "fn main() {\n    panic();\n}\n";
+    let (result, _) = interp::run_with_output(&module::parse_source(source).unwrap(), Vec::new());
+    assert_eq!(result.unwrap_err()[0].message, "explicit panic");
+}
+
+#[test]
+fn test_jump_at_end_of_block() {
+    // 块末尾的 `return`、`break` 和 `continue` 可以省略 `;`
+    let source = "fn f(n: i32) -> i32 {
+    let mut i = 0;
+    while true {
+        i += 1;
+        if i < n { continue }
+        if i > 10 { break }
+        return i
+    }
+    0
+}
+fn main() {
+    print(f(3));
+}
+";
+    assert_eq!(run(source), "3\n");
+}
+
+#[test]
+fn test_missing_return_value() {
+    let check = |body: &str| {
+        errors(&format!(
+            "fn f(c: bool) -> i32 {{\n{}\n}}\nfn main() {{}}\n",
+            body
+        ))
+    };
+
+    let found = check("    if c {\n        return 1;\n    }");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0308),
+            "function `f` returns `i32` but its body can finish without a value".to_string()
+        )]
+    );
+    assert_eq!(check("    let x = 1;").len(), 1);
+    assert_eq!(check("    if c {\n        1\n    }").len(), 1);
+    // 有 break 的 `while true` 会走到循环之后
+    assert_eq!(check("    while true {\n        break;\n    }").len(), 1);
+
+    // 每个分支都有值或者发散
+    for body in [
+        "    if c {\n        1\n    } else {\n        return 2;\n    }",
+        "    match c {\n        true => 1,\n        false => panic(\"no\"),\n    }",
+        "    while true {\n        if c {\n            return 1;\n        }\n    }",
+        "    'outer: while true {\n        while true {\n            break;\n        }\n        if c {\n            return 1;\n        }\n    }",
+        "    return 3;",
+        "    panic(\"unreachable\")",
+    ] {
+        assert!(check(body).is_empty(), "{}", body);
+    }
+    // 跳出外层循环的 break 使循环可以结束
+    assert_eq!(
+        check("    'outer: while true {\n        while true {\n            break 'outer;\n        }\n    }").len(),
+        1
+    );
+}
+
+#[test]
+fn test_never_function_must_diverge() {
+    let found = errors("fn stop() -> ! {\n    print(1);\n}\nfn main() {}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0308),
+            "function `stop` returns `!` but its body can finish".to_string()
+        )]
+    );
+    assert!(errors("fn stop() -> ! {\n    stop()\n}\nfn main() {}\n").is_empty());
+
+    // 调用返回 `!` 的函数使 `let ... else` 的 else 块发散
+    let source = "fn stop() -> ! {\n    panic()\n}\nfn main() {\n    let Some(x) = Some(1) else {\n        stop();\n    };\n    print(x);\n}\n";
+    assert!(errors(source).is_empty());
+}
+
+#[test]
+fn test_never_type() {
+    let mut repl = Repl::new(Vec::new());
+    for input in [
+        ":type if true { 1 } else { panic() }",
+        ":type match 3 { 0 => return, n => n > 1 }",
+        ":type { panic(\"x\") }",
+        ":type { 1; }",
+    ] {
+        repl.eval(input).unwrap();
+    }
+    let output = String::from_utf8(repl.output().clone()).unwrap();
+    assert_eq!(output, "i32\nbool\n!\n()\n");
+}
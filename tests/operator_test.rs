@@ -0,0 +1,166 @@
+// Contractus 自定义运算符测试
+// `#![feature(custom_operators)]` 开启后，`operator <*> (precedence 70, left)` 声明的运算符
+// 按声明的优先级和结合性解析为对实现它的函数的调用；导入的模块中公开的运算符也能使用
+
+mod common;
+
+use common::errors;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::format::{self, FormatOptions};
+use contractus::{interp, module, SemanticAnalyzer};
+use std::fs;
+
+const OPERATORS: &str = "#![feature(custom_operators)]
+
+operator <+> (precedence 110, left)
+fn plus(a: i32, b: i32) -> i32 {
+    return a + b + 100;
+}
+
+operator <-> (precedence 110, left)
+fn minus(a: i32, b: i32) -> i32 {
+    return a - b;
+}
+
+operator ** (precedence 120, right)
+fn pow(a: i32, b: i32) -> i32 {
+    let mut result = 1;
+    for _ in 0..b {
+        result = result * a;
+    }
+    return result;
+}
+";
+
+fn run(main: &str) -> String {
+    let source = format!("{}\nfn main() {{\n{}\n}}\n", OPERATORS, main);
+    let program = module::parse_source(&source).expect("parsing failed");
+    SemanticAnalyzer::new()
+        .analyze(&program)
+        .expect("semantic analysis failed");
+    let (result, output) = interp::run_with_output(&program, Vec::new());
+    result.expect("the interpreter failed");
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_precedence_and_associativity() {
+    // 运算符可以在声明之前使用
+    assert_eq!(run("    print(1 <+> 2 * 3);"), "107\n");
+    assert_eq!(run("    print(1 * 2 <+> 3);"), "105\n");
+    assert_eq!(run("    print(10 <-> 3 <-> 2);"), "5\n");
+    assert_eq!(run("    print(2 ** 3 ** 2);"), "512\n");
+    assert_eq!(run("    print(2 * 3 ** 2 == 18);"), "true\n");
+
+    // 解析为对函数的调用
+    let source = format!("{}fn main() {{ print(1 <-> 2 ** 3); }}\n", OPERATORS);
+    let program = module::parse_source(&source).unwrap();
+    assert!(program.to_source().contains("print(minus(1, pow(2, 3)));"));
+}
+
+#[test]
+fn test_operator_declaration_errors() {
+    // 没有开启特性
+    let source = "operator <+> (precedence 110, left)\nfn plus(a: i32, b: i32) -> i32 { return a + b; }\nfn main() {}\n";
+    let found = errors(source);
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, Some(ErrorCode::E0658));
+    assert_eq!(found[0].1, "custom operators are experimental");
+
+    let found = errors("#![feature(custom_operator)]\nfn main() {}\n");
+    assert_eq!(found[0].0, Some(ErrorCode::E0635));
+
+    let declaration = |text: &str| {
+        let source = format!("#![feature(custom_operators)]\n{}\nfn main() {{}}\n", text);
+        errors(&source)
+    };
+    for (text, message) in [
+        (
+            "operator + (precedence 110, left)\nfn add(a: i32, b: i32) -> i32 { return a; }",
+            "`+` is a built-in operator",
+        ),
+        (
+            "operator <+> (precedence 200, left)\nfn add(a: i32, b: i32) -> i32 { return a; }",
+            "operator precedence must be between 20 and 120",
+        ),
+        (
+            "operator <+> (precedence 110, left)\nfn add(a: i32) -> i32 { return a; }",
+            "the function `add` implementing operator `<+>` must have two parameters",
+        ),
+        (
+            "operator <+> (precedence 110, left)\nstruct Add {}",
+            "operator `<+>` must be followed by the function that implements it",
+        ),
+    ] {
+        let found = declaration(text);
+        assert_eq!(found.len(), 1, "{}: {:?}", text, found);
+        assert_eq!(found[0].0, Some(ErrorCode::E0113), "{}", text);
+        assert_eq!(found[0].1, message);
+    }
+}
+
+#[test]
+fn test_imported_operators() {
+    let dir = std::env::temp_dir().join(format!("contractus_operators_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let module = |name: &str, function: &str| {
+        let source = format!(
+            "#![feature(custom_operators)]\n\noperator <^> (precedence 120, left)\npub fn {}(a: i32, b: i32) -> i32 {{\n    return a * b;\n}}\n",
+            function
+        );
+        fs::write(dir.join(format!("{}.ctx", name)), source).unwrap();
+    };
+    module("vectors", "dot");
+    module("matrices", "product");
+
+    // 导入的模块中公开的运算符按它的优先级解析
+    fs::write(
+        dir.join("main.ctx"),
+        "#![feature(custom_operators)]\nimport vectors as v;\n\nfn main() {\n    let x: i32 = 1 + 2 <^> 3;\n    print(x);\n}\n",
+    )
+    .unwrap();
+    let errors = Compiler::new().file(dir.join("main.ctx")).check();
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    // 没有开启特性的文件不能使用
+    fs::write(
+        dir.join("main.ctx"),
+        "import vectors;\n\nfn main() {\n    print(2 <^> 3);\n}\n",
+    )
+    .unwrap();
+    assert!(!Compiler::new()
+        .file(dir.join("main.ctx"))
+        .check()
+        .is_empty());
+
+    // 两个模块声明了同样的运算符
+    fs::write(
+        dir.join("main.ctx"),
+        "#![feature(custom_operators)]\nimport vectors;\nimport matrices;\n\nfn main() {\n    print(2 <^> 3);\n}\n",
+    )
+    .unwrap();
+    let errors = Compiler::new().file(dir.join("main.ctx")).check();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].code, Some(ErrorCode::E0659));
+    assert_eq!(errors[0].message, "operator `<^>` is ambiguous");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_format_custom_operators() {
+    let options = FormatOptions::default();
+    let source = format!(
+        "{}\nfn main() {{\n    print(1 <+> 2 ** 3);\n}}\n",
+        OPERATORS
+    );
+    assert!(format::is_formatted(&source, &options).unwrap());
+
+    // 导入的运算符没有声明也能格式化
+    let messy = "#![feature(custom_operators)] import v;\nfn main() { print(1<^>2 <~> 3); }\n";
+    assert_eq!(
+        format::format_source(messy, &options).unwrap(),
+        "#![feature(custom_operators)]\nimport v;\nfn main() {\n    print(1 <^> 2 <~> 3);\n}\n"
+    );
+}
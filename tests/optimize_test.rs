@@ -0,0 +1,291 @@
+// Contractus MIR 优化测试
+// 测试常量折叠、跨基本块的常量传播和常量条件下的死分支删除，以及越界检查的消除
+
+use contractus::ast::{BinOp, Type, UnOp};
+use contractus::mir::transform::{
+    fold_binary, fold_cast, fold_unary, optimize, optimize_body, remove_bounds_checks, OptLevel,
+};
+use contractus::mir::{Body, ConstValue, Constant, TerminatorKind};
+use contractus::{Lexer, MirProgram, Parser};
+
+fn lower(input: &str) -> MirProgram {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    contractus::mir::lower_program(&program).expect("lowering failed")
+}
+
+fn optimized(input: &str, name: &str, level: OptLevel) -> Body {
+    let mut mir = lower(input);
+    optimize(&mut mir, level);
+    mir.body(name).unwrap().clone()
+}
+
+fn has_switch(body: &Body) -> bool {
+    body.blocks
+        .iter()
+        .any(|data| matches!(data.terminator.kind, TerminatorKind::SwitchInt { .. }))
+}
+
+fn bounds_checks(body: &Body) -> usize {
+    body.blocks
+        .iter()
+        .filter(|data| matches!(data.terminator.kind, TerminatorKind::Assert { .. }))
+        .count()
+}
+
+#[test]
+fn test_fold_arithmetic() {
+    let body = optimized(
+        r#"
+        fn f(x: i32) -> i32 {
+            let a = 2 * 3 + x;
+            return a;
+        }
+    "#,
+        "f",
+        OptLevel::O1,
+    );
+    let text = body.to_string();
+    assert!(text.contains("_2 = Add(const 6, _1);"), "{}", text);
+    assert!(!text.contains("Mul"), "{}", text);
+}
+
+#[test]
+fn test_propagate_across_blocks() {
+    let body = optimized(
+        r#"
+        fn f(flag: bool) -> i32 {
+            let k = 10;
+            let mut y = 0;
+            if (flag) {
+                y = k * 2;
+            } else {
+                y = k + 1;
+            }
+            return y;
+        }
+    "#,
+        "f",
+        OptLevel::O2,
+    );
+    let text = body.to_string();
+    assert!(text.contains("const 20"), "{}", text);
+    assert!(text.contains("const 11"), "{}", text);
+    // 分支条件来自参数，不能删除
+    assert!(has_switch(&body));
+}
+
+#[test]
+fn test_constant_condition_removes_branch() {
+    let body = optimized(
+        r#"
+        fn f() -> i32 {
+            let limit = 3;
+            if (limit > 5) {
+                return 1;
+            }
+            if (true) {
+                return 2;
+            }
+            return 3;
+        }
+    "#,
+        "f",
+        OptLevel::O2,
+    );
+    assert!(!has_switch(&body));
+    // 所有基本块合并为一个
+    assert_eq!(body.blocks.len(), 1);
+    let text = body.to_string();
+    assert!(text.contains("_0 = const 2;"), "{}", text);
+    assert!(!text.contains("const 1;"), "{}", text);
+}
+
+#[test]
+fn test_o0_leaves_mir_unchanged() {
+    let source = r#"
+        fn f(x: i32) -> i32 {
+            let a = 2 * 3 + x;
+            if (true) {
+                return a;
+            }
+            return 0;
+        }
+    "#;
+    let before = lower(source).body("f").unwrap().to_string();
+    let after = optimized(source, "f", OptLevel::O0).to_string();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn test_loop_variables_not_propagated() {
+    let mut mir = lower(
+        r#"
+        fn count() -> i32 {
+            let mut i = 0;
+            while i < 10 {
+                i = i + 1;
+            }
+            return i;
+        }
+    "#,
+    );
+    let body = &mut mir.bodies[0];
+    optimize_body(body, OptLevel::O2);
+    // i 在循环头有两个不同的值，条件不能折叠
+    assert!(has_switch(body));
+    assert!(body.to_string().contains("Add(_1, const 1)"));
+}
+
+#[test]
+fn test_no_fold_on_overflow_or_division_by_zero() {
+    let i32_max = Constant::int(i32::MAX as i64, Type::I32);
+    let one = Constant::int(1, Type::I32);
+    let zero = Constant::int(0, Type::I32);
+    assert_eq!(fold_binary(&BinOp::Add, &i32_max, &one), None);
+    assert_eq!(fold_binary(&BinOp::Div, &one, &zero), None);
+    assert_eq!(fold_binary(&BinOp::Mod, &one, &zero), None);
+    assert_eq!(
+        fold_binary(&BinOp::LeftShift, &one, &Constant::int(32, Type::I32)),
+        None
+    );
+    assert_eq!(
+        fold_unary(&UnOp::Neg, &Constant::int(i32::MIN as i64, Type::I32)),
+        None
+    );
+
+    // 溢出的表达式在 MIR 中保留到运行时
+    let body = optimized(
+        r#"
+        fn f() -> u8 {
+            let x: u8 = 200;
+            return x + 100;
+        }
+    "#,
+        "f",
+        OptLevel::O2,
+    );
+    assert!(body.to_string().contains("Add(const 200"), "{}", body);
+}
+
+#[test]
+fn test_fold_helpers() {
+    let six = fold_binary(
+        &BinOp::Mul,
+        &Constant::int(2, Type::I64),
+        &Constant::int(3, Type::I64),
+    );
+    assert_eq!(six, Some(Constant::int(6, Type::I64)));
+    assert_eq!(
+        fold_binary(
+            &BinOp::Less,
+            &Constant::int(2, Type::I32),
+            &Constant::int(3, Type::I32)
+        ),
+        Some(Constant::bool(true))
+    );
+    assert_eq!(
+        fold_unary(&UnOp::LogicalNot, &Constant::bool(true)),
+        Some(Constant::bool(false))
+    );
+    // `as` 截断
+    assert_eq!(
+        fold_cast(&Constant::int(300, Type::I32), &Type::U8),
+        Some(Constant::int(44, Type::U8))
+    );
+    assert_eq!(
+        fold_cast(&Constant::int(-1, Type::I32), &Type::U16),
+        Some(Constant::int(65535, Type::U16))
+    );
+    let float = fold_cast(&Constant::int(3, Type::I32), &Type::F64).unwrap();
+    assert_eq!(float.value, ConstValue::Float(3.0));
+}
+
+#[test]
+fn test_constant_index_bounds_check_elided() {
+    let input = r#"
+        fn f(values: [i32; 4], i: i32) -> i32 {
+            return values[2] + values[i];
+        }
+    "#;
+    assert_eq!(bounds_checks(&optimized(input, "f", OptLevel::O0)), 2);
+
+    // 常量下标在定长数组的范围内，检查被删除；变量下标的检查保留
+    let body = optimized(input, "f", OptLevel::O1);
+    assert_eq!(bounds_checks(&body), 1);
+    let text = body.to_string();
+    // 下标先复制到记录下标位置的临时变量
+    assert!(text.contains("_8 = _2;"), "{}", text);
+    assert!(text.contains("Lt(_8, const 4)"), "{}", text);
+
+    // 越界的常量下标保留到运行时报错
+    let body = optimized(
+        r#"
+        fn g(values: [i32; 4]) -> i32 {
+            return values[4];
+        }
+    "#,
+        "g",
+        OptLevel::O2,
+    );
+    assert_eq!(bounds_checks(&body), 1);
+}
+
+#[test]
+fn test_remove_bounds_checks() {
+    let mut body = lower(
+        r#"
+        fn f(values: [i32; 4], i: i32) -> i32 {
+            return values[i];
+        }
+    "#,
+    )
+    .body("f")
+    .unwrap()
+    .clone();
+    assert!(remove_bounds_checks(&mut body));
+    assert_eq!(bounds_checks(&body), 0);
+    // 只为检查计算的长度和比较一并删除
+    let text = body.to_string();
+    assert!(!text.contains("Len("), "{}", text);
+    assert!(!text.contains("BitAnd"), "{}", text);
+    assert!(!remove_bounds_checks(&mut body));
+}
+
+#[test]
+fn test_size_level_removes_unused_functions() {
+    let input = r#"
+        fn used(x: i32) -> i32 {
+            return x * 2;
+        }
+
+        fn only_in_dead_branch() -> i32 {
+            return 0;
+        }
+
+        fn never_called() {}
+
+        fn main() {
+            let add = |x: i32| used(x) + 1;
+            if 1 > 2 {
+                print(only_in_dead_branch());
+            }
+            print(add(3));
+        }
+    "#;
+    let names = |level: OptLevel| {
+        let mut mir = lower(input);
+        optimize(&mut mir, level);
+        let names: Vec<String> = mir.bodies.iter().map(|body| body.name.clone()).collect();
+        names
+    };
+    assert_eq!(names(OptLevel::O2).len(), 5);
+    // 常量条件的分支删除之后，其中调用的函数也不可达
+    assert_eq!(names(OptLevel::Os), ["used", "main", "main::{closure#0}"]);
+
+    // 没有 main 时保留所有函数
+    let mut mir = lower("fn helper() {}");
+    optimize(&mut mir, OptLevel::Os);
+    assert!(mir.body("helper").is_some());
+    assert_eq!("s".parse::<OptLevel>(), Ok(OptLevel::Os));
+}
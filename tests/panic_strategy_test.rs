@@ -0,0 +1,162 @@
+// Contractus panic 策略测试
+// 默认 `--panic=unwind`：调用和断言带 unwind 边，`catch_panic(f)` 把 f 中的 panic 变成 `Err`；
+// `--panic=abort` 不生成清理块，panic 立即结束程序，`catch_panic` 也不捕获
+
+mod common;
+
+use contractus::interp::{Host, Interpreter};
+use contractus::mir::{LowerOptions, PanicStrategy};
+use contractus::{bytecode, interp, mir, module};
+
+// 两个后端的输出和第一个运行时错误
+fn run(source: &str) -> (String, Option<String>) {
+    let execution = common::execute(source, Host::default());
+    (execution.output, execution.error.map(|error| error.message))
+}
+
+fn lower(source: &str, panic: PanicStrategy) -> String {
+    let program = module::parse_source(source).unwrap();
+    let options = LowerOptions {
+        panic,
+        ..Default::default()
+    };
+    mir::lower_program_with(&program, options)
+        .unwrap()
+        .to_string()
+}
+
+const CATCHING: &str = "fn check(n: i32) -> i32
+    requires n < 10,
+{
+    return n;
+}
+
+fn risky(n: i32) -> i32 {
+    if n > 2 {
+        panic(\"too big\");
+    }
+    return n * 10;
+}
+
+fn report(result: Result<i32, string>) {
+    match result {
+        Ok(value) => print(value),
+        Err(message) => print(message),
+    }
+}
+
+fn main() {
+    report(catch_panic(|| risky(1)));
+    report(catch_panic(|| risky(5)));
+    let values = [1, 2];
+    report(catch_panic(|| values[3]));
+    report(catch_panic(|| check(12)));
+    print(\"done\");
+}
+";
+
+#[test]
+fn test_catch_panic() {
+    let (output, error) = run(CATCHING);
+    assert_eq!(error, None);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[..3],
+        [
+            "10",
+            "panicked: too big",
+            "index out of bounds: the len is 2 but the index is 3"
+        ]
+    );
+    assert!(
+        lines[3].starts_with("precondition violated"),
+        "{}",
+        lines[3]
+    );
+    assert_eq!(lines[4], "done");
+}
+
+#[test]
+fn test_nested_catch_panic() {
+    // 捕获之后调用栈恢复，外层的调用和循环照常继续
+    let source = "fn inner(n: i32) -> i32 {
+    let result = catch_panic(|| {
+        if n % 2 == 1 {
+            panic(\"odd\");
+        }
+        n
+    });
+    match result {
+        Ok(value) => value,
+        Err(_) => 0 - n,
+    }
+}
+
+fn main() {
+    let mut total = 0;
+    for i in 0..5 {
+        total += inner(i);
+    }
+    let outer = catch_panic(|| inner(3) + inner(4));
+    match outer {
+        Ok(value) => print(total, value),
+        Err(message) => print(message),
+    }
+}
+";
+    assert_eq!(run(source), ("2\n1\n".to_string(), None));
+}
+
+#[test]
+fn test_cleanup_edges() {
+    let source = "fn risky() -> i32 {
+    return 1;
+}
+
+fn main() {
+    let values = Vec::new();
+    let x = risky();
+    print(x, len(&values));
+}
+";
+    let unwind = lower(source, PanicStrategy::Unwind);
+    assert!(unwind.contains("unwind: bb"), "{}", unwind);
+    assert!(unwind.contains("resume;"), "{}", unwind);
+
+    let abort = lower(source, PanicStrategy::Abort);
+    assert!(!abort.contains("unwind: bb"), "{}", abort);
+    assert!(!abort.contains("resume;"), "{}", abort);
+}
+
+#[test]
+fn test_abort() {
+    let program = module::parse_source(CATCHING).unwrap();
+    let (result, output) = interp::with_large_stack(|| {
+        let mut interpreter = Interpreter::with_output(&program, Vec::new());
+        interpreter.set_panic_strategy(PanicStrategy::Abort);
+        let result = interpreter.run_main().map(drop);
+        (result, interpreter.into_output())
+    });
+    assert_eq!(String::from_utf8(output).unwrap(), "10\n");
+    assert_eq!(result.unwrap_err()[0].message, "panicked: too big");
+
+    let options = LowerOptions {
+        panic: PanicStrategy::Abort,
+        ..Default::default()
+    };
+    let lowered = mir::lower_program_with(&program, options).unwrap();
+    let module = bytecode::compile(&mir::monomorphize(&lowered).unwrap()).unwrap();
+    let (result, output) = bytecode::run_with_output(&module, Vec::new());
+    assert_eq!(String::from_utf8(output).unwrap(), "10\n");
+    assert_eq!(result.unwrap_err()[0].message, "panicked: too big");
+}
+
+#[test]
+fn test_parse_strategy() {
+    assert_eq!("unwind".parse(), Ok(PanicStrategy::Unwind));
+    assert_eq!("abort".parse(), Ok(PanicStrategy::Abort));
+    assert_eq!(
+        "none".parse::<PanicStrategy>(),
+        Err("invalid panic strategy `none`; expected `unwind` or `abort`".to_string())
+    );
+}
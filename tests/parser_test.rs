@@ -63,6 +63,32 @@ fn test_parse_if_else() {
     assert!(parse_program(input).is_ok());
 }
 
+#[test]
+fn test_else_if_keeps_real_spans() {
+    use contractus::ast::{ElseBranch, Item, Statement};
+
+    let input = "fn test(x: i32) {\n    if x > 0 {\n        print(1);\n    } else if x < 0 {\n        print(2);\n    } else {\n        print(3);\n    }\n}\n";
+    let program = parse_program(input).unwrap();
+    let Item::Function(function) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    let Statement::If(outer) = &function.body.statements[0] else {
+        panic!("expected an if statement");
+    };
+    // `else if` 是嵌套的 if 语句，不再包在合成的代码块里
+    let Some(ElseBranch::If(nested)) = &outer.else_block else {
+        panic!("expected an else-if branch");
+    };
+    assert_eq!(&input[nested.span.start..nested.span.start + 2], "if");
+    assert_eq!((nested.span.line, nested.span.column), (4, 12));
+    assert_eq!(nested.span.end, outer.span.end);
+    assert!(matches!(nested.else_block, Some(ElseBranch::Block(_))));
+
+    // 表达式形式的 if 同样支持 `else if`
+    let input = "fn test(x: i32) -> i32 { let y = if x > 0 { 1 } else if x < 0 { 2 } else { 3 }; y }";
+    assert!(parse_program(input).is_ok());
+}
+
 #[test]
 fn test_parse_while_loop() {
     let input = r#"
@@ -109,6 +135,57 @@ fn test_parse_binary_ops() {
     assert!(parse_program(input5).is_ok());
 }
 
+// 加上括号的表达式，显示运算符的结合方式
+fn grouped(expr: &contractus::ast::Expr) -> String {
+    use contractus::ast::Expr;
+    match expr {
+        Expr::Ident(name, _) => name.clone(),
+        Expr::Binary(op, left, right, ..) => format!("({} {} {})", grouped(left), op, grouped(right)),
+        Expr::Assign(target, value, _) => format!("({} = {})", grouped(target), grouped(value)),
+        Expr::CompoundAssign(op, target, value, ..) => format!("({} {}= {})", grouped(target), op, grouped(value)),
+        Expr::Range(start, end, inclusive, _) => {
+            let bound = |bound: &Option<Box<Expr>>| bound.as_deref().map(grouped).unwrap_or_default();
+            format!("({}{}{})", bound(start), if *inclusive { "..=" } else { ".." }, bound(end))
+        }
+        Expr::Cast(inner, ty, _) => format!("({} as {})", grouped(inner), ty),
+        Expr::Unary(op, inner, ..) => format!("({:?} {})", op, grouped(inner)),
+        other => panic!("unexpected expression {:?}", other),
+    }
+}
+
+#[test]
+fn test_operator_binding_power() {
+    let parse = |expr: &str| {
+        let program = parse_program(&format!("fn test() {{ {}; }}", expr)).unwrap();
+        let contractus::ast::Item::Function(function) = &program.items[0] else {
+            panic!("expected a function");
+        };
+        let contractus::ast::Statement::Expr(statement) = &function.body.statements[0] else {
+            panic!("expected an expression statement");
+        };
+        grouped(&statement.expr)
+    };
+    let cases = [
+        ("a - b - c", "((a - b) - c)"),
+        ("a + b * c % d", "(a + ((b * c) % d))"),
+        ("a || b && c | d ^ e & f", "(a || (b && (c | (d ^ (e & f)))))"),
+        ("a == b < c << d", "(a == (b < (c << d)))"),
+        ("a << b .. c + d", "(a << (b..(c + d)))"),
+        ("a..=b << c", "((a..=b) << c)"),
+        ("-a * b as u8 as char", "((Neg a) * ((b as u8) as char))"),
+        ("a = b = c || d", "(a = (b = (c || d)))"),
+        ("a += b -= c * d", "(a += (b -= (c * d)))"),
+        ("a || b = c", "((a || b) = c)"),
+    ];
+    for (expr, expected) in cases {
+        assert_eq!(parse(expr), expected, "{}", expr);
+    }
+
+    // 区间不能连用
+    let errors = parse_program("fn test() { let r = a..b..c; }").unwrap_err();
+    assert_eq!(errors.len(), 1);
+}
+
 #[test]
 fn test_parse_struct_literal() {
     let input = r#"
@@ -182,4 +259,50 @@ fn test_parse_generics() {
         }
     "#;
     assert!(parse_program(input).is_ok());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_contracts() {
+    let input = r#"
+        fn divide(a: i32, b: i32) -> i32
+            requires b != 0,
+            ensures result*b <= a
+        {
+            return a / b;
+        }
+
+        struct Account {
+            balance: i32,
+            invariant self.balance >= 0
+        }
+    "#;
+    let program = parse_program(input).unwrap();
+    let contractus::ast::Item::Function(divide) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    let clauses: Vec<_> = divide
+        .contracts
+        .iter()
+        .map(|contract| (contract.kind, contract.text.as_str()))
+        .collect();
+    assert_eq!(
+        clauses,
+        [
+            (contractus::ast::ContractKind::Requires, "b != 0"),
+            (contractus::ast::ContractKind::Ensures, "result*b <= a"),
+        ]
+    );
+    let contractus::ast::Item::Struct(account) = &program.items[1] else {
+        panic!("expected a struct");
+    };
+    assert_eq!(account.invariants[0].text, "self.balance >= 0");
+
+    let input = r#"
+        struct Account {
+            invariant self.balance >= 0,
+            balance: i32,
+        }
+    "#;
+    let errors = parse_program(input).unwrap_err();
+    assert_eq!(errors[0].message, "Struct fields must come before invariants");
+}
@@ -118,7 +118,7 @@ fn test_complex_expressions() {
         }
         expr.push_str(&format!("(a{} * b{} + c{} / d{})", i, i, i, i));
     }
-    expr.push_str(";");
+    expr.push(';');
     
     let input = format!("fn complex_expr() {{\n    {}\n}}\n", expr);
     
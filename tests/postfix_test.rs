@@ -0,0 +1,106 @@
+// Contractus 后缀表达式测试
+// 方法调用、字段访问、元组下标和索引可以接在任何基本表达式之后；
+// `-x.abs()` 容易误读，必须用括号写明是 `-(x.abs())` 还是 `(-x).abs()`
+
+mod common;
+
+use common::run;
+use contractus::ast::{Expr, Item, Statement, UnOp};
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::module;
+
+const PROGRAM: &str = "struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {
+    let a = 3;
+    let b = -5;
+    print((a + b).abs());
+    print(\"hi\".len());
+    print([1, 2, 3].len());
+    print([10, 20, 30][1]);
+    print(7.abs());
+    print((4, 9).1);
+    print(Point { x: 1, y: 2 }.y);
+    print(
+        if a > b {
+            a
+        } else {
+            b
+        }.abs()
+    );
+    print(
+        match b {
+            0 => 1,
+            n => n,
+        }.abs()
+    );
+    print(-(b.abs()));
+    print((-b).abs());
+}
+";
+
+#[test]
+fn test_postfix_on_primaries() {
+    assert_eq!(run(PROGRAM), "2\n2\n3\n20\n7\n9\n2\n3\n5\n-5\n5\n");
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_negated_method_call() {
+    let errors = |main: &str| {
+        Compiler::new()
+            .source(format!("fn main() {{\n    let x = 5;\n{}\n}}\n", main))
+            .check()
+    };
+
+    let found = errors("    print(-x.abs());");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].code, Some(ErrorCode::E0114));
+    assert_eq!(
+        found[0].message,
+        "unary `-` has lower precedence than a method call"
+    );
+    assert_eq!(
+        found[0].help.as_deref(),
+        Some("write `-(x.abs())` to negate the result, or `(-x).abs()` to call the method on the negated value")
+    );
+
+    // 链式调用时建议给第一个值加上括号
+    let found = errors("    print(-5.abs().to_string());");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0]
+        .help
+        .as_deref()
+        .unwrap()
+        .contains("`(-5).abs().to_string()`"));
+
+    // 加了括号、或者 `-` 之后不是方法调用时没有歧义
+    assert!(errors("    print(-(x.abs()));\n    print((-x).abs());").is_empty());
+    assert!(errors(
+        "    let pair = (1, 2);\n    print(-pair.0);\n    print(!true.to_string().is_int());"
+    )
+    .is_empty());
+
+    // `-(x.abs())` 还原为源码时保留括号
+    let program =
+        module::parse_source("fn main() {\n    let x = 5;\n    print(-(x.abs()));\n}\n").unwrap();
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    let Statement::Expr(print) = &main.body.statements[1] else {
+        panic!("expected an expression statement");
+    };
+    let Expr::Call(_, args, _, _) = &print.expr else {
+        panic!("expected a call");
+    };
+    assert!(
+        matches!(&args[0], Expr::Unary(UnOp::Neg, inner, ..) if matches!(**inner, Expr::MethodCall(..)))
+    );
+    assert!(program.to_source().contains("print(-(x.abs()));"));
+}
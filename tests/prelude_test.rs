@@ -0,0 +1,227 @@
+// Contractus 预导入测试
+// 内置的 Option 和 Result、`?` 的展开（解释器和字节码虚拟机一致）、
+// `?` 的类型检查以及 match 的穷尽性检查
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String, Option<String>)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message, error.help))
+        .collect()
+}
+
+#[test]
+fn test_try_operator() {
+    let output = run(r#"
+        fn find(values: [i32; 4], target: i32) -> Option<i32> {
+            for i in 0..4 {
+                if values[i] == target {
+                    return Some(i);
+                }
+            }
+            return None;
+        }
+
+        fn distance(values: [i32; 4], a: i32, b: i32) -> Option<i32> {
+            let i = find(values, a)?;
+            let j = find(values, b)?;
+            return Some(j - i);
+        }
+
+        fn parse_digit(c: char) -> Result<i32, string> {
+            if c >= '0' && c <= '9' {
+                return Ok(c as i32 - '0' as i32);
+            }
+            return Err("not a digit");
+        }
+
+        fn parse_pair(a: char, b: char) -> Result<i32, string> {
+            return Ok(parse_digit(a)? * 10 + parse_digit(b)?);
+        }
+
+        fn main() {
+            let values = [5, 6, 7, 8];
+            print(distance(values, 6, 8), distance(values, 6, 9));
+            print(parse_pair('4', '2'), parse_pair('4', 'x'));
+            match parse_pair('x', '1') {
+                Ok(n) => print(n),
+                Err(message) => print(message),
+            }
+        }
+    "#);
+    assert_eq!(
+        output,
+        "Some(2)\nNone\nOk(42)\nErr(\"not a digit\")\nnot a digit\n"
+    );
+}
+
+#[test]
+fn test_bare_none_arguments() {
+    // 实参中的 `None` 由形参或变体字段的类型确定类型实参
+    let output = run(r#"
+        enum E {
+            A(Option<i32>),
+        }
+
+        fn g(x: Option<i32>) -> i32 {
+            match x {
+                Some(v) => v,
+                None => -1,
+            }
+        }
+
+        fn f(x: Option<string>) -> string {
+            match x {
+                Some(s) => s,
+                None => "none",
+            }
+        }
+
+        fn main() {
+            print(g(None), g(Some(4)));
+            match E::A(None) {
+                E::A(inner) => print(g(inner)),
+            }
+            print(f(Option::None));
+        }
+    "#);
+    assert_eq!(output, "-1\n4\n-1\nnone\n");
+}
+
+#[test]
+fn test_bare_none_elements() {
+    // 数组和元组元素、方法实参中的 `None` 由标注、其他元素或集合的类型确定类型实参；
+    // 没有任何上下文时同样可以运行
+    let output = run(r#"
+        struct Counter {
+            total: i32,
+        }
+
+        fn add(c: Counter, x: Option<i32>) -> i32 {
+            match x {
+                Some(v) => c.total + v,
+                None => c.total,
+            }
+        }
+
+        fn main() {
+            let a: [Option<i32>; 2] = [Some(1), None];
+            let b = [None, Some(2)];
+            print(a.len(), b.len());
+            let t = (None, 1);
+            print(t.1);
+            let u: (Option<i32>, i32) = (None, 2);
+            print(u.1);
+            let mut v = Vec::new();
+            v.push(Some(1));
+            v.push(None);
+            let mut w: Vec<Option<i32>> = Vec::new();
+            w.push(None);
+            print(v.len(), w.len());
+            let mut x = None;
+            x = Some(5);
+            match x {
+                Some(n) => print(n),
+                None => print(0),
+            }
+            print(Counter { total: 3 }.add(None));
+        }
+    "#);
+    assert_eq!(output, "2\n2\n1\n2\n2\n1\n5\n3\n");
+}
+
+#[test]
+fn test_try_diagnostics() {
+    let errors = check(
+        "fn get() -> Option<i32> {\n    return Some(1);\n}\nfn f() -> i32 {\n    let x = get()?;\n    return x;\n}\nfn main() {}",
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, Some(ErrorCode::E0277));
+    assert_eq!(
+        errors[0].1,
+        "the `?` operator can only be used in a function that returns `Option` or `Result`"
+    );
+    assert_eq!(errors[0].2.as_deref(), Some("this function returns `i32`"));
+
+    let errors = check(
+        "fn get() -> Option<i32> {\n    return Some(1);\n}\nfn f() -> Result<i32, string> {\n    return Ok(get()?);\n}\nfn main() {}",
+    );
+    assert_eq!(
+        errors[0].1,
+        "the `?` operator on `Option` cannot be used in a function that returns `Result`"
+    );
+
+    let errors = check("fn f(n: i32) -> Option<i32> {\n    return Some(n?);\n}\nfn main() {}");
+    assert_eq!(
+        errors[0].1,
+        "the `?` operator can only be applied to values of type `Option` or `Result`, found `i32`"
+    );
+}
+
+#[test]
+fn test_exhaustiveness() {
+    let missing = |arms: &str| -> Vec<String> {
+        let source = format!(
+            "fn f(x: Option<bool>, n: i32) -> i32 {{\n    match x {{\n{}\n    }}\n}}\nfn main() {{}}",
+            arms
+        );
+        check(&source)
+            .into_iter()
+            .filter(|error| error.0 == Some(ErrorCode::E0297))
+            .map(|error| error.1)
+            .collect()
+    };
+    assert!(missing("Some(b) => 1,\nNone => 0,").is_empty());
+    assert!(missing("Some(true) => 1,\nSome(false) | None => 0,").is_empty());
+    assert!(missing("_ => 0,").is_empty());
+    assert_eq!(
+        missing("Some(b) => 1,"),
+        vec!["non-exhaustive patterns: `None` not covered"]
+    );
+    assert_eq!(
+        missing("Some(true) => 1,\nNone => 0,"),
+        vec!["non-exhaustive patterns: `Some(false)` not covered"]
+    );
+    // 守卫不算覆盖
+    assert_eq!(
+        missing("Some(b) if n > 0 => 1,\nNone => 0,"),
+        vec!["non-exhaustive patterns: `Some(_)` not covered"]
+    );
+
+    let errors = check("fn f(n: i32) -> i32 {\n    match n {\n        0 => 1,\n        1 => 2,\n    }\n}\nfn main() {}");
+    assert_eq!(errors[0].0, Some(ErrorCode::E0297));
+    assert_eq!(errors[0].1, "non-exhaustive patterns: `_` not covered");
+    assert_eq!(
+        errors[0].2.as_deref(),
+        Some("add a wildcard arm `_ => ...`")
+    );
+}
+
+// 程序自己定义的 Option 代替预导入的定义
+#[test]
+fn test_user_definitions_take_precedence() {
+    let output = run(r#"
+        enum Option<T> {
+            Some(T),
+            Nothing,
+        }
+
+        fn main() {
+            let x: Option<i32> = Some(3);
+            match x {
+                Some(n) => print(n),
+                Nothing => print(0),
+            }
+            let r: Result<i32, i32> = Err(7);
+            print(r);
+        }
+    "#);
+    assert_eq!(output, "3\nErr(7)\n");
+}
@@ -0,0 +1,124 @@
+// Contractus Rc 测试
+// `Rc::new(value)` 创建共享的值，`Rc::clone(&rc)` 使计数加一，Rc 离开作用域时计数减一，
+// 减到零时析构其中的值；Rc 中的值不能修改（E0594）
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{bytecode, mir, module};
+
+#[test]
+fn test_shared_parent() {
+    let source = "struct Node {
+    name: string,
+    parent: Option<Rc<Node>>,
+}
+
+fn depth(node: &Rc<Node>) -> i32 {
+    match &node.parent {
+        Some(parent) => depth(parent) + 1,
+        None => 0,
+    }
+}
+
+fn main() {
+    let root = Rc::new(Node { name: \"root\", parent: None });
+    {
+        let child = Rc::new(Node { name: \"child\", parent: Some(Rc::clone(&root)) });
+        let leaf = Rc::new(Node { name: \"leaf\", parent: Some(Rc::clone(&child)) });
+        print(depth(&leaf), leaf.name, Rc::strong_count(&root), Rc::strong_count(&child));
+    }
+    print(Rc::strong_count(&root), root.name);
+}
+";
+    assert_eq!(run(source), "2\nleaf\n2\n2\n1\nroot\n");
+}
+
+#[test]
+fn test_drop_releases() {
+    // 集合、结构体和参数中的 Rc 随它们析构；计数减到零时，其中的 Rc 也随之析构
+    let source = "struct Holder {
+    inner: Rc<i32>,
+}
+
+fn unwrap(value: Rc<i32>) -> i32 {
+    *value
+}
+
+fn main() {
+    let shared = Rc::new(7);
+    print(unwrap(Rc::clone(&shared)), Rc::strong_count(&shared));
+    {
+        let copies = Vec::new();
+        for i in 0..3 {
+            copies.push(Rc::clone(&shared));
+        }
+        print(Rc::strong_count(&shared));
+    }
+    {
+        let holder = Rc::new(Holder { inner: Rc::clone(&shared) });
+        let again = Rc::clone(&holder);
+        print(Rc::strong_count(&shared), Rc::strong_count(&again), *again.inner + 1);
+    }
+    print(Rc::strong_count(&shared), shared);
+}
+";
+    assert_eq!(run(source), "7\n1\n4\n2\n2\n8\n1\n7\n");
+}
+
+#[test]
+fn test_refcount_operations_in_mir() {
+    let source = "fn main() {
+    let first = Rc::new(1);
+    let second = Rc::clone(&first);
+    print(*second);
+}
+";
+    let program = module::parse_source(source).unwrap();
+    let lowered = mir::lower_program(&program).unwrap();
+    let text = lowered.to_string();
+    assert!(text.contains("let _2: Rc<i32>;"), "{}", text);
+    assert!(text.contains("Rc::clone("), "{}", text);
+    assert!(text.contains("drop(_1)"), "{}", text);
+    assert!(text.contains("drop(_2)"), "{}", text);
+
+    // 析构在虚拟机中编译为维护计数的指令
+    let module = bytecode::compile(&mir::monomorphize(&lowered).unwrap()).unwrap();
+    assert!(module.to_string().contains("Drop"), "{}", module);
+}
+
+#[test]
+fn test_errors() {
+    let check = |body: &str| {
+        Compiler::new()
+            .source(format!(
+                "struct Point {{\n    x: i32,\n}}\nfn main() {{\n{}\n}}\n",
+                body
+            ))
+            .check()
+    };
+    for body in [
+        "let p = Rc::new(Point { x: 1 });\np.x = 2;",
+        "let n = Rc::new(1);\n*n += 1;",
+    ] {
+        let found = check(body);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert_eq!(found[0].code, Some(ErrorCode::E0594));
+        assert_eq!(found[0].message, "cannot assign to data in an `Rc`");
+    }
+
+    let found = check("let n = Rc::new(1);\nlet m = Rc::clone(n);");
+    assert_eq!(found[0].code, Some(ErrorCode::E0308));
+    assert_eq!(
+        found[0].message,
+        "`Rc::clone` expects `&Rc<T>`, found `Rc<i32>`"
+    );
+
+    // Rc 是间接的，可以打破递归类型的循环
+    let found = Compiler::new()
+        .source("struct Node {\n    next: Option<Rc<Node>>,\n}\nfn main() {}\n")
+        .check();
+    assert!(found.is_empty(), "{:?}", found);
+}
@@ -0,0 +1,107 @@
+// Contractus 递归类型测试
+// 结构体和枚举不能不经过间接包含自身，否则大小无限（E0072）；
+// 引用、指针、`Vec` 等是间接的，按值嵌套同一个泛型类型的不同实例不算递归
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::{Diagnostic, ErrorCode};
+use contractus::driver::Compiler;
+
+const PROGRAM: &str = "struct Tree {
+    value: i32,
+    children: Vec<Tree>,
+}
+
+struct Link {
+    value: i32,
+    next: *const Link,
+}
+
+struct Maybe {
+    inner: Option<Option<i32>>,
+}
+
+fn sum(tree: &Tree) -> i32 {
+    let mut total = tree.value;
+    for child in tree.children {
+        total += sum(&child);
+    }
+    total
+}
+
+fn main() {
+    let mut root = Tree { value: 1, children: Vec::new() };
+    root.children.push(Tree { value: 2, children: Vec::new() });
+    root.children.push(Tree { value: 3, children: Vec::new() });
+    print(sum(&root));
+    print(std::mem::size_of::<Maybe>(), std::mem::size_of::<Link>());
+}
+";
+
+fn errors(items: &str) -> Vec<Diagnostic> {
+    Compiler::new()
+        .source(format!("{}\nfn main() {{}}\n", items))
+        .check()
+}
+
+#[test]
+fn test_indirection() {
+    assert_eq!(run(PROGRAM), "6\n12\n16\n");
+}
+
+#[test]
+fn test_recursive_struct() {
+    let found = errors("struct Node {\n    value: i32,\n    next: Node,\n}");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].code, Some(ErrorCode::E0072));
+    assert_eq!(found[0].message, "recursive type `Node` has infinite size");
+    assert_eq!(
+        found[0].notes,
+        ["`Node` contains `Node` through field `next` at line 3, column 5"]
+    );
+    assert_eq!(
+        found[0].help.as_deref(),
+        Some("insert some indirection to break the cycle, e.g. change `Node` in field `next` to `Box<Node>`, `&Node` or `Vec<Node>`")
+    );
+
+    // 经过枚举、元组和数组也是按值包含
+    for items in [
+        "enum List {\n    Cons(i32, List),\n    Nil,\n}",
+        "struct Node {\n    next: Option<Node>,\n}",
+        "struct Node {\n    pair: (i32, [Node; 2]),\n}",
+    ] {
+        let found = errors(items);
+        assert_eq!(found.len(), 1, "{}: {:?}", items, found);
+        assert_eq!(found[0].code, Some(ErrorCode::E0072), "{}", items);
+    }
+}
+
+#[test]
+fn test_mutually_recursive_types() {
+    // 互相包含的类型只在声明最早的一个上报告，说明环上的每一步
+    let found = errors("struct A {\n    b: B,\n}\nenum B {\n    Leaf,\n    Branch(Wrapper<A>),\n}\nstruct Wrapper<T> {\n    value: T,\n}");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].message, "recursive type `A` has infinite size");
+    assert_eq!(
+        found[0].notes,
+        [
+            "`A` contains `B` through field `b` at line 2, column 5",
+            "`B` contains `Wrapper<A>` through variant `Branch` at line 6, column 5",
+            "`Wrapper<A>` contains `A` through field `value` at line 9, column 5",
+        ]
+    );
+
+    // 每层换一组类型实参的递归
+    let found = errors("struct W<T> {\n    next: W<(T, T)>,\n}");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(
+        found[0].notes,
+        ["`W<T>` contains `W<(T, T)>` through field `next` at line 2, column 5"]
+    );
+
+    // 包含递归类型的类型本身不另外报告
+    let found = errors("struct Node {\n    next: Node,\n}\nstruct Outer {\n    node: Node,\n}");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].message, "recursive type `Node` has infinite size");
+}
@@ -0,0 +1,145 @@
+// Contractus REPL 测试
+// 测试输入之间保留的绑定、条目重新定义、多行输入的判断以及 `:type`/`:ast` 命令
+
+use contractus::interp::Value;
+use contractus::repl::{is_incomplete, Action, History, Repl};
+
+// 依次处理每个输入，返回累计的输出
+fn session(inputs: &[&str]) -> String {
+    let mut repl = Repl::new(Vec::new());
+    for input in inputs {
+        assert_eq!(repl.eval(input).unwrap(), Action::Continue);
+    }
+    String::from_utf8(repl.output().clone()).unwrap()
+}
+
+#[test]
+fn test_bindings_persist_across_inputs() {
+    let mut repl = Repl::new(Vec::new());
+    repl.eval("let mut total = 1;").unwrap();
+    repl.eval("total = total + 41;").unwrap();
+    assert_eq!(repl.binding("total"), Some(Value::Int(42)));
+
+    let output = session(&["let x = 5;", "x * 2", "let x = x + 1;", "x"]);
+    assert_eq!(output, "10\n6\n");
+}
+
+#[test]
+fn test_define_and_redefine_items() {
+    let output = session(&[
+        "fn double(n: i32) -> i32 { n * 2 }",
+        "double(21)",
+        "fn double(n: i32) -> i32 { n + n + 1 }",
+        "double(21)",
+        "struct Point { x: i32, y: i32 }",
+        "let p = Point { x: 1, y: 2 };",
+        "p",
+    ]);
+    assert_eq!(output, "42\n43\nPoint { x: 1, y: 2 }\n");
+}
+
+#[test]
+fn test_const_is_reinitialized_after_redefinition() {
+    let output = session(&["const K: i32 = 7;", "K", "const K: i32 = 8;", "K"]);
+    assert_eq!(output, "7\n8\n");
+}
+
+#[test]
+fn test_errors_do_not_end_the_session() {
+    let output = session(&["print(missing)", "let y = 1 / 0;", "let z = 3;", "z"]);
+    assert!(
+        output.contains("cannot find value `missing` in this scope"),
+        "{}",
+        output
+    );
+    assert!(output.contains("attempt to divide by zero"), "{}", output);
+    assert!(output.ends_with("3\n"), "{}", output);
+}
+
+#[test]
+fn test_rejected_item_keeps_previous_definitions() {
+    let output = session(&[
+        "fn f() -> i32 { 1 }",
+        "fn f() -> i32 { undefined_name }",
+        "f()",
+    ]);
+    assert!(
+        output.contains("cannot find value `undefined_name`"),
+        "{}",
+        output
+    );
+    assert!(output.ends_with("1\n"), "{}", output);
+}
+
+#[test]
+fn test_multi_line_continuation() {
+    assert!(is_incomplete("fn f() {"));
+    assert!(is_incomplete("let a = [1,\n2,"));
+    assert!(is_incomplete("let s = \"unterminated"));
+    assert!(!is_incomplete("fn f() {\n}"));
+    assert!(!is_incomplete("let x = 1;"));
+    // 多余的右括号交给语法分析报错，不再等待输入
+    assert!(!is_incomplete("}"));
+
+    let output = session(&["fn f(n: i32) -> i32 {\n    n + 1\n}", "f(1)"]);
+    assert_eq!(output, "2\n");
+}
+
+#[test]
+fn test_type_command() {
+    let output = session(&[
+        "let x = 5;",
+        ":type x + 1",
+        ":type x < 3",
+        ":type [1, 2, 3]",
+        ":type (true, 'c')",
+        ":type nope",
+    ]);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[..4], ["i32", "bool", "[i32; 3]", "(bool, char)"]);
+    assert!(lines[4].contains("cannot find value `nope`"), "{}", output);
+}
+
+#[test]
+fn test_type_command_does_not_evaluate() {
+    let output = session(&["fn noisy() -> i32 { print(1); 2 }", ":type noisy()"]);
+    assert_eq!(output, "i32\n");
+}
+
+#[test]
+fn test_ast_command() {
+    let output = session(&[":ast 1 + 2"]);
+    assert!(output.starts_with("Binary(\n    Add,"), "{}", output);
+}
+
+#[test]
+fn test_commands() {
+    let mut repl = Repl::new(Vec::new());
+    assert_eq!(repl.eval(":quit").unwrap(), Action::Quit);
+    assert_eq!(repl.eval(":bogus").unwrap(), Action::Continue);
+    let output = String::from_utf8(repl.output().clone()).unwrap();
+    assert!(output.contains("unknown command `:bogus`"), "{}", output);
+}
+
+#[test]
+fn test_history() {
+    let mut history = History::new();
+    history.push("let x = 1;");
+    history.push("let x = 1;");
+    history.push("   ");
+    history.push("fn f() {\n}");
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.get(1), Some("fn f() {\n}"));
+
+    let path = std::env::temp_dir().join(format!("contractus_history_{}", std::process::id()));
+    let mut saved = History::load(path.clone());
+    saved.push("let s = \"a\\nb\";");
+    saved.push("fn f() {\n}");
+    saved.save().unwrap();
+    let loaded = History::load(path.clone());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        loaded.iter().collect::<Vec<_>>(),
+        ["let s = \"a\\nb\";", "fn f() {\n}"]
+    );
+}
@@ -0,0 +1,82 @@
+// Contractus 语法树往返测试
+// 随机生成的语法树还原为源码后重新解析，应当得到同样的结构
+
+use contractus::ast::{Expr, ExprStmt, Item, Statement};
+use contractus::testing::{self, Arbitrary, Gen};
+use contractus::{Lexer, Parser, Program};
+
+fn parse(source: &str) -> Program {
+    let tokens = Lexer::new(source).tokenize().expect("lexing failed");
+    Parser::new(tokens).parse().expect("parsing failed")
+}
+
+#[test]
+fn test_printed_programs_parse_back() {
+    testing::check::<Program>(500, testing::round_trip);
+}
+
+#[test]
+fn test_generation_is_deterministic() {
+    let first = Program::arbitrary(&mut Gen::new(7)).to_source();
+    let second = Program::arbitrary(&mut Gen::new(7)).to_source();
+    let other = Program::arbitrary(&mut Gen::new(8)).to_source();
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+
+    // 深度为零时只有字面量和名字
+    let expr = Expr::arbitrary(&mut Gen::with_size(3, 0));
+    assert!(
+        matches!(expr, Expr::Literal(..) | Expr::Ident(..) | Expr::Path(..)),
+        "{:?}",
+        expr
+    );
+}
+
+#[test]
+fn test_round_trip_reports_differences() {
+    // 只有一段的路径还原为名字，解析回来是 `Expr::Ident`
+    let mut program = parse("fn main() { x; }");
+    let Item::Function(main) = &mut program.items[0] else {
+        panic!("expected a function");
+    };
+    main.body.statements[0] = Statement::Expr(ExprStmt {
+        expr: Expr::Path(vec!["x".to_string()], main.body.span),
+        semicolon: true,
+        span: main.body.span,
+    });
+    let error = testing::round_trip(&program).unwrap_err();
+    assert!(error.contains("\"kind\": \"path\""), "{}", error);
+    assert!(error.ends_with("fn main() {\n    x;\n}\n"), "{}", error);
+}
+
+#[test]
+fn test_shapes_found_by_generation() {
+    // 位置不同不影响比较
+    let source = "fn f() -> &&i32 {
+    let g = (|x| x)(1);
+    let h = (p.f)(&&mut y);
+    let c = (a == b) == (c >= d);
+    let m = match x { _ => return, };
+    let n = if match x { _ if P { a: 1 }.ok => true, } { (return) } else { [break] };
+    for v in (|x| P { a: x }) {}
+    match x { _ => ({ 1 } + 2), }
+}
+
+struct S {
+    invariant (P { a: 1 }).a > 0,
+}
+";
+    let program = parse(source);
+    testing::round_trip(&program).unwrap();
+    let printed = program.to_source();
+    for expected in [
+        "let h = (p.f)(&&mut y);",
+        "let c = (a == b) == c >= d;",
+        "_ => ({\n",
+        "} {\n        (return)\n    } else {\n        [break]\n    };\n",
+        "for v in (|x| P { a: x }) {}",
+        "invariant (P { a: 1 }.a > 0),",
+    ] {
+        assert!(printed.contains(expected), "{}", printed);
+    }
+}
@@ -0,0 +1,140 @@
+// Contractus 沙箱测试
+// 正常结束、编译错误、运行时错误，以及时间、内存和输出的上限
+
+use contractus::diagnostic::ErrorCode;
+use contractus::sandbox::{self, Limits, Status};
+use std::time::Duration;
+
+fn limits() -> Limits {
+    Limits {
+        max_time: Duration::from_millis(200),
+        max_memory: 1024 * 1024,
+        max_output: 64,
+    }
+}
+
+#[test]
+fn test_run_program() {
+    let outcome = sandbox::compile_and_run(
+        "fn add(a: i32, b: i32) -> i32 { return a + b; }\nfn main() { print(add(1, 2)); print(\"done\"); }",
+        &limits(),
+    );
+    assert_eq!(outcome.status, Status::Success);
+    assert_eq!(outcome.stdout, "3\ndone\n");
+    assert_eq!(outcome.stderr, "");
+
+    let outcome = sandbox::compile_and_run("fn main() {\n    print(totl);\n}", &limits());
+    assert_eq!(outcome.status, Status::CompileError);
+    assert_eq!(outcome.diagnostics[0].code, Some(ErrorCode::E0425));
+    assert!(
+        outcome
+            .stderr
+            .starts_with("error[E0425] at line 2, column 11: "),
+        "{}",
+        outcome.stderr
+    );
+
+    // 出错前的输出保留
+    let outcome = sandbox::compile_and_run(
+        "fn main() {\n    let a = [1, 2, 3];\n    let i = 3;\n    print(a[0]);\n    print(a[i]);\n}",
+        &limits(),
+    );
+    assert_eq!(outcome.status, Status::RuntimeError);
+    assert_eq!(outcome.stdout, "1\n");
+    assert_eq!(outcome.diagnostics[0].code, Some(ErrorCode::E0900));
+}
+
+#[test]
+fn test_time_limit() {
+    let outcome = sandbox::compile_and_run(
+        "fn main() {\n    let mut i = 0;\n    while true {\n        i = i + 1;\n    }\n}",
+        &limits(),
+    );
+    assert_eq!(outcome.status, Status::TimeLimitExceeded);
+    // 报告的是设置的上限，而不是编译之后剩下的时间
+    assert!(
+        outcome.stderr.contains("time limit of 200ms exceeded"),
+        "{}",
+        outcome.stderr
+    );
+    assert!(outcome.elapsed < Duration::from_secs(5));
+
+    // 编译也在时间限制之内，超时后不再等待编译器
+    let source = format!("fn main() {{\n{}}}\n", "    print(1 + 2);\n".repeat(20_000));
+    let outcome = sandbox::compile_and_run(
+        &source,
+        &Limits {
+            max_time: Duration::from_millis(1),
+            ..limits()
+        },
+    );
+    assert_eq!(outcome.status, Status::TimeLimitExceeded);
+    assert!(
+        outcome
+            .stderr
+            .contains("time limit of 1ms exceeded while compiling"),
+        "{}",
+        outcome.stderr
+    );
+    assert_eq!(outcome.diagnostics[0].code, Some(ErrorCode::E0803));
+    assert!(outcome.elapsed < Duration::from_secs(1));
+}
+
+#[test]
+fn test_memory_limit() {
+    // 字符串每次翻倍
+    let outcome = sandbox::compile_and_run(
+        "fn main() {\n    let mut s = \"memory\";\n    while true {\n        s = s + s;\n    }\n}",
+        &limits(),
+    );
+    assert_eq!(outcome.status, Status::MemoryLimitExceeded);
+    assert!(
+        outcome
+            .stderr
+            .contains("memory limit of 1048576 bytes exceeded"),
+        "{}",
+        outcome.stderr
+    );
+
+    // StringBuilder 每次追加自身，不等到下次定期检查
+    let outcome = sandbox::compile_and_run(
+        "fn main() {\n    let mut b = StringBuilder::new();\n    append(&mut b, \"memory\");\n    while true {\n        let s = b.to_string();\n        append(&mut b, s);\n    }\n}",
+        &limits(),
+    );
+    assert_eq!(outcome.status, Status::MemoryLimitExceeded);
+    assert!(
+        outcome
+            .stderr
+            .contains("memory limit of 1048576 bytes exceeded"),
+        "{}",
+        outcome.stderr
+    );
+
+    // 深递归中每层都有数组
+    let outcome = sandbox::compile_and_run(
+        "fn deep(n: i32) -> i32 {\n    let a = [n, n, n, n, n, n, n, n, n, n, n, n, n, n, n, n];\n    if n == 0 {\n        return a[0];\n    }\n    return deep(n - 1) + a[1];\n}\nfn main() {\n    print(deep(5000));\n}",
+        &Limits {
+            max_memory: 64 * 1024,
+            ..limits()
+        },
+    );
+    assert_eq!(outcome.status, Status::MemoryLimitExceeded);
+}
+
+#[test]
+fn test_output_limit() {
+    let outcome = sandbox::compile_and_run(
+        "fn main() {\n    while true {\n        print(\"0123456789\");\n    }\n}",
+        &limits(),
+    );
+    assert_eq!(outcome.status, Status::OutputLimitExceeded);
+    assert_eq!(outcome.stdout.len(), 64);
+    assert!(outcome.stdout.starts_with("0123456789\n0123456789\n"));
+    assert!(
+        outcome
+            .stderr
+            .contains("the output limit of 64 bytes was exceeded"),
+        "{}",
+        outcome.stderr
+    );
+}
@@ -0,0 +1,516 @@
+// Contractus 语义分析测试
+// 测试名称解析、字段和方法查找、重复定义以及拼写建议
+
+use contractus::diagnostic::ErrorCode;
+use contractus::sema::Effects;
+use contractus::{Diagnostic, Lexer, Parser, SemanticAnalyzer};
+
+fn analyze(input: &str) -> Result<(), Vec<Diagnostic>> {
+    let tokens = Lexer::new(input).tokenize().expect("lexing failed");
+    let program = Parser::new(tokens).parse().expect("parsing failed");
+    SemanticAnalyzer::new().analyze(&program)
+}
+
+fn single_error(input: &str) -> Diagnostic {
+    let errors = analyze(input).unwrap_err();
+    assert_eq!(errors.len(), 1, "expected one error, got {:?}", errors);
+    errors.into_iter().next().unwrap()
+}
+
+#[test]
+fn test_valid_program() {
+    let input = r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        fn add(a: Point, b: Point) -> Point {
+            return Point { x: a.x + b.x, y: a.y + b.y };
+        }
+
+        fn main() -> i32 {
+            let p = add(Point { x: 1, y: 2 }, Point { x: 3, y: 4 });
+            let total = p.x + p.y;
+            for i in 0..10 {
+                let doubled = i * 2;
+            }
+            return total;
+        }
+    "#;
+
+    assert!(analyze(input).is_ok());
+}
+
+#[test]
+fn test_undefined_variable_suggestion() {
+    let input = r#"
+        fn main() -> i32 {
+            let length = 10;
+            return lenght;
+        }
+    "#;
+
+    let error = single_error(input);
+    assert_eq!(error.message, "cannot find value `lenght` in this scope");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a local variable with a similar name exists: `lenght` → `length`")
+    );
+    assert_eq!(error.span.line, 4);
+}
+
+#[test]
+fn test_undefined_function_suggestion() {
+    let input = r#"
+        fn compute(x: i32) -> i32 {
+            return x;
+        }
+
+        fn main() -> i32 {
+            return compte(1);
+        }
+    "#;
+
+    let error = single_error(input);
+    assert_eq!(error.message, "cannot find function `compte` in this scope");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a function with a similar name exists: `compte` → `compute`")
+    );
+}
+
+#[test]
+fn test_field_access_suggestion() {
+    let input = r#"
+        struct Buffer {
+            length: i32,
+            capacity: i32,
+        }
+
+        fn size(buf: Buffer) -> i32 {
+            return buf.lenght;
+        }
+    "#;
+
+    let error = single_error(input);
+    assert_eq!(error.message, "struct `Buffer` has no field named `lenght`");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a field with a similar name exists: `lenght` → `length`")
+    );
+}
+
+#[test]
+fn test_struct_literal_and_nested_fields() {
+    let input = r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        struct Line {
+            start: Point,
+            end: Point,
+        }
+
+        fn main() -> i32 {
+            let line = Line { start: Point { x: 0, y: 0 }, edn: Point { x: 1, y: 1 } };
+            return line.start.z;
+        }
+    "#;
+
+    let errors = analyze(input).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].message, "struct `Line` has no field named `edn`");
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("a field with a similar name exists: `edn` → `end`")
+    );
+    assert_eq!(errors[1].message, "struct `Point` has no field named `z`");
+    assert!(errors[1].help.is_none());
+}
+
+#[test]
+fn test_unknown_type_suggestion() {
+    let input = r#"
+        struct Point {
+            x: i32,
+        }
+
+        fn origin() -> Piont {
+            return Point { x: 0 };
+        }
+    "#;
+
+    let error = single_error(input);
+    assert_eq!(error.message, "cannot find type `Piont` in this scope");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a type with a similar name exists: `Piont` → `Point`")
+    );
+}
+
+#[test]
+fn test_no_suggestion_for_distant_names() {
+    let input = r#"
+        fn main() -> i32 {
+            let alpha = 1;
+            return omega;
+        }
+    "#;
+
+    let error = single_error(input);
+    assert!(error.help.is_none());
+}
+
+#[test]
+fn test_enum_variant_path() {
+    let input = r#"
+        enum Color {
+            Red,
+            Green,
+        }
+
+        fn main() -> i32 {
+            let ok = Color::Green;
+            let typo = Color::Gren;
+            return 0;
+        }
+    "#;
+
+    let error = single_error(input);
+    assert_eq!(error.message, "no variant named `Gren` in enum `Color`");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a variant with a similar name exists: `Gren` → `Green`")
+    );
+}
+
+#[test]
+fn test_contract_condition_must_be_bool() {
+    let error = single_error(
+        r#"
+        fn half(x: i32) -> i32
+            requires x
+        {
+            return x / 2;
+        }
+    "#,
+    );
+    assert_eq!(
+        error.message,
+        "`requires` condition must be `bool`, found `i32`"
+    );
+
+    // `result` 只在 ensures 中可见
+    let error = single_error(
+        r#"
+        fn half(x: i32) -> i32
+            requires result > 0
+        {
+            return x / 2;
+        }
+    "#,
+    );
+    assert!(error.message.contains("`result`"), "{}", error.message);
+
+    assert!(analyze(
+        r#"
+        struct Range {
+            start: i32,
+            end: i32,
+            invariant self.start <= self.end
+        }
+
+        fn half(x: i32) -> i32
+            requires x >= 0
+            ensures result <= x
+        {
+            return x / 2;
+        }
+    "#
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_impure_contract_conditions() {
+    let error = single_error(
+        r#"
+        fn log(x: i32) -> bool {
+            return report(x);
+        }
+
+        fn report(x: i32) -> bool {
+            print(x);
+            return true;
+        }
+
+        fn check(x: i32) -> i32
+            requires log(x)
+        {
+            return x;
+        }
+    "#,
+    );
+    assert_eq!(
+        error.message,
+        "`requires` condition calls impure function `log`"
+    );
+    assert_eq!(
+        error.notes,
+        [
+            "`log` is impure because it calls `report` at line 3, column 20",
+            "`report` is impure because it calls the builtin `print` at line 7, column 13",
+        ]
+    );
+
+    let error = single_error(
+        r#"
+        static mut COUNT: i32 = 0;
+
+        struct Counter {
+            value: i32,
+            invariant { unsafe { COUNT = COUNT + 1; } self.value >= 0 }
+        }
+    "#,
+    );
+    assert_eq!(
+        error.message,
+        "`invariant` condition must be side-effect free, but it writes to static `COUNT`"
+    );
+
+    let error = single_error(
+        r#"
+        fn apply(f: fn(i32) -> bool, x: i32) -> i32
+            requires f(x)
+        {
+            return x;
+        }
+    "#,
+    );
+    assert!(
+        error.message.contains("calls a function value"),
+        "{}",
+        error.message
+    );
+}
+
+#[test]
+fn test_pure_functions_in_contracts() {
+    // 只修改局部变量和指向局部变量的引用不算副作用
+    let input = r#"
+        fn sum(values: [i32; 3]) -> i32 {
+            let mut total = 0;
+            for value in values {
+                total = total + value;
+            }
+            let r = &mut total;
+            *r = *r + 0;
+            return total;
+        }
+
+        fn bump(counter: &mut i32) {
+            *counter = *counter + 1;
+        }
+
+        fn first(values: [i32; 3]) -> i32
+            requires sum(values) > 0
+            ensures result <= sum(values)
+        {
+            return values[0];
+        }
+    "#;
+    assert!(analyze(input).is_ok(), "{:?}", analyze(input));
+
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let program = Parser::new(tokens).parse().unwrap();
+    let effects = Effects::analyze(&program);
+    assert!(effects.is_pure("sum"));
+    assert!(effects.is_pure("first"));
+    assert!(!effects.is_pure("bump"));
+    assert_eq!(
+        effects.effect_of("bump").unwrap().describe(),
+        "writes through a reference"
+    );
+}
+
+#[test]
+fn test_invalid_assignment_targets() {
+    let errors = analyze(
+        r#"
+        struct Point { x: i32 }
+        fn value() -> i32 { 5 }
+        fn main() {
+            let mut x = 1;
+            5 = x;
+            value() = 3;
+            x + 1 = 2;
+            Point { x: 1 } = Point { x: 2 };
+            value() += 1;
+            (x, x) = (1, 2);
+        }
+    "#,
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 6, "{:?}", errors);
+    assert!(errors
+        .iter()
+        .all(|error| error.code == Some(ErrorCode::E0070)
+            && error.message == "invalid left-hand side of assignment"));
+    // 诊断指向左侧
+    assert_eq!(errors[1].span.column, 13);
+    assert_eq!(errors[1].span.end - errors[1].span.start, "value()".len());
+
+    assert!(analyze(
+        r#"
+        struct Point { x: i32 }
+        fn main() {
+            let mut x = 1;
+            let mut p = Point { x: 1 };
+            let mut values = [1, 2, 3];
+            x = 2;
+            p.x += x;
+            values[0] <<= 2;
+            let r = &mut x;
+            *r = 3;
+        }
+    "#
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_break_and_continue_targets() {
+    let errors = analyze(
+        r#"
+        fn main() {
+            break;
+            'outer: while true {
+                let stop = || {
+                    continue;
+                };
+                let nested = || {
+                    while true {
+                        break;
+                    }
+                };
+                let labeled = || {
+                    break 'outer;
+                };
+                break 'outr;
+            }
+            for i in 0..3 {
+                let f = |x: i32| x + 1;
+                continue;
+            }
+        }
+    "#,
+    )
+    .unwrap_err();
+    let errors: Vec<(Option<ErrorCode>, String)> = errors
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                Some(ErrorCode::E0268),
+                "`break` outside of a loop".to_string()
+            ),
+            (
+                Some(ErrorCode::E0267),
+                "`continue` inside of a closure".to_string()
+            ),
+            // 闭包中看不到外面的循环的标签
+            (
+                Some(ErrorCode::E0426),
+                "use of undeclared label `'outer`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0426),
+                "use of undeclared label `'outr`".to_string()
+            ),
+        ]
+    );
+
+    let error = single_error("fn main() { 'outer: while true { break 'outr; } }");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a label with a similar name exists: `'outr` → `'outer`")
+    );
+}
+
+#[test]
+fn test_duplicate_definitions() {
+    let input = "fn foo() {}\nstruct S {\n    x: i32,\n}\nfn foo() -> i32 {\n    1\n}\nenum S {\n    A,\n}\nfn main() {}\n";
+    let errors: Vec<(Option<ErrorCode>, String, u32, Vec<String>)> = analyze(input)
+        .unwrap_err()
+        .into_iter()
+        .map(|error| (error.code, error.message, error.span.line, error.notes))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                Some(ErrorCode::E0428),
+                "the name `foo` is defined multiple times".to_string(),
+                5,
+                vec!["the first definition of `foo` is at line 1, column 1".to_string()]
+            ),
+            (
+                Some(ErrorCode::E0428),
+                "the name `S` is defined multiple times".to_string(),
+                8,
+                vec!["the first definition of `S` is at line 2, column 1".to_string()]
+            ),
+        ]
+    );
+
+    // 类型和函数的名字互不冲突
+    assert!(analyze("struct S {\n    x: i32,\n}\nfn S() {}\nfn main() {}\n").is_ok());
+}
+
+#[test]
+fn test_unknown_method_suggestion() {
+    let input = r#"
+        struct Point {
+            x: i32,
+        }
+
+        fn point_area(p: Point) -> i32 {
+            p.x * p.x
+        }
+
+        fn main() {
+            let p = Point { x: 2 };
+            print(p.area(), p.point_area());
+            p.aera();
+        }
+    "#;
+    let error = single_error(input);
+    assert_eq!(error.code, Some(ErrorCode::E0599));
+    assert_eq!(
+        error.message,
+        "no method named `aera` found for type `Point`"
+    );
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a method with a similar name exists: `aera` → `area`")
+    );
+    assert_eq!(error.span.line, 13);
+
+    let error = single_error("fn main() {\n    let a = [1, 2];\n    a.nonexistent(3);\n}\n");
+    assert_eq!(
+        error.message,
+        "no method named `nonexistent` found for type `[i32; 2]`"
+    );
+    assert_eq!(error.help, None);
+    let error = single_error("fn main() {\n    let a = [1, 2];\n    print(a.lenn());\n}\n");
+    assert_eq!(
+        error.help.as_deref(),
+        Some("a method with a similar name exists: `lenn` → `len`")
+    );
+}
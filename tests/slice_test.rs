@@ -0,0 +1,245 @@
+// Contractus 切片测试
+// `&a[start..end]` 是指向数组一段的胖指针（起点和长度），解释器和虚拟机的表示相同；
+// 接收 `&[T]` 的函数可以处理任意长度的数组，越界的区间在执行时报错
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+use contractus::{bytecode, interp, mir, module, Diagnostic};
+
+const PROGRAM: &str = "fn sum(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for value in values {
+        total += value;
+    }
+    total
+}
+
+fn largest(values: &[i32]) -> i32 {
+    let mut best = values[0];
+    let mut i = 1;
+    while i < values.len() {
+        if values[i] > best {
+            best = values[i];
+        }
+        i += 1;
+    }
+    best
+}
+
+fn fill(target: &mut [i32], value: i32) {
+    let mut i = 0;
+    while i < len(target) {
+        target[i] = value;
+        i += 1;
+    }
+}
+
+fn main() {
+    let short = [4, 9];
+    let long = [3, 1, 4, 1, 5, 9, 2, 6];
+    print(sum(&short), sum(&long));
+    print(largest(&long), largest(&long[0..3]));
+    let middle = &long[2..6];
+    print(middle.len(), middle[0], middle[3]);
+    let inner = &middle[1..=2];
+    print(inner, sum(inner));
+    let mut buffer = [0, 0, 0, 0, 0];
+    fill(&mut buffer[1..4], 7);
+    print(buffer);
+    print(sum(&long[3..3]));
+}
+";
+
+// 两个后端的错误消息；两者报告的位置必须相同
+fn failures(source: &str) -> (String, String) {
+    let program = module::parse_source(source).unwrap();
+    let (result, _) = interp::run_with_output(&program, Vec::new());
+    let interp_error = result.unwrap_err()[0].clone();
+    let lowered = mir::monomorphize(&mir::lower_program(&program).unwrap()).unwrap();
+    let module = bytecode::compile(&lowered).unwrap();
+    let (result, _) = bytecode::run_with_output(&module, Vec::new());
+    let vm_error = result.unwrap_err()[0].clone();
+    assert_eq!(position(&vm_error), position(&interp_error), "{}", source);
+    (interp_error.message, vm_error.message)
+}
+
+fn position(error: &Diagnostic) -> (u32, u32) {
+    (error.span.line, error.span.column)
+}
+
+#[test]
+fn test_slices() {
+    assert_eq!(
+        run(PROGRAM),
+        "13\n31\n9\n4\n4\n4\n9\n[1, 5]\n6\n[0, 7, 7, 7, 0]\n0\n"
+    );
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_open_ended_slices() {
+    // 省略的起点是 0，省略的终点是数组或切片的长度
+    let source = "fn sum(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for value in values {
+        total += value;
+    }
+    total
+}
+
+fn main() {
+    let a = [1, 2, 3, 4, 5];
+    print(&a[2..], &a[..2], &a[..]);
+    print(sum(&a[..=1]), sum(&a[a.len() - 1..]));
+    let tail = &a[1..];
+    print(&tail[..tail.len() - 1], &tail[3..], (&tail[..])[0]);
+    let mut buffer = [0, 0, 0];
+    fill(&mut buffer[1..]);
+    print(buffer);
+}
+
+fn fill(target: &mut [i32]) {
+    let mut i = 0;
+    while i < len(target) {
+        target[i] = 7;
+        i += 1;
+    }
+}
+";
+    assert_eq!(
+        run(source),
+        "[3, 4, 5]\n[1, 2]\n[1, 2, 3, 4, 5]\n3\n5\n[2, 3, 4]\n[5]\n2\n[0, 7, 7]\n"
+    );
+    assert_eq!(common::roundtrip(source), source);
+}
+
+#[test]
+fn test_subslice_in_mir() {
+    let source = "fn main() {\n    let a = [1, 2, 3];\n    let s = &a[0..2];\n    print(s);\n}\n";
+    let program = module::parse_source(source).unwrap();
+    let text = mir::lower_program(&program).unwrap().to_string();
+    assert!(text.contains("= &_1[_"), "{}", text);
+}
+
+#[test]
+fn test_slice_bounds() {
+    let check = |slice: &str| {
+        failures(&format!(
+            "fn main() {{\n    let a = [1, 2, 3, 4];\n    let s = {};\n    print(s);\n}}\n",
+            slice
+        ))
+    };
+
+    let (interp_error, vm_error) = check("&a[1..9]");
+    assert_eq!(
+        interp_error,
+        "range end index 9 out of range for slice of length 4"
+    );
+    assert_eq!(vm_error, interp_error);
+
+    let (interp_error, vm_error) = check("&a[..9]");
+    assert_eq!(
+        interp_error,
+        "range end index 9 out of range for slice of length 4"
+    );
+    assert_eq!(vm_error, interp_error);
+
+    let (interp_error, vm_error) = check("&a[3..1]");
+    assert_eq!(interp_error, "slice index starts at 3 but ends at 1");
+    assert_eq!(vm_error, interp_error);
+
+    // 切片的边界和下标按切片的长度检查，不是数组的长度
+    let (interp_error, vm_error) = check("&(&a[1..3])[1..3]");
+    assert_eq!(
+        interp_error,
+        "range end index 3 out of range for slice of length 2"
+    );
+    assert_eq!(vm_error, interp_error);
+    let (interp_error, vm_error) = check("(&a[1..3])[2]");
+    assert_eq!(
+        interp_error,
+        "index out of bounds: the len is 2 but the index is 2"
+    );
+    assert_eq!(vm_error, interp_error);
+}
+
+#[test]
+fn test_error_positions() {
+    // 越界在下标表达式处报告，不是外层的 `&` 或语句的开头
+    let source =
+        "fn main() {\n    let a = [1, 2, 3, 4];\n    let s = &a[1..9];\n    print(s);\n}\n";
+    failures(source);
+    let program = module::parse_source(source).unwrap();
+    let (result, _) = interp::run_with_output(&program, Vec::new());
+    assert_eq!(position(&result.unwrap_err()[0]), (3, 14));
+
+    // 去掉边界检查后虚拟机自己发现越界，位置仍然与解释器相同
+    for (access, column) in [("a[i]", 11), ("&a[1..i]", 12)] {
+        let source = format!(
+            "fn main() {{\n    let a = [1, 2, 3, 4];\n    let i = 7;\n    print({});\n}}\n",
+            access
+        );
+        let program = module::parse_source(&source).unwrap();
+        let (result, _) = interp::run_with_output(&program, Vec::new());
+        let interp_error = result.unwrap_err()[0].clone();
+        assert_eq!(position(&interp_error), (4, column));
+        let module = Compiler::new()
+            .source(&source)
+            .bounds_checks(false)
+            .run()
+            .into_result()
+            .unwrap()
+            .into_object()
+            .unwrap();
+        let (result, _) = bytecode::run_with_output(&module, Vec::new());
+        let vm_error = result.unwrap_err()[0].clone();
+        assert_eq!(vm_error.message, interp_error.message);
+        assert_eq!(position(&vm_error), position(&interp_error));
+    }
+}
+
+#[test]
+fn test_slice_diagnostics() {
+    let found = errors("fn main() {\n    let a = [1, 2, 3];\n    let s = a[0..2];\n}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0161),
+            "a slice must be used through a reference".to_string()
+        )]
+    );
+
+    let found = errors("fn main() {\n    let n = 5;\n    let s = &n[0..2];\n}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0608),
+            "cannot slice a value of type `i32`".to_string()
+        )]
+    );
+    let found = Compiler::new()
+        .source("fn main() {\n    let s = \"abc\";\n    let t = &s[0..2];\n}\n")
+        .check();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        found[0].help.as_deref(),
+        Some("use `substring(s, start, end)` to take part of a string")
+    );
+
+    // 切片的引用是指针加长度，切片本身没有布局
+    let source = "fn main() {\n    print(std::mem::size_of::<&[i32]>(), std::mem::size_of::<&[u8; 3]>());\n}\n";
+    assert_eq!(run(source), "16\n8\n");
+    let found = errors("fn main() {\n    let n = std::mem::size_of::<[i32]>();\n}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0704),
+            "the size of `[i32]` is not known at compile time".to_string()
+        )]
+    );
+}
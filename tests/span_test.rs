@@ -0,0 +1,167 @@
+// Contractus 语法树范围测试
+// 每个节点的范围从它的第一个记号（包括 `pub`）到最后一个记号，行号和列号是起点的位置
+
+use contractus::ast::{ElseBranch, Expr, Item, Program, Statement};
+use contractus::span::Span;
+use contractus::{Lexer, Parser};
+
+// 只做语法分析，不展开宏和 `#[derive]`
+fn parse(source: &str) -> Program {
+    let tokens = Lexer::new(source).tokenize().expect("lexing failed");
+    Parser::new(tokens).parse().expect("parsing failed")
+}
+
+// 范围覆盖的源码，以及起点的行号和列号
+fn at(source: &str, span: Span) -> (&str, u32, u32) {
+    (&source[span.start..span.end], span.line, span.column)
+}
+
+#[test]
+fn test_item_spans() {
+    let source = "#[derive(Debug)]
+pub struct Point<T> {
+    pub x: T,
+    y: i32,
+}
+
+pub enum Shape {
+    Circle(i32),
+}
+
+pub const LIMIT: i32 = 10;
+pub static mut COUNT: i32 = 0;
+
+pub fn pick<T: Copy, U>(a: T, b: U) -> T {
+    a
+}
+";
+    let program = parse(source);
+    let Item::Struct(point) = &program.items[0] else {
+        panic!("expected a struct");
+    };
+    // 条目从 `pub` 开始，不包括属性
+    assert_eq!(
+        at(source, point.span),
+        ("pub struct Point<T> {\n    pub x: T,\n    y: i32,\n}", 2, 1)
+    );
+    assert_eq!(
+        at(source, point.attributes[0].span),
+        ("#[derive(Debug)]", 1, 1)
+    );
+    assert_eq!(
+        at(source, point.generics.as_ref().unwrap().span),
+        ("<T>", 2, 17)
+    );
+    assert_eq!(at(source, point.fields[0].span), ("pub x: T", 3, 5));
+    assert_eq!(at(source, point.fields[1].span), ("y: i32", 4, 5));
+
+    let Item::Enum(shape) = &program.items[1] else {
+        panic!("expected an enum");
+    };
+    assert_eq!(at(source, shape.span).1, 7);
+    assert!(at(source, shape.span).0.starts_with("pub enum Shape {"));
+    assert_eq!(at(source, shape.variants[0].span), ("Circle(i32)", 8, 5));
+
+    let Item::Const(limit) = &program.items[2] else {
+        panic!("expected a const");
+    };
+    assert_eq!(
+        at(source, limit.span),
+        ("pub const LIMIT: i32 = 10;", 11, 1)
+    );
+    let Item::Static(count) = &program.items[3] else {
+        panic!("expected a static");
+    };
+    assert_eq!(
+        at(source, count.span),
+        ("pub static mut COUNT: i32 = 0;", 12, 1)
+    );
+
+    let Item::Function(pick) = &program.items[4] else {
+        panic!("expected a function");
+    };
+    assert_eq!(at(source, pick.span).1, 14);
+    assert!(at(source, pick.span).0.starts_with("pub fn pick"));
+    let generics = pick.generics.as_ref().unwrap();
+    assert_eq!(at(source, generics.span), ("<T: Copy, U>", 14, 12));
+    assert_eq!(at(source, generics.params[0].span), ("T: Copy", 14, 13));
+    assert_eq!(at(source, pick.params[1].span), ("b: U", 14, 31));
+}
+
+#[test]
+fn test_statement_and_expression_spans() {
+    let source = "fn main() {
+    let mut total = 1;
+    total += 2;
+    'outer: while total < 10 {
+        break 'outer;
+    }
+    if total > 1 {
+        total = 0;
+    } else if total < 0 {
+        total = 1;
+    }
+    total
+}
+";
+    let program = parse(source);
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    let statements = &main.body.statements;
+    assert_eq!(
+        at(source, statements[0].span()),
+        ("let mut total = 1;", 2, 5)
+    );
+
+    // 表达式语句包括分号，表达式本身不包括
+    let Statement::Expr(stmt) = &statements[1] else {
+        panic!("expected an expression statement");
+    };
+    assert_eq!(at(source, stmt.span), ("total += 2;", 3, 5));
+    assert_eq!(at(source, stmt.expr.span()), ("total += 2", 3, 5));
+    let Expr::CompoundAssign(_, target, value, ..) = &stmt.expr else {
+        panic!("expected a compound assignment");
+    };
+    assert_eq!(at(source, target.span()), ("total", 3, 5));
+    assert_eq!(at(source, value.span()), ("2", 3, 14));
+
+    // 带标签的循环从标签开始
+    let Statement::While(looped) = &statements[2] else {
+        panic!("expected a while loop");
+    };
+    assert_eq!(at(source, looped.span).1, 4);
+    assert!(at(source, looped.span).0.starts_with("'outer: while"));
+    assert_eq!(at(source, looped.cond.span()), ("total < 10", 4, 19));
+    assert_eq!(
+        at(source, looped.body.statements[0].span()),
+        ("break 'outer;", 5, 9)
+    );
+
+    let Statement::If(branch) = &statements[3] else {
+        panic!("expected an if statement");
+    };
+    assert_eq!(at(source, branch.then_block.span).1, 7);
+    let Some(ElseBranch::If(nested)) = &branch.else_block else {
+        panic!("expected an else-if branch");
+    };
+    assert_eq!(
+        at(source, nested.span),
+        ("if total < 0 {\n        total = 1;\n    }", 9, 12)
+    );
+
+    let Statement::Expr(tail) = &statements[4] else {
+        panic!("expected the tail expression");
+    };
+    assert!(!tail.semicolon);
+    assert_eq!(at(source, tail.span), ("total", 12, 5));
+}
+
+#[test]
+fn test_merge_keeps_the_earlier_start() {
+    let first = Span::new(4, 9, 2, 3);
+    let second = Span::new(20, 25, 3, 1);
+    // 无论顺序如何，行号和列号都是靠前的范围的起点
+    assert_eq!(first.merge(&second), Span::new(4, 25, 2, 3));
+    assert_eq!(second.merge(&first), Span::new(4, 25, 2, 3));
+}
@@ -0,0 +1,168 @@
+// Contractus const 和 static 测试
+// 常量在使用处内联；静态变量有固定的地址，可以取引用，`static mut` 可以修改，
+// 但只能在 unsafe 块中使用。初始值必须是常量表达式
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+use contractus::{bytecode, mir, module};
+
+const PROGRAM: &str = "struct Config {
+    name: string,
+    level: i32,
+}
+
+const SCALE: i32 = 3;
+
+const LIMIT: i32 = SCALE * 10 + 2;
+
+static NAME: string = \"ctx\";
+
+static PRIMES: [i32; 3] = [2, 3, 5];
+
+static DEFAULT: Option<i32> = Some(-1);
+
+static mut COUNTER: i32 = 0;
+
+static mut CONFIG: Config = Config { name: \"default\", level: LIMIT };
+
+static mut PAIR: (i32, i32) = (1, 2);
+
+fn bump(by: i32) -> i32 {
+    unsafe {
+        COUNTER += by;
+        COUNTER
+    }
+}
+
+fn raise(level: &mut i32) {
+    *level += 1;
+}
+
+fn read(r: &i32) -> i32 {
+    *r
+}
+
+fn main() {
+    print(LIMIT, NAME, PRIMES[2]);
+    match DEFAULT {
+        Some(n) => print(n),
+        None => print(0),
+    }
+    bump(2);
+    print(bump(3));
+    unsafe {
+        CONFIG.level *= 2;
+        CONFIG.name = \"custom\";
+        raise(&mut CONFIG.level);
+        let r = &mut COUNTER;
+        *r = 100;
+        PAIR.1 = COUNTER;
+        print(CONFIG.name, CONFIG.level, PAIR.0 + PAIR.1);
+        let view = &COUNTER;
+        COUNTER = 7;
+        print(read(view));
+    }
+}
+";
+
+#[test]
+fn test_statics() {
+    // 先取的引用能看到之后对静态变量的修改
+    assert_eq!(run(PROGRAM), "32\nctx\n5\n-1\n5\ncustom\n65\n101\n7\n");
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_static_address() {
+    // 对静态变量的赋值和取引用作用于静态变量本身，而不是它的副本
+    let program = module::parse_source(PROGRAM).unwrap();
+    let lowered = mir::lower_program(&program).unwrap();
+    let text = lowered.to_string();
+    assert!(text.contains("= &static COUNTER;"), "{}", text);
+
+    let module = bytecode::compile(&mir::monomorphize(&lowered).unwrap()).unwrap();
+    assert!(module.to_string().contains("AddrStatic"));
+}
+
+#[test]
+fn test_static_mut_requires_unsafe() {
+    let check = |body: &str| {
+        errors(&format!(
+            "static mut COUNT: i32 = 0;\nstatic LIMIT: i32 = 10;\nconst MAX: i32 = 5;\nfn main() {{\n{}\n}}\n",
+            body
+        ))
+    };
+
+    let found = check("    COUNT += 1;");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0133),
+            "use of mutable static `COUNT` requires an unsafe block".to_string()
+        )]
+    );
+    assert_eq!(check("    print(COUNT);").len(), 1);
+    assert!(check("    unsafe {\n        COUNT += LIMIT + MAX;\n    }").is_empty());
+    // 同名的局部变量遮蔽静态变量
+    assert!(check("    let mut COUNT = 1;\n    COUNT += 1;").is_empty());
+
+    let found = check("    LIMIT = 3;");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0594),
+            "cannot assign to immutable static `LIMIT`".to_string()
+        )]
+    );
+    let found = check("    MAX += 1;");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0070),
+            "cannot assign to constant `MAX`".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_constant_initializers() {
+    let check = |items: &str| {
+        errors(&format!("fn twice(x: i32) -> i32 {{\n    x * 2\n}}\nstatic BASE: i32 = 1;\n{}\nfn main() {{}}\n", items))
+    };
+
+    for (items, code, message) in [
+        (
+            "const A: i32 = twice(2);",
+            ErrorCode::E0015,
+            "cannot call non-const function `twice` in constants",
+        ),
+        (
+            "static B: i32 = \"abc\".len() as i32;",
+            ErrorCode::E0015,
+            "cannot call method `len` in statics",
+        ),
+        (
+            "static C: i32 = if true {\n    1\n} else {\n    2\n};",
+            ErrorCode::E0015,
+            "this expression cannot be evaluated in statics",
+        ),
+        (
+            "const D: i32 = BASE + 1;",
+            ErrorCode::E0013,
+            "constants cannot refer to statics: `BASE` is a static",
+        ),
+    ] {
+        let found = check(items);
+        assert_eq!(found, [(Some(code), message.to_string())], "{}", items);
+    }
+
+    // 运算、类型转换、引用、复合字面量、变体构造、布局内建函数和其他静态变量
+    assert!(check(
+        "const E: i64 = (-3 + 4 * 2) as i64;\nstatic F: (i32, [bool; 2]) = (BASE, [true, !false]);\nstatic G: Option<i32> = Some(BASE);\nconst H: usize = std::mem::size_of::<i64>();\nstatic R: &i32 = &BASE;"
+    )
+    .is_empty());
+}
@@ -0,0 +1,115 @@
+// Contractus 字符串测试
+// 不可变的 UTF-8 字符串：按字节计的 len 和 substring、split、contains/find、to_int、
+// `+` 拼接和按字符遍历在解释器和字节码虚拟机中的结果，以及参数类型的检查
+
+mod common;
+
+use common::try_run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+fn check(input: &str) -> Vec<(Option<ErrorCode>, String)> {
+    Compiler::new()
+        .source(input)
+        .check()
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn test_string_methods() {
+    let output = try_run(
+        r#"
+        fn main() {
+            let s = "key=value=1";
+            print(len(s), s.substring(0, 3), substring(s, 4, len(s)));
+            let parts = s.split("=");
+            print(parts, len(parts));
+            print(s.contains("value"), s.find("="), s.find("?"));
+            let greeting = "hello" + ", " + parts[0];
+            print(greeting);
+        }
+    "#,
+    );
+    assert_eq!(
+        output.unwrap(),
+        "11\nkey\nvalue=1\n[\"key\", \"value\", \"1\"]\n3\ntrue\nSome(3)\nNone\nhello, key\n"
+    );
+}
+
+#[test]
+fn test_to_int() {
+    let output = try_run(
+        r#"
+        fn sum(line: string) -> Option<i64> {
+            let mut total = 0;
+            for part in line.split(",") {
+                total = total + part.to_int()?;
+            }
+            return Some(total);
+        }
+
+        fn main() {
+            print(sum("1,-2,30"), sum("1,x"), to_int("+7"), is_int(""));
+        }
+    "#,
+    );
+    assert_eq!(output.unwrap(), "Some(29)\nNone\nSome(7)\nfalse\n");
+}
+
+// 下标按字节计，字符可以占多个字节
+#[test]
+fn test_unicode() {
+    let output = try_run(
+        r#"
+        fn main() {
+            let s = "añb";
+            print(len(s), len(s.chars()), s.substring(1, 3));
+            let mut copy = StringBuilder::new();
+            for c in s {
+                print(c);
+            }
+            for c in chars(&s) {
+                copy.append(c);
+            }
+            print(copy);
+        }
+    "#,
+    );
+    assert_eq!(output.unwrap(), "4\n3\nñ\na\nñ\nb\nañb\n");
+
+    let error = try_run("fn main() {\n    print(\"añb\".substring(0, 2));\n}");
+    assert_eq!(error.unwrap_err(), "byte index 2 is not a char boundary");
+    let error = try_run("fn main() {\n    print(\"abc\".substring(2, 5));\n}");
+    assert_eq!(
+        error.unwrap_err(),
+        "byte range 2..5 is out of bounds of a string of length 3"
+    );
+    let error = try_run("fn main() {\n    print(\"abc\".split(\"\"));\n}");
+    assert_eq!(error.unwrap_err(), "separator must not be empty");
+}
+
+#[test]
+fn test_diagnostics() {
+    let errors = check(
+        "fn main() {\n    let s = \"abc\";\n    print(s.substring(\"a\", 2));\n    print(to_int(s, 10));\n    print(split(1, \",\"));\n}",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            (
+                Some(ErrorCode::E0308),
+                "`substring` expects an integer, found `string`".to_string()
+            ),
+            (
+                Some(ErrorCode::E0061),
+                "function `to_int` takes 1 argument but 2 were supplied".to_string()
+            ),
+            (
+                Some(ErrorCode::E0308),
+                "`split` expects `string`, found `i32`".to_string()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,142 @@
+// Contractus 编辑器语法测试
+// 生成的 TextMate 和 Tree-sitter 语法包含 token.rs 中的每个关键字和运算符
+
+use contractus::syntax::{self, SyntaxFormat};
+use contractus::token;
+use std::env;
+use std::fs;
+use std::process::Command;
+
+// 括号配对（不计字符串中的）
+fn balanced(text: &str) -> bool {
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0 && !in_string
+}
+
+#[test]
+fn test_format_names() {
+    assert_eq!("tmlanguage".parse(), Ok(SyntaxFormat::TextMate));
+    assert_eq!("tree-sitter".parse(), Ok(SyntaxFormat::TreeSitter));
+    assert_eq!(
+        "vim".parse::<SyntaxFormat>(),
+        Err("invalid syntax format `vim`; expected `tmlanguage` or `tree-sitter`".to_string())
+    );
+}
+
+#[test]
+fn test_tmlanguage() {
+    let json = syntax::tmlanguage();
+    assert!(balanced(&json), "{}", json);
+    assert!(json.contains("\"scopeName\": \"source.contractus\""));
+    assert!(json.contains("\"fileTypes\": [\"ctx\"]"));
+    // 正则中的元字符转义，JSON 中的反斜杠写两次
+    assert!(json.contains("\\\\b(?:fn|let|"), "{}", json);
+    assert!(json.contains("|_)\\\\b\"}"), "{}", json);
+    assert!(json.contains("\"match\": \"<<=|>>=|"), "{}", json);
+    assert!(json.contains("\\\\|\\\\|"), "{}", json);
+    assert!(json.contains(
+        "{\"name\": \"keyword.control.contractus\", \"match\": \"\\\\b(?:requires|ensures|invariant|pure|macro_rules|async|await|typeof|asm)\\\\b(?!\\\\s*:[^:])\"}"
+    ));
+
+    let words: Vec<&str> = json
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .collect();
+    for (keyword, _) in [token::KEYWORDS, token::TYPE_KEYWORDS, token::BOOL_LITERALS]
+        .iter()
+        .flat_map(|table| table.iter())
+    {
+        assert!(words.contains(keyword), "`{}` is missing", keyword);
+    }
+}
+
+#[test]
+fn test_tree_sitter() {
+    let files = syntax::generate(SyntaxFormat::TreeSitter);
+    let names: Vec<&str> = files.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["grammar.js", "queries/highlights.scm"]);
+
+    let grammar = &files[0].1;
+    assert!(grammar.contains("module.exports = grammar({\n  name: 'contractus',\n"));
+    let tables = [
+        token::KEYWORDS,
+        token::TYPE_KEYWORDS,
+        token::BOOL_LITERALS,
+        token::OPERATORS,
+        token::PUNCTUATION,
+    ];
+    for (text, _) in tables.iter().flat_map(|table| table.iter()) {
+        assert!(
+            grammar.contains(&format!("\n      '{}',\n", text)),
+            "`{}` is missing",
+            text
+        );
+    }
+
+    let highlights = &files[1].1;
+    assert!(highlights.contains("(keyword) @keyword\n"));
+    assert!(highlights.contains("(primitive_type) @type.builtin\n"));
+    assert!(highlights.contains(
+        "((identifier) @keyword (#any-of? @keyword \"requires\" \"ensures\" \"invariant\" \"pure\" \"macro_rules\" \"async\" \"await\" \"typeof\" \"asm\"))\n"
+    ));
+    assert!(highlights.contains("(source_file (identifier) @function.macro . (operator \"!\"))\n"));
+}
+
+#[test]
+fn test_gen_syntax_command() {
+    let contractus = env!("CARGO_BIN_EXE_contractus");
+
+    // 没有 `-o` 时输出主文件
+    let output = Command::new(contractus)
+        .args(["gen-syntax", "--format=tmlanguage"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        syntax::tmlanguage()
+    );
+
+    let dir = env::temp_dir().join(format!("contractus_syntax_{}", std::process::id()));
+    let output = Command::new(contractus)
+        .args(["gen-syntax", "--format=tree-sitter", "-o"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for (name, contents) in syntax::generate(SyntaxFormat::TreeSitter) {
+        assert_eq!(fs::read_to_string(dir.join(name)).unwrap(), contents);
+    }
+
+    let output = Command::new(contractus)
+        .args(["gen-syntax", "--format=vim"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid syntax format `vim`"));
+}
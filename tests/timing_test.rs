@@ -0,0 +1,155 @@
+// Contractus 编译计时测试
+// 各阶段的记录（嵌套、合并、内存峰值），文本和 JSON 输出，以及 `-Ztime-passes`
+
+use contractus::driver::{Compiler, OptLevel};
+use contractus::timing::{self, CountingAllocator, PassTiming};
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const PROGRAM: &str = "fn square(x: i32) -> i32 { return x * x; }
+fn main() { let v = [1, 2, 3]; print(square(v[1])); }";
+
+#[test]
+fn test_record_pipeline() {
+    let (output, passes) = timing::record(|| {
+        Compiler::new()
+            .source(PROGRAM)
+            .opt_level(OptLevel::Os)
+            .run()
+    });
+    assert!(!output.has_errors());
+
+    let names: Vec<(usize, &str)> = passes.iter().map(|pass| (pass.depth, pass.name)).collect();
+    assert_eq!(
+        names,
+        [
+            (0, "loading"),
+            (1, "lexing"),
+            (1, "parsing"),
+            (1, "macro expansion"),
+            (0, "semantic analysis"),
+            (1, "effect analysis"),
+            (1, "import resolution"),
+            (1, "name resolution and type checking"),
+            (0, "MIR lowering"),
+            (1, "drop elaboration"),
+            (0, "monomorphization"),
+            (0, "optimization"),
+            (1, "constant propagation"),
+            (1, "unreachable block removal"),
+            (1, "block merging"),
+            (1, "dead function removal"),
+            (0, "bytecode generation"),
+        ]
+    );
+    // 每个函数体各运行一次
+    let drops = passes
+        .iter()
+        .find(|pass| pass.name == "drop elaboration")
+        .unwrap();
+    assert_eq!(drops.count, 2);
+    assert!(passes
+        .iter()
+        .all(|pass| pass.peak_memory.is_some_and(|bytes| bytes > 0)));
+
+    // 没有在记录时不计时
+    Compiler::new().source(PROGRAM).run();
+    assert!(timing::finish().is_empty());
+}
+
+#[test]
+fn test_nested_passes() {
+    let ((), passes) = timing::record(|| {
+        timing::time("outer", || {
+            for _ in 0..3 {
+                timing::time("inner", || ());
+            }
+            timing::time("outer", || ());
+        })
+    });
+    let summary: Vec<(&str, usize, usize)> = passes
+        .iter()
+        .map(|pass| (pass.name, pass.depth, pass.count))
+        .collect();
+    assert_eq!(summary, [("outer", 0, 1), ("inner", 1, 3), ("outer", 1, 1)]);
+}
+
+#[test]
+fn test_report_formats() {
+    let passes = vec![
+        PassTiming {
+            name: "parsing",
+            depth: 0,
+            count: 1,
+            duration: Duration::from_micros(1500),
+            peak_memory: Some(2048),
+        },
+        PassTiming {
+            name: "constant propagation",
+            depth: 1,
+            count: 4,
+            duration: Duration::from_micros(250),
+            peak_memory: None,
+        },
+    ];
+    assert_eq!(
+        timing::report(&passes),
+        "time:     1.500ms  peak:    2.0 KiB  parsing\n\
+         time:     0.250ms  peak:          -    constant propagation (x4)\n\
+         time:     1.500ms  total\n"
+    );
+    assert_eq!(
+        timing::to_json(&passes),
+        "{\n  \"passes\": [\n    \
+         {\"name\": \"parsing\", \"depth\": 0, \"count\": 1, \"time_ns\": 1500000, \"peak_memory\": 2048},\n    \
+         {\"name\": \"constant propagation\", \"depth\": 1, \"count\": 4, \"time_ns\": 250000, \"peak_memory\": null}\n  \
+         ]\n}\n"
+    );
+    assert_eq!(timing::to_json(&[]), "{\n  \"passes\": []\n}\n");
+}
+
+#[test]
+fn test_time_passes_option() {
+    let dir = env::temp_dir().join(format!("contractus_timing_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.ctx");
+    fs::write(&file, PROGRAM).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_contractus"))
+            .args(args)
+            .arg(&file)
+            .output()
+            .expect("cannot run contractus")
+    };
+
+    let output = run(&["run", "--vm", "-Ztime-passes"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("KiB  loading\n"), "{}", stderr);
+    assert!(
+        stderr.contains("    name resolution and type checking\n"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("  bytecode generation\n"), "{}", stderr);
+    assert!(stderr.contains("ms  total\n"), "{}", stderr);
+
+    let output = run(&["check", "-Ztime-passes", "-Ztime-passes-format=json"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("{\n  \"passes\": ["), "{}", stderr);
+    assert!(stderr.contains("{\"name\": \"MIR lowering\", \"depth\": 0, \"count\": 1,"));
+
+    let output = run(&["check", "-Zno-such-option"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown debugging option `-Zno-such-option`"));
+
+    fs::remove_dir_all(&dir).ok();
+}
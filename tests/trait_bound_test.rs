@@ -0,0 +1,154 @@
+// Contractus 泛型参数的 trait 约束测试
+// 约束只能是内建的 trait，调用泛型函数时推断出的类型实参必须实现约束中的 trait：
+// 基本类型按固定的规则，结构体和枚举通过 derive，泛型参数通过它自己的约束
+
+mod common;
+
+use common::run;
+use contractus::diagnostic::ErrorCode;
+use contractus::driver::Compiler;
+
+const PROGRAM: &str = "#[derive(Eq, Debug)]
+struct Pair<T> {
+    a: T,
+    b: T,
+}
+
+fn max<T: Ord>(a: T, b: T) -> T {
+    if a > b {
+        return a;
+    }
+    return b;
+}
+
+fn same<T: Eq>(a: T, b: T) -> bool {
+    a == b
+}
+
+fn largest<T: Ord + Copy>(values: &[T]) -> T {
+    let mut best = values[0];
+    for i in 0..len(values) {
+        best = max(best, values[i]);
+    }
+    best
+}
+
+fn main() {
+    print(max(3, 7), max('a', 'b'), max(\"x\", \"y\"));
+    let values = [4, 9, 2];
+    print(largest(&values));
+    let p = Pair { a: 1, b: 2 };
+    print(same(p, Pair { a: 1, b: 2 }), same((1, p), (1, Pair { a: 1, b: 3 })));
+}
+";
+
+// (错误码, 信息, 注释, 帮助, 出错的源码)
+type Error = (
+    Option<ErrorCode>,
+    String,
+    Vec<String>,
+    Option<String>,
+    String,
+);
+
+fn errors(source: &str) -> Vec<Error> {
+    Compiler::new()
+        .source(source)
+        .check()
+        .into_iter()
+        .map(|error| {
+            let text = source[error.span.start..error.span.end].to_string();
+            (error.code, error.message, error.notes, error.help, text)
+        })
+        .collect()
+}
+
+#[test]
+fn test_satisfied_bounds() {
+    assert_eq!(errors(PROGRAM), []);
+    assert_eq!(run(PROGRAM), "7\nb\ny\n9\ntrue\nfalse\n");
+}
+
+#[test]
+fn test_unsatisfied_bound() {
+    let source = "struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn max<T: Ord>(a: T, b: T) -> T {
+    if a > b {
+        return a;
+    }
+    return b;
+}
+
+fn main() {
+    let p = max(Point { x: 1, y: 2 }, Point { x: 3, y: 4 });
+}
+";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0709),
+            "the trait bound `Point: Ord` is not satisfied".to_string(),
+            vec!["required by the bound `T: Ord` of `max` at line 6, column 8".to_string()],
+            None,
+            "max".to_string()
+        )]
+    );
+
+    // 可以派生的 trait 建议派生；泛型参数要在自己的约束中声明
+    let source = "struct Point {
+    x: i32,
+}
+
+fn same<T: Eq>(a: T, b: T) -> bool {
+    a == b
+}
+
+fn check<T>(value: T) -> bool {
+    same(value, value)
+}
+
+fn main() {
+    print(same(Point { x: 1 }, Point { x: 1 }));
+    print(same(1 as f64, 2 as f64));
+}
+";
+    let errors = errors(source);
+    let messages: Vec<(&str, Option<&str>)> = errors
+        .iter()
+        .map(|error| (error.1.as_str(), error.3.as_deref()))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            (
+                "the trait bound `T: Eq` is not satisfied",
+                Some("add `Eq` to the bounds of type parameter `T`")
+            ),
+            (
+                "the trait bound `Point: Eq` is not satisfied",
+                Some("add `#[derive(Eq)]` to `Point`")
+            ),
+            ("the trait bound `f64: Eq` is not satisfied", None),
+        ]
+    );
+}
+
+#[test]
+fn test_unknown_trait() {
+    let source =
+        "fn show<T: Debg>(value: T) {\n    print(value);\n}\n\nfn main() {\n    show(1);\n}\n";
+    assert_eq!(
+        errors(source),
+        [(
+            Some(ErrorCode::E0405),
+            "cannot find trait `Debg` in this scope".to_string(),
+            Vec::new(),
+            Some("a trait with a similar name exists: `Debg` → `Debug`".to_string()),
+            "T: Debg".to_string()
+        )]
+    );
+}
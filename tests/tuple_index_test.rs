@@ -0,0 +1,100 @@
+// Contractus 元组下标测试
+// `pair.0` 访问元组的元素，可以连写（`x.0.1`）、赋值，并通过引用自动解引用
+
+mod common;
+
+use common::{errors, run};
+use contractus::ast::{Expr, Item, Statement};
+use contractus::diagnostic::ErrorCode;
+use contractus::{module, Lexer, TokenKind};
+
+const PROGRAM: &str = "fn swap(pair: (i32, (bool, i32))) -> ((bool, i32), i32) {
+    (pair.1, pair.0)
+}
+
+fn main() {
+    let mut nested = swap((1, (true, 2)));
+    nested.0.1 = nested.0.1 + 40;
+    let view = &nested;
+    print(view.0.1);
+    print(nested.1);
+    print((7, 8).1);
+}
+";
+
+#[test]
+fn test_tuple_index() {
+    assert_eq!(run(PROGRAM), "42\n1\n8\n");
+
+    let program = module::parse_source(PROGRAM).unwrap();
+    let Item::Function(main) = &program.items[1] else {
+        panic!("expected a function");
+    };
+    let Statement::Expr(assign) = &main.body.statements[1] else {
+        panic!("expected an expression statement");
+    };
+    let Expr::Assign(target, _, _) = &assign.expr else {
+        panic!("expected an assignment");
+    };
+    let Expr::TupleIndex(inner, 1, _) = target.as_ref() else {
+        panic!("expected a tuple index: {:?}", target);
+    };
+    assert!(matches!(inner.as_ref(), Expr::TupleIndex(_, 0, _)));
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_tuple_index_tokens() {
+    // `.` 之后的 `0.1` 是两个下标，不是浮点数
+    let tokens = Lexer::new("x.0.1").tokenize().unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Ident("x".into()),
+            TokenKind::Dot,
+            TokenKind::IntLiteral(0),
+            TokenKind::Dot,
+            TokenKind::IntLiteral(1),
+            TokenKind::Eof,
+        ]
+    );
+
+    let found = errors("fn main() {\n    let pair = (1, 2);\n    print(pair.0x1);\n}\n");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(
+        found[0].1,
+        "invalid tuple index: expected a decimal integer like `0`"
+    );
+}
+
+#[test]
+fn test_tuple_index_errors() {
+    let check = |body: &str| {
+        errors(&format!(
+            "struct Point {{\n    x: i32,\n    y: i32,\n}}\nfn main() {{\n{}\n}}\n",
+            body
+        ))
+    };
+
+    for (body, message) in [
+        (
+            "    let pair = (1, 2);\n    print(pair.2);",
+            "no field `2` on type `(i32, i32)`",
+        ),
+        (
+            "    let point = Point { x: 1, y: 2 };\n    print(point.0);",
+            "struct `Point` has no field named `0`",
+        ),
+        (
+            "    let n = 5;\n    print(n.0);",
+            "no field `0` on type `i32`",
+        ),
+    ] {
+        let found = check(body);
+        assert_eq!(found.len(), 1, "{}: {:?}", body, found);
+        assert_eq!(found[0], (Some(ErrorCode::E0609), message.to_string()));
+    }
+}
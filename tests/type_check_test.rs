@@ -0,0 +1,175 @@
+// Contractus 类型检查测试
+// 用户定义的函数的实参个数和类型、let 的类型标注、返回值、赋值、二元运算的操作数、
+// 条件和结构体字段的类型要和上下文相容；类型推断不出的部分不检查
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+
+const PROGRAM: &str = "struct Point {
+    x: i32,
+    label: string,
+}
+
+fn scale(p: &Point, factor: i32) -> i32 {
+    p.x * factor
+}
+
+fn total(values: &[i64]) -> i64 {
+    let mut sum = 0;
+    for value in values {
+        sum += value;
+    }
+    sum
+}
+
+fn ready(count: usize) -> bool {
+    count > 2 && count % 2 == 1
+}
+
+fn main() {
+    let p = Point { x: 4, label: \"p\" + \"1\" };
+    let values = [1, 2, 3];
+    let doubled: i32 = scale(&p, 2);
+    print(doubled, total(&values), ready(values.len()));
+    if p.label == \"p1\" {
+        print(p.label);
+    }
+}
+";
+
+// `items` 之后的 main 函数体是 `body`
+fn with_main(items: &str, body: &str) -> Vec<(Option<ErrorCode>, String)> {
+    errors(&format!("{}\nfn main() {{\n{}\n}}\n", items, body))
+}
+
+fn mismatch(message: &str) -> Vec<(Option<ErrorCode>, String)> {
+    vec![(Some(ErrorCode::E0308), message.to_string())]
+}
+
+#[test]
+fn test_well_typed_program() {
+    assert!(errors(PROGRAM).is_empty(), "{:?}", errors(PROGRAM));
+    assert_eq!(run(PROGRAM), "8\n6\ntrue\np1\n");
+}
+
+#[test]
+fn test_let_annotation() {
+    assert_eq!(
+        with_main("", "    let x: i32 = \"s\";"),
+        mismatch("expected `i32`, found `string`")
+    );
+    assert_eq!(
+        with_main("", "    let x: f64 = 1;"),
+        mismatch("expected `f64`, found integer")
+    );
+    assert_eq!(
+        with_main("", "    let x: (i32, bool) = (1, 2);"),
+        mismatch("expected `(i32, bool)`, found `(i32, i32)`")
+    );
+    // 整数之间的宽度由字面量的检查决定
+    assert!(with_main("", "    let x: u64 = 5;\n    let y: i64 = 7;").is_empty());
+}
+
+#[test]
+fn test_call_arguments() {
+    let items = "fn f(x: i32) -> i32 {\n    x\n}\n\nfn two(a: i32, b: string) {}\n";
+    assert_eq!(
+        with_main(items, "    f(\"s\");"),
+        mismatch("expected `i32`, found `string`")
+    );
+    assert_eq!(
+        with_main(items, "    f(1);\n    two(2, true);"),
+        mismatch("expected `string`, found `bool`")
+    );
+    assert_eq!(
+        with_main(items, "    two(1);"),
+        vec![(
+            Some(ErrorCode::E0061),
+            "function `two` takes 2 arguments but 1 was supplied".to_string()
+        )]
+    );
+    assert_eq!(
+        with_main(items, "    f(1, 2);")[0].1,
+        "function `f` takes 1 argument but 2 were supplied"
+    );
+}
+
+#[test]
+fn test_return_values() {
+    assert_eq!(
+        with_main("fn b() -> i32 {\n    true\n}\n", ""),
+        mismatch("expected `i32`, found `bool`")
+    );
+    assert_eq!(
+        with_main(
+            "fn b(x: i32) -> string {\n    if x > 0 {\n        return x;\n    }\n    \"x\"\n}\n",
+            ""
+        ),
+        mismatch("expected `string`, found `i32`")
+    );
+    // 发散的函数体可以是任何类型
+    assert!(with_main("fn b() -> i32 {\n    panic(\"no\")\n}\n", "").is_empty());
+}
+
+#[test]
+fn test_binary_operands() {
+    assert_eq!(
+        with_main("", "    print(true + 1);"),
+        vec![(
+            Some(ErrorCode::E0369),
+            "binary operation `+` cannot be applied to `bool`".to_string()
+        )]
+    );
+    // 运算的结果不再报告类型不符
+    assert_eq!(with_main("", "    let x: i32 = true + 1;").len(), 1);
+    assert_eq!(
+        with_main("", "    print(\"a\" - \"b\");")[0].1,
+        "binary operation `-` cannot be applied to `string`"
+    );
+    assert_eq!(
+        with_main("", "    print(1 == \"one\");"),
+        mismatch("expected integer, found `string`")
+    );
+    assert_eq!(
+        with_main("", "    let x = 2 as f64;\n    print(x * 2);"),
+        mismatch("expected `f64`, found integer")
+    );
+    // 移位的右侧可以是另一种整数类型
+    assert!(with_main("", "    print(1u8 << 3u32, 1u64 + 2);").is_empty());
+}
+
+#[test]
+fn test_conditions() {
+    assert_eq!(
+        with_main("", "    if 1 {}"),
+        mismatch("expected `bool`, found integer")
+    );
+    assert_eq!(
+        with_main("", "    let s = \"x\";\n    while s {}"),
+        mismatch("expected `bool`, found `string`")
+    );
+}
+
+#[test]
+fn test_struct_fields_and_assignment() {
+    let items = "struct S {\n    n: i32,\n    name: string,\n}\n";
+    assert_eq!(
+        with_main(items, "    let s = S { n: \"one\", name: \"s\" };"),
+        mismatch("expected `i32`, found `string`")
+    );
+    assert_eq!(
+        with_main(
+            items,
+            "    let mut s = S { n: 1, name: \"s\" };\n    s.name = 'c';"
+        ),
+        mismatch("expected `string`, found `char`")
+    );
+    // 泛型结构体的字段由字段的值决定类型实参
+    assert!(with_main(
+        "struct Wrapper<T> {\n    value: T,\n}\n",
+        "    let w = Wrapper { value: \"s\" };"
+    )
+    .is_empty());
+}
@@ -0,0 +1,78 @@
+// Contractus typeof 和布局内建函数测试
+// `typeof(expr)` 在函数体中的 `let` 标注、`as` 转换和类型参数中表示表达式的类型，
+// 由类型检查确定；`size_of::<T>()` 和 `align_of::<T>()` 不写 `std::mem::` 也可以调用
+
+mod common;
+
+use common::{errors, run};
+use contractus::diagnostic::ErrorCode;
+
+const PROGRAM: &str = "struct Pair {
+    small: u8,
+    large: u32,
+}
+
+const LIMIT: u16 = 1000;
+
+fn main() {
+    let level: u8 = 250;
+    let next: typeof(level) = 5;
+    print(next, 300 as typeof(level));
+    let limit: typeof(LIMIT) = LIMIT;
+    print(limit + 1, size_of::<typeof(limit)>());
+    let pair = Pair { small: 1, large: 2 };
+    print(size_of::<typeof(pair)>(), align_of::<typeof(pair)>());
+    print(size_of::<typeof(Pair { small: 3, large: 4 })>());
+    print(std::mem::size_of::<(u8, u64)>(), align_of::<[u16; 3]>());
+}
+";
+
+#[test]
+fn test_typeof() {
+    assert_eq!(run(PROGRAM), "5\n44\n1001\n2\n8\n4\n8\n16\n2\n");
+
+    // 还原的源码经过格式化和原来相同
+    assert_eq!(common::roundtrip(PROGRAM), PROGRAM);
+}
+
+#[test]
+fn test_typeof_checks_literals() {
+    // `typeof` 得到的类型和直接写出的类型一样检查字面量
+    let found = errors("fn main() {\n    let a: u8 = 1;\n    let b: typeof(a) = 256;\n}\n");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, Some(ErrorCode::E0706));
+}
+
+#[test]
+fn test_misplaced_typeof() {
+    let found =
+        errors("const LIMIT: u8 = 1;\n\nfn clamp(value: typeof(LIMIT)) {}\n\nfn main() {}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0516),
+            "`typeof` is not allowed here".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_typeof_inference() {
+    let found = errors("fn main() {\n    let v: typeof([]) = [];\n}\n");
+    assert_eq!(
+        found,
+        [(
+            Some(ErrorCode::E0282),
+            "cannot infer the type of `typeof([])`".to_string()
+        )]
+    );
+
+    // 表达式本身的错误只报告一次
+    let found = errors("fn main() {\n    let v: typeof(missing) = 1;\n}\n");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, Some(ErrorCode::E0425));
+
+    // 同名的变量遮蔽内建函数
+    let found = errors("fn main() {\n    let size_of = 1;\n    let n = size_of::<i32>();\n}\n");
+    assert!(!found.is_empty());
+}